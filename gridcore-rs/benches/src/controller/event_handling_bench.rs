@@ -12,7 +12,9 @@ fn setup_controller(rows: usize, cols: usize) -> SpreadsheetController {
         total_cols: cols,
         ..Default::default()
     };
-    SpreadsheetController::with_config(config)
+    SpreadsheetController::builder()
+        .with_grid_config(config)
+        .build()
 }
 
 fn bench_keyboard_events(c: &mut Criterion) {
@@ -97,11 +97,64 @@ fn bench_resize_column(c: &mut Criterion) {
     group.finish();
 }
 
+/// A wide sheet where most columns have been resized, so every hit-test
+/// crosses thousands of custom widths
+fn setup_resized_viewport() -> ViewportManager {
+    let mut viewport = setup_viewport(100_000, 10_000);
+    for col in 0..5_000usize {
+        viewport.set_column_width(col * 2, 40.0 + (col % 37) as f64 * 10.0);
+    }
+    for row in (0..100_000usize).step_by(50) {
+        viewport.set_row_height(row, 40.0);
+    }
+    viewport.extend_to(&CellAddress::new(9_999, 99_999));
+    viewport
+}
+
+fn bench_hit_testing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hit_testing");
+    let mut viewport = setup_resized_viewport();
+    viewport.set_viewport_size(1600.0, 900.0);
+    viewport.scroll_to(400_000.0, 1_000_000.0);
+
+    // 10k pointer positions spread over the visible area
+    let points: Vec<(f64, f64)> = (0..10_000)
+        .map(|i| ((i * 37 % 1600) as f64, (i * 53 % 900) as f64))
+        .collect();
+
+    group.bench_function("cell_at_point_10k", |b| {
+        b.iter(|| {
+            points
+                .iter()
+                .filter_map(|&(x, y)| viewport.cell_at_point(black_box(x), black_box(y)))
+                .count()
+        });
+    });
+
+    group.bench_function("point_of_cell_10k", |b| {
+        b.iter(|| {
+            (0..10_000u32)
+                .map(|i| viewport.point_of_cell(&CellAddress::new(i, i * 7)).0)
+                .sum::<f64>()
+        });
+    });
+
+    group.bench_function("rebuild_after_resize", |b| {
+        b.iter(|| {
+            viewport.set_column_width(black_box(4_321), 120.0);
+            viewport.cell_at_point(black_box(800.0), black_box(450.0))
+        });
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_viewport_scroll,
     bench_cell_position_calculation,
     bench_visible_cells_iteration,
-    bench_resize_column
+    bench_resize_column,
+    bench_hit_testing
 );
 criterion_main!(benches);
@@ -0,0 +1,90 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use gridcore_core::SpreadsheetFacade;
+use gridcore_core::dependency::DependencyGraph;
+use gridcore_core::types::CellAddress;
+use std::collections::HashMap;
+use std::hint::black_box;
+
+const FORMULAS: u32 = 100_000;
+
+/// The rate cell every formula reads
+const RATE: CellAddress = CellAddress { col: 2, row: 0 };
+
+/// What each formula `B<n> = A<n> * $C$1` reads
+fn references() -> HashMap<CellAddress, Vec<CellAddress>> {
+    (0..FORMULAS)
+        .map(|row| {
+            (
+                CellAddress::new(1, row),
+                vec![CellAddress::new(0, row), RATE],
+            )
+        })
+        .collect()
+}
+
+fn full_rebuild(references: &HashMap<CellAddress, Vec<CellAddress>>) -> DependencyGraph {
+    let mut graph = DependencyGraph::new();
+    for (&cell, precedents) in references {
+        for &precedent in precedents {
+            graph.add_dependency(cell, precedent);
+        }
+    }
+    graph
+}
+
+/// Editing one formula against rebuilding the graph of all of them
+fn bench_edit_vs_rebuild(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dependency_graph_100k_formulas");
+    group.sample_size(10);
+
+    let references = references();
+    let mut graph = full_rebuild(&references);
+    let edited = CellAddress::new(1, FORMULAS / 2);
+    let mut toggle = false;
+    group.bench_function("edit_one_formula", |b| {
+        b.iter(|| {
+            // Alternate between reading the row's own cell and the next one
+            toggle = !toggle;
+            let own = CellAddress::new(0, edited.row + u32::from(toggle));
+            graph.set_dependencies(edited, [own, RATE]);
+            black_box(graph.len())
+        })
+    });
+
+    group.bench_function("full_rebuild", |b| {
+        b.iter(|| black_box(full_rebuild(&references).len()))
+    });
+
+    group.finish();
+}
+
+/// Setting one formula in a sheet of 100k formulas, through the facade
+fn bench_facade_edit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("facade_100k_formulas");
+    group.sample_size(10);
+
+    let facade = SpreadsheetFacade::new();
+    facade.set_cell_value(&RATE, "2").unwrap();
+    for row in 0..FORMULAS {
+        facade
+            .set_cell_value(&CellAddress::new(0, row), &row.to_string())
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(1, row), &format!("=A{}*$C$1", row + 1))
+            .unwrap();
+    }
+    let edited = CellAddress::new(1, FORMULAS / 2);
+    let mut toggle = false;
+    group.bench_function("edit_one_formula", |b| {
+        b.iter(|| {
+            toggle = !toggle;
+            let formula = format!("=A{}*$C$1", edited.row + 1 + u32::from(toggle));
+            facade.set_cell_value(&edited, &formula).unwrap();
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_edit_vs_rebuild, bench_facade_edit);
+criterion_main!(benches);
@@ -0,0 +1,78 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use gridcore_core::adapters::RepositoryAdapter;
+use gridcore_core::evaluator::PortContext;
+use gridcore_core::types::{CellAddress, CellValue};
+use gridcore_core::{Cell, CellRepository, Evaluator, Expr, FormulaParser};
+use std::hint::black_box;
+use std::sync::{Arc, Mutex};
+
+const TABLE_ROWS: u32 = 500_000;
+
+/// A two-column table of `TABLE_ROWS` keys and values in A:B. Lookups are
+/// evaluated directly, since a sheet holding a thousand formulas over the
+/// whole table would track each of their cells as a dependency.
+fn setup_repository(lookup_index: bool) -> Arc<RepositoryAdapter> {
+    let mut repository = CellRepository::new();
+    repository.set_lookup_index(lookup_index);
+    for row in 0..TABLE_ROWS {
+        repository.set(
+            &CellAddress::new(0, row),
+            Cell::new(CellValue::from_string(format!("k{}", row))),
+        );
+        repository.set(
+            &CellAddress::new(1, row),
+            Cell::new(CellValue::Number(row as f64)),
+        );
+    }
+    Arc::new(RepositoryAdapter::new(Arc::new(Mutex::new(repository))))
+}
+
+/// `count` exact-match VLOOKUPs spread over the table
+fn lookups(count: u32) -> Vec<Expr> {
+    let step = TABLE_ROWS / count;
+    (0..count)
+        .map(|n| {
+            FormulaParser::parse(&format!(
+                "VLOOKUP(\"k{}\",A1:B{},2,FALSE)",
+                n * step + step / 2,
+                TABLE_ROWS
+            ))
+            .unwrap()
+        })
+        .collect()
+}
+
+fn evaluate_all(repository: &Arc<RepositoryAdapter>, exprs: &[Expr]) -> f64 {
+    let mut context = PortContext::new(repository.clone());
+    let mut evaluator = Evaluator::new(&mut context);
+    exprs
+        .iter()
+        .map(|expr| match evaluator.evaluate(expr) {
+            Ok(CellValue::Number(n)) => n,
+            _ => f64::NAN,
+        })
+        .sum()
+}
+
+fn bench_lookup_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vlookup_500k_rows");
+    group.sample_size(10);
+
+    // Scans read the whole key column per lookup, so they run fewer
+    // lookups; compare the per-lookup throughput
+    for (name, lookup_index, count) in [("indexed", true, 1_000u32), ("scan", false, 10)] {
+        let repository = setup_repository(lookup_index);
+        let exprs = lookups(count);
+        // Build the index before timing
+        evaluate_all(&repository, &exprs[..1]);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new(name, count), &exprs, |b, exprs| {
+            b.iter(|| black_box(evaluate_all(&repository, exprs)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup_index);
+criterion_main!(benches);
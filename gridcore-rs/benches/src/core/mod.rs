@@ -1,6 +1,9 @@
+pub mod dependency_graph_bench;
 pub mod fill_bench;
+pub mod lookup_index_bench;
 pub mod memory_bench;
 pub mod memory_bench_simple;
+pub mod numeric_export_bench;
 pub mod optimization_bench;
 pub mod structural_ops_bench;
 pub mod transformer_bench;
@@ -0,0 +1,66 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use gridcore_core::SpreadsheetFacade;
+use gridcore_core::types::{CellAddress, CellValue};
+use std::hint::black_box;
+
+fn setup_facade(rows: u32) -> SpreadsheetFacade {
+    let facade = SpreadsheetFacade::new();
+    for row in 0..rows {
+        // Every tenth row is text so the export has NaN slots to fill
+        let value = if row % 10 == 9 {
+            "n/a".to_string()
+        } else {
+            (row as f64 * 0.5).to_string()
+        };
+        let _ = facade.set_cell_value_without_command(&CellAddress::new(0, row), &value);
+        let _ = facade.set_cell_value_without_command(&CellAddress::new(1, row), &row.to_string());
+    }
+    facade
+}
+
+/// Per-cell reads serialised to JSON, the shape of the existing range API
+fn json_export(facade: &SpreadsheetFacade, col: u32, rows: u32) -> String {
+    let values: Vec<Option<f64>> = (0..rows)
+        .map(
+            |row| match facade.get_cell_raw_value(&CellAddress::new(col, row)) {
+                Some(CellValue::Number(n)) => Some(n),
+                _ => None,
+            },
+        )
+        .collect();
+    serde_json::to_string(&values).unwrap()
+}
+
+fn bench_numeric_export(c: &mut Criterion) {
+    let mut group = c.benchmark_group("numeric_export");
+
+    for rows in [10_000u32, 100_000] {
+        let facade = setup_facade(rows);
+        group.throughput(Throughput::Elements(rows as u64));
+
+        group.bench_with_input(BenchmarkId::new("json", rows), &rows, |b, &rows| {
+            b.iter(|| black_box(json_export(&facade, 0, rows)))
+        });
+
+        let mut buffer = vec![0.0; rows as usize];
+        group.bench_with_input(BenchmarkId::new("buffer", rows), &rows, |b, &rows| {
+            b.iter(|| black_box(facade.export_numeric_column(0, 0, rows - 1, &mut buffer)))
+        });
+
+        let mut interleaved = vec![0.0; rows as usize * 2];
+        group.bench_with_input(
+            BenchmarkId::new("buffer_two_columns", rows),
+            &rows,
+            |b, &rows| {
+                b.iter(|| {
+                    black_box(facade.export_numeric_columns(&[0, 1], 0, rows - 1, &mut interleaved))
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_numeric_export);
+criterion_main!(benches);
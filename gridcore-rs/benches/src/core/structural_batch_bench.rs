@@ -0,0 +1,56 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use gridcore_core::facade::SpreadsheetFacade;
+use gridcore_core::types::CellAddress;
+use std::hint::black_box;
+
+const ROWS: u32 = 1_000;
+
+/// One value with its total right below
+fn setup_sheet() -> SpreadsheetFacade {
+    let facade = SpreadsheetFacade::new();
+    facade.set_cell_value(&CellAddress::new(1, 0), "0").unwrap();
+    facade
+        .set_cell_value(&CellAddress::new(1, 1), "=SUM(B1:B1)")
+        .unwrap();
+    facade
+}
+
+/// Insert a row right above the total and fill it in, with a formula
+/// beside the value, `ROWS` times
+fn load(facade: &SpreadsheetFacade) {
+    for row in 1..=ROWS {
+        facade.insert_rows(row, 1).unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(1, row), &row.to_string())
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(2, row), &format!("=B{}*2", row + 1))
+            .unwrap();
+    }
+}
+
+fn bench_insert_and_write_loop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_and_write_1000_rows");
+    group.sample_size(10);
+
+    for batched in [false, true] {
+        let name = if batched { "batched" } else { "one_by_one" };
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_with_setup(setup_sheet, |facade| {
+                if batched {
+                    facade.begin_structural_batch().unwrap();
+                }
+                load(&facade);
+                if batched {
+                    facade.commit_structural_batch().unwrap();
+                }
+                black_box(facade.get_cell_value(&CellAddress::new(1, ROWS + 1)))
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_insert_and_write_loop);
+criterion_main!(benches);
@@ -37,7 +37,7 @@ fn create_keyboard_event(key: &str, ctrl: bool, shift: bool) -> KeyboardEvent {
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen_test]
 fn bench_mouse_click_handling() {
-    let controller = SpreadsheetController::new();
+    let controller = SpreadsheetController::builder().build();
     let handler = MouseHandler::new(controller);
 
     let start = window().unwrap().performance().unwrap().now();
@@ -56,7 +56,7 @@ fn bench_mouse_click_handling() {
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen_test]
 fn bench_mouse_drag_handling() {
-    let controller = SpreadsheetController::new();
+    let controller = SpreadsheetController::builder().build();
     let handler = MouseHandler::new(controller);
 
     let start = window().unwrap().performance().unwrap().now();
@@ -75,7 +75,7 @@ fn bench_mouse_drag_handling() {
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen_test]
 fn bench_keyboard_navigation() {
-    let controller = SpreadsheetController::new();
+    let controller = SpreadsheetController::builder().build();
     let handler = KeyboardHandler::new(controller);
 
     let keys = vec!["ArrowDown", "ArrowRight", "ArrowUp", "ArrowLeft"];
@@ -95,7 +95,7 @@ fn bench_keyboard_navigation() {
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen_test]
 fn bench_keyboard_shortcuts() {
-    let controller = SpreadsheetController::new();
+    let controller = SpreadsheetController::builder().build();
     let handler = KeyboardHandler::new(controller);
 
     let shortcuts = vec![
@@ -124,7 +124,7 @@ fn bench_keyboard_shortcuts() {
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen_test]
 fn bench_text_input() {
-    let controller = SpreadsheetController::new();
+    let controller = SpreadsheetController::builder().build();
     let handler = KeyboardHandler::new(controller);
 
     let text = "The quick brown fox jumps over the lazy dog";
@@ -147,7 +147,7 @@ fn bench_text_input() {
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen_test]
 fn bench_wheel_scroll() {
-    let controller = SpreadsheetController::new();
+    let controller = SpreadsheetController::builder().build();
     let handler = MouseHandler::new(controller);
 
     let start = window().unwrap().performance().unwrap().now();
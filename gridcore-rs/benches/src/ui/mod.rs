@@ -9,4 +9,6 @@ pub fn run_all_benchmarks() {
     println!("UI benchmarks require WASM context.");
     println!("To run UI benchmarks, use:");
     println!("  wasm-pack test --headless --chrome gridcore-rs/benches");
+    println!("To check the UI performance budgets, use:");
+    println!("  wasm-pack test --headless --chrome gridcore-rs/gridcore-ui --features perf-test");
 }
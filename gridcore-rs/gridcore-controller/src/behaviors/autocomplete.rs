@@ -37,6 +37,20 @@ static FUNCTION_DATA: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     m.insert("COUNT", "(value1, [value2, ...])");
     m.insert("COUNTA", "(value1, [value2, ...])");
     m.insert("COUNTIF", "(range, criteria)");
+    m.insert(
+        "COUNTIFS",
+        "(criteria_range1, criteria1, [criteria_range2, criteria2], ...)",
+    );
+    m.insert("AVERAGEIF", "(range, criteria, [average_range])");
+    m.insert(
+        "AVERAGEIFS",
+        "(average_range, criteria_range1, criteria1, [criteria_range2, criteria2], ...)",
+    );
+    m.insert("SUMIF", "(range, criteria, [sum_range])");
+    m.insert(
+        "SUMIFS",
+        "(sum_range, criteria_range1, criteria1, [criteria_range2, criteria2], ...)",
+    );
     m.insert("MAX", "(value1, [value2, ...])");
     m.insert("MIN", "(value1, [value2, ...])");
     m.insert("ROUND", "(number, num_digits)");
@@ -64,6 +78,8 @@ static FUNCTION_DATA: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     m.insert("OR", "(logical1, [logical2, ...])");
     m.insert("NOT", "(logical)");
     m.insert("XOR", "(logical1, [logical2, ...])");
+    m.insert("IFERROR", "(value, value_if_error)");
+    m.insert("IFNA", "(value, value_if_na)");
 
     // Lookup functions
     m.insert(
@@ -85,6 +101,7 @@ static FUNCTION_DATA: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     m.insert("MID", "(text, start_num, num_chars)");
     m.insert("LEN", "(text)");
     m.insert("TRIM", "(text)");
+    m.insert("SUBSTITUTE", "(text, old_text, new_text, [instance_num])");
     m.insert("UPPER", "(text)");
     m.insert("LOWER", "(text)");
     m.insert("PROPER", "(text)");
@@ -97,6 +114,8 @@ static FUNCTION_DATA: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     m.insert("YEAR", "(date)");
     m.insert("MONTH", "(date)");
     m.insert("DAY", "(date)");
+    m.insert("EOMONTH", "(start_date, months)");
+    m.insert("DATEDIF", "(start_date, end_date, unit)");
     m.insert("HOUR", "(time)");
     m.insert("MINUTE", "(time)");
     m.insert("SECOND", "(time)");
@@ -109,6 +128,14 @@ static FUNCTION_DATA: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     m.insert("ISNUMBER", "(value)");
     m.insert("ISTEXT", "(value)");
     m.insert("ISLOGICAL", "(value)");
+    m.insert("NA", "()");
+
+    // Array functions
+    m.insert("SEQUENCE", "(rows, [columns], [start], [step])");
+    m.insert("TRANSPOSE", "(array)");
+
+    // Chart functions
+    m.insert("SPARKLINE", "(range, [type], [options])");
 
     m
 });
@@ -223,10 +250,10 @@ mod tests {
     #[test]
     fn test_function_suggestions() {
         let suggestions = get_function_suggestions("SU");
-        assert_eq!(suggestions, vec!["SUM"]);
+        assert_eq!(suggestions, vec!["SUBSTITUTE", "SUM", "SUMIF", "SUMIFS"]);
 
         let suggestions = get_function_suggestions("AV");
-        assert_eq!(suggestions, vec!["AVERAGE"]);
+        assert_eq!(suggestions, vec!["AVERAGE", "AVERAGEIF", "AVERAGEIFS"]);
 
         let suggestions = get_function_suggestions("ROUND");
         assert!(suggestions.contains(&"ROUND".to_string()));
@@ -236,7 +263,7 @@ mod tests {
 
     #[test]
     fn test_get_suggestions_for_formula() {
-        let suggestions = get_suggestions("=SU", 3);
+        let suggestions = get_suggestions("=SUM", 4);
         assert!(!suggestions.is_empty());
         assert!(matches!(
             &suggestions[0],
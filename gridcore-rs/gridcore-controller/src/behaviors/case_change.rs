@@ -0,0 +1,310 @@
+//! Case operators for text cells: `gU`, `gu` and `g~` in navigation mode,
+//! `U`, `u` and `~` over a visual selection.
+//!
+//! Case mapping uses Rust's `to_uppercase`/`to_lowercase`, i.e. the default
+//! Unicode mappings. Locale-specific rules are not applied, so Turkish `i`
+//! uppercases to `I` rather than `İ`.
+
+use crate::behaviors::vim::{Direction, Motion, TextObject};
+use gridcore_core::evaluator::parse_cell_value;
+use gridcore_core::formula::CellRange;
+use gridcore_core::types::{CellAddress, CellValue};
+use gridcore_core::Cell;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaseChange {
+    Upper,
+    Lower,
+    Toggle,
+}
+
+impl CaseChange {
+    /// The operator key: `U`, `u` or `~`
+    pub fn from_key(key: char) -> Option<Self> {
+        match key {
+            'U' => Some(CaseChange::Upper),
+            'u' => Some(CaseChange::Lower),
+            '~' => Some(CaseChange::Toggle),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            CaseChange::Upper => text.to_uppercase(),
+            CaseChange::Lower => text.to_lowercase(),
+            CaseChange::Toggle => text
+                .chars()
+                .flat_map(|c| {
+                    if c.is_uppercase() {
+                        c.to_lowercase().collect::<Vec<_>>()
+                    } else {
+                        c.to_uppercase().collect::<Vec<_>>()
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// New text for a cell, or `None` when the cell is left alone. Only
+    /// literal text is changed; formulas keep their case even when they
+    /// evaluate to text. Text that would read back as another type, such as
+    /// `TRUE` lowercased to `true`, is left alone too.
+    pub fn apply_to_cell(self, cell: &Cell) -> Option<String> {
+        match &cell.raw_value {
+            CellValue::String(text) if !cell.has_formula() => {
+                let changed = self.apply(text);
+                matches!(parse_cell_value(&changed), CellValue::String(_)).then_some(changed)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Counts reported after a case operator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaseSummary {
+    pub changed: usize,
+    /// Non-empty cells that are not text: numbers, booleans, formulas, errors
+    pub skipped: usize,
+}
+
+impl fmt::Display for CaseSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = if self.changed == 1 { "cell" } else { "cells" };
+        write!(
+            f,
+            "{} {} changed, {} skipped",
+            self.changed, noun, self.skipped
+        )
+    }
+}
+
+/// Cells a case operator applies to, relative to the cursor
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaseTarget {
+    /// `gUU`: the cursor's row and the `count - 1` rows below it
+    CurrentLine,
+    Motion(Motion),
+    TextObject(TextObject),
+    /// A visual selection of this size, for repeating a visual operator
+    Block {
+        cols: u32,
+        rows: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseCommand {
+    pub change: CaseChange,
+    pub target: CaseTarget,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaseKeys {
+    /// More keys are needed
+    Pending,
+    Invalid,
+    Complete(CaseCommand),
+}
+
+/// Parse the keys of a case operator typed in navigation mode, starting with
+/// the `g`. A count may follow the operator: `gU3j`, `g~2~`.
+pub fn parse_case_keys(keys: &str) -> CaseKeys {
+    let Some(rest) = keys.strip_prefix('g') else {
+        return CaseKeys::Invalid;
+    };
+    let mut chars = rest.chars();
+    let Some(operator) = chars.next() else {
+        return CaseKeys::Pending;
+    };
+    let Some(change) = CaseChange::from_key(operator) else {
+        return CaseKeys::Invalid;
+    };
+
+    let rest = chars.as_str();
+    let digits = if rest.starts_with('0') {
+        0
+    } else {
+        rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len()
+    };
+    let count = rest[..digits].parse::<usize>().unwrap_or(1).max(1);
+
+    let target = match &rest[digits..] {
+        "" | "g" | "i" | "a" => return CaseKeys::Pending,
+        key if key == operator.to_string() || key == format!("g{}", operator) => {
+            CaseTarget::CurrentLine
+        }
+        "h" => CaseTarget::Motion(Motion::Char(Direction::Left, count)),
+        "j" => CaseTarget::Motion(Motion::Char(Direction::Down, count)),
+        "k" => CaseTarget::Motion(Motion::Char(Direction::Up, count)),
+        "l" => CaseTarget::Motion(Motion::Char(Direction::Right, count)),
+        "0" => CaseTarget::Motion(Motion::LineStart),
+        "$" => CaseTarget::Motion(Motion::LineEnd),
+        "gg" => CaseTarget::Motion(Motion::DocumentStart),
+        "G" => CaseTarget::Motion(Motion::DocumentEnd),
+        "ic" => CaseTarget::TextObject(TextObject::InnerColumn),
+        "ac" => CaseTarget::TextObject(TextObject::Column),
+        "ir" => CaseTarget::TextObject(TextObject::InnerRow),
+        "ar" => CaseTarget::TextObject(TextObject::Row),
+        _ => return CaseKeys::Invalid,
+    };
+
+    CaseKeys::Complete(CaseCommand {
+        change,
+        target,
+        count,
+    })
+}
+
+impl CaseCommand {
+    /// The cells covered from `cursor`, within a grid whose last cell is
+    /// `limit`. `is_filled` tells whether a cell has content, for the text
+    /// objects that stop at the first empty cell.
+    pub fn range(
+        &self,
+        cursor: CellAddress,
+        limit: CellAddress,
+        is_filled: impl Fn(&CellAddress) -> bool,
+    ) -> Option<CellRange> {
+        let span = |from: u32, by: usize, max: u32| from.saturating_add(by as u32).min(max);
+        let at = CellAddress::new;
+        let (col, row) = (cursor.col, cursor.row);
+
+        let (start, end) = match &self.target {
+            CaseTarget::CurrentLine => (
+                at(0, row),
+                at(limit.col, span(row, self.count - 1, limit.row)),
+            ),
+            CaseTarget::Block { cols, rows } => (
+                cursor,
+                at(
+                    span(col, *cols as usize - 1, limit.col),
+                    span(row, *rows as usize - 1, limit.row),
+                ),
+            ),
+            CaseTarget::Motion(motion) => match motion {
+                Motion::Char(Direction::Left, n) => {
+                    (at(col.saturating_sub(*n as u32), row), cursor)
+                }
+                Motion::Char(Direction::Right, n) => (cursor, at(span(col, *n, limit.col), row)),
+                Motion::Char(Direction::Up, n) => (at(col, row.saturating_sub(*n as u32)), cursor),
+                Motion::Char(Direction::Down, n) => (cursor, at(col, span(row, *n, limit.row))),
+                Motion::LineStart => (at(0, row), cursor),
+                Motion::LineEnd => (cursor, at(limit.col, row)),
+                Motion::DocumentStart => (at(col, 0), cursor),
+                Motion::DocumentEnd => (cursor, at(col, limit.row)),
+                _ => return None,
+            },
+            CaseTarget::TextObject(object) => match object {
+                TextObject::Column => (at(col, 0), at(col, limit.row)),
+                TextObject::Row => (at(0, row), at(limit.col, row)),
+                TextObject::InnerColumn => {
+                    let filled = |r: u32| is_filled(&at(col, r));
+                    let first = (0..row).rev().take_while(|r| filled(*r)).last();
+                    let last = (row + 1..=limit.row).take_while(|r| filled(*r)).last();
+                    (at(col, first.unwrap_or(row)), at(col, last.unwrap_or(row)))
+                }
+                TextObject::InnerRow => {
+                    let filled = |c: u32| is_filled(&at(c, row));
+                    let first = (0..col).rev().take_while(|c| filled(*c)).last();
+                    let last = (col + 1..=limit.col).take_while(|c| filled(*c)).last();
+                    (at(first.unwrap_or(col), row), at(last.unwrap_or(col), row))
+                }
+                _ => return None,
+            },
+        };
+
+        Some(CellRange::new(start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_case_round_trips() {
+        for text in ["Hello World", "ÄrGER über straße", "ǅemal", "mixed 123 ÉtÉ"] {
+            let toggled = CaseChange::Toggle.apply(text);
+            assert_ne!(toggled, text);
+            // Characters whose case mapping is not one-to-one (ß, ǅ) are
+            // the exception; everything else toggles back
+            if text.chars().all(|c| c != 'ß' && c != 'ǅ') {
+                assert_eq!(CaseChange::Toggle.apply(&toggled), text);
+            }
+        }
+        assert_eq!(CaseChange::Toggle.apply("aBc"), "AbC");
+        assert_eq!(CaseChange::Upper.apply("straße"), "STRASSE");
+        // Default Unicode mapping, not Turkish casing
+        assert_eq!(CaseChange::Upper.apply("istanbul"), "ISTANBUL");
+    }
+
+    #[test]
+    fn test_only_literal_text_changes() {
+        let text = Cell::new(CellValue::string_from_str("abc"));
+        let number = Cell::new(CellValue::Number(1.0));
+        let formula = Cell::with_formula(
+            CellValue::string_from_str("=LOWER(\"X\")"),
+            "LOWER(\"X\")".to_string(),
+        );
+
+        assert_eq!(
+            CaseChange::Upper.apply_to_cell(&text),
+            Some("ABC".to_string())
+        );
+        assert_eq!(CaseChange::Upper.apply_to_cell(&number), None);
+        assert_eq!(CaseChange::Upper.apply_to_cell(&formula), None);
+
+        let keyword = Cell::new(CellValue::string_from_str("TRUE"));
+        assert_eq!(CaseChange::Lower.apply_to_cell(&keyword), None);
+    }
+
+    #[test]
+    fn test_parse_case_keys() {
+        assert_eq!(parse_case_keys("g"), CaseKeys::Pending);
+        assert_eq!(parse_case_keys("gU"), CaseKeys::Pending);
+        assert_eq!(parse_case_keys("gU3"), CaseKeys::Pending);
+        assert_eq!(parse_case_keys("gUi"), CaseKeys::Pending);
+        assert_eq!(parse_case_keys("gx"), CaseKeys::Invalid);
+        assert_eq!(parse_case_keys("gUx"), CaseKeys::Invalid);
+
+        let complete = |keys| match parse_case_keys(keys) {
+            CaseKeys::Complete(command) => command,
+            other => panic!("{} parsed as {:?}", keys, other),
+        };
+        assert_eq!(complete("gUU").target, CaseTarget::CurrentLine);
+        assert_eq!(complete("gUgU").target, CaseTarget::CurrentLine);
+        assert_eq!(complete("g~2~").count, 2);
+        assert_eq!(
+            complete("gu3j").target,
+            CaseTarget::Motion(Motion::Char(Direction::Down, 3))
+        );
+        assert_eq!(
+            complete("gU0").target,
+            CaseTarget::Motion(Motion::LineStart)
+        );
+        assert_eq!(
+            complete("gUic").target,
+            CaseTarget::TextObject(TextObject::InnerColumn)
+        );
+    }
+
+    #[test]
+    fn test_inner_column_stops_at_empty_cells() {
+        let command = match parse_case_keys("gUic") {
+            CaseKeys::Complete(command) => command,
+            other => panic!("{:?}", other),
+        };
+        // Rows 2..=5 of column B are filled
+        let filled = |address: &CellAddress| address.col == 1 && (2..=5).contains(&address.row);
+        let range = command
+            .range(CellAddress::new(1, 3), CellAddress::new(25, 99), filled)
+            .unwrap();
+        assert_eq!(range.start, CellAddress::new(1, 2));
+        assert_eq!(range.end, CellAddress::new(1, 5));
+    }
+}
@@ -0,0 +1,289 @@
+//! Bulk cleanup of cell values: `:trim`, `:upper`, `:lower`, `:titlecase`,
+//! `:clean`, `:round N` and `:tonum` over the selection.
+//!
+//! Transforms change stored values, never formats, and only touch literal
+//! values: formulas are skipped even when they evaluate to text or numbers,
+//! as are cells of a type the transform does not apply to.
+
+use crate::behaviors::case_change::CaseChange;
+use gridcore_core::evaluator::parse_cell_value;
+use gridcore_core::types::CellValue;
+use gridcore_core::{Cell, Result, SpreadsheetError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Ex commands that run a transform, for completion
+pub const TRANSFORM_COMMANDS: &[&str] = &[
+    "clean",
+    "lower",
+    "round",
+    "titlecase",
+    "tonum",
+    "trim",
+    "upper",
+];
+
+/// Decimals `:round` accepts either way of the decimal point; beyond them
+/// a double has no digits left to round
+const MAX_ROUND_DECIMALS: i32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellTransform {
+    /// Strip leading and trailing whitespace from text
+    Trim,
+    Upper,
+    Lower,
+    /// Capitalize the first letter of every word and lowercase the rest
+    TitleCase,
+    /// Round numbers to this many decimals; negative decimals round to
+    /// tens, hundreds and so on
+    Round {
+        decimals: i32,
+    },
+    /// Remove control and zero-width characters from text
+    Clean,
+    /// Store text that reads as a number, e.g. `"1,234"`, as that number
+    ToNumber,
+}
+
+impl CellTransform {
+    /// The transform run by the ex command `name` with `args`
+    pub fn from_command(name: &str, args: &[String]) -> Result<Self> {
+        let transform = match name {
+            "trim" => Self::Trim,
+            "upper" => Self::Upper,
+            "lower" => Self::Lower,
+            "titlecase" => Self::TitleCase,
+            "clean" => Self::Clean,
+            "tonum" => Self::ToNumber,
+            "round" => {
+                let decimals = match args.first() {
+                    None => 0,
+                    Some(arg) => arg
+                        .parse::<i32>()
+                        .ok()
+                        .filter(|decimals| decimals.abs() <= MAX_ROUND_DECIMALS)
+                        .ok_or_else(|| {
+                            SpreadsheetError::InvalidCommand(format!(
+                                "Round to between -{0} and {0} decimals, not {1}",
+                                MAX_ROUND_DECIMALS, arg
+                            ))
+                        })?,
+                };
+                return Ok(Self::Round { decimals });
+            }
+            other => {
+                return Err(SpreadsheetError::InvalidCommand(format!(
+                    "Unknown transform: {}",
+                    other
+                )))
+            }
+        };
+        if let Some(extra) = args.first() {
+            return Err(SpreadsheetError::InvalidCommand(format!(
+                ":{} takes no arguments, got {}",
+                name, extra
+            )));
+        }
+        Ok(transform)
+    }
+
+    /// Input to write for a cell, or `None` when the transform skips it:
+    /// formulas, empty cells and values of another type. Text stays text,
+    /// quoted when it would otherwise read as a number or boolean.
+    pub fn apply_to_cell(self, cell: &Cell) -> Option<String> {
+        if cell.has_formula() {
+            return None;
+        }
+        match (self, &cell.raw_value) {
+            (Self::Round { decimals }, CellValue::Number(n)) => {
+                Some(round(*n, decimals).to_string())
+            }
+            (Self::ToNumber, CellValue::String(text)) => {
+                let text = text.trim();
+                matches!(parse_cell_value(text), CellValue::Number(_)).then(|| text.to_string())
+            }
+            (Self::Round { .. } | Self::ToNumber, _) => None,
+            (_, CellValue::String(text)) => Some(text_input(&self.apply_to_text(text))),
+            _ => None,
+        }
+    }
+
+    fn apply_to_text(self, text: &str) -> String {
+        match self {
+            Self::Trim => text.trim().to_string(),
+            Self::Upper => CaseChange::Upper.apply(text),
+            Self::Lower => CaseChange::Lower.apply(text),
+            Self::TitleCase => title_case(text),
+            Self::Clean => text.chars().filter(|&c| !is_non_printable(c)).collect(),
+            Self::Round { .. } | Self::ToNumber => text.to_string(),
+        }
+    }
+}
+
+fn round(n: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+    let rounded = (n * factor).round() / factor;
+    // No "-0" for small negative numbers
+    if rounded == 0.0 {
+        0.0
+    } else {
+        rounded
+    }
+}
+
+fn title_case(text: &str) -> String {
+    let mut titled = String::with_capacity(text.len());
+    let mut word_start = true;
+    for c in text.chars() {
+        if word_start {
+            titled.extend(c.to_uppercase());
+        } else {
+            titled.extend(c.to_lowercase());
+        }
+        word_start = !c.is_alphanumeric();
+    }
+    titled
+}
+
+fn is_non_printable(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}')
+}
+
+/// Input that stores `text` as text even when it reads as something else
+fn text_input(text: &str) -> String {
+    let reads_as_text = !text.starts_with('=') && parse_cell_value(text).as_string() == Some(text);
+    if reads_as_text {
+        text.to_string()
+    } else {
+        format!("'{}", text)
+    }
+}
+
+/// Counts reported after a transform
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransformSummary {
+    pub transformed: usize,
+    /// Non-empty cells the transform does not apply to, formulas included
+    pub skipped: usize,
+}
+
+impl fmt::Display for TransformSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let noun = if self.transformed == 1 {
+            "cell"
+        } else {
+            "cells"
+        };
+        write!(
+            f,
+            "Transformed {} {}, skipped {}",
+            self.transformed, noun, self.skipped
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Cell {
+        Cell::new(CellValue::string_from_str(s))
+    }
+
+    fn apply(transform: CellTransform, cell: &Cell) -> Option<String> {
+        transform.apply_to_cell(cell)
+    }
+
+    #[test]
+    fn test_text_transforms() {
+        let padded = text("  hello wORLD \t");
+        assert_eq!(apply(CellTransform::Trim, &padded).unwrap(), "hello wORLD");
+        assert_eq!(
+            apply(CellTransform::Upper, &padded).unwrap(),
+            "  HELLO WORLD \t"
+        );
+        assert_eq!(
+            apply(CellTransform::TitleCase, &text("o'neil mcDONALD 3rd-place")).unwrap(),
+            "O'Neil Mcdonald 3rd-Place"
+        );
+        assert_eq!(
+            apply(CellTransform::Clean, &text("a\u{7}b\u{200B}c\nd")).unwrap(),
+            "abcd"
+        );
+
+        // Trimmed text that reads as a number or boolean stays text
+        assert_eq!(apply(CellTransform::Trim, &text(" 42 ")).unwrap(), "'42");
+        assert_eq!(apply(CellTransform::Upper, &text("true")).unwrap(), "'TRUE");
+        assert_eq!(apply(CellTransform::Trim, &text(" =A1")).unwrap(), "'=A1");
+    }
+
+    #[test]
+    fn test_numeric_transforms() {
+        let round = |n: f64, decimals| {
+            apply(
+                CellTransform::Round { decimals },
+                &Cell::new(CellValue::Number(n)),
+            )
+        };
+        assert_eq!(round(4.56789, 2).unwrap(), "4.57");
+        assert_eq!(round(2.5, 0).unwrap(), "3");
+        assert_eq!(round(1234.5, -2).unwrap(), "1200");
+        assert_eq!(round(-0.004, 2).unwrap(), "0");
+
+        assert_eq!(
+            apply(CellTransform::ToNumber, &text(" 1.5 ")).unwrap(),
+            "1.5"
+        );
+        assert_eq!(
+            apply(CellTransform::ToNumber, &text("$1,234")).unwrap(),
+            "$1,234"
+        );
+        assert_eq!(apply(CellTransform::ToNumber, &text("n/a")), None);
+    }
+
+    #[test]
+    fn test_skips_formulas_and_other_types() {
+        let formula = Cell::with_formula(CellValue::Number(2.567), "A1*2".to_string());
+        let number = Cell::new(CellValue::Number(2.567));
+        let boolean = Cell::new(CellValue::Boolean(true));
+        for transform in [
+            CellTransform::Trim,
+            CellTransform::Upper,
+            CellTransform::Round { decimals: 1 },
+            CellTransform::ToNumber,
+        ] {
+            assert_eq!(apply(transform, &formula), None);
+            assert_eq!(apply(transform, &boolean), None);
+        }
+        assert_eq!(apply(CellTransform::Trim, &number), None);
+        assert_eq!(
+            apply(CellTransform::Round { decimals: 0 }, &text("3.5")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_command() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            CellTransform::from_command("round", &args(&["2"])).unwrap(),
+            CellTransform::Round { decimals: 2 }
+        );
+        assert_eq!(
+            CellTransform::from_command("round", &[]).unwrap(),
+            CellTransform::Round { decimals: 0 }
+        );
+        assert!(CellTransform::from_command("round", &args(&["two"])).is_err());
+        assert!(CellTransform::from_command("round", &args(&["400"])).is_err());
+        assert!(CellTransform::from_command("trim", &args(&["x"])).is_err());
+        for name in TRANSFORM_COMMANDS {
+            let args = if *name == "round" {
+                args(&["1"])
+            } else {
+                Vec::new()
+            };
+            assert!(CellTransform::from_command(name, &args).is_ok());
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! Clipboard contents for copying cells
+//!
+//! A copy offers two representations. Other applications read tab-separated
+//! text with the values as displayed. Gridcore itself prefers a JSON payload
+//! under [`CLIPBOARD_TYPE`] that keeps each cell's input, format and named
+//! style, so a paste between two windows moves formulas like a copy within
+//! the sheet and brings along styles the other workbook lacks.
+//!
+//! A copy skips hidden rows and columns unless [`CopyOptions`] asks for
+//! them, so the copied block comes out compact.
+
+use super::paste::{move_formula, ParsedPaste, PasteContent, PastedFormat};
+use gridcore_core::domain::{CellFormat, CellStyle};
+use gridcore_core::formula::CellRange;
+use gridcore_core::types::{CellAddress, CellValue};
+use gridcore_core::SpreadsheetFacade;
+use serde::{Deserialize, Serialize};
+
+/// Clipboard type of the serialized [`ClipboardPayload`]
+pub const CLIPBOARD_TYPE: &str = "application/x-gridcore-cells";
+
+/// Payload version written by this build. Newer payloads are ignored in
+/// favour of the text.
+pub const CLIPBOARD_VERSION: u32 = 1;
+
+/// How a selection is copied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopyOptions {
+    /// Copy hidden rows and columns as well as the visible ones
+    pub include_hidden: bool,
+}
+
+/// One copied cell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipboardCell {
+    pub content: PasteContent,
+    /// The format the cell was displayed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<CellFormat>,
+    /// The named style the cell used and its definition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<CellStyle>,
+    /// Where the cell was copied from, when hidden lines were skipped and
+    /// it no longer sits at its distance from the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<CellAddress>,
+}
+
+/// Copied cells as gridcore reads them back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipboardPayload {
+    pub version: u32,
+    /// Top-left cell of the copied block, which formulas are relative to
+    pub source: CellAddress,
+    /// Cells row by row
+    pub rows: Vec<Vec<ClipboardCell>>,
+}
+
+impl ClipboardPayload {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Read a payload, `None` when it is malformed or from a newer version
+    pub fn from_json(json: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(json)
+            .ok()
+            .filter(|payload| payload.version <= CLIPBOARD_VERSION)
+    }
+
+    /// Lay the copied cells out from `target`, moving relative references
+    /// in formulas by the distance from the source
+    pub fn to_paste(&self, target: CellAddress) -> ParsedPaste {
+        let rows = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(r, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(c, cell)| self.moved_content(cell, c, r, offset(target, c, r)))
+                    .collect()
+            })
+            .collect();
+        let formats = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(ClipboardCell::pasted_format).collect())
+            .collect();
+
+        let width = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        ParsedPaste {
+            target,
+            rows,
+            width,
+            rectangular: self.rows.iter().all(|row| row.len() == width),
+            formats: Some(formats),
+        }
+    }
+
+    /// Repeat the copied cells across `target`. Each target cell takes the
+    /// copied cell at its offset from `anchor`, wrapped to the size of the
+    /// copied block, and its formula moves by the true distance between the
+    /// two, so a target that does not start at `anchor`, such as a visible
+    /// block below hidden rows, still refers to its own rows.
+    pub fn to_paste_tiled(&self, target: &CellRange, anchor: CellAddress) -> ParsedPaste {
+        let height = self.rows.len();
+        let width = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        if height == 0 || width == 0 {
+            return self.to_paste(target.start);
+        }
+        let wrap = |at: u32, start: u32, len: usize| {
+            (i64::from(at) - i64::from(start)).rem_euclid(len as i64) as usize
+        };
+
+        let mut rows = Vec::with_capacity(target.row_count());
+        let mut formats = Vec::with_capacity(target.row_count());
+        for row in target.start.row..=target.end.row {
+            let r = wrap(row, anchor.row, height);
+            let (contents, row_formats) = (target.start.col..=target.end.col)
+                .map(|col| {
+                    let c = wrap(col, anchor.col, width);
+                    match self.rows[r].get(c) {
+                        Some(cell) => (
+                            self.moved_content(cell, c, r, CellAddress::new(col, row)),
+                            cell.pasted_format(),
+                        ),
+                        None => (PasteContent::Empty, PastedFormat::default()),
+                    }
+                })
+                .unzip();
+            rows.push(contents);
+            formats.push(row_formats);
+        }
+
+        ParsedPaste {
+            target: target.start,
+            rows,
+            width: target.col_count(),
+            rectangular: true,
+            formats: Some(formats),
+        }
+    }
+
+    /// The content of the copied cell in column `c` and row `r` of the
+    /// block, pasted at `to`
+    fn moved_content(
+        &self,
+        cell: &ClipboardCell,
+        c: usize,
+        r: usize,
+        to: CellAddress,
+    ) -> PasteContent {
+        match &cell.content {
+            PasteContent::Input(input) if input.starts_with('=') => {
+                let from = cell.origin.unwrap_or(offset(self.source, c, r));
+                PasteContent::Input(move_formula(input.clone(), &from, &to))
+            }
+            content => content.clone(),
+        }
+    }
+}
+
+impl ClipboardCell {
+    fn pasted_format(&self) -> PastedFormat {
+        PastedFormat {
+            format: self.format.clone(),
+            style: self.style.clone(),
+        }
+    }
+}
+
+fn offset(base: CellAddress, col: usize, row: usize) -> CellAddress {
+    CellAddress::new(base.col + col as u32, base.row + row as u32)
+}
+
+/// Both representations of a copied block
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardContents {
+    /// Tab-separated display values for other applications
+    pub text: String,
+    pub payload: ClipboardPayload,
+}
+
+impl ClipboardContents {
+    /// Copy the cells of `range` in the active sheet
+    pub fn copy(facade: &SpreadsheetFacade, range: &CellRange) -> Self {
+        let rows: Vec<u32> = (range.start.row..=range.end.row).collect();
+        let cols: Vec<u32> = (range.start.col..=range.end.col).collect();
+        Self::copy_lines(facade, &rows, &cols)
+    }
+
+    /// Copy the cells where `rows` and `cols` cross, side by side as one
+    /// block, e.g. the visible lines of a range with hidden ones between
+    pub fn copy_lines(facade: &SpreadsheetFacade, rows: &[u32], cols: &[u32]) -> Self {
+        let source = CellAddress::new(
+            cols.first().copied().unwrap_or(0),
+            rows.first().copied().unwrap_or(0),
+        );
+        let mut text = String::new();
+        let mut copied = Vec::new();
+
+        for (r, &row) in rows.iter().enumerate() {
+            let mut cells = Vec::new();
+            for (c, &col) in cols.iter().enumerate() {
+                let address = CellAddress::new(col, row);
+                let moved =
+                    address != CellAddress::new(source.col + c as u32, source.row + r as u32);
+                let format = facade.get_effective_format(&address);
+                let style = facade
+                    .get_cell_style(&address)
+                    .and_then(|name| facade.get_style(&name));
+                let (content, display) = match facade.get_cell(&address) {
+                    Some(cell) if cell.has_formula() => (
+                        PasteContent::Input(cell.raw_value.to_string()),
+                        display_value(cell.get_display_value(), format.as_ref()),
+                    ),
+                    Some(cell) => match &cell.raw_value {
+                        CellValue::Empty => (PasteContent::Empty, String::new()),
+                        CellValue::String(value) => {
+                            (PasteContent::Text(value.to_string()), value.to_string())
+                        }
+                        value => (
+                            PasteContent::Input(value.to_string()),
+                            display_value(value, format.as_ref()),
+                        ),
+                    },
+                    None => (PasteContent::Empty, String::new()),
+                };
+                if c > 0 {
+                    text.push('\t');
+                }
+                text.push_str(&tsv_field(&display));
+                cells.push(ClipboardCell {
+                    content,
+                    format,
+                    style,
+                    origin: moved.then_some(address),
+                });
+            }
+            text.push('\n');
+            copied.push(cells);
+        }
+
+        Self {
+            text,
+            payload: ClipboardPayload {
+                version: CLIPBOARD_VERSION,
+                source,
+                rows: copied,
+            },
+        }
+    }
+}
+
+fn display_value(value: &CellValue, format: Option<&CellFormat>) -> String {
+    match (value, format) {
+        (CellValue::Number(_), Some(format)) => format.format_value(value),
+        _ => value.to_display_string(),
+    }
+}
+
+/// Quote a field holding separators, line breaks or a leading quote, as
+/// [`super::paste::PasteParser::split`] reads them
+fn tsv_field(value: &str) -> String {
+    if value.contains(['\t', '\n', '\r']) || value.starts_with('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::behaviors::paste::{PasteOptions, PasteParser};
+
+    #[test]
+    fn test_text_round_trips_through_paste_parser() {
+        let facade = SpreadsheetFacade::new();
+        facade
+            .set_cell_text(&CellAddress::new(0, 0), "two\tfields")
+            .unwrap();
+        facade
+            .set_cell_text(&CellAddress::new(1, 0), "\"quoted\"\nline")
+            .unwrap();
+        facade
+            .set_cell_format(&CellAddress::new(0, 1), CellFormat::percent(1))
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(0, 1), "0.25")
+            .unwrap();
+
+        let range = CellRange::new(CellAddress::new(0, 0), CellAddress::new(1, 1));
+        let contents = ClipboardContents::copy(&facade, &range);
+        let fields = PasteParser::new(PasteOptions::default()).split(&contents.text);
+        assert_eq!(
+            fields,
+            vec![vec!["two\tfields", "\"quoted\"\nline"], vec!["25.0%", ""]]
+        );
+    }
+
+    #[test]
+    fn test_payload_versions() {
+        let payload = ClipboardPayload {
+            version: CLIPBOARD_VERSION,
+            source: CellAddress::new(0, 0),
+            rows: vec![vec![ClipboardCell {
+                content: PasteContent::Input("=A2".to_string()),
+                format: None,
+                style: Some(CellStyle::new("Input", CellFormat::number(2))),
+                origin: None,
+            }]],
+        };
+        let json = payload.to_json();
+        assert_eq!(ClipboardPayload::from_json(&json), Some(payload.clone()));
+
+        let newer = ClipboardPayload {
+            version: CLIPBOARD_VERSION + 1,
+            ..payload
+        };
+        assert_eq!(ClipboardPayload::from_json(&newer.to_json()), None);
+        assert_eq!(ClipboardPayload::from_json("A1\tB1"), None);
+    }
+
+    #[test]
+    fn test_skipped_lines_keep_formula_origins() {
+        let facade = SpreadsheetFacade::new();
+        for (row, input) in ["1", "2", "=A2*10"].iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(0, row as u32), input)
+                .unwrap();
+        }
+
+        // Row 2 is skipped, so A3 lands right under A1 and its formula
+        // moves up a row with it
+        let contents = ClipboardContents::copy_lines(&facade, &[0, 2], &[0]);
+        assert_eq!(contents.text, "1\n20\n");
+        assert_eq!(
+            contents.payload.rows[1][0].origin,
+            Some(CellAddress::new(0, 2))
+        );
+
+        let paste = contents.payload.to_paste(CellAddress::new(2, 0));
+        assert_eq!(paste.rows[1][0], PasteContent::Input("=C1*10".to_string()));
+    }
+}
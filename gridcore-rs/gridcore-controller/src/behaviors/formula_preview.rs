@@ -0,0 +1,107 @@
+//! Live results for the formula being edited.
+//!
+//! While a formula is typed the editor shows what it would evaluate to, or
+//! with part of it selected what that part evaluates to, like F9 in Excel
+//! but without replacing the text. Nothing is written to the sheet.
+
+use gridcore_core::formula::FormulaTranslator;
+use gridcore_core::types::{CellAddress, CellValue};
+use gridcore_core::SpreadsheetFacade;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// A previewed value, ready to show next to the editor
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FormulaPreview {
+    /// The value as the cell would display it, errors as their code
+    pub text: String,
+    pub is_error: bool,
+    /// Byte range of the editor text that was evaluated, `None` when it is
+    /// the whole formula
+    pub span: Option<Range<usize>>,
+}
+
+impl FormulaPreview {
+    fn new(
+        facade: &SpreadsheetFacade,
+        at: &CellAddress,
+        value: CellValue,
+        span: Option<Range<usize>>,
+    ) -> Self {
+        let text = match (&value, facade.get_effective_format(at)) {
+            (CellValue::Number(_), Some(format)) => format.format_value(&value),
+            _ => value.to_display_string(),
+        };
+        Self {
+            text,
+            is_error: value.is_error(),
+            span,
+        }
+    }
+}
+
+/// Preview editor `text` as if it were committed to `at`. `None` unless it
+/// is a formula that parses and evaluates within the preview budget.
+pub fn preview(
+    facade: &SpreadsheetFacade,
+    translator: &FormulaTranslator,
+    text: &str,
+    at: &CellAddress,
+) -> Option<FormulaPreview> {
+    if !text.starts_with('=') {
+        return None;
+    }
+    let canonical = translator.to_canonical(text);
+    let value = facade.evaluate_preview(&canonical, at).ok()?;
+    Some(FormulaPreview::new(facade, at, value, None))
+}
+
+/// Preview the smallest complete part of formula `text` covering the
+/// editor selection `span`, given as byte offsets into `text`
+pub fn preview_selection(
+    facade: &SpreadsheetFacade,
+    translator: &FormulaTranslator,
+    text: &str,
+    span: Range<usize>,
+    at: &CellAddress,
+) -> Option<FormulaPreview> {
+    if !text.starts_with('=') {
+        return None;
+    }
+    let canonical = translator.to_canonical(text);
+    let span = translator.canonical_span(text, span);
+    let (span, value) = facade.evaluate_subexpression(&canonical, span, at).ok()?;
+    let span = translator.display_span(&canonical, span);
+    Some(FormulaPreview::new(facade, at, value, Some(span)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gridcore_core::domain::CellFormat;
+    use gridcore_core::formula::FormulaConvention;
+
+    #[test]
+    fn test_preview_follows_convention_and_format() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        facade.set_cell_value(&a1, "0.5").unwrap();
+        facade.set_cell_format(&b1, CellFormat::percent(0)).unwrap();
+        let translator = FormulaTranslator::new(FormulaConvention::Semicolon);
+
+        let result = preview(&facade, &translator, "=MAX(A1; 0,25)", &b1).unwrap();
+        assert_eq!(result.text, "50%");
+        assert!(!result.is_error);
+
+        let text = "=MAX(A1; 0,25)*SUM(A1; 1)";
+        let start = text.find("SUM").unwrap();
+        let result = preview_selection(&facade, &translator, text, start..start + 1, &b1).unwrap();
+        assert_eq!(&text[result.span.unwrap()], "SUM(A1; 1)");
+        assert_eq!(result.text, "150%");
+
+        // Plain values and half-typed formulas have no preview
+        assert_eq!(preview(&facade, &translator, "42", &b1), None);
+        assert_eq!(preview(&facade, &translator, "=MAX(A1;", &b1), None);
+    }
+}
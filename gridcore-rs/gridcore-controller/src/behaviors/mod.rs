@@ -1,5 +1,15 @@
 pub mod autocomplete;
+pub mod case_change;
+pub mod cell_transform;
+pub mod clipboard;
+pub mod formula_preview;
+pub mod numeric_entry;
+pub mod paste;
+pub mod quick_totals;
+pub mod range_drag;
 pub mod resize;
 pub mod selection_stats;
 pub mod shared;
+pub mod trace;
 pub mod vim;
+pub mod visible_cells;
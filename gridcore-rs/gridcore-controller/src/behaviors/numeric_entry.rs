@@ -0,0 +1,230 @@
+//! Forgiving number entry.
+//!
+//! Text committed from the editor is normalized before it is stored, so
+//! `$1,000,000` typed into a currency cell is stored as the number 1000000
+//! and `5` typed into a percent cell as 0.05. Input that cannot be read as
+//! a number is stored exactly as typed.
+
+use gridcore_core::domain::{CellFormat, NumberFormat};
+
+/// Currency symbols accepted around a number in any cell
+const CURRENCY_SYMBOLS: [&str; 4] = ["$", "€", "£", "¥"];
+
+/// Canonical text for an entry committed to a cell with `format`, reading
+/// numbers with `decimal` as the decimal separator.
+///
+/// Formulas and entries starting with an apostrophe (forced text) pass
+/// through unchanged, as does anything that is not a number.
+pub fn normalize_entry(text: &str, format: Option<&CellFormat>, decimal: char) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() || trimmed.starts_with('=') || trimmed.starts_with('\'') {
+        return text.to_string();
+    }
+    let number_format = format.map(|format| &format.number_format);
+    if matches!(number_format, Some(NumberFormat::Text)) {
+        // Text cells keep digits as typed
+        return format!("'{}", text);
+    }
+
+    let symbol = match number_format {
+        Some(NumberFormat::Currency { symbol, .. } | NumberFormat::Accounting { symbol, .. }) => {
+            Some(symbol.as_str())
+        }
+        _ => None,
+    };
+    // Accounting style negatives, e.g. (1,234.50)
+    let (trimmed, negative) = match trimmed.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        Some(inner) if !inner.trim_start().starts_with('-') => (inner.trim(), true),
+        _ => (trimmed, false),
+    };
+    let Some(body) = strip_currency(trimmed, symbol) else {
+        return text.to_string();
+    };
+    let typed_percent = body.trim_end().ends_with('%');
+    let Some(number) = localized_number(&body, decimal).and_then(|n| n.parse::<f64>().ok()) else {
+        return text.to_string();
+    };
+
+    // Like Excel's automatic percent entry: 5 in a percent cell means 5%,
+    // while 0.05 already is a fraction
+    let number = match number_format {
+        Some(NumberFormat::Percent { .. }) if !typed_percent && number.abs() >= 1.0 => {
+            number / 100.0
+        }
+        _ => number,
+    };
+    if negative {
+        (-number).to_string()
+    } else {
+        number.to_string()
+    }
+}
+
+/// Remove one currency symbol before or after the number, keeping any sign
+/// in front, e.g. `-$5`, `$-5` and `5 €` all become `-5` or `5`. Returns
+/// `None` for text with symbols in other places.
+fn strip_currency(text: &str, symbol: Option<&str>) -> Option<String> {
+    let (sign, rest) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest.trim_start()),
+        None => ("", text),
+    };
+    let symbols = symbol
+        .filter(|symbol| !symbol.is_empty())
+        .into_iter()
+        .chain(CURRENCY_SYMBOLS);
+    for symbol in symbols {
+        if let Some(rest) = rest.strip_prefix(symbol) {
+            return Some(format!("{}{}", sign, rest.trim_start()));
+        }
+        if let Some(rest) = rest.strip_suffix(symbol) {
+            return Some(format!("{}{}", sign, rest.trim_end()));
+        }
+    }
+    let stray = CURRENCY_SYMBOLS
+        .into_iter()
+        .chain(symbol)
+        .any(|symbol| !symbol.is_empty() && rest.contains(symbol));
+    (!stray).then(|| text.to_string())
+}
+
+/// Canonical text of a number written with `decimal` as the decimal
+/// separator, optional digit grouping, an optional exponent and an optional
+/// trailing `%`
+pub fn localized_number(text: &str, decimal: char) -> Option<String> {
+    let group = if decimal == ',' { '.' } else { ',' };
+    let text = text.trim();
+    let (body, percent) = match text.strip_suffix('%') {
+        Some(body) => (body.trim_end(), true),
+        None => (text, false),
+    };
+    let (sign, digits) = match body.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", body.strip_prefix('+').unwrap_or(body)),
+    };
+    let (mantissa, exponent) = match digits.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (digits, None),
+    };
+    let (whole, fraction) = match mantissa.split_once(decimal) {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (mantissa, None),
+    };
+
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let groups: Vec<&str> = whole.split([group, ' ', '\u{a0}']).collect();
+    let grouped = groups.len() > 1;
+    let groups_valid = groups.iter().enumerate().all(|(i, g)| {
+        all_digits(g)
+            && match (grouped, i) {
+                (false, _) => true,
+                (true, 0) => (1..=3).contains(&g.len()),
+                (true, _) => g.len() == 3,
+            }
+    });
+    let fraction_valid = fraction.is_none_or(|f| !f.is_empty() && all_digits(f));
+    let exponent_valid = exponent.is_none_or(|e| {
+        let e = e.strip_prefix(['+', '-']).unwrap_or(e);
+        !e.is_empty() && all_digits(e)
+    });
+    if !groups_valid
+        || !fraction_valid
+        || !exponent_valid
+        || (whole.is_empty() && fraction.is_none())
+    {
+        return None;
+    }
+
+    let mut canonical = format!("{}{}", sign, groups.concat());
+    if let Some(fraction) = fraction {
+        canonical.push('.');
+        canonical.push_str(fraction);
+    }
+    if let Some(exponent) = exponent {
+        canonical.push('e');
+        canonical.push_str(exponent);
+    }
+    let number: f64 = canonical.parse().ok()?;
+    Some(if percent {
+        (number / 100.0).to_string()
+    } else {
+        canonical
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_cells_read_whole_numbers_as_percentages() {
+        let percent = CellFormat::percent(0);
+        assert_eq!(normalize_entry("5", Some(&percent), '.'), "0.05");
+        assert_eq!(normalize_entry("5%", Some(&percent), '.'), "0.05");
+        assert_eq!(normalize_entry("0.25", Some(&percent), '.'), "0.25");
+        assert_eq!(normalize_entry("12,5 %", Some(&percent), ','), "0.125");
+        // Outside percent cells only a typed % divides
+        assert_eq!(normalize_entry("5", None, '.'), "5");
+        assert_eq!(normalize_entry("5%", None, '.'), "0.05");
+    }
+
+    #[test]
+    fn test_currency_cells_under_both_separators() {
+        let dollars = CellFormat::currency("$", 2);
+        assert_eq!(
+            normalize_entry("$1,000,000", Some(&dollars), '.'),
+            "1000000"
+        );
+        assert_eq!(
+            normalize_entry("-$1,234.50", Some(&dollars), '.'),
+            "-1234.5"
+        );
+        assert_eq!(normalize_entry("1000000", Some(&dollars), '.'), "1000000");
+
+        let euros = CellFormat::currency("€", 2);
+        assert_eq!(normalize_entry("1.234,50 €", Some(&euros), ','), "1234.5");
+        assert_eq!(normalize_entry("€-3,5", Some(&euros), ','), "-3.5");
+
+        let accounting = CellFormat::accounting("€", 2);
+        assert_eq!(
+            normalize_entry("(1.234,50 €)", Some(&accounting), ','),
+            "-1234.5"
+        );
+        assert_eq!(normalize_entry("(5)", None, '.'), "-5");
+
+        let francs = CellFormat::currency("CHF", 2);
+        assert_eq!(normalize_entry("CHF 12.5", Some(&francs), '.'), "12.5");
+    }
+
+    #[test]
+    fn test_unreadable_entries_are_kept_as_typed() {
+        let dollars = CellFormat::currency("$", 2);
+        assert_eq!(normalize_entry("1,5", None, '.'), "1,5");
+        assert_eq!(normalize_entry("$ 5 each", Some(&dollars), '.'), "$ 5 each");
+        assert_eq!(normalize_entry("5$5", None, '.'), "5$5");
+        assert_eq!(normalize_entry("hello", Some(&dollars), '.'), "hello");
+        assert_eq!(normalize_entry("=A1*2", Some(&dollars), '.'), "=A1*2");
+    }
+
+    #[test]
+    fn test_forced_text_and_text_cells() {
+        assert_eq!(normalize_entry("'00123", None, '.'), "'00123");
+        assert_eq!(
+            normalize_entry("00123", Some(&CellFormat::default()), '.'),
+            "123"
+        );
+        let text = CellFormat {
+            number_format: NumberFormat::Text,
+            ..CellFormat::default()
+        };
+        assert_eq!(normalize_entry("00123", Some(&text), '.'), "'00123");
+    }
+
+    #[test]
+    fn test_scientific_notation() {
+        assert_eq!(normalize_entry("1.5e3", None, '.'), "1500");
+        assert_eq!(normalize_entry("2E-3", None, '.'), "0.002");
+        assert_eq!(normalize_entry("1,5E+2", None, ','), "150");
+        assert_eq!(localized_number("1e", '.'), None);
+        assert_eq!(localized_number("1,000e2", '.'), Some("1000e2".to_string()));
+    }
+}
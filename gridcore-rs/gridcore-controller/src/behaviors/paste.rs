@@ -0,0 +1,369 @@
+//! Parsing of pasted TSV and CSV text
+//!
+//! Other spreadsheet apps put tab-separated text on the clipboard, with
+//! numbers written in the user's locale and formulas as typed. The parser
+//! splits the text into fields, honouring quoted fields that contain
+//! separators or line breaks, and turns every field into the input the
+//! facade expects: canonical numbers and canonical formula text.
+
+use super::numeric_entry::localized_number;
+use gridcore_core::domain::{CellFormat, CellStyle};
+use gridcore_core::fill::adjuster::DefaultFormulaAdjuster;
+use gridcore_core::fill::{FillDirection, FormulaAdjuster};
+use gridcore_core::formula::{CellRange, FormulaConvention, FormulaTranslator};
+use gridcore_core::types::CellAddress;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasteOptions {
+    /// Field separator: tab for clipboard text, the list separator for CSV
+    pub delimiter: char,
+    /// Treat fields starting with `=` as formulas; otherwise they are text
+    pub formulas: bool,
+    /// Top-left cell the text was copied from. When known, relative
+    /// references in formulas move with the paste like a copied formula.
+    pub source: Option<CellAddress>,
+}
+
+impl Default for PasteOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: '\t',
+            formulas: true,
+            source: None,
+        }
+    }
+}
+
+impl PasteOptions {
+    /// Options for CSV text in `convention`, whose list separator is also
+    /// the field separator (`,` or `;`)
+    pub fn csv(convention: FormulaConvention) -> Self {
+        Self {
+            delimiter: convention
+                .argument_separator()
+                .chars()
+                .next()
+                .unwrap_or(','),
+            ..Self::default()
+        }
+    }
+}
+
+/// What a pasted field writes to its cell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PasteContent {
+    /// Input as if typed: a formula, number, boolean or text
+    Input(String),
+    /// Text stored as is, for formula-like fields when formulas are off
+    Text(String),
+    /// An empty field, which clears the cell
+    Empty,
+}
+
+/// Problems to confirm before a paste is applied
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PasteConflicts {
+    /// Non-empty cells the paste would replace or clear
+    pub overwritten: Vec<CellAddress>,
+    /// Pasted rows below the last row of the sheet, which are dropped
+    pub rows_outside: usize,
+    /// Pasted columns right of the last column of the sheet, which are dropped
+    pub cols_outside: usize,
+}
+
+impl PasteConflicts {
+    pub fn is_empty(&self) -> bool {
+        self.overwritten.is_empty() && self.rows_outside == 0 && self.cols_outside == 0
+    }
+
+    /// Add the conflicts of a paste applied along with this one
+    pub fn merge(&mut self, other: PasteConflicts) {
+        self.overwritten.extend(other.overwritten);
+        self.rows_outside = self.rows_outside.max(other.rows_outside);
+        self.cols_outside = self.cols_outside.max(other.cols_outside);
+    }
+}
+
+/// Pasted text laid out from a target cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedPaste {
+    pub target: CellAddress,
+    pub rows: Vec<Vec<PasteContent>>,
+    /// Fields in the widest row
+    pub width: usize,
+    /// Whether every row has the same number of fields
+    pub rectangular: bool,
+    /// Formats for the pasted cells, laid out like `rows`. `None` leaves
+    /// the formats of the target cells alone.
+    pub formats: Option<Vec<Vec<PastedFormat>>>,
+}
+
+/// How one pasted cell is formatted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PastedFormat {
+    /// The format the cell was displayed with
+    pub format: Option<CellFormat>,
+    /// The named style the cell used, with its definition so the style can
+    /// be created in a workbook that lacks it
+    pub style: Option<CellStyle>,
+}
+
+impl ParsedPaste {
+    /// The block covered by the paste, or `None` for empty text
+    pub fn range(&self) -> Option<CellRange> {
+        (self.width > 0).then(|| {
+            CellRange::new(
+                self.target,
+                CellAddress::new(
+                    self.target.col.saturating_add(self.width as u32 - 1),
+                    self.target.row.saturating_add(self.rows.len() as u32 - 1),
+                ),
+            )
+        })
+    }
+
+    /// Every field with the cell it lands in
+    pub fn cells(&self) -> impl Iterator<Item = (CellAddress, &PasteContent)> + '_ {
+        self.rows.iter().enumerate().flat_map(move |(r, row)| {
+            row.iter().enumerate().map(move |(c, content)| {
+                let address = CellAddress::new(
+                    self.target.col.saturating_add(c as u32),
+                    self.target.row.saturating_add(r as u32),
+                );
+                (address, content)
+            })
+        })
+    }
+
+    /// What applying the paste would overwrite or drop, in a sheet whose
+    /// last cell is `limit`
+    pub fn conflicts(
+        &self,
+        limit: CellAddress,
+        is_filled: impl Fn(&CellAddress) -> bool,
+    ) -> PasteConflicts {
+        let outside = |start: u32, count: usize, last: u32| {
+            let fitting = (last.saturating_sub(start) as usize).saturating_add(1);
+            if start > last {
+                count
+            } else {
+                count.saturating_sub(fitting)
+            }
+        };
+
+        PasteConflicts {
+            overwritten: self
+                .cells()
+                .map(|(address, _)| address)
+                .filter(|address| address.col <= limit.col && address.row <= limit.row)
+                .filter(|address| is_filled(address))
+                .collect(),
+            rows_outside: outside(self.target.row, self.rows.len(), limit.row),
+            cols_outside: outside(self.target.col, self.width, limit.col),
+        }
+    }
+}
+
+pub struct PasteParser {
+    options: PasteOptions,
+    translator: FormulaTranslator,
+}
+
+impl PasteParser {
+    pub fn new(options: PasteOptions) -> Self {
+        Self {
+            options,
+            translator: FormulaTranslator::default(),
+        }
+    }
+
+    /// Read numbers and formulas in the translator's convention
+    pub fn with_translator(mut self, translator: FormulaTranslator) -> Self {
+        self.translator = translator;
+        self
+    }
+
+    /// Split text into records of raw fields. Quoted fields may contain the
+    /// delimiter, line breaks and doubled quotes; a trailing line break does
+    /// not start another record.
+    pub fn split(&self, text: &str) -> Vec<Vec<String>> {
+        let delimiter = self.options.delimiter;
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut field_start = true;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if quoted {
+                match c {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' => quoted = false,
+                    _ => field.push(c),
+                }
+                continue;
+            }
+
+            match c {
+                '"' if field_start => quoted = true,
+                c if c == delimiter => {
+                    record.push(std::mem::take(&mut field));
+                    field_start = true;
+                    continue;
+                }
+                '\r' if chars.peek() == Some(&'\n') => continue,
+                '\r' | '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                    field_start = true;
+                    continue;
+                }
+                _ => field.push(c),
+            }
+            field_start = false;
+        }
+
+        if !field.is_empty() || !record.is_empty() || quoted {
+            record.push(field);
+            records.push(record);
+        }
+        records
+    }
+
+    /// Parse text to be pasted with its top-left field at `target`
+    pub fn parse(&self, text: &str, target: CellAddress) -> ParsedPaste {
+        let records = self.split(text);
+        let width = records.iter().map(Vec::len).max().unwrap_or(0);
+        let rectangular = records.iter().all(|record| record.len() == width);
+
+        let rows = records
+            .iter()
+            .enumerate()
+            .map(|(r, record)| {
+                record
+                    .iter()
+                    .enumerate()
+                    .map(|(c, field)| self.content(field, target, c as u32, r as u32))
+                    .collect()
+            })
+            .collect();
+
+        ParsedPaste {
+            target,
+            rows,
+            width,
+            rectangular,
+            formats: None,
+        }
+    }
+
+    fn content(&self, field: &str, target: CellAddress, col: u32, row: u32) -> PasteContent {
+        if field.trim().is_empty() {
+            return PasteContent::Empty;
+        }
+        if field.starts_with('=') {
+            if !self.options.formulas {
+                return PasteContent::Text(field.to_string());
+            }
+            let formula = self.translator.to_canonical(field);
+            let Some(source) = self.options.source else {
+                return PasteContent::Input(formula);
+            };
+            let from = CellAddress::new(source.col + col, source.row + row);
+            let to = CellAddress::new(target.col + col, target.row + row);
+            return PasteContent::Input(move_formula(formula, &from, &to));
+        }
+
+        let decimal = self.translator.convention().decimal_separator();
+        match localized_number(field, decimal) {
+            Some(number) => PasteContent::Input(number),
+            None => PasteContent::Input(field.to_string()),
+        }
+    }
+}
+
+/// Canonical `formula` copied from `from` to `to`, with relative
+/// references following the move. Formulas the adjuster cannot read are
+/// left as they are.
+pub(crate) fn move_formula(formula: String, from: &CellAddress, to: &CellAddress) -> String {
+    DefaultFormulaAdjuster::new()
+        .adjust_formula(&formula, from, to, FillDirection::Down)
+        .unwrap_or(formula)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoted_fields_keep_separators_and_line_breaks() {
+        let parser = PasteParser::new(PasteOptions::default());
+        let text = "name\tnote\r\n\"Smith, J\"\t\"line one\nline \"\"two\"\"\"\r\nx\t\"a\tb\"\r\n";
+        assert_eq!(
+            parser.split(text),
+            vec![
+                vec!["name", "note"],
+                vec!["Smith, J", "line one\nline \"two\""],
+                vec!["x", "a\tb"],
+            ]
+        );
+
+        let csv = PasteParser::new(PasteOptions::csv(FormulaConvention::Semicolon));
+        assert_eq!(csv.split("a;\"b;c\";d"), vec![vec!["a", "b;c", "d"]]);
+    }
+
+    #[test]
+    fn test_localized_numbers() {
+        assert_eq!(localized_number("1,234.5", '.'), Some("1234.5".to_string()));
+        assert_eq!(localized_number("1.234,5", ','), Some("1234.5".to_string()));
+        assert_eq!(
+            localized_number("-1 234,5", ','),
+            Some("-1234.5".to_string())
+        );
+        assert_eq!(localized_number("12,5 %", ','), Some("0.125".to_string()));
+        // A comma that is not a valid thousands group is not a number
+        assert_eq!(localized_number("1,5", '.'), None);
+        assert_eq!(localized_number("abc", '.'), None);
+        assert_eq!(localized_number("1.", '.'), None);
+    }
+
+    #[test]
+    fn test_formulas_follow_options() {
+        let target = CellAddress::new(3, 4);
+        let semicolon = FormulaTranslator::new(FormulaConvention::Semicolon);
+        let parsed = PasteParser::new(PasteOptions::default())
+            .with_translator(semicolon.clone())
+            .parse("1,5\t=SUM(A1;2,5)", target);
+        assert_eq!(parsed.rows[0][0], PasteContent::Input("1.5".to_string()));
+        assert_eq!(
+            parsed.rows[0][1],
+            PasteContent::Input("=SUM(A1,2.5)".to_string())
+        );
+
+        let options = PasteOptions {
+            formulas: false,
+            ..PasteOptions::default()
+        };
+        let parsed = PasteParser::new(options).parse("=A1", target);
+        assert_eq!(parsed.rows[0][0], PasteContent::Text("=A1".to_string()));
+    }
+
+    #[test]
+    fn test_conflicts_report_overwrites_and_overflow() {
+        let parsed = PasteParser::new(PasteOptions::default())
+            .parse("1\t2\t3\n4\t5", CellAddress::new(8, 8));
+        assert!(!parsed.rectangular);
+        assert_eq!(parsed.width, 3);
+
+        let filled = CellAddress::new(9, 9);
+        let conflicts = parsed.conflicts(CellAddress::new(9, 99), |a| *a == filled);
+        assert_eq!(conflicts.overwritten, vec![filled]);
+        assert_eq!(conflicts.cols_outside, 1);
+        assert_eq!(conflicts.rows_outside, 0);
+    }
+}
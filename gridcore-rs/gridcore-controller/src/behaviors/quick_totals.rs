@@ -0,0 +1,272 @@
+//! Quick totals: `=SUM`, `=AVERAGE` or `=COUNT` formulas written next to a
+//! selected block of numbers.
+//!
+//! A column of numbers gets its total in the row below, a row of numbers in
+//! the column to its right. A block of several rows and columns gets column
+//! totals first; asking again right away adds row totals and the grand
+//! total. Totals never overwrite: when the row or column next to the block
+//! holds anything, the next empty one is used instead.
+
+use gridcore_core::formula::CellRange;
+use gridcore_core::types::CellAddress;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Key that writes sum totals: Alt+= in the notation of
+/// [`KeyboardEvent::to_vim_notation`](crate::controller::KeyboardEvent::to_vim_notation)
+pub const QUICK_SUM_KEY: &str = "A-=";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuickFunction {
+    Sum,
+    Average,
+    Count,
+}
+
+impl QuickFunction {
+    /// The formula function written for the totals
+    pub fn name(self) -> &'static str {
+        match self {
+            QuickFunction::Sum => "SUM",
+            QuickFunction::Average => "AVERAGE",
+            QuickFunction::Count => "COUNT",
+        }
+    }
+
+    /// Parse the name used by ex commands, e.g. `sum`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sum" => Some(QuickFunction::Sum),
+            "average" | "avg" => Some(QuickFunction::Average),
+            "count" => Some(QuickFunction::Count),
+            _ => None,
+        }
+    }
+
+    /// Formula applying the function to `range`
+    pub fn formula(self, range: &CellRange) -> String {
+        format!("={}({})", self.name(), range)
+    }
+}
+
+impl fmt::Display for QuickFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Column totals written for a block, remembered so that asking again adds
+/// the row totals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnTotals {
+    pub range: CellRange,
+    pub function: QuickFunction,
+    /// Row the column totals were written to
+    pub row: u32,
+    /// First total written, where the cursor was left
+    pub first: CellAddress,
+}
+
+/// Cells to write for one quick totals request
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TotalsPlan {
+    pub writes: Vec<(CellAddress, String)>,
+    /// Set when the plan wrote column totals of a block, which a repeated
+    /// request completes
+    pub column_totals: Option<ColumnTotals>,
+}
+
+/// Plan the totals of `range`. `previous` are column totals written by the
+/// request before, which are completed with row totals when they belong to
+/// the same block and function. `limit` is the last cell of the grid and
+/// `occupied` tells cells that may not be overwritten. Returns `None` when
+/// there is no empty row or column left for the totals.
+pub fn plan_totals(
+    range: &CellRange,
+    function: QuickFunction,
+    previous: Option<&ColumnTotals>,
+    limit: CellAddress,
+    occupied: impl Fn(&CellAddress) -> bool,
+) -> Option<TotalsPlan> {
+    let is_row = range.row_count() == 1 && range.col_count() > 1;
+    let is_block = range.row_count() > 1 && range.col_count() > 1;
+
+    if let Some(previous) = previous
+        .filter(|previous| is_block && previous.range == *range && previous.function == function)
+    {
+        let rows = range.start.row..=previous.row;
+        let col = free_column(range.end.col, rows, limit, &occupied)?;
+        let mut writes = row_totals(range, function, col);
+        writes.push((CellAddress::new(col, previous.row), function.formula(range)));
+        return Some(TotalsPlan {
+            writes,
+            column_totals: None,
+        });
+    }
+
+    if is_row {
+        let col = free_column(
+            range.end.col,
+            range.start.row..=range.end.row,
+            limit,
+            &occupied,
+        )?;
+        return Some(TotalsPlan {
+            writes: row_totals(range, function, col),
+            column_totals: None,
+        });
+    }
+
+    let row = free_row(range, limit, &occupied)?;
+    let writes = column_totals(range, function, row);
+    let column_totals = is_block.then(|| ColumnTotals {
+        range: *range,
+        function,
+        row,
+        first: writes[0].0,
+    });
+    Some(TotalsPlan {
+        writes,
+        column_totals,
+    })
+}
+
+/// First row below `range` that is empty across its columns
+fn free_row(
+    range: &CellRange,
+    limit: CellAddress,
+    occupied: &impl Fn(&CellAddress) -> bool,
+) -> Option<u32> {
+    (range.end.row + 1..=limit.row).find(|&row| {
+        (range.start.col..=range.end.col).all(|col| !occupied(&CellAddress::new(col, row)))
+    })
+}
+
+/// First column right of `after` that is empty across `rows`
+fn free_column(
+    after: u32,
+    rows: std::ops::RangeInclusive<u32>,
+    limit: CellAddress,
+    occupied: &impl Fn(&CellAddress) -> bool,
+) -> Option<u32> {
+    (after + 1..=limit.col).find(|&col| {
+        rows.clone()
+            .all(|row| !occupied(&CellAddress::new(col, row)))
+    })
+}
+
+fn column_totals(
+    range: &CellRange,
+    function: QuickFunction,
+    row: u32,
+) -> Vec<(CellAddress, String)> {
+    (range.start.col..=range.end.col)
+        .map(|col| {
+            let column = CellRange::new(
+                CellAddress::new(col, range.start.row),
+                CellAddress::new(col, range.end.row),
+            );
+            (CellAddress::new(col, row), function.formula(&column))
+        })
+        .collect()
+}
+
+fn row_totals(range: &CellRange, function: QuickFunction, col: u32) -> Vec<(CellAddress, String)> {
+    range
+        .iter_rows()
+        .map(|row| (CellAddress::new(col, row.start.row), function.formula(&row)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(a1: &str) -> CellRange {
+        CellRange::from_string(a1).unwrap()
+    }
+
+    fn cell(a1: &str) -> CellAddress {
+        CellAddress::from_a1(a1).unwrap()
+    }
+
+    fn formulas(plan: &TotalsPlan) -> Vec<(String, &str)> {
+        plan.writes
+            .iter()
+            .map(|(address, formula)| (address.to_a1(), formula.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_column_and_row_totals() {
+        let limit = cell("Z100");
+        let plan =
+            plan_totals(&range("B2:B4"), QuickFunction::Sum, None, limit, |_| false).unwrap();
+        assert_eq!(formulas(&plan), [("B5".to_string(), "=SUM(B2:B4)")]);
+        assert_eq!(plan.column_totals, None);
+
+        let plan = plan_totals(&range("A1:C1"), QuickFunction::Count, None, limit, |_| {
+            false
+        })
+        .unwrap();
+        assert_eq!(formulas(&plan), [("D1".to_string(), "=COUNT(A1:C1)")]);
+    }
+
+    #[test]
+    fn test_block_totals_take_two_requests() {
+        let limit = cell("Z100");
+        let block = range("A1:B2");
+        let first = plan_totals(&block, QuickFunction::Average, None, limit, |_| false).unwrap();
+        assert_eq!(
+            formulas(&first),
+            [
+                ("A3".to_string(), "=AVERAGE(A1:A2)"),
+                ("B3".to_string(), "=AVERAGE(B1:B2)"),
+            ]
+        );
+        let previous = first.column_totals.unwrap();
+        assert_eq!(previous.first, cell("A3"));
+
+        let second = plan_totals(
+            &block,
+            QuickFunction::Average,
+            Some(&previous),
+            limit,
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(
+            formulas(&second),
+            [
+                ("C1".to_string(), "=AVERAGE(A1:B1)"),
+                ("C2".to_string(), "=AVERAGE(A2:B2)"),
+                ("C3".to_string(), "=AVERAGE(A1:B2)"),
+            ]
+        );
+
+        // Another function starts over with column totals
+        let other = plan_totals(&block, QuickFunction::Sum, Some(&previous), limit, |_| {
+            false
+        });
+        assert!(other.unwrap().column_totals.is_some());
+    }
+
+    #[test]
+    fn test_occupied_targets_move_totals_on() {
+        let limit = cell("E5");
+        let taken = [cell("B4"), cell("D1")];
+        let occupied = |address: &CellAddress| taken.contains(address);
+
+        let plan = plan_totals(&range("A1:B3"), QuickFunction::Sum, None, limit, occupied).unwrap();
+        assert_eq!(plan.writes[0].0, cell("A5"));
+
+        let plan = plan_totals(&range("A1:C1"), QuickFunction::Sum, None, limit, occupied).unwrap();
+        assert_eq!(plan.writes[0].0, cell("E1"));
+
+        // No empty row before the end of the grid
+        assert_eq!(
+            plan_totals(&range("A4:A5"), QuickFunction::Sum, None, limit, occupied),
+            None
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Moving and copying a block of cells by dragging the selection border.
+//!
+//! A drag remembers the block it picked up and which of its cells the
+//! pointer grabbed, so the destination keeps that cell under the pointer.
+//! Nothing is written before the drop; until then the destination is only
+//! drawn as an outline.
+
+use crate::controller::CellPosition;
+use crate::state::Selection;
+use gridcore_core::formula::CellRange;
+use gridcore_core::types::CellAddress;
+
+/// Distance from the selection edge, in pixels, that still grabs the border
+pub const BORDER_TOLERANCE: f64 = 4.0;
+/// Side of the square at the bottom-right corner of the selection that is
+/// the fill handle rather than the border
+pub const FILL_HANDLE_SIZE: f64 = 6.0;
+/// Distance from the viewport edge at which a drag starts scrolling
+pub const AUTO_SCROLL_MARGIN: f64 = 24.0;
+/// Pixels scrolled per pointer update near the edge
+pub const AUTO_SCROLL_STEP: f64 = 20.0;
+
+/// What part of the selection a point is on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionHit {
+    Outside,
+    Interior,
+    /// The outline, which starts a drag
+    Border,
+    FillHandle,
+}
+
+/// Where `(x, y)` lies relative to the selection drawn at `rect`
+pub fn hit_test_selection(rect: &CellPosition, x: f64, y: f64) -> SelectionHit {
+    let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+    let t = BORDER_TOLERANCE;
+    if x < rect.x - t || x > right + t || y < rect.y - t || y > bottom + t {
+        return SelectionHit::Outside;
+    }
+    if (right - x).abs() <= FILL_HANDLE_SIZE && (bottom - y).abs() <= FILL_HANDLE_SIZE {
+        return SelectionHit::FillHandle;
+    }
+    if x <= rect.x + t || x >= right - t || y <= rect.y + t || y >= bottom - t {
+        SelectionHit::Border
+    } else {
+        SelectionHit::Interior
+    }
+}
+
+/// Scroll to apply for a pointer at `(x, y)` of a cell area `width` by
+/// `height`: one step towards each edge the pointer is near or past
+pub fn auto_scroll_delta(x: f64, y: f64, width: f64, height: f64) -> (f64, f64) {
+    let delta = |position: f64, extent: f64| {
+        if position < AUTO_SCROLL_MARGIN {
+            -AUTO_SCROLL_STEP
+        } else if position > extent - AUTO_SCROLL_MARGIN {
+            AUTO_SCROLL_STEP
+        } else {
+            0.0
+        }
+    };
+    (delta(x, width), delta(y, height))
+}
+
+/// A block of cells being dragged
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeDrag {
+    source: CellRange,
+    /// Column and row of the grabbed cell within the source
+    grab: (u32, u32),
+    destination: CellRange,
+    /// Selection and cursor from before the drag, restored on cancel
+    selection: Option<Selection>,
+    cursor: CellAddress,
+    /// A drop waiting for its overwrites to be confirmed, and whether it
+    /// copies
+    pending_drop: Option<bool>,
+}
+
+impl RangeDrag {
+    /// Pick up `source` by the cell `grab`, which is moved into the source
+    /// if it lies outside it
+    pub fn new(
+        source: CellRange,
+        grab: CellAddress,
+        selection: Option<Selection>,
+        cursor: CellAddress,
+    ) -> Self {
+        let grab = (
+            grab.col.clamp(source.start.col, source.end.col) - source.start.col,
+            grab.row.clamp(source.start.row, source.end.row) - source.start.row,
+        );
+        Self {
+            destination: source,
+            source,
+            grab,
+            selection,
+            cursor,
+            pending_drop: None,
+        }
+    }
+
+    pub fn source(&self) -> &CellRange {
+        &self.source
+    }
+
+    /// Where the block would land if dropped now
+    pub fn destination(&self) -> &CellRange {
+        &self.destination
+    }
+
+    pub fn original_selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+
+    pub fn original_cursor(&self) -> CellAddress {
+        self.cursor
+    }
+
+    /// Whether a drop is waiting for confirmation, and if so whether it copies
+    pub fn pending_drop(&self) -> Option<bool> {
+        self.pending_drop
+    }
+
+    pub(crate) fn set_pending_drop(&mut self, copy: Option<bool>) {
+        self.pending_drop = copy;
+    }
+
+    /// Put the grabbed cell at `pointer`, keeping the block inside the
+    /// sheet that ends at `last`. Returns whether the destination changed.
+    pub fn move_to(&mut self, pointer: CellAddress, last: CellAddress) -> bool {
+        let width = self.source.end.col - self.source.start.col;
+        let height = self.source.end.row - self.source.start.row;
+        let col = pointer
+            .col
+            .saturating_sub(self.grab.0)
+            .min(last.col.saturating_sub(width));
+        let row = pointer
+            .row
+            .saturating_sub(self.grab.1)
+            .min(last.row.saturating_sub(height));
+        let destination = CellRange::new(
+            CellAddress::new(col, row),
+            CellAddress::new(col + width, row + height),
+        );
+        if destination == self.destination {
+            return false;
+        }
+        self.destination = destination;
+        self.pending_drop = None;
+        true
+    }
+
+    /// Occupied destination cells a drop would replace. Cells of the source
+    /// do not count when moving, since the move vacates them.
+    pub fn overwritten<F>(&self, copy: bool, is_occupied: F) -> Vec<CellAddress>
+    where
+        F: Fn(&CellAddress) -> bool,
+    {
+        self.destination
+            .cells()
+            .filter(|address| copy || !self.source.contains(address))
+            .filter(|address| is_occupied(address))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit_test_and_destination_clamping() {
+        let rect = CellPosition {
+            x: 100.0,
+            y: 50.0,
+            width: 200.0,
+            height: 100.0,
+        };
+        let hits: Vec<SelectionHit> = [
+            (90.0, 100.0),
+            (101.0, 100.0),
+            (200.0, 100.0),
+            (200.0, 148.0),
+            (298.0, 148.0),
+        ]
+        .iter()
+        .map(|&(x, y)| hit_test_selection(&rect, x, y))
+        .collect();
+        assert_eq!(
+            hits,
+            [
+                SelectionHit::Outside,
+                SelectionHit::Border,
+                SelectionHit::Interior,
+                SelectionHit::Border,
+                SelectionHit::FillHandle,
+            ]
+        );
+
+        // B2:C3 grabbed by C3 and dragged towards the top-left corner
+        let source = CellRange::new(CellAddress::new(1, 1), CellAddress::new(2, 2));
+        let mut drag = RangeDrag::new(source, CellAddress::new(2, 2), None, CellAddress::new(1, 1));
+        let last = CellAddress::new(9, 9);
+        assert!(drag.move_to(CellAddress::new(0, 0), last));
+        assert_eq!(drag.destination().to_string(), "A1:B2");
+        assert!(drag.move_to(CellAddress::new(20, 20), last));
+        assert_eq!(drag.destination().to_string(), "I9:J10");
+        assert!(!drag.move_to(CellAddress::new(15, 15), last));
+    }
+}
@@ -2,6 +2,7 @@ use crate::behaviors::shared::{NavigationDirection, NumberBuffer};
 use crate::state::{Action, ResizeTarget, UIState};
 use gridcore_core::Result;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 /// Handles column and row resizing operations
 pub struct ResizeBehavior {
@@ -160,15 +161,44 @@ pub enum ResizeType {
     Row,
 }
 
+/// How the grid shows a resize before it is committed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResizePreview {
+    /// A guideline at the new boundary, leaving the layout alone
+    #[default]
+    Ghost,
+    /// The grid laid out again at every change of the size
+    LiveReflow,
+}
+
+impl FromStr for ResizePreview {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "ghost" => Ok(ResizePreview::Ghost),
+            "live" | "reflow" => Ok(ResizePreview::LiveReflow),
+            other => Err(format!(
+                "Unknown resize preview: {} (expected ghost or live)",
+                other
+            )),
+        }
+    }
+}
+
 /// State of an ongoing resize operation
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ResizeState {
     pub is_resizing: bool,
     pub resize_type: ResizeType,
     pub resize_index: usize,
     pub start_position: f64,
     pub start_size: f64,
+    /// Provisional size, committed to every line once the resize ends
     pub current_size: f64,
+    /// The lines taking the new size, the grabbed one included, with the
+    /// size each had before
+    pub lines: Vec<(usize, f64)>,
 }
 
 impl Default for ResizeState {
@@ -180,10 +210,18 @@ impl Default for ResizeState {
             start_position: 0.0,
             start_size: 0.0,
             current_size: 0.0,
+            lines: Vec::new(),
         }
     }
 }
 
+impl ResizeState {
+    /// Indices of the lines taking the new size
+    pub fn indices(&self) -> Vec<usize> {
+        self.lines.iter().map(|&(index, _)| index).collect()
+    }
+}
+
 /// Check if a position is near a resize handle
 pub fn check_resize_hover(
     x: f64,
@@ -225,6 +263,7 @@ pub fn start_mouse_resize(
         start_position,
         start_size: current_size,
         current_size,
+        lines: vec![(index, current_size)],
     }
 }
 
@@ -279,6 +318,8 @@ mod tests {
                     rows: 20,
                     cols: 10,
                 },
+                watches: Vec::new(),
+                recent_entries: Vec::new(),
             },
             selection: None,
             modal: Some(crate::state::NavigationModal::Resize {
@@ -0,0 +1,566 @@
+//! Formula auditing: precedent/dependent tracing and the arrow geometry
+//! drawn on top of the grid.
+//!
+//! Traces are stored as cell addresses only. Pixel geometry is derived on
+//! demand from the viewport so arrows follow scrolling and resizing.
+
+use crate::controller::{CellPosition, ViewportManager};
+use gridcore_core::types::CellAddress;
+use gridcore_core::{CellRange, Expr, FormulaParser, SpreadsheetFacade};
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+
+/// Which way a trace walks the formula graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TraceDirection {
+    /// Cells the traced cell reads from
+    Precedents,
+    /// Cells that read from the traced cell
+    Dependents,
+}
+
+/// The tail of a trace arrow
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TraceSource {
+    Cell(CellAddress),
+    /// A range reference, drawn as a box with a single arrow leaving it
+    Range(CellRange),
+}
+
+/// An arrow between two cells, stored by address
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceArrow {
+    pub direction: TraceDirection,
+    pub source: TraceSource,
+    pub target: CellAddress,
+    /// Expansion level the arrow was added at (1 = direct relation)
+    pub level: usize,
+}
+
+/// Screen-space geometry for one arrow, in canvas coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArrowGeometry {
+    pub direction: TraceDirection,
+    pub level: usize,
+    pub from: (f64, f64),
+    pub to: (f64, f64),
+    /// Outline of a range source (unclipped)
+    pub source_box: Option<CellPosition>,
+    /// The tail was off-screen and has been clamped to the viewport edge
+    pub from_clipped: bool,
+    /// The head was off-screen and has been clamped to the viewport edge
+    pub to_clipped: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct TraceFrontier {
+    level: usize,
+    frontier: Vec<CellAddress>,
+    visited: FxHashSet<CellAddress>,
+}
+
+/// Trace arrows for the active cell, expanded one level per invocation
+#[derive(Clone, Debug, Default)]
+pub struct TraceArrows {
+    origin: Option<CellAddress>,
+    arrows: Vec<TraceArrow>,
+    precedents: TraceFrontier,
+    dependents: TraceFrontier,
+}
+
+impl TraceArrows {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cell the current traces were started from
+    pub fn origin(&self) -> Option<CellAddress> {
+        self.origin
+    }
+
+    pub fn arrows(&self) -> &[TraceArrow] {
+        &self.arrows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty()
+    }
+
+    /// Number of levels expanded so far in the given direction
+    pub fn level(&self, direction: TraceDirection) -> usize {
+        match direction {
+            TraceDirection::Precedents => self.precedents.level,
+            TraceDirection::Dependents => self.dependents.level,
+        }
+    }
+
+    /// Remove all arrows
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Expand the trace for `origin` by one level and return the number of arrows added.
+    ///
+    /// The first call for a cell adds its direct relations; each further call
+    /// follows the cells reached by the previous level. Tracing a different
+    /// cell discards the existing arrows.
+    pub fn trace(
+        &mut self,
+        facade: &SpreadsheetFacade,
+        origin: CellAddress,
+        direction: TraceDirection,
+    ) -> usize {
+        if self.origin != Some(origin) {
+            self.clear();
+            self.origin = Some(origin);
+        }
+
+        let state = match direction {
+            TraceDirection::Precedents => &mut self.precedents,
+            TraceDirection::Dependents => &mut self.dependents,
+        };
+
+        if state.level == 0 {
+            state.frontier = vec![origin];
+            state.visited.insert(origin);
+        }
+
+        let level = state.level + 1;
+        let mut new_arrows = Vec::new();
+        let mut next_frontier = Vec::new();
+
+        for cell in std::mem::take(&mut state.frontier) {
+            match direction {
+                TraceDirection::Precedents => {
+                    for source in find_precedents(facade, &cell) {
+                        match &source {
+                            TraceSource::Cell(address) => {
+                                if state.visited.insert(*address) {
+                                    next_frontier.push(*address);
+                                }
+                            }
+                            TraceSource::Range(range) => {
+                                for address in range.cells() {
+                                    if state.visited.insert(address) {
+                                        next_frontier.push(address);
+                                    }
+                                }
+                            }
+                        }
+                        new_arrows.push(TraceArrow {
+                            direction,
+                            source,
+                            target: cell,
+                            level,
+                        });
+                    }
+                }
+                TraceDirection::Dependents => {
+                    for dependent in find_dependents(facade, &cell) {
+                        if state.visited.insert(dependent) {
+                            next_frontier.push(dependent);
+                        }
+                        new_arrows.push(TraceArrow {
+                            direction,
+                            source: TraceSource::Cell(cell),
+                            target: dependent,
+                            level,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Range sources can contribute many cells, most of which hold plain values;
+        // only formula cells can be expanded further
+        next_frontier.retain(|address| {
+            facade
+                .get_cell(address)
+                .is_some_and(|cell| cell.has_formula())
+        });
+        state.frontier = next_frontier;
+
+        // A repeat invocation that reaches nothing new leaves the level unchanged
+        new_arrows.retain(|arrow| !self.arrows.contains(arrow));
+        if !new_arrows.is_empty() {
+            state.level = level;
+        }
+
+        let added = new_arrows.len();
+        self.arrows.extend(new_arrows);
+        added
+    }
+
+    /// Compute canvas geometry for every arrow using the current viewport transform.
+    ///
+    /// Endpoints outside the visible grid area are clamped to its edge; arrows
+    /// that never cross the visible area are omitted.
+    pub fn geometry(&self, viewport: &ViewportManager) -> Vec<ArrowGeometry> {
+        let config = viewport.get_config();
+        let clip = ClipRect {
+            left: config.row_header_width,
+            top: config.column_header_height,
+            right: viewport.get_viewport_width(),
+            bottom: viewport.get_viewport_height(),
+        };
+
+        self.arrows
+            .iter()
+            .filter_map(|arrow| {
+                let (from, source_box) = match &arrow.source {
+                    TraceSource::Cell(address) => (cell_center(viewport, address), None),
+                    TraceSource::Range(range) => {
+                        let rect = range_rect(viewport, range);
+                        (
+                            (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0),
+                            Some(rect),
+                        )
+                    }
+                };
+                let to = cell_center(viewport, &arrow.target);
+
+                let segment = clip.clip_segment(from, to)?;
+                Some(ArrowGeometry {
+                    direction: arrow.direction,
+                    level: arrow.level,
+                    from: segment.from,
+                    to: segment.to,
+                    source_box,
+                    from_clipped: segment.from_clipped,
+                    to_clipped: segment.to_clipped,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Direct precedents of a cell, keeping range references intact
+pub fn find_precedents(facade: &SpreadsheetFacade, address: &CellAddress) -> Vec<TraceSource> {
+    let mut sources = Vec::new();
+    if let Some(expr) = parse_cell_formula(facade, address) {
+        collect_sources(&expr, &mut sources);
+    }
+    sources
+}
+
+/// Direct dependents of a cell on the active sheet, in row-major order
+pub fn find_dependents(facade: &SpreadsheetFacade, address: &CellAddress) -> Vec<CellAddress> {
+    let mut dependents: Vec<CellAddress> = facade
+        .get_all_cells()
+        .into_iter()
+        .filter(|(candidate, cell)| candidate != address && cell.has_formula())
+        .filter(|(candidate, _)| {
+            find_precedents(facade, candidate)
+                .iter()
+                .any(|source| match source {
+                    TraceSource::Cell(cell) => cell == address,
+                    TraceSource::Range(range) => range.contains(address),
+                })
+        })
+        .map(|(candidate, _)| candidate)
+        .collect();
+    dependents.sort_by_key(|a| (a.row, a.col));
+    dependents
+}
+
+fn parse_cell_formula(facade: &SpreadsheetFacade, address: &CellAddress) -> Option<Expr> {
+    let cell = facade.get_cell(address)?;
+    let formula = cell.formula_text.as_ref()?;
+    FormulaParser::parse(formula).ok()
+}
+
+fn collect_sources(expr: &Expr, sources: &mut Vec<TraceSource>) {
+    let source = match expr {
+        Expr::Reference { address, .. } => TraceSource::Cell(*address),
+        Expr::Range { range, .. } => TraceSource::Range(*range),
+        Expr::FunctionCall { args, .. } | Expr::Union { areas: args } => {
+            for arg in args {
+                collect_sources(arg, sources);
+            }
+            return;
+        }
+        Expr::UnaryOp { expr, .. } => return collect_sources(expr, sources),
+        Expr::BinaryOp { left, right, .. } | Expr::Intersection { left, right } => {
+            collect_sources(left, sources);
+            collect_sources(right, sources);
+            return;
+        }
+        // Arrows only join cells of the sheet on screen
+        Expr::Literal { .. } | Expr::Name { .. } | Expr::SheetReference { .. } => return,
+    };
+
+    if !sources.contains(&source) {
+        sources.push(source);
+    }
+}
+
+fn cell_center(viewport: &ViewportManager, address: &CellAddress) -> (f64, f64) {
+    let config = viewport.get_config();
+    let pos = viewport.get_cell_position(address);
+    (
+        pos.x + config.row_header_width + pos.width / 2.0,
+        pos.y + config.column_header_height + pos.height / 2.0,
+    )
+}
+
+fn range_rect(viewport: &ViewportManager, range: &CellRange) -> CellPosition {
+    let config = viewport.get_config();
+    let start = viewport.get_cell_position(&range.start);
+    let end = viewport.get_cell_position(&range.end);
+    CellPosition {
+        x: start.x + config.row_header_width,
+        y: start.y + config.column_header_height,
+        width: end.x + end.width - start.x,
+        height: end.y + end.height - start.y,
+    }
+}
+
+struct ClippedSegment {
+    from: (f64, f64),
+    to: (f64, f64),
+    from_clipped: bool,
+    to_clipped: bool,
+}
+
+struct ClipRect {
+    left: f64,
+    top: f64,
+    right: f64,
+    bottom: f64,
+}
+
+impl ClipRect {
+    /// Liang-Barsky clipping. Returns the visible part of the segment and
+    /// whether each end had to be moved.
+    fn clip_segment(&self, from: (f64, f64), to: (f64, f64)) -> Option<ClippedSegment> {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let mut t0: f64 = 0.0;
+        let mut t1: f64 = 1.0;
+
+        for (p, q) in [
+            (-dx, from.0 - self.left),
+            (dx, self.right - from.0),
+            (-dy, from.1 - self.top),
+            (dy, self.bottom - from.1),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let r = q / p;
+                if p < 0.0 {
+                    if r > t1 {
+                        return None;
+                    }
+                    t0 = t0.max(r);
+                } else {
+                    if r < t0 {
+                        return None;
+                    }
+                    t1 = t1.min(r);
+                }
+            }
+        }
+
+        Some(ClippedSegment {
+            from: (from.0 + t0 * dx, from.1 + t0 * dy),
+            to: (from.0 + t1 * dx, from.1 + t1 * dy),
+            from_clipped: t0 > 0.0,
+            to_clipped: t1 < 1.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facade_with(cells: &[(&str, &str)]) -> SpreadsheetFacade {
+        let facade = SpreadsheetFacade::new();
+        for (address, value) in cells {
+            facade
+                .set_cell_value(&CellAddress::from_a1(address).unwrap(), value)
+                .unwrap();
+        }
+        facade
+    }
+
+    fn addr(a1: &str) -> CellAddress {
+        CellAddress::from_a1(a1).unwrap()
+    }
+
+    fn viewport() -> ViewportManager {
+        let mut viewport = ViewportManager::new(100, 26)
+            .with_cell_dimensions(20.0, 100.0)
+            .with_header_dimensions(20.0, 50.0);
+        viewport.set_viewport_size(850.0, 420.0);
+        viewport
+    }
+
+    #[test]
+    fn test_precedents_keep_ranges_intact() {
+        let facade = facade_with(&[("A1", "1"), ("A2", "2"), ("B1", "=SUM(A1:A2)+A1")]);
+
+        let sources = find_precedents(&facade, &addr("B1"));
+        assert_eq!(
+            sources,
+            vec![
+                TraceSource::Range(CellRange::new(addr("A1"), addr("A2"))),
+                TraceSource::Cell(addr("A1")),
+            ]
+        );
+        assert!(find_precedents(&facade, &addr("A1")).is_empty());
+    }
+
+    #[test]
+    fn test_dependents_include_range_references() {
+        let facade = facade_with(&[
+            ("A1", "1"),
+            ("B1", "=A1*2"),
+            ("C3", "=SUM(A1:A5)"),
+            ("D1", "=B1"),
+        ]);
+
+        assert_eq!(
+            find_dependents(&facade, &addr("A1")),
+            vec![addr("B1"), addr("C3")]
+        );
+    }
+
+    #[test]
+    fn test_precedent_expansion_by_level() {
+        let facade = facade_with(&[("A1", "1"), ("B1", "2"), ("C1", "=A1+B1"), ("D1", "=C1*2")]);
+        let mut trace = TraceArrows::new();
+
+        assert_eq!(
+            trace.trace(&facade, addr("D1"), TraceDirection::Precedents),
+            1
+        );
+        assert_eq!(trace.level(TraceDirection::Precedents), 1);
+        assert_eq!(trace.arrows()[0].source, TraceSource::Cell(addr("C1")));
+        assert_eq!(trace.arrows()[0].target, addr("D1"));
+
+        assert_eq!(
+            trace.trace(&facade, addr("D1"), TraceDirection::Precedents),
+            2
+        );
+        assert_eq!(trace.level(TraceDirection::Precedents), 2);
+        assert!(trace
+            .arrows()
+            .iter()
+            .filter(|arrow| arrow.level == 2)
+            .all(|arrow| arrow.target == addr("C1")));
+
+        // Nothing further to expand
+        assert_eq!(
+            trace.trace(&facade, addr("D1"), TraceDirection::Precedents),
+            0
+        );
+        assert_eq!(trace.level(TraceDirection::Precedents), 2);
+        assert_eq!(trace.arrows().len(), 3);
+    }
+
+    #[test]
+    fn test_dependent_expansion_and_origin_reset() {
+        let facade = facade_with(&[("A1", "1"), ("A2", "=A1"), ("A3", "=A2"), ("B1", "=A1")]);
+        let mut trace = TraceArrows::new();
+
+        assert_eq!(
+            trace.trace(&facade, addr("A1"), TraceDirection::Dependents),
+            2
+        );
+        assert_eq!(
+            trace.trace(&facade, addr("A1"), TraceDirection::Dependents),
+            1
+        );
+        let last = trace.arrows().last().unwrap();
+        assert_eq!(last.source, TraceSource::Cell(addr("A2")));
+        assert_eq!(last.target, addr("A3"));
+        assert_eq!(last.level, 2);
+
+        // Precedents and dependents of the same cell coexist
+        trace.trace(&facade, addr("A1"), TraceDirection::Precedents);
+        assert_eq!(trace.level(TraceDirection::Dependents), 2);
+
+        // Tracing another cell starts over
+        assert_eq!(
+            trace.trace(&facade, addr("A3"), TraceDirection::Precedents),
+            1
+        );
+        assert_eq!(trace.origin(), Some(addr("A3")));
+        assert_eq!(trace.arrows().len(), 1);
+        assert_eq!(trace.level(TraceDirection::Dependents), 0);
+
+        trace.clear();
+        assert!(trace.is_empty());
+        assert_eq!(trace.origin(), None);
+    }
+
+    #[test]
+    fn test_geometry_follows_scroll() {
+        let facade = facade_with(&[("A1", "1"), ("C2", "=A1")]);
+        let mut trace = TraceArrows::new();
+        trace.trace(&facade, addr("C2"), TraceDirection::Precedents);
+
+        let mut viewport = viewport();
+        let geometry = trace.geometry(&viewport);
+        assert_eq!(geometry.len(), 1);
+        // A1 center: 50 + 100/2, 20 + 20/2; C2 center: 50 + 200 + 50, 20 + 20 + 10
+        assert_eq!(geometry[0].from, (100.0, 30.0));
+        assert_eq!(geometry[0].to, (300.0, 50.0));
+        assert!(!geometry[0].from_clipped && !geometry[0].to_clipped);
+
+        viewport.set_scroll_position(100.0, 0.0);
+        let geometry = trace.geometry(&viewport);
+        // A1 is now under the row header, so the tail is clamped to the grid edge
+        assert_eq!(geometry[0].to, (200.0, 50.0));
+        assert_eq!(geometry[0].from.0, 50.0);
+        assert!(geometry[0].from_clipped);
+        assert!(!geometry[0].to_clipped);
+
+        // Both ends scrolled away on the same side: nothing to draw
+        viewport.set_scroll_position(0.0, 500.0);
+        assert!(trace.geometry(&viewport).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_follows_cell_dimensions() {
+        let facade = facade_with(&[("A1", "1"), ("A2", "2"), ("C1", "=SUM(A1:A2)")]);
+        let mut trace = TraceArrows::new();
+        trace.trace(&facade, addr("C1"), TraceDirection::Precedents);
+
+        let mut viewport = viewport();
+        viewport.set_column_width(0, 200.0);
+        viewport.set_row_height(0, 40.0);
+
+        let geometry = trace.geometry(&viewport);
+        assert_eq!(geometry.len(), 1);
+        let source_box = geometry[0].source_box.as_ref().unwrap();
+        assert_eq!(
+            (
+                source_box.x,
+                source_box.y,
+                source_box.width,
+                source_box.height
+            ),
+            (50.0, 20.0, 200.0, 60.0)
+        );
+        assert_eq!(geometry[0].from, (150.0, 50.0));
+        // C1 starts after the 200px A column and the 100px B column
+        assert_eq!(geometry[0].to, (400.0, 40.0));
+    }
+
+    #[test]
+    fn test_geometry_clamps_far_endpoint_to_edge() {
+        let facade = facade_with(&[("A1", "=Z1")]);
+        let mut trace = TraceArrows::new();
+        trace.trace(&facade, addr("A1"), TraceDirection::Precedents);
+
+        let geometry = trace.geometry(&viewport());
+        assert_eq!(geometry.len(), 1);
+        assert!(geometry[0].from_clipped);
+        assert_eq!(geometry[0].from, (850.0, 30.0));
+        assert_eq!(geometry[0].to, (100.0, 30.0));
+    }
+}
@@ -95,32 +95,36 @@ impl ExParser {
 
     /// Parse command names
     fn command_name_parser<'a>() -> impl Parser<'a, &'a str, String, extra::Err<Rich<'a, char>>> {
-        // Common abbreviated commands - longer patterns first to avoid ambiguity
-        let abbreviations = choice((
-            just("wq").to("writequit"),
-            just("set").to("set"),
-            just("w").to("write"),
-            just("q").to("quit"),
-            just("x").to("exit"),
-            just("e").to("edit"),
-            just("r").to("read"),
-            just("s").to("substitute"),
-            just("g").to("global"),
-            just("v").to("vglobal"),
-            just("d").to("delete"),
-            just("y").to("yank"),
-            just("p").to("put"),
-            just("m").to("move"),
-            just("t").to("copy"),
-            just("j").to("join"),
-            just("!").to("shell"),
-        ));
-
-        // Full command names
-        let full_command = text::ident();
+        // Read the whole alphabetic word so that full names such as `trace` are not
+        // swallowed by a single-letter abbreviation, then expand the abbreviations
+        let word = any()
+            .filter(|c: &char| c.is_ascii_alphabetic())
+            .repeated()
+            .at_least(1)
+            .to_slice()
+            .map(|name: &str| {
+                match name {
+                    "wq" => "writequit",
+                    "w" => "write",
+                    "q" => "quit",
+                    "x" => "exit",
+                    "e" => "edit",
+                    "r" => "read",
+                    "s" => "substitute",
+                    "g" => "global",
+                    "v" => "vglobal",
+                    "d" => "delete",
+                    "y" => "yank",
+                    "p" => "put",
+                    "m" => "move",
+                    "t" => "copy",
+                    "j" => "join",
+                    other => other,
+                }
+                .to_string()
+            });
 
-        // Try abbreviations first, then full commands
-        abbreviations.or(full_command).map(String::from)
+        just('!').to("shell".to_string()).or(word)
     }
 
     /// Parse command arguments and flags
@@ -402,6 +406,13 @@ mod tests {
         assert_eq!(cmd.flags, vec!["g"]);
     }
 
+    #[test]
+    fn test_full_command_name_not_split_by_abbreviation() {
+        let cmd = ExParser::parse_ex("trace p").unwrap();
+        assert_eq!(cmd.command, "trace");
+        assert_eq!(cmd.args, vec!["p"]);
+    }
+
     #[test]
     fn test_complex_range() {
         let cmd = ExParser::parse_ex(".,+5d").unwrap();
@@ -120,6 +120,11 @@ pub enum TextObject {
     InnerBlock(char, char),
     InnerQuote(char),
     InnerTag,
+    // Grid objects: the filled run around the cursor, or the whole column/row
+    InnerColumn,
+    Column,
+    InnerRow,
+    Row,
 }
 
 /// Target for an operator
@@ -6,8 +6,9 @@ use super::vim_core::{
     VimContext, VimMode, VimResult, VisualMode,
 };
 use super::vim_parser::VimParser;
+use crate::controller::events::SpreadsheetEvent;
 use crate::state::{Action, Selection, SelectionType};
-use gridcore_core::{types::CellAddress, Result};
+use gridcore_core::{references::StructuralOperation, types::CellAddress, Result};
 use rustc_hash::FxHashMap;
 
 /// Main implementation of vim behavior
@@ -64,6 +65,27 @@ impl VimBehaviorImpl {
         self.marks.get(&mark)
     }
 
+    /// Move marks with their cells after a structural edit, dropping the
+    /// marks on deleted cells
+    pub fn shift_marks(&mut self, operation: &StructuralOperation) {
+        self.marks
+            .retain(|_, address| match operation.shift_address(address) {
+                Some(moved) => {
+                    *address = moved;
+                    true
+                }
+                None => false,
+            });
+    }
+
+    /// Follow an event of the controller, whose structural edits move the
+    /// marks the way they move its cursor and watches
+    pub fn handle_event(&mut self, event: &SpreadsheetEvent) {
+        if let Some(operation) = event.structural_operation() {
+            self.shift_marks(&operation);
+        }
+    }
+
     /// Process a normal mode key
     fn process_normal_key(&mut self, key: &str, context: &VimContext) -> Result<VimResult> {
         // Handle count prefix
@@ -708,4 +730,46 @@ mod tests {
             _ => panic!("Expected delete action"),
         }
     }
+
+    #[test]
+    fn test_marks_follow_structural_edits() {
+        let mut vim = VimBehaviorImpl::new();
+        vim.set_mark('a', CellAddress::new(0, 1));
+        vim.set_mark('b', CellAddress::new(3, 5));
+        vim.set_mark('c', CellAddress::new(3, 9));
+
+        vim.shift_marks(&StructuralOperation::InsertRows {
+            before_row: 5,
+            count: 2,
+        });
+        assert_eq!(vim.get_mark('a'), Some(&CellAddress::new(0, 1)));
+        assert_eq!(vim.get_mark('b'), Some(&CellAddress::new(3, 7)));
+
+        vim.shift_marks(&StructuralOperation::DeleteColumns {
+            start_col: 1,
+            count: 3,
+        });
+        assert_eq!(vim.get_mark('a'), Some(&CellAddress::new(0, 1)));
+        assert_eq!(vim.get_mark('b'), None);
+        assert_eq!(vim.get_mark('c'), None);
+    }
+
+    #[test]
+    fn test_marks_follow_the_controller_edits() {
+        use crate::controller::SpreadsheetController;
+        use std::sync::{Arc, Mutex};
+
+        let vim = Arc::new(Mutex::new(VimBehaviorImpl::new()));
+        vim.lock().unwrap().set_mark('a', CellAddress::new(2, 4));
+        vim.lock().unwrap().set_mark('b', CellAddress::new(0, 1));
+        let mut controller = SpreadsheetController::new();
+        let follower = vim.clone();
+        controller.subscribe_to_events(move |event| follower.lock().unwrap().handle_event(event));
+
+        controller.insert_rows(2, 3).unwrap();
+        controller.delete_rows(1, 1).unwrap();
+        let vim = vim.lock().unwrap();
+        assert_eq!(vim.get_mark('a'), Some(&CellAddress::new(2, 6)));
+        assert_eq!(vim.get_mark('b'), None);
+    }
 }
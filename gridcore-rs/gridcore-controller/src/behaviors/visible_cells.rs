@@ -0,0 +1,44 @@
+//! Selections over hidden rows and columns.
+//!
+//! Copying, the status bar statistics and clearing a selection only touch
+//! the cells left visible: a block with hidden rows between its parts
+//! copies as one compact block and clears without reaching the hidden
+//! cells. Alt+; narrows the selection to its visible blocks, so what the
+//! selection covers is what is on screen.
+
+use crate::controller::ViewportManager;
+use gridcore_core::formula::CellRange;
+
+/// Key that selects the visible cells of the selection: Alt+; in the
+/// notation of [`KeyboardEvent::to_vim_notation`](crate::controller::KeyboardEvent::to_vim_notation)
+pub const SELECT_VISIBLE_KEY: &str = "A-;";
+
+/// The visible rows and columns of `range`, in order
+pub fn visible_lines(viewport: &ViewportManager, range: &CellRange) -> (Vec<u32>, Vec<u32>) {
+    let rows = (range.start.row..=range.end.row)
+        .filter(|&row| !viewport.is_row_hidden(row as usize))
+        .collect();
+    let cols = (range.start.col..=range.end.col)
+        .filter(|&col| !viewport.is_column_hidden(col as usize))
+        .collect();
+    (rows, cols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gridcore_core::types::CellAddress;
+
+    #[test]
+    fn test_visible_lines_of_a_range() {
+        let mut viewport = ViewportManager::new(100, 26);
+        viewport.set_rows_hidden(1, 2, true);
+        viewport.set_columns_hidden(0, 0, true);
+
+        let range = CellRange::new(CellAddress::new(0, 0), CellAddress::new(2, 4));
+        assert_eq!(
+            visible_lines(&viewport, &range),
+            (vec![0, 3, 4], vec![1, 2])
+        );
+    }
+}
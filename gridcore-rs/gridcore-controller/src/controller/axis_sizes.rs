@@ -0,0 +1,276 @@
+//! Sizes of the rows or columns along one axis of the grid.
+//!
+//! Most rows and columns keep the default size, so only the custom sizes are
+//! stored. Positions come from a prefix sum over how much each custom size
+//! differs from the default, built on first use after a change and then
+//! binary-searched, so hit-testing costs `O(log n)` in the number of custom
+//! sizes rather than a scan over every column.
+//!
+//! A hidden row or column is stored as a custom size of zero, with the size
+//! it had kept aside until it is shown again.
+
+use rustc_hash::FxHashMap;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone)]
+pub(crate) struct AxisSizes {
+    default: f64,
+    sizes: FxHashMap<usize, f64>,
+    /// Hidden indices with the custom size they had, if any
+    hidden: FxHashMap<usize, Option<f64>>,
+    index: OnceLock<SizeIndex>,
+}
+
+/// Custom sizes sorted by index, with the start of each one
+#[derive(Debug, Clone, Default)]
+struct SizeIndex {
+    indices: Vec<usize>,
+    sizes: Vec<f64>,
+    starts: Vec<f64>,
+}
+
+impl AxisSizes {
+    pub(crate) fn new(default: f64) -> Self {
+        Self {
+            default,
+            sizes: FxHashMap::default(),
+            hidden: FxHashMap::default(),
+            index: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn set_default_size(&mut self, size: f64) {
+        self.default = size;
+        self.index = OnceLock::new();
+    }
+
+    pub(crate) fn size(&self, index: usize) -> f64 {
+        self.sizes.get(&index).copied().unwrap_or(self.default)
+    }
+
+    /// Set the size of `index`; a hidden index keeps it for when it is
+    /// shown. Going back to the default size drops the custom one.
+    pub(crate) fn set_size(&mut self, index: usize, size: f64) {
+        let custom = (size != self.default).then_some(size);
+        if let Some(kept) = self.hidden.get_mut(&index) {
+            *kept = custom;
+            return;
+        }
+        match custom {
+            Some(size) => self.sizes.insert(index, size),
+            None => self.sizes.remove(&index),
+        };
+        self.index = OnceLock::new();
+    }
+
+    /// Rows or columns with a custom size, in no particular order. Hidden
+    /// ones report the size they are shown with.
+    pub(crate) fn custom_sizes(&self) -> impl Iterator<Item = (usize, f64)> + '_ {
+        self.sizes
+            .iter()
+            .filter(|(index, _)| !self.hidden.contains_key(index))
+            .map(|(&index, &size)| (index, size))
+            .chain(
+                self.hidden
+                    .iter()
+                    .filter_map(|(&index, &size)| size.map(|size| (index, size))),
+            )
+    }
+
+    pub(crate) fn is_hidden(&self, index: usize) -> bool {
+        self.hidden.contains_key(&index)
+    }
+
+    /// Hidden indices, in no particular order
+    pub(crate) fn hidden(&self) -> impl Iterator<Item = usize> + '_ {
+        self.hidden.keys().copied()
+    }
+
+    /// Hide or show `index`. Returns whether anything changed.
+    pub(crate) fn set_hidden(&mut self, index: usize, hidden: bool) -> bool {
+        if hidden == self.is_hidden(index) {
+            return false;
+        }
+        if hidden {
+            let kept = self.sizes.insert(index, 0.0);
+            self.hidden.insert(index, kept);
+        } else {
+            match self.hidden.remove(&index).flatten() {
+                Some(size) => self.sizes.insert(index, size),
+                None => self.sizes.remove(&index),
+            };
+        }
+        self.index = OnceLock::new();
+        true
+    }
+
+    /// Move every custom size and hidden index to where `to` sends it,
+    /// dropping the ones it sends nowhere, e.g. after rows are deleted
+    pub(crate) fn remap(&mut self, to: impl Fn(usize) -> Option<usize>) {
+        self.sizes = self
+            .sizes
+            .drain()
+            .filter_map(|(index, size)| Some((to(index)?, size)))
+            .collect();
+        self.hidden = self
+            .hidden
+            .drain()
+            .filter_map(|(index, kept)| Some((to(index)?, kept)))
+            .collect();
+        self.index = OnceLock::new();
+    }
+
+    /// Distance from the start of the axis to the start of `index`
+    pub(crate) fn offset(&self, index: usize) -> f64 {
+        let built = self.index();
+        let before = built.indices.partition_point(|&i| i < index);
+        match before.checked_sub(1) {
+            Some(last) => {
+                let end = built.starts[last] + built.sizes[last];
+                end + (index - built.indices[last] - 1) as f64 * self.default
+            }
+            None => index as f64 * self.default,
+        }
+    }
+
+    /// Where `index` would end if every line in `resized` were `size`,
+    /// leaving the stored sizes alone. Hidden lines stay collapsed.
+    pub(crate) fn provisional_end(&self, index: usize, resized: &[usize], size: f64) -> f64 {
+        let mut resized: Vec<usize> = resized
+            .iter()
+            .copied()
+            .filter(|&i| i <= index && !self.is_hidden(i))
+            .collect();
+        resized.sort_unstable();
+        resized.dedup();
+        let growth: f64 = resized.iter().map(|&i| size - self.size(i)).sum();
+        self.offset(index) + self.size(index) + growth
+    }
+
+    /// Index of the row or column covering `position`, which may lie past
+    /// the end of the grid; `None` for negative positions
+    pub(crate) fn index_at(&self, position: f64) -> Option<usize> {
+        if position < 0.0 || position.is_nan() {
+            return None;
+        }
+        let built = self.index();
+        let covering = built.starts.partition_point(|&start| start <= position);
+        let Some(last) = covering.checked_sub(1) else {
+            return Some(self.default_steps(position, 0, built.indices.first()));
+        };
+        let end = built.starts[last] + built.sizes[last];
+        if position < end {
+            return Some(built.indices[last]);
+        }
+        let first = built.indices[last] + 1;
+        Some(first + self.default_steps(position - end, first, built.indices.get(last + 1)))
+    }
+
+    /// Whole default-sized steps in `distance`, kept short of the next
+    /// custom size in case rounding carries it over
+    fn default_steps(&self, distance: f64, from: usize, next: Option<&usize>) -> usize {
+        let steps = if self.default > 0.0 {
+            (distance / self.default) as usize
+        } else {
+            0
+        };
+        match next {
+            Some(&next) => steps.min((next - from).saturating_sub(1)),
+            None => steps,
+        }
+    }
+
+    fn index(&self) -> &SizeIndex {
+        self.index.get_or_init(|| {
+            let mut custom: Vec<(usize, f64)> =
+                self.sizes.iter().map(|(&i, &size)| (i, size)).collect();
+            custom.sort_unstable_by_key(|&(i, _)| i);
+
+            let mut index = SizeIndex::default();
+            let mut extra = 0.0;
+            for (i, size) in custom {
+                index.starts.push(i as f64 * self.default + extra);
+                index.indices.push(i);
+                index.sizes.push(size);
+                extra += size - self.default;
+            }
+            index
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offsets_and_hits_around_custom_sizes() {
+        let mut axis = AxisSizes::new(10.0);
+        axis.set_size(2, 30.0);
+        axis.set_size(3, 5.0);
+
+        let offsets: Vec<f64> = (0..6).map(|i| axis.offset(i)).collect();
+        assert_eq!(offsets, vec![0.0, 10.0, 20.0, 50.0, 55.0, 65.0]);
+
+        let hits: Vec<Option<usize>> = [-1.0, 0.0, 19.9, 20.0, 49.9, 50.0, 54.9, 55.0, 70.0]
+            .iter()
+            .map(|&p| axis.index_at(p))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![
+                None,
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(2),
+                Some(3),
+                Some(3),
+                Some(4),
+                Some(5)
+            ]
+        );
+
+        // Changing a size rebuilds the index on the next query
+        axis.set_size(0, 40.0);
+        assert_eq!(axis.offset(2), 50.0);
+        assert_eq!(axis.index_at(45.0), Some(1));
+    }
+
+    #[test]
+    fn test_provisional_ends_leave_sizes_alone() {
+        let mut axis = AxisSizes::new(10.0);
+        axis.set_size(1, 30.0);
+        axis.set_hidden(3, true);
+
+        assert_eq!(axis.provisional_end(1, &[1], 50.0), 60.0);
+        // Lines after the one asked about do not move its end
+        assert_eq!(axis.provisional_end(0, &[1, 2], 50.0), 10.0);
+        assert_eq!(axis.provisional_end(4, &[0, 1, 3, 4], 20.0), 70.0);
+        assert_eq!(axis.provisional_end(4, &[], 20.0), 60.0);
+        assert_eq!(axis.size(1), 30.0);
+        assert_eq!(axis.offset(5), 60.0);
+    }
+
+    #[test]
+    fn test_hidden_sizes_collapse_and_come_back() {
+        let mut axis = AxisSizes::new(10.0);
+        axis.set_size(1, 30.0);
+        assert!(axis.set_hidden(1, true));
+        assert!(axis.set_hidden(2, true));
+        assert!(!axis.set_hidden(2, true));
+
+        assert_eq!(axis.size(1), 0.0);
+        assert_eq!(axis.offset(3), 10.0);
+        assert_eq!(axis.index_at(10.0), Some(3));
+        assert_eq!(axis.custom_sizes().collect::<Vec<_>>(), [(1, 30.0)]);
+
+        // Resizing a hidden row takes effect once it is shown
+        axis.set_size(2, 20.0);
+        assert_eq!(axis.size(2), 0.0);
+        axis.set_hidden(1, false);
+        axis.set_hidden(2, false);
+        assert_eq!((axis.size(1), axis.size(2)), (30.0, 20.0));
+        assert_eq!(axis.hidden().count(), 0);
+    }
+}
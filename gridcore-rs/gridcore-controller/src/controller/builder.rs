@@ -0,0 +1,445 @@
+use crate::behaviors::{
+    clipboard::CopyOptions,
+    paste::PasteOptions,
+    resize::{ResizeBehavior, ResizePreview, ResizeState},
+    trace::TraceArrows,
+};
+use crate::controller::{
+    BehaviorPlugin, EditConflictPolicy, EditGuard, EditorMode, EnterDirection, EntryNavigation,
+    EventDispatcher, FocusManager, GridConfiguration, IdleWorkQueue, Keymap, PluginRegistry,
+    SpreadsheetController, TextWidths, ViewportCache, ViewportManager,
+};
+use crate::managers::{
+    ErrorSystem, Folds, LintWarnings, RecentEntries, RecentScope, SaveState, WatchList,
+};
+#[cfg(feature = "debug")]
+use crate::state::ActionLog;
+use crate::state::{UIState, ViewportInfo};
+use crate::theme::Palette;
+use gridcore_core::{
+    formula::FormulaTranslator, lint::LintSettings, script::ScriptSession, types::CellAddress,
+    SpreadsheetFacade,
+};
+
+use super::formula_bar::FormulaBarManager;
+use super::inspection::InspectionCache;
+use std::collections::BTreeSet;
+
+/// Builds a [`SpreadsheetController`] with injected services and configuration.
+///
+/// Everything not set explicitly falls back to the same defaults as
+/// [`SpreadsheetController::new`]. The swappable parts are:
+///
+/// - the [`SpreadsheetFacade`], e.g. one preloaded with data
+/// - the [`GridConfiguration`] (row/column counts, default sizes, headers)
+/// - the initial viewport window, or a fully configured [`ViewportManager`]
+/// - the [`EventDispatcher`], which may already have listeners attached
+/// - vim behavior and custom navigation key bindings
+/// - where Enter moves the cursor after committing an edit
+/// - [`BehaviorPlugin`]s that hook keys, actions and ex commands
+/// - the [`FormulaTranslator`] for the formula display convention
+/// - the capacity of the error system and the edit conflict policy
+/// - whether dropping a dragged range over data asks first
+/// - whether the workbook is read-only
+/// - the margin prefetched around the viewport
+/// - which formula lint rules run
+/// - whether recent entries are offered per sheet or across the workbook
+/// - a [`UIState`] snapshot to restore the cursor, viewport, watches and
+///   recent entries from
+pub struct SpreadsheetControllerBuilder {
+    facade: Option<SpreadsheetFacade>,
+    config: GridConfiguration,
+    viewport: Option<ViewportInfo>,
+    viewport_manager: Option<ViewportManager>,
+    event_dispatcher: Option<EventDispatcher>,
+    vim_enabled: bool,
+    keymap: Keymap,
+    enter_direction: Option<EnterDirection>,
+    plugins: PluginRegistry,
+    formula_translator: FormulaTranslator,
+    paste_options: PasteOptions,
+    copy_options: CopyOptions,
+    error_capacity: Option<usize>,
+    edit_conflict_policy: EditConflictPolicy,
+    confirm_drop_overwrites: bool,
+    read_only: bool,
+    prefetch_margin: (Option<usize>, Option<usize>),
+    lint_settings: LintSettings,
+    recent_scope: RecentScope,
+    ui_state: Option<UIState>,
+}
+
+impl Default for SpreadsheetControllerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpreadsheetControllerBuilder {
+    pub fn new() -> Self {
+        Self {
+            facade: None,
+            config: GridConfiguration {
+                total_rows: 1000,
+                total_cols: 100,
+                ..Default::default()
+            },
+            viewport: None,
+            viewport_manager: None,
+            event_dispatcher: None,
+            vim_enabled: true,
+            keymap: Keymap::new(),
+            enter_direction: None,
+            plugins: PluginRegistry::default(),
+            formula_translator: FormulaTranslator::default(),
+            paste_options: PasteOptions::default(),
+            copy_options: CopyOptions::default(),
+            error_capacity: None,
+            edit_conflict_policy: EditConflictPolicy::default(),
+            confirm_drop_overwrites: true,
+            read_only: false,
+            prefetch_margin: (None, None),
+            lint_settings: LintSettings::default(),
+            recent_scope: RecentScope::default(),
+            ui_state: None,
+        }
+    }
+
+    pub fn with_facade(mut self, facade: SpreadsheetFacade) -> Self {
+        self.facade = Some(facade);
+        self
+    }
+
+    pub fn with_grid_config(mut self, config: GridConfiguration) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Initial window of visible rows and columns
+    pub fn with_viewport(mut self, viewport: ViewportInfo) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    /// Use a preconfigured viewport manager instead of one derived from the grid config
+    pub fn with_viewport_manager(mut self, viewport_manager: ViewportManager) -> Self {
+        self.viewport_manager = Some(viewport_manager);
+        self
+    }
+
+    pub fn with_event_dispatcher(mut self, event_dispatcher: EventDispatcher) -> Self {
+        self.event_dispatcher = Some(event_dispatcher);
+        self
+    }
+
+    /// With vim disabled, printable keys in navigation mode start editing
+    /// instead of acting as commands, and Enter/Escape commit/cancel edits
+    pub fn vim_enabled(mut self, enabled: bool) -> Self {
+        self.vim_enabled = enabled;
+        self
+    }
+
+    pub fn with_keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// Where Enter moves the cursor after a commit, instead of down, or
+    /// staying put when vim is enabled
+    pub fn with_enter_direction(mut self, direction: EnterDirection) -> Self {
+        self.enter_direction = Some(direction);
+        self
+    }
+
+    /// Register a plugin, see [`SpreadsheetController::register_plugin`]
+    pub fn with_plugin(mut self, plugin: Box<dyn BehaviorPlugin>) -> Self {
+        self.plugins.register(plugin);
+        self
+    }
+
+    /// Show and accept formulas in a display convention other than the
+    /// canonical one, e.g. semicolon argument separators
+    pub fn with_formula_translator(mut self, translator: FormulaTranslator) -> Self {
+        self.formula_translator = translator;
+        self
+    }
+
+    /// How pasted text is read when a paste does not bring its own options
+    pub fn with_paste_options(mut self, options: PasteOptions) -> Self {
+        self.paste_options = options;
+        self
+    }
+
+    /// How selections are copied, e.g. with their hidden rows and columns
+    pub fn with_copy_options(mut self, options: CopyOptions) -> Self {
+        self.copy_options = options;
+        self
+    }
+
+    /// Maximum number of errors kept by the error system
+    pub fn with_error_capacity(mut self, capacity: usize) -> Self {
+        self.error_capacity = Some(capacity);
+        self
+    }
+
+    pub fn with_edit_conflict_policy(mut self, policy: EditConflictPolicy) -> Self {
+        self.edit_conflict_policy = policy;
+        self
+    }
+
+    /// Whether a dragged range dropped over non-empty cells waits for
+    /// confirmation; on by default
+    pub fn with_drop_overwrite_confirmation(mut self, confirm: bool) -> Self {
+        self.confirm_drop_overwrites = confirm;
+        self
+    }
+
+    /// Refuse changes to the workbook, for viewing reports; see
+    /// [`SpreadsheetController::set_read_only`]
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Rows and columns the viewport cache prefetches around the visible
+    /// region; by default one viewport height and width
+    pub fn with_prefetch_margin(mut self, rows: usize, cols: usize) -> Self {
+        self.prefetch_margin = (Some(rows), Some(cols));
+        self
+    }
+
+    /// Which rules `:lint` runs; all of them by default
+    pub fn with_lint_settings(mut self, settings: LintSettings) -> Self {
+        self.lint_settings = settings;
+        self
+    }
+
+    /// Whether Ctrl+N/Ctrl+P and the Ctrl+Down picker offer the entries
+    /// made in the edited sheet only, the default, or in any sheet
+    pub fn with_recent_scope(mut self, scope: RecentScope) -> Self {
+        self.recent_scope = scope;
+        self
+    }
+
+    /// Restore the cursor, viewport, watch list and recent entries from a
+    /// snapshot
+    pub fn with_ui_state(mut self, state: UIState) -> Self {
+        self.ui_state = Some(state);
+        self
+    }
+
+    pub fn build(self) -> SpreadsheetController {
+        let config = self.config;
+
+        let mut viewport_manager = self.viewport_manager.unwrap_or_else(|| {
+            ViewportManager::new(config.total_rows as u32, config.total_cols as u32)
+                .with_config(config.clone())
+        });
+
+        let mut cursor = CellAddress::new(0, 0);
+        let mut watch_list = WatchList::new();
+        let mut recent_entries = RecentEntries::new();
+        recent_entries.set_scope(self.recent_scope);
+        let mut viewport = self.viewport;
+        if let Some(state) = &self.ui_state {
+            cursor = *state.cursor();
+            viewport = viewport.or(Some(*state.viewport()));
+            for entry in &state.core().watches {
+                watch_list.add(entry.clone());
+            }
+            recent_entries.restore(state.core().recent_entries.clone());
+        }
+        if let Some(viewport) = viewport {
+            viewport_manager.set_viewport(viewport);
+        }
+
+        let error_system = match self.error_capacity {
+            Some(capacity) => ErrorSystem::with_capacity(capacity),
+            None => ErrorSystem::new(),
+        };
+
+        let mut controller = SpreadsheetController {
+            facade: self.facade.unwrap_or_default(),
+            event_dispatcher: self.event_dispatcher.unwrap_or_default(),
+            viewport_manager,
+            viewport_cache: ViewportCache::new()
+                .with_margin(self.prefetch_margin.0, self.prefetch_margin.1),
+            idle_work: IdleWorkQueue::new(),
+            text_widths: TextWidths::new(),
+            resize_state: ResizeState::default(),
+            resize_preview: ResizePreview::default(),
+            resize_behavior: ResizeBehavior::new(),
+            error_system,
+            config,
+            formula_bar_manager: FormulaBarManager::new(),
+            trace_arrows: TraceArrows::new(),
+            highlighted_cell: None,
+            watch_list,
+            recent_entries,
+            entry_completion: None,
+            entry_picker: None,
+            lint_warnings: LintWarnings::new(self.lint_settings),
+            content_generation: 0,
+            inspections: InspectionCache::default(),
+            load_report: None,
+            folds: Folds::new(),
+            palette: Palette::default(),
+            fold_hidden_rows: BTreeSet::new(),
+            save_state: SaveState::default(),
+            edit_guard: EditGuard::new(self.edit_conflict_policy),
+            read_only: self.read_only,
+            focus: FocusManager::new(),
+            published_state: None,
+            vim_enabled: self.vim_enabled,
+            keymap: self.keymap,
+            entry_navigation: EntryNavigation::new(self.enter_direction),
+            formula_translator: self.formula_translator,
+            paste_options: self.paste_options,
+            copy_options: self.copy_options,
+            pending_paste: Vec::new(),
+            range_drag: None,
+            confirm_drop_overwrites: self.confirm_drop_overwrites,
+            clipboard: None,
+            script_session: ScriptSession::new(),
+            plugins: self.plugins,
+            pending_key: None,
+            last_case_command: None,
+            last_column_totals: None,
+            last_autocorrection: None,
+            blocked_entry: None,
+            #[cfg(feature = "debug")]
+            action_log: ActionLog::default(),
+            // Initialize direct state fields
+            cursor,
+            selection: None,
+            mode: EditorMode::Navigation,
+            formula_bar: String::new(),
+        };
+
+        // Pick up values for watches and the used range of a preloaded facade
+        controller.refresh_watch_list();
+        controller.sync_grid_extent();
+        // What the controller starts with counts as saved
+        controller.save_state = SaveState::new(
+            controller.input_hashes(controller.get_sheets().into_iter().map(|(name, _)| name)),
+        );
+
+        // Initialize formula bar with current cell value
+        controller.update_formula_bar_from_cursor();
+
+        controller
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::KeyboardEvent;
+    use crate::state::Action;
+
+    fn press(controller: &mut SpreadsheetController, key: &str) {
+        controller
+            .handle_keyboard_event(KeyboardEvent::new(key.to_string()))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_navigation_clamps_to_configured_grid() {
+        let mut controller = SpreadsheetController::builder()
+            .with_grid_config(GridConfiguration {
+                total_rows: 5,
+                total_cols: 10,
+                ..Default::default()
+            })
+            .build();
+
+        for _ in 0..15 {
+            press(&mut controller, "l");
+        }
+        assert_eq!(controller.cursor(), CellAddress::new(9, 0));
+
+        for _ in 0..8 {
+            press(&mut controller, "j");
+        }
+        assert_eq!(controller.cursor(), CellAddress::new(9, 4));
+
+        // Tab at the last cell has nowhere to wrap to
+        press(&mut controller, "Tab");
+        assert_eq!(controller.cursor(), CellAddress::new(9, 4));
+    }
+
+    #[test]
+    fn test_injected_facade_is_visible_immediately() {
+        let facade = SpreadsheetFacade::new();
+        facade
+            .set_cell_value(&CellAddress::new(0, 0), "42")
+            .unwrap();
+
+        let controller = SpreadsheetController::builder()
+            .with_facade(facade)
+            .with_error_capacity(2)
+            .build();
+
+        assert_eq!(controller.get_formula_bar_value(), "42");
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::new(0, 0)),
+            "42"
+        );
+    }
+
+    #[test]
+    fn test_ui_state_restores_cursor_viewport_and_watches() {
+        use crate::managers::WatchEntry;
+
+        let viewport = ViewportInfo {
+            start_row: 40,
+            start_col: 2,
+            rows: 20,
+            cols: 10,
+        };
+        let mut state = UIState::new_navigation(CellAddress::new(3, 45), viewport);
+        state
+            .core_mut()
+            .watches
+            .push(WatchEntry::new("Sheet1", CellAddress::new(0, 0)));
+
+        let controller = SpreadsheetController::builder()
+            .with_ui_state(state)
+            .build();
+
+        assert_eq!(controller.cursor(), CellAddress::new(3, 45));
+        assert_eq!(controller.get_viewport_manager().get_viewport(), viewport);
+        assert_eq!(controller.get_watch_list().len(), 1);
+    }
+
+    #[test]
+    fn test_vim_disabled_and_custom_keymap() {
+        let keymap = Keymap::new().bind(
+            "C-g",
+            Action::UpdateCursor {
+                cursor: CellAddress::new(5, 5),
+            },
+        );
+        let mut controller = SpreadsheetController::builder()
+            .vim_enabled(false)
+            .with_keymap(keymap)
+            .build();
+
+        controller
+            .handle_keyboard_event(
+                KeyboardEvent::new("g".to_string()).with_modifiers(false, true, false, false),
+            )
+            .unwrap();
+        assert_eq!(controller.cursor(), CellAddress::new(5, 5));
+
+        // `j` types into the cell instead of moving, and Enter commits directly
+        press(&mut controller, "j");
+        assert!(controller.get_mode().is_editing());
+        press(&mut controller, "Enter");
+        assert!(controller.get_mode().is_navigation());
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::new(5, 5)),
+            "j"
+        );
+    }
+}
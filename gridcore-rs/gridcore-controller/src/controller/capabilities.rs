@@ -0,0 +1,37 @@
+//! What the user may do with the document, for views that offer commands
+
+/// A snapshot of the kinds of commands the controller currently accepts.
+///
+/// Views read it to disable menu entries and buttons rather than offering
+/// commands the controller would refuse; it changes when the controller is
+/// made read-only or editable again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Edit cells, formats, names and structure, paste, fill and undo
+    pub edit: bool,
+    /// Add, rename and remove sheets
+    pub manage_sheets: bool,
+    /// Run console scripts, which may write to the workbook
+    pub run_scripts: bool,
+}
+
+impl Capabilities {
+    pub const ALL: Self = Self {
+        edit: true,
+        manage_sheets: true,
+        run_scripts: true,
+    };
+
+    /// What a read-only controller still allows: only viewing
+    pub const VIEW_ONLY: Self = Self {
+        edit: false,
+        manage_sheets: false,
+        run_scripts: false,
+    };
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
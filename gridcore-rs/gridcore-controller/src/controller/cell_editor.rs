@@ -1,20 +1,28 @@
+use crate::behaviors::numeric_entry::normalize_entry;
 use crate::controller::events::{ErrorSeverity, SpreadsheetEvent};
 use crate::controller::mode::EditorMode;
 use crate::managers::ErrorSystem;
 use crate::state::Action;
-use gridcore_core::{types::CellAddress, Result, SpreadsheetFacade};
+use gridcore_core::domain::CellFormat;
+use gridcore_core::evaluator::parse_cell_input;
+use gridcore_core::formula::{autocorrect, Autocorrection};
+use gridcore_core::{formula::FormulaTranslator, types::CellAddress, Result, SpreadsheetFacade};
 
 /// Handles cell editing operations
 pub struct CellEditor;
 
 impl CellEditor {
-    /// Submit formula bar value to current cell
+    /// Submit formula bar value to current cell, translating it from the
+    /// display convention to canonical formula text first
     pub fn submit_formula_bar(
         facade: &mut SpreadsheetFacade,
+        translator: &FormulaTranslator,
         cursor: CellAddress,
         value: String,
     ) -> Result<CellEditResult> {
-        let result = facade.set_cell_value(&cursor, &value);
+        let (value, inferred, autocorrection) =
+            Self::canonical_entry(facade, translator, &cursor, &value);
+        let result = Self::store_entry(facade, &cursor, &value, inferred);
 
         match result {
             Ok(_) => {
@@ -27,6 +35,7 @@ impl CellEditor {
                         address: cursor,
                         value,
                         error_message: enhanced_message,
+                        autocorrection,
                     })
                 } else {
                     // Always clear formula bar after successful submission
@@ -34,6 +43,7 @@ impl CellEditor {
                         address: cursor,
                         value,
                         should_clear_formula_bar: true,
+                        autocorrection,
                     })
                 }
             }
@@ -44,23 +54,89 @@ impl CellEditor {
         }
     }
 
+    /// Text to store for an entry typed into `address`: formulas in canonical
+    /// form, numbers read against the cell's format and the locale's decimal
+    /// separator. For an unformatted cell, also the format the entry was
+    /// typed in, e.g. percent for `12%`, unless inference is turned off.
+    /// For a formula, the autocorrection made to it when autocorrect is on.
+    fn canonical_entry(
+        facade: &SpreadsheetFacade,
+        translator: &FormulaTranslator,
+        address: &CellAddress,
+        value: &str,
+    ) -> (String, Option<CellFormat>, Option<Autocorrection>) {
+        let value = translator.to_canonical(value);
+        if let Some(fix) = autocorrect(&value, &facade.workbook_settings().autocorrect) {
+            return (fix.corrected.clone(), None, Some(fix));
+        }
+        let format = facade.get_effective_format(address);
+        let decimal = translator.convention().decimal_separator();
+        if format.is_none() && !value.starts_with('=') && facade.workbook_settings().infer_formats {
+            let input = parse_cell_input(&value, decimal);
+            if let (Some(number), Some(inferred)) = (input.value.as_number(), input.format) {
+                return (number.to_string(), Some(inferred), None);
+            }
+        }
+        (
+            normalize_entry(&value, format.as_ref(), decimal),
+            None,
+            None,
+        )
+    }
+
+    /// Store an entry and give its cell the format it was typed in, unless
+    /// the cell got a format meanwhile
+    fn store_entry(
+        facade: &SpreadsheetFacade,
+        address: &CellAddress,
+        value: &str,
+        inferred: Option<CellFormat>,
+    ) -> Result<()> {
+        facade.set_cell_value(address, value)?;
+        match inferred {
+            Some(format) if facade.get_effective_format(address).is_none() => {
+                facade.set_cell_format(address, format)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `value` is a formula with a line break outside its string
+    /// literals. Text entries may hold any number of line breaks.
+    pub fn has_bare_line_break(value: &str) -> bool {
+        if !value.starts_with('=') {
+            return false;
+        }
+        let mut quoted = false;
+        for c in value.chars() {
+            match c {
+                // A doubled quote inside a literal toggles twice
+                '"' => quoted = !quoted,
+                '\n' | '\r' if !quoted => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+
     /// Submit cell edit from editing mode using new architecture
     pub fn submit_cell_edit_direct(
         mode: &EditorMode,
         cursor: CellAddress,
         facade: &mut SpreadsheetFacade,
+        translator: &FormulaTranslator,
     ) -> Option<CellEditResult> {
         let editing_value = match mode {
             EditorMode::Editing { value, .. } | EditorMode::CellEditing { value, .. } => {
-                Some(value.clone())
+                Some(Self::canonical_entry(facade, translator, &cursor, value))
             }
             _ => None,
         };
 
-        if let Some(cell_value) = editing_value {
+        if let Some((cell_value, inferred, autocorrection)) = editing_value {
             let address = cursor;
 
-            let result = facade.set_cell_value(&address, &cell_value);
+            let result = Self::store_entry(facade, &address, &cell_value, inferred);
 
             match result {
                 Ok(_) => {
@@ -75,12 +151,14 @@ impl CellEditor {
                             address,
                             value: cell_value,
                             error_message: enhanced_message,
+                            autocorrection,
                         })
                     } else {
                         Some(CellEditResult::Success {
                             address,
                             value: cell_value,
                             should_clear_formula_bar: false,
+                            autocorrection,
                         })
                     }
                 }
@@ -105,11 +183,13 @@ pub enum CellEditResult {
         address: CellAddress,
         value: String,
         should_clear_formula_bar: bool,
+        autocorrection: Option<Autocorrection>,
     },
     SuccessWithError {
         address: CellAddress,
         value: String,
         error_message: String,
+        autocorrection: Option<Autocorrection>,
     },
     Failed {
         address: CellAddress,
@@ -121,35 +201,46 @@ impl CellEditResult {
     /// Create appropriate events from the result
     pub fn create_events(&self) -> Vec<(SpreadsheetEvent, Option<(String, ErrorSeverity)>)> {
         match self {
-            CellEditResult::Success { address, value, .. } => {
-                vec![(
+            CellEditResult::Success {
+                address,
+                value,
+                autocorrection,
+                ..
+            } => {
+                let mut events = vec![(
                     SpreadsheetEvent::CellEditCompleted {
                         address: *address,
-                        value: value.clone(),
+                        value: value.as_str().into(),
                     },
                     None,
-                )]
+                )];
+                events.extend(autocorrection.as_ref().map(Self::autocorrection_event));
+                events
             }
             CellEditResult::SuccessWithError {
                 address,
                 value,
                 error_message,
-            } => vec![
-                (
+                autocorrection,
+            } => {
+                let mut events = vec![(
                     SpreadsheetEvent::CellEditCompleted {
                         address: *address,
-                        value: value.clone(),
+                        value: value.as_str().into(),
                     },
                     None,
-                ),
-                (
+                )];
+                // The formula error is what the status line should end on
+                events.extend(autocorrection.as_ref().map(Self::autocorrection_event));
+                events.push((
                     SpreadsheetEvent::ErrorOccurred {
                         message: error_message.clone(),
                         severity: ErrorSeverity::Error,
                     },
                     Some((error_message.clone(), ErrorSeverity::Error)),
-                ),
-            ],
+                ));
+                events
+            }
             CellEditResult::Failed { error, .. } => vec![(
                 SpreadsheetEvent::ErrorOccurred {
                     message: error.clone(),
@@ -160,6 +251,28 @@ impl CellEditResult {
         }
     }
 
+    /// The autocorrection made to the entry before it was stored
+    pub fn autocorrection(&self) -> Option<&Autocorrection> {
+        match self {
+            CellEditResult::Success { autocorrection, .. }
+            | CellEditResult::SuccessWithError { autocorrection, .. } => autocorrection.as_ref(),
+            CellEditResult::Failed { .. } => None,
+        }
+    }
+
+    fn autocorrection_event(
+        autocorrection: &Autocorrection,
+    ) -> (SpreadsheetEvent, Option<(String, ErrorSeverity)>) {
+        let message = autocorrection.description.clone();
+        (
+            SpreadsheetEvent::ErrorOccurred {
+                message: message.clone(),
+                severity: ErrorSeverity::Info,
+            },
+            Some((message, ErrorSeverity::Info)),
+        )
+    }
+
     /// Get the next action to take after this result
     pub fn next_action(&self) -> Option<Action> {
         match self {
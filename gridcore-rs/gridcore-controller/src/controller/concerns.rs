@@ -0,0 +1,414 @@
+//! Which parts of the controller state changed
+//!
+//! Most events are `StateChanged`, so the event alone rarely says what to
+//! redraw. Views instead track one concern each, and a concern only counts
+//! as changed once its value differs from the one last seen.
+
+use super::events::SpreadsheetEvent;
+use super::mode::EditorMode;
+use super::{Capabilities, SpreadsheetController};
+use crate::state::Selection;
+use gridcore_core::repository::SheetHealth;
+use gridcore_core::types::CellAddress;
+use std::ops::{BitOr, BitOrAssign};
+
+#[cfg(feature = "perf")]
+use crate::perf::SIGNAL_UPDATES;
+#[cfg(feature = "perf")]
+use metrics::counter;
+
+/// A set of the parts of the controller state a view can depend on.
+///
+/// Events say which concerns they may have touched through
+/// [`Concerns::of`]; a [`ConcernTracker`] then tells which of those really
+/// changed, so a view re-reads only what it shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Concerns(u8);
+
+impl Concerns {
+    pub const NONE: Self = Self(0);
+    pub const CURSOR: Self = Self(1);
+    pub const SELECTION: Self = Self(1 << 1);
+    pub const ACTIVE_SHEET: Self = Self(1 << 2);
+    /// Sheet names, their error counts and whether they changed since the
+    /// last save
+    pub const SHEETS: Self = Self(1 << 3);
+    /// Cells inside the visible range
+    pub const VISIBLE_DATA: Self = Self(1 << 4);
+    /// Mode, edited text and formula bar
+    pub const EDITING: Self = Self(1 << 5);
+    pub const ERRORS: Self = Self(1 << 6);
+    /// What the user may do, see [`Capabilities`]
+    pub const CAPABILITIES: Self = Self(1 << 7);
+    pub const ALL: Self = Self(u8::MAX);
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Unknown bits are dropped
+    pub fn from_bits(bits: u8) -> Self {
+        Self(bits & Self::ALL.0)
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of concerns in the set
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// What `event` may have changed. `StateChanged` and commands can
+    /// change anything.
+    pub fn of(event: &SpreadsheetEvent) -> Self {
+        match event {
+            // The formula bar shows the cell under the cursor
+            SpreadsheetEvent::CursorMoved { .. } => Self::CURSOR | Self::SELECTION | Self::EDITING,
+            SpreadsheetEvent::FormulaBarUpdated { .. } | SpreadsheetEvent::EditCanceled { .. } => {
+                Self::EDITING
+            }
+            SpreadsheetEvent::CellEditCompleted { .. }
+            | SpreadsheetEvent::EditConflict { .. }
+            | SpreadsheetEvent::DeferredWritesApplied { .. } => {
+                Self::VISIBLE_DATA | Self::SHEETS | Self::EDITING
+            }
+            SpreadsheetEvent::SheetAdded { .. }
+            | SpreadsheetEvent::SheetRemoved { .. }
+            | SpreadsheetEvent::SheetRenamed { .. } => Self::SHEETS | Self::ACTIVE_SHEET,
+            SpreadsheetEvent::ModifiedChanged { .. } => Self::SHEETS,
+            SpreadsheetEvent::RowsInserted { .. }
+            | SpreadsheetEvent::RowsDeleted { .. }
+            | SpreadsheetEvent::ColumnsInserted { .. }
+            | SpreadsheetEvent::ColumnsDeleted { .. }
+            | SpreadsheetEvent::RangeMoved { .. } => {
+                Self::CURSOR | Self::SELECTION | Self::VISIBLE_DATA | Self::SHEETS | Self::EDITING
+            }
+            SpreadsheetEvent::ErrorOccurred { .. } => Self::ERRORS,
+            SpreadsheetEvent::WatchValueChanged { .. }
+            | SpreadsheetEvent::PasteNeedsConfirmation { .. }
+            | SpreadsheetEvent::RangeDropNeedsConfirmation { .. }
+            | SpreadsheetEvent::ViewportScrolled { .. }
+            | SpreadsheetEvent::ChartRequested { .. }
+            | SpreadsheetEvent::FocusChanged { .. }
+            | SpreadsheetEvent::UIStateChanged { .. } => Self::NONE,
+            #[cfg(feature = "debug")]
+            SpreadsheetEvent::ActionLogged { .. } => Self::NONE,
+            SpreadsheetEvent::StateChanged
+            | SpreadsheetEvent::CommandExecuted { .. }
+            | SpreadsheetEvent::SheetChanged { .. } => Self::ALL,
+        }
+    }
+}
+
+impl BitOr for Concerns {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Concerns {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+/// The last seen value of every concern
+#[derive(Debug, Clone)]
+pub struct ConcernTracker {
+    cursor: CellAddress,
+    selection: Option<Selection>,
+    active_sheet: String,
+    sheets: (Vec<(String, SheetHealth)>, u64),
+    visible_data: u64,
+    editing: (EditorMode, String, bool),
+    errors: (u64, usize),
+    capabilities: Capabilities,
+    updates: u64,
+}
+
+impl ConcernTracker {
+    pub fn new(controller: &SpreadsheetController) -> Self {
+        Self {
+            cursor: controller.cursor(),
+            selection: controller.get_selection().cloned(),
+            active_sheet: controller.get_active_sheet(),
+            sheets: sheets_state(controller),
+            visible_data: controller.get_viewport_cache().generation(),
+            editing: editing_state(controller),
+            errors: error_state(controller),
+            capabilities: controller.capabilities(),
+            updates: 0,
+        }
+    }
+
+    /// Re-read the `pending` concerns and return those whose value changed
+    pub fn sync(&mut self, controller: &SpreadsheetController, pending: Concerns) -> Concerns {
+        let mut changed = Concerns::NONE;
+        if pending.contains(Concerns::CURSOR) {
+            changed |= update(&mut self.cursor, controller.cursor(), Concerns::CURSOR);
+        }
+        if pending.contains(Concerns::SELECTION) {
+            let selection = controller.get_selection();
+            if self.selection.as_ref() != selection {
+                self.selection = selection.cloned();
+                changed |= Concerns::SELECTION;
+            }
+        }
+        if pending.contains(Concerns::ACTIVE_SHEET) {
+            changed |= update(
+                &mut self.active_sheet,
+                controller.get_active_sheet(),
+                Concerns::ACTIVE_SHEET,
+            );
+        }
+        if pending.contains(Concerns::SHEETS) {
+            changed |= update(&mut self.sheets, sheets_state(controller), Concerns::SHEETS);
+        }
+        if pending.contains(Concerns::VISIBLE_DATA) {
+            changed |= update(
+                &mut self.visible_data,
+                controller.get_viewport_cache().generation(),
+                Concerns::VISIBLE_DATA,
+            );
+        }
+        if pending.contains(Concerns::EDITING) {
+            changed |= update(
+                &mut self.editing,
+                editing_state(controller),
+                Concerns::EDITING,
+            );
+        }
+        if pending.contains(Concerns::ERRORS) {
+            changed |= update(&mut self.errors, error_state(controller), Concerns::ERRORS);
+        }
+        if pending.contains(Concerns::CAPABILITIES) {
+            changed |= update(
+                &mut self.capabilities,
+                controller.capabilities(),
+                Concerns::CAPABILITIES,
+            );
+        }
+
+        self.updates += changed.len() as u64;
+        #[cfg(feature = "perf")]
+        counter!(SIGNAL_UPDATES).increment(changed.len() as u64);
+
+        changed
+    }
+
+    /// Concerns that changed over all syncs so far
+    pub fn updates(&self) -> u64 {
+        self.updates
+    }
+}
+
+fn update<T: PartialEq>(seen: &mut T, current: T, concern: Concerns) -> Concerns {
+    if *seen == current {
+        return Concerns::NONE;
+    }
+    *seen = current;
+    concern
+}
+
+/// The open editor goes stale when its cell is written to
+fn editing_state(controller: &SpreadsheetController) -> (EditorMode, String, bool) {
+    (
+        controller.get_mode().clone(),
+        controller.get_formula_bar_value().to_string(),
+        controller.is_edit_stale(),
+    )
+}
+
+fn sheets_state(controller: &SpreadsheetController) -> (Vec<(String, SheetHealth)>, u64) {
+    (
+        controller.get_workbook_health(),
+        controller.save_state().revision(),
+    )
+}
+
+/// Expired messages leave the active list without changing the revision
+fn error_state(controller: &SpreadsheetController) -> (u64, usize) {
+    let errors = controller.get_error_manager();
+    (errors.revision(), errors.error_count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::KeyboardEvent;
+    use crate::state::Action;
+    use std::sync::{Arc, Mutex};
+
+    /// A controller whose events are collected as pending concerns, and
+    /// counted, the way the UI collects them
+    struct Harness {
+        controller: SpreadsheetController,
+        tracker: ConcernTracker,
+        pending: Arc<Mutex<(Concerns, usize)>>,
+    }
+
+    impl Harness {
+        fn new() -> Self {
+            let mut controller = SpreadsheetController::new();
+            let pending = Arc::new(Mutex::new((Concerns::NONE, 0)));
+            let sink = pending.clone();
+            controller.subscribe_to_events(move |event| {
+                let mut pending = sink.lock().unwrap();
+                pending.0 |= Concerns::of(event);
+                pending.1 += 1;
+            });
+            let tracker = ConcernTracker::new(&controller);
+            Self {
+                controller,
+                tracker,
+                pending,
+            }
+        }
+
+        /// Concerns changed by `action`, and how many events it sent
+        fn run(&mut self, action: impl FnOnce(&mut SpreadsheetController)) -> (Concerns, usize) {
+            action(&mut self.controller);
+            let (pending, events) = std::mem::take(&mut *self.pending.lock().unwrap());
+            (self.tracker.sync(&self.controller, pending), events)
+        }
+
+        fn keys(&mut self, keys: &[&str]) -> (Concerns, usize) {
+            self.run(|controller| {
+                for key in keys {
+                    controller
+                        .handle_keyboard_event(KeyboardEvent::new(key.to_string()))
+                        .unwrap();
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn test_cursor_move_touches_cursor_selection_and_formula_bar() {
+        let mut harness = Harness::new();
+        harness
+            .controller
+            .facade()
+            .set_cell_value(&CellAddress::new(0, 1), "7")
+            .unwrap();
+
+        let (changed, _) = harness.keys(&["j"]);
+        assert!(changed.contains(Concerns::CURSOR));
+        assert!(!changed.contains(Concerns::VISIBLE_DATA));
+        assert!(!changed.contains(Concerns::SHEETS));
+        assert!(!changed.contains(Concerns::ERRORS));
+        assert!(!changed.contains(Concerns::ACTIVE_SHEET));
+    }
+
+    #[test]
+    fn test_typing_touches_only_editing() {
+        let mut harness = Harness::new();
+        assert!(harness.keys(&["i"]).0.contains(Concerns::EDITING));
+
+        let (changed, events) = harness.keys(&["a", "b"]);
+        assert_eq!(changed, Concerns::EDITING);
+        assert!(events >= 2);
+    }
+
+    #[test]
+    fn test_commit_inside_view_touches_data_and_sheets() {
+        let mut harness = Harness::new();
+        harness.keys(&["i", "4", "2"]);
+
+        let (changed, _) = harness.keys(&["Escape", "Escape"]);
+        assert!(changed.contains(Concerns::VISIBLE_DATA));
+        assert!(changed.contains(Concerns::EDITING));
+        assert!(!changed.contains(Concerns::CURSOR));
+        assert!(!changed.contains(Concerns::ACTIVE_SHEET));
+        assert!(!changed.contains(Concerns::ERRORS));
+    }
+
+    #[test]
+    fn test_write_outside_view_leaves_visible_data_alone() {
+        let mut harness = Harness::new();
+        let far = CellAddress::new(0, 5000);
+        let (changed, _) = harness.run(|controller| {
+            controller.facade().set_cell_value(&far, "1").unwrap();
+            controller.refresh_cached_cells(&[far]);
+            controller.dispatch_event(SpreadsheetEvent::StateChanged);
+        });
+        assert!(!changed.contains(Concerns::VISIBLE_DATA));
+    }
+
+    #[test]
+    fn test_sheet_actions_touch_sheets() {
+        let mut harness = Harness::new();
+        let (changed, _) = harness.run(|controller| {
+            controller
+                .dispatch_action(Action::AddSheet {
+                    name: "Budget".to_string(),
+                })
+                .unwrap();
+        });
+        assert!(changed.contains(Concerns::SHEETS));
+        assert!(!changed.contains(Concerns::CURSOR));
+
+        let (changed, _) = harness.run(|controller| {
+            controller
+                .dispatch_action(Action::SetActiveSheet {
+                    name: "Budget".to_string(),
+                })
+                .unwrap();
+        });
+        assert!(changed.contains(Concerns::ACTIVE_SHEET));
+        assert!(changed.contains(Concerns::VISIBLE_DATA));
+    }
+
+    #[test]
+    fn test_errors_touch_only_errors() {
+        let mut harness = Harness::new();
+        let (changed, _) = harness.run(|controller| {
+            controller.add_error(
+                "Nope".to_string(),
+                crate::controller::events::ErrorSeverity::Error,
+            );
+        });
+        assert_eq!(changed, Concerns::ERRORS);
+
+        let (changed, _) = harness.run(|controller| controller.clear_errors());
+        assert!(changed.is_empty(), "no event was sent");
+        let (changed, _) = harness.run(|controller| {
+            controller.dispatch_event(SpreadsheetEvent::StateChanged);
+        });
+        assert_eq!(changed, Concerns::ERRORS);
+    }
+
+    #[test]
+    fn test_read_only_touches_capabilities() {
+        let mut harness = Harness::new();
+        let (changed, _) = harness.run(|controller| controller.set_read_only(true));
+        assert!(changed.contains(Concerns::CAPABILITIES));
+        assert!(!harness.controller.capabilities().edit);
+
+        // Refused changes only add a message
+        let (changed, _) = harness.keys(&["i"]);
+        assert_eq!(changed, Concerns::ERRORS);
+    }
+
+    #[test]
+    fn test_updates_are_fewer_than_with_one_generation() {
+        let mut harness = Harness::new();
+        let mut events = 0;
+        for keys in [&["j"][..], &["l"], &["i", "x", "Escape", "Escape"], &["k"]] {
+            events += harness.keys(keys).1;
+        }
+        // With one generation counter every event reaches the views of
+        // every concern
+        let baseline = (events * Concerns::ALL.len()) as u64;
+        assert!(harness.tracker.updates() > 0);
+        assert!(harness.tracker.updates() * 2 < baseline);
+    }
+}
@@ -0,0 +1,65 @@
+//! Data bars and color scales of the active sheet. A rule's bars and
+//! colors depend on every number of its range, so a change to one cell
+//! redraws the whole range, not just the cell.
+
+use super::events::SpreadsheetEvent;
+use super::SpreadsheetController;
+use gridcore_core::conditional::ConditionalFormatRule;
+use gridcore_core::formula::CellRange;
+use gridcore_core::types::CellAddress;
+use gridcore_core::Result;
+
+impl SpreadsheetController {
+    /// Add a data bar or color scale to the active sheet after its other
+    /// rules. Returns the rule's index.
+    pub fn add_conditional_format(&mut self, rule: ConditionalFormatRule) -> Result<usize> {
+        let index = self.facade.add_conditional_format(rule)?;
+        self.conditional_formats_changed();
+        Ok(index)
+    }
+
+    /// Replace the conditional format at `index` of the active sheet
+    pub fn update_conditional_format(
+        &mut self,
+        index: usize,
+        rule: ConditionalFormatRule,
+    ) -> Result<()> {
+        self.facade.update_conditional_format(index, rule)?;
+        self.conditional_formats_changed();
+        Ok(())
+    }
+
+    /// Remove the conditional format at `index` of the active sheet
+    pub fn remove_conditional_format(&mut self, index: usize) -> Result<ConditionalFormatRule> {
+        let removed = self.facade.remove_conditional_format(index)?;
+        self.conditional_formats_changed();
+        Ok(removed)
+    }
+
+    fn conditional_formats_changed(&mut self) {
+        self.viewport_cache.clear();
+        self.note_active_sheet_edited();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+    }
+
+    /// Re-read the cached cells of every conditional format whose range
+    /// `touched` says changed
+    pub(super) fn refresh_conditional_ranges(&mut self, touched: impl Fn(&CellRange) -> bool) {
+        let visible = self.viewport_manager.get_visible_bounds();
+        let shown = CellRange::new(
+            CellAddress::new(visible.start_col as u32, visible.start_row as u32),
+            CellAddress::new(visible.end_col as u32, visible.end_row as u32),
+        );
+        for rule in self.facade.get_conditional_formats() {
+            if !touched(&rule.range) {
+                continue;
+            }
+            self.viewport_cache
+                .invalidate_range(&self.facade, &rule.range);
+            if let Some(drawn) = rule.range.intersect(&shown) {
+                self.viewport_cache.note_writes(&visible, &[drawn.start]);
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use gridcore_core::types::CellAddress;
+use serde::{Deserialize, Serialize};
+
+/// What happens when a programmatic write targets the cell being edited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EditConflictPolicy {
+    /// Hold the write back and apply it once the edit commits or is cancelled
+    #[default]
+    Defer,
+    /// Cancel the edit and apply the write immediately
+    CancelEdit,
+    /// Apply the write and flag the open editor as out of date
+    MarkStale,
+}
+
+/// Tracks writes that collided with an in-progress cell edit
+#[derive(Debug, Clone, Default)]
+pub struct EditGuard {
+    policy: EditConflictPolicy,
+    pending: Vec<(CellAddress, String)>,
+    stale: bool,
+}
+
+impl EditGuard {
+    pub fn new(policy: EditConflictPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn policy(&self) -> EditConflictPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: EditConflictPolicy) {
+        self.policy = policy;
+    }
+
+    /// Writes held back until the current edit ends, oldest first
+    pub fn pending_writes(&self) -> &[(CellAddress, String)] {
+        &self.pending
+    }
+
+    /// Whether the edited cell changed underneath the open editor
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    pub fn defer(&mut self, address: CellAddress, value: String) {
+        self.pending.push((address, value));
+    }
+
+    pub fn mark_stale(&mut self) {
+        self.stale = true;
+    }
+
+    /// Reset for the next edit, handing back the writes that still need applying
+    pub fn finish(&mut self) -> Vec<(CellAddress, String)> {
+        self.stale = false;
+        std::mem::take(&mut self.pending)
+    }
+}
@@ -0,0 +1,268 @@
+use gridcore_core::types::CellAddress;
+use serde::{Deserialize, Serialize};
+
+/// Where the cursor goes after Enter commits an edit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnterDirection {
+    Down,
+    Up,
+    Right,
+    Left,
+    Stay,
+}
+
+impl EnterDirection {
+    /// Down as in other spreadsheets, except with vim where Enter leaves
+    /// the cursor on the cell like leaving insert mode does
+    pub fn default_for(vim_enabled: bool) -> Self {
+        if vim_enabled {
+            EnterDirection::Stay
+        } else {
+            EnterDirection::Down
+        }
+    }
+
+    fn reversed(self) -> Self {
+        match self {
+            EnterDirection::Down => EnterDirection::Up,
+            EnterDirection::Up => EnterDirection::Down,
+            EnterDirection::Right => EnterDirection::Left,
+            EnterDirection::Left => EnterDirection::Right,
+            EnterDirection::Stay => EnterDirection::Stay,
+        }
+    }
+}
+
+/// Key that committed an edit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitKey {
+    Enter,
+    ShiftEnter,
+    Tab,
+    ShiftTab,
+}
+
+impl CommitKey {
+    pub fn from_key(key: &str, shift: bool) -> Option<Self> {
+        match (key, shift) {
+            ("Enter", false) => Some(CommitKey::Enter),
+            ("Enter", true) => Some(CommitKey::ShiftEnter),
+            ("Tab", false) => Some(CommitKey::Tab),
+            ("Tab", true) => Some(CommitKey::ShiftTab),
+            _ => None,
+        }
+    }
+}
+
+/// Moves the cursor after a commit and remembers where a run of Tab
+/// commits started, so that Enter returns to that column on the next row
+/// like Excel's data entry.
+#[derive(Debug, Clone, Default)]
+pub struct EntryNavigation {
+    /// Overrides the vim-dependent default for Enter
+    enter: Option<EnterDirection>,
+    /// Column the current run of Tab commits started in and the cell the
+    /// last one moved to. Moving anywhere else ends the run.
+    tab_run: Option<(u32, CellAddress)>,
+}
+
+impl EntryNavigation {
+    pub fn new(enter: Option<EnterDirection>) -> Self {
+        Self {
+            enter,
+            tab_run: None,
+        }
+    }
+
+    /// The configured direction, `None` when it follows vim mode
+    pub fn enter_override(&self) -> Option<EnterDirection> {
+        self.enter
+    }
+
+    pub fn set_enter_direction(&mut self, enter: Option<EnterDirection>) {
+        self.enter = enter;
+    }
+
+    pub fn enter_direction(&self, vim_enabled: bool) -> EnterDirection {
+        self.enter
+            .unwrap_or_else(|| EnterDirection::default_for(vim_enabled))
+    }
+
+    /// The cell to move to after `key` committed the edit of `from`.
+    ///
+    /// Inside `block`, a selected range given by its corners, the cursor
+    /// cycles through the range: row by row for Tab, column by column for
+    /// Enter, wrapping at the end. Elsewhere it stops at the last column
+    /// and row in `limits`.
+    pub fn next_cell(
+        &mut self,
+        key: CommitKey,
+        from: CellAddress,
+        block: Option<(CellAddress, CellAddress)>,
+        limits: (u32, u32),
+        vim_enabled: bool,
+    ) -> CellAddress {
+        let run_start = self
+            .tab_run
+            .take()
+            .filter(|(_, reached)| *reached == from)
+            .map(|(start, _)| start);
+        let enter = self.enter_direction(vim_enabled);
+        let direction = match key {
+            CommitKey::Enter => enter,
+            CommitKey::ShiftEnter => enter.reversed(),
+            CommitKey::Tab => EnterDirection::Right,
+            CommitKey::ShiftTab => EnterDirection::Left,
+        };
+
+        if let Some((start, end)) = block.filter(|(start, end)| start != end) {
+            return cycle_within(from, start, end, direction);
+        }
+
+        let (max_col, max_row) = limits;
+        let next = match direction {
+            EnterDirection::Down => CellAddress::new(from.col, (from.row + 1).min(max_row)),
+            EnterDirection::Up => CellAddress::new(from.col, from.row.saturating_sub(1)),
+            EnterDirection::Right => CellAddress::new((from.col + 1).min(max_col), from.row),
+            EnterDirection::Left => CellAddress::new(from.col.saturating_sub(1), from.row),
+            EnterDirection::Stay => from,
+        };
+
+        match (key, run_start) {
+            (CommitKey::Tab | CommitKey::ShiftTab, _) => {
+                self.tab_run = Some((run_start.unwrap_or(from.col), next));
+                next
+            }
+            (_, Some(start)) if from.row != next.row => CellAddress::new(start, next.row),
+            _ => next,
+        }
+    }
+}
+
+/// Step through the range `start..=end` from `from`, which must lie inside
+fn cycle_within(
+    from: CellAddress,
+    start: CellAddress,
+    end: CellAddress,
+    direction: EnterDirection,
+) -> CellAddress {
+    let cols = end.col - start.col + 1;
+    let rows = end.row - start.row + 1;
+    let (col, row) = (from.col - start.col, from.row - start.row);
+    let count = cols * rows;
+    let (index, by_row) = match direction {
+        EnterDirection::Right | EnterDirection::Left => (row * cols + col, true),
+        EnterDirection::Down | EnterDirection::Up => (col * rows + row, false),
+        EnterDirection::Stay => return from,
+    };
+    let index = match direction {
+        EnterDirection::Right | EnterDirection::Down => (index + 1) % count,
+        _ => (index + count - 1) % count,
+    };
+    if by_row {
+        CellAddress::new(start.col + index % cols, start.row + index / cols)
+    } else {
+        CellAddress::new(start.col + index / rows, start.row + index % rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: (u32, u32) = (99, 999);
+
+    #[test]
+    fn test_enter_returns_to_start_of_tab_run() {
+        let mut nav = EntryNavigation::new(None);
+        let mut cell = CellAddress::new(1, 0);
+        let mut path = Vec::new();
+        for key in [
+            CommitKey::Tab,
+            CommitKey::Tab,
+            CommitKey::Enter,
+            CommitKey::Tab,
+            CommitKey::ShiftTab,
+            CommitKey::Tab,
+            CommitKey::Enter,
+            CommitKey::ShiftEnter,
+        ] {
+            cell = nav.next_cell(key, cell, None, LIMITS, false);
+            path.push(cell.to_a1());
+        }
+        assert_eq!(path, ["C1", "D1", "B2", "C2", "B2", "C2", "B3", "B2"]);
+    }
+
+    #[test]
+    fn test_moving_elsewhere_ends_the_tab_run() {
+        let mut nav = EntryNavigation::new(None);
+        let next = nav.next_cell(CommitKey::Tab, CellAddress::new(0, 0), None, LIMITS, false);
+        assert_eq!(next, CellAddress::new(1, 0));
+        // The user moved to D5 before committing with Enter
+        let next = nav.next_cell(
+            CommitKey::Enter,
+            CellAddress::new(3, 4),
+            None,
+            LIMITS,
+            false,
+        );
+        assert_eq!(next, CellAddress::new(3, 5));
+    }
+
+    #[test]
+    fn test_defaults_and_edges() {
+        let mut nav = EntryNavigation::new(None);
+        let a1 = CellAddress::new(0, 0);
+        assert_eq!(nav.next_cell(CommitKey::Enter, a1, None, LIMITS, true), a1);
+        assert_eq!(
+            nav.next_cell(CommitKey::ShiftEnter, a1, None, LIMITS, false),
+            a1
+        );
+        assert_eq!(
+            nav.next_cell(CommitKey::ShiftTab, a1, None, LIMITS, false),
+            a1
+        );
+
+        let corner = CellAddress::new(99, 999);
+        assert_eq!(
+            nav.next_cell(CommitKey::Enter, corner, None, LIMITS, false),
+            corner
+        );
+        assert_eq!(
+            nav.next_cell(CommitKey::Tab, corner, None, LIMITS, false),
+            corner
+        );
+
+        nav.set_enter_direction(Some(EnterDirection::Right));
+        assert_eq!(
+            nav.next_cell(CommitKey::Enter, a1, None, LIMITS, true),
+            CellAddress::new(1, 0)
+        );
+    }
+
+    #[test]
+    fn test_commits_cycle_within_selected_range() {
+        let mut nav = EntryNavigation::new(None);
+        let block = Some((CellAddress::new(1, 1), CellAddress::new(2, 2)));
+        let mut cell = CellAddress::new(1, 1);
+        let mut path = Vec::new();
+        for _ in 0..4 {
+            cell = nav.next_cell(CommitKey::Enter, cell, block, LIMITS, false);
+            path.push(cell.to_a1());
+        }
+        assert_eq!(path, ["B3", "C2", "C3", "B2"]);
+
+        path.clear();
+        for key in [
+            CommitKey::Tab,
+            CommitKey::Tab,
+            CommitKey::ShiftTab,
+            CommitKey::ShiftTab,
+        ] {
+            cell = nav.next_cell(key, cell, block, LIMITS, false);
+            path.push(cell.to_a1());
+        }
+        assert_eq!(path, ["C2", "B3", "C2", "B2"]);
+    }
+}
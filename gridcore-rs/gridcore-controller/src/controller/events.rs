@@ -1,5 +1,123 @@
+use super::edit_guard::EditConflictPolicy;
+use super::focus::FocusTarget;
+use crate::behaviors::paste::PasteConflicts;
+use crate::state::StateDiff;
+use gridcore_core::chart::ChartData;
+use gridcore_core::formula::CellRange;
+use gridcore_core::references::StructuralOperation;
 use gridcore_core::types::CellAddress;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+/// Text carried by an event, such as a cell's input.
+///
+/// Clones share one copy of the text. Past [`EventText::ELIDE_AFTER`]
+/// characters, debug output and serialized events show only a prefix and
+/// the length, so a giant cell does not end up in every log line or
+/// message sent to the host.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "WireText", into = "WireText")]
+pub struct EventText {
+    text: Arc<str>,
+    /// Length of the whole text in characters; longer than `text` when
+    /// only a prefix arrived over the wire
+    len: usize,
+}
+
+impl EventText {
+    /// Characters kept when eliding
+    pub const ELIDE_AFTER: usize = 256;
+
+    /// All of the text; `None` for a text deserialized from its prefix
+    pub fn full(&self) -> Option<&str> {
+        (self.text.chars().count() == self.len).then_some(&*self.text)
+    }
+
+    /// Up to [`Self::ELIDE_AFTER`] characters from the start
+    pub fn prefix(&self) -> &str {
+        match self.text.char_indices().nth(Self::ELIDE_AFTER) {
+            Some((end, _)) => &self.text[..end],
+            None => &self.text,
+        }
+    }
+
+    /// Length of the whole text in characters
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the text is too long to show whole
+    pub fn is_elided(&self) -> bool {
+        self.len > Self::ELIDE_AFTER
+    }
+}
+
+impl From<&str> for EventText {
+    fn from(text: &str) -> Self {
+        Self {
+            len: text.chars().count(),
+            text: text.into(),
+        }
+    }
+}
+
+impl From<String> for EventText {
+    fn from(text: String) -> Self {
+        Self {
+            len: text.chars().count(),
+            text: text.into(),
+        }
+    }
+}
+
+impl fmt::Debug for EventText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_elided() {
+            write!(f, "{:?}… ({} chars)", self.prefix(), self.len)
+        } else {
+            write!(f, "{:?}", self.prefix())
+        }
+    }
+}
+
+/// [`EventText`] as serialized: a plain string, or a prefix and the length
+/// once elided
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum WireText {
+    Whole(String),
+    Elided { prefix: String, len: usize },
+}
+
+impl From<EventText> for WireText {
+    fn from(text: EventText) -> Self {
+        if text.is_elided() {
+            WireText::Elided {
+                prefix: text.prefix().to_string(),
+                len: text.len,
+            }
+        } else {
+            WireText::Whole(text.text.to_string())
+        }
+    }
+}
+
+impl From<WireText> for EventText {
+    fn from(wire: WireText) -> Self {
+        match wire {
+            WireText::Whole(text) => text.into(),
+            WireText::Elided { prefix, len } => Self {
+                text: prefix.into(),
+                len,
+            },
+        }
+    }
+}
 
 /// Simplified events - reduced from 27 to 10 core event types
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -14,7 +132,7 @@ pub enum SpreadsheetEvent {
     // Cell editing with unified state
     CellEditCompleted {
         address: CellAddress,
-        value: String,
+        value: EventText,
     },
     EditCanceled {
         address: CellAddress,
@@ -22,7 +140,7 @@ pub enum SpreadsheetEvent {
 
     // Formula bar
     FormulaBarUpdated {
-        value: String,
+        value: EventText,
     },
 
     // Command execution
@@ -46,6 +164,95 @@ pub enum SpreadsheetEvent {
         new_name: String,
     },
 
+    // Rows and columns inserted or deleted on the active sheet, or a block
+    // moved; see SpreadsheetEvent::structural_operation
+    RowsInserted {
+        before: u32,
+        count: u32,
+    },
+    RowsDeleted {
+        start: u32,
+        count: u32,
+    },
+    ColumnsInserted {
+        before: u32,
+        count: u32,
+    },
+    ColumnsDeleted {
+        start: u32,
+        count: u32,
+    },
+    RangeMoved {
+        from: CellRange,
+        to: CellAddress,
+    },
+
+    // Watch window
+    WatchValueChanged {
+        sheet: String,
+        address: CellAddress,
+        old_value: Option<String>,
+        new_value: Option<String>,
+        stale: bool,
+    },
+
+    // Programmatic writes that collided with the open editor
+    EditConflict {
+        address: CellAddress,
+        policy: EditConflictPolicy,
+    },
+    DeferredWritesApplied {
+        addresses: Vec<CellAddress>,
+    },
+
+    // A paste would overwrite cells or fall outside the sheet; it is applied
+    // on ConfirmPaste
+    PasteNeedsConfirmation {
+        conflicts: PasteConflicts,
+    },
+    // A dropped range would overwrite cells; it is applied on
+    // ConfirmRangeDrop
+    RangeDropNeedsConfirmation {
+        overwritten: Vec<CellAddress>,
+        copy: bool,
+    },
+
+    // The view moved by this many pixels; wheel scrolls are reported once
+    // per frame
+    ViewportScrolled {
+        delta_x: f64,
+        delta_y: f64,
+    },
+
+    // Chart data ready for the host to draw
+    ChartRequested {
+        data: ChartData,
+    },
+
+    // The UI state changed since the last one published, for frontends
+    // that turned state diffs on
+    UIStateChanged {
+        diff: StateDiff,
+    },
+
+    // An action was dispatched and logged under this sequence number in
+    // the controller's action log
+    #[cfg(feature = "debug")]
+    ActionLogged {
+        sequence: u64,
+    },
+
+    // Keyboard focus moved between the grid and a panel
+    FocusChanged {
+        target: FocusTarget,
+    },
+
+    // Sheets changed or went back to their saved state
+    ModifiedChanged {
+        modified_sheets: Vec<String>,
+        document_modified: bool,
+    },
+
     // Error handling
     ErrorOccurred {
         message: String,
@@ -53,6 +260,57 @@ pub enum SpreadsheetEvent {
     },
 }
 
+impl SpreadsheetEvent {
+    /// The event announcing `operation`
+    pub fn structural(operation: StructuralOperation) -> Self {
+        match operation {
+            StructuralOperation::InsertRows { before_row, count } => Self::RowsInserted {
+                before: before_row,
+                count,
+            },
+            StructuralOperation::DeleteRows { start_row, count } => Self::RowsDeleted {
+                start: start_row,
+                count,
+            },
+            StructuralOperation::InsertColumns { before_col, count } => Self::ColumnsInserted {
+                before: before_col,
+                count,
+            },
+            StructuralOperation::DeleteColumns { start_col, count } => Self::ColumnsDeleted {
+                start: start_col,
+                count,
+            },
+            StructuralOperation::MoveRange { from, to } => Self::RangeMoved { from, to },
+        }
+    }
+
+    /// The structural operation a structural event announces, to shift
+    /// positions kept outside the controller with
+    /// [`StructuralOperation::shift_address`]
+    pub fn structural_operation(&self) -> Option<StructuralOperation> {
+        Some(match *self {
+            Self::RowsInserted { before, count } => StructuralOperation::InsertRows {
+                before_row: before,
+                count,
+            },
+            Self::RowsDeleted { start, count } => StructuralOperation::DeleteRows {
+                start_row: start,
+                count,
+            },
+            Self::ColumnsInserted { before, count } => StructuralOperation::InsertColumns {
+                before_col: before,
+                count,
+            },
+            Self::ColumnsDeleted { start, count } => StructuralOperation::DeleteColumns {
+                start_col: start,
+                count,
+            },
+            Self::RangeMoved { from, to } => StructuralOperation::MoveRange { from, to },
+            _ => return None,
+        })
+    }
+}
+
 /// Error severity levels
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ErrorSeverity {
@@ -256,7 +514,7 @@ impl EventDispatcher {
             }
             SpreadsheetEvent::CellEditCompleted { address, value } => {
                 if let Some(ref callback) = self.cell_callback {
-                    callback(address, value);
+                    callback(address, value.full().unwrap_or_else(|| value.prefix()));
                 }
             }
             SpreadsheetEvent::ErrorOccurred { message, severity } => {
@@ -313,7 +571,7 @@ impl EventDispatcher {
         // Also dispatch as event for compatibility
         self.dispatch(&SpreadsheetEvent::CellEditCompleted {
             address: *address,
-            value: value.to_string(),
+            value: value.into(),
         });
     }
 
@@ -385,4 +643,38 @@ mod tests {
         let events = received.lock().expect("Test mutex should not be poisoned");
         assert_eq!(events.len(), 1);
     }
+
+    #[test]
+    fn test_event_text_elision() {
+        let long = "é".repeat(100_000);
+        let event = SpreadsheetEvent::CellEditCompleted {
+            address: CellAddress::new(0, 0),
+            value: long.as_str().into(),
+        };
+        let SpreadsheetEvent::CellEditCompleted { value, .. } = event.clone() else {
+            unreachable!();
+        };
+        assert!(value.is_elided());
+        assert_eq!(value.full(), Some(long.as_str()));
+        assert_eq!(value.prefix().chars().count(), EventText::ELIDE_AFTER);
+        assert!(format!("{:?}", event).len() < 1_000);
+
+        // Serialized events carry the prefix and the length only
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.len() < 1_000);
+        let SpreadsheetEvent::CellEditCompleted { value: read, .. } =
+            serde_json::from_str(&json).unwrap()
+        else {
+            unreachable!();
+        };
+        assert_eq!(read.len(), 100_000);
+        assert_eq!(read.prefix(), value.prefix());
+        assert_eq!(read.full(), None);
+
+        let short: EventText = "=SUM(A1:A3)".into();
+        let json = serde_json::to_string(&short).unwrap();
+        assert_eq!(json, "\"=SUM(A1:A3)\"");
+        let read: EventText = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.full(), Some("=SUM(A1:A3)"));
+    }
 }
@@ -0,0 +1,637 @@
+use crate::behaviors::cell_transform::{CellTransform, TRANSFORM_COMMANDS};
+use crate::behaviors::quick_totals::QuickFunction;
+use crate::behaviors::vim::ex_parser::ExParser;
+use crate::behaviors::vim::vim_core::CommandRange;
+use crate::state::Action;
+use gridcore_core::domain::{CellFormat, StyleRemoval};
+use gridcore_core::fill::running::RunningAggregate;
+use gridcore_core::formula::{CellRange, Correction};
+use gridcore_core::pivot::{PivotAggregation, PivotConfig};
+use gridcore_core::types::CellAddress;
+use gridcore_core::workbook::{split_sheet_reference, NameScope};
+use gridcore_core::{Result, SpreadsheetError};
+
+/// Commands [`ExCommandExecutor`] implements, for completion; the
+/// transforms of [`TRANSFORM_COMMANDS`] come on top
+pub const COMMANDS: &[&str] = &[
+    "calc",
+    "chart",
+    "checkhealth",
+    "compact",
+    "fold",
+    "foldclose",
+    "foldopen",
+    "hide",
+    "let",
+    "lint",
+    "pivot",
+    "refresh",
+    "running",
+    "set",
+    "style",
+    "total",
+    "trace",
+    "unhide",
+    "unlet",
+    "unstyle",
+    "unwatch",
+    "visible",
+    "watch",
+];
+
+/// Executes ex commands entered in command mode
+pub struct ExCommandExecutor<'a> {
+    controller: &'a mut super::SpreadsheetController,
+}
+
+impl<'a> ExCommandExecutor<'a> {
+    pub fn new(controller: &'a mut super::SpreadsheetController) -> Self {
+        Self { controller }
+    }
+
+    /// Execute a command line. Commands the controller does not implement are
+    /// ignored here and left to `CommandExecuted` listeners.
+    pub fn execute(&mut self, command_line: &str) -> Result<()> {
+        let Ok(command) = ExParser::parse_command_line(command_line.trim()) else {
+            log::debug!("Ignoring unparseable command: '{}'", command_line);
+            return Ok(());
+        };
+
+        match command.command.as_str() {
+            "trace" => self.trace(&command.args),
+            "lint" => self.lint(&command.args),
+            "watch" => self.watch(&command.args),
+            "unwatch" => self.unwatch(&command.args),
+            "pivot" => self.pivot(&command.args),
+            "chart" => self.chart(&command.args),
+            "let" => self.define_constant(raw_args(command_line, "let")),
+            "unlet" => self.remove_constant(&command.args),
+            "style" => self.style(raw_args(command_line, "style")),
+            "unstyle" => self.remove_style(raw_args(command_line, "unstyle")),
+            "refresh" => self.controller.dispatch_action(Action::RefreshExternalData),
+            "checkhealth" => self.check_health(&command.args),
+            "compact" => self.controller.dispatch_action(Action::CompactMemory),
+            "calc" => self.calc(&command.args),
+            "total" => self.total(&command.args),
+            "running" => self.running(&command.args),
+            "hide" => self.hide(&command.args, true),
+            "unhide" => self.hide(&command.args, false),
+            "visible" => self.controller.dispatch_action(Action::SelectVisibleCells),
+            "fold" | "fo" => {
+                let (start_row, end_row) = self.rows(command.range.as_ref());
+                self.controller
+                    .dispatch_action(Action::CreateFold { start_row, end_row })
+            }
+            "foldopen" | "foldo" | "foldclose" | "foldc" => {
+                let (start_row, end_row) = self.rows(command.range.as_ref());
+                let all = command.args.first().is_some_and(|arg| arg == "!");
+                self.controller
+                    .dispatch_action(if command.command.starts_with("foldo") {
+                        Action::OpenFolds {
+                            start_row,
+                            end_row,
+                            all,
+                        }
+                    } else {
+                        Action::CloseFolds {
+                            start_row,
+                            end_row,
+                            all,
+                        }
+                    })
+            }
+            "set" | "se" => self.set(&command.args),
+            "uncorrect" => self.controller.revert_autocorrection(),
+            name if TRANSFORM_COMMANDS.contains(&name) => {
+                let transform = CellTransform::from_command(name, &command.args)?;
+                self.controller
+                    .dispatch_action(Action::TransformSelection { transform })
+            }
+            name => {
+                let args = raw_args(command_line, name).to_string();
+                self.controller.run_plugin_command(name, &args).map(|_| ())
+            }
+        }
+    }
+
+    /// `:trace [p|d|clear]` - expand precedent/dependent arrows or remove them
+    fn trace(&mut self, args: &[String]) -> Result<()> {
+        let action = match args.first().map(String::as_str) {
+            None | Some("p") | Some("precedents") => Action::TracePrecedents,
+            Some("d") | Some("dependents") => Action::TraceDependents,
+            Some("clear") | Some("off") => Action::ClearTraceArrows,
+            Some(other) => {
+                return Err(SpreadsheetError::InvalidCommand(format!(
+                    "Unknown trace target '{}', expected p, d or clear",
+                    other
+                )))
+            }
+        };
+        self.controller.dispatch_action(action)
+    }
+
+    /// `:lint [RANGE|clear|next|prev|on RULE|off RULE]` - lint the formulas
+    /// of the sheet or a range, step through the warnings, or turn a rule
+    /// on or off
+    fn lint(&mut self, args: &[String]) -> Result<()> {
+        let action = match (args.first().map(String::as_str), args.get(1)) {
+            (None, _) => Action::Lint { range: None },
+            (Some("clear") | Some("off"), None) => Action::ClearLintWarnings,
+            (Some("next") | Some("n"), None) => Action::NextLintWarning,
+            (Some("prev") | Some("p"), None) => Action::PreviousLintWarning,
+            (Some(toggle @ ("on" | "off")), Some(rule)) => Action::SetLintRule {
+                rule: rule.parse().map_err(|e: SpreadsheetError| {
+                    SpreadsheetError::InvalidCommand(e.to_string())
+                })?,
+                enabled: toggle == "on",
+            },
+            (Some(range), None) => Action::Lint {
+                range: Some(
+                    CellRange::from_string(range).map_err(SpreadsheetError::InvalidCommand)?,
+                ),
+            },
+            _ => {
+                return Err(SpreadsheetError::InvalidCommand(
+                    "Usage: :lint [RANGE|clear|next|prev|on RULE|off RULE]".to_string(),
+                ))
+            }
+        };
+        self.controller.dispatch_action(action)
+    }
+
+    /// `:watch [Sheet!]A1 [label]` - pin a cell in the watch window, defaulting to the cursor
+    fn watch(&mut self, args: &[String]) -> Result<()> {
+        let (address, sheet) = match args.first() {
+            Some(target) => parse_target(target)?,
+            None => (self.controller.cursor(), None),
+        };
+        let label = (args.len() > 1).then(|| args[1..].join(" "));
+
+        self.controller.dispatch_action(Action::AddWatch {
+            address,
+            sheet,
+            label,
+        })
+    }
+
+    /// `:unwatch [Sheet!]A1` - remove a cell from the watch window, defaulting to the cursor
+    fn unwatch(&mut self, args: &[String]) -> Result<()> {
+        let (address, sheet) = match args.first() {
+            Some(target) => parse_target(target)?,
+            None => (self.controller.cursor(), None),
+        };
+
+        self.controller
+            .dispatch_action(Action::RemoveWatch { address, sheet })
+    }
+
+    /// `:pivot SOURCE ANCHOR ROWKEYS VALUE [agg] [COLKEY|-] [totals]` - write a pivot,
+    /// e.g. `:pivot A1:D200 F1 A,B D sum C totals`. Columns are given as letters.
+    ///
+    /// `:pivot refresh [ANCHOR]` - regenerate a pivot, defaulting to the cursor
+    fn pivot(&mut self, args: &[String]) -> Result<()> {
+        if args.first().map(String::as_str) == Some("refresh") {
+            let anchor = match args.get(1) {
+                Some(anchor) => CellAddress::parse_a1_notation(anchor)?,
+                None => self.controller.cursor(),
+            };
+            return self
+                .controller
+                .dispatch_action(Action::RefreshPivot { anchor });
+        }
+
+        if args.len() < 4 {
+            return Err(SpreadsheetError::InvalidCommand(
+                "Usage: :pivot SOURCE ANCHOR ROWKEYS VALUE [agg] [COLKEY|-] [totals]".to_string(),
+            ));
+        }
+
+        let source = CellRange::from_string(&args[0]).map_err(SpreadsheetError::InvalidCommand)?;
+        let anchor = CellAddress::parse_a1_notation(&args[1])?;
+        let row_keys = args[2]
+            .split(',')
+            .map(parse_column)
+            .collect::<Result<Vec<_>>>()?;
+        let value_column = parse_column(&args[3])?;
+
+        let mut rest = args[4..].iter().map(String::as_str).peekable();
+        let aggregation = match rest.next_if(|arg| arg.parse::<PivotAggregation>().is_ok()) {
+            Some(arg) => arg.parse()?,
+            None => PivotAggregation::Sum,
+        };
+        let mut config = PivotConfig::new(row_keys, value_column, aggregation);
+        for arg in rest {
+            match arg {
+                "totals" => config.grand_totals = true,
+                "-" => config.column_key = None,
+                column => config.column_key = Some(parse_column(column)?),
+            }
+        }
+
+        self.controller.dispatch_action(Action::CreatePivot {
+            source,
+            anchor,
+            config,
+        })
+    }
+
+    /// `:chart [RANGE[,RANGE...]]` - publish chart data for the ranges, or for
+    /// the selection when none are given
+    fn chart(&mut self, args: &[String]) -> Result<()> {
+        let ranges = if args.is_empty() {
+            None
+        } else {
+            Some(
+                args.iter()
+                    .flat_map(|arg| arg.split(','))
+                    .filter(|range| !range.is_empty())
+                    .map(|range| {
+                        CellRange::from_string(range).map_err(SpreadsheetError::InvalidCommand)
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            )
+        };
+        self.controller
+            .dispatch_action(Action::ShowChart { ranges })
+    }
+
+    /// `:total [sum|average|count]` - write totals next to the selection,
+    /// sums when no function is given
+    fn total(&mut self, args: &[String]) -> Result<()> {
+        let function = match args.first() {
+            None => QuickFunction::Sum,
+            Some(name) => QuickFunction::from_name(name).ok_or_else(|| {
+                SpreadsheetError::InvalidCommand(format!("Unknown total function: {}", name))
+            })?,
+        };
+        self.controller.dispatch_action(match function {
+            QuickFunction::Sum => Action::QuickSum,
+            QuickFunction::Average => Action::QuickAverage,
+            QuickFunction::Count => Action::QuickCount,
+        })
+    }
+
+    /// `:running [sum|count|average|min|max] [COLUMN] [values]` - running
+    /// aggregate of the selected column, a sum when not given, written as
+    /// formulas into COLUMN or the next column; `values` writes the values
+    fn running(&mut self, args: &[String]) -> Result<()> {
+        let mut args = args.iter().map(String::as_str).peekable();
+        let aggregate = match args.peek().and_then(|arg| arg.parse().ok()) {
+            Some(aggregate) => {
+                args.next();
+                aggregate
+            }
+            None => RunningAggregate::Sum,
+        };
+        let mut target_col = None;
+        let mut values_only = false;
+        for arg in args {
+            if arg.eq_ignore_ascii_case("values") {
+                values_only = true;
+            } else {
+                target_col = Some(parse_column(arg).map_err(|_| {
+                    SpreadsheetError::InvalidCommand(format!(
+                        "Unknown running argument '{}', expected a column or values",
+                        arg
+                    ))
+                })?);
+            }
+        }
+        self.controller.dispatch_action(Action::RunningAggregate {
+            aggregate,
+            target_col,
+            values_only,
+        })
+    }
+
+    /// `:hide [rows|cols]` - hide the rows or columns of the selection, or
+    /// of the cursor, rows when not given. `:unhide` shows them again.
+    fn hide(&mut self, args: &[String], hidden: bool) -> Result<()> {
+        let columns = match args.first().map(String::as_str) {
+            None | Some("rows") | Some("row") => false,
+            Some("cols") | Some("col") | Some("columns") | Some("column") => true,
+            Some(other) => {
+                return Err(SpreadsheetError::InvalidCommand(format!(
+                    "Unknown lines '{}', expected rows or cols",
+                    other
+                )))
+            }
+        };
+        self.controller.dispatch_action(if hidden {
+            Action::HideSelection { columns }
+        } else {
+            Action::UnhideSelection { columns }
+        })
+    }
+
+    /// Rows of a command's range, numbered from 0, or those of the
+    /// selection or the cursor when it has none. `$` is the last used row.
+    fn rows(&self, range: Option<&CommandRange>) -> (u32, u32) {
+        let Some(range) = range else {
+            return self.controller.selected_rows();
+        };
+        let last = self
+            .controller
+            .facade()
+            .get_used_extent()
+            .map_or(0, |used| used.row);
+        let (start, end) = range_rows(range, self.controller.cursor().row, last);
+        (start.min(end), start.max(end))
+    }
+
+    /// `:set foldcolumn=N` (`fdc`) - set the width of the fold column;
+    /// `:set palette=NAME` - draw with the standard, highcontrast or
+    /// colorblind palette;
+    /// `:set resizepreview=ghost|live` (`rsp`) - show a resize as a
+    /// guideline or by laying the grid out again;
+    /// `:set autoformat` (`af`) or `:set noautoformat` - turn inferring
+    /// formats from typed input on or off; `:set autocorrect` (`ac`) or
+    /// `:set noautocorrect` - turn formula autocorrect on or off;
+    /// `:set autocorrections=LIST` (`acs`) - the corrections it makes, out
+    /// of parens, trailing, case, separators and doubled;
+    /// `:set strict` or `:set nostrict` - refuse to commit formulas that
+    /// do not parse, or store them to show an error
+    fn set(&mut self, args: &[String]) -> Result<()> {
+        let usage = || {
+            SpreadsheetError::InvalidCommand(
+                "Usage: :set foldcolumn=N, :set palette=NAME, :set resizepreview=ghost|live, \
+                 :set autoformat, :set noautoformat, :set autocorrect, :set noautocorrect, \
+                 :set autocorrections=LIST, :set strict or :set nostrict"
+                    .to_string(),
+            )
+        };
+        let [option] = args else {
+            return Err(usage());
+        };
+        if let Some(enabled) = match option.as_str() {
+            "autoformat" | "af" => Some(true),
+            "noautoformat" | "noaf" => Some(false),
+            _ => None,
+        } {
+            let facade = self.controller.facade();
+            let mut settings = facade.workbook_settings();
+            settings.infer_formats = enabled;
+            facade.set_workbook_settings(settings);
+            return Ok(());
+        }
+        if let Some(enabled) = match option.as_str() {
+            "autocorrect" | "ac" => Some(true),
+            "noautocorrect" | "noac" => Some(false),
+            _ => None,
+        } {
+            let facade = self.controller.facade();
+            let mut settings = facade.workbook_settings();
+            settings.autocorrect.enabled = enabled;
+            facade.set_workbook_settings(settings);
+            return Ok(());
+        }
+        if let Some(enabled) = match option.as_str() {
+            "strict" => Some(true),
+            "nostrict" => Some(false),
+            _ => None,
+        } {
+            let facade = self.controller.facade();
+            let mut settings = facade.workbook_settings();
+            settings.strict_formulas = enabled;
+            facade.set_workbook_settings(settings);
+            return Ok(());
+        }
+        let Some((name, value)) = option.split_once('=') else {
+            return Err(usage());
+        };
+        match name {
+            "foldcolumn" | "fdc" => {
+                let width = value.parse().map_err(|_| {
+                    SpreadsheetError::InvalidCommand(format!("Invalid foldcolumn: {}", value))
+                })?;
+                self.controller
+                    .dispatch_action(Action::SetFoldColumn { width })
+            }
+            "palette" => {
+                let palette = value.parse().map_err(SpreadsheetError::InvalidCommand)?;
+                self.controller
+                    .dispatch_action(Action::SetPalette { palette })
+            }
+            "resizepreview" | "rsp" => {
+                let preview = value.parse().map_err(SpreadsheetError::InvalidCommand)?;
+                self.controller
+                    .dispatch_action(Action::SetResizePreview { preview })
+            }
+            // e.g. `:set autocorrections=parens,case`
+            "autocorrections" | "acs" => {
+                let corrections = value
+                    .split(',')
+                    .filter(|name| !name.is_empty())
+                    .map(|name| name.trim().parse::<Correction>())
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(SpreadsheetError::InvalidCommand)?;
+                let facade = self.controller.facade();
+                let mut settings = facade.workbook_settings();
+                settings.autocorrect.corrections = corrections;
+                facade.set_workbook_settings(settings);
+                Ok(())
+            }
+            other => Err(SpreadsheetError::InvalidCommand(format!(
+                "Unknown option: {}",
+                other
+            ))),
+        }
+    }
+
+    /// `:let [Sheet!]NAME=VALUE` - define a named constant, e.g.
+    /// `:let TaxRate=0.21`. Without a sheet the name is workbook-wide.
+    fn define_constant(&mut self, line: &str) -> Result<()> {
+        let Some((target, definition)) = line.split_once('=') else {
+            return Err(SpreadsheetError::InvalidCommand(
+                "Usage: :let [Sheet!]NAME=VALUE".to_string(),
+            ));
+        };
+        let (name, scope) = parse_name(target);
+        self.controller.dispatch_action(Action::DefineConstant {
+            name,
+            definition: definition.trim().to_string(),
+            scope,
+        })
+    }
+
+    /// `:style NAME` - apply a named style to the selection, or the cursor.
+    /// `:style NAME=FORMAT` - define or change a style, e.g.
+    /// `:style Total=currency € 2`; see [`CellFormat`]'s `FromStr` for formats.
+    fn style(&mut self, line: &str) -> Result<()> {
+        if line.is_empty() {
+            return Err(SpreadsheetError::InvalidCommand(
+                "Usage: :style NAME or :style NAME=FORMAT".to_string(),
+            ));
+        }
+        let action = match line.split_once('=') {
+            Some((name, format)) => Action::DefineStyle {
+                name: name.trim().to_string(),
+                format: format
+                    .parse::<CellFormat>()
+                    .map_err(|e| SpreadsheetError::InvalidCommand(e.to_string()))?,
+            },
+            None => Action::ApplyStyle {
+                name: line.to_string(),
+                ranges: None,
+            },
+        };
+        self.controller.dispatch_action(action)
+    }
+
+    /// `:unstyle NAME [convert]` - delete a named style. Without `convert`
+    /// this fails while cells use the style; with it they keep its format.
+    fn remove_style(&mut self, line: &str) -> Result<()> {
+        let (name, removal) = match line.strip_suffix(" convert") {
+            Some(name) => (name.trim(), StyleRemoval::ConvertToFormats),
+            None => (line, StyleRemoval::Block),
+        };
+        if name.is_empty() {
+            return Err(SpreadsheetError::InvalidCommand(
+                "Usage: :unstyle NAME [convert]".to_string(),
+            ));
+        }
+        self.controller.dispatch_action(Action::RemoveStyle {
+            name: name.to_string(),
+            removal,
+        })
+    }
+
+    /// `:calc [sheet|RANGE|on|off]` - recalculate the stale formulas of the
+    /// sheet or a range, or turn automatic calculation of the sheet on or off
+    fn calc(&mut self, args: &[String]) -> Result<()> {
+        if args.len() > 1 {
+            return Err(SpreadsheetError::InvalidCommand(
+                "Usage: :calc [sheet|RANGE|on|off]".to_string(),
+            ));
+        }
+        let action = match args.first().map(String::as_str) {
+            None | Some("sheet") => Action::RecalculateStale { range: None },
+            Some(toggle @ ("on" | "off")) => Action::SetCalculationEnabled {
+                enabled: toggle == "on",
+            },
+            Some(range) => Action::RecalculateStale {
+                range: Some(
+                    CellRange::from_string(range).map_err(SpreadsheetError::InvalidCommand)?,
+                ),
+            },
+        };
+        self.controller.dispatch_action(action)
+    }
+
+    /// `:checkhealth [repair]` - check the dependency graph, used range and
+    /// error index of the sheet, repairing what is wrong if asked
+    fn check_health(&mut self, args: &[String]) -> Result<()> {
+        let repair = match args.first().map(String::as_str) {
+            None => false,
+            Some("repair") | Some("fix") => true,
+            Some(_) => {
+                return Err(SpreadsheetError::InvalidCommand(
+                    "Usage: :checkhealth [repair]".to_string(),
+                ))
+            }
+        };
+        self.controller
+            .dispatch_action(Action::CheckHealth { repair })
+    }
+
+    /// `:unlet [Sheet!]NAME` - delete a named constant
+    fn remove_constant(&mut self, args: &[String]) -> Result<()> {
+        let Some(target) = args.first() else {
+            return Err(SpreadsheetError::InvalidCommand(
+                "Usage: :unlet [Sheet!]NAME".to_string(),
+            ));
+        };
+        let (name, scope) = parse_name(target);
+        self.controller
+            .dispatch_action(Action::RemoveConstant { name, scope })
+    }
+}
+
+impl super::SpreadsheetController {
+    /// Ex commands starting with `prefix`, built-in ones and those of the
+    /// enabled plugins, sorted
+    pub fn command_completions(&self, prefix: &str) -> Vec<String> {
+        let mut names: Vec<String> = COMMANDS
+            .iter()
+            .chain(TRANSFORM_COMMANDS)
+            .map(|name| name.to_string())
+            .chain(self.plugins.commands())
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Complete the command name being typed in `line` as far as the
+    /// candidates agree, and past a single candidate with a space
+    pub fn complete_command(&self, line: &str) -> String {
+        if line.contains(char::is_whitespace) {
+            return line.to_string();
+        }
+        let candidates = self.command_completions(line);
+        match candidates.as_slice() {
+            [] => line.to_string(),
+            [only] => format!("{} ", only),
+            [first, rest @ ..] => {
+                let shared = rest.iter().fold(first.len(), |len, name| {
+                    first
+                        .bytes()
+                        .zip(name.bytes())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                first[..shared].to_string()
+            }
+        }
+    }
+}
+
+/// First and last row, from 0, of a range whose line numbers count from 1
+fn range_rows(range: &CommandRange, cursor_row: u32, last_row: u32) -> (u32, u32) {
+    let row = |range: &CommandRange| range_rows(range, cursor_row, last_row);
+    match range {
+        CommandRange::Line(line) => (line.saturating_sub(1), line.saturating_sub(1)),
+        CommandRange::CurrentLine => (cursor_row, cursor_row),
+        CommandRange::LastLine => (last_row, last_row),
+        CommandRange::AllLines => (0, last_row),
+        CommandRange::Range(start, end) => (row(start).0, row(end).1),
+        CommandRange::RelativeForward(base, offset) => {
+            let at = row(base).0.saturating_add(*offset);
+            (at, at)
+        }
+        CommandRange::RelativeBackward(base, offset) => {
+            let at = row(base).0.saturating_sub(*offset);
+            (at, at)
+        }
+    }
+}
+
+/// Parse a column given by its letters, e.g. `C` or `AB`
+fn parse_column(label: &str) -> Result<u32> {
+    CellAddress::column_label_to_number(&label.to_ascii_uppercase())
+}
+
+/// Split an optionally sheet-qualified reference such as `'Q1 Data'!B7`
+fn parse_target(target: &str) -> Result<(CellAddress, Option<String>)> {
+    match split_sheet_reference(target) {
+        Some((sheet, cell)) => Ok((CellAddress::parse_a1_notation(cell)?, Some(sheet))),
+        None => Ok((CellAddress::parse_a1_notation(target)?, None)),
+    }
+}
+
+/// The text after the command name, untouched by argument splitting so
+/// quoted strings and negative numbers in it survive
+fn raw_args<'l>(command_line: &'l str, command: &str) -> &'l str {
+    let line = command_line.trim().trim_start_matches(':').trim_start();
+    line.strip_prefix(command).unwrap_or(line).trim()
+}
+
+/// Split `[Sheet!]NAME` into the name and the scope it is defined in
+fn parse_name(target: &str) -> (String, NameScope) {
+    match split_sheet_reference(target.trim()) {
+        Some((sheet, name)) => (name.to_string(), NameScope::Sheet(sheet)),
+        None => (target.trim().to_string(), NameScope::Workbook),
+    }
+}
@@ -0,0 +1,238 @@
+//! Keyboard focus between the grid and the panels around it.
+//!
+//! Panels that take keys (the console, dialogs, search fields) open a
+//! focus scope while they show and list the elements Tab moves between.
+//! Scopes stack: the top one has focus, Tab and Shift+Tab cycle through
+//! its items and Escape closes it, handing focus back to the scope below
+//! or, once none is left, to the grid. While any scope is open the grid's
+//! key handling is suspended, so keys typed into a panel never move the
+//! cursor.
+
+use serde::{Deserialize, Serialize};
+
+/// What has keyboard focus
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FocusTarget {
+    Grid,
+    /// A panel's scope, on one of its items unless it has none. Items are
+    /// named by the ids the panel registered, e.g. element ids.
+    Panel {
+        scope: String,
+        item: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FocusScope {
+    id: String,
+    items: Vec<String>,
+    /// Index of the focused item
+    active: usize,
+}
+
+impl FocusScope {
+    fn target(&self) -> FocusTarget {
+        FocusTarget::Panel {
+            scope: self.id.clone(),
+            item: self.items.get(self.active).cloned(),
+        }
+    }
+
+    fn set_items(&mut self, items: Vec<String>) {
+        let focused = self.items.get(self.active);
+        self.active = focused
+            .and_then(|focused| items.iter().position(|item| item == focused))
+            .unwrap_or(0);
+        self.items = items;
+    }
+}
+
+/// Stack of open focus scopes, the focused one on top
+#[derive(Debug, Clone, Default)]
+pub struct FocusManager {
+    scopes: Vec<FocusScope>,
+}
+
+impl FocusManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn current(&self) -> FocusTarget {
+        self.scopes
+            .last()
+            .map_or(FocusTarget::Grid, FocusScope::target)
+    }
+
+    pub fn has_grid_focus(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// Id of the focused scope
+    pub fn active_scope(&self) -> Option<&str> {
+        self.scopes.last().map(|scope| scope.id.as_str())
+    }
+
+    /// Number of open scopes
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    pub fn is_open(&self, id: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.id == id)
+    }
+
+    /// Open scope `id` on top with focus on its first item. A scope that
+    /// is already open moves to the top, keeping its focused item.
+    pub fn push_scope(&mut self, id: &str, items: Vec<String>) -> FocusTarget {
+        let scope = match self.scopes.iter().position(|scope| scope.id == id) {
+            Some(index) => {
+                let mut scope = self.scopes.remove(index);
+                scope.set_items(items);
+                scope
+            }
+            None => FocusScope {
+                id: id.to_string(),
+                items,
+                active: 0,
+            },
+        };
+        self.scopes.push(scope);
+        self.current()
+    }
+
+    /// Close the focused scope. Focus goes back to where it was in the
+    /// scope below, or to the grid.
+    pub fn pop_scope(&mut self) -> FocusTarget {
+        self.scopes.pop();
+        self.current()
+    }
+
+    /// Close scope `id` wherever it is in the stack, e.g. when its panel
+    /// hides without Escape
+    pub fn close_scope(&mut self, id: &str) -> FocusTarget {
+        self.scopes.retain(|scope| scope.id != id);
+        self.current()
+    }
+
+    /// Close every scope and give focus back to the grid
+    pub fn clear(&mut self) -> FocusTarget {
+        self.scopes.clear();
+        FocusTarget::Grid
+    }
+
+    /// Replace the items of scope `id`, e.g. after its panel re-rendered.
+    /// Focus stays on the same item while it is there.
+    pub fn set_items(&mut self, id: &str, items: Vec<String>) {
+        if let Some(scope) = self.scopes.iter_mut().find(|scope| scope.id == id) {
+            scope.set_items(items);
+        }
+    }
+
+    /// Focus `item` of scope `id`, e.g. after a click into it; the scope
+    /// moves to the top. Returns whether there was such an item.
+    pub fn focus_item(&mut self, id: &str, item: &str) -> bool {
+        let Some(index) = self.scopes.iter().position(|scope| scope.id == id) else {
+            return false;
+        };
+        let Some(active) = self.scopes[index].items.iter().position(|i| i == item) else {
+            return false;
+        };
+        let mut scope = self.scopes.remove(index);
+        scope.active = active;
+        self.scopes.push(scope);
+        true
+    }
+
+    /// Move focus to the next item of the focused scope, or the previous
+    /// one when `backwards`, wrapping around at either end
+    pub fn cycle(&mut self, backwards: bool) -> FocusTarget {
+        if let Some(scope) = self.scopes.last_mut() {
+            let len = scope.items.len();
+            if len > 0 {
+                scope.active = if backwards {
+                    (scope.active + len - 1) % len
+                } else {
+                    (scope.active + 1) % len
+                };
+            }
+        }
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn panel(scope: &str, item: Option<&str>) -> FocusTarget {
+        FocusTarget::Panel {
+            scope: scope.to_string(),
+            item: item.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_scopes_stack_and_restore_focus() {
+        let mut focus = FocusManager::new();
+        assert!(focus.has_grid_focus());
+
+        focus.push_scope("dialog", items(&["name", "ok", "cancel"]));
+        assert_eq!(focus.cycle(false), panel("dialog", Some("ok")));
+        assert_eq!(
+            focus.push_scope("find", items(&["query"])),
+            panel("find", Some("query"))
+        );
+        assert_eq!(focus.depth(), 2);
+
+        // Escape goes back to the item the dialog had focused
+        assert_eq!(focus.pop_scope(), panel("dialog", Some("ok")));
+        assert_eq!(focus.pop_scope(), FocusTarget::Grid);
+        assert_eq!(focus.pop_scope(), FocusTarget::Grid);
+    }
+
+    #[test]
+    fn test_tab_cycles_within_the_top_scope() {
+        let mut focus = FocusManager::new();
+        focus.push_scope("dialog", items(&["a", "b", "c"]));
+        assert_eq!(focus.cycle(false), panel("dialog", Some("b")));
+        assert_eq!(focus.cycle(false), panel("dialog", Some("c")));
+        assert_eq!(focus.cycle(false), panel("dialog", Some("a")));
+        assert_eq!(focus.cycle(true), panel("dialog", Some("c")));
+
+        focus.push_scope("empty", Vec::new());
+        assert_eq!(focus.cycle(false), panel("empty", None));
+    }
+
+    #[test]
+    fn test_reopening_and_closing_scopes() {
+        let mut focus = FocusManager::new();
+        focus.push_scope("watch", items(&["row1", "row2"]));
+        focus.cycle(false);
+        focus.push_scope("console", items(&["input"]));
+
+        // Clicking into the watch panel brings it back on top as it was
+        assert!(focus.focus_item("watch", "row1"));
+        assert_eq!(focus.active_scope(), Some("watch"));
+        assert!(!focus.focus_item("watch", "row9"));
+        assert_eq!(
+            focus.push_scope("console", items(&["input"])),
+            panel("console", Some("input"))
+        );
+
+        // Re-rendered items keep focus on the same one
+        focus.set_items("watch", items(&["row0", "row1", "row2"]));
+        assert_eq!(focus.pop_scope(), panel("watch", Some("row1")));
+
+        // A panel hiding closes its scope wherever it is
+        focus.push_scope("console", items(&["input"]));
+        assert_eq!(focus.close_scope("watch"), panel("console", Some("input")));
+        assert!(!focus.is_open("watch"));
+        assert_eq!(focus.clear(), FocusTarget::Grid);
+        assert!(focus.has_grid_focus());
+    }
+}
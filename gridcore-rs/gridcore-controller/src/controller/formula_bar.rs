@@ -42,7 +42,7 @@ impl FormulaBarManager {
     /// Create FormulaBarUpdated event
     pub fn create_update_event(&self) -> SpreadsheetEvent {
         SpreadsheetEvent::FormulaBarUpdated {
-            value: self.value.clone(),
+            value: self.value.as_str().into(),
         }
     }
 }
@@ -101,7 +101,9 @@ impl<'a> FormulaBarOperations<'a> {
     {
         let value = self.manager.update_from_cell(address, get_display_value);
         if let Some(callback) = self.event_callback.take() {
-            callback(SpreadsheetEvent::FormulaBarUpdated { value });
+            callback(SpreadsheetEvent::FormulaBarUpdated {
+                value: value.into(),
+            });
         }
     }
 }
@@ -0,0 +1,220 @@
+use gridcore_core::types::CellAddress;
+use serde::{Deserialize, Serialize};
+
+/// Rows and columns of slack kept beyond the used range
+pub const DEFAULT_MARGIN_ROWS: u32 = 50;
+pub const DEFAULT_MARGIN_COLS: u32 = 10;
+
+/// The part of the grid that can be scrolled to: the used range plus a
+/// margin, grown by another margin whenever the cursor moves past it and
+/// never larger than the configured grid.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GridExtent {
+    rows: u32,
+    cols: u32,
+    max_rows: u32,
+    max_cols: u32,
+    margin_rows: u32,
+    margin_cols: u32,
+    /// Last populated column and row, `None` for an empty sheet
+    used: Option<CellAddress>,
+}
+
+impl GridExtent {
+    pub fn new(max_rows: u32, max_cols: u32) -> Self {
+        Self {
+            rows: 0,
+            cols: 0,
+            max_rows,
+            max_cols,
+            margin_rows: 0,
+            margin_cols: 0,
+            used: None,
+        }
+        .with_margin(DEFAULT_MARGIN_ROWS, DEFAULT_MARGIN_COLS)
+    }
+
+    pub fn with_margin(mut self, rows: u32, cols: u32) -> Self {
+        self.margin_rows = rows;
+        self.margin_cols = cols;
+        (self.rows, self.cols) = self.needed(None);
+        self
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn cols(&self) -> u32 {
+        self.cols
+    }
+
+    pub fn used(&self) -> Option<CellAddress> {
+        self.used
+    }
+
+    /// Grow past `address`, with the margin to spare, if it lies outside.
+    /// Returns whether the extent changed.
+    pub fn include(&mut self, address: &CellAddress) -> bool {
+        let rows = if address.row >= self.rows {
+            self.fit_rows(address.row + 1)
+        } else {
+            self.rows
+        };
+        let cols = if address.col >= self.cols {
+            self.fit_cols(address.col + 1)
+        } else {
+            self.cols
+        };
+        self.resize(rows, cols)
+    }
+
+    /// Record that `address` now holds data, keeping the margin beyond it
+    pub fn note_populated(&mut self, address: &CellAddress) -> bool {
+        let used = match self.used {
+            Some(used) => CellAddress::new(used.col.max(address.col), used.row.max(address.row)),
+            None => *address,
+        };
+        self.used = Some(used);
+        self.resize(
+            self.rows.max(self.fit_rows(used.row + 1)),
+            self.cols.max(self.fit_cols(used.col + 1)),
+        )
+    }
+
+    /// Whether clearing `address` could move the edge of the used range
+    pub fn is_on_used_edge(&self, address: &CellAddress) -> bool {
+        self.used
+            .is_some_and(|used| address.row >= used.row || address.col >= used.col)
+    }
+
+    /// Replace the used range, e.g. after data was deleted or the sheet
+    /// changed. The extent shrinks only as far as `keep`, typically the
+    /// bottom-right cell on screen or the cursor, so the view never jumps.
+    pub fn set_used(&mut self, used: Option<CellAddress>, keep: &CellAddress) -> bool {
+        self.used = used;
+        let (rows, cols) = self.needed(Some(keep));
+        self.resize(rows, cols)
+    }
+
+    fn resize(&mut self, rows: u32, cols: u32) -> bool {
+        let changed = (rows, cols) != (self.rows, self.cols);
+        self.rows = rows;
+        self.cols = cols;
+        changed
+    }
+
+    fn needed(&self, keep: Option<&CellAddress>) -> (u32, u32) {
+        let used_rows = self.used.map_or(0, |used| used.row + 1);
+        let used_cols = self.used.map_or(0, |used| used.col + 1);
+        let keep_rows = keep.map_or(0, |keep| keep.row + 1);
+        let keep_cols = keep.map_or(0, |keep| keep.col + 1);
+        (
+            self.fit_rows(used_rows).max(keep_rows.min(self.max_rows)),
+            self.fit_cols(used_cols).max(keep_cols.min(self.max_cols)),
+        )
+    }
+
+    fn fit_rows(&self, rows: u32) -> u32 {
+        rows.saturating_add(self.margin_rows).min(self.max_rows)
+    }
+
+    fn fit_cols(&self, cols: u32) -> u32 {
+        cols.saturating_add(self.margin_cols).min(self.max_cols)
+    }
+}
+
+/// Size and position of a scrollbar thumb as fractions of the track
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScrollbarMetrics {
+    /// Thumb length relative to the track, 1.0 when everything fits
+    pub thumb_size: f64,
+    /// Offset of the thumb's start relative to the track
+    pub thumb_start: f64,
+}
+
+impl ScrollbarMetrics {
+    /// Metrics for one axis. `content` is the length of the scrollable
+    /// extent and `frozen` the part of it pinned at the start, both in
+    /// unzoomed pixels. `viewport` is the on-screen length and `offset` the
+    /// scroll position past the frozen part, both in screen pixels.
+    pub fn compute(content: f64, viewport: f64, offset: f64, frozen: f64, zoom: f64) -> Self {
+        let zoom = if zoom > 0.0 { zoom } else { 1.0 };
+        let scrollable = ((content - frozen) * zoom).max(0.0);
+        let visible = (viewport - frozen * zoom).max(0.0);
+        if scrollable <= visible {
+            return Self {
+                thumb_size: 1.0,
+                thumb_start: 0.0,
+            };
+        }
+
+        let thumb_size = visible / scrollable;
+        let max_offset = scrollable - visible;
+        Self {
+            thumb_size,
+            thumb_start: offset.clamp(0.0, max_offset) / scrollable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extent_grows_with_navigation() {
+        let mut extent = GridExtent::new(10_000, 256);
+        assert_eq!((extent.rows(), extent.cols()), (50, 10));
+
+        // Moving inside the extent changes nothing
+        assert!(!extent.include(&CellAddress::new(3, 20)));
+
+        assert!(extent.include(&CellAddress::new(12, 60)));
+        assert_eq!((extent.rows(), extent.cols()), (111, 23));
+
+        // Never past the configured grid
+        extent.include(&CellAddress::new(255, 9_999));
+        assert_eq!((extent.rows(), extent.cols()), (10_000, 256));
+    }
+
+    #[test]
+    fn test_extent_shrinks_conservatively() {
+        let mut extent = GridExtent::new(10_000, 256);
+        extent.note_populated(&CellAddress::new(5, 400));
+        assert_eq!(extent.rows(), 451);
+        assert!(extent.is_on_used_edge(&CellAddress::new(0, 400)));
+        assert!(!extent.is_on_used_edge(&CellAddress::new(0, 399)));
+
+        // The data is gone but row 300 is still on screen
+        extent.set_used(Some(CellAddress::new(1, 9)), &CellAddress::new(5, 300));
+        assert_eq!((extent.rows(), extent.cols()), (301, 12));
+
+        // Once the view has moved back the extent falls to data plus margin
+        extent.set_used(Some(CellAddress::new(1, 9)), &CellAddress::new(0, 0));
+        assert_eq!((extent.rows(), extent.cols()), (60, 12));
+        extent.set_used(None, &CellAddress::new(0, 0));
+        assert_eq!((extent.rows(), extent.cols()), (50, 10));
+    }
+
+    #[test]
+    fn test_scrollbar_ratio() {
+        let metrics = ScrollbarMetrics::compute(2_400.0, 600.0, 900.0, 0.0, 1.0);
+        assert_eq!(metrics.thumb_size, 0.25);
+        assert_eq!(metrics.thumb_start, 0.375);
+
+        // Everything fits
+        let metrics = ScrollbarMetrics::compute(400.0, 600.0, 0.0, 0.0, 1.0);
+        assert_eq!(metrics.thumb_size, 1.0);
+        assert_eq!(metrics.thumb_start, 0.0);
+
+        // Two frozen rows of 50px take their space from both content and viewport
+        let metrics = ScrollbarMetrics::compute(2_500.0, 600.0, 0.0, 100.0, 1.0);
+        assert_eq!(metrics.thumb_size, 500.0 / 2_400.0);
+
+        // At 200% the content is twice as long on screen, frozen rows too
+        let metrics = ScrollbarMetrics::compute(2_500.0, 600.0, 4_800.0, 100.0, 2.0);
+        assert_eq!(metrics.thumb_size, 400.0 / 4_800.0);
+        assert_eq!(metrics.thumb_start, 4_400.0 / 4_800.0);
+    }
+}
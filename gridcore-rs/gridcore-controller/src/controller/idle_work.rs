@@ -0,0 +1,237 @@
+//! Background work done while the host is idle.
+//!
+//! Subsystems queue [`IdleTask`]s with a priority instead of warming their
+//! caches on first use. The host drives the queue from `requestIdleCallback`
+//! or a timer through [`SpreadsheetController::run_idle_work`], giving each
+//! slice a time budget. Edits and scrolls cancel or promote the tasks they
+//! make stale, see [`IdleWorkQueue::invalidate`].
+//!
+//! [`SpreadsheetController::run_idle_work`]: crate::controller::SpreadsheetController::run_idle_work
+
+#[cfg(feature = "perf")]
+use crate::perf::{IDLE_CANCELLATIONS, IDLE_ITEMS_PROCESSED, IDLE_WORK_TIME};
+#[cfg(feature = "perf")]
+use metrics::{counter, histogram};
+
+/// How soon a task should run; higher runs first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IdlePriority {
+    Low,
+    Normal,
+    High,
+}
+
+/// Kinds of work the queue runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IdleTask {
+    /// Refill the viewport cache margin around the visible cells
+    PrefetchViewport,
+    /// Measure the text of cached cells that has not been measured yet.
+    /// Long lists are measured over several slices.
+    MeasureText,
+}
+
+/// Something that happened since tasks were queued
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleInvalidation {
+    /// The viewport moved: text picked for measuring may be off screen and
+    /// the cache margin is needed sooner
+    Scroll,
+    /// Cells changed: text picked for measuring may be gone
+    Edit,
+}
+
+/// Counters of the work done by the queue
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IdleStats {
+    /// Tasks run to completion
+    pub processed: u64,
+    /// Tasks dropped before they finished
+    pub cancelled: u64,
+    /// Times a task ran out of budget and was resumed in a later slice
+    pub yielded: u64,
+    /// Milliseconds spent running tasks
+    pub time_ms: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct QueuedTask {
+    task: IdleTask,
+    priority: IdlePriority,
+    /// Order of arrival, which breaks ties between equal priorities
+    sequence: u64,
+}
+
+/// Prioritized, cancellable tasks waiting for idle time
+#[derive(Debug, Default)]
+pub struct IdleWorkQueue {
+    queued: Vec<QueuedTask>,
+    /// Task taken by [`IdleWorkQueue::start`] and not yet finished
+    running: Option<QueuedTask>,
+    next_sequence: u64,
+    stats: IdleStats,
+}
+
+impl IdleWorkQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `task`. A task already waiting keeps its place and takes the
+    /// higher of the two priorities.
+    pub fn enqueue(&mut self, task: IdleTask, priority: IdlePriority) {
+        if let Some(queued) = self.queued.iter_mut().find(|queued| queued.task == task) {
+            queued.priority = queued.priority.max(priority);
+            return;
+        }
+        self.queued.push(QueuedTask {
+            task,
+            priority,
+            sequence: self.next_sequence,
+        });
+        self.next_sequence += 1;
+    }
+
+    /// Drop a waiting task. Returns whether it was queued.
+    pub fn cancel(&mut self, task: IdleTask) -> bool {
+        let before = self.queued.len();
+        self.queued.retain(|queued| queued.task != task);
+        let cancelled = self.queued.len() < before;
+        if cancelled {
+            self.stats.cancelled += 1;
+            #[cfg(feature = "perf")]
+            counter!(IDLE_CANCELLATIONS).increment(1);
+        }
+        cancelled
+    }
+
+    /// Change the priority of a waiting task. Returns whether it was queued.
+    pub fn reprioritize(&mut self, task: IdleTask, priority: IdlePriority) -> bool {
+        match self.queued.iter_mut().find(|queued| queued.task == task) {
+            Some(queued) => {
+                queued.priority = priority;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel or promote the tasks `cause` makes stale. Scrolling makes the
+    /// cache margin urgent and drops text measuring, whose list was taken
+    /// for the old view; edits drop text measuring too.
+    pub fn invalidate(&mut self, cause: IdleInvalidation) {
+        if cause == IdleInvalidation::Scroll {
+            self.reprioritize(IdleTask::PrefetchViewport, IdlePriority::High);
+        }
+        self.cancel(IdleTask::MeasureText);
+    }
+
+    /// Waiting tasks in the order they will run
+    pub fn pending(&self) -> Vec<IdleTask> {
+        let mut queued = self.queued.clone();
+        queued.sort_by_key(|queued| (std::cmp::Reverse(queued.priority), queued.sequence));
+        queued.into_iter().map(|queued| queued.task).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    pub fn stats(&self) -> IdleStats {
+        self.stats
+    }
+
+    /// Take the next task to run: the highest priority, oldest first
+    pub(crate) fn start(&mut self) -> Option<IdleTask> {
+        let index = (0..self.queued.len()).min_by_key(|&i| {
+            let queued = &self.queued[i];
+            (std::cmp::Reverse(queued.priority), queued.sequence)
+        })?;
+        let queued = self.queued.swap_remove(index);
+        self.running = Some(queued);
+        Some(queued.task)
+    }
+
+    /// Record the task taken by [`IdleWorkQueue::start`] after it ran for
+    /// `elapsed_ms`. An unfinished task goes back in its old place, unless
+    /// the same task was queued again meanwhile.
+    pub(crate) fn finish(&mut self, elapsed_ms: f64, done: bool) {
+        let Some(running) = self.running.take() else {
+            return;
+        };
+        self.stats.time_ms += elapsed_ms;
+        #[cfg(feature = "perf")]
+        histogram!(IDLE_WORK_TIME).record(elapsed_ms / 1000.0);
+
+        if done {
+            self.stats.processed += 1;
+            #[cfg(feature = "perf")]
+            counter!(IDLE_ITEMS_PROCESSED).increment(1);
+        } else {
+            self.stats.yielded += 1;
+            if !self.queued.iter().any(|queued| queued.task == running.task) {
+                self.queued.push(running);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_order_and_requeue() {
+        let mut queue = IdleWorkQueue::new();
+        queue.enqueue(IdleTask::MeasureText, IdlePriority::Low);
+        queue.enqueue(IdleTask::PrefetchViewport, IdlePriority::Normal);
+        assert_eq!(
+            queue.pending(),
+            [IdleTask::PrefetchViewport, IdleTask::MeasureText]
+        );
+
+        // Queuing again only ever raises the priority
+        queue.enqueue(IdleTask::MeasureText, IdlePriority::High);
+        queue.enqueue(IdleTask::MeasureText, IdlePriority::Low);
+        assert_eq!(
+            queue.pending(),
+            [IdleTask::MeasureText, IdleTask::PrefetchViewport]
+        );
+
+        // An unfinished task keeps its turn
+        assert_eq!(queue.start(), Some(IdleTask::MeasureText));
+        queue.finish(2.0, false);
+        assert_eq!(queue.start(), Some(IdleTask::MeasureText));
+        queue.finish(1.0, true);
+        assert_eq!(queue.start(), Some(IdleTask::PrefetchViewport));
+        queue.finish(1.0, true);
+        assert!(queue.is_empty());
+        assert_eq!(
+            queue.stats(),
+            IdleStats {
+                processed: 2,
+                cancelled: 0,
+                yielded: 1,
+                time_ms: 4.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalidation_cancels_and_promotes() {
+        let mut queue = IdleWorkQueue::new();
+        queue.enqueue(IdleTask::MeasureText, IdlePriority::Normal);
+        queue.enqueue(IdleTask::PrefetchViewport, IdlePriority::Low);
+
+        queue.invalidate(IdleInvalidation::Scroll);
+        assert_eq!(queue.pending(), [IdleTask::PrefetchViewport]);
+        assert_eq!(queue.stats().cancelled, 1);
+        assert!(queue.reprioritize(IdleTask::PrefetchViewport, IdlePriority::Low));
+        assert!(!queue.reprioritize(IdleTask::MeasureText, IdlePriority::Low));
+
+        // Nothing left to cancel on an edit
+        queue.invalidate(IdleInvalidation::Edit);
+        assert_eq!(queue.stats().cancelled, 1);
+        assert!(!queue.cancel(IdleTask::MeasureText));
+    }
+}
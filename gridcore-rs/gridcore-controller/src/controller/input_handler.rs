@@ -1,4 +1,10 @@
+use crate::behaviors::case_change::{parse_case_keys, CaseChange, CaseKeys};
+use crate::behaviors::quick_totals::QUICK_SUM_KEY;
+use crate::behaviors::visible_cells::SELECT_VISIBLE_KEY;
+use crate::controller::events::ErrorSeverity;
+use crate::controller::ex_commands::ExCommandExecutor;
 use crate::controller::{KeyboardEvent, MouseEvent, SpreadsheetEvent};
+use crate::managers::ErrorSystem;
 use crate::state::{Action, InsertMode, Selection, SelectionType};
 use gridcore_core::{types::CellAddress, Result};
 
@@ -7,6 +13,9 @@ use crate::perf::{KEYBOARD_EVENTS, MOUSE_EVENTS};
 #[cfg(feature = "perf")]
 use metrics::counter;
 
+/// Key that fills the selection down from its top row in visual mode: Ctrl+D
+const FILL_DOWN_KEY: &str = "C-d";
+
 /// Handles all input events for the spreadsheet controller
 pub struct InputHandler<'a> {
     controller: &'a mut super::SpreadsheetController,
@@ -29,6 +38,22 @@ impl<'a> InputHandler<'a> {
             mode
         );
 
+        // While a panel has focus the grid ignores keys, apart from the
+        // ones moving focus within or out of the panel
+        if !self.controller.has_grid_focus() {
+            self.controller.handle_focus_key(&event);
+            return Ok(());
+        }
+
+        // Escape puts a dragged range back before any mode sees it
+        if event.key == "Escape" && self.controller.get_range_drag().is_some() {
+            return self.controller.dispatch_action(Action::CancelRangeDrag);
+        }
+
+        if self.controller.plugins_take_key(&event)? {
+            return Ok(());
+        }
+
         use super::mode::EditorMode;
         match mode {
             EditorMode::Navigation => self.handle_navigation_key(event),
@@ -37,7 +62,22 @@ impl<'a> InputHandler<'a> {
             }
             EditorMode::Command { .. } => self.handle_command_key(event),
             EditorMode::Visual { .. } => self.handle_visual_key(event),
-            EditorMode::Resizing => Ok(()),
+            EditorMode::Resizing => self.handle_resize_key(event),
+        }
+    }
+
+    /// `+`/`-` grow or shrink by 5 pixels times a count, the arrows along
+    /// the resized axis move to the next line, Enter commits and Escape
+    /// puts the sizes back
+    fn handle_resize_key(&mut self, event: KeyboardEvent) -> Result<()> {
+        let state = self.controller.get_ui_state();
+        let action = self
+            .controller
+            .resize_behavior
+            .handle_key(&event.key, &state)?;
+        match action {
+            Some(action) => self.controller.dispatch_action(action),
+            None => Ok(()),
         }
     }
 
@@ -50,6 +90,54 @@ impl<'a> InputHandler<'a> {
             current_cursor
         );
 
+        // Continue a pending multi-key command such as `]p` or `gU3j`
+        if let Some(prefix) = self.controller.pending_key.take() {
+            if prefix.starts_with('g') {
+                let keys = format!("{}{}", prefix, event.key);
+                return match parse_case_keys(&keys) {
+                    CaseKeys::Pending => {
+                        self.controller.pending_key = Some(keys);
+                        Ok(())
+                    }
+                    CaseKeys::Complete(command) => self.controller.apply_case_command(command),
+                    CaseKeys::Invalid => Ok(()),
+                };
+            }
+            return match (prefix.as_str(), event.key.as_str()) {
+                ("]", "p") => self.controller.dispatch_action(Action::TracePrecedents),
+                ("]", "d") => self.controller.dispatch_action(Action::TraceDependents),
+                ("]", "w") => self.controller.dispatch_action(Action::NextLintWarning),
+                ("[", "w") => self.controller.dispatch_action(Action::PreviousLintWarning),
+                ("z", key) => self.handle_fold_key(key),
+                _ => Ok(()),
+            };
+        }
+
+        // Custom bindings take precedence over the built-in keys
+        if let Some(action) = self.controller.keymap.lookup(&event).cloned() {
+            return self.controller.dispatch_action(action);
+        }
+        if event.to_vim_notation() == QUICK_SUM_KEY {
+            return self.controller.dispatch_action(Action::QuickSum);
+        }
+        if event.to_vim_notation() == SELECT_VISIBLE_KEY {
+            return self.controller.dispatch_action(Action::SelectVisibleCells);
+        }
+
+        // Without vim, every printable key starts editing like a regular spreadsheet
+        if !self.controller.vim_enabled && event.is_printable() {
+            return self.start_editing_with_char(event.key);
+        }
+
+        let plain = !event.ctrl && !event.alt && !event.meta;
+        if plain && matches!(event.key.as_str(), "]" | "[" | "g" | "z") {
+            self.controller.pending_key = Some(event.key);
+            return Ok(());
+        }
+        if plain && event.key == "." {
+            return self.controller.repeat_case_command();
+        }
+
         // Check if this is a vim navigation key that should start editing
         if VimHandler::should_handle_navigation_key(&event.key) {
             match event.key.as_str() {
@@ -149,6 +237,9 @@ impl<'a> InputHandler<'a> {
                 "Tab" => self.handle_tab_navigation(event.shift, current_cursor),
 
                 // Cell operations
+                "Delete" | "Backspace" if self.controller.selection.is_some() => {
+                    self.controller.dispatch_action(Action::ClearSelectedCells)
+                }
                 "Delete" | "Backspace" => self.handle_delete_cell(current_cursor),
 
                 // Escape does nothing in navigation mode
@@ -157,15 +248,7 @@ impl<'a> InputHandler<'a> {
                 _ => {
                     // Check if this is a single printable character that should start editing
                     if event.key.len() == 1 && !event.ctrl && !event.alt && !event.meta {
-                        log::debug!("Starting edit mode with typed character: '{}'", event.key);
-                        use super::mode::{CellEditMode, EditorMode};
-                        self.controller.set_mode(EditorMode::CellEditing {
-                            value: event.key.clone(),
-                            cursor_pos: 1,
-                            mode: CellEditMode::Insert(InsertMode::I),
-                            visual_anchor: None,
-                        });
-                        Ok(())
+                        self.start_editing_with_char(event.key)
                     } else {
                         log::debug!("Unhandled navigation key: '{}'", event.key);
                         Ok(())
@@ -175,22 +258,45 @@ impl<'a> InputHandler<'a> {
         }
     }
 
+    fn start_editing_with_char(&mut self, key: String) -> Result<()> {
+        log::debug!("Starting edit mode with typed character: '{}'", key);
+        use super::mode::{CellEditMode, EditorMode};
+        let cursor_pos = key.len();
+        self.controller.set_mode(EditorMode::CellEditing {
+            value: key,
+            cursor_pos,
+            mode: CellEditMode::Insert(InsertMode::I),
+            visual_anchor: None,
+        });
+        Ok(())
+    }
+
+    /// Last valid column and row of the grid
+    fn grid_limits(&self) -> (u32, u32) {
+        let config = &self.controller.config;
+        (
+            config.total_cols.saturating_sub(1) as u32,
+            config.total_rows.saturating_sub(1) as u32,
+        )
+    }
+
     fn handle_tab_navigation(&mut self, shift: bool, current_cursor: CellAddress) -> Result<()> {
+        let (max_col, max_row) = self.grid_limits();
         let new_cursor = if shift {
             // Shift+Tab moves left, then wraps to previous row
             if current_cursor.col > 0 {
                 CellAddress::new(current_cursor.col - 1, current_cursor.row)
             } else if current_cursor.row > 0 {
-                // Wrap to end of previous row (assuming max 256 columns)
-                CellAddress::new(255, current_cursor.row - 1)
+                // Wrap to end of previous row
+                CellAddress::new(max_col, current_cursor.row - 1)
             } else {
                 return Ok(());
             }
         } else {
             // Tab moves right, then wraps to next row
-            if current_cursor.col < 255 {
+            if current_cursor.col < max_col {
                 CellAddress::new(current_cursor.col + 1, current_cursor.row)
-            } else if current_cursor.row < 9999 {
+            } else if current_cursor.row < max_row {
                 // Wrap to start of next row
                 CellAddress::new(0, current_cursor.row + 1)
             } else {
@@ -210,13 +316,15 @@ impl<'a> InputHandler<'a> {
             current_cursor
         );
         self.controller.facade.set_cell_value(&current_cursor, "")?;
+        self.controller.refresh_cached_cells(&[current_cursor]);
         self.controller
             .event_dispatcher
             .dispatch(&SpreadsheetEvent::CellEditCompleted {
                 address: current_cursor,
-                value: String::new(),
+                value: "".into(),
             });
         self.controller.update_formula_bar_from_cursor();
+        self.controller.refresh_watch_list();
         Ok(())
     }
 
@@ -246,12 +354,23 @@ impl<'a> InputHandler<'a> {
                 self.controller
                     .dispatch_action(Action::UpdateCommandValue { value: new_value })
             } else if event.key == "Enter" {
+                let command = value.clone();
                 self.controller
                     .event_dispatcher
                     .dispatch(&SpreadsheetEvent::CommandExecuted {
-                        command: value.clone(),
+                        command: command.clone(),
                     });
-                self.controller.dispatch_action(Action::ExitCommandMode)
+                self.controller.dispatch_action(Action::ExitCommandMode)?;
+
+                if let Err(e) = ExCommandExecutor::new(self.controller).execute(&command) {
+                    self.controller
+                        .add_error(ErrorSystem::format_error(&e), ErrorSeverity::Error);
+                }
+                Ok(())
+            } else if event.key == "Tab" {
+                let completed = self.controller.complete_command(value);
+                self.controller
+                    .dispatch_action(Action::UpdateCommandValue { value: completed })
             } else if event.key == "Backspace" && !value.is_empty() {
                 let mut new_value = value.clone();
                 new_value.pop();
@@ -265,10 +384,97 @@ impl<'a> InputHandler<'a> {
         }
     }
 
+    /// The key after `z` in navigation: `zj`/`zk` move to the next or
+    /// previous fold, `zo`/`zO` open and `zc`/`zC` close the folds at the
+    /// cursor, `za` toggles, `zd` deletes, and `zR`/`zM` open or close
+    /// every fold
+    fn handle_fold_key(&mut self, key: &str) -> Result<()> {
+        let row = self.controller.cursor().row;
+        let action = match key {
+            "j" => Action::NextFold,
+            "k" => Action::PreviousFold,
+            "o" | "O" | "c" | "C" | "R" | "M" => {
+                let (start_row, end_row) = if matches!(key, "R" | "M") {
+                    (0, u32::MAX)
+                } else {
+                    (row, row)
+                };
+                let all = key != "o" && key != "c";
+                if matches!(key, "o" | "O" | "R") {
+                    Action::OpenFolds {
+                        start_row,
+                        end_row,
+                        all,
+                    }
+                } else {
+                    Action::CloseFolds {
+                        start_row,
+                        end_row,
+                        all,
+                    }
+                }
+            }
+            "a" => Action::ToggleFold { row },
+            "d" => Action::DeleteFold { row },
+            _ => return Ok(()),
+        };
+        self.controller.dispatch_action(action)
+    }
+
     fn handle_visual_key(&mut self, event: KeyboardEvent) -> Result<()> {
         use super::mode::EditorMode;
 
+        // `zf` folds the rows of the selection
+        let pending = self.controller.pending_key.take();
+        if pending.as_deref() == Some("z") {
+            if event.key != "f" {
+                return Ok(());
+            }
+            let (start_row, end_row) = self.controller.selected_rows();
+            self.controller.set_mode(EditorMode::Navigation);
+            self.controller.set_selection(None);
+            self.controller
+                .dispatch_action(Action::ExitSpreadsheetVisualMode)?;
+            return self
+                .controller
+                .dispatch_action(Action::CreateFold { start_row, end_row });
+        }
+        if event.key == "z" {
+            self.controller.pending_key = Some(event.key);
+            return Ok(());
+        }
+
+        // `U`, `u` and `~`, optionally after `g`, change the case of the selection
+        let after_g = pending.is_some_and(|key| key == "g");
+        let mut chars = event.key.chars();
+        if let (Some(key), None) = (chars.next(), chars.next()) {
+            if let Some(change) = CaseChange::from_key(key) {
+                return self.controller.change_case_in_selection(change);
+            }
+        }
+        if event.key == "g" && !after_g {
+            self.controller.pending_key = Some(event.key);
+            return Ok(());
+        }
+
+        if event.to_vim_notation() == QUICK_SUM_KEY {
+            return self.controller.dispatch_action(Action::QuickSum);
+        }
+        if event.to_vim_notation() == SELECT_VISIBLE_KEY {
+            return self.controller.dispatch_action(Action::SelectVisibleCells);
+        }
+        if event.to_vim_notation() == FILL_DOWN_KEY {
+            return self.controller.dispatch_action(Action::FillDown);
+        }
+
         match event.key.as_str() {
+            "." => match self.controller.last_case_command.as_ref() {
+                Some(command) => {
+                    let change = command.change;
+                    self.controller.change_case_in_selection(change)
+                }
+                None => Ok(()),
+            },
             "Escape" => {
                 // Exit visual mode - clear selection and return to navigation
                 self.controller.set_mode(EditorMode::Navigation);
@@ -278,6 +484,11 @@ impl<'a> InputHandler<'a> {
                     .dispatch_action(Action::ExitSpreadsheetVisualMode)
             }
 
+            // Clear the visible cells of the selection
+            "Delete" | "Backspace" | "d" | "x" => {
+                self.controller.dispatch_action(Action::ClearSelectedCells)
+            }
+
             // Movement keys - extend selection
             "h" | "ArrowLeft" | "j" | "ArrowDown" | "k" | "ArrowUp" | "l" | "ArrowRight" => {
                 // Calculate new cursor position
@@ -430,8 +641,9 @@ impl<'a> InputHandler<'a> {
 
     fn move_cursor(&mut self, delta_col: i32, delta_row: i32) -> Result<()> {
         let current = self.controller.cursor();
-        let new_col = (current.col as i32 + delta_col).max(0) as u32;
-        let new_row = (current.row as i32 + delta_row).max(0) as u32;
+        let (max_col, max_row) = self.grid_limits();
+        let new_col = (current.col as i32 + delta_col).clamp(0, max_col as i32) as u32;
+        let new_row = (current.row as i32 + delta_row).clamp(0, max_row as i32) as u32;
         let new_cursor = CellAddress::new(new_col, new_row);
 
         log::debug!(
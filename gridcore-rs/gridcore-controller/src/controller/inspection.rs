@@ -0,0 +1,304 @@
+//! What hovering a cell shows: its text, what it holds, why it errs and
+//! what linting found, gathered into one [`CellInspection`] the tooltip
+//! renders. Inspections are cached until the workbook or the lint findings
+//! change, and [`HoverDebounce`] keeps the pointer from asking for more
+//! than one every [`HOVER_INTERVAL_MS`].
+
+use super::viewport_cache::DisplayCell;
+use super::SpreadsheetController;
+use gridcore_core::domain::{CellFormat, NumberFormat};
+use gridcore_core::lint::LintFinding;
+use gridcore_core::types::{CellAddress, CellValue};
+use rustc_hash::FxHashMap;
+
+/// Shortest time between two inspections while the pointer moves
+pub const HOVER_INTERVAL_MS: f64 = 100.0;
+
+/// Inspections kept before the cache starts over
+const CACHE_CAPACITY: usize = 256;
+
+/// Everything worth knowing about a cell under the pointer
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellInspection {
+    pub address: CellAddress,
+    /// Text drawn in the cell
+    pub display: String,
+    /// The formula as the editor shows it, or the value as entered
+    pub input: String,
+    pub is_formula: bool,
+    /// The text does not fit its column, or only its first line is drawn
+    pub clipped: bool,
+    pub error: Option<ErrorDetail>,
+    /// Lint findings about the cell, with their suggestions
+    pub findings: Vec<LintFinding>,
+    /// The format that applies, e.g. `Currency ($, 2 decimals)`
+    pub format: Option<String>,
+    /// Named style the cell refers to
+    pub style: Option<String>,
+    /// The value waits for a recalculation
+    pub stale: bool,
+}
+
+impl CellInspection {
+    /// Whether there is nothing to show, as for an empty cell without
+    /// findings
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+            && self.display.is_empty()
+            && self.findings.is_empty()
+            && self.format.is_none()
+            && self.style.is_none()
+    }
+}
+
+/// Why a cell shows an error
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorDetail {
+    /// E.g. `#DIV/0!`
+    pub code: String,
+    pub message: String,
+    /// Cells of a circular reference, e.g. `A1 → B2 → A1`
+    pub cycle: Option<String>,
+}
+
+/// Inspections of the current workbook content, keyed by sheet and cell
+#[derive(Debug, Default)]
+pub(crate) struct InspectionCache {
+    /// Content generation and lint revision the entries were read at
+    generation: (u64, u64),
+    entries: FxHashMap<(String, CellAddress), CellInspection>,
+}
+
+impl SpreadsheetController {
+    /// Inspect the cell at `address` of the active sheet, from the cache
+    /// when nothing changed since it was last inspected
+    pub fn inspect_cell(&mut self, address: &CellAddress) -> CellInspection {
+        let generation = (self.content_generation, self.lint_warnings.revision());
+        let cache = &mut self.inspections;
+        if cache.generation != generation || cache.entries.len() >= CACHE_CAPACITY {
+            cache.generation = generation;
+            cache.entries.clear();
+        }
+        let key = (self.facade.get_active_sheet(), *address);
+        if let Some(inspection) = self.inspections.entries.get(&key) {
+            return inspection.clone();
+        }
+        let inspection = self.read_inspection(address);
+        self.inspections.entries.insert(key, inspection.clone());
+        inspection
+    }
+
+    fn read_inspection(&self, address: &CellAddress) -> CellInspection {
+        let cell = self.facade.get_cell(address);
+        let display = DisplayCell::load(&self.facade, address);
+        let error = cell
+            .as_ref()
+            .and_then(|cell| match cell.get_computed_value() {
+                CellValue::Error(error) => Some(ErrorDetail {
+                    code: error.excel_code().to_string(),
+                    message: error.description(),
+                    cycle: error.cycle_path(),
+                }),
+                _ => None,
+            });
+        let clipped = display.as_ref().is_some_and(|display| {
+            let column = self.viewport_manager.get_column_width(address.col as usize);
+            let first_line = display.text.lines().next().unwrap_or_default();
+            (!display.wrap && display.text.contains('\n'))
+                || self
+                    .text_width(first_line)
+                    .is_some_and(|width| width > column)
+        });
+
+        CellInspection {
+            address: *address,
+            display: display.as_ref().map(|d| d.text.clone()).unwrap_or_default(),
+            input: self.get_cell_display_for_ui(address),
+            is_formula: cell.as_ref().is_some_and(|cell| cell.has_formula()),
+            clipped,
+            error,
+            findings: self.lint_warnings.for_cell(address).cloned().collect(),
+            format: self
+                .facade
+                .get_effective_format(address)
+                .map(|format| format_summary(&format)),
+            style: self.facade.get_cell_style(address),
+            stale: display.is_some_and(|display| display.is_stale),
+        }
+    }
+}
+
+/// A format in words, e.g. `Number (2 decimals, thousands)`
+fn format_summary(format: &CellFormat) -> String {
+    let decimals = |decimals: &u8| match decimals {
+        1 => "1 decimal".to_string(),
+        n => format!("{} decimals", n),
+    };
+    let mut summary = match &format.number_format {
+        NumberFormat::General => "General".to_string(),
+        NumberFormat::Number {
+            decimals: places,
+            thousands,
+        } => {
+            let grouping = if *thousands { ", thousands" } else { "" };
+            format!("Number ({}{})", decimals(places), grouping)
+        }
+        NumberFormat::Percent { decimals: places } => format!("Percent ({})", decimals(places)),
+        NumberFormat::Currency {
+            symbol,
+            decimals: places,
+        } => format!("Currency ({}, {})", symbol, decimals(places)),
+        NumberFormat::Accounting {
+            symbol,
+            decimals: places,
+        } => format!("Accounting ({}, {})", symbol, decimals(places)),
+        NumberFormat::Scientific { decimals: places } => {
+            format!("Scientific ({})", decimals(places))
+        }
+        NumberFormat::Unit {
+            unit,
+            decimals: places,
+        } => format!("Unit ({}, {})", unit, decimals(places)),
+        NumberFormat::Date { pattern } => format!("Date ({})", pattern),
+        NumberFormat::Text => "Text".to_string(),
+    };
+    if format.wrap_text {
+        summary.push_str(", wrapped");
+    }
+    summary
+}
+
+/// Decides when moving the pointer inspects the cell under it: only once
+/// it is over a different cell, and no sooner than [`HOVER_INTERVAL_MS`]
+/// after the last inspection. A cell reached too soon waits as pending
+/// until [`Self::poll`] finds it due.
+#[derive(Debug, Clone, Default)]
+pub struct HoverDebounce {
+    /// Cell shown last, `None` once the pointer left the cells
+    shown: Option<CellAddress>,
+    last_inspection_ms: Option<f64>,
+    pending: Option<CellAddress>,
+}
+
+impl HoverDebounce {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pointer is over `cell`, or over no cell, at `now_ms`. Returns
+    /// the cell to inspect now, if any.
+    pub fn hover(&mut self, cell: Option<CellAddress>, now_ms: f64) -> Option<CellAddress> {
+        let Some(cell) = cell else {
+            self.shown = None;
+            self.pending = None;
+            return None;
+        };
+        if self.shown == Some(cell) {
+            self.pending = None;
+            return None;
+        }
+        self.pending = Some(cell);
+        self.poll(now_ms)
+    }
+
+    /// Milliseconds until the pending cell is due, if one waits
+    pub fn pending_delay(&self, now_ms: f64) -> Option<f64> {
+        self.pending?;
+        let next = self
+            .last_inspection_ms
+            .map_or(now_ms, |last| last + HOVER_INTERVAL_MS);
+        Some((next - now_ms).max(0.0))
+    }
+
+    /// The pending cell, once it is due
+    pub fn poll(&mut self, now_ms: f64) -> Option<CellAddress> {
+        if self.pending_delay(now_ms)? > 0.0 {
+            return None;
+        }
+        let cell = self.pending.take()?;
+        self.shown = Some(cell);
+        self.last_inspection_ms = Some(now_ms);
+        Some(cell)
+    }
+
+    /// The cell whose inspection is shown
+    pub fn shown(&self) -> Option<CellAddress> {
+        self.shown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::SpreadsheetController;
+
+    #[test]
+    fn test_inspection_gathers_everything_about_a_cell() {
+        let mut ctrl = SpreadsheetController::new();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        ctrl.write_cell(&a1, "0").unwrap();
+        ctrl.write_cell(&b1, "=10/A1").unwrap();
+        ctrl.facade()
+            .set_cell_format(&b1, CellFormat::currency("$", 2))
+            .unwrap();
+
+        let inspection = ctrl.inspect_cell(&b1);
+        assert_eq!(inspection.input, "=10/A1");
+        assert!(inspection.is_formula);
+        assert_eq!(inspection.display, "#DIV/0!");
+        let error = inspection.error.unwrap();
+        assert_eq!(error.code, "#DIV/0!");
+        assert_eq!(error.message, "Division by zero");
+        assert_eq!(
+            inspection.format.as_deref(),
+            Some("Currency ($, 2 decimals)")
+        );
+        assert!(!inspection.stale);
+
+        assert!(ctrl.inspect_cell(&CellAddress::new(5, 5)).is_empty());
+    }
+
+    #[test]
+    fn test_edits_invalidate_cached_inspections() {
+        let mut ctrl = SpreadsheetController::new();
+        let a1 = CellAddress::new(0, 0);
+        ctrl.write_cell(&a1, "1").unwrap();
+        assert_eq!(ctrl.inspect_cell(&a1).display, "1");
+        // Unchanged, the cached inspection is returned
+        assert_eq!(ctrl.inspect_cell(&a1).display, "1");
+
+        ctrl.write_cell(&a1, "2").unwrap();
+        assert_eq!(ctrl.inspect_cell(&a1).display, "2");
+    }
+
+    #[test]
+    fn test_hover_inspects_at_most_once_per_interval() {
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        let c1 = CellAddress::new(2, 0);
+        let mut debounce = HoverDebounce::new();
+
+        assert_eq!(debounce.hover(Some(a1), 0.0), Some(a1));
+        // Moving within the same cell does nothing
+        assert_eq!(debounce.hover(Some(a1), 10.0), None);
+        assert_eq!(debounce.pending_delay(10.0), None);
+
+        // Too soon: the new cell waits, and the latest one wins
+        assert_eq!(debounce.hover(Some(b1), 40.0), None);
+        assert_eq!(debounce.hover(Some(c1), 60.0), None);
+        assert_eq!(debounce.pending_delay(60.0), Some(40.0));
+        assert_eq!(debounce.poll(99.0), None);
+        assert_eq!(debounce.poll(100.0), Some(c1));
+        assert_eq!(debounce.shown(), Some(c1));
+
+        // Returning to the shown cell drops the pending one
+        assert_eq!(debounce.hover(Some(b1), 150.0), None);
+        assert_eq!(debounce.hover(Some(c1), 160.0), None);
+        assert_eq!(debounce.poll(300.0), None);
+
+        // Leaving the cells forgets the shown one
+        assert_eq!(debounce.hover(None, 310.0), None);
+        assert_eq!(debounce.hover(Some(c1), 320.0), Some(c1));
+    }
+}
@@ -0,0 +1,45 @@
+use crate::controller::KeyboardEvent;
+use crate::state::Action;
+use rustc_hash::FxHashMap;
+
+/// Custom navigation-mode key bindings, consulted before the built-in keys.
+///
+/// Keys use the notation produced by [`KeyboardEvent::to_vim_notation`],
+/// e.g. `"g"`, `"C-d"` or `"Space"`.
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: FxHashMap<String, Action>,
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind a key, replacing any existing binding for it
+    pub fn bind(mut self, key: impl Into<String>, action: Action) -> Self {
+        self.bindings.insert(key.into(), action);
+        self
+    }
+
+    pub fn unbind(&mut self, key: &str) -> Option<Action> {
+        self.bindings.remove(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Action> {
+        self.bindings.get(key)
+    }
+
+    /// The action bound to a keyboard event, if any
+    pub fn lookup(&self, event: &KeyboardEvent) -> Option<&Action> {
+        self.get(&event.to_vim_notation())
+    }
+
+    pub fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
@@ -0,0 +1,161 @@
+//! Coordinate mapping for the sheet minimap
+//!
+//! The minimap stretches the covered part of the sheet over its canvas, with
+//! independent horizontal and vertical scales, so a narrow strip can still
+//! show a tall sheet.
+
+use super::viewport::ViewportBounds;
+use gridcore_core::repository::{DensityBlock, DensityMap};
+use gridcore_core::types::CellAddress;
+use serde::{Deserialize, Serialize};
+
+/// A rectangle in minimap pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MinimapRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// How many rows and columns a minimap of a given size shows
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MinimapGeometry {
+    pub width: f64,
+    pub height: f64,
+    pub rows: u32,
+    pub cols: u32,
+}
+
+impl MinimapGeometry {
+    /// Geometry that shows both the used range and the viewport
+    pub fn covering(width: f64, height: f64, map: &DensityMap, viewport: &ViewportBounds) -> Self {
+        let (last_col, last_row) = map
+            .extent
+            .map(|extent| (extent.col, extent.row))
+            .unwrap_or((0, 0));
+        Self {
+            width,
+            height,
+            rows: last_row.max(viewport.end_row as u32) + 1,
+            cols: last_col.max(viewport.end_col as u32) + 1,
+        }
+    }
+
+    fn col_width(&self) -> f64 {
+        self.width / self.cols.max(1) as f64
+    }
+
+    fn row_height(&self) -> f64 {
+        self.height / self.rows.max(1) as f64
+    }
+
+    /// Cell under a minimap point. Points outside the minimap are clamped to
+    /// its edge, so dragging past it keeps tracking the nearest cell.
+    pub fn cell_at(&self, x: f64, y: f64) -> CellAddress {
+        let col = (x.max(0.0) / self.col_width()) as u32;
+        let row = (y.max(0.0) / self.row_height()) as u32;
+        CellAddress::new(
+            col.min(self.cols.saturating_sub(1)),
+            row.min(self.rows.saturating_sub(1)),
+        )
+    }
+
+    /// Rectangle covering the cells from `start` to `end` inclusive
+    pub fn cells_rect(&self, start: CellAddress, end: CellAddress) -> MinimapRect {
+        let (col_width, row_height) = (self.col_width(), self.row_height());
+        MinimapRect {
+            x: start.col as f64 * col_width,
+            y: start.row as f64 * row_height,
+            width: (end.col.saturating_sub(start.col) + 1) as f64 * col_width,
+            height: (end.row.saturating_sub(start.row) + 1) as f64 * row_height,
+        }
+    }
+
+    /// Outline of the visible part of the sheet
+    pub fn viewport_rect(&self, bounds: &ViewportBounds) -> MinimapRect {
+        self.cells_rect(
+            CellAddress::new(bounds.start_col as u32, bounds.start_row as u32),
+            CellAddress::new(bounds.end_col as u32, bounds.end_row as u32),
+        )
+    }
+
+    /// Area of one density block
+    pub fn block_rect(&self, block: &DensityBlock, block_size: u32) -> MinimapRect {
+        let start = CellAddress::new(block.col * block_size, block.row * block_size);
+        let end = CellAddress::new(
+            start.col + block_size.saturating_sub(1),
+            start.row + block_size.saturating_sub(1),
+        );
+        self.cells_rect(start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(
+        start_row: usize,
+        end_row: usize,
+        start_col: usize,
+        end_col: usize,
+    ) -> ViewportBounds {
+        ViewportBounds {
+            start_row,
+            end_row,
+            start_col,
+            end_col,
+        }
+    }
+
+    #[test]
+    fn test_click_maps_to_covered_cell() {
+        let map = DensityMap {
+            block_size: 10,
+            blocks: Vec::new(),
+            extent: Some(CellAddress::new(19, 999)),
+        };
+        let geometry = MinimapGeometry::covering(100.0, 500.0, &map, &bounds(0, 29, 0, 9));
+        assert_eq!((geometry.cols, geometry.rows), (20, 1000));
+
+        assert_eq!(geometry.cell_at(0.0, 0.0), CellAddress::new(0, 0));
+        assert_eq!(geometry.cell_at(52.0, 250.0), CellAddress::new(10, 500));
+        assert_eq!(geometry.cell_at(99.9, 499.9), CellAddress::new(19, 999));
+        // Dragging past the edges keeps to the last cell
+        assert_eq!(geometry.cell_at(-5.0, 900.0), CellAddress::new(0, 999));
+
+        let rect = geometry.viewport_rect(&bounds(100, 149, 0, 9));
+        assert_eq!(
+            rect,
+            MinimapRect {
+                x: 0.0,
+                y: 50.0,
+                width: 50.0,
+                height: 25.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_viewport_beyond_data_extends_coverage() {
+        let geometry =
+            MinimapGeometry::covering(80.0, 80.0, &DensityMap::default(), &bounds(0, 39, 0, 7));
+        assert_eq!((geometry.cols, geometry.rows), (8, 40));
+        let block = DensityBlock {
+            row: 1,
+            col: 0,
+            count: 3,
+            kind: gridcore_core::repository::CellKind::Text,
+        };
+        assert_eq!(
+            geometry.block_rect(&block, 4),
+            MinimapRect {
+                x: 0.0,
+                y: 8.0,
+                width: 40.0,
+                height: 8.0
+            }
+        );
+    }
+}
@@ -1,10 +1,29 @@
+pub(crate) mod axis_sizes;
+pub mod builder;
+pub mod capabilities;
 pub mod cell_editor;
+pub mod concerns;
+pub mod conditional;
+pub mod edit_guard;
+pub mod entry_navigation;
 pub mod events;
+pub mod ex_commands;
+pub mod focus;
 pub mod formula_bar;
+pub mod grid_extent;
+pub mod idle_work;
 pub mod input_handler;
+pub mod inspection;
+pub mod keymap;
+pub mod minimap;
 pub mod mode;
+pub mod plugins;
+pub mod scroll_accumulator;
 pub mod spreadsheet;
+pub mod strict_entry;
+pub mod text_widths;
 pub mod viewport;
+pub mod viewport_cache;
 pub mod vim_handler;
 
 // New modular organization
@@ -14,14 +33,31 @@ pub mod sheets;
 #[cfg(test)]
 mod tests;
 
+pub use builder::SpreadsheetControllerBuilder;
+pub use capabilities::Capabilities;
+pub use concerns::{ConcernTracker, Concerns};
+pub use edit_guard::{EditConflictPolicy, EditGuard};
+pub use entry_navigation::{CommitKey, EnterDirection, EntryNavigation};
 pub use event_handling::EventHandling;
-pub use events::{EventDispatcher, KeyboardEvent, MouseEvent, SpreadsheetEvent};
+pub use events::{EventDispatcher, EventText, KeyboardEvent, MouseEvent, SpreadsheetEvent};
+pub use focus::{FocusManager, FocusTarget};
+pub use grid_extent::{GridExtent, ScrollbarMetrics};
+pub use idle_work::{IdleInvalidation, IdlePriority, IdleStats, IdleTask, IdleWorkQueue};
+pub use inspection::{CellInspection, ErrorDetail, HoverDebounce, HOVER_INTERVAL_MS};
+pub use keymap::Keymap;
+pub use minimap::{MinimapGeometry, MinimapRect};
 pub use mode::EditorMode;
+pub use plugins::{ActionVerdict, BehaviorPlugin, PluginContext, PluginRegistry};
+pub use scroll_accumulator::{ScrollAccumulator, ScrollDelta};
 pub use sheets::SheetManagement;
 pub use spreadsheet::SpreadsheetController;
+pub use strict_entry::BlockedEntry;
+pub use text_widths::{TextMeasurer, TextWidths};
 pub use viewport::{
     CellPosition, GridConfiguration, ScrollPosition, ViewportBounds, ViewportManager,
+    MIN_ROW_HEIGHT,
 };
+pub use viewport_cache::{CacheStats, DisplayCell, ViewportCache};
 
 // Column label utility functions (previously in utils.rs)
 
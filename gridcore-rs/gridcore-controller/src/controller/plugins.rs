@@ -0,0 +1,291 @@
+//! Behaviors embedders add to the controller.
+//!
+//! A [`BehaviorPlugin`] can take keys before the default handling, observe,
+//! veto or rewrite actions, and add ex commands. Plugins run in priority
+//! order and can be turned off by name. They never own the controller:
+//! every hook gets a [`PluginContext`] to read state through and to queue
+//! actions and status messages, which are applied once the hook returns.
+
+use super::{EditorMode, KeyboardEvent, SpreadsheetController};
+use crate::controller::events::ErrorSeverity;
+use crate::state::{Action, Selection};
+use gridcore_core::types::CellAddress;
+use gridcore_core::{Result, SpreadsheetFacade};
+
+/// What happens to an action after a plugin has seen it
+#[derive(Debug, Clone)]
+pub enum ActionVerdict {
+    /// Go on with this action, the original or a replacement
+    Continue(Action),
+    /// Drop the action and tell the user why
+    Veto(String),
+}
+
+pub trait BehaviorPlugin {
+    /// Unique name, used to enable and disable the plugin
+    fn name(&self) -> &str;
+
+    /// Plugins with a higher priority see keys and actions first
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// Return `true` to consume `event` before the controller handles it
+    fn handle_key(&mut self, _event: &KeyboardEvent, _ctx: &mut PluginContext) -> bool {
+        false
+    }
+
+    /// Called before an action is applied, including edits committed from
+    /// the cell editor, which arrive as [`Action::SubmitCellEdit`]
+    fn before_action(&mut self, action: Action, _ctx: &mut PluginContext) -> ActionVerdict {
+        ActionVerdict::Continue(action)
+    }
+
+    /// Called after an action was applied
+    fn after_action(&mut self, _action: &Action, _ctx: &mut PluginContext) {}
+
+    /// Names of the ex commands this plugin adds
+    fn commands(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Run one of [`Self::commands`] with the rest of the command line
+    fn execute_command(
+        &mut self,
+        _command: &str,
+        _args: &str,
+        _ctx: &mut PluginContext,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The view of the controller a plugin hook gets
+pub struct PluginContext<'a> {
+    controller: &'a SpreadsheetController,
+    actions: Vec<Action>,
+    messages: Vec<String>,
+}
+
+impl<'a> PluginContext<'a> {
+    fn new(controller: &'a SpreadsheetController) -> Self {
+        Self {
+            controller,
+            actions: Vec::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn cursor(&self) -> CellAddress {
+        self.controller.cursor()
+    }
+
+    pub fn mode(&self) -> &EditorMode {
+        self.controller.get_mode()
+    }
+
+    pub fn selection(&self) -> Option<&Selection> {
+        self.controller.get_selection()
+    }
+
+    /// A cell as the editor would show it
+    pub fn cell_text(&self, address: &CellAddress) -> String {
+        self.controller.get_cell_display_for_ui(address)
+    }
+
+    pub fn facade(&self) -> &SpreadsheetFacade {
+        self.controller.facade()
+    }
+
+    /// Dispatch `action` once the hook returns
+    pub fn dispatch(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    /// Show `message` in the status area
+    pub fn post_status(&mut self, message: impl Into<String>) {
+        self.messages.push(message.into());
+    }
+}
+
+struct Registered {
+    plugin: Box<dyn BehaviorPlugin>,
+    enabled: bool,
+}
+
+/// Registered plugins, highest priority first
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Registered>,
+}
+
+impl PluginRegistry {
+    /// Add a plugin after the ones with the same or a higher priority
+    pub fn register(&mut self, plugin: Box<dyn BehaviorPlugin>) {
+        let priority = plugin.priority();
+        let index = self
+            .plugins
+            .iter()
+            .position(|registered| registered.plugin.priority() < priority)
+            .unwrap_or(self.plugins.len());
+        self.plugins.insert(
+            index,
+            Registered {
+                plugin,
+                enabled: true,
+            },
+        );
+    }
+
+    /// Turn a plugin on or off, `false` if no plugin has that name
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.plugins.iter_mut().find(|r| r.plugin.name() == name) {
+            Some(registered) => {
+                registered.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> Option<bool> {
+        self.plugins
+            .iter()
+            .find(|r| r.plugin.name() == name)
+            .map(|r| r.enabled)
+    }
+
+    /// Names of all plugins in the order they run
+    pub fn names(&self) -> Vec<String> {
+        self.plugins
+            .iter()
+            .map(|r| r.plugin.name().to_string())
+            .collect()
+    }
+
+    /// Ex commands of the enabled plugins
+    pub fn commands(&self) -> Vec<String> {
+        self.plugins
+            .iter()
+            .filter(|r| r.enabled)
+            .flat_map(|r| r.plugin.commands())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    fn enabled(&mut self) -> impl Iterator<Item = &mut Box<dyn BehaviorPlugin>> {
+        self.plugins
+            .iter_mut()
+            .filter(|r| r.enabled)
+            .map(|r| &mut r.plugin)
+    }
+}
+
+impl SpreadsheetController {
+    /// Run `hook` with the plugins taken out of the controller, then apply
+    /// what the plugins queued on the context
+    fn with_plugins<R>(
+        &mut self,
+        hook: impl FnOnce(&mut PluginRegistry, &mut PluginContext) -> R,
+    ) -> Result<R> {
+        let mut registry = std::mem::take(&mut self.plugins);
+        let mut ctx = PluginContext::new(self);
+        let result = hook(&mut registry, &mut ctx);
+        let PluginContext {
+            actions, messages, ..
+        } = ctx;
+
+        // Keep plugins registered by a hook's own actions
+        let added = std::mem::replace(&mut self.plugins, registry);
+        for registered in added.plugins {
+            self.plugins.register(registered.plugin);
+        }
+        for message in messages {
+            self.add_error(message, ErrorSeverity::Info);
+        }
+        for action in actions {
+            self.dispatch_action(action)?;
+        }
+        Ok(result)
+    }
+
+    /// Offer a key to the plugins, `true` if one consumed it
+    pub(super) fn plugins_take_key(&mut self, event: &KeyboardEvent) -> Result<bool> {
+        if self.plugins.is_empty() {
+            return Ok(false);
+        }
+        self.with_plugins(|registry, ctx| {
+            registry
+                .enabled()
+                .any(|plugin| plugin.handle_key(event, ctx))
+        })
+    }
+
+    /// Pass an action through the plugins, `None` if one vetoed it
+    pub(super) fn plugins_before(&mut self, action: Action) -> Result<Option<Action>> {
+        if self.plugins.is_empty() {
+            return Ok(Some(action));
+        }
+        let verdict = self.with_plugins(|registry, ctx| {
+            let mut action = action;
+            for plugin in registry.enabled() {
+                match plugin.before_action(action, ctx) {
+                    ActionVerdict::Continue(next) => action = next,
+                    veto => return veto,
+                }
+            }
+            ActionVerdict::Continue(action)
+        })?;
+        match verdict {
+            ActionVerdict::Continue(action) => Ok(Some(action)),
+            ActionVerdict::Veto(reason) => {
+                self.add_error(reason, ErrorSeverity::Warning);
+                Ok(None)
+            }
+        }
+    }
+
+    pub(super) fn plugins_after(&mut self, action: &Action) -> Result<()> {
+        if self.plugins.is_empty() {
+            return Ok(());
+        }
+        self.with_plugins(|registry, ctx| {
+            for plugin in registry.enabled() {
+                plugin.after_action(action, ctx);
+            }
+        })
+    }
+
+    /// Run a plugin's ex command, `false` if no enabled plugin has it
+    pub(super) fn run_plugin_command(&mut self, command: &str, args: &str) -> Result<bool> {
+        if self.plugins.is_empty() {
+            return Ok(false);
+        }
+        self.with_plugins(|registry, ctx| {
+            match registry
+                .enabled()
+                .find(|plugin| plugin.commands().iter().any(|c| c == command))
+            {
+                Some(plugin) => plugin.execute_command(command, args, ctx).map(|_| true),
+                None => Ok(false),
+            }
+        })?
+    }
+
+    /// Add a plugin; it runs before plugins of a lower priority
+    pub fn register_plugin(&mut self, plugin: Box<dyn BehaviorPlugin>) {
+        self.plugins.register(plugin);
+    }
+
+    /// Turn a plugin on or off by name, `false` if there is none by that name
+    pub fn set_plugin_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.plugins.set_enabled(name, enabled)
+    }
+
+    pub fn plugins(&self) -> &PluginRegistry {
+        &self.plugins
+    }
+}
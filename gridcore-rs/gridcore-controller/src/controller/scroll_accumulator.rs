@@ -0,0 +1,139 @@
+//! Wheel scrolling applied once per frame.
+//!
+//! Trackpads send wheel events far faster than frames are drawn. Their
+//! deltas add up in a [`ScrollAccumulator`] and the host applies the net
+//! scroll from its animation frame through
+//! [`SpreadsheetController::apply_pending_scroll`], which moves the view by
+//! whole pixels and dispatches a single `ViewportScrolled` event. The
+//! fraction left over waits for the next frame, so slow scrolls still move.
+//! Keyboard scrolls do not wait, see [`SpreadsheetController::scroll_now`].
+//!
+//! [`SpreadsheetController::apply_pending_scroll`]: crate::controller::SpreadsheetController::apply_pending_scroll
+//! [`SpreadsheetController::scroll_now`]: crate::controller::SpreadsheetController::scroll_now
+
+use serde::{Deserialize, Serialize};
+
+/// A scroll distance in pixels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScrollDelta {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl ScrollDelta {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.x == 0.0 && self.y == 0.0
+    }
+}
+
+/// Wheel deltas received since the last frame
+#[derive(Debug, Clone, Default)]
+pub struct ScrollAccumulator {
+    pending: ScrollDelta,
+    /// Wheel events added since the accumulator was created
+    received: u64,
+}
+
+impl ScrollAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a wheel delta. Deltas in opposite directions cancel out.
+    pub fn add(&mut self, delta: ScrollDelta) {
+        self.pending.x += delta.x;
+        self.pending.y += delta.y;
+        self.received += 1;
+    }
+
+    /// The scroll not applied yet, including the fraction of a pixel kept
+    /// from earlier frames
+    pub fn pending(&self) -> ScrollDelta {
+        self.pending
+    }
+
+    /// Whether the next frame has at least a whole pixel to scroll
+    pub fn has_pending(&self) -> bool {
+        self.pending.x.abs() >= 1.0 || self.pending.y.abs() >= 1.0
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Take the whole pixels to scroll this frame, keeping the fraction
+    pub fn take(&mut self) -> ScrollDelta {
+        let whole = ScrollDelta::new(self.pending.x.trunc(), self.pending.y.trunc());
+        self.pending.x -= whole.x;
+        self.pending.y -= whole.y;
+        whole
+    }
+
+    /// Record that only `applied` of the `requested` scroll fit before an
+    /// edge of the grid. The fraction left on a clamped axis pushes against
+    /// the same edge and is dropped, so scrolling back moves at once; the
+    /// other axis keeps its fraction.
+    pub fn settle(&mut self, requested: ScrollDelta, applied: ScrollDelta) {
+        if applied.x != requested.x {
+            self.pending.x = 0.0;
+        }
+        if applied.y != requested.y {
+            self.pending.y = 0.0;
+        }
+    }
+
+    /// Forget the pending scroll, e.g. when the view jumps elsewhere
+    pub fn clear(&mut self) {
+        self.pending = ScrollDelta::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deltas_add_up_and_keep_the_fraction() {
+        let mut scroll = ScrollAccumulator::new();
+        for _ in 0..5 {
+            scroll.add(ScrollDelta::new(0.0, 1.5));
+        }
+        assert_eq!(scroll.received(), 5);
+        assert!(scroll.has_pending());
+        assert_eq!(scroll.take(), ScrollDelta::new(0.0, 7.0));
+        assert_eq!(scroll.pending(), ScrollDelta::new(0.0, 0.5));
+        assert!(!scroll.has_pending());
+
+        // The kept half pixel completes a pixel with the next event
+        scroll.add(ScrollDelta::new(0.0, 0.5));
+        assert_eq!(scroll.take(), ScrollDelta::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn test_reversal_cancels_out() {
+        let mut scroll = ScrollAccumulator::new();
+        scroll.add(ScrollDelta::new(0.0, 10.25));
+        scroll.add(ScrollDelta::new(0.0, -12.5));
+        assert_eq!(scroll.take(), ScrollDelta::new(0.0, -2.0));
+        assert_eq!(scroll.pending(), ScrollDelta::new(0.0, -0.25));
+
+        scroll.add(ScrollDelta::new(0.0, 0.25));
+        assert!(scroll.take().is_zero());
+    }
+
+    #[test]
+    fn test_clamped_axis_drops_its_residue() {
+        let mut scroll = ScrollAccumulator::new();
+        scroll.add(ScrollDelta::new(3.75, -20.5));
+        let requested = scroll.take();
+        assert_eq!(requested, ScrollDelta::new(3.0, -20.0));
+
+        // Only 8 pixels fit above the view
+        scroll.settle(requested, ScrollDelta::new(3.0, -8.0));
+        assert_eq!(scroll.pending(), ScrollDelta::new(0.75, 0.0));
+    }
+}
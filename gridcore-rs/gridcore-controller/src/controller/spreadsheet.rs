@@ -1,11 +1,59 @@
-use crate::behaviors::{resize::ResizeState, selection_stats};
+use crate::behaviors::{
+    case_change::{CaseChange, CaseCommand, CaseSummary, CaseTarget},
+    cell_transform::{CellTransform, TransformSummary},
+    clipboard::{ClipboardContents, ClipboardPayload, CopyOptions},
+    formula_preview::{self, FormulaPreview},
+    paste::{ParsedPaste, PasteConflicts, PasteContent, PasteOptions, PasteParser, PastedFormat},
+    quick_totals::{self, ColumnTotals, QuickFunction},
+    range_drag::{self, RangeDrag, SelectionHit},
+    resize::{ResizeBehavior, ResizePreview, ResizeState, ResizeType},
+    selection_stats,
+    trace::{ArrowGeometry, TraceArrows, TraceDirection},
+    visible_cells,
+};
 use crate::controller::{
-    mode::CellEditMode, EditorMode, EventDispatcher, GridConfiguration, KeyboardEvent, MouseEvent,
-    SpreadsheetEvent, ViewportManager,
+    mode::CellEditMode, plugins::PluginRegistry, Capabilities, CellPosition, CommitKey,
+    EditConflictPolicy, EditGuard, EditorMode, EnterDirection, EntryNavigation, EventDispatcher,
+    FocusManager, FocusTarget, GridConfiguration, IdleInvalidation, IdlePriority, IdleStats,
+    IdleTask, IdleWorkQueue, KeyboardEvent, Keymap, MinimapGeometry, MouseEvent, ScrollDelta,
+    SpreadsheetControllerBuilder, SpreadsheetEvent, TextMeasurer, TextWidths, ViewportBounds,
+    ViewportCache, ViewportManager, MIN_ROW_HEIGHT,
+};
+use crate::managers::{
+    EntryCompletion, EntryPicker, ErrorSystem, Fold, Folds, LintWarnings, RecentEntries,
+    RecentScope, SaveState, WatchEntry, WatchList,
+};
+use crate::state::{
+    Action, CoreState, EditMode, HandlerPath, InsertMode, NavigationModal, ResizeMoveDirection,
+    ResizeSizes, ResizeTarget, Selection, SelectionType, StateDiff, StateSnapshot, UIState,
+    ViewportInfo, VisualSelection,
+};
+#[cfg(feature = "debug")]
+use crate::state::{ActionLog, BugReport, LogBaseline};
+use crate::theme::Palette;
+use gridcore_core::{
+    chart::{ChartData, DEFAULT_MAX_POINTS},
+    csv::{
+        CsvExport, CsvImportOptions, FidelityIssue, ImportDestination, ImportOptions, ImportReport,
+        Sidecar,
+    },
+    domain::{CellFormat, CellStyle, StyleRemoval},
+    evaluator::parse_cell_value,
+    external::{ExternalRequest, ExternalResolver},
+    facade::RecalculationStatus,
+    fill::running::RunningAggregate,
+    formula::{Autocorrection, CellRange, FormulaTranslator},
+    lint::LintRule,
+    memory::CompactOptions,
+    pivot::PivotConfig,
+    references::StructuralOperation,
+    repository::{DensityMap, SheetHealth},
+    script::{ScriptError, ScriptOutput, ScriptSession},
+    types::{CellAddress, CellValue},
+    Result, SpreadsheetError, SpreadsheetFacade,
 };
-use crate::managers::ErrorSystem;
-use crate::state::{Action, InsertMode, Selection, UIState};
-use gridcore_core::{types::CellAddress, Result, SpreadsheetFacade};
+use std::collections::BTreeSet;
+use std::sync::Arc;
 
 #[cfg(feature = "perf")]
 use crate::perf::*;
@@ -14,97 +62,128 @@ use metrics::{counter, histogram};
 
 use super::cell_editor::{CellEditResult, CellEditor};
 use super::formula_bar::FormulaBarManager;
+use super::inspection::InspectionCache;
+use super::strict_entry::{BlockedEntry, StrictGate};
+
+/// What read-only controllers tell the user when refusing a change
+pub const READ_ONLY_MESSAGE: &str = "The spreadsheet is read-only";
 
 pub struct SpreadsheetController {
     pub(super) facade: SpreadsheetFacade,
     pub(super) event_dispatcher: EventDispatcher,
     pub(super) viewport_manager: ViewportManager,
+    pub(super) viewport_cache: ViewportCache,
+    /// Cache warm-ups waiting for the host to be idle
+    pub(super) idle_work: IdleWorkQueue,
+    pub(super) text_widths: TextWidths,
     pub(super) resize_state: ResizeState,
+    pub(super) resize_preview: ResizePreview,
+    /// Counts typed ahead of a key in keyboard resize mode
+    pub(super) resize_behavior: ResizeBehavior,
     pub(super) error_system: ErrorSystem,
     pub(super) config: GridConfiguration,
     pub(super) formula_bar_manager: FormulaBarManager,
+    pub(super) trace_arrows: TraceArrows,
+    pub(super) highlighted_cell: Option<CellAddress>,
+    pub(super) watch_list: WatchList,
+    /// Committed entries offered again while typing
+    pub(super) recent_entries: RecentEntries,
+    /// Ctrl+N/Ctrl+P cycling through recent entries in the editor
+    pub(super) entry_completion: Option<EntryCompletion>,
+    /// Recent entries listed under the editor by Ctrl+Down
+    pub(super) entry_picker: Option<EntryPicker>,
+    pub(super) lint_warnings: LintWarnings,
+    /// Counts edits to the workbook, for caches of what cells hold
+    pub(super) content_generation: u64,
+    /// Hover inspections read since the last edit
+    pub(super) inspections: InspectionCache,
+    /// Report of the last import that did not load cleanly, until dismissed
+    pub(super) load_report: Option<ImportReport>,
+    pub(super) folds: Folds,
+    pub(super) palette: Palette,
+    /// Rows hidden because a fold is closed over them, as opposed to rows
+    /// the user hid
+    pub(super) fold_hidden_rows: BTreeSet<u32>,
+    /// Which sheets changed since the document was last saved
+    pub(super) save_state: SaveState,
+    pub(super) edit_guard: EditGuard,
+    /// Whether changes to the workbook are refused
+    pub(super) read_only: bool,
+    /// Panels holding keyboard focus instead of the grid
+    pub(super) focus: FocusManager,
+    /// UI state last published as a diff, while state diffs are on
+    pub(super) published_state: Option<UIState>,
+    pub(super) vim_enabled: bool,
+    pub(super) keymap: Keymap,
+    /// Where commits move the cursor, and the Tab run Enter returns from
+    pub(super) entry_navigation: EntryNavigation,
+    /// Converts formulas between stored text and what the editor shows
+    pub(super) formula_translator: FormulaTranslator,
+    pub(super) paste_options: PasteOptions,
+    pub(super) copy_options: CopyOptions,
+    /// Pastes held back until the user confirms their conflicts, applied
+    /// together
+    pub(super) pending_paste: Vec<ParsedPaste>,
+    /// Block being dragged by the selection border
+    pub(super) range_drag: Option<RangeDrag>,
+    /// Whether dropping a range over data waits for confirmation
+    pub(super) confirm_drop_overwrites: bool,
+    /// The last copy, for pasting rich contents where the system clipboard
+    /// only carries text
+    pub(super) clipboard: Option<ClipboardContents>,
+    /// Console session whose `undo` reverts earlier script writes
+    pub(super) script_session: ScriptSession,
+    pub(super) plugins: PluginRegistry,
+    /// Keys typed so far of a pending navigation command such as `]p` or `gU3j`
+    pub(super) pending_key: Option<String>,
+    /// Last case operator, repeated by `.`
+    pub(super) last_case_command: Option<CaseCommand>,
+    /// Column totals of a block, completed by the next quick totals request
+    pub(super) last_column_totals: Option<ColumnTotals>,
+    /// Cell and correction of the last autocorrected entry, for reverting it
+    pub(super) last_autocorrection: Option<(CellAddress, Autocorrection)>,
+    /// Entry strict formula mode refused, committed as text if committed
+    /// again unchanged
+    pub(super) blocked_entry: Option<BlockedEntry>,
+    /// The last dispatched actions, for the debug overlay
+    #[cfg(feature = "debug")]
+    pub(super) action_log: ActionLog,
 
     // NEW: Direct state fields for hybrid approach
-    cursor: CellAddress,
-    selection: Option<Selection>,
-    mode: EditorMode,
-    formula_bar: String,
+    pub(super) cursor: CellAddress,
+    pub(super) selection: Option<Selection>,
+    pub(super) mode: EditorMode,
+    pub(super) formula_bar: String,
 }
 
 impl SpreadsheetController {
     pub fn new() -> Self {
-        let config = GridConfiguration {
-            total_rows: 1000,
-            total_cols: 100,
-            ..Default::default()
-        };
-        Self::with_config(config)
+        SpreadsheetControllerBuilder::new().build()
+    }
+
+    /// Start building a controller with injected services and configuration
+    pub fn builder() -> SpreadsheetControllerBuilder {
+        SpreadsheetControllerBuilder::new()
     }
 
     pub fn with_config(config: GridConfiguration) -> Self {
-        let viewport_manager =
-            ViewportManager::new(config.total_rows as u32, config.total_cols as u32)
-                .with_config(config.clone());
-        Self::with_viewport(viewport_manager, config)
+        SpreadsheetControllerBuilder::new()
+            .with_grid_config(config)
+            .build()
     }
 
     pub fn with_viewport(viewport_manager: ViewportManager, config: GridConfiguration) -> Self {
-        let mut controller = Self {
-            facade: SpreadsheetFacade::new(),
-            event_dispatcher: EventDispatcher::new(),
-            viewport_manager,
-            resize_state: ResizeState::default(),
-            error_system: ErrorSystem::new(),
-            config,
-            formula_bar_manager: FormulaBarManager::new(),
-            // Initialize direct state fields
-            cursor: CellAddress::new(0, 0),
-            selection: None,
-            mode: EditorMode::Navigation,
-            formula_bar: String::new(),
-        };
-
-        // Subscribe to state changes
-        controller.setup_state_listener();
-
-        // Initialize formula bar with current cell value
-        controller.update_formula_bar_from_cursor();
-
-        controller
+        SpreadsheetControllerBuilder::new()
+            .with_grid_config(config)
+            .with_viewport_manager(viewport_manager)
+            .build()
     }
 
     pub fn with_state(initial_state: UIState) -> Self {
-        let config = GridConfiguration::default();
-
-        // Extract cursor from initial state
-        let cursor = *initial_state.cursor();
-
-        let mut controller = Self {
-            facade: SpreadsheetFacade::new(),
-            event_dispatcher: EventDispatcher::new(),
-            viewport_manager: ViewportManager::new(1000, 100).with_config(config.clone()),
-            resize_state: ResizeState::default(),
-            error_system: ErrorSystem::new(),
-            config,
-            formula_bar_manager: FormulaBarManager::new(),
-            // Initialize direct state fields
-            cursor,
-            selection: None,
-            mode: EditorMode::Navigation,
-            formula_bar: String::new(),
-        };
-
-        controller.setup_state_listener();
-
-        // Initialize formula bar with current cell value
-        controller.update_formula_bar_from_cursor();
-
-        controller
-    }
-
-    fn setup_state_listener(&mut self) {
-        // State changes are handled through the event system
-        // No additional setup needed here
+        SpreadsheetControllerBuilder::new()
+            .with_grid_config(GridConfiguration::default())
+            .with_ui_state(initial_state)
+            .build()
     }
 
     /// Get the current cursor position
@@ -129,6 +208,115 @@ impl SpreadsheetController {
         &self.mode
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Refuse or allow changes to the workbook. Turning read-only on drops
+    /// an open edit and any paste or drop waiting for confirmation, without
+    /// applying them.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        if self.read_only == read_only {
+            return;
+        }
+        if read_only {
+            if self.mode.is_editing() {
+                self.mode = EditorMode::Navigation;
+                self.update_formula_bar_from_cursor();
+            }
+            self.pending_paste.clear();
+            self.range_drag = None;
+        }
+        self.read_only = read_only;
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+    }
+
+    /// What has keyboard focus
+    pub fn focus(&self) -> FocusTarget {
+        self.focus.current()
+    }
+
+    /// Whether keys go to the grid. While a panel has focus the grid's
+    /// key handling is suspended and the host draws the cursor dimmed.
+    pub fn has_grid_focus(&self) -> bool {
+        self.focus.has_grid_focus()
+    }
+
+    /// Give focus to panel scope `id`, which Tab cycles through `items` of
+    /// and Escape closes again. Panels open their scope when they show.
+    pub fn push_focus_scope(&mut self, id: &str, items: Vec<String>) {
+        let target = self.focus.push_scope(id, items);
+        self.focus_changed(target);
+    }
+
+    /// Close panel scope `id`, e.g. when the panel hides
+    pub fn close_focus_scope(&mut self, id: &str) {
+        if self.focus.is_open(id) {
+            let target = self.focus.close_scope(id);
+            self.focus_changed(target);
+        }
+    }
+
+    /// Update the items of panel scope `id` after they changed
+    pub fn set_focus_scope_items(&mut self, id: &str, items: Vec<String>) {
+        self.focus.set_items(id, items);
+    }
+
+    /// Focus `item` of panel scope `id`, e.g. after the user clicked it
+    pub fn focus_item(&mut self, id: &str, item: &str) {
+        if self.focus.focus_item(id, item) {
+            self.focus_changed(self.focus.current());
+        }
+    }
+
+    /// Close every panel scope and give focus back to the grid
+    pub fn focus_grid(&mut self) {
+        if !self.focus.has_grid_focus() {
+            let target = self.focus.clear();
+            self.focus_changed(target);
+        }
+    }
+
+    /// Handle a key while a panel has focus: Tab and Shift+Tab cycle
+    /// through its items and Escape closes it. Returns whether the key
+    /// moved focus; other keys belong to the panel.
+    pub fn handle_focus_key(&mut self, event: &KeyboardEvent) -> bool {
+        if self.focus.has_grid_focus() {
+            return false;
+        }
+        let target = match event.key.as_str() {
+            "Tab" => self.focus.cycle(event.shift),
+            "Escape" => self.focus.pop_scope(),
+            _ => return false,
+        };
+        self.focus_changed(target);
+        true
+    }
+
+    fn focus_changed(&mut self, target: FocusTarget) {
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::FocusChanged { target });
+    }
+
+    /// The kinds of commands the controller accepts at the moment
+    pub fn capabilities(&self) -> Capabilities {
+        if self.read_only {
+            Capabilities::VIEW_ONLY
+        } else {
+            Capabilities::ALL
+        }
+    }
+
+    /// Tell the user a change was refused because the controller is
+    /// read-only
+    fn refuse_change(&mut self) {
+        self.add_error(
+            READ_ONLY_MESSAGE.to_string(),
+            crate::controller::events::ErrorSeverity::Warning,
+        );
+    }
+
     /// Get the formula bar content
     pub fn get_formula_bar(&self) -> &str {
         &self.formula_bar
@@ -141,6 +329,7 @@ impl SpreadsheetController {
 
         let old = self.cursor;
         self.cursor = cursor;
+        self.viewport_manager.extend_to(&cursor);
 
         // Emit event for cursor movement
         self.event_dispatcher
@@ -166,6 +355,11 @@ impl SpreadsheetController {
     pub fn set_mode(&mut self, mode: EditorMode) {
         log::debug!("Setting mode from {:?} to {:?}", self.mode, mode);
 
+        if self.read_only && mode.is_editing() && !self.mode.is_editing() {
+            self.refuse_change();
+            return;
+        }
+
         // When entering visual mode, set up initial selection
         if let EditorMode::Visual { anchor, .. } = &mode {
             use crate::state::{Selection, SelectionType};
@@ -173,8 +367,9 @@ impl SpreadsheetController {
                 selection_type: SelectionType::Cell { address: *anchor },
                 anchor: Some(*anchor),
             });
-        } else if !matches!(mode, EditorMode::Visual { .. }) {
-            // Clear selection when exiting visual mode
+        } else if !(mode.is_editing() && self.commit_block().is_some()) {
+            // Clear selection when exiting visual mode, but keep a range
+            // being filled in, which commits cycle through
             self.selection = None;
         }
 
@@ -183,16 +378,104 @@ impl SpreadsheetController {
         // Emit state changed event
         self.event_dispatcher
             .dispatch(&SpreadsheetEvent::StateChanged);
+
+        self.settle_edit_conflicts();
     }
 
     /// Set the formula bar content directly
     pub fn set_formula_bar(&mut self, value: String) {
         self.formula_bar = value.clone();
         self.event_dispatcher
-            .dispatch(&SpreadsheetEvent::FormulaBarUpdated { value });
+            .dispatch(&SpreadsheetEvent::FormulaBarUpdated {
+                value: value.into(),
+            });
     }
 
+    /// Apply `action`, passing it through the registered plugins first
     pub fn dispatch_action(&mut self, action: Action) -> Result<()> {
+        #[cfg(feature = "debug")]
+        let logged = self.start_logging(&action);
+        let (_path, result) = self.run_action(action);
+        #[cfg(feature = "debug")]
+        self.finish_logging(logged, _path, &result);
+        self.publish_state_diff();
+        result
+    }
+
+    fn run_action(&mut self, action: Action) -> (HandlerPath, Result<()>) {
+        if self.read_only && action.changes_workbook() {
+            self.refuse_change();
+            return (HandlerPath::Refused, Ok(()));
+        }
+        if self.plugins.is_empty() {
+            return (HandlerPath::Direct, self.apply_action(action));
+        }
+        let action = match self.plugins_before(action) {
+            Ok(Some(action)) => action,
+            Ok(None) => return (HandlerPath::Vetoed, Ok(())),
+            Err(e) => return (HandlerPath::Vetoed, Err(e)),
+        };
+        let applied = action.clone();
+        let result = self
+            .apply_action(action)
+            .and_then(|()| self.plugins_after(&applied));
+        (HandlerPath::ViaPlugins, result)
+    }
+
+    /// The action and the UI state it runs against, taking the log's
+    /// baseline first when it has none
+    #[cfg(feature = "debug")]
+    fn start_logging(&mut self, action: &Action) -> (Action, UIState) {
+        if self.action_log.needs_baseline() {
+            let export = self.facade.export_csv(true);
+            self.action_log.set_baseline(LogBaseline {
+                state: self.get_state_snapshot(),
+                csv: export.csv,
+                sidecar: export.sidecar,
+                determinism: self.facade.determinism_state(),
+            });
+        }
+        (action.clone(), self.get_ui_state())
+    }
+
+    #[cfg(feature = "debug")]
+    fn finish_logging(
+        &mut self,
+        (action, before): (Action, UIState),
+        path: HandlerPath,
+        result: &Result<()>,
+    ) {
+        let error = result.as_ref().err().map(ToString::to_string);
+        let after = self.get_ui_state();
+        let timestamp_ms = chrono::Utc::now().timestamp_millis();
+        let sequence = self
+            .action_log
+            .record(timestamp_ms, action, path, error, before, after);
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::ActionLogged { sequence });
+    }
+
+    /// The last dispatched actions, oldest first
+    #[cfg(feature = "debug")]
+    pub fn action_log(&self) -> &ActionLog {
+        &self.action_log
+    }
+
+    /// Start the action log afresh from the current workbook
+    #[cfg(feature = "debug")]
+    pub fn clear_action_log(&mut self) {
+        self.action_log.clear();
+    }
+
+    /// The logged actions, the workbook they started from and the content
+    /// hash they led to, for replaying elsewhere. `None` until an action
+    /// was logged.
+    #[cfg(feature = "debug")]
+    pub fn bug_report(&self) -> Option<BugReport> {
+        self.action_log.bug_report(self.facade.content_hash())
+    }
+
+    fn apply_action(&mut self, action: Action) -> Result<()> {
         #[cfg(feature = "perf")]
         let _start = std::time::Instant::now();
         #[cfg(feature = "perf")]
@@ -223,21 +506,353 @@ impl SpreadsheetController {
             return self.set_active_sheet(name);
         }
 
+        // Handle formula auditing actions
+        if matches!(action, Action::TracePrecedents) {
+            return self.trace(TraceDirection::Precedents);
+        }
+
+        if matches!(action, Action::TraceDependents) {
+            return self.trace(TraceDirection::Dependents);
+        }
+
+        if matches!(action, Action::ClearTraceArrows) {
+            self.trace_arrows.clear();
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+            return Ok(());
+        }
+
+        // Handle formula linting actions
+        if let Action::Lint { range } = &action {
+            return self.lint(range.as_ref());
+        }
+
+        if matches!(action, Action::ClearLintWarnings) {
+            self.lint_warnings.clear();
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+            return Ok(());
+        }
+
+        if matches!(action, Action::DismissLoadReport) {
+            self.load_report = None;
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+            return Ok(());
+        }
+
+        if matches!(action, Action::NextLintWarning) {
+            return self.goto_lint_warning(true);
+        }
+
+        if matches!(action, Action::PreviousLintWarning) {
+            return self.goto_lint_warning(false);
+        }
+
+        if let Action::SetLintRule { rule, enabled } = action {
+            self.lint_warnings.set_rule_enabled(rule, enabled);
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+            return Ok(());
+        }
+
+        // Handle watch window actions
+        if let Action::AddWatch {
+            address,
+            sheet,
+            label,
+        } = &action
+        {
+            let sheet = sheet.clone().unwrap_or_else(|| self.get_active_sheet());
+            let mut entry = WatchEntry::new(sheet, *address);
+            entry.label = label.clone();
+            if self.watch_list.add(entry) {
+                self.refresh_watch_list();
+                self.event_dispatcher
+                    .dispatch(&SpreadsheetEvent::StateChanged);
+            }
+            return Ok(());
+        }
+
+        if let Action::RemoveWatch { address, sheet } = &action {
+            let sheet = sheet.clone().unwrap_or_else(|| self.get_active_sheet());
+            if self.watch_list.remove(&sheet, address) {
+                self.event_dispatcher
+                    .dispatch(&SpreadsheetEvent::StateChanged);
+            }
+            return Ok(());
+        }
+
+        if let Action::GotoCell { address, sheet } = &action {
+            return self.goto_cell(*address, sheet.as_deref());
+        }
+
+        if let Action::GotoFirstError { sheet } = &action {
+            return self.goto_first_error(sheet);
+        }
+
+        if let Action::SetColumnFormat { column, format } = action {
+            return self.set_column_format(column, format);
+        }
+
+        if let Action::ApplyStyle { name, ranges } = &action {
+            let ranges = match ranges {
+                Some(ranges) => ranges.clone(),
+                None => match &self.selection {
+                    Some(selection) => self.selection_ranges(selection),
+                    None => vec![CellRange::new(self.cursor, self.cursor)],
+                },
+            };
+            return self.apply_style(&ranges, name);
+        }
+
+        if let Action::DefineStyle { name, format } = action {
+            return self.define_style(&name, format);
+        }
+
+        if let Action::RemoveStyle { name, removal } = &action {
+            return self.remove_style(name, *removal);
+        }
+
+        if let Action::CreatePivot {
+            source,
+            anchor,
+            config,
+        } = action
+        {
+            return self.create_pivot(&source, config, anchor);
+        }
+
+        if let Action::RefreshPivot { anchor } = action {
+            return self.refresh_pivot(anchor);
+        }
+
+        if let Action::DefineConstant {
+            name,
+            definition,
+            scope,
+        } = action
+        {
+            let changed = self.facade.define_constant(&name, &definition, scope)?;
+            self.cells_recalculated(changed);
+            return Ok(());
+        }
+
+        if let Action::RemoveConstant { name, scope } = &action {
+            let changed = self.facade.remove_constant(name, scope)?;
+            self.cells_recalculated(changed);
+            return Ok(());
+        }
+
+        if let Action::ChangeCase { ranges, change } = &action {
+            return self.change_case(ranges, *change).map(|_| ());
+        }
+
+        if let Action::TransformSelection { transform } = action {
+            return self.transform_selection(transform).map(|_| ());
+        }
+
+        if let Action::RunningAggregate {
+            aggregate,
+            target_col,
+            values_only,
+        } = action
+        {
+            return self.running_aggregate(aggregate, target_col, values_only);
+        }
+
+        if let Action::Paste { text } = &action {
+            let options = self.paste_options.clone();
+            return self.paste_text(text, &options).map(|_| ());
+        }
+
+        if let Action::PasteSpecial { text, options } = &action {
+            return self.paste_text(text, options).map(|_| ());
+        }
+
+        if let Action::PasteClipboard { text, rich } = &action {
+            return self.paste_clipboard(text, rich.as_deref()).map(|_| ());
+        }
+
+        if matches!(action, Action::ConfirmPaste) {
+            return self.confirm_paste();
+        }
+
+        if matches!(action, Action::CancelPaste) {
+            self.cancel_paste();
+            return Ok(());
+        }
+
+        if let Action::StartRangeDrag { grab } = action {
+            return self.start_range_drag(grab);
+        }
+
+        if let Action::UpdateRangeDrag { x, y } = action {
+            return self.update_range_drag(x, y);
+        }
+
+        if let Action::DropRange { copy } = action {
+            return self.drop_range(copy);
+        }
+
+        if matches!(action, Action::ConfirmRangeDrop) {
+            return self.confirm_range_drop();
+        }
+
+        if matches!(action, Action::CancelRangeDrag) {
+            self.cancel_range_drag();
+            return Ok(());
+        }
+
+        if let Some(function) = match action {
+            Action::QuickSum => Some(QuickFunction::Sum),
+            Action::QuickAverage => Some(QuickFunction::Average),
+            Action::QuickCount => Some(QuickFunction::Count),
+            _ => None,
+        } {
+            return self.quick_totals(function);
+        }
+
+        match action {
+            Action::HideSelection { columns } => {
+                return self.set_selection_hidden(columns, true).map(|_| ())
+            }
+            Action::UnhideSelection { columns } => {
+                return self.set_selection_hidden(columns, false).map(|_| ())
+            }
+            Action::SetSizes {
+                columns,
+                indices,
+                size,
+            } => {
+                self.set_sizes(columns, &indices, size);
+                return Ok(());
+            }
+            Action::SelectVisibleCells => return self.select_visible_cells(),
+            Action::ClearSelectedCells => return self.clear_selected_cells(),
+            Action::FillDown => return self.fill_down(),
+            _ => {}
+        }
+
+        let folds_changed = match action {
+            Action::CreateFold { start_row, end_row } => {
+                self.folds.create(start_row, end_row)?;
+                Some(true)
+            }
+            Action::DeleteFold { row } => {
+                if !self.folds.delete_at(row) {
+                    return Err(SpreadsheetError::InvalidOperation(format!(
+                        "No fold at row {}",
+                        row + 1
+                    )));
+                }
+                Some(true)
+            }
+            Action::OpenFolds {
+                start_row,
+                end_row,
+                all,
+            } => Some(self.folds.open(start_row, end_row, all) > 0),
+            Action::CloseFolds {
+                start_row,
+                end_row,
+                all,
+            } => Some(self.folds.close(start_row, end_row, all) > 0),
+            Action::ToggleFold { row } => Some(self.folds.toggle(row)),
+            Action::NextFold => return self.goto_fold_boundary(true),
+            Action::PreviousFold => return self.goto_fold_boundary(false),
+            Action::SetFoldColumn { width } => {
+                self.folds.set_fold_column(width)?;
+                self.event_dispatcher
+                    .dispatch(&SpreadsheetEvent::StateChanged);
+                return Ok(());
+            }
+            _ => None,
+        };
+        if let Some(changed) = folds_changed {
+            if changed {
+                self.sync_fold_rows();
+            }
+            return Ok(());
+        }
+
+        if let Action::ShowChart { ranges } = action {
+            return self.show_chart(ranges);
+        }
+
+        if let Action::SetPalette { palette } = action {
+            self.palette = palette;
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+            return Ok(());
+        }
+
+        if let Action::SetResizePreview { preview } = action {
+            self.resize_preview = preview;
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+            return Ok(());
+        }
+
+        match action {
+            Action::StartResize {
+                target,
+                initial_position,
+            } => return self.start_resize(target, initial_position),
+            Action::UpdateResize { delta } => {
+                self.set_provisional_size(self.resize_state.current_size + delta);
+                return Ok(());
+            }
+            Action::MoveResizeTarget { direction } => return self.move_resize_target(direction),
+            Action::ConfirmResize => return self.confirm_resize(),
+            Action::CancelResize => {
+                self.cancel_resize();
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        if matches!(action, Action::RefreshExternalData) {
+            self.refresh_external();
+            return Ok(());
+        }
+
+        if let Action::CheckHealth { repair } = action {
+            return self.check_health(repair);
+        }
+
+        if matches!(action, Action::CompactMemory) {
+            return self.compact_memory();
+        }
+
+        if let Action::RecalculateStale { range } = &action {
+            return self.recalculate_stale(range.as_ref());
+        }
+
+        if let Action::SetCalculationEnabled { enabled } = action {
+            return self.set_calculation_enabled(enabled);
+        }
+
         if matches!(action, Action::SubmitFormulaBar) {
             // Submit the formula bar value to the current cell
-            let value = self.formula_bar_manager.value().to_string();
+            let mut value = self.formula_bar_manager.value().to_string();
             let cursor = self.cursor();
+            match self.strict_gate(cursor, &value) {
+                StrictGate::Commit => {}
+                StrictGate::Blocked => return Ok(()),
+                StrictGate::AsText(text) => value = text,
+            }
 
             // Use CellEditor to handle submission
-            let result = CellEditor::submit_formula_bar(&mut self.facade, cursor, value)?;
+            let result = CellEditor::submit_formula_bar(
+                &mut self.facade,
+                &self.formula_translator,
+                cursor,
+                value,
+            )?;
+            self.refresh_cached_cells(&[cursor]);
 
-            // Process events from result
-            for (event, error_info) in result.create_events() {
-                self.event_dispatcher.dispatch(&event);
-                if let Some((msg, severity)) = error_info {
-                    self.error_system.add_error(msg, severity);
-                }
-            }
+            self.finish_cell_edit(&result);
 
             // Clear formula bar if needed
             if let CellEditResult::Success {
@@ -250,6 +865,7 @@ impl SpreadsheetController {
                 self.event_dispatcher.dispatch(&event);
             }
 
+            self.refresh_watch_list();
             return Ok(());
         }
 
@@ -265,6 +881,24 @@ impl SpreadsheetController {
         {
             use crate::controller::vim_handler::{VimHandler, VimKeyResult};
 
+            if self.handle_recent_entry_key(key, *ctrl, *alt)? {
+                return Ok(());
+            }
+
+            // Without vim there is no normal mode: Enter and Tab commit and
+            // Escape cancels. With vim, Tab commits from any editing mode.
+            if !*alt {
+                if let Some(commit) = CommitKey::from_key(key, *shift) {
+                    let tab = matches!(commit, CommitKey::Tab | CommitKey::ShiftTab);
+                    if !self.vim_enabled || tab {
+                        return self.commit_and_move(commit);
+                    }
+                }
+                if !self.vim_enabled && !*shift && key == "Escape" {
+                    return self.cancel_editing();
+                }
+            }
+
             if let Some(result) = VimHandler::handle_editing_key(
                 &self.mode,
                 key,
@@ -307,7 +941,9 @@ impl SpreadsheetController {
                         // Update formula bar to match
                         self.formula_bar = value.clone();
                         self.event_dispatcher
-                            .dispatch(&SpreadsheetEvent::FormulaBarUpdated { value });
+                            .dispatch(&SpreadsheetEvent::FormulaBarUpdated {
+                                value: value.into(),
+                            });
                     }
                     VimKeyResult::UpdateCursor { cursor_pos } => {
                         // Just update cursor position
@@ -345,13 +981,17 @@ impl SpreadsheetController {
                         self.mode = mode;
                         self.formula_bar = value.clone();
                         self.event_dispatcher
-                            .dispatch(&SpreadsheetEvent::FormulaBarUpdated { value });
+                            .dispatch(&SpreadsheetEvent::FormulaBarUpdated {
+                                value: value.into(),
+                            });
                         self.event_dispatcher
                             .dispatch(&SpreadsheetEvent::StateChanged);
                     }
-                    VimKeyResult::CompleteEdit => {
-                        self.complete_editing()?;
-                    }
+                    VimKeyResult::CompleteEdit => match CommitKey::from_key(key, *shift) {
+                        Some(commit) => self.commit_and_move(commit)?,
+                        // Escape leaves the cursor where it is
+                        None => self.complete_editing()?,
+                    },
                     VimKeyResult::CancelEdit => {
                         self.cancel_editing()?;
                     }
@@ -394,7 +1034,7 @@ impl SpreadsheetController {
             self.formula_bar = value.clone();
             self.event_dispatcher
                 .dispatch(&SpreadsheetEvent::FormulaBarUpdated {
-                    value: value.clone(),
+                    value: value.as_str().into(),
                 });
             return Ok(());
         }
@@ -402,26 +1042,32 @@ impl SpreadsheetController {
         if let Action::SubmitCellEdit { value } = &action {
             if let EditorMode::Editing { .. } = &self.mode {
                 let address = self.cursor;
+                let value = match self.strict_gate(address, value) {
+                    StrictGate::Commit => value.clone(),
+                    StrictGate::Blocked => return Ok(()),
+                    StrictGate::AsText(text) => text,
+                };
 
                 // Use CellEditor to handle submission
-                let result =
-                    CellEditor::submit_formula_bar(&mut self.facade, address, value.clone())?;
-
-                // Process events from result
-                for (event, error_info) in result.create_events() {
-                    self.event_dispatcher.dispatch(&event);
-                    if let Some((msg, severity)) = error_info {
-                        self.error_system.add_error(msg, severity);
-                    }
-                }
+                let result = CellEditor::submit_formula_bar(
+                    &mut self.facade,
+                    &self.formula_translator,
+                    address,
+                    value,
+                )?;
+                self.refresh_cached_cells(&[address]);
+
+                self.finish_cell_edit(&result);
 
                 // Update formula bar to show the new value
                 self.update_formula_bar_from_cursor();
+                self.refresh_watch_list();
 
                 // Exit editing mode directly
                 self.mode = EditorMode::Navigation;
                 self.event_dispatcher
                     .dispatch(&SpreadsheetEvent::StateChanged);
+                self.settle_edit_conflicts();
             }
             return Ok(());
         }
@@ -690,13 +1336,56 @@ impl SpreadsheetController {
         &mut self.facade
     }
 
-    /// Get the display value for a cell in the UI
-    /// Returns the formula if the cell has one, otherwise the display value
+    /// Replace the document with `facade`, e.g. one created from a
+    /// template. Everything tied to the old document is dropped, the cursor
+    /// goes back to A1 and the new content counts as saved.
+    pub fn open_document(&mut self, facade: SpreadsheetFacade) {
+        self.facade = facade;
+        self.viewport_cache.clear();
+        self.idle_work = IdleWorkQueue::new();
+        self.trace_arrows.clear();
+        self.highlighted_cell = None;
+        self.watch_list.clear();
+        self.lint_warnings.clear();
+        self.load_report = None;
+        self.folds.clear();
+        self.sync_fold_rows();
+        self.pending_paste.clear();
+        self.range_drag = None;
+        self.script_session = ScriptSession::new();
+        self.pending_key = None;
+        self.last_case_command = None;
+        self.last_column_totals = None;
+        self.cursor = CellAddress::new(0, 0);
+        self.selection = None;
+        self.mode = EditorMode::Navigation;
+        self.viewport_manager.scroll_to(0.0, 0.0);
+        self.sync_grid_extent();
+        self.save_state =
+            SaveState::new(self.input_hashes(self.get_sheets().into_iter().map(|(name, _)| name)));
+        self.update_formula_bar_from_cursor();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+    }
+
+    /// Get the display value for a cell in the UI
+    /// Returns the formula if the cell has one, otherwise the display value
     pub fn get_cell_display_for_ui(&self, address: &CellAddress) -> String {
         if let Some(cell) = self.facade.get_cell(address) {
             if cell.has_formula() {
-                // Show the formula for editing
-                cell.raw_value.to_string()
+                // Show the formula for editing, in the display convention
+                self.formula_translator
+                    .to_display(&cell.raw_value.to_string())
+            } else if let CellValue::String(text) = cell.get_display_value() {
+                // Text that would read back as something else keeps the
+                // apostrophe it was entered with
+                let reads_as_text = !text.starts_with('=')
+                    && matches!(parse_cell_value(text), CellValue::String(parsed) if parsed == *text);
+                if reads_as_text {
+                    text.to_string()
+                } else {
+                    format!("'{}", text)
+                }
             } else {
                 // Show the display value
                 cell.get_display_value().to_string()
@@ -721,82 +1410,2456 @@ impl SpreadsheetController {
         self.error_system.remove_error(error_id);
     }
 
-    /// Clear all errors
-    pub fn clear_errors(&mut self) {
-        self.error_system.clear_all();
+    /// Clear all errors
+    pub fn clear_errors(&mut self) {
+        self.error_system.clear_all();
+    }
+
+    /// Get current errors
+    pub fn get_errors(&self) -> Vec<crate::managers::ErrorEntry> {
+        self.error_system.get_errors()
+    }
+
+    /// Get mutable reference to error system (mainly for tests)
+    pub fn errors(&mut self) -> &mut ErrorSystem {
+        &mut self.error_system
+    }
+
+    pub fn get_viewport_manager(&self) -> &ViewportManager {
+        &self.viewport_manager
+    }
+
+    pub fn get_viewport_manager_mut(&mut self) -> &mut ViewportManager {
+        &mut self.viewport_manager
+    }
+
+    pub fn get_config(&self) -> &GridConfiguration {
+        &self.config
+    }
+
+    /// Get the resize state
+    pub fn resize_state(&self) -> &ResizeState {
+        &self.resize_state
+    }
+
+    /// Get mutable access to the resize state
+    pub fn resize_state_mut(&mut self) -> &mut ResizeState {
+        &mut self.resize_state
+    }
+
+    pub fn get_current_selection_stats(&self) -> selection_stats::SelectionStats {
+        use crate::state::SelectionType;
+
+        // Get the current selection directly
+        let selection = self.get_selection();
+
+        if let Some(sel) = selection {
+            match &sel.selection_type {
+                SelectionType::Cell { address } => {
+                    selection_stats::calculate_single_cell(&self.facade, address)
+                }
+                // Hidden rows and columns are left out, and cells covered
+                // twice by a multi selection only count once
+                _ => {
+                    let ranges: Vec<(CellAddress, CellAddress)> =
+                        CellRange::union_all(&self.visible_selected_ranges())
+                            .into_iter()
+                            .map(|range| (range.start, range.end))
+                            .collect();
+                    selection_stats::calculate_multi_range(&self.facade, &ranges)
+                }
+            }
+        } else {
+            // No selection, calculate for current cursor position
+            let cursor = self.get_cursor();
+            selection_stats::calculate_single_cell(&self.facade, &cursor)
+        }
+    }
+
+    /// Cell the host points the user at, e.g. the target of a tutorial
+    /// step, which the grid outlines
+    pub fn highlighted_cell(&self) -> Option<CellAddress> {
+        self.highlighted_cell
+    }
+
+    pub fn set_highlighted_cell(&mut self, cell: Option<CellAddress>) {
+        if self.highlighted_cell != cell {
+            self.highlighted_cell = cell;
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+        }
+    }
+
+    /// Get the trace arrows for the active cell
+    pub fn get_trace_arrows(&self) -> &TraceArrows {
+        &self.trace_arrows
+    }
+
+    /// Get the trace arrow geometry for the current viewport
+    pub fn get_trace_arrow_geometry(&self) -> Vec<ArrowGeometry> {
+        self.trace_arrows.geometry(&self.viewport_manager)
+    }
+
+    /// Expand precedent or dependent arrows for the cell under the cursor by one level
+    fn trace(&mut self, direction: TraceDirection) -> Result<()> {
+        let cursor = self.cursor;
+        let added = self.trace_arrows.trace(&self.facade, cursor, direction);
+
+        if added == 0 && self.trace_arrows.level(direction) == 0 {
+            let kind = match direction {
+                TraceDirection::Precedents => "precedents",
+                TraceDirection::Dependents => "dependents",
+            };
+            self.add_error(
+                format!("No {} found for {}", kind, cursor),
+                crate::controller::events::ErrorSeverity::Info,
+            );
+        }
+
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Warnings from the last lint run
+    pub fn get_lint_warnings(&self) -> &LintWarnings {
+        &self.lint_warnings
+    }
+
+    /// Lint the formulas in `range`, or the whole active sheet, replacing
+    /// the previous warnings
+    fn lint(&mut self, range: Option<&CellRange>) -> Result<()> {
+        let findings = self.facade.lint(range, self.lint_warnings.settings());
+        let message = match findings.len() {
+            0 => "No lint warnings".to_string(),
+            1 => "1 lint warning".to_string(),
+            count => format!("{} lint warnings", count),
+        };
+        // Mixed units in a total are worth a look even with the markers hidden
+        let mixed: Vec<String> = findings
+            .iter()
+            .filter(|finding| finding.rule == LintRule::MixedFormats)
+            .map(|finding| format!("{}: {}", finding.address, finding.message))
+            .collect();
+        self.lint_warnings.set_findings(findings);
+        for warning in mixed {
+            self.add_error(warning, crate::controller::events::ErrorSeverity::Warning);
+        }
+        self.add_error(message, crate::controller::events::ErrorSeverity::Info);
+
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Move the cursor to the next or previous lint warning and show it
+    fn goto_lint_warning(&mut self, forward: bool) -> Result<()> {
+        let finding = if forward {
+            self.lint_warnings.next_after(&self.cursor)
+        } else {
+            self.lint_warnings.previous_before(&self.cursor)
+        };
+        let Some(finding) = finding.cloned() else {
+            return Ok(());
+        };
+
+        self.goto_cell(finding.address, None)?;
+        let message = match &finding.suggestion {
+            Some(suggestion) => {
+                format!("{}: {} ({})", finding.address, finding.message, suggestion)
+            }
+            None => format!("{}: {}", finding.address, finding.message),
+        };
+        self.add_error(message, crate::controller::events::ErrorSeverity::Info);
+        Ok(())
+    }
+
+    /// Run a console script against the workbook; see
+    /// [`gridcore_core::script`] for the commands. The grid is refreshed
+    /// even when the script fails part way, since earlier lines stay
+    /// applied.
+    pub fn execute_script(
+        &mut self,
+        script: &str,
+    ) -> std::result::Result<Vec<ScriptOutput>, ScriptError> {
+        if self.read_only {
+            return Err(ScriptError {
+                line: 0,
+                column: 0,
+                message: READ_ONLY_MESSAGE.to_string(),
+            });
+        }
+        let result = self.script_session.execute(&self.facade, script);
+        self.viewport_cache.clear();
+        self.note_workbook_edited();
+        self.sync_grid_extent();
+        self.refresh_watch_list();
+        self.update_formula_bar_from_cursor();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        result
+    }
+
+    /// Export the active sheet as CSV, see [`SpreadsheetFacade::export_csv`].
+    /// The sidecar also holds the columns' custom widths and the folds.
+    pub fn export_csv(&self, with_sidecar: bool) -> CsvExport {
+        let mut export = self.facade.export_csv(with_sidecar);
+        if let Some(sidecar) = &mut export.sidecar {
+            sidecar.row_folds = self.folds.to_sidecar();
+            for (col, width) in self.viewport_manager.custom_column_widths() {
+                sidecar
+                    .column_widths
+                    .insert(CellAddress::column_number_to_label(col as u32), width);
+            }
+        }
+        export
+    }
+
+    /// Import CSV into the active sheet, see
+    /// [`SpreadsheetFacade::import_csv`], and size the columns the sidecar
+    /// lists
+    pub fn import_csv(&mut self, csv: &str, sidecar: Option<&Sidecar>) -> Result<ImportReport> {
+        self.import_csv_with(csv, sidecar, &CsvImportOptions::default())
+    }
+
+    /// [`Self::import_csv`], reading fields as `options` says
+    pub fn import_csv_with(
+        &mut self,
+        csv: &str,
+        sidecar: Option<&Sidecar>,
+        options: &CsvImportOptions,
+    ) -> Result<ImportReport> {
+        let report = self.facade.import_csv_with(csv, sidecar, options)?;
+        Ok(self.finish_import(report, sidecar))
+    }
+
+    /// [`Self::import_csv_with`] for a sidecar given as JSON text, read as
+    /// [`Sidecar::read`] says
+    pub fn import_csv_document(
+        &mut self,
+        csv: &str,
+        sidecar_json: Option<&str>,
+        options: &CsvImportOptions,
+    ) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let sidecar = match sidecar_json {
+            Some(json) => Sidecar::read(json, options.mode, &mut report)?,
+            None => None,
+        };
+        report.merge(
+            self.facade
+                .import_csv_with(csv, sidecar.as_ref(), options)?,
+        );
+        Ok(self.finish_import(report, sidecar.as_ref()))
+    }
+
+    /// Import delimited text as `options` says, see
+    /// [`SpreadsheetFacade::commit_import`]. A new sheet for the import is
+    /// added and switched to as [`Self::add_sheet`] and
+    /// [`Self::set_active_sheet`] do.
+    pub fn commit_import(
+        &mut self,
+        source: &[u8],
+        options: &ImportOptions,
+    ) -> Result<ImportReport> {
+        let mut options = options.clone();
+        if let ImportDestination::NewSheet { name } = &options.destination {
+            self.add_sheet(name)?;
+            self.set_active_sheet(name)?;
+            options.destination = ImportDestination::default();
+        }
+        let report = self.facade.commit_import(source, &options)?;
+        Ok(self.finish_import(report, None))
+    }
+
+    /// Size the columns `sidecar` lists and show the imported content,
+    /// keeping `report` for review when the import was not clean
+    fn finish_import(
+        &mut self,
+        mut report: ImportReport,
+        sidecar: Option<&Sidecar>,
+    ) -> ImportReport {
+        for (label, &width) in sidecar.iter().flat_map(|sidecar| &sidecar.column_widths) {
+            match CellAddress::column_label_to_number(label) {
+                Ok(col) => self.viewport_manager.set_column_width(col as usize, width),
+                Err(e) => report.issues.push(FidelityIssue {
+                    address: None,
+                    message: format!("column {}: {}", label, e),
+                }),
+            }
+        }
+        let row_folds = sidecar.map_or(&[][..], |sidecar| &sidecar.row_folds[..]);
+        for fold in self.folds.load_sidecar(row_folds) {
+            report.issues.push(FidelityIssue {
+                address: None,
+                message: format!(
+                    "fold over rows {}-{} was not restored",
+                    fold.start, fold.end
+                ),
+            });
+        }
+        self.sync_fold_rows();
+        self.load_report = (!report.is_faithful()).then(|| report.clone());
+        self.viewport_cache.clear();
+        self.note_active_sheet_edited();
+        self.sync_grid_extent();
+        self.refresh_watch_list();
+        self.update_formula_bar_from_cursor();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        report
+    }
+
+    /// Report of the last import that did not load cleanly, until
+    /// dismissed with [`Action::DismissLoadReport`]
+    pub fn get_load_report(&self) -> Option<&ImportReport> {
+        self.load_report.as_ref()
+    }
+
+    /// Get the cells pinned in the watch window
+    pub fn get_watch_list(&self) -> &WatchList {
+        &self.watch_list
+    }
+
+    /// Re-read watched cells and notify listeners about the ones that changed
+    pub fn refresh_watch_list(&mut self) {
+        for update in self.watch_list.refresh(&self.facade) {
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::WatchValueChanged {
+                    sheet: update.sheet,
+                    address: update.address,
+                    old_value: update.old_value,
+                    new_value: update.new_value,
+                    stale: update.stale,
+                });
+        }
+    }
+
+    /// Cells to draw for the given bounds, served from the viewport cache
+    /// when it covers them
+    pub fn get_display_list(&self, bounds: &ViewportBounds) -> Vec<super::DisplayCell> {
+        self.viewport_cache.display_list(&self.facade, bounds)
+    }
+
+    /// Whether the cache should be refilled before scrolling reaches its edge
+    pub fn needs_prefetch(&self, bounds: &ViewportBounds) -> bool {
+        self.viewport_cache.needs_prefetch(bounds, &self.config)
+    }
+
+    /// Fill the viewport cache around the given bounds. Meant to run off the
+    /// render path, e.g. from an idle callback after a scroll.
+    pub fn prefetch_viewport(&mut self, bounds: &ViewportBounds) {
+        self.viewport_cache
+            .prefetch(&self.facade, bounds, &self.config);
+        self.queue_text_measuring();
+    }
+
+    pub fn get_viewport_cache(&self) -> &ViewportCache {
+        &self.viewport_cache
+    }
+
+    /// Rows and columns prefetched around the viewport; `None` uses one viewport height/width
+    pub fn set_prefetch_margin(&mut self, rows: Option<usize>, cols: Option<usize>) {
+        self.viewport_cache.set_margin(rows, cols);
+    }
+
+    /// Tasks waiting for idle time
+    pub fn idle_work(&self) -> &IdleWorkQueue {
+        &self.idle_work
+    }
+
+    pub fn idle_stats(&self) -> IdleStats {
+        self.idle_work.stats()
+    }
+
+    /// Queue `task` to run when the host is idle
+    pub fn enqueue_idle_work(&mut self, task: IdleTask, priority: IdlePriority) {
+        self.idle_work.enqueue(task, priority);
+    }
+
+    /// Queue the idle work the current view calls for, e.g. after a frame
+    /// is drawn. Returns whether any work is waiting.
+    pub fn schedule_idle_work(&mut self) -> bool {
+        let bounds = self.viewport_manager.get_visible_bounds();
+        if self.needs_prefetch(&bounds) {
+            self.idle_work
+                .enqueue(IdleTask::PrefetchViewport, IdlePriority::Normal);
+        }
+        !self.idle_work.is_empty()
+    }
+
+    /// Tell the controller the viewport moved, so queued work picked for
+    /// the old view is dropped or hurried
+    pub fn note_viewport_scrolled(&mut self) {
+        self.invalidate_idle_work(IdleInvalidation::Scroll);
+    }
+
+    /// Add a wheel delta to the scroll applied on the next frame
+    pub fn queue_scroll(&mut self, delta_x: f64, delta_y: f64) {
+        self.viewport_manager.queue_scroll(delta_x, delta_y);
+    }
+
+    /// Apply the wheel scroll queued since the last frame, dispatching one
+    /// `ViewportScrolled` event for all of it. Hosts call this from their
+    /// animation frame.
+    pub fn apply_pending_scroll(&mut self) -> Option<ScrollDelta> {
+        let delta = self.viewport_manager.apply_pending_scroll()?;
+        self.viewport_scrolled(delta);
+        Some(delta)
+    }
+
+    /// Scroll right away, for keyboard scrolls that should not wait for a
+    /// frame. Wheel deltas still queued are left for the next frame.
+    pub fn scroll_now(&mut self, delta_x: f64, delta_y: f64) -> Option<ScrollDelta> {
+        let before = self.viewport_manager.get_scroll_position();
+        self.viewport_manager.scroll_by(delta_x, delta_y);
+        let after = self.viewport_manager.get_scroll_position();
+        let delta = ScrollDelta::new(after.x - before.x, after.y - before.y);
+        if delta.is_zero() {
+            return None;
+        }
+        self.viewport_scrolled(delta);
+        Some(delta)
+    }
+
+    fn viewport_scrolled(&mut self, delta: ScrollDelta) {
+        self.note_viewport_scrolled();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::ViewportScrolled {
+                delta_x: delta.x,
+                delta_y: delta.y,
+            });
+    }
+
+    /// Run queued idle work until the queue is empty or `budget_ms` has
+    /// passed on `clock`, a timer in milliseconds. A task that runs out of
+    /// time stops where it was and continues in the next slice. Returns
+    /// whether work is left.
+    pub fn run_idle_work(&mut self, budget_ms: f64, clock: &dyn Fn() -> f64) -> bool {
+        let start = clock();
+        let out_of_time = || clock() - start >= budget_ms;
+        while !out_of_time() {
+            let Some(task) = self.idle_work.start() else {
+                break;
+            };
+            let task_start = clock();
+            let done = match task {
+                IdleTask::PrefetchViewport => {
+                    let bounds = self.viewport_manager.get_visible_bounds();
+                    if self.needs_prefetch(&bounds) {
+                        self.prefetch_viewport(&bounds);
+                    }
+                    true
+                }
+                IdleTask::MeasureText => {
+                    if self.text_widths.pending() == 0 {
+                        let texts = self.viewport_cache.cells().map(|cell| cell.text.clone());
+                        self.text_widths.plan(texts);
+                    }
+                    self.text_widths.measure_pending(&out_of_time)
+                }
+            };
+            self.idle_work.finish(clock() - task_start, done);
+            if !done {
+                break;
+            }
+        }
+        !self.idle_work.is_empty()
+    }
+
+    /// Measure cell texts with `measurer`, or stop measuring with `None`.
+    /// Replacing the measurer, e.g. after a font change, drops the widths
+    /// measured so far.
+    pub fn set_text_measurer(&mut self, measurer: Option<TextMeasurer>) {
+        self.text_widths.set_measurer(measurer);
+        self.queue_text_measuring();
+    }
+
+    /// Width of a cell text in pixels, from the cache when it was measured
+    /// before. `None` without a text measurer.
+    pub fn text_width(&self, text: &str) -> Option<f64> {
+        self.text_widths.width(text)
+    }
+
+    pub fn get_text_widths(&self) -> &TextWidths {
+        &self.text_widths
+    }
+
+    fn queue_text_measuring(&mut self) {
+        if self.text_widths.has_measurer() {
+            self.idle_work
+                .enqueue(IdleTask::MeasureText, IdlePriority::Low);
+        }
+    }
+
+    fn invalidate_idle_work(&mut self, cause: IdleInvalidation) {
+        self.idle_work.invalidate(cause);
+        self.text_widths.clear_pending();
+    }
+
+    /// Warn about inputs the facade cut to the length limits
+    fn warn_about_truncation(&mut self) {
+        let truncated = self.facade.take_truncated_cells();
+        let Some(first) = truncated.first() else {
+            return;
+        };
+        let limit = self.facade.workbook_settings().max_text_length;
+        let cells = match truncated.len() {
+            1 => first.to_string(),
+            n => format!("{} cells", n),
+        };
+        self.add_error(
+            format!("Text in {} was cut to {} characters", cells, limit),
+            crate::controller::events::ErrorSeverity::Warning,
+        );
+    }
+
+    /// Re-read changed cells into the viewport cache and fit the scrollable
+    /// area to them
+    pub(super) fn refresh_cached_cells(&mut self, addresses: &[CellAddress]) {
+        self.note_active_sheet_edited();
+        self.warn_about_truncation();
+        // Formulas reading the changed cells were recalculated with them
+        let addresses = &self.facade.with_dependents(addresses);
+        self.viewport_cache.invalidate(&self.facade, addresses);
+        let visible = self.viewport_manager.get_visible_bounds();
+        self.viewport_cache.note_writes(&visible, addresses);
+        self.refresh_conditional_ranges(|range| {
+            addresses.iter().any(|address| range.contains(address))
+        });
+        // Texts listed for measuring may be gone; list them again
+        self.invalidate_idle_work(IdleInvalidation::Edit);
+        self.queue_text_measuring();
+
+        let mut cleared_edge = false;
+        for address in addresses {
+            if self
+                .facade
+                .get_cell(address)
+                .is_some_and(|cell| !cell.is_empty())
+            {
+                self.viewport_manager.note_populated(address);
+            } else {
+                cleared_edge |= self.viewport_manager.is_on_used_edge(address);
+            }
+        }
+        if cleared_edge {
+            self.sync_grid_extent();
+        }
+    }
+
+    /// Fit the scrollable area to the used range of the active sheet
+    pub(super) fn sync_grid_extent(&mut self) {
+        let used = self.facade.get_used_extent();
+        self.viewport_manager.set_used_range(used, &self.cursor);
+    }
+
+    /// Set or clear the default format of a column in the active sheet.
+    /// Cells with an explicit format keep it.
+    pub fn set_column_format(&mut self, column: u32, format: Option<CellFormat>) -> Result<()> {
+        self.facade.set_column_format(column, format)?;
+        self.viewport_cache.clear();
+        self.note_active_sheet_edited();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Make the cells of `ranges` refer to a named style
+    pub fn apply_style(&mut self, ranges: &[CellRange], name: &str) -> Result<()> {
+        for range in ranges {
+            self.facade.apply_style(range, name)?;
+        }
+        self.viewport_cache.clear();
+        self.note_active_sheet_edited();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Define a named style or change an existing one. Cached display text
+    /// is dropped since any cell on screen may use the style.
+    pub fn define_style(&mut self, name: &str, format: CellFormat) -> Result<()> {
+        self.facade.define_style(name, format)?;
+        self.viewport_cache.clear();
+        self.note_workbook_edited();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Remove a named style, blocking or converting the cells using it
+    pub fn remove_style(&mut self, name: &str, removal: StyleRemoval) -> Result<()> {
+        self.facade.remove_style(name, removal)?;
+        self.viewport_cache.clear();
+        self.note_workbook_edited();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Named styles offered in the style gallery
+    pub fn get_styles(&self) -> Vec<CellStyle> {
+        self.facade.get_styles()
+    }
+
+    /// Write a pivot of `source` with its top-left corner at `anchor`
+    pub fn create_pivot(
+        &mut self,
+        source: &CellRange,
+        config: PivotConfig,
+        anchor: CellAddress,
+    ) -> Result<()> {
+        let previous = self.facade.get_pivot(&anchor).map(|pivot| pivot.output);
+        let output = self.facade.pivot_range(source, config, &anchor)?;
+        self.pivot_written(previous, output);
+        Ok(())
+    }
+
+    /// Regenerate the pivot anchored at `anchor` from its current source values
+    pub fn refresh_pivot(&mut self, anchor: CellAddress) -> Result<()> {
+        let previous = self.facade.get_pivot(&anchor).map(|pivot| pivot.output);
+        let output = self.facade.refresh_pivot(&anchor)?;
+        self.pivot_written(previous, output);
+        Ok(())
+    }
+
+    fn pivot_written(&mut self, previous: Option<CellRange>, output: CellRange) {
+        let mut changed: Vec<CellAddress> = output.cells().collect();
+        if let Some(previous) = previous {
+            changed.extend(previous.cells().filter(|address| !output.contains(address)));
+        }
+        self.refresh_cached_cells(&changed);
+        self.update_formula_bar_from_cursor();
+        self.refresh_watch_list();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+    }
+
+    /// Options used by [`Action::Paste`]
+    pub fn paste_options(&self) -> &PasteOptions {
+        &self.paste_options
+    }
+
+    pub fn set_paste_options(&mut self, options: PasteOptions) {
+        self.paste_options = options;
+    }
+
+    /// Options used by [`Self::copy_selection`]
+    pub fn copy_options(&self) -> &CopyOptions {
+        &self.copy_options
+    }
+
+    pub fn set_copy_options(&mut self, options: CopyOptions) {
+        self.copy_options = options;
+    }
+
+    /// Paste `text` with its first field at the cursor. A paste that would
+    /// overwrite non-empty cells or run past the sheet is held back until
+    /// [`Self::confirm_paste`], announced with
+    /// [`SpreadsheetEvent::PasteNeedsConfirmation`] and returned.
+    pub fn paste_text(
+        &mut self,
+        text: &str,
+        options: &PasteOptions,
+    ) -> Result<Option<PasteConflicts>> {
+        let parsed = PasteParser::new(options.clone())
+            .with_translator(self.formula_translator.clone())
+            .parse(text, self.cursor);
+        self.stage_paste(parsed)
+    }
+
+    /// Copy the selection, or the cursor cell without one. The contents
+    /// are kept for [`Self::paste_clipboard`] and returned for the host to
+    /// put on the system clipboard, the payload under
+    /// [`crate::behaviors::clipboard::CLIPBOARD_TYPE`].
+    ///
+    /// Hidden rows and columns are left out, closing up the copied block,
+    /// unless [`CopyOptions::include_hidden`] is set.
+    pub fn copy_selection(&mut self) -> Result<ClipboardContents> {
+        let range = self.clip_to_used(self.copied_range()?);
+        let contents = if self.copy_options.include_hidden {
+            ClipboardContents::copy(&self.facade, &range)
+        } else {
+            let (rows, cols) = visible_cells::visible_lines(&self.viewport_manager, &range);
+            if rows.is_empty() || cols.is_empty() {
+                return Err(SpreadsheetError::InvalidOperation(
+                    "No visible cells to copy".to_string(),
+                ));
+            }
+            ClipboardContents::copy_lines(&self.facade, &rows, &cols)
+        };
+        self.clipboard = Some(contents.clone());
+        Ok(contents)
+    }
+
+    /// The range a copy takes: the selection as one range, or the block a
+    /// selection of visible cells was narrowed from
+    fn copied_range(&self) -> Result<CellRange> {
+        if let Some(selection) = &self.selection {
+            let ranges = self.selection_ranges(selection);
+            if ranges.len() > 1 {
+                if let Some(block) = CellRange::bounding_box(&ranges) {
+                    if self.visible_subranges(&block) == ranges {
+                        return Ok(block);
+                    }
+                }
+            }
+        }
+        self.single_selected_range("copy")
+    }
+
+    /// The selection as one range, or the cursor cell without one
+    fn single_selected_range(&self, verb: &str) -> Result<CellRange> {
+        let mut ranges = match &self.selection {
+            Some(selection) => self.selection_ranges(selection),
+            None => vec![CellRange::new(self.cursor, self.cursor)],
+        };
+        if ranges.len() != 1 {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "Cannot {} a selection of several ranges",
+                verb
+            )));
+        }
+        Ok(ranges.remove(0))
+    }
+
+    /// Stop a range at the last used cell, so whole rows and columns do
+    /// not reach the end of the sheet
+    fn clip_to_used(&self, mut range: CellRange) -> CellRange {
+        if let Some(used) = self.facade.get_used_extent() {
+            range.end = CellAddress::new(
+                range.end.col.min(used.col.max(range.start.col)),
+                range.end.row.min(used.row.max(range.start.row)),
+            );
+        }
+        range
+    }
+
+    /// The block a drag of the selection picks up
+    fn draggable_range(&self) -> Result<CellRange> {
+        self.selected_block("drag")
+    }
+
+    /// The selected range, with whole rows and columns stopped at the last
+    /// used cell
+    fn selected_block(&self, verb: &str) -> Result<CellRange> {
+        let range = self.single_selected_range(verb)?;
+        let whole_lines = self.selection.as_ref().is_some_and(|selection| {
+            matches!(
+                selection.selection_type,
+                SelectionType::Row { .. } | SelectionType::Column { .. }
+            )
+        });
+        Ok(if whole_lines {
+            self.clip_to_used(range)
+        } else {
+            range
+        })
+    }
+
+    /// Paste from the system clipboard at the cursor. `rich` is the
+    /// clipboard's gridcore payload if it has one; without it, text that
+    /// matches the last copy in this controller still pastes the copied
+    /// inputs and formats. Anything else pastes as text.
+    pub fn paste_clipboard(
+        &mut self,
+        text: &str,
+        rich: Option<&str>,
+    ) -> Result<Option<PasteConflicts>> {
+        if self.read_only {
+            self.refuse_change();
+            return Ok(None);
+        }
+        let payload = rich.and_then(ClipboardPayload::from_json).or_else(|| {
+            self.clipboard
+                .as_ref()
+                .filter(|copied| copied.text == text)
+                .map(|copied| copied.payload.clone())
+        });
+        match payload {
+            Some(payload) => match self.paste_ranges() {
+                Some(ranges) => {
+                    let parsed = ranges
+                        .iter()
+                        .map(|range| payload.to_paste_tiled(range, range.start))
+                        .collect();
+                    self.stage_pastes(parsed)
+                }
+                None => {
+                    let parsed = payload.to_paste(self.cursor);
+                    self.stage_paste(parsed)
+                }
+            },
+            None => {
+                let options = self.paste_options.clone();
+                self.paste_text(text, &options)
+            }
+        }
+    }
+
+    /// The rectangles a copied block is repeated across: the visible
+    /// blocks of a selection of several ranges, or of one range with
+    /// hidden rows or columns in it. `None` pastes at the cursor.
+    fn paste_ranges(&self) -> Option<Vec<CellRange>> {
+        self.selection.as_ref()?;
+        let ranges = self.visible_selected_ranges();
+        (ranges.len() > 1).then_some(ranges)
+    }
+
+    /// Apply a paste, or hold it back when it needs confirmation
+    fn stage_paste(&mut self, parsed: ParsedPaste) -> Result<Option<PasteConflicts>> {
+        self.stage_pastes(vec![parsed])
+    }
+
+    /// Apply pastes into separate blocks together, or hold them all back
+    /// when any needs confirmation
+    fn stage_pastes(&mut self, pastes: Vec<ParsedPaste>) -> Result<Option<PasteConflicts>> {
+        let pastes: Vec<ParsedPaste> = pastes
+            .into_iter()
+            .filter(|parsed| parsed.width > 0)
+            .collect();
+        if pastes.is_empty() {
+            return Ok(None);
+        }
+        // Refuse a paste with an overlong field before writing or asking
+        // about any of it
+        let settings = self.facade.workbook_settings();
+        for (_, content) in pastes.iter().flat_map(ParsedPaste::cells) {
+            match content {
+                PasteContent::Input(input) => settings.fit(input).map(drop)?,
+                PasteContent::Text(text) => settings.fit_text(text).map(drop)?,
+                PasteContent::Empty => {}
+            }
+        }
+        if pastes.iter().any(|parsed| !parsed.rectangular) {
+            self.add_error(
+                "Pasted rows have different numbers of fields".to_string(),
+                crate::controller::events::ErrorSeverity::Info,
+            );
+        }
+
+        let facade = &self.facade;
+        let limit = self.last_cell();
+        let mut conflicts = PasteConflicts::default();
+        for parsed in &pastes {
+            conflicts.merge(parsed.conflicts(limit, |address| {
+                facade
+                    .get_cell(address)
+                    .is_some_and(|cell| !cell.is_empty())
+            }));
+        }
+        if conflicts.is_empty() {
+            self.apply_pastes(pastes)?;
+            return Ok(None);
+        }
+
+        self.pending_paste = pastes;
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::PasteNeedsConfirmation {
+                conflicts: conflicts.clone(),
+            });
+        Ok(Some(conflicts))
+    }
+
+    /// Apply the pastes held back by [`Self::paste_text`] or
+    /// [`Self::paste_clipboard`], if any
+    pub fn confirm_paste(&mut self) -> Result<()> {
+        let pastes = std::mem::take(&mut self.pending_paste);
+        self.apply_pastes(pastes)
+    }
+
+    /// Apply pastes in one batch
+    fn apply_pastes(&mut self, pastes: Vec<ParsedPaste>) -> Result<()> {
+        if pastes.is_empty() {
+            return Ok(());
+        }
+        let batch_id = self.facade.begin_batch()?;
+        let applied = pastes
+            .into_iter()
+            .try_for_each(|parsed| self.apply_paste(parsed));
+        self.facade.commit_batch(&batch_id)?;
+        applied
+    }
+
+    pub fn cancel_paste(&mut self) {
+        self.pending_paste.clear();
+    }
+
+    /// Write a parsed paste; fields outside the sheet are dropped
+    fn apply_paste(&mut self, parsed: ParsedPaste) -> Result<()> {
+        let limit = self.last_cell();
+        let mut writes = Vec::new();
+        let mut changed = Vec::new();
+
+        for (address, content) in parsed.cells() {
+            if address.col > limit.col || address.row > limit.row {
+                continue;
+            }
+            match content {
+                PasteContent::Input(input) => writes.push((address, input.clone())),
+                PasteContent::Text(text) => {
+                    self.facade.set_cell_text(&address, text)?;
+                    changed.push(address);
+                }
+                PasteContent::Empty if self.facade.get_cell(&address).is_some() => {
+                    self.facade.delete_cell(&address)?;
+                    changed.push(address);
+                }
+                PasteContent::Empty => {}
+            }
+        }
+
+        if let Some(formats) = &parsed.formats {
+            for (r, row) in formats.iter().enumerate() {
+                for (c, format) in row.iter().enumerate() {
+                    let address = CellAddress::new(
+                        parsed.target.col.saturating_add(c as u32),
+                        parsed.target.row.saturating_add(r as u32),
+                    );
+                    if address.col > limit.col || address.row > limit.row {
+                        continue;
+                    }
+                    self.paste_format(&address, format)?;
+                    changed.push(address);
+                }
+            }
+        }
+
+        self.write_cells(&writes)?;
+        self.refresh_cached_cells(&changed);
+        self.update_formula_bar_from_cursor();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Give a pasted cell its format and style, defining the style first
+    /// when this workbook lacks it. The cell only gets an explicit format
+    /// where it looked different from its style.
+    fn paste_format(&mut self, address: &CellAddress, pasted: &PastedFormat) -> Result<()> {
+        self.facade.clear_formats(address)?;
+        if let Some(style) = &pasted.style {
+            if self.facade.get_style(&style.name).is_none() {
+                self.facade
+                    .define_style(&style.name, style.format.clone())?;
+            }
+            self.facade.set_cell_style(address, Some(&style.name))?;
+        }
+        match (&pasted.format, &pasted.style) {
+            (Some(format), Some(style)) if *format == style.format => Ok(()),
+            (Some(format), _) => self.facade.set_cell_format(address, format.clone()),
+            (None, _) => Ok(()),
+        }
+    }
+
+    pub fn set_drop_overwrite_confirmation(&mut self, confirm: bool) {
+        self.confirm_drop_overwrites = confirm;
+    }
+
+    /// The block being dragged by the selection border, if any
+    pub fn get_range_drag(&self) -> Option<&RangeDrag> {
+        self.range_drag.as_ref()
+    }
+
+    /// What part of the drawn selection, or the cursor cell without one,
+    /// lies under `(x, y)` in the coordinates of
+    /// [`ViewportManager::cell_at_point`]
+    pub fn selection_hit(&self, x: f64, y: f64) -> SelectionHit {
+        let Ok(range) = self.draggable_range() else {
+            return SelectionHit::Outside;
+        };
+        let (left, top) = self.viewport_manager.point_of_cell(&range.start);
+        let end = self.viewport_manager.get_cell_position(&range.end);
+        let rect = CellPosition {
+            x: left,
+            y: top,
+            width: end.x + end.width - left,
+            height: end.y + end.height - top,
+        };
+        range_drag::hit_test_selection(&rect, x, y)
+    }
+
+    /// Pick up the selection by the cell `grab`. Until the drop, the
+    /// destination is shown by [`Self::get_range_drag`] and nothing moves.
+    pub fn start_range_drag(&mut self, grab: CellAddress) -> Result<()> {
+        let source = self.draggable_range()?;
+        self.range_drag = Some(RangeDrag::new(
+            source,
+            grab,
+            self.selection.clone(),
+            self.cursor,
+        ));
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Move the dragged block under the pointer at `(x, y)`, in the
+    /// coordinates of [`ViewportManager::cell_at_point`]. Near the edge of
+    /// the viewport this scrolls one step, so holding the pointer there and
+    /// moving it keeps scrolling.
+    pub fn update_range_drag(&mut self, x: f64, y: f64) -> Result<()> {
+        if self.range_drag.is_none() {
+            return Ok(());
+        }
+        let width = self.viewport_manager.get_viewport_width();
+        let height = self.viewport_manager.get_viewport_height();
+        let (dx, dy) = range_drag::auto_scroll_delta(x, y, width, height);
+        if dx != 0.0 || dy != 0.0 {
+            self.viewport_manager.scroll_by(dx, dy);
+        }
+
+        let point = (
+            x.clamp(0.0, (width - 1.0).max(0.0)),
+            y.clamp(0.0, (height - 1.0).max(0.0)),
+        );
+        let Some(pointer) = self.viewport_manager.cell_at_point(point.0, point.1) else {
+            return Ok(());
+        };
+        let last = self.last_cell();
+        if let Some(drag) = &mut self.range_drag {
+            drag.move_to(pointer, last);
+        }
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Drop the dragged block, copying it if `copy` and moving it
+    /// otherwise. Unless confirmation is turned off, a drop over occupied
+    /// cells is held back until [`Self::confirm_range_drop`], announced with
+    /// [`SpreadsheetEvent::RangeDropNeedsConfirmation`].
+    pub fn drop_range(&mut self, copy: bool) -> Result<()> {
+        let Some(drag) = &mut self.range_drag else {
+            return Ok(());
+        };
+        if !copy && drag.destination() == drag.source() {
+            self.range_drag = None;
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+            return Ok(());
+        }
+
+        let facade = &self.facade;
+        let overwritten = drag.overwritten(copy, |address| {
+            facade
+                .get_cell(address)
+                .is_some_and(|cell| !cell.is_empty())
+        });
+        if overwritten.is_empty() || !self.confirm_drop_overwrites {
+            return self.apply_range_drop(copy);
+        }
+        drag.set_pending_drop(Some(copy));
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::RangeDropNeedsConfirmation { overwritten, copy });
+        Ok(())
+    }
+
+    /// Apply the drop held back by [`Self::drop_range`], if any
+    pub fn confirm_range_drop(&mut self) -> Result<()> {
+        match self.range_drag.as_ref().and_then(RangeDrag::pending_drop) {
+            Some(copy) => self.apply_range_drop(copy),
+            None => Ok(()),
+        }
+    }
+
+    /// Put the block back and restore the selection from before the drag
+    pub fn cancel_range_drag(&mut self) {
+        if let Some(drag) = self.range_drag.take() {
+            self.selection = drag.original_selection().cloned();
+            self.cursor = drag.original_cursor();
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+        }
+    }
+
+    /// Write the dragged block at its destination as one batch. Copies move
+    /// relative references like a paste; moved formulas keep reading the
+    /// cells they read before.
+    fn apply_range_drop(&mut self, copy: bool) -> Result<()> {
+        let Some(drag) = self.range_drag.take() else {
+            return Ok(());
+        };
+        let (source, destination) = (*drag.source(), *drag.destination());
+        let mut payload = ClipboardContents::copy(&self.facade, &source).payload;
+        if !copy {
+            payload.source = destination.start;
+        }
+        let parsed = payload.to_paste(destination.start);
+
+        let batch_id = self.facade.begin_batch()?;
+        if !copy {
+            let vacated: Vec<CellAddress> = source
+                .cells()
+                .filter(|address| !destination.contains(address))
+                .collect();
+            for address in &vacated {
+                if self.facade.get_cell(address).is_some() {
+                    self.facade.delete_cell(address)?;
+                }
+                self.facade.clear_formats(address)?;
+            }
+            self.refresh_cached_cells(&vacated);
+        }
+        let applied = self.apply_paste(parsed);
+        self.facade.commit_batch(&batch_id)?;
+        applied?;
+        if !copy {
+            self.follow_structural_operation(StructuralOperation::MoveRange {
+                from: source,
+                to: destination.start,
+            });
+        }
+
+        self.selection = Some(Selection {
+            selection_type: SelectionType::Range {
+                start: destination.start,
+                end: destination.end,
+            },
+            anchor: Some(destination.start),
+        });
+        self.cursor = destination.start;
+        self.update_formula_bar_from_cursor();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Insert `count` empty rows above row `before` of the active sheet
+    pub fn insert_rows(&mut self, before: u32, count: u32) -> Result<()> {
+        self.apply_structural_operation(StructuralOperation::InsertRows {
+            before_row: before,
+            count,
+        })
+    }
+
+    /// Delete `count` rows of the active sheet from row `start` down
+    pub fn delete_rows(&mut self, start: u32, count: u32) -> Result<()> {
+        self.apply_structural_operation(StructuralOperation::DeleteRows {
+            start_row: start,
+            count,
+        })
+    }
+
+    /// Insert `count` empty columns left of column `before` of the active
+    /// sheet
+    pub fn insert_columns(&mut self, before: u32, count: u32) -> Result<()> {
+        self.apply_structural_operation(StructuralOperation::InsertColumns {
+            before_col: before,
+            count,
+        })
+    }
+
+    /// Delete `count` columns of the active sheet from column `start` right
+    pub fn delete_columns(&mut self, start: u32, count: u32) -> Result<()> {
+        self.apply_structural_operation(StructuralOperation::DeleteColumns {
+            start_col: start,
+            count,
+        })
+    }
+
+    fn apply_structural_operation(&mut self, operation: StructuralOperation) -> Result<()> {
+        if self.read_only {
+            self.refuse_change();
+            return Ok(());
+        }
+        match operation {
+            StructuralOperation::InsertRows { before_row, count } => {
+                self.facade.insert_rows(before_row, count)?
+            }
+            StructuralOperation::DeleteRows { start_row, count } => {
+                self.facade.delete_rows(start_row, count)?
+            }
+            StructuralOperation::InsertColumns { before_col, count } => {
+                self.facade.insert_columns(before_col, count)?
+            }
+            StructuralOperation::DeleteColumns { start_col, count } => {
+                self.facade.delete_columns(start_col, count)?
+            }
+            StructuralOperation::MoveRange { .. } => {}
+        }
+        self.follow_structural_operation(operation);
+        Ok(())
+    }
+
+    /// Shift what points at cells of the active sheet after `operation`:
+    /// the cursor, selection, scroll position and watches. Only the cells
+    /// from the edit point on are re-read, then the operation is announced.
+    /// Moves only carry watches; the drop already placed the selection.
+    fn follow_structural_operation(&mut self, operation: StructuralOperation) {
+        let last = self.last_cell();
+        if !matches!(operation, StructuralOperation::MoveRange { .. }) {
+            let cursor = operation.shift_address_or_next(&self.cursor);
+            self.cursor = CellAddress::new(cursor.col.min(last.col), cursor.row.min(last.row));
+            self.selection = self
+                .selection
+                .as_ref()
+                .and_then(|selection| selection.shifted(&operation));
+            self.viewport_manager.apply_structural_operation(&operation);
+            self.follow_folds(&operation);
+        }
+        let sheet = self.get_active_sheet();
+        self.watch_list
+            .apply_structural_operation(&sheet, &operation);
+
+        let damage = operation.damaged_range(&last);
+        self.viewport_cache.invalidate_range(&self.facade, &damage);
+        let visible = self.viewport_manager.get_visible_bounds();
+        self.viewport_cache.note_writes(&visible, &[damage.start]);
+        self.refresh_conditional_ranges(|range| range.intersect(&damage).is_some());
+        self.note_active_sheet_edited();
+        self.invalidate_idle_work(IdleInvalidation::Edit);
+        self.sync_grid_extent();
+        self.refresh_watch_list();
+        self.update_formula_bar_from_cursor();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::structural(operation));
+    }
+
+    /// The visible cells a structural event may have changed, so a view
+    /// can repaint those alone; `None` for other events or when none of
+    /// the damage is on screen
+    pub fn structural_damage(&self, event: &SpreadsheetEvent) -> Option<CellRange> {
+        let damage = event
+            .structural_operation()?
+            .damaged_range(&self.last_cell());
+        let visible = self.viewport_manager.get_visible_bounds();
+        let start = CellAddress::new(
+            damage.start.col.max(visible.start_col as u32),
+            damage.start.row.max(visible.start_row as u32),
+        );
+        let end = CellAddress::new(
+            damage.end.col.min(visible.end_col as u32),
+            damage.end.row.min(visible.end_row as u32),
+        );
+        (start.col <= end.col && start.row <= end.row).then(|| CellRange::new(start, end))
+    }
+
+    /// Last column and row of the grid
+    fn last_cell(&self) -> CellAddress {
+        CellAddress::new(
+            self.config.total_cols.saturating_sub(1) as u32,
+            self.config.total_rows.saturating_sub(1) as u32,
+        )
+    }
+
+    /// Populated cells of the active sheet per `block_size` block, for the
+    /// minimap. Unchanged sheets return the cached map.
+    pub fn minimap_density(&self, block_size: u32) -> Arc<DensityMap> {
+        self.facade.aggregate_density(block_size)
+    }
+
+    /// Centre the viewport on the cell under a minimap point and return it
+    pub fn jump_from_minimap(&mut self, geometry: &MinimapGeometry, x: f64, y: f64) -> CellAddress {
+        let cell = geometry.cell_at(x, y);
+        let manager = &mut self.viewport_manager;
+        manager.extend_to(&cell);
+        let target_x = manager.get_column_x(cell.col as usize)
+            + (manager.get_column_width(cell.col as usize) - manager.get_viewport_width()) / 2.0;
+        let target_y = manager.get_row_y(cell.row as usize)
+            + (manager.get_row_height(cell.row as usize) - manager.get_viewport_height()) / 2.0;
+        manager.scroll_to(target_x, target_y);
+        cell
+    }
+
+    /// Chart-ready series for `ranges` of the active sheet
+    pub fn chart_data(&self, ranges: &[CellRange]) -> Result<ChartData> {
+        self.facade.chart_data(ranges, DEFAULT_MAX_POINTS)
+    }
+
+    /// Chart-ready series for the current selection
+    pub fn chart_data_from_selection(&self) -> Result<ChartData> {
+        let ranges = self
+            .selection
+            .as_ref()
+            .map(|selection| self.selection_ranges(selection))
+            .unwrap_or_default();
+        self.chart_data(&ranges)
+    }
+
+    /// Emit [`SpreadsheetEvent::ChartRequested`] for `ranges`, or for the
+    /// selection when `None`, so the host can open a chart
+    pub fn show_chart(&mut self, ranges: Option<Vec<CellRange>>) -> Result<()> {
+        let data = match ranges {
+            Some(ranges) => self.chart_data(&ranges)?,
+            None => self.chart_data_from_selection()?,
+        };
+        if data.skipped > 0 {
+            self.add_error(
+                format!("Chart skips {} non-numeric cells", data.skipped),
+                crate::controller::events::ErrorSeverity::Info,
+            );
+        }
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::ChartRequested { data });
+        Ok(())
+    }
+
+    /// Change the case of the text cells in `ranges` with a single write and
+    /// report the counts as a status message
+    pub fn change_case(&mut self, ranges: &[CellRange], change: CaseChange) -> Result<CaseSummary> {
+        let mut summary = CaseSummary::default();
+        let mut writes = Vec::new();
+
+        for (address, cell) in self.facade.get_all_cells() {
+            if cell.is_empty() || !ranges.iter().any(|range| range.contains(&address)) {
+                continue;
+            }
+            match change.apply_to_cell(&cell) {
+                Some(text) if cell.raw_value.to_string() != text => {
+                    summary.changed += 1;
+                    writes.push((address, text));
+                }
+                Some(_) => {}
+                None => summary.skipped += 1,
+            }
+        }
+
+        if !writes.is_empty() {
+            writes.sort_by_key(|(address, _)| (address.row, address.col));
+            self.write_cells(&writes)?;
+        }
+        self.add_error(
+            summary.to_string(),
+            crate::controller::events::ErrorSeverity::Info,
+        );
+        Ok(summary)
+    }
+
+    /// Apply `transform` to the visible cells of the selection, or to the
+    /// cursor cell without one, as one undoable batch, and leave visual
+    /// mode. Formulas and cells the transform does not apply to are
+    /// skipped and counted.
+    pub fn transform_selection(&mut self, transform: CellTransform) -> Result<TransformSummary> {
+        let mut addresses: Vec<CellAddress> = self
+            .visible_selected_ranges()
+            .iter()
+            .flat_map(|range| range.cells())
+            .collect();
+        // Parts of a multi-selection may overlap
+        addresses.sort_by_key(|address| (address.row, address.col));
+        addresses.dedup();
+
+        let mut summary = TransformSummary::default();
+        let mut writes = Vec::new();
+        for address in addresses {
+            let Some(cell) = self
+                .facade
+                .get_cell(&address)
+                .filter(|cell| !cell.is_empty())
+            else {
+                continue;
+            };
+            match transform.apply_to_cell(&cell) {
+                Some(input) if parse_cell_value(&input) != cell.raw_value => {
+                    summary.transformed += 1;
+                    writes.push((address, input));
+                }
+                Some(_) => {}
+                None => summary.skipped += 1,
+            }
+        }
+
+        if !writes.is_empty() {
+            let batch_id = self.facade.begin_batch()?;
+            let written = self.write_cells(&writes);
+            self.facade.commit_batch(&batch_id)?;
+            written?;
+        }
+
+        let was_visual = self.mode.is_visual();
+        if self.selection.is_some() {
+            self.set_mode(EditorMode::Navigation);
+            self.set_selection(None);
+        }
+        if was_visual {
+            self.dispatch_action(Action::ExitSpreadsheetVisualMode)?;
+        }
+        self.update_formula_bar_from_cursor();
+        self.add_error(
+            summary.to_string(),
+            crate::controller::events::ErrorSeverity::Info,
+        );
+        Ok(summary)
+    }
+
+    /// Write a running aggregate of the selected column, or of the cursor
+    /// cell without a selection, into the same rows of `target_col`, the
+    /// next column when `None`, as one batch. The target cells must be
+    /// empty. With `values_only` the values are written instead of the
+    /// formulas, which is quicker to recalculate on long columns.
+    pub fn running_aggregate(
+        &mut self,
+        aggregate: RunningAggregate,
+        target_col: Option<u32>,
+        values_only: bool,
+    ) -> Result<()> {
+        let source = self.selected_block("total")?;
+        let target_col = target_col.unwrap_or(source.end.col + 1);
+        if target_col > self.last_cell().col {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "No column right of {} for a running {}",
+                source,
+                aggregate.label()
+            )));
+        }
+        let occupied = (source.start.row..=source.end.row)
+            .map(|row| CellAddress::new(target_col, row))
+            .find(|address| {
+                self.facade
+                    .get_cell(address)
+                    .is_some_and(|cell| !cell.is_empty())
+            });
+        if let Some(occupied) = occupied {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "A running {} would overwrite {}",
+                aggregate.label(),
+                occupied
+            )));
+        }
+
+        let written = self
+            .facade
+            .running_aggregate(&source, target_col, aggregate, values_only)?;
+        self.refresh_cached_cells(&written);
+        self.refresh_watch_list();
+
+        let was_visual = self.mode.is_visual();
+        if self.selection.is_some() {
+            self.set_mode(EditorMode::Navigation);
+            self.set_selection(None);
+        }
+        if was_visual {
+            self.dispatch_action(Action::ExitSpreadsheetVisualMode)?;
+        }
+        self.update_formula_bar_from_cursor();
+        self.add_error(
+            format!(
+                "Running {} of {} written to {}",
+                aggregate.label(),
+                source,
+                CellRange::new(written[0], written[written.len() - 1])
+            ),
+            crate::controller::events::ErrorSeverity::Info,
+        );
+        Ok(())
+    }
+
+    /// Run a case operator typed in navigation mode and remember it for `.`
+    pub fn apply_case_command(&mut self, command: CaseCommand) -> Result<()> {
+        let cursor = self.cursor;
+        let limit = self.last_cell();
+        let facade = &self.facade;
+        let Some(range) = command.range(cursor, limit, |address| {
+            facade
+                .get_cell(address)
+                .is_some_and(|cell| !cell.is_empty())
+        }) else {
+            return Ok(());
+        };
+
+        // Like vim, the cursor ends at the start of the operated range
+        let col = if (range.start.col..=range.end.col).contains(&cursor.col) {
+            cursor.col
+        } else {
+            range.start.col
+        };
+        let new_cursor = CellAddress::new(col, range.start.row);
+
+        let change = command.change;
+        self.last_case_command = Some(command);
+        self.dispatch_action(Action::ChangeCase {
+            ranges: vec![range],
+            change,
+        })?;
+        self.set_cursor(new_cursor);
+        self.update_formula_bar_from_cursor();
+        Ok(())
+    }
+
+    /// `.`: repeat the last case operator from the cursor
+    pub fn repeat_case_command(&mut self) -> Result<()> {
+        match self.last_case_command.clone() {
+            Some(command) => self.apply_case_command(command),
+            None => Ok(()),
+        }
+    }
+
+    /// Change the case of the visual selection and leave visual mode. `.`
+    /// then repeats the change over a block of the same size.
+    pub fn change_case_in_selection(&mut self, change: CaseChange) -> Result<()> {
+        let Some(selection) = self.selection.clone() else {
+            return Ok(());
+        };
+        let ranges = self.selection_ranges(&selection);
+        let (Some(start_col), Some(start_row), Some(end_col), Some(end_row)) = (
+            ranges.iter().map(|range| range.start.col).min(),
+            ranges.iter().map(|range| range.start.row).min(),
+            ranges.iter().map(|range| range.end.col).max(),
+            ranges.iter().map(|range| range.end.row).max(),
+        ) else {
+            return Ok(());
+        };
+
+        self.dispatch_action(Action::ChangeCase { ranges, change })?;
+        self.last_case_command = Some(CaseCommand {
+            change,
+            target: CaseTarget::Block {
+                cols: end_col - start_col + 1,
+                rows: end_row - start_row + 1,
+            },
+            count: 1,
+        });
+
+        self.set_mode(EditorMode::Navigation);
+        self.set_selection(None);
+        self.set_cursor(CellAddress::new(start_col, start_row));
+        self.update_formula_bar_from_cursor();
+        self.dispatch_action(Action::ExitSpreadsheetVisualMode)
+    }
+
+    /// Write `function` totals of the selection, or of the cursor cell
+    /// without one, as one batch, leave visual mode and move the cursor to
+    /// the first total. Repeated with the cursor still there, totals of a
+    /// block are completed with row totals and the grand total.
+    pub fn quick_totals(&mut self, function: QuickFunction) -> Result<()> {
+        let cursor = self.cursor;
+        let previous = self
+            .last_column_totals
+            .take()
+            .filter(|previous| self.selection.is_none() && previous.first == cursor);
+        let range = match &previous {
+            Some(previous) => previous.range,
+            None => self.selected_block("total")?,
+        };
+
+        let facade = &self.facade;
+        let occupied = |address: &CellAddress| {
+            facade
+                .get_cell(address)
+                .is_some_and(|cell| !cell.is_empty())
+        };
+        if !range.cells().any(|address| occupied(&address)) {
+            self.add_error(
+                format!("Nothing to total in {}", range),
+                crate::controller::events::ErrorSeverity::Info,
+            );
+            return Ok(());
+        }
+        let Some(plan) = quick_totals::plan_totals(
+            &range,
+            function,
+            previous.as_ref(),
+            self.last_cell(),
+            occupied,
+        ) else {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "No empty cells left for the totals of {}",
+                range
+            )));
+        };
+
+        let batch_id = self.facade.begin_batch()?;
+        let written = self.write_cells(&plan.writes);
+        self.facade.commit_batch(&batch_id)?;
+        written?;
+        self.last_column_totals = plan.column_totals;
+
+        let was_visual = self.mode.is_visual();
+        if self.selection.is_some() {
+            self.set_mode(EditorMode::Navigation);
+            self.set_selection(None);
+        }
+        self.set_cursor(plan.writes[0].0);
+        if was_visual {
+            self.dispatch_action(Action::ExitSpreadsheetVisualMode)?;
+        }
+        Ok(())
+    }
+
+    /// The parts of `range` on visible rows and columns, see
+    /// [`ViewportManager::visible_subranges`]
+    pub fn visible_subranges(&self, range: &CellRange) -> Vec<CellRange> {
+        self.viewport_manager.visible_subranges(range)
+    }
+
+    /// The visible parts of the selection, or of the cursor cell without
+    /// one. Whole rows and columns stop at the last used cell.
+    fn visible_selected_ranges(&self) -> Vec<CellRange> {
+        let Some(selection) = &self.selection else {
+            return self.visible_subranges(&CellRange::new(self.cursor, self.cursor));
+        };
+        let whole_lines = matches!(
+            selection.selection_type,
+            SelectionType::Row { .. } | SelectionType::Column { .. }
+        );
+        self.selection_ranges(selection)
+            .into_iter()
+            .map(|range| {
+                if whole_lines {
+                    self.clip_to_used(range)
+                } else {
+                    range
+                }
+            })
+            .flat_map(|range| self.visible_subranges(&range))
+            .collect()
+    }
+
+    /// Hide or show the rows of the selection, or its columns if `columns`.
+    /// Returns how many rows or columns changed.
+    pub fn set_selection_hidden(&mut self, columns: bool, hidden: bool) -> Result<usize> {
+        let ranges = match &self.selection {
+            Some(selection) => {
+                let across = if columns {
+                    matches!(selection.selection_type, SelectionType::Row { .. })
+                } else {
+                    matches!(selection.selection_type, SelectionType::Column { .. })
+                };
+                if across {
+                    return Err(SpreadsheetError::InvalidOperation(format!(
+                        "Cannot {} every {}",
+                        if hidden { "hide" } else { "unhide" },
+                        if columns { "column" } else { "row" }
+                    )));
+                }
+                self.selection_ranges(selection)
+            }
+            None => vec![CellRange::new(self.cursor, self.cursor)],
+        };
+
+        let changed: usize = ranges
+            .iter()
+            .map(|range| {
+                if columns {
+                    self.viewport_manager.set_columns_hidden(
+                        range.start.col as usize,
+                        range.end.col as usize,
+                        hidden,
+                    )
+                } else {
+                    self.viewport_manager.set_rows_hidden(
+                        range.start.row as usize,
+                        range.end.row as usize,
+                        hidden,
+                    )
+                }
+            })
+            .sum();
+
+        let lines = if columns { "column" } else { "row" };
+        self.add_error(
+            format!(
+                "{} {} {}{}",
+                if hidden { "Hid" } else { "Unhid" },
+                changed,
+                lines,
+                if changed == 1 { "" } else { "s" }
+            ),
+            crate::controller::events::ErrorSeverity::Info,
+        );
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(changed)
+    }
+
+    /// The folds of the sheet, outer folds before the folds they hold
+    pub fn folds(&self) -> Vec<Fold> {
+        self.folds.list()
+    }
+
+    /// Width of the fold column in the gutter, in characters; 0 hides it
+    pub fn fold_column(&self) -> u8 {
+        self.folds.fold_column()
+    }
+
+    /// Colors the grid is drawn with
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// How a resize shows before it is committed
+    pub fn resize_preview(&self) -> ResizePreview {
+        self.resize_preview
+    }
+
+    /// Give every column in `indices` the width `size`, or every row the
+    /// height unless `columns`
+    pub fn set_sizes(&mut self, columns: bool, indices: &[u32], size: f64) {
+        for &index in indices {
+            if columns {
+                self.viewport_manager.set_column_width(index as usize, size);
+            } else {
+                self.viewport_manager.set_row_height(index as usize, size);
+            }
+        }
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+    }
+
+    /// Start resizing `target` from the pointer at `position`, along with
+    /// the rest of the selection when it selects whole columns or rows
+    /// including it. Until the resize is confirmed, the new size is only
+    /// provisional: see [`Self::resize_guideline`].
+    pub fn start_resize(&mut self, target: ResizeTarget, position: f64) -> Result<()> {
+        let (resize_type, index) = match target {
+            ResizeTarget::Column { index } => (ResizeType::Column, index),
+            ResizeTarget::Row { index } => (ResizeType::Row, index),
+        };
+        let columns = resize_type == ResizeType::Column;
+        let mut indices = match self.selection.as_ref().map(|s| &s.selection_type) {
+            Some(SelectionType::Column { columns: selected })
+                if columns && selected.contains(&index) =>
+            {
+                selected.clone()
+            }
+            Some(SelectionType::Row { rows }) if !columns && rows.contains(&index) => rows.clone(),
+            _ => vec![index],
+        };
+        indices.sort_unstable();
+        indices.dedup();
+
+        let size = |index: u32| {
+            if columns {
+                self.viewport_manager.get_column_width(index as usize)
+            } else {
+                self.viewport_manager.get_row_height(index as usize)
+            }
+        };
+        let start_size = size(index);
+        self.resize_state = ResizeState {
+            is_resizing: true,
+            resize_type,
+            resize_index: index as usize,
+            start_position: position,
+            start_size,
+            current_size: start_size,
+            lines: indices
+                .into_iter()
+                .map(|index| (index as usize, size(index)))
+                .collect(),
+        };
+        self.resize_behavior.reset();
+        self.mode = EditorMode::Resizing;
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Make `size` the provisional size of the resize in progress, within
+    /// the sizes the grid allows. A live reflow lays the grid out with it
+    /// straight away.
+    pub fn set_provisional_size(&mut self, size: f64) {
+        if !self.resize_state.is_resizing {
+            return;
+        }
+        let size = match self.resize_state.resize_type {
+            ResizeType::Column => {
+                size.clamp(self.config.min_cell_width, self.config.max_cell_width)
+            }
+            _ => size.max(MIN_ROW_HEIGHT),
+        };
+        self.resize_state.current_size = size;
+        if self.resize_preview == ResizePreview::LiveReflow {
+            let lines: Vec<(usize, f64)> = self
+                .resize_state
+                .lines
+                .iter()
+                .map(|&(index, _)| (index, size))
+                .collect();
+            self.size_lines(self.resize_state.resize_type, &lines);
+        }
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+    }
+
+    /// Where the ghost guideline of the resize in progress goes: the
+    /// provisional far edge of the grabbed column or row, along its axis
+    /// in the coordinates of [`ViewportManager::point_of_cell`]. `None`
+    /// unless a resize is previewed as a ghost.
+    pub fn resize_guideline(&self) -> Option<(ResizeType, f64)> {
+        let resize = &self.resize_state;
+        if !resize.is_resizing || self.resize_preview != ResizePreview::Ghost {
+            return None;
+        }
+        let indices = resize.indices();
+        let scroll = self.viewport_manager.get_scroll_position();
+        let edge = match resize.resize_type {
+            ResizeType::Column => {
+                self.viewport_manager.provisional_column_edge(
+                    resize.resize_index,
+                    &indices,
+                    resize.current_size,
+                ) - scroll.x
+            }
+            ResizeType::Row => {
+                self.viewport_manager.provisional_row_edge(
+                    resize.resize_index,
+                    &indices,
+                    resize.current_size,
+                ) - scroll.y
+            }
+            ResizeType::None => return None,
+        };
+        Some((resize.resize_type, edge))
+    }
+
+    /// Commit the provisional size to every line being resized, through
+    /// [`Action::SetSizes`]
+    pub fn confirm_resize(&mut self) -> Result<()> {
+        let resize = self.finish_resize();
+        if !resize.is_resizing {
+            return Ok(());
+        }
+        let indices = resize
+            .lines
+            .iter()
+            .map(|&(index, _)| index as u32)
+            .collect();
+        self.dispatch_action(Action::SetSizes {
+            columns: resize.resize_type == ResizeType::Column,
+            indices,
+            size: resize.current_size,
+        })
+    }
+
+    /// Drop the resize in progress, giving every line the size it had
+    pub fn cancel_resize(&mut self) {
+        let resize = self.finish_resize();
+        if resize.is_resizing {
+            self.size_lines(resize.resize_type, &resize.lines);
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+        }
+    }
+
+    /// Commit the resize in progress and go on resizing the column or row
+    /// next to the grabbed one, by itself
+    fn move_resize_target(&mut self, direction: ResizeMoveDirection) -> Result<()> {
+        let (resize_type, index) = (
+            self.resize_state.resize_type,
+            self.resize_state.resize_index,
+        );
+        let index = match direction {
+            ResizeMoveDirection::Previous => index.checked_sub(1),
+            ResizeMoveDirection::Next => Some(index + 1),
+        };
+        let Some(index) = index.map(|index| index as u32) else {
+            return Ok(());
+        };
+        let target = match resize_type {
+            ResizeType::Column => ResizeTarget::Column { index },
+            ResizeType::Row => ResizeTarget::Row { index },
+            ResizeType::None => return Ok(()),
+        };
+        let position = self.resize_state.start_position;
+        self.confirm_resize()?;
+        self.selection = None;
+        self.start_resize(target, position)
+    }
+
+    /// End the resize in progress, back in navigation, returning it
+    fn finish_resize(&mut self) -> ResizeState {
+        let resize = std::mem::take(&mut self.resize_state);
+        self.resize_behavior.reset();
+        if self.mode == EditorMode::Resizing {
+            self.mode = EditorMode::Navigation;
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+        }
+        resize
+    }
+
+    /// Give each column or row of `lines` its size
+    fn size_lines(&mut self, resize_type: ResizeType, lines: &[(usize, f64)]) {
+        for &(index, size) in lines {
+            match resize_type {
+                ResizeType::Column => self.viewport_manager.set_column_width(index, size),
+                ResizeType::Row => self.viewport_manager.set_row_height(index, size),
+                ResizeType::None => {}
+            }
+        }
+    }
+
+    /// Move, grow or shrink folds with rows inserted or deleted by
+    /// `operation`, along with the rows they hide
+    fn follow_folds(&mut self, operation: &StructuralOperation) {
+        if self.folds.is_empty() && self.fold_hidden_rows.is_empty() {
+            return;
+        }
+        self.folds.apply_structural_operation(operation);
+        // The viewport already moved the hidden rows themselves
+        self.fold_hidden_rows = self
+            .fold_hidden_rows
+            .iter()
+            .filter_map(|&row| operation.shift_address(&CellAddress::new(0, row)))
+            .map(|address| address.row)
+            .collect();
+        self.sync_fold_rows();
+    }
+
+    /// Hide the rows closed folds cover and show those they no longer do,
+    /// leaving alone rows the user hid
+    fn sync_fold_rows(&mut self) {
+        let covered = self.folds.hidden_rows();
+        for &row in self.fold_hidden_rows.difference(&covered) {
+            self.viewport_manager
+                .set_rows_hidden(row as usize, row as usize, false);
+        }
+        let mut hidden = BTreeSet::new();
+        for row in covered {
+            if self.fold_hidden_rows.contains(&row)
+                || self
+                    .viewport_manager
+                    .set_rows_hidden(row as usize, row as usize, true)
+                    > 0
+            {
+                hidden.insert(row);
+            }
+        }
+        self.fold_hidden_rows = hidden;
+        self.viewport_cache.clear();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+    }
+
+    /// First and last row of the selection, or the cursor's row
+    pub(super) fn selected_rows(&self) -> (u32, u32) {
+        match &self.selection {
+            Some(selection) => self
+                .selection_ranges(selection)
+                .iter()
+                .fold((u32::MAX, 0), |(start, end), range| {
+                    (start.min(range.start.row), end.max(range.end.row))
+                }),
+            None => (self.cursor.row, self.cursor.row),
+        }
+    }
+
+    /// Move the cursor down to the start of the next fold, or up to the
+    /// end of the previous one
+    fn goto_fold_boundary(&mut self, forward: bool) -> Result<()> {
+        let row = self.cursor.row;
+        let target = if forward {
+            self.folds.next_start(row)
+        } else {
+            self.folds.previous_end(row)
+        };
+        match target {
+            Some(row) => self.goto_cell(CellAddress::new(self.cursor.col, row), None),
+            None => Ok(()),
+        }
+    }
+
+    /// Narrow the selection to its visible cells, one range per block
+    /// between hidden rows and columns
+    pub fn select_visible_cells(&mut self) -> Result<()> {
+        let ranges = self.visible_selected_ranges();
+        let range_selection = |range: &CellRange| SelectionType::Range {
+            start: range.start,
+            end: range.end,
+        };
+        let selection_type = match ranges.as_slice() {
+            [] => {
+                return Err(SpreadsheetError::InvalidOperation(
+                    "No visible cells in the selection".to_string(),
+                ))
+            }
+            [range] => range_selection(range),
+            ranges => SelectionType::Multi {
+                selections: ranges
+                    .iter()
+                    .map(|range| Selection {
+                        selection_type: range_selection(range),
+                        anchor: None,
+                    })
+                    .collect(),
+            },
+        };
+        let anchor = self
+            .selection
+            .as_ref()
+            .and_then(|selection| selection.anchor)
+            .or(Some(self.cursor));
+        self.set_selection(Some(Selection {
+            selection_type,
+            anchor,
+        }));
+        Ok(())
+    }
+
+    /// Clear the visible cells of the selection, or the cursor cell
+    /// without one, leaving hidden rows and columns as they are. Ends
+    /// visual mode.
+    pub fn clear_selected_cells(&mut self) -> Result<()> {
+        let cleared: Vec<CellAddress> = self
+            .visible_selected_ranges()
+            .iter()
+            .flat_map(|range| range.cells())
+            .filter(|address| self.facade.get_cell(address).is_some())
+            .collect();
+
+        let batch_id = self.facade.begin_batch()?;
+        let deleted = cleared
+            .iter()
+            .try_for_each(|address| self.facade.delete_cell(address));
+        self.facade.commit_batch(&batch_id)?;
+        deleted?;
+        self.refresh_cached_cells(&cleared);
+
+        if self.mode.is_visual() {
+            self.set_mode(EditorMode::Navigation);
+            self.set_selection(None);
+            self.dispatch_action(Action::ExitSpreadsheetVisualMode)?;
+        }
+        self.update_formula_bar_from_cursor();
+        self.refresh_watch_list();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Copy the top row of the selection into the visible rows below it,
+    /// moving formulas like a paste would. Rows and columns hidden by a
+    /// filter or fold are left alone, and each filled row's references
+    /// follow its own row number. Ends visual mode.
+    pub fn fill_down(&mut self) -> Result<()> {
+        let block = self.selected_block("fill")?;
+        if block.start.row == block.end.row {
+            return Err(SpreadsheetError::InvalidOperation(
+                "Select the rows to fill down into".to_string(),
+            ));
+        }
+        let top = CellRange::new(
+            block.start,
+            CellAddress::new(block.end.col, block.start.row),
+        );
+        let below = CellRange::new(
+            CellAddress::new(block.start.col, block.start.row + 1),
+            block.end,
+        );
+        let payload = ClipboardContents::copy(&self.facade, &top).payload;
+        let pastes = self
+            .visible_subranges(&below)
+            .iter()
+            .map(|range| payload.to_paste_tiled(range, top.start))
+            .collect();
+        self.apply_pastes(pastes)?;
+
+        if self.mode.is_visual() {
+            self.set_mode(EditorMode::Navigation);
+            self.set_selection(None);
+            self.dispatch_action(Action::ExitSpreadsheetVisualMode)?;
+        }
+        self.refresh_watch_list();
+        Ok(())
+    }
+
+    /// Rectangles covered by a selection; whole rows and columns span the grid
+    fn selection_ranges(&self, selection: &Selection) -> Vec<CellRange> {
+        let max_col = self.config.total_cols.saturating_sub(1) as u32;
+        let max_row = self.config.total_rows.saturating_sub(1) as u32;
+
+        match &selection.selection_type {
+            SelectionType::Cell { address } => vec![CellRange::new(*address, *address)],
+            SelectionType::Range { start, end } => vec![CellRange::new(
+                CellAddress::new(start.col.min(end.col), start.row.min(end.row)),
+                CellAddress::new(start.col.max(end.col), start.row.max(end.row)),
+            )],
+            SelectionType::Row { rows } => rows
+                .iter()
+                .map(|&row| {
+                    CellRange::new(CellAddress::new(0, row), CellAddress::new(max_col, row))
+                })
+                .collect(),
+            SelectionType::Column { columns } => columns
+                .iter()
+                .map(|&col| {
+                    CellRange::new(CellAddress::new(col, 0), CellAddress::new(col, max_row))
+                })
+                .collect(),
+            SelectionType::Multi { selections } => selections
+                .iter()
+                .flat_map(|selection| self.selection_ranges(selection))
+                .collect(),
+        }
+    }
+
+    /// Requests from FETCH formulas that the host has to perform
+    pub fn take_external_requests(&self) -> Vec<ExternalRequest> {
+        self.facade.take_external_requests()
+    }
+
+    /// Requests the host may abort because no formula reads them any more
+    pub fn take_cancelled_external_requests(&self) -> Vec<ExternalRequest> {
+        self.facade.take_cancelled_external_requests()
+    }
+
+    /// Deliver the host's response for `request` and redraw the cells it changed
+    pub fn resolve_external(
+        &mut self,
+        request: &ExternalRequest,
+        response: std::result::Result<String, String>,
+    ) -> Result<()> {
+        let changed = self.facade.resolve_external(request, response)?;
+        self.cells_recalculated(changed);
+        Ok(())
+    }
+
+    /// Resolve every pending request with a synchronous resolver
+    pub fn resolve_external_with(&mut self, resolver: &dyn ExternalResolver) -> Result<()> {
+        let changed = self.facade.resolve_external_with(resolver)?;
+        self.cells_recalculated(changed);
+        Ok(())
+    }
+
+    /// Queue every external request again. Listeners to `StateChanged` pick
+    /// the requests up with [`Self::take_external_requests`].
+    pub fn refresh_external(&mut self) -> usize {
+        let queued = self.facade.refresh_external();
+        if queued > 0 {
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::StateChanged);
+        }
+        queued
+    }
+
+    /// Recalculate the stale formulas of the active sheet, or only those
+    /// inside `range`, and report how many were recalculated
+    pub fn recalculate_stale(&mut self, range: Option<&CellRange>) -> Result<()> {
+        let recalculation = match range {
+            Some(range) => self.facade.recalculate_range(range)?,
+            None => self.facade.recalculate_sheet(&self.get_active_sheet())?,
+        };
+
+        let mut message = format!(
+            "Recalculated {} formula(s)",
+            recalculation.recalculated.len()
+        );
+        if recalculation.cancelled {
+            message = format!(
+                "Recalculation cancelled after {} formula(s), the rest stay stale",
+                recalculation.recalculated.len()
+            );
+        } else if let Some(first) = recalculation.stale.first() {
+            message.push_str(&format!(
+                ", {} still stale from reading outside the range (first {})",
+                recalculation.stale.len(),
+                first
+            ));
+        }
+        self.add_error(message, crate::controller::events::ErrorSeverity::Info);
+        self.viewport_cache.clear();
+        self.update_formula_bar_from_cursor();
+        self.refresh_watch_list();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Progress of the recalculation running, `None` when none runs or it
+    /// is too small to be reported. Formulas it has yet to evaluate are
+    /// stale meanwhile.
+    pub fn calculation_status(&self) -> Option<RecalculationStatus> {
+        self.facade.recalculation_monitor().status()
+    }
+
+    pub fn is_calculating(&self) -> bool {
+        self.calculation_status().is_some()
+    }
+
+    /// Stop the running recalculation, leaving what it did not reach stale
+    pub fn cancel_recalculation(&self) {
+        self.facade.recalculation_monitor().cancel();
+    }
+
+    /// Turn automatic calculation of the active sheet on or off. While it
+    /// is off, edits only mark the formulas reading them stale.
+    pub fn set_calculation_enabled(&mut self, enabled: bool) -> Result<()> {
+        let sheet = self.get_active_sheet();
+        let recalculation = self.facade.set_calculation_enabled(&sheet, enabled)?;
+        let message = if enabled {
+            format!(
+                "Calculation on for {}, recalculated {} formula(s)",
+                sheet,
+                recalculation.recalculated.len()
+            )
+        } else {
+            format!("Calculation off for {}", sheet)
+        };
+        self.add_error(message, crate::controller::events::ErrorSeverity::Info);
+        self.viewport_cache.clear();
+        self.update_formula_bar_from_cursor();
+        self.refresh_watch_list();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Check the active sheet's dependency graph, the used range the
+    /// viewport was fitted to and the error index against its cells, and
+    /// report the result. With `repair` the graph is rebuilt where it is
+    /// wrong and the viewport refitted.
+    pub fn check_health(&mut self, repair: bool) -> Result<()> {
+        let dependencies = if repair {
+            self.facade.repair_dependencies()?
+        } else {
+            self.facade.verify_dependencies()
+        };
+        let used = self.facade.get_used_extent();
+        let indexed_used = self.viewport_manager.used_range();
+        let errors = self.facade.scan_sheet_health();
+        let indexed_errors = self
+            .facade
+            .get_sheet_health(&self.get_active_sheet())
+            .unwrap_or_default();
+
+        let mut problems = Vec::new();
+        if !dependencies.is_consistent() {
+            problems.push(dependencies.to_string());
+        }
+        if indexed_used != used {
+            let show = |cell: Option<CellAddress>| cell.map_or("none".to_string(), |c| c.to_a1());
+            problems.push(format!(
+                "used range ends at {} but the cells end at {}",
+                show(indexed_used),
+                show(used)
+            ));
+        }
+        if indexed_errors != errors {
+            problems.push(format!(
+                "error index holds {} error cell(s) but the sheet has {}",
+                indexed_errors.error_cells, errors.error_cells
+            ));
+        }
+
+        if repair {
+            self.sync_grid_extent();
+            self.viewport_cache.clear();
+        }
+        let (message, severity) = match (problems.is_empty(), repair) {
+            (true, _) => (
+                "Health check passed: dependency graph, used range and error index are consistent"
+                    .to_string(),
+                crate::controller::events::ErrorSeverity::Info,
+            ),
+            (false, false) => (
+                format!("Health check found {}", problems.join("; ")),
+                crate::controller::events::ErrorSeverity::Warning,
+            ),
+            (false, true) => (
+                format!("Health check repaired {}", problems.join("; ")),
+                crate::controller::events::ErrorSeverity::Info,
+            ),
+        };
+        self.add_error(message, severity);
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+        Ok(())
+    }
+
+    /// Shrink what the facade keeps for cleared cells and drop its caches,
+    /// reporting the sizes before and after
+    pub fn compact_memory(&mut self) -> Result<()> {
+        let report = self.facade.compact(&CompactOptions::default());
+        self.add_error(
+            report.to_string(),
+            crate::controller::events::ErrorSeverity::Info,
+        );
+        Ok(())
+    }
+
+    /// Refresh after the facade recalculated `changed` cells, given with
+    /// their sheet names
+    fn cells_recalculated(&mut self, changed: Vec<(String, CellAddress)>) {
+        if changed.is_empty() {
+            return;
+        }
+        let active_sheet = self.get_active_sheet();
+        let visible: Vec<CellAddress> = changed
+            .into_iter()
+            .filter(|(sheet, _)| *sheet == active_sheet)
+            .map(|(_, address)| address)
+            .collect();
+        self.refresh_cached_cells(&visible);
+        self.update_formula_bar_from_cursor();
+        self.refresh_watch_list();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::StateChanged);
+    }
+
+    /// Move the cursor to a cell, switching sheets and scrolling it into view
+    pub fn goto_cell(&mut self, address: CellAddress, sheet: Option<&str>) -> Result<()> {
+        if let Some(sheet) = sheet {
+            if sheet != self.get_active_sheet() {
+                self.set_active_sheet(sheet)?;
+            }
+        }
+
+        if !self.viewport_manager.is_visible(&address) {
+            self.viewport_manager.scroll_to_cell(&address, "center");
+        }
+        self.set_cursor(address);
+        self.update_formula_bar_from_cursor();
+        Ok(())
+    }
+
+    /// Move to the first error on a sheet in reading order. Does nothing
+    /// when the sheet has no errors.
+    pub fn goto_first_error(&mut self, sheet: &str) -> Result<()> {
+        let health = self.facade.get_sheet_health(sheet).ok_or_else(|| {
+            SpreadsheetError::InvalidOperation(format!("Sheet '{}' does not exist", sheet))
+        })?;
+        match health.first_error {
+            Some(address) => self.goto_cell(address, Some(sheet)),
+            None => Ok(()),
+        }
     }
 
-    /// Get current errors
-    pub fn get_errors(&self) -> Vec<crate::managers::ErrorEntry> {
-        self.error_system.get_errors()
+    /// Error counts for one sheet
+    pub fn get_sheet_health(&self, sheet: &str) -> Option<SheetHealth> {
+        self.facade.get_sheet_health(sheet)
     }
 
-    /// Get mutable reference to error system (mainly for tests)
-    pub fn errors(&mut self) -> &mut ErrorSystem {
-        &mut self.error_system
+    /// Error counts for every sheet, in tab order
+    pub fn get_workbook_health(&self) -> Vec<(String, SheetHealth)> {
+        self.facade.get_workbook_health()
     }
 
-    pub fn get_viewport_manager(&self) -> &ViewportManager {
-        &self.viewport_manager
+    /// Snapshot of the UI state: cursor, viewport and watch list, plus the
+    /// selection and modal when navigating or the edited text when editing
+    pub fn get_ui_state(&self) -> UIState {
+        let bounds = self.viewport_manager.get_visible_bounds();
+        let viewport = ViewportInfo {
+            start_row: bounds.start_row as u32,
+            start_col: bounds.start_col as u32,
+            rows: (bounds.end_row - bounds.start_row) as u32,
+            cols: (bounds.end_col - bounds.start_col) as u32,
+        };
+        let mut core = CoreState::new(self.cursor, viewport);
+        core.watches = self.watch_list.entries();
+        core.recent_entries = self.recent_entries.entries().to_vec();
+
+        match &self.mode {
+            EditorMode::Editing {
+                value,
+                cursor_pos,
+                insert_mode,
+            } => UIState::Editing {
+                core,
+                value: value.clone(),
+                cursor_pos: *cursor_pos,
+                mode: EditMode::Insert,
+                visual_selection: None,
+                insert_variant: *insert_mode,
+            },
+            EditorMode::CellEditing {
+                value,
+                cursor_pos,
+                mode,
+                visual_anchor,
+            } => {
+                let (edit_mode, insert_variant, visual_mode) = match mode {
+                    CellEditMode::Normal => (EditMode::Normal, None, None),
+                    CellEditMode::Insert(insert) => (EditMode::Insert, Some(*insert), None),
+                    CellEditMode::Visual(visual) => (EditMode::Visual, None, Some(*visual)),
+                };
+                UIState::Editing {
+                    core,
+                    value: value.clone(),
+                    cursor_pos: *cursor_pos,
+                    mode: edit_mode,
+                    visual_selection: visual_anchor
+                        .zip(visual_mode)
+                        .map(|(start, mode)| VisualSelection { start, mode }),
+                    insert_variant,
+                }
+            }
+            _ => UIState::Navigation {
+                core,
+                selection: self.selection.clone(),
+                modal: self.navigation_modal(),
+            },
+        }
     }
 
-    pub fn get_viewport_manager_mut(&mut self) -> &mut ViewportManager {
-        &mut self.viewport_manager
+    /// The modal of a navigation mode other than plain navigation
+    fn navigation_modal(&self) -> Option<NavigationModal> {
+        match &self.mode {
+            EditorMode::Command { value } => Some(NavigationModal::Command {
+                value: value.clone(),
+            }),
+            EditorMode::Visual { mode, anchor } => Some(NavigationModal::Visual {
+                mode: *mode,
+                anchor: *anchor,
+                selection: self.selection.clone().unwrap_or(Selection {
+                    selection_type: SelectionType::Cell {
+                        address: self.cursor,
+                    },
+                    anchor: Some(*anchor),
+                }),
+            }),
+            EditorMode::Resizing => {
+                let resize = &self.resize_state;
+                let index = resize.resize_index as u32;
+                let target = match resize.resize_type {
+                    ResizeType::Column => ResizeTarget::Column { index },
+                    ResizeType::Row => ResizeTarget::Row { index },
+                    ResizeType::None => return None,
+                };
+                Some(NavigationModal::Resize {
+                    target,
+                    sizes: ResizeSizes {
+                        original_size: resize.start_size as u32,
+                        current_size: resize.current_size as u32,
+                        initial_position: resize.start_position,
+                        current_position: resize.start_position + resize.current_size
+                            - resize.start_size,
+                        resize_index: index,
+                    },
+                })
+            }
+            _ => None,
+        }
     }
 
-    pub fn get_config(&self) -> &GridConfiguration {
-        &self.config
+    /// The whole state a frontend draws, in the documented schema of
+    /// [`StateSnapshot`]
+    pub fn get_state_snapshot(&self) -> StateSnapshot {
+        StateSnapshot::new(
+            self.get_ui_state(),
+            self.get_active_sheet(),
+            self.read_only,
+            self.get_formula_bar_value(),
+        )
     }
 
-    /// Get the resize state
-    pub fn resize_state(&self) -> &ResizeState {
-        &self.resize_state
+    /// Publish a [`SpreadsheetEvent::UIStateChanged`] diff after every key,
+    /// mouse event and action that changes the UI state. Returns the
+    /// snapshot the first diff applies to.
+    pub fn enable_state_diffs(&mut self) -> StateSnapshot {
+        let snapshot = self.get_state_snapshot();
+        self.published_state = Some(snapshot.state.clone());
+        snapshot
     }
 
-    /// Get mutable access to the resize state
-    pub fn resize_state_mut(&mut self) -> &mut ResizeState {
-        &mut self.resize_state
+    pub fn disable_state_diffs(&mut self) {
+        self.published_state = None;
     }
 
-    pub fn get_current_selection_stats(&self) -> selection_stats::SelectionStats {
-        use crate::state::SelectionType;
-
-        // Get the current selection directly
-        let selection = self.get_selection();
-
-        if let Some(sel) = selection {
-            // Calculate stats based on selection type
-            match &sel.selection_type {
-                SelectionType::Range { start, end } => {
-                    selection_stats::calculate_range(&self.facade, start, end)
-                }
-                SelectionType::Cell { address } => {
-                    selection_stats::calculate_single_cell(&self.facade, address)
-                }
-                SelectionType::Column { columns: _ } => {
-                    // For column selections, calculate stats for all cells in those columns
-                    // For now, just return default stats
-                    // TODO: Implement column selection stats
-                    selection_stats::SelectionStats::default()
-                }
-                SelectionType::Row { rows: _ } => {
-                    // For row selections, calculate stats for all cells in those rows
-                    // For now, just return default stats
-                    // TODO: Implement row selection stats
-                    selection_stats::SelectionStats::default()
-                }
-                SelectionType::Multi { selections: _ } => {
-                    // For multi selections, we would need to handle multiple ranges
-                    // For now, just return default stats
-                    // TODO: Implement multi selection stats
-                    selection_stats::SelectionStats::default()
-                }
-            }
-        } else {
-            // No selection, calculate for current cursor position
-            let cursor = self.get_cursor();
-            selection_stats::calculate_single_cell(&self.facade, &cursor)
+    fn publish_state_diff(&mut self) {
+        let Some(published) = &self.published_state else {
+            return;
+        };
+        let state = self.get_ui_state();
+        if *published == state {
+            return;
         }
+        let diff = StateDiff::create(published, &state);
+        self.published_state = Some(state);
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::UIStateChanged { diff });
     }
 
     /// Get the error manager
@@ -829,6 +3892,24 @@ impl SpreadsheetController {
         self.formula_bar_manager.value()
     }
 
+    /// A warning for the editor once `value`, a formula being typed, is
+    /// within a tenth of the formula length limit or past it
+    pub fn formula_length_warning(&self, value: &str) -> Option<String> {
+        let length = value.strip_prefix('=')?.chars().count();
+        let limit = self.facade.workbook_settings().max_formula_length;
+        if length > limit {
+            Some(format!(
+                "Formula is {} characters over the {} limit",
+                length - limit,
+                limit
+            ))
+        } else if length * 10 >= limit * 9 {
+            Some(format!("Formula is {} of {} characters", length, limit))
+        } else {
+            None
+        }
+    }
+
     /// Set the formula bar value and dispatch event
     pub fn set_formula_bar_value(&mut self, value: String) {
         let event = {
@@ -838,6 +3919,219 @@ impl SpreadsheetController {
         self.event_dispatcher.dispatch(&event);
     }
 
+    /// Report a stored entry: its events, its status messages and the
+    /// autocorrection made to it, if any
+    fn finish_cell_edit(&mut self, result: &CellEditResult) {
+        for (event, error_info) in result.create_events() {
+            self.event_dispatcher.dispatch(&event);
+            if let Some((msg, severity)) = error_info {
+                self.error_system.add_error(msg, severity);
+            }
+        }
+        if let CellEditResult::Success { address, .. }
+        | CellEditResult::SuccessWithError { address, .. } = result
+        {
+            self.last_autocorrection = result
+                .autocorrection()
+                .map(|autocorrection| (*address, autocorrection.clone()));
+            // Only text is offered again: not numbers, dates or formulas
+            if let Some(CellValue::String(text)) = self.facade.get_cell_raw_value(address) {
+                let sheet = self.facade.get_active_sheet();
+                self.recent_entries.record(&sheet, &text);
+            }
+        }
+        self.entry_completion = None;
+        self.entry_picker = None;
+    }
+
+    /// Entries committed recently, most recent first
+    pub fn get_recent_entries(&self) -> &RecentEntries {
+        &self.recent_entries
+    }
+
+    /// Offer the entries of the edited sheet only, or of every sheet
+    pub fn set_recent_scope(&mut self, scope: RecentScope) {
+        self.recent_entries.set_scope(scope);
+    }
+
+    /// Values to offer for the text typed so far in the cursor's cell:
+    /// recent entries and the other texts of its column, most used first
+    pub fn entry_suggestions(&self, typed: &str) -> Vec<String> {
+        let cells = self.facade.get_all_cells();
+        let column = cells.iter().filter_map(|(address, cell)| {
+            let other_row = address.col == self.cursor.col && address.row != self.cursor.row;
+            match cell.get_computed_value() {
+                CellValue::String(_) if other_row && !cell.has_formula() => {
+                    cell.raw_value.as_string()
+                }
+                _ => None,
+            }
+        });
+        self.recent_entries
+            .suggestions(&self.facade.get_active_sheet(), typed, column)
+    }
+
+    /// The Ctrl+Down picker, while it is open
+    pub fn entry_picker(&self) -> Option<&EntryPicker> {
+        self.insert_text().and(self.entry_picker.as_ref())
+    }
+
+    /// Text being typed into a cell, outside vim's normal and visual modes
+    fn insert_text(&self) -> Option<&str> {
+        match &self.mode {
+            EditorMode::Editing { value, .. }
+            | EditorMode::CellEditing {
+                value,
+                mode: CellEditMode::Insert(_),
+                ..
+            } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Replace the text being typed, with the text cursor at its end
+    fn set_insert_text(&mut self, text: String) {
+        let end = text.len();
+        match &mut self.mode {
+            EditorMode::Editing {
+                value, cursor_pos, ..
+            }
+            | EditorMode::CellEditing {
+                value, cursor_pos, ..
+            } => {
+                *value = text.clone();
+                *cursor_pos = end;
+            }
+            _ => return,
+        }
+        self.formula_bar = text.clone();
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::FormulaBarUpdated { value: text.into() });
+    }
+
+    /// Ctrl+N/Ctrl+P cycle through the suggestions for the typed text in
+    /// place, Ctrl+Down lists them, and while listed the arrows choose one,
+    /// Enter commits it and Escape closes the list. Returns whether the key
+    /// was used.
+    fn handle_recent_entry_key(&mut self, key: &str, ctrl: bool, alt: bool) -> Result<bool> {
+        let Some(typed) = self.insert_text().map(str::to_string) else {
+            self.entry_completion = None;
+            self.entry_picker = None;
+            return Ok(false);
+        };
+        // Pressing a modifier on its own keeps the list open and the cycle going
+        if matches!(key, "Control" | "Shift" | "Alt" | "Meta") {
+            return Ok(false);
+        }
+
+        if let Some(picker) = &mut self.entry_picker {
+            match key {
+                "ArrowDown" | "ArrowUp" if !alt => {
+                    picker.move_selection(key == "ArrowUp");
+                    self.event_dispatcher
+                        .dispatch(&SpreadsheetEvent::StateChanged);
+                    return Ok(true);
+                }
+                "Enter" if !ctrl && !alt => {
+                    let text = picker.selected_text().to_string();
+                    self.entry_picker = None;
+                    self.set_insert_text(text);
+                    self.commit_and_move(CommitKey::Enter)?;
+                    return Ok(true);
+                }
+                "Escape" => {
+                    self.entry_picker = None;
+                    self.event_dispatcher
+                        .dispatch(&SpreadsheetEvent::StateChanged);
+                    return Ok(true);
+                }
+                _ => {
+                    self.entry_picker = None;
+                    self.event_dispatcher
+                        .dispatch(&SpreadsheetEvent::StateChanged);
+                }
+            }
+        }
+        if !ctrl || alt {
+            return Ok(false);
+        }
+
+        match key {
+            "ArrowDown" => {
+                self.entry_completion = None;
+                self.entry_picker = EntryPicker::new(self.entry_suggestions(&typed));
+                if self.entry_picker.is_none() {
+                    self.add_error(
+                        "No recent entries match".to_string(),
+                        crate::controller::events::ErrorSeverity::Info,
+                    );
+                }
+                self.event_dispatcher
+                    .dispatch(&SpreadsheetEvent::StateChanged);
+                Ok(true)
+            }
+            "n" | "N" | "p" | "P" => {
+                // Typing since the last step starts over from the new text
+                let completion = match self.entry_completion.take() {
+                    Some(completion) if completion.current() == typed => completion,
+                    _ => EntryCompletion::new(typed.clone(), self.entry_suggestions(&typed)),
+                };
+                let completion = self.entry_completion.insert(completion);
+                let text = completion.step(key.eq_ignore_ascii_case("p")).to_string();
+                self.set_insert_text(text);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// The cell and correction of the last autocorrected entry
+    pub fn last_autocorrection(&self) -> Option<&(CellAddress, Autocorrection)> {
+        self.last_autocorrection.as_ref()
+    }
+
+    /// Put back the formula as typed before the last autocorrection, as
+    /// long as its cell still holds the corrected one
+    pub fn revert_autocorrection(&mut self) -> Result<()> {
+        if self.read_only {
+            self.refuse_change();
+            return Ok(());
+        }
+        let Some((address, autocorrection)) = self.last_autocorrection.take() else {
+            self.add_error(
+                "Nothing was autocorrected".to_string(),
+                crate::controller::events::ErrorSeverity::Info,
+            );
+            return Ok(());
+        };
+        let unchanged = self
+            .facade
+            .get_cell(&address)
+            .is_some_and(|cell| cell.raw_value.to_string() == autocorrection.corrected);
+        if !unchanged {
+            self.add_error(
+                format!("{} changed since it was autocorrected", address),
+                crate::controller::events::ErrorSeverity::Warning,
+            );
+            return Ok(());
+        }
+        self.facade
+            .set_cell_value(&address, &autocorrection.original)?;
+        self.refresh_cached_cells(&[address]);
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::CellEditCompleted {
+                address,
+                value: autocorrection.original.as_str().into(),
+            });
+        self.update_formula_bar_from_cursor();
+        self.refresh_watch_list();
+        self.add_error(
+            format!("Restored {}", autocorrection.original),
+            crate::controller::events::ErrorSeverity::Info,
+        );
+        Ok(())
+    }
+
     /// Update formula bar based on current cursor position
     pub fn update_formula_bar_from_cursor(&mut self) {
         let cursor = self.cursor();
@@ -869,6 +4163,10 @@ impl SpreadsheetController {
     /// Set the active sheet
     pub fn set_active_sheet(&mut self, sheet_name: &str) -> Result<()> {
         self.facade.set_active_sheet(sheet_name)?;
+        self.viewport_cache.clear();
+        // Warnings are about the cells of the sheet they were found on
+        self.lint_warnings.clear();
+        self.sync_grid_extent();
         self.event_dispatcher
             .dispatch(&SpreadsheetEvent::SheetChanged {
                 from: self.get_active_sheet(),
@@ -884,6 +4182,7 @@ impl SpreadsheetController {
             .dispatch(&SpreadsheetEvent::SheetAdded {
                 name: name.to_string(),
             });
+        self.note_sheets_edited(&[name]);
         Ok(())
     }
 
@@ -894,17 +4193,22 @@ impl SpreadsheetController {
             .dispatch(&SpreadsheetEvent::SheetRemoved {
                 name: name.to_string(),
             });
+        self.refresh_watch_list();
+        self.note_sheets_edited(&[name]);
         Ok(())
     }
 
     /// Rename a sheet
     pub fn rename_sheet(&mut self, old_name: &str, new_name: &str) -> Result<()> {
         self.facade.rename_sheet(old_name, new_name)?;
+        self.watch_list.rename_sheet(old_name, new_name);
+        self.recent_entries.rename_sheet(old_name, new_name);
         self.event_dispatcher
             .dispatch(&SpreadsheetEvent::SheetRenamed {
                 old_name: old_name.to_string(),
                 new_name: new_name.to_string(),
             });
+        self.note_sheets_edited(&[old_name, new_name]);
         Ok(())
     }
 
@@ -913,6 +4217,94 @@ impl SpreadsheetController {
         self.facade.sheet_count()
     }
 
+    /// Which sheets changed since the last save, how many changes that took
+    /// and when the last one was made
+    pub fn save_state(&self) -> &SaveState {
+        &self.save_state
+    }
+
+    /// Whether anything changed since the last save
+    pub fn is_document_modified(&self) -> bool {
+        self.save_state.is_modified()
+    }
+
+    /// Take the current content of every sheet as saved, once the host has
+    /// written the document out
+    pub fn mark_saved(&mut self) {
+        let sheets = self.get_sheets().into_iter().map(|(name, _)| name);
+        let hashes = self.input_hashes(sheets);
+        self.update_save_state(|state| state.mark_saved(hashes, true));
+    }
+
+    /// Take the current content of `sheets` as saved, for hosts that write
+    /// sheets out one at a time. Other sheets keep their flags.
+    pub fn mark_sheets_saved(&mut self, sheets: &[&str]) {
+        let hashes = self.input_hashes(sheets.iter().map(|sheet| sheet.to_string()));
+        self.update_save_state(|state| state.mark_saved(hashes, false));
+    }
+
+    /// Compare `sheets` with their saved state after an operation that may
+    /// have changed them. Sheets that no longer exist count as removed.
+    pub(super) fn note_sheets_edited(&mut self, sheets: &[&str]) {
+        self.content_generation += 1;
+        let now = chrono::Utc::now();
+        let hashes: Vec<(&str, Option<u64>)> = sheets
+            .iter()
+            .map(|sheet| (*sheet, self.facade.sheet_input_hash(sheet)))
+            .collect();
+        self.update_save_state(|state| {
+            for (sheet, hash) in hashes {
+                state.note(sheet, hash, now);
+            }
+        });
+    }
+
+    pub(super) fn note_active_sheet_edited(&mut self) {
+        let active_sheet = self.get_active_sheet();
+        self.note_sheets_edited(&[&active_sheet]);
+    }
+
+    /// [`note_sheets_edited`](Self::note_sheets_edited) for every sheet,
+    /// after operations that may touch any of them
+    pub(super) fn note_workbook_edited(&mut self) {
+        let mut sheets: Vec<String> = self
+            .get_sheets()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        sheets.extend(self.save_state.sheets().map(str::to_string));
+        sheets.sort();
+        sheets.dedup();
+        let sheets: Vec<&str> = sheets.iter().map(String::as_str).collect();
+        self.note_sheets_edited(&sheets);
+    }
+
+    pub(super) fn input_hashes(
+        &self,
+        sheets: impl IntoIterator<Item = String>,
+    ) -> Vec<(String, u64)> {
+        sheets
+            .into_iter()
+            .filter_map(|sheet| {
+                let hash = self.facade.sheet_input_hash(&sheet)?;
+                Some((sheet, hash))
+            })
+            .collect()
+    }
+
+    /// Apply `update` to the save state and tell listeners if any flag moved
+    fn update_save_state(&mut self, update: impl FnOnce(&mut SaveState)) {
+        let revision = self.save_state.revision();
+        update(&mut self.save_state);
+        if self.save_state.revision() != revision {
+            self.event_dispatcher
+                .dispatch(&SpreadsheetEvent::ModifiedChanged {
+                    modified_sheets: self.save_state.modified_sheets(),
+                    document_modified: self.save_state.is_modified(),
+                });
+        }
+    }
+
     pub fn subscribe_to_events<F>(&mut self, listener: F) -> usize
     where
         F: Fn(&SpreadsheetEvent) + Send + 'static,
@@ -924,41 +4316,175 @@ impl SpreadsheetController {
         self.event_dispatcher.unsubscribe(index)
     }
 
+    /// Drop every event listener and callback, e.g. before the controller
+    /// is discarded while subscribers still hold state
+    pub fn clear_event_listeners(&mut self) {
+        self.event_dispatcher.clear()
+    }
+
     // High-level keyboard handling
     pub fn handle_keyboard_event(&mut self, event: KeyboardEvent) -> Result<()> {
-        super::input_handler::InputHandler::new(self).handle_keyboard_event(event)
+        let result = super::input_handler::InputHandler::new(self).handle_keyboard_event(event);
+        self.publish_state_diff();
+        result
     }
 
     pub fn complete_editing(&mut self) -> Result<()> {
         log::debug!("complete_editing called, current mode: {:?}", self.mode);
 
-        // Use CellEditor to complete editing with new architecture
-        if let Some(result) =
-            CellEditor::submit_cell_edit_direct(&self.mode, self.cursor, &mut self.facade)
+        // A formula may only break lines inside its string literals; keep
+        // the editor open so the entry can be fixed
+        if let EditorMode::Editing { value, .. } | EditorMode::CellEditing { value, .. } =
+            &self.mode
         {
-            log::debug!("CellEditor returned a result for editing completion");
+            if CellEditor::has_bare_line_break(value) {
+                self.add_error(
+                    "Formulas can only contain line breaks inside quoted text".to_string(),
+                    crate::controller::events::ErrorSeverity::Error,
+                );
+                return Ok(());
+            }
+        }
 
-            // Process events from result
-            for (event, error_info) in result.create_events() {
-                self.event_dispatcher.dispatch(&event);
-                if let Some((msg, severity)) = error_info {
-                    self.error_system.add_error(msg, severity);
+        // Strict mode keeps the editor open on a formula that does not
+        // parse, until it is fixed or committed again as text
+        let entry = match &self.mode {
+            EditorMode::Editing { value, .. } | EditorMode::CellEditing { value, .. } => {
+                Some(value.clone())
+            }
+            _ => None,
+        };
+        if let Some(entry) = entry {
+            match self.strict_gate(self.cursor, &entry) {
+                StrictGate::Commit => {}
+                StrictGate::Blocked => return Ok(()),
+                StrictGate::AsText(text) => {
+                    if let EditorMode::Editing {
+                        value, cursor_pos, ..
+                    }
+                    | EditorMode::CellEditing {
+                        value, cursor_pos, ..
+                    } = &mut self.mode
+                    {
+                        *cursor_pos = text.len();
+                        *value = text;
+                    }
+                }
+            }
+        }
+
+        // Plugins see editor commits as SubmitCellEdit and may veto or
+        // rewrite them
+        let committed = match &self.mode {
+            EditorMode::Editing { value, .. } | EditorMode::CellEditing { value, .. }
+                if !self.plugins.is_empty() =>
+            {
+                Some(value.clone())
+            }
+            _ => None,
+        };
+        if let Some(value) = &committed {
+            let action = Action::SubmitCellEdit {
+                value: value.clone(),
+            };
+            match self.plugins_before(action)? {
+                None => return self.cancel_editing(),
+                Some(Action::SubmitCellEdit { value: rewritten }) if rewritten != *value => {
+                    if let EditorMode::Editing {
+                        value, cursor_pos, ..
+                    }
+                    | EditorMode::CellEditing {
+                        value, cursor_pos, ..
+                    } = &mut self.mode
+                    {
+                        *cursor_pos = rewritten.len();
+                        *value = rewritten;
+                    }
                 }
+                Some(_) => {}
             }
+        }
+
+        // Use CellEditor to complete editing with new architecture
+        if let Some(result) = CellEditor::submit_cell_edit_direct(
+            &self.mode,
+            self.cursor,
+            &mut self.facade,
+            &self.formula_translator,
+        ) {
+            log::debug!("CellEditor returned a result for editing completion");
+            self.refresh_cached_cells(&[self.cursor]);
+
+            self.finish_cell_edit(&result);
 
             // Update formula bar to reflect new value
             self.update_formula_bar_from_cursor();
+            self.refresh_watch_list();
 
             // Exit editing mode
             self.mode = EditorMode::Navigation;
+            self.settle_edit_conflicts();
 
             log::debug!("Editing completed, mode now: {:?}", self.mode);
         } else {
             log::debug!("CellEditor returned None - not in editing mode?");
         }
+        match committed {
+            Some(value) => self.plugins_after(&Action::SubmitCellEdit { value }),
+            None => Ok(()),
+        }
+    }
+
+    /// Commit the edit, then move the cursor as `key` asks: along the
+    /// Enter direction or sideways for Tab, reversed with Shift, and
+    /// cycling within the selected range when there is one
+    pub fn commit_and_move(&mut self, key: CommitKey) -> Result<()> {
+        if !self.mode.is_editing() {
+            return Ok(());
+        }
+        let from = self.cursor;
+        self.complete_editing()?;
+        if self.mode.is_editing() {
+            return Ok(());
+        }
+
+        let limits = (
+            self.config.total_cols.saturating_sub(1) as u32,
+            self.config.total_rows.saturating_sub(1) as u32,
+        );
+        let block = self.commit_block();
+        let next = self
+            .entry_navigation
+            .next_cell(key, from, block, limits, self.vim_enabled);
+        if next != from {
+            self.set_cursor(next);
+        }
         Ok(())
     }
 
+    /// Corners of the selected range when the cursor is inside it
+    fn commit_block(&self) -> Option<(CellAddress, CellAddress)> {
+        let SelectionType::Range { start, end } = &self.selection.as_ref()?.selection_type else {
+            return None;
+        };
+        let top_left = CellAddress::new(start.col.min(end.col), start.row.min(end.row));
+        let bottom_right = CellAddress::new(start.col.max(end.col), start.row.max(end.row));
+        let inside = (top_left.col..=bottom_right.col).contains(&self.cursor.col)
+            && (top_left.row..=bottom_right.row).contains(&self.cursor.row);
+        inside.then_some((top_left, bottom_right))
+    }
+
+    /// Where Enter moves the cursor after a commit
+    pub fn enter_direction(&self) -> EnterDirection {
+        self.entry_navigation.enter_direction(self.vim_enabled)
+    }
+
+    /// Override where Enter moves the cursor, or `None` to go back to the
+    /// default of moving down, or staying put when vim is enabled
+    pub fn set_enter_direction(&mut self, direction: Option<EnterDirection>) {
+        self.entry_navigation.set_enter_direction(direction);
+    }
+
     pub fn cancel_editing(&mut self) -> Result<()> {
         // Cancel editing without saving - just exit editing mode
         if matches!(
@@ -970,6 +4496,9 @@ impl SpreadsheetController {
 
             // Exit editing mode without saving
             self.mode = EditorMode::Navigation;
+            self.entry_completion = None;
+            self.entry_picker = None;
+            self.blocked_entry = None;
 
             // Dispatch event to notify UI
             self.event_dispatcher
@@ -980,13 +4509,203 @@ impl SpreadsheetController {
             // Also dispatch StateChanged to update the UI mode indicator
             self.event_dispatcher
                 .dispatch(&SpreadsheetEvent::StateChanged);
+
+            self.settle_edit_conflicts();
+        }
+        Ok(())
+    }
+
+    // Editing settings, previews and programmatic writes
+    /// Whether vim-style modal editing is enabled
+    pub fn is_vim_enabled(&self) -> bool {
+        self.vim_enabled
+    }
+
+    /// Custom navigation-mode key bindings
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Translator between stored formulas and the display convention
+    pub fn formula_translator(&self) -> &FormulaTranslator {
+        &self.formula_translator
+    }
+
+    /// Change how formulas are shown and typed. Stored formulas are
+    /// unaffected; the formula bar is refreshed to the new convention.
+    pub fn set_formula_translator(&mut self, translator: FormulaTranslator) {
+        self.formula_translator = translator;
+        if !self.mode.is_editing() {
+            self.update_formula_bar_from_cursor();
+        }
+    }
+
+    /// Policy applied when a programmatic write hits the cell being edited
+    pub fn edit_conflict_policy(&self) -> EditConflictPolicy {
+        self.edit_guard.policy()
+    }
+
+    pub fn set_edit_conflict_policy(&mut self, policy: EditConflictPolicy) {
+        self.edit_guard.set_policy(policy);
+    }
+
+    /// Get the edit guard holding deferred writes for the current edit
+    pub fn get_edit_guard(&self) -> &EditGuard {
+        &self.edit_guard
+    }
+
+    /// Whether the cell being edited was changed underneath the editor
+    pub fn is_edit_stale(&self) -> bool {
+        self.edit_guard.is_stale()
+    }
+
+    /// What the formula being edited would evaluate to, or in visual mode
+    /// the smallest complete part of it covering the selection
+    pub fn formula_preview(&self) -> Option<FormulaPreview> {
+        match &self.mode {
+            EditorMode::CellEditing {
+                value,
+                cursor_pos,
+                mode: CellEditMode::Visual(_),
+                visual_anchor: Some(anchor),
+            } => {
+                // Visual selections include the character under the cursor
+                let end = (*anchor.max(cursor_pos) + 1).min(value.len());
+                self.preview_formula_selection(*anchor.min(cursor_pos), end)
+            }
+            EditorMode::Editing { value, .. } | EditorMode::CellEditing { value, .. } => {
+                formula_preview::preview(
+                    &self.facade,
+                    &self.formula_translator,
+                    value,
+                    &self.cursor,
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// Value of the smallest complete part of the edited formula covering
+    /// the byte offsets `start..end`, e.g. text selected with the mouse
+    pub fn preview_formula_selection(&self, start: usize, end: usize) -> Option<FormulaPreview> {
+        let (EditorMode::Editing { value, .. } | EditorMode::CellEditing { value, .. }) =
+            &self.mode
+        else {
+            return None;
+        };
+        formula_preview::preview_selection(
+            &self.facade,
+            &self.formula_translator,
+            value,
+            start..end,
+            &self.cursor,
+        )
+    }
+
+    /// Write a cell on behalf of a script, demo or embedder.
+    ///
+    /// Unlike writing through the facade directly, this respects the edit
+    /// conflict policy when the target is the cell currently being edited.
+    pub fn write_cell(&mut self, address: &CellAddress, value: &str) -> Result<()> {
+        self.write_cells(&[(*address, value.to_string())])
+    }
+
+    /// Write several cells, applying the edit conflict policy to any write
+    /// that targets the cell being edited
+    pub fn write_cells(&mut self, writes: &[(CellAddress, String)]) -> Result<()> {
+        let addresses: Vec<CellAddress> = writes.iter().map(|(address, _)| *address).collect();
+        let edited = self
+            .mode
+            .is_editing()
+            .then_some(self.cursor)
+            .filter(|cursor| writes.iter().any(|(address, _)| address == cursor));
+
+        let Some(edited) = edited else {
+            for (address, value) in writes {
+                self.facade.set_cell_value(address, value)?;
+            }
+            self.refresh_cached_cells(&addresses);
+            self.refresh_watch_list();
+            return Ok(());
+        };
+
+        let policy = self.edit_guard.policy();
+        match policy {
+            EditConflictPolicy::Defer => {
+                for (address, value) in writes {
+                    if *address == edited {
+                        self.edit_guard.defer(*address, value.clone());
+                    } else {
+                        self.facade.set_cell_value(address, value)?;
+                    }
+                }
+            }
+            EditConflictPolicy::CancelEdit => {
+                self.cancel_editing()?;
+                self.add_error(
+                    format!(
+                        "Edit of {} was cancelled because the cell was changed",
+                        edited
+                    ),
+                    crate::controller::events::ErrorSeverity::Warning,
+                );
+                for (address, value) in writes {
+                    self.facade.set_cell_value(address, value)?;
+                }
+            }
+            EditConflictPolicy::MarkStale => {
+                for (address, value) in writes {
+                    self.facade.set_cell_value(address, value)?;
+                }
+                self.edit_guard.mark_stale();
+            }
         }
+
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::EditConflict {
+                address: edited,
+                policy,
+            });
+        self.refresh_cached_cells(&addresses);
+        self.refresh_watch_list();
         Ok(())
     }
 
+    /// Once no edit is open, apply any writes that were held back during it
+    fn settle_edit_conflicts(&mut self) {
+        if self.mode.is_editing() {
+            return;
+        }
+
+        let pending = self.edit_guard.finish();
+        if pending.is_empty() {
+            return;
+        }
+
+        let mut addresses = Vec::with_capacity(pending.len());
+        for (address, value) in pending {
+            if let Err(e) = self.facade.set_cell_value(&address, &value) {
+                self.add_error(
+                    format!("Deferred write to {} failed: {}", address, e),
+                    crate::controller::events::ErrorSeverity::Error,
+                );
+                continue;
+            }
+            addresses.push(address);
+        }
+
+        self.update_formula_bar_from_cursor();
+        self.refresh_cached_cells(&addresses);
+        self.event_dispatcher
+            .dispatch(&SpreadsheetEvent::DeferredWritesApplied { addresses });
+        self.refresh_watch_list();
+    }
+
     // Mouse event handling
     pub fn handle_mouse_event(&mut self, event: MouseEvent) -> Result<()> {
-        super::input_handler::InputHandler::new(self).handle_mouse_event(event)
+        let result = super::input_handler::InputHandler::new(self).handle_mouse_event(event);
+        self.publish_state_diff();
+        result
     }
 }
 
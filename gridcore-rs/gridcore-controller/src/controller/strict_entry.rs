@@ -0,0 +1,102 @@
+//! Strict formula entry: with `strict_formulas` on in the workbook
+//! settings, a typed formula that does not parse is not committed. The
+//! editor stays open with the offending text marked and the reason on the
+//! status line. Committing the same entry again stores it as text, and
+//! Escape cancels as usual.
+
+use super::events::ErrorSeverity;
+use super::SpreadsheetController;
+use gridcore_core::formula::{autocorrect, FormulaParser};
+use gridcore_core::types::CellAddress;
+use std::ops::Range;
+
+/// An entry strict mode refused to commit
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockedEntry {
+    pub address: CellAddress,
+    /// The entry as typed, with its `=`
+    pub value: String,
+    /// Byte range of the offending text in `value`, empty where text is
+    /// missing
+    pub span: Range<usize>,
+    /// Why it does not parse, e.g. `Unexpected '*'`
+    pub message: String,
+}
+
+/// What a commit may go ahead with
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum StrictGate {
+    /// Commit the entry as typed
+    Commit,
+    /// Keep editing; the entry was refused
+    Blocked,
+    /// Commit this instead: the entry refused before, as text
+    AsText(String),
+}
+
+impl SpreadsheetController {
+    /// Decide whether `value`, about to be committed to `address`, may be.
+    /// Text and formulas that parse pass, as do those autocorrect can fix.
+    /// Others are refused once; the same entry committed again at the same
+    /// cell goes through as text.
+    pub(crate) fn strict_gate(&mut self, address: CellAddress, value: &str) -> StrictGate {
+        let settings = self.facade.workbook_settings();
+        if !settings.strict_formulas || !value.starts_with('=') {
+            self.blocked_entry = None;
+            return StrictGate::Commit;
+        }
+        // Autocorrect goes first; only what it cannot fix is refused
+        let canonical = self.formula_translator.to_canonical(value);
+        let diagnostic = match autocorrect(&canonical, &settings.autocorrect) {
+            Some(_) => None,
+            None => FormulaParser::diagnose(&canonical),
+        };
+        let Some(diagnostic) = diagnostic else {
+            self.blocked_entry = None;
+            return StrictGate::Commit;
+        };
+
+        let refused_before = self
+            .blocked_entry
+            .take()
+            .is_some_and(|blocked| blocked.address == address && blocked.value == value);
+        if refused_before {
+            return StrictGate::AsText(format!("'{}", value));
+        }
+        let span = self
+            .formula_translator
+            .display_span(&canonical, diagnostic.span);
+        self.add_error(
+            format!(
+                "{} - press Enter again to store it as text",
+                diagnostic.message
+            ),
+            ErrorSeverity::Error,
+        );
+        self.blocked_entry = Some(BlockedEntry {
+            address,
+            value: value.to_string(),
+            span: clamp_span(value, span),
+            message: diagnostic.message,
+        });
+        StrictGate::Blocked
+    }
+
+    /// The entry strict mode refused, while it is being edited
+    pub fn blocked_entry(&self) -> Option<&BlockedEntry> {
+        self.blocked_entry.as_ref()
+    }
+}
+
+/// `span` within `text`, both ends on character boundaries
+fn clamp_span(text: &str, span: Range<usize>) -> Range<usize> {
+    let floor = |mut at: usize| {
+        at = at.min(text.len());
+        while !text.is_char_boundary(at) {
+            at -= 1;
+        }
+        at
+    };
+    let start = floor(span.start);
+    start..floor(span.end).max(start)
+}
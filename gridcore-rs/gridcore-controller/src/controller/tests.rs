@@ -1,10 +1,14 @@
 #[cfg(test)]
 mod controller_tests {
-    use super::super::{KeyboardEvent, MouseEvent, SpreadsheetController};
+    use super::super::spreadsheet::READ_ONLY_MESSAGE;
+    use super::super::{FocusTarget, KeyboardEvent, MouseEvent, SpreadsheetController};
+    use crate::behaviors::range_drag::SelectionHit;
     use crate::controller::events::{ErrorSeverity, MouseEventType};
     use crate::controller::mode::{CellEditMode, EditorMode};
-    use crate::state::{InsertMode, SelectionType, VisualMode};
-    use gridcore_core::types::CellAddress;
+    use crate::state::{InsertMode, Selection, SelectionType, VisualMode};
+    use crate::theme::Palette;
+    use gridcore_core::formula::CellRange;
+    use gridcore_core::types::{CellAddress, CellValue, ErrorType};
 
     // Helper functions
     fn create_controller() -> SpreadsheetController {
@@ -402,4 +406,4129 @@ mod controller_tests {
         let errors = controller.errors().get_active_errors();
         assert_eq!(errors.len(), 0);
     }
+
+    #[test]
+    fn test_trace_arrows_from_keys_and_commands() {
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        let facade = controller.facade();
+        facade.set_cell_value(&CellAddress::new(0, 0), "1").unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(1, 0), "=A1")
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(2, 0), "=B1+A1")
+            .unwrap();
+        controller.set_cursor(CellAddress::new(1, 0));
+
+        // ]p traces B1's precedents without entering edit mode
+        controller.handle_keyboard_event(key_event("]")).unwrap();
+        controller.handle_keyboard_event(key_event("p")).unwrap();
+        assert!(matches!(controller.get_mode(), EditorMode::Navigation));
+        assert_eq!(controller.get_trace_arrows().arrows().len(), 1);
+        assert_eq!(controller.get_trace_arrow_geometry().len(), 1);
+
+        // :trace d adds B1 -> C1
+        controller.handle_keyboard_event(key_event(":")).unwrap();
+        for key in ["t", "r", "a", "c", "e", " ", "d", "Enter"] {
+            controller.handle_keyboard_event(key_event(key)).unwrap();
+        }
+        assert!(matches!(controller.get_mode(), EditorMode::Navigation));
+        let arrows = controller.get_trace_arrows().arrows();
+        assert_eq!(arrows.len(), 2);
+        assert_eq!(arrows[1].target, CellAddress::new(2, 0));
+
+        controller
+            .dispatch_action(Action::ClearTraceArrows)
+            .unwrap();
+        assert!(controller.get_trace_arrows().is_empty());
+    }
+
+    type WatchEvents = std::sync::Arc<std::sync::Mutex<Vec<(Option<String>, bool)>>>;
+
+    fn collect_watch_events(controller: &mut SpreadsheetController) -> WatchEvents {
+        use crate::controller::events::SpreadsheetEvent;
+
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        controller.subscribe_to_events(move |event| {
+            if let SpreadsheetEvent::WatchValueChanged {
+                new_value, stale, ..
+            } = event
+            {
+                sink.lock().unwrap().push((new_value.clone(), *stale));
+            }
+        });
+        events
+    }
+
+    #[test]
+    fn test_watch_receives_value_updates() {
+        let mut controller = create_controller();
+        let events = collect_watch_events(&mut controller);
+
+        // :watch B2 Total pins a cell that does not exist yet
+        controller.handle_keyboard_event(key_event(":")).unwrap();
+        for key in [
+            "w", "a", "t", "c", "h", " ", "B", "2", " ", "T", "o", "t", "a", "l", "Enter",
+        ] {
+            controller.handle_keyboard_event(key_event(key)).unwrap();
+        }
+        let watched = &controller.get_watch_list().cells()[0];
+        assert_eq!(watched.entry.qualified_address(), "Sheet1!B2");
+        assert_eq!(watched.entry.label.as_deref(), Some("Total"));
+        assert!(events.lock().unwrap().is_empty());
+
+        // Editing the cell elsewhere pushes the new value
+        controller.set_cursor(CellAddress::new(1, 1));
+        controller.handle_keyboard_event(key_event("i")).unwrap();
+        for key in ["4", "2", "Escape", "Enter"] {
+            controller.handle_keyboard_event(key_event(key)).unwrap();
+        }
+        assert_eq!(
+            events.lock().unwrap().as_slice(),
+            &[(Some("42".to_string()), false)]
+        );
+        assert_eq!(controller.get_watch_list().cells()[0].change_count, 1);
+
+        // Watching the same cell twice is a no-op
+        controller
+            .dispatch_action(crate::state::Action::AddWatch {
+                address: CellAddress::new(1, 1),
+                sheet: None,
+                label: None,
+            })
+            .unwrap();
+        assert_eq!(controller.get_watch_list().len(), 1);
+    }
+
+    #[test]
+    fn test_watch_marks_deleted_cells_and_sheets_stale() {
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        controller.add_sheet("Sheet2").unwrap();
+        controller.set_active_sheet("Sheet2").unwrap();
+        controller
+            .facade()
+            .set_cell_value(&CellAddress::new(0, 0), "7")
+            .unwrap();
+        controller.set_active_sheet("Sheet1").unwrap();
+        controller
+            .facade()
+            .set_cell_value(&CellAddress::new(2, 2), "1")
+            .unwrap();
+
+        for (sheet, address) in [
+            ("Sheet1", CellAddress::new(2, 2)),
+            ("Sheet2", CellAddress::new(0, 0)),
+        ] {
+            controller
+                .dispatch_action(Action::AddWatch {
+                    address,
+                    sheet: Some(sheet.to_string()),
+                    label: None,
+                })
+                .unwrap();
+        }
+        let events = collect_watch_events(&mut controller);
+        let cells = controller.get_watch_list().cells();
+        assert_eq!(cells[1].value.as_deref(), Some("7"));
+
+        // Deleting the cell marks it stale instead of failing
+        controller
+            .facade()
+            .delete_cell(&CellAddress::new(2, 2))
+            .unwrap();
+        controller.refresh_watch_list();
+        assert!(controller.get_watch_list().cells()[0].stale);
+
+        // So does removing the sheet of the other watch
+        controller.remove_sheet("Sheet2").unwrap();
+        let cells = controller.get_watch_list().cells();
+        assert!(cells[1].stale);
+        assert_eq!(cells[1].value, None);
+        assert_eq!(
+            events.lock().unwrap().as_slice(),
+            &[(None, true), (None, true)]
+        );
+
+        // Watches survive a round trip through the UI state snapshot
+        let restored = SpreadsheetController::with_state(controller.get_ui_state());
+        assert_eq!(
+            restored.get_watch_list().entries(),
+            controller.get_watch_list().entries()
+        );
+    }
+
+    #[test]
+    fn test_goto_cell_switches_sheet() {
+        let mut controller = create_controller();
+        controller.add_sheet("Sheet2").unwrap();
+
+        controller
+            .dispatch_action(crate::state::Action::GotoCell {
+                address: CellAddress::new(3, 250),
+                sheet: Some("Sheet2".to_string()),
+            })
+            .unwrap();
+        assert_eq!(controller.get_active_sheet(), "Sheet2");
+        assert_eq!(controller.get_cursor(), CellAddress::new(3, 250));
+        assert!(controller
+            .get_viewport_manager()
+            .is_visible(&CellAddress::new(3, 250)));
+    }
+
+    #[test]
+    fn test_goto_first_error_from_badge() {
+        let mut controller = create_controller();
+        controller.add_sheet("Sheet2").unwrap();
+        controller.set_active_sheet("Sheet2").unwrap();
+        controller
+            .write_cell(&CellAddress::new(4, 300), "=1/0")
+            .unwrap();
+        controller
+            .write_cell(&CellAddress::new(2, 120), "=1/0")
+            .unwrap();
+        controller.set_active_sheet("Sheet1").unwrap();
+
+        let summary = controller.get_workbook_health();
+        assert_eq!(summary[0].1.error_cells, 0);
+        assert_eq!(summary[1].1.error_cells, 2);
+
+        // A healthy sheet leaves the cursor alone
+        controller
+            .dispatch_action(crate::state::Action::GotoFirstError {
+                sheet: "Sheet1".to_string(),
+            })
+            .unwrap();
+        assert_eq!(controller.get_cursor(), CellAddress::new(0, 0));
+
+        controller
+            .dispatch_action(crate::state::Action::GotoFirstError {
+                sheet: "Sheet2".to_string(),
+            })
+            .unwrap();
+        assert_eq!(controller.get_active_sheet(), "Sheet2");
+        assert_eq!(controller.get_cursor(), CellAddress::new(2, 120));
+        assert!(controller
+            .get_viewport_manager()
+            .is_visible(&CellAddress::new(2, 120)));
+
+        assert!(controller
+            .dispatch_action(crate::state::Action::GotoFirstError {
+                sheet: "Missing".to_string(),
+            })
+            .is_err());
+    }
+
+    fn type_keys(controller: &mut SpreadsheetController, keys: &[&str]) {
+        for key in keys {
+            controller.handle_keyboard_event(key_event(key)).unwrap();
+        }
+    }
+
+    /// Type an ex command and run it
+    fn run_ex(controller: &mut SpreadsheetController, command: &str) {
+        controller.handle_keyboard_event(key_event(":")).unwrap();
+        for ch in command.chars() {
+            controller
+                .handle_keyboard_event(key_event(&ch.to_string()))
+                .unwrap();
+        }
+        controller
+            .handle_keyboard_event(key_event("Enter"))
+            .unwrap();
+    }
+
+    fn text_at(controller: &SpreadsheetController, a1: &str) -> CellValue {
+        controller
+            .facade()
+            .get_cell_raw_value(&CellAddress::from_a1(a1).unwrap())
+            .unwrap_or(CellValue::Empty)
+    }
+
+    #[test]
+    fn test_upper_case_column_object_skips_non_text() {
+        let mut controller = create_controller();
+        for (a1, value) in [
+            ("B1", "apple"),
+            ("B2", "42"),
+            ("B3", "=LOWER(\"X\")"),
+            ("B4", "true"),
+            ("B5", "Pear"),
+            ("B7", "kiwi"),
+        ] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+        controller.set_cursor(CellAddress::from_a1("B3").unwrap());
+
+        type_keys(&mut controller, &["g", "U", "i", "c"]);
+
+        assert_eq!(
+            text_at(&controller, "B1"),
+            CellValue::string_from_str("APPLE")
+        );
+        assert_eq!(
+            text_at(&controller, "B5"),
+            CellValue::string_from_str("PEAR")
+        );
+        assert_eq!(text_at(&controller, "B2"), CellValue::Number(42.0));
+        assert_eq!(text_at(&controller, "B4"), CellValue::Boolean(true));
+        assert!(controller
+            .facade()
+            .get_cell(&CellAddress::from_a1("B3").unwrap())
+            .unwrap()
+            .has_formula());
+        // The empty B6 ends the column object
+        assert_eq!(
+            text_at(&controller, "B7"),
+            CellValue::string_from_str("kiwi")
+        );
+
+        let status = controller.get_errors().last().unwrap().message.clone();
+        assert_eq!(status, "2 cells changed, 3 skipped");
+        assert_eq!(controller.get_cursor(), CellAddress::from_a1("B1").unwrap());
+    }
+
+    fn last_status(controller: &SpreadsheetController) -> String {
+        controller.get_errors().last().unwrap().message.clone()
+    }
+
+    #[test]
+    fn test_text_transforms_skip_formulas_and_hidden_rows() {
+        let mut controller = create_controller();
+        for (a1, value) in [
+            ("A1", "  apple pie "),
+            ("A2", " Pear"),
+            ("A3", "7"),
+            ("A4", "=A2"),
+            ("A5", "true"),
+            ("A6", "  kiwi"),
+        ] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+        select_range(&mut controller, "A6:A6");
+        run_ex(&mut controller, "hide");
+
+        select_range(&mut controller, "A1:A6");
+        run_ex(&mut controller, "trim");
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("apple pie")
+        );
+        assert_eq!(
+            text_at(&controller, "A2"),
+            CellValue::string_from_str("Pear")
+        );
+        assert_eq!(text_at(&controller, "A3"), CellValue::Number(7.0));
+        assert_eq!(
+            text_at(&controller, "A6"),
+            CellValue::string_from_str("  kiwi")
+        );
+        assert_eq!(last_status(&controller), "Transformed 2 cells, skipped 3");
+        assert!(controller.get_mode().is_navigation());
+        assert!(controller.get_selection().is_none());
+
+        // Without a selection only the cursor cell is transformed
+        controller.set_cursor(CellAddress::from_a1("A1").unwrap());
+        run_ex(&mut controller, "titlecase");
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("Apple Pie")
+        );
+        assert_eq!(
+            text_at(&controller, "A2"),
+            CellValue::string_from_str("Pear")
+        );
+        assert_eq!(last_status(&controller), "Transformed 1 cell, skipped 0");
+
+        select_range(&mut controller, "A1:A5");
+        run_ex(&mut controller, "upper");
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("APPLE PIE")
+        );
+        // The formula reads the new value but keeps its text
+        let a4 = CellAddress::from_a1("A4").unwrap();
+        assert_eq!(
+            controller
+                .facade()
+                .get_cell(&a4)
+                .unwrap()
+                .formula_text
+                .as_deref(),
+            Some("A2")
+        );
+        assert_eq!(
+            controller
+                .facade()
+                .get_cell(&a4)
+                .unwrap()
+                .get_computed_value(),
+            CellValue::string_from_str("PEAR")
+        );
+
+        select_range(&mut controller, "A1:A2");
+        run_ex(&mut controller, "lower");
+        assert_eq!(
+            text_at(&controller, "A2"),
+            CellValue::string_from_str("pear")
+        );
+
+        controller
+            .write_cell(&CellAddress::from_a1("B1").unwrap(), "tab\there\u{200B}")
+            .unwrap();
+        controller.set_cursor(CellAddress::from_a1("B1").unwrap());
+        run_ex(&mut controller, "clean");
+        assert_eq!(
+            text_at(&controller, "B1"),
+            CellValue::string_from_str("tabhere")
+        );
+    }
+
+    #[test]
+    fn test_numeric_transforms_over_a_multi_selection() {
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        for (a1, value) in [
+            ("B1", "4.56789"),
+            ("B2", "'2.5"),
+            ("B3", "=B1*2"),
+            ("D1", "'1234"),
+            ("D2", "n/a"),
+            ("D3", "1.23456"),
+        ] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+        let range = |a1: &str| {
+            let range = CellRange::from_string(a1).unwrap();
+            Selection {
+                selection_type: SelectionType::Range {
+                    start: range.start,
+                    end: range.end,
+                },
+                anchor: Some(range.start),
+            }
+        };
+        // Overlapping parts count their shared cell once
+        let select_both = |controller: &mut SpreadsheetController| {
+            controller
+                .dispatch_action(Action::UpdateSelection {
+                    selection: Selection {
+                        selection_type: SelectionType::Multi {
+                            selections: vec![range("B1:B3"), range("D1:D3"), range("B1:B1")],
+                        },
+                        anchor: None,
+                    },
+                })
+                .unwrap();
+        };
+
+        select_both(&mut controller);
+        run_ex(&mut controller, "round 2");
+        assert_eq!(text_at(&controller, "B1"), CellValue::Number(4.57));
+        assert_eq!(text_at(&controller, "D3"), CellValue::Number(1.23));
+        assert_eq!(
+            controller
+                .facade()
+                .get_cell(&CellAddress::from_a1("B3").unwrap())
+                .unwrap()
+                .get_computed_value(),
+            CellValue::Number(9.14)
+        );
+        assert_eq!(last_status(&controller), "Transformed 2 cells, skipped 4");
+
+        select_both(&mut controller);
+        run_ex(&mut controller, "tonum");
+        assert_eq!(text_at(&controller, "B2"), CellValue::Number(2.5));
+        assert_eq!(text_at(&controller, "D1"), CellValue::Number(1234.0));
+        assert_eq!(
+            text_at(&controller, "D2"),
+            CellValue::string_from_str("n/a")
+        );
+        assert_eq!(last_status(&controller), "Transformed 2 cells, skipped 4");
+
+        // Bad arguments change nothing
+        select_both(&mut controller);
+        run_ex(&mut controller, "round x");
+        assert_eq!(text_at(&controller, "B1"), CellValue::Number(4.57));
+        assert_eq!(controller.command_completions("ro"), ["round"]);
+    }
+
+    #[test]
+    fn test_running_command_writes_next_to_the_selection() {
+        let mut controller = create_controller();
+        for (a1, value) in [("B1", "3"), ("B2", "5"), ("B3", "1"), ("B4", "7")] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+        let computed = |controller: &SpreadsheetController, a1: &str| {
+            controller
+                .facade()
+                .get_cell(&CellAddress::from_a1(a1).unwrap())
+                .unwrap()
+                .get_computed_value()
+        };
+
+        select_range(&mut controller, "B1:B4");
+        run_ex(&mut controller, "running");
+        assert_eq!(
+            controller
+                .facade()
+                .get_cell(&CellAddress::from_a1("C3").unwrap())
+                .unwrap()
+                .formula_text
+                .as_deref(),
+            Some("SUM($B$1:B3)")
+        );
+        assert_eq!(computed(&controller, "C4"), CellValue::Number(16.0));
+        assert_eq!(
+            last_status(&controller),
+            "Running sum of B1:B4 written to C1:C4"
+        );
+        assert!(controller.get_selection().is_none());
+
+        // Another column, values only
+        select_range(&mut controller, "B1:B4");
+        run_ex(&mut controller, "running max E values");
+        assert_eq!(text_at(&controller, "E2"), CellValue::Number(5.0));
+        assert_eq!(text_at(&controller, "E4"), CellValue::Number(7.0));
+
+        // Occupied targets are left alone
+        select_range(&mut controller, "B1:B4");
+        run_ex(&mut controller, "running count");
+        assert_eq!(computed(&controller, "C4"), CellValue::Number(16.0));
+
+        controller
+            .write_cell(&CellAddress::from_a1("B2").unwrap(), "10")
+            .unwrap();
+        assert_eq!(computed(&controller, "C2"), CellValue::Number(13.0));
+        assert_eq!(computed(&controller, "C4"), CellValue::Number(21.0));
+        assert_eq!(text_at(&controller, "E4"), CellValue::Number(7.0));
+    }
+
+    #[test]
+    fn test_toggle_case_round_trips_over_rows() {
+        let mut controller = create_controller();
+        controller
+            .write_cell(&CellAddress::from_a1("A1").unwrap(), "Hello Wörld")
+            .unwrap();
+        controller
+            .write_cell(&CellAddress::from_a1("C2").unwrap(), "mIxEd")
+            .unwrap();
+
+        // g~2~ covers the cursor row and the one below
+        type_keys(&mut controller, &["g", "~", "2", "~"]);
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("hELLO wÖRLD")
+        );
+        assert_eq!(
+            text_at(&controller, "C2"),
+            CellValue::string_from_str("MiXeD")
+        );
+
+        type_keys(&mut controller, &["."]);
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("Hello Wörld")
+        );
+        assert_eq!(
+            text_at(&controller, "C2"),
+            CellValue::string_from_str("mIxEd")
+        );
+    }
+
+    #[test]
+    fn test_dot_repeats_case_change_on_new_selection() {
+        let mut controller = create_controller();
+        for a1 in ["A1", "B1", "A3", "A4", "A5", "A6"] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), "text")
+                .unwrap();
+        }
+
+        // Visual selection of A1:B1, uppercased with U
+        type_keys(&mut controller, &["v", "l", "U"]);
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("TEXT")
+        );
+        assert_eq!(
+            text_at(&controller, "B1"),
+            CellValue::string_from_str("TEXT")
+        );
+        assert!(controller.get_selection().is_none());
+
+        // A taller selection repeats the change over all of it
+        controller.set_cursor(CellAddress::from_a1("A3").unwrap());
+        type_keys(&mut controller, &["v", "j", "j", "."]);
+        for a1 in ["A3", "A4", "A5"] {
+            assert_eq!(text_at(&controller, a1), CellValue::string_from_str("TEXT"));
+        }
+        assert_eq!(
+            text_at(&controller, "A6"),
+            CellValue::string_from_str("text")
+        );
+
+        // A motion repeats from wherever the cursor is
+        type_keys(&mut controller, &["g", "u", "j"]);
+        assert_eq!(
+            text_at(&controller, "A4"),
+            CellValue::string_from_str("text")
+        );
+        controller.set_cursor(CellAddress::from_a1("A5").unwrap());
+        type_keys(&mut controller, &["."]);
+        assert_eq!(
+            text_at(&controller, "A5"),
+            CellValue::string_from_str("text")
+        );
+        assert_eq!(
+            text_at(&controller, "A6"),
+            CellValue::string_from_str("text")
+        );
+    }
+
+    #[test]
+    fn test_external_data_redraws_cells() {
+        struct Prices(std::cell::Cell<f64>);
+
+        impl gridcore_core::external::ExternalResolver for Prices {
+            fn fetch(&self, _url: &str) -> std::result::Result<String, String> {
+                Ok(format!("{{\"price\": {}}}", self.0.get()))
+            }
+        }
+
+        let mut controller = create_controller();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        controller
+            .write_cell(&a1, "=FETCH(\"https://api.test/q\", \"json:$.price\")")
+            .unwrap();
+        controller.write_cell(&b1, "=A1+1").unwrap();
+        let value = |controller: &SpreadsheetController, address| {
+            controller.facade().get_cell_raw_value(&address).unwrap()
+        };
+        assert_eq!(
+            value(&controller, a1),
+            CellValue::from_error(ErrorType::GettingData)
+        );
+
+        let prices = Prices(std::cell::Cell::new(10.0));
+        controller.resolve_external_with(&prices).unwrap();
+        assert_eq!(value(&controller, a1), CellValue::Number(10.0));
+        assert_eq!(value(&controller, b1), CellValue::Number(11.0));
+
+        // Nothing is fetched again until an explicit refresh
+        prices.0.set(20.0);
+        controller.resolve_external_with(&prices).unwrap();
+        assert_eq!(value(&controller, b1), CellValue::Number(11.0));
+
+        controller
+            .dispatch_action(crate::state::Action::RefreshExternalData)
+            .unwrap();
+        assert_eq!(controller.take_external_requests().len(), 1);
+        controller
+            .resolve_external(
+                &gridcore_core::external::ExternalRequest::new(
+                    "https://api.test/q",
+                    Some("json:$.price".to_string()),
+                ),
+                Ok("{\"price\": 20}".to_string()),
+            )
+            .unwrap();
+        assert_eq!(value(&controller, b1), CellValue::Number(21.0));
+    }
+
+    #[test]
+    fn test_chart_command_publishes_selection_series() {
+        use crate::controller::events::SpreadsheetEvent;
+        use crate::state::Selection;
+        use gridcore_core::chart::SeriesOrientation;
+
+        let mut controller = create_controller();
+        let rows = [
+            ["Month", "Sales", "Cost"],
+            ["Jan", "10", "4"],
+            ["Feb", "12", "x"],
+        ];
+        for (row, cells) in rows.iter().enumerate() {
+            for (col, text) in cells.iter().enumerate() {
+                controller
+                    .write_cell(&CellAddress::new(col as u32, row as u32), text)
+                    .unwrap();
+            }
+        }
+
+        let charts = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = charts.clone();
+        controller.subscribe_to_events(move |event| {
+            if let SpreadsheetEvent::ChartRequested { data } = event {
+                sink.lock().unwrap().push(data.clone());
+            }
+        });
+
+        // Whole columns are clipped to the used rows
+        controller.set_selection(Some(Selection {
+            selection_type: SelectionType::Column {
+                columns: vec![0, 1, 2],
+            },
+            anchor: None,
+        }));
+        let data = controller.chart_data_from_selection().unwrap();
+        assert_eq!(data.orientation, SeriesOrientation::Columns);
+        assert_eq!(data.categories, vec!["Jan", "Feb"]);
+        assert_eq!(data.series[0].values, vec![Some(10.0), Some(12.0)]);
+        assert_eq!(data.series[1].values, vec![Some(4.0), None]);
+
+        controller.set_selection(None);
+        controller.handle_keyboard_event(key_event(":")).unwrap();
+        type_keys(&mut controller, &["c", "h", "a", "r", "t", " "]);
+        type_keys(&mut controller, &["A", "1", ":", "B", "3", "Enter"]);
+
+        let charts = charts.lock().unwrap();
+        assert_eq!(charts.len(), 1);
+        assert_eq!(charts[0].series.len(), 1);
+        assert_eq!(charts[0].series[0].name, "Sales");
+        assert!(controller.chart_data_from_selection().is_err());
+    }
+
+    #[test]
+    fn test_minimap_click_centres_viewport_on_cell() {
+        use crate::controller::MinimapGeometry;
+
+        let mut controller = create_controller();
+        controller
+            .write_cell(&CellAddress::new(3, 999), "last")
+            .unwrap();
+        controller
+            .get_viewport_manager_mut()
+            .set_viewport_size(400.0, 300.0);
+
+        let density = controller.minimap_density(8);
+        assert_eq!(density.extent, Some(CellAddress::new(3, 999)));
+        let bounds = controller.get_viewport_manager().get_visible_bounds();
+        let geometry = MinimapGeometry::covering(40.0, 500.0, &density, &bounds);
+        assert_eq!(geometry.rows, 1000);
+
+        let cell = controller.jump_from_minimap(&geometry, 0.0, 250.0);
+        assert_eq!(cell, CellAddress::new(0, 500));
+        let manager = controller.get_viewport_manager();
+        let scroll = manager.get_scroll_position();
+        let centre = scroll.y + manager.get_viewport_height() / 2.0;
+        assert!(centre >= manager.get_row_y(500));
+        assert!(centre <= manager.get_row_y(501));
+        assert_eq!(scroll.x, 0.0);
+
+        // Past the bottom of the minimap the jump stops at the last cell
+        let cell = controller.jump_from_minimap(&geometry, 0.0, 800.0);
+        assert_eq!(cell, CellAddress::new(0, 999));
+    }
+
+    #[test]
+    fn test_paste_moves_formulas_to_the_paste_location() {
+        use crate::behaviors::paste::PasteOptions;
+
+        let mut controller = create_controller();
+        controller.set_cursor(CellAddress::from_a1("D5").unwrap());
+        // Copied from A1:C2, with a quoted multi-line note in the last row
+        let text = "1\t2\t=A1+B1\n3\t\"4\"\t=A2+B2\n\"two\nlines\"\r\n";
+        let options = PasteOptions {
+            source: Some(CellAddress::new(0, 0)),
+            ..PasteOptions::default()
+        };
+        let conflicts = controller.paste_text(text, &options).unwrap();
+        assert_eq!(conflicts, None);
+
+        let cell = controller
+            .facade()
+            .get_cell(&CellAddress::from_a1("F6").unwrap())
+            .unwrap();
+        assert_eq!(cell.formula_text.as_deref(), Some("D6+E6"));
+        assert_eq!(text_at(&controller, "F5"), CellValue::Number(3.0));
+        assert_eq!(text_at(&controller, "F6"), CellValue::Number(7.0));
+        assert_eq!(
+            text_at(&controller, "D7"),
+            CellValue::string_from_str("two\nlines")
+        );
+    }
+
+    #[test]
+    fn test_paste_over_data_waits_for_confirmation() {
+        use crate::controller::events::SpreadsheetEvent;
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        controller
+            .write_cell(&CellAddress::new(1, 0), "keep")
+            .unwrap();
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = requests.clone();
+        controller.subscribe_to_events(move |event| {
+            if let SpreadsheetEvent::PasteNeedsConfirmation { conflicts } = event {
+                sink.lock().unwrap().push(conflicts.clone());
+            }
+        });
+
+        controller
+            .dispatch_action(Action::Paste {
+                text: "1\t=A1*2".to_string(),
+            })
+            .unwrap();
+        let conflicts = requests.lock().unwrap()[0].clone();
+        assert_eq!(conflicts.overwritten, vec![CellAddress::new(1, 0)]);
+        assert_eq!(conflicts.rows_outside, 0);
+        assert_eq!(
+            text_at(&controller, "B1"),
+            CellValue::string_from_str("keep")
+        );
+
+        controller.dispatch_action(Action::ConfirmPaste).unwrap();
+        assert_eq!(text_at(&controller, "B1"), CellValue::Number(2.0));
+
+        // Paste special stores formula-like text as text
+        controller.set_cursor(CellAddress::new(0, 3));
+        let options = crate::behaviors::paste::PasteOptions {
+            formulas: false,
+            ..Default::default()
+        };
+        controller
+            .dispatch_action(Action::PasteSpecial {
+                text: "=A1".to_string(),
+                options,
+            })
+            .unwrap();
+        assert_eq!(
+            text_at(&controller, "A4"),
+            CellValue::string_from_str("=A1")
+        );
+    }
+
+    #[test]
+    fn test_paste_past_the_length_limits() {
+        use crate::behaviors::paste::PasteOptions;
+        use gridcore_core::workbook::OverlongInput;
+        use gridcore_core::SpreadsheetError;
+
+        let mut controller = create_controller();
+        let mut settings = controller.facade().workbook_settings();
+        settings.max_text_length = 8;
+        controller.facade().set_workbook_settings(settings.clone());
+        let options = PasteOptions::default();
+
+        // Nothing of a paste with an overlong field is written
+        let refused = controller.paste_text("short\ttoo long to keep", &options);
+        assert!(matches!(
+            refused,
+            Err(SpreadsheetError::InputTooLong {
+                length: 16,
+                limit: 8,
+                formula: false
+            })
+        ));
+        assert_eq!(text_at(&controller, "A1"), CellValue::Empty);
+
+        settings.overlong_input = OverlongInput::Truncate;
+        controller.facade().set_workbook_settings(settings);
+        controller
+            .paste_text("short\ttoo long to keep\nalso too long\tok", &options)
+            .unwrap();
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("short")
+        );
+        assert_eq!(
+            text_at(&controller, "B1"),
+            CellValue::string_from_str("too long")
+        );
+        assert_eq!(
+            text_at(&controller, "A2"),
+            CellValue::string_from_str("also too")
+        );
+        let errors = controller.errors().get_active_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Text in 2 cells was cut to 8 characters");
+        assert_eq!(errors[0].severity, ErrorSeverity::Warning);
+
+        // Formulas are refused either way, before asking to overwrite
+        controller.set_cursor(CellAddress::new(0, 4));
+        assert_eq!(
+            controller.paste_text("=SUM(1,2,3)", &options).unwrap(),
+            None
+        );
+        let mut settings = controller.facade().workbook_settings();
+        settings.max_formula_length = 8;
+        controller.facade().set_workbook_settings(settings);
+        assert!(controller.paste_text("=SUM(1,2,3)", &options).is_err());
+        assert!(controller.pending_paste.is_empty());
+    }
+
+    #[test]
+    fn test_formula_length_warning() {
+        let controller = create_controller();
+        let formula = |length: usize| format!("={}", "1".repeat(length));
+        assert_eq!(controller.formula_length_warning(&formula(7_000)), None);
+        assert_eq!(controller.formula_length_warning(&"x".repeat(9_000)), None);
+        assert_eq!(
+            controller
+                .formula_length_warning(&formula(7_400))
+                .as_deref(),
+            Some("Formula is 7400 of 8192 characters")
+        );
+        assert_eq!(
+            controller
+                .formula_length_warning(&formula(8_200))
+                .as_deref(),
+            Some("Formula is 8 characters over the 8192 limit")
+        );
+    }
+
+    #[test]
+    fn test_copy_and_paste_keep_formulas_and_formats() {
+        use crate::state::{Action, Selection, SelectionType};
+        use gridcore_core::domain::CellFormat;
+
+        let mut source = create_controller();
+        source.write_cell(&CellAddress::new(0, 0), "0.5").unwrap();
+        source.write_cell(&CellAddress::new(1, 0), "=A1*2").unwrap();
+        source.write_cell(&CellAddress::new(0, 1), "'007").unwrap();
+        source
+            .facade()
+            .set_cell_format(&CellAddress::new(1, 0), CellFormat::percent(0))
+            .unwrap();
+        source.set_selection(Some(Selection {
+            selection_type: SelectionType::Range {
+                start: CellAddress::new(0, 0),
+                end: CellAddress::new(1, 1),
+            },
+            anchor: None,
+        }));
+        let copied = source.copy_selection().unwrap();
+        assert_eq!(copied.text, "0.5\t100%\n007\t\n");
+
+        // Another window reads the payload
+        let mut target = create_controller();
+        target.write_cell(&CellAddress::new(3, 4), "x").unwrap();
+        target.set_cursor(CellAddress::from_a1("C3").unwrap());
+        target
+            .dispatch_action(Action::PasteClipboard {
+                text: copied.text.clone(),
+                rich: Some(copied.payload.to_json()),
+            })
+            .unwrap();
+        let d3 = CellAddress::from_a1("D3").unwrap();
+        let cell = target.facade().get_cell(&d3).unwrap();
+        assert_eq!(cell.formula_text.as_deref(), Some("C3*2"));
+        assert_eq!(
+            target.facade().get_effective_format(&d3),
+            Some(CellFormat::percent(0))
+        );
+        assert_eq!(text_at(&target, "C4"), CellValue::string_from_str("007"));
+        // Cells below the pasted block are untouched
+        assert_eq!(text_at(&target, "D5"), CellValue::string_from_str("x"));
+
+        // Without a payload the same window still remembers its copy
+        source.set_cursor(CellAddress::from_a1("A5").unwrap());
+        source.paste_clipboard(&copied.text, None).unwrap();
+        let b5 = source
+            .facade()
+            .get_cell(&CellAddress::from_a1("B5").unwrap());
+        assert_eq!(b5.unwrap().formula_text.as_deref(), Some("A5*2"));
+
+        // Other windows fall back to the displayed values
+        let mut other = create_controller();
+        other.paste_clipboard(&copied.text, None).unwrap();
+        let b1 = other.facade().get_cell(&CellAddress::new(1, 0)).unwrap();
+        assert!(!b1.has_formula());
+        assert_eq!(text_at(&other, "B1"), CellValue::Number(1.0));
+        assert_eq!(text_at(&other, "A2"), CellValue::Number(7.0));
+    }
+
+    #[test]
+    fn test_styles_apply_from_command_line_and_travel_with_copies() {
+        use crate::controller::ViewportBounds;
+        use crate::state::Action;
+        use gridcore_core::domain::CellFormat;
+
+        fn run_command(controller: &mut SpreadsheetController, command: &str) {
+            controller.handle_keyboard_event(key_event(":")).unwrap();
+            for ch in command.chars() {
+                controller
+                    .handle_keyboard_event(key_event(&ch.to_string()))
+                    .unwrap();
+            }
+            controller
+                .handle_keyboard_event(key_event("Enter"))
+                .unwrap();
+        }
+
+        let mut source = create_controller();
+        source.write_cell(&CellAddress::new(0, 0), "0.5").unwrap();
+        run_command(&mut source, "style Warning=percent 0");
+        run_command(&mut source, "style warning");
+        let a1 = CellAddress::new(0, 0);
+        assert_eq!(
+            source.facade().get_cell_style(&a1).as_deref(),
+            Some("Warning")
+        );
+
+        // Changing the style redraws cells already on screen
+        let bounds = ViewportBounds {
+            start_row: 0,
+            end_row: 9,
+            start_col: 0,
+            end_col: 9,
+        };
+        source.prefetch_viewport(&bounds);
+        assert_eq!(source.get_display_list(&bounds)[0].text, "50%");
+        run_command(&mut source, "style Warning=percent 1");
+        assert_eq!(source.get_display_list(&bounds)[0].text, "50.0%");
+
+        // A window without the style picks it up from the payload
+        source.set_cursor(a1);
+        let copied = source.copy_selection().unwrap();
+        let mut target = create_controller();
+        target
+            .dispatch_action(Action::PasteClipboard {
+                text: copied.text.clone(),
+                rich: Some(copied.payload.to_json()),
+            })
+            .unwrap();
+        assert_eq!(
+            target
+                .facade()
+                .get_style("Warning")
+                .map(|style| style.format),
+            Some(CellFormat::percent(1))
+        );
+        assert_eq!(
+            target.facade().get_cell_style(&a1).as_deref(),
+            Some("Warning")
+        );
+
+        // Styles in use are only removed on request
+        assert!(target
+            .dispatch_action(Action::RemoveStyle {
+                name: "Warning".to_string(),
+                removal: Default::default(),
+            })
+            .is_err());
+        run_command(&mut target, "unstyle Warning convert");
+        assert!(target.facade().get_style("Warning").is_none());
+        assert_eq!(
+            target.facade().get_effective_format(&a1),
+            Some(CellFormat::percent(1))
+        );
+    }
+
+    #[test]
+    fn test_lint_command_lists_and_steps_through_warnings() {
+        use gridcore_core::lint::LintRule;
+
+        let mut controller = create_controller();
+        for (a1, value) in [
+            ("A1", "5"),
+            ("A2", "7"),
+            ("B1", "=A1*1.2"),
+            ("B2", "=A2*1.2"),
+            ("D4", "=C4"),
+        ] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+
+        run_ex(&mut controller, "lint");
+        let cells: Vec<String> = controller
+            .get_lint_warnings()
+            .findings()
+            .iter()
+            .map(|finding| finding.address.to_string())
+            .collect();
+        assert_eq!(cells, ["B1", "B2", "D4"]);
+        assert_eq!(
+            controller.get_errors().last().unwrap().message,
+            "3 lint warnings"
+        );
+
+        // ]w and [w step through the warnings and wrap around
+        controller.set_cursor(CellAddress::from_a1("C2").unwrap());
+        type_keys(&mut controller, &["]", "w"]);
+        assert_eq!(controller.cursor(), CellAddress::from_a1("D4").unwrap());
+        assert!(controller
+            .get_errors()
+            .last()
+            .unwrap()
+            .message
+            .starts_with("D4: Refers to empty cell C4"));
+        type_keys(&mut controller, &["]", "w"]);
+        assert_eq!(controller.cursor(), CellAddress::from_a1("B1").unwrap());
+        type_keys(&mut controller, &["[", "w"]);
+        assert_eq!(controller.cursor(), CellAddress::from_a1("D4").unwrap());
+
+        // A disabled rule drops its warnings and stays off for later runs
+        run_ex(&mut controller, "lint off hard-coded-constant");
+        assert_eq!(controller.get_lint_warnings().len(), 1);
+        assert!(!controller
+            .get_lint_warnings()
+            .settings()
+            .is_enabled(LintRule::HardCodedConstant));
+        run_ex(&mut controller, "lint A1:B2");
+        assert!(controller.get_lint_warnings().is_empty());
+
+        run_ex(&mut controller, "lint on hard-coded-constant");
+        run_ex(&mut controller, "lint A1:B2");
+        assert_eq!(controller.get_lint_warnings().len(), 2);
+        run_ex(&mut controller, "lint clear");
+        assert!(controller.get_lint_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_lint_warns_about_totals_over_mixed_formats_once_enabled() {
+        use gridcore_core::domain::CellFormat;
+        use gridcore_core::lint::LintRule;
+
+        let mut controller = create_controller();
+        for (a1, value) in [
+            ("A1", "5"),
+            ("A2", "12"),
+            ("A3", "0.05"),
+            ("B1", "=SUM(A1:A3)"),
+        ] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+        controller
+            .facade()
+            .set_cell_format(&CellAddress::from_a1("A3").unwrap(), CellFormat::percent(0))
+            .unwrap();
+
+        // Off by default
+        run_ex(&mut controller, "lint");
+        assert!(controller.get_lint_warnings().is_empty());
+
+        run_ex(&mut controller, "lint on mixed-formats");
+        run_ex(&mut controller, "lint");
+        let finding = &controller.get_lint_warnings().findings()[0];
+        assert_eq!(finding.rule, LintRule::MixedFormats);
+        assert_eq!(finding.address, CellAddress::from_a1("B1").unwrap());
+        let errors = controller.get_errors();
+        let warning = &errors[errors.len() - 2];
+        assert_eq!(warning.severity, ErrorSeverity::Warning);
+        assert!(warning
+            .message
+            .starts_with("B1: Range A1:A3 mixes plain, percent"));
+    }
+
+    fn start_edit(controller: &mut SpreadsheetController, address: CellAddress, text: &str) {
+        controller.set_cursor(address);
+        controller.handle_keyboard_event(key_event("i")).unwrap();
+        for c in text.chars() {
+            controller
+                .handle_keyboard_event(key_event(&c.to_string()))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_edit_conflict_defers_write_until_edit_ends() {
+        let mut controller = create_controller();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        start_edit(&mut controller, a1, "7");
+
+        // A demo step writes to the edited cell and a neighbour in one batch
+        controller
+            .write_cells(&[(a1, "100".to_string()), (b1, "200".to_string())])
+            .unwrap();
+        assert!(controller.get_mode().is_editing());
+        assert_eq!(controller.get_cell_display_for_ui(&b1), "200");
+        assert_eq!(controller.get_cell_display_for_ui(&a1), "");
+        assert_eq!(controller.get_edit_guard().pending_writes().len(), 1);
+
+        // Committing the edit applies the deferred write afterwards
+        controller
+            .handle_keyboard_event(key_event("Escape"))
+            .unwrap();
+        controller
+            .handle_keyboard_event(key_event("Enter"))
+            .unwrap();
+        assert!(matches!(controller.get_mode(), EditorMode::Navigation));
+        assert_eq!(controller.get_cell_display_for_ui(&a1), "100");
+        assert!(controller.get_edit_guard().pending_writes().is_empty());
+
+        // Cancelling flushes the queue as well
+        start_edit(&mut controller, a1, "8");
+        controller.write_cell(&a1, "300").unwrap();
+        controller.cancel_editing().unwrap();
+        assert_eq!(controller.get_cell_display_for_ui(&a1), "300");
+    }
+
+    #[test]
+    fn test_edit_conflict_cancel_policy() {
+        use crate::controller::EditConflictPolicy;
+
+        let mut controller = create_controller();
+        controller.set_edit_conflict_policy(EditConflictPolicy::CancelEdit);
+        let a1 = CellAddress::new(0, 0);
+        start_edit(&mut controller, a1, "7");
+
+        controller.write_cell(&a1, "100").unwrap();
+        assert!(matches!(controller.get_mode(), EditorMode::Navigation));
+        assert_eq!(controller.get_cell_display_for_ui(&a1), "100");
+        assert!(controller
+            .get_errors()
+            .iter()
+            .any(|e| e.severity == ErrorSeverity::Warning));
+    }
+
+    #[test]
+    fn test_edit_conflict_mark_stale_policy() {
+        use crate::controller::events::SpreadsheetEvent;
+        use crate::controller::EditConflictPolicy;
+
+        let mut controller = create_controller();
+        controller.set_edit_conflict_policy(EditConflictPolicy::MarkStale);
+        let conflicts = std::sync::Arc::new(std::sync::Mutex::new(0));
+        let sink = conflicts.clone();
+        controller.subscribe_to_events(move |event| {
+            if let SpreadsheetEvent::EditConflict { .. } = event {
+                *sink.lock().unwrap() += 1;
+            }
+        });
+
+        let a1 = CellAddress::new(0, 0);
+        let a2 = CellAddress::new(0, 1);
+        start_edit(&mut controller, a1, "7");
+
+        // Writes elsewhere do not touch the editor
+        controller.write_cell(&a2, "1").unwrap();
+        assert!(!controller.is_edit_stale());
+
+        controller
+            .write_cells(&[(a2, "2".to_string()), (a1, "100".to_string())])
+            .unwrap();
+        assert!(controller.get_mode().is_editing());
+        assert!(controller.is_edit_stale());
+        assert_eq!(controller.get_cell_display_for_ui(&a1), "100");
+        assert_eq!(*conflicts.lock().unwrap(), 1);
+
+        // Committing keeps the user's value and clears the flag
+        controller
+            .handle_keyboard_event(key_event("Escape"))
+            .unwrap();
+        controller
+            .handle_keyboard_event(key_event("Enter"))
+            .unwrap();
+        assert_eq!(controller.get_cell_display_for_ui(&a1), "7");
+        assert!(!controller.is_edit_stale());
+    }
+
+    #[test]
+    fn test_set_column_format_action() {
+        use crate::state::Action;
+        use gridcore_core::domain::CellFormat;
+
+        let mut controller = create_controller();
+        let b2 = CellAddress::new(1, 1);
+        controller.facade().set_cell_value(&b2, "1234.5").unwrap();
+
+        controller
+            .dispatch_action(Action::SetColumnFormat {
+                column: 1,
+                format: Some(CellFormat::currency("$", 2)),
+            })
+            .unwrap();
+        assert_eq!(
+            controller.facade().get_display_value(&b2).unwrap(),
+            "$1234.50"
+        );
+        // Editing still shows the stored value
+        assert_eq!(controller.get_cell_display_for_ui(&b2), "1234.5");
+
+        controller
+            .dispatch_action(Action::SetColumnFormat {
+                column: 1,
+                format: None,
+            })
+            .unwrap();
+        assert_eq!(
+            controller.facade().get_display_value(&b2).unwrap(),
+            "1234.5"
+        );
+    }
+
+    #[test]
+    fn test_edits_refresh_viewport_cache() {
+        use crate::controller::ViewportBounds;
+
+        let mut controller = create_controller();
+        let bounds = ViewportBounds {
+            start_row: 0,
+            end_row: 19,
+            start_col: 0,
+            end_col: 9,
+        };
+        controller.prefetch_viewport(&bounds);
+        assert!(!controller.needs_prefetch(&bounds));
+
+        let b2 = CellAddress::new(1, 1);
+        controller.write_cell(&b2, "5").unwrap();
+        let cells = controller.get_display_list(&bounds);
+        assert_eq!(cells.len(), 1);
+        assert_eq!(cells[0].text, "5");
+
+        // Delete from navigation mode clears the cached cell too
+        controller.set_cursor(b2);
+        controller
+            .handle_keyboard_event(KeyboardEvent::new("Delete".to_string()))
+            .unwrap();
+        assert!(controller.get_display_list(&bounds).is_empty());
+
+        let stats = controller.get_viewport_cache().stats();
+        assert_eq!((stats.hits, stats.misses), (2, 0));
+    }
+
+    #[test]
+    fn test_edits_redraw_the_data_bars_of_the_whole_range() {
+        use crate::controller::ViewportBounds;
+        use gridcore_core::conditional::{ConditionalFormatRule, DataBar};
+
+        let mut controller = create_controller();
+        for (row, value) in ["1", "2", "4"].iter().enumerate() {
+            controller
+                .write_cell(&CellAddress::new(0, row as u32), value)
+                .unwrap();
+        }
+        let range = CellRange::from_string("A1:A3").unwrap();
+        controller
+            .add_conditional_format(ConditionalFormatRule::data_bar(
+                range,
+                DataBar::new("#638ec6"),
+            ))
+            .unwrap();
+        let bounds = ViewportBounds {
+            start_row: 0,
+            end_row: 19,
+            start_col: 0,
+            end_col: 9,
+        };
+        controller.prefetch_viewport(&bounds);
+        let bar_end = |controller: &SpreadsheetController, row: u32| {
+            controller
+                .get_viewport_cache()
+                .get(&CellAddress::new(0, row))
+                .flatten()
+                .and_then(|cell| cell.conditional.clone())
+                .and_then(|fill| fill.data_bar)
+                .map(|bar| bar.end)
+        };
+        assert_eq!(bar_end(&controller, 1), Some(0.5));
+
+        // A new largest number shortens the bars of the cells left alone
+        let generation = controller.get_viewport_cache().generation();
+        controller.write_cell(&CellAddress::new(0, 2), "8").unwrap();
+        assert_eq!(bar_end(&controller, 1), Some(0.25));
+        assert!(controller.get_viewport_cache().generation() > generation);
+
+        controller.remove_conditional_format(0).unwrap();
+        assert!(controller.get_viewport_cache().region().is_none());
+    }
+
+    #[test]
+    fn test_pivot_ex_command_and_refresh() {
+        use gridcore_core::types::CellValue;
+
+        fn run_command(controller: &mut SpreadsheetController, command: &str) {
+            controller.handle_keyboard_event(key_event(":")).unwrap();
+            for ch in command.chars() {
+                controller
+                    .handle_keyboard_event(key_event(&ch.to_string()))
+                    .unwrap();
+            }
+            controller
+                .handle_keyboard_event(key_event("Enter"))
+                .unwrap();
+        }
+
+        let mut controller = create_controller();
+        for (row, (region, amount)) in [("West", "10"), ("East", "5"), ("West", "3")]
+            .into_iter()
+            .enumerate()
+        {
+            controller
+                .write_cell(&CellAddress::new(0, row as u32), region)
+                .unwrap();
+            controller
+                .write_cell(&CellAddress::new(1, row as u32), amount)
+                .unwrap();
+        }
+
+        run_command(&mut controller, "pivot A1:B3 D1 A B sum totals");
+        let value = |controller: &SpreadsheetController, col, row| {
+            controller
+                .facade()
+                .get_cell_raw_value(&CellAddress::new(col, row))
+        };
+        assert_eq!(
+            value(&controller, 3, 1),
+            Some(CellValue::string_from_str("East"))
+        );
+        assert_eq!(value(&controller, 4, 2), Some(CellValue::Number(13.0)));
+        assert_eq!(value(&controller, 4, 3), Some(CellValue::Number(18.0)));
+
+        controller.write_cell(&CellAddress::new(1, 1), "7").unwrap();
+        run_command(&mut controller, "pivot refresh D1");
+        assert_eq!(value(&controller, 4, 3), Some(CellValue::Number(20.0)));
+    }
+
+    #[test]
+    fn test_let_ex_command_defines_constants() {
+        use gridcore_core::types::CellValue;
+
+        fn run_command(controller: &mut SpreadsheetController, command: &str) {
+            controller.handle_keyboard_event(key_event(":")).unwrap();
+            for ch in command.chars() {
+                controller
+                    .handle_keyboard_event(key_event(&ch.to_string()))
+                    .unwrap();
+            }
+            controller
+                .handle_keyboard_event(key_event("Enter"))
+                .unwrap();
+        }
+
+        let mut controller = create_controller();
+        let b1 = CellAddress::new(1, 0);
+        run_command(&mut controller, "let TaxRate=0.2");
+        controller
+            .write_cell(&CellAddress::new(0, 0), "100")
+            .unwrap();
+        controller.write_cell(&b1, "=A1*TaxRate").unwrap();
+        assert_eq!(
+            controller.facade().get_cell_raw_value(&b1),
+            Some(CellValue::Number(20.0))
+        );
+
+        run_command(&mut controller, "let Sheet1!TaxRate = -0.5");
+        assert_eq!(
+            controller.facade().get_cell_raw_value(&b1),
+            Some(CellValue::Number(-50.0))
+        );
+
+        run_command(&mut controller, "unlet Sheet1!TaxRate");
+        run_command(&mut controller, "unlet TaxRate");
+        assert!(matches!(
+            controller.facade().get_cell_raw_value(&b1),
+            Some(CellValue::Error(_))
+        ));
+    }
+
+    #[test]
+    fn test_formulas_edited_in_display_convention() {
+        use gridcore_core::formula::{FormulaConvention, FormulaTranslator};
+
+        let mut controller = SpreadsheetController::builder()
+            .with_formula_translator(
+                FormulaTranslator::new(FormulaConvention::Semicolon)
+                    .with_function_name("SUM", "SUMME"),
+            )
+            .build();
+        let a1 = CellAddress::new(0, 0);
+        controller
+            .facade()
+            .set_cell_value(&a1, "=SUM(1.5, 2)")
+            .unwrap();
+
+        controller.set_cursor(a1);
+        assert_eq!(controller.get_formula_bar_value(), "=SUMME(1,5; 2)");
+
+        // Typed display text is stored canonically
+        let b1 = CellAddress::new(1, 0);
+        start_edit(&mut controller, b1, "=summe(2,5; \"a;b\")");
+        controller
+            .handle_keyboard_event(key_event("Escape"))
+            .unwrap();
+        controller
+            .handle_keyboard_event(key_event("Enter"))
+            .unwrap();
+        let stored = controller.facade().get_cell(&b1).unwrap();
+        assert_eq!(stored.raw_value.to_string(), "=SUM(2.5, \"a;b\")");
+
+        // Switching back to the canonical convention changes only the display
+        controller.set_formula_translator(FormulaTranslator::default());
+        controller.set_cursor(a1);
+        assert_eq!(controller.get_formula_bar_value(), "=SUM(1.5, 2)");
+    }
+
+    #[test]
+    fn test_typed_numbers_follow_cell_format() {
+        use gridcore_core::domain::CellFormat;
+
+        let mut controller = create_controller();
+        let commit = |controller: &mut SpreadsheetController, address, text: &str| {
+            start_edit(controller, address, text);
+            controller
+                .handle_keyboard_event(key_event("Escape"))
+                .unwrap();
+            controller
+                .handle_keyboard_event(key_event("Enter"))
+                .unwrap();
+            controller.facade().get_cell_raw_value(&address)
+        };
+
+        let a1 = CellAddress::new(0, 0);
+        controller
+            .facade()
+            .set_cell_format(&a1, CellFormat::percent(0))
+            .unwrap();
+        assert_eq!(
+            commit(&mut controller, a1, "5"),
+            Some(CellValue::Number(0.05))
+        );
+
+        let b1 = CellAddress::new(1, 0);
+        controller
+            .facade()
+            .set_cell_format(&b1, CellFormat::currency("$", 2))
+            .unwrap();
+        assert_eq!(
+            commit(&mut controller, b1, "$1,000,000"),
+            Some(CellValue::Number(1_000_000.0))
+        );
+
+        // An unformatted cell takes its format from what was typed
+        let d1 = CellAddress::new(3, 0);
+        assert_eq!(
+            commit(&mut controller, d1, "$12.50"),
+            Some(CellValue::Number(12.5))
+        );
+        assert_eq!(
+            controller.facade().get_display_value(&d1).as_deref(),
+            Some("$12.50")
+        );
+
+        // A leading apostrophe keeps digits as text, and editing shows it again
+        let c1 = CellAddress::new(2, 0);
+        assert_eq!(
+            commit(&mut controller, c1, "'00123"),
+            Some(CellValue::from_string("00123".to_string()))
+        );
+        controller.set_cursor(c1);
+        assert_eq!(controller.get_formula_bar_value(), "'00123");
+    }
+
+    #[test]
+    fn test_typed_entries_infer_sticky_formats() {
+        use gridcore_core::domain::CellFormat;
+
+        let mut controller = create_controller();
+        let enter = |controller: &mut SpreadsheetController, a1: &str, text: &str| {
+            let address = CellAddress::from_a1(a1).unwrap();
+            // Clear the cell first so the entry replaces its contents
+            controller.set_cursor(address);
+            type_keys(controller, &["Delete"]);
+            start_edit(controller, address, text);
+            type_keys(controller, &["Escape", "Escape"]);
+            let facade = controller.facade();
+            (
+                facade.get_cell_raw_value(&address),
+                facade.get_effective_format(&address),
+                facade.get_display_value(&address).unwrap(),
+            )
+        };
+
+        let number = |n: f64| Some(CellValue::Number(n));
+        let cases = [
+            ("A1", "12%", number(0.12), CellFormat::percent(0), "12%"),
+            (
+                "A2",
+                "1,234.56",
+                number(1234.56),
+                CellFormat::thousands(2),
+                "1,234.56",
+            ),
+            (
+                "A3",
+                "1.5e3",
+                number(1500.0),
+                CellFormat::scientific(1),
+                "1.5E+03",
+            ),
+            (
+                "A4",
+                "($5.00)",
+                number(-5.0),
+                CellFormat::accounting("$", 2),
+                "($5.00)",
+            ),
+            // The format stays for later entries
+            ("A1", "30", number(0.3), CellFormat::percent(0), "30%"),
+        ];
+        for (a1, text, value, format, display) in cases {
+            assert_eq!(
+                enter(&mut controller, a1, text),
+                (value, Some(format), display.to_string()),
+                "{text}"
+            );
+        }
+
+        // An explicit format is never replaced
+        controller
+            .facade()
+            .set_cell_format(&CellAddress::new(1, 0), CellFormat::number(1))
+            .unwrap();
+        assert_eq!(
+            enter(&mut controller, "B1", "$5.00").1,
+            Some(CellFormat::number(1))
+        );
+
+        // Turning inference off leaves typed cells unformatted
+        run_ex(&mut controller, "set noautoformat");
+        assert!(!controller.facade().workbook_settings().infer_formats);
+        assert_eq!(
+            enter(&mut controller, "C1", "12%"),
+            (number(0.12), None, "0.12".to_string())
+        );
+        run_ex(&mut controller, "set autoformat");
+        assert_eq!(
+            enter(&mut controller, "C2", "$5.00").1,
+            Some(CellFormat::currency("$", 2))
+        );
+    }
+
+    #[test]
+    fn test_autocorrect_reports_and_reverts_corrections() {
+        let mut controller = create_controller();
+        let enter = |controller: &mut SpreadsheetController, a1: &str, text: &str| {
+            let address = CellAddress::from_a1(a1).unwrap();
+            // Clear the cell first so the entry replaces its contents
+            controller.set_cursor(address);
+            type_keys(controller, &["Delete"]);
+            start_edit(controller, address, text);
+            type_keys(controller, &["Escape", "Escape"]);
+            controller
+                .facade()
+                .get_cell(&address)
+                .map(|cell| cell.raw_value.to_string())
+                .unwrap()
+        };
+        let a1 = CellAddress::from_a1("A1").unwrap();
+
+        // Off by default: the typo is stored as typed
+        assert_eq!(enter(&mut controller, "A1", "=SUM(1,2"), "=SUM(1,2");
+        assert!(controller.last_autocorrection().is_none());
+
+        run_ex(&mut controller, "set autocorrect");
+        assert_eq!(
+            enter(&mut controller, "A2", "=MAX(1,MIN(2,3"),
+            "=MAX(1,MIN(2,3))"
+        );
+        assert_eq!(last_status(&controller), "Added 2 closing parentheses");
+        assert_eq!(enter(&mut controller, "A1", "=SUM(1,2"), "=SUM(1,2)");
+        assert_eq!(
+            controller.facade().get_cell_value(&a1).as_deref(),
+            Some("3")
+        );
+        // An ambiguous mistake is left for the parse error
+        assert_eq!(enter(&mut controller, "A3", "=1+*2"), "=1+*2");
+        assert!(controller.last_autocorrection().is_none());
+
+        // Reverting puts back the formula as typed
+        enter(&mut controller, "A1", "=sum(1,2)");
+        assert_eq!(
+            controller.last_autocorrection().map(|(address, fix)| (
+                *address,
+                fix.original.as_str(),
+                fix.corrected.as_str()
+            )),
+            Some((a1, "=sum(1,2)", "=SUM(1,2)"))
+        );
+        run_ex(&mut controller, "uncorrect");
+        assert_eq!(
+            controller
+                .facade()
+                .get_cell(&a1)
+                .unwrap()
+                .raw_value
+                .to_string(),
+            "=sum(1,2)"
+        );
+        assert_eq!(last_status(&controller), "Restored =sum(1,2)");
+        assert!(controller.last_autocorrection().is_none());
+
+        // Only the chosen corrections are made
+        run_ex(&mut controller, "set autocorrections=trailing");
+        assert_eq!(enter(&mut controller, "B1", "=SUM(1,2"), "=SUM(1,2");
+        assert_eq!(enter(&mut controller, "B2", "=1+2+"), "=1+2");
+        assert_eq!(last_status(&controller), "Removed the trailing '+'");
+        run_ex(&mut controller, "set noautocorrect");
+        assert_eq!(enter(&mut controller, "B3", "=1+2+"), "=1+2+");
+    }
+
+    #[test]
+    fn test_strict_formulas_refuse_then_store_as_text() {
+        let mut controller = create_controller();
+        let a1 = CellAddress::from_a1("A1").unwrap();
+        let a2 = CellAddress::from_a1("A2").unwrap();
+        run_ex(&mut controller, "set strict");
+
+        // Refused: the editor stays open on the entry, the error marked
+        start_edit(&mut controller, a1, "=1+*2");
+        type_keys(&mut controller, &["Enter"]);
+        assert!(controller.get_mode().is_editing());
+        assert!(controller.facade().get_cell(&a1).is_none());
+        let blocked = controller.blocked_entry().unwrap();
+        assert_eq!(&blocked.value[blocked.span.clone()], "*");
+        assert_eq!(
+            last_status(&controller),
+            "Unexpected '*' - press Enter again to store it as text"
+        );
+
+        // Enter again stores the entry as text
+        type_keys(&mut controller, &["Enter"]);
+        assert!(!controller.get_mode().is_editing());
+        assert!(controller.blocked_entry().is_none());
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::from_string("=1+*2".to_string())
+        );
+
+        // A fixed entry commits; a changed one is checked afresh
+        start_edit(&mut controller, a2, "=SUM(1");
+        type_keys(&mut controller, &["Enter", ","]);
+        type_keys(&mut controller, &["Enter"]);
+        assert!(controller.get_mode().is_editing());
+        type_keys(&mut controller, &["Backspace", ")", "Enter"]);
+        assert!(!controller.get_mode().is_editing());
+        assert_eq!(
+            controller.facade().get_cell_raw_value(&a2),
+            Some(CellValue::Number(1.0))
+        );
+
+        // Valid formulas and text are unaffected
+        let a3 = CellAddress::from_a1("A3").unwrap();
+        start_edit(&mut controller, a3, "=1+2");
+        type_keys(&mut controller, &["Enter"]);
+        assert!(!controller.get_mode().is_editing());
+        assert_eq!(
+            controller.facade().get_cell_raw_value(&a3),
+            Some(CellValue::Number(3.0))
+        );
+    }
+
+    #[test]
+    fn test_strict_formulas_on_every_commit_path() {
+        let mut controller = SpreadsheetController::builder().vim_enabled(false).build();
+        let a1 = CellAddress::from_a1("A1").unwrap();
+        let set = |controller: &SpreadsheetController,
+                   change: fn(&mut gridcore_core::workbook::WorkbookSettings)| {
+            let mut settings = controller.facade().workbook_settings();
+            change(&mut settings);
+            controller.facade().set_workbook_settings(settings);
+        };
+        set(&controller, |settings| settings.strict_formulas = true);
+
+        // Escape cancels a refused entry, and the refusal with it
+        controller.set_cursor(a1);
+        type_keys(&mut controller, &["=", "(", "1", "Enter"]);
+        assert!(controller.get_mode().is_editing());
+        type_keys(&mut controller, &["Escape"]);
+        assert!(!controller.get_mode().is_editing());
+        assert!(controller.blocked_entry().is_none());
+        assert!(controller.facade().get_cell(&a1).is_none());
+
+        // The formula bar is refused once too, then stores text
+        controller.set_cursor(a1);
+        controller.set_formula_bar_value("=SUM(,".to_string());
+        controller
+            .dispatch_action(crate::state::Action::SubmitFormulaBar)
+            .unwrap();
+        assert!(controller.facade().get_cell(&a1).is_none());
+        assert!(controller.blocked_entry().is_some());
+        controller
+            .dispatch_action(crate::state::Action::SubmitFormulaBar)
+            .unwrap();
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::from_string("=SUM(,".to_string())
+        );
+
+        // Autocorrect goes first; strict mode only refuses what it cannot fix
+        set(&controller, |settings| settings.autocorrect.enabled = true);
+        let b1 = CellAddress::from_a1("B1").unwrap();
+        controller.set_cursor(b1);
+        type_keys(
+            &mut controller,
+            &["=", "S", "U", "M", "(", "1", ",", "2", "Enter"],
+        );
+        assert!(!controller.get_mode().is_editing());
+        assert_eq!(
+            controller.facade().get_cell_raw_value(&b1),
+            Some(CellValue::Number(3.0))
+        );
+        controller.set_cursor(b1);
+        type_keys(&mut controller, &["=", "1", "+", "*", "2", "Enter"]);
+        assert!(controller.get_mode().is_editing());
+        assert_eq!(
+            controller
+                .blocked_entry()
+                .map(|blocked| blocked.value.as_str()),
+            Some("=1+*2")
+        );
+    }
+
+    #[test]
+    fn test_grid_extent_grows_on_navigation_and_paste() {
+        use crate::behaviors::paste::PasteOptions;
+
+        let mut controller = create_controller();
+        let extent = |controller: &SpreadsheetController| {
+            let extent = controller.get_viewport_manager().get_extent();
+            (extent.rows(), extent.cols())
+        };
+        assert_eq!(extent(&controller), (50, 10));
+
+        // Jumping far away grows the extent and scrolls there at once
+        let far = CellAddress::new(2, 500);
+        controller.goto_cell(far, None).unwrap();
+        assert_eq!(extent(&controller), (551, 10));
+        assert!(controller.get_viewport_manager().is_visible(&far));
+
+        // Pasting past the right edge grows it to the pasted data
+        controller.set_cursor(CellAddress::new(20, 0));
+        assert_eq!(extent(&controller), (551, 31));
+        controller
+            .paste_text("1\t2\t3", &PasteOptions::default())
+            .unwrap();
+        assert_eq!(extent(&controller), (551, 33));
+        assert_eq!(
+            controller.get_viewport_manager().get_extent().used(),
+            Some(CellAddress::new(22, 0))
+        );
+    }
+
+    #[test]
+    fn test_formula_preview_while_editing() {
+        let mut controller = create_controller();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        controller.facade().set_cell_value(&a1, "4").unwrap();
+
+        start_edit(&mut controller, b1, "=A1*2+1/0");
+        let preview = controller.formula_preview().unwrap();
+        assert_eq!(preview.text, "#DIV/0!");
+        assert!(preview.is_error);
+
+        // Select `A1*` in visual mode: the preview snaps to `A1*2`
+        controller
+            .handle_keyboard_event(key_event("Escape"))
+            .unwrap();
+        for key in ["0", "l", "v", "l", "l"] {
+            controller.handle_keyboard_event(key_event(key)).unwrap();
+        }
+        let preview = controller.formula_preview().unwrap();
+        assert_eq!(preview.span, Some(1..5));
+        assert_eq!(preview.text, "8");
+
+        // A selection of just the operator covers its operands
+        let preview = controller.preview_formula_selection(7, 8).unwrap();
+        assert_eq!(preview.span, Some(6..9));
+        assert_eq!(preview.text, "#DIV/0!");
+        let preview = controller.preview_formula_selection(8, 9).unwrap();
+        assert_eq!(preview.text, "0");
+        assert_eq!(controller.facade().get_cell(&b1), None);
+    }
+
+    /// Type `text` into the cursor cell and commit it with `key`
+    fn enter_value(controller: &mut SpreadsheetController, text: &str, key: &str, shift: bool) {
+        for c in text.chars() {
+            controller
+                .handle_keyboard_event(key_event(&c.to_string()))
+                .unwrap();
+        }
+        controller
+            .handle_keyboard_event(key_event(key).with_modifiers(shift, false, false, false))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tab_and_enter_data_entry_session() {
+        use crate::controller::EnterDirection;
+
+        let mut controller = SpreadsheetController::builder().vim_enabled(false).build();
+        assert_eq!(controller.enter_direction(), EnterDirection::Down);
+
+        // Fill a 3x2 block the way a form is typed in
+        let mut path = Vec::new();
+        for (text, key) in [
+            ("1", "Tab"),
+            ("2", "Tab"),
+            ("3", "Enter"),
+            ("4", "Tab"),
+            ("5", "Tab"),
+            ("6", "Enter"),
+        ] {
+            enter_value(&mut controller, text, key, false);
+            path.push(controller.cursor().to_a1());
+        }
+        assert_eq!(path, ["B1", "C1", "A2", "B2", "C2", "A3"]);
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::new(2, 1)),
+            "6"
+        );
+
+        // Shift reverses, Escape cancels in place
+        enter_value(&mut controller, "7", "Enter", true);
+        assert_eq!(controller.cursor(), CellAddress::new(0, 1));
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::new(0, 2)),
+            "7"
+        );
+        enter_value(&mut controller, "x", "Escape", false);
+        assert_eq!(controller.cursor(), CellAddress::new(0, 1));
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::new(0, 1)),
+            "4"
+        );
+
+        // Switching the setting mid-session applies to the next commit
+        controller.set_enter_direction(Some(EnterDirection::Right));
+        enter_value(&mut controller, "8", "Enter", false);
+        assert_eq!(controller.cursor(), CellAddress::new(1, 1));
+        controller.set_enter_direction(Some(EnterDirection::Stay));
+        enter_value(&mut controller, "9", "Enter", false);
+        assert_eq!(controller.cursor(), CellAddress::new(1, 1));
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::new(1, 1)),
+            "9"
+        );
+    }
+
+    #[test]
+    fn test_commits_cycle_within_selection_and_vim_stays() {
+        use crate::state::Selection;
+
+        let mut controller = SpreadsheetController::builder().vim_enabled(false).build();
+        let b2 = CellAddress::new(1, 1);
+        controller.set_cursor(b2);
+        controller.set_selection(Some(Selection {
+            selection_type: SelectionType::Range {
+                start: CellAddress::new(2, 2),
+                end: b2,
+            },
+            anchor: Some(b2),
+        }));
+
+        let mut path = Vec::new();
+        for text in ["1", "2", "3", "4"] {
+            enter_value(&mut controller, text, "Enter", false);
+            path.push(controller.cursor().to_a1());
+        }
+        assert_eq!(path, ["B3", "C2", "C3", "B2"]);
+        assert!(controller.get_selection().is_some());
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::new(2, 2)),
+            "4"
+        );
+
+        // With vim, Enter leaves the cursor on the cell unless configured
+        let mut controller = create_controller();
+        start_edit(&mut controller, b2, "5");
+        controller
+            .handle_keyboard_event(key_event("Escape"))
+            .unwrap();
+        controller
+            .handle_keyboard_event(key_event("Enter"))
+            .unwrap();
+        assert_eq!(controller.cursor(), b2);
+        assert_eq!(controller.get_cell_display_for_ui(&b2), "5");
+
+        // but Tab still commits and moves right
+        let b3 = CellAddress::new(1, 2);
+        start_edit(&mut controller, b3, "6");
+        controller.handle_keyboard_event(key_event("Tab")).unwrap();
+        assert_eq!(controller.cursor(), CellAddress::new(2, 2));
+        assert_eq!(controller.get_cell_display_for_ui(&b3), "6");
+    }
+
+    mod plugins {
+        use super::*;
+        use crate::controller::{ActionVerdict, BehaviorPlugin, PluginContext};
+        use crate::state::Action;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        /// Takes `j` and records who saw it
+        struct Swallow {
+            name: &'static str,
+            priority: i32,
+            seen: Rc<RefCell<Vec<&'static str>>>,
+        }
+
+        impl BehaviorPlugin for Swallow {
+            fn name(&self) -> &str {
+                self.name
+            }
+
+            fn priority(&self) -> i32 {
+                self.priority
+            }
+
+            fn handle_key(&mut self, event: &KeyboardEvent, _ctx: &mut PluginContext) -> bool {
+                self.seen.borrow_mut().push(self.name);
+                event.key == "j"
+            }
+        }
+
+        #[test]
+        fn test_extension_consumes_key_before_default_handling() {
+            let seen = Rc::new(RefCell::new(Vec::new()));
+            let plugin = |name, priority| {
+                Box::new(Swallow {
+                    name,
+                    priority,
+                    seen: seen.clone(),
+                })
+            };
+            let mut controller = SpreadsheetController::builder()
+                .with_plugin(plugin("low", 0))
+                .build();
+            controller.register_plugin(plugin("high", 10));
+            assert_eq!(controller.plugins().names(), ["high", "low"]);
+
+            controller.handle_keyboard_event(key_event("j")).unwrap();
+            assert_eq!(controller.cursor(), CellAddress::new(0, 0));
+            assert_eq!(*seen.borrow(), ["high"]);
+
+            assert!(controller.set_plugin_enabled("high", false));
+            assert!(controller.set_plugin_enabled("low", false));
+            assert!(!controller.set_plugin_enabled("missing", false));
+            controller.handle_keyboard_event(key_event("j")).unwrap();
+            assert_eq!(controller.cursor(), CellAddress::new(0, 1));
+        }
+
+        /// Refuses edits in column B and upper-cases the rest
+        struct LockColumnB;
+
+        impl BehaviorPlugin for LockColumnB {
+            fn name(&self) -> &str {
+                "lock-b"
+            }
+
+            fn before_action(&mut self, action: Action, ctx: &mut PluginContext) -> ActionVerdict {
+                match action {
+                    Action::SubmitCellEdit { .. } if ctx.cursor().col == 1 => {
+                        ActionVerdict::Veto("Column B is locked".to_string())
+                    }
+                    Action::SubmitCellEdit { value } => {
+                        ActionVerdict::Continue(Action::SubmitCellEdit {
+                            value: value.to_uppercase(),
+                        })
+                    }
+                    action => ActionVerdict::Continue(action),
+                }
+            }
+        }
+
+        #[test]
+        fn test_plugin_vetoes_edits_to_a_column() {
+            let mut controller = create_controller();
+            controller.register_plugin(Box::new(LockColumnB));
+
+            start_edit(&mut controller, CellAddress::new(1, 0), "x");
+            controller
+                .handle_keyboard_event(key_event("Escape"))
+                .unwrap();
+            controller
+                .handle_keyboard_event(key_event("Escape"))
+                .unwrap();
+            assert!(!controller.get_mode().is_editing());
+            assert_eq!(controller.facade().get_cell(&CellAddress::new(1, 0)), None);
+            let errors = controller.get_errors();
+            assert_eq!(errors.last().unwrap().message, "Column B is locked");
+
+            start_edit(&mut controller, CellAddress::new(0, 0), "x");
+            controller
+                .handle_keyboard_event(key_event("Escape"))
+                .unwrap();
+            controller
+                .handle_keyboard_event(key_event("Escape"))
+                .unwrap();
+            assert_eq!(text_at(&controller, "A1"), CellValue::string_from_str("X"));
+        }
+
+        /// Adds `:stamp`, which dispatches a cursor move to A1 and reports
+        struct Stamp;
+
+        impl BehaviorPlugin for Stamp {
+            fn name(&self) -> &str {
+                "stamp"
+            }
+
+            fn commands(&self) -> Vec<String> {
+                vec!["stamp".to_string()]
+            }
+
+            fn execute_command(
+                &mut self,
+                _command: &str,
+                args: &str,
+                ctx: &mut PluginContext,
+            ) -> gridcore_core::Result<()> {
+                ctx.post_status(format!("stamped {}", args));
+                ctx.dispatch(Action::UpdateCursor {
+                    cursor: CellAddress::new(0, 0),
+                });
+                Ok(())
+            }
+        }
+
+        #[test]
+        fn test_plugin_command_completes_and_runs() {
+            let mut controller = create_controller();
+            controller.register_plugin(Box::new(Stamp));
+            controller.set_cursor(CellAddress::new(3, 3));
+            assert_eq!(controller.command_completions("tr"), ["trace", "trim"]);
+            assert!(controller
+                .command_completions("")
+                .contains(&"stamp".to_string()));
+
+            type_keys(&mut controller, &[":", "s", "t", "a", "Tab"]);
+            assert_eq!(
+                controller.get_mode(),
+                &EditorMode::Command {
+                    value: "stamp ".to_string()
+                }
+            );
+            type_keys(&mut controller, &["n", "o", "w", "Enter"]);
+            assert_eq!(controller.cursor(), CellAddress::new(0, 0));
+            let errors = controller.get_errors();
+            assert_eq!(errors.last().unwrap().message, "stamped now");
+
+            // Two candidates complete as far as they agree
+            type_keys(&mut controller, &[":", "u", "n", "Tab"]);
+            assert_eq!(
+                controller.get_mode(),
+                &EditorMode::Command {
+                    value: "un".to_string()
+                }
+            );
+        }
+    }
+
+    fn select_range(controller: &mut SpreadsheetController, range: &str) {
+        use crate::state::Action;
+
+        let range = CellRange::from_string(range).unwrap();
+        controller.set_cursor(range.start);
+        controller
+            .dispatch_action(Action::UpdateSelection {
+                selection: Selection {
+                    selection_type: SelectionType::Range {
+                        start: range.start,
+                        end: range.end,
+                    },
+                    anchor: Some(range.start),
+                },
+            })
+            .unwrap();
+    }
+
+    /// Middle of a cell in cell-area coordinates of an unscrolled viewport
+    fn point_in(controller: &SpreadsheetController, a1: &str) -> (f64, f64) {
+        let address = CellAddress::from_a1(a1).unwrap();
+        let (x, y) = controller.get_viewport_manager().point_of_cell(&address);
+        (x + 10.0, y + 10.0)
+    }
+
+    #[test]
+    fn test_range_drag_follows_pointer_and_cancels() {
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        controller.write_cell(&CellAddress::new(1, 1), "7").unwrap();
+        select_range(&mut controller, "B2:C3");
+        let selection = controller.get_selection().cloned();
+
+        let (x, y) = point_in(&controller, "C3");
+        assert_eq!(controller.selection_hit(x, y), SelectionHit::Interior);
+        let (x, y) = controller
+            .get_viewport_manager()
+            .point_of_cell(&CellAddress::new(1, 1));
+        assert_eq!(
+            controller.selection_hit(x + 1.0, y + 10.0),
+            SelectionHit::Border
+        );
+
+        controller
+            .dispatch_action(Action::StartRangeDrag {
+                grab: CellAddress::new(2, 2),
+            })
+            .unwrap();
+        let (x, y) = point_in(&controller, "F7");
+        controller
+            .dispatch_action(Action::UpdateRangeDrag { x, y })
+            .unwrap();
+        let drag = controller.get_range_drag().unwrap();
+        assert_eq!(drag.source().to_string(), "B2:C3");
+        assert_eq!(drag.destination().to_string(), "E6:F7");
+
+        // Escape puts everything back without writing
+        controller.set_cursor(CellAddress::new(9, 9));
+        controller
+            .handle_keyboard_event(key_event("Escape"))
+            .unwrap();
+        assert!(controller.get_range_drag().is_none());
+        assert_eq!(controller.get_selection().cloned(), selection);
+        assert_eq!(controller.cursor(), CellAddress::new(1, 1));
+        assert_eq!(text_at(&controller, "B2"), CellValue::Number(7.0));
+        assert_eq!(text_at(&controller, "E6"), CellValue::Empty);
+    }
+
+    #[test]
+    fn test_range_drag_scrolls_near_the_viewport_edge() {
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        select_range(&mut controller, "A1:A1");
+        controller
+            .dispatch_action(Action::StartRangeDrag {
+                grab: CellAddress::new(0, 0),
+            })
+            .unwrap();
+        let width = controller.get_viewport_manager().get_viewport_width();
+        let height = controller.get_viewport_manager().get_viewport_height();
+
+        // Away from the edges nothing scrolls
+        controller
+            .dispatch_action(Action::UpdateRangeDrag {
+                x: width / 2.0,
+                y: height / 2.0,
+            })
+            .unwrap();
+        assert_eq!(
+            controller.get_viewport_manager().get_scroll_position().x,
+            0.0
+        );
+
+        for _ in 0..3 {
+            controller
+                .dispatch_action(Action::UpdateRangeDrag {
+                    x: width - 2.0,
+                    y: height + 40.0,
+                })
+                .unwrap();
+        }
+        let scroll = controller.get_viewport_manager().get_scroll_position();
+        assert_eq!((scroll.x, scroll.y), (60.0, 60.0));
+        // The block stays under the pointer, which is clamped to the viewport
+        let under_pointer = controller
+            .get_viewport_manager()
+            .cell_at_point(width - 2.0, height - 1.0)
+            .unwrap();
+        assert_eq!(
+            controller.get_range_drag().unwrap().destination().start,
+            under_pointer
+        );
+    }
+
+    #[test]
+    fn test_range_drop_copies_or_moves_and_confirms_overwrites() {
+        use crate::controller::events::SpreadsheetEvent;
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        for (a1, value) in [("A1", "1"), ("A2", "2"), ("B1", "=A1*10"), ("B2", "=A2*10")] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = requests.clone();
+        controller.subscribe_to_events(move |event| {
+            if let SpreadsheetEvent::RangeDropNeedsConfirmation { overwritten, copy } = event {
+                sink.lock().unwrap().push((overwritten.clone(), *copy));
+            }
+        });
+        let formula = |controller: &SpreadsheetController, a1: &str| {
+            controller
+                .facade()
+                .get_cell(&CellAddress::from_a1(a1).unwrap())
+                .and_then(|cell| cell.formula_text.clone())
+        };
+        let drag = |controller: &mut SpreadsheetController, to: &str, copy: bool| {
+            let grab = controller.cursor();
+            controller
+                .dispatch_action(Action::StartRangeDrag { grab })
+                .unwrap();
+            let (x, y) = point_in(controller, to);
+            controller
+                .dispatch_action(Action::UpdateRangeDrag { x, y })
+                .unwrap();
+            controller
+                .dispatch_action(Action::DropRange { copy })
+                .unwrap();
+        };
+
+        // A copy moves relative references along and leaves the source
+        select_range(&mut controller, "B1:B2");
+        drag(&mut controller, "D1", true);
+        assert_eq!(formula(&controller, "D1").as_deref(), Some("C1*10"));
+        assert_eq!(formula(&controller, "B1").as_deref(), Some("A1*10"));
+        assert_eq!(
+            controller.get_selection().unwrap().selection_type,
+            SelectionType::Range {
+                start: CellAddress::from_a1("D1").unwrap(),
+                end: CellAddress::from_a1("D2").unwrap(),
+            }
+        );
+
+        // A move keeps reading the same cells and empties the source
+        select_range(&mut controller, "B1:B2");
+        drag(&mut controller, "D4", false);
+        assert_eq!(formula(&controller, "D4").as_deref(), Some("A1*10"));
+        assert_eq!(text_at(&controller, "D5"), CellValue::Number(20.0));
+        assert_eq!(text_at(&controller, "B1"), CellValue::Empty);
+        assert_eq!(controller.cursor(), CellAddress::from_a1("D4").unwrap());
+        assert!(requests.lock().unwrap().is_empty());
+
+        // Dropping onto the copy waits for confirmation
+        drag(&mut controller, "D1", false);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [(
+                vec![
+                    CellAddress::from_a1("D1").unwrap(),
+                    CellAddress::from_a1("D2").unwrap()
+                ],
+                false
+            )]
+        );
+        assert_eq!(formula(&controller, "D1").as_deref(), Some("C1*10"));
+        controller
+            .dispatch_action(Action::ConfirmRangeDrop)
+            .unwrap();
+        assert!(controller.get_range_drag().is_none());
+        assert_eq!(formula(&controller, "D1").as_deref(), Some("A1*10"));
+        assert_eq!(text_at(&controller, "D2"), CellValue::Number(20.0));
+        assert_eq!(text_at(&controller, "D4"), CellValue::Empty);
+
+        // With confirmation off, a copy over data lands straight away
+        controller.set_drop_overwrite_confirmation(false);
+        drag(&mut controller, "D2", true);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(formula(&controller, "D3").as_deref(), Some("A3*10"));
+    }
+
+    #[test]
+    fn test_script_console_writes_through_the_controller() {
+        let mut controller = create_controller();
+        controller.set_cursor(CellAddress::new(1, 0));
+        let outputs = controller
+            .execute_script("set A1 = 4\nset B1 = =A1*2\nget B1")
+            .unwrap();
+        assert_eq!(outputs.last().unwrap().to_string(), "B1 = 8 (=A1*2)");
+        assert_eq!(controller.get_formula_bar_value(), "=A1*2");
+
+        // Undo belongs to the controller's session, across scripts
+        let error = controller.execute_script("undo\nundo\nundo").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(text_at(&controller, "A1"), CellValue::Empty);
+        assert_eq!(controller.get_formula_bar_value(), "");
+    }
+
+    #[test]
+    fn test_checkhealth_reports_and_repairs() {
+        let mut controller = create_controller();
+        let last_message = |controller: &SpreadsheetController| {
+            let entry = controller.get_errors().last().unwrap().clone();
+            (entry.message, entry.severity)
+        };
+
+        run_ex(&mut controller, "checkhealth");
+        let (message, severity) = last_message(&controller);
+        assert!(message.starts_with("Health check passed"));
+        assert_eq!(severity, ErrorSeverity::Info);
+
+        // A write behind the controller's back leaves the viewport unaware
+        controller
+            .facade()
+            .set_cell_value(&CellAddress::from_a1("J40").unwrap(), "1")
+            .unwrap();
+        run_ex(&mut controller, "checkhealth");
+        assert_eq!(
+            last_message(&controller),
+            (
+                "Health check found used range ends at none but the cells end at J40".to_string(),
+                ErrorSeverity::Warning
+            )
+        );
+
+        run_ex(&mut controller, "checkhealth repair");
+        assert!(last_message(&controller)
+            .0
+            .starts_with("Health check repaired"));
+        run_ex(&mut controller, "checkhealth");
+        assert!(last_message(&controller)
+            .0
+            .starts_with("Health check passed"));
+    }
+
+    #[test]
+    fn test_compact_command_reports_freed_memory() {
+        let mut controller = create_controller();
+        for row in 0..300 {
+            controller
+                .facade()
+                .set_cell_value(&CellAddress::new(0, row), "text")
+                .unwrap();
+        }
+        for row in 1..300 {
+            controller
+                .facade()
+                .delete_cell(&CellAddress::new(0, row))
+                .unwrap();
+        }
+
+        run_ex(&mut controller, "compact");
+        let entry = controller.get_errors().last().unwrap().clone();
+        assert!(entry.message.starts_with("Compacted "), "{}", entry.message);
+        assert_eq!(entry.severity, ErrorSeverity::Info);
+        assert_eq!(
+            controller
+                .facade()
+                .get_cell_value(&CellAddress::new(0, 0))
+                .as_deref(),
+            Some("text")
+        );
+    }
+
+    #[test]
+    fn test_calc_command_turns_calculation_off_and_catches_up() {
+        let mut controller = create_controller();
+        let set = |controller: &SpreadsheetController, a1: &str, value: &str| {
+            controller
+                .facade()
+                .set_cell_value(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        };
+        let last_message = |controller: &SpreadsheetController| {
+            controller.get_errors().last().unwrap().message.clone()
+        };
+        set(&controller, "A1", "1");
+        set(&controller, "B1", "=A1*2");
+
+        run_ex(&mut controller, "calc off");
+        assert_eq!(last_message(&controller), "Calculation off for Sheet1");
+        set(&controller, "A1", "4");
+        assert_eq!(text_at(&controller, "B1"), CellValue::Number(2.0));
+        assert!(controller
+            .facade()
+            .is_stale(&CellAddress::from_a1("B1").unwrap()));
+
+        run_ex(&mut controller, "calc B1:B1");
+        assert_eq!(last_message(&controller), "Recalculated 1 formula(s)");
+        assert_eq!(text_at(&controller, "B1"), CellValue::Number(8.0));
+
+        set(&controller, "A1", "5");
+        run_ex(&mut controller, "calc on");
+        assert_eq!(
+            last_message(&controller),
+            "Calculation on for Sheet1, recalculated 1 formula(s)"
+        );
+        assert_eq!(text_at(&controller, "B1"), CellValue::Number(10.0));
+    }
+
+    #[test]
+    fn test_calculating_flag_clears_on_completion_and_cancellation() {
+        use gridcore_core::adapters::{EventAdapter, RepositoryAdapter};
+        use gridcore_core::facade::{ProgressCadence, RecalculationMonitor};
+        use gridcore_core::ports::event_port::DomainEvent;
+        use gridcore_core::ports::EventPort;
+        use gridcore_core::SpreadsheetFacade;
+        use std::sync::{Arc, Mutex, OnceLock};
+
+        // A handler watching the progress reports, cancelling the first
+        // recalculation once it is under way
+        let calculating = Arc::new(Mutex::new(Vec::new()));
+        let monitor = Arc::new(OnceLock::<RecalculationMonitor>::new());
+        let (sink, watched) = (calculating.clone(), monitor.clone());
+        let mut events = EventAdapter::new_empty();
+        events
+            .subscribe(Box::new(move |event| {
+                if let DomainEvent::RecalculationProgress { .. } = event {
+                    let monitor = watched.get().unwrap();
+                    let mut calculating = sink.lock().unwrap();
+                    if calculating.is_empty() {
+                        monitor.cancel();
+                    }
+                    calculating.push(monitor.is_calculating());
+                }
+            }))
+            .unwrap();
+        let facade = SpreadsheetFacade::with_ports(
+            Arc::new(RepositoryAdapter::new_empty()),
+            Arc::new(events),
+        );
+        monitor.set(facade.recalculation_monitor()).unwrap();
+        facade.set_progress_cadence(ProgressCadence {
+            min_cells: 100,
+            every_cells: 100,
+            every_ms: f64::INFINITY,
+        });
+        let mut controller = SpreadsheetController::builder().with_facade(facade).build();
+        let last_message = |controller: &SpreadsheetController| {
+            controller.get_errors().last().unwrap().message.clone()
+        };
+
+        let facade = controller.facade();
+        facade.set_cell_value(&CellAddress::new(0, 0), "1").unwrap();
+        for row in 0..300 {
+            facade
+                .set_cell_value(&CellAddress::new(1, row), "=$A$1*2")
+                .unwrap();
+        }
+        run_ex(&mut controller, "calc off");
+        controller
+            .facade()
+            .set_cell_value(&CellAddress::new(0, 0), "2")
+            .unwrap();
+
+        run_ex(&mut controller, "calc");
+        assert_eq!(
+            last_message(&controller),
+            "Recalculation cancelled after 100 formula(s), the rest stay stale"
+        );
+        assert!(!controller.is_calculating());
+        assert_eq!(controller.facade().stale_cells("Sheet1").len(), 200);
+
+        run_ex(&mut controller, "calc");
+        assert_eq!(last_message(&controller), "Recalculated 200 formula(s)");
+        assert!(!controller.is_calculating());
+        assert_eq!(controller.calculation_status(), None);
+        assert!(controller.facade().stale_cells("Sheet1").is_empty());
+        // The handler saw it running at every report
+        assert_eq!(*calculating.lock().unwrap(), vec![true, true]);
+    }
+
+    #[test]
+    fn test_idle_text_measuring_keeps_to_its_budget() {
+        use crate::controller::{IdleTask, ViewportBounds};
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let mut controller = create_controller();
+        for row in 0..40 {
+            controller
+                .write_cell(&CellAddress::new(0, row), &format!("row {}", row))
+                .unwrap();
+        }
+        let measured = Rc::new(Cell::new(0));
+        let counter = measured.clone();
+        controller.set_text_measurer(Some(Box::new(move |text: &str| {
+            counter.set(counter.get() + 1);
+            text.len() as f64 * 7.0
+        })));
+        controller.prefetch_viewport(&ViewportBounds {
+            start_row: 0,
+            end_row: 49,
+            start_col: 0,
+            end_col: 9,
+        });
+        assert_eq!(controller.idle_work().pending(), [IdleTask::MeasureText]);
+
+        // Every reading of the clock takes a millisecond
+        let now = Cell::new(0.0);
+        let clock = || {
+            now.set(now.get() + 1.0);
+            now.get()
+        };
+        assert!(controller.run_idle_work(10.0, &clock));
+        let first_slice = measured.get();
+        assert!(first_slice > 0 && first_slice < 10, "{}", first_slice);
+        assert_eq!(controller.idle_stats().yielded, 1);
+
+        let mut slices = 1;
+        while controller.run_idle_work(10.0, &clock) {
+            slices += 1;
+            assert!(slices < 20);
+        }
+        assert_eq!(measured.get(), 40);
+        assert_eq!(controller.idle_stats().processed, 1);
+
+        // Measured widths are served without measuring again
+        assert_eq!(controller.text_width("row 12"), Some(42.0));
+        assert_eq!(measured.get(), 40);
+    }
+
+    #[test]
+    fn test_giant_text_is_measured_once_and_cut_short() {
+        use crate::controller::viewport_cache::MAX_DISPLAY_CHARS;
+        use crate::controller::ViewportBounds;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut controller = create_controller();
+        let measured = Rc::new(RefCell::new(Vec::new()));
+        let log = measured.clone();
+        controller.set_text_measurer(Some(Box::new(move |text: &str| {
+            log.borrow_mut().push(text.chars().count());
+            text.len() as f64 * 7.0
+        })));
+        controller
+            .write_cell(&CellAddress::new(0, 0), &"x".repeat(30_000))
+            .unwrap();
+        let bounds = ViewportBounds {
+            start_row: 0,
+            end_row: 9,
+            start_col: 0,
+            end_col: 4,
+        };
+        controller.prefetch_viewport(&bounds);
+        while controller.run_idle_work(100.0, &|| 0.0) {}
+
+        // Drawing the cell again and again measures nothing new
+        for _ in 0..10 {
+            for cell in controller.get_display_list(&bounds) {
+                for line in cell.lines() {
+                    controller.text_width(&line);
+                }
+            }
+        }
+        assert_eq!(*measured.borrow(), vec![MAX_DISPLAY_CHARS + 1]);
+    }
+
+    #[test]
+    fn test_scrolls_and_edits_invalidate_idle_work() {
+        use crate::controller::{IdlePriority, IdleTask, ViewportBounds};
+
+        let mut controller = create_controller();
+        controller.set_text_measurer(Some(Box::new(|text: &str| text.len() as f64)));
+        controller.enqueue_idle_work(IdleTask::PrefetchViewport, IdlePriority::Low);
+        assert_eq!(
+            controller.idle_work().pending(),
+            [IdleTask::MeasureText, IdleTask::PrefetchViewport]
+        );
+
+        // Scrolling drops the text picked for the old view and hurries the
+        // prefetch past later work
+        controller.note_viewport_scrolled();
+        assert_eq!(
+            controller.idle_work().pending(),
+            [IdleTask::PrefetchViewport]
+        );
+        assert_eq!(controller.idle_stats().cancelled, 1);
+        controller.enqueue_idle_work(IdleTask::MeasureText, IdlePriority::Normal);
+        assert_eq!(
+            controller.idle_work().pending(),
+            [IdleTask::PrefetchViewport, IdleTask::MeasureText]
+        );
+
+        // An edit lists the texts again
+        assert!(!controller.run_idle_work(100.0, &|| 0.0));
+        controller
+            .write_cell(&CellAddress::new(1, 1), "hello")
+            .unwrap();
+        assert_eq!(controller.idle_work().pending(), [IdleTask::MeasureText]);
+        assert!(!controller.run_idle_work(100.0, &|| 0.0));
+        assert!(controller.get_text_widths().is_measured("hello"));
+        assert!(!controller.needs_prefetch(&ViewportBounds {
+            start_row: 0,
+            end_row: 9,
+            start_col: 0,
+            end_col: 4,
+        }));
+    }
+
+    fn quick_sum_key() -> KeyboardEvent {
+        key_event("=").with_modifiers(false, false, true, false)
+    }
+
+    #[test]
+    fn test_quick_sum_of_a_column_and_a_row() {
+        let mut controller = create_controller();
+        for (a1, value) in [
+            ("A1", "1"),
+            ("A2", "2"),
+            ("A3", "3"),
+            ("C1", "4"),
+            ("D1", "5"),
+        ] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+
+        // Visual selection of the column, totalled below it
+        controller.set_cursor(CellAddress::from_a1("A1").unwrap());
+        for key in ["v", "j", "j"] {
+            controller.handle_keyboard_event(key_event(key)).unwrap();
+        }
+        controller.handle_keyboard_event(quick_sum_key()).unwrap();
+        assert_eq!(text_at(&controller, "A4"), CellValue::Number(6.0));
+        assert_eq!(controller.cursor(), CellAddress::from_a1("A4").unwrap());
+        assert!(controller.get_mode().is_navigation());
+        assert!(controller.get_selection().is_none());
+
+        select_range(&mut controller, "C1:D1");
+        run_ex(&mut controller, "total count");
+        assert_eq!(text_at(&controller, "E1"), CellValue::Number(2.0));
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::from_a1("E1").unwrap()),
+            "=COUNT(C1:D1)"
+        );
+        assert_eq!(controller.cursor(), CellAddress::from_a1("E1").unwrap());
+    }
+
+    #[test]
+    fn test_quick_sum_of_a_block_twice_adds_row_and_grand_totals() {
+        let mut controller = create_controller();
+        for (a1, value) in [("A1", "1"), ("B1", "2"), ("A2", "3"), ("B2", "4")] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+
+        select_range(&mut controller, "A1:B2");
+        controller.handle_keyboard_event(quick_sum_key()).unwrap();
+        assert_eq!(text_at(&controller, "A3"), CellValue::Number(4.0));
+        assert_eq!(text_at(&controller, "B3"), CellValue::Number(6.0));
+        assert_eq!(text_at(&controller, "C1"), CellValue::Empty);
+        assert_eq!(controller.cursor(), CellAddress::from_a1("A3").unwrap());
+
+        controller.handle_keyboard_event(quick_sum_key()).unwrap();
+        assert_eq!(text_at(&controller, "C1"), CellValue::Number(3.0));
+        assert_eq!(text_at(&controller, "C2"), CellValue::Number(7.0));
+        assert_eq!(text_at(&controller, "C3"), CellValue::Number(10.0));
+        assert_eq!(controller.cursor(), CellAddress::from_a1("C1").unwrap());
+
+        // A third press totals the cursor cell rather than the block again
+        controller.handle_keyboard_event(quick_sum_key()).unwrap();
+        assert_eq!(text_at(&controller, "D1"), CellValue::Empty);
+    }
+
+    #[test]
+    fn test_quick_totals_skip_occupied_rows_and_blank_selections() {
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        for (a1, value) in [("A1", "1"), ("A2", "2"), ("A3", "note")] {
+            controller
+                .write_cell(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+
+        select_range(&mut controller, "A1:A2");
+        controller.dispatch_action(Action::QuickAverage).unwrap();
+        assert_eq!(
+            controller.get_cell_display_for_ui(&CellAddress::from_a1("A3").unwrap()),
+            "note"
+        );
+        assert_eq!(text_at(&controller, "A4"), CellValue::Number(1.5));
+        assert_eq!(controller.cursor(), CellAddress::from_a1("A4").unwrap());
+
+        select_range(&mut controller, "F1:F3");
+        controller.dispatch_action(Action::QuickSum).unwrap();
+        assert_eq!(text_at(&controller, "F4"), CellValue::Empty);
+        assert!(controller
+            .get_errors()
+            .iter()
+            .any(|error| error.message.contains("Nothing to total")));
+    }
+
+    #[test]
+    fn test_csv_sidecar_carries_column_widths() {
+        let mut controller = create_controller();
+        controller
+            .write_cell(&CellAddress::from_a1("A1").unwrap(), "=B1*2")
+            .unwrap();
+        controller
+            .write_cell(&CellAddress::from_a1("B1").unwrap(), "21")
+            .unwrap();
+        controller
+            .get_viewport_manager_mut()
+            .set_column_width(1, 180.0);
+
+        let export = controller.export_csv(true);
+        let sidecar = export.sidecar.unwrap();
+        assert_eq!(sidecar.column_widths["B"], 180.0);
+
+        let mut copy = create_controller();
+        let report = copy.import_csv(&export.csv, Some(&sidecar)).unwrap();
+        assert!(report.is_faithful(), "{}", report);
+        assert_eq!(copy.get_viewport_manager().get_column_width(1), 180.0);
+        assert_eq!(text_at(&copy, "A1"), CellValue::Number(42.0));
+        assert_eq!(
+            copy.facade().content_hash(),
+            controller.facade().content_hash()
+        );
+    }
+
+    #[test]
+    fn test_lenient_import_keeps_a_report_until_dismissed() {
+        use crate::state::Action;
+        use gridcore_core::csv::CsvImportOptions;
+
+        let csv = "Item,Total\nApples,=2*(3\n";
+        let sidecar = r#"{"version": 1, "column_widths": {"A": 120.0}, "charts": []}"#;
+        let mut controller = create_controller();
+        let report = controller
+            .import_csv_document(csv, Some(sidecar), &CsvImportOptions::lenient())
+            .unwrap();
+        assert_eq!(report.formulas_as_text.len(), 1);
+        assert_eq!(report.preserved_sections, ["charts"]);
+        assert_eq!(controller.get_viewport_manager().get_column_width(0), 120.0);
+        assert_eq!(
+            controller
+                .get_load_report()
+                .and_then(|report| report.summary()),
+            report.summary()
+        );
+
+        controller
+            .dispatch_action(Action::DismissLoadReport)
+            .unwrap();
+        assert!(controller.get_load_report().is_none());
+
+        // A sidecar that does not read fails a strict import
+        let mut strict = create_controller();
+        assert!(strict
+            .import_csv_document(csv, Some("{"), &CsvImportOptions::default())
+            .is_err());
+        assert!(strict.get_load_report().is_none());
+    }
+
+    #[test]
+    fn test_commit_import_into_a_new_sheet() {
+        use gridcore_core::csv::{preview_import, ColumnType, ImportDestination, ImportOptions};
+
+        let source = b"sku,price\n0042,1.5\n0107,2\n";
+        let preview = preview_import(source, &ImportOptions::default(), 1);
+        assert_eq!(preview.sample, [vec!["0042", "1.5"]]);
+        let options = ImportOptions {
+            column_types: vec![ColumnType::Skip, ColumnType::Number],
+            destination: ImportDestination::NewSheet {
+                name: "Prices".to_string(),
+            },
+            ..preview.options
+        };
+
+        let mut controller = create_controller();
+        let report = controller.commit_import(source, &options).unwrap();
+        assert_eq!(report.cells, 3);
+        assert_eq!(controller.get_active_sheet(), "Prices");
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("price")
+        );
+        assert_eq!(text_at(&controller, "A3"), CellValue::Number(2.0));
+        assert_eq!(controller.sheet_count(), 2);
+    }
+
+    #[test]
+    fn test_wheel_scrolls_dispatch_once_per_frame() {
+        use crate::controller::events::SpreadsheetEvent;
+        use std::sync::{Arc, Mutex};
+
+        let mut controller = create_controller();
+        let scrolls = Arc::new(Mutex::new(Vec::new()));
+        let sink = scrolls.clone();
+        controller.subscribe_to_events(move |event| {
+            if let SpreadsheetEvent::ViewportScrolled { delta_x, delta_y } = event {
+                sink.lock().unwrap().push((*delta_x, *delta_y));
+            }
+        });
+
+        for _ in 0..40 {
+            controller.queue_scroll(0.0, 2.5);
+        }
+        assert!(scrolls.lock().unwrap().is_empty());
+        assert!(controller.apply_pending_scroll().is_some());
+        assert_eq!(*scrolls.lock().unwrap(), [(0.0, 100.0)]);
+        assert_eq!(controller.apply_pending_scroll(), None);
+
+        // Keyboard scrolls skip the queue and leave queued wheel deltas alone
+        controller.queue_scroll(0.0, 10.0);
+        controller.scroll_now(0.0, -30.0);
+        assert_eq!(scrolls.lock().unwrap().last(), Some(&(0.0, -30.0)));
+        controller.apply_pending_scroll();
+        assert_eq!(scrolls.lock().unwrap().last(), Some(&(0.0, 10.0)));
+        assert_eq!(
+            controller.get_viewport_manager().get_scroll_position().y,
+            80.0
+        );
+    }
+
+    /// A1:A6 holding 1..=6 with rows 2, 3 and 5 hidden
+    fn filtered_column() -> SpreadsheetController {
+        let mut controller = create_controller();
+        for row in 0..6 {
+            controller
+                .write_cell(&CellAddress::new(0, row), &(row + 1).to_string())
+                .unwrap();
+        }
+        select_range(&mut controller, "A2:A3");
+        run_ex(&mut controller, "hide rows");
+        select_range(&mut controller, "A5:A5");
+        run_ex(&mut controller, "hide");
+        assert_eq!(controller.get_viewport_manager().hidden_rows(), [1, 2, 4]);
+        controller
+    }
+
+    #[test]
+    fn test_copy_skips_hidden_rows() {
+        use crate::behaviors::clipboard::CopyOptions;
+        use crate::state::Action;
+
+        let mut controller = filtered_column();
+        controller
+            .write_cell(&CellAddress::new(1, 5), "=A6*10")
+            .unwrap();
+        select_range(&mut controller, "A1:B6");
+        let copied = controller.copy_selection().unwrap();
+        assert_eq!(copied.text, "1\t\n4\t\n6\t60\n");
+
+        // The visible rows paste as one compact block
+        controller.set_selection(None);
+        controller.set_cursor(CellAddress::from_a1("D1").unwrap());
+        controller
+            .dispatch_action(Action::PasteClipboard {
+                text: copied.text.clone(),
+                rich: Some(copied.payload.to_json()),
+            })
+            .unwrap();
+        assert_eq!(text_at(&controller, "D2"), CellValue::Number(4.0));
+        assert_eq!(text_at(&controller, "D3"), CellValue::Number(6.0));
+        assert_eq!(text_at(&controller, "D4"), CellValue::Empty);
+        let e3 = controller
+            .facade()
+            .get_cell(&CellAddress::from_a1("E3").unwrap())
+            .unwrap();
+        assert_eq!(e3.formula_text.as_deref(), Some("D3*10"));
+
+        // Hidden rows can still be asked for
+        controller.set_copy_options(CopyOptions {
+            include_hidden: true,
+        });
+        select_range(&mut controller, "A1:A6");
+        let copied = controller.copy_selection().unwrap();
+        assert_eq!(copied.text, "1\n2\n3\n4\n5\n6\n");
+    }
+
+    #[test]
+    fn test_stats_and_visible_selection_skip_hidden_rows() {
+        let mut controller = filtered_column();
+        select_range(&mut controller, "A1:A6");
+        let stats = controller.get_current_selection_stats();
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.sum, Some(11.0));
+
+        // Alt+; narrows the selection to the visible blocks
+        let mut alt = key_event(";");
+        alt.alt = true;
+        controller.handle_keyboard_event(alt).unwrap();
+        let Some(SelectionType::Multi { selections }) = controller
+            .get_selection()
+            .map(|selection| selection.selection_type.clone())
+        else {
+            panic!("expected a multi selection");
+        };
+        assert_eq!(selections.len(), 3);
+        assert_eq!(controller.get_current_selection_stats().sum, Some(11.0));
+
+        // The narrowed selection still copies as one block
+        let copied = controller.copy_selection().unwrap();
+        assert_eq!(copied.text, "1\n4\n6\n");
+    }
+
+    #[test]
+    fn test_clearing_a_selection_leaves_hidden_rows() {
+        let mut controller = filtered_column();
+        controller.set_cursor(CellAddress::new(0, 0));
+        controller.handle_keyboard_event(key_event("v")).unwrap();
+        for _ in 0..5 {
+            controller.handle_keyboard_event(key_event("j")).unwrap();
+        }
+        controller.handle_keyboard_event(key_event("d")).unwrap();
+
+        assert!(!controller.get_mode().is_visual());
+        let column: Vec<CellValue> = (1..=6)
+            .map(|row| text_at(&controller, &format!("A{}", row)))
+            .collect();
+        assert_eq!(
+            column,
+            [
+                CellValue::Empty,
+                CellValue::Number(2.0),
+                CellValue::Number(3.0),
+                CellValue::Empty,
+                CellValue::Number(5.0),
+                CellValue::Empty,
+            ]
+        );
+
+        // Unhiding brings the rows back untouched
+        select_range(&mut controller, "A1:A6");
+        run_ex(&mut controller, "unhide rows");
+        assert!(controller.get_viewport_manager().hidden_rows().is_empty());
+        assert_eq!(controller.get_viewport_manager().get_row_height(1), 24.0);
+    }
+
+    #[test]
+    fn test_fill_down_copies_the_top_row() {
+        let mut controller = create_controller();
+        for (row, price) in ["3", "5", "12"].iter().enumerate() {
+            controller
+                .facade()
+                .set_cell_value(&CellAddress::new(0, row as u32), price)
+                .unwrap();
+        }
+        controller
+            .facade()
+            .set_cell_value(&CellAddress::new(1, 0), "=A1*2")
+            .unwrap();
+
+        // A single row has nothing to fill
+        controller.set_cursor(CellAddress::new(1, 0));
+        controller.handle_keyboard_event(key_event("v")).unwrap();
+        assert!(controller.fill_down().is_err());
+
+        let mut ctrl_d = key_event("d");
+        ctrl_d.ctrl = true;
+        type_keys(&mut controller, &["j", "j"]);
+        controller.handle_keyboard_event(ctrl_d).unwrap();
+
+        assert!(!controller.get_mode().is_visual());
+        assert_eq!(text_at(&controller, "B2"), CellValue::Number(10.0));
+        assert_eq!(text_at(&controller, "B3"), CellValue::Number(24.0));
+    }
+
+    fn formula_at(controller: &SpreadsheetController, a1: &str) -> Option<String> {
+        controller
+            .facade()
+            .get_cell(&CellAddress::from_a1(a1).unwrap())
+            .and_then(|cell| cell.formula_text.as_deref().map(str::to_string))
+    }
+
+    #[test]
+    fn test_fill_down_skips_filtered_rows() {
+        let mut controller = create_controller();
+        for row in 1..=8 {
+            controller
+                .write_cell(&CellAddress::new(1, row), &(row * 10).to_string())
+                .unwrap();
+        }
+        controller
+            .write_cell(&CellAddress::from_a1("C2").unwrap(), "=B2*2")
+            .unwrap();
+        controller
+            .write_cell(&CellAddress::from_a1("C5").unwrap(), "kept")
+            .unwrap();
+        // Rows 3, 5 and 6 are filtered out
+        let viewport = controller.get_viewport_manager_mut();
+        viewport.set_rows_hidden(2, 2, true);
+        viewport.set_rows_hidden(4, 5, true);
+
+        select_range(&mut controller, "C2:C8");
+        controller.fill_down().unwrap();
+
+        for (a1, formula) in [("C4", "B4*2"), ("C7", "B7*2"), ("C8", "B8*2")] {
+            assert_eq!(
+                formula_at(&controller, a1).as_deref(),
+                Some(formula),
+                "{}",
+                a1
+            );
+        }
+        assert_eq!(text_at(&controller, "C4"), CellValue::Number(60.0));
+        assert_eq!(text_at(&controller, "C3"), CellValue::Empty);
+        assert_eq!(
+            text_at(&controller, "C5"),
+            CellValue::string_from_str("kept")
+        );
+        assert_eq!(text_at(&controller, "C6"), CellValue::Empty);
+    }
+
+    #[test]
+    fn test_paste_repeats_a_formula_across_every_selected_range() {
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        controller
+            .write_cell(&CellAddress::from_a1("B1").unwrap(), "=A1*2")
+            .unwrap();
+        controller.set_cursor(CellAddress::from_a1("B1").unwrap());
+        let copied = controller.copy_selection().unwrap();
+
+        let part = |a1: &str| {
+            let range = CellRange::from_string(a1).unwrap();
+            Selection {
+                selection_type: SelectionType::Range {
+                    start: range.start,
+                    end: range.end,
+                },
+                anchor: Some(range.start),
+            }
+        };
+        controller
+            .dispatch_action(Action::UpdateSelection {
+                selection: Selection {
+                    selection_type: SelectionType::Multi {
+                        selections: vec![part("D2:D3"), part("F5:G5"), part("D8:D8")],
+                    },
+                    anchor: None,
+                },
+            })
+            .unwrap();
+        let conflicts = controller
+            .paste_clipboard(&copied.text, Some(&copied.payload.to_json()))
+            .unwrap();
+        assert!(conflicts.is_none());
+
+        for (a1, formula) in [
+            ("D2", "C2*2"),
+            ("D3", "C3*2"),
+            ("F5", "E5*2"),
+            ("G5", "F5*2"),
+            ("D8", "C8*2"),
+        ] {
+            assert_eq!(
+                formula_at(&controller, a1).as_deref(),
+                Some(formula),
+                "{}",
+                a1
+            );
+        }
+        for a1 in ["D4", "E5", "D5", "D9"] {
+            assert_eq!(text_at(&controller, a1), CellValue::Empty, "{}", a1);
+        }
+
+        // Overwriting any part asks first, then writes all of them
+        controller
+            .write_cell(&CellAddress::from_a1("G5").unwrap(), "x")
+            .unwrap();
+        let conflicts = controller
+            .paste_clipboard(&copied.text, Some(&copied.payload.to_json()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(conflicts.overwritten.len(), 5);
+        controller.confirm_paste().unwrap();
+        assert_eq!(formula_at(&controller, "G5").as_deref(), Some("F5*2"));
+    }
+
+    #[test]
+    fn test_sparklines_reach_the_display_list() {
+        use crate::controller::ViewportBounds;
+        use gridcore_core::sparkline::SparklineKind;
+
+        let mut controller = create_controller();
+        for (a1, value) in [
+            ("A1", "1"),
+            ("B1", "3"),
+            ("C1", "2"),
+            ("A2", "4"),
+            ("B2", "4"),
+            ("C2", "4"),
+            ("D1", "=SPARKLINE(A1:C1, \"bar\", \"color=#188038\")"),
+        ] {
+            controller
+                .facade()
+                .set_cell_value(&CellAddress::from_a1(a1).unwrap(), value)
+                .unwrap();
+        }
+        let bounds = ViewportBounds {
+            start_row: 0,
+            end_row: 19,
+            start_col: 0,
+            end_col: 9,
+        };
+        let sparkline_at = |controller: &SpreadsheetController, a1: &str| {
+            let address = CellAddress::from_a1(a1).unwrap();
+            controller
+                .get_display_list(&bounds)
+                .into_iter()
+                .find(|cell| cell.address == address)
+                .and_then(|cell| cell.sparkline)
+        };
+
+        let sparkline = sparkline_at(&controller, "D1").unwrap();
+        assert_eq!(sparkline.kind, SparklineKind::Bar);
+        assert_eq!(sparkline.options.color.as_deref(), Some("#188038"));
+        assert_eq!(
+            sparkline.points,
+            [Some(1.0 / 3.0), Some(1.0), Some(2.0 / 3.0)]
+        );
+
+        // Filled down, the range follows the row
+        controller.set_cursor(CellAddress::from_a1("D1").unwrap());
+        controller.handle_keyboard_event(key_event("v")).unwrap();
+        controller.handle_keyboard_event(key_event("j")).unwrap();
+        controller.fill_down().unwrap();
+        let equal = sparkline_at(&controller, "D2").unwrap();
+        assert_eq!(equal.points, [Some(1.0); 3]);
+
+        // A changed value redraws the sparkline
+        controller
+            .write_cell(&CellAddress::from_a1("B1").unwrap(), "0")
+            .unwrap();
+        let sparkline = sparkline_at(&controller, "D1").unwrap();
+        assert_eq!(sparkline.points, [Some(0.5), Some(0.0), Some(1.0)]);
+    }
+
+    fn alt_enter() -> KeyboardEvent {
+        key_event("Enter").with_modifiers(false, false, true, false)
+    }
+
+    fn editing_value(controller: &SpreadsheetController) -> Option<String> {
+        match controller.get_mode() {
+            EditorMode::Editing { value, .. } | EditorMode::CellEditing { value, .. } => {
+                Some(value.clone())
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_alt_enter_breaks_lines_and_enter_commits() {
+        // Vim insert mode: Alt+Enter breaks the line, Enter commits
+        let mut controller = create_controller();
+        type_keys(&mut controller, &["i", "a", "b"]);
+        controller.handle_keyboard_event(alt_enter()).unwrap();
+        type_keys(&mut controller, &["c", "d"]);
+        assert_eq!(editing_value(&controller).as_deref(), Some("ab\ncd"));
+        type_keys(&mut controller, &["Enter"]);
+        assert!(!controller.get_mode().is_editing());
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::from_string("ab\ncd".to_string())
+        );
+
+        // Without vim: Escape drops the lines, Enter stores them and moves on
+        let mut controller = SpreadsheetController::builder().vim_enabled(false).build();
+        type_keys(&mut controller, &["x"]);
+        controller.handle_keyboard_event(alt_enter()).unwrap();
+        type_keys(&mut controller, &["y", "Escape"]);
+        assert_eq!(text_at(&controller, "A1"), CellValue::Empty);
+
+        enter_value(&mut controller, "x", "Tab", false);
+        controller.set_cursor(CellAddress::new(0, 0));
+        type_keys(&mut controller, &["y"]);
+        controller.handle_keyboard_event(alt_enter()).unwrap();
+        controller.handle_keyboard_event(alt_enter()).unwrap();
+        type_keys(&mut controller, &["z", "Enter"]);
+        assert_eq!(controller.cursor(), CellAddress::new(0, 1));
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::from_string("y\n\nz".to_string())
+        );
+    }
+
+    fn ctrl(key: &str) -> KeyboardEvent {
+        key_event(key).with_modifiers(false, true, false, false)
+    }
+
+    #[test]
+    fn test_ctrl_n_and_ctrl_p_cycle_recent_entries() {
+        let mut controller = SpreadsheetController::builder().vim_enabled(false).build();
+        for text in ["Paid", "Pending", "Paid", "=1+1", "42"] {
+            enter_value(&mut controller, text, "Enter", false);
+        }
+        // Formulas and numbers are not offered again
+        let texts: Vec<&str> = controller
+            .get_recent_entries()
+            .entries()
+            .iter()
+            .map(|entry| entry.text.as_str())
+            .collect();
+        assert_eq!(texts, ["Paid", "Pending"]);
+
+        // B1 holds a value typed into another column: only column A counts
+        controller
+            .write_cell(&CellAddress::new(1, 0), "Parcel")
+            .unwrap();
+        type_keys(&mut controller, &["p"]);
+        controller.handle_keyboard_event(ctrl("n")).unwrap();
+        assert_eq!(editing_value(&controller).as_deref(), Some("Paid"));
+        controller.handle_keyboard_event(ctrl("n")).unwrap();
+        assert_eq!(editing_value(&controller).as_deref(), Some("Pending"));
+        controller.handle_keyboard_event(ctrl("n")).unwrap();
+        assert_eq!(editing_value(&controller).as_deref(), Some("p"));
+        controller.handle_keyboard_event(ctrl("p")).unwrap();
+        assert_eq!(editing_value(&controller).as_deref(), Some("Pending"));
+
+        // Typing starts over from the new text
+        type_keys(
+            &mut controller,
+            &["Backspace", "Backspace", "Backspace", "Backspace"],
+        );
+        assert_eq!(editing_value(&controller).as_deref(), Some("Pen"));
+        controller.handle_keyboard_event(ctrl("n")).unwrap();
+        assert_eq!(editing_value(&controller).as_deref(), Some("Pending"));
+        type_keys(&mut controller, &["Enter"]);
+        assert_eq!(
+            text_at(&controller, "A6"),
+            CellValue::from_string("Pending".to_string())
+        );
+        assert_eq!(controller.get_recent_entries().entries()[0].uses, 2);
+    }
+
+    #[test]
+    fn test_ctrl_down_picks_a_recent_entry() {
+        let mut controller = create_controller();
+        for (row, text) in ["North", "South", "North"].into_iter().enumerate() {
+            controller.set_cursor(CellAddress::new(0, row as u32));
+            type_keys(&mut controller, &["i"]);
+            enter_value(&mut controller, text, "Enter", false);
+        }
+        controller.set_cursor(CellAddress::new(0, 3));
+        type_keys(&mut controller, &["i"]);
+        controller.handle_keyboard_event(ctrl("ArrowDown")).unwrap();
+        let picker = controller.entry_picker().unwrap();
+        assert_eq!(picker.candidates, ["North", "South"]);
+
+        type_keys(&mut controller, &["ArrowDown"]);
+        assert_eq!(controller.entry_picker().unwrap().selected, 1);
+        type_keys(&mut controller, &["Enter"]);
+        assert!(controller.entry_picker().is_none());
+        assert!(!controller.get_mode().is_editing());
+        assert_eq!(
+            text_at(&controller, "A4"),
+            CellValue::from_string("South".to_string())
+        );
+
+        // Only matching entries are listed, and Escape closes the list
+        // without leaving the editor
+        controller.set_cursor(CellAddress::new(0, 4));
+        type_keys(&mut controller, &["i", "s"]);
+        controller.handle_keyboard_event(ctrl("ArrowDown")).unwrap();
+        assert_eq!(controller.entry_picker().unwrap().candidates, ["South"]);
+        type_keys(&mut controller, &["Escape"]);
+        assert!(controller.entry_picker().is_none());
+        assert_eq!(editing_value(&controller).as_deref(), Some("s"));
+
+        // The entries survive a UI state snapshot
+        let restored = SpreadsheetController::builder()
+            .with_ui_state(controller.get_ui_state())
+            .build();
+        assert_eq!(
+            restored.get_recent_entries().entries(),
+            controller.get_recent_entries().entries()
+        );
+    }
+
+    #[test]
+    fn test_formulas_break_lines_only_inside_strings() {
+        let mut controller = SpreadsheetController::builder().vim_enabled(false).build();
+        type_keys(&mut controller, &["=", "1", "+"]);
+        controller.handle_keyboard_event(alt_enter()).unwrap();
+        type_keys(&mut controller, &["2", "Enter"]);
+
+        // Refused: the editor stays open with the entry intact
+        assert_eq!(editing_value(&controller).as_deref(), Some("=1+\n2"));
+        assert_eq!(text_at(&controller, "A1"), CellValue::Empty);
+        let errors = controller.errors().get_active_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("line breaks"));
+        type_keys(&mut controller, &["Escape"]);
+
+        type_keys(&mut controller, &["=", "L", "E", "N", "(", "\"", "a"]);
+        controller.handle_keyboard_event(alt_enter()).unwrap();
+        type_keys(&mut controller, &["b", "\"", ")", "Enter"]);
+        assert!(!controller.get_mode().is_editing());
+        assert_eq!(text_at(&controller, "A1"), CellValue::Number(3.0));
+    }
+
+    #[test]
+    fn test_modified_flags_per_sheet() {
+        use crate::controller::events::SpreadsheetEvent;
+
+        let mut controller = SpreadsheetController::builder().vim_enabled(false).build();
+        assert!(!controller.is_document_modified());
+        let transitions = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = transitions.clone();
+        controller.subscribe_to_events(move |event| {
+            if let SpreadsheetEvent::ModifiedChanged {
+                modified_sheets,
+                document_modified,
+            } = event
+            {
+                sink.lock()
+                    .unwrap()
+                    .push((modified_sheets.clone(), *document_modified));
+            }
+        });
+
+        // Sheet1 reads Data; both saved
+        controller.add_sheet("Data").unwrap();
+        assert_eq!(controller.save_state().modified_sheets(), vec!["Data"]);
+        enter_value(&mut controller, "=Data!A1*2", "Enter", false);
+        controller.mark_saved();
+        assert!(!controller.is_document_modified());
+        transitions.lock().unwrap().clear();
+
+        // Editing Data dirties only Data: Sheet1 is compared by what was
+        // typed into it, not by what its formulas compute
+        controller.set_active_sheet("Data").unwrap();
+        controller.set_cursor(CellAddress::new(0, 0));
+        enter_value(&mut controller, "5", "Enter", false);
+        assert_eq!(controller.save_state().modified_sheets(), vec!["Data"]);
+        assert!(controller.is_document_modified());
+        let state = controller.save_state();
+        assert_eq!(state.unsaved_operations(), 1);
+        assert_eq!(
+            state.last_change_label().as_deref(),
+            Some("Edited just now")
+        );
+
+        // More edits are more operations but no new transition
+        controller.set_cursor(CellAddress::new(0, 0));
+        enter_value(&mut controller, "6", "Enter", false);
+        assert_eq!(controller.save_state().unsaved_operations(), 2);
+
+        // Back to the saved content: clean again
+        controller.set_cursor(CellAddress::new(0, 0));
+        type_keys(&mut controller, &["Delete"]);
+        assert!(!controller.is_document_modified());
+        assert_eq!(controller.save_state().unsaved_operations(), 0);
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            vec![(vec!["Data".to_string()], true), (vec![], false)]
+        );
+
+        // A partial save leaves the other sheet flagged
+        enter_value(&mut controller, "7", "Enter", false);
+        controller.set_active_sheet("Sheet1").unwrap();
+        controller.set_cursor(CellAddress::new(1, 0));
+        enter_value(&mut controller, "x", "Enter", false);
+        controller.mark_sheets_saved(&["Data"]);
+        assert_eq!(controller.save_state().modified_sheets(), vec!["Sheet1"]);
+
+        // Removing a saved sheet modifies the document without a tab to mark
+        controller.mark_saved();
+        controller.remove_sheet("Data").unwrap();
+        assert!(controller.save_state().modified_sheets().is_empty());
+        assert!(controller.is_document_modified());
+    }
+
+    #[test]
+    fn test_open_document_from_template() {
+        use gridcore_core::template::{instantiate_template, EmbeddedTemplates};
+
+        let mut controller = SpreadsheetController::builder().vim_enabled(false).build();
+        enter_value(&mut controller, "old", "Enter", false);
+        controller.set_cursor(CellAddress::new(3, 5));
+        assert!(controller.is_document_modified());
+
+        let document = instantiate_template(
+            &EmbeddedTemplates,
+            "invoice",
+            &[("customer".to_string(), "Acme".to_string())],
+        )
+        .unwrap();
+        controller.open_document(document);
+        assert_eq!(
+            text_at(&controller, "A1"),
+            CellValue::string_from_str("Invoice")
+        );
+        assert_eq!(
+            text_at(&controller, "B3"),
+            CellValue::string_from_str("Acme")
+        );
+        assert_eq!(controller.cursor(), CellAddress::new(0, 0));
+        assert_eq!(controller.get_formula_bar_value(), "Invoice");
+        assert!(!controller.is_document_modified());
+    }
+    #[test]
+    fn test_fold_commands_hide_and_show_rows() {
+        let mut controller = create_controller();
+        run_ex(&mut controller, "3,8fold");
+        run_ex(&mut controller, "4,5fold");
+        let folds: Vec<_> = controller
+            .folds()
+            .iter()
+            .map(|fold| (fold.start_row, fold.end_row, fold.level, fold.collapsed))
+            .collect();
+        assert_eq!(folds, [(2, 7, 1, false), (3, 4, 2, false)]);
+
+        run_ex(&mut controller, "3,8foldclose!");
+        assert_eq!(
+            controller.get_viewport_manager().hidden_rows(),
+            [3, 4, 5, 6, 7]
+        );
+
+        // One level at a time: the outer fold opens, the inner stays closed
+        run_ex(&mut controller, "3foldopen");
+        assert_eq!(controller.get_viewport_manager().hidden_rows(), [4]);
+        run_ex(&mut controller, "foldopen!");
+        controller.set_cursor(CellAddress::new(0, 3));
+        run_ex(&mut controller, "foldopen!");
+        assert!(controller.get_viewport_manager().hidden_rows().is_empty());
+
+        run_ex(&mut controller, "set foldcolumn=3");
+        assert_eq!(controller.fold_column(), 3);
+        run_ex(&mut controller, "set foldcolumn=13");
+        assert_eq!(controller.fold_column(), 3);
+        assert!(!controller.get_errors().is_empty());
+    }
+
+    #[test]
+    fn test_set_palette() {
+        let mut controller = create_controller();
+        assert_eq!(controller.palette(), Palette::Standard);
+        run_ex(&mut controller, "set palette=colorblind");
+        assert_eq!(controller.palette(), Palette::ColorblindSafe);
+        run_ex(&mut controller, "set palette=sepia");
+        assert_eq!(controller.palette(), Palette::ColorblindSafe);
+        assert!(!controller.get_errors().is_empty());
+    }
+
+    #[test]
+    fn test_fold_keys() {
+        let mut controller = create_controller();
+        select_range(&mut controller, "A3:A6");
+        type_keys(&mut controller, &["v", "j", "z", "f"]);
+        assert_eq!(controller.folds().len(), 1);
+        let fold = &controller.folds()[0];
+        assert_eq!((fold.start_row, fold.end_row), (2, 3));
+
+        controller.set_cursor(CellAddress::new(0, 0));
+        type_keys(&mut controller, &["z", "j"]);
+        assert_eq!(controller.get_cursor(), CellAddress::new(0, 2));
+        type_keys(&mut controller, &["z", "c"]);
+        assert_eq!(controller.get_viewport_manager().hidden_rows(), [3]);
+        type_keys(&mut controller, &["z", "a"]);
+        assert!(controller.get_viewport_manager().hidden_rows().is_empty());
+
+        controller.set_cursor(CellAddress::new(0, 9));
+        type_keys(&mut controller, &["z", "k"]);
+        assert_eq!(controller.get_cursor(), CellAddress::new(0, 3));
+        type_keys(&mut controller, &["z", "M"]);
+        assert_eq!(controller.get_viewport_manager().hidden_rows(), [3]);
+        type_keys(&mut controller, &["z", "d"]);
+        assert!(controller.folds().is_empty());
+        assert!(controller.get_viewport_manager().hidden_rows().is_empty());
+    }
+
+    #[test]
+    fn test_folds_keep_rows_the_user_hid() {
+        let mut controller = create_controller();
+        select_range(&mut controller, "A4:A4");
+        run_ex(&mut controller, "hide");
+        run_ex(&mut controller, "2,6fold");
+        run_ex(&mut controller, "2foldclose");
+        assert_eq!(
+            controller.get_viewport_manager().hidden_rows(),
+            [2, 3, 4, 5]
+        );
+        run_ex(&mut controller, "2foldopen");
+        assert_eq!(controller.get_viewport_manager().hidden_rows(), [3]);
+
+        // Deleting rows above the fold moves the rows it hides
+        run_ex(&mut controller, "2foldclose");
+        controller.delete_rows(0, 1).unwrap();
+        let fold = &controller.folds()[0];
+        assert_eq!((fold.start_row, fold.end_row), (0, 4));
+        assert_eq!(
+            controller.get_viewport_manager().hidden_rows(),
+            [1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_folds_follow_inserted_rows() {
+        let mut controller = create_controller();
+        run_ex(&mut controller, "6,8fold");
+        run_ex(&mut controller, "6foldclose");
+        assert_eq!(controller.get_viewport_manager().hidden_rows(), [6, 7]);
+
+        controller.insert_rows(2, 1).unwrap();
+        let fold = &controller.folds()[0];
+        assert_eq!((fold.start_row, fold.end_row), (6, 8));
+        assert_eq!(controller.get_viewport_manager().hidden_rows(), [7, 8]);
+
+        // Rows inserted inside a closed fold are hidden with it
+        controller.insert_rows(8, 2).unwrap();
+        let fold = &controller.folds()[0];
+        assert_eq!((fold.start_row, fold.end_row), (6, 10));
+        assert_eq!(
+            controller.get_viewport_manager().hidden_rows(),
+            [7, 8, 9, 10]
+        );
+    }
+
+    #[test]
+    fn test_folds_survive_a_csv_round_trip() {
+        let mut controller = create_controller();
+        controller
+            .write_cell(&CellAddress::new(0, 0), "Header")
+            .unwrap();
+        run_ex(&mut controller, "2,4fold");
+        run_ex(&mut controller, "6,9fold");
+        run_ex(&mut controller, "6foldclose");
+
+        let export = controller.export_csv(true);
+        let sidecar = export.sidecar.unwrap();
+        assert_eq!(sidecar.row_folds.len(), 2);
+
+        let mut reopened = create_controller();
+        reopened.import_csv(&export.csv, Some(&sidecar)).unwrap();
+        let folds: Vec<_> = reopened
+            .folds()
+            .iter()
+            .map(|fold| (fold.start_row, fold.end_row, fold.collapsed))
+            .collect();
+        assert_eq!(folds, [(1, 3, false), (5, 8, true)]);
+        assert_eq!(reopened.get_viewport_manager().hidden_rows(), [6, 7, 8]);
+    }
+    /// Every action that changes the workbook, with arguments that would
+    /// change this test's sheet
+    fn workbook_changes() -> Vec<crate::state::Action> {
+        use crate::behaviors::case_change::CaseChange;
+        use crate::behaviors::cell_transform::CellTransform;
+        use crate::state::{Action, DeleteType, InsertPosition, InsertType, ParsedBulkCommand};
+        use gridcore_core::domain::{CellFormat, StyleRemoval};
+        use gridcore_core::pivot::{PivotAggregation, PivotConfig};
+        use gridcore_core::workbook::NameScope;
+
+        let a1_a2 = CellRange::from_string("A1:A2").unwrap();
+        vec![
+            Action::StartEditing {
+                edit_mode: None,
+                initial_value: Some("x".to_string()),
+                cursor_position: None,
+            },
+            Action::SubmitCellEdit {
+                value: "x".to_string(),
+            },
+            Action::UpdateEditingValue {
+                value: "x".to_string(),
+                cursor_position: 1,
+            },
+            Action::InsertCharacterAtCursor { character: 'x' },
+            Action::DeleteCharacterAtCursor { forward: true },
+            Action::HandleEditingKey {
+                key: "x".to_string(),
+                shift: false,
+                ctrl: false,
+                alt: false,
+                selection_start: None,
+                selection_end: None,
+            },
+            Action::SubmitFormulaBar,
+            Action::AddSheet {
+                name: "Extra".to_string(),
+            },
+            Action::RemoveSheet {
+                name: "Sheet2".to_string(),
+            },
+            Action::RenameSheet {
+                old_name: "Sheet2".to_string(),
+                new_name: "Renamed".to_string(),
+            },
+            Action::StartInsert {
+                insert_type: InsertType::Row,
+                position: InsertPosition::Before,
+                reference: 0,
+            },
+            Action::ConfirmInsert,
+            Action::StartDelete {
+                targets: vec![0],
+                delete_type: DeleteType::Row,
+            },
+            Action::ConfirmDelete,
+            Action::StartBulkOperation {
+                parsed_command: ParsedBulkCommand::SetValue {
+                    value: "x".to_string(),
+                },
+                affected_cells: None,
+            },
+            Action::ExecuteBulkOperation,
+            Action::BulkCommand {
+                command: ParsedBulkCommand::SetValue {
+                    value: "x".to_string(),
+                },
+            },
+            Action::SetColumnFormat {
+                column: 0,
+                format: Some(CellFormat::default()),
+            },
+            Action::DefineStyle {
+                name: "Shout".to_string(),
+                format: CellFormat::default(),
+            },
+            Action::ApplyStyle {
+                name: "Shout".to_string(),
+                ranges: None,
+            },
+            Action::RemoveStyle {
+                name: "Shout".to_string(),
+                removal: StyleRemoval::ConvertToFormats,
+            },
+            Action::CreatePivot {
+                source: a1_a2,
+                anchor: CellAddress::new(3, 0),
+                config: PivotConfig::new(vec![0], 0, PivotAggregation::Count),
+            },
+            Action::RefreshPivot {
+                anchor: CellAddress::new(3, 0),
+            },
+            Action::DefineConstant {
+                name: "Rate".to_string(),
+                definition: "0.5".to_string(),
+                scope: NameScope::Workbook,
+            },
+            Action::RemoveConstant {
+                name: "Rate".to_string(),
+                scope: NameScope::Workbook,
+            },
+            Action::ChangeCase {
+                ranges: vec![a1_a2],
+                change: CaseChange::Upper,
+            },
+            Action::TransformSelection {
+                transform: CellTransform::Trim,
+            },
+            Action::RunningAggregate {
+                aggregate: gridcore_core::fill::running::RunningAggregate::Sum,
+                target_col: None,
+                values_only: false,
+            },
+            Action::Paste {
+                text: "x".to_string(),
+            },
+            Action::PasteSpecial {
+                text: "x".to_string(),
+                options: Default::default(),
+            },
+            Action::PasteClipboard {
+                text: "x".to_string(),
+                rich: None,
+            },
+            Action::ConfirmPaste,
+            Action::StartRangeDrag {
+                grab: CellAddress::new(0, 0),
+            },
+            Action::DropRange { copy: false },
+            Action::ConfirmRangeDrop,
+            Action::QuickSum,
+            Action::QuickAverage,
+            Action::QuickCount,
+            Action::ClearSelectedCells,
+            Action::FillDown,
+            Action::Undo,
+            Action::UndoLine,
+            Action::Redo,
+            Action::EnterInsertMode { mode: None },
+        ]
+    }
+
+    /// What read-only mode must leave alone: the cells, the sheets and the
+    /// mode
+    fn workbook_snapshot(controller: &SpreadsheetController) -> (String, Vec<String>, EditorMode) {
+        (
+            controller.export_csv(false).csv,
+            controller
+                .get_sheets()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect(),
+            controller.get_mode().clone(),
+        )
+    }
+
+    #[test]
+    fn test_read_only_refuses_every_workbook_change() {
+        use crate::state::Action;
+
+        let viewer = SpreadsheetController::builder().read_only(true).build();
+        assert!(viewer.is_read_only());
+        assert!(!viewer.capabilities().edit);
+
+        let mut controller = create_controller();
+        controller
+            .write_cell(&CellAddress::new(0, 0), "apples")
+            .unwrap();
+        controller.write_cell(&CellAddress::new(0, 1), "2").unwrap();
+        controller
+            .dispatch_action(Action::AddSheet {
+                name: "Sheet2".to_string(),
+            })
+            .unwrap();
+        controller.set_read_only(true);
+        select_range(&mut controller, "A1:A2");
+        let before = workbook_snapshot(&controller);
+
+        let changes = workbook_changes();
+        for action in changes.clone() {
+            assert!(action.changes_workbook(), "{:?}", action);
+            controller.dispatch_action(action.clone()).unwrap();
+            assert_eq!(workbook_snapshot(&controller), before, "{:?}", action);
+        }
+        let refusals = controller
+            .get_errors()
+            .iter()
+            .filter(|error| error.message == READ_ONLY_MESSAGE)
+            .count();
+        assert_eq!(refusals, changes.len());
+
+        // Editing keys and vim operators are refused the same way
+        controller.clear_errors();
+        type_keys(&mut controller, &["Escape", "i", "Enter", "x", "Delete"]);
+        run_ex(&mut controller, "let Rate=2");
+        assert_eq!(workbook_snapshot(&controller), before);
+        assert!(controller
+            .get_errors()
+            .iter()
+            .all(|error| error.message == READ_ONLY_MESSAGE));
+        assert!(controller.paste_clipboard("x", None).unwrap().is_none());
+        assert!(controller.execute_script("set A1 9").is_err());
+        assert_eq!(workbook_snapshot(&controller), before);
+    }
+
+    #[test]
+    fn test_read_only_keeps_viewing() {
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        controller
+            .write_cell(&CellAddress::new(0, 0), "apples")
+            .unwrap();
+        controller
+            .dispatch_action(Action::AddSheet {
+                name: "Sheet2".to_string(),
+            })
+            .unwrap();
+        controller.set_read_only(true);
+
+        type_keys(&mut controller, &["j", "l"]);
+        assert_eq!(controller.get_cursor(), CellAddress::new(1, 1));
+        select_range(&mut controller, "A1:A1");
+        assert_eq!(controller.copy_selection().unwrap().text, "apples\n");
+        run_ex(&mut controller, "2,4fold");
+        run_ex(&mut controller, "2foldclose");
+        assert_eq!(controller.get_viewport_manager().hidden_rows(), [2, 3]);
+        controller
+            .dispatch_action(Action::SetActiveSheet {
+                name: "Sheet2".to_string(),
+            })
+            .unwrap();
+        assert_eq!(controller.get_active_sheet(), "Sheet2");
+        assert!(controller.get_errors().is_empty());
+    }
+
+    #[test]
+    fn test_leaving_read_only_restores_editing() {
+        let mut controller = create_controller();
+        type_keys(&mut controller, &["i", "4"]);
+        controller.set_read_only(true);
+        // The open edit is dropped, not committed
+        assert!(controller.get_mode().is_navigation());
+        assert_eq!(text_at(&controller, "A1"), CellValue::Empty);
+
+        controller.set_read_only(false);
+        assert!(controller.capabilities().edit);
+        type_keys(&mut controller, &["i", "4", "2", "Escape", "Escape"]);
+        assert_eq!(text_at(&controller, "A1"), CellValue::Number(42.0));
+    }
+
+    #[test]
+    fn test_typing_in_a_panel_leaves_the_grid_alone() {
+        let mut controller = create_controller();
+        type_keys(&mut controller, &["i", "4", "Escape", "Escape"]);
+        let cursor = controller.cursor();
+
+        // Keys typed into a find field must not reach the grid
+        controller.push_focus_scope("find", vec!["find-query".into(), "find-next".into()]);
+        assert!(!controller.has_grid_focus());
+        type_keys(&mut controller, &["j", "l", "x", "G", "d", "d", ":"]);
+        assert_eq!(controller.cursor(), cursor);
+        assert!(controller.get_mode().is_navigation());
+        assert_eq!(text_at(&controller, "A1"), CellValue::Number(4.0));
+
+        // Tab cycles through the panel, Escape hands keys back to the grid
+        controller.handle_keyboard_event(key_event("Tab")).unwrap();
+        assert_eq!(
+            controller.focus(),
+            FocusTarget::Panel {
+                scope: "find".into(),
+                item: Some("find-next".into()),
+            }
+        );
+        type_keys(&mut controller, &["Escape"]);
+        assert_eq!(controller.focus(), FocusTarget::Grid);
+        assert_eq!(controller.cursor(), cursor);
+        type_keys(&mut controller, &["j"]);
+        assert_eq!(controller.cursor(), CellAddress::new(0, 1));
+    }
+
+    #[test]
+    fn test_state_diffs_follow_keys_and_actions() {
+        use crate::controller::SpreadsheetEvent;
+        use crate::state::{Action, SpreadsheetMode, StateDiff, UIState};
+
+        let mut controller = create_controller();
+        let snapshot = controller.enable_state_diffs();
+        assert_eq!(snapshot.mode, SpreadsheetMode::Navigation);
+
+        let diffs = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = diffs.clone();
+        controller.subscribe_to_events(move |event| {
+            if let SpreadsheetEvent::UIStateChanged { diff } = event {
+                sink.lock().unwrap().push(diff.clone());
+            }
+        });
+
+        // A frontend replaying the diffs ends up with the controller's state
+        let mut mirror: UIState = snapshot.state;
+        type_keys(&mut controller, &["j", "l", ":"]);
+        controller
+            .dispatch_action(Action::UpdateCommandValue {
+                value: ":sort".to_string(),
+            })
+            .unwrap();
+        for diff in diffs.lock().unwrap().iter() {
+            mirror = diff.apply(&mirror);
+        }
+        assert_eq!(diffs.lock().unwrap().len(), 4);
+        assert_eq!(mirror, controller.get_ui_state());
+        assert_eq!(
+            controller.get_state_snapshot().mode,
+            SpreadsheetMode::Command
+        );
+        assert!(matches!(
+            diffs.lock().unwrap()[0],
+            StateDiff::Partial(ref changes) if changes.cursor_changed == Some(CellAddress::new(0, 1))
+        ));
+
+        // Nothing is published once diffs are off
+        controller.disable_state_diffs();
+        type_keys(&mut controller, &["Escape", "j"]);
+        assert_eq!(diffs.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_structural_edits_shift_cursor_selection_and_watches() {
+        use crate::controller::SpreadsheetEvent;
+        use crate::state::Action;
+
+        let mut controller = create_controller();
+        let a1 = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (address, label) in [("B2", "above"), ("B6", "within"), ("B9", "below")] {
+            controller
+                .dispatch_action(Action::AddWatch {
+                    address: a1(address),
+                    sheet: None,
+                    label: Some(label.to_string()),
+                })
+                .unwrap();
+        }
+        controller.set_cursor(a1("C5"));
+        controller.selection = Some(Selection {
+            selection_type: SelectionType::Range {
+                start: a1("C4"),
+                end: a1("D7"),
+            },
+            anchor: Some(a1("C4")),
+        });
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = events.clone();
+        controller.subscribe_to_events(move |event| {
+            if event.structural_operation().is_some() {
+                sink.lock().unwrap().push(event.clone());
+            }
+        });
+        let watched = |controller: &SpreadsheetController| -> Vec<String> {
+            let entries = controller.get_watch_list().entries();
+            entries.iter().map(|entry| entry.address.to_a1()).collect()
+        };
+
+        // Above the cursor and inside the selection
+        controller.insert_rows(4, 2).unwrap();
+        assert_eq!(controller.cursor(), a1("C7"));
+        assert_eq!(
+            controller.selection.as_ref().unwrap().selection_type,
+            SelectionType::Range {
+                start: a1("C4"),
+                end: a1("D9"),
+            }
+        );
+        assert_eq!(watched(&controller), ["B2", "B8", "B11"]);
+
+        // Below everything: nothing moves
+        controller.insert_rows(50, 1).unwrap();
+        assert_eq!(controller.cursor(), a1("C7"));
+        assert_eq!(watched(&controller), ["B2", "B8", "B11"]);
+
+        // Deleting the cursor's column leaves it on the next one, and the
+        // watches in that column go
+        controller.delete_columns(1, 2).unwrap();
+        assert_eq!(controller.cursor(), a1("B7"));
+        assert_eq!(
+            controller.selection.as_ref().unwrap().selection_type,
+            SelectionType::Range {
+                start: a1("B4"),
+                end: a1("B9"),
+            }
+        );
+        assert!(watched(&controller).is_empty());
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                SpreadsheetEvent::RowsInserted {
+                    before: 4,
+                    count: 2
+                },
+                SpreadsheetEvent::RowsInserted {
+                    before: 50,
+                    count: 1
+                },
+                SpreadsheetEvent::ColumnsDeleted { start: 1, count: 2 },
+            ]
+        );
+        let damage = controller
+            .structural_damage(&events.lock().unwrap()[0])
+            .unwrap();
+        assert_eq!(damage.start, a1("A5"));
+    }
+
+    #[test]
+    fn test_structural_edits_move_cell_content() {
+        let mut controller = create_controller();
+        let a1 = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        controller.write_cell(&a1("A3"), "4").unwrap();
+        controller.write_cell(&a1("B1"), "=A3+1").unwrap();
+        let value = |controller: &SpreadsheetController, a1: &str| {
+            controller
+                .facade()
+                .get_cell_value(&CellAddress::from_a1(a1).unwrap())
+        };
+        controller.insert_rows(0, 1).unwrap();
+        assert_eq!(value(&controller, "A4").as_deref(), Some("4"));
+        assert_eq!(value(&controller, "B2").as_deref(), Some("5"));
+
+        controller.read_only = true;
+        controller.delete_rows(0, 5).unwrap();
+        assert_eq!(value(&controller, "A4").as_deref(), Some("4"));
+    }
+
+    fn select_columns(controller: &mut SpreadsheetController, columns: &[u32]) {
+        controller.set_selection(Some(Selection {
+            selection_type: SelectionType::Column {
+                columns: columns.to_vec(),
+            },
+            anchor: None,
+        }));
+    }
+
+    fn column_widths(controller: &SpreadsheetController, columns: &[usize]) -> Vec<f64> {
+        let viewport = controller.get_viewport_manager();
+        columns
+            .iter()
+            .map(|&col| viewport.get_column_width(col))
+            .collect()
+    }
+
+    #[test]
+    fn test_multi_column_resize_commits_one_width() {
+        use crate::behaviors::resize::ResizeType;
+        use crate::state::{Action, ResizeTarget};
+
+        let mut controller = create_controller();
+        select_columns(&mut controller, &[1, 2, 3]);
+        controller
+            .dispatch_action(Action::StartResize {
+                target: ResizeTarget::Column { index: 2 },
+                initial_position: 250.0,
+            })
+            .unwrap();
+        assert_eq!(controller.get_mode(), &EditorMode::Resizing);
+        controller
+            .dispatch_action(Action::UpdateResize { delta: 40.0 })
+            .unwrap();
+
+        // A ghost leaves the widths alone until the resize is committed,
+        // while the guideline already counts the grown column before
+        assert_eq!(column_widths(&controller, &[1, 2, 3]), [100.0; 3]);
+        assert_eq!(
+            controller.resize_guideline(),
+            Some((ResizeType::Column, 380.0))
+        );
+
+        type_keys(&mut controller, &["2", "+", "Enter"]);
+        assert_eq!(controller.get_mode(), &EditorMode::Navigation);
+        assert_eq!(
+            column_widths(&controller, &[0, 1, 2, 3, 4]),
+            [100.0, 150.0, 150.0, 150.0, 100.0]
+        );
+        assert_eq!(controller.resize_guideline(), None);
+
+        // Outside the selection a column is resized by itself
+        controller
+            .dispatch_action(Action::StartResize {
+                target: ResizeTarget::Column { index: 5 },
+                initial_position: 0.0,
+            })
+            .unwrap();
+        controller
+            .dispatch_action(Action::UpdateResize { delta: -1000.0 })
+            .unwrap();
+        controller.dispatch_action(Action::ConfirmResize).unwrap();
+        assert_eq!(
+            column_widths(&controller, &[3, 4, 5]),
+            [150.0, 100.0, controller.get_config().min_cell_width]
+        );
+    }
+
+    #[test]
+    fn test_cancelled_live_resize_restores_sizes() {
+        use crate::behaviors::resize::ResizePreview;
+        use crate::state::{Action, ResizeTarget};
+
+        let mut controller = create_controller();
+        run_ex(&mut controller, "set resizepreview=live");
+        assert_eq!(controller.resize_preview(), ResizePreview::LiveReflow);
+        controller
+            .dispatch_action(Action::SetSizes {
+                columns: true,
+                indices: vec![1],
+                size: 150.0,
+            })
+            .unwrap();
+
+        select_columns(&mut controller, &[1, 2]);
+        controller
+            .dispatch_action(Action::StartResize {
+                target: ResizeTarget::Column { index: 1 },
+                initial_position: 0.0,
+            })
+            .unwrap();
+        controller
+            .dispatch_action(Action::UpdateResize { delta: -30.0 })
+            .unwrap();
+
+        // Reflowing lays the grid out with the provisional width
+        assert_eq!(column_widths(&controller, &[1, 2]), [120.0, 120.0]);
+        assert_eq!(controller.resize_guideline(), None);
+
+        type_keys(&mut controller, &["Escape"]);
+        assert_eq!(controller.get_mode(), &EditorMode::Navigation);
+        assert_eq!(column_widths(&controller, &[1, 2]), [150.0, 100.0]);
+        assert_eq!(
+            controller.get_viewport_manager().custom_column_widths(),
+            [(1, 150.0)]
+        );
+    }
 }
@@ -0,0 +1,85 @@
+use rustc_hash::FxHashMap;
+use std::cell::RefCell;
+
+/// Measures the width of a text in pixels, as drawn in grid cells
+pub type TextMeasurer = Box<dyn Fn(&str) -> f64>;
+
+/// Pixel widths of cell texts, measured by the host and cached by text.
+///
+/// Texts are measured on first use, or ahead of it from idle time: texts
+/// queued with [`TextWidths::plan`] are measured a few at a time by
+/// [`TextWidths::measure_pending`].
+#[derive(Default)]
+pub struct TextWidths {
+    measurer: Option<TextMeasurer>,
+    widths: RefCell<FxHashMap<String, f64>>,
+    /// Texts queued for measuring
+    pending: Vec<String>,
+}
+
+impl TextWidths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `measurer` for texts from now on, e.g. after the cell font
+    /// changed. Earlier widths are dropped.
+    pub fn set_measurer(&mut self, measurer: Option<TextMeasurer>) {
+        self.measurer = measurer;
+        self.widths.borrow_mut().clear();
+        self.pending.clear();
+    }
+
+    pub fn has_measurer(&self) -> bool {
+        self.measurer.is_some()
+    }
+
+    /// Width of `text`, measured now if it is not cached. `None` when no
+    /// measurer is set.
+    pub fn width(&self, text: &str) -> Option<f64> {
+        if let Some(width) = self.widths.borrow().get(text) {
+            return Some(*width);
+        }
+        let width = (self.measurer.as_ref()?)(text);
+        self.widths.borrow_mut().insert(text.to_string(), width);
+        Some(width)
+    }
+
+    pub fn is_measured(&self, text: &str) -> bool {
+        self.widths.borrow().contains_key(text)
+    }
+
+    /// Queue the texts not measured yet for [`TextWidths::measure_pending`]
+    pub fn plan(&mut self, texts: impl IntoIterator<Item = String>) {
+        if self.measurer.is_none() {
+            return;
+        }
+        let widths = self.widths.borrow();
+        self.pending
+            .extend(texts.into_iter().filter(|text| !widths.contains_key(text)));
+        self.pending.sort_unstable();
+        self.pending.dedup();
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Measure queued texts, asking `should_yield` before each one. Returns
+    /// whether the queue was emptied.
+    pub fn measure_pending(&mut self, should_yield: &dyn Fn() -> bool) -> bool {
+        while !self.pending.is_empty() {
+            if should_yield() {
+                return false;
+            }
+            if let Some(text) = self.pending.pop() {
+                self.width(&text);
+            }
+        }
+        true
+    }
+}
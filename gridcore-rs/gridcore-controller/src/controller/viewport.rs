@@ -1,10 +1,14 @@
+use super::axis_sizes::AxisSizes;
+use super::grid_extent::{GridExtent, ScrollbarMetrics};
+use super::scroll_accumulator::{ScrollAccumulator, ScrollDelta};
 use crate::state::ViewportInfo;
+use gridcore_core::formula::CellRange;
+use gridcore_core::references::StructuralOperation;
 use gridcore_core::types::CellAddress;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 /// Represents the visible bounds of the viewport
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ViewportBounds {
     pub start_row: usize,
     pub end_row: usize,
@@ -20,7 +24,7 @@ pub struct ScrollPosition {
 }
 
 /// Represents the position and dimensions of a cell in the viewport
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CellPosition {
     pub x: f64,
     pub y: f64,
@@ -56,6 +60,9 @@ impl Default for GridConfiguration {
     }
 }
 
+/// Height rows are kept at least
+pub const MIN_ROW_HEIGHT: f64 = 16.0;
+
 /// Manages the visible viewport of the spreadsheet
 pub struct ViewportManager {
     viewport: ViewportInfo,
@@ -63,8 +70,10 @@ pub struct ViewportManager {
     scroll_position: ScrollPosition,
     viewport_width: f64,
     viewport_height: f64,
-    column_widths: HashMap<usize, f64>,
-    row_heights: HashMap<usize, f64>,
+    column_widths: AxisSizes,
+    row_heights: AxisSizes,
+    extent: GridExtent,
+    pending_scroll: ScrollAccumulator,
 }
 
 impl ViewportManager {
@@ -75,6 +84,8 @@ impl ViewportManager {
             ..Default::default()
         };
 
+        let column_widths = AxisSizes::new(config.default_cell_width);
+        let row_heights = AxisSizes::new(config.default_cell_height);
         Self {
             viewport: ViewportInfo {
                 start_row: 0,
@@ -86,19 +97,34 @@ impl ViewportManager {
             scroll_position: ScrollPosition::default(),
             viewport_width: 800.0,
             viewport_height: 600.0,
-            column_widths: HashMap::new(),
-            row_heights: HashMap::new(),
+            column_widths,
+            row_heights,
+            extent: GridExtent::new(rows, cols),
+            pending_scroll: ScrollAccumulator::new(),
         }
     }
 
     pub fn with_config(mut self, config: GridConfiguration) -> Self {
+        self.extent = GridExtent::new(config.total_rows as u32, config.total_cols as u32);
+        self.column_widths
+            .set_default_size(config.default_cell_width);
+        self.row_heights
+            .set_default_size(config.default_cell_height);
         self.config = config;
         self
     }
 
+    /// Keep `rows` and `cols` of scrollable space beyond the used range
+    pub fn with_extent_margin(mut self, rows: u32, cols: u32) -> Self {
+        self.extent = self.extent.with_margin(rows, cols);
+        self
+    }
+
     pub fn with_cell_dimensions(mut self, row_height: f64, col_width: f64) -> Self {
         self.config.default_cell_height = row_height;
         self.config.default_cell_width = col_width;
+        self.row_heights.set_default_size(row_height);
+        self.column_widths.set_default_size(col_width);
         self
     }
 
@@ -150,6 +176,10 @@ impl ViewportManager {
 
         self.viewport.start_row = new_start_row;
         self.viewport.start_col = new_start_col;
+        self.extent.include(&CellAddress::new(
+            (new_start_col + self.viewport.cols).saturating_sub(1),
+            (new_start_row + self.viewport.rows).saturating_sub(1),
+        ));
     }
 
     pub fn scroll_by(&mut self, delta_x: f64, delta_y: f64) {
@@ -165,7 +195,46 @@ impl ViewportManager {
         };
     }
 
+    /// Add a wheel delta to the scroll applied by the next
+    /// [`Self::apply_pending_scroll`]
+    pub fn queue_scroll(&mut self, delta_x: f64, delta_y: f64) {
+        self.pending_scroll.add(ScrollDelta::new(delta_x, delta_y));
+    }
+
+    pub fn pending_scroll(&self) -> &ScrollAccumulator {
+        &self.pending_scroll
+    }
+
+    /// Scroll by the whole pixels queued since the last call, clamped to
+    /// the grid. Returns how far the view moved, or `None` if it did not.
+    pub fn apply_pending_scroll(&mut self) -> Option<ScrollDelta> {
+        let requested = self.pending_scroll.take();
+        if requested.is_zero() {
+            return None;
+        }
+        let before = self.scroll_position.clone();
+        self.scroll_by(requested.x, requested.y);
+        let applied = ScrollDelta::new(
+            self.scroll_position.x - before.x,
+            self.scroll_position.y - before.y,
+        );
+        self.pending_scroll.settle(requested, applied);
+        (!applied.is_zero()).then_some(applied)
+    }
+
+    /// Scroll to an absolute position, clamped to the grid
+    pub fn scroll_to(&mut self, x: f64, y: f64) {
+        let max_x = (self.get_total_grid_width() - self.viewport_width).max(0.0);
+        let max_y = (self.get_total_grid_height() - self.viewport_height).max(0.0);
+
+        self.scroll_position = ScrollPosition {
+            x: x.clamp(0.0, max_x),
+            y: y.clamp(0.0, max_y),
+        };
+    }
+
     pub fn scroll_to_cell(&mut self, cell: &CellAddress, position: &str) {
+        self.extent.include(cell);
         let cell_pos = self.get_cell_position(cell);
         let absolute_x = cell_pos.x + self.scroll_position.x;
         let absolute_y = cell_pos.y + self.scroll_position.y;
@@ -196,6 +265,7 @@ impl ViewportManager {
     }
 
     pub fn ensure_visible(&mut self, address: &CellAddress) {
+        self.extent.include(address);
         let row = address.row;
         let col = address.col;
 
@@ -213,20 +283,63 @@ impl ViewportManager {
         }
     }
 
+    pub fn get_config(&self) -> &GridConfiguration {
+        &self.config
+    }
+
     pub fn get_dimensions(&self) -> (u32, u32) {
         (self.config.total_rows as u32, self.config.total_cols as u32)
     }
 
+    /// The scrollable part of the grid
+    pub fn get_extent(&self) -> &GridExtent {
+        &self.extent
+    }
+
+    /// Grow the scrollable area to reach `address`, e.g. when the cursor
+    /// moves past its edge. Returns whether it changed.
+    pub fn extend_to(&mut self, address: &CellAddress) -> bool {
+        self.extent.include(address)
+    }
+
+    /// Grow the scrollable area for a cell that now holds data
+    pub fn note_populated(&mut self, address: &CellAddress) -> bool {
+        self.extent.note_populated(address)
+    }
+
+    /// Last used cell the scrollable area was fitted to
+    pub fn used_range(&self) -> Option<CellAddress> {
+        self.extent.used()
+    }
+
+    /// Whether clearing `address` may let the scrollable area shrink
+    pub fn is_on_used_edge(&self, address: &CellAddress) -> bool {
+        self.extent.is_on_used_edge(address)
+    }
+
+    /// Fit the scrollable area to a new used range without shrinking it
+    /// past what is on screen or `keep`
+    pub fn set_used_range(&mut self, used: Option<CellAddress>, keep: &CellAddress) -> bool {
+        let bounds = self.get_visible_bounds();
+        let keep = CellAddress::new(
+            keep.col.max(bounds.end_col as u32),
+            keep.row.max(bounds.end_row as u32),
+        );
+        self.extent.set_used(used, &keep)
+    }
+
+    /// Cell under a point of the viewport, measured from its top-left
+    /// corner including the headers
     pub fn viewport_to_cell(&self, x: f64, y: f64) -> Option<CellAddress> {
         // Account for headers
         if x < self.config.row_header_width || y < self.config.column_header_height {
             return None;
         }
 
-        let absolute_x = x - self.config.row_header_width + self.scroll_position.x;
-        let absolute_y = y - self.config.column_header_height + self.scroll_position.y;
-
-        self.get_cell_at_position(absolute_x, absolute_y)
+        self.cell_at_point(
+            x - self.config.row_header_width,
+            y - self.config.column_header_height,
+        )
     }
 
     pub fn cell_to_viewport(&self, address: &CellAddress) -> Option<(f64, f64)> {
@@ -234,13 +347,29 @@ impl ViewportManager {
             return None;
         }
 
-        let pos = self.get_cell_position(address);
+        let (x, y) = self.point_of_cell(address);
         Some((
-            pos.x + self.config.row_header_width,
-            pos.y + self.config.column_header_height,
+            x + self.config.row_header_width,
+            y + self.config.column_header_height,
         ))
     }
 
+    /// Cell under a point of the cell area, measured from the corner below
+    /// and right of the headers, with scrolling applied. This and
+    /// [`Self::point_of_cell`] are the one mapping between points and cells
+    /// that event handling, rendering and selection drawing share.
+    pub fn cell_at_point(&self, x: f64, y: f64) -> Option<CellAddress> {
+        self.get_cell_at_position(x + self.scroll_position.x, y + self.scroll_position.y)
+    }
+
+    /// Top-left corner of a cell in the coordinates of [`Self::cell_at_point`]
+    pub fn point_of_cell(&self, address: &CellAddress) -> (f64, f64) {
+        (
+            self.get_column_x(address.col as usize) - self.scroll_position.x,
+            self.get_row_y(address.row as usize) - self.scroll_position.y,
+        )
+    }
+
     pub fn is_visible(&self, address: &CellAddress) -> bool {
         let bounds = self.get_visible_bounds();
         address.row as usize >= bounds.start_row
@@ -250,178 +379,193 @@ impl ViewportManager {
     }
 
     pub fn get_visible_bounds(&self) -> ViewportBounds {
-        let mut start_row = None;
-        let mut end_row = self.config.total_rows;
-        let mut start_col = None;
-        let mut end_col = self.config.total_cols;
-
-        // Calculate visible rows
-        let mut y = 0.0;
-        let scroll_y = self.scroll_position.y;
-        for row in 0..self.config.total_rows {
-            let height = self.get_row_height(row);
-            if y + height > scroll_y && start_row.is_none() {
-                start_row = Some(row);
-            }
-            if y >= scroll_y + self.viewport_height {
-                end_row = row;
-                break;
-            }
-            y += height;
-        }
-
-        // Calculate visible columns
-        let mut x = 0.0;
-        let scroll_x = self.scroll_position.x;
-        for col in 0..self.config.total_cols {
-            let width = self.get_column_width(col);
-            if x + width > scroll_x && start_col.is_none() {
-                start_col = Some(col);
-            }
-            if x >= scroll_x + self.viewport_width {
-                end_col = col;
-                break;
+        let last_row = self.config.total_rows.saturating_sub(1);
+        let last_col = self.config.total_cols.saturating_sub(1);
+        let scroll = &self.scroll_position;
+
+        // Bounds run from the row or column under the near edge to the first
+        // one starting at or past the far edge
+        let first = |axis: &AxisSizes, edge: f64| axis.index_at(edge.max(0.0)).unwrap_or(0);
+        let past = |axis: &AxisSizes, edge: f64| {
+            let index = first(axis, edge);
+            if axis.offset(index) < edge {
+                index + 1
+            } else {
+                index
             }
-            x += width;
-        }
+        };
 
         ViewportBounds {
-            start_row: start_row.unwrap_or(0),
-            end_row: end_row.min(self.config.total_rows - 1),
-            start_col: start_col.unwrap_or(0),
-            end_col: end_col.min(self.config.total_cols - 1),
+            start_row: first(&self.row_heights, scroll.y).min(last_row),
+            end_row: past(&self.row_heights, scroll.y + self.viewport_height).min(last_row),
+            start_col: first(&self.column_widths, scroll.x).min(last_col),
+            end_col: past(&self.column_widths, scroll.x + self.viewport_width).min(last_col),
         }
     }
 
     pub fn get_cell_position(&self, address: &CellAddress) -> CellPosition {
-        let mut x = 0.0;
-        let mut y = 0.0;
-
-        for col in 0..address.col as usize {
-            x += self.get_column_width(col);
-        }
-
-        for row in 0..address.row as usize {
-            y += self.get_row_height(row);
-        }
-
+        let (x, y) = self.point_of_cell(address);
         CellPosition {
-            x: x - self.scroll_position.x,
-            y: y - self.scroll_position.y,
+            x,
+            y,
             width: self.get_column_width(address.col as usize),
             height: self.get_row_height(address.row as usize),
         }
     }
 
+    /// Cell at a point of the whole grid, ignoring scrolling
     pub fn get_cell_at_position(&self, x: f64, y: f64) -> Option<CellAddress> {
-        // Early return if coordinates are negative
-        if x < 0.0 || y < 0.0 {
-            return None;
-        }
-
-        // For columns with uniform width, use direct calculation
-        let col = if self.column_widths.is_empty() {
-            // All columns have default width - direct calculation
-            let col_index = (x / self.config.default_cell_width) as usize;
-            if col_index < self.config.total_cols {
-                Some(col_index)
-            } else {
-                None
-            }
-        } else {
-            // Mixed widths - need to iterate but with early termination
-            let mut current_x = 0.0;
-            let mut col = None;
-            
-            for c in 0..self.config.total_cols {
-                let width = self.get_column_width(c);
-                if x >= current_x && x < current_x + width {
-                    col = Some(c);
-                    break;
-                }
-                current_x += width;
-                // Early termination if we've passed the target x
-                if current_x > x + width {
-                    break;
-                }
-            }
-            col
-        };
-
-        // For rows with uniform height, use direct calculation  
-        let row = if self.row_heights.is_empty() {
-            // All rows have default height - direct calculation
-            let row_index = (y / self.config.default_cell_height) as usize;
-            if row_index < self.config.total_rows {
-                Some(row_index)
-            } else {
-                None
-            }
-        } else {
-            // Mixed heights - need to iterate but with early termination
-            let mut current_y = 0.0;
-            let mut row = None;
-            
-            for r in 0..self.config.total_rows {
-                let height = self.get_row_height(r);
-                if y >= current_y && y < current_y + height {
-                    row = Some(r);
-                    break;
-                }
-                current_y += height;
-                // Early termination if we've passed the target y
-                if current_y > y + height {
-                    break;
-                }
-            }
-            row
-        };
-
-        match (row, col) {
-            (Some(r), Some(c)) => Some(CellAddress::new(c as u32, r as u32)),
-            _ => None,
-        }
+        let col = self
+            .column_widths
+            .index_at(x)
+            .filter(|&col| col < self.config.total_cols)?;
+        let row = self
+            .row_heights
+            .index_at(y)
+            .filter(|&row| row < self.config.total_rows)?;
+        Some(CellAddress::new(col as u32, row as u32))
     }
 
     pub fn get_column_width(&self, col: usize) -> f64 {
-        *self
-            .column_widths
-            .get(&col)
-            .unwrap_or(&self.config.default_cell_width)
+        self.column_widths.size(col)
+    }
+
+    /// Columns whose width was changed from the default, in column order
+    pub fn custom_column_widths(&self) -> Vec<(usize, f64)> {
+        let mut widths: Vec<(usize, f64)> = self.column_widths.custom_sizes().collect();
+        widths.sort_unstable_by_key(|&(col, _)| col);
+        widths
     }
 
     pub fn set_column_width(&mut self, col: usize, width: f64) {
         let clamped_width = width
             .max(self.config.min_cell_width)
             .min(self.config.max_cell_width);
-        self.column_widths.insert(col, clamped_width);
+        self.column_widths.set_size(col, clamped_width);
     }
 
     pub fn get_row_height(&self, row: usize) -> f64 {
-        *self
-            .row_heights
-            .get(&row)
-            .unwrap_or(&self.config.default_cell_height)
+        self.row_heights.size(row)
     }
 
     pub fn set_row_height(&mut self, row: usize, height: f64) {
-        self.row_heights.insert(row, height.max(16.0));
+        self.row_heights.set_size(row, height.max(MIN_ROW_HEIGHT));
     }
 
-    pub fn get_column_x(&self, col: usize) -> f64 {
-        let mut x = 0.0;
-        for c in 0..col {
-            x += self.get_column_width(c);
+    /// Where the right edge of `col` would be, from the start of the grid,
+    /// if every column in `resized` were `width` wide. Nothing is resized.
+    pub fn provisional_column_edge(&self, col: usize, resized: &[usize], width: f64) -> f64 {
+        self.column_widths.provisional_end(col, resized, width)
+    }
+
+    /// Where the bottom edge of `row` would be, from the start of the grid,
+    /// if every row in `resized` were `height` high. Nothing is resized.
+    pub fn provisional_row_edge(&self, row: usize, resized: &[usize], height: f64) -> f64 {
+        self.row_heights.provisional_end(row, resized, height)
+    }
+
+    pub fn is_row_hidden(&self, row: usize) -> bool {
+        self.row_heights.is_hidden(row)
+    }
+
+    pub fn is_column_hidden(&self, col: usize) -> bool {
+        self.column_widths.is_hidden(col)
+    }
+
+    /// Hide or show rows `start..=end`. Returns how many changed.
+    pub fn set_rows_hidden(&mut self, start: usize, end: usize, hidden: bool) -> usize {
+        (start..=end)
+            .filter(|&row| self.row_heights.set_hidden(row, hidden))
+            .count()
+    }
+
+    /// Hide or show columns `start..=end`. Returns how many changed.
+    pub fn set_columns_hidden(&mut self, start: usize, end: usize, hidden: bool) -> usize {
+        (start..=end)
+            .filter(|&col| self.column_widths.set_hidden(col, hidden))
+            .count()
+    }
+
+    /// Hidden rows, in row order
+    pub fn hidden_rows(&self) -> Vec<usize> {
+        let mut rows: Vec<usize> = self.row_heights.hidden().collect();
+        rows.sort_unstable();
+        rows
+    }
+
+    /// Hidden columns, in column order
+    pub fn hidden_columns(&self) -> Vec<usize> {
+        let mut cols: Vec<usize> = self.column_widths.hidden().collect();
+        cols.sort_unstable();
+        cols
+    }
+
+    /// The parts of `range` on visible rows and columns, as the largest
+    /// blocks between hidden lines, in row-major order. Empty when every
+    /// row or every column of the range is hidden.
+    pub fn visible_subranges(&self, range: &CellRange) -> Vec<CellRange> {
+        let rows = visible_runs(range.start.row, range.end.row, |row| {
+            self.row_heights.is_hidden(row)
+        });
+        let cols = visible_runs(range.start.col, range.end.col, |col| {
+            self.column_widths.is_hidden(col)
+        });
+        rows.iter()
+            .flat_map(|&(top, bottom)| {
+                cols.iter().map(move |&(left, right)| {
+                    CellRange::new(CellAddress::new(left, top), CellAddress::new(right, bottom))
+                })
+            })
+            .collect()
+    }
+
+    /// Follow rows or columns inserted or deleted by `operation`: custom
+    /// sizes and hidden lines move with their rows and columns, and the
+    /// scroll position moves by what changed ahead of the view so the same
+    /// content stays on screen. Moves leave the view alone.
+    pub fn apply_structural_operation(&mut self, operation: &StructuralOperation) {
+        if matches!(operation, StructuralOperation::MoveRange { .. }) {
+            return;
         }
-        x
+        let bounds = self.get_visible_bounds();
+        let top_left = CellAddress::new(bounds.start_col as u32, bounds.start_row as u32);
+        let into = (
+            self.scroll_position.x - self.get_column_x(bounds.start_col),
+            self.scroll_position.y - self.get_row_y(bounds.start_row),
+        );
+
+        let shift = |address: CellAddress| operation.shift_address(&address);
+        self.column_widths
+            .remap(|col| shift(CellAddress::new(col as u32, 0)).map(|a| a.col as usize));
+        self.row_heights
+            .remap(|row| shift(CellAddress::new(0, row as u32)).map(|a| a.row as usize));
+
+        // A deleted first line leaves the one after it at the top
+        let (top_left, into) = match shift(top_left) {
+            Some(moved) => (moved, into),
+            None => (operation.shift_address_or_next(&top_left), (0.0, 0.0)),
+        };
+        self.scroll_to(
+            self.get_column_x(top_left.col as usize) + into.0,
+            self.get_row_y(top_left.row as usize) + into.1,
+        );
+        let start = operation.shift_address_or_next(&CellAddress::new(
+            self.viewport.start_col,
+            self.viewport.start_row,
+        ));
+        self.set_viewport(ViewportInfo {
+            start_row: start.row,
+            start_col: start.col,
+            ..self.viewport
+        });
+    }
+
+    pub fn get_column_x(&self, col: usize) -> f64 {
+        self.column_widths.offset(col)
     }
 
     pub fn get_row_y(&self, row: usize) -> f64 {
-        let mut y = 0.0;
-        for r in 0..row {
-            y += self.get_row_height(r);
-        }
-        y
+        self.row_heights.offset(row)
     }
 
     pub fn get_scroll_position(&self) -> ScrollPosition {
@@ -446,20 +590,49 @@ impl ViewportManager {
     }
 
     pub fn get_total_grid_width(&self) -> f64 {
-        let mut width = 0.0;
-        for col in 0..self.config.total_cols {
-            width += self.get_column_width(col);
-        }
-        width
+        self.column_widths.offset(self.extent.cols() as usize)
     }
 
     pub fn get_total_grid_height(&self) -> f64 {
-        let mut height = 0.0;
-        for row in 0..self.config.total_rows {
-            height += self.get_row_height(row);
+        self.row_heights.offset(self.extent.rows() as usize)
+    }
+
+    /// Thumb of the vertical scrollbar over the scrollable extent
+    pub fn vertical_scrollbar(&self) -> ScrollbarMetrics {
+        ScrollbarMetrics::compute(
+            self.get_total_grid_height(),
+            self.viewport_height,
+            self.scroll_position.y,
+            0.0,
+            1.0,
+        )
+    }
+
+    /// Thumb of the horizontal scrollbar over the scrollable extent
+    pub fn horizontal_scrollbar(&self) -> ScrollbarMetrics {
+        ScrollbarMetrics::compute(
+            self.get_total_grid_width(),
+            self.viewport_width,
+            self.scroll_position.x,
+            0.0,
+            1.0,
+        )
+    }
+}
+
+/// Runs of consecutive lines in `start..=end` that are not hidden
+fn visible_runs(start: u32, end: u32, hidden: impl Fn(usize) -> bool) -> Vec<(u32, u32)> {
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for line in start..=end {
+        if hidden(line as usize) {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, last)) if *last + 1 == line => *last = line,
+            _ => runs.push((line, line)),
         }
-        height
     }
+    runs
 }
 
 #[cfg(test)]
@@ -514,6 +687,49 @@ mod tests {
         assert_eq!(y, 80.0); // 30 + 2 * 25
     }
 
+    #[test]
+    fn test_point_and_cell_round_trip_over_random_sizes() {
+        let mut seed: u64 = 0x9e37;
+        let mut next = |bound: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % bound
+        };
+
+        for _ in 0..20 {
+            let mut manager = ViewportManager::new(2000, 500)
+                .with_cell_dimensions(10.0 + next(30) as f64, 40.0 + next(100) as f64);
+            for _ in 0..next(300) {
+                manager.set_column_width(next(500), 40.0 + next(400) as f64);
+                manager.set_row_height(next(2000), 16.0 + next(60) as f64);
+            }
+            manager.extend_to(&CellAddress::new(499, 1999));
+            manager.scroll_to(next(20_000) as f64, next(40_000) as f64);
+            let scroll = manager.get_scroll_position();
+
+            for _ in 0..200 {
+                let cell = CellAddress::new(next(500) as u32, next(2000) as u32);
+                let (x, y) = manager.point_of_cell(&cell);
+                let position = manager.get_cell_position(&cell);
+                assert_eq!((position.x, position.y), (x, y));
+
+                // Every point inside the cell maps back to it
+                let (dx, dy) = (next(100) as f64 / 100.0, next(100) as f64 / 100.0);
+                let inside = (x + dx * position.width, y + dy * position.height);
+                if inside.0 + scroll.x >= 0.0 && inside.1 + scroll.y >= 0.0 {
+                    assert_eq!(manager.cell_at_point(inside.0, inside.1), Some(cell));
+                }
+
+                // And the scan the prefix sums replaced agrees on the offset
+                let scanned: f64 = (0..cell.col as usize)
+                    .map(|col| manager.get_column_width(col))
+                    .sum();
+                assert!((manager.get_column_x(cell.col as usize) - scanned).abs() < 1e-6);
+            }
+        }
+    }
+
     #[test]
     fn test_visibility() {
         let mut manager = ViewportManager::new(100, 50);
@@ -547,4 +763,94 @@ mod tests {
             )));
         }
     }
+
+    #[test]
+    fn test_pending_scroll_clamps_at_the_edges() {
+        let mut manager = ViewportManager::new(100, 50);
+        manager.queue_scroll(10.5, 4.0);
+        manager.queue_scroll(0.0, -30.75);
+
+        // Nothing above the first row: only the horizontal scroll applies
+        assert_eq!(
+            manager.apply_pending_scroll(),
+            Some(ScrollDelta::new(10.0, 0.0))
+        );
+        assert_eq!(
+            manager.pending_scroll().pending(),
+            ScrollDelta::new(0.5, 0.0)
+        );
+
+        // Scrolling back down moves at once, without paying off the overshoot
+        manager.queue_scroll(0.5, 2.0);
+        assert_eq!(
+            manager.apply_pending_scroll(),
+            Some(ScrollDelta::new(1.0, 2.0))
+        );
+        assert_eq!(manager.get_scroll_position().x, 11.0);
+        assert_eq!(manager.apply_pending_scroll(), None);
+    }
+
+    #[test]
+    fn test_visible_subranges_skip_hidden_lines() {
+        let mut manager = ViewportManager::new(100, 50);
+        assert_eq!(manager.set_rows_hidden(2, 3, true), 2);
+        assert_eq!(manager.set_columns_hidden(1, 1, true), 1);
+        assert!(manager.is_row_hidden(3));
+        assert_eq!(manager.get_row_height(2), 0.0);
+
+        let range = CellRange::new(CellAddress::new(0, 0), CellAddress::new(2, 5));
+        let parts: Vec<String> = manager
+            .visible_subranges(&range)
+            .iter()
+            .map(|part| part.to_string())
+            .collect();
+        assert_eq!(parts, ["A1:A2", "C1:C2", "A5:A6", "C5:C6"]);
+
+        manager.set_rows_hidden(0, 5, false);
+        manager.set_columns_hidden(0, 2, false);
+        assert_eq!(manager.visible_subranges(&range), [range]);
+        assert!(manager.hidden_rows().is_empty());
+    }
+
+    #[test]
+    fn test_structural_edits_keep_the_content_on_screen() {
+        let insert = |before_row| StructuralOperation::InsertRows {
+            before_row,
+            count: 3,
+        };
+        let mut manager = ViewportManager::new(1000, 50).with_cell_dimensions(20.0, 100.0);
+        manager.set_viewport_size(400.0, 200.0);
+        manager.set_row_height(12, 40.0);
+        manager.scroll_to(0.0, 10.0 * 20.0 + 5.0);
+        assert_eq!(manager.get_visible_bounds().start_row, 10);
+
+        // Above the view: the rows on screen move down together with it
+        manager.apply_structural_operation(&insert(2));
+        assert_eq!(manager.get_visible_bounds().start_row, 13);
+        assert_eq!(manager.get_scroll_position().y, 13.0 * 20.0 + 5.0);
+        assert_eq!(manager.get_row_height(15), 40.0);
+        assert_eq!(manager.get_row_height(12), 20.0);
+
+        // Inside or below the view: nothing above the view changed
+        manager.apply_structural_operation(&insert(14));
+        manager.apply_structural_operation(&insert(500));
+        assert_eq!(manager.get_scroll_position().y, 13.0 * 20.0 + 5.0);
+        assert_eq!(manager.get_row_height(18), 40.0);
+
+        // Deleting the first visible row brings up the next one
+        manager.apply_structural_operation(&StructuralOperation::DeleteRows {
+            start_row: 12,
+            count: 2,
+        });
+        assert_eq!(manager.get_visible_bounds().start_row, 12);
+        assert_eq!(manager.get_scroll_position().y, 12.0 * 20.0);
+        assert_eq!(manager.get_row_height(16), 40.0);
+
+        manager.set_column_width(4, 150.0);
+        manager.apply_structural_operation(&StructuralOperation::DeleteColumns {
+            start_col: 4,
+            count: 1,
+        });
+        assert_eq!(manager.custom_column_widths(), Vec::new());
+    }
 }
@@ -0,0 +1,515 @@
+use crate::controller::{GridConfiguration, ViewportBounds};
+use gridcore_core::{
+    conditional::ConditionalFill,
+    formula::CellRange,
+    sparkline::Sparkline,
+    types::{CellAddress, CellValue},
+    SpreadsheetFacade,
+};
+use rustc_hash::FxHashMap;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::sync::Arc;
+
+#[cfg(feature = "perf")]
+use crate::perf::{VIEWPORT_CACHE_HITS, VIEWPORT_CACHE_MISSES, VIEWPORT_PREFETCHES};
+#[cfg(feature = "perf")]
+use metrics::counter;
+
+/// Characters of a cell's text kept for drawing. No screen is wide enough
+/// to show more, so the rest is never measured or drawn.
+pub const MAX_DISPLAY_CHARS: usize = 1_000;
+
+/// A cell ready to be drawn: its formatted text or sparkline, whether it
+/// holds an error or a boolean, whether its formula waits for a
+/// recalculation and what its conditional formats draw beneath it
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayCell {
+    pub address: CellAddress,
+    pub text: String,
+    pub is_error: bool,
+    /// TRUE and FALSE are drawn centered
+    pub is_boolean: bool,
+    pub is_stale: bool,
+    /// The cell's format asks for every line of the text to be shown
+    pub wrap: bool,
+    /// Chart of a `SPARKLINE` formula, drawn over the cell instead of the
+    /// (empty) text
+    pub sparkline: Option<Arc<Sparkline>>,
+    /// Data bar and background of the cell's conditional formats
+    pub conditional: Option<ConditionalFill>,
+}
+
+impl DisplayCell {
+    /// Read a cell from the facade and format it for display.
+    /// Empty cells produce nothing to draw.
+    pub fn load(facade: &SpreadsheetFacade, address: &CellAddress) -> Option<Self> {
+        let value = facade.get_cell(address)?.get_computed_value();
+        let format = facade.get_effective_format(address);
+        let mut text = match &format {
+            Some(format) => format.format_value(&value),
+            None => value.to_string(),
+        };
+        let sparkline = match &value {
+            CellValue::Sparkline(sparkline) => Some(Arc::clone(sparkline)),
+            _ => None,
+        };
+        if text.is_empty() && sparkline.is_none() {
+            return None;
+        }
+        if let Some((end, _)) = text.char_indices().nth(MAX_DISPLAY_CHARS) {
+            text.truncate(end);
+            text.push('…');
+        }
+
+        Some(Self {
+            address: *address,
+            is_error: matches!(value, CellValue::Error(_)),
+            is_boolean: value.is_boolean(),
+            is_stale: facade.is_stale(address),
+            wrap: format.is_some_and(|format| format.wrap_text),
+            conditional: facade.get_conditional_fill(address),
+            text,
+            sparkline,
+        })
+    }
+
+    /// Lines to draw, top to bottom. A wrapped cell shows all of its lines;
+    /// otherwise only the first is shown, ending in `…` when more follow.
+    pub fn lines(&self) -> Vec<Cow<'_, str>> {
+        let mut lines = self.text.lines();
+        if self.wrap {
+            return lines.map(Cow::Borrowed).collect();
+        }
+        let first = lines.next().unwrap_or_default();
+        match lines.next() {
+            Some(_) => vec![Cow::Owned(format!("{}…", first))],
+            None => vec![Cow::Borrowed(first)],
+        }
+    }
+}
+
+/// Hit/miss counters of the viewport cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Display lists served entirely from the cache
+    pub hits: u64,
+    /// Display lists that had to read the facade
+    pub misses: u64,
+    pub prefetches: u64,
+}
+
+/// Display-ready cell data for the visible region plus a margin around it.
+///
+/// The cache is filled by [`ViewportCache::prefetch`], which the UI runs
+/// outside of the render path once scrolling gets close to the edge of the
+/// cached region. Writes refresh only the cells they touch, and only when
+/// those cells fall inside the cached region.
+#[derive(Debug, Default)]
+pub struct ViewportCache {
+    /// Rows fetched above and below the visible rows; one viewport height if unset
+    margin_rows: Option<usize>,
+    /// Columns fetched left and right of the visible columns; one viewport width if unset
+    margin_cols: Option<usize>,
+    region: Option<ViewportBounds>,
+    cells: FxHashMap<CellAddress, DisplayCell>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+    prefetches: u64,
+    generation: u64,
+}
+
+impl ViewportCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_margin(mut self, rows: Option<usize>, cols: Option<usize>) -> Self {
+        self.set_margin(rows, cols);
+        self
+    }
+
+    /// Change the prefetch margin. Takes effect on the next prefetch.
+    pub fn set_margin(&mut self, rows: Option<usize>, cols: Option<usize>) {
+        self.margin_rows = rows;
+        self.margin_cols = cols;
+    }
+
+    pub fn margin(&self) -> (Option<usize>, Option<usize>) {
+        (self.margin_rows, self.margin_cols)
+    }
+
+    /// The block of cells currently held, if anything has been prefetched
+    pub fn region(&self) -> Option<&ViewportBounds> {
+        self.region.as_ref()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.get(),
+            misses: self.misses.get(),
+            prefetches: self.prefetches,
+        }
+    }
+
+    /// Whether every cell of `bounds` is cached
+    pub fn covers(&self, bounds: &ViewportBounds) -> bool {
+        self.region.as_ref().is_some_and(|region| {
+            region.start_row <= bounds.start_row
+                && region.end_row >= bounds.end_row
+                && region.start_col <= bounds.start_col
+                && region.end_col >= bounds.end_col
+        })
+    }
+
+    /// Every non-empty cached cell
+    pub fn cells(&self) -> impl Iterator<Item = &DisplayCell> {
+        self.cells.values()
+    }
+
+    /// Cached cell at an address. `None` means the address is outside the
+    /// cached region; `Some(None)` means it is cached and empty.
+    pub fn get(&self, address: &CellAddress) -> Option<Option<&DisplayCell>> {
+        let region = self.region.as_ref()?;
+        contains(region, address).then(|| self.cells.get(address))
+    }
+
+    /// Whether the visible bounds have left the cached region or come within
+    /// half a margin of one of its edges
+    pub fn needs_prefetch(&self, bounds: &ViewportBounds, config: &GridConfiguration) -> bool {
+        let Some(region) = &self.region else {
+            return true;
+        };
+        if !self.covers(bounds) {
+            return true;
+        }
+
+        let (margin_rows, margin_cols) = self.margins_for(bounds);
+        let threshold_rows = (margin_rows / 2).max(1);
+        let threshold_cols = (margin_cols / 2).max(1);
+        let last_row = config.total_rows.saturating_sub(1);
+        let last_col = config.total_cols.saturating_sub(1);
+
+        (region.start_row > 0 && bounds.start_row - region.start_row < threshold_rows)
+            || (region.end_row < last_row && region.end_row - bounds.end_row < threshold_rows)
+            || (region.start_col > 0 && bounds.start_col - region.start_col < threshold_cols)
+            || (region.end_col < last_col && region.end_col - bounds.end_col < threshold_cols)
+    }
+
+    /// Refill the cache around `bounds`
+    pub fn prefetch(
+        &mut self,
+        facade: &SpreadsheetFacade,
+        bounds: &ViewportBounds,
+        config: &GridConfiguration,
+    ) {
+        let (margin_rows, margin_cols) = self.margins_for(bounds);
+        let region = ViewportBounds {
+            start_row: bounds.start_row.saturating_sub(margin_rows),
+            end_row: (bounds.end_row + margin_rows).min(config.total_rows.saturating_sub(1)),
+            start_col: bounds.start_col.saturating_sub(margin_cols),
+            end_col: (bounds.end_col + margin_cols).min(config.total_cols.saturating_sub(1)),
+        };
+
+        self.cells.clear();
+        for row in region.start_row..=region.end_row {
+            for col in region.start_col..=region.end_col {
+                let address = CellAddress::new(col as u32, row as u32);
+                if let Some(cell) = DisplayCell::load(facade, &address) {
+                    self.cells.insert(address, cell);
+                }
+            }
+        }
+        self.region = Some(region);
+        self.prefetches += 1;
+
+        #[cfg(feature = "perf")]
+        counter!(VIEWPORT_PREFETCHES).increment(1);
+    }
+
+    /// Re-read cells that changed. Addresses outside the cached region are ignored.
+    /// Returns how many cached cells were refreshed.
+    pub fn invalidate(&mut self, facade: &SpreadsheetFacade, addresses: &[CellAddress]) -> usize {
+        let Some(region) = &self.region else {
+            return 0;
+        };
+
+        let mut refreshed = 0;
+        for address in addresses.iter().filter(|address| contains(region, address)) {
+            match DisplayCell::load(facade, address) {
+                Some(cell) => self.cells.insert(*address, cell),
+                None => self.cells.remove(address),
+            };
+            refreshed += 1;
+        }
+        refreshed
+    }
+
+    /// Re-read the cached cells inside `range`, e.g. everything from where
+    /// rows were inserted down, without dropping the rest of the cache.
+    /// Returns how many cached cells were refreshed.
+    pub fn invalidate_range(&mut self, facade: &SpreadsheetFacade, range: &CellRange) -> usize {
+        let Some(region) = &self.region else {
+            return 0;
+        };
+        let rows = (region.start_row as u32).max(range.start.row)
+            ..=(region.end_row as u32).min(range.end.row);
+        let cols = (region.start_col as u32).max(range.start.col)
+            ..=(region.end_col as u32).min(range.end.col);
+        let addresses: Vec<CellAddress> = rows
+            .flat_map(|row| cols.clone().map(move |col| CellAddress::new(col, row)))
+            .collect();
+        self.invalidate(facade, &addresses)
+    }
+
+    /// Drop everything, e.g. after switching sheets
+    pub fn clear(&mut self) {
+        self.region = None;
+        self.cells.clear();
+        self.generation += 1;
+    }
+
+    /// Count a write to `addresses` as a change of the drawn cells when one
+    /// of them lies inside `visible`
+    pub fn note_writes(&mut self, visible: &ViewportBounds, addresses: &[CellAddress]) {
+        if addresses.iter().any(|address| contains(visible, address)) {
+            self.generation += 1;
+        }
+    }
+
+    /// Changes whenever a visible cell may have changed: a write noted by
+    /// [`Self::note_writes`] or a cleared cache
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Cells to draw for `bounds`, read from the cache when it covers them
+    /// and from the facade otherwise
+    pub fn display_list(
+        &self,
+        facade: &SpreadsheetFacade,
+        bounds: &ViewportBounds,
+    ) -> Vec<DisplayCell> {
+        if self.covers(bounds) {
+            self.hits.set(self.hits.get() + 1);
+            #[cfg(feature = "perf")]
+            counter!(VIEWPORT_CACHE_HITS).increment(1);
+
+            return self
+                .cells
+                .values()
+                .filter(|cell| contains(bounds, &cell.address))
+                .cloned()
+                .collect();
+        }
+
+        self.misses.set(self.misses.get() + 1);
+        #[cfg(feature = "perf")]
+        counter!(VIEWPORT_CACHE_MISSES).increment(1);
+
+        let mut cells = Vec::new();
+        for row in bounds.start_row..=bounds.end_row {
+            for col in bounds.start_col..=bounds.end_col {
+                let address = CellAddress::new(col as u32, row as u32);
+                cells.extend(DisplayCell::load(facade, &address));
+            }
+        }
+        cells
+    }
+
+    fn margins_for(&self, bounds: &ViewportBounds) -> (usize, usize) {
+        (
+            self.margin_rows
+                .unwrap_or(bounds.end_row - bounds.start_row + 1),
+            self.margin_cols
+                .unwrap_or(bounds.end_col - bounds.start_col + 1),
+        )
+    }
+}
+
+fn contains(bounds: &ViewportBounds, address: &CellAddress) -> bool {
+    let (row, col) = (address.row as usize, address.col as usize);
+    row >= bounds.start_row
+        && row <= bounds.end_row
+        && col >= bounds.start_col
+        && col <= bounds.end_col
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gridcore_core::domain::CellFormat;
+
+    fn bounds(
+        start_row: usize,
+        end_row: usize,
+        start_col: usize,
+        end_col: usize,
+    ) -> ViewportBounds {
+        ViewportBounds {
+            start_row,
+            end_row,
+            start_col,
+            end_col,
+        }
+    }
+
+    fn config() -> GridConfiguration {
+        GridConfiguration {
+            total_rows: 1000,
+            total_cols: 50,
+            ..Default::default()
+        }
+    }
+
+    /// Cache rows 80..=140 and columns 0..=14 around a viewport of rows 100..=120
+    fn primed_cache(facade: &SpreadsheetFacade) -> ViewportCache {
+        let mut cache = ViewportCache::new().with_margin(Some(20), Some(5));
+        cache.prefetch(facade, &bounds(100, 120, 0, 9), &config());
+        assert_eq!(cache.region(), Some(&bounds(80, 140, 0, 14)));
+        cache
+    }
+
+    #[test]
+    fn test_display_list_served_from_cache() {
+        let facade = SpreadsheetFacade::new();
+        facade
+            .set_cell_value(&CellAddress::new(2, 105), "hello")
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(2, 90), "above")
+            .unwrap();
+        let cache = primed_cache(&facade);
+
+        let visible = cache.display_list(&facade, &bounds(100, 120, 0, 9));
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].text, "hello");
+
+        // Scrolled past the margin: falls back to the facade
+        cache.display_list(&facade, &bounds(130, 150, 0, 9));
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                prefetches: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalidate_inside_region() {
+        let facade = SpreadsheetFacade::new();
+        let address = CellAddress::new(3, 110);
+        facade.set_cell_value(&address, "1").unwrap();
+        let mut cache = primed_cache(&facade);
+
+        facade.set_cell_value(&address, "2").unwrap();
+        assert_eq!(cache.invalidate(&facade, &[address]), 1);
+        assert_eq!(cache.get(&address).unwrap().unwrap().text, "2");
+
+        facade.delete_cell(&address).unwrap();
+        cache.invalidate(&facade, &[address]);
+        assert_eq!(cache.get(&address), Some(None));
+    }
+
+    #[test]
+    fn test_invalidate_at_region_edge() {
+        let facade = SpreadsheetFacade::new();
+        let mut cache = primed_cache(&facade);
+
+        let corner = CellAddress::new(14, 140);
+        let just_outside = CellAddress::new(15, 140);
+        facade.set_cell_value(&corner, "edge").unwrap();
+        facade.set_cell_value(&just_outside, "out").unwrap();
+
+        assert_eq!(cache.invalidate(&facade, &[corner, just_outside]), 1);
+        assert_eq!(cache.get(&corner).unwrap().unwrap().text, "edge");
+        assert_eq!(cache.get(&just_outside), None);
+    }
+
+    #[test]
+    fn test_invalidate_outside_region() {
+        let facade = SpreadsheetFacade::new();
+        let mut cache = primed_cache(&facade);
+
+        let far = CellAddress::new(0, 500);
+        facade.set_cell_value(&far, "far").unwrap();
+        assert_eq!(cache.invalidate(&facade, &[far]), 0);
+        assert_eq!(cache.get(&far), None);
+        assert!(cache.cells.is_empty());
+
+        // Picked up once the viewport reaches it
+        let far_view = bounds(490, 510, 0, 9);
+        assert!(cache.needs_prefetch(&far_view, &config()));
+        cache.prefetch(&facade, &far_view, &config());
+        assert_eq!(cache.get(&far).unwrap().unwrap().text, "far");
+    }
+
+    #[test]
+    fn test_invalidate_range_after_inserting_rows() {
+        let facade = SpreadsheetFacade::new();
+        facade
+            .set_cell_value(&CellAddress::new(1, 95), "above")
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(1, 105), "below")
+            .unwrap();
+        let mut cache = primed_cache(&facade);
+
+        facade.insert_rows(100, 2).unwrap();
+        let damage = CellRange::new(CellAddress::new(0, 100), CellAddress::new(49, 999));
+        // Rows 100..=140 of the 15 cached columns
+        assert_eq!(cache.invalidate_range(&facade, &damage), 41 * 15);
+        assert_eq!(cache.get(&CellAddress::new(1, 105)), Some(None));
+        assert_eq!(
+            cache.get(&CellAddress::new(1, 107)).unwrap().unwrap().text,
+            "below"
+        );
+        assert_eq!(
+            cache.get(&CellAddress::new(1, 95)).unwrap().unwrap().text,
+            "above"
+        );
+    }
+
+    #[test]
+    fn test_needs_prefetch_near_edge() {
+        let facade = SpreadsheetFacade::new();
+        let cache = primed_cache(&facade);
+        let config = config();
+
+        assert!(!cache.needs_prefetch(&bounds(100, 120, 0, 9), &config));
+        // Within half a margin (10 rows) of the bottom edge
+        assert!(cache.needs_prefetch(&bounds(112, 132, 0, 9), &config));
+        // The top of the sheet is not an edge to approach
+        let mut top = ViewportCache::new().with_margin(Some(20), Some(5));
+        top.prefetch(&facade, &bounds(0, 20, 0, 9), &config);
+        assert!(!top.needs_prefetch(&bounds(0, 20, 0, 9), &config));
+    }
+
+    #[test]
+    fn test_multi_line_cells() {
+        let facade = SpreadsheetFacade::new();
+        let plain = CellAddress::new(0, 0);
+        let wrapped = CellAddress::new(1, 0);
+        let single = CellAddress::new(2, 0);
+        facade.set_cell_value(&plain, "one\ntwo\nthree").unwrap();
+        facade.set_cell_value(&wrapped, "one\ntwo\nthree").unwrap();
+        facade
+            .set_cell_format(&wrapped, CellFormat::default().wrapped())
+            .unwrap();
+        facade.set_cell_value(&single, "one").unwrap();
+
+        // Unwrapped: the first line with a marker for the rest
+        let cell = DisplayCell::load(&facade, &plain).unwrap();
+        assert!(!cell.wrap);
+        assert_eq!(cell.lines(), vec!["one…"]);
+
+        let cell = DisplayCell::load(&facade, &wrapped).unwrap();
+        assert!(cell.wrap);
+        assert_eq!(cell.lines(), vec!["one", "two", "three"]);
+
+        let cell = DisplayCell::load(&facade, &single).unwrap();
+        assert_eq!(cell.lines(), vec!["one"]);
+    }
+}
@@ -16,6 +16,11 @@ impl VimHandler {
         selection_start: Option<usize>,
         selection_end: Option<usize>,
     ) -> Result<Option<VimKeyResult>> {
+        // Alt+Enter breaks the line wherever text is being typed
+        if alt && !ctrl && key == "Enter" {
+            return Ok(Self::line_break(mode, selection_start, selection_end));
+        }
+
         // Don't handle special keys with modifiers (except shift for capital letters)
         if ctrl || alt {
             return Ok(None);
@@ -71,6 +76,41 @@ impl VimHandler {
         }
     }
 
+    /// Insert a line break at the cursor, replacing any selection. Only
+    /// applies while typing, not in vim normal or visual mode.
+    fn line_break(
+        mode: &EditorMode,
+        selection_start: Option<usize>,
+        selection_end: Option<usize>,
+    ) -> Option<VimKeyResult> {
+        let (value, cursor_pos) = match mode {
+            EditorMode::Editing {
+                value, cursor_pos, ..
+            }
+            | EditorMode::CellEditing {
+                value,
+                cursor_pos,
+                mode: CellEditMode::Insert(_),
+                ..
+            } => (value, *cursor_pos),
+            _ => return None,
+        };
+        let (start, end) = match (selection_start, selection_end) {
+            (Some(start), Some(end)) if start != end => (start.min(end), start.max(end)),
+            _ => (cursor_pos, cursor_pos),
+        };
+        let end = end.min(value.len());
+        let start = start.min(end);
+        let mut new_value = String::with_capacity(value.len() + 1);
+        new_value.push_str(&value[..start]);
+        new_value.push('\n');
+        new_value.push_str(&value[end..]);
+        Some(VimKeyResult::UpdateText {
+            value: new_value,
+            cursor_pos: start + 1,
+        })
+    }
+
     fn handle_normal_mode_key(
         key: &str,
         value: &str,
@@ -169,18 +209,8 @@ impl VimHandler {
                     visual_anchor: None,
                 }))
             }
-            "Enter" => {
-                // In insert mode, Enter adds a newline
-                let mut new_value = String::new();
-                new_value.push_str(&value[..cursor_pos]);
-                new_value.push('\n');
-                new_value.push_str(&value[cursor_pos..]);
-
-                Some(VimKeyResult::UpdateText {
-                    value: new_value,
-                    cursor_pos: cursor_pos + 1,
-                })
-            }
+            // Enter commits; Alt+Enter is the way to add a line break
+            "Enter" => Some(VimKeyResult::CompleteEdit),
             "Backspace" => {
                 if cursor_pos > 0 {
                     let mut new_value = String::new();
@@ -2,6 +2,7 @@ pub mod behaviors;
 pub mod controller;
 pub mod managers;
 pub mod state;
+pub mod theme;
 
 #[cfg(feature = "perf")]
 pub mod perf;
@@ -9,7 +10,8 @@ pub mod perf;
 // Re-export key types
 pub use controller::SpreadsheetController;
 pub use state::{
-    Action, EditMode, Selection, SelectionType, SpreadsheetMode, UIState, ViewportInfo,
+    Action, EditMode, Selection, SelectionType, SpreadsheetMode, StateDiff, StateSnapshot, UIState,
+    ViewportInfo,
 };
 
 #[cfg(test)]
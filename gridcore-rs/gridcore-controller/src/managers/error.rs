@@ -18,6 +18,7 @@ pub struct ErrorSystem {
     errors: VecDeque<ErrorEntry>,
     next_id: usize,
     max_errors: usize,
+    revision: u64,
 }
 
 impl ErrorSystem {
@@ -27,6 +28,7 @@ impl ErrorSystem {
             errors: VecDeque::new(),
             next_id: 0,
             max_errors: 100, // Keep max 100 errors in memory
+            revision: 0,
         }
     }
 
@@ -36,6 +38,7 @@ impl ErrorSystem {
             errors: VecDeque::with_capacity(max_errors),
             next_id: 0,
             max_errors,
+            revision: 0,
         }
     }
 
@@ -64,6 +67,7 @@ impl ErrorSystem {
         }
 
         self.errors.push_back(error);
+        self.revision += 1;
         id
     }
 
@@ -71,6 +75,7 @@ impl ErrorSystem {
     pub fn remove_error(&mut self, id: usize) -> bool {
         if let Some(pos) = self.errors.iter().position(|e| e.id == id) {
             self.errors.remove(pos);
+            self.revision += 1;
             true
         } else {
             false
@@ -100,12 +105,16 @@ impl ErrorSystem {
 
     /// Clear all errors
     pub fn clear_all(&mut self) {
-        self.errors.clear();
+        if !self.errors.is_empty() {
+            self.errors.clear();
+            self.revision += 1;
+        }
     }
 
     /// Clean up expired errors (should be called periodically)
     pub fn cleanup_expired(&mut self) {
         let now = Utc::now();
+        let before = self.errors.len();
         self.errors.retain(|error| {
             if let Some(dismiss_after) = error.auto_dismiss_after {
                 now.signed_duration_since(error.timestamp) < dismiss_after
@@ -113,6 +122,14 @@ impl ErrorSystem {
                 true
             }
         });
+        if self.errors.len() != before {
+            self.revision += 1;
+        }
+    }
+
+    /// Changes whenever an error is added or removed
+    pub fn revision(&self) -> u64 {
+        self.revision
     }
 
     /// Get the count of active errors
@@ -0,0 +1,467 @@
+use gridcore_core::csv::RowFold;
+use gridcore_core::formula::CellRange;
+use gridcore_core::references::StructuralOperation;
+use gridcore_core::types::CellAddress;
+use gridcore_core::{Result, SpreadsheetError};
+use std::collections::BTreeSet;
+
+/// Widest fold column vim allows
+pub const MAX_FOLD_COLUMN: u8 = 12;
+
+/// A fold as scripts see it: 0-based rows, and its nesting level counting
+/// from 1 for the outermost folds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fold {
+    pub start_row: u32,
+    pub end_row: u32,
+    pub level: usize,
+    pub collapsed: bool,
+}
+
+impl Fold {
+    fn contains(&self, row: u32) -> bool {
+        (self.start_row..=self.end_row).contains(&row)
+    }
+
+    fn intersects(&self, start_row: u32, end_row: u32) -> bool {
+        self.start_row <= end_row && start_row <= self.end_row
+    }
+
+    fn within(&self, other: &Fold) -> bool {
+        other.start_row <= self.start_row && self.end_row <= other.end_row
+    }
+}
+
+/// Vim-style folds over the rows of the sheet.
+///
+/// Folds nest but never partly overlap. A collapsed fold hides every row
+/// but its first, which stands for the whole fold. Folds follow rows
+/// inserted and deleted around and inside them: a fold whose rows are all
+/// deleted goes, one that loses some of them shrinks.
+#[derive(Debug, Clone, Default)]
+pub struct Folds {
+    /// Sorted by start row, outer folds before the folds they hold. Levels
+    /// are worked out when the folds are listed.
+    folds: Vec<Fold>,
+    /// Width of the fold indicator column in the gutter, in characters
+    column: u8,
+}
+
+impl Folds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every fold with its level, outer folds before the folds they hold
+    pub fn list(&self) -> Vec<Fold> {
+        let mut open: Vec<Fold> = Vec::new();
+        self.folds
+            .iter()
+            .map(|fold| {
+                open.retain(|outer| fold.within(outer));
+                open.push(*fold);
+                Fold {
+                    level: open.len(),
+                    ..*fold
+                }
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.folds.is_empty()
+    }
+
+    pub fn fold_column(&self) -> u8 {
+        self.column
+    }
+
+    /// Set the width of the fold column, as vim's `foldcolumn`
+    pub fn set_fold_column(&mut self, width: u8) -> Result<()> {
+        if width > MAX_FOLD_COLUMN {
+            return Err(SpreadsheetError::InvalidArguments(format!(
+                "foldcolumn must be between 0 and {}",
+                MAX_FOLD_COLUMN
+            )));
+        }
+        self.column = width;
+        Ok(())
+    }
+
+    /// Fold rows `start_row` to `end_row`, open. A fold may hold or sit
+    /// inside others but not partly overlap one.
+    pub fn create(&mut self, start_row: u32, end_row: u32) -> Result<()> {
+        let (start_row, end_row) = (start_row.min(end_row), start_row.max(end_row));
+        let fold = Fold {
+            start_row,
+            end_row,
+            level: 0,
+            collapsed: false,
+        };
+        for other in &self.folds {
+            if (other.start_row, other.end_row) == (start_row, end_row) {
+                return Err(SpreadsheetError::InvalidOperation(format!(
+                    "Rows {}-{} are already folded",
+                    start_row + 1,
+                    end_row + 1
+                )));
+            }
+            if fold.intersects(other.start_row, other.end_row)
+                && !fold.within(other)
+                && !other.within(&fold)
+            {
+                return Err(SpreadsheetError::InvalidOperation(format!(
+                    "A fold over rows {}-{} would cross the fold over rows {}-{}",
+                    start_row + 1,
+                    end_row + 1,
+                    other.start_row + 1,
+                    other.end_row + 1
+                )));
+            }
+        }
+        self.folds.push(fold);
+        self.sort();
+        Ok(())
+    }
+
+    /// Remove the innermost fold holding `row`, returning whether there was one
+    pub fn delete_at(&mut self, row: u32) -> bool {
+        match self.innermost_at(row) {
+            Some(index) => {
+                self.folds.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Open the closed folds touching the rows: one level, the outermost
+    /// closed folds, or all of them. Returns the number opened.
+    pub fn open(&mut self, start_row: u32, end_row: u32, all: bool) -> usize {
+        let targets: Vec<usize> = (0..self.folds.len())
+            .filter(|&i| {
+                let fold = &self.folds[i];
+                fold.collapsed
+                    && fold.intersects(start_row, end_row)
+                    && (all
+                        || !self.folds.iter().any(|outer| {
+                            outer.collapsed
+                                && outer != fold
+                                && fold.within(outer)
+                                && outer.intersects(start_row, end_row)
+                        }))
+            })
+            .collect();
+        self.set_collapsed(&targets, false)
+    }
+
+    /// Close the open folds touching the rows: one level, the innermost
+    /// open folds, or all of them. Returns the number closed.
+    pub fn close(&mut self, start_row: u32, end_row: u32, all: bool) -> usize {
+        let targets: Vec<usize> = (0..self.folds.len())
+            .filter(|&i| {
+                let fold = &self.folds[i];
+                !fold.collapsed
+                    && fold.intersects(start_row, end_row)
+                    && (all
+                        || !self.folds.iter().any(|inner| {
+                            !inner.collapsed
+                                && inner != fold
+                                && inner.within(fold)
+                                && inner.intersects(start_row, end_row)
+                        }))
+            })
+            .collect();
+        self.set_collapsed(&targets, true)
+    }
+
+    /// Open the outermost closed fold holding `row`, or else close the
+    /// innermost one, as vim's `za`. Returns whether a fold changed.
+    pub fn toggle(&mut self, row: u32) -> bool {
+        if let Some(index) = self
+            .folds
+            .iter()
+            .position(|fold| fold.collapsed && fold.contains(row))
+        {
+            self.folds[index].collapsed = false;
+            return true;
+        }
+        match self.innermost_at(row) {
+            Some(index) => {
+                self.folds[index].collapsed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// First row of the nearest fold starting below `row`, as vim's `zj`
+    pub fn next_start(&self, row: u32) -> Option<u32> {
+        self.folds
+            .iter()
+            .map(|fold| fold.start_row)
+            .filter(|&start| start > row)
+            .min()
+    }
+
+    /// Last row of the nearest fold ending above `row`, as vim's `zk`
+    pub fn previous_end(&self, row: u32) -> Option<u32> {
+        self.folds
+            .iter()
+            .map(|fold| fold.end_row)
+            .filter(|&end| end < row)
+            .max()
+    }
+
+    /// Rows the collapsed folds hide
+    pub fn hidden_rows(&self) -> BTreeSet<u32> {
+        self.folds
+            .iter()
+            .filter(|fold| fold.collapsed)
+            .flat_map(|fold| fold.start_row + 1..=fold.end_row)
+            .collect()
+    }
+
+    /// Follow rows inserted or deleted by `operation`: folds below move,
+    /// folds the rows land in grow or shrink and folds losing all their
+    /// rows are dropped. Column edits and moves leave folds alone.
+    pub fn apply_structural_operation(&mut self, operation: &StructuralOperation) {
+        if !matches!(
+            operation,
+            StructuralOperation::InsertRows { .. } | StructuralOperation::DeleteRows { .. }
+        ) {
+            return;
+        }
+        self.folds.retain_mut(|fold| {
+            let rows = CellRange::new(
+                CellAddress::new(0, fold.start_row),
+                CellAddress::new(0, fold.end_row),
+            );
+            match operation.shift_range(&rows) {
+                Some(rows) => {
+                    fold.start_row = rows.start.row;
+                    fold.end_row = rows.end.row;
+                    true
+                }
+                None => false,
+            }
+        });
+        // Folds shrunk onto the same rows become one
+        self.sort();
+        self.folds.dedup_by(|later, kept| {
+            (later.start_row, later.end_row) == (kept.start_row, kept.end_row)
+        });
+    }
+
+    /// The folds as a sidecar stores them
+    pub fn to_sidecar(&self) -> Vec<RowFold> {
+        self.folds
+            .iter()
+            .map(|fold| RowFold {
+                start: fold.start_row + 1,
+                end: fold.end_row + 1,
+                collapsed: fold.collapsed,
+            })
+            .collect()
+    }
+
+    /// Replace the folds with those of a sidecar. Folds that are empty or
+    /// cross one read before are skipped and returned.
+    pub fn load_sidecar(&mut self, folds: &[RowFold]) -> Vec<RowFold> {
+        self.folds.clear();
+        let mut skipped = Vec::new();
+        for fold in folds {
+            let created = match (fold.start.checked_sub(1), fold.end.checked_sub(1)) {
+                (Some(start), Some(end)) if start <= end => self.create(start, end).is_ok(),
+                _ => false,
+            };
+            if !created {
+                skipped.push(*fold);
+            } else if fold.collapsed {
+                let index = self
+                    .folds
+                    .iter()
+                    .position(|f| (f.start_row, f.end_row) == (fold.start - 1, fold.end - 1));
+                if let Some(index) = index {
+                    self.folds[index].collapsed = true;
+                }
+            }
+        }
+        skipped
+    }
+
+    pub fn clear(&mut self) {
+        self.folds.clear();
+    }
+
+    fn innermost_at(&self, row: u32) -> Option<usize> {
+        // Inner folds sort after the folds holding them
+        self.folds.iter().rposition(|fold| fold.contains(row))
+    }
+
+    fn set_collapsed(&mut self, indexes: &[usize], collapsed: bool) -> usize {
+        for &index in indexes {
+            self.folds[index].collapsed = collapsed;
+        }
+        indexes.len()
+    }
+
+    fn sort(&mut self) {
+        self.folds
+            .sort_by_key(|fold| (fold.start_row, std::cmp::Reverse(fold.end_row)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(folds: &Folds) -> Vec<(u32, u32, usize)> {
+        folds
+            .list()
+            .iter()
+            .map(|fold| (fold.start_row, fold.end_row, fold.level))
+            .collect()
+    }
+
+    fn folds(spans: &[(u32, u32)]) -> Folds {
+        let mut folds = Folds::new();
+        for &(start, end) in spans {
+            folds.create(start, end).unwrap();
+        }
+        folds
+    }
+
+    #[test]
+    fn test_folds_nest_but_do_not_cross() {
+        let mut folds = folds(&[(10, 20), (2, 30), (12, 14)]);
+        assert_eq!(ranges(&folds), [(2, 30, 1), (10, 20, 2), (12, 14, 3)]);
+        assert!(folds.create(15, 25).is_err());
+        assert!(folds.create(10, 20).is_err());
+        folds.create(22, 25).unwrap();
+        assert_eq!(folds.list()[3].level, 2);
+
+        assert!(folds.delete_at(13));
+        assert_eq!(ranges(&folds), [(2, 30, 1), (10, 20, 2), (22, 25, 2)]);
+        assert!(!folds.delete_at(40));
+    }
+
+    #[test]
+    fn test_open_and_close_one_level_at_a_time() {
+        let mut folds = folds(&[(0, 20), (5, 10)]);
+        assert_eq!(folds.close(6, 6, false), 1);
+        assert_eq!(folds.hidden_rows(), (6..=10).collect());
+        assert_eq!(folds.close(6, 6, false), 1);
+        assert_eq!(folds.hidden_rows(), (1..=20).collect());
+        assert_eq!(folds.close(6, 6, false), 0);
+
+        assert_eq!(folds.open(6, 6, false), 1);
+        assert_eq!(folds.hidden_rows(), (6..=10).collect());
+        folds.close(0, 0, true);
+        assert_eq!(folds.open(0, 30, true), 2);
+        assert!(folds.hidden_rows().is_empty());
+
+        assert!(folds.toggle(7));
+        assert!(folds.list()[1].collapsed);
+        assert!(folds.toggle(7));
+        assert!(!folds.list()[1].collapsed);
+        assert!(!folds.toggle(25));
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let folds = folds(&[(2, 4), (8, 12), (9, 10)]);
+        assert_eq!(folds.next_start(0), Some(2));
+        assert_eq!(folds.next_start(2), Some(8));
+        assert_eq!(folds.next_start(8), Some(9));
+        assert_eq!(folds.next_start(9), None);
+        assert_eq!(folds.previous_end(12), Some(10));
+        assert_eq!(folds.previous_end(9), Some(4));
+        assert_eq!(folds.previous_end(4), None);
+    }
+
+    #[test]
+    fn test_folds_follow_inserted_rows() {
+        // Above, at the first row, inside, at the last row and below
+        for (before, expected) in [
+            (2, (8, 14)),
+            (5, (8, 14)),
+            (7, (5, 14)),
+            (11, (5, 14)),
+            (12, (5, 11)),
+        ] {
+            let mut folds = folds(&[(5, 11)]);
+            folds.apply_structural_operation(&StructuralOperation::InsertRows {
+                before_row: before,
+                count: 3,
+            });
+            let fold = folds.list()[0];
+            assert_eq!(
+                (fold.start_row, fold.end_row),
+                expected,
+                "before {}",
+                before
+            );
+        }
+    }
+
+    #[test]
+    fn test_folds_follow_deleted_rows() {
+        // Deleted rows against a fold over rows 5-11
+        for ((start, count), expected) in [
+            ((0, 2), Some((3, 9))),   // above
+            ((0, 6), Some((0, 5))),   // over its start
+            ((5, 2), Some((5, 9))),   // its first rows
+            ((7, 2), Some((5, 9))),   // inside
+            ((10, 4), Some((5, 9))),  // over its end
+            ((12, 3), Some((5, 11))), // below
+            ((5, 7), None),           // exactly its rows
+            ((3, 12), None),          // around it
+        ] {
+            let mut folds = folds(&[(5, 11)]);
+            folds.apply_structural_operation(&StructuralOperation::DeleteRows {
+                start_row: start,
+                count,
+            });
+            let fold = folds
+                .list()
+                .first()
+                .map(|fold| (fold.start_row, fold.end_row));
+            assert_eq!(fold, expected, "deleting {} from {}", count, start);
+        }
+
+        // An outer fold shrunk onto its inner fold merges with it
+        let mut nested = folds(&[(5, 11), (5, 8)]);
+        nested.apply_structural_operation(&StructuralOperation::DeleteRows {
+            start_row: 9,
+            count: 3,
+        });
+        assert_eq!(ranges(&nested), [(5, 8, 1)]);
+    }
+
+    #[test]
+    fn test_sidecar_round_trip() {
+        let mut original = folds(&[(0, 9), (2, 4)]);
+        original.close(3, 3, false);
+        let sidecar = original.to_sidecar();
+        assert_eq!(
+            sidecar[1],
+            RowFold {
+                start: 3,
+                end: 5,
+                collapsed: true
+            }
+        );
+
+        let mut loaded = Folds::new();
+        let crossing = RowFold {
+            start: 5,
+            end: 15,
+            collapsed: false,
+        };
+        let mut with_bad = sidecar.clone();
+        with_bad.push(crossing);
+        assert_eq!(loaded.load_sidecar(&with_bad), [crossing]);
+        assert_eq!(loaded.list(), original.list());
+    }
+}
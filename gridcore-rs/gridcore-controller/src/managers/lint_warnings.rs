@@ -0,0 +1,99 @@
+use gridcore_core::lint::{LintFinding, LintRule, LintSettings};
+use gridcore_core::types::CellAddress;
+
+/// Findings of the last lint run over the active sheet, with the settings
+/// that choose which rules run.
+///
+/// Findings are only replaced when linting runs again, so they can go stale
+/// as cells are edited; they are never recomputed during recalculation.
+#[derive(Debug, Clone, Default)]
+pub struct LintWarnings {
+    settings: LintSettings,
+    /// Sorted by row, then column
+    findings: Vec<LintFinding>,
+    /// Counts changes to the findings
+    revision: u64,
+}
+
+impl LintWarnings {
+    pub fn new(settings: LintSettings) -> Self {
+        Self {
+            settings,
+            findings: Vec::new(),
+            revision: 0,
+        }
+    }
+
+    pub fn settings(&self) -> &LintSettings {
+        &self.settings
+    }
+
+    /// Turn a rule on or off for later runs. Turning it off also drops its
+    /// current findings.
+    pub fn set_rule_enabled(&mut self, rule: LintRule, enabled: bool) {
+        self.settings.set_enabled(rule, enabled);
+        if !enabled {
+            self.findings.retain(|finding| finding.rule != rule);
+            self.revision += 1;
+        }
+    }
+
+    pub fn set_findings(&mut self, mut findings: Vec<LintFinding>) {
+        findings.sort_by_key(|finding| position(&finding.address));
+        self.findings = findings;
+        self.revision += 1;
+    }
+
+    /// Changes whenever the findings do
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn findings(&self) -> &[LintFinding] {
+        &self.findings
+    }
+
+    /// Findings about one cell
+    pub fn for_cell(&self, address: &CellAddress) -> impl Iterator<Item = &LintFinding> {
+        let address = *address;
+        self.findings
+            .iter()
+            .filter(move |finding| finding.address == address)
+    }
+
+    /// The first finding after `address` in row-major order, wrapping
+    /// around to the first one
+    pub fn next_after(&self, address: &CellAddress) -> Option<&LintFinding> {
+        self.findings
+            .iter()
+            .find(|finding| position(&finding.address) > position(address))
+            .or_else(|| self.findings.first())
+    }
+
+    /// The last finding before `address` in row-major order, wrapping
+    /// around to the last one
+    pub fn previous_before(&self, address: &CellAddress) -> Option<&LintFinding> {
+        self.findings
+            .iter()
+            .rev()
+            .find(|finding| position(&finding.address) < position(address))
+            .or_else(|| self.findings.last())
+    }
+
+    pub fn clear(&mut self) {
+        self.findings.clear();
+        self.revision += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.findings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+fn position(address: &CellAddress) -> (u32, u32) {
+    (address.row, address.col)
+}
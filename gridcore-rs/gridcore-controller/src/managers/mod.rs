@@ -1,8 +1,18 @@
 pub mod error;
+pub mod folds;
+pub mod lint_warnings;
 pub mod manager_access;
+pub mod recent_entries;
+pub mod save_state;
+pub mod watch_list;
 
 // Re-export for backwards compatibility during migration
 pub use error::ErrorSystem as ErrorManager;
 pub use error::ErrorSystem as ErrorFormatter;
 pub use error::{ErrorEntry, ErrorSystem};
+pub use folds::{Fold, Folds};
+pub use lint_warnings::LintWarnings;
 pub use manager_access::ManagerAccess;
+pub use recent_entries::{EntryCompletion, EntryPicker, RecentEntries, RecentEntry, RecentScope};
+pub use save_state::{edited_ago, SaveState};
+pub use watch_list::{WatchEntry, WatchList, WatchUpdate, WatchedCell};
@@ -0,0 +1,332 @@
+//! Values committed during the session, offered again while typing.
+//!
+//! Data entry tends to repeat a handful of values. Every committed entry
+//! short enough to retype is remembered, most recent first; while editing,
+//! `Ctrl+N`/`Ctrl+P` cycle through the remembered values and the distinct
+//! texts of the cell's column that start with what was typed, and
+//! `Ctrl+Down` lists them in a picker.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Entries kept before the least recently used is dropped
+pub const DEFAULT_CAPACITY: usize = 50;
+
+/// Longest entry remembered, in characters
+pub const DEFAULT_MAX_CHARS: usize = 100;
+
+/// Which entries are offered in a sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecentScope {
+    /// Only the entries made in the same sheet
+    #[default]
+    Sheet,
+    /// Entries from every sheet
+    Global,
+}
+
+/// A remembered entry, as stored in the UI state snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentEntry {
+    pub sheet: String,
+    pub text: String,
+    /// Times the entry was committed
+    pub uses: u32,
+}
+
+/// Recently committed entries, most recent first and bounded in number
+#[derive(Debug, Clone)]
+pub struct RecentEntries {
+    entries: Vec<RecentEntry>,
+    capacity: usize,
+    max_chars: usize,
+    scope: RecentScope,
+}
+
+impl Default for RecentEntries {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity: DEFAULT_CAPACITY,
+            max_chars: DEFAULT_MAX_CHARS,
+            scope: RecentScope::default(),
+        }
+    }
+}
+
+impl RecentEntries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self.entries.truncate(capacity);
+        self
+    }
+
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    pub fn scope(&self) -> RecentScope {
+        self.scope
+    }
+
+    pub fn set_scope(&mut self, scope: RecentScope) {
+        self.scope = scope;
+    }
+
+    /// Whether `text` is worth offering again: not blank, not a formula
+    /// and not longer than the limit
+    pub fn is_recordable(&self, text: &str) -> bool {
+        !text.trim().is_empty() && !text.starts_with('=') && text.chars().count() <= self.max_chars
+    }
+
+    /// Remember `text` as entered in `sheet`. Returns false when it is not
+    /// recordable.
+    pub fn record(&mut self, sheet: &str, text: &str) -> bool {
+        if !self.is_recordable(text) {
+            return false;
+        }
+        let uses = match self
+            .entries
+            .iter()
+            .position(|entry| entry.sheet == sheet && entry.text == text)
+        {
+            Some(index) => self.entries.remove(index).uses,
+            None => 0,
+        };
+        self.entries.insert(
+            0,
+            RecentEntry {
+                sheet: sheet.to_string(),
+                text: text.to_string(),
+                uses: uses + 1,
+            },
+        );
+        self.entries.truncate(self.capacity);
+        true
+    }
+
+    /// Every remembered entry, most recent first
+    pub fn entries(&self) -> &[RecentEntry] {
+        &self.entries
+    }
+
+    /// Replace the remembered entries, e.g. from a UI state snapshot
+    pub fn restore(&mut self, entries: Vec<RecentEntry>) {
+        self.entries = entries;
+        self.entries.truncate(self.capacity);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Follow a sheet rename so its entries stay with it
+    pub fn rename_sheet(&mut self, old_name: &str, new_name: &str) {
+        for entry in &mut self.entries {
+            if entry.sheet == old_name {
+                entry.sheet = new_name.to_string();
+            }
+        }
+    }
+
+    /// Values to offer in `sheet` for the partial input `typed`: the
+    /// remembered entries in scope and the texts of `column`, one per cell,
+    /// that start with `typed` ignoring case. The most frequent come first,
+    /// counting both commits and cells, then the most recently committed.
+    pub fn suggestions<'a>(
+        &self,
+        sheet: &str,
+        typed: &str,
+        column: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<String> {
+        let typed = typed.to_lowercase();
+        // Frequency, and age in commits of the latest use
+        let mut ranks: HashMap<&str, (u32, Option<usize>)> = HashMap::new();
+        let in_scope = self
+            .entries
+            .iter()
+            .filter(|entry| self.scope == RecentScope::Global || entry.sheet == sheet);
+        for (age, entry) in in_scope.enumerate() {
+            let rank = ranks.entry(&entry.text).or_default();
+            rank.0 += entry.uses;
+            rank.1 = rank.1.or(Some(age));
+        }
+        for text in column {
+            if self.is_recordable(text) {
+                ranks.entry(text).or_default().0 += 1;
+            }
+        }
+
+        let mut ranked: Vec<(&str, (u32, Option<usize>))> = ranks
+            .into_iter()
+            .filter(|(text, _)| {
+                let lower = text.to_lowercase();
+                lower.starts_with(&typed) && lower != typed
+            })
+            .collect();
+        ranked.sort_by_key(|&(text, (frequency, age))| {
+            (Reverse(frequency), age.unwrap_or(usize::MAX), text)
+        });
+        ranked
+            .into_iter()
+            .map(|(text, _)| text.to_string())
+            .collect()
+    }
+}
+
+/// Where Ctrl+N/Ctrl+P cycling stands: what was typed before it started
+/// and the suggestion shown, if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryCompletion {
+    pub typed: String,
+    pub candidates: Vec<String>,
+    /// Suggestion in the editor, `None` while the typed text is back
+    pub index: Option<usize>,
+}
+
+impl EntryCompletion {
+    pub fn new(typed: String, candidates: Vec<String>) -> Self {
+        Self {
+            typed,
+            candidates,
+            index: None,
+        }
+    }
+
+    /// Move to the next suggestion, or the previous one going `back`; past
+    /// either end the typed text comes back, as in vim
+    pub fn step(&mut self, back: bool) -> &str {
+        let count = self.candidates.len();
+        self.index = match (self.index, back) {
+            _ if count == 0 => None,
+            (None, false) => Some(0),
+            (None, true) => Some(count - 1),
+            (Some(index), false) => (index + 1 < count).then_some(index + 1),
+            (Some(index), true) => index.checked_sub(1),
+        };
+        self.current()
+    }
+
+    /// Text the editor should show
+    pub fn current(&self) -> &str {
+        match self.index {
+            Some(index) => &self.candidates[index],
+            None => &self.typed,
+        }
+    }
+}
+
+/// The Ctrl+Down list of suggestions under the editor
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPicker {
+    pub candidates: Vec<String>,
+    pub selected: usize,
+}
+
+impl EntryPicker {
+    /// A picker over `candidates`, or `None` when there are none
+    pub fn new(candidates: Vec<String>) -> Option<Self> {
+        (!candidates.is_empty()).then_some(Self {
+            candidates,
+            selected: 0,
+        })
+    }
+
+    /// Move the highlight by one, wrapping around
+    pub fn move_selection(&mut self, back: bool) {
+        let count = self.candidates.len();
+        self.selected = if back {
+            (self.selected + count - 1) % count
+        } else {
+            (self.selected + 1) % count
+        };
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.candidates[self.selected]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_are_bounded_and_exclude_formulas_and_long_text() {
+        let mut recent = RecentEntries::new().with_capacity(2).with_max_chars(5);
+        assert!(!recent.record("Sheet1", "=A1+1"));
+        assert!(!recent.record("Sheet1", "   "));
+        assert!(!recent.record("Sheet1", "toolong"));
+        assert!(recent.record("Sheet1", "héllo"));
+        assert!(recent.record("Sheet1", "a"));
+        assert!(recent.record("Sheet1", "b"));
+        let texts: Vec<&str> = recent.entries().iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, ["b", "a"]);
+
+        // Committing again moves an entry to the front and counts the use
+        recent.record("Sheet1", "a");
+        assert_eq!(recent.entries()[0].text, "a");
+        assert_eq!(recent.entries()[0].uses, 2);
+        assert_eq!(recent.len(), 2);
+    }
+
+    #[test]
+    fn test_suggestions_rank_by_frequency_then_recency() {
+        let mut recent = RecentEntries::new();
+        for text in ["Pending", "Paid", "Paid", "Partial", "Other"] {
+            recent.record("Sheet1", text);
+        }
+        recent.record("Sheet2", "Parked");
+
+        // Paid twice beats Partial, the most recent; the column counts too
+        assert_eq!(
+            recent.suggestions("Sheet1", "p", ["Pending", "Pending", "Open"]),
+            ["Pending", "Paid", "Partial"]
+        );
+        assert_eq!(
+            recent.suggestions("Sheet1", "pa", std::iter::empty()),
+            ["Paid", "Partial"]
+        );
+        // The typed text itself is not offered
+        assert_eq!(
+            recent.suggestions("Sheet1", "paid", std::iter::empty()),
+            Vec::<String>::new()
+        );
+
+        recent.set_scope(RecentScope::Global);
+        assert_eq!(
+            recent.suggestions("Sheet1", "par", std::iter::empty()),
+            ["Parked", "Partial"]
+        );
+    }
+
+    #[test]
+    fn test_completion_cycles_back_to_the_typed_text() {
+        let mut completion = EntryCompletion::new(
+            "p".to_string(),
+            vec!["Paid".to_string(), "Partial".to_string()],
+        );
+        assert_eq!(completion.step(false), "Paid");
+        assert_eq!(completion.step(false), "Partial");
+        assert_eq!(completion.step(false), "p");
+        assert_eq!(completion.step(true), "Partial");
+
+        let mut empty = EntryCompletion::new("x".to_string(), Vec::new());
+        assert_eq!(empty.step(false), "x");
+    }
+}
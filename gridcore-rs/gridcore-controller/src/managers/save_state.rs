@@ -0,0 +1,230 @@
+use chrono::{DateTime, Utc};
+use rustc_hash::FxHashMap;
+use std::collections::BTreeSet;
+
+/// Which sheets differ from their last saved state.
+///
+/// Each sheet is compared by the hash of its inputs against the hash taken
+/// when it was last saved, not by counting operations, so edits that are
+/// reverted by whatever means leave the sheet clean again.
+#[derive(Debug, Clone, Default)]
+pub struct SaveState {
+    /// Hash of each sheet when it was last saved
+    baselines: FxHashMap<String, u64>,
+    /// Hash of each sheet when it was last looked at
+    current: FxHashMap<String, u64>,
+    /// Sheets differing from their baseline, or without one
+    modified: BTreeSet<String>,
+    /// Saved sheets that no longer exist
+    removed: BTreeSet<String>,
+    unsaved_operations: usize,
+    last_change: Option<DateTime<Utc>>,
+    /// Bumped whenever `modified` or `removed` changes
+    revision: u64,
+}
+
+impl SaveState {
+    /// Start from `hashes` of every sheet as the saved state
+    pub fn new(hashes: impl IntoIterator<Item = (String, u64)>) -> Self {
+        let baselines: FxHashMap<String, u64> = hashes.into_iter().collect();
+        Self {
+            current: baselines.clone(),
+            baselines,
+            ..Self::default()
+        }
+    }
+
+    /// Record that `sheet` may have changed and now hashes to `hash`, or is
+    /// gone when `hash` is `None`. Returns whether any flag changed.
+    pub fn note(&mut self, sheet: &str, hash: Option<u64>, at: DateTime<Utc>) -> bool {
+        if self.current.get(sheet).copied() == hash {
+            return false;
+        }
+        match hash {
+            Some(hash) => self.current.insert(sheet.to_string(), hash),
+            None => self.current.remove(sheet),
+        };
+        self.unsaved_operations += 1;
+        self.last_change = Some(at);
+        self.reflag(sheet)
+    }
+
+    /// Take `hashes` as the saved state of those sheets. A full save also
+    /// forgets saved sheets that have been removed since.
+    pub fn mark_saved(&mut self, hashes: impl IntoIterator<Item = (String, u64)>, full: bool) {
+        if full {
+            self.baselines.clear();
+            self.removed.clear();
+        }
+        for (sheet, hash) in hashes {
+            self.current.insert(sheet.clone(), hash);
+            self.baselines.insert(sheet.clone(), hash);
+            self.reflag(&sheet);
+        }
+        if !self.is_modified() {
+            self.unsaved_operations = 0;
+        }
+        self.revision += 1;
+    }
+
+    /// Recompute the flags of `sheet`. Returns whether any changed.
+    fn reflag(&mut self, sheet: &str) -> bool {
+        let baseline = self.baselines.get(sheet);
+        let current = self.current.get(sheet);
+        let changed = match (baseline, current) {
+            (Some(_), None) => self.modified.remove(sheet) | self.removed.insert(sheet.to_string()),
+            (baseline, Some(current)) if baseline == Some(current) => {
+                self.modified.remove(sheet) | self.removed.remove(sheet)
+            }
+            (_, Some(_)) => self.modified.insert(sheet.to_string()) | self.removed.remove(sheet),
+            (None, None) => self.modified.remove(sheet),
+        };
+        if changed {
+            self.revision += 1;
+            if !self.is_modified() {
+                self.unsaved_operations = 0;
+            }
+        }
+        changed
+    }
+
+    /// Whether anything differs from the last save
+    pub fn is_modified(&self) -> bool {
+        !self.modified.is_empty() || !self.removed.is_empty()
+    }
+
+    pub fn is_sheet_modified(&self, sheet: &str) -> bool {
+        self.modified.contains(sheet)
+    }
+
+    /// Existing sheets that differ from their last save, by name
+    pub fn modified_sheets(&self) -> Vec<String> {
+        self.modified.iter().cloned().collect()
+    }
+
+    /// Changes made since the last save; zero once everything is back to
+    /// the saved state
+    pub fn unsaved_operations(&self) -> usize {
+        self.unsaved_operations
+    }
+
+    /// When a sheet last changed, saved or not
+    pub fn last_change(&self) -> Option<DateTime<Utc>> {
+        self.last_change
+    }
+
+    /// [`edited_ago`] for the last change, as of now
+    pub fn last_change_label(&self) -> Option<String> {
+        self.last_change.map(|then| edited_ago(then, Utc::now()))
+    }
+
+    /// Sheets seen so far, saved or not
+    pub fn sheets(&self) -> impl Iterator<Item = &str> {
+        self.baselines
+            .keys()
+            .chain(self.current.keys())
+            .map(String::as_str)
+    }
+
+    /// Changes whenever the flags do
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+}
+
+/// How long ago `then` was, for the status bar: `Edited just now`,
+/// `Edited 2 min ago`, `Edited 3 h ago` or `Edited 2 days ago`
+pub fn edited_ago(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - then).num_minutes().max(0);
+    match minutes {
+        0 => "Edited just now".to_string(),
+        1..=59 => format!("Edited {} min ago", minutes),
+        60..=1439 => format!("Edited {} h ago", minutes / 60),
+        _ => match minutes / 1440 {
+            1 => "Edited yesterday".to_string(),
+            days => format!("Edited {} days ago", days),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn saved() -> SaveState {
+        SaveState::new([("Sheet1".to_string(), 1), ("Sheet2".to_string(), 2)])
+    }
+
+    #[test]
+    fn test_reverting_clears_the_flag() {
+        let mut state = saved();
+        let now = Utc::now();
+        assert!(!state.is_modified());
+
+        assert!(state.note("Sheet1", Some(10), now));
+        assert!(!state.note("Sheet1", Some(11), now));
+        assert!(state.is_sheet_modified("Sheet1"));
+        assert!(!state.is_sheet_modified("Sheet2"));
+        assert_eq!(state.unsaved_operations(), 2);
+
+        // Back to the saved content, however it got there
+        assert!(state.note("Sheet1", Some(1), now));
+        assert!(!state.is_modified());
+        assert_eq!(state.unsaved_operations(), 0);
+        assert_eq!(state.last_change(), Some(now));
+
+        // Nothing new: not an operation
+        assert!(!state.note("Sheet1", Some(1), now));
+        assert_eq!(state.unsaved_operations(), 0);
+    }
+
+    #[test]
+    fn test_added_and_removed_sheets() {
+        let mut state = saved();
+        let now = Utc::now();
+
+        assert!(state.note("Sheet3", Some(3), now));
+        assert_eq!(state.modified_sheets(), vec!["Sheet3"]);
+        assert!(state.note("Sheet3", None, now));
+        assert!(!state.is_modified());
+
+        // A removed saved sheet has no tab to mark but the document is modified
+        assert!(state.note("Sheet2", None, now));
+        assert!(state.modified_sheets().is_empty());
+        assert!(state.is_modified());
+        assert!(state.note("Sheet2", Some(2), now));
+        assert!(!state.is_modified());
+    }
+
+    #[test]
+    fn test_full_and_partial_saves_move_baselines() {
+        let mut state = saved();
+        let now = Utc::now();
+        state.note("Sheet1", Some(10), now);
+        state.note("Sheet2", Some(20), now);
+
+        state.mark_saved([("Sheet1".to_string(), 10)], false);
+        assert_eq!(state.modified_sheets(), vec!["Sheet2"]);
+        assert_eq!(state.unsaved_operations(), 2);
+        // Sheet1's old content now counts as a change
+        assert!(state.note("Sheet1", Some(1), now));
+
+        state.note("Sheet2", None, now);
+        state.mark_saved([("Sheet1".to_string(), 1)], true);
+        assert!(!state.is_modified());
+        assert_eq!(state.unsaved_operations(), 0);
+        assert!(!state.note("Sheet2", None, now));
+    }
+
+    #[test]
+    fn test_edited_ago() {
+        let now = Utc::now();
+        let ago = |minutes| edited_ago(now - Duration::minutes(minutes), now);
+        assert_eq!(ago(0), "Edited just now");
+        assert_eq!(ago(2), "Edited 2 min ago");
+        assert_eq!(ago(150), "Edited 2 h ago");
+        assert_eq!(ago(1500), "Edited yesterday");
+        assert_eq!(ago(5000), "Edited 3 days ago");
+    }
+}
@@ -0,0 +1,194 @@
+use gridcore_core::references::StructuralOperation;
+use gridcore_core::types::CellAddress;
+use gridcore_core::utils::format_cell_value;
+use gridcore_core::workbook::sheet_reference;
+use gridcore_core::SpreadsheetFacade;
+use serde::{Deserialize, Serialize};
+
+/// A pinned cell, as stored in the UI state snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchEntry {
+    pub sheet: String,
+    pub address: CellAddress,
+    pub label: Option<String>,
+}
+
+impl WatchEntry {
+    pub fn new(sheet: impl Into<String>, address: CellAddress) -> Self {
+        Self {
+            sheet: sheet.into(),
+            address,
+            label: None,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Sheet-qualified address, e.g. `Sheet1!B7` or `'Q1 Data'!B7`
+    pub fn qualified_address(&self) -> String {
+        sheet_reference(&self.sheet, &self.address.to_string())
+    }
+}
+
+/// A watch entry together with the last value seen for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchedCell {
+    pub entry: WatchEntry,
+    /// Formatted value, `None` when the cell is empty or missing
+    pub value: Option<String>,
+    /// The cell was deleted or its sheet removed since it was last seen
+    pub stale: bool,
+    /// Number of value changes observed, used by the UI to flash updated rows
+    pub change_count: u32,
+    seen: bool,
+}
+
+/// A value change reported by [`WatchList::refresh`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchUpdate {
+    pub sheet: String,
+    pub address: CellAddress,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub stale: bool,
+}
+
+/// Cells pinned by the user so their values can be monitored from anywhere
+#[derive(Debug, Clone, Default)]
+pub struct WatchList {
+    cells: Vec<WatchedCell>,
+}
+
+impl WatchList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watch a cell. Returns false if it is already watched.
+    pub fn add(&mut self, entry: WatchEntry) -> bool {
+        if self.position(&entry.sheet, &entry.address).is_some() {
+            return false;
+        }
+
+        self.cells.push(WatchedCell {
+            entry,
+            value: None,
+            stale: false,
+            change_count: 0,
+            seen: false,
+        });
+        true
+    }
+
+    /// Stop watching a cell. Returns false if it was not watched.
+    pub fn remove(&mut self, sheet: &str, address: &CellAddress) -> bool {
+        match self.position(sheet, address) {
+            Some(index) => {
+                self.cells.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn contains(&self, sheet: &str, address: &CellAddress) -> bool {
+        self.position(sheet, address).is_some()
+    }
+
+    pub fn cells(&self) -> &[WatchedCell] {
+        &self.cells
+    }
+
+    /// The persisted part of every watch, in insertion order
+    pub fn entries(&self) -> Vec<WatchEntry> {
+        self.cells.iter().map(|cell| cell.entry.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Follow a sheet rename so existing watches keep pointing at the same cells
+    pub fn rename_sheet(&mut self, old_name: &str, new_name: &str) {
+        for cell in &mut self.cells {
+            if cell.entry.sheet == old_name {
+                cell.entry.sheet = new_name.to_string();
+            }
+        }
+    }
+
+    /// Follow a structural edit of `sheet`: watches move with their cells
+    /// and watches on deleted cells are dropped. Returns how many were.
+    pub fn apply_structural_operation(
+        &mut self,
+        sheet: &str,
+        operation: &StructuralOperation,
+    ) -> usize {
+        let before = self.cells.len();
+        self.cells.retain_mut(|cell| {
+            if cell.entry.sheet != sheet {
+                return true;
+            }
+            match operation.shift_address(&cell.entry.address) {
+                Some(address) => {
+                    cell.entry.address = address;
+                    true
+                }
+                None => false,
+            }
+        });
+        before - self.cells.len()
+    }
+
+    /// Re-read every watched cell and report the ones whose value or stale flag changed
+    pub fn refresh(&mut self, facade: &SpreadsheetFacade) -> Vec<WatchUpdate> {
+        let mut updates = Vec::new();
+
+        for cell in &mut self.cells {
+            let (value, stale) = if facade.has_sheet(&cell.entry.sheet) {
+                match facade.get_cell_in_sheet(&cell.entry.sheet, &cell.entry.address) {
+                    Some(found) => {
+                        cell.seen = true;
+                        (Some(format_cell_value(found.get_computed_value())), false)
+                    }
+                    // An empty cell is only stale if it used to exist
+                    None => (None, cell.seen),
+                }
+            } else {
+                (None, true)
+            };
+
+            if value != cell.value || stale != cell.stale {
+                updates.push(WatchUpdate {
+                    sheet: cell.entry.sheet.clone(),
+                    address: cell.entry.address,
+                    old_value: cell.value.take(),
+                    new_value: value.clone(),
+                    stale,
+                });
+                cell.value = value;
+                cell.stale = stale;
+                cell.change_count += 1;
+            }
+        }
+
+        updates
+    }
+
+    fn position(&self, sheet: &str, address: &CellAddress) -> Option<usize> {
+        self.cells
+            .iter()
+            .position(|cell| cell.entry.sheet == sheet && cell.entry.address == *address)
+    }
+}
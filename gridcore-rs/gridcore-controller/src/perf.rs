@@ -6,6 +6,9 @@ pub const ACTION_DISPATCHES: &str = "gridcore_action_dispatches_total";
 pub const ACTION_DISPATCH_TIME: &str = "gridcore_action_dispatch_duration_seconds";
 pub const VIEWPORT_SCROLLS: &str = "gridcore_viewport_scrolls_total";
 pub const VIEWPORT_RESIZE: &str = "gridcore_viewport_resize_total";
+pub const VIEWPORT_CACHE_HITS: &str = "gridcore_viewport_cache_hits_total";
+pub const VIEWPORT_CACHE_MISSES: &str = "gridcore_viewport_cache_misses_total";
+pub const VIEWPORT_PREFETCHES: &str = "gridcore_viewport_prefetches_total";
 pub const KEYBOARD_EVENTS: &str = "gridcore_keyboard_events_total";
 pub const MOUSE_EVENTS: &str = "gridcore_mouse_events_total";
 pub const MODE_CHANGES: &str = "gridcore_mode_changes_total";
@@ -13,6 +16,10 @@ pub const SELECTION_CHANGES: &str = "gridcore_selection_changes_total";
 pub const SHEET_OPERATIONS: &str = "gridcore_sheet_operations_total";
 pub const FORMULA_BAR_UPDATES: &str = "gridcore_formula_bar_updates_total";
 pub const EVENT_DISPATCH_TIME: &str = "gridcore_event_dispatch_duration_seconds";
+pub const IDLE_ITEMS_PROCESSED: &str = "gridcore_idle_items_processed_total";
+pub const IDLE_CANCELLATIONS: &str = "gridcore_idle_cancellations_total";
+pub const IDLE_WORK_TIME: &str = "gridcore_idle_work_duration_seconds";
+pub const SIGNAL_UPDATES: &str = "gridcore_signal_updates_total";
 
 // Labels for metrics
 pub const ACTION_LABEL: &str = "action";
@@ -0,0 +1,363 @@
+//! The last actions the controller dispatched, for debugging.
+//!
+//! With the `debug` feature the controller records every dispatched action
+//! along with the mode before and after it, which way it was handled and
+//! the UI state on either side. Recording only clones what it needs into a
+//! preallocated ring buffer; diffs and JSON are made when someone looks.
+//! A [`BugReport`] bundles the logged actions with the workbook and state
+//! they started from, so the session can be replayed elsewhere.
+
+use super::{Action, SpreadsheetMode, StateDiff, StateSnapshot, UIState};
+use gridcore_core::csv::{migrate, Sidecar};
+use gridcore_core::workbook::Determinism;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Entries kept unless the log is created with another capacity
+pub const ACTION_LOG_CAPACITY: usize = 256;
+
+/// Version of the [`BugReport`] JSON layout
+pub const BUG_REPORT_VERSION: u32 = 1;
+
+/// How the controller handled a dispatched action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HandlerPath {
+    /// Refused because the workbook is read-only
+    Refused,
+    /// Dropped, or failed, in a plugin before it was applied
+    Vetoed,
+    /// Applied with no plugins registered
+    Direct,
+    /// Passed through the plugins and applied
+    ViaPlugins,
+}
+
+/// One dispatched action
+#[derive(Debug, Clone)]
+pub struct ActionLogEntry {
+    /// Position among all actions logged since the log was last cleared
+    pub sequence: u64,
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: i64,
+    pub action: Action,
+    pub mode_before: SpreadsheetMode,
+    pub mode_after: SpreadsheetMode,
+    pub path: HandlerPath,
+    /// The error the action failed with
+    pub error: Option<String>,
+    before: UIState,
+    after: UIState,
+}
+
+impl ActionLogEntry {
+    /// What the action changed in the UI state
+    pub fn state_diff(&self) -> StateDiff {
+        StateDiff::create(&self.before, &self.after)
+    }
+
+    /// [`Self::state_diff`] as pretty JSON, for display
+    pub fn state_diff_json(&self) -> String {
+        serde_json::to_string_pretty(&self.state_diff()).unwrap_or_default()
+    }
+}
+
+/// The workbook and UI state the first logged action ran against
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogBaseline {
+    pub state: StateSnapshot,
+    /// The active sheet as CSV, formulas as text
+    pub csv: String,
+    /// Formats the CSV cannot hold
+    #[serde(
+        default,
+        deserialize_with = "migrate::deserialize_sidecar",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sidecar: Option<Sidecar>,
+    /// Where deterministic mode stood, so a replay draws the same numbers
+    /// and reads the same time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub determinism: Option<Determinism>,
+}
+
+/// Ring buffer of the last dispatched actions
+#[derive(Debug)]
+pub struct ActionLog {
+    entries: VecDeque<ActionLogEntry>,
+    capacity: usize,
+    next_sequence: u64,
+    baseline: Option<LogBaseline>,
+}
+
+impl Default for ActionLog {
+    fn default() -> Self {
+        Self::new(ACTION_LOG_CAPACITY)
+    }
+}
+
+impl ActionLog {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            next_sequence: 0,
+            baseline: None,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries from oldest to newest
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &ActionLogEntry> {
+        self.entries.iter()
+    }
+
+    /// The entry with `sequence`, while it is still kept
+    pub fn get(&self, sequence: u64) -> Option<&ActionLogEntry> {
+        let first = self.entries.front()?.sequence;
+        let index = usize::try_from(sequence.checked_sub(first)?).ok()?;
+        self.entries.get(index)
+    }
+
+    /// Actions pushed out of the buffer since the log was last cleared
+    pub fn dropped(&self) -> u64 {
+        self.next_sequence - self.entries.len() as u64
+    }
+
+    /// Whether a baseline is needed before the next action is recorded
+    pub fn needs_baseline(&self) -> bool {
+        self.baseline.is_none()
+    }
+
+    pub fn set_baseline(&mut self, baseline: LogBaseline) {
+        self.baseline = Some(baseline);
+    }
+
+    pub fn baseline(&self) -> Option<&LogBaseline> {
+        self.baseline.as_ref()
+    }
+
+    /// Log an action, pushing out the oldest entry when full. Returns the
+    /// sequence number of the new entry.
+    pub fn record(
+        &mut self,
+        timestamp_ms: i64,
+        action: Action,
+        path: HandlerPath,
+        error: Option<String>,
+        before: UIState,
+        after: UIState,
+    ) -> u64 {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.entries.push_back(ActionLogEntry {
+            sequence,
+            timestamp_ms,
+            action,
+            mode_before: before.spreadsheet_mode(),
+            mode_after: after.spreadsheet_mode(),
+            path,
+            error,
+            before,
+            after,
+        });
+        sequence
+    }
+
+    /// Forget every entry and the baseline; the next action starts afresh
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.next_sequence = 0;
+        self.baseline = None;
+    }
+
+    /// Bundle the log for replay, with `content_hash` the hash of the
+    /// active sheet now. `None` before anything was logged.
+    pub fn bug_report(&self, content_hash: u64) -> Option<BugReport> {
+        Some(BugReport {
+            version: BUG_REPORT_VERSION,
+            initial: self.baseline.clone()?,
+            actions: self
+                .entries
+                .iter()
+                .map(|entry| entry.action.clone())
+                .collect(),
+            dropped: self.dropped(),
+            content_hash: format_content_hash(content_hash),
+        })
+    }
+}
+
+/// A session to replay: the workbook and state it started from, the
+/// actions dispatched and the content hash they led to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BugReport {
+    /// [`BUG_REPORT_VERSION`] of the writer
+    pub version: u32,
+    pub initial: LogBaseline,
+    pub actions: Vec<Action>,
+    /// Actions that ran after `initial` but were pushed out of the log;
+    /// replaying a report with any does not reach `content_hash`
+    #[serde(default)]
+    pub dropped: u64,
+    /// Content hash of the active sheet at the end, see
+    /// [`format_content_hash`]
+    pub content_hash: String,
+}
+
+impl BugReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid bug report: {}", e))
+    }
+}
+
+/// A sheet's content hash as hex text, which survives JSON readers that
+/// hold numbers as doubles
+pub fn format_content_hash(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{CoreState, EditMode, ViewportInfo};
+    use gridcore_core::types::CellAddress;
+
+    fn core(col: u32) -> CoreState {
+        let viewport = ViewportInfo {
+            start_row: 0,
+            start_col: 0,
+            rows: 20,
+            cols: 10,
+        };
+        CoreState::new(CellAddress::new(col, 0), viewport)
+    }
+
+    fn navigation(col: u32) -> UIState {
+        UIState::Navigation {
+            core: core(col),
+            selection: None,
+            modal: None,
+        }
+    }
+
+    fn editing(value: &str) -> UIState {
+        UIState::Editing {
+            core: core(0),
+            value: value.to_string(),
+            cursor_pos: value.len(),
+            mode: EditMode::Insert,
+            visual_selection: None,
+            insert_variant: None,
+        }
+    }
+
+    fn log_moves(log: &mut ActionLog, count: u32) {
+        for col in 0..count {
+            log.record(
+                0,
+                Action::UpdateCursor {
+                    cursor: CellAddress::new(col + 1, 0),
+                },
+                HandlerPath::Direct,
+                None,
+                navigation(col),
+                navigation(col + 1),
+            );
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_the_newest_entries() {
+        let mut log = ActionLog::new(3);
+        log_moves(&mut log, 2);
+        assert_eq!(log.len(), 2);
+        assert_eq!(log.dropped(), 0);
+
+        log_moves(&mut log, 3);
+        assert_eq!(log.len(), 3);
+        assert_eq!(log.dropped(), 2);
+        let sequences: Vec<u64> = log.entries().map(|entry| entry.sequence).collect();
+        assert_eq!(sequences, [2, 3, 4]);
+        assert!(log.get(1).is_none());
+        assert_eq!(log.get(4).unwrap().sequence, 4);
+        assert!(log.get(5).is_none());
+
+        log.clear();
+        assert!(log.is_empty());
+        assert!(log.needs_baseline());
+        log_moves(&mut log, 1);
+        assert_eq!(log.entries().next().unwrap().sequence, 0);
+        assert_eq!(log.dropped(), 0);
+    }
+
+    #[test]
+    fn test_entries_derive_modes_and_diffs_from_their_states() {
+        let mut log = ActionLog::default();
+        log.record(
+            0,
+            Action::StartEditing {
+                edit_mode: None,
+                initial_value: Some("42".to_string()),
+                cursor_position: None,
+            },
+            HandlerPath::ViaPlugins,
+            None,
+            navigation(0),
+            editing("42"),
+        );
+        let entry = log.entries().next().unwrap();
+        assert_eq!(entry.mode_before, SpreadsheetMode::Navigation);
+        assert_eq!(entry.mode_after, SpreadsheetMode::Insert);
+        assert!(matches!(
+            entry.state_diff(),
+            StateDiff::Full(UIState::Editing { .. })
+        ));
+        assert!(entry.state_diff_json().contains("\"42\""));
+    }
+
+    #[test]
+    fn test_bug_report_needs_a_baseline_and_round_trips() {
+        let mut log = ActionLog::new(2);
+        log_moves(&mut log, 3);
+        assert!(log.bug_report(7).is_none());
+
+        log.set_baseline(LogBaseline {
+            state: StateSnapshot::new(navigation(0), "Sheet1", false, ""),
+            csv: "1,=A1+1\n".to_string(),
+            sidecar: None,
+            determinism: None,
+        });
+        let report = log.bug_report(0xabc).unwrap();
+        assert_eq!(report.actions.len(), 2);
+        assert_eq!(report.dropped, 1);
+        assert_eq!(report.content_hash, "0000000000000abc");
+
+        let json = report.to_json();
+        let read = BugReport::from_json(&json).unwrap();
+        assert_eq!(read.initial, report.initial);
+        assert_eq!(read.to_json(), json);
+        assert!(BugReport::from_json("{}").is_err());
+    }
+}
@@ -1,8 +1,21 @@
+use crate::behaviors::case_change::CaseChange;
+use crate::behaviors::cell_transform::CellTransform;
+use crate::behaviors::paste::PasteOptions;
+use crate::behaviors::resize::ResizePreview;
 use crate::state::{
     DeleteType, InsertMode, InsertPosition, InsertType, ParsedBulkCommand, ResizeMoveDirection,
     ResizeTarget, Selection, ViewportInfo, VisualMode,
 };
-use gridcore_core::types::CellAddress;
+use crate::theme::Palette;
+use gridcore_core::{
+    domain::{CellFormat, StyleRemoval},
+    fill::running::RunningAggregate,
+    formula::CellRange,
+    lint::LintRule,
+    pivot::PivotConfig,
+    types::CellAddress,
+    workbook::NameScope,
+};
 use serde::{Deserialize, Serialize};
 
 // Slimmed down Action enum - removed redundant actions that can be handled directly
@@ -113,6 +126,259 @@ pub enum Action {
         command: ParsedBulkCommand,
     },
 
+    // Formula auditing
+    TracePrecedents,
+    TraceDependents,
+    ClearTraceArrows,
+
+    // Formula linting
+    /// Lint the formulas in `range`, or the whole active sheet
+    Lint {
+        range: Option<CellRange>,
+    },
+    ClearLintWarnings,
+    /// Move the cursor to the next lint warning after it, wrapping around
+    NextLintWarning,
+    PreviousLintWarning,
+    SetLintRule {
+        rule: LintRule,
+        enabled: bool,
+    },
+
+    // Loading
+    /// Hide the report of the last import
+    DismissLoadReport,
+
+    // Watch window
+    AddWatch {
+        address: CellAddress,
+        sheet: Option<String>,
+        label: Option<String>,
+    },
+    RemoveWatch {
+        address: CellAddress,
+        sheet: Option<String>,
+    },
+
+    // Formatting
+    SetColumnFormat {
+        column: u32,
+        format: Option<CellFormat>,
+    },
+    /// Make cells refer to a named style: `ranges`, or the selection (the
+    /// cursor without one) when `None`
+    ApplyStyle {
+        name: String,
+        ranges: Option<Vec<CellRange>>,
+    },
+    /// Define a named style or change the format of an existing one
+    DefineStyle {
+        name: String,
+        format: CellFormat,
+    },
+    RemoveStyle {
+        name: String,
+        removal: StyleRemoval,
+    },
+
+    // Pivots
+    CreatePivot {
+        source: CellRange,
+        anchor: CellAddress,
+        config: PivotConfig,
+    },
+    RefreshPivot {
+        anchor: CellAddress,
+    },
+
+    // Defined names
+    /// Define or redefine a named constant, e.g. `TaxRate` as `0.21`
+    DefineConstant {
+        name: String,
+        definition: String,
+        scope: NameScope,
+    },
+    RemoveConstant {
+        name: String,
+        scope: NameScope,
+    },
+
+    // Case operators
+    /// Change the case of the text cells in `ranges`
+    ChangeCase {
+        ranges: Vec<CellRange>,
+        change: CaseChange,
+    },
+
+    // Transforms
+    /// Transform the values of the visible cells of the selection, or of
+    /// the cursor cell without one
+    TransformSelection {
+        transform: CellTransform,
+    },
+    /// Write a running aggregate of the selected column into the same rows
+    /// of `target_col`, the next column when `None`
+    RunningAggregate {
+        aggregate: RunningAggregate,
+        target_col: Option<u32>,
+        values_only: bool,
+    },
+
+    // Paste
+    /// Paste text at the cursor with the controller's paste options
+    Paste {
+        text: String,
+    },
+    /// Paste text at the cursor with options for this paste only
+    PasteSpecial {
+        text: String,
+        options: PasteOptions,
+    },
+    /// Paste what the system clipboard holds: its text, and the gridcore
+    /// payload when it carries one
+    PasteClipboard {
+        text: String,
+        rich: Option<String>,
+    },
+    /// Apply a paste that was held back for confirmation
+    ConfirmPaste,
+    CancelPaste,
+
+    // Dragging ranges
+    /// Pick up the selection by the cell `grab` to drag it elsewhere
+    StartRangeDrag {
+        grab: CellAddress,
+    },
+    /// Follow the pointer at `(x, y)` in cell-area coordinates, scrolling
+    /// when it is near the edge
+    UpdateRangeDrag {
+        x: f64,
+        y: f64,
+    },
+    /// Drop the dragged block, copying it rather than moving it if `copy`
+    DropRange {
+        copy: bool,
+    },
+    /// Apply a drop that was held back for confirmation
+    ConfirmRangeDrop,
+    CancelRangeDrag,
+
+    // Quick totals
+    /// Write `=SUM` totals next to the selection
+    QuickSum,
+    /// Write `=AVERAGE` totals next to the selection
+    QuickAverage,
+    /// Write `=COUNT` totals next to the selection
+    QuickCount,
+
+    // Hidden rows and columns
+    /// Hide the rows of the selection, or its columns if `columns`
+    HideSelection {
+        columns: bool,
+    },
+    /// Show the hidden rows of the selection, or its columns if `columns`
+    UnhideSelection {
+        columns: bool,
+    },
+    /// Give every column in `indices` the width `size`, or every row the
+    /// height unless `columns`
+    SetSizes {
+        columns: bool,
+        indices: Vec<u32>,
+        size: f64,
+    },
+    /// Narrow the selection to its visible cells
+    SelectVisibleCells,
+    /// Clear the visible cells of the selection, or the cursor cell
+    ClearSelectedCells,
+    /// Copy the top row of the selection into the rows below it
+    FillDown,
+
+    // Folds
+    /// Fold rows `start_row` to `end_row`
+    CreateFold {
+        start_row: u32,
+        end_row: u32,
+    },
+    /// Remove the innermost fold holding `row`
+    DeleteFold {
+        row: u32,
+    },
+    /// Open one level of the folds touching the rows, or all of them
+    OpenFolds {
+        start_row: u32,
+        end_row: u32,
+        all: bool,
+    },
+    /// Close one level of the folds touching the rows, or all of them
+    CloseFolds {
+        start_row: u32,
+        end_row: u32,
+        all: bool,
+    },
+    /// Open the fold holding `row` if it is closed, else close it
+    ToggleFold {
+        row: u32,
+    },
+    /// Move the cursor to the start of the next fold down
+    NextFold,
+    /// Move the cursor to the end of the previous fold up
+    PreviousFold,
+    /// Width of the fold column in the gutter, in characters
+    SetFoldColumn {
+        width: u8,
+    },
+
+    // View
+    /// Colors the grid, markers and notices are drawn with
+    SetPalette {
+        palette: Palette,
+    },
+    /// How a resize shows before it is committed
+    SetResizePreview {
+        preview: ResizePreview,
+    },
+
+    // Charts
+    /// Publish chart data for `ranges`, or for the selection when `None`
+    ShowChart {
+        ranges: Option<Vec<CellRange>>,
+    },
+
+    // External data
+    /// Fetch the data of every FETCH formula again
+    RefreshExternalData,
+
+    // Calculation
+    /// Recalculate the stale formulas of the active sheet, or of a range
+    RecalculateStale {
+        range: Option<CellRange>,
+    },
+    /// Turn automatic calculation of the active sheet on or off
+    SetCalculationEnabled {
+        enabled: bool,
+    },
+
+    // Diagnostics
+    /// Check the active sheet's dependency graph and indexes, repairing
+    /// what is wrong when `repair` is set
+    CheckHealth {
+        repair: bool,
+    },
+    /// Give unused memory back and report what each part of the document
+    /// holds
+    CompactMemory,
+
+    // Navigation
+    GotoCell {
+        address: CellAddress,
+        sheet: Option<String>,
+    },
+    /// Jump to the first error cell of a sheet, if it has any
+    GotoFirstError {
+        sheet: String,
+    },
+
     // Undo/Redo
     Undo,
     UndoLine,
@@ -152,3 +418,59 @@ pub enum Action {
         new_mode: VisualMode,
     },
 }
+
+impl Action {
+    /// Whether the action changes the workbook: its cells, formats, names
+    /// or sheets, or its history through undo and redo. Read-only
+    /// controllers refuse these; moving around, selecting, folding, hiding
+    /// and other view changes stay allowed.
+    pub fn changes_workbook(&self) -> bool {
+        matches!(
+            self,
+            Action::StartEditing { .. }
+                | Action::SubmitCellEdit { .. }
+                | Action::UpdateEditingValue { .. }
+                | Action::InsertCharacterAtCursor { .. }
+                | Action::DeleteCharacterAtCursor { .. }
+                | Action::HandleEditingKey { .. }
+                | Action::SubmitFormulaBar
+                | Action::AddSheet { .. }
+                | Action::RemoveSheet { .. }
+                | Action::RenameSheet { .. }
+                | Action::StartInsert { .. }
+                | Action::ConfirmInsert
+                | Action::StartDelete { .. }
+                | Action::ConfirmDelete
+                | Action::StartBulkOperation { .. }
+                | Action::ExecuteBulkOperation
+                | Action::BulkCommand { .. }
+                | Action::SetColumnFormat { .. }
+                | Action::ApplyStyle { .. }
+                | Action::DefineStyle { .. }
+                | Action::RemoveStyle { .. }
+                | Action::CreatePivot { .. }
+                | Action::RefreshPivot { .. }
+                | Action::DefineConstant { .. }
+                | Action::RemoveConstant { .. }
+                | Action::ChangeCase { .. }
+                | Action::TransformSelection { .. }
+                | Action::RunningAggregate { .. }
+                | Action::Paste { .. }
+                | Action::PasteSpecial { .. }
+                | Action::PasteClipboard { .. }
+                | Action::ConfirmPaste
+                | Action::StartRangeDrag { .. }
+                | Action::DropRange { .. }
+                | Action::ConfirmRangeDrop
+                | Action::QuickSum
+                | Action::QuickAverage
+                | Action::QuickCount
+                | Action::ClearSelectedCells
+                | Action::FillDown
+                | Action::Undo
+                | Action::UndoLine
+                | Action::Redo
+                | Action::EnterInsertMode { .. }
+        )
+    }
+}
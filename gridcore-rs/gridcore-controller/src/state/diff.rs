@@ -1,9 +1,10 @@
-use super::{EditMode, InsertMode, Selection, UIState, ViewportInfo, VisualMode};
+use super::{EditMode, InsertMode, NavigationModal, Selection, UIState, ViewportInfo, VisualMode};
+use crate::managers::{RecentEntry, WatchEntry};
 use gridcore_core::types::CellAddress;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 /// Represents a change in the UI state
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StateDiff {
     /// Complete state replacement (used for first entry or major transitions)
     Full(UIState),
@@ -12,36 +13,91 @@ pub enum StateDiff {
 }
 
 /// Tracks specific changes between states
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+///
+/// Fields that did not change are `None` and left out of the JSON, whose
+/// field names are camelCase like the rest of the state schema.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct StateChanges {
     /// Type transition (e.g., Navigation -> Editing)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub state_type_changed: Option<StateTypeChange>,
     /// Cursor position change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cursor_changed: Option<CellAddress>,
     /// Viewport change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub viewport_changed: Option<ViewportInfo>,
     /// Selection change
+    #[serde(
+        default,
+        deserialize_with = "changed_to",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub selection_changed: Option<Option<Selection>>,
+    /// Modal change, e.g. entering command mode
+    #[serde(
+        default,
+        deserialize_with = "changed_to",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub modal_changed: Option<Option<NavigationModal>>,
+    /// Watch list change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watches_changed: Option<Vec<WatchEntry>>,
+    /// Recent entries change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recent_entries_changed: Option<Vec<RecentEntry>>,
     /// Editing value change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub editing_value_changed: Option<String>,
     /// Cursor position in text change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub text_cursor_changed: Option<usize>,
     /// Edit mode change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub edit_mode_changed: Option<EditMode>,
     /// Visual mode change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub visual_mode_changed: Option<VisualMode>,
     /// Visual anchor change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub anchor_changed: Option<CellAddress>,
     /// Visual start position change
+    #[serde(
+        default,
+        deserialize_with = "changed_to",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub visual_start_changed: Option<Option<usize>>,
     /// Visual type change
+    #[serde(
+        default,
+        deserialize_with = "changed_to",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub visual_type_changed: Option<Option<VisualMode>>,
     /// Edit variant change
+    #[serde(
+        default,
+        deserialize_with = "changed_to",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub edit_variant_changed: Option<Option<InsertMode>>,
 }
 
+/// Reads `null` as a change to nothing, `Some(None)`, where a missing field
+/// stays `None`
+fn changed_to<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 /// Represents a change in the state type
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StateTypeChange {
     ToNavigation,
     ToVisual,
@@ -88,7 +144,13 @@ impl StateDiff {
                     changes.selection_changed = Some(new_selection.clone());
                 }
                 if old_modal != new_modal {
-                    // Handle modal changes if needed
+                    changes.modal_changed = Some(new_modal.clone());
+                }
+                if old_core.watches != new_core.watches {
+                    changes.watches_changed = Some(new_core.watches.clone());
+                }
+                if old_core.recent_entries != new_core.recent_entries {
+                    changes.recent_entries_changed = Some(new_core.recent_entries.clone());
                 }
             }
             (
@@ -115,6 +177,12 @@ impl StateDiff {
                 if old_core.viewport != new_core.viewport {
                     changes.viewport_changed = Some(new_core.viewport);
                 }
+                if old_core.watches != new_core.watches {
+                    changes.watches_changed = Some(new_core.watches.clone());
+                }
+                if old_core.recent_entries != new_core.recent_entries {
+                    changes.recent_entries_changed = Some(new_core.recent_entries.clone());
+                }
                 if old_mode != new_mode {
                     changes.edit_mode_changed = Some(*new_mode);
                 }
@@ -174,6 +242,9 @@ impl StateChanges {
             || self.cursor_changed.is_some()
             || self.viewport_changed.is_some()
             || self.selection_changed.is_some()
+            || self.modal_changed.is_some()
+            || self.watches_changed.is_some()
+            || self.recent_entries_changed.is_some()
             || self.editing_value_changed.is_some()
             || self.text_cursor_changed.is_some()
             || self.edit_mode_changed.is_some()
@@ -190,7 +261,9 @@ impl StateChanges {
 
         match &mut result {
             UIState::Navigation {
-                core, selection, ..
+                core,
+                selection,
+                modal,
             } => {
                 if let Some(new_cursor) = self.cursor_changed {
                     core.cursor = new_cursor;
@@ -198,9 +271,18 @@ impl StateChanges {
                 if let Some(new_viewport) = self.viewport_changed {
                     core.viewport = new_viewport;
                 }
+                if let Some(ref new_watches) = self.watches_changed {
+                    core.watches = new_watches.clone();
+                }
+                if let Some(ref new_entries) = self.recent_entries_changed {
+                    core.recent_entries = new_entries.clone();
+                }
                 if let Some(ref new_selection) = self.selection_changed {
                     *selection = new_selection.clone();
                 }
+                if let Some(ref new_modal) = self.modal_changed {
+                    *modal = new_modal.clone();
+                }
             }
             UIState::Editing {
                 core,
@@ -216,6 +298,12 @@ impl StateChanges {
                 if let Some(new_viewport) = self.viewport_changed {
                     core.viewport = new_viewport;
                 }
+                if let Some(ref new_watches) = self.watches_changed {
+                    core.watches = new_watches.clone();
+                }
+                if let Some(ref new_entries) = self.recent_entries_changed {
+                    core.recent_entries = new_entries.clone();
+                }
                 if let Some(new_mode) = self.edit_mode_changed {
                     *mode = new_mode;
                 }
@@ -227,7 +315,9 @@ impl StateChanges {
                 }
                 // Handle visual selection changes
                 if let Some(new_vstart) = self.visual_start_changed {
-                    if let Some(ref mut visual) = visual_selection {
+                    if new_vstart.is_none() {
+                        *visual_selection = None;
+                    } else if let Some(ref mut visual) = visual_selection {
                         visual.start = new_vstart.unwrap_or(0);
                     } else if let Some(start) = new_vstart {
                         *visual_selection = Some(crate::state::VisualSelection {
@@ -1,13 +1,20 @@
+pub mod action_log;
 pub mod actions;
 pub mod context;
 pub mod diff;
+pub mod snapshot;
 pub mod spreadsheet;
 
 #[cfg(test)]
 mod refactoring_tests;
 
+pub use action_log::{
+    format_content_hash, ActionLog, ActionLogEntry, BugReport, HandlerPath, LogBaseline,
+};
 pub use actions::Action;
 pub use context::StateContext;
+pub use diff::{StateChanges, StateDiff};
+pub use snapshot::{StateSnapshot, STATE_SCHEMA_VERSION};
 pub use spreadsheet::{
     BulkOperationStatus, CoreState, DeleteConfig, DeleteType, EditMode, InsertConfig, InsertMode,
     InsertPosition, InsertType, ModalKind, NavigationModal, ParsedBulkCommand, ResizeMoveDirection,
@@ -0,0 +1,262 @@
+//! The controller's state as one serializable structure, for frontends
+//! outside this crate.
+//!
+//! A frontend takes a [`StateSnapshot`] from
+//! `SpreadsheetController::get_state_snapshot` when it starts and, once it
+//! called `enable_state_diffs`, follows `SpreadsheetEvent::UIStateChanged`
+//! events carrying a [`StateDiff`](super::diff::StateDiff) of what changed
+//! since. The JSON field names are part of the schema: renaming or removing
+//! one bumps [`STATE_SCHEMA_VERSION`], adding an optional one does not.
+//! `fixtures/state_snapshot.json` holds a snapshot of every state shape,
+//! regenerated by the schema test, for frontends in other languages to
+//! generate their types from.
+
+use super::{SpreadsheetMode, UIState};
+use serde::{Deserialize, Serialize};
+
+/// Version of the snapshot and diff JSON layout
+pub const STATE_SCHEMA_VERSION: u32 = 1;
+
+/// Everything a frontend needs to draw the controller's state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateSnapshot {
+    /// [`STATE_SCHEMA_VERSION`] of the writer
+    pub version: u32,
+    /// Mode to show in the status line, derived from `state`
+    pub mode: SpreadsheetMode,
+    /// Sheet the cursor is on
+    pub active_sheet: String,
+    /// Whether changes to the workbook are refused
+    pub read_only: bool,
+    /// Text of the formula bar
+    pub formula_bar: String,
+    /// Cursor, viewport, watches, selection and the mode's own data
+    pub state: UIState,
+}
+
+impl StateSnapshot {
+    pub fn new(
+        state: UIState,
+        active_sheet: impl Into<String>,
+        read_only: bool,
+        formula_bar: impl Into<String>,
+    ) -> Self {
+        Self {
+            version: STATE_SCHEMA_VERSION,
+            mode: state.spreadsheet_mode(),
+            active_sheet: active_sheet.into(),
+            read_only,
+            formula_bar: formula_bar.into(),
+            state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::managers::{RecentEntry, WatchEntry};
+    use crate::state::diff::{StateChanges, StateDiff};
+    use crate::state::{
+        BulkOperationStatus, CoreState, DeleteConfig, DeleteType, EditMode, InsertConfig,
+        InsertMode, InsertPosition, InsertType, NavigationModal, ParsedBulkCommand, ResizeSizes,
+        ResizeTarget, Selection, SelectionType, ViewportInfo, VisualMode, VisualSelection,
+    };
+    use gridcore_core::types::CellAddress;
+
+    const FIXTURE: &str = "src/state/fixtures/state_snapshot.json";
+
+    fn core() -> CoreState {
+        let mut core = CoreState::new(
+            CellAddress::new(2, 4),
+            ViewportInfo {
+                start_row: 0,
+                start_col: 0,
+                rows: 30,
+                cols: 10,
+            },
+        );
+        core.watches
+            .push(WatchEntry::new("Sheet1", CellAddress::new(1, 1)).with_label("Total"));
+        core.recent_entries.push(RecentEntry {
+            sheet: "Sheet1".to_string(),
+            text: "Paid".to_string(),
+            uses: 2,
+        });
+        core
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Selection {
+        Selection {
+            selection_type: SelectionType::Range {
+                start: CellAddress::new(start.0, start.1),
+                end: CellAddress::new(end.0, end.1),
+            },
+            anchor: Some(CellAddress::new(start.0, start.1)),
+        }
+    }
+
+    /// One state of every shape: each modal, each selection type and each
+    /// editing mode
+    fn examples() -> Vec<UIState> {
+        let navigation = |selection, modal| UIState::Navigation {
+            core: core(),
+            selection,
+            modal,
+        };
+        let selections = [
+            SelectionType::Cell {
+                address: CellAddress::new(2, 4),
+            },
+            SelectionType::Column {
+                columns: vec![1, 2],
+            },
+            SelectionType::Row { rows: vec![3] },
+            SelectionType::Multi {
+                selections: vec![range((0, 0), (1, 1)), range((4, 4), (5, 6))],
+            },
+        ];
+        let modals = [
+            NavigationModal::Command {
+                value: "sort desc".to_string(),
+            },
+            NavigationModal::Visual {
+                mode: VisualMode::Block,
+                anchor: CellAddress::new(0, 0),
+                selection: range((0, 0), (2, 4)),
+            },
+            NavigationModal::Resize {
+                target: ResizeTarget::Column { index: 2 },
+                sizes: ResizeSizes {
+                    original_size: 100,
+                    current_size: 120,
+                    initial_position: 340.0,
+                    current_position: 360.0,
+                    resize_index: 2,
+                },
+            },
+            NavigationModal::Insert {
+                config: InsertConfig {
+                    insert_type: InsertType::Row,
+                    position: InsertPosition::After,
+                    reference: 4,
+                    count: 2,
+                    target_index: 5,
+                },
+            },
+            NavigationModal::Delete {
+                config: DeleteConfig {
+                    delete_type: DeleteType::Column,
+                    targets: vec![2],
+                    selection: vec![2],
+                    confirmation_pending: true,
+                },
+            },
+            NavigationModal::BulkOperation {
+                command: ParsedBulkCommand::FindReplace {
+                    pattern: "a".to_string(),
+                    replacement: "b".to_string(),
+                    global: true,
+                    case_sensitive: false,
+                },
+                status: BulkOperationStatus::Previewing,
+            },
+        ];
+
+        let mut states = vec![navigation(None, None)];
+        states.extend(selections.into_iter().map(|selection_type| {
+            navigation(
+                Some(Selection {
+                    selection_type,
+                    anchor: None,
+                }),
+                None,
+            )
+        }));
+        states.extend(
+            modals
+                .into_iter()
+                .map(|modal| navigation(None, Some(modal))),
+        );
+        states.push(UIState::Editing {
+            core: core(),
+            value: "=SUM(A1:A4)".to_string(),
+            cursor_pos: 11,
+            mode: EditMode::Insert,
+            visual_selection: None,
+            insert_variant: Some(InsertMode::A),
+        });
+        states.push(UIState::Editing {
+            core: core(),
+            value: "hello world".to_string(),
+            cursor_pos: 3,
+            mode: EditMode::Visual,
+            visual_selection: Some(VisualSelection {
+                start: 0,
+                mode: VisualMode::Character,
+            }),
+            insert_variant: None,
+        });
+        states
+    }
+
+    fn snapshots() -> Vec<StateSnapshot> {
+        examples()
+            .into_iter()
+            .map(|state| StateSnapshot::new(state, "Sheet1", false, "42"))
+            .collect()
+    }
+
+    #[test]
+    fn test_snapshots_round_trip() {
+        for snapshot in snapshots() {
+            let json = serde_json::to_string(&snapshot).unwrap();
+            let restored: StateSnapshot = serde_json::from_str(&json).unwrap();
+            assert_eq!(restored, snapshot, "{json}");
+        }
+    }
+
+    #[test]
+    fn test_diffs_round_trip_and_apply() {
+        let states = examples();
+        for old in &states {
+            for new in &states {
+                let diff = StateDiff::create(old, new);
+                let json = serde_json::to_string(&diff).unwrap();
+                let restored: StateDiff = serde_json::from_str(&json).unwrap();
+                assert_eq!(restored, diff);
+                assert_eq!(&restored.apply(old), new, "{json}");
+            }
+        }
+
+        // Unchanged fields are left out of the JSON
+        let moved = StateDiff::Partial(StateChanges {
+            cursor_changed: Some(CellAddress::new(0, 1)),
+            ..StateChanges::default()
+        });
+        assert_eq!(
+            serde_json::to_value(&moved).unwrap(),
+            serde_json::json!({"Partial": {"cursorChanged": {"col": 0, "row": 1}}})
+        );
+    }
+
+    /// The published fixture matches what this version writes. Run with
+    /// `GRIDCORE_UPDATE_FIXTURES=1` after a schema change to rewrite it.
+    #[test]
+    fn test_schema_fixture_is_current() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURE);
+        let json = serde_json::to_string_pretty(&snapshots()).unwrap() + "\n";
+        if std::env::var_os("GRIDCORE_UPDATE_FIXTURES").is_some() {
+            std::fs::write(&path, &json).unwrap();
+        }
+        let published = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            published == json,
+            "{FIXTURE} is out of date; rerun with GRIDCORE_UPDATE_FIXTURES=1 and bump \
+             STATE_SCHEMA_VERSION if a field was renamed or removed"
+        );
+        let restored: Vec<StateSnapshot> = serde_json::from_str(&published).unwrap();
+        assert_eq!(restored, snapshots());
+    }
+}
@@ -1,3 +1,6 @@
+use crate::managers::{RecentEntry, WatchEntry};
+use gridcore_core::formula::CellRange;
+use gridcore_core::references::StructuralOperation;
 use gridcore_core::types::CellAddress;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
@@ -10,11 +13,22 @@ use std::borrow::Cow;
 pub struct CoreState {
     pub cursor: CellAddress,
     pub viewport: ViewportInfo,
+    /// Cells pinned in the watch window
+    #[serde(default)]
+    pub watches: Vec<WatchEntry>,
+    /// Committed entries offered again while typing, most recent first
+    #[serde(default)]
+    pub recent_entries: Vec<RecentEntry>,
 }
 
 impl CoreState {
     pub fn new(cursor: CellAddress, viewport: ViewportInfo) -> Self {
-        Self { cursor, viewport }
+        Self {
+            cursor,
+            viewport,
+            watches: Vec::new(),
+            recent_entries: Vec::new(),
+        }
     }
 }
 
@@ -65,6 +79,59 @@ pub struct Selection {
     pub anchor: Option<CellAddress>,
 }
 
+impl Selection {
+    /// The selection after `operation`, following the cells it covers;
+    /// `None` once all of them were deleted
+    pub fn shifted(&self, operation: &StructuralOperation) -> Option<Selection> {
+        let line = |address: CellAddress| operation.shift_address(&address);
+        let selection_type = match &self.selection_type {
+            SelectionType::Cell { address } => SelectionType::Cell {
+                address: line(*address)?,
+            },
+            SelectionType::Range { start, end } => {
+                let range = operation.shift_range(&CellRange::new(*start, *end))?;
+                SelectionType::Range {
+                    start: range.start,
+                    end: range.end,
+                }
+            }
+            SelectionType::Column { columns } => SelectionType::Column {
+                columns: non_empty(
+                    columns
+                        .iter()
+                        .filter_map(|&col| Some(line(CellAddress::new(col, 0))?.col))
+                        .collect(),
+                )?,
+            },
+            SelectionType::Row { rows } => SelectionType::Row {
+                rows: non_empty(
+                    rows.iter()
+                        .filter_map(|&row| Some(line(CellAddress::new(0, row))?.row))
+                        .collect(),
+                )?,
+            },
+            SelectionType::Multi { selections } => SelectionType::Multi {
+                selections: non_empty(
+                    selections
+                        .iter()
+                        .filter_map(|selection| selection.shifted(operation))
+                        .collect(),
+                )?,
+            },
+        };
+        Some(Selection {
+            selection_type,
+            anchor: self
+                .anchor
+                .map(|anchor| operation.shift_address_or_next(&anchor)),
+        })
+    }
+}
+
+fn non_empty<T>(items: Vec<T>) -> Option<Vec<T>> {
+    (!items.is_empty()).then_some(items)
+}
+
 // ============================================================================
 // Modal Types - Consolidated modal behaviors
 // ============================================================================
@@ -357,7 +424,8 @@ impl UIState {
 // Modal Kind for type checking
 // ============================================================================
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum ModalKind {
     Command,
     Visual,
@@ -384,7 +452,9 @@ impl NavigationModal {
 // Supporting Types
 // ============================================================================
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Mode shown in the status line, as `"navigation"`, `"bulkOperation"`, …
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum SpreadsheetMode {
     Navigation,
     Visual,
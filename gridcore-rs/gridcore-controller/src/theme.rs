@@ -0,0 +1,468 @@
+//! Color palettes the grid can be drawn with, and the contrast checks
+//! they are held to.
+//!
+//! Every palette comes in a light and a dark variant. Colors users store
+//! on rules, such as sparkline colors, are never rewritten: [`Palette::remap`]
+//! swaps the standard hues for the palette's own when they are drawn.
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+/// Contrast WCAG asks of normal-size text against what it sits on
+pub const MIN_TEXT_CONTRAST: f64 = 4.5;
+/// Contrast WCAG asks of borders and other non-text marks
+pub const MIN_MARK_CONTRAST: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Standard,
+    /// Darker text and stronger outlines, meeting 7:1 for text
+    HighContrast,
+    /// Errors, warnings and selection told apart without relying on
+    /// red against green, for deuteranopia
+    ColorblindSafe,
+}
+
+/// What the grid is drawn on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Surface {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// The colors of one palette on one surface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteColors {
+    pub background: &'static str,
+    pub text: &'static str,
+    /// Translucent, drawn over the cells
+    pub selection_fill: &'static str,
+    pub selection_border: &'static str,
+    pub active_border: &'static str,
+    pub error_text: &'static str,
+    pub warning_text: &'static str,
+    /// Fill and text of cells marked good, bad or neutral by a rule that
+    /// names no colors of its own
+    pub good_fill: &'static str,
+    pub good_text: &'static str,
+    pub bad_fill: &'static str,
+    pub bad_text: &'static str,
+    pub neutral_fill: &'static str,
+    pub neutral_text: &'static str,
+    /// Badges and notices, all with `badge_text`
+    pub error_badge: &'static str,
+    pub warning_badge: &'static str,
+    pub info_badge: &'static str,
+    pub badge_text: &'static str,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [
+        Palette::Standard,
+        Palette::HighContrast,
+        Palette::ColorblindSafe,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Standard => "standard",
+            Palette::HighContrast => "highcontrast",
+            Palette::ColorblindSafe => "colorblind",
+        }
+    }
+
+    /// Name shown in menus
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::Standard => "Standard",
+            Palette::HighContrast => "High contrast",
+            Palette::ColorblindSafe => "Colorblind safe",
+        }
+    }
+
+    pub fn colors(self, surface: Surface) -> PaletteColors {
+        match (self, surface) {
+            (Palette::Standard, Surface::Light) => PaletteColors {
+                background: "#ffffff",
+                text: "#333333",
+                selection_fill: "rgba(0, 102, 204, 0.1)",
+                selection_border: "#0066cc",
+                active_border: "#0066cc",
+                error_text: "#c5221f",
+                warning_text: "#985200",
+                good_fill: "#e6f4ea",
+                good_text: "#137333",
+                bad_fill: "#fce8e6",
+                bad_text: "#c5221f",
+                neutral_fill: "#fef7e0",
+                neutral_text: "#8a5700",
+                error_badge: "#c5221f",
+                warning_badge: "#985200",
+                info_badge: "#1967d2",
+                badge_text: "#ffffff",
+            },
+            (Palette::Standard, Surface::Dark) => PaletteColors {
+                background: "#1e1e1e",
+                text: "#e8eaed",
+                selection_fill: "rgba(138, 180, 248, 0.2)",
+                selection_border: "#8ab4f8",
+                active_border: "#8ab4f8",
+                error_text: "#f28b82",
+                warning_text: "#fdd663",
+                good_fill: "#0d3b1e",
+                good_text: "#81c995",
+                bad_fill: "#4a1512",
+                bad_text: "#f28b82",
+                neutral_fill: "#3d2e00",
+                neutral_text: "#fdd663",
+                error_badge: "#f28b82",
+                warning_badge: "#fdd663",
+                info_badge: "#8ab4f8",
+                badge_text: "#202124",
+            },
+            (Palette::HighContrast, Surface::Light) => PaletteColors {
+                background: "#ffffff",
+                text: "#000000",
+                selection_fill: "rgba(0, 0, 204, 0.08)",
+                selection_border: "#0000cc",
+                active_border: "#000000",
+                error_text: "#8b0000",
+                warning_text: "#5c3400",
+                good_fill: "#ffffff",
+                good_text: "#0b5a24",
+                bad_fill: "#ffffff",
+                bad_text: "#8b0000",
+                neutral_fill: "#ffffff",
+                neutral_text: "#000000",
+                error_badge: "#8b0000",
+                warning_badge: "#5c3400",
+                info_badge: "#0b3d91",
+                badge_text: "#ffffff",
+            },
+            (Palette::HighContrast, Surface::Dark) => PaletteColors {
+                background: "#000000",
+                text: "#ffffff",
+                selection_fill: "rgba(255, 255, 0, 0.15)",
+                selection_border: "#ffff00",
+                active_border: "#ffffff",
+                error_text: "#ffb3b3",
+                warning_text: "#ffd54f",
+                good_fill: "#000000",
+                good_text: "#8cf0a8",
+                bad_fill: "#000000",
+                bad_text: "#ffb3b3",
+                neutral_fill: "#000000",
+                neutral_text: "#ffffff",
+                error_badge: "#ffb3b3",
+                warning_badge: "#ffd54f",
+                info_badge: "#9ecbff",
+                badge_text: "#000000",
+            },
+            // Blue stands in for green and vermillion for red, and the
+            // selection moves to purple so it is not mistaken for either
+            (Palette::ColorblindSafe, Surface::Light) => PaletteColors {
+                background: "#ffffff",
+                text: "#333333",
+                selection_fill: "rgba(128, 80, 200, 0.12)",
+                selection_border: "#8050c8",
+                active_border: "#8050c8",
+                error_text: "#b03e00",
+                warning_text: "#594200",
+                good_fill: "#e3f0fa",
+                good_text: "#004c80",
+                bad_fill: "#fcebdc",
+                bad_text: "#b03e00",
+                neutral_fill: "#f2f2f2",
+                neutral_text: "#4d4d4d",
+                error_badge: "#b03e00",
+                warning_badge: "#594200",
+                info_badge: "#004c80",
+                badge_text: "#ffffff",
+            },
+            (Palette::ColorblindSafe, Surface::Dark) => PaletteColors {
+                background: "#1e1e1e",
+                text: "#e8eaed",
+                selection_fill: "rgba(217, 184, 255, 0.2)",
+                selection_border: "#d9b8ff",
+                active_border: "#d9b8ff",
+                error_text: "#ffa366",
+                warning_text: "#f0e442",
+                good_fill: "#0b2f45",
+                good_text: "#7cc4f0",
+                bad_fill: "#45230b",
+                bad_text: "#ffa366",
+                neutral_fill: "#333333",
+                neutral_text: "#e0e0e0",
+                error_badge: "#ffa366",
+                warning_badge: "#f0e442",
+                info_badge: "#7cc4f0",
+                badge_text: "#1e1e1e",
+            },
+        }
+    }
+
+    /// The color to draw a stored color with. Reds, greens and ambers of
+    /// the standard palette become the palette's error, good and warning
+    /// colors; anything else, and everything under the standard palette,
+    /// is drawn as stored.
+    pub fn remap<'a>(self, surface: Surface, color: &'a str) -> Cow<'a, str> {
+        if self == Palette::Standard {
+            return Cow::Borrowed(color);
+        }
+        let colors = self.colors(surface);
+        let standard = Palette::Standard.colors(surface);
+        let replacement = match color.trim().to_ascii_lowercase().as_str() {
+            "red" | "#ff0000" | "#d93025" | "#ff4444" => colors.error_text,
+            "green" | "#008000" | "#188038" => colors.good_text,
+            "orange" | "#ffa500" | "#f29900" => colors.warning_text,
+            stored if stored == standard.error_text || stored == standard.bad_text => {
+                colors.error_text
+            }
+            stored if stored == standard.good_text => colors.good_text,
+            stored if stored == standard.warning_text => colors.warning_text,
+            _ => return Cow::Borrowed(color),
+        };
+        Cow::Borrowed(replacement)
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(Palette::Standard),
+            "highcontrast" | "hc" => Ok(Palette::HighContrast),
+            "colorblind" | "cb" => Ok(Palette::ColorblindSafe),
+            other => Err(format!(
+                "Unknown palette: {} (expected standard, highcontrast or colorblind)",
+                other
+            )),
+        }
+    }
+}
+
+/// Red, green, blue and alpha of a `#rgb`, `#rrggbb`, `rgb(..)` or
+/// `rgba(..)` color, channels from 0 to 255 and alpha from 0 to 1
+pub fn parse_color(color: &str) -> Option<[f64; 4]> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        let channel = |i: usize, len: usize| u8::from_str_radix(hex.get(i..i + len)?, 16).ok();
+        return match hex.len() {
+            3 => Some([
+                channel(0, 1)? as f64 * 17.0,
+                channel(1, 1)? as f64 * 17.0,
+                channel(2, 1)? as f64 * 17.0,
+                1.0,
+            ]),
+            6 => Some([
+                channel(0, 2)? as f64,
+                channel(2, 2)? as f64,
+                channel(4, 2)? as f64,
+                1.0,
+            ]),
+            _ => None,
+        };
+    }
+    let inner = color
+        .strip_prefix("rgba(")
+        .or_else(|| color.strip_prefix("rgb("))?
+        .strip_suffix(')')?;
+    let parts = inner
+        .split(',')
+        .map(|part| part.trim().parse::<f64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match parts[..] {
+        [r, g, b] => Some([r, g, b, 1.0]),
+        [r, g, b, a] => Some([r, g, b, a]),
+        _ => None,
+    }
+}
+
+/// `color` as it shows over the opaque `background`
+pub fn composite(color: [f64; 4], background: [f64; 4]) -> [f64; 4] {
+    let alpha = color[3];
+    [
+        color[0] * alpha + background[0] * (1.0 - alpha),
+        color[1] * alpha + background[1] * (1.0 - alpha),
+        color[2] * alpha + background[2] * (1.0 - alpha),
+        1.0,
+    ]
+}
+
+/// WCAG relative luminance, 0 for black to 1 for white
+pub fn relative_luminance(color: [f64; 4]) -> f64 {
+    let linear = |channel: f64| {
+        let c = channel / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color[0]) + 0.7152 * linear(color[1]) + 0.0722 * linear(color[2])
+}
+
+/// WCAG contrast ratio of two colors, from 1 to 21. A translucent
+/// `background` is first composited over white.
+pub fn contrast_ratio(foreground: &str, background: &str) -> Option<f64> {
+    let background = composite(parse_color(background)?, [255.0, 255.0, 255.0, 1.0]);
+    let foreground = composite(parse_color(foreground)?, background);
+    let (a, b) = (
+        relative_luminance(foreground),
+        relative_luminance(background),
+    );
+    Some((a.max(b) + 0.05) / (a.min(b) + 0.05))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gridcore_core::sparkline::SparklineOptions;
+
+    const SURFACES: [Surface; 2] = [Surface::Light, Surface::Dark];
+
+    /// The selection fill as it shows over the background
+    fn selection(colors: &PaletteColors) -> String {
+        let [r, g, b, _] = composite(
+            parse_color(colors.selection_fill).unwrap(),
+            parse_color(colors.background).unwrap(),
+        );
+        format!("rgb({}, {}, {})", r, g, b)
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let ratio = contrast_ratio("#000000", "#ffffff").unwrap();
+        assert!((ratio - 21.0).abs() < 1e-9);
+        assert_eq!(contrast_ratio("#fff", "#ffffff"), Some(1.0));
+        // #767676 is the lightest grey passing 4.5:1 on white
+        assert!(contrast_ratio("#767676", "#ffffff").unwrap() >= MIN_TEXT_CONTRAST);
+        assert!(contrast_ratio("#777777", "#ffffff").unwrap() < MIN_TEXT_CONTRAST);
+        assert_eq!(
+            contrast_ratio("#333333", "rgba(0, 0, 0, 0)"),
+            contrast_ratio("#333333", "#ffffff")
+        );
+        assert_eq!(contrast_ratio("blue", "#ffffff"), None);
+    }
+
+    #[test]
+    fn test_every_text_pairing_meets_wcag() {
+        for palette in Palette::ALL {
+            let minimum = if palette == Palette::HighContrast {
+                7.0
+            } else {
+                MIN_TEXT_CONTRAST
+            };
+            for surface in SURFACES {
+                let colors = palette.colors(surface);
+                let selected = selection(&colors);
+                let pairings = [
+                    ("text", colors.text, colors.background),
+                    ("selected text", colors.text, &selected),
+                    ("error", colors.error_text, colors.background),
+                    ("selected error", colors.error_text, &selected),
+                    ("warning", colors.warning_text, colors.background),
+                    ("selected warning", colors.warning_text, &selected),
+                    ("good", colors.good_text, colors.good_fill),
+                    ("bad", colors.bad_text, colors.bad_fill),
+                    ("neutral", colors.neutral_text, colors.neutral_fill),
+                    ("error badge", colors.badge_text, colors.error_badge),
+                    ("warning badge", colors.badge_text, colors.warning_badge),
+                    ("info badge", colors.badge_text, colors.info_badge),
+                ];
+                for (name, text, fill) in pairings {
+                    let ratio = contrast_ratio(text, fill).unwrap();
+                    assert!(
+                        ratio >= minimum,
+                        "{} {:?}: {} on {} is {:.2}:1",
+                        palette,
+                        surface,
+                        name,
+                        text,
+                        ratio
+                    );
+                }
+                for border in [colors.selection_border, colors.active_border] {
+                    let ratio = contrast_ratio(border, colors.background).unwrap();
+                    assert!(ratio >= MIN_MARK_CONTRAST, "{} {:?}", palette, surface);
+                }
+            }
+        }
+    }
+
+    /// Linear RGB as seen with deuteranopia (Machado et al. 2009)
+    fn deuteranopia(color: &str) -> [f64; 3] {
+        const SIMULATION: [[f64; 3]; 3] = [
+            [0.367322, 0.860646, -0.227968],
+            [0.280085, 0.672501, 0.047413],
+            [-0.011820, 0.042940, 0.968881],
+        ];
+        let [r, g, b, _] = parse_color(color).unwrap();
+        let linear = [r, g, b].map(|channel| relative_luminance([channel, channel, channel, 1.0]));
+        SIMULATION.map(|row| (0..3).map(|i| row[i] * linear[i]).sum())
+    }
+
+    fn seen_apart(a: &str, b: &str) -> bool {
+        let (a, b) = (deuteranopia(a), deuteranopia(b));
+        (0..3).map(|i| (a[i] - b[i]).powi(2)).sum::<f64>().sqrt() >= 0.12
+    }
+
+    #[test]
+    fn test_colorblind_palette_tells_states_apart() {
+        for surface in SURFACES {
+            let standard = Palette::Standard.colors(surface);
+            assert!(!seen_apart(standard.error_text, standard.good_text));
+
+            let colors = Palette::ColorblindSafe.colors(surface);
+            let states = [
+                colors.error_text,
+                colors.warning_text,
+                colors.good_text,
+                colors.selection_border,
+            ];
+            for (i, a) in states.iter().enumerate() {
+                for b in &states[i + 1..] {
+                    assert!(seen_apart(a, b), "{:?}: {} and {}", surface, a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_remapping_leaves_stored_rule_colors_alone() {
+        let options = SparklineOptions {
+            color: Some("green".to_string()),
+            negative_color: Some("#D93025".to_string()),
+        };
+        let stored = options.clone();
+
+        let palette = Palette::ColorblindSafe;
+        let colors = palette.colors(Surface::Light);
+        let color = options.color.as_deref().unwrap();
+        let negative = options.negative_color.as_deref().unwrap();
+        assert_eq!(palette.remap(Surface::Light, color), colors.good_text);
+        assert_eq!(palette.remap(Surface::Light, negative), colors.error_text);
+        assert_eq!(palette.remap(Surface::Light, "#123456"), "#123456");
+        assert_eq!(Palette::Standard.remap(Surface::Light, negative), "#D93025");
+        assert_eq!(options, stored);
+    }
+
+    #[test]
+    fn test_palette_names_round_trip() {
+        for palette in Palette::ALL {
+            assert_eq!(palette.name().parse::<Palette>(), Ok(palette));
+        }
+        assert!("sepia".parse::<Palette>().is_err());
+    }
+}
@@ -4,7 +4,7 @@
 
 use crate::Result;
 use crate::ports::EventPort;
-use crate::ports::event_port::{DomainEvent, EventHandler};
+use crate::ports::event_port::{BatchDelta, DomainEvent, EventHandler};
 use crate::services::{EventManager, SpreadsheetEvent};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -31,34 +31,43 @@ impl EventAdapter {
         Self::new(Arc::new(EventManager::new()))
     }
 
-    /// Convert a domain event to a spreadsheet event
-    fn domain_to_spreadsheet_event(event: &DomainEvent) -> SpreadsheetEvent {
-        match event {
+    /// Convert a domain event to a spreadsheet event, if it has one
+    fn domain_to_spreadsheet_event(event: &DomainEvent) -> Option<SpreadsheetEvent> {
+        let event = match event {
             DomainEvent::CellChanged {
                 address,
                 old_value,
                 new_value,
-            } => {
-                SpreadsheetEvent::cell_updated(address, old_value.clone(), new_value.clone(), None)
-            }
+                new_formula,
+                ..
+            } => SpreadsheetEvent::cell_updated(
+                address,
+                old_value.clone(),
+                new_value.clone(),
+                new_formula.clone(),
+            ),
             DomainEvent::CellDeleted { address, .. } => SpreadsheetEvent::cell_deleted(address),
             DomainEvent::RowInserted { index } => {
                 SpreadsheetEvent::error(format!("Row {} inserted", index), None)
             }
-            DomainEvent::RowDeleted { index } => {
+            DomainEvent::RowDeleted { index, .. } => {
                 SpreadsheetEvent::error(format!("Row {} deleted", index), None)
             }
             DomainEvent::ColumnInserted { index } => {
                 SpreadsheetEvent::error(format!("Column {} inserted", index), None)
             }
-            DomainEvent::ColumnDeleted { index } => {
+            DomainEvent::ColumnDeleted { index, .. } => {
                 SpreadsheetEvent::error(format!("Column {} deleted", index), None)
             }
             DomainEvent::BatchStarted { batch_id } => {
                 SpreadsheetEvent::batch_started(batch_id.clone())
             }
-            DomainEvent::BatchCommitted { batch_id } => {
-                SpreadsheetEvent::batch_completed(batch_id.clone(), 0)
+            DomainEvent::BatchCommitted { batch_id, delta } => {
+                let changed = match delta {
+                    BatchDelta::Cells(cells) => cells.len(),
+                    BatchDelta::Summary { changed, .. } => *changed,
+                };
+                SpreadsheetEvent::batch_completed(batch_id.clone(), changed)
             }
             DomainEvent::BatchRolledBack { batch_id } => {
                 SpreadsheetEvent::batch_completed(batch_id.clone(), 0)
@@ -68,15 +77,28 @@ impl EventAdapter {
                     affected_cells.iter().map(|addr| addr.to_string()).collect();
                 SpreadsheetEvent::calculation_completed(cell_strings, 0)
             }
-        }
+            DomainEvent::RecalculationStarted { .. } => {
+                SpreadsheetEvent::calculation_started(Vec::new())
+            }
+            // Progress is for the handlers watching, not the event history
+            DomainEvent::RecalculationProgress { .. } => return None,
+            DomainEvent::RecalculationFinished { duration_ms, .. } => {
+                SpreadsheetEvent::calculation_completed(Vec::new(), *duration_ms as u64)
+            }
+            DomainEvent::RecalculationCancelled { .. } => {
+                SpreadsheetEvent::calculation_completed(Vec::new(), 0)
+            }
+        };
+        Some(event)
     }
 }
 
 impl EventPort for EventAdapter {
     fn publish(&self, event: DomainEvent) -> Result<()> {
         // Convert domain event to spreadsheet event and emit
-        let spreadsheet_event = Self::domain_to_spreadsheet_event(&event);
-        self.event_manager.emit(spreadsheet_event);
+        if let Some(spreadsheet_event) = Self::domain_to_spreadsheet_event(&event) {
+            self.event_manager.emit(spreadsheet_event);
+        }
 
         // Also call registered handlers directly
         if let Ok(handlers) = self.handlers.lock() {
@@ -132,7 +154,9 @@ mod tests {
         let event = DomainEvent::CellChanged {
             address: CellAddress::new(0, 0),
             old_value: None,
+            old_formula: None,
             new_value: CellValue::Number(42.0),
+            new_formula: None,
         };
 
         assert!(adapter.publish(event).is_ok());
@@ -154,7 +178,9 @@ mod tests {
         let event = DomainEvent::CellChanged {
             address: CellAddress::new(0, 0),
             old_value: None,
+            old_formula: None,
             new_value: CellValue::Number(42.0),
+            new_formula: None,
         };
         adapter.publish(event).unwrap();
 
@@ -168,6 +194,7 @@ mod tests {
         let event2 = DomainEvent::CellDeleted {
             address: CellAddress::new(0, 0),
             old_value: CellValue::Number(42.0),
+            old_formula: None,
         };
         adapter.publish(event2).unwrap();
 
@@ -193,7 +220,9 @@ mod tests {
         let event = DomainEvent::CellChanged {
             address: CellAddress::new(0, 0),
             old_value: None,
+            old_formula: None,
             new_value: CellValue::Number(42.0),
+            new_formula: None,
         };
         adapter.publish(event.clone()).unwrap();
         assert_eq!(counter.load(Ordering::SeqCst), 3);
@@ -4,9 +4,10 @@
 
 use crate::Result;
 use crate::domain::Cell;
+use crate::memory::{self, MemoryFootprint};
 use crate::ports::RepositoryPort;
-use crate::repository::CellRepository;
-use crate::types::{CellAddress, CellRange};
+use crate::repository::{CellRepository, LookupKey, SheetHealth, SpillOutcome, SpillRange};
+use crate::types::{CellAddress, CellArray, CellRange, CellValue};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
@@ -27,6 +28,14 @@ impl RepositoryAdapter {
             repository: Arc::new(Mutex::new(CellRepository::new())),
         }
     }
+
+    /// Remove spilled values before moving cells; their formulas spill
+    /// again when recalculated
+    fn clear_spills(&self) {
+        if let Ok(mut repo) = self.repository.lock() {
+            repo.clear_spills();
+        }
+    }
 }
 
 impl RepositoryPort for RepositoryAdapter {
@@ -67,6 +76,113 @@ impl RepositoryPort for RepositoryAdapter {
             .unwrap_or_default()
     }
 
+    fn health(&self) -> SheetHealth {
+        self.repository
+            .lock()
+            .map(|repo| repo.health())
+            .unwrap_or_default()
+    }
+
+    fn revision(&self) -> Option<u64> {
+        self.repository.lock().ok().map(|repo| repo.revision())
+    }
+
+    fn for_each_number_in_column(
+        &self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        visit: &mut dyn FnMut(u32, f64),
+    ) {
+        // Single lock and no cell clones, unlike the get_range based default
+        if let Ok(repo) = self.repository.lock() {
+            for row in start_row..=end_row {
+                if let Some(CellValue::Number(n)) = repo
+                    .get(&CellAddress::new(col, row))
+                    .map(|cell| cell.get_display_value())
+                {
+                    visit(row, *n);
+                }
+            }
+        }
+    }
+
+    fn indexed_first_match(
+        &self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        key: &LookupKey,
+    ) -> Option<Option<u32>> {
+        self.repository
+            .lock()
+            .ok()?
+            .indexed_first_match(col, start_row, end_row, key)
+    }
+
+    fn indexed_match_count(
+        &self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        key: &LookupKey,
+    ) -> Option<usize> {
+        self.repository
+            .lock()
+            .ok()?
+            .indexed_match_count(col, start_row, end_row, key)
+    }
+
+    fn set_lookup_index(&self, enabled: bool) {
+        if let Ok(mut repo) = self.repository.lock() {
+            repo.set_lookup_index(enabled);
+        }
+    }
+
+    fn memory_footprint(&self) -> Vec<(&'static str, usize)> {
+        let Ok(repo) = self.repository.lock() else {
+            return Vec::new();
+        };
+        vec![
+            (memory::CELLS, repo.footprint_bytes()),
+            (memory::LOOKUP_INDEX, repo.lookup_footprint_bytes()),
+        ]
+    }
+
+    fn compact(&self) {
+        if let Ok(mut repo) = self.repository.lock() {
+            MemoryFootprint::compact(&mut *repo);
+        }
+    }
+
+    fn spill(&self, anchor: &CellAddress, array: Option<&CellArray>) -> SpillOutcome {
+        self.repository
+            .lock()
+            .map(|mut repo| repo.spill(anchor, array))
+            .unwrap_or_default()
+    }
+
+    fn spill_ranges(&self) -> Vec<SpillRange> {
+        self.repository
+            .lock()
+            .map(|repo| repo.spill_ranges())
+            .unwrap_or_default()
+    }
+
+    fn spill_range_at(&self, address: &CellAddress) -> Option<SpillRange> {
+        self.repository
+            .lock()
+            .ok()
+            .and_then(|repo| repo.spill_range_at(address))
+    }
+
+    fn spills_over(&self, address: &CellAddress) -> Vec<CellAddress> {
+        self.repository
+            .lock()
+            .map(|repo| repo.spills_over(address))
+            .unwrap_or_default()
+    }
+
     fn get_range(&self, range: &CellRange) -> Vec<(CellAddress, Cell)> {
         let mut result = Vec::new();
         if let Ok(repo) = self.repository.lock() {
@@ -107,6 +223,7 @@ impl RepositoryPort for RepositoryAdapter {
     }
 
     fn insert_row(&self, row_index: u32) -> Result<()> {
+        self.clear_spills();
         let mut cells_to_move = Vec::new();
 
         // Collect cells that need to be moved
@@ -136,6 +253,7 @@ impl RepositoryPort for RepositoryAdapter {
     }
 
     fn insert_column(&self, col_index: u32) -> Result<()> {
+        self.clear_spills();
         let mut cells_to_move = Vec::new();
 
         // Collect cells that need to be moved
@@ -165,6 +283,7 @@ impl RepositoryPort for RepositoryAdapter {
     }
 
     fn delete_row(&self, row_index: u32) -> Result<()> {
+        self.clear_spills();
         let mut cells_to_delete = Vec::new();
         let mut cells_to_move = Vec::new();
 
@@ -202,6 +321,7 @@ impl RepositoryPort for RepositoryAdapter {
     }
 
     fn delete_column(&self, col_index: u32) -> Result<()> {
+        self.clear_spills();
         let mut cells_to_delete = Vec::new();
         let mut cells_to_move = Vec::new();
 
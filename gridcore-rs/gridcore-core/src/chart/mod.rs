@@ -0,0 +1,498 @@
+//! Chart-ready series extracted from a block of cells
+//!
+//! The extraction decides which way the series run, pulls out series names
+//! and category labels, and returns the numbers with blanks kept as gaps.
+//! Drawing is left to the host.
+//!
+//! For a single range the orientation is inferred from the first row and
+//! the first column. A line "holds labels" when its cells, apart from the
+//! top-left corner, are text or blank with at least one text cell. A blank
+//! top-left corner marks both the first row and the first column as labels,
+//! so a header row of years still reads as categories.
+//!
+//! - only the first row holds labels: series run down the columns
+//! - only the first column holds labels: series run along the rows
+//! - both or neither: series run along the longer side of the data, down
+//!   the columns on a tie
+//!
+//! With several ranges every range is one series. A first range made only
+//! of text supplies the category labels instead.
+
+use crate::formula::CellRange;
+use crate::types::{CellAddress, CellValue};
+use crate::{Result, SpreadsheetError};
+use serde::{Deserialize, Serialize};
+
+/// Points per series kept before the data is downsampled
+pub const DEFAULT_MAX_POINTS: usize = 1000;
+/// Skipped cells listed individually; the rest are only counted
+pub const MAX_WARNINGS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeriesOrientation {
+    /// Each column is a series and each row a category
+    Columns,
+    /// Each row is a series and each column a category
+    Rows,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartSeries {
+    pub name: String,
+    /// One value per category; `None` for blank and skipped cells
+    pub values: Vec<Option<f64>>,
+}
+
+/// A non-numeric cell inside the data that was left out of its series
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartWarning {
+    pub address: CellAddress,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChartData {
+    pub orientation: SeriesOrientation,
+    pub categories: Vec<String>,
+    pub series: Vec<ChartSeries>,
+    /// The first [`MAX_WARNINGS`] skipped cells
+    pub warnings: Vec<ChartWarning>,
+    /// Number of skipped cells, including those not listed in `warnings`
+    pub skipped: usize,
+    /// Every `sample_step`-th point was kept; 1 when nothing was dropped
+    pub sample_step: usize,
+}
+
+/// One cell of the block being charted
+type Slot = (CellAddress, CellValue);
+
+/// Extract chart data from `ranges`, reading cells through `read`. Series
+/// longer than `max_points` are downsampled by keeping every n-th point.
+pub fn build_chart_data<F>(ranges: &[CellRange], max_points: usize, read: F) -> Result<ChartData>
+where
+    F: Fn(&CellAddress) -> CellValue,
+{
+    let mut data = match ranges {
+        [] => {
+            return Err(SpreadsheetError::InvalidOperation(
+                "Nothing selected to chart".to_string(),
+            ));
+        }
+        [range] => from_block(range, &read),
+        _ => from_ranges(ranges, &read),
+    };
+
+    if data
+        .series
+        .iter()
+        .all(|series| series.values.iter().all(Option::is_none))
+    {
+        return Err(SpreadsheetError::InvalidOperation(
+            "No numeric data to chart".to_string(),
+        ));
+    }
+
+    downsample(&mut data, max_points.max(1));
+    Ok(data)
+}
+
+fn is_label(value: &CellValue) -> bool {
+    matches!(value, CellValue::String(text) if !text.is_empty())
+}
+
+/// Whether the cells hold labels: text or blank, with at least one text cell
+fn holds_labels<'a>(mut values: impl Iterator<Item = &'a CellValue>) -> bool {
+    let mut any = false;
+    values.all(|value| {
+        any |= is_label(value);
+        is_label(value) || value.is_empty()
+    }) && any
+}
+
+fn label_text(value: &CellValue) -> String {
+    match value {
+        CellValue::Empty => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn read_rows<F>(range: &CellRange, read: &F) -> Vec<Vec<Slot>>
+where
+    F: Fn(&CellAddress) -> CellValue,
+{
+    (range.start.row..=range.end.row)
+        .map(|row| {
+            (range.start.col..=range.end.col)
+                .map(|col| {
+                    let address = CellAddress::new(col, row);
+                    let value = read(&address);
+                    (address, value)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn transpose(rows: Vec<Vec<Slot>>) -> Vec<Vec<Slot>> {
+    let width = rows.first().map_or(0, Vec::len);
+    let mut columns: Vec<Vec<Slot>> = (0..width).map(|_| Vec::with_capacity(rows.len())).collect();
+    for row in rows {
+        for (column, slot) in columns.iter_mut().zip(row) {
+            column.push(slot);
+        }
+    }
+    columns
+}
+
+/// Accumulates values and the cells skipped on the way
+#[derive(Default)]
+struct Collector {
+    warnings: Vec<ChartWarning>,
+    skipped: usize,
+}
+
+impl Collector {
+    fn value(&mut self, (address, value): &Slot) -> Option<f64> {
+        match value {
+            CellValue::Number(n) => Some(*n),
+            CellValue::Empty => None,
+            other => {
+                self.skipped += 1;
+                if self.warnings.len() < MAX_WARNINGS {
+                    self.warnings.push(ChartWarning {
+                        address: *address,
+                        value: other.to_string(),
+                    });
+                }
+                None
+            }
+        }
+    }
+}
+
+fn numbered(count: usize) -> Vec<String> {
+    (1..=count).map(|i| i.to_string()).collect()
+}
+
+fn from_block<F>(range: &CellRange, read: &F) -> ChartData
+where
+    F: Fn(&CellAddress) -> CellValue,
+{
+    let rows = read_rows(range, read);
+    let (height, width) = (range.row_count(), range.col_count());
+
+    let blank_corner = height > 1 && width > 1 && rows[0][0].1.is_empty();
+    let row_labels =
+        height > 1 && (blank_corner || holds_labels(rows[0][1..].iter().map(|(_, v)| v)));
+    let column_labels =
+        width > 1 && (blank_corner || holds_labels(rows[1..].iter().map(|row| &row[0].1)));
+
+    let orientation = match (row_labels, column_labels) {
+        (true, false) => SeriesOrientation::Columns,
+        (false, true) => SeriesOrientation::Rows,
+        _ => {
+            let data_rows = height - row_labels as usize;
+            let data_cols = width - column_labels as usize;
+            if data_rows >= data_cols {
+                SeriesOrientation::Columns
+            } else {
+                SeriesOrientation::Rows
+            }
+        }
+    };
+
+    // Lay the block out as rows of categories with one column per series
+    let (grid, has_names, has_categories) = match orientation {
+        SeriesOrientation::Columns => (rows, row_labels, column_labels),
+        SeriesOrientation::Rows => (transpose(rows), column_labels, row_labels),
+    };
+    let first_data_row = has_names as usize;
+    let first_data_col = has_categories as usize;
+    let series_count = grid[0].len() - first_data_col;
+
+    let categories = if has_categories {
+        grid[first_data_row..]
+            .iter()
+            .map(|row| label_text(&row[0].1))
+            .collect()
+    } else {
+        numbered(grid.len() - first_data_row)
+    };
+
+    let mut collector = Collector::default();
+    let series = (0..series_count)
+        .map(|i| {
+            let col = first_data_col + i;
+            let name = if has_names {
+                label_text(&grid[0][col].1)
+            } else {
+                String::new()
+            };
+            ChartSeries {
+                name: if name.is_empty() {
+                    format!("Series {}", i + 1)
+                } else {
+                    name
+                },
+                values: grid[first_data_row..]
+                    .iter()
+                    .map(|row| collector.value(&row[col]))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    ChartData {
+        orientation,
+        categories,
+        series,
+        warnings: collector.warnings,
+        skipped: collector.skipped,
+        sample_step: 1,
+    }
+}
+
+fn from_ranges<F>(ranges: &[CellRange], read: &F) -> ChartData
+where
+    F: Fn(&CellAddress) -> CellValue,
+{
+    let orientation = if ranges[0].row_count() >= ranges[0].col_count() {
+        SeriesOrientation::Columns
+    } else {
+        SeriesOrientation::Rows
+    };
+    let mut blocks: Vec<Vec<Slot>> = ranges
+        .iter()
+        .map(|range| read_rows(range, read).into_iter().flatten().collect())
+        .collect();
+
+    let category_block = holds_labels(blocks[0].iter().map(|(_, v)| v)).then(|| blocks.remove(0));
+
+    // A series is named by its first cell when that cell is text
+    let has_names = blocks
+        .iter()
+        .all(|block| block.len() > 1 && is_label(&block[0].1));
+
+    let mut collector = Collector::default();
+    let series: Vec<ChartSeries> = blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| {
+            let (name, cells) = if has_names {
+                (label_text(&block[0].1), &block[1..])
+            } else {
+                (format!("Series {}", i + 1), &block[..])
+            };
+            ChartSeries {
+                name,
+                values: cells.iter().map(|slot| collector.value(slot)).collect(),
+            }
+        })
+        .collect();
+
+    let categories = match category_block {
+        Some(block) => block
+            .iter()
+            .skip(has_names as usize)
+            .map(|(_, value)| label_text(value))
+            .collect(),
+        None => numbered(series.iter().map(|s| s.values.len()).max().unwrap_or(0)),
+    };
+
+    ChartData {
+        orientation,
+        categories,
+        series,
+        warnings: collector.warnings,
+        skipped: collector.skipped,
+        sample_step: 1,
+    }
+}
+
+fn downsample(data: &mut ChartData, max_points: usize) {
+    let points = data
+        .series
+        .iter()
+        .map(|series| series.values.len())
+        .chain([data.categories.len()])
+        .max()
+        .unwrap_or(0);
+    if points <= max_points {
+        return;
+    }
+
+    let step = points.div_ceil(max_points);
+    let keep = |i: &usize| i.is_multiple_of(step);
+    data.categories = std::mem::take(&mut data.categories)
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| keep(i))
+        .map(|(_, category)| category)
+        .collect();
+    for series in &mut data.series {
+        series.values = std::mem::take(&mut series.values)
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| keep(i))
+            .map(|(_, value)| value)
+            .collect();
+    }
+    data.sample_step = step;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Cells given row by row from A1; "" is blank and text that parses as
+    /// a number is a number
+    fn sheet(rows: &[&[&str]]) -> HashMap<CellAddress, CellValue> {
+        let mut cells = HashMap::new();
+        for (r, row) in rows.iter().enumerate() {
+            for (c, text) in row.iter().enumerate() {
+                let value = match text.parse::<f64>() {
+                    Ok(n) => CellValue::Number(n),
+                    Err(_) if text.is_empty() => CellValue::Empty,
+                    Err(_) => CellValue::string_from_str(text),
+                };
+                cells.insert(CellAddress::new(c as u32, r as u32), value);
+            }
+        }
+        cells
+    }
+
+    fn chart(cells: &HashMap<CellAddress, CellValue>, ranges: &[&str]) -> ChartData {
+        let ranges: Vec<CellRange> = ranges
+            .iter()
+            .map(|range| CellRange::from_string(range).unwrap())
+            .collect();
+        build_chart_data(&ranges, DEFAULT_MAX_POINTS, |address| {
+            cells.get(address).cloned().unwrap_or_default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_header_row_gives_column_series() {
+        let cells = sheet(&[
+            &["Month", "Sales", "Cost"],
+            &["Jan", "10", "4"],
+            &["Feb", "", "5"],
+            &["Mar", "12", "n/a"],
+        ]);
+        let data = chart(&cells, &["A1:C4"]);
+
+        assert_eq!(data.orientation, SeriesOrientation::Columns);
+        assert_eq!(data.categories, vec!["Jan", "Feb", "Mar"]);
+        assert_eq!(data.series[0].name, "Sales");
+        assert_eq!(data.series[0].values, vec![Some(10.0), None, Some(12.0)]);
+        assert_eq!(data.series[1].values, vec![Some(4.0), Some(5.0), None]);
+        assert_eq!(data.skipped, 1);
+        assert_eq!(data.warnings[0].address, CellAddress::new(2, 3));
+        assert_eq!(data.warnings[0].value, "n/a");
+    }
+
+    #[test]
+    fn test_label_column_gives_row_series() {
+        // Only the first column holds labels
+        let cells = sheet(&[&["North", "1", "2", "3"], &["South", "4", "5", "6"]]);
+        let data = chart(&cells, &["A1:D2"]);
+
+        assert_eq!(data.orientation, SeriesOrientation::Rows);
+        assert_eq!(data.series.len(), 2);
+        assert_eq!(data.series[1].name, "South");
+        assert_eq!(data.series[1].values, vec![Some(4.0), Some(5.0), Some(6.0)]);
+        assert_eq!(data.categories, vec!["1", "2", "3"]);
+
+        // A blank corner turns a numeric header row into categories
+        let cells = sheet(&[
+            &["", "2021", "2022", "2023"],
+            &["North", "1", "2", "3"],
+            &["South", "4", "5", "6"],
+        ]);
+        let data = chart(&cells, &["A1:D3"]);
+        assert_eq!(data.orientation, SeriesOrientation::Rows);
+        assert_eq!(data.categories, vec!["2021", "2022", "2023"]);
+        assert_eq!(data.series[0].name, "North");
+    }
+
+    #[test]
+    fn test_ambiguous_blocks_follow_the_longer_side() {
+        // Labels on both sides: three months by two series runs down columns
+        let both = sheet(&[
+            &["", "A", "B"],
+            &["Jan", "1", "2"],
+            &["Feb", "3", "4"],
+            &["Mar", "5", "6"],
+        ]);
+        let data = chart(&both, &["A1:C4"]);
+        assert_eq!(data.orientation, SeriesOrientation::Columns);
+        assert_eq!(data.categories, vec!["Jan", "Feb", "Mar"]);
+        assert_eq!(data.series[1].name, "B");
+
+        // Labels on both sides, wider than tall: series run along the rows
+        let wide = sheet(&[
+            &["", "Q1", "Q2", "Q3"],
+            &["A", "1", "2", "3"],
+            &["B", "4", "5", "6"],
+        ]);
+        let data = chart(&wide, &["A1:D3"]);
+        assert_eq!(data.orientation, SeriesOrientation::Rows);
+        assert_eq!(data.categories, vec!["Q1", "Q2", "Q3"]);
+        assert_eq!(data.series[0].name, "A");
+
+        // No labels at all, square: the tie goes to columns
+        let plain = sheet(&[&["1", "2"], &["3", "4"]]);
+        let data = chart(&plain, &["A1:B2"]);
+        assert_eq!(data.orientation, SeriesOrientation::Columns);
+        assert_eq!(data.series[0].name, "Series 1");
+        assert_eq!(data.series[0].values, vec![Some(1.0), Some(3.0)]);
+
+        // A single row of numbers is one series
+        let row = sheet(&[&["1", "2", "3"]]);
+        let data = chart(&row, &["A1:C1"]);
+        assert_eq!(data.orientation, SeriesOrientation::Rows);
+        assert_eq!(data.series.len(), 1);
+        assert_eq!(data.series[0].values.len(), 3);
+    }
+
+    #[test]
+    fn test_each_range_is_a_series() {
+        let cells = sheet(&[
+            &["Month", "Sales", "", "Cost"],
+            &["Jan", "10", "", "4"],
+            &["Feb", "11", "", "5"],
+        ]);
+        let data = chart(&cells, &["A1:A3", "B1:B3", "D1:D3"]);
+
+        assert_eq!(data.categories, vec!["Jan", "Feb"]);
+        assert_eq!(data.series.len(), 2);
+        assert_eq!(data.series[1].name, "Cost");
+        assert_eq!(data.series[1].values, vec![Some(4.0), Some(5.0)]);
+    }
+
+    #[test]
+    fn test_large_series_are_downsampled() {
+        let ranges = [CellRange::from_string("A1:A10000").unwrap()];
+        let data = build_chart_data(&ranges, 1000, |address| {
+            CellValue::Number(address.row as f64)
+        })
+        .unwrap();
+
+        assert_eq!(data.sample_step, 10);
+        assert_eq!(data.series[0].values.len(), 1000);
+        assert_eq!(data.categories.len(), 1000);
+        assert_eq!(data.series[0].values[1], Some(10.0));
+    }
+
+    #[test]
+    fn test_text_only_selection_is_rejected() {
+        let ranges = [CellRange::from_string("A1:B2").unwrap()];
+        let result = build_chart_data(&ranges, DEFAULT_MAX_POINTS, |_| {
+            CellValue::string_from_str("x")
+        });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,560 @@
+//! Conditional formats drawn from the values of a range: data bars, a bar
+//! behind the text as long as the value is large within the range, and
+//! color scales, a background blended between colors anchored along the
+//! range's values.
+//!
+//! Both read [`RangeStats`] of the rule's range: its numbers sorted, from
+//! which the smallest, the largest and any percentile are read.
+//! [`RangeStatsCache`] keeps them until a cell of the range changes.
+
+use crate::formula::CellRange;
+use crate::types::{CellAddress, CellValue};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// A point on a rule's scale
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleAnchor {
+    /// The smallest number in the range
+    Min,
+    /// The largest number in the range
+    Max,
+    /// A fixed value
+    Number(f64),
+    /// A percentile of the range's numbers, from 0 to 100
+    Percentile(f64),
+}
+
+impl ScaleAnchor {
+    /// The middle of the range's numbers
+    pub const MEDIAN: Self = Self::Percentile(50.0);
+
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Percentile(p) if !(0.0..=100.0).contains(p) => {
+                Err(format!("percentile {} is not between 0 and 100", p))
+            }
+            Self::Number(n) if !n.is_finite() => Err("anchor is not a number".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A bar behind each value, as long as the value is large between the
+/// range's `min` and `max`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataBar {
+    /// `#rrggbb` of the bars
+    pub color: String,
+    /// `#rrggbb` of bars of negative values; `color` when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub negative_color: Option<String>,
+    /// Where bars are shortest; values below it get none
+    pub min: ScaleAnchor,
+    /// Where bars reach across the cell; values above it stop there
+    pub max: ScaleAnchor,
+    /// Bars start from zero, so negatives grow leftward from an axis
+    /// rather than rightward from the cell's left edge
+    #[serde(default)]
+    pub axis_at_zero: bool,
+}
+
+impl DataBar {
+    /// Bars in `color` from the smallest to the largest number, negatives
+    /// left of an axis at zero
+    pub fn new(color: impl Into<String>) -> Self {
+        Self {
+            color: color.into(),
+            negative_color: None,
+            min: ScaleAnchor::Min,
+            max: ScaleAnchor::Max,
+            axis_at_zero: true,
+        }
+    }
+
+    /// The bar of `value`, `None` when the range has no numbers
+    pub fn fill(&self, value: f64, stats: &RangeStats) -> Option<DataBarFill> {
+        let (mut low, mut high) = (stats.resolve(self.min)?, stats.resolve(self.max)?);
+        if low > high {
+            std::mem::swap(&mut low, &mut high);
+        }
+        if self.axis_at_zero {
+            low = low.min(0.0);
+            high = high.max(0.0);
+        }
+        let span = high - low;
+        let position = |n: f64| {
+            if span > 0.0 {
+                (n.clamp(low, high) - low) / span
+            } else {
+                1.0
+            }
+        };
+
+        let axis = (self.axis_at_zero && low < 0.0).then(|| position(0.0));
+        let from = axis.unwrap_or(0.0);
+        let to = position(value);
+        let color = match &self.negative_color {
+            Some(negative) if value < 0.0 => negative.clone(),
+            _ => self.color.clone(),
+        };
+        Some(DataBarFill {
+            color,
+            start: from.min(to),
+            end: from.max(to),
+            axis,
+        })
+    }
+}
+
+/// A color a scale passes through at an anchor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+    pub anchor: ScaleAnchor,
+    /// `#rrggbb`
+    pub color: String,
+}
+
+/// A background blended between two or three colors along the range's
+/// values. Values before the first stop or after the last take its color.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorScale {
+    pub stops: Vec<ColorStop>,
+}
+
+impl ColorScale {
+    /// From `min_color` at the smallest number to `max_color` at the largest
+    pub fn two_color(min_color: impl Into<String>, max_color: impl Into<String>) -> Self {
+        Self {
+            stops: vec![
+                ColorStop {
+                    anchor: ScaleAnchor::Min,
+                    color: min_color.into(),
+                },
+                ColorStop {
+                    anchor: ScaleAnchor::Max,
+                    color: max_color.into(),
+                },
+            ],
+        }
+    }
+
+    /// From `min_color` at the smallest number through `mid_color` at the
+    /// median to `max_color` at the largest
+    pub fn three_color(
+        min_color: impl Into<String>,
+        mid_color: impl Into<String>,
+        max_color: impl Into<String>,
+    ) -> Self {
+        let mut scale = Self::two_color(min_color, max_color);
+        scale.stops.insert(
+            1,
+            ColorStop {
+                anchor: ScaleAnchor::MEDIAN,
+                color: mid_color.into(),
+            },
+        );
+        scale
+    }
+
+    /// The background of `value` as `#rrggbb`, `None` when the range has no
+    /// numbers
+    pub fn color(&self, value: f64, stats: &RangeStats) -> Option<String> {
+        let stops = self
+            .stops
+            .iter()
+            .map(|stop| Some((stats.resolve(stop.anchor)?, stop.color.as_str())))
+            .collect::<Option<Vec<_>>>()?;
+        let (first, last) = (stops.first()?, stops.last()?);
+        if value <= first.0 {
+            return Some(first.1.to_string());
+        }
+        for pair in stops.windows(2) {
+            let ((from, from_color), (to, to_color)) = (pair[0], pair[1]);
+            if value <= to {
+                let t = if to > from {
+                    (value - from) / (to - from)
+                } else {
+                    1.0
+                };
+                return interpolate_color(from_color, to_color, t);
+            }
+        }
+        Some(last.1.to_string())
+    }
+}
+
+/// What a conditional format draws
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConditionalFormatKind {
+    DataBar(DataBar),
+    ColorScale(ColorScale),
+}
+
+/// A conditional format over a range of one sheet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConditionalFormatRule {
+    pub range: CellRange,
+    pub kind: ConditionalFormatKind,
+}
+
+impl ConditionalFormatRule {
+    pub fn data_bar(range: CellRange, bar: DataBar) -> Self {
+        Self {
+            range,
+            kind: ConditionalFormatKind::DataBar(bar),
+        }
+    }
+
+    pub fn color_scale(range: CellRange, scale: ColorScale) -> Self {
+        Self {
+            range,
+            kind: ConditionalFormatKind::ColorScale(scale),
+        }
+    }
+
+    /// Check the rule can be drawn: colors are `#rrggbb`, percentiles lie
+    /// between 0 and 100 and scales have two or three stops
+    pub fn validate(&self) -> Result<(), String> {
+        let color = |color: &str| match parse_color(color) {
+            Some(_) => Ok(()),
+            None => Err(format!("{} is not a #rrggbb color", color)),
+        };
+        match &self.kind {
+            ConditionalFormatKind::DataBar(bar) => {
+                color(&bar.color)?;
+                if let Some(negative) = &bar.negative_color {
+                    color(negative)?;
+                }
+                bar.min.validate()?;
+                bar.max.validate()
+            }
+            ConditionalFormatKind::ColorScale(scale) => {
+                if !(2..=3).contains(&scale.stops.len()) {
+                    return Err(format!(
+                        "a color scale has 2 or 3 colors, not {}",
+                        scale.stops.len()
+                    ));
+                }
+                for stop in &scale.stops {
+                    color(&stop.color)?;
+                    stop.anchor.validate()?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A data bar ready to draw, as fractions of the cell's width
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataBarFill {
+    pub color: String,
+    pub start: f64,
+    pub end: f64,
+    /// Where zero is, when the bars of the range reach left of it
+    pub axis: Option<f64>,
+}
+
+/// What the conditional formats of a cell draw beneath its text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConditionalFill {
+    /// `#rrggbb` of the cell background, from the first color scale
+    pub background: Option<String>,
+    /// From the first data bar
+    pub data_bar: Option<DataBarFill>,
+}
+
+impl ConditionalFill {
+    /// The fill of `value` at `address` under `rules`, reading each rule's
+    /// range statistics from `stats`. Earlier rules win.
+    pub fn evaluate<'a>(
+        rules: impl IntoIterator<Item = &'a ConditionalFormatRule>,
+        address: &CellAddress,
+        value: f64,
+        mut stats: impl FnMut(&CellRange) -> Arc<RangeStats>,
+    ) -> Self {
+        let mut fill = Self::default();
+        for rule in rules {
+            if !rule.range.contains(address) {
+                continue;
+            }
+            match &rule.kind {
+                ConditionalFormatKind::DataBar(bar) if fill.data_bar.is_none() => {
+                    fill.data_bar = bar.fill(value, &stats(&rule.range));
+                }
+                ConditionalFormatKind::ColorScale(scale) if fill.background.is_none() => {
+                    fill.background = scale.color(value, &stats(&rule.range));
+                }
+                _ => {}
+            }
+        }
+        fill
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.background.is_none() && self.data_bar.is_none()
+    }
+}
+
+/// The numbers of a range, sorted. Text, booleans, blanks and errors are
+/// left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RangeStats {
+    numbers: Vec<f64>,
+}
+
+impl RangeStats {
+    pub fn new(values: impl IntoIterator<Item = CellValue>) -> Self {
+        let mut numbers: Vec<f64> = values
+            .into_iter()
+            .filter_map(|value| match value {
+                CellValue::Number(n) if n.is_finite() => Some(n),
+                _ => None,
+            })
+            .collect();
+        numbers.sort_by(f64::total_cmp);
+        Self { numbers }
+    }
+
+    /// How many numbers the range holds
+    pub fn count(&self) -> usize {
+        self.numbers.len()
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.numbers.first().copied()
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.numbers.last().copied()
+    }
+
+    /// The `p`th percentile, from 0 to 100, interpolated between the two
+    /// nearest numbers as Excel's `PERCENTILE.INC` does
+    pub fn percentile(&self, p: f64) -> Option<f64> {
+        let last = self.numbers.len().checked_sub(1)?;
+        let rank = (p.clamp(0.0, 100.0) / 100.0) * last as f64;
+        let below = rank.floor() as usize;
+        let above = (below + 1).min(last);
+        let (low, high) = (self.numbers[below], self.numbers[above]);
+        Some(low + (high - low) * (rank - below as f64))
+    }
+
+    /// The value `anchor` stands for in this range
+    pub fn resolve(&self, anchor: ScaleAnchor) -> Option<f64> {
+        match anchor {
+            ScaleAnchor::Min => self.min(),
+            ScaleAnchor::Max => self.max(),
+            ScaleAnchor::Number(n) => Some(n),
+            ScaleAnchor::Percentile(p) => self.percentile(p),
+        }
+    }
+}
+
+/// Statistics of the ranges conditional formats read, by sheet and range.
+/// An entry stays until a cell inside its range changes.
+#[derive(Debug, Default)]
+pub struct RangeStatsCache {
+    entries: FxHashMap<(String, CellRange), Arc<RangeStats>>,
+    hits: u64,
+    misses: u64,
+}
+
+impl RangeStatsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The statistics of `range` in `sheet`, from the cache or, when a
+    /// cell of the range changed since, from `compute`
+    pub fn get_or_compute(
+        &mut self,
+        sheet: &str,
+        range: &CellRange,
+        compute: impl FnOnce() -> RangeStats,
+    ) -> Arc<RangeStats> {
+        let key = (sheet.to_string(), *range);
+        if let Some(stats) = self.entries.get(&key) {
+            self.hits += 1;
+            return Arc::clone(stats);
+        }
+        self.misses += 1;
+        let stats = Arc::new(compute());
+        self.entries.insert(key, Arc::clone(&stats));
+        stats
+    }
+
+    /// `address` changed: forget every range holding it, in any sheet
+    pub fn invalidate(&mut self, address: &CellAddress) {
+        self.entries
+            .retain(|(_, range), _| !range.contains(address));
+    }
+
+    /// Forget everything, e.g. after rows moved
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Statistics answered from the cache and computed, since creation
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+}
+
+/// The red, green and blue of `#rrggbb`
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// `#rrggbb` a fraction `t` of the way from `from` to `to`
+pub fn interpolate_color(from: &str, to: &str, t: f64) -> Option<String> {
+    let (from, to) = (parse_color(from)?, parse_color(to)?);
+    let t = t.clamp(0.0, 1.0);
+    let channel = |i: usize| {
+        let (a, b) = (f64::from(from[i]), f64::from(to[i]));
+        (a + (b - a) * t).round() as u8
+    };
+    Some(format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(0),
+        channel(1),
+        channel(2)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(numbers: &[f64]) -> RangeStats {
+        RangeStats::new(numbers.iter().map(|&n| CellValue::Number(n)))
+    }
+
+    #[test]
+    fn test_percentiles_interpolate_between_numbers() {
+        let stats = RangeStats::new([
+            CellValue::Number(40.0),
+            CellValue::from_string("text".to_string()),
+            CellValue::Number(10.0),
+            CellValue::Empty,
+            CellValue::Number(20.0),
+            CellValue::Number(30.0),
+        ]);
+        assert_eq!(stats.count(), 4);
+        assert_eq!(stats.min(), Some(10.0));
+        assert_eq!(stats.max(), Some(40.0));
+        assert_eq!(stats.percentile(0.0), Some(10.0));
+        assert_eq!(stats.percentile(100.0), Some(40.0));
+        assert_eq!(stats.resolve(ScaleAnchor::MEDIAN), Some(25.0));
+        assert_eq!(stats.percentile(25.0), Some(17.5));
+        assert_eq!(RangeStats::default().percentile(50.0), None);
+    }
+
+    #[test]
+    fn test_color_scales_hit_their_colors_at_the_anchors() {
+        let stats = stats(&[0.0, 10.0, 20.0, 100.0]);
+        let scale = ColorScale::three_color("#ff0000", "#ffff00", "#00ff00");
+        // min, median (15) and max
+        assert_eq!(scale.color(0.0, &stats).as_deref(), Some("#ff0000"));
+        assert_eq!(scale.color(15.0, &stats).as_deref(), Some("#ffff00"));
+        assert_eq!(scale.color(100.0, &stats).as_deref(), Some("#00ff00"));
+        // Halfway between min and median, and between median and max
+        assert_eq!(scale.color(7.5, &stats).as_deref(), Some("#ff8000"));
+        assert_eq!(scale.color(57.5, &stats).as_deref(), Some("#80ff00"));
+        // Beyond the ends
+        assert_eq!(scale.color(-5.0, &stats).as_deref(), Some("#ff0000"));
+
+        let fixed = ColorScale {
+            stops: vec![
+                ColorStop {
+                    anchor: ScaleAnchor::Number(50.0),
+                    color: "#000000".to_string(),
+                },
+                ColorStop {
+                    anchor: ScaleAnchor::Percentile(100.0),
+                    color: "#ffffff".to_string(),
+                },
+            ],
+        };
+        assert_eq!(fixed.color(20.0, &stats).as_deref(), Some("#000000"));
+        assert_eq!(fixed.color(75.0, &stats).as_deref(), Some("#808080"));
+        assert_eq!(scale.color(1.0, &RangeStats::default()), None);
+    }
+
+    #[test]
+    fn test_data_bars_grow_from_the_axis() {
+        let positive = stats(&[0.0, 50.0, 100.0]);
+        let bar = DataBar {
+            axis_at_zero: false,
+            ..DataBar::new("#638ec6")
+        };
+        let fill = bar.fill(50.0, &positive).unwrap();
+        assert_eq!((fill.start, fill.end, fill.axis), (0.0, 0.5, None));
+        assert_eq!(bar.fill(100.0, &positive).unwrap().end, 1.0);
+
+        // Negatives grow leftward from zero, a quarter of the way across
+        let mixed = stats(&[-25.0, 0.0, 75.0]);
+        let bar = DataBar {
+            negative_color: Some("#ff0000".to_string()),
+            ..DataBar::new("#638ec6")
+        };
+        let fill = bar.fill(-25.0, &mixed).unwrap();
+        assert_eq!((fill.start, fill.end), (0.0, 0.25));
+        assert_eq!(fill.axis, Some(0.25));
+        assert_eq!(fill.color, "#ff0000");
+        let fill = bar.fill(75.0, &mixed).unwrap();
+        assert_eq!((fill.start, fill.end), (0.25, 1.0));
+        assert_eq!(fill.color, "#638ec6");
+
+        // Overridden ends clamp the values beyond them
+        let bar = DataBar {
+            min: ScaleAnchor::Number(0.0),
+            max: ScaleAnchor::Number(50.0),
+            ..DataBar::new("#638ec6")
+        };
+        assert_eq!(bar.fill(75.0, &mixed).unwrap().end, 1.0);
+        assert_eq!(bar.fill(25.0, &mixed).unwrap().end, 0.5);
+    }
+
+    #[test]
+    fn test_rules_are_validated() {
+        let range = CellRange::from_string("A1:A5").unwrap();
+        assert!(
+            ConditionalFormatRule::data_bar(range, DataBar::new("#638ec6"))
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            ConditionalFormatRule::data_bar(range, DataBar::new("blue"))
+                .validate()
+                .is_err()
+        );
+        let mut scale = ColorScale::two_color("#ffffff", "#000000");
+        scale.stops[0].anchor = ScaleAnchor::Percentile(120.0);
+        assert!(
+            ConditionalFormatRule::color_scale(range, scale)
+                .validate()
+                .is_err()
+        );
+        let scale = ColorScale {
+            stops: vec![ColorStop {
+                anchor: ScaleAnchor::Min,
+                color: "#ffffff".to_string(),
+            }],
+        };
+        assert!(
+            ConditionalFormatRule::color_scale(range, scale)
+                .validate()
+                .is_err()
+        );
+    }
+}
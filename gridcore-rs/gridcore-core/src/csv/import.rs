@@ -0,0 +1,486 @@
+//! Two-phase import of delimited text.
+//!
+//! [`preview_import`] reads the first rows of a file and guesses what a
+//! blind import would: the encoding, the delimiter, whether the first row
+//! is a header and the type of each column. The guesses come back as
+//! [`ImportOptions`] for the user to correct, per column or as a whole,
+//! before [`SpreadsheetFacade::commit_import`] reads the whole file with
+//! them. The options serialize to JSON so every host shares one model.
+//!
+//! [`SpreadsheetFacade::commit_import`]: crate::SpreadsheetFacade::commit_import
+
+use super::{CsvImportOptions, DelimitedRows};
+use crate::SpreadsheetError;
+use crate::evaluator::dates::parse_date_text;
+use crate::evaluator::parse_cell_value;
+use crate::types::{CellAddress, CellValue};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+/// Delimiters [`preview_import`] recognizes
+pub const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// Lines read to guess the delimiter
+const DELIMITER_SAMPLE_LINES: usize = 20;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextEncoding {
+    Utf8,
+    /// UTF-8 starting with a byte order mark, as spreadsheet apps write it
+    Utf8Bom,
+    /// ISO 8859-1, read for anything that is not valid UTF-8
+    Latin1,
+}
+
+impl TextEncoding {
+    pub fn detect(source: &[u8]) -> Self {
+        if source.starts_with(UTF8_BOM) {
+            TextEncoding::Utf8Bom
+        } else if std::str::from_utf8(source).is_ok() {
+            TextEncoding::Utf8
+        } else {
+            TextEncoding::Latin1
+        }
+    }
+
+    /// Text of `source`, without a byte order mark. Invalid UTF-8 is
+    /// replaced rather than failing the import.
+    pub fn decode(self, source: &[u8]) -> Cow<'_, str> {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8_lossy(source),
+            TextEncoding::Utf8Bom => {
+                String::from_utf8_lossy(source.strip_prefix(UTF8_BOM).unwrap_or(source))
+            }
+            TextEncoding::Latin1 => Cow::Owned(source.iter().map(|&b| b as char).collect()),
+        }
+    }
+}
+
+/// How the fields of a column are stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    /// Each field as if typed into the grid, formulas included
+    #[default]
+    General,
+    /// Fields as text, even when they read as numbers, e.g. ids with
+    /// leading zeros
+    Text,
+    Number,
+    /// Dates in any of the common forms, stored as dates shown as
+    /// `YYYY-MM-DD`
+    Date,
+    /// Leave the column out; the columns after it move left
+    Skip,
+}
+
+impl ColumnType {
+    pub fn label(self) -> &'static str {
+        match self {
+            ColumnType::General => "general",
+            ColumnType::Text => "text",
+            ColumnType::Number => "number",
+            ColumnType::Date => "date",
+            ColumnType::Skip => "skip",
+        }
+    }
+
+    /// Input to store for a non-empty `field` of a column of this type, or
+    /// an error saying why the field does not fit it. Skipped columns have
+    /// no input.
+    pub fn input(self, field: &str, options: &CsvImportOptions) -> Result<Option<String>, String> {
+        match self {
+            ColumnType::General => Ok(Some(super::field_input(field, options))),
+            ColumnType::Text => Ok(Some(format!("'{}", field))),
+            ColumnType::Number => match parse_cell_value(field.trim()) {
+                CellValue::Number(_) => Ok(Some(field.trim().to_string())),
+                _ => Err(format!("{} is not a number", field)),
+            },
+            ColumnType::Date => match parse_date_text(field.trim()) {
+                Some(date) => Ok(Some(date.format("%Y-%m-%d").to_string())),
+                None => Err(format!("{} is not a date", field)),
+            },
+            ColumnType::Skip => Ok(None),
+        }
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = SpreadsheetError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "general" | "auto" => Ok(ColumnType::General),
+            "text" => Ok(ColumnType::Text),
+            "number" => Ok(ColumnType::Number),
+            "date" => Ok(ColumnType::Date),
+            "skip" => Ok(ColumnType::Skip),
+            other => Err(SpreadsheetError::InvalidArguments(format!(
+                "Unknown column type '{}', expected general, text, number, date or skip",
+                other
+            ))),
+        }
+    }
+}
+
+/// Where imported rows go
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ImportDestination {
+    /// The active sheet, with the first field at `anchor`
+    Anchor { anchor: CellAddress },
+    /// A sheet added for the import, made the active sheet
+    NewSheet { name: String },
+}
+
+impl Default for ImportDestination {
+    fn default() -> Self {
+        ImportDestination::Anchor {
+            anchor: CellAddress::new(0, 0),
+        }
+    }
+}
+
+/// How to read and place an import. What is left `None` is detected.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<TextEncoding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delimiter: Option<char>,
+    /// Whether the first row holds column names, imported as text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_header: Option<bool>,
+    /// Type of each column from the left; columns past the end are
+    /// [`ColumnType::General`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_types: Vec<ColumnType>,
+    #[serde(default)]
+    pub destination: ImportDestination,
+    #[serde(default)]
+    pub fields: CsvImportOptions,
+}
+
+impl ImportOptions {
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        serde_json::from_str(json).map_err(|e| {
+            SpreadsheetError::InvalidArguments(format!("Invalid import options: {}", e))
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Read column types given as a comma-separated list, e.g.
+    /// `text,number,skip`, the form the command line takes
+    pub fn parse_column_types(list: &str) -> crate::Result<Vec<ColumnType>> {
+        list.split(',').map(str::parse).collect()
+    }
+
+    pub fn column_type(&self, col: usize) -> ColumnType {
+        self.column_types.get(col).copied().unwrap_or_default()
+    }
+}
+
+/// What [`preview_import`] found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub encoding: TextEncoding,
+    pub delimiter: char,
+    pub has_header: bool,
+    /// Names of the columns, empty without a header
+    pub headers: Vec<String>,
+    /// Type of each column guessed from the sample
+    pub inferred_types: Vec<ColumnType>,
+    /// The first rows after the header, as read
+    pub sample: Vec<Vec<String>>,
+    /// The options given, with everything left to detect filled in and
+    /// a type for every column, ready to correct and commit
+    pub options: ImportOptions,
+}
+
+/// Read up to `sample_rows` rows after the header of `source` and guess
+/// how to import it. Anything `options` sets is kept as given.
+pub fn preview_import(source: &[u8], options: &ImportOptions, sample_rows: usize) -> ImportPreview {
+    let encoding = options
+        .encoding
+        .unwrap_or_else(|| TextEncoding::detect(source));
+    let text = encoding.decode(source);
+    let delimiter = options.delimiter.unwrap_or_else(|| detect_delimiter(&text));
+
+    let mut rows: Vec<Vec<String>> = DelimitedRows::new(&text, delimiter)
+        .take(sample_rows + 1)
+        .collect();
+    let has_header = options.has_header.unwrap_or_else(|| detect_header(&rows));
+    let headers = if has_header && !rows.is_empty() {
+        rows.remove(0)
+    } else {
+        rows.truncate(sample_rows);
+        Vec::new()
+    };
+
+    let width = rows
+        .iter()
+        .chain(std::iter::once(&headers))
+        .map(Vec::len)
+        .max()
+        .unwrap_or(0);
+    let inferred_types: Vec<ColumnType> = (0..width)
+        .map(|col| infer_column_type(rows.iter().filter_map(|row| row.get(col))))
+        .collect();
+    let column_types = (0..width)
+        .map(|col| {
+            options
+                .column_types
+                .get(col)
+                .copied()
+                .unwrap_or(inferred_types[col])
+        })
+        .collect();
+
+    ImportPreview {
+        encoding,
+        delimiter,
+        has_header,
+        headers,
+        inferred_types,
+        sample: rows,
+        options: ImportOptions {
+            encoding: Some(encoding),
+            delimiter: Some(delimiter),
+            has_header: Some(has_header),
+            column_types,
+            ..options.clone()
+        },
+    }
+}
+
+/// The delimiter splitting the first lines into the same number of fields
+/// most often, a comma when none does
+pub fn detect_delimiter(text: &str) -> char {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(DELIMITER_SAMPLE_LINES)
+        .collect();
+    // Ties go to the delimiter listed first
+    DELIMITERS
+        .iter()
+        .rev()
+        .map(|&delimiter| {
+            let counts: Vec<usize> = lines
+                .iter()
+                .map(|line| count_unquoted(line, delimiter))
+                .collect();
+            let first = counts.first().copied().unwrap_or(0);
+            let consistent = counts.iter().filter(|&&count| count == first).count();
+            (delimiter, first, consistent)
+        })
+        .filter(|&(_, first, _)| first > 0)
+        .max_by_key(|&(_, first, consistent)| (consistent, first))
+        .map_or(',', |(delimiter, _, _)| delimiter)
+}
+
+fn count_unquoted(line: &str, delimiter: char) -> usize {
+    let mut quoted = false;
+    line.chars()
+        .filter(|&c| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == delimiter && !quoted
+        })
+        .count()
+}
+
+/// What a field reads as, for guessing types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Number,
+    /// Digits with a leading zero, e.g. an id or a postcode
+    PaddedDigits,
+    Date,
+    Text,
+    /// Booleans and formulas, which only a general column keeps
+    Other,
+}
+
+fn field_kind(field: &str) -> FieldKind {
+    let field = field.trim();
+    let padded =
+        field.len() > 1 && field.starts_with('0') && field.chars().all(|c| c.is_ascii_digit());
+    if padded {
+        return FieldKind::PaddedDigits;
+    }
+    if field.starts_with('=') {
+        return FieldKind::Other;
+    }
+    if parse_date_text(field).is_some() {
+        return FieldKind::Date;
+    }
+    match parse_cell_value(field) {
+        CellValue::Number(_) => FieldKind::Number,
+        CellValue::Boolean(_) => FieldKind::Other,
+        _ => FieldKind::Text,
+    }
+}
+
+/// Type of a column from its fields: numbers, dates or text when all its
+/// fields agree, text for numbers written with leading zeros, and general
+/// for a mix
+fn infer_column_type<'a>(fields: impl Iterator<Item = &'a String>) -> ColumnType {
+    let kinds: Vec<FieldKind> = fields
+        .filter(|field| !field.trim().is_empty())
+        .map(|field| field_kind(field))
+        .collect();
+    let all = |wanted: &[FieldKind]| kinds.iter().all(|kind| wanted.contains(kind));
+    if kinds.is_empty() {
+        ColumnType::General
+    } else if all(&[FieldKind::Number]) {
+        ColumnType::Number
+    } else if all(&[FieldKind::Date]) {
+        ColumnType::Date
+    } else if all(&[FieldKind::Number, FieldKind::PaddedDigits]) || all(&[FieldKind::Text]) {
+        // Ids with leading zeros would lose them as numbers
+        ColumnType::Text
+    } else {
+        ColumnType::General
+    }
+}
+
+/// Whether the first row names the columns: all its fields are distinct
+/// text, and the rows below hold something other than text in some column
+/// or there are no rows below to compare with
+fn detect_header(rows: &[Vec<String>]) -> bool {
+    let Some(first) = rows.first() else {
+        return false;
+    };
+    let names: Vec<&str> = first.iter().map(|field| field.trim()).collect();
+    let all_text = names
+        .iter()
+        .all(|name| !name.is_empty() && field_kind(name) == FieldKind::Text);
+    let distinct = names
+        .iter()
+        .enumerate()
+        .all(|(i, name)| !names[..i].contains(name));
+    if !all_text || !distinct {
+        return false;
+    }
+    let body = &rows[1..];
+    (0..first.len()).any(|col| {
+        let fields = body.iter().filter_map(|row| row.get(col));
+        !matches!(infer_column_type(fields), ColumnType::Text)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preview(text: &str) -> ImportPreview {
+        preview_import(text.as_bytes(), &ImportOptions::default(), 10)
+    }
+
+    #[test]
+    fn test_infers_tricky_columns() {
+        let preview = preview(
+            "id,amount,when,note,mixed\n\
+             007,1.5,2024-01-05,hello,1\n\
+             012,\"12.5\",01/15/2024,,n/a\n\
+             100,-3,2024/02/29,world,2\n",
+        );
+        assert!(preview.has_header);
+        assert_eq!(preview.headers, ["id", "amount", "when", "note", "mixed"]);
+        assert_eq!(
+            preview.inferred_types,
+            [
+                ColumnType::Text,
+                ColumnType::Number,
+                ColumnType::Date,
+                ColumnType::Text,
+                ColumnType::General,
+            ]
+        );
+        assert_eq!(preview.sample.len(), 3);
+        assert_eq!(preview.sample[1][1], "12.5");
+        assert_eq!(preview.options.column_types, preview.inferred_types);
+
+        // A date column with one field that is not a date stays general
+        let preview = self::preview("2024-01-05\nsoon\n");
+        assert!(!preview.has_header);
+        assert_eq!(preview.inferred_types, [ColumnType::General]);
+    }
+
+    #[test]
+    fn test_detects_delimiter_and_encoding() {
+        let preview = self::preview("a;b;c\n1;\"x;y\";3\n");
+        assert_eq!(preview.delimiter, ';');
+        assert_eq!(preview.sample, [vec!["1", "x;y", "3"]]);
+        assert_eq!(detect_delimiter("a\tb\tc,d\n1\t2\t3,4\n"), '\t');
+        assert_eq!(detect_delimiter("a;b,c\n1;2,3\n"), ',');
+        assert_eq!(detect_delimiter("just one column\n"), ',');
+
+        let bom = b"\xEF\xBB\xBFname,age\ncaf\xC3\xA9,3\n";
+        let preview = preview_import(bom, &ImportOptions::default(), 10);
+        assert_eq!(preview.encoding, TextEncoding::Utf8Bom);
+        assert_eq!(preview.headers, ["name", "age"]);
+        assert_eq!(preview.sample, [vec!["café", "3"]]);
+
+        let latin1 = b"name\ncaf\xE9\n";
+        assert_eq!(TextEncoding::detect(latin1), TextEncoding::Latin1);
+        assert_eq!(TextEncoding::Latin1.decode(latin1), "name\ncafé\n");
+    }
+
+    #[test]
+    fn test_given_options_are_kept() {
+        let options = ImportOptions {
+            delimiter: Some('|'),
+            has_header: Some(false),
+            column_types: vec![ColumnType::Skip],
+            ..ImportOptions::default()
+        };
+        let preview = preview_import(b"a,b|c\n1|2\n3|4\n", &options, 1);
+        assert!(!preview.has_header);
+        assert_eq!(preview.sample, [vec!["a,b", "c"]]);
+        assert_eq!(
+            preview.options.column_types,
+            [ColumnType::Skip, ColumnType::Text]
+        );
+
+        let json = preview.options.to_json();
+        assert_eq!(ImportOptions::from_json(&json).unwrap(), preview.options);
+        assert_eq!(
+            ImportOptions::parse_column_types("text, number,skip").unwrap(),
+            [ColumnType::Text, ColumnType::Number, ColumnType::Skip]
+        );
+        assert!(ImportOptions::parse_column_types("text,money").is_err());
+    }
+
+    #[test]
+    fn test_column_inputs() {
+        let options = CsvImportOptions::default();
+        let input = |column_type: ColumnType, field: &str| column_type.input(field, &options);
+        assert_eq!(input(ColumnType::Text, "007").unwrap().unwrap(), "'007");
+        assert_eq!(input(ColumnType::Number, " 007").unwrap().unwrap(), "007");
+        assert!(input(ColumnType::Number, "n/a").is_err());
+        assert_eq!(
+            input(ColumnType::Date, "01/15/2024").unwrap().unwrap(),
+            "2024-01-15"
+        );
+        assert!(input(ColumnType::Date, "2024-02-30").is_err());
+        assert_eq!(input(ColumnType::Skip, "x").unwrap(), None);
+        assert_eq!(input(ColumnType::General, "=A1").unwrap().unwrap(), "=A1");
+    }
+}
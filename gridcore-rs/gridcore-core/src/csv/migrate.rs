@@ -0,0 +1,223 @@
+//! Migrations of the sidecar layout.
+//!
+//! Every sidecar records the layout it was written in. Reading one runs the
+//! migrations from that version up to [`SIDECAR_VERSION`] in order, each on
+//! the raw JSON, before it is read into a [`Sidecar`]; writing always uses
+//! the current layout. A change to the layout bumps [`SIDECAR_VERSION`],
+//! adds a migration here and freezes a fixture of the new version under
+//! `fixtures/v<N>`, so documents of every past version keep loading.
+
+use super::{SIDECAR_VERSION, Sidecar};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+/// One step of the chain, bringing a sidecar of version `to - 1` to `to`
+struct Migration {
+    to: u32,
+    migrate: fn(&mut Map<String, Value>) -> Result<(), String>,
+}
+
+/// Every migration, oldest first, ending at [`SIDECAR_VERSION`]
+const MIGRATIONS: &[Migration] = &[Migration {
+    to: 2,
+    migrate: rename_version,
+}];
+
+/// Version 2 names the layout version `schema_version` rather than `version`
+fn rename_version(sidecar: &mut Map<String, Value>) -> Result<(), String> {
+    let version = sidecar.remove("version").unwrap_or(Value::from(1));
+    sidecar.insert("schema_version".to_string(), version);
+    Ok(())
+}
+
+/// Layout version of a raw sidecar. Version 1 called it `version`, and the
+/// earliest sidecars may not have it at all.
+pub fn schema_version(sidecar: &Map<String, Value>) -> Result<u32, String> {
+    let Some(version) = sidecar
+        .get("schema_version")
+        .or_else(|| sidecar.get("version"))
+    else {
+        return Ok(1);
+    };
+    version
+        .as_u64()
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| format!("sidecar version {} is not a number", version))
+}
+
+/// Bring a raw sidecar up to [`SIDECAR_VERSION`], returning the version it
+/// was written in. Sidecars of a newer version are left as they are.
+pub fn migrate(sidecar: &mut Value) -> Result<u32, String> {
+    let Value::Object(fields) = sidecar else {
+        return Err("a sidecar is a JSON object".to_string());
+    };
+    let written = schema_version(fields)?;
+    for migration in MIGRATIONS.iter().filter(|m| m.to > written) {
+        (migration.migrate)(fields)
+            .map_err(|e| format!("migrating to version {}: {}", migration.to, e))?;
+        fields.insert("schema_version".to_string(), Value::from(migration.to));
+    }
+    Ok(written)
+}
+
+/// Read a sidecar written in any version
+pub fn sidecar_from_value(mut value: Value) -> Result<Sidecar, String> {
+    let written = migrate(&mut value)?;
+    let mut sidecar: Sidecar = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    if written < SIDECAR_VERSION {
+        sidecar.migrated_from = Some(written);
+    }
+    Ok(sidecar)
+}
+
+/// For `#[serde(deserialize_with)]` on sidecars held in other documents,
+/// so they are migrated too
+pub fn deserialize_sidecar<'de, D>(deserializer: D) -> Result<Option<Sidecar>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        Some(value) => sidecar_from_value(value)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpreadsheetFacade;
+    use crate::csv::RowFold;
+    use crate::domain::{CellFormat, CellStyle, NumberFormat};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    /// The same sheet saved by every version: its CSV, its sidecar and the
+    /// content hash it loads to
+    const FIXTURES: &[(u32, &str, &str, u64)] = &[
+        (
+            1,
+            include_str!("fixtures/v1/budget.csv"),
+            include_str!("fixtures/v1/budget.sidecar.json"),
+            BUDGET_HASH,
+        ),
+        (
+            2,
+            include_str!("fixtures/v2/budget.csv"),
+            include_str!("fixtures/v2/budget.sidecar.json"),
+            BUDGET_HASH,
+        ),
+    ];
+
+    /// Content hash of the budget sheet. It covers the sidecar the sheet
+    /// saves, so it changes along with the current layout.
+    const BUDGET_HASH: u64 = 5_881_227_139_358_411_554;
+
+    #[test]
+    fn test_migrations_chain_up_to_the_current_version() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.to).collect();
+        let expected: Vec<u32> = (2..=SIDECAR_VERSION).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn test_version_1_names_its_version_schema_version() {
+        let mut sidecar = json!({"version": 1, "column_widths": {"A": 80.0}});
+        assert_eq!(migrate(&mut sidecar), Ok(1));
+        assert_eq!(
+            sidecar,
+            json!({"schema_version": 2, "column_widths": {"A": 80.0}})
+        );
+
+        // Without a version a sidecar is from version 1
+        let mut sidecar = json!({"formats": {}});
+        assert_eq!(migrate(&mut sidecar), Ok(1));
+        assert_eq!(sidecar["schema_version"], 2);
+
+        // Current and newer sidecars are left alone
+        let mut sidecar = json!({"schema_version": 9, "version": "x"});
+        assert_eq!(migrate(&mut sidecar), Ok(9));
+        assert_eq!(sidecar, json!({"schema_version": 9, "version": "x"}));
+
+        assert!(migrate(&mut json!({"version": "one"})).is_err());
+        assert!(migrate(&mut json!([1])).is_err());
+    }
+
+    #[test]
+    fn test_every_fixture_loads_to_the_same_sheet() {
+        for &(version, csv, sidecar, hash) in FIXTURES {
+            let sidecar = Sidecar::from_json(sidecar).unwrap();
+            assert_eq!(sidecar.schema_version, SIDECAR_VERSION);
+            assert_eq!(
+                sidecar.migrated_from,
+                (version < SIDECAR_VERSION).then_some(version)
+            );
+            assert!(sidecar.unknown.is_empty(), "version {}", version);
+
+            let facade = SpreadsheetFacade::new();
+            let report = facade.import_csv(csv, Some(&sidecar)).unwrap();
+            assert!(report.is_faithful(), "version {}: {}", version, report);
+            assert_eq!(facade.content_hash(), hash, "version {}", version);
+            assert_eq!(
+                facade.workbook_metadata().loaded_schema_version,
+                Some(version)
+            );
+
+            // Saving writes the current version
+            let saved = facade.export_csv(true).sidecar.unwrap().to_json();
+            let saved: Map<String, Value> = serde_json::from_str(&saved).unwrap();
+            assert_eq!(schema_version(&saved), Ok(SIDECAR_VERSION));
+        }
+    }
+
+    /// A sidecar using every field, as the current version writes it
+    fn full_sidecar() -> Sidecar {
+        let currency = CellFormat {
+            number_format: NumberFormat::Currency {
+                symbol: "$".to_string(),
+                decimals: 2,
+            },
+            ..CellFormat::default()
+        };
+        Sidecar {
+            schema_version: SIDECAR_VERSION,
+            formats: BTreeMap::from([("B3".to_string(), currency.clone())]),
+            cell_styles: BTreeMap::from([("A1".to_string(), "Title".to_string())]),
+            styles: vec![CellStyle {
+                name: "Title".to_string(),
+                format: CellFormat {
+                    wrap_text: true,
+                    ..CellFormat::default()
+                },
+            }],
+            column_formats: BTreeMap::from([("C".to_string(), currency.clone())]),
+            row_formats: BTreeMap::from([(5, currency)]),
+            column_widths: BTreeMap::from([("A".to_string(), 140.0)]),
+            row_folds: vec![RowFold {
+                start: 6,
+                end: 11,
+                collapsed: true,
+            }],
+            conditional_formats: Vec::new(),
+            unknown: BTreeMap::new(),
+            migrated_from: None,
+        }
+    }
+
+    /// Fails when the sidecar model changes without a new version: a
+    /// change to what is written needs a migration and a new fixture, or,
+    /// for a change every older reader copes with, an update of the fixture
+    /// of the current version
+    #[test]
+    fn test_model_matches_the_current_fixture() {
+        let fixture = FIXTURES
+            .iter()
+            .find(|(version, ..)| *version == SIDECAR_VERSION)
+            .map(|(_, _, sidecar, _)| *sidecar)
+            .expect("a fixture of the current version");
+        let written = full_sidecar().to_json();
+        assert_eq!(written.trim_end(), fixture.trim_end());
+        assert_eq!(Sidecar::from_json(fixture).unwrap(), full_sidecar());
+    }
+}
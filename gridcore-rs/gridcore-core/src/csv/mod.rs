@@ -0,0 +1,467 @@
+//! CSV export and import that keep formulas.
+//!
+//! A sheet is exported as its inputs rather than its values: formulas as
+//! `=` text, numbers as plain numbers and text with a leading apostrophe
+//! when it would otherwise read back as something else. What a CSV cannot
+//! hold goes into an optional JSON [`Sidecar`], keyed by A1 address so a
+//! change to one cell is a one-line diff. Importing the CSV with its sidecar
+//! rebuilds the sheet; anything that could not be restored is listed in the
+//! [`ImportReport`]. A [`LoadMode::Lenient`] import loads what it can of
+//! damaged or newer files and keeps what it cannot read for the next export.
+//!
+//! Fields are separated by commas and quoted as in RFC 4180. Rows end with
+//! `\n`; `\r\n` is accepted on import.
+
+use crate::Cell;
+use crate::conditional::ConditionalFormatKind;
+use crate::domain::{CellFormat, CellStyle};
+use crate::evaluator::parse_cell_value;
+use crate::types::{CellAddress, CellValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub mod import;
+pub mod migrate;
+
+pub use import::{ColumnType, ImportDestination, ImportOptions, ImportPreview, preview_import};
+
+/// Sidecar layout written by this version; older ones are read through
+/// [`migrate`]
+pub const SIDECAR_VERSION: u32 = 2;
+
+/// Formats and layout of an exported sheet that the CSV cannot hold.
+///
+/// Cells are keyed by A1 address, columns by letter and rows by number, as
+/// they are labelled in the grid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub schema_version: u32,
+    /// Explicit formats of single cells
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub formats: BTreeMap<String, CellFormat>,
+    /// Names of the styles cells refer to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub cell_styles: BTreeMap<String, String>,
+    /// Definitions of the styles in `cell_styles`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub styles: Vec<CellStyle>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_formats: BTreeMap<String, CellFormat>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub row_formats: BTreeMap<u32, CellFormat>,
+    /// Column widths in pixels, filled in by hosts that size columns
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_widths: BTreeMap<String, f64>,
+    /// Folded row ranges, filled in by hosts that fold rows
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub row_folds: Vec<RowFold>,
+    /// Data bars and color scales, earliest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditional_formats: Vec<SidecarConditionalFormat>,
+    /// Sections this version does not know, e.g. from a newer version,
+    /// by name. A lenient import keeps them so the next export writes them
+    /// back as they were.
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+    /// Version the sidecar was written in, when it was read from an older
+    /// one
+    #[serde(skip)]
+    pub migrated_from: Option<u32>,
+}
+
+/// Rows folded together, numbered as in the grid. A collapsed fold shows
+/// only its first row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowFold {
+    pub start: u32,
+    pub end: u32,
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+/// A conditional format with its range in A1 notation, e.g. `B2:B20`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarConditionalFormat {
+    pub range: String,
+    #[serde(flatten)]
+    pub kind: ConditionalFormatKind,
+}
+
+impl Default for Sidecar {
+    fn default() -> Self {
+        Self {
+            schema_version: SIDECAR_VERSION,
+            formats: BTreeMap::new(),
+            cell_styles: BTreeMap::new(),
+            styles: Vec::new(),
+            column_formats: BTreeMap::new(),
+            row_formats: BTreeMap::new(),
+            column_widths: BTreeMap::new(),
+            row_folds: Vec::new(),
+            conditional_formats: Vec::new(),
+            unknown: BTreeMap::new(),
+            migrated_from: None,
+        }
+    }
+}
+
+impl Sidecar {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Read a sidecar of this version or, migrating it, of an older one
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| e.to_string())
+            .and_then(migrate::sidecar_from_value)
+            .map_err(|e| {
+                crate::SpreadsheetError::InvalidOperation(format!("Invalid CSV sidecar: {}", e))
+            })
+    }
+
+    /// Read `json` as `mode` allows. A sidecar that cannot be read fails a
+    /// strict load; a lenient load goes on without it, noting it in
+    /// `report` with its text kept.
+    pub fn read(
+        json: &str,
+        mode: LoadMode,
+        report: &mut ImportReport,
+    ) -> crate::Result<Option<Self>> {
+        match Self::from_json(json) {
+            Ok(sidecar) => Ok(Some(sidecar)),
+            Err(e) if mode == LoadMode::Lenient => {
+                report.issue(None, format!("{}; loaded without it", e));
+                report.unread_sidecar = Some(json.to_string());
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// How an import treats content it cannot load as written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadMode {
+    /// Load everything as written: formulas that do not parse show an
+    /// error, a sidecar that cannot be read fails the import and sidecar
+    /// sections this version does not know are dropped. For round trips
+    /// that must be exact.
+    #[default]
+    Strict,
+    /// Load what can be loaded: formulas that do not parse come in as text
+    /// to fix by hand, a sidecar that cannot be read is set aside and
+    /// unknown sidecar sections are kept for the next export
+    Lenient,
+}
+
+/// Choices for reading CSV fields on import
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvImportOptions {
+    /// Read TRUE and FALSE in any case as booleans rather than text
+    pub booleans: bool,
+    #[serde(default)]
+    pub mode: LoadMode,
+}
+
+impl Default for CsvImportOptions {
+    fn default() -> Self {
+        Self {
+            booleans: true,
+            mode: LoadMode::Strict,
+        }
+    }
+}
+
+impl CsvImportOptions {
+    pub fn lenient() -> Self {
+        Self {
+            mode: LoadMode::Lenient,
+            ..Self::default()
+        }
+    }
+}
+
+/// An exported sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvExport {
+    pub csv: String,
+    pub sidecar: Option<Sidecar>,
+}
+
+/// Something an import could not restore
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FidelityIssue {
+    /// The cell concerned, if the issue is about one
+    pub address: Option<CellAddress>,
+    pub message: String,
+}
+
+impl fmt::Display for FidelityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.address {
+            Some(address) => write!(f, "{}: {}", address, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Cells written from the CSV
+    pub cells: usize,
+    pub issues: Vec<FidelityIssue>,
+    /// Formulas a lenient import loaded as text because they do not parse
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formulas_as_text: Vec<FidelityIssue>,
+    /// Sidecar sections a lenient import kept without understanding them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preserved_sections: Vec<String>,
+    /// Text of a sidecar a lenient import could not read, for the host to
+    /// keep or show
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unread_sidecar: Option<String>,
+}
+
+impl ImportReport {
+    /// Whether everything was restored
+    pub fn is_faithful(&self) -> bool {
+        self.issues.is_empty() && self.formulas_as_text.is_empty()
+    }
+
+    /// One line on what needs review, e.g. "3 formulas could not be
+    /// parsed", or `None` when everything was restored
+    pub fn summary(&self) -> Option<String> {
+        let plural =
+            |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+        let mut parts = Vec::new();
+        if !self.formulas_as_text.is_empty() {
+            parts.push(format!(
+                "{} could not be parsed",
+                plural(self.formulas_as_text.len(), "formula", "formulas")
+            ));
+        }
+        if !self.issues.is_empty() {
+            parts.push(format!(
+                "{} not restored",
+                plural(self.issues.len(), "item", "items")
+            ));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+
+    /// Add the cells and findings of `other`, a later part of the same load
+    pub fn merge(&mut self, other: ImportReport) {
+        self.cells += other.cells;
+        self.issues.extend(other.issues);
+        self.formulas_as_text.extend(other.formulas_as_text);
+        self.preserved_sections.extend(other.preserved_sections);
+        if other.unread_sidecar.is_some() {
+            self.unread_sidecar = other.unread_sidecar;
+        }
+    }
+
+    pub(crate) fn issue(&mut self, address: Option<CellAddress>, message: impl Into<String>) {
+        self.issues.push(FidelityIssue {
+            address,
+            message: message.into(),
+        });
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Imported {} cells", self.cells)?;
+        if !self.issues.is_empty() {
+            write!(f, ", {} not restored:", self.issues.len())?;
+            for issue in &self.issues {
+                write!(f, "\n  {}", issue)?;
+            }
+        }
+        if !self.formulas_as_text.is_empty() {
+            write!(f, "\nLoaded as text:")?;
+            for issue in &self.formulas_as_text {
+                write!(f, "\n  {}", issue)?;
+            }
+        }
+        if !self.preserved_sections.is_empty() {
+            write!(
+                f,
+                "\nKept unknown sidecar sections: {}",
+                self.preserved_sections.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// How a cell is written to the CSV: its formula, or its value in a form
+/// [`field_input`] reads back as the same value
+pub fn cell_field(cell: &Cell) -> String {
+    if let Some(formula) = &cell.formula_text {
+        return format!("={}", formula);
+    }
+    match &cell.raw_value {
+        CellValue::String(text) if field_value(text) != cell.raw_value => format!("'{}", text),
+        CellValue::Boolean(b) => b.to_string().to_uppercase(),
+        value => value.to_string(),
+    }
+}
+
+/// The input to store for a CSV field: formulas and apostrophe-quoted text
+/// as written, `TRUE` and `FALSE` in any case as booleans unless `options`
+/// keeps them as text, and anything else typed like a value entered in the
+/// grid
+pub fn field_input(field: &str, options: &CsvImportOptions) -> String {
+    let boolean = field.eq_ignore_ascii_case("true") || field.eq_ignore_ascii_case("false");
+    if boolean && !options.booleans {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    }
+}
+
+/// Value a non-formula field reads back as
+fn field_value(field: &str) -> CellValue {
+    if field.starts_with('=') {
+        return CellValue::Empty;
+    }
+    parse_cell_value(&field_input(field, &CsvImportOptions::default()))
+}
+
+/// Write rows of fields as CSV
+pub fn write_csv(rows: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    for row in rows {
+        for (i, field) in row.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            if field.contains([',', '"', '\n', '\r']) {
+                out.push('"');
+                out.push_str(&field.replace('"', "\"\""));
+                out.push('"');
+            } else {
+                out.push_str(field);
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Split CSV text into rows of fields. Quoted fields may hold commas, line
+/// breaks and doubled quotes; an unterminated quote runs to the end.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    DelimitedRows::new(text, ',').collect()
+}
+
+/// Rows of fields of delimited text, read one at a time, quoted as in
+/// [`parse_csv`] with any single-character delimiter
+pub struct DelimitedRows<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    delimiter: char,
+}
+
+impl<'a> DelimitedRows<'a> {
+    pub fn new(text: &'a str, delimiter: char) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            delimiter,
+        }
+    }
+}
+
+impl Iterator for DelimitedRows<'_> {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Vec<String>> {
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+
+        while let Some(c) = self.chars.next() {
+            if quoted {
+                match c {
+                    '"' if self.chars.peek() == Some(&'"') => {
+                        self.chars.next();
+                        field.push('"');
+                    }
+                    '"' => quoted = false,
+                    c => field.push(c),
+                }
+                continue;
+            }
+            match c {
+                '"' if field.is_empty() => quoted = true,
+                c if c == self.delimiter => row.push(std::mem::take(&mut field)),
+                '\r' if self.chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    row.push(field);
+                    return Some(row);
+                }
+                c => field.push(c),
+            }
+        }
+        if !field.is_empty() || !row.is_empty() {
+            row.push(field);
+            return Some(row);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ErrorType;
+
+    #[test]
+    fn test_csv_quoting_round_trips() {
+        let rows = vec![
+            vec!["plain".to_string(), "a,b".to_string(), String::new()],
+            vec![
+                "say \"hi\"".to_string(),
+                "two\nlines".to_string(),
+                "=SUM(A1,B1)".to_string(),
+            ],
+        ];
+        let csv = write_csv(&rows);
+        assert_eq!(
+            csv,
+            "plain,\"a,b\",\n\"say \"\"hi\"\"\",\"two\nlines\",\"=SUM(A1,B1)\"\n"
+        );
+        assert_eq!(parse_csv(&csv), rows);
+        assert_eq!(parse_csv("a,b\r\nc"), [vec!["a", "b"], vec!["c"]]);
+    }
+
+    #[test]
+    fn test_fields_read_back_as_the_same_value() {
+        let values = [
+            CellValue::Number(1.5),
+            CellValue::Number(-0.1),
+            CellValue::Boolean(true),
+            CellValue::from_string("hello".to_string()),
+            CellValue::from_string("42".to_string()),
+            CellValue::from_string("true".to_string()),
+            CellValue::from_string("=not a formula".to_string()),
+            CellValue::from_string("'quoted".to_string()),
+            CellValue::from_string("45 kg".to_string()),
+        ];
+        for value in values {
+            let field = cell_field(&Cell::new(value.clone()));
+            let input = field_input(&field, &CsvImportOptions::default());
+            assert_eq!(parse_cell_value(&input), value, "{}", field);
+        }
+        assert_eq!(cell_field(&Cell::new(CellValue::Boolean(false))), "FALSE");
+
+        let formula = Cell::with_formula(
+            CellValue::Error(ErrorType::DivideByZero.into()),
+            "A1/0".to_string(),
+        );
+        assert_eq!(cell_field(&formula), "=A1/0");
+    }
+}
@@ -1,11 +1,37 @@
 use crate::formula::ast::{CellRange, Expr};
 use crate::types::CellAddress;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Analyzes formula ASTs to extract cell dependencies
 pub struct DependencyAnalyzer;
 
+/// How the cells a formula reads change when it is replaced. Applying it
+/// leaves the edges of references both formulas make untouched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyDelta {
+    /// Cells only the new formula reads, in reading order
+    pub added: Vec<CellAddress>,
+    /// Cells only the old formula read, in reading order
+    pub removed: Vec<CellAddress>,
+}
+
+impl DependencyDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
 impl DependencyAnalyzer {
+    /// The references to add and remove when a formula reading `old` is
+    /// replaced by one reading `new`
+    pub fn delta(old: &HashSet<CellAddress>, new: &HashSet<CellAddress>) -> DependencyDelta {
+        let mut added: Vec<CellAddress> = new.difference(old).copied().collect();
+        let mut removed: Vec<CellAddress> = old.difference(new).copied().collect();
+        added.sort_by_key(|address| (address.row, address.col));
+        removed.sort_by_key(|address| (address.row, address.col));
+        DependencyDelta { added, removed }
+    }
+
     /// Extract all cell addresses referenced in a formula expression
     pub fn extract_dependencies(expr: &Expr) -> HashSet<CellAddress> {
         let mut dependencies = HashSet::new();
@@ -13,6 +39,23 @@ impl DependencyAnalyzer {
         dependencies
     }
 
+    /// Like [`Self::extract_dependencies`], also reading the cells of the
+    /// named ranges the expression uses, found in `ranges` by uppercased name
+    pub fn extract_dependencies_with_names(
+        expr: &Expr,
+        ranges: &HashMap<String, CellRange>,
+    ) -> HashSet<CellAddress> {
+        let mut dependencies = Self::extract_dependencies(expr);
+        if !ranges.is_empty() {
+            for name in Self::extract_names(expr) {
+                if let Some(range) = ranges.get(&name) {
+                    dependencies.extend(range.cells());
+                }
+            }
+        }
+        dependencies
+    }
+
     /// Recursively extract dependencies from an expression
     fn extract_from_expr(expr: &Expr, dependencies: &mut HashSet<CellAddress>) {
         match expr {
@@ -27,8 +70,8 @@ impl DependencyAnalyzer {
                 }
             }
 
-            Expr::FunctionCall { args, .. } => {
-                // Recursively check all function arguments
+            Expr::FunctionCall { args, .. } | Expr::Union { areas: args } => {
+                // Recursively check all function arguments and union areas
                 for arg in args {
                     Self::extract_from_expr(arg, dependencies);
                 }
@@ -38,13 +81,17 @@ impl DependencyAnalyzer {
                 Self::extract_from_expr(expr, dependencies);
             }
 
-            Expr::BinaryOp { left, right, .. } => {
+            // Both operands of an intersection are read, not just the cells
+            // they share, so edits inside either area recalculate it
+            Expr::BinaryOp { left, right, .. } | Expr::Intersection { left, right } => {
                 Self::extract_from_expr(left, dependencies);
                 Self::extract_from_expr(right, dependencies);
             }
 
-            Expr::Literal { .. } => {
-                // Literals don't have dependencies
+            Expr::Literal { .. } | Expr::Name { .. } | Expr::SheetReference { .. } => {
+                // Literals and names don't depend on cells, and the cells of
+                // other sheets are not this sheet's; see
+                // [`Self::extract_sheet_dependencies`]
             }
         }
     }
@@ -52,7 +99,11 @@ impl DependencyAnalyzer {
     /// Check if an expression contains any cell references
     pub fn has_dependencies(expr: &Expr) -> bool {
         match expr {
-            Expr::Reference { .. } | Expr::Range { .. } => true,
+            Expr::Reference { .. }
+            | Expr::Range { .. }
+            | Expr::SheetReference { .. }
+            | Expr::Union { .. }
+            | Expr::Intersection { .. } => true,
 
             Expr::FunctionCall { args, .. } => args.iter().any(Self::has_dependencies),
 
@@ -62,7 +113,7 @@ impl DependencyAnalyzer {
                 Self::has_dependencies(left) || Self::has_dependencies(right)
             }
 
-            Expr::Literal { .. } => false,
+            Expr::Literal { .. } | Expr::Name { .. } => false,
         }
     }
 
@@ -78,17 +129,105 @@ impl DependencyAnalyzer {
 
             Expr::Range { range, .. } => range.contains(target),
 
-            Expr::FunctionCall { args, .. } => {
+            Expr::FunctionCall { args, .. } | Expr::Union { areas: args } => {
                 args.iter().any(|arg| Self::references_cell(arg, target))
             }
 
             Expr::UnaryOp { expr, .. } => Self::references_cell(expr, target),
 
-            Expr::BinaryOp { left, right, .. } => {
+            Expr::BinaryOp { left, right, .. } | Expr::Intersection { left, right } => {
                 Self::references_cell(left, target) || Self::references_cell(right, target)
             }
 
-            Expr::Literal { .. } => false,
+            Expr::Literal { .. } | Expr::Name { .. } | Expr::SheetReference { .. } => false,
+        }
+    }
+
+    /// Extract the cells of other sheets a formula reads, with the sheet
+    /// names as written
+    pub fn extract_sheet_dependencies(expr: &Expr) -> HashSet<(String, CellAddress)> {
+        let mut dependencies = HashSet::new();
+        Self::extract_sheets_from_expr(expr, &mut dependencies);
+        dependencies
+    }
+
+    fn extract_sheets_from_expr(expr: &Expr, dependencies: &mut HashSet<(String, CellAddress)>) {
+        match expr {
+            Expr::SheetReference { sheet, reference } => {
+                for cell in Self::extract_dependencies(reference) {
+                    dependencies.insert((sheet.clone(), cell));
+                }
+            }
+            Expr::FunctionCall { args, .. } | Expr::Union { areas: args } => {
+                for arg in args {
+                    Self::extract_sheets_from_expr(arg, dependencies);
+                }
+            }
+            Expr::UnaryOp { expr, .. } => Self::extract_sheets_from_expr(expr, dependencies),
+            Expr::BinaryOp { left, right, .. } | Expr::Intersection { left, right } => {
+                Self::extract_sheets_from_expr(left, dependencies);
+                Self::extract_sheets_from_expr(right, dependencies);
+            }
+            Expr::Literal { .. }
+            | Expr::Reference { .. }
+            | Expr::Range { .. }
+            | Expr::Name { .. } => {}
+        }
+    }
+
+    /// Extract the defined names used in a formula expression, uppercased
+    /// since names are matched without regard to case
+    pub fn extract_names(expr: &Expr) -> HashSet<String> {
+        let mut names = HashSet::new();
+        Self::extract_names_from_expr(expr, &mut names);
+        names
+    }
+
+    fn extract_names_from_expr(expr: &Expr, names: &mut HashSet<String>) {
+        match expr {
+            Expr::Name { name } => {
+                names.insert(name.to_uppercase());
+            }
+            Expr::FunctionCall { args, .. } => {
+                for arg in args {
+                    Self::extract_names_from_expr(arg, names);
+                }
+            }
+            Expr::UnaryOp { expr, .. } => Self::extract_names_from_expr(expr, names),
+            Expr::BinaryOp { left, right, .. } => {
+                Self::extract_names_from_expr(left, names);
+                Self::extract_names_from_expr(right, names);
+            }
+            Expr::Literal { .. }
+            | Expr::Reference { .. }
+            | Expr::Range { .. }
+            | Expr::SheetReference { .. }
+            | Expr::Union { .. }
+            | Expr::Intersection { .. } => {}
+        }
+    }
+
+    /// Check if an expression uses a defined name, ignoring case
+    pub fn references_name(expr: &Expr, target: &str) -> bool {
+        match expr {
+            Expr::Name { name } => name.eq_ignore_ascii_case(target),
+
+            Expr::FunctionCall { args, .. } => {
+                args.iter().any(|arg| Self::references_name(arg, target))
+            }
+
+            Expr::UnaryOp { expr, .. } => Self::references_name(expr, target),
+
+            Expr::BinaryOp { left, right, .. } => {
+                Self::references_name(left, target) || Self::references_name(right, target)
+            }
+
+            Expr::Literal { .. }
+            | Expr::Reference { .. }
+            | Expr::Range { .. }
+            | Expr::SheetReference { .. }
+            | Expr::Union { .. }
+            | Expr::Intersection { .. } => false,
         }
     }
 
@@ -161,6 +300,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_delta_lists_only_changed_references() {
+        let old = DependencyAnalyzer::extract_dependencies(&FormulaParser::parse("A1+B1").unwrap());
+        let new = DependencyAnalyzer::extract_dependencies(&FormulaParser::parse("B1+C2").unwrap());
+        let delta = DependencyAnalyzer::delta(&old, &new);
+        assert_eq!(delta.added, vec![CellAddress::new(2, 1)]);
+        assert_eq!(delta.removed, vec![CellAddress::new(0, 0)]);
+        assert!(DependencyAnalyzer::delta(&new, &new).is_empty());
+    }
+
     #[test]
     fn test_references_cell() {
         let expr = FormulaParser::parse("A1 + B2").unwrap();
@@ -179,6 +328,18 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_extract_named_range_dependencies() {
+        let expr = FormulaParser::parse("SUM(sales) + C3 + TaxRate").unwrap();
+        let sales = CellRange::new(CellAddress::new(0, 0), CellAddress::new(0, 2));
+        let ranges = HashMap::from([("SALES".to_string(), sales)]);
+        let deps = DependencyAnalyzer::extract_dependencies_with_names(&expr, &ranges);
+
+        assert_eq!(deps.len(), 4);
+        assert!(sales.cells().all(|cell| deps.contains(&cell)));
+        assert!(deps.contains(&CellAddress::new(2, 2))); // C3
+    }
+
     #[test]
     fn test_references_range() {
         let expr = FormulaParser::parse("A1 + C3").unwrap();
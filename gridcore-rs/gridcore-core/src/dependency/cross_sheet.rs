@@ -0,0 +1,142 @@
+//! Formulas reading the cells of other sheets.
+//!
+//! Each sheet's [`DependencyGraph`](super::DependencyGraph) only knows its
+//! own cells, so references such as `Sheet2!A1` are kept here instead,
+//! keyed by sheet and address on both ends. An edit on `Sheet2` finds the
+//! formulas on `Sheet1` to recalculate through [`Self::readers`].
+
+use crate::types::CellAddress;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::collections::BTreeMap;
+
+/// A cell of a particular sheet
+pub type SheetCell = (String, CellAddress);
+
+/// Cross-sheet dependency edges in both directions
+#[derive(Debug, Clone, Default)]
+pub struct CrossSheetDependencies {
+    /// Formula → the cells of other sheets it reads
+    reads: FxHashMap<SheetCell, FxHashSet<SheetCell>>,
+    /// Cell → the formulas on other sheets reading it
+    readers: FxHashMap<SheetCell, FxHashSet<SheetCell>>,
+}
+
+impl CrossSheetDependencies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace what the formula at `address` on `sheet` reads from other
+    /// sheets; nothing forgets the formula
+    pub fn set_reads(
+        &mut self,
+        sheet: &str,
+        address: CellAddress,
+        reads: impl IntoIterator<Item = SheetCell>,
+    ) {
+        let formula = (sheet.to_string(), address);
+        if let Some(old) = self.reads.remove(&formula) {
+            for cell in old {
+                if let Some(readers) = self.readers.get_mut(&cell) {
+                    readers.remove(&formula);
+                    if readers.is_empty() {
+                        self.readers.remove(&cell);
+                    }
+                }
+            }
+        }
+        let reads: FxHashSet<SheetCell> = reads.into_iter().collect();
+        if reads.is_empty() {
+            return;
+        }
+        for cell in &reads {
+            self.readers
+                .entry(cell.clone())
+                .or_default()
+                .insert(formula.clone());
+        }
+        self.reads.insert(formula, reads);
+    }
+
+    /// The formulas reading any of `cells` of `sheet`, grouped by their
+    /// sheet, each sheet's in row-major order
+    pub fn readers(
+        &self,
+        sheet: &str,
+        cells: &[CellAddress],
+    ) -> BTreeMap<String, Vec<CellAddress>> {
+        let mut found: BTreeMap<String, Vec<CellAddress>> = BTreeMap::new();
+        let mut key = (sheet.to_string(), CellAddress::new(0, 0));
+        for address in cells {
+            key.1 = *address;
+            for (reader_sheet, reader) in self.readers.get(&key).into_iter().flatten() {
+                found.entry(reader_sheet.clone()).or_default().push(*reader);
+            }
+        }
+        for addresses in found.values_mut() {
+            addresses.sort_by_key(|address| (address.row, address.col));
+            addresses.dedup();
+        }
+        found
+    }
+
+    /// The formulas reading any cell of `sheet`, grouped as by
+    /// [`Self::readers`]
+    pub fn readers_of_sheet(&self, sheet: &str) -> BTreeMap<String, Vec<CellAddress>> {
+        let cells: Vec<CellAddress> = self
+            .readers
+            .keys()
+            .filter(|(name, _)| name == sheet)
+            .map(|(_, address)| *address)
+            .collect();
+        self.readers(sheet, &cells)
+    }
+
+    /// The cells of other sheets the formula at `address` on `sheet` reads
+    pub fn reads_of(&self, sheet: &str, address: &CellAddress) -> Vec<SheetCell> {
+        let mut cells: Vec<SheetCell> = self
+            .reads
+            .get(&(sheet.to_string(), *address))
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        cells.sort_by(|a, b| (&a.0, a.1.row, a.1.col).cmp(&(&b.0, b.1.row, b.1.col)));
+        cells
+    }
+
+    pub fn clear(&mut self) {
+        self.reads.clear();
+        self.readers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(sheet: &str, a1: &str) -> SheetCell {
+        (sheet.to_string(), CellAddress::from_a1(a1).unwrap())
+    }
+
+    #[test]
+    fn test_readers_follow_replaced_reads() {
+        let mut deps = CrossSheetDependencies::new();
+        let formula = CellAddress::from_a1("C1").unwrap();
+        deps.set_reads("Sheet1", formula, [cell("Data", "A1"), cell("Data", "A2")]);
+
+        let readers = deps.readers("Data", &[CellAddress::from_a1("A2").unwrap()]);
+        assert_eq!(readers.get("Sheet1"), Some(&vec![formula]));
+        assert_eq!(deps.readers_of_sheet("Data").len(), 1);
+
+        deps.set_reads("Sheet1", formula, [cell("Data", "B1")]);
+        assert!(
+            deps.readers("Data", &[CellAddress::from_a1("A2").unwrap()])
+                .is_empty()
+        );
+        assert_eq!(deps.reads_of("Sheet1", &formula), vec![cell("Data", "B1")]);
+
+        deps.set_reads("Sheet1", formula, []);
+        assert!(deps.readers_of_sheet("Data").is_empty());
+    }
+}
@@ -1,9 +1,16 @@
+use super::analyzer::{DependencyAnalyzer, DependencyDelta};
+use crate::memory::{MemoryFootprint, hash_table_bytes};
 use crate::types::CellAddress;
 use crate::{Result, SpreadsheetError};
+use petgraph::Direction;
 use petgraph::algo::toposort;
-use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::graph::{DiGraph, Edge, EdgeIndex, Node, NodeIndex};
 use petgraph::visit::EdgeRef;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem::size_of;
 
 /// Manages dependencies between cells in a spreadsheet
 #[derive(Debug, Clone)]
@@ -14,6 +21,17 @@ pub struct DependencyGraph {
 
     /// Mapping from cell address to graph node index
     node_map: FxHashMap<CellAddress, NodeIndex>,
+
+    /// Name nodes: the cells using each defined name, keyed by uppercased
+    /// name. Names have no dependencies of their own, so they stay out of
+    /// the cell graph and its calculation order.
+    name_dependents: FxHashMap<String, FxHashSet<CellAddress>>,
+
+    /// Formulas whose value may be out of date, waiting for a recalculation
+    dirty: FxHashSet<CellAddress>,
+
+    /// Formulas calling volatile functions, recalculated on every edit
+    volatile: FxHashSet<CellAddress>,
 }
 
 impl DependencyGraph {
@@ -22,6 +40,9 @@ impl DependencyGraph {
         DependencyGraph {
             graph: DiGraph::new(),
             node_map: FxHashMap::default(),
+            name_dependents: FxHashMap::default(),
+            dirty: FxHashSet::default(),
+            volatile: FxHashSet::default(),
         }
     }
 
@@ -44,31 +65,114 @@ impl DependencyGraph {
         self.graph.add_edge(from_idx, to_idx, ());
     }
 
+    /// Add a dependency of a cell on a defined name
+    pub fn add_name_dependency(&mut self, from: CellAddress, name: &str) {
+        self.name_dependents
+            .entry(name.to_uppercase())
+            .or_default()
+            .insert(from);
+    }
+
     /// Remove all dependencies for a cell (when its formula changes or is deleted)
     pub fn remove_dependencies_for(&mut self, address: &CellAddress) {
         if let Some(&idx) = self.node_map.get(address) {
             // Remove all outgoing edges (dependencies)
             let edges: Vec<_> = self.graph.edges(idx).map(|e| e.id()).collect();
-            for edge in edges {
-                self.graph.remove_edge(edge);
+            self.remove_edges(edges);
+        }
+        self.remove_name_dependencies_for(address);
+    }
+
+    /// Remove edges by index. Removing one moves the last edge into its
+    /// index, so they go from the highest index down to keep the rest valid.
+    fn remove_edges(&mut self, mut edges: Vec<EdgeIndex>) {
+        edges.sort_unstable_by_key(|edge| std::cmp::Reverse(edge.index()));
+        for edge in edges {
+            self.graph.remove_edge(edge);
+        }
+    }
+
+    /// Replace the cells `address` depends on with `dependencies`, dropping
+    /// nodes that are left without any edges. Only the references that
+    /// changed are touched, see [`Self::apply_delta`].
+    pub fn set_dependencies(
+        &mut self,
+        address: CellAddress,
+        dependencies: impl IntoIterator<Item = CellAddress>,
+    ) {
+        let current: HashSet<CellAddress> = self.get_dependencies(&address).into_iter().collect();
+        let dependencies: HashSet<CellAddress> = dependencies.into_iter().collect();
+        let delta = DependencyAnalyzer::delta(&current, &dependencies);
+        self.apply_delta(address, &delta);
+    }
+
+    /// Add and remove the references of `address` that `delta` lists,
+    /// leaving the rest of the graph alone, and drop nodes left without any
+    /// edges. Costs what the delta and the cell's own edges do, however
+    /// large the sheet.
+    pub fn apply_delta(&mut self, address: CellAddress, delta: &DependencyDelta) {
+        if !delta.removed.is_empty()
+            && let Some(&idx) = self.node_map.get(&address)
+        {
+            let removed: FxHashSet<CellAddress> = delta.removed.iter().copied().collect();
+            let edges: Vec<EdgeIndex> = self
+                .graph
+                .edges(idx)
+                .filter(|edge| removed.contains(&self.graph[edge.target()]))
+                .map(|edge| edge.id())
+                .collect();
+            self.remove_edges(edges);
+        }
+        for &dependency in &delta.added {
+            self.add_dependency(address, dependency);
+        }
+
+        for cell in std::iter::once(&address).chain(&delta.removed) {
+            if self.is_isolated(cell) {
+                self.remove_node(cell);
             }
         }
     }
 
     /// Remove a cell completely from the graph
     pub fn remove_cell(&mut self, address: &CellAddress) {
+        self.remove_node(address);
+        self.remove_name_dependencies_for(address);
+        self.dirty.remove(address);
+        self.volatile.remove(address);
+    }
+
+    fn remove_node(&mut self, address: &CellAddress) {
         if let Some(idx) = self.node_map.remove(address) {
             self.graph.remove_node(idx);
+            // The last node takes the index of the removed one
+            if let Some(&moved) = self.graph.node_weight(idx) {
+                self.node_map.insert(moved, idx);
+            }
         }
     }
 
+    fn is_isolated(&self, address: &CellAddress) -> bool {
+        self.node_map
+            .get(address)
+            .is_some_and(|&idx| self.graph.neighbors_undirected(idx).next().is_none())
+    }
+
+    fn remove_name_dependencies_for(&mut self, address: &CellAddress) {
+        self.name_dependents.retain(|_, cells| {
+            cells.remove(address);
+            !cells.is_empty()
+        });
+    }
+
     /// Get all cells that depend on the given cell (cells that reference this cell)
     pub fn get_dependents(&self, address: &CellAddress) -> Vec<CellAddress> {
         if let Some(&idx) = self.node_map.get(address) {
             // Find nodes with edges TO this node (incoming edges)
+            let mut seen = FxHashSet::default();
             self.graph
-                .node_indices()
-                .filter(|&node| self.graph.edges(node).any(|e| e.target() == idx))
+                .neighbors_directed(idx, Direction::Incoming)
+                .filter(|&node| seen.insert(node))
                 .map(|node| self.graph[node])
                 .collect()
         } else {
@@ -76,6 +180,17 @@ impl DependencyGraph {
         }
     }
 
+    /// Get all cells that use the given defined name, ignoring case
+    pub fn get_name_dependents(&self, name: &str) -> Vec<CellAddress> {
+        let mut dependents: Vec<CellAddress> = self
+            .name_dependents
+            .get(&name.to_uppercase())
+            .map(|cells| cells.iter().copied().collect())
+            .unwrap_or_default();
+        dependents.sort_by_key(|address| (address.row, address.col));
+        dependents
+    }
+
     /// Get all cells that this cell depends on (cells referenced by this cell)
     pub fn get_dependencies(&self, address: &CellAddress) -> Vec<CellAddress> {
         if let Some(&idx) = self.node_map.get(address) {
@@ -88,6 +203,34 @@ impl DependencyGraph {
         }
     }
 
+    /// Every cell `address` depends on, directly or through other cells
+    pub fn all_dependencies(&self, address: &CellAddress) -> Vec<CellAddress> {
+        self.reachable(address, Direction::Outgoing)
+    }
+
+    /// Every cell depending on `address`, directly or through other cells
+    pub fn all_dependents(&self, address: &CellAddress) -> Vec<CellAddress> {
+        self.reachable(address, Direction::Incoming)
+    }
+
+    /// Cells reached from `address` along edges in `direction`, without
+    /// `address` itself unless it is part of a cycle
+    fn reachable(&self, address: &CellAddress, direction: Direction) -> Vec<CellAddress> {
+        let Some(&start) = self.node_map.get(address) else {
+            return Vec::new();
+        };
+        let mut seen = FxHashSet::default();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            for next in self.graph.neighbors_directed(node, direction) {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen.into_iter().map(|node| self.graph[node]).collect()
+    }
+
     /// Get the calculation order for all cells (topological sort)
     /// Returns cells in the order they should be calculated
     pub fn get_calculation_order(&self) -> Result<Vec<CellAddress>> {
@@ -101,8 +244,213 @@ impl DependencyGraph {
                 order.reverse();
                 Ok(order)
             }
-            Err(_) => Err(SpreadsheetError::CircularDependency),
+            Err(cycle) => Err(self
+                .find_cycle(&self.graph[cycle.node_id()])
+                .map_or(SpreadsheetError::CircularDependency, |cells| {
+                    SpreadsheetError::CircularDependencyWith(cells)
+                })),
+        }
+    }
+
+    /// `starts` and every cell that reads them, directly or through other
+    /// cells, in the order they should be recalculated. Cells in a cycle
+    /// are each listed once.
+    pub fn recalculation_order(&self, starts: &[CellAddress]) -> Vec<CellAddress> {
+        let mut visited = FxHashSet::default();
+        let mut finished = Vec::new();
+        for start in starts {
+            let Some(&idx) = self.node_map.get(start) else {
+                continue;
+            };
+            if !visited.insert(idx) {
+                continue;
+            }
+            // Depth-first over dependents; a cell finishes after everything
+            // that reads it
+            let mut stack = vec![(idx, self.dependent_nodes(idx))];
+            while let Some((node, pending)) = stack.last_mut() {
+                match pending.pop() {
+                    Some(next) => {
+                        if visited.insert(next) {
+                            let dependents = self.dependent_nodes(next);
+                            stack.push((next, dependents));
+                        }
+                    }
+                    None => {
+                        finished.push(self.graph[*node]);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        finished.reverse();
+
+        // Cells without a node read nothing and nothing reads them
+        let mut order: Vec<CellAddress> = starts
+            .iter()
+            .filter(|start| !self.node_map.contains_key(start))
+            .copied()
+            .collect();
+        order.extend(finished);
+        order
+    }
+
+    fn dependent_nodes(&self, idx: NodeIndex) -> Vec<NodeIndex> {
+        self.graph
+            .neighbors_directed(idx, Direction::Incoming)
+            .collect()
+    }
+
+    /// Mark formulas as waiting for a recalculation
+    pub fn mark_dirty(&mut self, cells: impl IntoIterator<Item = CellAddress>) {
+        self.dirty.extend(cells);
+    }
+
+    /// Mark a formula as up to date, or as waiting for a recalculation
+    pub fn set_dirty(&mut self, address: CellAddress, dirty: bool) {
+        if dirty {
+            self.dirty.insert(address);
+        } else {
+            self.dirty.remove(&address);
+        }
+    }
+
+    pub fn is_dirty(&self, address: &CellAddress) -> bool {
+        self.dirty.contains(address)
+    }
+
+    /// Formulas waiting for a recalculation, in reading order
+    pub fn dirty_cells(&self) -> Vec<CellAddress> {
+        let mut cells: Vec<CellAddress> = self.dirty.iter().copied().collect();
+        cells.sort_by_key(|address| (address.row, address.col));
+        cells
+    }
+
+    /// Mark whether the formula at `address` calls a volatile function
+    pub fn set_volatile(&mut self, address: CellAddress, volatile: bool) {
+        if volatile {
+            self.volatile.insert(address);
+        } else {
+            self.volatile.remove(&address);
+        }
+    }
+
+    /// Replace every volatile formula with `cells`
+    pub fn set_volatile_cells(&mut self, cells: impl IntoIterator<Item = CellAddress>) {
+        self.volatile = cells.into_iter().collect();
+    }
+
+    pub fn is_volatile(&self, address: &CellAddress) -> bool {
+        self.volatile.contains(address)
+    }
+
+    /// Formulas calling volatile functions, in reading order
+    pub fn volatile_cells(&self) -> Vec<CellAddress> {
+        let mut cells: Vec<CellAddress> = self.volatile.iter().copied().collect();
+        cells.sort_by_key(|address| (address.row, address.col));
+        cells
+    }
+
+    /// Dirty formulas for which `in_scope` holds, in recalculation order,
+    /// each with whether it reads a dirty formula outside the scope,
+    /// directly or through other cells. Those would be computed from values
+    /// that may be out of date.
+    pub fn dirty_in_scope(
+        &self,
+        in_scope: impl Fn(&CellAddress) -> bool,
+    ) -> Vec<(CellAddress, bool)> {
+        let starts: Vec<CellAddress> = self
+            .dirty_cells()
+            .into_iter()
+            .filter(|address| in_scope(address))
+            .collect();
+        let mut tainted = FxHashSet::default();
+        self.recalculation_order(&starts)
+            .into_iter()
+            .filter(|address| self.dirty.contains(address) && in_scope(address))
+            .map(|address| {
+                let stale = self.get_dependencies(&address).iter().any(|precedent| {
+                    tainted.contains(precedent)
+                        || (self.dirty.contains(precedent) && !in_scope(precedent))
+                });
+                if stale {
+                    tainted.insert(address);
+                }
+                (address, stale)
+            })
+            .collect()
+    }
+
+    /// Compare the graph with the references each formula cell makes,
+    /// given as the cells read by every formula. Name dependencies are not
+    /// checked.
+    pub fn verify(
+        &self,
+        expected: &HashMap<CellAddress, HashSet<CellAddress>>,
+    ) -> DependencyReport {
+        let mut report = DependencyReport::default();
+        for (&cell, references) in expected {
+            let actual: FxHashSet<CellAddress> = self.get_dependencies(&cell).into_iter().collect();
+            report.missing.extend(
+                references
+                    .iter()
+                    .filter(|reference| !actual.contains(reference))
+                    .map(|&reference| (cell, reference)),
+            );
+        }
+        for edge in self.graph.edge_references() {
+            let (from, to) = (self.graph[edge.source()], self.graph[edge.target()]);
+            if !expected.get(&from).is_some_and(|refs| refs.contains(&to)) {
+                report.dangling.push((from, to));
+            }
         }
+        report.orphans = self
+            .node_map
+            .keys()
+            .filter(|address| self.is_isolated(address))
+            .copied()
+            .collect();
+
+        report
+            .missing
+            .sort_by_key(|(from, to)| row_major_pair(from, to));
+        report
+            .dangling
+            .sort_by_key(|(from, to)| row_major_pair(from, to));
+        report
+            .orphans
+            .sort_by_key(|address| (address.row, address.col));
+        report
+    }
+
+    /// Rebuild the entries `report` found wrong from `expected`, leaving
+    /// the rest of the graph alone. Returns the cells whose dependencies
+    /// were rebuilt.
+    pub fn repair(
+        &mut self,
+        expected: &HashMap<CellAddress, HashSet<CellAddress>>,
+        report: &DependencyReport,
+    ) -> Vec<CellAddress> {
+        let mut cells: Vec<CellAddress> = report
+            .missing
+            .iter()
+            .chain(&report.dangling)
+            .map(|&(from, _)| from)
+            .collect::<FxHashSet<_>>()
+            .into_iter()
+            .collect();
+        cells.sort_by_key(|address| (address.row, address.col));
+
+        for &cell in &cells {
+            let references = expected.get(&cell).into_iter().flatten().copied();
+            self.set_dependencies(cell, references);
+        }
+        for orphan in &report.orphans {
+            if self.is_isolated(orphan) {
+                self.remove_node(orphan);
+            }
+        }
+        cells
     }
 
     /// Check if adding a dependency would create a cycle
@@ -122,10 +470,69 @@ impl DependencyGraph {
         petgraph::algo::has_path_connecting(&self.graph, to_idx, from_idx, None)
     }
 
+    /// The cells of a shortest cycle through `address`, following what
+    /// each cell depends on and ending back at `address`, e.g. `[A1, B1,
+    /// A1]` when A1 and B1 read each other, or `[A1, A1]` for a formula
+    /// reading its own cell
+    pub fn find_cycle(&self, address: &CellAddress) -> Option<Vec<CellAddress>> {
+        let &start = self.node_map.get(address)?;
+        // Searched through the cells reading `address`, which an edit
+        // recalculates anyway, rather than everything it reads
+        let mut cells = self.shortest_path(start, start, Direction::Incoming)?;
+        cells.reverse();
+        Some(cells)
+    }
+
+    /// The cycle adding the dependency `from` → `to` would close, as
+    /// [`Self::find_cycle`] gives it for `from`
+    pub fn cycle_if_added(&self, from: &CellAddress, to: &CellAddress) -> Option<Vec<CellAddress>> {
+        if from == to {
+            return Some(vec![*from, *from]);
+        }
+        let (&from_idx, &to_idx) = (self.node_map.get(from)?, self.node_map.get(to)?);
+        let mut cells = vec![*from];
+        cells.extend(self.shortest_path(to_idx, from_idx, Direction::Outgoing)?);
+        Some(cells)
+    }
+
+    /// Breadth-first path of at least one edge along `direction` from
+    /// `start` to `end`, both included
+    fn shortest_path(
+        &self,
+        start: NodeIndex,
+        end: NodeIndex,
+        direction: Direction,
+    ) -> Option<Vec<CellAddress>> {
+        let mut previous: FxHashMap<NodeIndex, NodeIndex> = FxHashMap::default();
+        let mut queue = std::collections::VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            for next in self.graph.neighbors_directed(node, direction) {
+                if next == end {
+                    let mut path = vec![self.graph[end], self.graph[node]];
+                    let mut at = node;
+                    while at != start {
+                        at = previous[&at];
+                        path.push(self.graph[at]);
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                if next != start && !previous.contains_key(&next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
     /// Clear all dependencies
     pub fn clear(&mut self) {
         self.graph.clear();
         self.node_map.clear();
+        self.name_dependents.clear();
+        self.dirty.clear();
+        self.volatile.clear();
     }
 
     /// Get the number of cells in the dependency graph
@@ -145,6 +552,78 @@ impl Default for DependencyGraph {
     }
 }
 
+impl MemoryFootprint for DependencyGraph {
+    fn footprint_bytes(&self) -> usize {
+        let (nodes, edges) = self.graph.capacity();
+        nodes * size_of::<Node<CellAddress>>()
+            + edges * size_of::<Edge<()>>()
+            + hash_table_bytes::<CellAddress, NodeIndex>(self.node_map.capacity())
+            + hash_table_bytes::<String, FxHashSet<CellAddress>>(self.name_dependents.capacity())
+            + self
+                .name_dependents
+                .iter()
+                .map(|(name, cells)| {
+                    name.capacity() + hash_table_bytes::<CellAddress, ()>(cells.capacity())
+                })
+                .sum::<usize>()
+            + hash_table_bytes::<CellAddress, ()>(self.dirty.capacity())
+            + hash_table_bytes::<CellAddress, ()>(self.volatile.capacity())
+    }
+
+    fn compact(&mut self) {
+        self.graph.shrink_to_fit();
+        self.node_map.shrink_to_fit();
+        self.name_dependents
+            .values_mut()
+            .for_each(FxHashSet::shrink_to_fit);
+        self.name_dependents.shrink_to_fit();
+        self.dirty.shrink_to_fit();
+        self.volatile.shrink_to_fit();
+    }
+}
+
+fn row_major_pair(from: &CellAddress, to: &CellAddress) -> (u32, u32, u32, u32) {
+    (from.row, from.col, to.row, to.col)
+}
+
+/// Differences between a dependency graph and the formulas it describes.
+/// Edges are `(dependent, precedent)` pairs; everything is listed in
+/// reading order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DependencyReport {
+    /// References a formula makes that the graph lacks
+    pub missing: Vec<(CellAddress, CellAddress)>,
+    /// Edges no formula accounts for
+    pub dangling: Vec<(CellAddress, CellAddress)>,
+    /// Nodes without any edges
+    pub orphans: Vec<CellAddress>,
+}
+
+impl DependencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.dangling.is_empty() && self.orphans.is_empty()
+    }
+}
+
+impl fmt::Display for DependencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_consistent() {
+            return write!(f, "dependency graph consistent");
+        }
+        write!(
+            f,
+            "{} missing edge(s), {} dangling edge(s), {} orphan node(s)",
+            self.missing.len(),
+            self.dangling.len(),
+            self.orphans.len()
+        )?;
+        if let Some((from, to)) = self.missing.first().or(self.dangling.first()) {
+            write!(f, ", first {} -> {}", from, to)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,13 +690,35 @@ mod tests {
         // Actually add it to create the cycle
         graph.add_dependency(c1, a1);
 
-        // Calculation order should fail due to cycle
+        // Calculation order should fail due to cycle, naming its cells
         let result = graph.get_calculation_order();
         assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
-            SpreadsheetError::CircularDependency
+            SpreadsheetError::CircularDependencyWith(cells) if cells.len() == 4
         ));
+        assert_eq!(graph.find_cycle(&b1), Some(vec![b1, c1, a1, b1]));
+    }
+
+    #[test]
+    fn test_cycle_paths() {
+        let mut graph = DependencyGraph::new();
+        let [a1, b1, c1, d1] = [0, 1, 2, 3].map(|col| CellAddress::new(col, 0));
+        graph.add_dependency(a1, b1);
+        graph.add_dependency(b1, c1);
+        graph.add_dependency(b1, d1);
+
+        assert_eq!(graph.find_cycle(&a1), None);
+        assert_eq!(graph.cycle_if_added(&d1, &a1), Some(vec![d1, a1, b1, d1]));
+        assert_eq!(graph.cycle_if_added(&a1, &d1), None);
+        assert_eq!(graph.cycle_if_added(&d1, &d1), Some(vec![d1, d1]));
+
+        // The shortest way round is reported
+        graph.add_dependency(c1, a1);
+        graph.add_dependency(d1, b1);
+        assert_eq!(graph.find_cycle(&b1), Some(vec![b1, d1, b1]));
+        graph.add_dependency(a1, a1);
+        assert_eq!(graph.find_cycle(&a1), Some(vec![a1, a1]));
     }
 
     #[test]
@@ -240,4 +741,166 @@ mod tests {
         assert_eq!(graph.get_dependents(&b1).len(), 0);
         assert_eq!(graph.get_dependents(&c1).len(), 0);
     }
+
+    #[test]
+    fn test_name_dependents() {
+        let mut graph = DependencyGraph::new();
+        let a1 = CellAddress::new(0, 0);
+        let b2 = CellAddress::new(1, 1);
+
+        graph.add_name_dependency(b2, "TaxRate");
+        graph.add_name_dependency(a1, "TAXRATE");
+        assert_eq!(graph.get_name_dependents("taxrate"), vec![a1, b2]);
+
+        graph.remove_dependencies_for(&a1);
+        assert_eq!(graph.get_name_dependents("TaxRate"), vec![b2]);
+        graph.remove_cell(&b2);
+        assert!(graph.get_name_dependents("TaxRate").is_empty());
+    }
+
+    #[test]
+    fn test_removed_nodes_keep_the_map_in_step() {
+        let mut graph = DependencyGraph::new();
+        let [a1, b1, c1, d1] = [0, 1, 2, 3].map(|col| CellAddress::new(col, 0));
+        graph.add_dependency(a1, b1);
+        graph.add_dependency(c1, d1);
+
+        // D1 is the last node and takes over A1's index
+        graph.remove_cell(&a1);
+        assert_eq!(graph.get_dependencies(&c1), vec![d1]);
+        assert_eq!(graph.get_dependents(&d1), vec![c1]);
+
+        // Replacing C1's dependencies drops the nodes left without edges
+        graph.set_dependencies(c1, []);
+        assert_eq!(graph.len(), 1);
+        graph.set_dependencies(b1, [a1, a1]);
+        assert_eq!(graph.get_dependencies(&b1), vec![a1]);
+    }
+
+    #[test]
+    fn test_transitive_queries_follow_the_chain() {
+        let mut graph = DependencyGraph::new();
+        let [a1, b1, c1, d1] = [0, 1, 2, 3].map(|col| CellAddress::new(col, 0));
+        // C1 reads B1, which reads A1; D1 reads A1 and C1
+        graph.add_dependency(b1, a1);
+        graph.add_dependency(c1, b1);
+        graph.add_dependency(d1, a1);
+        graph.add_dependency(d1, c1);
+
+        let sorted = |mut cells: Vec<CellAddress>| {
+            cells.sort_by_key(|a| a.col);
+            cells
+        };
+        assert_eq!(sorted(graph.all_dependents(&a1)), vec![b1, c1, d1]);
+        assert_eq!(sorted(graph.all_dependencies(&d1)), vec![a1, b1, c1]);
+        assert_eq!(graph.all_dependencies(&a1), vec![]);
+        assert_eq!(graph.all_dependents(&CellAddress::new(9, 9)), vec![]);
+    }
+
+    #[test]
+    fn test_recalculation_order_follows_dependents() {
+        let mut graph = DependencyGraph::new();
+        let [a1, b1, c1, d1, e1] = [0, 1, 2, 3, 4].map(|col| CellAddress::new(col, 0));
+        // B1 and C1 read A1, D1 reads both, E1 is unrelated
+        graph.add_dependency(b1, a1);
+        graph.add_dependency(c1, a1);
+        graph.add_dependency(d1, b1);
+        graph.add_dependency(d1, c1);
+        graph.add_dependency(e1, CellAddress::new(9, 9));
+
+        let order = graph.recalculation_order(&[a1]);
+        assert_eq!(order.len(), 4);
+        assert_eq!((order[0], order[3]), (a1, d1));
+        assert_eq!(graph.recalculation_order(&[c1]), vec![c1, d1]);
+
+        // A cycle still ends, listing each cell once
+        graph.add_dependency(a1, d1);
+        assert_eq!(graph.recalculation_order(&[b1]).len(), 4);
+    }
+
+    #[test]
+    fn test_verify_and_repair() {
+        let [a1, b1, c1, d1] = [0, 1, 2, 3].map(|col| CellAddress::new(col, 0));
+        // B1 reads A1, C1 reads A1 and B1
+        let expected: HashMap<CellAddress, HashSet<CellAddress>> =
+            [(b1, HashSet::from([a1])), (c1, HashSet::from([a1, b1]))]
+                .into_iter()
+                .collect();
+
+        let mut graph = DependencyGraph::new();
+        graph.add_dependency(b1, a1);
+        graph.add_dependency(c1, b1);
+        graph.add_dependency(c1, d1);
+        graph.add_dependency(d1, CellAddress::new(0, 5));
+        graph.remove_dependencies_for(&d1);
+
+        let report = graph.verify(&expected);
+        assert_eq!(report.missing, vec![(c1, a1)]);
+        assert_eq!(report.dangling, vec![(c1, d1)]);
+        assert_eq!(report.orphans, vec![CellAddress::new(0, 5)]);
+        assert_eq!(
+            report.to_string(),
+            "1 missing edge(s), 1 dangling edge(s), 1 orphan node(s), first C1 -> A1"
+        );
+
+        assert_eq!(graph.repair(&expected, &report), vec![c1]);
+        assert!(graph.verify(&expected).is_consistent());
+        assert_eq!(graph.len(), 3);
+    }
+
+    /// A graph built at once from every formula's references
+    fn rebuilt(references: &HashMap<CellAddress, HashSet<CellAddress>>) -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        for (&cell, precedents) in references {
+            for &precedent in precedents {
+                graph.add_dependency(cell, precedent);
+            }
+        }
+        graph
+    }
+
+    fn edges(graph: &DependencyGraph) -> Vec<(CellAddress, CellAddress)> {
+        let mut edges: Vec<_> = graph
+            .graph
+            .edge_references()
+            .map(|edge| (graph.graph[edge.source()], graph.graph[edge.target()]))
+            .collect();
+        edges.sort_by_key(|(from, to)| row_major_pair(from, to));
+        edges
+    }
+
+    #[test]
+    fn test_edits_match_a_full_rebuild() {
+        let mut rng = crate::evaluator::random::Rng::new(7);
+        let cell = |n: u64| CellAddress::new((n % 6) as u32, (n / 6 % 6) as u32);
+        let mut references: HashMap<CellAddress, HashSet<CellAddress>> = HashMap::new();
+        let mut graph = DependencyGraph::new();
+
+        for _ in 0..500 {
+            let target = cell(rng.next_u64());
+            let precedents: HashSet<CellAddress> = (0..rng.next_u64() % 4)
+                .map(|_| cell(rng.next_u64()))
+                .collect();
+            graph.set_dependencies(target, precedents.iter().copied());
+            if precedents.is_empty() {
+                references.remove(&target);
+            } else {
+                references.insert(target, precedents);
+            }
+
+            let full = rebuilt(&references);
+            assert_eq!(edges(&graph), edges(&full));
+            assert_eq!(graph.len(), full.len());
+            assert!(graph.verify(&references).is_consistent());
+            let mut dependents = graph.all_dependents(&target);
+            let mut expected = full.all_dependents(&target);
+            dependents.sort_by_key(|address| (address.row, address.col));
+            expected.sort_by_key(|address| (address.row, address.col));
+            assert_eq!(dependents, expected);
+            assert_eq!(
+                graph.find_cycle(&target).is_some(),
+                full.find_cycle(&target).is_some()
+            );
+        }
+    }
 }
@@ -1,5 +1,7 @@
 pub mod analyzer;
+pub mod cross_sheet;
 pub mod graph;
 
-pub use analyzer::DependencyAnalyzer;
-pub use graph::DependencyGraph;
+pub use analyzer::{DependencyAnalyzer, DependencyDelta};
+pub use cross_sheet::{CrossSheetDependencies, SheetCell};
+pub use graph::{DependencyGraph, DependencyReport};
@@ -1,5 +1,6 @@
+use crate::SpreadsheetError;
 use crate::constants::*;
-use crate::types::{CellValue, ErrorType};
+use crate::types::{CellArray, CellValue, ErrorType};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
@@ -17,6 +18,12 @@ pub struct Cell {
 
     /// Any error that occurred during parsing or evaluation
     pub error: Option<Arc<str>>,
+
+    /// The array a formula evaluated to when it has more than one value,
+    /// spilled over the cells right of and below this one; the computed
+    /// value is its first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill: Option<Arc<CellArray>>,
 }
 
 impl Cell {
@@ -28,6 +35,7 @@ impl Cell {
             computed_value: computed,
             formula_text: None,
             error: None,
+            spill: None,
         }
     }
 
@@ -38,6 +46,7 @@ impl Cell {
             computed_value: CellValue::Empty, // Will be computed later
             formula_text: Some(Arc::from(formula_text.as_str())),
             error: None,
+            spill: None,
         }
     }
 
@@ -76,6 +85,7 @@ impl Cell {
             computed_value: CellValue::from_error(error_type),
             formula_text: None,
             error: Some(error_arc),
+            spill: None,
         }
     }
 
@@ -86,6 +96,7 @@ impl Cell {
             computed_value: CellValue::Empty,
             formula_text: None,
             error: None,
+            spill: None,
         }
     }
 
@@ -114,10 +125,34 @@ impl Cell {
         self.computed_value.clone()
     }
 
+    /// A value spilled here from a formula's array result: it has no
+    /// input of its own
+    pub fn spilled(value: CellValue) -> Self {
+        Cell {
+            raw_value: CellValue::Empty,
+            computed_value: value,
+            formula_text: None,
+            error: None,
+            spill: None,
+        }
+    }
+
     /// Update the computed value
     pub fn set_computed_value(&mut self, value: CellValue) {
         self.computed_value = value;
         self.error = None;
+        self.spill = None;
+    }
+
+    /// Set the computed value from a formula's result, keeping an array of
+    /// more than one value to spill
+    pub fn set_array_value(&mut self, array: CellArray) {
+        if array.is_scalar() {
+            self.set_computed_value(array.into_value());
+        } else {
+            self.set_computed_value(array.values()[0].clone());
+            self.spill = Some(Arc::new(array));
+        }
     }
 
     /// Set an error on the cell
@@ -125,6 +160,7 @@ impl Cell {
         // Convert to Arc for storage
         let error_arc = Arc::from(error.as_str());
         self.error = Some(Arc::clone(&error_arc));
+        self.spill = None;
 
         // Parse the error string to determine the appropriate ErrorType
         let error_type = if error.contains("#DIV/0!") || error.contains("Division by zero") {
@@ -146,6 +182,8 @@ impl Cell {
             ErrorType::CircularDependency { cells: Vec::new() }
         } else if error.contains("#NUM!") {
             ErrorType::NumError
+        } else if error.contains("#N/A") {
+            ErrorType::NotAvailable
         } else {
             ErrorType::ParseError {
                 message: error.clone(),
@@ -154,6 +192,15 @@ impl Cell {
 
         self.computed_value = CellValue::from_error(error_type);
     }
+
+    /// Set an error keeping the details `error` carries, such as the cells
+    /// of a circular reference, which [`Self::set_error`] cannot read back
+    /// from its message
+    pub fn set_error_from(&mut self, error: &SpreadsheetError) {
+        self.error = Some(Arc::from(error.to_string().as_str()));
+        self.computed_value = CellValue::from_error(error.to_error_type());
+        self.spill = None;
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,717 @@
+use super::style::StyleRegistry;
+use crate::evaluator::dates::{ISO_DATE, serial_datetime};
+use crate::memory::{MemoryFootprint, hash_table_bytes};
+use crate::references::StructuralOperation;
+use crate::types::{CellAddress, CellValue};
+use crate::{Result, SpreadsheetError};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// How numbers are rendered for display
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum NumberFormat {
+    #[default]
+    General,
+    /// Fixed number of decimal places, optionally with the thousands
+    /// grouped, e.g. `1,234.50`
+    Number {
+        decimals: u8,
+        #[serde(default)]
+        thousands: bool,
+    },
+    /// Value multiplied by 100 with a trailing `%`
+    Percent {
+        decimals: u8,
+    },
+    Currency {
+        symbol: String,
+        decimals: u8,
+    },
+    /// Negatives in parentheses, thousands grouped and the symbol, if any,
+    /// in front, e.g. `($1,234.50)`
+    Accounting {
+        symbol: String,
+        decimals: u8,
+    },
+    /// Mantissa with a fixed number of decimal places and a signed
+    /// exponent, e.g. `1.50E+03`
+    Scientific {
+        decimals: u8,
+    },
+    /// Fixed number of decimal places followed by a unit, e.g. `45 kg`
+    Unit {
+        unit: String,
+        decimals: u8,
+    },
+    /// Serial number shown as a date and time with a strftime pattern,
+    /// e.g. `%Y-%m-%d` for `2024-01-15`
+    Date {
+        pattern: String,
+    },
+    /// Show the value exactly as stored
+    Text,
+}
+
+/// Display format of a cell
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct CellFormat {
+    pub number_format: NumberFormat,
+    /// Show every line of a multi-line value instead of just the first
+    #[serde(default)]
+    pub wrap_text: bool,
+}
+
+impl CellFormat {
+    pub fn number(decimals: u8) -> Self {
+        Self {
+            number_format: NumberFormat::Number {
+                decimals,
+                thousands: false,
+            },
+            ..Self::default()
+        }
+    }
+
+    /// Number with the thousands grouped
+    pub fn thousands(decimals: u8) -> Self {
+        Self {
+            number_format: NumberFormat::Number {
+                decimals,
+                thousands: true,
+            },
+            ..Self::default()
+        }
+    }
+
+    pub fn percent(decimals: u8) -> Self {
+        Self {
+            number_format: NumberFormat::Percent { decimals },
+            ..Self::default()
+        }
+    }
+
+    pub fn currency(symbol: impl Into<String>, decimals: u8) -> Self {
+        Self {
+            number_format: NumberFormat::Currency {
+                symbol: symbol.into(),
+                decimals,
+            },
+            ..Self::default()
+        }
+    }
+
+    /// Accounting format; `symbol` may be empty
+    pub fn accounting(symbol: impl Into<String>, decimals: u8) -> Self {
+        Self {
+            number_format: NumberFormat::Accounting {
+                symbol: symbol.into(),
+                decimals,
+            },
+            ..Self::default()
+        }
+    }
+
+    pub fn scientific(decimals: u8) -> Self {
+        Self {
+            number_format: NumberFormat::Scientific { decimals },
+            ..Self::default()
+        }
+    }
+
+    pub fn text() -> Self {
+        Self {
+            number_format: NumberFormat::Text,
+            ..Self::default()
+        }
+    }
+
+    pub fn unit(unit: impl Into<String>, decimals: u8) -> Self {
+        Self {
+            number_format: NumberFormat::Unit {
+                unit: unit.into(),
+                decimals,
+            },
+            ..Self::default()
+        }
+    }
+
+    /// Date shown with a strftime `pattern`, see [`crate::evaluator::dates`]
+    pub fn date(pattern: impl Into<String>) -> Self {
+        Self {
+            number_format: NumberFormat::Date {
+                pattern: pattern.into(),
+            },
+            ..Self::default()
+        }
+    }
+
+    /// The same format with wrapping turned on
+    pub fn wrapped(mut self) -> Self {
+        self.wrap_text = true;
+        self
+    }
+
+    /// Render a value using this format. Non-numeric values are shown as-is.
+    pub fn format_value(&self, value: &CellValue) -> String {
+        let CellValue::Number(n) = value else {
+            return value.to_string();
+        };
+
+        match &self.number_format {
+            NumberFormat::General | NumberFormat::Text => value.to_string(),
+            NumberFormat::Number {
+                decimals,
+                thousands: false,
+            } => format!("{:.*}", *decimals as usize, n),
+            NumberFormat::Number {
+                decimals,
+                thousands: true,
+            } => {
+                let sign = if *n < 0.0 { "-" } else { "" };
+                format!("{}{}", sign, group_thousands(n.abs(), *decimals))
+            }
+            NumberFormat::Percent { decimals } => {
+                format!("{:.*}%", *decimals as usize, n * 100.0)
+            }
+            NumberFormat::Currency { symbol, decimals } => {
+                let sign = if *n < 0.0 { "-" } else { "" };
+                format!("{}{}{:.*}", sign, symbol, *decimals as usize, n.abs())
+            }
+            NumberFormat::Accounting { symbol, decimals } => {
+                let amount = format!("{}{}", symbol, group_thousands(n.abs(), *decimals));
+                if *n < 0.0 {
+                    format!("({})", amount)
+                } else {
+                    amount
+                }
+            }
+            NumberFormat::Scientific { decimals } => {
+                // Rust writes `1.50E3`; spreadsheets pad the exponent to
+                // two digits and always sign it
+                let formatted = format!("{:.*E}", *decimals as usize, n);
+                let (mantissa, exponent) = formatted.split_once('E').unwrap_or((&formatted, "0"));
+                let (sign, digits) = match exponent.strip_prefix('-') {
+                    Some(digits) => ('-', digits),
+                    None => ('+', exponent),
+                };
+                format!("{}E{}{:0>2}", mantissa, sign, digits)
+            }
+            NumberFormat::Unit { unit, decimals } => {
+                format!("{:.*} {}", *decimals as usize, n, unit)
+            }
+            NumberFormat::Date { pattern } => {
+                // A pattern chrono can't render shows the serial instead
+                // of panicking mid-render
+                let items = chrono::format::StrftimeItems::new(pattern);
+                match serial_datetime(*n) {
+                    Some(at)
+                        if !items
+                            .clone()
+                            .any(|item| item == chrono::format::Item::Error) =>
+                    {
+                        at.format_with_items(items).to_string()
+                    }
+                    _ => value.to_string(),
+                }
+            }
+        }
+    }
+}
+
+/// `n`, which is not negative, with `decimals` places and commas between
+/// the groups of thousands
+fn group_thousands(n: f64, decimals: u8) -> String {
+    let formatted = format!("{:.*}", decimals as usize, n);
+    let (whole, fraction) = match formatted.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+    let mut grouped = String::with_capacity(formatted.len() + whole.len() / 3);
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    grouped
+}
+
+/// Read a format written as a kind and its options, as `:style` takes it:
+/// `general`, `text`, `number 2`, `thousands 2`, `percent 1`, `currency € 2`,
+/// `accounting $ 2`, `scientific 2`, `unit kg 1` or `date %d.%m.%Y`. The
+/// accounting symbol
+/// is optional. Decimals default to 2 for numbers, currencies, accounting
+/// and scientific notation and 0 otherwise, and dates default to
+/// `%Y-%m-%d`. A trailing `wrap` turns on text wrapping, and `wrap` alone wraps a general
+/// format.
+impl FromStr for CellFormat {
+    type Err = SpreadsheetError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut words: Vec<&str> = s.split_whitespace().collect();
+        let wrap_text = words.last().is_some_and(|w| w.eq_ignore_ascii_case("wrap"));
+        if wrap_text {
+            words.pop();
+            if words.is_empty() {
+                return Ok(Self::default().wrapped());
+            }
+        }
+        let mut parts = words.into_iter();
+        let kind = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let rest: Vec<&str> = parts.collect();
+        let decimals = |arg: Option<&&str>, default: u8| match arg {
+            Some(arg) => arg.parse::<u8>().map_err(|_| {
+                SpreadsheetError::InvalidOperation(format!("Invalid decimals '{}'", arg))
+            }),
+            None => Ok(default),
+        };
+
+        let format = match (kind.as_str(), rest.as_slice()) {
+            ("general", []) => Ok(Self::default()),
+            ("text", []) => Ok(Self::text()),
+            ("number", [] | [_]) => Ok(Self::number(decimals(rest.first(), 2)?)),
+            ("thousands", [] | [_]) => Ok(Self::thousands(decimals(rest.first(), 2)?)),
+            ("scientific", [] | [_]) => Ok(Self::scientific(decimals(rest.first(), 2)?)),
+            ("accounting", []) => Ok(Self::accounting("", 2)),
+            ("accounting", [only]) if only.parse::<u8>().is_ok() => {
+                Ok(Self::accounting("", decimals(rest.first(), 2)?))
+            }
+            ("accounting", [symbol] | [symbol, _]) => {
+                Ok(Self::accounting(*symbol, decimals(rest.get(1), 2)?))
+            }
+            ("percent", [] | [_]) => Ok(Self::percent(decimals(rest.first(), 0)?)),
+            ("currency", [symbol] | [symbol, _]) => {
+                Ok(Self::currency(*symbol, decimals(rest.get(1), 2)?))
+            }
+            ("unit", [unit] | [unit, _]) => Ok(Self::unit(*unit, decimals(rest.get(1), 0)?)),
+            ("date", []) => Ok(Self::date(ISO_DATE)),
+            ("date", pattern) => Ok(Self::date(pattern.join(" "))),
+            _ => Err(SpreadsheetError::InvalidOperation(format!(
+                "Unknown format '{}', expected general, text, number [N], thousands [N], \
+                 percent [N], currency SYMBOL [N], accounting [SYMBOL] [N], scientific [N] \
+                 unit UNIT [N] or date [PATTERN], optionally followed by wrap",
+                s.trim()
+            ))),
+        }?;
+        Ok(CellFormat {
+            wrap_text,
+            ..format
+        })
+    }
+}
+
+/// Cell, row, column and sheet-level formats of a sheet.
+///
+/// Row and column formats are stored once and resolved on lookup, so
+/// formatting a whole column costs the same as formatting a single cell.
+/// Cells may also refer to a named style, which is looked up in the
+/// workbook's [`StyleRegistry`] at the same time.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SerializedFormats", into = "SerializedFormats")]
+pub struct FormatStore {
+    cells: FxHashMap<CellAddress, CellFormat>,
+    styles: FxHashMap<CellAddress, String>,
+    rows: FxHashMap<u32, CellFormat>,
+    columns: FxHashMap<u32, CellFormat>,
+    default: Option<CellFormat>,
+}
+
+impl FormatStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Explicit format of a single cell, ignoring row/column defaults
+    pub fn cell_format(&self, address: &CellAddress) -> Option<&CellFormat> {
+        self.cells.get(address)
+    }
+
+    pub fn set_cell_format(&mut self, address: CellAddress, format: CellFormat) {
+        self.cells.insert(address, format);
+    }
+
+    /// Drop a cell's explicit format so it falls back to its row/column default
+    pub fn clear_cell_format(&mut self, address: &CellAddress) -> Option<CellFormat> {
+        self.cells.remove(address)
+    }
+
+    /// Name of the style a cell refers to
+    pub fn cell_style(&self, address: &CellAddress) -> Option<&str> {
+        self.styles.get(address).map(String::as_str)
+    }
+
+    /// Set or clear the style a cell refers to
+    pub fn set_cell_style(&mut self, address: CellAddress, style: Option<String>) {
+        match style {
+            Some(style) => self.styles.insert(address, style),
+            None => self.styles.remove(&address),
+        };
+    }
+
+    /// Cells with an explicit format
+    pub fn cell_formats(&self) -> impl Iterator<Item = (&CellAddress, &CellFormat)> {
+        self.cells.iter()
+    }
+
+    /// Cells referring to a style, with the style's name
+    pub fn cell_styles(&self) -> impl Iterator<Item = (&CellAddress, &str)> {
+        self.styles
+            .iter()
+            .map(|(address, name)| (address, name.as_str()))
+    }
+
+    /// Rows with a default format
+    pub fn row_formats(&self) -> impl Iterator<Item = (u32, &CellFormat)> {
+        self.rows.iter().map(|(row, format)| (*row, format))
+    }
+
+    /// Columns with a default format
+    pub fn column_formats(&self) -> impl Iterator<Item = (u32, &CellFormat)> {
+        self.columns
+            .iter()
+            .map(|(column, format)| (*column, format))
+    }
+
+    /// Cells referring to `style`
+    pub fn cells_with_style<'a>(
+        &'a self,
+        style: &'a str,
+    ) -> impl Iterator<Item = &'a CellAddress> + 'a {
+        self.styles
+            .iter()
+            .filter(move |(_, name)| name.eq_ignore_ascii_case(style))
+            .map(|(address, _)| address)
+    }
+
+    pub fn row_format(&self, row: u32) -> Option<&CellFormat> {
+        self.rows.get(&row)
+    }
+
+    /// Set or clear the default format of a row
+    pub fn set_row_format(&mut self, row: u32, format: Option<CellFormat>) {
+        match format {
+            Some(format) => self.rows.insert(row, format),
+            None => self.rows.remove(&row),
+        };
+    }
+
+    pub fn column_format(&self, column: u32) -> Option<&CellFormat> {
+        self.columns.get(&column)
+    }
+
+    /// Set or clear the default format of a column
+    pub fn set_column_format(&mut self, column: u32, format: Option<CellFormat>) {
+        match format {
+            Some(format) => self.columns.insert(column, format),
+            None => self.columns.remove(&column),
+        };
+    }
+
+    pub fn default_format(&self) -> Option<&CellFormat> {
+        self.default.as_ref()
+    }
+
+    pub fn set_default_format(&mut self, format: Option<CellFormat>) {
+        self.default = format;
+    }
+
+    /// Format that applies to a cell, resolved as cell > cell style > row >
+    /// column > sheet default. Styles missing from `styles` are skipped.
+    pub fn effective_format<'a>(
+        &'a self,
+        address: &CellAddress,
+        styles: &'a StyleRegistry,
+    ) -> Option<&'a CellFormat> {
+        self.cells
+            .get(address)
+            .or_else(|| {
+                let name = self.styles.get(address)?;
+                styles.get(name).map(|style| &style.format)
+            })
+            .or_else(|| self.rows.get(&address.row))
+            .or_else(|| self.columns.get(&address.col))
+            .or(self.default.as_ref())
+    }
+
+    /// Render a value for the given cell using its effective format
+    pub fn format_value(
+        &self,
+        address: &CellAddress,
+        value: &CellValue,
+        styles: &StyleRegistry,
+    ) -> String {
+        match self.effective_format(address, styles) {
+            Some(format) => format.format_value(value),
+            None => value.to_string(),
+        }
+    }
+
+    /// Move cell formats, style assignments and row or column defaults
+    /// with their cells for `operation`. Formats of deleted cells, rows and
+    /// columns are dropped.
+    pub fn apply_structural_operation(&mut self, operation: &StructuralOperation) {
+        let shift = |address: CellAddress| operation.shift_address(&address);
+        shift_keys(&mut self.cells, shift);
+        shift_keys(&mut self.styles, shift);
+        match operation {
+            StructuralOperation::InsertRows { .. } | StructuralOperation::DeleteRows { .. } => {
+                shift_keys(&mut self.rows, |row| {
+                    Some(shift(CellAddress::new(0, row))?.row)
+                })
+            }
+            StructuralOperation::InsertColumns { .. }
+            | StructuralOperation::DeleteColumns { .. } => shift_keys(&mut self.columns, |col| {
+                Some(shift(CellAddress::new(col, 0))?.col)
+            }),
+            StructuralOperation::MoveRange { .. } => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+            && self.styles.is_empty()
+            && self.rows.is_empty()
+            && self.columns.is_empty()
+            && self.default.is_none()
+    }
+}
+
+impl MemoryFootprint for FormatStore {
+    fn footprint_bytes(&self) -> usize {
+        hash_table_bytes::<CellAddress, CellFormat>(self.cells.capacity())
+            + hash_table_bytes::<CellAddress, String>(self.styles.capacity())
+            + self.styles.values().map(String::capacity).sum::<usize>()
+            + hash_table_bytes::<u32, CellFormat>(self.rows.capacity())
+            + hash_table_bytes::<u32, CellFormat>(self.columns.capacity())
+    }
+
+    fn compact(&mut self) {
+        self.cells.shrink_to_fit();
+        self.styles.shrink_to_fit();
+        self.rows.shrink_to_fit();
+        self.columns.shrink_to_fit();
+    }
+}
+
+/// Re-key `map` by `shift`, dropping entries it maps to `None`
+fn shift_keys<K: Copy + Eq + Hash, V>(map: &mut FxHashMap<K, V>, shift: impl Fn(K) -> Option<K>) {
+    *map = map
+        .drain()
+        .filter_map(|(key, value)| Some((shift(key)?, value)))
+        .collect();
+}
+
+/// Serialized form of [`FormatStore`]; maps keyed by addresses become lists
+#[derive(Serialize, Deserialize)]
+struct SerializedFormats {
+    #[serde(default)]
+    cells: Vec<(CellAddress, CellFormat)>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    styles: Vec<(CellAddress, String)>,
+    #[serde(default)]
+    rows: Vec<(u32, CellFormat)>,
+    #[serde(default)]
+    columns: Vec<(u32, CellFormat)>,
+    #[serde(default)]
+    default: Option<CellFormat>,
+}
+
+impl From<FormatStore> for SerializedFormats {
+    fn from(store: FormatStore) -> Self {
+        Self {
+            cells: store.cells.into_iter().collect(),
+            styles: store.styles.into_iter().collect(),
+            rows: store.rows.into_iter().collect(),
+            columns: store.columns.into_iter().collect(),
+            default: store.default,
+        }
+    }
+}
+
+impl From<SerializedFormats> for FormatStore {
+    fn from(data: SerializedFormats) -> Self {
+        Self {
+            cells: data.cells.into_iter().collect(),
+            styles: data.styles.into_iter().collect(),
+            rows: data.rows.into_iter().collect(),
+            columns: data.columns.into_iter().collect(),
+            default: data.default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_value() {
+        let value = CellValue::Number(0.1234);
+        assert_eq!(CellFormat::default().format_value(&value), "0.1234");
+        assert_eq!(CellFormat::number(2).format_value(&value), "0.12");
+        assert_eq!(CellFormat::percent(1).format_value(&value), "12.3%");
+        assert_eq!(
+            CellFormat::currency("$", 2).format_value(&CellValue::Number(-3.5)),
+            "-$3.50"
+        );
+        assert_eq!(
+            CellFormat::unit("kg", 1).format_value(&CellValue::Number(45.0)),
+            "45.0 kg"
+        );
+        assert_eq!(
+            CellFormat::percent(1).format_value(&CellValue::string_from_str("n/a")),
+            "n/a"
+        );
+    }
+
+    #[test]
+    fn test_grouped_accounting_and_scientific_formats() {
+        let format = |format: CellFormat, n: f64| format.format_value(&CellValue::Number(n));
+        assert_eq!(
+            format(CellFormat::thousands(2), 1234567.891),
+            "1,234,567.89"
+        );
+        assert_eq!(format(CellFormat::thousands(0), -999.0), "-999");
+        assert_eq!(format(CellFormat::thousands(0), 100000.0), "100,000");
+        assert_eq!(
+            format(CellFormat::accounting("$", 2), -1234.5),
+            "($1,234.50)"
+        );
+        assert_eq!(format(CellFormat::accounting("", 0), 42.0), "42");
+        assert_eq!(format(CellFormat::scientific(2), 1500.0), "1.50E+03");
+        assert_eq!(format(CellFormat::scientific(1), -0.00025), "-2.5E-04");
+        assert_eq!(format(CellFormat::date("%Y-%m-%d"), 45306.0), "2024-01-15");
+        assert_eq!(
+            format(CellFormat::date("%-m/%-d/%Y %H:%M"), 45306.75),
+            "1/15/2024 18:00"
+        );
+        assert_eq!(format(CellFormat::date("%Q"), 45306.0), "45306");
+
+        // Number formats saved before grouping existed read as ungrouped
+        let old: NumberFormat = serde_json::from_str(r#"{"Number":{"decimals":2}}"#).unwrap();
+        assert_eq!(old, CellFormat::number(2).number_format);
+    }
+
+    #[test]
+    fn test_parse_format_spec() {
+        let parse = |spec: &str| spec.parse::<CellFormat>();
+        assert_eq!(parse("general").unwrap(), CellFormat::default());
+        assert_eq!(parse("Number").unwrap(), CellFormat::number(2));
+        assert_eq!(parse("percent 1").unwrap(), CellFormat::percent(1));
+        assert_eq!(parse("currency € 0").unwrap(), CellFormat::currency("€", 0));
+        assert_eq!(parse("unit kg").unwrap(), CellFormat::unit("kg", 0));
+        assert_eq!(parse("thousands 0").unwrap(), CellFormat::thousands(0));
+        assert_eq!(parse("scientific").unwrap(), CellFormat::scientific(2));
+        assert_eq!(parse("date").unwrap(), CellFormat::date("%Y-%m-%d"));
+        assert_eq!(
+            parse("date %d.%m.%Y %H:%M").unwrap(),
+            CellFormat::date("%d.%m.%Y %H:%M")
+        );
+        assert_eq!(parse("accounting").unwrap(), CellFormat::accounting("", 2));
+        assert_eq!(
+            parse("accounting 1").unwrap(),
+            CellFormat::accounting("", 1)
+        );
+        assert_eq!(
+            parse("accounting € 0").unwrap(),
+            CellFormat::accounting("€", 0)
+        );
+        assert!(parse("currency").is_err());
+        assert!(parse("number two").is_err());
+        assert!(parse("bold").is_err());
+
+        assert_eq!(parse("wrap").unwrap(), CellFormat::default().wrapped());
+        assert_eq!(
+            parse("number 1 WRAP").unwrap(),
+            CellFormat::number(1).wrapped()
+        );
+        assert!(parse("wrap number").is_err());
+    }
+
+    #[test]
+    fn test_effective_format_precedence() {
+        let mut store = FormatStore::new();
+        let mut styles = StyleRegistry::new();
+        let c5 = CellAddress::new(2, 4);
+
+        store.set_default_format(Some(CellFormat::number(0)));
+        assert_eq!(
+            store.effective_format(&c5, &styles),
+            Some(&CellFormat::number(0))
+        );
+
+        store.set_column_format(2, Some(CellFormat::percent(1)));
+        assert_eq!(
+            store.effective_format(&c5, &styles),
+            Some(&CellFormat::percent(1))
+        );
+        // Other cells in the column inherit the default without being materialized
+        assert_eq!(
+            store.effective_format(&CellAddress::new(2, 999_999), &styles),
+            Some(&CellFormat::percent(1))
+        );
+        assert!(store.cell_format(&c5).is_none());
+
+        store.set_row_format(4, Some(CellFormat::number(3)));
+        assert_eq!(
+            store.effective_format(&c5, &styles),
+            Some(&CellFormat::number(3))
+        );
+
+        // A style outranks row and column formats once it is defined, and
+        // editing it changes the cell without touching the store
+        store.set_cell_style(c5, Some("Result".to_string()));
+        assert_eq!(
+            store.effective_format(&c5, &styles),
+            Some(&CellFormat::number(3))
+        );
+        styles.define("Result", CellFormat::number(1)).unwrap();
+        assert_eq!(
+            store.effective_format(&c5, &styles),
+            Some(&CellFormat::number(1))
+        );
+        styles.define("Result", CellFormat::unit("kg", 0)).unwrap();
+        assert_eq!(
+            store.effective_format(&c5, &styles),
+            Some(&CellFormat::unit("kg", 0))
+        );
+
+        store.set_cell_format(c5, CellFormat::currency("$", 2));
+        assert_eq!(
+            store.effective_format(&c5, &styles),
+            Some(&CellFormat::currency("$", 2))
+        );
+
+        store.clear_cell_format(&c5);
+        store.set_cell_style(c5, None);
+        store.set_row_format(4, None);
+        assert_eq!(
+            store.effective_format(&c5, &styles),
+            Some(&CellFormat::percent(1))
+        );
+    }
+
+    #[test]
+    fn test_format_store_serialization_roundtrip() {
+        let mut store = FormatStore::new();
+        store.set_column_format(2, Some(CellFormat::percent(1)));
+        store.set_cell_format(CellAddress::new(0, 0), CellFormat::number(2));
+        store.set_cell_style(CellAddress::new(1, 1), Some("Heading".to_string()));
+
+        store.set_cell_format(CellAddress::new(3, 0), CellFormat::text().wrapped());
+
+        let json = serde_json::to_string(&store).unwrap();
+        let restored: FormatStore = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, store);
+
+        // Formats saved before wrapping existed read as unwrapped
+        let old: CellFormat = serde_json::from_str(r#"{"number_format":"Text"}"#).unwrap();
+        assert_eq!(old, CellFormat::text());
+    }
+}
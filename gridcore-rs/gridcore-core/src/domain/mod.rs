@@ -1,3 +1,7 @@
 pub mod cell;
+pub mod format;
+pub mod style;
 
 pub use cell::Cell;
+pub use format::{CellFormat, FormatStore, NumberFormat};
+pub use style::{CellStyle, StyleRegistry, StyleRemoval};
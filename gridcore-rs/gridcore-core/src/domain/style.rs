@@ -0,0 +1,170 @@
+//! Named cell styles
+//!
+//! A style is a format defined once under a name. Cells refer to it by name
+//! and resolve it when their effective format is looked up, so editing a
+//! style restyles every cell using it without touching those cells.
+
+use super::format::CellFormat;
+use crate::{Result, SpreadsheetError};
+use serde::{Deserialize, Serialize};
+
+/// Longest style name accepted
+pub const MAX_STYLE_NAME_LEN: usize = 64;
+
+/// A named format cells can refer to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellStyle {
+    pub name: String,
+    pub format: CellFormat,
+}
+
+impl CellStyle {
+    pub fn new(name: impl Into<String>, format: CellFormat) -> Self {
+        Self {
+            name: name.into(),
+            format,
+        }
+    }
+}
+
+/// What happens to the cells using a style when it is removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StyleRemoval {
+    /// Refuse to remove a style that cells still use
+    #[default]
+    Block,
+    /// Give those cells the style's format directly, then remove it
+    ConvertToFormats,
+}
+
+/// The styles of a workbook, in the order they are offered in the gallery.
+/// Names are matched without regard to case.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StyleRegistry {
+    styles: Vec<CellStyle>,
+}
+
+impl StyleRegistry {
+    /// A registry without any styles
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The styles a new workbook starts with
+    pub fn with_builtins() -> Self {
+        let builtins = [
+            ("Heading", CellFormat::text()),
+            ("Input", CellFormat::number(2)),
+            ("Result", CellFormat::number(2)),
+            ("Currency", CellFormat::currency("$", 2)),
+            ("Percent", CellFormat::percent(1)),
+        ];
+        Self {
+            styles: builtins
+                .into_iter()
+                .map(|(name, format)| CellStyle::new(name, format))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CellStyle> {
+        self.styles
+            .iter()
+            .find(|style| style.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Define a style or change the format of an existing one, which keeps
+    /// its spelling and place in the gallery. Returns the name cells should
+    /// refer to it by.
+    pub fn define(&mut self, name: &str, format: CellFormat) -> Result<String> {
+        let name = validate_style_name(name)?;
+        match self
+            .styles
+            .iter_mut()
+            .find(|style| style.name.eq_ignore_ascii_case(name))
+        {
+            Some(style) => {
+                style.format = format;
+                Ok(style.name.clone())
+            }
+            None => {
+                self.styles.push(CellStyle::new(name, format));
+                Ok(name.to_string())
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<CellStyle> {
+        let index = self
+            .styles
+            .iter()
+            .position(|style| style.name.eq_ignore_ascii_case(name))?;
+        Some(self.styles.remove(index))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CellStyle> {
+        self.styles.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.styles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty()
+    }
+}
+
+/// Check a style name, returning it without surrounding whitespace
+pub fn validate_style_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(SpreadsheetError::InvalidOperation(
+            "Style name cannot be empty".to_string(),
+        ));
+    }
+    if name.chars().count() > MAX_STYLE_NAME_LEN {
+        return Err(SpreadsheetError::InvalidOperation(format!(
+            "Style name cannot be longer than {} characters",
+            MAX_STYLE_NAME_LEN
+        )));
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_define_update_and_remove() {
+        let mut styles = StyleRegistry::with_builtins();
+        assert_eq!(styles.get("heading").unwrap().name, "Heading");
+
+        // Redefining keeps the original spelling and gallery position
+        let name = styles.define("INPUT", CellFormat::number(0)).unwrap();
+        assert_eq!(name, "Input");
+        assert_eq!(styles.get("Input").unwrap().format, CellFormat::number(0));
+        assert_eq!(styles.iter().nth(1).unwrap().name, "Input");
+
+        let name = styles.define("  Warning ", CellFormat::percent(0)).unwrap();
+        assert_eq!(name, "Warning");
+        assert_eq!(styles.iter().last().unwrap().name, "Warning");
+        assert!(styles.define(" ", CellFormat::default()).is_err());
+
+        assert!(styles.remove("warning").is_some());
+        assert!(!styles.contains("Warning"));
+
+        let json = serde_json::to_string(&styles).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StyleRegistry>(&json).unwrap(),
+            styles
+        );
+    }
+}
@@ -1,7 +1,8 @@
 pub mod recovery;
 
 use crate::constants::*;
-use crate::types::ErrorType;
+use crate::types::error_type::{cell_list, cycle_text};
+use crate::types::{CellAddress, ErrorType};
 use thiserror::Error;
 
 #[derive(Debug, Error, Clone)]
@@ -33,6 +34,15 @@ pub enum SpreadsheetError {
     #[error("Circular dependency detected")]
     CircularDependency,
 
+    /// A circular dependency through the listed cells, the first repeated
+    /// at the end
+    #[error("Circular dependency: {}", cycle_text(.0))]
+    CircularDependencyWith(Vec<CellAddress>),
+
+    /// An array result blocked from spilling by the listed cells
+    #[error("Spill range isn't blank: {}", cell_list(.0))]
+    SpillBlocked(Vec<CellAddress>),
+
     #[error("Invalid range: {0}")]
     InvalidRange(String),
 
@@ -71,6 +81,16 @@ pub enum SpreadsheetError {
 
     #[error("Invalid command: {0}")]
     InvalidCommand(String),
+
+    #[error("Evaluation budget exceeded")]
+    BudgetExceeded,
+
+    #[error("{} is {length} characters long; the limit is {limit}", input_kind(.formula))]
+    InputTooLong {
+        length: usize,
+        limit: usize,
+        formula: bool,
+    },
 }
 
 impl SpreadsheetError {
@@ -105,6 +125,12 @@ impl SpreadsheetError {
             SpreadsheetError::CircularDependency => {
                 ErrorType::CircularDependency { cells: Vec::new() }
             }
+            SpreadsheetError::CircularDependencyWith(cells) => ErrorType::CircularDependency {
+                cells: cells.clone(),
+            },
+            SpreadsheetError::SpillBlocked(cells) => ErrorType::Spill {
+                blocked_by: cells.clone(),
+            },
             SpreadsheetError::InvalidRange(range) => ErrorType::InvalidRange {
                 range: range.clone(),
             },
@@ -137,6 +163,10 @@ impl SpreadsheetError {
     }
 }
 
+fn input_kind(formula: &bool) -> &'static str {
+    if *formula { "Formula" } else { "Text" }
+}
+
 pub type Result<T> = std::result::Result<T, SpreadsheetError>;
 
 #[cfg(test)]
@@ -214,6 +244,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_to_error_type_keeps_the_cycle() {
+        let cells = vec![
+            CellAddress::new(0, 0),
+            CellAddress::new(1, 0),
+            CellAddress::new(0, 0),
+        ];
+        let error = SpreadsheetError::CircularDependencyWith(cells.clone());
+        assert_eq!(error.to_string(), "Circular dependency: A1 → B1 → A1");
+        assert_eq!(
+            error.to_error_type(),
+            ErrorType::CircularDependency { cells }
+        );
+    }
+
     #[test]
     fn test_to_error_type_invalid_address() {
         let error = SpreadsheetError::InvalidAddress("ZZ999999".to_string());
@@ -1,6 +1,12 @@
+use super::deterministic::DeterministicSource;
 use crate::Result;
+use crate::external::{ExternalCell, ExternalDataStore, ExternalRequest};
+use crate::formula::CellRange;
 use crate::ports::RepositoryPort;
+use crate::repository::LookupKey;
 use crate::types::{CellAddress, CellValue};
+use crate::workbook::names::{VisibleNames, name_key};
+use rustc_hash::FxHashMap;
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
 
@@ -17,6 +23,58 @@ pub trait EvaluationContext {
 
     /// Pop a cell address from the evaluation stack
     fn pop_evaluation(&mut self, address: &CellAddress);
+
+    /// Fetched value of an external request, or `None` while it is pending.
+    /// Contexts without a data store never resolve external data.
+    fn external_value(&mut self, _request: &ExternalRequest) -> Option<CellValue> {
+        None
+    }
+
+    /// Where volatile functions take their randomness and time from in
+    /// deterministic mode, or `None` for entropy and the clock
+    fn deterministic(&self) -> Option<&DeterministicSource> {
+        None
+    }
+
+    /// Value of a defined name, or `None` when no such name is visible.
+    /// Contexts without a names table resolve no names.
+    fn name_value(&self, _name: &str) -> Option<CellValue> {
+        None
+    }
+
+    /// Cells a named range stands for, or `None` when no such range is
+    /// visible. Names with a value take precedence over ranges.
+    fn name_range(&self, _name: &str) -> Option<CellRange> {
+        None
+    }
+
+    /// Context reading the cells of the sheet named `sheet`, for references
+    /// like `Sheet2!A1`, or `None` when there is no such sheet. Contexts
+    /// without a workbook see no other sheets.
+    fn sheet_context(&self, _sheet: &str) -> Option<Box<dyn EvaluationContext>> {
+        None
+    }
+
+    /// Values of the cells of `range` in row order, or `None` to read them
+    /// one by one through [`Self::get_cell_value`]. Contexts that answer
+    /// here are not checked for circular references.
+    fn range_values(&self, _range: &CellRange) -> Option<Vec<CellValue>> {
+        None
+    }
+
+    /// First row of the single-column `column` whose value matches `key`,
+    /// from an index, or `None` to scan the cells instead. Contexts must
+    /// not answer while a cell of `column` is being evaluated, so the scan
+    /// reports the circular reference.
+    fn indexed_first_match(&self, _column: &CellRange, _key: &LookupKey) -> Option<Option<u32>> {
+        None
+    }
+
+    /// Cells of the single-column `column` whose value matches `key`, from
+    /// an index, or `None` to scan; see [`Self::indexed_first_match`]
+    fn indexed_match_count(&self, _column: &CellRange, _key: &LookupKey) -> Option<usize> {
+        None
+    }
 }
 
 /// Basic context for testing
@@ -116,15 +174,64 @@ impl<'a> EvaluationContext for RepositoryContext<'a> {
 pub struct PortContext {
     repository: Arc<dyn RepositoryPort>,
     evaluation_stack: HashSet<CellAddress>,
+    external: Option<(Arc<Mutex<ExternalDataStore>>, ExternalCell)>,
+    /// Store read without subscribing, for evaluations nothing is kept from
+    peek_external: Option<Arc<Mutex<ExternalDataStore>>>,
+    names: Option<Arc<VisibleNames>>,
+    deterministic: Option<Arc<DeterministicSource>>,
+    sheets: Option<Arc<SheetRepositories>>,
 }
 
+/// The cells of each sheet of a workbook, by sheet name
+pub type SheetRepositories = FxHashMap<String, Arc<dyn RepositoryPort>>;
+
 impl PortContext {
     pub fn new(repository: Arc<dyn RepositoryPort>) -> Self {
         PortContext {
             repository,
             evaluation_stack: HashSet::new(),
+            external: None,
+            peek_external: None,
+            names: None,
+            deterministic: None,
+            sheets: None,
         }
     }
+
+    /// Read references to other sheets from `sheets`
+    pub fn with_sheets(mut self, sheets: Arc<SheetRepositories>) -> Self {
+        self.sheets = Some(sheets);
+        self
+    }
+
+    /// Resolve defined names through `names`
+    pub fn with_names(mut self, names: Arc<VisibleNames>) -> Self {
+        self.names = Some(names);
+        self
+    }
+
+    /// Resolve external data for the formula in `cell` through `store`
+    pub fn with_external(
+        mut self,
+        store: Arc<Mutex<ExternalDataStore>>,
+        cell: ExternalCell,
+    ) -> Self {
+        self.external = Some((store, cell));
+        self
+    }
+
+    /// Evaluate volatile functions from `source` in deterministic mode
+    pub fn with_deterministic(mut self, source: Option<Arc<DeterministicSource>>) -> Self {
+        self.deterministic = source;
+        self
+    }
+
+    /// Read external data already fetched into `store` without subscribing
+    /// to it or queueing requests; anything missing reads as pending
+    pub fn with_external_peek(mut self, store: Arc<Mutex<ExternalDataStore>>) -> Self {
+        self.peek_external = Some(store);
+        self
+    }
 }
 
 impl EvaluationContext for PortContext {
@@ -153,4 +260,141 @@ impl EvaluationContext for PortContext {
     fn pop_evaluation(&mut self, address: &CellAddress) {
         self.evaluation_stack.remove(address);
     }
+
+    fn external_value(&mut self, request: &ExternalRequest) -> Option<CellValue> {
+        if let Some(store) = &self.peek_external {
+            return store.lock().ok()?.peek(request, chrono::Utc::now());
+        }
+        let (store, cell) = self.external.as_ref()?;
+        store.lock().ok()?.lookup(cell, request, chrono::Utc::now())
+    }
+
+    fn deterministic(&self) -> Option<&DeterministicSource> {
+        self.deterministic.as_deref()
+    }
+
+    fn name_value(&self, name: &str) -> Option<CellValue> {
+        self.names.as_ref()?.values.get(&name_key(name)).cloned()
+    }
+
+    fn name_range(&self, name: &str) -> Option<CellRange> {
+        self.names.as_ref()?.ranges.get(&name_key(name)).copied()
+    }
+
+    /// Sheet names are matched exactly first, then ignoring case
+    fn sheet_context(&self, sheet: &str) -> Option<Box<dyn EvaluationContext>> {
+        let sheets = self.sheets.as_ref()?;
+        let repository = sheets.get(sheet).or_else(|| {
+            sheets
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(sheet))
+                .map(|(_, repository)| repository)
+        })?;
+        let context = PortContext::new(repository.clone())
+            .with_sheets(sheets.clone())
+            .with_deterministic(self.deterministic.clone());
+        Some(Box::new(context))
+    }
+
+    fn indexed_first_match(&self, column: &CellRange, key: &LookupKey) -> Option<Option<u32>> {
+        if self.evaluates_within(column) {
+            return None;
+        }
+        self.repository
+            .indexed_first_match(column.start.col, column.start.row, column.end.row, key)
+    }
+
+    fn indexed_match_count(&self, column: &CellRange, key: &LookupKey) -> Option<usize> {
+        if self.evaluates_within(column) {
+            return None;
+        }
+        self.repository
+            .indexed_match_count(column.start.col, column.start.row, column.end.row, key)
+    }
+}
+
+impl PortContext {
+    fn evaluates_within(&self, range: &CellRange) -> bool {
+        self.evaluation_stack
+            .iter()
+            .any(|address| range.contains(address))
+    }
+}
+
+/// Context reading some cells from an overlay of values in place of the
+/// repository's, for what-if evaluation. Nothing is written: the cells
+/// outside the overlay read as their stored values.
+pub struct OverlayContext<'a> {
+    inner: PortContext,
+    overlay: &'a FxHashMap<CellAddress, CellValue>,
+}
+
+impl<'a> OverlayContext<'a> {
+    pub fn new(inner: PortContext, overlay: &'a FxHashMap<CellAddress, CellValue>) -> Self {
+        OverlayContext { inner, overlay }
+    }
+
+    /// Whether the overlay replaces any cell of `range`, so the
+    /// repository's index does not describe it
+    fn overlays(&self, range: &CellRange) -> bool {
+        self.overlay.keys().any(|address| range.contains(address))
+    }
+}
+
+impl EvaluationContext for OverlayContext<'_> {
+    fn get_cell_value(&self, address: &CellAddress) -> Result<CellValue> {
+        if self.is_evaluating(address) {
+            return Err(crate::SpreadsheetError::CircularDependency);
+        }
+        match self.overlay.get(address) {
+            Some(value) => Ok(value.clone()),
+            None => self.inner.get_cell_value(address),
+        }
+    }
+
+    fn is_evaluating(&self, address: &CellAddress) -> bool {
+        self.inner.is_evaluating(address)
+    }
+
+    fn push_evaluation(&mut self, address: &CellAddress) {
+        self.inner.push_evaluation(address);
+    }
+
+    fn pop_evaluation(&mut self, address: &CellAddress) {
+        self.inner.pop_evaluation(address);
+    }
+
+    fn external_value(&mut self, request: &ExternalRequest) -> Option<CellValue> {
+        self.inner.external_value(request)
+    }
+
+    fn deterministic(&self) -> Option<&DeterministicSource> {
+        self.inner.deterministic()
+    }
+
+    fn name_value(&self, name: &str) -> Option<CellValue> {
+        self.inner.name_value(name)
+    }
+
+    fn name_range(&self, name: &str) -> Option<CellRange> {
+        self.inner.name_range(name)
+    }
+
+    fn sheet_context(&self, sheet: &str) -> Option<Box<dyn EvaluationContext>> {
+        self.inner.sheet_context(sheet)
+    }
+
+    fn indexed_first_match(&self, column: &CellRange, key: &LookupKey) -> Option<Option<u32>> {
+        if self.overlays(column) {
+            return None;
+        }
+        self.inner.indexed_first_match(column, key)
+    }
+
+    fn indexed_match_count(&self, column: &CellRange, key: &LookupKey) -> Option<usize> {
+        if self.overlays(column) {
+            return None;
+        }
+        self.inner.indexed_match_count(column, key)
+    }
 }
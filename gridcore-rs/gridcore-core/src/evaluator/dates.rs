@@ -0,0 +1,299 @@
+//! Dates and times as serial numbers.
+//!
+//! A date is stored as the number of days since 1899-12-30, so 45306 is
+//! 2024-01-15, the same serial other spreadsheets give every date from
+//! March 1900 on. A time of day is the fraction of a day after the point.
+//! Like an amount typed with a currency, a date typed as `2024-01-15` or
+//! `1/15/2024` is stored as its number with a date display format inferred
+//! from how it was written, so `=A1+30` is the date 30 days later and
+//! `=B1-A1` the days between two dates.
+
+use crate::domain::CellFormat;
+use crate::formula::{BinaryOperator, Expr};
+use crate::types::CellValue;
+use crate::{Result, SpreadsheetError};
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime};
+
+/// Display pattern of dates shown without a typed form to follow
+pub const ISO_DATE: &str = "%Y-%m-%d";
+
+/// Display pattern of date and time values such as `NOW()`
+pub const ISO_DATE_TIME: &str = "%Y-%m-%d %H:%M";
+
+/// Forms a typed date is read in, with the pattern it is shown in. Month
+/// first when both readings of a slashed date work.
+const DATE_FORMS: [(&str, &str); 4] = [
+    ("%Y-%m-%d", ISO_DATE),
+    ("%m/%d/%Y", "%-m/%-d/%Y"),
+    ("%d/%m/%Y", "%-d/%-m/%Y"),
+    ("%Y/%m/%d", "%Y/%m/%d"),
+];
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1899, 12, 30).expect("valid epoch")
+}
+
+/// Serial number of `date`
+pub fn date_serial(date: NaiveDate) -> f64 {
+    date.signed_duration_since(epoch()).num_days() as f64
+}
+
+/// Serial number of `at`, its time of day as the fraction
+pub fn datetime_serial(at: NaiveDateTime) -> f64 {
+    let seconds = at
+        .time()
+        .signed_duration_since(NaiveTime::MIN)
+        .num_seconds();
+    date_serial(at.date()) + seconds as f64 / SECONDS_PER_DAY
+}
+
+/// Date of a serial number, ignoring the time of day
+pub fn serial_date(serial: f64) -> Option<NaiveDate> {
+    if !serial.is_finite() {
+        return None;
+    }
+    epoch().checked_add_signed(Duration::try_days(serial.floor() as i64)?)
+}
+
+/// Date and time of a serial number, to the second
+pub fn serial_datetime(serial: f64) -> Option<NaiveDateTime> {
+    let date = serial_date(serial)?;
+    let seconds = ((serial - serial.floor()) * SECONDS_PER_DAY).round() as i64;
+    date.and_time(NaiveTime::MIN)
+        .checked_add_signed(Duration::try_seconds(seconds)?)
+}
+
+/// Date written in one of the common forms: `2024-01-31`, `01/31/2024`,
+/// `31/01/2024` or `2024/01/31`
+pub fn parse_date_text(text: &str) -> Option<NaiveDate> {
+    parse_date_form(text).map(|(date, _)| date)
+}
+
+/// Date written in one of the common forms, with the pattern that shows a
+/// date the way it was written
+fn parse_date_form(text: &str) -> Option<(NaiveDate, &'static str)> {
+    // Years are written in full, so `1/2/3` stays text
+    if !text.split(['-', '/']).any(|part| part.len() == 4) {
+        return None;
+    }
+    DATE_FORMS.iter().find_map(|(form, shown)| {
+        NaiveDate::parse_from_str(text, form)
+            .ok()
+            .map(|date| (date, *shown))
+    })
+}
+
+/// A typed date as its serial number and the format showing it as typed
+pub fn parse_date_input(text: &str) -> Option<(f64, CellFormat)> {
+    let (date, shown) = parse_date_form(text.trim())?;
+    Some((date_serial(date), CellFormat::date(shown)))
+}
+
+/// A date written in the same form as `like`, or in ISO form when `like`
+/// is not a date
+pub fn date_text_like(date: NaiveDate, like: &str) -> String {
+    let shown = parse_date_form(like).map_or(ISO_DATE, |(_, shown)| shown);
+    date.format(shown).to_string()
+}
+
+/// Format a formula's result is shown in when it is a date or a time from
+/// a date function, e.g. `=DATE(2024,1,15)` or `=EOMONTH(A1,1)`
+pub fn result_format(expr: &Expr) -> Option<CellFormat> {
+    let Expr::FunctionCall { name, .. } = expr else {
+        return None;
+    };
+    match name.to_ascii_uppercase().as_str() {
+        "DATE" | "TODAY" | "EOMONTH" => Some(CellFormat::date(ISO_DATE)),
+        "NOW" => Some(CellFormat::date(ISO_DATE_TIME)),
+        _ => None,
+    }
+}
+
+/// Whether `expr` subtracts one date from another, which gives days rather
+/// than a date, e.g. `=B1-A1` but not `=B1-7`
+pub fn counts_days(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::BinaryOp {
+            op: BinaryOperator::Subtract,
+            right,
+            ..
+        } if !matches!(**right, Expr::Literal { .. })
+    )
+}
+
+/// A date argument: a serial number, or text in one of the common forms
+pub(crate) fn date_arg(value: &CellValue) -> Result<NaiveDate> {
+    match value {
+        CellValue::String(text) => parse_date_text(text.trim()).ok_or(SpreadsheetError::ValueError),
+        value => serial_date(super::operators::coerce_to_number(value)?)
+            .filter(|date| *date >= epoch())
+            .ok_or(SpreadsheetError::NumError),
+    }
+}
+
+/// `DATE(year, month, day)`, rolling months and days past the end of their
+/// year and month over into the next, and years below 1900 counted from it
+pub(crate) fn date_of(year: f64, month: f64, day: f64) -> Result<NaiveDate> {
+    let year = year.trunc() as i64;
+    let year = if (0..1900).contains(&year) {
+        year + 1900
+    } else {
+        year
+    };
+    let months = year * 12 + month.trunc() as i64 - 1;
+    let first = i32::try_from(months.div_euclid(12))
+        .ok()
+        .and_then(|year| NaiveDate::from_ymd_opt(year, months.rem_euclid(12) as u32 + 1, 1));
+    first
+        .and_then(|first| first.checked_add_signed(Duration::try_days(day.trunc() as i64 - 1)?))
+        .filter(|date| *date >= epoch())
+        .ok_or(SpreadsheetError::NumError)
+}
+
+/// `date` moved by whole `months`, on the last day of the month when it has
+/// no such day
+fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let shift = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+    if months < 0 {
+        date.checked_sub_months(shift)
+    } else {
+        date.checked_add_months(shift)
+    }
+}
+
+/// `EOMONTH(start, months)`: the last day of the month `months` after
+/// the one `start` is in
+pub(crate) fn end_of_month(start: NaiveDate, months: f64) -> Result<NaiveDate> {
+    add_months(
+        start.with_day(1).ok_or(SpreadsheetError::NumError)?,
+        months.trunc() as i64 + 1,
+    )
+    .and_then(|next| next.pred_opt())
+    .filter(|date| *date >= epoch())
+    .ok_or(SpreadsheetError::NumError)
+}
+
+/// `DATEDIF(start, end, unit)`: whole years (`Y`), months (`M`) or days
+/// (`D`) from `start` to `end`, or the days left over after whole months
+/// (`MD`), the months after whole years (`YM`) or the days after whole
+/// years (`YD`)
+pub(crate) fn date_difference(start: NaiveDate, end: NaiveDate, unit: &str) -> Result<f64> {
+    if start > end {
+        return Err(SpreadsheetError::NumError);
+    }
+    // A month is whole once the end reaches the start's day of the month
+    let months = i64::from(end.year() - start.year()) * 12 + i64::from(end.month())
+        - i64::from(start.month())
+        - i64::from(end.day() < start.day());
+    let days_after = |months: i64| {
+        add_months(start, months)
+            .map(|date| end.signed_duration_since(date).num_days() as f64)
+            .ok_or(SpreadsheetError::NumError)
+    };
+    match unit.trim().to_ascii_uppercase().as_str() {
+        "Y" => Ok((months / 12) as f64),
+        "M" => Ok(months as f64),
+        "D" => Ok(end.signed_duration_since(start).num_days() as f64),
+        "MD" => days_after(months),
+        "YM" => Ok((months % 12) as f64),
+        "YD" => days_after(months / 12 * 12),
+        _ => Err(SpreadsheetError::NumError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_serials_match_other_spreadsheets() {
+        assert_eq!(date_serial(date(2024, 1, 15)), 45306.0);
+        assert_eq!(date_serial(date(1900, 3, 1)), 61.0);
+        assert_eq!(serial_date(45306.75), Some(date(2024, 1, 15)));
+        let noon = date(2024, 1, 15).and_hms_opt(12, 0, 0).unwrap();
+        assert_eq!(datetime_serial(noon), 45306.5);
+        assert_eq!(serial_datetime(45306.5), Some(noon));
+        assert_eq!(serial_date(f64::NAN), None);
+    }
+
+    #[test]
+    fn test_typed_dates_keep_their_form() {
+        let input = parse_date_input;
+        assert_eq!(
+            input("2024-01-15"),
+            Some((45306.0, CellFormat::date(ISO_DATE)))
+        );
+        assert_eq!(
+            input("1/15/2024"),
+            Some((45306.0, CellFormat::date("%-m/%-d/%Y")))
+        );
+        assert_eq!(
+            input("15/01/2024"),
+            Some((45306.0, CellFormat::date("%-d/%-m/%Y")))
+        );
+        assert_eq!(input("2024-02-30"), None);
+        assert_eq!(input("15 Jan"), None);
+        assert_eq!(date_text_like(date(2024, 2, 1), "1/15/2024"), "2/1/2024");
+        assert_eq!(date_text_like(date(2024, 2, 1), "x"), "2024-02-01");
+    }
+
+    #[test]
+    fn test_date_rolls_over() {
+        assert_eq!(date_of(2024.0, 1.0, 15.0).unwrap(), date(2024, 1, 15));
+        assert_eq!(date_of(2024.0, 14.0, 1.0).unwrap(), date(2025, 2, 1));
+        assert_eq!(date_of(2024.0, 3.0, 0.0).unwrap(), date(2024, 2, 29));
+        assert_eq!(date_of(2024.0, 0.0, 1.0).unwrap(), date(2023, 12, 1));
+        assert_eq!(date_of(99.0, 1.0, 1.0).unwrap(), date(1999, 1, 1));
+        assert_eq!(date_of(1899.0, 1.0, 1.0).unwrap(), date(3799, 1, 1));
+        assert!(date_of(-1.0, 1.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_end_of_month() {
+        assert_eq!(
+            end_of_month(date(2024, 1, 31), 1.0).unwrap(),
+            date(2024, 2, 29)
+        );
+        assert_eq!(
+            end_of_month(date(2024, 1, 15), 0.0).unwrap(),
+            date(2024, 1, 31)
+        );
+        assert_eq!(
+            end_of_month(date(2024, 3, 10), -1.0).unwrap(),
+            date(2024, 2, 29)
+        );
+        assert_eq!(
+            end_of_month(date(2023, 11, 5), 2.0).unwrap(),
+            date(2024, 1, 31)
+        );
+    }
+
+    #[test]
+    fn test_date_difference() {
+        let (start, end) = (date(2020, 5, 20), date(2024, 2, 10));
+        let diff = |unit| date_difference(start, end, unit).unwrap();
+        assert_eq!(diff("Y"), 3.0);
+        assert_eq!(diff("M"), 44.0);
+        assert_eq!(diff("D"), 1361.0);
+        assert_eq!(diff("MD"), 21.0);
+        assert_eq!(diff("ym"), 8.0);
+        assert_eq!(diff("YD"), 266.0);
+        assert_eq!(
+            date_difference(date(2024, 1, 31), date(2024, 2, 29), "M").unwrap(),
+            0.0
+        );
+        assert_eq!(
+            date_difference(date(2024, 1, 31), date(2024, 3, 1), "M").unwrap(),
+            1.0
+        );
+        assert!(date_difference(end, start, "D").is_err());
+        assert!(date_difference(start, end, "W").is_err());
+    }
+}
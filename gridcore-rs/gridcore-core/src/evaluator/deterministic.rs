@@ -0,0 +1,53 @@
+//! What volatile functions read in deterministic mode, see
+//! [`Determinism`]. A workbook keeps one source for as long as the mode is
+//! on, so every evaluation draws the next numbers from the same generator.
+
+use super::random::Rng;
+use crate::workbook::Determinism;
+use chrono::NaiveDateTime;
+use std::sync::Mutex;
+
+/// A seeded generator and a frozen time
+#[derive(Debug)]
+pub struct DeterministicSource {
+    rng: Mutex<Rng>,
+    now_ms: i64,
+}
+
+impl DeterministicSource {
+    pub fn new(determinism: &Determinism) -> Self {
+        Self {
+            rng: Mutex::new(Rng::new(determinism.seed)),
+            now_ms: determinism.now_ms,
+        }
+    }
+
+    /// A number in `[0, 1)`
+    pub fn next_f64(&self) -> f64 {
+        self.rng.lock().unwrap().next_f64()
+    }
+
+    /// A whole number from `bottom` to `top`, both included
+    pub fn between(&self, bottom: i64, top: i64) -> i64 {
+        self.rng.lock().unwrap().between(bottom, top)
+    }
+
+    /// The time NOW reads
+    pub fn now(&self) -> NaiveDateTime {
+        self.determinism().now()
+    }
+
+    /// Settings that carry on from here: the generator's state as the seed
+    pub fn determinism(&self) -> Determinism {
+        Determinism {
+            seed: self.rng.lock().unwrap().state(),
+            now_ms: self.now_ms,
+        }
+    }
+
+    /// A copy drawing what this one would draw next, for evaluations that
+    /// should leave it as it is
+    pub fn fork(&self) -> Self {
+        Self::new(&self.determinism())
+    }
+}
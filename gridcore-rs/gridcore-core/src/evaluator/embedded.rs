@@ -0,0 +1,274 @@
+//! Formulas evaluated outside a sheet.
+//!
+//! Hosts evaluate expressions such as `price * qty * (1 + TaxRate)` or
+//! `=SUM(items)` against their own data by implementing [`ValueResolver`]
+//! over it, without building a [`SpreadsheetFacade`]. Names and A1
+//! addresses are both looked up through the resolver; a name it does not
+//! know evaluates to `#NAME?`, a cell it does not know is empty. Maps of
+//! names to values and JSON objects are resolvers already.
+//!
+//! Functions that need a cell to evaluate in ([`CELL_CONTEXT_FUNCTIONS`])
+//! are refused when the expression is compiled.
+//!
+//! [`SpreadsheetFacade`]: crate::SpreadsheetFacade
+
+use super::{EvaluationContext, Evaluator};
+use crate::external::FETCH_FUNCTION;
+use crate::formula::{CellRange, Expr, FormulaParser};
+use crate::types::{CellAddress, CellValue};
+use crate::{Result, SpreadsheetError};
+use std::collections::HashMap;
+
+/// Functions that depend on the cell a formula sits in, or on the sheet's
+/// data store, and cannot be used in an embedded expression
+pub const CELL_CONTEXT_FUNCTIONS: &[&str] =
+    &["ROW", "COLUMN", "OFFSET", "INDIRECT", FETCH_FUNCTION];
+
+/// Host data an embedded expression reads
+pub trait ValueResolver {
+    /// Value of a name (e.g. `TaxRate`) or an A1 address, or `None` when
+    /// the host has no such value
+    fn resolve(&self, name: &str) -> Option<CellValue>;
+
+    /// Values of the cells of `range` in row order. By default each cell is
+    /// resolved by its address, cells the host does not know as empty.
+    fn resolve_range<'a>(
+        &'a self,
+        range: &'a CellRange,
+    ) -> Box<dyn Iterator<Item = CellValue> + 'a> {
+        Box::new(
+            range
+                .cells()
+                .map(|address| self.resolve(&address.to_a1()).unwrap_or(CellValue::Empty)),
+        )
+    }
+}
+
+/// Values keyed by name or address, as written in the expression
+impl ValueResolver for HashMap<String, CellValue> {
+    fn resolve(&self, name: &str) -> Option<CellValue> {
+        self.get(name).cloned()
+    }
+}
+
+/// Members of a JSON object: numbers, strings and booleans as themselves,
+/// `null` as empty and arrays as arrays, so `=SUM(items)` works over
+/// `{"items": [1, 2, 3]}`. Nested objects cannot be used.
+impl ValueResolver for serde_json::Map<String, serde_json::Value> {
+    fn resolve(&self, name: &str) -> Option<CellValue> {
+        self.get(name).and_then(json_value)
+    }
+}
+
+fn json_value(value: &serde_json::Value) -> Option<CellValue> {
+    use serde_json::Value;
+
+    Some(match value {
+        Value::Null => CellValue::Empty,
+        Value::Bool(b) => CellValue::Boolean(*b),
+        Value::Number(n) => CellValue::Number(n.as_f64()?),
+        Value::String(s) => CellValue::from_string(s.clone()),
+        Value::Array(items) => CellValue::from_array(items.iter().filter_map(json_value).collect()),
+        Value::Object(_) => return None,
+    })
+}
+
+/// An expression parsed once and evaluated against any number of contexts
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledExpression {
+    expr: Expr,
+}
+
+impl CompiledExpression {
+    /// Parse `formula`, with or without a leading `=`. Fails on syntax
+    /// errors and on calls to [`CELL_CONTEXT_FUNCTIONS`].
+    pub fn compile(formula: &str) -> Result<Self> {
+        let expr = FormulaParser::parse(formula)?;
+        if let Some(function) = CELL_CONTEXT_FUNCTIONS
+            .iter()
+            .find(|function| expr.calls(function))
+        {
+            return Err(SpreadsheetError::InvalidFormula(format!(
+                "{} needs a cell to evaluate in and cannot be used in an embedded expression",
+                function
+            )));
+        }
+        Ok(Self { expr })
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Evaluate against `resolver`. Formula errors such as `#DIV/0!` come
+    /// back as error values, as they would in a cell.
+    pub fn evaluate(&self, resolver: &dyn ValueResolver) -> Result<CellValue> {
+        let mut context = ResolverContext { resolver };
+        Evaluator::new(&mut context).evaluate(&self.expr)
+    }
+}
+
+/// Parse and evaluate `formula` against `resolver` in one go. Use
+/// [`CompiledExpression`] to evaluate the same formula many times.
+pub fn evaluate_expression(formula: &str, resolver: &dyn ValueResolver) -> Result<CellValue> {
+    CompiledExpression::compile(formula)?.evaluate(resolver)
+}
+
+/// Evaluation context reading everything from a resolver. Host values
+/// cannot refer back to the expression, so nothing is ever circular.
+struct ResolverContext<'a> {
+    resolver: &'a dyn ValueResolver,
+}
+
+impl EvaluationContext for ResolverContext<'_> {
+    fn get_cell_value(&self, address: &CellAddress) -> Result<CellValue> {
+        Ok(self
+            .resolver
+            .resolve(&address.to_a1())
+            .unwrap_or(CellValue::Empty))
+    }
+
+    fn is_evaluating(&self, _address: &CellAddress) -> bool {
+        false
+    }
+
+    fn push_evaluation(&mut self, _address: &CellAddress) {}
+
+    fn pop_evaluation(&mut self, _address: &CellAddress) {}
+
+    fn name_value(&self, name: &str) -> Option<CellValue> {
+        self.resolver.resolve(name)
+    }
+
+    fn range_values(&self, range: &CellRange) -> Option<Vec<CellValue>> {
+        Some(self.resolver.resolve_range(range).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ErrorType;
+    use std::cell::Cell;
+
+    fn values(entries: &[(&str, CellValue)]) -> HashMap<String, CellValue> {
+        entries
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_arithmetic_over_host_variables() {
+        let order = values(&[
+            ("price", CellValue::Number(12.5)),
+            ("qty", CellValue::Number(4.0)),
+            ("TaxRate", CellValue::Number(0.2)),
+        ]);
+        assert_eq!(
+            evaluate_expression("price * qty * (1 + TaxRate)", &order).unwrap(),
+            CellValue::Number(60.0)
+        );
+        assert_eq!(
+            evaluate_expression("=IF(qty > 3, \"bulk\", \"single\")", &order).unwrap(),
+            CellValue::from_string("bulk".to_string())
+        );
+    }
+
+    #[test]
+    fn test_range_functions_over_host_arrays() {
+        let items = CellValue::from_array(vec![
+            CellValue::Number(3.0),
+            CellValue::Number(4.0),
+            CellValue::Number(5.0),
+        ]);
+        let data = values(&[
+            ("items", items),
+            ("A1", CellValue::Number(10.0)),
+            ("A2", CellValue::Number(20.0)),
+        ]);
+        assert_eq!(
+            evaluate_expression("=SUM(items)", &data).unwrap(),
+            CellValue::Number(12.0)
+        );
+        // A3 is not provided and reads as empty
+        assert_eq!(
+            evaluate_expression("=SUM(A1:A3) + A3", &data).unwrap(),
+            CellValue::Number(30.0)
+        );
+    }
+
+    #[test]
+    fn test_json_object_context() {
+        let serde_json::Value::Object(order) = serde_json::json!({
+            "items": [2, 3, null],
+            "discount": 0.5,
+            "label": "box",
+        }) else {
+            unreachable!()
+        };
+        assert_eq!(
+            evaluate_expression("=SUM(items) * discount", &order).unwrap(),
+            CellValue::Number(2.5)
+        );
+        assert_eq!(
+            evaluate_expression("=label & \"!\"", &order).unwrap(),
+            CellValue::from_string("box!".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_names_and_cell_functions() {
+        let empty = HashMap::new();
+        let value = evaluate_expression("price * 2", &empty).unwrap();
+        assert_eq!(
+            value,
+            CellValue::from_error(ErrorType::NameError {
+                name: "price".to_string()
+            })
+        );
+
+        let refused = CompiledExpression::compile("=ROW() + 1").unwrap_err();
+        assert!(refused.to_string().contains("ROW needs a cell"));
+        assert!(CompiledExpression::compile("FETCH(\"https://x\")").is_err());
+    }
+
+    #[test]
+    fn test_compiled_expression_over_many_contexts() {
+        /// Resolver counting its range reads
+        struct Row {
+            price: f64,
+            ranges: Cell<usize>,
+        }
+
+        impl ValueResolver for Row {
+            fn resolve(&self, name: &str) -> Option<CellValue> {
+                (name == "price").then_some(CellValue::Number(self.price))
+            }
+
+            fn resolve_range<'a>(
+                &'a self,
+                range: &'a CellRange,
+            ) -> Box<dyn Iterator<Item = CellValue> + 'a> {
+                self.ranges.set(self.ranges.get() + 1);
+                Box::new(std::iter::repeat_n(CellValue::Number(1.0), range.size()))
+            }
+        }
+
+        let compiled = CompiledExpression::compile("=price + SUM(B1:B3)").unwrap();
+        let rows: Vec<Row> = (0..100)
+            .map(|i| Row {
+                price: i as f64,
+                ranges: Cell::new(0),
+            })
+            .collect();
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(
+                compiled.evaluate(row).unwrap(),
+                CellValue::Number(i as f64 + 3.0)
+            );
+            // The whole range is read in one call
+            assert_eq!(row.ranges.get(), 1);
+        }
+    }
+}
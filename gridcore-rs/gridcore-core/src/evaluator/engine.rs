@@ -1,8 +1,12 @@
 use super::context::EvaluationContext;
 use super::functions::FunctionLibrary;
+use super::lookup::{self, Criterion};
 use super::operators;
+use crate::external::{ExternalRequest, FETCH_FUNCTION};
 use crate::formula::ast::{CellRange, Expr};
-use crate::types::{CellValue, ErrorType};
+use crate::repository::LookupKey;
+use crate::sparkline::{SPARKLINE_FUNCTION, Sparkline, SparklineKind, SparklineOptions};
+use crate::types::{CellAddress, CellArray, CellValue, ErrorType};
 use crate::utils::object_pool::global::CELL_VALUE_VEC_POOL;
 use crate::{Result, SpreadsheetError};
 use smallvec::SmallVec;
@@ -16,6 +20,8 @@ use crate::perf_incr;
 pub struct Evaluator<'a> {
     context: &'a mut dyn EvaluationContext,
     function_library: FunctionLibrary,
+    /// Steps left before evaluation gives up, `None` for no limit
+    budget: Option<usize>,
 }
 
 impl<'a> Evaluator<'a> {
@@ -24,13 +30,31 @@ impl<'a> Evaluator<'a> {
         Evaluator {
             context,
             function_library: FunctionLibrary::new(),
+            budget: None,
         }
     }
 
+    /// Stop with [`SpreadsheetError::BudgetExceeded`] after `steps` steps,
+    /// counting each evaluated expression and each cell read from a range
+    pub fn with_budget(mut self, steps: usize) -> Self {
+        self.budget = Some(steps);
+        self
+    }
+
+    fn spend(&mut self, steps: usize) -> Result<()> {
+        if let Some(budget) = self.budget.as_mut() {
+            *budget = budget
+                .checked_sub(steps)
+                .ok_or(SpreadsheetError::BudgetExceeded)?;
+        }
+        Ok(())
+    }
+
     /// Evaluate a formula expression
     pub fn evaluate(&mut self, expr: &Expr) -> Result<CellValue> {
         #[cfg(feature = "perf")]
         perf_incr!(FORMULA_EVALUATIONS);
+        self.spend(1)?;
 
         match expr {
             Expr::Literal { value, .. } => Ok(value.clone()),
@@ -61,6 +85,28 @@ impl<'a> Evaluator<'a> {
                 }
             }
 
+            Expr::Name { name } => {
+                if let Some(value) = self.context.name_value(name) {
+                    return Ok(value);
+                }
+                match self.context.name_range(name) {
+                    // A one-cell range reads like a reference to its cell
+                    Some(range) if range.size() == 1 => self.evaluate(&Expr::Reference {
+                        address: range.start,
+                        absolute_col: true,
+                        absolute_row: true,
+                    }),
+                    Some(range) => self.evaluate(&named_range_expr(range)),
+                    None => Ok(CellValue::from_error(ErrorType::NameError {
+                        name: name.clone(),
+                    })),
+                }
+            }
+
+            Expr::SheetReference { sheet, reference } => Ok(self
+                .on_sheet(sheet, |evaluator| evaluator.evaluate(reference))?
+                .unwrap_or_else(|| missing_sheet(sheet))),
+
             Expr::Range { .. } => {
                 // Ranges by themselves evaluate to an error
                 // They should only be used as function arguments
@@ -69,6 +115,25 @@ impl<'a> Evaluator<'a> {
                 ))
             }
 
+            // A union stands for several areas at once, so like a range it
+            // only makes sense as a function argument. An intersection that
+            // comes down to one cell reads that cell; an empty one is #NULL!.
+            Expr::Union { .. } | Expr::Intersection { .. } => {
+                let areas = expr.areas().unwrap_or_default();
+                match areas.as_slice() {
+                    [] => Ok(CellValue::from_error(ErrorType::NullIntersection)),
+                    [area] if matches!(expr, Expr::Intersection { .. }) && area.size() == 1 => self
+                        .evaluate(&Expr::Reference {
+                            address: area.start,
+                            absolute_col: false,
+                            absolute_row: false,
+                        }),
+                    _ => Err(SpreadsheetError::InvalidFormula(
+                        "Range expressions can only be used as function arguments".to_string(),
+                    )),
+                }
+            }
+
             Expr::UnaryOp { op, expr, .. } => {
                 let value = self.evaluate(expr)?;
                 operators::apply_unary(op, value)
@@ -86,38 +151,262 @@ impl<'a> Evaluator<'a> {
         }
     }
 
+    /// Evaluate a formula as a cell holds it: a range, an operation over
+    /// ranges and the array functions give arrays, which the cell spills.
+    /// Everything else gives a single value as [`Self::evaluate`] does.
+    pub fn evaluate_array(&mut self, expr: &Expr) -> Result<CellArray> {
+        match expr {
+            Expr::Range { range, .. } => self.range_array(range),
+            Expr::SheetReference { sheet, reference } => Ok(self
+                .on_sheet(sheet, |evaluator| evaluator.evaluate_array(reference))?
+                .unwrap_or_else(|| CellArray::scalar(missing_sheet(sheet)))),
+            Expr::Name { name } if self.context.name_value(name).is_none() => {
+                match self.context.name_range(name) {
+                    Some(range) if range.size() > 1 => self.range_array(&range),
+                    _ => self.evaluate(expr).map(CellArray::scalar),
+                }
+            }
+            Expr::UnaryOp { op, expr: operand } => {
+                let array = self.evaluate_array(operand)?;
+                if array.is_scalar() {
+                    return operators::apply_unary(op, array.into_value()).map(CellArray::scalar);
+                }
+                Ok(array.map(|value| element(operators::apply_unary(op, value.clone()))))
+            }
+            Expr::BinaryOp { op, left, right } => {
+                let left = self.evaluate_array(left)?;
+                let right = self.evaluate_array(right)?;
+                if left.is_scalar() && right.is_scalar() {
+                    return operators::apply_binary(op, left.into_value(), right.into_value())
+                        .map(CellArray::scalar);
+                }
+                // A single row or column repeats along the other operand
+                Ok(CellArray::from_fn(
+                    left.rows().max(right.rows()),
+                    left.cols().max(right.cols()),
+                    |row, col| {
+                        element(operators::apply_binary(
+                            op,
+                            left.broadcast(row, col),
+                            right.broadcast(row, col),
+                        ))
+                    },
+                ))
+            }
+            Expr::FunctionCall { name, args } if is_array_function(name) => {
+                self.evaluate_array_function(name, args)
+            }
+            _ => self.evaluate(expr).map(CellArray::scalar),
+        }
+    }
+
+    /// Run `evaluate` against the cells of the sheet named `sheet`, or
+    /// `None` when the workbook has no such sheet. The steps it takes count
+    /// against this evaluation's budget.
+    fn on_sheet<T>(
+        &mut self,
+        sheet: &str,
+        evaluate: impl FnOnce(&mut Evaluator<'_>) -> Result<T>,
+    ) -> Result<Option<T>> {
+        let Some(mut context) = self.context.sheet_context(sheet) else {
+            return Ok(None);
+        };
+        let mut evaluator = Evaluator::new(context.as_mut());
+        evaluator.budget = self.budget;
+        let result = evaluate(&mut evaluator);
+        self.budget = evaluator.budget;
+        result.map(Some)
+    }
+
+    /// The values of `range` in its shape, or the circular reference error
+    /// reading it runs into
+    fn range_array(&mut self, range: &CellRange) -> Result<CellArray> {
+        match self.area_values(std::slice::from_ref(range))? {
+            CellValue::Array(values) => {
+                Ok(
+                    CellArray::new(range.row_count(), range.col_count(), values.to_vec())
+                        .unwrap_or_else(|| CellArray::scalar(CellValue::Empty)),
+                )
+            }
+            value => Ok(CellArray::scalar(value)),
+        }
+    }
+
+    /// SEQUENCE(rows, [columns], [start], [step]) counts from `start` by
+    /// `step` row by row; TRANSPOSE(array) turns rows into columns
+    fn evaluate_array_function(&mut self, name: &str, args: &[Expr]) -> Result<CellArray> {
+        if name.eq_ignore_ascii_case("TRANSPOSE") {
+            let [arg] = args else {
+                return Err(SpreadsheetError::InvalidArguments(
+                    "TRANSPOSE expects one array".to_string(),
+                ));
+            };
+            return Ok(self.evaluate_array(arg)?.transpose());
+        }
+
+        if args.is_empty() || args.len() > 4 {
+            return Err(SpreadsheetError::InvalidArguments(
+                "SEQUENCE expects rows and optional columns, start and step".to_string(),
+            ));
+        }
+        let mut numbers = [1.0, 1.0, 1.0, 1.0];
+        for (number, arg) in numbers.iter_mut().zip(args) {
+            match self.evaluate(arg)? {
+                value if value.is_error() => return Ok(CellArray::scalar(value)),
+                CellValue::Empty => {}
+                value => *number = operators::coerce_to_number(&value)?,
+            }
+        }
+        let [rows, cols, start, step] = numbers;
+        let (rows, cols) = (rows.trunc(), cols.trunc());
+        if rows < 1.0 || cols < 1.0 || rows * cols > MAX_ARRAY_CELLS as f64 {
+            return Ok(CellArray::scalar(CellValue::from_error(
+                ErrorType::NumError,
+            )));
+        }
+        let cols = cols as usize;
+        Ok(CellArray::from_fn(rows as usize, cols, |row, col| {
+            CellValue::Number(start + step * (row * cols + col) as f64)
+        }))
+    }
+
+    /// `args` with each named range replaced by its range, or `None` when
+    /// no argument is one
+    fn resolve_named_ranges(&self, args: &[Expr]) -> Option<Vec<Expr>> {
+        let range_of = |arg: &Expr| match arg {
+            Expr::Name { name } if self.context.name_value(name).is_none() => {
+                self.context.name_range(name)
+            }
+            _ => None,
+        };
+        if !args.iter().any(|arg| range_of(arg).is_some()) {
+            return None;
+        }
+        Some(
+            args.iter()
+                .map(|arg| match range_of(arg) {
+                    Some(range) => named_range_expr(range),
+                    None => arg.clone(),
+                })
+                .collect(),
+        )
+    }
+
     /// Evaluate a function call
     fn evaluate_function(&mut self, name: &str, args: &[Expr]) -> Result<CellValue> {
+        // Named ranges are passed like the ranges they stand for
+        if let Some(args) = self.resolve_named_ranges(args) {
+            return self.evaluate_function(name, &args);
+        }
+        // What is left of names stands for nothing, which the functions
+        // reading their ranges themselves would take for a bad range
+        if READS_OWN_RANGES
+            .iter()
+            .any(|f| name.eq_ignore_ascii_case(f))
+            && let Some(unknown) = args.iter().find_map(|arg| match arg {
+                Expr::Name { name } if self.context.name_value(name).is_none() => Some(name),
+                _ => None,
+            })
+        {
+            return Ok(CellValue::from_error(ErrorType::NameError {
+                name: unknown.clone(),
+            }));
+        }
+
+        // Lookups read their ranges themselves, so they can use an index
+        if name.eq_ignore_ascii_case("VLOOKUP") {
+            return self.evaluate_table_lookup(args, false);
+        }
+        if name.eq_ignore_ascii_case("HLOOKUP") {
+            return self.evaluate_table_lookup(args, true);
+        }
+        if name.eq_ignore_ascii_case("MATCH") {
+            return self.evaluate_match(args);
+        }
+        if name.eq_ignore_ascii_case("INDEX") {
+            return self.evaluate_index(args);
+        }
+        if name.eq_ignore_ascii_case("COUNTIF") {
+            return self.evaluate_countif(args);
+        }
+        if name.eq_ignore_ascii_case("SUMIF") {
+            return self.evaluate_sumif(args);
+        }
+        if name.eq_ignore_ascii_case("SUMIFS") {
+            return self.evaluate_sumifs(args);
+        }
+        if name.eq_ignore_ascii_case("COUNTIFS") {
+            return self.evaluate_countifs(args);
+        }
+        if name.eq_ignore_ascii_case("AVERAGEIF") {
+            return self.evaluate_averageif(args);
+        }
+        if name.eq_ignore_ascii_case("AVERAGEIFS") {
+            return self.evaluate_averageifs(args);
+        }
+        if name.eq_ignore_ascii_case(SPARKLINE_FUNCTION) {
+            return self.evaluate_sparkline(args);
+        }
+        if name.eq_ignore_ascii_case("IFERROR") || name.eq_ignore_ascii_case("IFNA") {
+            return self.evaluate_if_error(name, args);
+        }
+        // Inside other formulas an array's values are read like a range's
+        if is_array_function(name) {
+            return self
+                .evaluate_array_function(name, args)
+                .map(CellArray::into_value);
+        }
+
         // Special handling for functions that take ranges
         // Most functions have 1-4 arguments, so use SmallVec to avoid heap allocation
         let mut evaluated_args: SmallVec<[CellValue; 4]> = SmallVec::with_capacity(args.len());
 
         for arg in args {
             match arg {
-                Expr::Range { range, .. } => {
-                    // For ranges, collect all cell values using pooled vector
-                    let cells: Vec<_> = range.cells().collect();
-                    let mut values = CELL_VALUE_VEC_POOL.get();
-                    values.reserve(cells.len());
-                    for cell_addr in cells {
-                        if self.context.is_evaluating(&cell_addr) {
-                            // Return circular reference error as CellValue::Error
-                            return Ok(CellValue::from_error(ErrorType::CircularDependency {
-                                cells: vec![cell_addr],
-                            }));
-                        }
-                        match self.context.get_cell_value(&cell_addr) {
-                            Ok(value) => values.push(value),
-                            Err(SpreadsheetError::CircularDependency) => {
-                                return Ok(CellValue::from_error(ErrorType::CircularDependency {
-                                    cells: vec![cell_addr],
-                                }));
-                            }
-                            Err(e) => return Err(e),
-                        }
+                Expr::Range { .. } | Expr::Union { .. } | Expr::Intersection { .. } => {
+                    // Each area of a union is read in turn into one array
+                    let areas = arg.areas().unwrap_or_default();
+                    if areas.is_empty() {
+                        evaluated_args.push(CellValue::from_error(ErrorType::NullIntersection));
+                        continue;
                     }
-                    // Take ownership from pool for the array
-                    evaluated_args.push(CellValue::from_array(values.take()));
+                    let values = self.area_values(&areas)?;
+                    if values.is_error() {
+                        // A circular reference fails the whole call
+                        return Ok(values);
+                    }
+                    evaluated_args.push(values);
+                }
+                Expr::SheetReference { sheet, reference }
+                    if matches!(**reference, Expr::Range { .. }) =>
+                {
+                    let areas = reference.areas().unwrap_or_default();
+                    let values = self
+                        .on_sheet(sheet, |evaluator| evaluator.area_values(&areas))?
+                        .unwrap_or_else(|| missing_sheet(sheet));
+                    if values.is_error() {
+                        // As is a sheet that is not there
+                        return Ok(values);
+                    }
+                    evaluated_args.push(values);
+                }
+                Expr::Reference { .. } if super::functions::reads_references_as_ranges(name) => {
+                    // Passed like a one-cell range, so a referenced TRUE or
+                    // text is skipped; errors still propagate as they are
+                    let value = self.evaluate(arg)?;
+                    evaluated_args.push(if value.is_error() {
+                        value
+                    } else {
+                        CellValue::from_array(vec![value])
+                    });
+                }
+                Expr::UnaryOp { .. } | Expr::BinaryOp { .. } if operates_on_ranges(arg) => {
+                    // An operation over a range is passed as the array of
+                    // its results, element by element
+                    evaluated_args.push(self.evaluate_array(arg)?.into_value());
+                }
+                _ if super::functions::inspects_errors(name) => {
+                    evaluated_args.push(self.evaluate_caught(arg)?);
                 }
                 _ => {
                     // Regular expression evaluation
@@ -126,12 +415,613 @@ impl<'a> Evaluator<'a> {
             }
         }
 
+        if name == FETCH_FUNCTION {
+            return self.evaluate_fetch(&evaluated_args);
+        }
+
+        // Deterministic mode takes volatile functions' randomness and time
+        // from the workbook
+        if let Some(source) = self.context.deterministic() {
+            return self
+                .function_library
+                .call_deterministic(name, &evaluated_args, source);
+        }
+
         // Call the function (convert SmallVec to slice)
         self.function_library.call(name, &evaluated_args)
     }
 
+    /// Evaluate `expr` with its errors as error values, for the functions
+    /// that look at an error rather than pass it on. Circular references
+    /// and running out of budget still stop the evaluation.
+    fn evaluate_caught(&mut self, expr: &Expr) -> Result<CellValue> {
+        match self.evaluate(expr) {
+            Err(
+                e @ (SpreadsheetError::BudgetExceeded
+                | SpreadsheetError::CircularDependency
+                | SpreadsheetError::CircularDependencyWith(_)),
+            ) => Err(e),
+            Err(e) => Ok(CellValue::from_error(e.to_error_type())),
+            value => value,
+        }
+    }
+
+    /// IFERROR(value, fallback) and IFNA(value, fallback): `value`, or
+    /// `fallback` when it is an error (IFNA: only #N/A). The fallback is
+    /// evaluated only when it is used.
+    fn evaluate_if_error(&mut self, name: &str, args: &[Expr]) -> Result<CellValue> {
+        if args.len() != 2 {
+            return Err(SpreadsheetError::InvalidArguments(format!(
+                "{} requires exactly 2 arguments",
+                name.to_uppercase()
+            )));
+        }
+        let value = self.evaluate_caught(&args[0])?;
+        let caught = match &value {
+            CellValue::Error(error) => {
+                !name.eq_ignore_ascii_case("IFNA") || matches!(**error, ErrorType::NotAvailable)
+            }
+            _ => false,
+        };
+        if caught {
+            self.evaluate(&args[1])
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Values of the cells of `areas` as one array, or a circular reference
+    /// error when one of them is being evaluated
+    fn area_values(&mut self, areas: &[CellRange]) -> Result<CellValue> {
+        self.spend(areas.iter().map(CellRange::size).sum())?;
+        let mut values = CELL_VALUE_VEC_POOL.get();
+        values.reserve(areas.iter().map(CellRange::size).sum());
+        for area in areas {
+            // Contexts may hand over a whole area at once
+            if let Some(area_values) = self.context.range_values(area) {
+                values.extend(area_values);
+                continue;
+            }
+            for cell_addr in area.cells() {
+                if self.context.is_evaluating(&cell_addr) {
+                    // Return circular reference error as CellValue::Error
+                    return Ok(CellValue::from_error(ErrorType::CircularDependency {
+                        cells: vec![cell_addr],
+                    }));
+                }
+                match self.context.get_cell_value(&cell_addr) {
+                    Ok(value) => values.push(value),
+                    Err(SpreadsheetError::CircularDependency) => {
+                        return Ok(CellValue::from_error(ErrorType::CircularDependency {
+                            cells: vec![cell_addr],
+                        }));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        // Take ownership from pool for the array
+        Ok(CellValue::from_array(values.take()))
+    }
+
+    /// FETCH(url, [selector]) reads its value from the context's data store
+    /// and shows #GETTING_DATA until the host has delivered it
+    fn evaluate_fetch(&mut self, args: &[CellValue]) -> Result<CellValue> {
+        if args.is_empty() || args.len() > 2 {
+            return Err(SpreadsheetError::InvalidArguments(
+                "FETCH expects a URL and an optional selector".to_string(),
+            ));
+        }
+        if let Some(error) = args.iter().find(|arg| arg.is_error()) {
+            return Ok(error.clone());
+        }
+
+        let url = args[0].to_string();
+        if url.trim().is_empty() {
+            return Err(SpreadsheetError::ValueError);
+        }
+        let selector = args
+            .get(1)
+            .map(|selector| selector.to_string())
+            .filter(|selector| !selector.is_empty());
+
+        let request = ExternalRequest::new(url, selector);
+        Ok(self
+            .context
+            .external_value(&request)
+            .unwrap_or_else(|| CellValue::from_error(ErrorType::GettingData)))
+    }
+
+    /// VLOOKUP(key, table, column, [approximate]) finds `key` in the first
+    /// column of `table` and reads `column` of that row. Approximate
+    /// lookups, the default, take the last row not after `key` in a first
+    /// column sorted ascending. HLOOKUP(key, table, row, [approximate]),
+    /// for `horizontal`, is the same with rows and columns swapped.
+    fn evaluate_table_lookup(&mut self, args: &[Expr], horizontal: bool) -> Result<CellValue> {
+        let (function, line) = if horizontal {
+            ("HLOOKUP", "row")
+        } else {
+            ("VLOOKUP", "column")
+        };
+        if !(3..=4).contains(&args.len()) {
+            return Err(SpreadsheetError::InvalidArguments(format!(
+                "{} expects a key, a table, a {} and an optional match type",
+                function, line
+            )));
+        }
+        let key = self.evaluate(&args[0])?;
+        if key.is_error() {
+            return Ok(key);
+        }
+        let Some(table) = single_area(&args[1]) else {
+            return Ok(value_error("range", "value"));
+        };
+        let index = match self.evaluate(&args[2])? {
+            CellValue::Number(n) => n.trunc(),
+            error @ CellValue::Error(_) => return Ok(error),
+            other => return Ok(value_error("number", other.type_name())),
+        };
+        let approximate = match args.get(3) {
+            Some(arg) => match self.evaluate(arg)? {
+                CellValue::Boolean(b) => b,
+                CellValue::Number(n) => n != 0.0,
+                CellValue::Empty => false,
+                error @ CellValue::Error(_) => return Ok(error),
+                other => return Ok(value_error("boolean", other.type_name())),
+            },
+            None => true,
+        };
+
+        let extent = if horizontal {
+            table.end.row - table.start.row + 1
+        } else {
+            table.end.col - table.start.col + 1
+        };
+        if index < 1.0 {
+            return Ok(value_error(&format!("{} of at least 1", line), "number"));
+        }
+        if index > extent as f64 {
+            return Ok(CellValue::from_error(ErrorType::InvalidRef {
+                reference: format!("{} {} of {}", line, index, table),
+            }));
+        }
+
+        let keys = if horizontal {
+            CellRange::new(
+                table.start,
+                CellAddress::new(table.end.col, table.start.row),
+            )
+        } else {
+            CellRange::new(
+                table.start,
+                CellAddress::new(table.start.col, table.end.row),
+            )
+        };
+        let found = if approximate {
+            self.approximate_position(&keys, &key, false)?
+        } else {
+            self.exact_position(&keys, &key)?
+        };
+        let index = index as u32 - 1;
+        match found {
+            Ok(Some(offset)) => self.evaluate(&Expr::Reference {
+                address: if horizontal {
+                    CellAddress::new(table.start.col + offset as u32, table.start.row + index)
+                } else {
+                    CellAddress::new(table.start.col + index, table.start.row + offset as u32)
+                },
+                absolute_col: false,
+                absolute_row: false,
+            }),
+            Ok(None) => Ok(CellValue::from_error(ErrorType::NotAvailable)),
+            Err(error) => Ok(error),
+        }
+    }
+
+    /// MATCH(key, range, [type]) is the position of `key` in a one-row or
+    /// one-column range: exactly for type 0, in ascending data for 1 (the
+    /// default) and in descending data for -1
+    fn evaluate_match(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if !(2..=3).contains(&args.len()) {
+            return Err(SpreadsheetError::InvalidArguments(
+                "MATCH expects a key, a range and an optional match type".to_string(),
+            ));
+        }
+        let key = self.evaluate(&args[0])?;
+        if key.is_error() {
+            return Ok(key);
+        }
+        let Some(range) = single_area(&args[1]) else {
+            return Ok(value_error("range", "value"));
+        };
+        if range.start.row != range.end.row && range.start.col != range.end.col {
+            return Ok(CellValue::from_error(ErrorType::NotAvailable));
+        }
+        let match_type = match args.get(2) {
+            Some(arg) => match self.evaluate(arg)? {
+                CellValue::Number(n) if n > 0.0 => 1,
+                CellValue::Number(n) if n < 0.0 => -1,
+                CellValue::Number(_) | CellValue::Empty => 0,
+                error @ CellValue::Error(_) => return Ok(error),
+                other => return Ok(value_error("number", other.type_name())),
+            },
+            None => 1,
+        };
+
+        let found = match match_type {
+            0 => self.exact_position(&range, &key)?,
+            _ => self.approximate_position(&range, &key, match_type < 0)?,
+        };
+        Ok(match found {
+            Ok(Some(offset)) => CellValue::Number(offset as f64 + 1.0),
+            Ok(None) => CellValue::from_error(ErrorType::NotAvailable),
+            Err(error) => error,
+        })
+    }
+
+    /// INDEX(range, row, [column]) is the cell of `range` at `row` and
+    /// `column`, counted from 1. A one-row range may take its column as
+    /// the only position. Positions past the range are #REF!.
+    fn evaluate_index(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if !(2..=3).contains(&args.len()) {
+            return Err(SpreadsheetError::InvalidArguments(
+                "INDEX expects a range, a row and an optional column".to_string(),
+            ));
+        }
+        let Some(range) = single_area(&args[0]) else {
+            return Ok(value_error("range", "value"));
+        };
+        let mut positions = [1.0; 2];
+        for (position, arg) in positions.iter_mut().zip(&args[1..]) {
+            *position = match self.evaluate(arg)? {
+                CellValue::Number(n) => n.trunc(),
+                error @ CellValue::Error(_) => return Ok(error),
+                other => return Ok(value_error("number", other.type_name())),
+            };
+        }
+        let [mut row, mut column] = positions;
+        if args.len() == 2 && range.start.row == range.end.row && range.start.col != range.end.col {
+            (row, column) = (1.0, row);
+        }
+        if row < 1.0 || column < 1.0 {
+            return Ok(value_error("position of at least 1", "number"));
+        }
+
+        let height = range.end.row - range.start.row + 1;
+        let width = range.end.col - range.start.col + 1;
+        if row > height as f64 || column > width as f64 {
+            return Ok(CellValue::from_error(ErrorType::InvalidRef {
+                reference: format!("row {}, column {} of {}", row, column, range),
+            }));
+        }
+        self.evaluate(&Expr::Reference {
+            address: CellAddress::new(
+                range.start.col + column as u32 - 1,
+                range.start.row + row as u32 - 1,
+            ),
+            absolute_col: false,
+            absolute_row: false,
+        })
+    }
+
+    /// COUNTIF(range, criterion) counts the cells of `range` meeting
+    /// `criterion`; see [`Criterion::parse`]
+    fn evaluate_countif(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if args.len() != 2 {
+            return Err(SpreadsheetError::InvalidArguments(
+                "COUNTIF expects a range and a criterion".to_string(),
+            ));
+        }
+        let Some(areas) = args[0].areas().filter(|areas| !areas.is_empty()) else {
+            return Ok(value_error("range", "value"));
+        };
+        let criterion = self.evaluate(&args[1])?;
+        if criterion.is_error() {
+            return Ok(criterion);
+        }
+        let criterion = Criterion::parse(&criterion);
+
+        let mut count = 0;
+        for area in &areas {
+            for col in area.start.col..=area.end.col {
+                let column = CellRange::new(
+                    CellAddress::new(col, area.start.row),
+                    CellAddress::new(col, area.end.row),
+                );
+                if let Criterion::Equals(Some(key)) = &criterion
+                    && let Some(matches) = self.context.indexed_match_count(&column, key)
+                {
+                    self.spend(1)?;
+                    count += matches;
+                    continue;
+                }
+                match self.area_values(&[column])? {
+                    CellValue::Array(values) => {
+                        count += values
+                            .iter()
+                            .filter(|value| criterion.matches(value))
+                            .count();
+                    }
+                    error => return Ok(error),
+                }
+            }
+        }
+        Ok(CellValue::Number(count as f64))
+    }
+
+    /// SUMIF(range, criterion, [sum_range]) adds the numbers of `sum_range`,
+    /// or of `range` itself, where `range` meets `criterion`
+    fn evaluate_sumif(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if !(2..=3).contains(&args.len()) {
+            return Err(SpreadsheetError::InvalidArguments(
+                "SUMIF expects a range, a criterion and an optional sum range".to_string(),
+            ));
+        }
+        let sum_range = args.get(2).unwrap_or(&args[0]);
+        self.conditional_sum(sum_range, &[(&args[0], &args[1])])
+    }
+
+    /// SUMIFS(sum_range, criteria_range1, criterion1, ...) adds the numbers
+    /// of `sum_range` where every criteria range meets its criterion
+    fn evaluate_sumifs(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if args.len() < 3 || args.len().is_multiple_of(2) {
+            return Err(SpreadsheetError::InvalidArguments(
+                "SUMIFS expects a sum range and pairs of ranges and criteria".to_string(),
+            ));
+        }
+        self.conditional_sum(&args[0], &criteria_pairs(&args[1..]))
+    }
+
+    /// COUNTIFS(criteria_range1, criterion1, ...) counts the cells where
+    /// every criteria range meets its criterion
+    fn evaluate_countifs(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(SpreadsheetError::InvalidArguments(
+                "COUNTIFS expects pairs of ranges and criteria".to_string(),
+            ));
+        }
+        let conditions = criteria_pairs(args);
+        let Some(area) = single_area(&args[0]) else {
+            return Ok(value_error("range", "value"));
+        };
+        Ok(match self.condition_mask(&area, &conditions)? {
+            Ok(included) => {
+                CellValue::Number(included.iter().filter(|include| **include).count() as f64)
+            }
+            Err(error) => error,
+        })
+    }
+
+    /// AVERAGEIF(range, criterion, [average_range]) averages the numbers of
+    /// `average_range`, or of `range` itself, where `range` meets
+    /// `criterion`
+    fn evaluate_averageif(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if !(2..=3).contains(&args.len()) {
+            return Err(SpreadsheetError::InvalidArguments(
+                "AVERAGEIF expects a range, a criterion and an optional average range".to_string(),
+            ));
+        }
+        let average_range = args.get(2).unwrap_or(&args[0]);
+        self.conditional_average(average_range, &[(&args[0], &args[1])])
+    }
+
+    /// AVERAGEIFS(average_range, criteria_range1, criterion1, ...) averages
+    /// the numbers of `average_range` where every criteria range meets its
+    /// criterion
+    fn evaluate_averageifs(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if args.len() < 3 || args.len().is_multiple_of(2) {
+            return Err(SpreadsheetError::InvalidArguments(
+                "AVERAGEIFS expects an average range and pairs of ranges and criteria".to_string(),
+            ));
+        }
+        self.conditional_average(&args[0], &criteria_pairs(&args[1..]))
+    }
+
+    /// Sum of the numbers of `sum_range` whose cells, at the same offset
+    /// in each range, meet every criterion
+    fn conditional_sum(
+        &mut self,
+        sum_range: &Expr,
+        conditions: &[(&Expr, &Expr)],
+    ) -> Result<CellValue> {
+        Ok(match self.conditional_numbers(sum_range, conditions)? {
+            Ok(numbers) => {
+                // Summed from 0 rather than -0, as `Sum` does
+                let mut sum = 0.0;
+                for n in numbers {
+                    sum += n;
+                }
+                CellValue::Number(sum)
+            }
+            Err(error) => error,
+        })
+    }
+
+    /// Mean of the numbers of `average_range` whose cells meet every
+    /// criterion; #DIV/0! when there are none
+    fn conditional_average(
+        &mut self,
+        average_range: &Expr,
+        conditions: &[(&Expr, &Expr)],
+    ) -> Result<CellValue> {
+        match self.conditional_numbers(average_range, conditions)? {
+            Ok(numbers) if numbers.is_empty() => Err(SpreadsheetError::DivideByZero),
+            Ok(numbers) => Ok(CellValue::Number(
+                numbers.iter().sum::<f64>() / numbers.len() as f64,
+            )),
+            Err(error) => Ok(error),
+        }
+    }
+
+    /// Numbers of `range` whose cells, at the same offset in each criteria
+    /// range, meet every criterion; text, booleans and blanks among them
+    /// are skipped and an error is returned as the value
+    fn conditional_numbers(
+        &mut self,
+        range: &Expr,
+        conditions: &[(&Expr, &Expr)],
+    ) -> Result<std::result::Result<Vec<f64>, CellValue>> {
+        let Some(area) = single_area(range) else {
+            return Ok(Err(value_error("range", "value")));
+        };
+        let included = match self.condition_mask(&area, conditions)? {
+            Ok(included) => included,
+            Err(error) => return Ok(Err(error)),
+        };
+        let values = match self.range_values(&area)? {
+            Ok(values) => values,
+            Err(error) => return Ok(Err(error)),
+        };
+        let mut numbers = Vec::new();
+        for (value, _) in values
+            .iter()
+            .zip(&included)
+            .filter(|(_, include)| **include)
+        {
+            match value {
+                CellValue::Number(n) => numbers.push(*n),
+                CellValue::Error(_) => return Ok(Err(value.clone())),
+                _ => {}
+            }
+        }
+        Ok(Ok(numbers))
+    }
+
+    /// Which cells of an area shaped like `shape` meet every criterion, at
+    /// the same offset in each criteria range; see [`Criterion::parse`].
+    /// The ranges must have the same shape; an error in a criteria range
+    /// fails the whole function.
+    fn condition_mask(
+        &mut self,
+        shape: &CellRange,
+        conditions: &[(&Expr, &Expr)],
+    ) -> Result<std::result::Result<Vec<bool>, CellValue>> {
+        let describe =
+            |area: &CellRange| format!("{}x{} range", area.row_count(), area.col_count());
+
+        let mut included = vec![true; shape.size()];
+        for (range, criterion) in conditions {
+            let Some(area) = single_area(range) else {
+                return Ok(Err(value_error("range", "value")));
+            };
+            if (area.row_count(), area.col_count()) != (shape.row_count(), shape.col_count()) {
+                return Ok(Err(value_error(&describe(shape), &describe(&area))));
+            }
+            let criterion = self.evaluate(criterion)?;
+            if criterion.is_error() {
+                return Ok(Err(criterion));
+            }
+            let criterion = Criterion::parse(&criterion);
+            let values = match self.range_values(&area)? {
+                Ok(values) => values,
+                Err(error) => return Ok(Err(error)),
+            };
+            for (include, value) in included.iter_mut().zip(values.iter()) {
+                if value.is_error() {
+                    return Err(SpreadsheetError::ValueError);
+                }
+                *include = *include && criterion.matches(value);
+            }
+        }
+        Ok(Ok(included))
+    }
+
+    /// SPARKLINE(range, [type], [options]) draws the numbers of `range` as
+    /// a line, bar or win/loss chart; see [`crate::sparkline`]
+    fn evaluate_sparkline(&mut self, args: &[Expr]) -> Result<CellValue> {
+        if !(1..=3).contains(&args.len()) {
+            return Err(SpreadsheetError::InvalidArguments(
+                "SPARKLINE expects a range, an optional type and optional options".to_string(),
+            ));
+        }
+        let Some(range) = single_area(&args[0]) else {
+            return Ok(value_error("range", "value"));
+        };
+        let kind = match args.get(1) {
+            Some(arg) => match self.evaluate(arg)? {
+                CellValue::String(name) => match SparklineKind::parse(&name) {
+                    Some(kind) => kind,
+                    None => return Ok(value_error("line, bar or winloss", &name)),
+                },
+                CellValue::Empty => SparklineKind::Line,
+                error @ CellValue::Error(_) => return Ok(error),
+                other => return Ok(value_error("string", other.type_name())),
+            },
+            None => SparklineKind::Line,
+        };
+        let options = match args.get(2) {
+            Some(arg) => match self.evaluate(arg)? {
+                CellValue::String(text) => match SparklineOptions::parse(&text) {
+                    Ok(options) => options,
+                    Err(message) => return Ok(value_error("key=value options", &message)),
+                },
+                CellValue::Empty => SparklineOptions::default(),
+                error @ CellValue::Error(_) => return Ok(error),
+                other => return Ok(value_error("string", other.type_name())),
+            },
+            None => SparklineOptions::default(),
+        };
+
+        Ok(match self.range_values(&range)? {
+            Ok(values) => {
+                CellValue::Sparkline(std::sync::Arc::new(Sparkline::new(kind, &values, options)))
+            }
+            Err(error) => error,
+        })
+    }
+
+    /// Offset of the first cell of the one-row or one-column `range` equal
+    /// to `key`, from the context's index when it has one. `Err` holds a
+    /// circular reference met while scanning.
+    fn exact_position(
+        &mut self,
+        range: &CellRange,
+        key: &CellValue,
+    ) -> Result<std::result::Result<Option<usize>, CellValue>> {
+        let Some(key) = LookupKey::of(key) else {
+            return Ok(Ok(None));
+        };
+        if range.start.col == range.end.col
+            && let Some(row) = self.context.indexed_first_match(range, &key)
+        {
+            self.spend(1)?;
+            return Ok(Ok(row.map(|row| (row - range.start.row) as usize)));
+        }
+        Ok(self.range_values(range)?.map(|values| {
+            values
+                .iter()
+                .position(|value| lookup::matches_exactly(value, &key))
+        }))
+    }
+
+    /// Offset of the approximate match of `key` in the sorted `range`; see
+    /// [`lookup::approximate_position`]
+    fn approximate_position(
+        &mut self,
+        range: &CellRange,
+        key: &CellValue,
+        descending: bool,
+    ) -> Result<std::result::Result<Option<usize>, CellValue>> {
+        Ok(self
+            .range_values(range)?
+            .map(|values| lookup::approximate_position(&values, key, descending)))
+    }
+
+    /// Values of `range` in row order, or the circular reference error met
+    /// reading them
+    fn range_values(
+        &mut self,
+        range: &CellRange,
+    ) -> Result<std::result::Result<std::sync::Arc<Vec<CellValue>>, CellValue>> {
+        Ok(match self.area_values(std::slice::from_ref(range))? {
+            CellValue::Array(values) => Ok(values),
+            error => Err(error),
+        })
+    }
+
     /// Evaluate a range of cells and return as array
     pub fn evaluate_range(&mut self, range: &CellRange) -> Result<Vec<CellValue>> {
+        self.spend(range.size())?;
         let cells: Vec<_> = range.cells().collect();
         let mut values = CELL_VALUE_VEC_POOL.get();
         values.reserve(cells.len());
@@ -160,12 +1050,95 @@ impl<'a> Evaluator<'a> {
     }
 }
 
+/// Functions [`Evaluator::evaluate_function`] hands their range arguments
+/// unevaluated
+const READS_OWN_RANGES: [&str; 11] = [
+    "VLOOKUP",
+    "HLOOKUP",
+    "MATCH",
+    "INDEX",
+    "COUNTIF",
+    "SUMIF",
+    "SUMIFS",
+    "COUNTIFS",
+    "AVERAGEIF",
+    "AVERAGEIFS",
+    SPARKLINE_FUNCTION,
+];
+
+/// Most values an array function may produce
+const MAX_ARRAY_CELLS: usize = 1 << 20;
+
+/// Functions whose result is an array, see [`Evaluator::evaluate_array`]
+fn is_array_function(name: &str) -> bool {
+    name.eq_ignore_ascii_case("SEQUENCE") || name.eq_ignore_ascii_case("TRANSPOSE")
+}
+
+/// Whether an operator in `expr` works on a range or an array, so that
+/// [`Evaluator::evaluate_array`] gives an array for it
+fn operates_on_ranges(expr: &Expr) -> bool {
+    match expr {
+        Expr::Range { .. } | Expr::Union { .. } | Expr::Name { .. } => true,
+        Expr::SheetReference { reference, .. } => operates_on_ranges(reference),
+        Expr::FunctionCall { name, .. } => is_array_function(name),
+        Expr::UnaryOp { expr, .. } => operates_on_ranges(expr),
+        Expr::BinaryOp { left, right, .. } => operates_on_ranges(left) || operates_on_ranges(right),
+        _ => false,
+    }
+}
+
+/// One value of an array computed element by element, where a failure
+/// stands in as its error rather than failing the whole array
+fn element(result: Result<CellValue>) -> CellValue {
+    result.unwrap_or_else(|error| CellValue::from_error(error.to_error_type()))
+}
+
+/// Criteria ranges paired with their criteria, from arguments written as
+/// `range1, criterion1, range2, criterion2, ...`
+fn criteria_pairs(args: &[Expr]) -> Vec<(&Expr, &Expr)> {
+    args.chunks(2).map(|pair| (&pair[0], &pair[1])).collect()
+}
+
+/// The single area `expr` refers to
+fn single_area(expr: &Expr) -> Option<CellRange> {
+    match expr.areas()?.as_slice() {
+        [area] => Some(*area),
+        _ => None,
+    }
+}
+
+/// The range a named range stands for, as if written out absolute
+fn named_range_expr(range: CellRange) -> Expr {
+    Expr::Range {
+        range,
+        absolute_start_col: true,
+        absolute_start_row: true,
+        absolute_end_col: true,
+        absolute_end_row: true,
+    }
+}
+
+/// The `#REF!` of a reference to a sheet the workbook does not have
+fn missing_sheet(sheet: &str) -> CellValue {
+    CellValue::from_error(ErrorType::InvalidRef {
+        reference: sheet.to_string(),
+    })
+}
+
+fn value_error(expected: &str, actual: &str) -> CellValue {
+    CellValue::from_error(ErrorType::ValueError {
+        expected: expected.to_string(),
+        actual: actual.to_string(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::context::BasicContext;
     use super::*;
     use crate::formula::FormulaParser;
     use crate::types::{CellAddress, ErrorType};
+    use std::collections::HashMap;
 
     #[test]
     fn test_evaluate_literal() {
@@ -232,47 +1205,45 @@ mod tests {
         assert_eq!(result, CellValue::from_error(ErrorType::DivideByZero));
     }
 
-    #[test]
-    fn test_type_mismatch_in_arithmetic() {
-        // Create a test context with values
-        use std::collections::HashMap;
-
-        struct TestContext {
-            values: HashMap<CellAddress, CellValue>,
-            evaluation_stack: std::collections::HashSet<CellAddress>,
-        }
+    /// Context reading cell values from a map
+    struct TestContext {
+        values: HashMap<CellAddress, CellValue>,
+        evaluation_stack: std::collections::HashSet<CellAddress>,
+    }
 
-        impl TestContext {
-            fn new() -> Self {
-                TestContext {
-                    values: HashMap::new(),
-                    evaluation_stack: std::collections::HashSet::new(),
-                }
+    impl TestContext {
+        fn new() -> Self {
+            TestContext {
+                values: HashMap::new(),
+                evaluation_stack: std::collections::HashSet::new(),
             }
         }
+    }
 
-        impl crate::evaluator::context::EvaluationContext for TestContext {
-            fn get_cell_value(&self, address: &CellAddress) -> crate::Result<CellValue> {
-                Ok(self
-                    .values
-                    .get(address)
-                    .cloned()
-                    .unwrap_or(CellValue::Empty))
-            }
+    impl crate::evaluator::context::EvaluationContext for TestContext {
+        fn get_cell_value(&self, address: &CellAddress) -> crate::Result<CellValue> {
+            Ok(self
+                .values
+                .get(address)
+                .cloned()
+                .unwrap_or(CellValue::Empty))
+        }
 
-            fn is_evaluating(&self, address: &CellAddress) -> bool {
-                self.evaluation_stack.contains(address)
-            }
+        fn is_evaluating(&self, address: &CellAddress) -> bool {
+            self.evaluation_stack.contains(address)
+        }
 
-            fn push_evaluation(&mut self, address: &CellAddress) {
-                self.evaluation_stack.insert(*address);
-            }
+        fn push_evaluation(&mut self, address: &CellAddress) {
+            self.evaluation_stack.insert(*address);
+        }
 
-            fn pop_evaluation(&mut self, address: &CellAddress) {
-                self.evaluation_stack.remove(address);
-            }
+        fn pop_evaluation(&mut self, address: &CellAddress) {
+            self.evaluation_stack.remove(address);
         }
+    }
 
+    #[test]
+    fn test_type_mismatch_in_arithmetic() {
         let mut context = TestContext::new();
         context.values.insert(
             CellAddress::new(0, 0),
@@ -291,6 +1262,128 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_text_functions_chain() {
+        let mut context = TestContext::new();
+        context.values.insert(
+            CellAddress::new(0, 0),
+            CellValue::string_from_str("x  héllo wörld"),
+        );
+        context
+            .values
+            .insert(CellAddress::new(0, 1), CellValue::Number(12345.5));
+
+        let mut evaluator = Evaluator::new(&mut context);
+        let mut evaluate = |formula| evaluator.evaluate(&FormulaParser::parse(formula).unwrap());
+        let text = |s: &str| CellValue::string_from_str(s);
+
+        assert_eq!(evaluate("UPPER(TRIM(MID(A1,2,5)))").unwrap(), text("HÉL"));
+        assert_eq!(
+            evaluate("CONCAT(LEFT(A2,3),\"-\",RIGHT(A2,3),\"/\",LEN(A1))").unwrap(),
+            text("123-5.5/14")
+        );
+        assert_eq!(
+            evaluate("SUBSTITUTE(LOWER(A1),\"l\",\"L\",3)").unwrap(),
+            text("x  héllo wörLd")
+        );
+    }
+
+    #[test]
+    fn test_index_of_match_reads_a_repository() {
+        use crate::domain::Cell;
+        use crate::evaluator::context::RepositoryContext;
+        use crate::repository::CellRepository;
+        use std::sync::{Arc, Mutex};
+
+        let mut repository = CellRepository::new();
+        for (row, (key, value)) in [("apple", 3.0), ("Pear", 5.0), ("plum", 8.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let row = row as u32;
+            repository.set(
+                &CellAddress::new(0, row),
+                Cell::new(CellValue::string_from_str(key)),
+            );
+            repository.set(
+                &CellAddress::new(1, row),
+                Cell::new(CellValue::Number(value)),
+            );
+            // Descending
+            repository.set(
+                &CellAddress::new(2, row),
+                Cell::new(CellValue::Number(10.0 - value)),
+            );
+        }
+        let repository = Arc::new(Mutex::new(repository));
+        let mut context = RepositoryContext::new(&repository);
+        let mut evaluator = Evaluator::new(&mut context);
+        let mut evaluate = |formula| {
+            evaluator
+                .evaluate(&FormulaParser::parse(formula).unwrap())
+                .unwrap()
+        };
+        let is_error = |value: CellValue, expected: fn(&ErrorType) -> bool| match value {
+            CellValue::Error(error) => expected(&error),
+            _ => false,
+        };
+
+        assert_eq!(
+            evaluate("INDEX(B1:B10, MATCH(\"PEAR\", A1:A10, 0))"),
+            CellValue::Number(5.0)
+        );
+        assert_eq!(
+            evaluate("INDEX(A1:B3, MATCH(6, B1:B3), 2)"),
+            CellValue::Number(5.0)
+        );
+        assert_eq!(
+            evaluate("INDEX(A1:B3, 3, MATCH(8, A3:B3, 0))"),
+            CellValue::Number(8.0)
+        );
+        assert_eq!(evaluate("MATCH(4, C1:C3, -1)"), CellValue::Number(2.0));
+        assert_eq!(
+            evaluate("MATCH(\"apple\", A1:C1, 0)"),
+            CellValue::Number(1.0)
+        );
+        assert_eq!(evaluate("INDEX(A1:B1, 2)"), CellValue::Number(3.0));
+
+        assert!(is_error(evaluate("INDEX(A1:A10, 0)"), |e| matches!(
+            e,
+            ErrorType::ValueError { .. }
+        )));
+        assert!(is_error(evaluate("INDEX(A1:B3, 4, 1)"), |e| matches!(
+            e,
+            ErrorType::InvalidRef { .. }
+        )));
+        assert!(is_error(evaluate("MATCH(1, D1:D10, 0)"), |e| matches!(
+            e,
+            ErrorType::NotAvailable
+        )));
+        assert!(is_error(evaluate("MATCH(1, D1:D10)"), |e| matches!(
+            e,
+            ErrorType::NotAvailable
+        )));
+        assert!(is_error(
+            evaluate("INDEX(B1:B3, MATCH(\"fig\", A1:A3, 0))"),
+            |e| matches!(e, ErrorType::NotAvailable)
+        ));
+    }
+
+    #[test]
+    fn test_budget_stops_evaluation() {
+        let mut context = BasicContext::new();
+        let expr = FormulaParser::parse("SUM(A1:Z1000)+1").unwrap();
+
+        let mut evaluator = Evaluator::new(&mut context).with_budget(1_000);
+        assert!(matches!(
+            evaluator.evaluate(&expr),
+            Err(SpreadsheetError::BudgetExceeded)
+        ));
+
+        let mut evaluator = Evaluator::new(&mut context).with_budget(30_000);
+        assert_eq!(evaluator.evaluate(&expr).unwrap(), CellValue::Number(1.0));
+    }
+
     // Removing duplicated tests that are causing compilation errors
     // These tests need to be fixed with proper context setup
 }
@@ -1,10 +1,24 @@
+use super::deterministic::DeterministicSource;
 use super::operators::{coerce_to_boolean, coerce_to_number, coerce_to_string};
+use super::{dates, quantity, random};
 use crate::types::CellValue;
 use crate::types::ErrorType;
 use crate::{Result, SpreadsheetError};
+use chrono::{Datelike, NaiveDateTime};
 use std::collections::HashMap;
 
 type FunctionImpl = Box<dyn Fn(&[CellValue]) -> Result<CellValue>>;
+type Predicate = fn(&CellValue) -> bool;
+
+/// The first or last characters of a text, e.g. for LEFT
+type TextEnd = fn(&str, usize) -> String;
+
+/// One part of a date, e.g. its year
+type DatePart = fn(chrono::NaiveDate) -> f64;
+
+/// Functions whose value can change on its own, see
+/// [`FunctionLibrary::is_volatile`]
+const VOLATILE_FUNCTIONS: [&str; 4] = ["RAND", "RANDBETWEEN", "NOW", "TODAY"];
 
 /// Library of spreadsheet functions
 pub struct FunctionLibrary {
@@ -28,6 +42,7 @@ impl FunctionLibrary {
         lib.register_math_functions();
         lib.register_text_functions();
         lib.register_logical_functions();
+        lib.register_date_functions();
 
         lib
     }
@@ -43,6 +58,31 @@ impl FunctionLibrary {
         }
     }
 
+    /// Whether a function's value can change without any of its inputs
+    /// changing, so formulas calling it are recalculated on every edit
+    pub fn is_volatile(name: &str) -> bool {
+        VOLATILE_FUNCTIONS
+            .iter()
+            .any(|volatile| volatile.eq_ignore_ascii_case(name))
+    }
+
+    /// Call a function, taking the randomness and time of volatile ones
+    /// from `source` rather than from entropy and the clock
+    pub fn call_deterministic(
+        &self,
+        name: &str,
+        args: &[CellValue],
+        source: &DeterministicSource,
+    ) -> Result<CellValue> {
+        match name.to_uppercase().as_str() {
+            "RAND" => rand(args, || source.next_f64()),
+            "RANDBETWEEN" => rand_between(args, |bottom, top| source.between(bottom, top)),
+            "TODAY" => today(args, source.now()),
+            "NOW" => now(args, source.now()),
+            _ => self.call(name, args),
+        }
+    }
+
     /// Register a function
     fn register(&mut self, name: &str, func: FunctionImpl) {
         self.functions.insert(name.to_uppercase(), func);
@@ -55,15 +95,10 @@ impl FunctionLibrary {
             "SUM",
             Box::new(|args| {
                 let mut sum = 0.0;
-                let mut has_error = false;
-                let mut error_value = None;
-
                 for arg in args {
                     // Check for errors first and propagate them
-                    if let CellValue::Error(e) = arg.clone() {
-                        has_error = true;
-                        error_value = Some(e);
-                        break;
+                    if let Some(error) = error_in(arg) {
+                        return Ok(error);
                     }
 
                     // Try to extract numbers, catching any errors
@@ -101,14 +136,7 @@ impl FunctionLibrary {
                     }
                 }
 
-                if has_error {
-                    // Safe to unwrap because has_error is only true when error_value is Some
-                    Ok(CellValue::Error(error_value.expect(
-                        "error_value should be set when has_error is true",
-                    )))
-                } else {
-                    Ok(CellValue::Number(sum))
-                }
+                Ok(CellValue::Number(sum))
             }),
         );
 
@@ -119,8 +147,8 @@ impl FunctionLibrary {
                 let mut all_numbers = Vec::new();
                 for arg in args {
                     // Check for errors first and propagate them
-                    if let CellValue::Error(e) = arg.clone() {
-                        return Ok(CellValue::Error(e));
+                    if let Some(error) = error_in(arg) {
+                        return Ok(error);
                     }
 
                     // Try to extract numbers, catching any errors
@@ -176,8 +204,8 @@ impl FunctionLibrary {
                 let mut all_numbers = Vec::new();
                 for arg in args {
                     // Check for errors first and propagate them
-                    if let CellValue::Error(e) = arg.clone() {
-                        return Ok(CellValue::Error(e));
+                    if let Some(error) = error_in(arg) {
+                        return Ok(error);
                     }
 
                     // Try to extract numbers, catching any errors
@@ -234,8 +262,8 @@ impl FunctionLibrary {
                 let mut all_numbers = Vec::new();
                 for arg in args {
                     // Check for errors first and propagate them
-                    if let CellValue::Error(e) = arg.clone() {
-                        return Ok(CellValue::Error(e));
+                    if let Some(error) = error_in(arg) {
+                        return Ok(error);
                     }
 
                     // Try to extract numbers, catching any errors
@@ -291,43 +319,24 @@ impl FunctionLibrary {
             Box::new(|args| {
                 let mut count = 0;
                 for arg in args {
-                    // Check for errors first and propagate them
-                    if let CellValue::Error(e) = arg.clone() {
-                        return Ok(CellValue::Error(e));
-                    }
-
-                    // Try to extract numbers, catching any errors
-                    match extract_numbers(arg) {
-                        Ok(numbers) => {
-                            count += numbers.len();
-                        }
-                        Err(e) => {
-                            // Convert error to Excel format and return as CellValue::Error
-                            return match e {
-                                SpreadsheetError::DivisionByZero
-                                | SpreadsheetError::DivideByZero => {
-                                    Ok(CellValue::from_error(ErrorType::DivideByZero))
-                                }
-                                SpreadsheetError::ValueError | SpreadsheetError::TypeError(_) => {
-                                    Ok(CellValue::from_error(ErrorType::ValueError {
-                                        expected: "numeric".to_string(),
-                                        actual: "non-numeric".to_string(),
-                                    }))
+                    match arg {
+                        CellValue::Error(e) => return Ok(CellValue::Error(e.clone())),
+                        // Ranges and referenced cells count numbers only
+                        CellValue::Array(values) => {
+                            for value in values.iter() {
+                                match value {
+                                    CellValue::Error(e) => return Ok(CellValue::Error(e.clone())),
+                                    CellValue::Number(_) => count += 1,
+                                    _ => {}
                                 }
-                                SpreadsheetError::NumError => {
-                                    Ok(CellValue::from_error(ErrorType::NumError))
-                                }
-                                SpreadsheetError::FormulaError(err_str) => {
-                                    Ok(CellValue::from_error(ErrorType::ParseError {
-                                        message: err_str,
-                                    }))
-                                }
-                                _ => Ok(CellValue::from_error(ErrorType::ValueError {
-                                    expected: "numeric".to_string(),
-                                    actual: "non-numeric".to_string(),
-                                })),
-                            };
+                            }
                         }
+                        CellValue::Empty => {}
+                        // Empty text reads as 0 in arithmetic but is no number
+                        CellValue::String(text) if text.is_empty() => {}
+                        // Typed arguments count when they read as a number,
+                        // which includes TRUE, FALSE and "3"
+                        value => count += usize::from(coerce_to_number(value).is_ok()),
                     }
                 }
 
@@ -335,6 +344,26 @@ impl FunctionLibrary {
             }),
         );
 
+        // COUNTA function: counts every value but blanks, so empty text
+        // like the result of ="" and errors count
+        self.register(
+            "COUNTA",
+            Box::new(|args| {
+                let count: usize = args
+                    .iter()
+                    .map(|arg| match arg {
+                        CellValue::Array(values) => values
+                            .iter()
+                            .filter(|value| !matches!(value, CellValue::Empty))
+                            .count(),
+                        CellValue::Empty => 0,
+                        _ => 1,
+                    })
+                    .sum();
+                Ok(CellValue::Number(count as f64))
+            }),
+        );
+
         // ROUND function
         self.register(
             "ROUND",
@@ -392,9 +421,42 @@ impl FunctionLibrary {
                 Ok(CellValue::Number(number.sqrt()))
             }),
         );
+
+        // CONVERT function; unknown or incompatible units give #VALUE!
+        self.register(
+            "CONVERT",
+            Box::new(|args| {
+                if args.len() != 3 {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "CONVERT requires exactly 3 arguments".to_string(),
+                    ));
+                }
+
+                if let CellValue::Error(e) = args[0].clone() {
+                    return Ok(CellValue::Error(e));
+                }
+
+                let number = coerce_to_number(&args[0])?;
+                let from = coerce_to_string(&args[1]);
+                let to = coerce_to_string(&args[2]);
+                quantity::convert(number, &from, &to)
+                    .map(CellValue::Number)
+                    .ok_or(SpreadsheetError::ValueError)
+            }),
+        );
+
+        // RAND and RANDBETWEEN functions
+        self.register("RAND", Box::new(|args| rand(args, random::next_f64)));
+        self.register(
+            "RANDBETWEEN",
+            Box::new(|args| rand_between(args, random::between)),
+        );
     }
 
-    /// Register text functions
+    /// Register text functions. Numbers read as the grid shows them
+    /// without a format, and positions and lengths count characters, i.e.
+    /// Unicode scalar values: `héllo` is 5 long and `👍` is 1, while an
+    /// emoji joined from several, like a family, counts each of them.
     fn register_text_functions(&mut self) {
         // CONCATENATE function
         self.register(
@@ -408,6 +470,33 @@ impl FunctionLibrary {
             }),
         );
 
+        // CONCAT function; ranges join their cells in order
+        self.register(
+            "CONCAT",
+            Box::new(|args| {
+                if args.is_empty() {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "CONCAT requires at least 1 argument".to_string(),
+                    ));
+                }
+
+                let mut result = String::new();
+                for arg in args {
+                    let values = match arg {
+                        CellValue::Array(values) => values.as_slice(),
+                        value => std::slice::from_ref(value),
+                    };
+                    if let Some(error) = first_error(values) {
+                        return Ok(error);
+                    }
+                    for value in values {
+                        result.push_str(&coerce_to_string(value));
+                    }
+                }
+                Ok(CellValue::from_string(result))
+            }),
+        );
+
         // LEN function
         self.register(
             "LEN",
@@ -418,8 +507,70 @@ impl FunctionLibrary {
                     ));
                 }
 
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
                 let text = coerce_to_string(&args[0]);
-                Ok(CellValue::Number(text.len() as f64))
+                Ok(CellValue::Number(text.chars().count() as f64))
+            }),
+        );
+
+        // LEFT and RIGHT functions; the count defaults to 1
+        let ends: [(&str, TextEnd); 2] = [
+            ("LEFT", |text, count| text.chars().take(count).collect()),
+            ("RIGHT", |text, count| {
+                let skip = text.chars().count().saturating_sub(count);
+                text.chars().skip(skip).collect()
+            }),
+        ];
+        for (name, end) in ends {
+            self.register(
+                name,
+                Box::new(move |args| {
+                    if !(1..=2).contains(&args.len()) {
+                        return Err(SpreadsheetError::InvalidArguments(format!(
+                            "{} requires 1 or 2 arguments",
+                            name
+                        )));
+                    }
+
+                    if let Some(error) = first_error(args) {
+                        return Ok(error);
+                    }
+
+                    let text = coerce_to_string(&args[0]);
+                    let count = match args.get(1) {
+                        Some(count) => char_count(count)?,
+                        None => 1,
+                    };
+                    Ok(CellValue::from_string(end(&text, count)))
+                }),
+            );
+        }
+
+        // MID function; a start past the end gives empty text
+        self.register(
+            "MID",
+            Box::new(|args| {
+                if args.len() != 3 {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "MID requires exactly 3 arguments".to_string(),
+                    ));
+                }
+
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
+                let text = coerce_to_string(&args[0]);
+                let start = coerce_to_number(&args[1])?.trunc();
+                if start < 1.0 {
+                    return Err(SpreadsheetError::ValueError);
+                }
+                let count = char_count(&args[2])?;
+                let mid = text.chars().skip(start as usize - 1).take(count);
+                Ok(CellValue::from_string(mid.collect()))
             }),
         );
 
@@ -433,6 +584,10 @@ impl FunctionLibrary {
                     ));
                 }
 
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
                 let text = coerce_to_string(&args[0]);
                 Ok(CellValue::from_string(text.to_uppercase()))
             }),
@@ -448,12 +603,17 @@ impl FunctionLibrary {
                     ));
                 }
 
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
                 let text = coerce_to_string(&args[0]);
                 Ok(CellValue::from_string(text.to_lowercase()))
             }),
         );
 
-        // TRIM function
+        // TRIM function; spaces at either end go and runs of them inside
+        // shrink to one
         self.register(
             "TRIM",
             Box::new(|args| {
@@ -463,8 +623,51 @@ impl FunctionLibrary {
                     ));
                 }
 
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
                 let text = coerce_to_string(&args[0]);
-                Ok(CellValue::from_string(text.trim().to_string()))
+                let words: Vec<&str> = text.split(' ').filter(|word| !word.is_empty()).collect();
+                Ok(CellValue::from_string(words.join(" ")))
+            }),
+        );
+
+        // SUBSTITUTE function; with an instance number only that
+        // occurrence is replaced
+        self.register(
+            "SUBSTITUTE",
+            Box::new(|args| {
+                if !(3..=4).contains(&args.len()) {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "SUBSTITUTE requires 3 or 4 arguments".to_string(),
+                    ));
+                }
+
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
+                let text = coerce_to_string(&args[0]);
+                let old = coerce_to_string(&args[1]);
+                let new = coerce_to_string(&args[2]);
+                if old.is_empty() {
+                    return Ok(CellValue::from_string(text));
+                }
+                let Some(instance) = args.get(3) else {
+                    return Ok(CellValue::from_string(text.replace(&old, &new)));
+                };
+                let instance = coerce_to_number(instance)?.trunc();
+                if instance < 1.0 {
+                    return Err(SpreadsheetError::ValueError);
+                }
+                let result = match text.match_indices(&old).nth(instance as usize - 1) {
+                    Some((at, _)) => {
+                        format!("{}{}{}", &text[..at], new, &text[at + old.len()..])
+                    }
+                    None => text,
+                };
+                Ok(CellValue::from_string(result))
             }),
         );
     }
@@ -542,10 +745,266 @@ impl FunctionLibrary {
                 Ok(CellValue::Boolean(!value))
             }),
         );
+
+        // ISBLANK function: only a cell holding nothing is blank, not one
+        // holding empty text like the result of =""
+        self.register(
+            "ISBLANK",
+            Box::new(|args| {
+                if args.len() != 1 {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "ISBLANK requires exactly 1 argument".to_string(),
+                    ));
+                }
+
+                Ok(CellValue::Boolean(matches!(args[0], CellValue::Empty)))
+            }),
+        );
+
+        // NA function: #N/A, to mark a value as missing
+        self.register(
+            "NA",
+            Box::new(|args| {
+                if !args.is_empty() {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "NA takes no arguments".to_string(),
+                    ));
+                }
+
+                Ok(CellValue::from_error(ErrorType::NotAvailable))
+            }),
+        );
+
+        // Information functions; their argument is evaluated with its
+        // errors as values, see [`inspects_errors`]
+        let predicates: [(&str, Predicate); 5] = [
+            ("ISERROR", CellValue::is_error),
+            (
+                "ISNA",
+                |value| matches!(value, CellValue::Error(error) if **error == ErrorType::NotAvailable),
+            ),
+            ("ISNUMBER", |value| matches!(value, CellValue::Number(_))),
+            ("ISTEXT", |value| matches!(value, CellValue::String(_))),
+            ("ISLOGICAL", |value| matches!(value, CellValue::Boolean(_))),
+        ];
+        for (name, predicate) in predicates {
+            self.register(
+                name,
+                Box::new(move |args| {
+                    if args.len() != 1 {
+                        return Err(SpreadsheetError::InvalidArguments(format!(
+                            "{} requires exactly 1 argument",
+                            name
+                        )));
+                    }
+
+                    Ok(CellValue::Boolean(predicate(&args[0])))
+                }),
+            );
+        }
+    }
+
+    /// Register date functions; dates are serial numbers, see [`dates`]
+    fn register_date_functions(&mut self) {
+        // DATE function
+        self.register(
+            "DATE",
+            Box::new(|args| {
+                if args.len() != 3 {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "DATE requires exactly 3 arguments".to_string(),
+                    ));
+                }
+
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
+                let date = dates::date_of(
+                    coerce_to_number(&args[0])?,
+                    coerce_to_number(&args[1])?,
+                    coerce_to_number(&args[2])?,
+                )?;
+                Ok(CellValue::Number(dates::date_serial(date)))
+            }),
+        );
+
+        // TODAY and NOW functions
+        self.register(
+            "TODAY",
+            Box::new(|args| today(args, chrono::Local::now().naive_local())),
+        );
+        self.register(
+            "NOW",
+            Box::new(|args| now(args, chrono::Local::now().naive_local())),
+        );
+
+        // YEAR, MONTH and DAY functions
+        let parts: [(&str, DatePart); 3] = [
+            ("YEAR", |date| f64::from(date.year())),
+            ("MONTH", |date| f64::from(date.month())),
+            ("DAY", |date| f64::from(date.day())),
+        ];
+        for (name, part) in parts {
+            self.register(
+                name,
+                Box::new(move |args| {
+                    if args.len() != 1 {
+                        return Err(SpreadsheetError::InvalidArguments(format!(
+                            "{} requires exactly 1 argument",
+                            name
+                        )));
+                    }
+
+                    if let CellValue::Error(e) = args[0].clone() {
+                        return Ok(CellValue::Error(e));
+                    }
+
+                    let date = dates::date_arg(&args[0])?;
+                    Ok(CellValue::Number(part(date)))
+                }),
+            );
+        }
+
+        // EOMONTH function
+        self.register(
+            "EOMONTH",
+            Box::new(|args| {
+                if args.len() != 2 {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "EOMONTH requires exactly 2 arguments".to_string(),
+                    ));
+                }
+
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
+                let start = dates::date_arg(&args[0])?;
+                let months = coerce_to_number(&args[1])?;
+                let end = dates::end_of_month(start, months)?;
+                Ok(CellValue::Number(dates::date_serial(end)))
+            }),
+        );
+
+        // DATEDIF function
+        self.register(
+            "DATEDIF",
+            Box::new(|args| {
+                if args.len() != 3 {
+                    return Err(SpreadsheetError::InvalidArguments(
+                        "DATEDIF requires exactly 3 arguments".to_string(),
+                    ));
+                }
+
+                if let Some(error) = first_error(args) {
+                    return Ok(error);
+                }
+
+                let start = dates::date_arg(&args[0])?;
+                let end = dates::date_arg(&args[1])?;
+                let unit = coerce_to_string(&args[2]);
+                Ok(CellValue::Number(dates::date_difference(
+                    start, end, &unit,
+                )?))
+            }),
+        );
+    }
+}
+
+/// RAND() with its number from `draw`
+fn rand(args: &[CellValue], draw: impl FnOnce() -> f64) -> Result<CellValue> {
+    if !args.is_empty() {
+        return Err(SpreadsheetError::InvalidArguments(
+            "RAND takes no arguments".to_string(),
+        ));
+    }
+    Ok(CellValue::Number(draw()))
+}
+
+/// RANDBETWEEN(bottom, top) with its number from `draw`
+fn rand_between(args: &[CellValue], draw: impl FnOnce(i64, i64) -> i64) -> Result<CellValue> {
+    if args.len() != 2 {
+        return Err(SpreadsheetError::InvalidArguments(
+            "RANDBETWEEN requires exactly 2 arguments".to_string(),
+        ));
+    }
+    if let Some(error) = first_error(args) {
+        return Ok(error);
+    }
+
+    let bottom = coerce_to_number(&args[0])?.ceil();
+    let top = coerce_to_number(&args[1])?.floor();
+    if bottom > top {
+        return Err(SpreadsheetError::NumError);
+    }
+    Ok(CellValue::Number(draw(bottom as i64, top as i64) as f64))
+}
+
+/// TODAY() on the day of `now`
+fn today(args: &[CellValue], now: NaiveDateTime) -> Result<CellValue> {
+    if !args.is_empty() {
+        return Err(SpreadsheetError::InvalidArguments(
+            "TODAY takes no arguments".to_string(),
+        ));
+    }
+    Ok(CellValue::Number(dates::date_serial(now.date())))
+}
+
+/// NOW() at `now`
+fn now(args: &[CellValue], now: NaiveDateTime) -> Result<CellValue> {
+    if !args.is_empty() {
+        return Err(SpreadsheetError::InvalidArguments(
+            "NOW takes no arguments".to_string(),
+        ));
     }
+    Ok(CellValue::Number(dates::datetime_serial(now)))
 }
 
-/// Extract numbers from a cell value (including arrays)
+/// A number of characters to take; negative counts give #VALUE!
+fn char_count(value: &CellValue) -> Result<usize> {
+    let count = coerce_to_number(value)?.trunc();
+    if count < 0.0 {
+        return Err(SpreadsheetError::ValueError);
+    }
+    Ok(count as usize)
+}
+
+/// First error among a function's arguments, which it returns as its value
+fn first_error(args: &[CellValue]) -> Option<CellValue> {
+    args.iter().find(|arg| arg.is_error()).cloned()
+}
+
+/// `value` when it is an error, or the first error among its elements, so
+/// that an aggregate over a range passes on the error as it is
+fn error_in(value: &CellValue) -> Option<CellValue> {
+    match value {
+        CellValue::Array(values) => first_error(values),
+        value => value.is_error().then(|| value.clone()),
+    }
+}
+
+/// Whether `name` looks at its argument's error instead of failing with it
+pub fn inspects_errors(name: &str) -> bool {
+    matches!(
+        name.to_uppercase().as_str(),
+        "ISBLANK" | "ISERROR" | "ISNA" | "ISNUMBER" | "ISTEXT" | "ISLOGICAL"
+    )
+}
+
+/// Whether `name` reads a single cell reference like a one-cell range, so
+/// that the cell is skipped unless it holds a number
+pub fn reads_references_as_ranges(name: &str) -> bool {
+    matches!(
+        name.to_uppercase().as_str(),
+        "SUM" | "AVERAGE" | "MIN" | "MAX" | "COUNT"
+    )
+}
+
+/// Numbers an aggregate reads from one argument, by the usual spreadsheet
+/// rules: ranges and referenced cells contribute only their numbers, so
+/// TRUE, FALSE, text and blanks in them are skipped, while a typed argument
+/// is coerced, so TRUE counts as 1 and "3" as 3
 fn extract_numbers(value: &CellValue) -> Result<Vec<f64>> {
     match value {
         CellValue::Number(n) => Ok(vec![*n]),
@@ -557,12 +1016,9 @@ fn extract_numbers(value: &CellValue) -> Result<Vec<f64>> {
                     CellValue::Error(e) => {
                         return Err(SpreadsheetError::FormulaError(e.to_string()));
                     }
-                    _ => {
-                        if let Ok(n) = coerce_to_number(val) {
-                            numbers.push(n);
-                        }
-                        // Skip non-numeric values
-                    }
+                    CellValue::Number(n) => numbers.push(*n),
+                    // Skip text, booleans and blanks
+                    _ => {}
                 }
             }
             Ok(numbers)
@@ -574,3 +1030,145 @@ fn extract_numbers(value: &CellValue) -> Result<Vec<f64>> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: &[CellValue]) -> Result<CellValue> {
+        FunctionLibrary::new().call(name, args)
+    }
+
+    fn text(s: &str) -> CellValue {
+        CellValue::string_from_str(s)
+    }
+
+    #[test]
+    fn test_concat_joins_ranges_and_numbers() {
+        let range =
+            CellValue::from_array(vec![text("a"), CellValue::Empty, CellValue::Number(1.5)]);
+        assert_eq!(
+            call(
+                "CONCAT",
+                &[range, CellValue::Boolean(true), CellValue::Number(2.0)]
+            )
+            .unwrap(),
+            text("a1.5TRUE2")
+        );
+        let error = CellValue::from_error(ErrorType::DivideByZero);
+        let range = CellValue::from_array(vec![text("a"), error.clone()]);
+        assert_eq!(call("CONCAT", &[range]).unwrap(), error);
+        assert!(call("CONCAT", &[]).is_err());
+    }
+
+    #[test]
+    fn test_random_functions() {
+        let n = CellValue::Number;
+        for _ in 0..100 {
+            let Ok(CellValue::Number(value)) = call("RAND", &[]) else {
+                panic!("RAND gives a number");
+            };
+            assert!((0.0..1.0).contains(&value));
+            let Ok(CellValue::Number(value)) = call("RANDBETWEEN", &[n(-2.5), n(2.5)]) else {
+                panic!("RANDBETWEEN gives a number");
+            };
+            assert!([-2.0, -1.0, 0.0, 1.0, 2.0].contains(&value));
+        }
+        assert_eq!(call("RANDBETWEEN", &[n(4.0), n(4.0)]).unwrap(), n(4.0));
+        assert!(call("RANDBETWEEN", &[n(5.0), n(4.0)]).is_err());
+        assert!(call("RAND", &[n(1.0)]).is_err());
+
+        assert!(FunctionLibrary::is_volatile("rand"));
+        assert!(FunctionLibrary::is_volatile("NOW"));
+        assert!(!FunctionLibrary::is_volatile("SUM"));
+    }
+
+    #[test]
+    fn test_len_counts_characters() {
+        assert_eq!(
+            call("LEN", &[text("héllo")]).unwrap(),
+            CellValue::Number(5.0)
+        );
+        assert_eq!(call("LEN", &[text("👍🏽!")]).unwrap(), CellValue::Number(3.0));
+        assert_eq!(
+            call("LEN", &[CellValue::Number(-1.25)]).unwrap(),
+            CellValue::Number(5.0)
+        );
+        assert_eq!(
+            call("LEN", &[CellValue::Empty]).unwrap(),
+            CellValue::Number(0.0)
+        );
+    }
+
+    #[test]
+    fn test_left_and_right() {
+        let n = CellValue::Number;
+        assert_eq!(call("LEFT", &[text("héllo")]).unwrap(), text("h"));
+        assert_eq!(call("LEFT", &[text("héllo"), n(2.0)]).unwrap(), text("hé"));
+        assert_eq!(call("LEFT", &[text("hi"), n(10.0)]).unwrap(), text("hi"));
+        assert_eq!(
+            call("RIGHT", &[text("héllo"), n(4.0)]).unwrap(),
+            text("éllo")
+        );
+        assert_eq!(call("RIGHT", &[n(2024.0), n(2.0)]).unwrap(), text("24"));
+        assert_eq!(call("RIGHT", &[text("🙂x"), n(0.0)]).unwrap(), text(""));
+        assert!(call("LEFT", &[text("hi"), n(-1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_mid() {
+        let n = CellValue::Number;
+        assert_eq!(
+            call("MID", &[text("héllo"), n(2.0), n(3.0)]).unwrap(),
+            text("éll")
+        );
+        assert_eq!(
+            call("MID", &[text("héllo"), n(4.0), n(9.0)]).unwrap(),
+            text("lo")
+        );
+        assert_eq!(
+            call("MID", &[text("héllo"), n(9.0), n(2.0)]).unwrap(),
+            text("")
+        );
+        assert!(call("MID", &[text("héllo"), n(0.0), n(2.0)]).is_err());
+        assert!(call("MID", &[text("héllo"), n(1.0), n(-2.0)]).is_err());
+    }
+
+    #[test]
+    fn test_trim_upper_and_lower() {
+        assert_eq!(call("TRIM", &[text("  a   b c ")]).unwrap(), text("a b c"));
+        assert_eq!(call("TRIM", &[text("a\nb")]).unwrap(), text("a\nb"));
+        assert_eq!(call("UPPER", &[text("straße")]).unwrap(), text("STRASSE"));
+        assert_eq!(call("LOWER", &[text("ÉTÉ")]).unwrap(), text("été"));
+        let error = CellValue::from_error(ErrorType::NumError);
+        assert_eq!(call("UPPER", std::slice::from_ref(&error)).unwrap(), error);
+    }
+
+    #[test]
+    fn test_substitute() {
+        let n = CellValue::Number;
+        let args = |instance: Option<f64>| {
+            let mut args = vec![text("a-b-c-d"), text("-"), text("+")];
+            args.extend(instance.map(n));
+            args
+        };
+        assert_eq!(call("SUBSTITUTE", &args(None)).unwrap(), text("a+b+c+d"));
+        assert_eq!(
+            call("SUBSTITUTE", &args(Some(2.0))).unwrap(),
+            text("a-b+c-d")
+        );
+        assert_eq!(
+            call("SUBSTITUTE", &args(Some(5.0))).unwrap(),
+            text("a-b-c-d")
+        );
+        assert!(call("SUBSTITUTE", &args(Some(0.0))).is_err());
+        assert_eq!(
+            call("SUBSTITUTE", &[text("héé"), text("é"), text("e"), n(2.0)]).unwrap(),
+            text("hée")
+        );
+        assert_eq!(
+            call("SUBSTITUTE", &[text("abc"), text(""), text("x")]).unwrap(),
+            text("abc")
+        );
+    }
+}
@@ -1,7 +1,9 @@
 //! Helper functions for formula evaluation
 
-use crate::domain::Cell;
-use crate::evaluator::{Evaluator, PortContext};
+use crate::domain::{Cell, CellFormat};
+use crate::evaluator::dates::parse_date_input;
+use crate::evaluator::quantity::parse_quantity;
+use crate::evaluator::{EvaluationContext, Evaluator, PortContext};
 use crate::formula::FormulaParser;
 use crate::ports::RepositoryPort;
 use crate::types::CellValue;
@@ -10,6 +12,14 @@ use std::sync::Arc;
 
 /// Evaluate a cell formula and return a fully configured Cell
 pub fn evaluate_cell_formula(value: &str, repository: Arc<dyn RepositoryPort>) -> Result<Cell> {
+    evaluate_cell_formula_with(value, &mut PortContext::new(repository))
+}
+
+/// Like [`evaluate_cell_formula`], reading references through `context`
+pub fn evaluate_cell_formula_with(
+    value: &str,
+    context: &mut dyn EvaluationContext,
+) -> Result<Cell> {
     if let Some(formula_text) = value.strip_prefix('=') {
         // It's a formula
         let formula_string = formula_text.to_string();
@@ -21,12 +31,12 @@ pub fn evaluate_cell_formula(value: &str, repository: Arc<dyn RepositoryPort>) -
         // Try to evaluate the formula
         match FormulaParser::parse(&formula_string) {
             Ok(expr) => {
-                let mut context = PortContext::new(repository);
-                let mut evaluator = Evaluator::new(&mut context);
+                let mut evaluator = Evaluator::new(context);
 
-                // Evaluate and set the computed value
-                match evaluator.evaluate(&expr) {
-                    Ok(result) => cell.set_computed_value(result),
+                // Evaluate and set the computed value, an array to spill
+                match evaluator.evaluate_array(&expr) {
+                    Ok(result) => cell.set_array_value(result),
+                    Err(e @ SpreadsheetError::CircularDependencyWith(_)) => cell.set_error_from(&e),
                     Err(e) => cell.set_error(e.to_string()),
                 }
             }
@@ -46,13 +56,144 @@ pub fn evaluate_cell_formula(value: &str, repository: Arc<dyn RepositoryPort>) -
     }
 }
 
-/// Parse a string into a CellValue
+/// A value as typed, read into the value to store and the display format
+/// the typing implies
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedInput {
+    pub value: CellValue,
+    /// Format implied by a currency, percent sign, unit, grouped thousands,
+    /// exponent, parentheses or date, e.g. currency for `$1,234.50`
+    pub format: Option<CellFormat>,
+}
+
+/// Parse a string into a CellValue. Numbers typed with a currency, percent
+/// sign or unit, and dates like `2024-01-15` or `1/15/2024`, become bare
+/// numbers; see [`infer_input_format`] for the format they imply. TRUE
+/// and FALSE in any case are booleans. Nothing typed is a blank, not empty
+/// text; a lone `'` is empty text.
 pub fn parse_cell_value(value: &str) -> CellValue {
-    if let Ok(num) = value.parse::<f64>() {
-        CellValue::Number(num)
-    } else if let Ok(bool_val) = value.parse::<bool>() {
-        CellValue::Boolean(bool_val)
+    parse_cell_input(value, '.').value
+}
+
+/// [`parse_cell_value`] for numbers written with `decimal` as the decimal
+/// separator, also returning the format the input implies. With `,` as
+/// the separator, `.` groups thousands: `1.234,5 €` reads as 1234.5 in
+/// euros.
+pub fn parse_cell_input(value: &str, decimal: char) -> ParsedInput {
+    let plain = |value| ParsedInput {
+        value,
+        format: None,
+    };
+    if value.is_empty() {
+        return plain(CellValue::Empty);
+    }
+    if let Some(text) = value.strip_prefix('\'') {
+        // A leading apostrophe stores the rest as text, e.g. '00123
+        return plain(CellValue::from_string(text.to_string()));
+    }
+    let swapped;
+    let number = if decimal == ',' {
+        swapped = value
+            .chars()
+            .map(|c| match c {
+                ',' => '.',
+                '.' => ',',
+                c => c,
+            })
+            .collect::<String>();
+        swapped.as_str()
+    } else {
+        value
+    };
+    if let Ok(num) = number.parse::<f64>() {
+        // Numbers like `1.5e3` parse as they are but still imply a format
+        ParsedInput {
+            value: CellValue::Number(num),
+            format: parse_quantity(number).map(|(_, format)| format),
+        }
+    } else if let Some((num, format)) = parse_quantity(number) {
+        ParsedInput {
+            value: CellValue::Number(num),
+            format: Some(format),
+        }
+    } else if let Some((serial, format)) = parse_date_input(value) {
+        ParsedInput {
+            value: CellValue::Number(serial),
+            format: Some(format),
+        }
+    } else if value.eq_ignore_ascii_case("true") {
+        plain(CellValue::Boolean(true))
+    } else if value.eq_ignore_ascii_case("false") {
+        plain(CellValue::Boolean(false))
     } else {
-        CellValue::from_string(value.to_string())
+        plain(CellValue::from_string(value.to_string()))
+    }
+}
+
+/// Display format implied by how a value was typed, e.g. currency for
+/// `$1,234.50`. Formulas and plain values imply none.
+pub fn infer_input_format(value: &str) -> Option<CellFormat> {
+    if value.starts_with(['=', '\'']) {
+        return None;
+    }
+    parse_cell_input(value, '.').format
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cell_input_infers_formats() {
+        let parse = |value: &str, decimal| {
+            let input = parse_cell_input(value, decimal);
+            (input.value.as_number(), input.format)
+        };
+        let cases = [
+            ("12%", Some(0.12), Some(CellFormat::percent(0))),
+            ("$5.00", Some(5.0), Some(CellFormat::currency("$", 2))),
+            ("1,234.56", Some(1234.56), Some(CellFormat::thousands(2))),
+            ("1.5e3", Some(1500.0), Some(CellFormat::scientific(1))),
+            (
+                "(1,234.56)",
+                Some(-1234.56),
+                Some(CellFormat::accounting("", 2)),
+            ),
+            ("($5.00)", Some(-5.0), Some(CellFormat::accounting("$", 2))),
+            ("42", Some(42.0), None),
+            (
+                "2024-01-15",
+                Some(45306.0),
+                Some(CellFormat::date("%Y-%m-%d")),
+            ),
+            (
+                "1/15/2024",
+                Some(45306.0),
+                Some(CellFormat::date("%-m/%-d/%Y")),
+            ),
+            ("1/2/3", None, None),
+        ];
+        for (value, number, format) in cases {
+            assert_eq!(parse(value, '.'), (number, format), "{value}");
+        }
+
+        // With a comma for decimals, points group the thousands
+        assert_eq!(
+            parse("1.234,56", ','),
+            (Some(1234.56), Some(CellFormat::thousands(2)))
+        );
+        assert_eq!(
+            parse("12,5 %", ','),
+            (Some(0.125), Some(CellFormat::percent(1)))
+        );
+        assert_eq!(parse("1,5", ','), (Some(1.5), None));
+        assert_eq!(parse("1,5", '.'), (None, None));
+        assert_eq!(
+            parse_cell_input("'12%", '.'),
+            ParsedInput {
+                value: CellValue::string_from_str("12%"),
+                format: None
+            }
+        );
     }
 }
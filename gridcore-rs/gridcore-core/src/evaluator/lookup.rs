@@ -0,0 +1,275 @@
+//! Matching rules of the lookup functions VLOOKUP, HLOOKUP, MATCH, and of the
+//! conditional COUNTIF, COUNTIFS, SUMIF, SUMIFS, AVERAGEIF and AVERAGEIFS.
+//!
+//! Exact matches compare [`LookupKey`]s, the same keys the repository's
+//! lookup index is built from, so an indexed lookup and a scan find the
+//! same cells.
+
+use crate::repository::LookupKey;
+use crate::types::CellValue;
+use std::cmp::Ordering;
+
+/// Whether `value` is what an exact lookup of `key` finds
+pub fn matches_exactly(value: &CellValue, key: &LookupKey) -> bool {
+    LookupKey::of(value).as_ref() == Some(key)
+}
+
+/// Order of two values for approximate lookups: numbers by value, text
+/// ignoring case and FALSE before TRUE. Values of different types, blanks
+/// and errors do not compare.
+pub fn compare(value: &CellValue, key: &CellValue) -> Option<Ordering> {
+    match (value, key) {
+        (CellValue::Number(a), CellValue::Number(b)) => a.partial_cmp(b),
+        (CellValue::String(a), CellValue::String(b)) => {
+            Some(a.to_lowercase().cmp(&b.to_lowercase()))
+        }
+        (CellValue::Boolean(a), CellValue::Boolean(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Position in `values` of the last value not after `key` (`descending`
+/// false) or not before it (`descending` true), for lookups in sorted data.
+/// Values that do not compare with `key` are passed over.
+pub fn approximate_position(
+    values: &[CellValue],
+    key: &CellValue,
+    descending: bool,
+) -> Option<usize> {
+    let past = if descending {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    };
+    let mut found = None;
+    for (position, value) in values.iter().enumerate() {
+        match compare(value, key) {
+            Some(order) if order == past => break,
+            Some(_) => found = Some(position),
+            None => {}
+        }
+    }
+    found
+}
+
+/// A criterion of the conditional functions, such as `5`, `">=10"`, `"<>"` or `"app*"`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Criterion {
+    /// Equal to the key, or blank for `None`
+    Equals(Option<LookupKey>),
+    /// Anything but the key, or anything not blank for `None`
+    NotEquals(Option<LookupKey>),
+    /// Ordered against the operand as [`compare`] orders values
+    Compare(Ordering, bool, CellValue),
+    /// Text matching a `*`/`?` pattern, or not matching it when negated
+    Pattern(Vec<PatternToken>, bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternToken {
+    /// `*`: any run of characters
+    Any,
+    /// `?`: one character
+    One,
+    Char(char),
+}
+
+impl Criterion {
+    /// Criterion described by `value`. Text may start with `=`, `<>`, `<`,
+    /// `<=`, `>` or `>=`; `*` and `?` in it are wildcards unless escaped
+    /// with `~`. Anything else matches equal values.
+    pub fn parse(value: &CellValue) -> Self {
+        let text = match value {
+            CellValue::String(text) => text.as_str(),
+            CellValue::Empty => return Self::Equals(None),
+            other => return Self::Equals(LookupKey::of(other)),
+        };
+
+        let (operator, operand) = [">=", "<=", "<>", "=", "<", ">"]
+            .into_iter()
+            .find_map(|operator| text.strip_prefix(operator).map(|rest| (operator, rest)))
+            .unwrap_or(("=", text));
+        let ordered = |order, inclusive| Self::Compare(order, inclusive, operand_value(operand));
+        match operator {
+            "<" => ordered(Ordering::Less, false),
+            "<=" => ordered(Ordering::Less, true),
+            ">" => ordered(Ordering::Greater, false),
+            ">=" => ordered(Ordering::Greater, true),
+            _ => {
+                let negated = operator == "<>";
+                if operand.is_empty() {
+                    return if negated {
+                        Self::NotEquals(None)
+                    } else {
+                        Self::Equals(None)
+                    };
+                }
+                if operand.contains(['*', '?', '~']) {
+                    return Self::Pattern(pattern(operand), negated);
+                }
+                let key = LookupKey::of(&operand_value(operand));
+                if negated {
+                    Self::NotEquals(key)
+                } else {
+                    Self::Equals(key)
+                }
+            }
+        }
+    }
+
+    pub fn matches(&self, value: &CellValue) -> bool {
+        match self {
+            Self::Equals(None) => is_blank(value),
+            Self::Equals(Some(key)) => matches_exactly(value, key),
+            Self::NotEquals(None) => !is_blank(value),
+            Self::NotEquals(Some(key)) => !matches_exactly(value, key),
+            Self::Compare(order, inclusive, operand) => match compare(value, operand) {
+                Some(Ordering::Equal) => *inclusive,
+                Some(found) => found == *order,
+                None => false,
+            },
+            Self::Pattern(tokens, negated) => {
+                let matched = match value {
+                    CellValue::String(text) => {
+                        let text: Vec<char> = text.to_lowercase().chars().collect();
+                        glob(tokens, &text)
+                    }
+                    _ => false,
+                };
+                matched != *negated
+            }
+        }
+    }
+}
+
+fn is_blank(value: &CellValue) -> bool {
+    match value {
+        CellValue::Empty => true,
+        CellValue::String(text) => text.is_empty(),
+        _ => false,
+    }
+}
+
+/// A criterion's operand: a number or boolean when it reads as one
+fn operand_value(operand: &str) -> CellValue {
+    if let Ok(n) = operand.trim().parse::<f64>() {
+        return CellValue::Number(n);
+    }
+    match operand.to_uppercase().as_str() {
+        "TRUE" => CellValue::Boolean(true),
+        "FALSE" => CellValue::Boolean(false),
+        _ => CellValue::from_string(operand.to_string()),
+    }
+}
+
+fn pattern(operand: &str) -> Vec<PatternToken> {
+    let mut tokens = Vec::new();
+    let mut chars = operand.chars().flat_map(char::to_lowercase);
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '*' => PatternToken::Any,
+            '?' => PatternToken::One,
+            '~' => PatternToken::Char(chars.next().unwrap_or('~')),
+            c => PatternToken::Char(c),
+        });
+    }
+    tokens
+}
+
+/// Whether `text` matches `tokens`, backtracking only to the last `*` so
+/// long text with many wildcards stays cheap
+fn glob(tokens: &[PatternToken], text: &[char]) -> bool {
+    let (mut t, mut c) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while c < text.len() {
+        match tokens.get(t) {
+            Some(PatternToken::Any) => {
+                star = Some((t, c));
+                t += 1;
+            }
+            Some(PatternToken::One) => {
+                t += 1;
+                c += 1;
+            }
+            Some(PatternToken::Char(ch)) if *ch == text[c] => {
+                t += 1;
+                c += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more character
+                Some((star_t, star_c)) => {
+                    star = Some((star_t, star_c + 1));
+                    t = star_t + 1;
+                    c = star_c + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[t..].iter().all(|token| *token == PatternToken::Any)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> CellValue {
+        CellValue::string_from_str(s)
+    }
+
+    fn count(criterion: CellValue, values: &[CellValue]) -> usize {
+        let criterion = Criterion::parse(&criterion);
+        values
+            .iter()
+            .filter(|value| criterion.matches(value))
+            .count()
+    }
+
+    #[test]
+    fn test_criteria() {
+        let values = [
+            CellValue::Number(1.0),
+            CellValue::Number(5.0),
+            CellValue::Number(10.0),
+            text("Apple"),
+            text("apricot"),
+            text("banana"),
+            text("a*b"),
+            CellValue::Boolean(true),
+            CellValue::Empty,
+        ];
+        assert_eq!(count(CellValue::Number(5.0), &values), 1);
+        assert_eq!(count(text("5"), &values), 1);
+        assert_eq!(count(text(">=5"), &values), 2);
+        assert_eq!(count(text("<5"), &values), 1);
+        assert_eq!(count(text("<>5"), &values), 8);
+        assert_eq!(count(text("apple"), &values), 1);
+        assert_eq!(count(text("ap*"), &values), 2);
+        assert_eq!(count(text("<>ap*"), &values), 7);
+        assert_eq!(count(text("b?nana"), &values), 1);
+        assert_eq!(count(text("a~*b"), &values), 1);
+        assert_eq!(count(text(">b"), &values), 1);
+        assert_eq!(count(text("TRUE"), &values), 1);
+        assert_eq!(count(text(""), &values), 1);
+        assert_eq!(count(text("<>"), &values), 8);
+    }
+
+    #[test]
+    fn test_approximate_position() {
+        let ascending = [
+            CellValue::Number(10.0),
+            CellValue::Number(20.0),
+            text("x"),
+            CellValue::Number(30.0),
+        ];
+        let at = |key: f64| approximate_position(&ascending, &CellValue::Number(key), false);
+        assert_eq!(at(5.0), None);
+        assert_eq!(at(20.0), Some(1));
+        assert_eq!(at(25.0), Some(1));
+        assert_eq!(at(99.0), Some(3));
+
+        let descending = [CellValue::Number(30.0), CellValue::Number(20.0)];
+        let key = CellValue::Number(25.0);
+        assert_eq!(approximate_position(&descending, &key, true), Some(0));
+    }
+}
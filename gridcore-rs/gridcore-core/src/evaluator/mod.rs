@@ -1,10 +1,23 @@
 pub mod context;
+pub mod dates;
+pub mod deterministic;
+pub mod embedded;
 pub mod engine;
 pub mod functions;
 pub mod helpers;
+pub mod lookup;
 pub mod operators;
+pub mod quantity;
+pub mod random;
 
-pub use context::{EvaluationContext, PortContext, RepositoryContext};
+pub use context::{
+    EvaluationContext, OverlayContext, PortContext, RepositoryContext, SheetRepositories,
+};
+pub use deterministic::DeterministicSource;
+pub use embedded::{CompiledExpression, ValueResolver, evaluate_expression};
 pub use engine::Evaluator;
 pub use functions::FunctionLibrary;
-pub use helpers::{evaluate_cell_formula, parse_cell_value};
+pub use helpers::{
+    ParsedInput, evaluate_cell_formula, evaluate_cell_formula_with, infer_input_format,
+    parse_cell_input, parse_cell_value,
+};
@@ -265,10 +265,14 @@ where
     Ok(CellValue::Boolean(op(cmp)))
 }
 
-/// Check if two values are equal
+/// Check if two values are equal. A blank equals empty text, so `=A1=""`
+/// holds both for a blank A1 and for one holding the result of `=""`.
 fn values_equal(left: &CellValue, right: &CellValue) -> bool {
     match (left, right) {
         (CellValue::Empty, CellValue::Empty) => true,
+        (CellValue::Empty, CellValue::String(s)) | (CellValue::String(s), CellValue::Empty) => {
+            s.is_empty()
+        }
         (CellValue::Number(l), CellValue::Number(r)) => (l - r).abs() < f64::EPSILON,
         (CellValue::String(l), CellValue::String(r)) => l == r,
         (CellValue::Boolean(l), CellValue::Boolean(r)) => l == r,
@@ -313,11 +317,20 @@ fn compare_cell_values(left: &CellValue, right: &CellValue) -> i32 {
             }
         }
 
-        // Empty is less than everything except empty
+        // Empty is less than everything except empty and empty text
         (CellValue::Empty, CellValue::Empty) => 0,
+        (CellValue::Empty, CellValue::String(s)) | (CellValue::String(s), CellValue::Empty)
+            if s.is_empty() =>
+        {
+            0
+        }
         (CellValue::Empty, _) => -1,
         (_, CellValue::Empty) => 1,
 
+        // Booleans come after numbers and text
+        (CellValue::Boolean(_), _) => 1,
+        (_, CellValue::Boolean(_)) => -1,
+
         // Try to coerce to numbers for comparison
         _ => {
             if let (Ok(l), Ok(r)) = (coerce_to_number(left), coerce_to_number(right)) {
@@ -344,11 +357,13 @@ fn compare_cell_values(left: &CellValue, right: &CellValue) -> i32 {
     }
 }
 
-/// Try to coerce a value to a number
+/// Try to coerce a value to a number. Blanks and empty text, like the
+/// result of `=""`, both read as 0.
 pub fn coerce_to_number(value: &CellValue) -> Result<f64> {
     match value {
         CellValue::Number(n) => Ok(*n),
         CellValue::Boolean(b) => Ok(if *b { 1.0 } else { 0.0 }),
+        CellValue::String(s) if s.is_empty() => Ok(0.0),
         CellValue::String(s) => s
             .parse::<f64>()
             .map_err(|_| SpreadsheetError::TypeError(format!("Cannot convert '{}' to number", s))),
@@ -357,6 +372,9 @@ pub fn coerce_to_number(value: &CellValue) -> Result<f64> {
         CellValue::Array(_) => Err(SpreadsheetError::TypeError(
             "Cannot convert array to number".to_string(),
         )),
+        CellValue::Sparkline(_) => Err(SpreadsheetError::TypeError(
+            "Cannot convert sparkline to number".to_string(),
+        )),
     }
 }
 
@@ -364,14 +382,8 @@ pub fn coerce_to_number(value: &CellValue) -> Result<f64> {
 pub fn coerce_to_string(value: &CellValue) -> String {
     match value {
         CellValue::String(s) => s.as_ref().clone(),
-        CellValue::Number(n) => {
-            // Format number nicely (remove unnecessary decimals)
-            if n.fract() == 0.0 && n.abs() < 1e10 {
-                format!("{:.0}", n)
-            } else {
-                format!("{}", n)
-            }
-        }
+        // As the grid shows the number without a format
+        CellValue::Number(_) => value.to_display_string(),
         CellValue::Boolean(b) => {
             if *b {
                 "TRUE".to_string()
@@ -382,6 +394,7 @@ pub fn coerce_to_string(value: &CellValue) -> String {
         CellValue::Empty => String::new(),
         CellValue::Error(e) => format!("#{}!", e),
         CellValue::Array(arr) => format!("{:?}", arr), // For debugging
+        CellValue::Sparkline(_) => String::new(),
     }
 }
 
@@ -408,5 +421,8 @@ pub fn coerce_to_boolean(value: &CellValue) -> Result<bool> {
         CellValue::Array(_) => Err(SpreadsheetError::TypeError(
             "Cannot convert array to boolean".to_string(),
         )),
+        CellValue::Sparkline(_) => Err(SpreadsheetError::TypeError(
+            "Cannot convert sparkline to boolean".to_string(),
+        )),
     }
 }
@@ -0,0 +1,479 @@
+//! Numbers typed with a currency, percent sign or unit.
+//!
+//! `$1,234.50`, `3.5%` and `45 kg` are stored as plain numbers, as are
+//! numbers written with grouped thousands (`1,234.56`), in scientific
+//! notation (`1.5e3`) or negative in parentheses (`($5.00)`). The currency,
+//! percent, unit or notation is kept as the cell's display format, inferred when the
+//! value is entered, so `CellValue` stays a bare number and arithmetic is
+//! unaffected. A simple aggregate over cells that share one format, such as
+//! `=SUM(B2:B9)` or `=B2-B3`, shows its result in that format too. Mixing
+//! formats is not an error: the result is just a number.
+//!
+//! [`convert`] backs the `CONVERT` function with the length, mass, time,
+//! volume and temperature units of Excel's table. It never converts
+//! currencies and never fetches rates. Keep rates in the workbook instead,
+//! e.g. `:let EUR_USD 1.08` and `=B2*EUR_USD`, or a named cell holding the
+//! rate.
+
+use crate::domain::{CellFormat, NumberFormat};
+use crate::formula::{BinaryOperator, CellRange, Expr};
+use crate::types::CellValue;
+use Dimension::*;
+
+/// Currency symbols read in front of or after a number
+const CURRENCY_SYMBOLS: [&str; 4] = ["$", "€", "£", "¥"];
+
+/// What a unit measures; only units of the same dimension convert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Volume,
+    Temperature,
+}
+
+/// A unit and its size in the base unit of its dimension: metres, grams,
+/// seconds, litres or kelvin
+struct Unit {
+    names: &'static [&'static str],
+    dimension: Dimension,
+    factor: f64,
+    /// Takes metric prefixes such as `k` in `km`
+    metric: bool,
+}
+
+const fn unit(names: &'static [&'static str], dimension: Dimension, factor: f64) -> Unit {
+    Unit {
+        names,
+        dimension,
+        factor,
+        metric: false,
+    }
+}
+
+const fn metric(names: &'static [&'static str], dimension: Dimension, factor: f64) -> Unit {
+    Unit {
+        names,
+        dimension,
+        factor,
+        metric: true,
+    }
+}
+
+const UNITS: &[Unit] = &[
+    // Length, in metres
+    metric(&["m"], Length, 1.0),
+    unit(&["mi"], Length, 1609.344),
+    unit(&["Nmi"], Length, 1852.0),
+    unit(&["in"], Length, 0.0254),
+    unit(&["ft"], Length, 0.3048),
+    unit(&["yd"], Length, 0.9144),
+    metric(&["ang"], Length, 1e-10),
+    unit(&["ell"], Length, 1.143),
+    metric(&["ly"], Length, 9_460_730_472_580_800.0),
+    unit(&["Pica"], Length, 0.0254 / 72.0),
+    unit(&["pica"], Length, 0.0254 / 6.0),
+    unit(&["survey_mi"], Length, 1_609.347_218_694_437),
+    // Mass, in grams
+    metric(&["g"], Mass, 1.0),
+    unit(&["sg"], Mass, 14_593.902_937_206_4),
+    unit(&["lbm"], Mass, 453.592_37),
+    metric(&["u"], Mass, 1.660_539_066_60e-24),
+    unit(&["ozm"], Mass, 28.349_523_125),
+    unit(&["grain"], Mass, 0.064_798_91),
+    unit(&["cwt", "shweight"], Mass, 45_359.237),
+    unit(&["uk_cwt", "lcwt", "hweight"], Mass, 50_802.345_44),
+    unit(&["stone"], Mass, 6_350.293_18),
+    unit(&["ton"], Mass, 907_184.74),
+    unit(&["uk_ton", "LTON", "brton"], Mass, 1_016_046.908_8),
+    // Time, in seconds
+    unit(&["yr"], Time, 31_557_600.0),
+    unit(&["day", "d"], Time, 86_400.0),
+    unit(&["hr"], Time, 3_600.0),
+    unit(&["mn", "min"], Time, 60.0),
+    metric(&["sec", "s"], Time, 1.0),
+    // Volume, in litres
+    metric(&["l", "L", "lt"], Volume, 1.0),
+    unit(&["tsp"], Volume, 0.004_928_921_593_75),
+    unit(&["tspm"], Volume, 0.005),
+    unit(&["tbs"], Volume, 0.014_786_764_781_25),
+    unit(&["oz"], Volume, 0.029_573_529_562_5),
+    unit(&["cup"], Volume, 0.236_588_236_5),
+    unit(&["pt", "us_pt"], Volume, 0.473_176_473),
+    unit(&["uk_pt"], Volume, 0.568_261_25),
+    unit(&["qt"], Volume, 0.946_352_946),
+    unit(&["uk_qt"], Volume, 1.136_522_5),
+    unit(&["gal"], Volume, 3.785_411_784),
+    unit(&["uk_gal"], Volume, 4.546_09),
+    // Temperature; the factor is unused, see `to_kelvin`
+    unit(&["C", "cel"], Temperature, 1.0),
+    unit(&["F", "fah"], Temperature, 1.0),
+    metric(&["K", "kel"], Temperature, 1.0),
+    unit(&["Rank"], Temperature, 1.0),
+    unit(&["Reau"], Temperature, 1.0),
+];
+
+/// Metric prefixes, `da` before `d` so the longer one wins
+const PREFIXES: &[(&str, f64)] = &[
+    ("Y", 1e24),
+    ("Z", 1e21),
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+    ("h", 1e2),
+    ("da", 1e1),
+    ("e", 1e1),
+    ("d", 1e-1),
+    ("c", 1e-2),
+    ("m", 1e-3),
+    ("u", 1e-6),
+    ("n", 1e-9),
+    ("p", 1e-12),
+    ("f", 1e-15),
+    ("a", 1e-18),
+    ("z", 1e-21),
+    ("y", 1e-24),
+];
+
+/// The unit written as `name` and the prefix multiplier it carries. Names
+/// are case sensitive, as in Excel.
+fn lookup(name: &str) -> Option<(&'static Unit, f64)> {
+    let find = |name: &str| UNITS.iter().find(|unit| unit.names.contains(&name));
+    if let Some(unit) = find(name) {
+        return Some((unit, 1.0));
+    }
+    PREFIXES.iter().find_map(|(prefix, multiplier)| {
+        let unit = find(name.strip_prefix(prefix)?).filter(|unit| unit.metric)?;
+        Some((unit, *multiplier))
+    })
+}
+
+fn to_kelvin(value: f64, name: &str) -> f64 {
+    match name {
+        "C" | "cel" => value + 273.15,
+        "F" | "fah" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        "Rank" => value * 5.0 / 9.0,
+        "Reau" => value * 1.25 + 273.15,
+        _ => value,
+    }
+}
+
+fn from_kelvin(kelvin: f64, name: &str) -> f64 {
+    match name {
+        "C" | "cel" => kelvin - 273.15,
+        "F" | "fah" => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+        "Rank" => kelvin * 9.0 / 5.0,
+        "Reau" => (kelvin - 273.15) / 1.25,
+        _ => kelvin,
+    }
+}
+
+/// Whether `name` is a unit `CONVERT` knows
+pub fn is_unit(name: &str) -> bool {
+    lookup(name).is_some()
+}
+
+/// Convert `value` from one unit to another, `None` when either unit is
+/// unknown or they measure different things
+pub fn convert(value: f64, from: &str, to: &str) -> Option<f64> {
+    let (from_unit, from_prefix) = lookup(from)?;
+    let (to_unit, to_prefix) = lookup(to)?;
+    if from_unit.dimension != to_unit.dimension {
+        return None;
+    }
+    if from_unit.dimension == Temperature {
+        // Only kelvin takes prefixes, e.g. mK
+        let kelvin = match from_prefix {
+            1.0 => to_kelvin(value, from),
+            prefix => value * prefix,
+        };
+        return Some(match to_prefix {
+            1.0 => from_kelvin(kelvin, to),
+            prefix => kelvin / prefix,
+        });
+    }
+    Some(value * from_unit.factor * from_prefix / (to_unit.factor * to_prefix))
+}
+
+/// Read `45`, `-1,234.50` or `.5`: the number and how many decimals it was
+/// written with
+fn plain_number(text: &str) -> Option<(f64, u8)> {
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (digits, ""),
+    };
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    let groups: Vec<&str> = whole.split(',').collect();
+    let grouped = groups.len() > 1;
+    let whole_valid = groups.iter().enumerate().all(|(i, group)| {
+        all_digits(group)
+            && (!grouped
+                || if i == 0 {
+                    (1..=3).contains(&group.len())
+                } else {
+                    group.len() == 3
+                })
+    });
+    if !whole_valid || !all_digits(fraction) || (whole.is_empty() && fraction.is_empty()) {
+        return None;
+    }
+    let number = text.replace(',', "").parse::<f64>().ok()?;
+    Some((number, fraction.len().min(u8::MAX as usize) as u8))
+}
+
+/// Read `1.5e3` or `-2E-04`: the number and how many decimals its mantissa
+/// was written with
+fn scientific_number(text: &str) -> Option<(f64, u8)> {
+    let (mantissa, exponent) = text.split_once(['e', 'E'])?;
+    let digits = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+    if mantissa.contains(',') || digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (_, decimals) = plain_number(mantissa)?;
+    Some((text.parse().ok()?, decimals))
+}
+
+/// Read a number written with a currency symbol, a percent sign, a unit,
+/// grouped thousands, an exponent or in parentheses, returning the number
+/// to store and the format it was typed in. Plain numbers return `None`.
+pub fn parse_quantity(text: &str) -> Option<(f64, CellFormat)> {
+    let text = text.trim();
+
+    // Accounting negatives: (5), (1,234.50) or ($5.00)
+    if let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        let inner = inner.trim();
+        if inner.starts_with(['-', '+']) {
+            return None;
+        }
+        let (number, format) = match parse_quantity(inner) {
+            Some((number, format)) => match format.number_format {
+                NumberFormat::Currency { symbol, decimals } => {
+                    (number, CellFormat::accounting(symbol, decimals))
+                }
+                NumberFormat::Number { decimals, .. } => {
+                    (number, CellFormat::accounting("", decimals))
+                }
+                _ => return None,
+            },
+            None => {
+                let (number, decimals) = plain_number(inner)?;
+                (number, CellFormat::accounting("", decimals))
+            }
+        };
+        return Some((-number, format));
+    }
+
+    if let Some(body) = text.strip_suffix('%') {
+        let (number, decimals) = plain_number(body.trim_end())?;
+        return Some((number / 100.0, CellFormat::percent(decimals)));
+    }
+
+    let (sign, rest) = match text.strip_prefix('-') {
+        Some(rest) => (-1.0, rest.trim_start()),
+        None => (1.0, text),
+    };
+    for symbol in CURRENCY_SYMBOLS {
+        let body = rest
+            .strip_prefix(symbol)
+            .map(str::trim_start)
+            .or_else(|| rest.strip_suffix(symbol).map(str::trim_end));
+        if let Some((number, decimals)) = body.and_then(plain_number) {
+            return Some((sign * number, CellFormat::currency(symbol, decimals)));
+        }
+    }
+
+    if let Some((number, decimals)) = plain_number(text) {
+        return text
+            .contains(',')
+            .then(|| (number, CellFormat::thousands(decimals)));
+    }
+    if let Some((number, decimals)) = scientific_number(text) {
+        return Some((number, CellFormat::scientific(decimals)));
+    }
+
+    let split = text.find(|c: char| c.is_alphabetic() || c == '_')?;
+    let (number, unit_name) = (text[..split].trim_end(), &text[split..]);
+    let (number, decimals) = plain_number(number)?;
+    is_unit(unit_name).then(|| (number, CellFormat::unit(unit_name, decimals)))
+}
+
+/// The cells a formula aggregates when it is a simple aggregate: `SUM`,
+/// `AVERAGE`, `MIN` or `MAX` of references, ranges and multi-area
+/// references, or references and numbers added and subtracted, e.g.
+/// `A1+30`. Anything else returns `None`.
+pub fn aggregate_inputs(expr: &Expr) -> Option<Vec<CellRange>> {
+    let mut inputs = Vec::new();
+    collect_inputs(expr, true, &mut inputs).then_some(inputs)
+}
+
+fn collect_inputs(expr: &Expr, top: bool, inputs: &mut Vec<CellRange>) -> bool {
+    match expr {
+        Expr::Reference { address, .. } => {
+            inputs.push(CellRange::new(*address, *address));
+            true
+        }
+        Expr::Range { range, .. } => {
+            inputs.push(*range);
+            true
+        }
+        Expr::Union { .. } | Expr::Intersection { .. } if !top => match expr.areas() {
+            Some(areas) => {
+                inputs.extend(areas);
+                true
+            }
+            None => false,
+        },
+        Expr::FunctionCall { name, args } if top => {
+            matches!(name.as_str(), "SUM" | "AVERAGE" | "MIN" | "MAX")
+                && args.iter().all(|arg| collect_inputs(arg, false, inputs))
+        }
+        Expr::BinaryOp {
+            op: BinaryOperator::Add | BinaryOperator::Subtract,
+            left,
+            right,
+        } => {
+            let operand = |side: &Expr, inputs: &mut Vec<CellRange>| match side {
+                Expr::Range { .. } => false,
+                Expr::Literal {
+                    value: CellValue::Number(_),
+                } => true,
+                side => collect_inputs(side, top, inputs),
+            };
+            operand(left, inputs) && operand(right, inputs)
+        }
+        _ => false,
+    }
+}
+
+/// The format shared by all `formats`, when they agree on one that is not
+/// General
+pub fn common_format(formats: impl IntoIterator<Item = Option<CellFormat>>) -> Option<CellFormat> {
+    let mut formats = formats.into_iter();
+    let first = formats.next()??;
+    (first.number_format != NumberFormat::General && formats.all(|f| f.as_ref() == Some(&first)))
+        .then_some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::FormulaParser;
+
+    fn close(actual: Option<f64>, expected: f64) -> bool {
+        actual.is_some_and(|actual| (actual - expected).abs() <= 1e-9 * expected.abs().max(1.0))
+    }
+
+    #[test]
+    fn test_convert_table() {
+        let cases = [
+            (1.0, "mi", "km", 1.609344),
+            (1.0, "in", "cm", 2.54),
+            (3.0, "ft", "yd", 1.0),
+            (1.0, "Nmi", "m", 1852.0),
+            (1.0, "lbm", "kg", 0.45359237),
+            (16.0, "ozm", "lbm", 1.0),
+            (1.0, "stone", "lbm", 14.0),
+            (1.0, "ton", "lbm", 2000.0),
+            (1.0, "day", "hr", 24.0),
+            (90.0, "min", "hr", 1.5),
+            (1.0, "yr", "day", 365.25),
+            (1.0, "ms", "sec", 0.001),
+            (1.0, "gal", "l", 3.785411784),
+            (4.0, "qt", "gal", 1.0),
+            (1.0, "cup", "tbs", 16.0),
+            (100.0, "C", "F", 212.0),
+            (32.0, "fah", "cel", 0.0),
+            (0.0, "C", "K", 273.15),
+            (1.0, "kK", "K", 1000.0),
+            (491.67, "Rank", "C", 0.0),
+            (80.0, "Reau", "C", 100.0),
+        ];
+        for (value, from, to, expected) in cases {
+            assert!(
+                close(convert(value, from, to), expected),
+                "CONVERT({value}, {from}, {to}) = {:?}, expected {expected}",
+                convert(value, from, to)
+            );
+        }
+        // Every unit converts to itself
+        for unit in UNITS {
+            for name in unit.names {
+                assert!(close(convert(42.0, name, name), 42.0), "{name}");
+            }
+        }
+        assert_eq!(convert(1.0, "m", "kg"), None);
+        assert_eq!(convert(1.0, "furlong", "m"), None);
+        assert_eq!(convert(1.0, "kft", "m"), None);
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(
+            parse_quantity("$1,234.50"),
+            Some((1234.5, CellFormat::currency("$", 2)))
+        );
+        assert_eq!(
+            parse_quantity("-€5"),
+            Some((-5.0, CellFormat::currency("€", 0)))
+        );
+        assert_eq!(
+            parse_quantity("3.5%"),
+            Some((0.035, CellFormat::percent(1)))
+        );
+        assert_eq!(
+            parse_quantity("45 kg"),
+            Some((45.0, CellFormat::unit("kg", 0)))
+        );
+        assert_eq!(
+            parse_quantity("1,234.56"),
+            Some((1234.56, CellFormat::thousands(2)))
+        );
+        assert_eq!(
+            parse_quantity("-1,000"),
+            Some((-1000.0, CellFormat::thousands(0)))
+        );
+        assert_eq!(
+            parse_quantity("1.5e3"),
+            Some((1500.0, CellFormat::scientific(1)))
+        );
+        assert_eq!(
+            parse_quantity("2E-04"),
+            Some((0.0002, CellFormat::scientific(0)))
+        );
+        assert_eq!(
+            parse_quantity("(1,234.50)"),
+            Some((-1234.5, CellFormat::accounting("", 2)))
+        );
+        assert_eq!(
+            parse_quantity("($5.00)"),
+            Some((-5.0, CellFormat::accounting("$", 2)))
+        );
+        assert_eq!(
+            parse_quantity("(7)"),
+            Some((-7.0, CellFormat::accounting("", 0)))
+        );
+        for plain in [
+            "45", "12,34", "$", "5 apples", "kg", "1,2345", "(-5)", "(5%)", "()", "1e", "e5",
+            "1,000e3",
+        ] {
+            assert_eq!(parse_quantity(plain), None, "{plain}");
+        }
+    }
+
+    #[test]
+    fn test_aggregate_inputs() {
+        let inputs = |formula| aggregate_inputs(&FormulaParser::parse(formula).unwrap());
+        assert_eq!(inputs("SUM(A1:A3)").map(|r| r.len()), Some(1));
+        assert_eq!(inputs("A1+A2-B1").map(|r| r.len()), Some(3));
+        assert_eq!(inputs("A1+30").map(|r| r.len()), Some(1));
+        assert_eq!(inputs("A1*2"), None);
+        assert_eq!(inputs("SUM(A1:A3)*2"), None);
+        assert_eq!(inputs("COUNT(A1:A3)"), None);
+        assert_eq!(inputs("SUM(A1:A3, SUM(B1:B2))"), None);
+    }
+}
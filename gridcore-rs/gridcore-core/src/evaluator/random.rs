@@ -0,0 +1,82 @@
+//! Random numbers for RAND and RANDBETWEEN. Each thread keeps a SplitMix64
+//! generator seeded from the system's randomness the first time it is
+//! used, which works alike on native targets and in the browser. In
+//! deterministic mode a workbook draws from its own seeded [`Rng`] instead.
+
+use std::cell::RefCell;
+
+thread_local! {
+    static ENTROPY: RefCell<Rng> = RefCell::new(Rng::new(initial_seed()));
+}
+
+fn initial_seed() -> u64 {
+    let (high, low) = uuid::Uuid::new_v4().as_u64_pair();
+    high ^ low
+}
+
+/// SplitMix64 generator. Its state is a single number, so the state after
+/// any number of draws is also a seed that continues from there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// The seed that draws what this generator draws next
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        // The top 53 bits fill an f64's mantissa exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A whole number from `bottom` to `top`, both included
+    pub fn between(&mut self, bottom: i64, top: i64) -> i64 {
+        let span = top.abs_diff(bottom) + 1;
+        // A span of the whole u64 range wraps to 0
+        if span == 0 {
+            return self.next_u64() as i64;
+        }
+        bottom.wrapping_add((self.next_u64() % span) as i64)
+    }
+}
+
+/// A number in `[0, 1)` from the thread's entropy-seeded generator
+pub fn next_f64() -> f64 {
+    ENTROPY.with(|rng| rng.borrow_mut().next_f64())
+}
+
+/// A whole number from `bottom` to `top` from the thread's entropy-seeded
+/// generator
+pub fn between(bottom: i64, top: i64) -> i64 {
+    ENTROPY.with(|rng| rng.borrow_mut().between(bottom, top))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_the_state_continues_the_draws() {
+        let mut rng = Rng::new(42);
+        rng.next_u64();
+        let mut resumed = Rng::new(rng.state());
+        assert_eq!(rng.next_u64(), resumed.next_u64());
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+}
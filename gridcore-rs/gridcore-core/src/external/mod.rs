@@ -0,0 +1,435 @@
+//! External data for `FETCH` formulas
+//!
+//! `=FETCH(url, selector)` never blocks evaluation. The first evaluation
+//! registers a request and yields `#GETTING_DATA`; the host performs the
+//! request (browser fetch, a native HTTP client, or an [`ExternalResolver`]
+//! in headless use) and hands the body back to the facade, which caches the
+//! value and recalculates the subscribed cells. Core itself never does I/O.
+
+use crate::evaluator::parse_cell_value;
+use crate::memory::{MemoryFootprint, hash_table_bytes};
+use crate::types::{CellAddress, CellValue, ErrorType};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Name of the formula function that reads external data
+pub const FETCH_FUNCTION: &str = "FETCH";
+
+/// Selector prefix for picking a value out of a JSON body, e.g. `json:$.price`
+pub const JSON_SELECTOR_PREFIX: &str = "json:";
+
+/// One external value, identified by its URL and the selector applied to the body
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExternalRequest {
+    pub url: String,
+    pub selector: Option<String>,
+}
+
+impl ExternalRequest {
+    pub fn new(url: impl Into<String>, selector: Option<String>) -> Self {
+        Self {
+            url: url.into(),
+            selector,
+        }
+    }
+}
+
+/// A formula cell that reads one or more external values
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExternalCell {
+    pub sheet: String,
+    pub address: CellAddress,
+}
+
+impl ExternalCell {
+    pub fn new(sheet: impl Into<String>, address: CellAddress) -> Self {
+        Self {
+            sheet: sheet.into(),
+            address,
+        }
+    }
+}
+
+/// Host-provided fetcher used to resolve pending requests synchronously
+pub trait ExternalResolver {
+    /// Fetch the body at `url`, or describe why that failed (e.g. "HTTP 404 Not Found")
+    fn fetch(&self, url: &str) -> std::result::Result<String, String>;
+}
+
+/// Turn a response body into a cell value using the request's selector.
+///
+/// Without a selector the trimmed body is read like typed input. With
+/// `json:` the rest is a path such as `$.data.items[0].price`.
+pub fn extract_value(body: &str, selector: Option<&str>) -> std::result::Result<CellValue, String> {
+    let Some(selector) = selector.filter(|s| !s.is_empty()) else {
+        return Ok(parse_cell_value(body.trim()));
+    };
+
+    let Some(path) = selector.strip_prefix(JSON_SELECTOR_PREFIX) else {
+        return Err(format!("Unsupported selector '{}'", selector));
+    };
+
+    let json: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| format!("Response is not valid JSON: {}", e))?;
+    let value = select_json(&json, path).ok_or_else(|| format!("Nothing found at {}", path))?;
+
+    Ok(match value {
+        serde_json::Value::Null => CellValue::Empty,
+        serde_json::Value::Bool(b) => CellValue::Boolean(*b),
+        serde_json::Value::Number(n) => n
+            .as_f64()
+            .map(CellValue::Number)
+            .unwrap_or_else(|| CellValue::from_string(n.to_string())),
+        serde_json::Value::String(s) => CellValue::from_string(s.clone()),
+        other => CellValue::from_string(other.to_string()),
+    })
+}
+
+/// Walk a `$.a.b[0]` style path
+fn select_json<'a>(json: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut current = json;
+
+    while !rest.is_empty() {
+        if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            current = current.get(&after_dot[..end])?;
+            rest = &after_dot[end..];
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket.find(']')?;
+            let key = &after_bracket[..end];
+            current = match key.parse::<usize>() {
+                Ok(index) => current.get(index)?,
+                Err(_) => current.get(key.trim_matches(|c| c == '"' || c == '\''))?,
+            };
+            rest = &after_bracket[end + 1..];
+        } else {
+            return None;
+        }
+    }
+
+    Some(current)
+}
+
+struct CachedValue {
+    value: CellValue,
+    fetched_at: DateTime<Utc>,
+}
+
+/// Bookkeeping for external requests: which cells read which requests,
+/// which requests still need the host, and the values already fetched.
+///
+/// A request leaves the queue when the host takes it and is then in flight
+/// until a result arrives. When the last cell reading a request is edited
+/// away, the request is dropped from the queue, or reported as cancelled if
+/// the host already took it.
+#[derive(Default)]
+pub struct ExternalDataStore {
+    cache: HashMap<ExternalRequest, CachedValue>,
+    subscribers: HashMap<ExternalRequest, HashSet<ExternalCell>>,
+    cell_requests: HashMap<ExternalCell, HashSet<ExternalRequest>>,
+    queued: Vec<ExternalRequest>,
+    in_flight: HashSet<ExternalRequest>,
+    cancelled: Vec<ExternalRequest>,
+    /// How long fetched values stay valid; `None` keeps them until refreshed
+    ttl: Option<Duration>,
+}
+
+impl ExternalDataStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    pub fn set_ttl(&mut self, ttl: Option<Duration>) {
+        self.ttl = ttl;
+    }
+
+    /// Value of `request` for a formula in `cell`, subscribing the cell to it.
+    /// Returns `None` while the value still has to be fetched, queueing the
+    /// request unless the host already has it.
+    pub fn lookup(
+        &mut self,
+        cell: &ExternalCell,
+        request: &ExternalRequest,
+        now: DateTime<Utc>,
+    ) -> Option<CellValue> {
+        self.subscribers
+            .entry(request.clone())
+            .or_default()
+            .insert(cell.clone());
+        self.cell_requests
+            .entry(cell.clone())
+            .or_default()
+            .insert(request.clone());
+
+        if let Some(value) = self.peek(request, now) {
+            return Some(value);
+        }
+
+        self.queue(request);
+        None
+    }
+
+    /// The fetched value of `request` if it is still fresh, without
+    /// subscribing anything or queueing a fetch
+    pub fn peek(&self, request: &ExternalRequest, now: DateTime<Utc>) -> Option<CellValue> {
+        let cached = self.cache.get(request)?;
+        let fresh = self.ttl.is_none_or(|ttl| now - cached.fetched_at < ttl);
+        fresh.then(|| cached.value.clone())
+    }
+
+    /// Drop every subscription of a cell, e.g. before its formula is replaced
+    pub fn forget_cell(&mut self, cell: &ExternalCell) {
+        let Some(requests) = self.cell_requests.remove(cell) else {
+            return;
+        };
+
+        for request in requests {
+            let unused = match self.subscribers.get_mut(&request) {
+                Some(cells) => {
+                    cells.remove(cell);
+                    cells.is_empty()
+                }
+                None => true,
+            };
+            if !unused {
+                continue;
+            }
+
+            self.subscribers.remove(&request);
+            self.queued.retain(|queued| *queued != request);
+            if self.in_flight.remove(&request) {
+                self.cancelled.push(request);
+            }
+        }
+    }
+
+    /// Drop the subscriptions of every cell on a removed sheet
+    pub fn forget_sheet(&mut self, sheet: &str) {
+        let cells: Vec<ExternalCell> = self
+            .cell_requests
+            .keys()
+            .filter(|cell| cell.sheet == sheet)
+            .cloned()
+            .collect();
+        for cell in cells {
+            self.forget_cell(&cell);
+        }
+    }
+
+    /// Keep subscriptions attached to their cells when a sheet is renamed
+    pub fn rename_sheet(&mut self, old_name: &str, new_name: &str) {
+        let rename = |cell: ExternalCell| {
+            if cell.sheet == old_name {
+                ExternalCell::new(new_name, cell.address)
+            } else {
+                cell
+            }
+        };
+
+        self.cell_requests = std::mem::take(&mut self.cell_requests)
+            .into_iter()
+            .map(|(cell, requests)| (rename(cell), requests))
+            .collect();
+        for cells in self.subscribers.values_mut() {
+            *cells = std::mem::take(cells).into_iter().map(rename).collect();
+        }
+    }
+
+    /// Hand the queued requests to the host, marking them in flight
+    pub fn take_requests(&mut self) -> Vec<ExternalRequest> {
+        let requests = std::mem::take(&mut self.queued);
+        self.in_flight.extend(requests.iter().cloned());
+        requests
+    }
+
+    /// Requests taken by the host that no cell needs any more
+    pub fn take_cancelled(&mut self) -> Vec<ExternalRequest> {
+        std::mem::take(&mut self.cancelled)
+    }
+
+    /// Number of requests queued or in flight
+    pub fn pending_count(&self) -> usize {
+        self.queued.len() + self.in_flight.len()
+    }
+
+    /// Store the value fetched for `request` and return the cells to
+    /// recalculate, in a stable order. Results for requests no cell reads
+    /// any more are discarded and return `None`.
+    pub fn complete(
+        &mut self,
+        request: &ExternalRequest,
+        value: CellValue,
+        now: DateTime<Utc>,
+    ) -> Option<Vec<ExternalCell>> {
+        self.in_flight.remove(request);
+        self.queued.retain(|queued| queued != request);
+
+        let cells = self
+            .subscribers
+            .get(request)
+            .filter(|cells| !cells.is_empty())?;
+        let mut cells: Vec<ExternalCell> = cells.iter().cloned().collect();
+        cells.sort_by(|a, b| {
+            (&a.sheet, a.address.row, a.address.col).cmp(&(&b.sheet, b.address.row, b.address.col))
+        });
+
+        self.cache.insert(
+            request.clone(),
+            CachedValue {
+                value,
+                fetched_at: now,
+            },
+        );
+        Some(cells)
+    }
+
+    /// Forget every fetched value and queue all subscribed requests again.
+    /// Cells keep showing their current values until the new results arrive.
+    pub fn refresh(&mut self) -> usize {
+        self.cache.clear();
+        let mut requests: Vec<ExternalRequest> = self.subscribers.keys().cloned().collect();
+        requests.sort_by(|a, b| (&a.url, &a.selector).cmp(&(&b.url, &b.selector)));
+        for request in &requests {
+            self.queue(request);
+        }
+        requests.len()
+    }
+
+    fn queue(&mut self, request: &ExternalRequest) {
+        if !self.in_flight.contains(request) && !self.queued.contains(request) {
+            self.queued.push(request.clone());
+        }
+    }
+}
+
+impl MemoryFootprint for ExternalDataStore {
+    fn footprint_bytes(&self) -> usize {
+        hash_table_bytes::<ExternalRequest, CachedValue>(self.cache.capacity())
+            + hash_table_bytes::<ExternalRequest, HashSet<ExternalCell>>(
+                self.subscribers.capacity(),
+            )
+            + set_bytes(&self.subscribers)
+            + hash_table_bytes::<ExternalCell, HashSet<ExternalRequest>>(
+                self.cell_requests.capacity(),
+            )
+            + set_bytes(&self.cell_requests)
+            + hash_table_bytes::<ExternalRequest, ()>(self.in_flight.capacity())
+            + (self.queued.capacity() + self.cancelled.capacity()) * size_of::<ExternalRequest>()
+    }
+
+    fn compact(&mut self) {
+        self.cache.shrink_to_fit();
+        self.subscribers
+            .values_mut()
+            .for_each(HashSet::shrink_to_fit);
+        self.subscribers.shrink_to_fit();
+        self.cell_requests
+            .values_mut()
+            .for_each(HashSet::shrink_to_fit);
+        self.cell_requests.shrink_to_fit();
+        self.queued.shrink_to_fit();
+        self.in_flight.shrink_to_fit();
+        self.cancelled.shrink_to_fit();
+    }
+}
+
+/// Estimated bytes of the sets a map holds
+fn set_bytes<K, T>(sets: &HashMap<K, HashSet<T>>) -> usize {
+    sets.values()
+        .map(|set| hash_table_bytes::<T, ()>(set.capacity()))
+        .sum()
+}
+
+/// Error value stored for a failed request
+pub fn external_error(message: impl Into<String>) -> CellValue {
+    CellValue::from_error(ErrorType::ExternalData {
+        message: message.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(a1: &str) -> ExternalCell {
+        ExternalCell::new("Sheet1", CellAddress::from_a1(a1).unwrap())
+    }
+
+    #[test]
+    fn test_extract_json_path() {
+        let body = r#"{"data": {"items": [{"price": 12.5}, {"name": "b"}]}, "ok": true}"#;
+        assert_eq!(
+            extract_value(body, Some("json:$.data.items[0].price")),
+            Ok(CellValue::Number(12.5))
+        );
+        assert_eq!(
+            extract_value(body, Some("json:$.data.items[1][\"name\"]")),
+            Ok(CellValue::string_from_str("b"))
+        );
+        assert_eq!(
+            extract_value(body, Some("json:$.ok")),
+            Ok(CellValue::Boolean(true))
+        );
+        assert!(extract_value(body, Some("json:$.missing")).is_err());
+        assert!(extract_value("not json", Some("json:$.a")).is_err());
+        assert!(extract_value(body, Some("xpath://a")).is_err());
+        assert_eq!(extract_value(" 42\n", None), Ok(CellValue::Number(42.0)));
+    }
+
+    #[test]
+    fn test_requests_are_shared_and_cancelled() {
+        let mut store = ExternalDataStore::new();
+        let now = Utc::now();
+        let request = ExternalRequest::new("https://example.com/a", None);
+
+        assert_eq!(store.lookup(&cell("A1"), &request, now), None);
+        assert_eq!(store.lookup(&cell("A2"), &request, now), None);
+        assert_eq!(store.take_requests(), vec![request.clone()]);
+        assert_eq!(store.pending_count(), 1);
+
+        // One subscriber left keeps the request alive
+        store.forget_cell(&cell("A1"));
+        assert!(store.take_cancelled().is_empty());
+        store.forget_cell(&cell("A2"));
+        assert_eq!(store.take_cancelled(), vec![request.clone()]);
+        assert_eq!(store.pending_count(), 0);
+
+        // A late result for a cancelled request is ignored
+        assert_eq!(store.complete(&request, CellValue::Number(1.0), now), None);
+    }
+
+    #[test]
+    fn test_cache_ttl_and_refresh() {
+        let mut store = ExternalDataStore::new();
+        store.set_ttl(Some(Duration::seconds(60)));
+        let now = Utc::now();
+        let request = ExternalRequest::new("https://example.com/a", None);
+
+        store.lookup(&cell("A1"), &request, now);
+        store.take_requests();
+        assert_eq!(
+            store.complete(&request, CellValue::Number(3.0), now),
+            Some(vec![cell("A1")])
+        );
+
+        assert_eq!(
+            store.lookup(&cell("A1"), &request, now + Duration::seconds(30)),
+            Some(CellValue::Number(3.0))
+        );
+        assert_eq!(
+            store.lookup(&cell("A1"), &request, now + Duration::seconds(90)),
+            None
+        );
+        assert_eq!(store.take_requests(), vec![request.clone()]);
+        store.complete(&request, CellValue::Number(4.0), now);
+
+        assert_eq!(store.refresh(), 1);
+        assert_eq!(store.take_requests(), vec![request]);
+    }
+}
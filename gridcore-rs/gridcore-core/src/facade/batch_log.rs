@@ -0,0 +1,176 @@
+//! Change recording for facade batches
+
+use crate::domain::Cell;
+use crate::error::{Result, SpreadsheetError};
+use crate::memory::{MemoryFootprint, value_heap_bytes};
+use crate::ports::event_port::{BATCH_DELTA_INLINE_LIMIT, BatchDelta, CellDelta};
+use crate::types::{CellAddress, CellRange, CellValue};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// How many summarised batches keep their full delta for fetching
+const RETAINED_BATCHES: usize = 16;
+
+/// Changes recorded while a batch is open, and the deltas of recent batches
+/// that were too large to inline in their commit event
+#[derive(Debug, Default)]
+pub(super) struct BatchLog {
+    open: Option<OpenBatch>,
+    retained: VecDeque<(String, Arc<Vec<CellDelta>>)>,
+    counter: u64,
+}
+
+#[derive(Debug)]
+struct OpenBatch {
+    id: String,
+    /// Nested `begin` calls join the open batch; it commits at depth 0
+    depth: usize,
+    changes: Vec<CellDelta>,
+    positions: HashMap<CellAddress, usize>,
+}
+
+impl BatchLog {
+    /// Open a batch, or join the one already open. Returns its id and
+    /// whether it was newly opened.
+    pub(super) fn begin(&mut self) -> (String, bool) {
+        if let Some(open) = &mut self.open {
+            open.depth += 1;
+            return (open.id.clone(), false);
+        }
+        self.counter += 1;
+        let id = format!("batch_{}", self.counter);
+        self.open = Some(OpenBatch {
+            id: id.clone(),
+            depth: 1,
+            changes: Vec::new(),
+            positions: HashMap::new(),
+        });
+        (id, true)
+    }
+
+    /// Leave the batch `id`. Returns its delta once the outermost caller
+    /// commits, `Ok(None)` while nested callers are still inside it.
+    pub(super) fn commit(&mut self, id: &str) -> Result<Option<BatchDelta>> {
+        let open = match &mut self.open {
+            Some(open) if open.id == id => open,
+            _ => return Err(SpreadsheetError::BatchNotFound(id.to_string())),
+        };
+        open.depth -= 1;
+        if open.depth > 0 {
+            return Ok(None);
+        }
+
+        let open = self.open.take().expect("checked above");
+        let changes = open.changes;
+        if changes.len() <= BATCH_DELTA_INLINE_LIMIT {
+            return Ok(Some(BatchDelta::Cells(changes)));
+        }
+
+        let summary = BatchDelta::Summary {
+            changed: changes.len(),
+            bounds: bounds(&changes),
+        };
+        self.retained.push_back((open.id, Arc::new(changes)));
+        while self.retained.len() > RETAINED_BATCHES {
+            self.retained.pop_front();
+        }
+        Ok(Some(summary))
+    }
+
+    /// Record a change when a batch is open. A cell changed several times
+    /// keeps its first old state and its last new state.
+    pub(super) fn record(&mut self, address: CellAddress, old: Option<&Cell>, new: Option<&Cell>) {
+        let Some(open) = &mut self.open else {
+            return;
+        };
+        let new_value = new.map(|cell| cell.get_computed_value());
+        let new_formula = new.and_then(formula_of);
+        if let Some(&position) = open.positions.get(&address) {
+            let change = &mut open.changes[position];
+            change.new_value = new_value;
+            change.new_formula = new_formula;
+            return;
+        }
+        open.positions.insert(address, open.changes.len());
+        open.changes.push(CellDelta {
+            address,
+            old_value: old.map(|cell| cell.get_computed_value()),
+            old_formula: old.and_then(formula_of),
+            new_value,
+            new_formula,
+        });
+    }
+
+    pub(super) fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// Drop the oldest retained deltas until the rest fit in `budget` bytes
+    pub(super) fn trim_history(&mut self, budget: usize) {
+        let mut bytes: usize = self.retained.iter().map(retained_bytes).sum();
+        while bytes > budget {
+            let Some(oldest) = self.retained.pop_front() else {
+                break;
+            };
+            bytes = bytes.saturating_sub(retained_bytes(&oldest));
+        }
+    }
+
+    /// Full delta of a recent batch that was summarised in its event
+    pub(super) fn fetch(&self, id: &str) -> Option<Arc<Vec<CellDelta>>> {
+        self.retained
+            .iter()
+            .find(|(retained, _)| retained == id)
+            .map(|(_, changes)| changes.clone())
+    }
+}
+
+/// The retained deltas and the changes of the open batch
+impl MemoryFootprint for BatchLog {
+    fn footprint_bytes(&self) -> usize {
+        let open = self.open.as_ref().map_or(0, |open| {
+            changes_bytes(&open.changes)
+                + open.positions.capacity() * (size_of::<(CellAddress, usize)>() + 1)
+        });
+        open + self.retained.iter().map(retained_bytes).sum::<usize>()
+    }
+
+    fn compact(&mut self) {
+        self.retained.shrink_to_fit();
+    }
+}
+
+fn retained_bytes((id, changes): &(String, Arc<Vec<CellDelta>>)) -> usize {
+    id.capacity() + changes_bytes(changes)
+}
+
+fn changes_bytes(changes: &[CellDelta]) -> usize {
+    let value = |value: &Option<CellValue>| value.as_ref().map_or(0, value_heap_bytes);
+    let formula = |formula: &Option<String>| formula.as_ref().map_or(0, String::capacity);
+    size_of_val(changes)
+        + changes
+            .iter()
+            .map(|change| {
+                value(&change.old_value)
+                    + value(&change.new_value)
+                    + formula(&change.old_formula)
+                    + formula(&change.new_formula)
+            })
+            .sum::<usize>()
+}
+
+pub(super) fn formula_of(cell: &Cell) -> Option<String> {
+    cell.formula_text.as_deref().map(str::to_string)
+}
+
+fn bounds(changes: &[CellDelta]) -> Option<CellRange> {
+    let first = changes.first()?.address;
+    let (mut start, mut end) = (first, first);
+    for change in changes {
+        start.col = start.col.min(change.address.col);
+        start.row = start.row.min(change.address.row);
+        end.col = end.col.max(change.address.col);
+        end.row = end.row.max(change.address.row);
+    }
+    Some(CellRange::new(start, end))
+}
@@ -1,4 +1,7 @@
+mod batch_log;
+pub mod progress;
 pub mod spreadsheet_facade;
 
 // Re-export main types
-pub use spreadsheet_facade::SpreadsheetFacade;
+pub use progress::{ProgressCadence, RecalculationMonitor, RecalculationStatus};
+pub use spreadsheet_facade::{PREVIEW_STEP_BUDGET, Recalculation, SpreadsheetFacade};
@@ -0,0 +1,208 @@
+//! Progress of long recalculations.
+//!
+//! A recalculation of at least [`ProgressCadence::min_cells`] formulas is
+//! announced with [`DomainEvent::RecalculationStarted`], reports
+//! [`DomainEvent::RecalculationProgress`] every so many formulas or
+//! milliseconds, and ends with [`DomainEvent::RecalculationFinished`], or
+//! [`DomainEvent::RecalculationCancelled`] when a [`RecalculationMonitor`]
+//! cancelled it. Smaller ones run without events, so indicators do not
+//! flicker on every edit.
+
+use crate::ports::event_port::DomainEvent;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Milliseconds from some fixed point, for timing recalculations
+pub type Clock = Arc<dyn Fn() -> f64 + Send + Sync>;
+
+/// The wall clock
+pub fn system_clock() -> Clock {
+    Arc::new(|| chrono::Utc::now().timestamp_millis() as f64)
+}
+
+/// When recalculations report their progress
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressCadence {
+    /// Fewest formulas a recalculation reports on
+    pub min_cells: usize,
+    /// A progress event follows at most this many formulas after the last
+    pub every_cells: usize,
+    /// ... or this many milliseconds, whichever comes first
+    pub every_ms: f64,
+}
+
+impl Default for ProgressCadence {
+    fn default() -> Self {
+        Self {
+            min_cells: 2_000,
+            every_cells: 1_000,
+            every_ms: 100.0,
+        }
+    }
+}
+
+/// How far a running recalculation got
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecalculationStatus {
+    /// Formulas evaluated so far
+    pub completed: usize,
+    /// Formulas to evaluate, growing when arrays spill onto cells already
+    /// evaluated
+    pub total: usize,
+}
+
+impl RecalculationStatus {
+    /// Share of the formulas evaluated, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        (self.completed as f64 / self.total as f64).min(1.0)
+    }
+}
+
+/// Watches and cancels the recalculations of one facade. Clones share the
+/// same state, so a host can hand one to another thread or to an event
+/// handler.
+#[derive(Debug, Clone, Default)]
+pub struct RecalculationMonitor {
+    state: Arc<MonitorState>,
+}
+
+#[derive(Debug, Default)]
+struct MonitorState {
+    /// Set while a reported recalculation runs
+    status: Mutex<Option<RecalculationStatus>>,
+    cancel: AtomicBool,
+}
+
+impl RecalculationMonitor {
+    /// Progress of the running recalculation, `None` when none runs or it
+    /// is too small to be reported
+    pub fn status(&self) -> Option<RecalculationStatus> {
+        *self.state.status.lock().unwrap()
+    }
+
+    pub fn is_calculating(&self) -> bool {
+        self.status().is_some()
+    }
+
+    /// Stop the running recalculation before its next formula. Formulas
+    /// not evaluated yet stay stale until the sheet is recalculated again.
+    /// Does nothing when no reported recalculation runs.
+    pub fn cancel(&self) {
+        if self.is_calculating() {
+            self.state.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Whether the running, or last, recalculation was cancelled
+    pub(crate) fn cancelled(&self) -> bool {
+        self.state.cancel.load(Ordering::Relaxed)
+    }
+
+    fn set_status(&self, status: Option<RecalculationStatus>) {
+        *self.state.status.lock().unwrap() = status;
+    }
+}
+
+/// Counts the formulas of one recalculation and says when to report
+pub(crate) struct ProgressReporter {
+    cadence: ProgressCadence,
+    clock: Clock,
+    monitor: RecalculationMonitor,
+    status: RecalculationStatus,
+    /// The recalculation is large enough to be reported
+    reported: bool,
+    started_ms: f64,
+    /// Time and count at the last report
+    last_ms: f64,
+    last_completed: usize,
+}
+
+impl ProgressReporter {
+    /// Start a recalculation of `total` formulas. Returns the event
+    /// announcing it when it is large enough to report on.
+    pub fn start(
+        total: usize,
+        cadence: ProgressCadence,
+        clock: Clock,
+        monitor: RecalculationMonitor,
+    ) -> (Self, Option<DomainEvent>) {
+        monitor.state.cancel.store(false, Ordering::Relaxed);
+        let started_ms = clock();
+        let mut reporter = Self {
+            cadence,
+            clock,
+            monitor,
+            status: RecalculationStatus {
+                completed: 0,
+                total,
+            },
+            reported: false,
+            started_ms,
+            last_ms: started_ms,
+            last_completed: 0,
+        };
+        let event = (total >= cadence.min_cells.max(1)).then(|| {
+            reporter.reported = true;
+            reporter.monitor.set_status(Some(reporter.status));
+            DomainEvent::RecalculationStarted { total_dirty: total }
+        });
+        (reporter, event)
+    }
+
+    pub fn is_reported(&self) -> bool {
+        self.reported
+    }
+
+    /// `more` formulas join the recalculation
+    pub fn extend(&mut self, more: usize) {
+        self.status.total += more;
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.reported && self.monitor.cancelled()
+    }
+
+    /// One more formula was evaluated. Returns a progress event when one
+    /// is due.
+    pub fn advance(&mut self) -> Option<DomainEvent> {
+        self.status.completed += 1;
+        if !self.reported {
+            return None;
+        }
+        let now = (self.clock)();
+        let due = self.status.completed - self.last_completed >= self.cadence.every_cells
+            || now - self.last_ms >= self.cadence.every_ms;
+        if !due || self.status.completed >= self.status.total {
+            return None;
+        }
+        self.last_ms = now;
+        self.last_completed = self.status.completed;
+        self.monitor.set_status(Some(self.status));
+        Some(DomainEvent::RecalculationProgress {
+            completed: self.status.completed,
+            total: self.status.total,
+        })
+    }
+
+    /// The recalculation ended. Returns the event saying how, when it was
+    /// reported on.
+    pub fn finish(self) -> Option<DomainEvent> {
+        if !self.reported {
+            return None;
+        }
+        self.monitor.set_status(None);
+        if self.monitor.cancelled() {
+            return Some(DomainEvent::RecalculationCancelled {
+                completed: self.status.completed,
+                total: self.status.total,
+            });
+        }
+        Some(DomainEvent::RecalculationFinished {
+            duration_ms: (self.clock)() - self.started_ms,
+            cells_evaluated: self.status.completed,
+        })
+    }
+}
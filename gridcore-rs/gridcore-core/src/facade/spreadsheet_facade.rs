@@ -3,21 +3,234 @@
 //! This facade provides a clean API for spreadsheet operations,
 //! delegating to appropriate services and utilities.
 
-use crate::Result;
-use crate::domain::Cell;
-use crate::evaluator::evaluate_cell_formula;
+use super::batch_log::{BatchLog, formula_of};
+use super::progress::{
+    Clock, ProgressCadence, ProgressReporter, RecalculationMonitor, system_clock,
+};
+use crate::chart::{ChartData, build_chart_data};
+use crate::conditional::{ConditionalFill, ConditionalFormatRule, RangeStats, RangeStatsCache};
+use crate::csv::{
+    ColumnType, CsvExport, CsvImportOptions, DelimitedRows, FidelityIssue, ImportDestination,
+    ImportOptions, ImportReport, LoadMode, SIDECAR_VERSION, Sidecar, SidecarConditionalFormat,
+    cell_field, field_input, preview_import, write_csv,
+};
+use crate::dependency::{
+    CrossSheetDependencies, DependencyAnalyzer, DependencyGraph, DependencyReport, SheetCell,
+};
+use crate::domain::{
+    Cell, CellFormat, CellStyle, FormatStore, NumberFormat, StyleRegistry, StyleRemoval,
+};
+use crate::evaluator::dates;
+use crate::evaluator::quantity::{aggregate_inputs, common_format};
+use crate::evaluator::{
+    DeterministicSource, EvaluationContext, Evaluator, OverlayContext, PortContext,
+    SheetRepositories, evaluate_cell_formula_with, infer_input_format,
+};
+use crate::external::{
+    ExternalCell, ExternalDataStore, ExternalRequest, ExternalResolver, FETCH_FUNCTION,
+    external_error, extract_value,
+};
+use crate::fill::running::RunningAggregate;
+use crate::formula::CellRange;
+use crate::formula::{FormulaParser, FormulaTransformer, enclosing_subexpression};
+use crate::lint::{LintFinding, LintSettings, LintSource, MixedFormatCache, lint_source};
+use crate::memory::{self, CompactOptions, MemoryFootprint, MemoryReport};
+use crate::pivot::{PivotConfig, PivotDefinition, PivotTable, build_pivot};
+use crate::ports::event_port::{CellDelta, DomainEvent};
 use crate::ports::{EventPort, RepositoryPort};
+use crate::references::{ReferenceStyle, StructuralOperation};
+use crate::repository::{DensityBlock, DensityMap, ErrorIndex, SheetHealth, SpillRange};
 use crate::services::{ServiceContainer, ServiceContainerBuilder};
 use crate::types::{CellAddress, CellValue};
 use crate::utils::format_cell_value;
-use crate::workbook::{Sheet, SheetManager, Workbook};
+use crate::workbook::names::{name_key, validate_name};
+use crate::workbook::{
+    DefinedName, Determinism, NameScope, NamedConstant, NamedRange, Sheet, SheetManager,
+    VisibleNames, Workbook, WorkbookMetadata, WorkbookSettings,
+};
+use crate::{Result, SpreadsheetError};
+use rustc_hash::{FxHashMap, FxHasher};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
+/// Evaluation steps a preview may take before it gives up, see
+/// [`Evaluator::with_budget`]
+pub const PREVIEW_STEP_BUDGET: usize = 100_000;
+
+/// Rows [`SpreadsheetFacade::commit_import`] reads to detect what its
+/// options leave open
+const IMPORT_DETECTION_ROWS: usize = 50;
+
+/// Defined names a sheet's formulas can see
+type Names = Arc<VisibleNames>;
+
+/// Where in a recalculation pass the last array filled or left cells, and
+/// all the cells arrays did, see [`SpreadsheetFacade::recalculate_pass`]
+type Spills = Option<(usize, Vec<CellAddress>)>;
+
+/// Formulas a scoped recalculation evaluated
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recalculation {
+    /// Every formula evaluated, in the order it was evaluated
+    pub recalculated: Vec<CellAddress>,
+    /// Formulas that read stale ones outside the scope, so their new
+    /// values may be out of date too. They stay stale.
+    pub stale: Vec<CellAddress>,
+    /// The recalculation was cancelled; the formulas it did not reach are
+    /// stale
+    pub cancelled: bool,
+}
+
 /// Simplified facade for spreadsheet operations
 pub struct SpreadsheetFacade {
     container: Arc<ServiceContainer>,
     sheet_manager: Arc<Mutex<SheetManager>>,
     active_sheet: Arc<Mutex<String>>,
+    external: Arc<Mutex<ExternalDataStore>>,
+    density_cache: Arc<Mutex<Option<DensityCache>>>,
+    /// Mixed format checks of the last lint runs
+    mixed_format_cache: Arc<Mutex<MixedFormatCache>>,
+    batches: Arc<Mutex<BatchLog>>,
+    auto_compact: Arc<Mutex<AutoCompact>>,
+    /// Cells whose input was cut to the length limits since the last
+    /// [`SpreadsheetFacade::take_truncated_cells`]
+    truncated: Arc<Mutex<Vec<CellAddress>>>,
+    /// Insertions and deletions waiting for their formulas to be adjusted,
+    /// see [`SpreadsheetFacade::begin_structural_batch`]
+    structural_batch: Arc<Mutex<Option<StructuralBatch>>>,
+    /// Formulas re-evaluated by recalculations, see
+    /// [`SpreadsheetFacade::evaluation_count`]
+    evaluations: Arc<AtomicU64>,
+    /// Where volatile functions draw from in deterministic mode
+    deterministic: Arc<Mutex<Option<Arc<DeterministicSource>>>>,
+    /// Progress of the running recalculation, see [`super::progress`]
+    recalculation: RecalculationMonitor,
+    progress_cadence: Arc<Mutex<ProgressCadence>>,
+    clock: Arc<Mutex<Clock>>,
+    /// Statistics of the ranges conditional formats read, see
+    /// [`SpreadsheetFacade::conditional_range_stats`]
+    range_stats: Arc<Mutex<RangeStatsCache>>,
+    /// Formulas reading other sheets' cells, which the sheets' own
+    /// dependency graphs do not know
+    cross_sheet: Arc<Mutex<CrossSheetDependencies>>,
+}
+
+/// The active sheet as linting reads it
+struct SheetLintSource {
+    sheet: String,
+    /// Revision of the sheet's cells, `None` when the repository keeps none
+    revision: Option<u64>,
+    formats: FormatStore,
+    styles: StyleRegistry,
+    cells: HashMap<CellAddress, Cell>,
+}
+
+impl LintSource for SheetLintSource {
+    fn cell(&self, address: &CellAddress) -> Option<Cell> {
+        self.cells.get(address).cloned()
+    }
+
+    fn format(&self, address: &CellAddress) -> Option<CellFormat> {
+        self.formats
+            .effective_format(address, &self.styles)
+            .cloned()
+    }
+
+    /// The sheet, its cells' revision and every format reaching into
+    /// `range`. Entries are hashed one by one and summed, so the order the
+    /// format store keeps them in does not matter.
+    fn fingerprint(&self, range: &CellRange) -> Option<u64> {
+        let entry = |entry: &dyn Fn(&mut FxHasher)| {
+            let mut hasher = FxHasher::default();
+            entry(&mut hasher);
+            hasher.finish()
+        };
+        let revision = self.revision?;
+        let mut sum = entry(&|h| (&self.sheet, revision, range).hash(h));
+        let rows = range.start.row..=range.end.row;
+        let cols = range.start.col..=range.end.col;
+        for (address, format) in self.formats.cell_formats() {
+            if range.contains(address) {
+                sum = sum.wrapping_add(entry(&|h| ("cell", address, format).hash(h)));
+            }
+        }
+        for (address, name) in self.formats.cell_styles() {
+            if range.contains(address) {
+                let format = self.styles.get(name).map(|style| &style.format);
+                sum = sum.wrapping_add(entry(&|h| ("style", address, format).hash(h)));
+            }
+        }
+        for (row, format) in self.formats.row_formats() {
+            if rows.contains(&row) {
+                sum = sum.wrapping_add(entry(&|h| ("row", row, format).hash(h)));
+            }
+        }
+        for (col, format) in self.formats.column_formats() {
+            if cols.contains(&col) {
+                sum = sum.wrapping_add(entry(&|h| ("column", col, format).hash(h)));
+            }
+        }
+        let default = self.formats.default_format();
+        Some(sum.wrapping_add(entry(&|h| ("default", default).hash(h))))
+    }
+
+    fn cells_in(&self, range: &CellRange) -> Vec<(CellAddress, Cell)> {
+        let mut cells: Vec<(CellAddress, Cell)> = self
+            .cells
+            .iter()
+            .filter(|(address, _)| range.contains(address))
+            .map(|(address, cell)| (*address, cell.clone()))
+            .collect();
+        cells.sort_unstable_by_key(|(address, _)| (address.row, address.col));
+        cells
+    }
+}
+
+/// When the facade compacts on its own, see
+/// [`SpreadsheetFacade::set_auto_compact`]
+#[derive(Debug, Default)]
+struct AutoCompact {
+    threshold: Option<usize>,
+    /// Cells deleted since the last compaction
+    freed: usize,
+}
+
+/// Record the size of `subsystem` under `name`, compacting it in between
+/// when asked
+fn measure(
+    report: &mut MemoryReport,
+    name: &'static str,
+    subsystem: &mut dyn MemoryFootprint,
+    compact: bool,
+) {
+    let before = subsystem.footprint_bytes();
+    if compact {
+        subsystem.compact();
+    }
+    report.record(name, before, subsystem.footprint_bytes());
+}
+
+/// An open structural batch
+struct StructuralBatch {
+    /// The sheet its rows and columns change on
+    sheet: String,
+    operations: Vec<StructuralOperation>,
+    /// Cells written since it opened, by the number of operations before
+    /// the write, the sheet and where the cell was written then
+    written: Vec<(usize, String, CellAddress)>,
+    /// Every sheet's cells from before, for a rollback
+    before: Vec<(String, HashMap<CellAddress, Cell>)>,
+}
+
+/// Last density map built, with what it was built from
+struct DensityCache {
+    repository: Arc<dyn RepositoryPort>,
+    block_size: u32,
+    revision: u64,
+    map: Arc<DensityMap>,
 }
 
 impl SpreadsheetFacade {
@@ -41,6 +254,20 @@ impl SpreadsheetFacade {
             container: Arc::new(container),
             sheet_manager: Arc::new(Mutex::new(sheet_manager)),
             active_sheet: Arc::new(Mutex::new("Sheet1".to_string())),
+            external: Arc::new(Mutex::new(ExternalDataStore::new())),
+            density_cache: Arc::new(Mutex::new(None)),
+            mixed_format_cache: Arc::new(Mutex::new(MixedFormatCache::new())),
+            batches: Arc::new(Mutex::new(BatchLog::default())),
+            auto_compact: Arc::new(Mutex::new(AutoCompact::default())),
+            truncated: Arc::new(Mutex::new(Vec::new())),
+            structural_batch: Arc::new(Mutex::new(None)),
+            evaluations: Arc::new(AtomicU64::new(0)),
+            deterministic: Arc::new(Mutex::new(None)),
+            recalculation: RecalculationMonitor::default(),
+            progress_cadence: Arc::new(Mutex::new(ProgressCadence::default())),
+            clock: Arc::new(Mutex::new(system_clock())),
+            range_stats: Arc::new(Mutex::new(RangeStatsCache::new())),
+            cross_sheet: Arc::new(Mutex::new(CrossSheetDependencies::new())),
         }
     }
 
@@ -53,6 +280,20 @@ impl SpreadsheetFacade {
             container: Arc::new(container),
             sheet_manager: Arc::new(Mutex::new(sheet_manager)),
             active_sheet: Arc::new(Mutex::new("Sheet1".to_string())),
+            external: Arc::new(Mutex::new(ExternalDataStore::new())),
+            density_cache: Arc::new(Mutex::new(None)),
+            mixed_format_cache: Arc::new(Mutex::new(MixedFormatCache::new())),
+            batches: Arc::new(Mutex::new(BatchLog::default())),
+            auto_compact: Arc::new(Mutex::new(AutoCompact::default())),
+            truncated: Arc::new(Mutex::new(Vec::new())),
+            structural_batch: Arc::new(Mutex::new(None)),
+            evaluations: Arc::new(AtomicU64::new(0)),
+            deterministic: Arc::new(Mutex::new(None)),
+            recalculation: RecalculationMonitor::default(),
+            progress_cadence: Arc::new(Mutex::new(ProgressCadence::default())),
+            clock: Arc::new(Mutex::new(system_clock())),
+            range_stats: Arc::new(Mutex::new(RangeStatsCache::new())),
+            cross_sheet: Arc::new(Mutex::new(CrossSheetDependencies::new())),
         }
     }
 
@@ -66,302 +307,6802 @@ impl SpreadsheetFacade {
         Self::with_container(container)
     }
 
-    // Core cell operations
-
-    /// Get a cell by address
-    pub fn get_cell(&self, address: &CellAddress) -> Option<Cell> {
+    /// Get the cell repository backing the active sheet
+    fn active_repository(&self) -> Option<Arc<dyn RepositoryPort>> {
         let manager = self.sheet_manager.lock().unwrap();
         let active_sheet_name = self.active_sheet.lock().unwrap();
 
         if let Some(sheet) = manager.workbook().get_sheet(&active_sheet_name) {
-            sheet.cells().get(address)
+            Some(sheet.cells())
         } else {
-            self.container.repository()?.get(address)
+            self.container.repository()
         }
     }
 
-    /// Set a cell value (handles formulas and regular values)
-    pub fn set_cell_value(&self, address: &CellAddress, value: &str) -> Result<()> {
-        let old_value = self.get_cell(address).map(|c| c.get_computed_value());
-
-        // Get the repository for the active sheet
-        let manager = self.sheet_manager.lock().unwrap();
-        let active_sheet_name = self.active_sheet.lock().unwrap();
+    // Core cell operations
 
-        let repository = if let Some(sheet) = manager.workbook().get_sheet(&active_sheet_name) {
-            Some(sheet.cells())
-        } else {
-            self.container.repository()
-        };
+    /// Get a cell by address
+    pub fn get_cell(&self, address: &CellAddress) -> Option<Cell> {
+        self.active_repository()?.get(address)
+    }
 
-        if let Some(repo) = repository {
-            // Use the helper to evaluate formulas
-            let cell = evaluate_cell_formula(value, repo.clone())?;
-            let new_value = cell.get_computed_value();
+    /// Get a cell from a specific sheet, regardless of which sheet is active
+    pub fn get_cell_in_sheet(&self, sheet_name: &str, address: &CellAddress) -> Option<Cell> {
+        let manager = self.sheet_manager.lock().unwrap();
+        manager
+            .workbook()
+            .get_sheet(sheet_name)?
+            .cells()
+            .get(address)
+    }
 
-            // Store the cell
-            repo.set(address, cell)?;
-
-            // Emit event
-            if let Some(events) = self.container.events() {
-                use crate::ports::event_port::DomainEvent;
-                let event = DomainEvent::CellChanged {
-                    address: *address,
-                    old_value,
-                    new_value,
-                };
-                events.publish(event)?;
-            }
+    /// Set a cell value (handles formulas and regular values). Entering
+    /// nothing deletes the cell, leaving it blank; empty text is entered
+    /// as `'` or computed with `=""`.
+    pub fn set_cell_value(&self, address: &CellAddress, value: &str) -> Result<()> {
+        if value.is_empty() {
+            return self.delete_cell(address);
         }
-
-        Ok(())
+        let value = self.fit_input(address, self.workbook_settings().fit(value)?, value);
+        self.store_cell(address, |context| {
+            evaluate_cell_formula_with(value, context)
+        })?;
+        self.infer_format(address, value)
     }
 
-    /// Delete a cell
-    pub fn delete_cell(&self, address: &CellAddress) -> Result<()> {
-        let old_cell = self.get_cell(address);
-
-        if let Some(repository) = self.container.repository() {
-            repository.delete(address)?;
+    /// Give an unformatted cell the format its input implies: the currency,
+    /// percent or unit it was typed with, or the format shared by the
+    /// numbers a simple aggregate like `=SUM(B2:B9)` reads
+    fn infer_format(&self, address: &CellAddress, value: &str) -> Result<()> {
+        if !self.workbook_settings().infer_formats || self.get_effective_format(address).is_some() {
+            return Ok(());
         }
 
-        // Emit event
-        if let Some(events) = self.container.events() {
-            use crate::ports::event_port::DomainEvent;
-            if let Some(cell) = old_cell {
-                let event = DomainEvent::CellDeleted {
-                    address: *address,
-                    old_value: cell.get_computed_value(),
-                };
-                events.publish(event)?;
+        let inferred = match value.strip_prefix('=') {
+            Some(formula) => {
+                let expr = FormulaParser::parse(formula).ok();
+                let inputs = expr.as_ref().and_then(aggregate_inputs);
+                match (inputs, self.active_repository()) {
+                    _ if let Some(format) = expr.as_ref().and_then(dates::result_format) => {
+                        Some(format)
+                    }
+                    (Some(ranges), Some(repo)) => {
+                        let formats = self.get_formats();
+                        let styles = self.style_registry();
+                        common_format(
+                            ranges
+                                .iter()
+                                .flat_map(|range| repo.get_range(range))
+                                .filter(|(_, cell)| {
+                                    matches!(cell.get_computed_value(), CellValue::Number(_))
+                                })
+                                .map(|(input, _)| {
+                                    formats.effective_format(&input, &styles).cloned()
+                                }),
+                        )
+                        .filter(|format| {
+                            !matches!(format.number_format, NumberFormat::Date { .. })
+                                || !expr.as_ref().is_some_and(dates::counts_days)
+                        })
+                    }
+                    _ => None,
+                }
             }
-        }
+            None => infer_input_format(value),
+        };
 
-        Ok(())
+        match inferred {
+            Some(format) => self.set_cell_format(address, format),
+            None => Ok(()),
+        }
     }
 
-    /// Get cell value as a formatted string
-    pub fn get_cell_value(&self, address: &CellAddress) -> Option<String> {
-        self.get_cell(address)
-            .map(|cell| format_cell_value(cell.get_computed_value()))
+    /// Store `text` as a string, even when it reads like a formula or a number
+    pub fn set_cell_text(&self, address: &CellAddress, text: &str) -> Result<()> {
+        let text = self.fit_input(address, self.workbook_settings().fit_text(text)?, text);
+        self.store_cell(address, |_| {
+            Ok(Cell::new(CellValue::from_string(text.to_string())))
+        })
     }
 
-    /// Get raw cell value
-    pub fn get_cell_raw_value(&self, address: &CellAddress) -> Option<CellValue> {
-        self.get_cell(address).map(|cell| cell.get_computed_value())
+    /// Note `address` as truncated when `kept` is shorter than the `input`
+    /// it was cut from
+    fn fit_input<'a>(&self, address: &CellAddress, kept: &'a str, input: &str) -> &'a str {
+        if kept.len() < input.len() {
+            self.truncated.lock().unwrap().push(*address);
+        }
+        kept
     }
 
-    /// Get all cells
-    pub fn get_all_cells(&self) -> Vec<(CellAddress, Cell)> {
-        self.container
-            .repository()
-            .map(|repo| repo.get_all().into_iter().collect())
-            .unwrap_or_default()
+    /// Cells whose input was cut to the length limits since the last call,
+    /// for the caller to warn about
+    pub fn take_truncated_cells(&self) -> Vec<CellAddress> {
+        std::mem::take(&mut *self.truncated.lock().unwrap())
     }
 
-    /// Get the number of cells
-    pub fn cell_count(&self) -> usize {
-        let manager = self.sheet_manager.lock().unwrap();
-        let active_sheet_name = self.active_sheet.lock().unwrap();
+    /// Length limits and other settings of the workbook
+    pub fn workbook_settings(&self) -> WorkbookSettings {
+        self.sheet_manager
+            .lock()
+            .unwrap()
+            .workbook()
+            .settings()
+            .clone()
+    }
 
-        if let Some(sheet) = manager.workbook().get_sheet(&active_sheet_name) {
-            sheet.cells().count()
-        } else {
-            self.container
-                .repository()
-                .map(|repo| repo.count())
-                .unwrap_or(0)
+    /// Replace the workbook's settings. Changing the deterministic mode
+    /// starts its generator afresh from the new seed.
+    pub fn set_workbook_settings(&self, settings: WorkbookSettings) {
+        let determinism = settings.deterministic;
+        let previous = {
+            let mut manager = self.sheet_manager.lock().unwrap();
+            let previous = manager.workbook().settings().deterministic;
+            manager.workbook_mut().set_settings(settings);
+            previous
+        };
+        if determinism != previous {
+            *self.deterministic.lock().unwrap() =
+                determinism.map(|determinism| Arc::new(DeterministicSource::new(&determinism)));
         }
     }
 
-    /// Recalculate all cells
-    pub fn recalculate(&self) -> Result<()> {
-        if let Some(calc_service) = self.container.calculation_service() {
-            calc_service.recalculate()?;
+    /// Turn deterministic mode on with `determinism`, starting its
+    /// generator from the seed even when it is the one in use, or off
+    pub fn set_determinism(&self, determinism: Option<Determinism>) {
+        {
+            let mut manager = self.sheet_manager.lock().unwrap();
+            let mut settings = manager.workbook().settings().clone();
+            settings.deterministic = determinism;
+            manager.workbook_mut().set_settings(settings);
         }
-        Ok(())
+        *self.deterministic.lock().unwrap() =
+            determinism.map(|determinism| Arc::new(DeterministicSource::new(&determinism)));
     }
 
-    // Sheet management
-
-    /// Get list of all sheets
-    pub fn get_sheets(&self) -> Vec<(String, usize)> {
-        let manager = self.sheet_manager.lock().unwrap();
-        manager
-            .workbook()
-            .sheet_names()
-            .iter()
-            .enumerate()
-            .map(|(idx, name)| (name.clone(), idx))
-            .collect()
+    /// Where deterministic mode stands now: the seed that draws the numbers
+    /// still to come and the frozen time, or `None` when it is off.
+    /// Recordings keep it, so a replay started from it draws the same.
+    pub fn determinism_state(&self) -> Option<Determinism> {
+        self.deterministic_source()
+            .map(|source| source.determinism())
     }
 
-    /// Get the active sheet name
-    pub fn get_active_sheet(&self) -> String {
-        self.active_sheet.lock().unwrap().clone()
+    fn deterministic_source(&self) -> Option<Arc<DeterministicSource>> {
+        self.deterministic.lock().unwrap().clone()
     }
 
-    /// Set the active sheet
-    pub fn set_active_sheet(&self, sheet_name: &str) -> Result<()> {
-        let manager = self.sheet_manager.lock().unwrap();
-        if manager.workbook().get_sheet(sheet_name).is_some() {
-            *self.active_sheet.lock().unwrap() = sheet_name.to_string();
-            Ok(())
-        } else {
-            Err(crate::SpreadsheetError::InvalidOperation(format!(
-                "Sheet '{}' does not exist",
-                sheet_name
-            )))
-        }
+    /// A copy of the deterministic source for evaluations nothing is kept
+    /// from, so they leave the numbers to come as they are
+    fn deterministic_fork(&self) -> Option<Arc<DeterministicSource>> {
+        self.deterministic_source()
+            .map(|source| Arc::new(source.fork()))
     }
 
-    /// Add a new sheet
-    pub fn add_sheet(&self, name: &str) -> Result<()> {
+    /// Display formulas in `style`; they are stored in A1 notation either
+    /// way
+    pub fn set_reference_style(&self, style: ReferenceStyle) {
         let mut manager = self.sheet_manager.lock().unwrap();
-        let sheet = Sheet::new(name);
-        manager.workbook_mut().add_sheet(sheet)
+        let mut settings = manager.workbook().settings().clone();
+        settings.reference_style = style;
+        manager.workbook_mut().set_settings(settings);
     }
 
-    /// Remove a sheet
-    pub fn remove_sheet(&self, name: &str) -> Result<()> {
-        let mut manager = self.sheet_manager.lock().unwrap();
+    /// A cell's formula, with its `=`, as displayed in the workbook's
+    /// reference style. Formulas that do not parse are shown as stored.
+    pub fn get_formula_display(&self, address: &CellAddress) -> Option<String> {
+        let cell = self.get_cell(address)?;
+        let formula = cell.formula_text.as_deref()?;
+        let style = self.workbook_settings().reference_style;
+        let displayed = match (style, FormulaParser::parse(formula)) {
+            (ReferenceStyle::R1C1, Ok(ast)) => {
+                FormulaTransformer::new().display(&ast, style, address)
+            }
+            _ => formula.to_string(),
+        };
+        Some(format!("={}", displayed))
+    }
 
-        // Don't allow removing the last sheet
-        if manager.workbook().sheet_count() <= 1 {
-            return Err(crate::SpreadsheetError::InvalidOperation(
-                "Cannot remove the last sheet".to_string(),
-            ));
-        }
+    /// Title, author and the like, and the sidecar version the document
+    /// was loaded from
+    pub fn workbook_metadata(&self) -> WorkbookMetadata {
+        self.sheet_manager
+            .lock()
+            .unwrap()
+            .workbook()
+            .metadata()
+            .clone()
+    }
+
+    fn store_cell(
+        &self,
+        address: &CellAddress,
+        build: impl FnOnce(&mut PortContext) -> Result<Cell>,
+    ) -> Result<()> {
+        let old_cell = self.get_cell(address);
+        let spilled;
+
+        {
+            // Get the repository for the active sheet
+            let manager = self.sheet_manager.lock().unwrap();
+            let active_sheet_name = self.active_sheet.lock().unwrap();
+            let sheet = manager.workbook().get_sheet(&active_sheet_name);
+
+            let repository = match sheet {
+                Some(sheet) => Some(sheet.cells()),
+                None => self.container.repository(),
+            };
+
+            let Some(repo) = repository else {
+                return Ok(());
+            };
 
-        // If removing the active sheet, switch to another one
-        if self.get_active_sheet() == name {
-            let sheets = manager.workbook().sheet_names();
-            for sheet_name in sheets {
-                if sheet_name != name {
-                    self.set_active_sheet(sheet_name)?;
-                    break;
+            // The new text replaces whatever external data the old formula read
+            let external_cell = ExternalCell::new(active_sheet_name.as_str(), *address);
+            self.external.lock().unwrap().forget_cell(&external_cell);
+
+            // Use the helper to evaluate formulas
+            let names = Arc::new(
+                manager
+                    .workbook()
+                    .visible_names(&NameScope::Sheet(active_sheet_name.clone())),
+            );
+            let mut context = PortContext::new(repo.clone())
+                .with_external(self.external.clone(), external_cell)
+                .with_names(names.clone())
+                .with_deterministic(self.deterministic_source())
+                .with_sheets(sheet_repositories(manager.workbook()));
+            let mut cell = build(&mut context)?;
+            self.cross_sheet.lock().unwrap().set_reads(
+                &active_sheet_name,
+                *address,
+                sheet_cell_references(&cell, manager.workbook()),
+            );
+
+            let graph = sheet.map(|sheet| sheet.dependencies());
+            if let Some(graph) = &graph {
+                let mut graph = graph.lock().unwrap();
+                // A formula reading stale cells is as stale as they are
+                let references = cell_references(&cell, &names);
+                let stale = references.iter().any(|cell| graph.is_dirty(cell));
+                graph.set_dependencies(*address, references);
+                graph.set_dirty(*address, stale);
+                graph.set_volatile(*address, calls_volatile(&cell));
+                // A formula closing a cycle has no value; it shows the cycle
+                if let Some(cells) = graph.find_cycle(address) {
+                    cell.set_error_from(&SpreadsheetError::CircularDependencyWith(cells));
                 }
             }
+
+            spilled = self.settle_spill(&repo, graph.as_deref(), address, Some(&mut cell))?;
+
+            // Store the cell
+            repo.set(address, cell.clone())?;
+            self.publish_change(address, old_cell.as_ref(), &cell)?;
         }
 
-        manager.workbook_mut().remove_sheet(name)?;
-        Ok(())
+        if self.note_batched_write(address) {
+            return Ok(());
+        }
+        self.recalculate_dependents(address, &spilled)
     }
 
-    /// Rename a sheet
-    pub fn rename_sheet(&self, old_name: &str, new_name: &str) -> Result<()> {
-        let mut manager = self.sheet_manager.lock().unwrap();
-        manager.workbook_mut().rename_sheet(old_name, new_name)?;
+    /// Delete a cell
+    pub fn delete_cell(&self, address: &CellAddress) -> Result<()> {
+        let old_cell = self.get_cell(address);
+        self.forget_external_cell(address);
 
-        // Update active sheet if it was renamed
-        if self.get_active_sheet() == old_name {
-            *self.active_sheet.lock().unwrap() = new_name.to_string();
+        let graph = self.active_graph();
+        let mut spilled = Vec::new();
+        if let Some(repository) = self.active_repository() {
+            spilled = self.settle_spill(&repository, graph.as_deref(), address, None)?;
+            repository.delete(address)?;
+        }
+        if let Some(graph) = &graph {
+            let mut graph = graph.lock().unwrap();
+            graph.set_dependencies(*address, []);
+            graph.set_dirty(*address, false);
+            graph.set_volatile(*address, false);
+        }
+        self.cross_sheet
+            .lock()
+            .unwrap()
+            .set_reads(&self.get_active_sheet(), *address, []);
+
+        match old_cell {
+            Some(cell) => {
+                self.publish_deletion(address, &cell)?;
+                if !self.note_batched_write(address) {
+                    self.recalculate_dependents(address, &spilled)?;
+                }
+                self.count_freed_cell();
+                Ok(())
+            }
+            None => Ok(()),
         }
+    }
 
+    /// Record a change in the open batch and publish it
+    fn publish_change(&self, address: &CellAddress, old: Option<&Cell>, new: &Cell) -> Result<()> {
+        self.range_stats.lock().unwrap().invalidate(address);
+        self.batches
+            .lock()
+            .unwrap()
+            .record(*address, old, Some(new));
+        if let Some(events) = self.container.events() {
+            events.publish(DomainEvent::CellChanged {
+                address: *address,
+                old_value: old.map(|cell| cell.get_computed_value()),
+                old_formula: old.and_then(formula_of),
+                new_value: new.get_computed_value(),
+                new_formula: formula_of(new),
+            })?;
+        }
         Ok(())
     }
 
-    /// Get the number of sheets
-    pub fn sheet_count(&self) -> usize {
-        let manager = self.sheet_manager.lock().unwrap();
-        manager.workbook().sheet_count()
+    fn publish_deletion(&self, address: &CellAddress, old: &Cell) -> Result<()> {
+        self.range_stats.lock().unwrap().invalidate(address);
+        self.batches
+            .lock()
+            .unwrap()
+            .record(*address, Some(old), None);
+        if let Some(events) = self.container.events() {
+            events.publish(DomainEvent::CellDeleted {
+                address: *address,
+                old_value: old.get_computed_value(),
+                old_formula: formula_of(old),
+            })?;
+        }
+        Ok(())
     }
 
-    // Command system compatibility methods
-    // These are thin wrappers that just call the main methods
+    /// Spill the array `cell` evaluated to over the cells right of and
+    /// below `address`, or remove what `address` spilled before when there
+    /// is no array or no cell. An array kept from its area by data there,
+    /// or covering a cell its own formula reads, turns the cell into an
+    /// error instead. Spilled cells read their anchor in `graph` and are
+    /// published like edits. Returns the spilled cells written or removed.
+    fn settle_spill(
+        &self,
+        repository: &Arc<dyn RepositoryPort>,
+        graph: Option<&Mutex<DependencyGraph>>,
+        address: &CellAddress,
+        mut cell: Option<&mut Cell>,
+    ) -> Result<Vec<CellAddress>> {
+        if let Some(cell) = cell.as_deref_mut()
+            && let Some(array) = cell.spill.clone()
+            && let Some(graph) = graph
+        {
+            let area = CellRange::new(
+                *address,
+                CellAddress::new(
+                    address.col + array.cols() as u32 - 1,
+                    address.row + array.rows() as u32 - 1,
+                ),
+            );
+            let read = graph
+                .lock()
+                .unwrap()
+                .get_dependencies(address)
+                .into_iter()
+                .find(|precedent| precedent != address && area.contains(precedent));
+            if let Some(read) = read {
+                let cycle = vec![*address, read, *address];
+                cell.set_error_from(&SpreadsheetError::CircularDependencyWith(cycle));
+            }
+        }
 
-    /// Set cell value without command (for command system)
-    pub fn set_cell_value_without_command(&self, address: &CellAddress, value: &str) -> Result<()> {
-        self.set_cell_value(address, value)
-    }
+        let array = cell.as_deref().and_then(|cell| cell.spill.clone());
+        let outcome = repository.spill(address, array.as_deref());
+        if let Some(cell) = cell
+            && !outcome.blocked_by.is_empty()
+        {
+            cell.set_error_from(&SpreadsheetError::SpillBlocked(outcome.blocked_by));
+        }
 
-    /// Delete cell without command (for command system)
-    pub fn delete_cell_without_command(&self, address: &CellAddress) -> Result<()> {
-        self.delete_cell(address)
+        let mut changed = Vec::with_capacity(outcome.changed.len());
+        for (spilled, old) in outcome.changed {
+            let new = repository.get(&spilled);
+            if let Some(graph) = graph {
+                let anchor = new.is_some().then_some(*address);
+                graph.lock().unwrap().set_dependencies(spilled, anchor);
+            }
+            match (&old, &new) {
+                (_, Some(new)) => self.publish_change(&spilled, old.as_ref(), new)?,
+                (Some(old), None) => self.publish_deletion(&spilled, old)?,
+                (None, None) => {}
+            }
+            changed.push(spilled);
+        }
+        Ok(changed)
     }
 
-    /// Insert row without command (placeholder)
-    pub fn insert_row_without_command(&self, _index: u32) -> Result<()> {
-        // Use structural operations service when available
-        if let Some(structural_ops) = self.container.structural_operations() {
-            structural_ops.insert_rows(_index, 1)?;
+    // Batches
+
+    /// Start grouping changes into a batch and return its id. Calls made
+    /// while a batch is open join it, and the batch is committed when the
+    /// outermost caller commits.
+    pub fn begin_batch(&self) -> Result<String> {
+        let (batch_id, opened) = self.batches.lock().unwrap().begin();
+        if opened && let Some(events) = self.container.events() {
+            events.publish(DomainEvent::BatchStarted {
+                batch_id: batch_id.clone(),
+            })?;
         }
-        Ok(())
+        Ok(batch_id)
     }
 
-    /// Delete row without command (placeholder)
-    pub fn delete_row_without_command(&self, _index: u32) -> Result<()> {
-        // Use structural operations service when available
-        if let Some(structural_ops) = self.container.structural_operations() {
-            structural_ops.delete_rows(_index, 1)?;
+    /// Close a batch. The outermost commit publishes `BatchCommitted` with
+    /// the old and new state of every changed cell, or a summary when more
+    /// than [`BATCH_DELTA_INLINE_LIMIT`](crate::ports::event_port::BATCH_DELTA_INLINE_LIMIT)
+    /// cells changed.
+    pub fn commit_batch(&self, batch_id: &str) -> Result<()> {
+        let delta = self.batches.lock().unwrap().commit(batch_id)?;
+        if let Some(delta) = delta
+            && let Some(events) = self.container.events()
+        {
+            events.publish(DomainEvent::BatchCommitted {
+                batch_id: batch_id.to_string(),
+                delta,
+            })?;
+        }
+        if !self.batches.lock().unwrap().is_open() {
+            self.auto_compact_if_due();
         }
         Ok(())
     }
 
-    /// Insert column without command (placeholder)
-    pub fn insert_column_without_command(&self, _index: u32) -> Result<()> {
-        // Use structural operations service when available
-        if let Some(structural_ops) = self.container.structural_operations() {
-            structural_ops.insert_columns(_index, 1)?;
-        }
-        Ok(())
+    /// Every change of a recent batch whose commit event carried only a
+    /// summary
+    pub fn fetch_batch_delta(&self, batch_id: &str) -> Option<Arc<Vec<CellDelta>>> {
+        self.batches.lock().unwrap().fetch(batch_id)
     }
 
-    /// Delete column without command (placeholder)
-    pub fn delete_column_without_command(&self, _index: u32) -> Result<()> {
-        // Use structural operations service when available
-        if let Some(structural_ops) = self.container.structural_operations() {
-            structural_ops.delete_columns(_index, 1)?;
+    /// Start a structural batch on the active sheet. Until it is committed,
+    /// rows and columns inserted and deleted there only move its cells:
+    /// formulas are adjusted, the dependency graph patched and the sheet
+    /// recalculated once, on commit. Cells are written where they are at
+    /// the time, and formulas read the rows and columns of that time, so
+    /// loading data with many insertions costs one adjustment pass instead
+    /// of one per insertion. Cells do not recalculate while it is open.
+    pub fn begin_structural_batch(&self) -> Result<()> {
+        let mut batch = self.structural_batch.lock().unwrap();
+        if batch.is_some() {
+            return Err(SpreadsheetError::InvalidOperation(
+                "A structural batch is already open".to_string(),
+            ));
         }
+        let before = {
+            let manager = self.sheet_manager.lock().unwrap();
+            let workbook = manager.workbook();
+            workbook
+                .sheet_names()
+                .iter()
+                .filter_map(|name| {
+                    Some((name.clone(), workbook.get_sheet(name)?.cells().get_all()))
+                })
+                .collect()
+        };
+        *batch = Some(StructuralBatch {
+            sheet: self.get_active_sheet(),
+            operations: Vec::new(),
+            written: Vec::new(),
+            before,
+        });
         Ok(())
     }
-}
 
-impl Default for SpreadsheetFacade {
-    fn default() -> Self {
-        Self::new()
+    pub fn is_structural_batch_open(&self) -> bool {
+        self.structural_batch.lock().unwrap().is_some()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::adapters::{EventAdapter, RepositoryAdapter};
+    /// Adjust every formula for the batch's insertions and deletions as if
+    /// each had been applied on its own, then repair the dependency graph
+    /// and recalculate once
+    pub fn commit_structural_batch(&self) -> Result<()> {
+        let batch = self.take_structural_batch()?;
+        self.sheet_manager.lock().unwrap().apply_structural_batch(
+            &batch.sheet,
+            &batch.operations,
+            &batch.written,
+        )?;
+        self.settle_structural_batch(&batch.sheet)
+    }
 
-    #[test]
-    fn test_facade_creation() {
-        let repository = Arc::new(RepositoryAdapter::new_empty());
-        let events = Arc::new(EventAdapter::new_empty());
+    /// Put every sheet's cells back as they were when the batch began
+    pub fn rollback_structural_batch(&self) -> Result<()> {
+        let batch = self.take_structural_batch()?;
+        self.range_stats.lock().unwrap().clear();
+        {
+            let manager = self.sheet_manager.lock().unwrap();
+            for (name, cells) in batch.before {
+                let Some(sheet) = manager.workbook().get_sheet(&name) else {
+                    continue;
+                };
+                let repository = sheet.cells();
+                repository.clear()?;
+                for (address, cell) in cells {
+                    repository.set(&address, cell)?;
+                }
+            }
+        }
+        self.settle_structural_batch(&batch.sheet)
+    }
 
-        let facade = SpreadsheetFacade::with_ports(
-            repository as Arc<dyn RepositoryPort>,
-            events as Arc<dyn EventPort>,
-        );
+    fn take_structural_batch(&self) -> Result<StructuralBatch> {
+        self.structural_batch.lock().unwrap().take().ok_or_else(|| {
+            SpreadsheetError::InvalidOperation("No structural batch is open".to_string())
+        })
+    }
 
-        assert_eq!(facade.cell_count(), 0);
+    /// Repair and recalculate the sheet a structural batch changed, even
+    /// when another one is active by now
+    fn settle_structural_batch(&self, sheet: &str) -> Result<()> {
+        let active = std::mem::replace(&mut *self.active_sheet.lock().unwrap(), sheet.to_string());
+        let settled = self.repair_dependencies().and_then(|_| self.recalculate());
+        *self.active_sheet.lock().unwrap() = active;
+        settled?;
+        self.settle_sheet_readers(sheet)
     }
 
-    #[test]
-    fn test_facade_get_cell() {
-        let facade = SpreadsheetFacade::new();
-        let address = CellAddress::new(0, 0);
-        assert!(facade.get_cell(&address).is_none());
+    /// Note a cell written on the active sheet in the open structural
+    /// batch. Returns whether one is open.
+    fn note_batched_write(&self, address: &CellAddress) -> bool {
+        let mut batch = self.structural_batch.lock().unwrap();
+        let Some(batch) = batch.as_mut() else {
+            return false;
+        };
+        let sheet = self.get_active_sheet();
+        batch
+            .written
+            .push((batch.operations.len(), sheet, *address));
+        true
     }
 
-    #[test]
-    fn test_sheet_management() {
-        let facade = SpreadsheetFacade::new();
+    // Memory
 
-        // Should start with one sheet
-        assert_eq!(facade.sheet_count(), 1);
+    /// Estimated bytes each subsystem holds, spare capacity included
+    pub fn memory_report(&self) -> MemoryReport {
+        self.measure_memory(None)
+    }
+
+    /// Give back the capacity collections kept after they were emptied,
+    /// drop caches that are rebuilt on demand and trim the batch history to
+    /// `options.history_budget`. Cells, values and formats are untouched.
+    pub fn compact(&self, options: &CompactOptions) -> MemoryReport {
+        self.auto_compact.lock().unwrap().freed = 0;
+        self.measure_memory(Some(options))
+    }
+
+    /// Compact once at least `threshold` cells were deleted since the last
+    /// compaction, checked after each deletion outside a batch and when a
+    /// batch commits. `None`, the default, turns it off.
+    pub fn set_auto_compact(&self, threshold: Option<usize>) {
+        let mut auto = self.auto_compact.lock().unwrap();
+        auto.threshold = threshold;
+        auto.freed = 0;
+    }
+
+    fn count_freed_cell(&self) {
+        self.auto_compact.lock().unwrap().freed += 1;
+        if !self.batches.lock().unwrap().is_open() {
+            self.auto_compact_if_due();
+        }
+    }
+
+    fn auto_compact_if_due(&self) {
+        let due = {
+            let auto = self.auto_compact.lock().unwrap();
+            auto.threshold
+                .is_some_and(|threshold| auto.freed >= threshold)
+        };
+        if due {
+            self.compact(&CompactOptions::default());
+        }
+    }
+
+    /// Sizes of every subsystem, compacting each in between when `compact`
+    /// is given
+    fn measure_memory(&self, compact: Option<&CompactOptions>) -> MemoryReport {
+        let compacting = compact.is_some();
+        let mut report = MemoryReport::new();
+        let (repositories, graphs) = {
+            let mut manager = self.sheet_manager.lock().unwrap();
+            let workbook = manager.workbook_mut();
+            let mut repositories = Vec::new();
+            let mut graphs = Vec::new();
+            for name in workbook.sheet_names().to_vec() {
+                let Some(sheet) = workbook.get_sheet_mut(&name) else {
+                    continue;
+                };
+                measure(
+                    &mut report,
+                    memory::FORMATS,
+                    sheet.formats_mut(),
+                    compacting,
+                );
+                repositories.push(sheet.cells());
+                graphs.push(sheet.dependencies());
+            }
+            (repositories, graphs)
+        };
+
+        for repository in repositories {
+            let before = repository.memory_footprint();
+            if compacting {
+                repository.compact();
+            }
+            for ((name, before), (_, after)) in
+                before.into_iter().zip(repository.memory_footprint())
+            {
+                report.record(name, before, after);
+            }
+        }
+        for graph in graphs {
+            measure(
+                &mut report,
+                memory::DEPENDENCIES,
+                &mut *graph.lock().unwrap(),
+                compacting,
+            );
+        }
+        measure(
+            &mut report,
+            memory::EXTERNAL_DATA,
+            &mut *self.external.lock().unwrap(),
+            compacting,
+        );
+
+        {
+            let mut batches = self.batches.lock().unwrap();
+            let before = batches.footprint_bytes();
+            if let Some(options) = compact {
+                batches.trim_history(options.history_budget);
+                batches.compact();
+            }
+            report.record(memory::BATCH_HISTORY, before, batches.footprint_bytes());
+        }
+
+        {
+            let mut density = self.density_cache.lock().unwrap();
+            let bytes = |cache: &Option<DensityCache>| {
+                cache.as_ref().map_or(0, |cache| {
+                    size_of::<DensityMap>()
+                        + cache.map.blocks.capacity() * size_of::<DensityBlock>()
+                })
+            };
+            let before = bytes(&density);
+            if compacting {
+                *density = None;
+            }
+            report.record(memory::CACHES, before, bytes(&density));
+        }
+        measure(
+            &mut report,
+            memory::CACHES,
+            &mut *self.mixed_format_cache.lock().unwrap(),
+            compacting,
+        );
+        report
+    }
+
+    /// Get cell value as a formatted string
+    pub fn get_cell_value(&self, address: &CellAddress) -> Option<String> {
+        self.get_cell(address)
+            .map(|cell| format_cell_value(cell.get_computed_value()))
+    }
+
+    /// Get a cell's value rendered with its effective format
+    pub fn get_display_value(&self, address: &CellAddress) -> Option<String> {
+        let value = self.get_cell(address)?.get_computed_value();
+        Some(match self.get_effective_format(address) {
+            Some(format) => format.format_value(&value),
+            None => format_cell_value(value),
+        })
+    }
+
+    /// Get raw cell value
+    pub fn get_cell_raw_value(&self, address: &CellAddress) -> Option<CellValue> {
+        self.get_cell(address).map(|cell| cell.get_computed_value())
+    }
+
+    /// Get all cells
+    pub fn get_all_cells(&self) -> Vec<(CellAddress, Cell)> {
+        self.active_repository()
+            .map(|repo| repo.get_all().into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Every cell of a sheet, empty if there is no sheet by that name
+    pub fn get_all_cells_in_sheet(&self, sheet_name: &str) -> Vec<(CellAddress, Cell)> {
+        let manager = self.sheet_manager.lock().unwrap();
+        manager
+            .workbook()
+            .get_sheet(sheet_name)
+            .map(|sheet| sheet.cells().get_all().into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the number of cells
+    pub fn cell_count(&self) -> usize {
+        let manager = self.sheet_manager.lock().unwrap();
+        let active_sheet_name = self.active_sheet.lock().unwrap();
+
+        if let Some(sheet) = manager.workbook().get_sheet(&active_sheet_name) {
+            sheet.cells().count()
+        } else {
+            self.container
+                .repository()
+                .map(|repo| repo.count())
+                .unwrap_or(0)
+        }
+    }
+
+    /// Number of formulas recalculations re-evaluated so far. Entering a
+    /// formula evaluates it without counting.
+    pub fn evaluation_count(&self) -> u64 {
+        self.evaluations.load(Ordering::Relaxed)
+    }
+
+    /// Recalculate all cells, the formulas of the active sheet in the
+    /// order of its dependency graph, then the formulas on other sheets
+    /// reading those that changed
+    pub fn recalculate(&self) -> Result<()> {
+        if let Some(calc_service) = self.container.calculation_service() {
+            calc_service.recalculate()?;
+        }
+        let (Some(graph), Some(repository)) = (self.active_graph(), self.active_repository())
+        else {
+            return Ok(());
+        };
+
+        let (mut order, cyclic) = match graph.lock().unwrap().get_calculation_order() {
+            Ok(order) => (order, false),
+            Err(_) => (Vec::new(), true),
+        };
+        // The graph orders the cells formulas read too; only formulas count
+        let formulas: HashSet<CellAddress> = repository
+            .get_all()
+            .into_iter()
+            .filter(|(_, cell)| cell.has_formula())
+            .map(|(address, _)| address)
+            .collect();
+        order.retain(|address| formulas.contains(address));
+        let ordered: HashSet<CellAddress> = order.iter().copied().collect();
+        let mut rest: Vec<CellAddress> = formulas
+            .into_iter()
+            .filter(|address| !ordered.contains(address))
+            .collect();
+        rest.sort_by_key(|address| (address.row, address.col));
+        order.extend(rest);
+        // A cycle has no order; following dependents from every formula in
+        // reading order still orders everything around it
+        if cyclic {
+            order = graph.lock().unwrap().recalculation_order(&order);
+        }
+
+        let sheet_name = self.get_active_sheet();
+        let changed = self.recalculate_cells(&sheet_name, &order)?;
+        // A cancelled recalculation leaves what it did not reach stale
+        if !self.recalculation.cancelled() {
+            let mut graph = graph.lock().unwrap();
+            for address in graph.dirty_cells() {
+                graph.set_dirty(address, false);
+            }
+        }
+        self.recalculate_sheet_readers(&sheet_name, &changed)
+    }
+
+    /// Recalculate the stale formulas of a sheet, whether or not its
+    /// automatic calculation is on
+    pub fn recalculate_sheet(&self, sheet_name: &str) -> Result<Recalculation> {
+        let graph = self
+            .sheet_graph(sheet_name)
+            .ok_or_else(|| sheet_not_found(sheet_name))?;
+        self.recalculate_scope(sheet_name, &graph, |_| true)
+    }
+
+    /// Recalculate the stale formulas inside `range` of the active sheet.
+    /// Those reading stale formulas outside the range are evaluated from
+    /// the values there and reported in [`Recalculation::stale`].
+    pub fn recalculate_range(&self, range: &CellRange) -> Result<Recalculation> {
+        let sheet_name = self.get_active_sheet();
+        let graph = self
+            .sheet_graph(&sheet_name)
+            .ok_or_else(|| sheet_not_found(&sheet_name))?;
+        self.recalculate_scope(&sheet_name, &graph, |address| range.contains(address))
+    }
+
+    fn recalculate_scope(
+        &self,
+        sheet_name: &str,
+        graph: &Arc<Mutex<DependencyGraph>>,
+        in_scope: impl Fn(&CellAddress) -> bool,
+    ) -> Result<Recalculation> {
+        let scoped = graph.lock().unwrap().dirty_in_scope(in_scope);
+        let order: Vec<CellAddress> = scoped.iter().map(|&(address, _)| address).collect();
+        self.recalculate_cells(sheet_name, &order)?;
+
+        // Only a cancelled recalculation leaves formulas of the order stale
+        let mut graph = graph.lock().unwrap();
+        let recalculated: Vec<CellAddress> = order
+            .into_iter()
+            .filter(|address| !graph.is_dirty(address))
+            .collect();
+        let mut stale = Vec::new();
+        for (address, reads_stale) in scoped {
+            if reads_stale {
+                graph.set_dirty(address, true);
+                stale.push(address);
+            }
+        }
+        Ok(Recalculation {
+            recalculated,
+            stale,
+            cancelled: self.recalculation.cancelled(),
+        })
+    }
+
+    /// Turn a sheet's automatic calculation on or off. Turning it back on
+    /// recalculates the formulas that went stale meanwhile.
+    pub fn set_calculation_enabled(
+        &self,
+        sheet_name: &str,
+        enabled: bool,
+    ) -> Result<Recalculation> {
+        {
+            let mut manager = self.sheet_manager.lock().unwrap();
+            let sheet = manager
+                .workbook_mut()
+                .get_sheet_mut(sheet_name)
+                .ok_or_else(|| sheet_not_found(sheet_name))?;
+            sheet.properties_mut().calculation_enabled = enabled;
+        }
+        if enabled {
+            self.recalculate_sheet(sheet_name)
+        } else {
+            Ok(Recalculation::default())
+        }
+    }
+
+    /// Whether edits on a sheet recalculate its formulas
+    pub fn is_calculation_enabled(&self, sheet_name: &str) -> bool {
+        let manager = self.sheet_manager.lock().unwrap();
+        manager
+            .workbook()
+            .get_sheet(sheet_name)
+            .is_none_or(|sheet| sheet.properties().calculation_enabled)
+    }
+
+    /// Whether a formula of the active sheet waits for a recalculation
+    pub fn is_stale(&self, address: &CellAddress) -> bool {
+        self.active_graph()
+            .is_some_and(|graph| graph.lock().unwrap().is_dirty(address))
+    }
+
+    /// Formulas of a sheet waiting for a recalculation, in reading order.
+    /// While a reported recalculation runs, these include the formulas it
+    /// has yet to evaluate.
+    pub fn stale_cells(&self, sheet_name: &str) -> Vec<CellAddress> {
+        self.sheet_graph(sheet_name)
+            .map(|graph| graph.lock().unwrap().dirty_cells())
+            .unwrap_or_default()
+    }
+
+    /// Handle on the progress of recalculations, which also cancels them
+    pub fn recalculation_monitor(&self) -> RecalculationMonitor {
+        self.recalculation.clone()
+    }
+
+    /// Change how large a recalculation must be to publish progress
+    /// events, and how often it does
+    pub fn set_progress_cadence(&self, cadence: ProgressCadence) {
+        *self.progress_cadence.lock().unwrap() = cadence;
+    }
+
+    /// Time recalculations with `clock` instead of the wall clock
+    pub fn set_clock(&self, clock: Clock) {
+        *self.clock.lock().unwrap() = clock;
+    }
+
+    // Dependency graph
+
+    /// Compare the active sheet's dependency graph with the references its
+    /// formulas make, re-derived from the formula text
+    pub fn verify_dependencies(&self) -> DependencyReport {
+        match self.sheet_context(&self.get_active_sheet()) {
+            Some((repository, names)) => match self.active_graph() {
+                Some(graph) => graph
+                    .lock()
+                    .unwrap()
+                    .verify(&formula_references(repository.as_ref(), &names)),
+                None => DependencyReport::default(),
+            },
+            None => DependencyReport::default(),
+        }
+    }
+
+    /// Rebuild the graph entries [`Self::verify_dependencies`] finds wrong
+    /// and recalculate the formulas they affect. Returns what was found.
+    pub fn repair_dependencies(&self) -> Result<DependencyReport> {
+        let (Some(graph), Some((repository, names))) = (
+            self.active_graph(),
+            self.sheet_context(&self.get_active_sheet()),
+        ) else {
+            return Ok(DependencyReport::default());
+        };
+        let (report, order) = {
+            let mut graph = graph.lock().unwrap();
+            let expected = formula_references(repository.as_ref(), &names);
+            let report = graph.verify(&expected);
+            let repaired = graph.repair(&expected, &report);
+            // Structural changes move volatile formulas along with the rest
+            graph.set_volatile_cells(
+                repository
+                    .get_all()
+                    .into_iter()
+                    .filter(|(_, cell)| calls_volatile(cell))
+                    .map(|(address, _)| address),
+            );
+            (report, graph.recalculation_order(&repaired))
+        };
+        self.recalculate_cells(&self.get_active_sheet(), &order)?;
+        Ok(report)
+    }
+
+    /// `cells` and every formula on the active sheet that reads them,
+    /// directly or through other cells, in recalculation order
+    pub fn with_dependents(&self, cells: &[CellAddress]) -> Vec<CellAddress> {
+        match self.active_graph() {
+            Some(graph) => graph.lock().unwrap().recalculation_order(cells),
+            None => cells.to_vec(),
+        }
+    }
+
+    /// Cells the formula at `address` on the active sheet reads, in
+    /// row-major order. A range reference lists each of its cells. With
+    /// `transitive`, also the cells those read, and so on.
+    pub fn get_precedents(&self, address: &CellAddress, transitive: bool) -> Vec<CellAddress> {
+        self.graph_query(|graph| match transitive {
+            true => graph.all_dependencies(address),
+            false => graph.get_dependencies(address),
+        })
+    }
+
+    /// Formulas on the active sheet reading `address`, directly or through
+    /// a range containing it, in row-major order. With `transitive`, also
+    /// the formulas reading those, and so on.
+    pub fn get_dependents(&self, address: &CellAddress, transitive: bool) -> Vec<CellAddress> {
+        self.graph_query(|graph| match transitive {
+            true => graph.all_dependents(address),
+            false => graph.get_dependents(address),
+        })
+    }
+
+    fn graph_query(
+        &self,
+        query: impl FnOnce(&DependencyGraph) -> Vec<CellAddress>,
+    ) -> Vec<CellAddress> {
+        let Some(graph) = self.active_graph() else {
+            return Vec::new();
+        };
+        let mut cells = query(&graph.lock().unwrap());
+        cells.sort_by_key(|address| (address.row, address.col));
+        cells.dedup();
+        cells
+    }
+
+    /// Debug builds check the dependency graph after every structural
+    /// change, since that is where stale edges come from
+    fn debug_check_dependencies(&self) {
+        if cfg!(debug_assertions) {
+            let report = self.verify_dependencies();
+            assert!(
+                report.is_consistent(),
+                "dependency graph out of step after a structural change: {}",
+                report
+            );
+        }
+    }
+
+    /// Dependency graph of the active sheet
+    fn active_graph(&self) -> Option<Arc<Mutex<DependencyGraph>>> {
+        self.sheet_graph(&self.get_active_sheet())
+    }
+
+    fn sheet_graph(&self, sheet_name: &str) -> Option<Arc<Mutex<DependencyGraph>>> {
+        let manager = self.sheet_manager.lock().unwrap();
+        Some(manager.workbook().get_sheet(sheet_name)?.dependencies())
+    }
+
+    /// Re-evaluate the formulas that read `address` on the active sheet,
+    /// directly or through other cells, and the volatile formulas with
+    /// theirs, in dependency order. Formulas that read the cells in
+    /// `spilled` are included, as are the arrays that cover `address` and
+    /// may spill differently now. A sheet whose calculation is off only
+    /// has them marked stale. Formulas on other sheets reading what changed
+    /// follow.
+    fn recalculate_dependents(&self, address: &CellAddress, spilled: &[CellAddress]) -> Result<()> {
+        let Some(graph) = self.active_graph() else {
+            return Ok(());
+        };
+        let anchors = self
+            .active_repository()
+            .map(|repository| repository.spills_over(address))
+            .unwrap_or_default();
+        let order = {
+            let graph = graph.lock().unwrap();
+            let volatile: Vec<CellAddress> = graph
+                .volatile_cells()
+                .into_iter()
+                .filter(|cell| cell != address)
+                .collect();
+            // The edited cell was just evaluated, unless it reads a
+            // volatile formula about to change
+            let reads_volatile = graph.recalculation_order(&volatile).contains(address);
+            let mut starts = vec![*address];
+            starts.extend(anchors);
+            starts.extend_from_slice(spilled);
+            starts.extend(volatile);
+            let mut order = graph.recalculation_order(&starts);
+            if !reads_volatile {
+                order.retain(|cell| cell != address);
+            }
+            order
+        };
+        let sheet_name = self.get_active_sheet();
+        let mut changed = vec![*address];
+        changed.extend_from_slice(spilled);
+        if !order.is_empty() {
+            if self.is_calculation_enabled(&sheet_name) {
+                changed.extend(self.recalculate_cells(&sheet_name, &order)?);
+            } else {
+                graph.lock().unwrap().mark_dirty(order);
+            }
+        }
+        self.recalculate_sheet_readers(&sheet_name, &changed)
+    }
+
+    /// Re-evaluate the formulas on other sheets that read the `changed`
+    /// cells of `sheet_name`, with what reads them on their own sheets and
+    /// so on across sheets. Each formula is evaluated at most once, so
+    /// sheets reading each other in a circle settle.
+    fn recalculate_sheet_readers(&self, sheet_name: &str, changed: &[CellAddress]) -> Result<()> {
+        let readers = self
+            .cross_sheet
+            .lock()
+            .unwrap()
+            .readers(sheet_name, changed);
+        self.recalculate_readers(readers)
+    }
+
+    /// Re-evaluate the formulas in `readers`, by sheet, as
+    /// [`Self::recalculate_sheet_readers`] does
+    fn recalculate_readers(&self, readers: BTreeMap<String, Vec<CellAddress>>) -> Result<()> {
+        let mut visited: HashSet<SheetCell> = HashSet::new();
+        let mut pending: Vec<(String, Vec<CellAddress>)> = readers.into_iter().collect();
+        while let Some((sheet_name, starts)) = pending.pop() {
+            let starts: Vec<CellAddress> = starts
+                .into_iter()
+                .filter(|address| visited.insert((sheet_name.clone(), *address)))
+                .collect();
+            if starts.is_empty() {
+                continue;
+            }
+            let Some(graph) = self.sheet_graph(&sheet_name) else {
+                continue;
+            };
+            let order = graph.lock().unwrap().recalculation_order(&starts);
+            if !self.is_calculation_enabled(&sheet_name) {
+                graph.lock().unwrap().mark_dirty(order);
+                continue;
+            }
+            let changed = self.recalculate_cells(&sheet_name, &order)?;
+            let readers = self
+                .cross_sheet
+                .lock()
+                .unwrap()
+                .readers(&sheet_name, &changed);
+            pending.extend(readers);
+        }
+        Ok(())
+    }
+
+    /// Re-derive which formulas read other sheets, after formulas were
+    /// rewritten or moved wholesale, and recalculate those reading
+    /// `sheet_name`
+    fn settle_sheet_readers(&self, sheet_name: &str) -> Result<()> {
+        {
+            let manager = self.sheet_manager.lock().unwrap();
+            let workbook = manager.workbook();
+            let mut cross_sheet = self.cross_sheet.lock().unwrap();
+            cross_sheet.clear();
+            for name in workbook.sheet_names() {
+                let Some(sheet) = workbook.get_sheet(name) else {
+                    continue;
+                };
+                for (address, cell) in sheet.cells().get_all() {
+                    let reads = sheet_cell_references(&cell, workbook);
+                    if !reads.is_empty() {
+                        cross_sheet.set_reads(name, address, reads);
+                    }
+                }
+            }
+        }
+        let readers = self
+            .cross_sheet
+            .lock()
+            .unwrap()
+            .readers_of_sheet(sheet_name);
+        self.recalculate_readers(readers)
+    }
+
+    /// The cells of other sheets the formula at `address` on the active
+    /// sheet reads, with the sheet names, in order
+    pub fn get_sheet_precedents(&self, address: &CellAddress) -> Vec<SheetCell> {
+        self.cross_sheet
+            .lock()
+            .unwrap()
+            .reads_of(&self.get_active_sheet(), address)
+    }
+
+    // Formatting
+
+    /// Apply an explicit format to a cell, overriding row and column defaults
+    pub fn set_cell_format(&self, address: &CellAddress, format: CellFormat) -> Result<()> {
+        self.with_active_formats(|formats| formats.set_cell_format(*address, format))
+    }
+
+    /// Remove a cell's explicit format and style so it reverts to its
+    /// row/column default
+    pub fn clear_formats(&self, address: &CellAddress) -> Result<()> {
+        self.with_active_formats(|formats| {
+            formats.clear_cell_format(address);
+            formats.set_cell_style(*address, None);
+        })
+    }
+
+    /// Set or clear the default format of a column in the active sheet
+    pub fn set_column_format(&self, column: u32, format: Option<CellFormat>) -> Result<()> {
+        self.with_active_formats(|formats| formats.set_column_format(column, format))
+    }
+
+    /// Set or clear the default format of a row in the active sheet
+    pub fn set_row_format(&self, row: u32, format: Option<CellFormat>) -> Result<()> {
+        self.with_active_formats(|formats| formats.set_row_format(row, format))
+    }
+
+    /// Format that applies to a cell, resolved as cell > cell style > row >
+    /// column > sheet default
+    pub fn get_effective_format(&self, address: &CellAddress) -> Option<CellFormat> {
+        let manager = self.sheet_manager.lock().unwrap();
+        let active_sheet_name = self.active_sheet.lock().unwrap();
+        let workbook = manager.workbook();
+        workbook
+            .get_sheet(&active_sheet_name)?
+            .formats()
+            .effective_format(address, workbook.styles())
+            .cloned()
+    }
+
+    /// Snapshot of all formats in the active sheet
+    pub fn get_formats(&self) -> FormatStore {
+        let manager = self.sheet_manager.lock().unwrap();
+        let active_sheet_name = self.active_sheet.lock().unwrap();
+        manager
+            .workbook()
+            .get_sheet(&active_sheet_name)
+            .map(|sheet| sheet.formats().clone())
+            .unwrap_or_default()
+    }
+
+    fn with_active_formats(&self, update: impl FnOnce(&mut FormatStore)) -> Result<()> {
+        self.with_active_sheet_mut(|sheet| update(sheet.formats_mut()))
+    }
+
+    // Styles
+
+    /// Named styles of the workbook, in gallery order
+    pub fn get_styles(&self) -> Vec<CellStyle> {
+        self.style_registry().iter().cloned().collect()
+    }
+
+    pub fn get_style(&self, name: &str) -> Option<CellStyle> {
+        self.style_registry().get(name).cloned()
+    }
+
+    /// Define a style or change an existing one. Every cell using the style
+    /// shows the new format without being rewritten.
+    pub fn define_style(&self, name: &str, format: CellFormat) -> Result<()> {
+        let mut manager = self.sheet_manager.lock().unwrap();
+        manager.workbook_mut().define_style(name, format)?;
+        Ok(())
+    }
+
+    /// Remove a style, blocking or converting the cells still using it
+    pub fn remove_style(&self, name: &str, removal: StyleRemoval) -> Result<CellStyle> {
+        let mut manager = self.sheet_manager.lock().unwrap();
+        manager.workbook_mut().remove_style(name, removal)
+    }
+
+    /// Make the cells of `range` in the active sheet refer to a style. Their
+    /// explicit formats are dropped so the style shows.
+    pub fn apply_style(&self, range: &CellRange, name: &str) -> Result<()> {
+        let style = self.get_style(name).ok_or_else(|| {
+            SpreadsheetError::InvalidOperation(format!("Style '{}' does not exist", name))
+        })?;
+        self.with_active_formats(|formats| {
+            for address in range.cells() {
+                formats.clear_cell_format(&address);
+                formats.set_cell_style(address, Some(style.name.clone()));
+            }
+        })
+    }
+
+    /// Name of the style a cell in the active sheet refers to
+    pub fn get_cell_style(&self, address: &CellAddress) -> Option<String> {
+        let manager = self.sheet_manager.lock().unwrap();
+        let active_sheet_name = self.active_sheet.lock().unwrap();
+        manager
+            .workbook()
+            .get_sheet(&active_sheet_name)?
+            .formats()
+            .cell_style(address)
+            .map(str::to_string)
+    }
+
+    /// Point a cell of the active sheet at a style, or clear its style
+    pub fn set_cell_style(&self, address: &CellAddress, name: Option<&str>) -> Result<()> {
+        let name = match name {
+            Some(name) => Some(
+                self.get_style(name)
+                    .ok_or_else(|| {
+                        SpreadsheetError::InvalidOperation(format!(
+                            "Style '{}' does not exist",
+                            name
+                        ))
+                    })?
+                    .name,
+            ),
+            None => None,
+        };
+        self.with_active_formats(|formats| formats.set_cell_style(*address, name))
+    }
+
+    fn style_registry(&self) -> StyleRegistry {
+        self.sheet_manager
+            .lock()
+            .unwrap()
+            .workbook()
+            .styles()
+            .clone()
+    }
+
+    // CSV
+
+    /// Export the active sheet as CSV from A1 to its last used cell, with
+    /// formulas as text. With `with_sidecar`, the formats the CSV cannot
+    /// hold come along in a [`Sidecar`]; column widths are left for the
+    /// host to fill in.
+    pub fn export_csv(&self, with_sidecar: bool) -> CsvExport {
+        let mut rows = Vec::new();
+        if let Some(extent) = self.get_used_extent() {
+            let cells: HashMap<CellAddress, Cell> = self.get_all_cells().into_iter().collect();
+            for row in 0..=extent.row {
+                rows.push(
+                    (0..=extent.col)
+                        .map(|col| {
+                            cells
+                                .get(&CellAddress::new(col, row))
+                                .map(cell_field)
+                                .unwrap_or_default()
+                        })
+                        .collect(),
+                );
+            }
+        }
+        CsvExport {
+            csv: write_csv(&rows),
+            sidecar: with_sidecar.then(|| self.csv_sidecar()),
+        }
+    }
+
+    fn csv_sidecar(&self) -> Sidecar {
+        let mut sidecar = self.sidecar_of(&self.get_formats());
+        let manager = self.sheet_manager.lock().unwrap();
+        let active_sheet_name = self.active_sheet.lock().unwrap();
+        if let Some(sheet) = manager.workbook().get_sheet(&active_sheet_name) {
+            sidecar.unknown = sheet.preserved_sections().clone();
+            sidecar.conditional_formats = sheet
+                .conditional_formats()
+                .iter()
+                .map(|rule| SidecarConditionalFormat {
+                    range: rule.range.to_string(),
+                    kind: rule.kind.clone(),
+                })
+                .collect();
+        }
+        sidecar
+    }
+
+    /// Sidecar holding `formats` and the styles they refer to
+    fn sidecar_of(&self, formats: &FormatStore) -> Sidecar {
+        let registry = self.style_registry();
+        let mut sidecar = Sidecar::default();
+        for (address, format) in formats.cell_formats() {
+            sidecar.formats.insert(address.to_a1(), format.clone());
+        }
+        for (address, name) in formats.cell_styles() {
+            sidecar
+                .cell_styles
+                .insert(address.to_a1(), name.to_string());
+        }
+        let used: HashSet<String> = sidecar
+            .cell_styles
+            .values()
+            .map(|name| name.to_ascii_lowercase())
+            .collect();
+        sidecar.styles = registry
+            .iter()
+            .filter(|style| used.contains(&style.name.to_ascii_lowercase()))
+            .cloned()
+            .collect();
+        for (column, format) in formats.column_formats() {
+            sidecar
+                .column_formats
+                .insert(CellAddress::column_number_to_label(column), format.clone());
+        }
+        for (row, format) in formats.row_formats() {
+            sidecar.row_formats.insert(row + 1, format.clone());
+        }
+        sidecar
+    }
+
+    /// Write CSV from [`Self::export_csv`] into the active sheet from A1
+    /// and apply its sidecar, as one batch. Values are written before
+    /// formats, so formulas do not pick up formats from the cells they
+    /// read. Column widths are left to the host. Everything that could not
+    /// be restored is listed in the report.
+    pub fn import_csv(&self, csv: &str, sidecar: Option<&Sidecar>) -> Result<ImportReport> {
+        self.import_csv_with(csv, sidecar, &CsvImportOptions::default())
+    }
+
+    /// [`Self::import_csv_with`] for a sidecar given as JSON text, read as
+    /// [`Sidecar::read`] says
+    pub fn import_csv_document(
+        &self,
+        csv: &str,
+        sidecar_json: Option<&str>,
+        options: &CsvImportOptions,
+    ) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let sidecar = match sidecar_json {
+            Some(json) => Sidecar::read(json, options.mode, &mut report)?,
+            None => None,
+        };
+        report.merge(self.import_csv_with(csv, sidecar.as_ref(), options)?);
+        Ok(report)
+    }
+
+    /// [`Self::import_csv`], reading fields as `options` says. A lenient
+    /// import loads formulas that do not parse as text and keeps sidecar
+    /// sections it does not know; see [`LoadMode`].
+    pub fn import_csv_with(
+        &self,
+        csv: &str,
+        sidecar: Option<&Sidecar>,
+        options: &CsvImportOptions,
+    ) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let batch_id = self.begin_batch()?;
+
+        for (row, fields) in crate::csv::parse_csv(csv).iter().enumerate() {
+            for (col, field) in fields.iter().enumerate() {
+                if field.is_empty() {
+                    continue;
+                }
+                let address = CellAddress::new(col as u32, row as u32);
+                self.import_input(address, field_input(field, options), options, &mut report);
+            }
+        }
+        if let Some(sidecar) = sidecar {
+            self.apply_sidecar(sidecar, options.mode, &mut report);
+        }
+
+        self.commit_batch(&batch_id)?;
+        Ok(report)
+    }
+
+    /// Store the `input` read from an imported field, noting in `report`
+    /// what did not load as written
+    fn import_input(
+        &self,
+        address: CellAddress,
+        mut input: String,
+        options: &CsvImportOptions,
+        report: &mut ImportReport,
+    ) {
+        if let Some(formula) = input.strip_prefix('=')
+            && let Err(e) = FormulaParser::parse(formula)
+        {
+            match options.mode {
+                LoadMode::Strict => {
+                    report.issue(Some(address), format!("formula {} does not parse", input))
+                }
+                LoadMode::Lenient => {
+                    report.formulas_as_text.push(FidelityIssue {
+                        address: Some(address),
+                        message: format!("{} does not parse: {}", input, e),
+                    });
+                    input = format!("'{}", input);
+                }
+            }
+        }
+        match self.set_cell_value(&address, &input) {
+            Ok(()) => report.cells += 1,
+            Err(e) => report.issue(Some(address), e.to_string()),
+        }
+        if !self.take_truncated_cells().is_empty() {
+            report.issue(
+                Some(address),
+                format!(
+                    "text cut to {} characters",
+                    self.workbook_settings().max_text_length
+                ),
+            );
+        }
+    }
+
+    /// Import all of `source`, delimited text, as `options` says; see
+    /// [`crate::csv::import`]. What `options` leaves to detect is detected
+    /// as [`preview_import`] does; columns without a type are read as
+    /// typed. Fields that do not fit the type of their
+    /// column are imported as text and listed in the report. A new sheet
+    /// destination is added and made the active sheet first.
+    pub fn commit_import(&self, source: &[u8], options: &ImportOptions) -> Result<ImportReport> {
+        let detected = preview_import(source, options, IMPORT_DETECTION_ROWS);
+        let anchor = match &options.destination {
+            ImportDestination::Anchor { anchor } => *anchor,
+            ImportDestination::NewSheet { name } => {
+                self.add_sheet(name)?;
+                self.set_active_sheet(name)?;
+                CellAddress::new(0, 0)
+            }
+        };
+
+        // Skipped columns close up
+        let mut targets = Vec::new();
+        let mut next_col = anchor.col;
+        let mut target_col = |col: usize| -> Option<u32> {
+            while targets.len() <= col {
+                let kept = options.column_type(targets.len()) != ColumnType::Skip;
+                targets.push(kept.then_some(next_col));
+                next_col += u32::from(kept);
+            }
+            targets[col]
+        };
+
+        let mut report = ImportReport::default();
+        let batch_id = self.begin_batch()?;
+        let text = detected.encoding.decode(source);
+        for (row, fields) in DelimitedRows::new(&text, detected.delimiter).enumerate() {
+            let header = detected.has_header && row == 0;
+            for (col, field) in fields.iter().enumerate() {
+                let Some(target_col) = target_col(col) else {
+                    continue;
+                };
+                if field.is_empty() {
+                    continue;
+                }
+                let address = CellAddress::new(target_col, anchor.row + row as u32);
+                let column_type = if header {
+                    ColumnType::Text
+                } else {
+                    options.column_type(col)
+                };
+                let input = match column_type.input(field, &options.fields) {
+                    Ok(Some(input)) => input,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        report.issue(Some(address), format!("{}; imported as text", e));
+                        format!("'{}", field)
+                    }
+                };
+                self.import_input(address, input, &options.fields, &mut report);
+            }
+        }
+        self.commit_batch(&batch_id)?;
+        Ok(report)
+    }
+
+    fn apply_sidecar(&self, sidecar: &Sidecar, mode: LoadMode, report: &mut ImportReport) {
+        if sidecar.schema_version > SIDECAR_VERSION {
+            report.issue(
+                None,
+                format!(
+                    "sidecar version {} is newer than {}; some of it may be ignored",
+                    sidecar.schema_version, SIDECAR_VERSION
+                ),
+            );
+        }
+        self.sheet_manager
+            .lock()
+            .unwrap()
+            .workbook_mut()
+            .metadata_mut()
+            .loaded_schema_version = Some(sidecar.migrated_from.unwrap_or(sidecar.schema_version));
+        match mode {
+            LoadMode::Strict => {
+                for name in sidecar.unknown.keys() {
+                    report.issue(None, format!("sidecar section {} is not understood", name));
+                }
+            }
+            LoadMode::Lenient if !sidecar.unknown.is_empty() => {
+                report
+                    .preserved_sections
+                    .extend(sidecar.unknown.keys().cloned());
+                let _ = self.with_active_sheet_mut(|sheet| {
+                    sheet
+                        .preserved_sections_mut()
+                        .extend(sidecar.unknown.clone())
+                });
+            }
+            LoadMode::Lenient => {}
+        }
+        for style in &sidecar.styles {
+            if self.get_style(&style.name).as_ref() != Some(style)
+                && let Err(e) = self.define_style(&style.name, style.format.clone())
+            {
+                report.issue(None, format!("style {}: {}", style.name, e));
+            }
+        }
+
+        let mut cell =
+            |key: &str, apply: &dyn Fn(&CellAddress) -> Result<()>| match CellAddress::from_a1(key)
+            {
+                Ok(address) => {
+                    if let Err(e) = apply(&address) {
+                        report.issue(Some(address), e.to_string());
+                    }
+                }
+                Err(_) => report.issue(None, format!("{} is not a cell address", key)),
+            };
+        for (key, format) in &sidecar.formats {
+            cell(key, &|address| {
+                self.set_cell_format(address, format.clone())
+            });
+        }
+        for (key, name) in &sidecar.cell_styles {
+            cell(key, &|address| self.set_cell_style(address, Some(name)));
+        }
+
+        for (label, format) in &sidecar.column_formats {
+            let applied = CellAddress::column_label_to_number(label)
+                .and_then(|column| self.set_column_format(column, Some(format.clone())));
+            if let Err(e) = applied {
+                report.issue(None, format!("column {}: {}", label, e));
+            }
+        }
+        for (&row, format) in &sidecar.row_formats {
+            let applied = match row.checked_sub(1) {
+                Some(index) => self.set_row_format(index, Some(format.clone())),
+                None => Err(SpreadsheetError::InvalidAddress(
+                    "rows start at 1".to_string(),
+                )),
+            };
+            if let Err(e) = applied {
+                report.issue(None, format!("row {}: {}", row, e));
+            }
+        }
+
+        // Rules the sheet has already, as after importing twice, stay once
+        let existing = self.get_conditional_formats();
+        for format in &sidecar.conditional_formats {
+            let added = CellRange::from_string(&format.range)
+                .map_err(SpreadsheetError::InvalidAddress)
+                .map(|range| ConditionalFormatRule {
+                    range,
+                    kind: format.kind.clone(),
+                })
+                .and_then(|rule| {
+                    if existing.contains(&rule) {
+                        return Ok(());
+                    }
+                    self.add_conditional_format(rule).map(|_| ())
+                });
+            if let Err(e) = added {
+                report.issue(
+                    None,
+                    format!("conditional format on {}: {}", format.range, e),
+                );
+            }
+        }
+    }
+
+    /// Hash of the active sheet's inputs, values and formats, the same for
+    /// sheets with the same content. Values of formulas calling FETCH, and
+    /// of the formulas reading them, are left out, as they change without
+    /// the sheet being edited; so are those of volatile functions unless
+    /// deterministic mode is on.
+    pub fn content_hash(&self) -> u64 {
+        let mut cells: Vec<(CellAddress, Cell)> = self
+            .get_all_cells()
+            .into_iter()
+            .filter(|(_, cell)| !cell.is_empty())
+            .collect();
+        cells.sort_by_key(|(address, _)| (address.row, address.col));
+
+        let deterministic = self.deterministic_source().is_some();
+        let changing: Vec<CellAddress> = cells
+            .iter()
+            .filter(|(_, cell)| {
+                cell.formula_text
+                    .as_deref()
+                    .and_then(|formula| FormulaParser::parse(formula).ok())
+                    .is_some_and(|expr| {
+                        expr.calls(FETCH_FUNCTION) || (!deterministic && expr.is_volatile())
+                    })
+            })
+            .map(|(address, _)| *address)
+            .collect();
+        let volatile: HashSet<CellAddress> = self.with_dependents(&changing).into_iter().collect();
+
+        let mut hasher = FxHasher::default();
+        for (address, cell) in &cells {
+            address.hash(&mut hasher);
+            cell_field(cell).hash(&mut hasher);
+            if !volatile.contains(address) {
+                cell.computed_value.to_string().hash(&mut hasher);
+            }
+        }
+        self.csv_sidecar().to_json().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Hash of what a sheet was given rather than what it computed: its
+    /// inputs, formats and the styles those use. Unlike
+    /// [`content_hash`](Self::content_hash) it does not change when other
+    /// sheets its formulas read are edited. `None` if there is no sheet by
+    /// that name.
+    pub fn sheet_input_hash(&self, sheet_name: &str) -> Option<u64> {
+        let (repository, formats) = {
+            let manager = self.sheet_manager.lock().unwrap();
+            let sheet = manager.workbook().get_sheet(sheet_name)?;
+            (sheet.cells(), sheet.formats().clone())
+        };
+        // Cleared cells may hold an empty text, which saves as nothing
+        let mut cells: Vec<(CellAddress, String)> = repository
+            .get_all()
+            .into_iter()
+            .map(|(address, cell)| (address, cell_field(&cell)))
+            .filter(|(_, field)| !field.is_empty())
+            .collect();
+        cells.sort_by_key(|(address, _)| (address.row, address.col));
+
+        let mut hasher = FxHasher::default();
+        for (address, field) in &cells {
+            address.hash(&mut hasher);
+            field.hash(&mut hasher);
+        }
+        self.sidecar_of(&formats).to_json().hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    // Bulk export
+
+    /// Copy the numbers in column `col` from `start_row` to `end_row`
+    /// (inclusive) into `out`, one slot per row. Blank, text and error cells
+    /// become NaN. At most `out.len()` rows are written; returns how many were.
+    pub fn export_numeric_column(
+        &self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        out: &mut [f64],
+    ) -> usize {
+        let count = export_row_count(start_row, end_row, out.len());
+        out[..count].fill(f64::NAN);
+        if count == 0 {
+            return 0;
+        }
+
+        if let Some(repository) = self.active_repository() {
+            let last_row = start_row + count as u32 - 1;
+            repository.for_each_number_in_column(col, start_row, last_row, &mut |row, n| {
+                out[(row - start_row) as usize] = n;
+            });
+        }
+        count
+    }
+
+    /// Like [`Self::export_numeric_column`] for several columns at once, written
+    /// row-major into `out` so that row `r` of `cols[i]` lands at
+    /// `out[r * cols.len() + i]`. Returns the number of rows written.
+    pub fn export_numeric_columns(
+        &self,
+        cols: &[u32],
+        start_row: u32,
+        end_row: u32,
+        out: &mut [f64],
+    ) -> usize {
+        if cols.is_empty() {
+            return 0;
+        }
+        let stride = cols.len();
+        let count = export_row_count(start_row, end_row, out.len() / stride);
+        out[..count * stride].fill(f64::NAN);
+        if count == 0 {
+            return 0;
+        }
+
+        if let Some(repository) = self.active_repository() {
+            let last_row = start_row + count as u32 - 1;
+            for (i, &col) in cols.iter().enumerate() {
+                repository.for_each_number_in_column(col, start_row, last_row, &mut |row, n| {
+                    out[(row - start_row) as usize * stride + i] = n;
+                });
+            }
+        }
+        count
+    }
+
+    // Overview
+
+    /// Last populated column and row of the active sheet, `None` when the
+    /// sheet is empty
+    pub fn get_used_extent(&self) -> Option<CellAddress> {
+        self.get_all_cells()
+            .iter()
+            .filter(|(_, cell)| !cell.is_empty())
+            .map(|(address, _)| *address)
+            .reduce(|extent, address| {
+                CellAddress::new(extent.col.max(address.col), extent.row.max(address.row))
+            })
+    }
+
+    /// Populated cells of the active sheet counted per `block_size` square
+    /// block. The map is cached until the sheet changes, so asking again for
+    /// an unchanged sheet returns the same `Arc`.
+    pub fn aggregate_density(&self, block_size: u32) -> Arc<DensityMap> {
+        let Some(repository) = self.active_repository() else {
+            return Arc::default();
+        };
+        let revision = repository.revision();
+
+        let mut cache = self.density_cache.lock().unwrap();
+        if let (Some(entry), Some(revision)) = (cache.as_ref(), revision)
+            && Arc::ptr_eq(&entry.repository, &repository)
+            && entry.block_size == block_size
+            && entry.revision == revision
+        {
+            return entry.map.clone();
+        }
+
+        let cells = repository.get_all();
+        let map = Arc::new(DensityMap::build(
+            block_size,
+            cells.iter().map(|(a, c)| (*a, c)),
+        ));
+        if let Some(revision) = revision {
+            *cache = Some(DensityCache {
+                repository,
+                block_size,
+                revision,
+                map: map.clone(),
+            });
+        }
+        map
+    }
+
+    // Charts
+
+    /// Series for a chart of `ranges` in the active sheet. Ranges are clipped
+    /// to the used part of the sheet, so whole rows and columns can be passed.
+    pub fn chart_data(&self, ranges: &[CellRange], max_points: usize) -> Result<ChartData> {
+        let (max_col, max_row) = self
+            .get_all_cells()
+            .iter()
+            .filter(|(_, cell)| !cell.is_empty())
+            .fold((0, 0), |(col, row), (address, _)| {
+                (col.max(address.col), row.max(address.row))
+            });
+        let clipped: Vec<CellRange> = ranges
+            .iter()
+            .filter(|range| range.start.col <= max_col && range.start.row <= max_row)
+            .map(|range| {
+                CellRange::new(
+                    range.start,
+                    CellAddress::new(range.end.col.min(max_col), range.end.row.min(max_row)),
+                )
+            })
+            .collect();
+
+        build_chart_data(&clipped, max_points, |address| {
+            self.get_cell_raw_value(address).unwrap_or_default()
+        })
+    }
+
+    // Linting
+
+    /// Lint the formulas of the active sheet, or only those inside `range`,
+    /// in row-major order. Each call reads the cells as they are now, except
+    /// that mixed format checks are reused for ranges whose cells and
+    /// formats did not change.
+    pub fn lint(&self, range: Option<&CellRange>, settings: &LintSettings) -> Vec<LintFinding> {
+        let cells: HashMap<CellAddress, Cell> = self.get_all_cells().into_iter().collect();
+        let mut formulas: Vec<CellAddress> = cells
+            .iter()
+            .filter(|(address, cell)| {
+                cell.has_formula() && range.is_none_or(|range| range.contains(address))
+            })
+            .map(|(address, _)| *address)
+            .collect();
+        formulas.sort_unstable_by_key(|address| (address.row, address.col));
+        let source = SheetLintSource {
+            sheet: self.get_active_sheet(),
+            revision: self
+                .active_repository()
+                .and_then(|repository| repository.revision()),
+            formats: self.get_formats(),
+            styles: self.style_registry(),
+            cells,
+        };
+        let mut cache = self.mixed_format_cache.lock().unwrap();
+        lint_source(formulas, settings, &source, &mut cache)
+    }
+
+    /// Mixed format checks answered from the cache and checks run
+    pub fn mixed_format_cache_stats(&self) -> (u64, u64) {
+        self.mixed_format_cache.lock().unwrap().stats()
+    }
+
+    // Pivots
+
+    /// Aggregate a range of the active sheet without writing the result
+    pub fn pivot_values(&self, source: &CellRange, config: &PivotConfig) -> Result<PivotTable> {
+        build_pivot(source, config, |address| {
+            self.get_cell_raw_value(address).unwrap_or_default()
+        })
+    }
+
+    /// Write a pivot of `source` as plain values with its top-left corner at
+    /// `anchor`. The configuration is remembered so [`Self::refresh_pivot`]
+    /// can regenerate the output. Returns the range that was written.
+    pub fn pivot_range(
+        &self,
+        source: &CellRange,
+        config: PivotConfig,
+        anchor: &CellAddress,
+    ) -> Result<CellRange> {
+        let previous = self.get_pivot(anchor).map(|pivot| pivot.output);
+        let output = self.write_pivot(source, &config, anchor, previous.as_ref())?;
+
+        self.with_active_sheet_mut(|sheet| {
+            sheet.pivots_mut().insert(
+                *anchor,
+                PivotDefinition {
+                    source: *source,
+                    config,
+                    output,
+                },
+            );
+        })?;
+        Ok(output)
+    }
+
+    /// Regenerate the pivot anchored at `anchor` from the current source
+    /// values, clearing cells the previous output covered but the new one does not
+    pub fn refresh_pivot(&self, anchor: &CellAddress) -> Result<CellRange> {
+        let pivot = self.get_pivot(anchor).ok_or_else(|| {
+            crate::SpreadsheetError::InvalidOperation(format!("No pivot at {}", anchor))
+        })?;
+        let output = self.write_pivot(&pivot.source, &pivot.config, anchor, Some(&pivot.output))?;
+
+        self.with_active_sheet_mut(|sheet| {
+            if let Some(stored) = sheet.pivots_mut().get_mut(anchor) {
+                stored.output = output;
+            }
+        })?;
+        Ok(output)
+    }
+
+    /// The pivot anchored at a cell of the active sheet
+    pub fn get_pivot(&self, anchor: &CellAddress) -> Option<PivotDefinition> {
+        let manager = self.sheet_manager.lock().unwrap();
+        let active_sheet_name = self.active_sheet.lock().unwrap();
+        manager
+            .workbook()
+            .get_sheet(&active_sheet_name)?
+            .pivots()
+            .get(anchor)
+            .cloned()
+    }
+
+    fn write_pivot(
+        &self,
+        source: &CellRange,
+        config: &PivotConfig,
+        anchor: &CellAddress,
+        previous: Option<&CellRange>,
+    ) -> Result<CellRange> {
+        let table = self.pivot_values(source, config)?;
+        let output = table.output_range(anchor);
+        if ranges_overlap(&output, source) {
+            return Err(crate::SpreadsheetError::InvalidOperation(format!(
+                "Pivot output {} would overwrite its source {}",
+                output, source
+            )));
+        }
+
+        if let Some(previous) = previous {
+            for address in previous.cells().filter(|a| !output.contains(a)) {
+                if self.get_cell(&address).is_some() {
+                    self.delete_cell(&address)?;
+                }
+            }
+        }
+
+        for (row, values) in table.rows.iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                let address = CellAddress::new(anchor.col + col as u32, anchor.row + row as u32);
+                if value.is_empty() {
+                    if self.get_cell(&address).is_some() {
+                        self.delete_cell(&address)?;
+                    }
+                } else {
+                    self.set_cell_to_value(&address, value.clone())?;
+                }
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Store a literal value, bypassing formula parsing
+    fn set_cell_to_value(&self, address: &CellAddress, value: CellValue) -> Result<()> {
+        let Some(repository) = self.active_repository() else {
+            return Ok(());
+        };
+        let old_cell = repository.get(address);
+        self.forget_external_cell(address);
+        let cell = Cell::new(value);
+        repository.set(address, cell.clone())?;
+        self.publish_change(address, old_cell.as_ref(), &cell)
+    }
+
+    fn with_active_sheet_mut(&self, update: impl FnOnce(&mut Sheet)) -> Result<()> {
+        let mut manager = self.sheet_manager.lock().unwrap();
+        let active_sheet_name = self.active_sheet.lock().unwrap();
+        let sheet = manager
+            .workbook_mut()
+            .get_sheet_mut(&active_sheet_name)
+            .ok_or_else(|| {
+                crate::SpreadsheetError::InvalidOperation(format!(
+                    "Sheet '{}' does not exist",
+                    active_sheet_name
+                ))
+            })?;
+        update(sheet);
+        Ok(())
+    }
+
+    // Conditional formats
+
+    /// Add a data bar or color scale to the active sheet, after the rules
+    /// it has. Returns the rule's index.
+    pub fn add_conditional_format(&self, rule: ConditionalFormatRule) -> Result<usize> {
+        rule.validate()
+            .map_err(SpreadsheetError::InvalidOperation)?;
+        let mut index = 0;
+        self.with_active_sheet_mut(|sheet| {
+            index = sheet.conditional_formats().len();
+            sheet.conditional_formats_mut().push(rule);
+        })?;
+        Ok(index)
+    }
+
+    /// The conditional formats of the active sheet, earlier rules winning
+    pub fn get_conditional_formats(&self) -> Vec<ConditionalFormatRule> {
+        let manager = self.sheet_manager.lock().unwrap();
+        let active_sheet_name = self.active_sheet.lock().unwrap();
+        manager
+            .workbook()
+            .get_sheet(&active_sheet_name)
+            .map(|sheet| sheet.conditional_formats().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Replace the conditional format at `index` of the active sheet
+    pub fn update_conditional_format(
+        &self,
+        index: usize,
+        rule: ConditionalFormatRule,
+    ) -> Result<()> {
+        rule.validate()
+            .map_err(SpreadsheetError::InvalidOperation)?;
+        let mut found = false;
+        self.with_active_sheet_mut(|sheet| {
+            if let Some(stored) = sheet.conditional_formats_mut().get_mut(index) {
+                *stored = rule;
+                found = true;
+            }
+        })?;
+        if !found {
+            return Err(no_conditional_format(index));
+        }
+        Ok(())
+    }
+
+    /// Remove the conditional format at `index` of the active sheet;
+    /// later rules move up
+    pub fn remove_conditional_format(&self, index: usize) -> Result<ConditionalFormatRule> {
+        let mut removed = None;
+        self.with_active_sheet_mut(|sheet| {
+            let rules = sheet.conditional_formats_mut();
+            if index < rules.len() {
+                removed = Some(rules.remove(index));
+            }
+        })?;
+        removed.ok_or_else(|| no_conditional_format(index))
+    }
+
+    /// The numbers of `range` in the active sheet, sorted, as conditional
+    /// formats read them. They are kept until a cell of the range changes,
+    /// including a formula recalculated because a cell it reads changed.
+    pub fn conditional_range_stats(&self, range: &CellRange) -> Arc<RangeStats> {
+        let sheet = self.get_active_sheet();
+        let repository = self.active_repository();
+        self.range_stats
+            .lock()
+            .unwrap()
+            .get_or_compute(&sheet, range, || {
+                RangeStats::new(repository.iter().flat_map(|repository| {
+                    repository
+                        .get_range(range)
+                        .into_iter()
+                        .map(|(_, cell)| cell.get_computed_value())
+                }))
+            })
+    }
+
+    /// Range statistics served from the cache and computed, since creation
+    pub fn range_stats_counts(&self) -> (u64, u64) {
+        self.range_stats.lock().unwrap().stats()
+    }
+
+    /// What the conditional formats of the active sheet draw beneath the
+    /// cell at `address`: a data bar, a background, or `None` when the cell
+    /// holds no number or no rule covers it
+    pub fn get_conditional_fill(&self, address: &CellAddress) -> Option<ConditionalFill> {
+        let rules: Vec<ConditionalFormatRule> = {
+            let manager = self.sheet_manager.lock().unwrap();
+            let active_sheet_name = self.active_sheet.lock().unwrap();
+            let sheet = manager.workbook().get_sheet(&active_sheet_name)?;
+            sheet
+                .conditional_formats()
+                .iter()
+                .filter(|rule| rule.range.contains(address))
+                .cloned()
+                .collect()
+        };
+        if rules.is_empty() {
+            return None;
+        }
+        let CellValue::Number(value) = self.get_cell_raw_value(address)? else {
+            return None;
+        };
+        let fill = ConditionalFill::evaluate(&rules, address, value, |range| {
+            self.conditional_range_stats(range)
+        });
+        (!fill.is_empty()).then_some(fill)
+    }
+
+    // Running aggregates
+
+    /// Write a running aggregate of `source`, a range in one column, into
+    /// the same rows of `target_col`: `=SUM($B$1:B1)`, `=SUM($B$1:B2)`, …
+    /// With `values_only` the computed values are written instead of the
+    /// formulas. All writes form one batch. Returns the cells written.
+    pub fn running_aggregate(
+        &self,
+        source: &CellRange,
+        target_col: u32,
+        aggregate: RunningAggregate,
+        values_only: bool,
+    ) -> Result<Vec<CellAddress>> {
+        if source.start.col != source.end.col {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "A running {} reads one column, not {}",
+                aggregate.label(),
+                source
+            )));
+        }
+        if target_col == source.start.col {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "A running {} cannot overwrite its source {}",
+                aggregate.label(),
+                source
+            )));
+        }
+
+        let rows = source.start.row..=source.end.row;
+        let targets: Vec<CellAddress> = rows
+            .clone()
+            .map(|row| CellAddress::new(target_col, row))
+            .collect();
+        let batch_id = self.begin_batch()?;
+        let written = if values_only {
+            let values: Vec<CellValue> = rows
+                .map(|row| {
+                    self.get_cell(&CellAddress::new(source.start.col, row))
+                        .map(|cell| cell.get_computed_value())
+                        .unwrap_or_default()
+                })
+                .collect();
+            targets
+                .iter()
+                .zip(aggregate.running_values(&values))
+                .try_for_each(|(target, value)| self.set_cell_to_value(target, value))
+        } else {
+            targets.iter().try_for_each(|target| {
+                let formula = aggregate.formula(source, target.row);
+                self.set_cell_value(target, &format!("={}", formula))
+            })
+        };
+        self.commit_batch(&batch_id)?;
+        written?;
+        Ok(targets)
+    }
+
+    // External data
+
+    /// Requests from FETCH formulas the host still has to perform
+    pub fn take_external_requests(&self) -> Vec<ExternalRequest> {
+        self.external.lock().unwrap().take_requests()
+    }
+
+    /// Requests handed to the host that no formula reads any more
+    pub fn take_cancelled_external_requests(&self) -> Vec<ExternalRequest> {
+        self.external.lock().unwrap().take_cancelled()
+    }
+
+    /// Number of external requests that are queued or in flight
+    pub fn pending_external_count(&self) -> usize {
+        self.external.lock().unwrap().pending_count()
+    }
+
+    /// How long fetched values are reused before FETCH asks the host again
+    pub fn set_external_ttl(&self, ttl: Option<chrono::Duration>) {
+        self.external.lock().unwrap().set_ttl(ttl);
+    }
+
+    /// Queue every external request again, e.g. for a manual refresh.
+    /// Returns the number of requests queued.
+    pub fn refresh_external(&self) -> usize {
+        self.external.lock().unwrap().refresh()
+    }
+
+    /// Deliver the host's result for `request`: the response body, or a
+    /// message describing the failure, which the cells show as #VALUE!.
+    ///
+    /// Recalculates the formulas reading the request and everything that
+    /// depends on them, returning the changed cells with their sheet names.
+    pub fn resolve_external(
+        &self,
+        request: &ExternalRequest,
+        response: std::result::Result<String, String>,
+    ) -> Result<Vec<(String, CellAddress)>> {
+        let value = match response {
+            Ok(body) => {
+                extract_value(&body, request.selector.as_deref()).unwrap_or_else(external_error)
+            }
+            Err(message) => external_error(message),
+        };
+
+        let Some(cells) =
+            self.external
+                .lock()
+                .unwrap()
+                .complete(request, value, chrono::Utc::now())
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut changed = Vec::new();
+        for cell in cells {
+            for address in self.recalculate_external(&cell)? {
+                if !changed.contains(&(cell.sheet.clone(), address)) {
+                    changed.push((cell.sheet.clone(), address));
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Perform every pending request with `resolver` and deliver the results.
+    /// Requests queued while resolving are left for the next call.
+    pub fn resolve_external_with(
+        &self,
+        resolver: &dyn ExternalResolver,
+    ) -> Result<Vec<(String, CellAddress)>> {
+        let mut changed = Vec::new();
+        for request in self.take_external_requests() {
+            for cell in self.resolve_external(&request, resolver.fetch(&request.url))? {
+                if !changed.contains(&cell) {
+                    changed.push(cell);
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    fn forget_external_cell(&self, address: &CellAddress) {
+        let sheet = self.get_active_sheet();
+        self.external
+            .lock()
+            .unwrap()
+            .forget_cell(&ExternalCell::new(sheet, *address));
+    }
+
+    /// Re-evaluate a formula whose external data arrived, then every formula
+    /// on the same sheet that reads it, directly or through other cells
+    fn recalculate_external(&self, start: &ExternalCell) -> Result<Vec<CellAddress>> {
+        self.recalculate_from(&start.sheet, vec![start.address])
+    }
+
+    /// Re-evaluate the formulas at `starts` on `sheet_name`, then every
+    /// formula on that sheet that reads them, directly or through other cells.
+    /// On a sheet whose calculation is off they are marked stale instead,
+    /// and returned so their markers get drawn.
+    fn recalculate_from(
+        &self,
+        sheet_name: &str,
+        starts: Vec<CellAddress>,
+    ) -> Result<Vec<CellAddress>> {
+        if !self.is_calculation_enabled(sheet_name) {
+            let Some(graph) = self.sheet_graph(sheet_name) else {
+                return Ok(Vec::new());
+            };
+            let mut graph = graph.lock().unwrap();
+            let stale = graph.recalculation_order(&starts);
+            graph.mark_dirty(stale.iter().copied());
+            return Ok(stale);
+        }
+        let Some((repository, names)) = self.sheet_context(sheet_name) else {
+            return Ok(Vec::new());
+        };
+
+        let mut changed = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = starts;
+
+        while let Some(address) = pending.pop() {
+            if !visited.insert(address) {
+                continue;
+            }
+            let Some(old_cell) = repository.get(&address) else {
+                continue;
+            };
+            let Some(cell) =
+                self.evaluate_stored(sheet_name, &repository, &names, &address, &old_cell)?
+            else {
+                continue;
+            };
+            repository.set(&address, cell.clone())?;
+            changed.push(address);
+            self.publish_change(&address, Some(&old_cell), &cell)?;
+
+            for (dependent, cell) in repository.get_all() {
+                let reads_address = cell
+                    .formula_text
+                    .as_deref()
+                    .and_then(|formula| FormulaParser::parse(formula).ok())
+                    .is_some_and(|expr| DependencyAnalyzer::references_cell(&expr, &address));
+                if reads_address && !visited.contains(&dependent) {
+                    pending.push(dependent);
+                }
+            }
+        }
+
+        self.recalculate_sheet_readers(sheet_name, &changed)?;
+        Ok(changed)
+    }
+
+    /// Re-evaluate the formulas at `order` on `sheet_name`, one after the
+    /// other. Only cells whose value changed are published and returned,
+    /// along with the cells arrays spilled over or left. Long
+    /// recalculations report their progress, see [`super::progress`].
+    fn recalculate_cells(
+        &self,
+        sheet_name: &str,
+        order: &[CellAddress],
+    ) -> Result<Vec<CellAddress>> {
+        let Some((repository, names)) = self.sheet_context(sheet_name) else {
+            return Ok(Vec::new());
+        };
+        let graph = self.sheet_graph(sheet_name);
+
+        let (mut progress, started) = ProgressReporter::start(
+            order.len(),
+            *self.progress_cadence.lock().unwrap(),
+            self.clock.lock().unwrap().clone(),
+            self.recalculation.clone(),
+        );
+        if let Some(event) = started {
+            self.publish(event)?;
+        }
+        let mut changed = Vec::new();
+        let mut order = order.to_vec();
+        let mut revisited = HashSet::new();
+        let recalculated = loop {
+            let pass = self.recalculate_pass(
+                sheet_name,
+                &repository,
+                &names,
+                graph.as_deref(),
+                &order,
+                &mut progress,
+            );
+            let (last, spilled) = match pass {
+                Ok((pass_changed, spills)) => {
+                    changed.extend(pass_changed);
+                    match spills {
+                        Some(spills) => spills,
+                        None => break Ok(changed),
+                    }
+                }
+                Err(e) => break Err(e),
+            };
+            let Some(graph) = &graph else {
+                break Ok(changed);
+            };
+            // Formulas evaluated before an array filled or left the cells
+            // they read, which the order could not know, read them again,
+            // each at most once
+            let position: HashMap<CellAddress, usize> = order
+                .iter()
+                .enumerate()
+                .map(|(index, address)| (*address, index))
+                .collect();
+            order = graph
+                .lock()
+                .unwrap()
+                .recalculation_order(&spilled)
+                .into_iter()
+                .filter(|cell| !spilled.contains(cell))
+                .filter(|cell| position.get(cell).is_none_or(|&at| at <= last))
+                .filter(|cell| revisited.insert(*cell))
+                .collect();
+            if order.is_empty() {
+                break Ok(changed);
+            }
+            progress.extend(order.len());
+        };
+        if let Some(event) = progress.finish() {
+            self.publish(event)?;
+        }
+        recalculated
+    }
+
+    /// One pass of [`Self::recalculate_cells`] over `order`. Returns the
+    /// cells that changed, and the position in `order` of the last formula
+    /// whose array filled or left cells with all such cells, or `None` when
+    /// no array did or the pass was cancelled.
+    ///
+    /// The formulas of the pass are stale until it reaches them: a
+    /// reported pass marks them all first and clears them as it reports,
+    /// any other clears them at the end.
+    fn recalculate_pass(
+        &self,
+        sheet_name: &str,
+        repository: &Arc<dyn RepositoryPort>,
+        names: &Names,
+        graph: Option<&Mutex<DependencyGraph>>,
+        order: &[CellAddress],
+        progress: &mut ProgressReporter,
+    ) -> Result<(Vec<CellAddress>, Spills)> {
+        // In recalculation order a formula only reads cells recalculated
+        // before it, unless it is part of a cycle
+        let position: FxHashMap<CellAddress, usize> = order
+            .iter()
+            .enumerate()
+            .map(|(index, address)| (*address, index))
+            .collect();
+        let cycle_through = |index: usize, address: &CellAddress| {
+            let graph = graph?.lock().unwrap();
+            let reads_ahead = graph
+                .get_dependencies(address)
+                .iter()
+                .any(|precedent| position.get(precedent).is_some_and(|&at| at >= index));
+            if reads_ahead {
+                graph.find_cycle(address)
+            } else {
+                None
+            }
+        };
+        let settle = |cells: &[CellAddress], stale: bool| {
+            if let Some(graph) = graph {
+                let mut graph = graph.lock().unwrap();
+                for &address in cells {
+                    graph.set_dirty(address, stale);
+                }
+            }
+        };
+        if progress.is_reported() {
+            settle(order, true);
+        }
+
+        let mut changed = Vec::new();
+        let mut spills = None;
+        // Start of the formulas evaluated but still marked stale
+        let mut settled = 0;
+        for (index, address) in order.iter().enumerate() {
+            if progress.cancelled() {
+                settle(&order[settled..index], false);
+                settle(&order[index..], true);
+                return Ok((changed, None));
+            }
+            let (value_changed, spilled) = self.recalculate_formula(
+                sheet_name,
+                repository,
+                names,
+                graph,
+                address,
+                cycle_through(index, address),
+            )?;
+            changed.extend_from_slice(&spilled);
+            if value_changed {
+                changed.push(*address);
+            }
+            if !spilled.is_empty() {
+                let (last, cells) = spills.get_or_insert_with(|| (index, Vec::new()));
+                *last = index;
+                cells.extend(spilled);
+            }
+            if let Some(event) = progress.advance() {
+                settle(&order[settled..=index], false);
+                settled = index + 1;
+                self.publish(event)?;
+            }
+        }
+        settle(&order[settled..], false);
+        Ok((changed, spills))
+    }
+
+    /// Re-evaluate the formula at `address`, or give it the error of the
+    /// circular reference `cycle` it is part of. Returns whether its value
+    /// changed, which is then published, and the cells its array filled or
+    /// left.
+    fn recalculate_formula(
+        &self,
+        sheet_name: &str,
+        repository: &Arc<dyn RepositoryPort>,
+        names: &Names,
+        graph: Option<&Mutex<DependencyGraph>>,
+        address: &CellAddress,
+        cycle: Option<Vec<CellAddress>>,
+    ) -> Result<(bool, Vec<CellAddress>)> {
+        let Some(old_cell) = repository.get(address) else {
+            return Ok((false, Vec::new()));
+        };
+        let mut cell = match cycle {
+            Some(cells) if old_cell.has_formula() => {
+                let mut cell = old_cell.clone();
+                cell.set_error_from(&SpreadsheetError::CircularDependencyWith(cells));
+                cell
+            }
+            _ => match self.evaluate_stored(sheet_name, repository, names, address, &old_cell)? {
+                Some(cell) => cell,
+                None => return Ok((false, Vec::new())),
+            },
+        };
+        let spilled = self.settle_spill(repository, graph, address, Some(&mut cell))?;
+        if cell.get_computed_value() == old_cell.get_computed_value()
+            && cell.spill == old_cell.spill
+        {
+            return Ok((false, spilled));
+        }
+        repository.set(address, cell.clone())?;
+        self.publish_change(address, Some(&old_cell), &cell)?;
+        Ok((true, spilled))
+    }
+
+    /// The cells of every sheet, for formulas reading other sheets
+    fn sheet_repositories(&self) -> Arc<SheetRepositories> {
+        sheet_repositories(self.sheet_manager.lock().unwrap().workbook())
+    }
+
+    /// Cells of `sheet_name` and the constants its formulas can see
+    fn sheet_context(&self, sheet_name: &str) -> Option<(Arc<dyn RepositoryPort>, Names)> {
+        let manager = self.sheet_manager.lock().unwrap();
+        let workbook = manager.workbook();
+        let sheet = workbook.get_sheet(sheet_name)?;
+        let names = workbook.visible_names(&NameScope::Sheet(sheet_name.to_string()));
+        Some((sheet.cells(), Arc::new(names)))
+    }
+
+    /// Evaluate the formula stored in `cell` again, or `None` when it holds
+    /// a plain value
+    fn evaluate_stored(
+        &self,
+        sheet_name: &str,
+        repository: &Arc<dyn RepositoryPort>,
+        names: &Names,
+        address: &CellAddress,
+        cell: &Cell,
+    ) -> Result<Option<Cell>> {
+        let Some(formula) = cell.formula_text.as_deref() else {
+            return Ok(None);
+        };
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+        let mut context = PortContext::new(repository.clone())
+            .with_external(
+                self.external.clone(),
+                ExternalCell::new(sheet_name, *address),
+            )
+            .with_names(names.clone())
+            .with_deterministic(self.deterministic_source())
+            .with_sheets(self.sheet_repositories());
+        evaluate_cell_formula_with(&format!("={}", formula), &mut context).map(Some)
+    }
+
+    // Previews
+
+    /// Value `formula` would have if it were entered in `at`, computed from
+    /// the current data without storing anything. External data shows only
+    /// when it has been fetched already; nothing is subscribed or queued.
+    ///
+    /// Fails when the formula does not parse or takes more than
+    /// [`PREVIEW_STEP_BUDGET`] steps; other evaluation failures come back
+    /// as error values.
+    pub fn evaluate_preview(&self, formula: &str, at: &CellAddress) -> Result<CellValue> {
+        let expr = FormulaParser::parse(formula)?;
+        self.preview_expr(&expr, at)
+    }
+
+    /// Like [`Self::evaluate_preview`] for just the part of `formula` in the
+    /// byte range `span`, widened to the smallest complete sub-expression
+    /// covering it. Returns the widened range with the value.
+    pub fn evaluate_subexpression(
+        &self,
+        formula: &str,
+        span: Range<usize>,
+        at: &CellAddress,
+    ) -> Result<(Range<usize>, CellValue)> {
+        let span = enclosing_subexpression(formula, span)?;
+        let expr = FormulaParser::parse(&formula[span.clone()])?;
+        let value = self.preview_expr(&expr, at)?;
+        Ok((span, value))
+    }
+
+    fn preview_expr(&self, expr: &crate::Expr, at: &CellAddress) -> Result<CellValue> {
+        let (repository, names) = {
+            let manager = self.sheet_manager.lock().unwrap();
+            let active_sheet_name = self.active_sheet.lock().unwrap();
+            let repository = match manager.workbook().get_sheet(&active_sheet_name) {
+                Some(sheet) => sheet.cells(),
+                None => match self.container.repository() {
+                    Some(repository) => repository,
+                    None => return Ok(CellValue::Empty),
+                },
+            };
+            let names = manager
+                .workbook()
+                .visible_names(&NameScope::Sheet(active_sheet_name.clone()));
+            (repository, Arc::new(names))
+        };
+
+        let mut context = PortContext::new(repository)
+            .with_external_peek(self.external.clone())
+            .with_names(names)
+            .with_deterministic(self.deterministic_fork())
+            .with_sheets(self.sheet_repositories());
+        // The formula would live in `at`, so reading it is circular
+        context.push_evaluation(at);
+        match Evaluator::new(&mut context)
+            .with_budget(PREVIEW_STEP_BUDGET)
+            .evaluate(expr)
+        {
+            Err(SpreadsheetError::BudgetExceeded) => Err(SpreadsheetError::BudgetExceeded),
+            Err(error) => Ok(CellValue::from_error(error.to_error_type())),
+            value => value,
+        }
+    }
+
+    /// Values the `outputs` cells of the active sheet would have if the
+    /// `overrides` cells held the given values instead, e.g. for previewing
+    /// a slider live. Only the formulas reading an override, directly or
+    /// through other cells, are evaluated again, from an overlay of their
+    /// new values; everything else reads its stored value. Nothing is
+    /// written, published or marked stale, and volatile formulas in the
+    /// overlay leave their stored values alone. External data shows only
+    /// when it has been fetched already.
+    pub fn evaluate_with_overrides(
+        &self,
+        overrides: Vec<(CellAddress, CellValue)>,
+        outputs: Vec<CellAddress>,
+    ) -> Result<Vec<CellValue>> {
+        let sheet_name = self.get_active_sheet();
+        let Some((repository, names)) = self.sheet_context(&sheet_name) else {
+            return Ok(vec![CellValue::Empty; outputs.len()]);
+        };
+        let starts: Vec<CellAddress> = overrides.iter().map(|(address, _)| *address).collect();
+        let mut overlay: FxHashMap<CellAddress, CellValue> = overrides.into_iter().collect();
+        let deterministic = self.deterministic_fork();
+        let sheets = self.sheet_repositories();
+        for address in self.with_dependents(&starts) {
+            if overlay.contains_key(&address) {
+                continue;
+            }
+            let Some(formula) = repository.get(&address).and_then(|cell| cell.formula_text) else {
+                continue;
+            };
+            let context = PortContext::new(repository.clone())
+                .with_external_peek(self.external.clone())
+                .with_names(names.clone())
+                .with_deterministic(deterministic.clone())
+                .with_sheets(sheets.clone());
+            let cell = evaluate_cell_formula_with(
+                &format!("={}", formula),
+                &mut OverlayContext::new(context, &overlay),
+            )?;
+            overlay.insert(address, cell.get_computed_value());
+        }
+
+        Ok(outputs
+            .iter()
+            .map(|address| match overlay.get(address) {
+                Some(value) => value.clone(),
+                None => repository
+                    .get(address)
+                    .map(|cell| cell.get_computed_value())
+                    .unwrap_or(CellValue::Empty),
+            })
+            .collect())
+    }
+
+    // Defined names
+
+    /// Define or redefine the constant `name` in `scope`, e.g. `TaxRate` as
+    /// `0.21`. The definition is evaluated once, now; it may use other
+    /// constants but not cells.
+    ///
+    /// Recalculates the formulas using the name, returning the changed cells
+    /// with their sheet names.
+    pub fn define_constant(
+        &self,
+        name: &str,
+        definition: &str,
+        scope: NameScope,
+    ) -> Result<Vec<(String, CellAddress)>> {
+        validate_name(name)?;
+        let definition = definition.trim();
+        let expr = FormulaParser::parse(definition)?;
+        if DependencyAnalyzer::has_dependencies(&expr) {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "Constant {} cannot refer to cells",
+                name
+            )));
+        }
+
+        let names = {
+            let manager = self.sheet_manager.lock().unwrap();
+            if let NameScope::Sheet(sheet_name) = &scope
+                && manager.workbook().get_sheet(sheet_name).is_none()
+            {
+                return Err(SpreadsheetError::InvalidOperation(format!(
+                    "Sheet '{}' not found",
+                    sheet_name
+                )));
+            }
+            manager.workbook().visible_names(&scope)
+        };
+        let Some(repository) = self.active_repository() else {
+            return Ok(Vec::new());
+        };
+        let mut context = PortContext::new(repository)
+            .with_names(Arc::new(names))
+            .with_deterministic(self.deterministic_source());
+        let value = Evaluator::new(&mut context).evaluate(&expr)?;
+        if let CellValue::Error(error) = &value {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "Constant {} evaluates to {}",
+                name,
+                error.excel_code()
+            )));
+        }
+
+        let constant = NamedConstant::new(name, value, definition.trim_start_matches('='));
+        {
+            let mut manager = self.sheet_manager.lock().unwrap();
+            let workbook = manager.workbook_mut();
+            match &scope {
+                NameScope::Workbook => workbook.define_global_constant(constant),
+                NameScope::Sheet(sheet_name) => {
+                    if let Some(sheet) = workbook.get_sheet_mut(sheet_name) {
+                        sheet.define_constant(constant);
+                    }
+                }
+            }
+        }
+        self.recalculate_name_dependents(name, &scope)
+    }
+
+    /// The constant `name` defined in exactly `scope`
+    pub fn get_constant(&self, name: &str, scope: &NameScope) -> Option<NamedConstant> {
+        let manager = self.sheet_manager.lock().unwrap();
+        match scope {
+            NameScope::Workbook => manager.workbook().get_global_constant(name).cloned(),
+            NameScope::Sheet(sheet_name) => manager
+                .workbook()
+                .get_sheet(sheet_name)?
+                .get_constant(name)
+                .cloned(),
+        }
+    }
+
+    /// Delete the constant `name` from `scope`. Formulas using it fall back
+    /// to a workbook constant of the same name, or show #NAME?.
+    ///
+    /// Returns the changed cells with their sheet names.
+    pub fn remove_constant(
+        &self,
+        name: &str,
+        scope: &NameScope,
+    ) -> Result<Vec<(String, CellAddress)>> {
+        let removed = {
+            let mut manager = self.sheet_manager.lock().unwrap();
+            let workbook = manager.workbook_mut();
+            match scope {
+                NameScope::Workbook => workbook.remove_global_constant(name),
+                NameScope::Sheet(sheet_name) => workbook
+                    .get_sheet_mut(sheet_name)
+                    .and_then(|sheet| sheet.remove_constant(name)),
+            }
+        };
+        if removed.is_none() {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "Name {} is not defined",
+                name
+            )));
+        }
+        self.recalculate_name_dependents(name, scope)
+    }
+
+    /// Define or redefine the workbook-scoped name `name` as `range` of the
+    /// active sheet, e.g. `Sales` as `B2:B20`. Formulas read a one-cell
+    /// range like a reference to its cell and pass others to functions
+    /// like a range. Rows and columns inserted or deleted on the sheet move
+    /// the range like a formula reference.
+    ///
+    /// Recalculates the formulas using the name, returning the changed cells
+    /// with their sheet names.
+    pub fn define_name(&self, name: &str, range: CellRange) -> Result<Vec<(String, CellAddress)>> {
+        validate_name(name)?;
+        let range = CellRange::create(range.start, range.end)
+            .map_err(SpreadsheetError::InvalidOperation)?;
+        let sheet_name = self.get_active_sheet();
+        {
+            let mut manager = self.sheet_manager.lock().unwrap();
+            let workbook = manager.workbook_mut();
+            if workbook.get_global_constant(name).is_some() {
+                return Err(SpreadsheetError::InvalidOperation(format!(
+                    "Name {} is already a constant",
+                    name
+                )));
+            }
+            workbook.add_global_named_range(name, sheet_name, range)?;
+        }
+        self.recalculate_name_dependents(name, &NameScope::Workbook)
+    }
+
+    /// Delete the workbook-scoped named range `name`. Formulas still using
+    /// it show #NAME?.
+    ///
+    /// Returns the changed cells with their sheet names.
+    pub fn delete_name(&self, name: &str) -> Result<Vec<(String, CellAddress)>> {
+        let removed = self
+            .sheet_manager
+            .lock()
+            .unwrap()
+            .workbook_mut()
+            .remove_global_named_range(name);
+        if removed.is_none() {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "Name {} is not defined",
+                name
+            )));
+        }
+        self.recalculate_name_dependents(name, &NameScope::Workbook)
+    }
+
+    /// Workbook-scoped named ranges, alphabetically
+    pub fn list_names(&self) -> Vec<NamedRange> {
+        let manager = self.sheet_manager.lock().unwrap();
+        let mut names: Vec<NamedRange> =
+            manager.workbook().global_named_ranges().cloned().collect();
+        names.sort_by_key(|named| name_key(&named.name));
+        names
+    }
+
+    /// Every named range and named constant, for a names manager
+    pub fn defined_names(&self) -> Vec<DefinedName> {
+        self.sheet_manager
+            .lock()
+            .unwrap()
+            .workbook()
+            .defined_names()
+    }
+
+    /// Add names listed by [`Self::defined_names`], e.g. from a saved
+    /// workbook, then recalculate the formulas using them
+    pub fn restore_defined_names(
+        &self,
+        names: Vec<DefinedName>,
+    ) -> Result<Vec<(String, CellAddress)>> {
+        let mut restored = Vec::new();
+        {
+            let mut manager = self.sheet_manager.lock().unwrap();
+            for entry in names {
+                validate_name(&entry.name)?;
+                restored.push((entry.name.clone(), entry.scope.clone()));
+                manager.workbook_mut().restore_name(entry)?;
+            }
+        }
+
+        let mut changed = Vec::new();
+        for (name, scope) in restored {
+            for cell in self.recalculate_name_dependents(&name, &scope)? {
+                if !changed.contains(&cell) {
+                    changed.push(cell);
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Recalculate the formulas that can see `name` from `scope`
+    fn recalculate_name_dependents(
+        &self,
+        name: &str,
+        scope: &NameScope,
+    ) -> Result<Vec<(String, CellAddress)>> {
+        let sheets: Vec<(String, Arc<dyn RepositoryPort>)> = {
+            let manager = self.sheet_manager.lock().unwrap();
+            let workbook = manager.workbook();
+            let names = match scope {
+                NameScope::Workbook => workbook.sheet_names().to_vec(),
+                NameScope::Sheet(sheet_name) => vec![sheet_name.clone()],
+            };
+            names
+                .into_iter()
+                .filter_map(|sheet_name| {
+                    let sheet = workbook.get_sheet(&sheet_name)?;
+                    // Sheets with their own name of that spelling never see
+                    // the workbook's
+                    let shadowed = *scope == NameScope::Workbook
+                        && (sheet.get_constant(name).is_some()
+                            || sheet.get_named_range(name).is_some());
+                    (!shadowed).then(|| (sheet_name, sheet.cells()))
+                })
+                .collect()
+        };
+
+        let mut changed = Vec::new();
+        for (sheet_name, repository) in sheets {
+            let starts: Vec<CellAddress> = repository
+                .get_all()
+                .into_iter()
+                .filter(|(_, cell)| {
+                    cell.formula_text
+                        .as_deref()
+                        .and_then(|formula| FormulaParser::parse(formula).ok())
+                        .is_some_and(|expr| DependencyAnalyzer::references_name(&expr, name))
+                })
+                .map(|(address, _)| address)
+                .collect();
+            if starts.is_empty() {
+                continue;
+            }
+            // A named range the formulas read may have changed cells
+            if let (Some(graph), Some((_, names))) = (
+                self.sheet_graph(&sheet_name),
+                self.sheet_context(&sheet_name),
+            ) {
+                let mut graph = graph.lock().unwrap();
+                for address in &starts {
+                    if let Some(cell) = repository.get(address) {
+                        graph.set_dependencies(*address, cell_references(&cell, &names));
+                    }
+                }
+            }
+            for address in self.recalculate_from(&sheet_name, starts)? {
+                changed.push((sheet_name.clone(), address));
+            }
+        }
+        Ok(changed)
+    }
+
+    // Sheet management
+
+    /// Get list of all sheets
+    pub fn get_sheets(&self) -> Vec<(String, usize)> {
+        let manager = self.sheet_manager.lock().unwrap();
+        manager
+            .workbook()
+            .sheet_names()
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| (name.clone(), idx))
+            .collect()
+    }
+
+    /// Get the active sheet name
+    pub fn get_active_sheet(&self) -> String {
+        self.active_sheet.lock().unwrap().clone()
+    }
+
+    /// Set the active sheet
+    pub fn set_active_sheet(&self, sheet_name: &str) -> Result<()> {
+        let manager = self.sheet_manager.lock().unwrap();
+        if manager.workbook().get_sheet(sheet_name).is_some() {
+            *self.active_sheet.lock().unwrap() = sheet_name.to_string();
+            Ok(())
+        } else {
+            Err(crate::SpreadsheetError::InvalidOperation(format!(
+                "Sheet '{}' does not exist",
+                sheet_name
+            )))
+        }
+    }
+
+    /// Add a new sheet
+    pub fn add_sheet(&self, name: &str) -> Result<()> {
+        let mut manager = self.sheet_manager.lock().unwrap();
+        let sheet = Sheet::new(name);
+        manager.workbook_mut().add_sheet(sheet)
+    }
+
+    /// Remove a sheet. Formulas on other sheets reading it turn into
+    /// `#REF!`.
+    pub fn remove_sheet(&self, name: &str) -> Result<()> {
+        {
+            let mut manager = self.sheet_manager.lock().unwrap();
+
+            // Don't allow removing the last sheet
+            if manager.workbook().sheet_count() <= 1 {
+                return Err(crate::SpreadsheetError::InvalidOperation(
+                    "Cannot remove the last sheet".to_string(),
+                ));
+            }
+
+            manager.workbook_mut().remove_sheet(name)?;
+
+            // If removing the active sheet, switch to another one
+            if self.get_active_sheet() == name
+                && let Some(sheet_name) = manager.workbook().sheet_names().first()
+            {
+                *self.active_sheet.lock().unwrap() = sheet_name.clone();
+            }
+        }
+        self.external.lock().unwrap().forget_sheet(name);
+        self.range_stats.lock().unwrap().clear();
+        let readers = self.cross_sheet.lock().unwrap().readers_of_sheet(name);
+        self.settle_sheet_readers(name)?;
+        self.recalculate_readers(readers)
+    }
+
+    /// Rename a sheet
+    pub fn rename_sheet(&self, old_name: &str, new_name: &str) -> Result<()> {
+        let mut manager = self.sheet_manager.lock().unwrap();
+        manager.workbook_mut().rename_sheet(old_name, new_name)?;
+        self.external
+            .lock()
+            .unwrap()
+            .rename_sheet(old_name, new_name);
+        self.range_stats.lock().unwrap().clear();
+
+        // Update active sheet if it was renamed
+        if self.get_active_sheet() == old_name {
+            *self.active_sheet.lock().unwrap() = new_name.to_string();
+        }
+        drop(manager);
+
+        // The formulas reading the sheet were rewritten for the new name
+        self.settle_sheet_readers(new_name)
+    }
+
+    /// Check whether a sheet with the given name exists
+    pub fn has_sheet(&self, name: &str) -> bool {
+        let manager = self.sheet_manager.lock().unwrap();
+        manager.workbook().get_sheet(name).is_some()
+    }
+
+    /// Get the number of sheets
+    pub fn sheet_count(&self) -> usize {
+        let manager = self.sheet_manager.lock().unwrap();
+        manager.workbook().sheet_count()
+    }
+
+    /// Areas the array formulas of the active sheet spill over, blocked
+    /// ones included, in reading order of their formulas
+    pub fn spill_ranges(&self) -> Vec<SpillRange> {
+        self.active_repository()
+            .map(|repository| repository.spill_ranges())
+            .unwrap_or_default()
+    }
+
+    /// The spill area `address` is the formula of or a value spilled into
+    pub fn spill_range_at(&self, address: &CellAddress) -> Option<SpillRange> {
+        self.active_repository()?.spill_range_at(address)
+    }
+
+    /// Error summary of one sheet
+    pub fn get_sheet_health(&self, name: &str) -> Option<SheetHealth> {
+        let manager = self.sheet_manager.lock().unwrap();
+        manager
+            .workbook()
+            .get_sheet(name)
+            .map(|sheet| sheet.cells().health())
+    }
+
+    /// Error counts of the active sheet found by reading every cell, to
+    /// check its error index against
+    pub fn scan_sheet_health(&self) -> SheetHealth {
+        let mut index = ErrorIndex::new();
+        for (address, cell) in self.get_all_cells() {
+            index.update(&address, Some(&cell));
+        }
+        index.health()
+    }
+
+    /// Error summary of every sheet, in tab order
+    pub fn get_workbook_health(&self) -> Vec<(String, SheetHealth)> {
+        let manager = self.sheet_manager.lock().unwrap();
+        let workbook = manager.workbook();
+        workbook
+            .sheet_names()
+            .iter()
+            .filter_map(|name| {
+                let sheet = workbook.get_sheet(name)?;
+                Some((name.clone(), sheet.cells().health()))
+            })
+            .collect()
+    }
+
+    // Command system compatibility methods
+    // These are thin wrappers that just call the main methods
+
+    /// Set cell value without command (for command system)
+    pub fn set_cell_value_without_command(&self, address: &CellAddress, value: &str) -> Result<()> {
+        self.set_cell_value(address, value)
+    }
+
+    /// Delete cell without command (for command system)
+    pub fn delete_cell_without_command(&self, address: &CellAddress) -> Result<()> {
+        self.delete_cell(address)
+    }
+
+    /// Insert row without command (placeholder)
+    pub fn insert_row_without_command(&self, index: u32) -> Result<()> {
+        // Use structural operations service when available
+        if let Some(structural_ops) = self.container.structural_operations() {
+            structural_ops.insert_rows(index, 1)?;
+            self.publish(DomainEvent::RowInserted { index })?;
+            self.debug_check_dependencies();
+        }
+        Ok(())
+    }
+
+    /// Delete row without command (placeholder)
+    pub fn delete_row_without_command(&self, index: u32) -> Result<()> {
+        // Use structural operations service when available
+        if let Some(structural_ops) = self.container.structural_operations() {
+            let removed = structural_ops.delete_rows(index, 1)?;
+            self.publish(DomainEvent::RowDeleted { index, removed })?;
+            self.debug_check_dependencies();
+        }
+        Ok(())
+    }
+
+    /// Insert column without command (placeholder)
+    pub fn insert_column_without_command(&self, index: u32) -> Result<()> {
+        // Use structural operations service when available
+        if let Some(structural_ops) = self.container.structural_operations() {
+            structural_ops.insert_columns(index, 1)?;
+            self.publish(DomainEvent::ColumnInserted { index })?;
+            self.debug_check_dependencies();
+        }
+        Ok(())
+    }
+
+    /// Delete column without command (placeholder)
+    pub fn delete_column_without_command(&self, index: u32) -> Result<()> {
+        // Use structural operations service when available
+        if let Some(structural_ops) = self.container.structural_operations() {
+            let removed = structural_ops.delete_columns(index, 1)?;
+            self.publish(DomainEvent::ColumnDeleted { index, removed })?;
+            self.debug_check_dependencies();
+        }
+        Ok(())
+    }
+
+    /// Insert `count` empty rows above `before_row` of the active sheet
+    pub fn insert_rows(&self, before_row: u32, count: u32) -> Result<()> {
+        self.apply_structural_operation(StructuralOperation::InsertRows { before_row, count })
+    }
+
+    /// Delete `count` rows of the active sheet from `start_row` down
+    pub fn delete_rows(&self, start_row: u32, count: u32) -> Result<()> {
+        self.apply_structural_operation(StructuralOperation::DeleteRows { start_row, count })
+    }
+
+    /// Insert `count` empty columns left of `before_col` of the active sheet
+    pub fn insert_columns(&self, before_col: u32, count: u32) -> Result<()> {
+        self.apply_structural_operation(StructuralOperation::InsertColumns { before_col, count })
+    }
+
+    /// Delete `count` columns of the active sheet from `start_col` right
+    pub fn delete_columns(&self, start_col: u32, count: u32) -> Result<()> {
+        self.apply_structural_operation(StructuralOperation::DeleteColumns { start_col, count })
+    }
+
+    /// Adjust the formulas of every sheet for `operation` on the active
+    /// sheet, then move its cells out of the way and recalculate
+    fn apply_structural_operation(&self, operation: StructuralOperation) -> Result<()> {
+        let Some(repository) = self.active_repository() else {
+            return Ok(());
+        };
+        if let StructuralOperation::MoveRange { .. } = operation {
+            return Err(SpreadsheetError::InvalidOperation(
+                "Ranges are moved by pasting them".to_string(),
+            ));
+        }
+        // Cells move without being written, so no range keeps its numbers
+        self.range_stats.lock().unwrap().clear();
+        if let Some(batch) = self.structural_batch.lock().unwrap().as_mut() {
+            if batch.sheet != self.get_active_sheet() {
+                return Err(SpreadsheetError::InvalidOperation(format!(
+                    "The open structural batch changes sheet '{}' only",
+                    batch.sheet
+                )));
+            }
+            Self::move_cells(repository.as_ref(), operation)?;
+            batch.operations.push(operation);
+            return Ok(());
+        }
+        // Formulas are adjusted where they are, as a range ending right
+        // above a formula grows with rows inserted between the two
+        let active_sheet = self.get_active_sheet();
+        self.sheet_manager
+            .lock()
+            .unwrap()
+            .apply_structural_operation_to_all(&active_sheet, operation)?;
+        Self::move_cells(repository.as_ref(), operation)?;
+        self.repair_dependencies()?;
+        self.recalculate()?;
+        self.settle_sheet_readers(&active_sheet)
+    }
+
+    /// Move the cells of `repository` out of the way of an insertion or
+    /// deletion
+    fn move_cells(repository: &dyn RepositoryPort, operation: StructuralOperation) -> Result<()> {
+        match operation {
+            StructuralOperation::InsertRows { before_row, count } => {
+                (0..count).try_for_each(|_| repository.insert_row(before_row))
+            }
+            StructuralOperation::DeleteRows { start_row, count } => {
+                (0..count).try_for_each(|_| repository.delete_row(start_row))
+            }
+            StructuralOperation::InsertColumns { before_col, count } => {
+                (0..count).try_for_each(|_| repository.insert_column(before_col))
+            }
+            StructuralOperation::DeleteColumns { start_col, count } => {
+                (0..count).try_for_each(|_| repository.delete_column(start_col))
+            }
+            StructuralOperation::MoveRange { .. } => Ok(()),
+        }
+    }
+
+    fn publish(&self, event: DomainEvent) -> Result<()> {
+        match self.container.events() {
+            Some(events) => events.publish(event),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Rows of an export that fit in a buffer of `capacity` slots
+fn export_row_count(start_row: u32, end_row: u32, capacity: usize) -> usize {
+    if end_row < start_row {
+        return 0;
+    }
+    ((end_row - start_row) as usize + 1).min(capacity)
+}
+
+fn sheet_not_found(sheet_name: &str) -> SpreadsheetError {
+    SpreadsheetError::InvalidOperation(format!("Sheet '{}' does not exist", sheet_name))
+}
+
+fn no_conditional_format(index: usize) -> SpreadsheetError {
+    SpreadsheetError::InvalidOperation(format!("No conditional format at index {}", index))
+}
+
+/// Cells on the same sheet that a cell's formula reads, directly or
+/// through the named ranges in `names`
+fn cell_references(cell: &Cell, names: &VisibleNames) -> HashSet<CellAddress> {
+    cell.formula_text
+        .as_deref()
+        .and_then(|formula| FormulaParser::parse(formula).ok())
+        .map(|expr| DependencyAnalyzer::extract_dependencies_with_names(&expr, &names.ranges))
+        .unwrap_or_default()
+}
+
+/// Cells of other sheets a cell's formula reads, under the names of the
+/// sheets of `workbook` they match; see [`PortContext::with_sheets`]
+fn sheet_cell_references(cell: &Cell, workbook: &Workbook) -> Vec<SheetCell> {
+    let Some(expr) = cell
+        .formula_text
+        .as_deref()
+        .and_then(|formula| FormulaParser::parse(formula).ok())
+    else {
+        return Vec::new();
+    };
+    let names = workbook.sheet_names();
+    DependencyAnalyzer::extract_sheet_dependencies(&expr)
+        .into_iter()
+        .map(|(written, address)| {
+            let sheet = names
+                .iter()
+                .find(|name| **name == written)
+                .or_else(|| {
+                    names
+                        .iter()
+                        .find(|name| name.eq_ignore_ascii_case(&written))
+                })
+                .cloned()
+                .unwrap_or(written);
+            (sheet, address)
+        })
+        .collect()
+}
+
+/// The cells of every sheet of `workbook`, for formulas reading other
+/// sheets
+fn sheet_repositories(workbook: &Workbook) -> Arc<SheetRepositories> {
+    Arc::new(
+        workbook
+            .sheet_names()
+            .iter()
+            .filter_map(|name| Some((name.clone(), workbook.get_sheet(name)?.cells())))
+            .collect(),
+    )
+}
+
+/// Whether a cell's formula calls a volatile function
+fn calls_volatile(cell: &Cell) -> bool {
+    cell.formula_text
+        .as_deref()
+        .and_then(|formula| FormulaParser::parse(formula).ok())
+        .is_some_and(|expr| expr.is_volatile())
+}
+
+/// The cells read by every formula in `repository` that reads any, with
+/// each spilled cell reading the formula it was spilled from
+fn formula_references(
+    repository: &dyn RepositoryPort,
+    names: &VisibleNames,
+) -> HashMap<CellAddress, HashSet<CellAddress>> {
+    let mut references: HashMap<CellAddress, HashSet<CellAddress>> = repository
+        .get_all()
+        .into_iter()
+        .map(|(address, cell)| (address, cell_references(&cell, names)))
+        .filter(|(_, references)| !references.is_empty())
+        .collect();
+    for spill in repository.spill_ranges() {
+        for cell in spill.range.cells() {
+            let filled = repository
+                .spill_range_at(&cell)
+                .is_some_and(|range| cell != spill.anchor && range.anchor == spill.anchor);
+            if filled {
+                references.entry(cell).or_default().insert(spill.anchor);
+            }
+        }
+    }
+    references
+}
+
+fn ranges_overlap(a: &CellRange, b: &CellRange) -> bool {
+    a.start.col <= b.end.col
+        && b.start.col <= a.end.col
+        && a.start.row <= b.end.row
+        && b.start.row <= a.end.row
+}
+
+impl Default for SpreadsheetFacade {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::{EventAdapter, RepositoryAdapter};
+    use crate::facade::{ProgressCadence, RecalculationStatus};
+    use crate::ports::event_port::{BATCH_DELTA_INLINE_LIMIT, BatchDelta};
+    use crate::types::ErrorType;
+    use crate::workbook::NameDefinition;
+
+    #[test]
+    fn test_facade_creation() {
+        let repository = Arc::new(RepositoryAdapter::new_empty());
+        let events = Arc::new(EventAdapter::new_empty());
+
+        let facade = SpreadsheetFacade::with_ports(
+            repository as Arc<dyn RepositoryPort>,
+            events as Arc<dyn EventPort>,
+        );
+
+        assert_eq!(facade.cell_count(), 0);
+    }
+
+    #[test]
+    fn test_facade_get_cell() {
+        let facade = SpreadsheetFacade::new();
+        let address = CellAddress::new(0, 0);
+        assert!(facade.get_cell(&address).is_none());
+    }
+
+    #[test]
+    fn test_sheet_management() {
+        let facade = SpreadsheetFacade::new();
+
+        // Should start with one sheet
+        assert_eq!(facade.sheet_count(), 1);
+        assert_eq!(facade.get_active_sheet(), "Sheet1");
+
+        // Add a new sheet
+        assert!(facade.add_sheet("Sheet2").is_ok());
+        assert_eq!(facade.sheet_count(), 2);
+
+        // Switch active sheet
+        assert!(facade.set_active_sheet("Sheet2").is_ok());
+        assert_eq!(facade.get_active_sheet(), "Sheet2");
+    }
+
+    #[test]
+    fn test_get_cell_in_inactive_sheet() {
+        let facade = SpreadsheetFacade::new();
+        let address = CellAddress::new(0, 0);
+        facade.set_cell_value(&address, "42").unwrap();
+        facade.add_sheet("Sheet2").unwrap();
+        facade.set_active_sheet("Sheet2").unwrap();
+
+        assert!(facade.get_cell(&address).is_none());
+        let cell = facade.get_cell_in_sheet("Sheet1", &address).unwrap();
+        assert_eq!(cell.get_computed_value(), CellValue::Number(42.0));
+        assert!(facade.get_cell_in_sheet("Missing", &address).is_none());
+        assert!(facade.has_sheet("Sheet1"));
+        assert!(!facade.has_sheet("Missing"));
+    }
+
+    #[test]
+    fn test_formulas_read_and_follow_other_sheets() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |sheet: &str, a1: &str| {
+            facade
+                .get_cell_in_sheet(sheet, &cell(a1))
+                .map(|cell| cell.get_computed_value())
+        };
+        facade.add_sheet("My Data").unwrap();
+        facade.set_active_sheet("My Data").unwrap();
+        facade.set_cell_value(&cell("A1"), "2").unwrap();
+        facade.set_cell_value(&cell("A2"), "3").unwrap();
+
+        facade.set_active_sheet("Sheet1").unwrap();
+        facade
+            .set_cell_value(&cell("A1"), "='My Data'!A1*10")
+            .unwrap();
+        facade
+            .set_cell_value(&cell("B1"), "=SUM('my data'!A1:A2)+A1")
+            .unwrap();
+        assert_eq!(value("Sheet1", "A1"), Some(CellValue::Number(20.0)));
+        assert_eq!(value("Sheet1", "B1"), Some(CellValue::Number(25.0)));
+        assert_eq!(
+            facade.get_sheet_precedents(&cell("A1")),
+            vec![("My Data".to_string(), cell("A1"))]
+        );
+
+        // Edits on the other sheet reach the formulas reading it and theirs
+        facade.set_active_sheet("My Data").unwrap();
+        facade.set_cell_value(&cell("A1"), "4").unwrap();
+        assert_eq!(value("Sheet1", "A1"), Some(CellValue::Number(40.0)));
+        assert_eq!(value("Sheet1", "B1"), Some(CellValue::Number(47.0)));
+        facade.delete_cell(&cell("A2")).unwrap();
+        assert_eq!(value("Sheet1", "B1"), Some(CellValue::Number(44.0)));
+
+        // A chain back to the first sheet follows too
+        facade.set_cell_value(&cell("B1"), "=Sheet1!A1+1").unwrap();
+        assert_eq!(value("My Data", "B1"), Some(CellValue::Number(41.0)));
+        facade.set_cell_value(&cell("A1"), "1").unwrap();
+        assert_eq!(value("My Data", "B1"), Some(CellValue::Number(11.0)));
+
+        let ref_error = |sheet: &str, a1: &str| {
+            matches!(value(sheet, a1), Some(CellValue::Error(error))
+                if matches!(*error, ErrorType::InvalidRef { .. }))
+        };
+        facade.set_cell_value(&cell("C1"), "=Missing!A1").unwrap();
+        assert!(ref_error("My Data", "C1"));
+    }
+
+    #[test]
+    fn test_reference_style_changes_display_not_storage() {
+        let facade = SpreadsheetFacade::new();
+        let b3 = CellAddress::new(1, 2);
+        facade.set_cell_value(&b3, "=A1+$A$1*SUM(A1:A$2)").unwrap();
+
+        facade.set_reference_style(ReferenceStyle::R1C1);
+        assert_eq!(
+            facade.get_formula_display(&b3).as_deref(),
+            Some("=R[-2]C[-1]+R1C1*SUM(R[-2]C[-1]:R2C[-1])")
+        );
+        let cell = facade.get_cell(&b3).unwrap();
+        assert_eq!(cell.formula_text.as_deref(), Some("A1+$A$1*SUM(A1:A$2)"));
+        assert_eq!(facade.get_formula_display(&CellAddress::new(0, 0)), None);
+
+        facade.set_reference_style(ReferenceStyle::A1);
+        assert_eq!(
+            facade.get_formula_display(&b3).as_deref(),
+            Some("=A1+$A$1*SUM(A1:A$2)")
+        );
+    }
+
+    #[test]
+    fn test_renaming_and_removing_sheets_rewrites_references() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.add_sheet("Data").unwrap();
+        facade.set_active_sheet("Data").unwrap();
+        facade.set_cell_value(&cell("A1"), "5").unwrap();
+        facade.set_active_sheet("Sheet1").unwrap();
+        facade.set_cell_value(&cell("A1"), "=Data!A1*2").unwrap();
+
+        facade.rename_sheet("Data", "Q1 Data").unwrap();
+        let formula = facade.get_cell(&cell("A1")).unwrap().formula_text;
+        assert_eq!(formula.as_deref(), Some("'Q1 Data'!A1*2"));
+
+        // The renamed sheet's edits still reach the formula
+        facade.set_active_sheet("Q1 Data").unwrap();
+        facade.set_cell_value(&cell("A1"), "6").unwrap();
+        assert_eq!(
+            facade
+                .get_cell_in_sheet("Sheet1", &cell("A1"))
+                .map(|cell| cell.get_computed_value()),
+            Some(CellValue::Number(12.0))
+        );
+
+        facade.remove_sheet("Q1 Data").unwrap();
         assert_eq!(facade.get_active_sheet(), "Sheet1");
+        let removed = facade.get_cell(&cell("A1")).unwrap();
+        assert_eq!(removed.formula_text.as_deref(), Some("#REF!*2"));
+        assert!(
+            matches!(removed.get_computed_value(), CellValue::Error(error)
+            if matches!(*error, ErrorType::InvalidRef { .. }))
+        );
+        assert!(facade.get_sheet_precedents(&cell("A1")).is_empty());
+    }
+
+    #[test]
+    fn test_structural_changes_move_references_from_other_sheets() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.add_sheet("Data").unwrap();
+        facade.set_active_sheet("Data").unwrap();
+        facade.set_cell_value(&cell("A2"), "7").unwrap();
+        facade.set_active_sheet("Sheet1").unwrap();
+        facade.set_cell_value(&cell("A1"), "=Data!A2").unwrap();
+
+        facade.set_active_sheet("Data").unwrap();
+        facade.insert_rows(0, 1).unwrap();
+        facade.set_cell_value(&cell("A3"), "8").unwrap();
+        let reader = facade.get_cell_in_sheet("Sheet1", &cell("A1")).unwrap();
+        assert_eq!(reader.formula_text.as_deref(), Some("Data!A3"));
+        assert_eq!(reader.get_computed_value(), CellValue::Number(8.0));
+    }
+
+    #[test]
+    fn test_clear_formats_reverts_to_column_default() {
+        let facade = SpreadsheetFacade::new();
+        let address = CellAddress::new(1, 3);
+        facade.set_cell_value(&address, "0.5").unwrap();
+        assert_eq!(facade.get_display_value(&address).unwrap(), "0.5");
+
+        facade
+            .set_column_format(1, Some(CellFormat::percent(0)))
+            .unwrap();
+        assert_eq!(facade.get_display_value(&address).unwrap(), "50%");
+
+        facade
+            .set_cell_format(&address, CellFormat::currency("$", 2))
+            .unwrap();
+        assert_eq!(facade.get_display_value(&address).unwrap(), "$0.50");
+
+        facade.clear_formats(&address).unwrap();
+        assert_eq!(facade.get_display_value(&address).unwrap(), "50%");
+        assert_eq!(
+            facade.get_effective_format(&address),
+            Some(CellFormat::percent(0))
+        );
+    }
+
+    #[test]
+    fn test_typed_currency_and_percent_infer_formats() {
+        let facade = SpreadsheetFacade::new();
+        let entries = ["$1,234.50", "3.5%", "$-2", "12%", "45 kg", "7"];
+        for (row, entry) in entries.iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(0, row as u32), entry)
+                .unwrap();
+        }
+
+        let column: Vec<_> = (0..entries.len() as u32)
+            .map(|row| {
+                let address = CellAddress::new(0, row);
+                (
+                    facade.get_cell(&address).unwrap().get_computed_value(),
+                    facade.get_effective_format(&address),
+                    facade.get_display_value(&address).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            column,
+            vec![
+                (
+                    CellValue::Number(1234.5),
+                    Some(CellFormat::currency("$", 2)),
+                    "$1234.50".to_string()
+                ),
+                (
+                    CellValue::Number(0.035),
+                    Some(CellFormat::percent(1)),
+                    "3.5%".to_string()
+                ),
+                (
+                    CellValue::Number(-2.0),
+                    Some(CellFormat::currency("$", 0)),
+                    "-$2".to_string()
+                ),
+                (
+                    CellValue::Number(0.12),
+                    Some(CellFormat::percent(0)),
+                    "12%".to_string()
+                ),
+                (
+                    CellValue::Number(45.0),
+                    Some(CellFormat::unit("kg", 0)),
+                    "45 kg".to_string()
+                ),
+                (CellValue::Number(7.0), None, "7".to_string()),
+            ]
+        );
+
+        // An explicit format is never replaced by an inferred one
+        let fixed = CellAddress::new(1, 0);
+        facade
+            .set_cell_format(&fixed, CellFormat::number(1))
+            .unwrap();
+        facade.set_cell_value(&fixed, "$5").unwrap();
+        assert_eq!(facade.get_display_value(&fixed).unwrap(), "5.0");
+    }
+
+    #[test]
+    fn test_date_arithmetic_shows_dates() {
+        let facade = SpreadsheetFacade::new();
+        let cells = [
+            ("A1", "1/15/2024"),
+            ("A2", "=A1+30"),
+            ("A3", "=A2-A1"),
+            ("A4", "=DATE(2024,2,29)"),
+            ("A5", "=EOMONTH(A1,1)"),
+            ("A6", "=DATEDIF(A1,\"2025-03-01\",\"M\")"),
+            ("A7", "=YEAR(A2)*100+MONTH(A2)"),
+        ];
+        for (address, input) in cells {
+            facade
+                .set_cell_value(&CellAddress::parse_a1_notation(address).unwrap(), input)
+                .unwrap();
+        }
+
+        let shown: Vec<_> = cells
+            .iter()
+            .map(|(address, _)| {
+                let address = CellAddress::parse_a1_notation(address).unwrap();
+                facade.get_display_value(&address).unwrap()
+            })
+            .collect();
+        assert_eq!(
+            shown,
+            vec![
+                "1/15/2024",
+                "2/14/2024",
+                "30",
+                "2024-02-29",
+                "2024-02-29",
+                "13",
+                "202402"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sum_of_currency_column_shows_currency() {
+        let facade = SpreadsheetFacade::new();
+        for (row, entry) in ["Price", "$10.00", "$2.50", "$7.25"].iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(0, row as u32), entry)
+                .unwrap();
+        }
+
+        let total = CellAddress::new(0, 4);
+        facade.set_cell_value(&total, "=SUM(A2:A4)").unwrap();
+        assert_eq!(
+            facade.get_effective_format(&total),
+            Some(CellFormat::currency("$", 2))
+        );
+        assert_eq!(facade.get_display_value(&total).unwrap(), "$19.75");
+
+        // Mixing in a percent, or doing more than aggregating, infers nothing
+        facade
+            .set_cell_value(&CellAddress::new(1, 0), "5%")
+            .unwrap();
+        let mixed = CellAddress::new(1, 1);
+        facade.set_cell_value(&mixed, "=A2+B1").unwrap();
+        assert_eq!(facade.get_effective_format(&mixed), None);
+        let scaled = CellAddress::new(1, 2);
+        facade.set_cell_value(&scaled, "=SUM(A2:A4)*2").unwrap();
+        assert_eq!(facade.get_effective_format(&scaled), None);
+
+        let converted = CellAddress::new(1, 3);
+        facade
+            .set_cell_value(&converted, "=CONVERT(2, \"kg\", \"lbm\")")
+            .unwrap();
+        let CellValue::Number(pounds) = facade.get_cell(&converted).unwrap().get_computed_value()
+        else {
+            panic!("CONVERT should give a number");
+        };
+        assert!((pounds - 4.409_245).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_style_edit_reaches_every_styled_cell_without_writes() {
+        let facade = SpreadsheetFacade::new();
+        let column = CellRange::new(CellAddress::new(0, 0), CellAddress::new(0, 9_999));
+        facade.apply_style(&column, "result").unwrap();
+        for row in [0, 5_000, 9_999] {
+            facade
+                .set_cell_value(&CellAddress::new(0, row), "1.5")
+                .unwrap();
+        }
+        assert_eq!(
+            facade.get_cell_style(&CellAddress::new(0, 42)).as_deref(),
+            Some("Result")
+        );
+        assert_eq!(
+            facade
+                .get_display_value(&CellAddress::new(0, 5_000))
+                .unwrap(),
+            "1.50"
+        );
+
+        // Editing the style leaves the per-cell data untouched
+        let before = facade.get_formats();
+        facade
+            .define_style("Result", CellFormat::currency("€", 0))
+            .unwrap();
+        assert_eq!(facade.get_formats(), before);
+        for row in [0, 5_000, 9_999] {
+            assert_eq!(
+                facade.get_display_value(&CellAddress::new(0, row)).unwrap(),
+                "€2"
+            );
+        }
+    }
+
+    #[test]
+    fn test_style_precedence_and_removal() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = CellAddress::new(0, 0);
+        let a2 = CellAddress::new(0, 1);
+        facade.set_cell_value(&a1, "0.5").unwrap();
+        facade.set_cell_value(&a2, "0.5").unwrap();
+
+        // A style outranks the column default; an explicit format outranks both
+        facade
+            .set_column_format(0, Some(CellFormat::number(3)))
+            .unwrap();
+        facade
+            .apply_style(&CellRange::new(a1, a2), "Percent")
+            .unwrap();
+        assert_eq!(facade.get_display_value(&a1).unwrap(), "50.0%");
+        facade.set_cell_format(&a2, CellFormat::number(1)).unwrap();
+        assert_eq!(facade.get_display_value(&a2).unwrap(), "0.5");
+
+        // Removal is blocked while cells use the style unless asked to convert
+        facade
+            .define_style("Share", CellFormat::percent(0))
+            .unwrap();
+        facade
+            .apply_style(&CellRange::new(a1, a2), "share")
+            .unwrap();
+        facade.set_cell_format(&a2, CellFormat::number(1)).unwrap();
+        assert!(facade.remove_style("Share", StyleRemoval::Block).is_err());
+        assert_eq!(facade.get_display_value(&a1).unwrap(), "50%");
+
+        facade
+            .remove_style("Share", StyleRemoval::ConvertToFormats)
+            .unwrap();
+        assert!(facade.get_style("Share").is_none());
+        assert_eq!(facade.get_cell_style(&a1), None);
+        assert_eq!(facade.get_display_value(&a1).unwrap(), "50%");
+        assert_eq!(facade.get_display_value(&a2).unwrap(), "0.5");
+
+        // Clearing formats drops the style as well
+        facade
+            .apply_style(&CellRange::new(a1, a1), "Input")
+            .unwrap();
+        facade.clear_formats(&a1).unwrap();
+        assert_eq!(facade.get_cell_style(&a1), None);
+        assert_eq!(facade.get_display_value(&a1).unwrap(), "0.500");
+    }
+
+    #[test]
+    fn test_pivot_refresh_after_source_edits() {
+        use crate::pivot::{GRAND_TOTAL_LABEL, PivotAggregation};
+
+        let facade = SpreadsheetFacade::new();
+        let rows = [("West", "10"), ("East", "5"), ("West", "3"), ("North", "4")];
+        for (row, (region, amount)) in rows.iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(0, row as u32), region)
+                .unwrap();
+            facade
+                .set_cell_value(&CellAddress::new(1, row as u32), amount)
+                .unwrap();
+        }
+
+        let source = CellRange::new(CellAddress::new(0, 0), CellAddress::new(1, 9));
+        let anchor = CellAddress::new(3, 0);
+        let config = PivotConfig::new(vec![0], 1, PivotAggregation::Sum).with_grand_totals(true);
+        let output = facade.pivot_range(&source, config, &anchor).unwrap();
+        // Header, East, North, West, total
+        assert_eq!(output.end, CellAddress::new(4, 4));
+        assert_eq!(
+            facade.get_cell_raw_value(&CellAddress::new(4, 3)),
+            Some(CellValue::Number(13.0))
+        );
+
+        // Output is plain values, so it only changes on refresh
+        facade
+            .set_cell_value(&CellAddress::new(1, 0), "20")
+            .unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&CellAddress::new(4, 3)),
+            Some(CellValue::Number(13.0))
+        );
+        facade.refresh_pivot(&anchor).unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&CellAddress::new(4, 3)),
+            Some(CellValue::Number(23.0))
+        );
+
+        // Removing the North row shrinks the output and clears the old total row
+        facade.delete_cell(&CellAddress::new(0, 3)).unwrap();
+        facade.delete_cell(&CellAddress::new(1, 3)).unwrap();
+        let output = facade.refresh_pivot(&anchor).unwrap();
+        assert_eq!(output.end, CellAddress::new(4, 3));
+        assert_eq!(
+            facade.get_cell_raw_value(&CellAddress::new(3, 3)),
+            Some(CellValue::string_from_str(GRAND_TOTAL_LABEL))
+        );
+        assert!(facade.get_cell(&CellAddress::new(3, 4)).is_none());
+        assert!(facade.get_cell(&CellAddress::new(4, 4)).is_none());
+        assert_eq!(facade.get_pivot(&anchor).unwrap().output, output);
+    }
+
+    #[test]
+    fn test_pivot_output_cannot_overlap_source() {
+        use crate::pivot::PivotAggregation;
+
+        let facade = SpreadsheetFacade::new();
+        facade.set_cell_value(&CellAddress::new(0, 0), "a").unwrap();
+        let source = CellRange::new(CellAddress::new(0, 0), CellAddress::new(1, 5));
+        let config = PivotConfig::new(vec![0], 1, PivotAggregation::Count);
+        assert!(
+            facade
+                .pivot_range(&source, config, &CellAddress::new(1, 2))
+                .is_err()
+        );
+        assert!(facade.refresh_pivot(&CellAddress::new(1, 2)).is_err());
+    }
+
+    #[test]
+    fn test_range_stats_are_cached_until_the_range_changes() {
+        use crate::conditional::{ConditionalFormatRule, DataBar};
+
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (row, input) in ["1", "2", "3", "=C1*2"].iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(0, row as u32), input)
+                .unwrap();
+        }
+        facade.set_cell_value(&cell("C1"), "2").unwrap();
+        let range = CellRange::from_string("A1:A4").unwrap();
+        facade
+            .add_conditional_format(ConditionalFormatRule::data_bar(
+                range,
+                DataBar::new("#638ec6"),
+            ))
+            .unwrap();
+
+        let stats = facade.conditional_range_stats(&range);
+        assert_eq!((stats.min(), stats.max()), (Some(1.0), Some(4.0)));
+        assert!(Arc::ptr_eq(&stats, &facade.conditional_range_stats(&range)));
+        // A cell outside the range leaves it alone
+        facade.set_cell_value(&cell("D9"), "100").unwrap();
+        let fill = facade.get_conditional_fill(&cell("A2")).unwrap();
+        assert_eq!(fill.data_bar.unwrap().end, 0.5);
+        assert_eq!(facade.range_stats_counts(), (2, 1));
+
+        // Neither does one outside it that a formula inside it reads, until
+        // the formula's value changes
+        facade.set_cell_value(&cell("C1"), "4").unwrap();
+        let stats = facade.conditional_range_stats(&range);
+        assert_eq!(stats.max(), Some(8.0));
+        assert_eq!(facade.range_stats_counts(), (2, 2));
+
+        facade.set_cell_value(&cell("A1"), "-8").unwrap();
+        assert_eq!(facade.conditional_range_stats(&range).min(), Some(-8.0));
+        facade.delete_cell(&cell("A1")).unwrap();
+        assert_eq!(facade.conditional_range_stats(&range).min(), Some(2.0));
+        // Moving cells drops every range
+        facade.insert_rows(0, 1).unwrap();
+        facade.conditional_range_stats(&range);
+        assert_eq!(facade.range_stats_counts(), (2, 5));
+
+        // Cells without a number draw nothing
+        facade.set_cell_value(&cell("A2"), "text").unwrap();
+        assert_eq!(facade.get_conditional_fill(&cell("A2")), None);
+        assert_eq!(facade.get_conditional_fill(&cell("B2")), None);
+    }
+
+    #[test]
+    fn test_conditional_formats_are_edited_and_saved() {
+        use crate::conditional::{ColorScale, ConditionalFormatRule, DataBar};
+
+        let facade = SpreadsheetFacade::new();
+        let range = CellRange::from_string("B2:B6").unwrap();
+        let scale = ConditionalFormatRule::color_scale(
+            range,
+            ColorScale::three_color("#f8696b", "#ffeb84", "#63be7b"),
+        );
+        assert_eq!(facade.add_conditional_format(scale.clone()).unwrap(), 0);
+        let bar = ConditionalFormatRule::data_bar(range, DataBar::new("#638ec6"));
+        assert_eq!(facade.add_conditional_format(bar.clone()).unwrap(), 1);
+        assert!(
+            facade
+                .add_conditional_format(ConditionalFormatRule::data_bar(
+                    range,
+                    DataBar::new("blue")
+                ))
+                .is_err()
+        );
+
+        let narrow = ConditionalFormatRule {
+            range: CellRange::from_string("B2:B3").unwrap(),
+            ..bar.clone()
+        };
+        facade.update_conditional_format(1, narrow.clone()).unwrap();
+        assert!(facade.update_conditional_format(2, bar).is_err());
+        assert_eq!(
+            facade.get_conditional_formats(),
+            vec![scale.clone(), narrow.clone()]
+        );
+
+        // Both come back from the sidecar, once however often it is read
+        let export = facade.export_csv(true);
+        let sidecar = Sidecar::from_json(&export.sidecar.unwrap().to_json()).unwrap();
+        assert_eq!(sidecar.conditional_formats[1].range, "B2:B3");
+        let restored = SpreadsheetFacade::new();
+        restored.import_csv(&export.csv, Some(&sidecar)).unwrap();
+        restored.import_csv(&export.csv, Some(&sidecar)).unwrap();
+        assert_eq!(
+            restored.get_conditional_formats(),
+            vec![scale, narrow.clone()]
+        );
+
+        assert_eq!(facade.remove_conditional_format(0).unwrap().range, range);
+        assert_eq!(facade.get_conditional_formats(), vec![narrow]);
+        assert!(facade.remove_conditional_format(1).is_err());
+    }
+
+    #[test]
+    fn test_conditional_formats_follow_row_and_column_edits() {
+        use crate::conditional::{ConditionalFormatRule, DataBar};
+
+        let facade = SpreadsheetFacade::new();
+        let range = |a1: &str| CellRange::from_string(a1).unwrap();
+        let ranges = || {
+            facade
+                .get_conditional_formats()
+                .iter()
+                .map(|rule| rule.range)
+                .collect::<Vec<_>>()
+        };
+        for (row, value) in ["2", "4", "8"].iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(1, row as u32 + 1), value)
+                .unwrap();
+        }
+        let bar = |a1: &str| ConditionalFormatRule::data_bar(range(a1), DataBar::new("#638ec6"));
+        facade.add_conditional_format(bar("B2:B4")).unwrap();
+        facade.add_conditional_format(bar("D1:D2")).unwrap();
+
+        // A row above the bars moves them onto the same cells
+        facade.insert_rows(0, 1).unwrap();
+        assert_eq!(ranges(), [range("B3:B5"), range("D2:D3")]);
+        let fill = |a1: &str| facade.get_conditional_fill(&CellAddress::from_a1(a1).unwrap());
+        assert!(fill("B2").is_none());
+        assert!(fill("B5").is_some());
+
+        // Rows inside grow a rule, deletions shrink it or drop it
+        facade.insert_rows(3, 2).unwrap();
+        assert_eq!(ranges(), [range("B3:B7"), range("D2:D3")]);
+        facade.delete_rows(0, 2).unwrap();
+        assert_eq!(ranges(), [range("B1:B5"), range("D1:D1")]);
+        facade.delete_columns(3, 1).unwrap();
+        assert_eq!(ranges(), [range("B1:B5")]);
+        facade.insert_columns(0, 1).unwrap();
+        assert_eq!(ranges(), [range("C1:C5")]);
+    }
+
+    #[test]
+    fn test_running_aggregate_formulas_recalculate() {
+        let facade = SpreadsheetFacade::new();
+        for (row, value) in ["5", "", "3", "x", "2"].iter().enumerate() {
+            if !value.is_empty() {
+                facade
+                    .set_cell_value(&CellAddress::new(1, row as u32 + 1), value)
+                    .unwrap();
+            }
+        }
+        let source = CellRange::from_string("B2:B6").unwrap();
+        let written = facade
+            .running_aggregate(&source, 2, RunningAggregate::Sum, false)
+            .unwrap();
+        assert_eq!(written.len(), 5);
+
+        let formula = |a1: &str| {
+            let cell = facade.get_cell(&CellAddress::from_a1(a1).unwrap()).unwrap();
+            cell.formula_text.unwrap().to_string()
+        };
+        let value = |a1: &str| {
+            facade
+                .get_cell(&CellAddress::from_a1(a1).unwrap())
+                .unwrap()
+                .get_computed_value()
+        };
+        assert_eq!(formula("C2"), "SUM($B$2:B2)");
+        assert_eq!(formula("C4"), "SUM($B$2:B4)");
+        assert_eq!(formula("C6"), "SUM($B$2:B6)");
+        assert_eq!(value("C6"), CellValue::Number(10.0));
+
+        // Editing a source cell mid-column updates the rows below it
+        facade.set_cell_value(&CellAddress::new(1, 2), "4").unwrap();
+        assert_eq!(value("C2"), CellValue::Number(5.0));
+        assert_eq!(value("C3"), CellValue::Number(9.0));
+        assert_eq!(value("C6"), CellValue::Number(14.0));
+
+        assert!(
+            facade
+                .running_aggregate(&source, 1, RunningAggregate::Sum, false)
+                .is_err()
+        );
+        let two_columns = CellRange::from_string("A2:B6").unwrap();
+        assert!(
+            facade
+                .running_aggregate(&two_columns, 3, RunningAggregate::Sum, false)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_running_aggregate_values_only() {
+        let facade = SpreadsheetFacade::new();
+        for (row, value) in ["4", "=A1*2", "text", "3"].iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(0, row as u32), value)
+                .unwrap();
+        }
+        let source = CellRange::from_string("A1:A4").unwrap();
+        facade
+            .running_aggregate(&source, 1, RunningAggregate::Max, true)
+            .unwrap();
+
+        let cells: Vec<Cell> = (0..4)
+            .map(|row| facade.get_cell(&CellAddress::new(1, row)).unwrap())
+            .collect();
+        assert!(cells.iter().all(|cell| !cell.has_formula()));
+        let values: Vec<CellValue> = cells.iter().map(Cell::get_computed_value).collect();
+        assert_eq!(values, [4.0, 8.0, 8.0, 8.0].map(CellValue::Number).to_vec());
+
+        // Values do not follow later edits
+        facade
+            .set_cell_value(&CellAddress::new(0, 3), "20")
+            .unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&CellAddress::new(1, 3)),
+            Some(CellValue::Number(8.0))
+        );
+    }
+
+    #[test]
+    fn test_export_numeric_column_places_nan() {
+        let facade = SpreadsheetFacade::new();
+        facade
+            .set_cell_value(&CellAddress::new(1, 2), "1.5")
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(1, 3), "text")
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(1, 4), "=1/0")
+            .unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(1, 5), "=2*3")
+            .unwrap();
+
+        // Buffer is reused across calls and may be larger than the range
+        let mut buffer = vec![0.0; 8];
+        let written = facade.export_numeric_column(1, 2, 6, &mut buffer);
+        assert_eq!(written, 5);
+        assert_eq!(buffer[0], 1.5);
+        assert!(buffer[1].is_nan() && buffer[2].is_nan() && buffer[4].is_nan());
+        assert_eq!(buffer[3], 6.0);
+        assert_eq!(buffer[5..], [0.0, 0.0, 0.0]);
+
+        // A short buffer truncates the export
+        let mut short = [0.0; 2];
+        assert_eq!(facade.export_numeric_column(1, 2, 6, &mut short), 2);
+        assert_eq!(short[0], 1.5);
+        assert_eq!(facade.export_numeric_column(1, 6, 2, &mut short), 0);
+    }
+
+    #[test]
+    fn test_export_numeric_columns_interleaved() {
+        let facade = SpreadsheetFacade::new();
+        facade.set_cell_value(&CellAddress::new(0, 0), "1").unwrap();
+        facade.set_cell_value(&CellAddress::new(0, 1), "2").unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(2, 1), "20")
+            .unwrap();
+
+        let mut buffer = vec![0.0; 6];
+        assert_eq!(facade.export_numeric_columns(&[0, 2], 0, 2, &mut buffer), 3);
+        assert_eq!(buffer[0], 1.0);
+        assert!(buffer[1].is_nan());
+        assert_eq!(buffer[2..4], [2.0, 20.0]);
+        assert!(buffer[4].is_nan() && buffer[5].is_nan());
+    }
+
+    #[test]
+    fn test_sheet_health_across_sheets() {
+        let facade = SpreadsheetFacade::new();
+        let first = facade.get_active_sheet();
+        facade.add_sheet("Data").unwrap();
+        let sheet_cells = |name: &str| {
+            let manager = facade.sheet_manager.lock().unwrap();
+            manager.workbook().get_sheet(name).unwrap().cells()
+        };
+
+        facade
+            .set_cell_value(&CellAddress::new(2, 4), "=1/0")
+            .unwrap();
+        facade.set_cell_value(&CellAddress::new(0, 0), "1").unwrap();
+        // Circular errors are only produced by recalculation, so store one directly
+        sheet_cells(&first)
+            .set(
+                &CellAddress::new(1, 1),
+                Cell::new(CellValue::from_error(ErrorType::CircularDependency {
+                    cells: vec![],
+                })),
+            )
+            .unwrap();
+        facade.set_active_sheet("Data").unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(0, 0), "42")
+            .unwrap();
+
+        let health = facade.get_sheet_health(&first).unwrap();
+        assert_eq!(health.error_cells, 2);
+        assert_eq!(health.circular_cells, 1);
+        assert_eq!(health.first_error, Some(CellAddress::new(1, 1)));
+        assert!(facade.get_sheet_health("Data").unwrap().is_healthy());
+        assert!(facade.get_sheet_health("Missing").is_none());
+
+        // Structural changes move the indexed errors with their cells
+        sheet_cells(&first).insert_row(0).unwrap();
+        let health = facade.get_sheet_health(&first).unwrap();
+        assert_eq!(health.error_cells, 2);
+        assert_eq!(health.first_error, Some(CellAddress::new(1, 2)));
+
+        // Fixing the cells brings the counts back down
+        facade.set_active_sheet(&first).unwrap();
+        facade.set_cell_value(&CellAddress::new(1, 2), "2").unwrap();
+        let health = facade.get_sheet_health(&first).unwrap();
+        assert_eq!(health.error_cells, 1);
+        assert_eq!(health.circular_cells, 0);
+        assert_eq!(health.first_error, Some(CellAddress::new(2, 5)));
+
+        facade.delete_cell(&CellAddress::new(2, 5)).unwrap();
+        let summary = facade.get_workbook_health();
+        assert_eq!(summary.len(), 2);
+        assert!(summary.iter().all(|(_, health)| health.is_healthy()));
+    }
+
+    /// Resolver that answers from a fixed table, like a host with no latency
+    struct FakeResolver(
+        Vec<(
+            &'static str,
+            std::result::Result<&'static str, &'static str>,
+        )>,
+    );
+
+    impl ExternalResolver for FakeResolver {
+        fn fetch(&self, url: &str) -> std::result::Result<String, String> {
+            self.0
+                .iter()
+                .find(|(known, _)| *known == url)
+                .map(|(_, response)| response.map(str::to_string).map_err(str::to_string))
+                .unwrap_or_else(|| Err(format!("No route to {}", url)))
+        }
+    }
+
+    #[test]
+    fn test_fetch_resolves_and_updates_dependents() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        let c1 = CellAddress::new(2, 0);
+
+        facade
+            .set_cell_value(&a1, "=FETCH(\"https://api.test/q\", \"json:$.price\")")
+            .unwrap();
+        facade.set_cell_value(&b1, "=A1*2").unwrap();
+        facade.set_cell_value(&c1, "=B1+1").unwrap();
+
+        assert_eq!(
+            facade.get_cell_raw_value(&a1),
+            Some(CellValue::from_error(ErrorType::GettingData))
+        );
+        // Waiting for data does not count against the sheet
+        assert!(facade.get_sheet_health("Sheet1").unwrap().is_healthy());
+
+        let resolver = FakeResolver(vec![("https://api.test/q", Ok(r#"{"price": 21}"#))]);
+        let changed = facade.resolve_external_with(&resolver).unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&a1),
+            Some(CellValue::Number(21.0))
+        );
+        assert_eq!(
+            facade.get_cell_raw_value(&b1),
+            Some(CellValue::Number(42.0))
+        );
+        assert_eq!(
+            facade.get_cell_raw_value(&c1),
+            Some(CellValue::Number(43.0))
+        );
+        assert_eq!(changed.len(), 3);
+        assert_eq!(facade.pending_external_count(), 0);
+
+        // A second formula reading the same data uses the cached value
+        facade
+            .set_cell_value(
+                &CellAddress::new(0, 1),
+                "=FETCH(\"https://api.test/q\", \"json:$.price\")",
+            )
+            .unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&CellAddress::new(0, 1)),
+            Some(CellValue::Number(21.0))
+        );
+        assert!(facade.take_external_requests().is_empty());
+
+        // A refresh asks the host again without clearing the cells
+        assert_eq!(facade.refresh_external(), 1);
+        assert_eq!(
+            facade.get_cell_raw_value(&a1),
+            Some(CellValue::Number(21.0))
+        );
+        let resolver = FakeResolver(vec![("https://api.test/q", Ok(r#"{"price": 5}"#))]);
+        facade.resolve_external_with(&resolver).unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&c1),
+            Some(CellValue::Number(11.0))
+        );
+    }
+
+    #[test]
+    fn test_fetch_errors_show_as_value_error() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = CellAddress::new(0, 0);
+        let a2 = CellAddress::new(0, 1);
+
+        facade
+            .set_cell_value(&a1, "=FETCH(\"https://api.test/missing\")")
+            .unwrap();
+        facade
+            .set_cell_value(&a2, "=FETCH(\"https://api.test/q\", \"json:$.nope\")")
+            .unwrap();
+
+        let resolver = FakeResolver(vec![
+            ("https://api.test/missing", Err("HTTP 404 Not Found")),
+            ("https://api.test/q", Ok(r#"{"price": 21}"#)),
+        ]);
+        facade.resolve_external_with(&resolver).unwrap();
+
+        assert_eq!(
+            facade.get_cell_raw_value(&a1),
+            Some(CellValue::from_error(ErrorType::ExternalData {
+                message: "HTTP 404 Not Found".to_string()
+            }))
+        );
+        match facade.get_cell_raw_value(&a2) {
+            Some(CellValue::Error(error)) => {
+                assert_eq!(error.excel_code(), "#VALUE!");
+                assert!(error.description().contains("$.nope"));
+            }
+            other => panic!("expected an error, got {:?}", other),
+        }
+        assert_eq!(facade.get_sheet_health("Sheet1").unwrap().error_cells, 2);
+    }
+
+    #[test]
+    fn test_editing_fetch_away_cancels_request() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = CellAddress::new(0, 0);
+        let request = ExternalRequest::new("https://api.test/slow", None);
+
+        facade
+            .set_cell_value(&a1, "=FETCH(\"https://api.test/slow\")")
+            .unwrap();
+        assert_eq!(facade.take_external_requests(), vec![request.clone()]);
+
+        facade.set_cell_value(&a1, "7").unwrap();
+        assert_eq!(
+            facade.take_cancelled_external_requests(),
+            vec![request.clone()]
+        );
+        assert_eq!(facade.pending_external_count(), 0);
+
+        // The late response does not overwrite the new value
+        let changed = facade
+            .resolve_external(&request, Ok("99".to_string()))
+            .unwrap();
+        assert!(changed.is_empty());
+        assert_eq!(facade.get_cell_raw_value(&a1), Some(CellValue::Number(7.0)));
+    }
+
+    #[test]
+    fn test_aggregate_density_is_cached_until_the_sheet_changes() {
+        let facade = SpreadsheetFacade::new();
+        facade.set_cell_value(&CellAddress::new(0, 0), "1").unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(40, 90), "text")
+            .unwrap();
+
+        let first = facade.aggregate_density(16);
+        assert_eq!(first.blocks.len(), 2);
+        assert!(Arc::ptr_eq(&first, &facade.aggregate_density(16)));
+        assert!(!Arc::ptr_eq(&first, &facade.aggregate_density(8)));
+
+        let coarse = facade.aggregate_density(8);
+        facade.set_cell_value(&CellAddress::new(1, 0), "2").unwrap();
+        let updated = facade.aggregate_density(8);
+        assert!(!Arc::ptr_eq(&coarse, &updated));
+        assert_eq!(updated.blocks[0].count, 2);
+    }
+
+    fn recording_facade() -> (SpreadsheetFacade, Arc<Mutex<Vec<DomainEvent>>>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let mut events = EventAdapter::new_empty();
+        events
+            .subscribe(Box::new(move |event| {
+                sink.lock().unwrap().push(event.clone())
+            }))
+            .unwrap();
+        let facade = SpreadsheetFacade::with_ports(
+            Arc::new(RepositoryAdapter::new_empty()),
+            Arc::new(events),
+        );
+        (facade, seen)
+    }
+
+    #[test]
+    fn test_change_events_carry_old_state() {
+        let (facade, seen) = recording_facade();
+        let a1 = CellAddress::new(0, 0);
+
+        facade.set_cell_value(&a1, "5").unwrap();
+        facade.set_cell_value(&a1, "=1+2").unwrap();
+        facade.delete_cell(&a1).unwrap();
+
+        let seen = seen.lock().unwrap();
+        match &seen[0] {
+            DomainEvent::CellChanged {
+                old_value,
+                new_value,
+                ..
+            } => {
+                assert_eq!(*old_value, None);
+                assert_eq!(*new_value, CellValue::Number(5.0));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        match &seen[1] {
+            DomainEvent::CellChanged {
+                old_value,
+                old_formula,
+                new_value,
+                new_formula,
+                ..
+            } => {
+                assert_eq!(*old_value, Some(CellValue::Number(5.0)));
+                assert_eq!(*old_formula, None);
+                assert_eq!(*new_value, CellValue::Number(3.0));
+                assert_eq!(new_formula.as_deref(), Some("1+2"));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        match &seen[2] {
+            DomainEvent::CellDeleted {
+                old_value,
+                old_formula,
+                ..
+            } => {
+                assert_eq!(*old_value, CellValue::Number(3.0));
+                assert_eq!(old_formula.as_deref(), Some("1+2"));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_batch_commit_inlines_small_deltas() {
+        let (facade, seen) = recording_facade();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        facade.set_cell_value(&a1, "1").unwrap();
+
+        let batch = facade.begin_batch().unwrap();
+        // A nested caller joins the open batch
+        assert_eq!(facade.begin_batch().unwrap(), batch);
+        facade.set_cell_value(&a1, "2").unwrap();
+        facade.set_cell_value(&b1, "x").unwrap();
+        facade.set_cell_value(&a1, "3").unwrap();
+        facade.commit_batch(&batch).unwrap();
+        let before_outer_commit = seen.lock().unwrap().len();
+        facade.commit_batch(&batch).unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), before_outer_commit + 1);
+        let started = seen
+            .iter()
+            .filter(|event| matches!(event, DomainEvent::BatchStarted { .. }))
+            .count();
+        assert_eq!(started, 1);
+        match seen.last().unwrap() {
+            DomainEvent::BatchCommitted { batch_id, delta } => {
+                assert_eq!(*batch_id, batch);
+                assert_eq!(
+                    *delta,
+                    BatchDelta::Cells(vec![
+                        CellDelta {
+                            address: a1,
+                            old_value: Some(CellValue::Number(1.0)),
+                            old_formula: None,
+                            new_value: Some(CellValue::Number(3.0)),
+                            new_formula: None,
+                        },
+                        CellDelta {
+                            address: b1,
+                            old_value: None,
+                            old_formula: None,
+                            new_value: Some(CellValue::from_string("x".to_string())),
+                            new_formula: None,
+                        },
+                    ])
+                );
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(facade.commit_batch(&batch).is_err());
+    }
+
+    #[test]
+    fn test_large_batch_is_summarised_and_fetchable() {
+        let (facade, seen) = recording_facade();
+        let rows = BATCH_DELTA_INLINE_LIMIT as u32 + 4;
+
+        let batch = facade.begin_batch().unwrap();
+        for row in 0..rows {
+            facade
+                .set_cell_value(&CellAddress::new(2, row), "1")
+                .unwrap();
+        }
+        facade.commit_batch(&batch).unwrap();
+
+        match seen.lock().unwrap().last().unwrap() {
+            DomainEvent::BatchCommitted { delta, .. } => assert_eq!(
+                *delta,
+                BatchDelta::Summary {
+                    changed: rows as usize,
+                    bounds: Some(CellRange::new(
+                        CellAddress::new(2, 0),
+                        CellAddress::new(2, rows - 1)
+                    )),
+                }
+            ),
+            other => panic!("unexpected event {:?}", other),
+        }
+        let changes = facade.fetch_batch_delta(&batch).unwrap();
+        assert_eq!(changes.len(), rows as usize);
+        assert_eq!(changes[0].old_value, None);
+        assert!(facade.fetch_batch_delta("batch_unknown").is_none());
+    }
+
+    #[test]
+    fn test_compact_after_clearing_shrinks_memory_and_keeps_content() {
+        let facade = SpreadsheetFacade::new();
+        let rows = 2000;
+        let batch = facade.begin_batch().unwrap();
+        for row in 0..rows {
+            let address = CellAddress::new(0, row);
+            facade
+                .set_cell_value(&address, &format!("item {}", row))
+                .unwrap();
+        }
+        facade.commit_batch(&batch).unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(1, 0), "=A1")
+            .unwrap();
+
+        let batch = facade.begin_batch().unwrap();
+        for row in 10..rows {
+            facade.delete_cell(&CellAddress::new(0, row)).unwrap();
+        }
+        facade.commit_batch(&batch).unwrap();
+        let hash = facade.content_hash();
+
+        let report = facade.compact(&CompactOptions { history_budget: 0 });
+        let cells = report.get(memory::CELLS).unwrap();
+        assert!(cells.after < cells.before, "{:?}", cells);
+        assert!(report.get(memory::BATCH_HISTORY).unwrap().after < 1024);
+        assert!(report.after_bytes() < report.before_bytes());
+        assert!(facade.fetch_batch_delta(&batch).is_none());
+
+        assert_eq!(facade.content_hash(), hash);
+        assert_eq!(
+            facade.get_cell_value(&CellAddress::new(1, 0)).as_deref(),
+            Some("item 0")
+        );
+        // Nothing left to give back
+        assert_eq!(facade.compact(&CompactOptions::default()).freed_bytes(), 0);
+    }
+
+    #[test]
+    fn test_auto_compaction_runs_when_the_batch_commits() {
+        let facade = SpreadsheetFacade::new();
+        let cells_bytes =
+            |facade: &SpreadsheetFacade| facade.memory_report().get(memory::CELLS).unwrap().after;
+        for row in 0..500 {
+            facade
+                .set_cell_value(&CellAddress::new(0, row), "1")
+                .unwrap();
+        }
+        facade.set_auto_compact(Some(100));
+
+        let batch = facade.begin_batch().unwrap();
+        for row in 0..400 {
+            facade.delete_cell(&CellAddress::new(0, row)).unwrap();
+        }
+        let during = cells_bytes(&facade);
+        facade.commit_batch(&batch).unwrap();
+        let compacted = cells_bytes(&facade);
+        assert!(compacted < during);
+        assert_eq!(facade.get_all_cells().len(), 100);
+    }
+
+    #[test]
+    fn test_undo_and_redo_publish_their_old_values() {
+        use crate::command::{CommandExecutorImpl, SpreadsheetCommand, UndoRedoManager};
+
+        let (facade, seen) = recording_facade();
+        let facade = Arc::new(Mutex::new(facade));
+        let mut executor = CommandExecutorImpl::new(facade.clone());
+        let mut manager = UndoRedoManager::new();
+        let a1 = CellAddress::new(0, 0);
+
+        manager
+            .execute_command(
+                SpreadsheetCommand::set_cell(a1, None, "1".to_string()),
+                &mut executor,
+            )
+            .unwrap();
+        let old = facade.lock().unwrap().get_cell(&a1);
+        manager
+            .execute_command(
+                SpreadsheetCommand::set_cell(a1, old, "2".to_string()),
+                &mut executor,
+            )
+            .unwrap();
+
+        let values = |event: &DomainEvent| match event {
+            DomainEvent::CellChanged {
+                old_value,
+                new_value,
+                ..
+            } => (old_value.clone(), new_value.clone()),
+            other => panic!("unexpected event {:?}", other),
+        };
+        manager.undo(&mut executor).unwrap();
+        assert_eq!(
+            values(seen.lock().unwrap().last().unwrap()),
+            (Some(CellValue::Number(2.0)), CellValue::Number(1.0))
+        );
+        manager.redo(&mut executor).unwrap();
+        assert_eq!(
+            values(seen.lock().unwrap().last().unwrap()),
+            (Some(CellValue::Number(1.0)), CellValue::Number(2.0))
+        );
+    }
+
+    #[test]
+    fn test_constants_shadow_and_recalculate_dependents() {
+        let facade = SpreadsheetFacade::new();
+        facade.add_sheet("Sheet2").unwrap();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        let c1 = CellAddress::new(2, 0);
+
+        facade
+            .define_constant("TaxRate", "0.2", NameScope::Workbook)
+            .unwrap();
+        facade.set_cell_value(&a1, "100").unwrap();
+        facade.set_cell_value(&b1, "=A1*taxrate").unwrap();
+        facade.set_cell_value(&c1, "=B1+1").unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&b1),
+            Some(CellValue::Number(20.0))
+        );
+
+        // A sheet constant shadows the workbook one on its sheet only
+        let changed = facade
+            .define_constant("TAXRATE", "=0.1", NameScope::Sheet("Sheet1".to_string()))
+            .unwrap();
+        assert!(changed.contains(&("Sheet1".to_string(), b1)));
+        assert!(changed.contains(&("Sheet1".to_string(), c1)));
+        assert_eq!(
+            facade.get_cell_raw_value(&b1),
+            Some(CellValue::Number(10.0))
+        );
+        assert_eq!(
+            facade.get_cell_raw_value(&c1),
+            Some(CellValue::Number(11.0))
+        );
+
+        facade.set_active_sheet("Sheet2").unwrap();
+        facade.set_cell_value(&a1, "=TaxRate").unwrap();
+        assert_eq!(facade.get_cell_raw_value(&a1), Some(CellValue::Number(0.2)));
+
+        // Redefining the workbook constant leaves the shadowed sheet alone
+        let changed = facade
+            .define_constant("TaxRate", "0.25", NameScope::Workbook)
+            .unwrap();
+        assert_eq!(changed, vec![("Sheet2".to_string(), a1)]);
+        assert_eq!(
+            facade.get_cell_raw_value(&a1),
+            Some(CellValue::Number(0.25))
+        );
+
+        // Removing the sheet constant falls back to the workbook one
+        facade.set_active_sheet("Sheet1").unwrap();
+        facade
+            .remove_constant("taxrate", &NameScope::Sheet("Sheet1".to_string()))
+            .unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&b1),
+            Some(CellValue::Number(25.0))
+        );
+
+        // ...and removing that turns dependents to #NAME?
+        facade
+            .remove_constant("TaxRate", &NameScope::Workbook)
+            .unwrap();
+        assert!(matches!(
+            facade.get_cell_raw_value(&b1),
+            Some(CellValue::Error(error)) if matches!(*error, ErrorType::NameError { .. })
+        ));
+        assert!(
+            facade
+                .remove_constant("TaxRate", &NameScope::Workbook)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_constant_definitions_use_other_constants() {
+        let facade = SpreadsheetFacade::new();
+        facade
+            .define_constant("Rate", "0.2", NameScope::Workbook)
+            .unwrap();
+        facade
+            .define_constant("Greeting", "\"hi\"", NameScope::Workbook)
+            .unwrap();
+        facade
+            .define_constant("Net", "1-Rate", NameScope::Workbook)
+            .unwrap();
+        let net = facade.get_constant("net", &NameScope::Workbook).unwrap();
+        assert_eq!(net.name, "Net");
+        assert_eq!(net.value, CellValue::Number(0.8));
+        assert_eq!(net.definition, "1-Rate");
+
+        // Evaluated once: redefining Rate does not change Net
+        facade
+            .define_constant("Rate", "0.5", NameScope::Workbook)
+            .unwrap();
+        assert_eq!(
+            facade
+                .get_constant("Net", &NameScope::Workbook)
+                .unwrap()
+                .value,
+            CellValue::Number(0.8)
+        );
+
+        assert!(
+            facade
+                .define_constant("Bad", "A1*2", NameScope::Workbook)
+                .is_err()
+        );
+        assert!(
+            facade
+                .define_constant("Bad", "Missing+1", NameScope::Workbook)
+                .is_err()
+        );
+        assert!(
+            facade
+                .define_constant("A1", "1", NameScope::Workbook)
+                .is_err()
+        );
+        assert!(
+            facade
+                .define_constant("Rate", "1", NameScope::Sheet("Nope".to_string()))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_named_ranges_in_formulas() {
+        let facade = SpreadsheetFacade::new();
+        for (row, value) in ["10", "20", "30"].iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(1, row as u32), value)
+                .unwrap();
+        }
+        let total = CellAddress::new(3, 0);
+        let second = CellAddress::new(3, 1);
+        let first = CellAddress::new(3, 2);
+        facade.set_cell_value(&total, "=SUM(sales)").unwrap();
+        facade.set_cell_value(&second, "=INDEX(Sales, 2)").unwrap();
+        facade.set_cell_value(&first, "=First*2").unwrap();
+        let value = |address: &CellAddress| facade.get_cell_raw_value(address).unwrap().to_string();
+        assert_eq!(value(&total), "#NAME?");
+
+        let b1 = CellAddress::new(1, 0);
+        let sales = CellRange::new(b1, CellAddress::new(1, 2));
+        facade.define_name("Sales", sales).unwrap();
+        facade.define_name("First", CellRange::new(b1, b1)).unwrap();
+        assert_eq!(
+            (value(&total), value(&second), value(&first)),
+            ("60".into(), "20".into(), "20".into())
+        );
+
+        // Formulas reading a name follow the cells it covers
+        facade.set_cell_value(&b1, "15").unwrap();
+        assert_eq!((value(&total), value(&first)), ("65".into(), "30".into()));
+
+        // Rows inserted inside the range grow it
+        facade.insert_rows(1, 1).unwrap();
+        let grown = CellRange::new(b1, CellAddress::new(1, 3));
+        let listed: Vec<_> = facade
+            .list_names()
+            .into_iter()
+            .map(|named| (named.name, named.range))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("First".to_string(), Some(CellRange::new(b1, b1))),
+                ("Sales".to_string(), Some(grown)),
+            ]
+        );
+        facade.set_cell_value(&CellAddress::new(1, 1), "5").unwrap();
+        assert_eq!(value(&CellAddress::new(3, 0)), "70");
+
+        // Deleting every cell of a range leaves formulas using it #REF!
+        facade.delete_rows(0, 1).unwrap();
+        let (second, first) = (CellAddress::new(3, 1), CellAddress::new(3, 2));
+        assert_eq!(
+            (value(&second), value(&first)),
+            ("20".into(), "#REF!".into())
+        );
+
+        facade.delete_name("SALES").unwrap();
+        assert_eq!(value(&second), "#NAME?");
+        assert!(facade.delete_name("Sales").is_err());
+        assert!(facade.define_name("A1", sales).is_err());
+    }
+
+    #[test]
+    fn test_blank_and_empty_text_conformance() {
+        // A1 is blank, A2 holds the result of ="" and A3 empty text typed
+        // with an apostrophe; both kinds of empty text behave alike
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.set_cell_value(&cell("A2"), "=\"\"").unwrap();
+        facade.set_cell_value(&cell("A3"), "'").unwrap();
+        let result = |formula: &str| {
+            facade.set_cell_value(&cell("H1"), formula).unwrap();
+            facade.get_cell_raw_value(&cell("H1")).unwrap().to_string()
+        };
+
+        // Consumer, formula reading `{}`, result for a blank, for empty text
+        let table = [
+            ("COUNTA", "=COUNTA({})", "0", "1"),
+            ("COUNTA over a range", "=COUNTA({}:{})", "0", "1"),
+            ("COUNT", "=COUNT({})", "0", "0"),
+            ("ISBLANK", "=ISBLANK({})", "TRUE", "FALSE"),
+            ("arithmetic", "={}+1", "1", "1"),
+            ("SUM", "=SUM({})", "0", "0"),
+            ("equals empty text", "={}=\"\"", "TRUE", "TRUE"),
+            ("equals a blank", "={}=Z99", "TRUE", "TRUE"),
+            ("concatenation", "=\"<\"&{}&\">\"", "<>", "<>"),
+        ];
+        for (consumer, formula, blank, empty_text) in table {
+            for (a1, expected) in [("A1", blank), ("A2", empty_text), ("A3", empty_text)] {
+                assert_eq!(
+                    result(&formula.replace("{}", a1)),
+                    expected,
+                    "{consumer} of {a1}"
+                );
+            }
+        }
+
+        // The grid draws both as nothing
+        for a1 in ["A1", "A2", "A3"] {
+            assert_eq!(facade.get_display_value(&cell(a1)).unwrap_or_default(), "");
+        }
+
+        // Empty text counts as used; a blank does not
+        facade.delete_cell(&cell("H1")).unwrap();
+        assert_eq!(facade.get_used_extent(), Some(cell("A3")));
+
+        // CSV keeps typed empty text apart from a blank
+        assert_eq!(
+            facade.export_csv(false).csv.lines().collect::<Vec<_>>(),
+            vec!["", "\"=\"\"\"\"\"", "'"]
+        );
+
+        // Entering nothing deletes the cell instead of storing empty text
+        facade.set_cell_value(&cell("A3"), "").unwrap();
+        assert!(facade.get_cell(&cell("A3")).is_none());
+        assert_eq!(crate::evaluator::parse_cell_value(""), CellValue::Empty);
+
+        // A series does not run through empty text
+        for (row, value) in ["1", "=\"\"", "3"].iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(2, row as u32), value)
+                .unwrap();
+        }
+        let source = CellRange::new(CellAddress::new(2, 0), CellAddress::new(2, 2));
+        let target = CellRange::new(CellAddress::new(2, 3), CellAddress::new(2, 3));
+        let engine = crate::fill::FillEngine::new(facade.active_repository().unwrap());
+        let filled = engine
+            .preview(&crate::fill::FillOperation {
+                source_range: source,
+                target_range: target,
+                direction: crate::fill::FillDirection::Down,
+                pattern: None,
+            })
+            .unwrap();
+        assert_eq!(
+            filled,
+            vec![(CellAddress::new(2, 3), CellValue::Number(1.0))]
+        );
+    }
+
+    #[test]
+    fn test_error_catching_functions() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.set_cell_value(&cell("A1"), "apple").unwrap();
+        facade.set_cell_value(&cell("B1"), "3").unwrap();
+        facade.set_cell_value(&cell("A2"), "=1/0").unwrap();
+        let result = |formula: &str| {
+            facade.set_cell_value(&cell("H1"), formula).unwrap();
+            facade.get_cell_raw_value(&cell("H1")).unwrap().to_string()
+        };
+
+        assert_eq!(result("=IFERROR(1/0, \"fallback\")"), "fallback");
+        assert_eq!(result("=IFERROR(A2, 0)"), "0");
+        // The fallback is only evaluated when it is used
+        assert_eq!(result("=IFERROR(B1*2, 1/0)"), "6");
+        assert_eq!(result("=IFERROR(1/0, 1/0)"), "#DIV/0!");
+        // IFNA catches only #N/A
+        assert_eq!(
+            result("=IFNA(VLOOKUP(\"pear\",A1:B1,2,FALSE), \"none\")"),
+            "none"
+        );
+        assert_eq!(result("=IFNA(1/0, \"none\")"), "#DIV/0!");
+        assert_eq!(result("=NA()"), "#N/A");
+        assert_eq!(result("=IFNA(NA(), \"none\")"), "none");
+        assert_eq!(result("=IFERROR(NA(), 0)"), "0");
+
+        assert_eq!(result("=ISERROR(VLOOKUP(\"pear\",A1:B1,2,FALSE))"), "TRUE");
+        assert_eq!(
+            result("=ISERROR(VLOOKUP(\"apple\",A1:B1,2,FALSE))"),
+            "FALSE"
+        );
+        assert_eq!(result("=ISNA(VLOOKUP(\"pear\",A1:B1,2,FALSE))"), "TRUE");
+        assert_eq!(result("=ISERROR(1/0)"), "TRUE");
+        assert_eq!(result("=ISNA(1/0)"), "FALSE");
+        assert_eq!(result("=ISNA(NA())"), "TRUE");
+        assert_eq!(result("=ISERROR(NA())"), "TRUE");
+        assert_eq!(result("=ISERROR(A2)"), "TRUE");
+        assert_eq!(result("=ISNUMBER(B1)"), "TRUE");
+        assert_eq!(result("=ISNUMBER(A1)"), "FALSE");
+        assert_eq!(result("=ISTEXT(A1)"), "TRUE");
+        assert_eq!(result("=ISLOGICAL(B1>1)"), "TRUE");
+        assert_eq!(result("=ISBLANK(1/0)"), "FALSE");
+    }
+
+    #[test]
+    fn test_defined_names_list_ranges_and_constants_and_restore() {
+        let facade = SpreadsheetFacade::new();
+        facade
+            .define_constant("Rate", "0.2", NameScope::Sheet("Sheet1".to_string()))
+            .unwrap();
+        facade
+            .define_constant("Limit", "10", NameScope::Workbook)
+            .unwrap();
+        let a1 = CellAddress::new(0, 0);
+        facade
+            .define_name("Inputs", CellRange::new(a1, a1))
+            .unwrap();
+
+        let names = facade.defined_names();
+        let listed: Vec<(&str, &NameScope)> = names
+            .iter()
+            .map(|entry| (entry.name.as_str(), &entry.scope))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("Inputs", &NameScope::Workbook),
+                ("Limit", &NameScope::Workbook),
+                ("Rate", &NameScope::Sheet("Sheet1".to_string())),
+            ]
+        );
+        assert!(matches!(names[0].definition, NameDefinition::Range { .. }));
+
+        let json = serde_json::to_string(&names).unwrap();
+        let restored = SpreadsheetFacade::new();
+        let a1 = CellAddress::new(0, 0);
+        restored.set_cell_value(&a1, "=Rate*Limit").unwrap();
+        let changed = restored
+            .restore_defined_names(serde_json::from_str(&json).unwrap())
+            .unwrap();
+        assert_eq!(changed, vec![("Sheet1".to_string(), a1)]);
+        assert_eq!(
+            restored.get_cell_raw_value(&a1),
+            Some(CellValue::Number(2.0))
+        );
+        assert_eq!(restored.defined_names(), names);
+    }
+
+    #[test]
+    fn test_preview_reports_errors_without_storing() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        facade.set_cell_value(&a1, "4").unwrap();
+
+        assert_eq!(
+            facade.evaluate_preview("=A1*2", &b1).unwrap(),
+            CellValue::Number(8.0)
+        );
+        assert_eq!(
+            facade.evaluate_preview("=A1/0", &b1).unwrap(),
+            CellValue::from_error(ErrorType::DivideByZero)
+        );
+        assert!(matches!(
+            facade.evaluate_preview("=B1+1", &b1),
+            Ok(CellValue::Error(error))
+                if matches!(error.as_ref(), ErrorType::CircularDependency { .. })
+        ));
+        assert!(matches!(
+            facade.evaluate_preview("=NOPE(1)", &b1),
+            Ok(CellValue::Error(_))
+        ));
+        assert!(facade.evaluate_preview("=A1*(", &b1).is_err());
+        // Too large to preview while typing
+        assert!(matches!(
+            facade.evaluate_preview("=SUM(A1:Z10000)", &b1),
+            Err(SpreadsheetError::BudgetExceeded)
+        ));
+
+        assert_eq!(facade.cell_count(), 1);
+        assert_eq!(facade.get_cell(&b1), None);
+    }
+
+    #[test]
+    fn test_preview_reads_fetched_data_without_subscribing() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = CellAddress::new(0, 0);
+        let b1 = CellAddress::new(1, 0);
+        let formula = "=FETCH(\"https://api.test/q\", \"json:$.price\")";
+
+        assert_eq!(
+            facade.evaluate_preview(formula, &b1).unwrap(),
+            CellValue::from_error(ErrorType::GettingData)
+        );
+        assert_eq!(facade.pending_external_count(), 0);
+
+        facade.set_cell_value(&a1, formula).unwrap();
+        let resolver = FakeResolver(vec![("https://api.test/q", Ok(r#"{"price": 21}"#))]);
+        facade.resolve_external_with(&resolver).unwrap();
+        assert_eq!(
+            facade
+                .evaluate_preview(&format!("{}+1", formula), &b1)
+                .unwrap(),
+            CellValue::Number(22.0)
+        );
+
+        // Once the cell stops reading it, refreshing does not ask for the
+        // data again on behalf of the preview
+        facade.set_cell_value(&a1, "1").unwrap();
+        assert_eq!(facade.refresh_external(), 0);
+    }
+
+    #[test]
+    fn test_overrides_preview_a_chain_without_touching_it() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (a1, value) in [
+            ("A1", "2"),
+            ("A2", "=A1*10"),
+            ("A3", "=A2+B1"),
+            ("A4", "=A3+NOW()*0"),
+            ("B1", "1"),
+            ("C1", "=B1*100"),
+        ] {
+            facade.set_cell_value(&cell(a1), value).unwrap();
+        }
+        let hash = facade.content_hash();
+        let outputs = vec![cell("A2"), cell("A3"), cell("A4"), cell("C1"), cell("Z9")];
+        let n = CellValue::Number;
+
+        assert_eq!(
+            facade
+                .evaluate_with_overrides(vec![(cell("A1"), n(5.0))], outputs.clone())
+                .unwrap(),
+            vec![n(50.0), n(51.0), n(51.0), n(100.0), CellValue::Empty]
+        );
+        // Overriding a formula replaces it; several overrides combine
+        assert_eq!(
+            facade
+                .evaluate_with_overrides(
+                    vec![(cell("A2"), n(7.0)), (cell("B1"), n(3.0))],
+                    outputs.clone()
+                )
+                .unwrap(),
+            vec![n(7.0), n(10.0), n(10.0), n(300.0), CellValue::Empty]
+        );
+        assert_eq!(
+            facade
+                .evaluate_with_overrides(
+                    vec![(cell("A1"), CellValue::string_from_str("x"))],
+                    vec![cell("A3")]
+                )
+                .unwrap()[0]
+                .type_name(),
+            "error"
+        );
+
+        assert_eq!(facade.content_hash(), hash);
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("A3")),
+            Some(CellValue::Number(21.0))
+        );
+        assert!(!facade.is_stale(&cell("A3")));
+    }
+
+    #[test]
+    fn test_overrides_evaluate_only_the_cells_reading_them() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for row in 1..=2000 {
+            facade
+                .set_cell_value(&cell(&format!("A{}", row)), &row.to_string())
+                .unwrap();
+            facade
+                .set_cell_value(&cell(&format!("B{}", row)), &format!("=A{}*2", row))
+                .unwrap();
+        }
+        facade.set_cell_value(&cell("D1"), "5").unwrap();
+        facade.set_cell_value(&cell("D2"), "=D1+B1").unwrap();
+
+        // B1 goes stale; evaluating it again would give 2000
+        let sheet = facade.get_active_sheet();
+        facade.set_calculation_enabled(&sheet, false).unwrap();
+        facade.set_cell_value(&cell("A1"), "1000").unwrap();
+
+        let values = facade
+            .evaluate_with_overrides(
+                vec![(cell("D1"), CellValue::Number(7.0))],
+                vec![cell("D2"), cell("B1")],
+            )
+            .unwrap();
+        assert_eq!(values, vec![CellValue::Number(9.0), CellValue::Number(2.0)]);
+        assert_eq!(facade.with_dependents(&[cell("D1")]).len(), 2);
+    }
+
+    #[test]
+    fn test_subexpression_value_and_span() {
+        let facade = SpreadsheetFacade::new();
+        let b1 = CellAddress::new(1, 0);
+        facade.set_cell_value(&CellAddress::new(0, 0), "3").unwrap();
+        facade.set_cell_value(&b1, "5").unwrap();
+        facade
+            .set_cell_value(&CellAddress::new(2, 0), "10")
+            .unwrap();
+
+        let formula = "=A1*B1+SUM(C1:C10)";
+        let at = CellAddress::new(3, 0);
+        let (span, value) = facade.evaluate_subexpression(formula, 3..5, &at).unwrap();
+        assert_eq!(&formula[span], "A1*B1");
+        assert_eq!(value, CellValue::Number(15.0));
+
+        let (span, value) = facade.evaluate_subexpression(formula, 12..14, &at).unwrap();
+        assert_eq!(&formula[span], "SUM(C1:C10)");
+        assert_eq!(value, CellValue::Number(10.0));
+        assert_eq!(facade.get_cell(&at), None);
+    }
+
+    #[test]
+    fn test_dependents_recalculate_when_precedents_change() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        facade.set_cell_value(&cell("A1"), "1").unwrap();
+        facade.set_cell_value(&cell("B1"), "=A1+1").unwrap();
+        facade.set_cell_value(&cell("C1"), "=A1*10").unwrap();
+        facade.set_cell_value(&cell("D1"), "=SUM(B1:C1)").unwrap();
+
+        facade.set_cell_value(&cell("A1"), "2").unwrap();
+        assert_eq!(value("D1"), Some(CellValue::Number(23.0)));
+
+        facade.delete_cell(&cell("A1")).unwrap();
+        assert_eq!(value("D1"), Some(CellValue::Number(1.0)));
+
+        // Replacing a formula drops the references it made
+        facade.set_cell_value(&cell("C1"), "5").unwrap();
+        facade.set_cell_value(&cell("A1"), "3").unwrap();
+        assert_eq!(value("D1"), Some(CellValue::Number(9.0)));
+        assert!(facade.verify_dependencies().is_consistent());
+    }
+
+    #[test]
+    fn test_volatile_formulas_recalculate_on_every_edit() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        facade.set_cell_value(&cell("A1"), "=RAND()").unwrap();
+        facade.set_cell_value(&cell("B1"), "=A1+1").unwrap();
+        facade.set_cell_value(&cell("C1"), "5").unwrap();
+        facade.set_cell_value(&cell("D1"), "=C1*2").unwrap();
+
+        // An unrelated edit re-evaluates the volatile formula and what
+        // reads it, and nothing else
+        let before = value("A1");
+        let evaluations = facade.evaluation_count();
+        facade.set_cell_value(&cell("E1"), "7").unwrap();
+        assert_ne!(value("A1"), before);
+        let Some(CellValue::Number(random)) = value("A1") else {
+            panic!("RAND gives a number");
+        };
+        assert!((0.0..1.0).contains(&random));
+        assert_eq!(value("B1"), Some(CellValue::Number(random + 1.0)));
+        assert_eq!(facade.evaluation_count() - evaluations, 2);
+        assert_eq!(value("D1"), Some(CellValue::Number(10.0)));
+
+        // Volatile formulas move with structural changes
+        facade.insert_rows(0, 1).unwrap();
+        let before = value("A2");
+        facade.set_cell_value(&cell("E1"), "8").unwrap();
+        assert_ne!(value("A2"), before);
+
+        // Once the formula is gone, edits leave its cell alone
+        facade
+            .set_cell_value(&cell("A2"), "=RANDBETWEEN(1, 6)")
+            .unwrap();
+        let Some(CellValue::Number(roll)) = value("A2") else {
+            panic!("RANDBETWEEN gives a number");
+        };
+        assert!((1.0..=6.0).contains(&roll) && roll.fract() == 0.0);
+        facade.set_cell_value(&cell("A2"), "3").unwrap();
+        let evaluations = facade.evaluation_count();
+        facade.set_cell_value(&cell("E1"), "9").unwrap();
+        assert_eq!(facade.evaluation_count(), evaluations);
+    }
+
+    /// A sheet of volatile formulas in deterministic mode with `seed`,
+    /// edited a few times
+    fn deterministic_run(seed: u64) -> SpreadsheetFacade {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.set_determinism(Some(Determinism {
+            seed,
+            now_ms: 1_700_000_000_000,
+        }));
+        facade.set_cell_value(&cell("A1"), "=RAND()").unwrap();
+        facade
+            .set_cell_value(&cell("A2"), "=RANDBETWEEN(1, 1000)")
+            .unwrap();
+        facade.set_cell_value(&cell("A3"), "=A1+A2").unwrap();
+        facade.set_cell_value(&cell("B1"), "=NOW()").unwrap();
+        for value in ["1", "2", "3"] {
+            facade.set_cell_value(&cell("C1"), value).unwrap();
+        }
+        facade
+    }
+
+    #[test]
+    fn test_deterministic_mode_reproduces_volatile_values() {
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let values = |facade: &SpreadsheetFacade| {
+            ["A1", "A2", "A3", "B1"].map(|a1| facade.get_cell_raw_value(&cell(a1)))
+        };
+        let first = deterministic_run(7);
+        let second = deterministic_run(7);
+        assert_eq!(values(&first), values(&second));
+        assert_eq!(first.content_hash(), second.content_hash());
+        assert_ne!(values(&first)[..3], values(&deterministic_run(8))[..3]);
+        assert_ne!(first.content_hash(), deterministic_run(8).content_hash());
+
+        // NOW and TODAY read the frozen time
+        assert_eq!(
+            first.get_cell_raw_value(&cell("B1")),
+            Some(CellValue::Number(dates::datetime_serial(
+                Determinism {
+                    seed: 0,
+                    now_ms: 1_700_000_000_000
+                }
+                .now()
+            )))
+        );
+
+        // Previews leave the numbers to come alone, and a replay from the
+        // state carries on with the same draws
+        let state = first.determinism_state().unwrap();
+        first.evaluate_preview("RAND()", &cell("D1")).unwrap();
+        assert_eq!(first.determinism_state(), Some(state));
+        second.evaluate_preview("RAND()", &cell("D1")).unwrap();
+        let resumed = SpreadsheetFacade::new();
+        let export = first.export_csv(true);
+        resumed
+            .import_csv(&export.csv, export.sidecar.as_ref())
+            .unwrap();
+        resumed.set_determinism(Some(state));
+        for facade in [&first, &resumed] {
+            facade.set_cell_value(&cell("C1"), "4").unwrap();
+        }
+        assert_eq!(values(&first), values(&resumed));
+
+        // Off again, the numbers come from entropy
+        first.set_determinism(None);
+        assert_eq!(first.determinism_state(), None);
+        assert_eq!(first.workbook_settings().deterministic, None);
+    }
+
+    #[test]
+    fn test_circular_errors_name_the_cycle() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let cycle = |a1: &str| match facade.get_cell_raw_value(&cell(a1)) {
+            Some(CellValue::Error(error)) => error.cycle_path(),
+            _ => None,
+        };
+
+        // Two cells reading each other
+        facade.set_cell_value(&cell("A1"), "=B1+1").unwrap();
+        facade.set_cell_value(&cell("B1"), "=A1+1").unwrap();
+        assert_eq!(cycle("B1").as_deref(), Some("B1 → A1 → B1"));
+        assert!(cycle("A1").is_some());
+
+        // A formula reading its own cell
+        facade.set_cell_value(&cell("C1"), "=C1*2").unwrap();
+        assert_eq!(cycle("C1").as_deref(), Some("C1 → C1"));
+
+        // A cycle closed through a range
+        facade.set_cell_value(&cell("D1"), "=SUM(D2:D5)").unwrap();
+        facade.set_cell_value(&cell("D3"), "=D1").unwrap();
+        assert_eq!(cycle("D3").as_deref(), Some("D3 → D1 → D3"));
+        assert!(cycle("D1").is_some());
+
+        // Recalculating everything finds the same cycles
+        facade.recalculate().unwrap();
+        assert!(cycle("A1").is_some() && cycle("B1").is_some());
+        assert_eq!(cycle("C1").as_deref(), Some("C1 → C1"));
+
+        // Breaking a cycle brings the values back
+        facade.set_cell_value(&cell("B1"), "5").unwrap();
+        assert_eq!(cycle("A1"), None);
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("A1")),
+            Some(CellValue::Number(6.0))
+        );
+        facade.set_cell_value(&cell("D3"), "4").unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("D1")),
+            Some(CellValue::Number(4.0))
+        );
+    }
+
+    #[test]
+    fn test_precedent_and_dependent_queries() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let cells = |list: &[&str]| list.iter().map(|a1| cell(a1)).collect::<Vec<_>>();
+        for (a1, value) in [
+            ("A1", "1"),
+            ("A2", "2"),
+            ("A3", "3"),
+            ("B1", "=SUM(A1:A3)"),
+            ("C1", "=B1*2"),
+            ("C2", "=C1+A1"),
+            ("E5", "7"),
+        ] {
+            facade.set_cell_value(&cell(a1), value).unwrap();
+        }
+
+        assert_eq!(facade.get_precedents(&cell("C1"), false), cells(&["B1"]));
+        assert_eq!(
+            facade.get_precedents(&cell("C2"), true),
+            cells(&["A1", "B1", "C1", "A2", "A3"])
+        );
+        // A2 is only read through the range
+        assert_eq!(facade.get_dependents(&cell("A2"), false), cells(&["B1"]));
+        assert_eq!(
+            facade.get_dependents(&cell("A2"), true),
+            cells(&["B1", "C1", "C2"])
+        );
+        assert_eq!(
+            facade.get_dependents(&cell("A1"), false),
+            cells(&["B1", "C2"])
+        );
+        for unconnected in ["E5", "Z99"] {
+            assert!(facade.get_precedents(&cell(unconnected), true).is_empty());
+            assert!(facade.get_dependents(&cell(unconnected), true).is_empty());
+        }
+        assert!(facade.get_dependents(&cell("C2"), true).is_empty());
+    }
+
+    #[test]
+    fn test_multi_area_sums() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        for (i, row) in ["1", "2", "3", "4", "5"].iter().enumerate() {
+            facade
+                .set_cell_value(&CellAddress::new(0, i as u32), row)
+                .unwrap();
+            facade
+                .set_cell_value(&CellAddress::new(2, i as u32), "10")
+                .unwrap();
+        }
+        facade
+            .set_cell_value(&cell("E1"), "=SUM((A1:A5,C1:C5))")
+            .unwrap();
+        facade
+            .set_cell_value(&cell("E2"), "=SUM((A1:A2,A2))")
+            .unwrap();
+        facade
+            .set_cell_value(&cell("E3"), "=SUM(A1:C3 B2:C5)")
+            .unwrap();
+        facade.set_cell_value(&cell("E4"), "=A1:C3 C2:D2").unwrap();
+        facade
+            .set_cell_value(&cell("E5"), "=SUM(A1:A2 C1:C2)")
+            .unwrap();
+        assert_eq!(value("E1"), Some(CellValue::Number(65.0)));
+        // Overlapping areas count their shared cells twice
+        assert_eq!(value("E2"), Some(CellValue::Number(5.0)));
+        assert_eq!(value("E3"), Some(CellValue::Number(20.0)));
+        assert_eq!(value("E4"), Some(CellValue::Number(10.0)));
+        assert_eq!(
+            value("E5"),
+            Some(CellValue::from_error(ErrorType::NullIntersection))
+        );
+
+        // A range written end-first is the same range, not an empty one
+        facade.set_cell_value(&cell("E6"), "=SUM(A5:A1)").unwrap();
+        facade.set_cell_value(&cell("E7"), "=SUM(A10:A1)").unwrap();
+        assert_eq!(value("E6"), Some(CellValue::Number(15.0)));
+        assert_eq!(value("E7"), Some(CellValue::Number(15.0)));
+
+        // Every area is a precedent
+        facade.set_cell_value(&cell("C5"), "20").unwrap();
+        assert_eq!(value("E1"), Some(CellValue::Number(75.0)));
+        facade.set_cell_value(&cell("A5"), "0").unwrap();
+        assert_eq!(value("E1"), Some(CellValue::Number(70.0)));
+        assert!(facade.verify_dependencies().is_consistent());
+    }
+
+    #[test]
+    fn test_repair_corrupted_dependency_graph() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        facade.set_cell_value(&cell("A1"), "1").unwrap();
+        facade.set_cell_value(&cell("B1"), "=A1+1").unwrap();
+        facade.set_cell_value(&cell("C1"), "=B1+1").unwrap();
+        assert!(facade.verify_dependencies().is_consistent());
+
+        // Lose C1's edge, invent one for B1 and leave two stray nodes
+        let graph = facade.active_graph().unwrap();
+        {
+            let mut graph = graph.lock().unwrap();
+            graph.remove_dependencies_for(&cell("C1"));
+            graph.add_dependency(cell("B1"), cell("E5"));
+            graph.add_dependency(cell("F9"), cell("G9"));
+            graph.remove_dependencies_for(&cell("F9"));
+        }
+
+        // The stale graph no longer reaches C1
+        facade.set_cell_value(&cell("A1"), "10").unwrap();
+        assert_eq!(value("B1"), Some(CellValue::Number(11.0)));
+        assert_eq!(value("C1"), Some(CellValue::Number(3.0)));
+
+        let report = facade.verify_dependencies();
+        assert_eq!(report.missing, vec![(cell("C1"), cell("B1"))]);
+        assert_eq!(report.dangling, vec![(cell("B1"), cell("E5"))]);
+        assert_eq!(report.orphans, vec![cell("C1"), cell("F9"), cell("G9")]);
+
+        // Repairing recalculates what the graph had missed
+        assert_eq!(facade.repair_dependencies().unwrap(), report);
+        assert!(facade.verify_dependencies().is_consistent());
+        assert_eq!(value("C1"), Some(CellValue::Number(12.0)));
+        facade.set_cell_value(&cell("A1"), "20").unwrap();
+        assert_eq!(value("C1"), Some(CellValue::Number(22.0)));
+    }
+
+    #[test]
+    fn test_edited_formulas_match_a_fresh_load() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (a1, value) in [
+            ("A1", "1"),
+            ("A2", "2"),
+            ("A3", "3"),
+            ("B1", "=A1*2"),
+            ("B2", "=B1+A2"),
+            ("B3", "=SUM(B1:B2)"),
+            ("C1", "=B3+A3"),
+            // Rewrites keep some references and swap others
+            ("B2", "=B1+A3"),
+            ("B3", "=SUM(B1:B2)+A1"),
+            ("B1", "=A2*2"),
+            ("C1", "=B3"),
+            ("A2", "5"),
+        ] {
+            facade.set_cell_value(&cell(a1), value).unwrap();
+            assert!(facade.verify_dependencies().is_consistent(), "after {}", a1);
+        }
+
+        let fresh = SpreadsheetFacade::new();
+        fresh
+            .import_csv(&facade.export_csv(false).csv, None)
+            .unwrap();
+        for a1 in ["B1", "B2", "B3", "C1"] {
+            assert_eq!(
+                facade.get_cell_raw_value(&cell(a1)),
+                fresh.get_cell_raw_value(&cell(a1)),
+                "{}",
+                a1
+            );
+        }
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("C1")),
+            Some(CellValue::Number(24.0))
+        );
+    }
+
+    #[test]
+    fn test_array_formulas_spill_and_clean_up() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        for row in 1..=5 {
+            facade
+                .set_cell_value(&cell(&format!("A{}", row)), &row.to_string())
+                .unwrap();
+        }
+
+        facade.set_cell_value(&cell("B1"), "=A1:A5*2").unwrap();
+        for row in 1..=5 {
+            assert_eq!(
+                value(&format!("B{}", row)),
+                Some(CellValue::Number(row as f64 * 2.0))
+            );
+        }
+        let spill = facade.spill_range_at(&cell("B3")).unwrap();
+        assert_eq!(spill.anchor, cell("B1"));
+        assert_eq!(spill.range, CellRange::new(cell("B1"), cell("B5")));
+        assert!(!spill.blocked);
+        assert_eq!(facade.spill_ranges(), vec![spill]);
+
+        // Shrinking the array removes what it no longer covers
+        facade.set_cell_value(&cell("B1"), "=A1:A2*2").unwrap();
+        assert_eq!(value("B2"), Some(CellValue::Number(4.0)));
+        assert_eq!(value("B3"), None);
+
+        // Deleting the formula removes everything it spilled
+        facade.delete_cell(&cell("B1")).unwrap();
+        assert_eq!(value("B2"), None);
+        assert!(facade.spill_ranges().is_empty());
+        assert!(facade.verify_dependencies().is_consistent());
+    }
+
+    #[test]
+    fn test_functions_read_array_expressions() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for row in 1..=5 {
+            facade
+                .set_cell_value(&cell(&format!("A{}", row)), &row.to_string())
+                .unwrap();
+        }
+        let result = |formula: &str| {
+            facade.set_cell_value(&cell("C1"), formula).unwrap();
+            facade.get_cell_raw_value(&cell("C1")).unwrap().to_string()
+        };
+
+        assert_eq!(result("=SUM(A1:A5*2)"), "30");
+        assert_eq!(result("=MAX(A1:A5+1)"), "6");
+        assert_eq!(result("=AVERAGE(A1:A5*A1:A5)"), "11");
+        assert_eq!(result("=SUM(-A1:A5)"), "-15");
+        assert_eq!(result("=SUM(SEQUENCE(3)*10)+1"), "61");
+        assert_eq!(result("=SUM(A1:A5/0)"), "#DIV/0!");
+        assert_eq!(result("=SUM(1+2, A1*2)"), "5");
+
+        // The array follows edits to the cells it is computed from
+        facade.set_cell_value(&cell("D1"), "=SUM(A1:A5*2)").unwrap();
+        facade.set_cell_value(&cell("A3"), "10").unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("D1")),
+            Some(CellValue::Number(44.0))
+        );
+    }
+
+    #[test]
+    fn test_blocked_spill_retries_once_cleared() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        facade.set_cell_value(&cell("A3"), "x").unwrap();
+
+        facade.set_cell_value(&cell("A1"), "=SEQUENCE(3)").unwrap();
+        assert_eq!(
+            value("A1"),
+            Some(CellValue::from_error(ErrorType::Spill {
+                blocked_by: vec![cell("A3")]
+            }))
+        );
+        assert_eq!(value("A2"), None);
+        assert!(facade.spill_range_at(&cell("A1")).unwrap().blocked);
+
+        facade.delete_cell(&cell("A3")).unwrap();
+        assert_eq!(value("A1"), Some(CellValue::Number(1.0)));
+        assert_eq!(value("A3"), Some(CellValue::Number(3.0)));
+
+        // Typing into the spilled area blocks the array again
+        facade.set_cell_value(&cell("A2"), "y").unwrap();
+        assert!(value("A1").is_some_and(|value| value.is_error()));
+        assert_eq!(value("A2"), Some(CellValue::from_string("y".to_string())));
+        assert_eq!(value("A3"), None);
+        assert!(facade.verify_dependencies().is_consistent());
+    }
+
+    #[test]
+    fn test_formulas_reading_spilled_cells_follow_the_array() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        facade.set_cell_value(&cell("A1"), "10").unwrap();
+        facade.set_cell_value(&cell("D1"), "=SUM(B1:C2)").unwrap();
+        facade.set_cell_value(&cell("D2"), "=C2").unwrap();
+
+        facade
+            .set_cell_value(&cell("B1"), "=TRANSPOSE(SEQUENCE(2,2,A1))")
+            .unwrap();
+        // 10 12 / 11 13 read down the columns
+        assert_eq!(value("C1"), Some(CellValue::Number(12.0)));
+        assert_eq!(value("D1"), Some(CellValue::Number(46.0)));
+        assert_eq!(value("D2"), Some(CellValue::Number(13.0)));
+
+        facade.set_cell_value(&cell("A1"), "0").unwrap();
+        assert_eq!(value("D1"), Some(CellValue::Number(6.0)));
+        assert_eq!(value("D2"), Some(CellValue::Number(3.0)));
+        assert!(facade.verify_dependencies().is_consistent());
+
+        // An array over a cell its own formula reads is circular
+        facade
+            .set_cell_value(&cell("F1"), "=SEQUENCE(3)+F3")
+            .unwrap();
+        assert!(matches!(
+            value("F1"),
+            Some(CellValue::Error(error)) if matches!(*error, ErrorType::CircularDependency { .. })
+        ));
+    }
+
+    #[test]
+    fn test_recalculate_follows_dependency_order() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.set_cell_value(&cell("A1"), "2").unwrap();
+        facade.set_cell_value(&cell("C1"), "=B1*2").unwrap();
+        facade.set_cell_value(&cell("B1"), "=A1+1").unwrap();
+
+        // Overwrite the values behind the graph's back
+        let repository = facade.active_repository().unwrap();
+        repository
+            .set(&cell("A1"), Cell::new(CellValue::Number(5.0)))
+            .unwrap();
+        facade.recalculate().unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("C1")),
+            Some(CellValue::Number(12.0))
+        );
+    }
 
-        // Add a new sheet
-        assert!(facade.add_sheet("Sheet2").is_ok());
-        assert_eq!(facade.sheet_count(), 2);
+    #[test]
+    fn test_sheet_with_calculation_off_goes_stale() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        let first = facade.get_active_sheet();
+        facade.add_sheet("Costs").unwrap();
+        for sheet in [first.as_str(), "Costs"] {
+            facade.set_active_sheet(sheet).unwrap();
+            facade.set_cell_value(&cell("A1"), "1").unwrap();
+            facade.set_cell_value(&cell("B1"), "=A1*2").unwrap();
+        }
 
-        // Switch active sheet
-        assert!(facade.set_active_sheet("Sheet2").is_ok());
-        assert_eq!(facade.get_active_sheet(), "Sheet2");
+        facade.set_calculation_enabled("Costs", false).unwrap();
+        assert!(!facade.is_calculation_enabled("Costs"));
+        facade.set_cell_value(&cell("A1"), "5").unwrap();
+        assert_eq!(value("B1"), Some(CellValue::Number(2.0)));
+        assert_eq!(facade.stale_cells("Costs"), vec![cell("B1")]);
+        assert!(!facade.is_stale(&cell("A1")));
+
+        // The other sheet stays live
+        facade.set_active_sheet(&first).unwrap();
+        facade.set_cell_value(&cell("A1"), "7").unwrap();
+        assert_eq!(value("B1"), Some(CellValue::Number(14.0)));
+        assert!(facade.stale_cells(&first).is_empty());
+
+        // Turning calculation back on catches up
+        let recalculation = facade.set_calculation_enabled("Costs", true).unwrap();
+        assert_eq!(recalculation.recalculated, vec![cell("B1")]);
+        facade.set_active_sheet("Costs").unwrap();
+        assert_eq!(value("B1"), Some(CellValue::Number(10.0)));
+        assert!(facade.stale_cells("Costs").is_empty());
+        assert!(facade.recalculate_sheet("Nowhere").is_err());
+    }
+
+    #[test]
+    fn test_range_recalculation_flags_cross_scope_reads() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let value = |a1: &str| facade.get_cell_raw_value(&cell(a1));
+        facade.set_cell_value(&cell("A1"), "1").unwrap();
+        facade.set_cell_value(&cell("B1"), "=A1*2").unwrap();
+        facade.set_cell_value(&cell("C1"), "=B1+1").unwrap();
+        facade.set_cell_value(&cell("D5"), "=A1+100").unwrap();
+
+        let sheet = facade.get_active_sheet();
+        facade.set_calculation_enabled(&sheet, false).unwrap();
+        facade.set_cell_value(&cell("A1"), "2").unwrap();
+        assert_eq!(
+            facade.stale_cells(&sheet),
+            vec![cell("B1"), cell("C1"), cell("D5")]
+        );
+
+        // C1 reads B1, which is stale and outside the range
+        let range = CellRange::from_string("C1:D5").unwrap();
+        let mut recalculation = facade.recalculate_range(&range).unwrap();
+        recalculation
+            .recalculated
+            .sort_by_key(|address| address.row);
+        assert_eq!(recalculation.recalculated, vec![cell("C1"), cell("D5")]);
+        assert_eq!(recalculation.stale, vec![cell("C1")]);
+        assert_eq!(value("C1"), Some(CellValue::Number(3.0)));
+        assert_eq!(value("D5"), Some(CellValue::Number(102.0)));
+        assert_eq!(facade.stale_cells(&sheet), vec![cell("B1"), cell("C1")]);
+
+        let recalculation = facade.recalculate_sheet(&sheet).unwrap();
+        assert_eq!(recalculation.recalculated, vec![cell("B1"), cell("C1")]);
+        assert!(recalculation.stale.is_empty());
+        assert_eq!(value("C1"), Some(CellValue::Number(5.0)));
+        assert!(facade.stale_cells(&sheet).is_empty());
+    }
+
+    /// Recalculation events among `events`
+    fn recalculation_events(events: &[DomainEvent]) -> Vec<DomainEvent> {
+        events
+            .iter()
+            .filter(|event| {
+                matches!(
+                    event,
+                    DomainEvent::RecalculationStarted { .. }
+                        | DomainEvent::RecalculationProgress { .. }
+                        | DomainEvent::RecalculationFinished { .. }
+                        | DomainEvent::RecalculationCancelled { .. }
+                )
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// `count` formulas in column B, each reading its row of column A
+    fn fill_formulas(facade: &SpreadsheetFacade, count: u32) {
+        for row in 0..count {
+            facade
+                .set_cell_value(&CellAddress::new(1, row), &format!("=A{}*2", row + 1))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_long_recalculations_report_progress_at_a_cadence() {
+        let (facade, seen) = recording_facade();
+        fill_formulas(&facade, 1000);
+        facade.set_progress_cadence(ProgressCadence {
+            min_cells: 100,
+            every_cells: 300,
+            every_ms: 50.0,
+        });
+
+        // With time standing still, progress follows the cell count
+        facade.set_clock(Arc::new(|| 0.0));
+        seen.lock().unwrap().clear();
+        facade.recalculate().unwrap();
+        assert_eq!(
+            recalculation_events(&seen.lock().unwrap()),
+            vec![
+                DomainEvent::RecalculationStarted { total_dirty: 1000 },
+                DomainEvent::RecalculationProgress {
+                    completed: 300,
+                    total: 1000
+                },
+                DomainEvent::RecalculationProgress {
+                    completed: 600,
+                    total: 1000
+                },
+                DomainEvent::RecalculationProgress {
+                    completed: 900,
+                    total: 1000
+                },
+                DomainEvent::RecalculationFinished {
+                    duration_ms: 0.0,
+                    cells_evaluated: 1000
+                },
+            ]
+        );
+
+        // A slow evaluation, a millisecond per formula, reports every 50
+        let now = Arc::new(AtomicU64::new(0));
+        let ticks = now.clone();
+        facade.set_clock(Arc::new(move || {
+            ticks.fetch_add(1, Ordering::Relaxed) as f64
+        }));
+        seen.lock().unwrap().clear();
+        facade.recalculate().unwrap();
+        let events = recalculation_events(&seen.lock().unwrap());
+        let reported: Vec<usize> = events
+            .iter()
+            .filter_map(|event| match event {
+                DomainEvent::RecalculationProgress { completed, total } => {
+                    assert_eq!(*total, 1000);
+                    Some(*completed)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reported.len(), 19);
+        assert!(
+            reported
+                .windows(2)
+                .all(|pair| (50..=300).contains(&(pair[1] - pair[0])))
+        );
+        assert!(matches!(
+            events.last(),
+            Some(DomainEvent::RecalculationFinished {
+                duration_ms,
+                cells_evaluated: 1000
+            }) if *duration_ms >= 1000.0
+        ));
+        assert!(!facade.recalculation_monitor().is_calculating());
+    }
+
+    #[test]
+    fn test_small_recalculations_report_nothing() {
+        let (facade, seen) = recording_facade();
+        fill_formulas(&facade, 10);
+        facade.set_cell_value(&CellAddress::new(0, 0), "4").unwrap();
+        facade.recalculate().unwrap();
+        assert!(recalculation_events(&seen.lock().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_recalculation_leaves_the_rest_stale() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let monitor = Arc::new(std::sync::OnceLock::<RecalculationMonitor>::new());
+        let (sink, watched) = (seen.clone(), monitor.clone());
+        let mut events = EventAdapter::new_empty();
+        events
+            .subscribe(Box::new(move |event| {
+                // Cancel at the first report, noting what the monitor saw
+                let mut seen = sink.lock().unwrap();
+                if let DomainEvent::RecalculationProgress { .. } = event
+                    && seen.is_empty()
+                {
+                    let monitor = watched.get().unwrap();
+                    seen.push(monitor.status());
+                    monitor.cancel();
+                }
+            }))
+            .unwrap();
+        let facade = SpreadsheetFacade::with_ports(
+            Arc::new(RepositoryAdapter::new_empty()),
+            Arc::new(events),
+        );
+        monitor.set(facade.recalculation_monitor()).unwrap();
+        fill_formulas(&facade, 1000);
+        facade.set_progress_cadence(ProgressCadence {
+            min_cells: 100,
+            every_cells: 300,
+            every_ms: f64::INFINITY,
+        });
+
+        facade.recalculate().unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![Some(RecalculationStatus {
+                completed: 300,
+                total: 1000
+            })]
+        );
+        let sheet = facade.get_active_sheet();
+        assert!(!facade.recalculation_monitor().is_calculating());
+        assert_eq!(facade.stale_cells(&sheet).len(), 700);
+        assert!(!facade.is_stale(&CellAddress::new(1, 0)));
+        assert!(facade.is_stale(&CellAddress::new(1, 999)));
+
+        // Cancelling when nothing runs does nothing
+        facade.recalculation_monitor().cancel();
+        let recalculation = facade.recalculate_sheet(&sheet).unwrap();
+        assert_eq!(recalculation.recalculated.len(), 700);
+        assert!(!recalculation.cancelled);
+        assert!(facade.stale_cells(&sheet).is_empty());
+    }
+
+    #[test]
+    fn test_csv_round_trip_keeps_formulas_and_formats() {
+        let source = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (a1, value) in [
+            ("A1", "Item"),
+            ("B1", "Price"),
+            ("C1", "Twice"),
+            ("A2", "'00123"),
+            ("B2", "$1,234.50"),
+            ("C2", "=B2*2"),
+            ("A3", "true"),
+            ("B3", "45 kg"),
+            ("C3", "=SUM(B2:B3)"),
+            ("A4", "say \"hi\", twice"),
+            ("B4", "=FETCH(\"https://api.test/q\", \"json:$.price\")"),
+            ("C4", "=B4+1"),
+        ] {
+            source.set_cell_value(&cell(a1), value).unwrap();
+        }
+        source
+            .apply_style(&CellRange::from_string("A1:C1").unwrap(), "Heading")
+            .unwrap();
+        source
+            .define_style("Total", CellFormat::percent(0))
+            .unwrap();
+        source.set_cell_style(&cell("C3"), Some("Total")).unwrap();
+        source
+            .set_column_format(2, Some(CellFormat::number(1)))
+            .unwrap();
+        source.set_row_format(4, Some(CellFormat::text())).unwrap();
+        let resolver = FakeResolver(vec![("https://api.test/q", Ok(r#"{"price": 21}"#))]);
+        source.resolve_external_with(&resolver).unwrap();
+
+        let export = source.export_csv(true);
+        assert_eq!(export.csv.lines().next(), Some("Item,Price,Twice"));
+        assert!(export.csv.contains("'00123,1234.5,=B2*2"));
+        let sidecar = Sidecar::from_json(&export.sidecar.unwrap().to_json()).unwrap();
+        assert_eq!(sidecar.cell_styles["C3"], "Total");
+        assert_eq!(sidecar.row_formats[&5], CellFormat::text());
+
+        let copy = SpreadsheetFacade::new();
+        let report = copy.import_csv(&export.csv, Some(&sidecar)).unwrap();
+        assert!(report.is_faithful(), "{}", report);
+        assert_eq!(report.cells, 12);
+        // The fetched value and its dependent differ until the copy fetches
+        assert_ne!(
+            copy.get_cell_raw_value(&cell("C4")),
+            source.get_cell_raw_value(&cell("C4"))
+        );
+        assert_eq!(copy.content_hash(), source.content_hash());
+        assert_eq!(copy.export_csv(true).csv, export.csv);
+
+        // Without the sidecar the content is there but the formats are not
+        let bare = SpreadsheetFacade::new();
+        assert!(bare.import_csv(&export.csv, None).unwrap().is_faithful());
+        assert_ne!(bare.content_hash(), source.content_hash());
+    }
+
+    #[test]
+    fn test_csv_import_reports_what_it_cannot_restore() {
+        let facade = SpreadsheetFacade::new();
+        let mut sidecar = Sidecar::default();
+        sidecar
+            .formats
+            .insert("not a cell".to_string(), CellFormat::number(2));
+        sidecar.row_formats.insert(0, CellFormat::number(2));
+        sidecar
+            .cell_styles
+            .insert("A1".to_string(), "Missing".to_string());
+
+        let report = facade
+            .import_csv("1,\"=SUM(A1,\"\n", Some(&sidecar))
+            .unwrap();
+        assert_eq!(report.cells, 2);
+        let issues: Vec<String> = report.issues.iter().map(ToString::to_string).collect();
+        assert_eq!(issues.len(), 4, "{:?}", issues);
+        assert!(issues[0].starts_with("B1: formula =SUM(A1, does not parse"));
+        assert!(issues.iter().any(|issue| issue.starts_with("A1: ")));
+        assert!(
+            issues
+                .iter()
+                .any(|issue| issue == "not a cell is not a cell address")
+        );
+        assert!(issues.iter().any(|issue| issue.starts_with("row 0:")));
+    }
+
+    #[test]
+    fn test_booleans_parse_display_and_coerce() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (input, expected) in [
+            ("TRUE", CellValue::Boolean(true)),
+            ("false", CellValue::Boolean(false)),
+            ("True", CellValue::Boolean(true)),
+            ("'TRUE", CellValue::string_from_str("TRUE")),
+            ("truth", CellValue::string_from_str("truth")),
+        ] {
+            facade.set_cell_value(&cell("A1"), input).unwrap();
+            assert_eq!(
+                facade.get_cell_raw_value(&cell("A1")),
+                Some(expected),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            CellValue::Boolean(false).to_display_string(),
+            "FALSE".to_string()
+        );
+
+        facade.set_cell_value(&cell("A1"), "true").unwrap();
+        facade.set_cell_value(&cell("A2"), "5").unwrap();
+        facade.set_cell_value(&cell("A3"), "'7").unwrap();
+        let n = CellValue::Number;
+        for (formula, expected) in [
+            ("=A1+1", n(2.0)),
+            ("=SUM(A1:A3)", n(5.0)),
+            ("=SUM(A1)", n(0.0)),
+            ("=SUM(TRUE,2)", n(3.0)),
+            ("=COUNT(A1:A3)", n(1.0)),
+            ("=COUNT(A1)", n(0.0)),
+            ("=COUNT(TRUE,\"3\",\"x\")", n(2.0)),
+            ("=A1>100", CellValue::Boolean(true)),
+            ("=A3<A1", CellValue::Boolean(true)),
+            ("=IF(A1,\"yes\",\"no\")", CellValue::string_from_str("yes")),
+        ] {
+            facade.set_cell_value(&cell("B1"), formula).unwrap();
+            assert_eq!(
+                facade.get_cell_raw_value(&cell("B1")),
+                Some(expected),
+                "{}",
+                formula
+            );
+        }
+    }
+
+    #[test]
+    fn test_csv_booleans() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.import_csv("true,False,yes\n", None).unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("A1")),
+            Some(CellValue::Boolean(true))
+        );
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("C1")),
+            Some(CellValue::string_from_str("yes"))
+        );
+        assert_eq!(facade.export_csv(false).csv.trim_end(), "TRUE,FALSE,yes");
+
+        let text = SpreadsheetFacade::new();
+        let options = CsvImportOptions {
+            booleans: false,
+            ..CsvImportOptions::default()
+        };
+        text.import_csv_with("TRUE,1\n", None, &options).unwrap();
+        assert_eq!(
+            text.get_cell_raw_value(&cell("A1")),
+            Some(CellValue::string_from_str("TRUE"))
+        );
+        assert_eq!(
+            text.get_cell_raw_value(&cell("B1")),
+            Some(CellValue::Number(1.0))
+        );
+    }
+
+    #[test]
+    fn test_csv_multi_line_cells_round_trip() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade
+            .set_cell_value(&cell("A1"), "Ship to:\n12 High St\r\nLeeds")
+            .unwrap();
+        facade
+            .set_cell_format(&cell("A1"), CellFormat::default().wrapped())
+            .unwrap();
+        facade.set_cell_value(&cell("B1"), "=LEN(A1)").unwrap();
+
+        let export = facade.export_csv(true);
+        assert!(
+            export
+                .csv
+                .starts_with("\"Ship to:\n12 High St\r\nLeeds\",=LEN(A1)\n")
+        );
+        let sidecar = Sidecar::from_json(&export.sidecar.unwrap().to_json()).unwrap();
+        assert!(sidecar.formats["A1"].wrap_text);
+
+        let copy = SpreadsheetFacade::new();
+        assert!(
+            copy.import_csv(&export.csv, Some(&sidecar))
+                .unwrap()
+                .is_faithful()
+        );
+        assert_eq!(
+            copy.get_cell_raw_value(&cell("A1")),
+            facade.get_cell_raw_value(&cell("A1"))
+        );
+        assert_eq!(
+            copy.get_cell_raw_value(&cell("B1")),
+            Some(CellValue::Number(26.0))
+        );
+        assert_eq!(
+            copy.get_effective_format(&cell("A1")),
+            Some(CellFormat::default().wrapped())
+        );
+        assert_eq!(copy.content_hash(), facade.content_hash());
+    }
+
+    #[test]
+    fn test_input_length_limits() {
+        use crate::workbook::OverlongInput;
+        use crate::workbook::settings::MAX_FORMULA_LENGTH;
+
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+
+        // `=SUM(1,1,…,1)` at exactly the formula limit still parses
+        let ones = (MAX_FORMULA_LENGTH - 4) / 2;
+        let formula = format!("=SUM({}1)", "1,".repeat(ones - 1));
+        assert_eq!(formula.len(), MAX_FORMULA_LENGTH + 1);
+        facade.set_cell_value(&cell("A1"), &formula).unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("A1")),
+            Some(CellValue::Number(ones as f64))
+        );
+        assert!(matches!(
+            facade.set_cell_value(&cell("A2"), &format!("{}+1", formula)),
+            Err(SpreadsheetError::InputTooLong { formula: true, .. })
+        ));
+
+        let mut settings = facade.workbook_settings();
+        settings.max_text_length = 10;
+        facade.set_workbook_settings(settings.clone());
+        assert!(facade.set_cell_text(&cell("B1"), "abcdefghijk").is_err());
+        assert_eq!(facade.get_cell(&cell("B1")), None);
+
+        settings.overlong_input = OverlongInput::Truncate;
+        facade.set_workbook_settings(settings);
+        facade.set_cell_value(&cell("B1"), "abcdefghijk").unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("B1")),
+            Some(CellValue::from_string("abcdefghij".to_string()))
+        );
+        assert_eq!(facade.take_truncated_cells(), vec![cell("B1")]);
+        assert!(facade.take_truncated_cells().is_empty());
+
+        // Imports report the cells they cut
+        let report = facade
+            .import_csv("short,a long line of text\n", None)
+            .unwrap();
+        assert_eq!(report.cells, 2);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].address, Some(cell("B1")));
+        assert!(facade.take_truncated_cells().is_empty());
+    }
+
+    #[test]
+    fn test_sheet_input_hash_follows_inputs_only() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.add_sheet("Data").unwrap();
+        let empty = facade.sheet_input_hash("Sheet1").unwrap();
+        assert_eq!(facade.sheet_input_hash("Data"), Some(empty));
+        assert_eq!(facade.sheet_input_hash("Missing"), None);
+
+        facade.set_cell_value(&cell("A1"), "2").unwrap();
+        facade.set_cell_value(&cell("B1"), "=A1*2").unwrap();
+        let saved = facade.sheet_input_hash("Sheet1").unwrap();
+        assert_ne!(saved, empty);
+
+        // A recalculated value is not an edit
+        facade.recalculate().unwrap();
+        assert_eq!(facade.sheet_input_hash("Sheet1"), Some(saved));
+
+        // Formats count; a cleared cell is the same as one never written
+        facade
+            .set_cell_format(&cell("A1"), CellFormat::number(1))
+            .unwrap();
+        assert_ne!(facade.sheet_input_hash("Sheet1"), Some(saved));
+        facade.clear_formats(&cell("A1")).unwrap();
+        facade.set_cell_value(&cell("C1"), "x").unwrap();
+        facade.set_cell_value(&cell("C1"), "").unwrap();
+        assert_eq!(facade.sheet_input_hash("Sheet1"), Some(saved));
+    }
+
+    /// A sheet whose A1:B600 holds keys with duplicates and case variants,
+    /// and lookups into it in D1:D6
+    fn lookup_sheet(lookup_index: bool) -> SpreadsheetFacade {
+        let facade = SpreadsheetFacade::new();
+        let mut settings = facade.workbook_settings();
+        settings.lookup_index = lookup_index;
+        facade.set_workbook_settings(settings);
+        let cell = |a1: String| CellAddress::from_a1(&a1).unwrap();
+        for row in 1..=600 {
+            let key = match row {
+                5 => "Apple".to_string(),
+                550 => "apple".to_string(),
+                _ => format!("k{}", row % 300),
+            };
+            facade
+                .set_cell_value(&cell(format!("A{}", row)), &key)
+                .unwrap();
+            facade
+                .set_cell_value(&cell(format!("B{}", row)), &row.to_string())
+                .unwrap();
+        }
+        for (row, formula) in [
+            "=VLOOKUP(\"K7\",A1:B600,2,FALSE)",
+            "=VLOOKUP(\"APPLE\",A1:B600,2,FALSE)",
+            "=MATCH(\"k299\",A1:A600,0)",
+            "=COUNTIF(A1:A600,\"k10\")",
+            "=VLOOKUP(\"missing\",A1:B600,2,FALSE)",
+            "=COUNTIF(A1:B600,\"apple\")",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            facade
+                .set_cell_value(&cell(format!("D{}", row + 1)), formula)
+                .unwrap();
+        }
+        facade
+    }
+
+    fn lookup_results(facade: &SpreadsheetFacade) -> Vec<Option<String>> {
+        (0..6)
+            .map(|row| {
+                facade
+                    .get_cell_raw_value(&CellAddress::new(3, row))
+                    .map(|value| value.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_indexed_lookups_match_scans() {
+        let indexed = lookup_sheet(true);
+        let expected: Vec<Option<String>> = ["7", "5", "299", "2", "#N/A", "2"]
+            .into_iter()
+            .map(|value| Some(value.to_string()))
+            .collect();
+        assert_eq!(lookup_results(&indexed), expected);
+        assert_eq!(lookup_results(&lookup_sheet(false)), expected);
+
+        // Turning the index off keeps the results
+        let mut settings = indexed.workbook_settings();
+        settings.lookup_index = false;
+        indexed.set_workbook_settings(settings);
+        indexed.recalculate().unwrap();
+        assert_eq!(lookup_results(&indexed), expected);
+    }
+
+    #[test]
+    fn test_indexed_lookups_follow_edits() {
+        let facade = lookup_sheet(true);
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let d1 = || {
+            facade
+                .get_cell_raw_value(&cell("D1"))
+                .map(|value| value.to_string())
+        };
+
+        // The first k7 moves down, then goes altogether
+        facade.set_cell_value(&cell("A7"), "other").unwrap();
+        assert_eq!(d1(), Some("307".to_string()));
+        facade.delete_cell(&cell("A307")).unwrap();
+        assert_eq!(d1(), Some("#N/A".to_string()));
+        facade.set_cell_value(&cell("A600"), "K7").unwrap();
+        assert_eq!(d1(), Some("600".to_string()));
+        facade.set_cell_value(&cell("A600"), "k0").unwrap();
+        assert_eq!(d1(), Some("#N/A".to_string()));
+
+        // Keys computed by formulas are indexed by their values
+        facade
+            .set_cell_value(&cell("A400"), "=\"k\"&\"7\"")
+            .unwrap();
+        assert_eq!(d1(), Some("400".to_string()));
+    }
+
+    #[test]
+    fn test_lookup_functions() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (a1, value) in [
+            ("A1", "10"),
+            ("A2", "20"),
+            ("A3", "30"),
+            ("B1", "low"),
+            ("B2", "mid"),
+            ("B3", "high"),
+            ("C1", "30"),
+            ("C2", "20"),
+            ("C3", "10"),
+            ("E1", "x"),
+            ("F1", "y"),
+            ("G1", "z"),
+        ] {
+            facade.set_cell_value(&cell(a1), value).unwrap();
+        }
+        let result = |formula: &str| {
+            facade.set_cell_value(&cell("H1"), formula).unwrap();
+            facade.get_cell_raw_value(&cell("H1")).unwrap().to_string()
+        };
+
+        assert_eq!(result("=VLOOKUP(25,A1:B3,2)"), "mid");
+        assert_eq!(result("=VLOOKUP(25,A1:B3,2,TRUE)"), "mid");
+        assert_eq!(result("=VLOOKUP(5,A1:B3,2)"), "#N/A");
+        assert_eq!(result("=VLOOKUP(30,A1:B3,2,FALSE)"), "high");
+        assert_eq!(result("=VLOOKUP(25,A1:B3,2,FALSE)"), "#N/A");
+        assert_eq!(result("=VLOOKUP(20,A1:B3,3,FALSE)"), "#REF!");
+        assert_eq!(result("=VLOOKUP(20,A1:B3,0,FALSE)"), "#VALUE!");
+        assert_eq!(result("=HLOOKUP(\"y\",E1:G1,1,FALSE)"), "y");
+        assert_eq!(result("=HLOOKUP(25,A1:C3,2)"), "20");
+        assert_eq!(result("=HLOOKUP(25,A1:C3,3,TRUE)"), "30");
+        assert_eq!(result("=HLOOKUP(5,A1:C3,2)"), "#N/A");
+        assert_eq!(result("=HLOOKUP(\"w\",E1:G1,1,FALSE)"), "#N/A");
+        assert_eq!(result("=HLOOKUP(\"x\",E1:G1,2,FALSE)"), "#REF!");
+        assert_eq!(result("=MATCH(25,A1:A3)"), "2");
+        assert_eq!(result("=MATCH(25,C1:C3,-1)"), "1");
+        assert_eq!(result("=MATCH(\"Y\",E1:G1,0)"), "2");
+        assert_eq!(result("=MATCH(1,A1:B3,0)"), "#N/A");
+        assert_eq!(result("=COUNTIF(A1:C3,\">=20\")"), "4");
+        assert_eq!(result("=COUNTIF(A1:C3,A1)"), "2");
+        // Ranges written end-first read the same cells
+        assert_eq!(result("=VLOOKUP(30,B3:A1,2,FALSE)"), "high");
+        assert_eq!(result("=MATCH(20,A3:A1,0)"), "2");
+        assert_eq!(result("=MATCH(9,D5:D4,0)"), "#N/A");
+
+        // A lookup reading its own cell is circular, even over a range
+        // long enough to be indexed
+        assert!(matches!(
+            facade.evaluate_preview("=COUNTIF(H1:H600,1)", &cell("H1")),
+            Ok(CellValue::Error(error))
+                if matches!(error.as_ref(), ErrorType::CircularDependency { .. })
+        ));
+    }
+
+    #[test]
+    fn test_conditional_sums() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (a1, value) in [
+            ("A1", "3"),
+            ("A2", "7"),
+            ("A3", "12"),
+            ("A4", "apple"),
+            ("A5", "apricot"),
+            ("B1", "1"),
+            ("B2", "10"),
+            ("B3", "100"),
+            ("B4", "1000"),
+            ("B5", "10000"),
+            ("C1", "north"),
+            ("C2", "south"),
+            ("C3", "north"),
+            ("C4", "north"),
+            ("C5", "south"),
+        ] {
+            facade.set_cell_value(&cell(a1), value).unwrap();
+        }
+        let result = |formula: &str| {
+            facade.set_cell_value(&cell("H1"), formula).unwrap();
+            facade.get_cell_raw_value(&cell("H1")).unwrap().to_string()
+        };
+
+        assert_eq!(result("=SUMIF(A1:A5,\">5\",B1:B5)"), "110");
+        assert_eq!(result("=SUMIF(A1:A3,\"<>7\")"), "15");
+        assert_eq!(result("=SUMIF(A1:A5,\"ap*\",B1:B5)"), "11000");
+        assert_eq!(result("=SUMIF(C1:C5,\"north\",B1:B5)"), "1101");
+        assert_eq!(result("=SUMIFS(B1:B5,C1:C5,\"north\",A1:A5,\">5\")"), "100");
+        assert_eq!(result("=SUMIFS(B1:B5,C1:C5,\"east\")"), "0");
+        assert_eq!(result("=SUMIF(A1:A5,\">5\",B1:B4)"), "#VALUE!");
+        assert_eq!(result("=SUMIFS(B1:B5,C1:C4,\"north\")"), "#VALUE!");
+
+        // An error among the criteria fails the sum
+        facade.set_cell_value(&cell("A2"), "=1/0").unwrap();
+        assert_eq!(result("=SUMIF(A1:A5,\">5\",B1:B5)"), "#VALUE!");
+    }
+
+    #[test]
+    fn test_conditional_counts_and_averages() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        // A3 and B4 are blank
+        for (a1, value) in [
+            ("A1", "done"),
+            ("A2", "open"),
+            ("A4", "done"),
+            ("A5", "cat"),
+            ("B1", "4"),
+            ("B2", "12"),
+            ("B3", "20"),
+            ("B5", "30"),
+        ] {
+            facade.set_cell_value(&cell(a1), value).unwrap();
+        }
+        let result = |formula: &str| {
+            facade.set_cell_value(&cell("H1"), formula).unwrap();
+            facade.get_cell_raw_value(&cell("H1")).unwrap().to_string()
+        };
+
+        // Blanks are not "done" but are not anything at all
+        assert_eq!(result("=COUNTIF(A1:A5,\"<>done\")"), "3");
+        assert_eq!(result("=COUNTIFS(A1:A5,\"<>done\")"), "3");
+        assert_eq!(result("=COUNTIFS(A1:A5,\"<>\")"), "4");
+        assert_eq!(result("=COUNTIFS(A1:A5,\"\")"), "1");
+        assert_eq!(result("=COUNTIFS(B1:B5,\">=10\")"), "3");
+        assert_eq!(result("=COUNTIFS(A1:A5,\"?at\",B1:B5,30)"), "1");
+        assert_eq!(result("=COUNTIFS(A1:A5,\"<>done\",B1:B5,\"<>\")"), "3");
+
+        assert_eq!(result("=AVERAGEIF(B1:B5,\">=10\")"), "20.666666666666668");
+        assert_eq!(result("=AVERAGEIF(A1:A5,\"done\",B1:B5)"), "4");
+        assert_eq!(
+            result("=AVERAGEIF(A1:A5,\"<>\",B1:B5)"),
+            "15.333333333333334"
+        );
+        assert_eq!(
+            result("=AVERAGEIFS(B1:B5,A1:A5,\"<>done\",B1:B5,\"<25\")"),
+            "16"
+        );
+        assert_eq!(result("=AVERAGEIF(A1:A5,\"dog\",B1:B5)"), "#DIV/0!");
+        assert_eq!(result("=AVERAGEIFS(B1:B5,A1:A4,\"done\")"), "#VALUE!");
+    }
+
+    #[test]
+    fn test_sparkline_formulas() {
+        use crate::sparkline::SparklineKind;
+
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        for (a1, value) in [("A1", "10"), ("A2", "30"), ("A4", "20"), ("B1", "7")] {
+            facade.set_cell_value(&cell(a1), value).unwrap();
+        }
+        let sparkline = |formula: &str| {
+            facade.set_cell_value(&cell("D1"), formula).unwrap();
+            match facade.get_cell_raw_value(&cell("D1")).unwrap() {
+                CellValue::Sparkline(sparkline) => Ok(sparkline),
+                other => Err(other.to_string()),
+            }
+        };
+
+        let line = sparkline("=SPARKLINE(A1:A4)").unwrap();
+        assert_eq!(line.kind, SparklineKind::Line);
+        assert_eq!(line.points, [Some(0.0), Some(1.0), None, Some(0.5)]);
+        let winloss = sparkline("=SPARKLINE(A1:A2, \"winloss\")").unwrap();
+        assert_eq!(winloss.points, [Some(1.0), Some(1.0)]);
+
+        // Data changes reach the sparkline through its range
+        sparkline("=SPARKLINE(A1:A4)").unwrap();
+        facade.set_cell_value(&cell("A2"), "15").unwrap();
+        let CellValue::Sparkline(line) = facade.get_cell_raw_value(&cell("D1")).unwrap() else {
+            panic!("D1 is no longer a sparkline");
+        };
+        assert_eq!(line.points, [Some(0.0), Some(0.5), None, Some(1.0)]);
+
+        // Equal and blank ranges draw flat or not at all
+        assert_eq!(sparkline("=SPARKLINE(B1:B1)").unwrap().points, [Some(0.5)]);
+        assert!(sparkline("=SPARKLINE(C1:C5, \"bar\")").unwrap().is_blank());
+
+        assert_eq!(
+            sparkline("=SPARKLINE(A1:A4, \"pie\")").unwrap_err(),
+            "#VALUE!"
+        );
+        assert_eq!(
+            sparkline("=SPARKLINE(A1:A4, \"line\", \"width=2\")").unwrap_err(),
+            "#VALUE!"
+        );
+        assert_eq!(sparkline("=SPARKLINE(5)").unwrap_err(), "#VALUE!");
+
+        // A sparkline has no text: CSV keeps its formula, values are blank
+        sparkline("=SPARKLINE(A1:A4)").unwrap();
+        assert!(facade.export_csv(false).csv.contains("=SPARKLINE(A1:A4)"));
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("D1")).unwrap().to_string(),
+            ""
+        );
+        assert_eq!(
+            facade
+                .evaluate_preview("=D1+1", &cell("E1"))
+                .unwrap()
+                .to_string(),
+            "#VALUE!"
+        );
+    }
+
+    const DAMAGED_CSV: &str = include_str!("../csv/fixtures/damaged.csv");
+    const DAMAGED_SIDECAR: &str = include_str!("../csv/fixtures/damaged.sidecar.json");
+
+    #[test]
+    fn test_strict_import_reports_damage() {
+        let facade = SpreadsheetFacade::new();
+        let report = facade
+            .import_csv_document(
+                DAMAGED_CSV,
+                Some(DAMAGED_SIDECAR),
+                &CsvImportOptions::default(),
+            )
+            .unwrap();
+        let issues: Vec<String> = report.issues.iter().map(ToString::to_string).collect();
+        assert_eq!(issues.len(), 3, "{:?}", issues);
+        assert!(issues[0].starts_with("D3: formula =B3*(C3 does not parse"));
+        assert_eq!(issues[2], "sidecar section charts is not understood");
+        assert!(report.formulas_as_text.is_empty());
+        assert!(
+            !facade
+                .export_csv(true)
+                .sidecar
+                .unwrap()
+                .to_json()
+                .contains("charts")
+        );
+
+        assert!(
+            facade
+                .import_csv_document(
+                    DAMAGED_CSV,
+                    Some("{ not json"),
+                    &CsvImportOptions::default()
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_lenient_import_keeps_what_it_cannot_read() {
+        let facade = SpreadsheetFacade::new();
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let report = facade
+            .import_csv_document(
+                DAMAGED_CSV,
+                Some(DAMAGED_SIDECAR),
+                &CsvImportOptions::lenient(),
+            )
+            .unwrap();
+        assert!(report.issues.is_empty(), "{}", report);
+        let as_text: Vec<Option<CellAddress>> = report
+            .formulas_as_text
+            .iter()
+            .map(|issue| issue.address)
+            .collect();
+        assert_eq!(as_text, [Some(cell("D3")), Some(cell("D4"))]);
+        assert_eq!(
+            report.summary().as_deref(),
+            Some("2 formulas could not be parsed")
+        );
+        assert_eq!(report.preserved_sections, ["charts"]);
+
+        // Broken formulas come in as their text; the rest calculates
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("D3")),
+            Some(CellValue::string_from_str("=B3*(C3"))
+        );
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("D5")),
+            Some(CellValue::Number(6.0))
+        );
+
+        // Fixed by hand, then saved with the unknown section as it was
+        facade.set_cell_value(&cell("D3"), "=B3*C3").unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("D5")),
+            Some(CellValue::Number(12.0))
+        );
+        let export = facade.export_csv(true);
+        assert!(export.csv.contains("Pears,2,3,=B3*C3\n"));
+        assert!(export.csv.contains("Plums,3,2,'=SUM(B4:\n"));
+        let section = &DAMAGED_SIDECAR
+            [DAMAGED_SIDECAR.find("  \"charts\"").unwrap()..DAMAGED_SIDECAR.rfind("\n}").unwrap()];
+        assert!(export.sidecar.unwrap().to_json().contains(section));
+
+        // A sidecar that cannot be read is set aside
+        let bare = SpreadsheetFacade::new();
+        let report = bare
+            .import_csv_document(
+                DAMAGED_CSV,
+                Some("{ not json"),
+                &CsvImportOptions::lenient(),
+            )
+            .unwrap();
+        assert_eq!(report.unread_sidecar.as_deref(), Some("{ not json"));
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(
+            report.summary().as_deref(),
+            Some("2 formulas could not be parsed, 1 item not restored")
+        );
+    }
+
+    const ORDERS_CSV: &str = "id;zip;placed;qty\n\
+                              007;01234;2024-01-05;3\n\
+                              012;98765;01/15/2024;n/a\n";
+
+    #[test]
+    fn test_commit_import_with_column_overrides() {
+        let cell = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let text = |s: &str| Some(CellValue::string_from_str(s));
+
+        let preview = preview_import(ORDERS_CSV.as_bytes(), &ImportOptions::default(), 5);
+        assert!(preview.has_header);
+        assert_eq!(
+            preview.options.column_types,
+            [
+                ColumnType::Text,
+                ColumnType::Text,
+                ColumnType::Date,
+                ColumnType::General
+            ]
+        );
+
+        // As previewed: ids keep their zeros and dates are written alike
+        let facade = SpreadsheetFacade::new();
+        let report = facade
+            .commit_import(ORDERS_CSV.as_bytes(), &preview.options)
+            .unwrap();
+        assert!(report.is_faithful(), "{}", report);
+        assert_eq!(facade.get_cell_raw_value(&cell("A2")), text("007"));
+        assert_eq!(facade.get_display_value(&cell("C3")).unwrap(), "2024-01-15");
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("C3")),
+            Some(CellValue::Number(45306.0))
+        );
+        assert_eq!(facade.get_cell_raw_value(&cell("D1")), text("qty"));
+
+        // Overridden: ids as numbers, zip skipped, quantities as numbers
+        let options = ImportOptions {
+            column_types: vec![
+                ColumnType::Number,
+                ColumnType::Skip,
+                ColumnType::Text,
+                ColumnType::Number,
+            ],
+            destination: ImportDestination::Anchor { anchor: cell("B2") },
+            ..preview.options.clone()
+        };
+        let facade = SpreadsheetFacade::new();
+        let report = facade
+            .commit_import(ORDERS_CSV.as_bytes(), &options)
+            .unwrap();
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("B3")),
+            Some(CellValue::Number(7.0))
+        );
+        assert_eq!(facade.get_cell_raw_value(&cell("C2")), text("placed"));
+        assert_eq!(facade.get_cell_raw_value(&cell("C4")), text("01/15/2024"));
+        assert_eq!(
+            facade.get_cell_raw_value(&cell("D3")),
+            Some(CellValue::Number(3.0))
+        );
+        assert_eq!(facade.get_cell_raw_value(&cell("D4")), text("n/a"));
+        assert!(facade.get_cell(&cell("E2")).is_none());
+        let issues: Vec<Option<CellAddress>> =
+            report.issues.iter().map(|issue| issue.address).collect();
+        assert_eq!(issues, [Some(cell("D4"))]);
+    }
+
+    #[test]
+    fn test_commit_import_into_a_new_sheet() {
+        let facade = SpreadsheetFacade::new();
+        let options = ImportOptions {
+            has_header: Some(false),
+            destination: ImportDestination::NewSheet {
+                name: "Orders".to_string(),
+            },
+            ..ImportOptions::default()
+        };
+        let report = facade.commit_import(b"caf\xE9,=1+1\n", &options).unwrap();
+        assert_eq!(report.cells, 2);
+        assert_eq!(facade.get_active_sheet(), "Orders");
+        assert_eq!(
+            facade.get_cell_raw_value(&CellAddress::new(0, 0)),
+            Some(CellValue::string_from_str("café"))
+        );
+        assert_eq!(
+            facade
+                .get_cell(&CellAddress::new(1, 0))
+                .unwrap()
+                .get_computed_value(),
+            CellValue::Number(2.0)
+        );
+        assert!(facade.commit_import(b"x", &options).is_err());
+    }
+
+    #[test]
+    fn test_row_and_column_edits_move_cells_and_formulas() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.set_cell_value(&a1("A1"), "5").unwrap();
+        facade.set_cell_value(&a1("B3"), "=A1*2").unwrap();
+
+        facade.insert_rows(0, 2).unwrap();
+        assert_eq!(facade.get_cell_value(&a1("A3")), Some("5".to_string()));
+        let formula = facade.get_cell(&a1("B5")).unwrap();
+        assert_eq!(formula.formula_text.as_deref(), Some("A3*2"));
+        assert_eq!(formula.get_computed_value(), CellValue::Number(10.0));
+
+        // The formula follows its input and recalculates
+        facade.set_cell_value(&a1("A3"), "6").unwrap();
+        assert_eq!(facade.get_cell_value(&a1("B5")), Some("12".to_string()));
+
+        facade.delete_columns(0, 1).unwrap();
+        let formula = facade.get_cell(&a1("A5")).unwrap();
+        assert_eq!(formula.formula_text.as_deref(), Some("#REF!*2"));
+        assert!(facade.get_cell(&a1("B5")).is_none());
+        assert!(facade.delete_rows(0, 0).is_ok());
+    }
+
+    #[test]
+    fn test_formats_move_with_their_cells() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.set_cell_value(&a1("A2"), "0.5").unwrap();
+        facade
+            .set_cell_format(&a1("A2"), CellFormat::percent(0))
+            .unwrap();
+        facade.set_cell_value(&a1("B3"), "7").unwrap();
+        facade.set_cell_style(&a1("B3"), Some("Currency")).unwrap();
+        facade
+            .set_row_format(4, Some(CellFormat::number(2)))
+            .unwrap();
+        facade
+            .set_column_format(2, Some(CellFormat::percent(1)))
+            .unwrap();
+
+        facade.insert_rows(0, 1).unwrap();
+        assert_eq!(facade.get_display_value(&a1("A3")).as_deref(), Some("50%"));
+        assert_eq!(facade.get_effective_format(&a1("A2")), None);
+        assert_eq!(
+            facade.get_cell_style(&a1("B4")).as_deref(),
+            Some("Currency")
+        );
+        assert_eq!(
+            facade.get_formats().row_format(5),
+            Some(&CellFormat::number(2))
+        );
+
+        facade.insert_columns(0, 1).unwrap();
+        assert_eq!(facade.get_display_value(&a1("B3")).as_deref(), Some("50%"));
+        assert_eq!(
+            facade.get_formats().column_format(3),
+            Some(&CellFormat::percent(1))
+        );
+
+        // Formats of deleted cells go with them
+        facade.delete_rows(0, 1).unwrap();
+        assert_eq!(facade.get_display_value(&a1("B2")).as_deref(), Some("50%"));
+        facade.delete_rows(2, 1).unwrap();
+        assert_eq!(facade.get_cell_style(&a1("C3")), None);
+        assert_eq!(
+            facade.get_formats().row_format(3),
+            Some(&CellFormat::number(2))
+        );
+        facade.delete_columns(3, 1).unwrap();
+        assert_eq!(facade.get_formats().column_formats().count(), 0);
+    }
+
+    #[test]
+    fn test_row_edits_leave_other_sheets_alone() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let formula_in = |sheet: &str, at: &str| {
+            facade
+                .get_cell_in_sheet(sheet, &a1(at))
+                .and_then(|cell| cell.formula_text.map(|formula| formula.to_string()))
+        };
+        let first = facade.get_active_sheet();
+        facade.set_cell_value(&a1("A1"), "1").unwrap();
+        facade.set_cell_value(&a1("A2"), "2").unwrap();
+        facade.set_cell_value(&a1("A3"), "=SUM(A1:A2)").unwrap();
+        facade.add_sheet("Other").unwrap();
+        facade.set_active_sheet("Other").unwrap();
+        facade.set_cell_value(&a1("B5"), "=A1+SUM(A1:A4)").unwrap();
+
+        facade.set_active_sheet(&first).unwrap();
+        facade.insert_rows(0, 1).unwrap();
+        facade.insert_rows(3, 1).unwrap();
+        assert_eq!(formula_in(&first, "A5").as_deref(), Some("SUM(A2:A4)"));
+        assert_eq!(facade.get_cell_value(&a1("A5")), Some("3".to_string()));
+        assert_eq!(formula_in("Other", "B5").as_deref(), Some("A1+SUM(A1:A4)"));
+
+        facade.delete_rows(0, 3).unwrap();
+        assert_eq!(formula_in(&first, "A2").as_deref(), Some("SUM(A1:A1)"));
+        assert_eq!(formula_in("Other", "B5").as_deref(), Some("A1+SUM(A1:A4)"));
+        assert_eq!(facade.get_all_cells_in_sheet("Other").len(), 1);
+        assert!(facade.get_all_cells_in_sheet("Missing").is_empty());
+    }
+
+    #[test]
+    fn test_structural_batch_matches_one_operation_at_a_time() {
+        let a1 = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        let load = |batched: bool| {
+            let facade = SpreadsheetFacade::new();
+            let first = facade.get_active_sheet();
+            for (at, value) in [("A1", "1"), ("A2", "2"), ("A3", "3"), ("A4", "=SUM(A1:A3)")] {
+                facade.set_cell_value(&a1(at), value).unwrap();
+            }
+            facade.add_sheet("Other").unwrap();
+            facade.set_active_sheet("Other").unwrap();
+            facade
+                .set_cell_value(&a1("B1"), &format!("={}!A4+SUM({}!A1:A3)", first, first))
+                .unwrap();
+            facade.set_active_sheet(&first).unwrap();
+
+            if batched {
+                facade.begin_structural_batch().unwrap();
+            }
+            for row in 0..5 {
+                // Append a row right above the total and fill it in
+                facade.insert_rows(3 + row, 1).unwrap();
+                facade
+                    .set_cell_value(&CellAddress::new(0, 3 + row), &(10 * row).to_string())
+                    .unwrap();
+                facade
+                    .set_cell_value(&CellAddress::new(1, 3 + row), &format!("=A{}*2", 4 + row))
+                    .unwrap();
+            }
+            facade.delete_rows(0, 1).unwrap();
+            facade.insert_rows(0, 2).unwrap();
+            facade.set_cell_value(&a1("C1"), "=SUM(A3:A5)").unwrap();
+            facade.delete_rows(5, 1).unwrap();
+            if batched {
+                assert!(facade.is_structural_batch_open());
+                facade.commit_structural_batch().unwrap();
+            }
+            assert!(!facade.is_structural_batch_open());
+            facade
+        };
+
+        let (one_by_one, batched) = (load(false), load(true));
+        for sheet in [one_by_one.get_active_sheet(), "Other".to_string()] {
+            let cells = |facade: &SpreadsheetFacade| {
+                let mut cells: Vec<_> = facade
+                    .get_all_cells_in_sheet(&sheet)
+                    .into_iter()
+                    .map(|(address, cell)| {
+                        (
+                            address,
+                            cell.formula_text.clone(),
+                            cell.get_computed_value(),
+                        )
+                    })
+                    .collect();
+                cells.sort_by_key(|(address, ..)| (address.row, address.col));
+                cells
+            };
+            assert_eq!(cells(&batched), cells(&one_by_one), "{}", sheet);
+        }
+        let total = batched.get_cell(&a1("A9")).unwrap();
+        assert_eq!(total.formula_text.as_deref(), Some("SUM(A3:A8)"));
+        assert_eq!(total.get_computed_value(), CellValue::Number(95.0));
+        assert_eq!(batched.get_cell_value(&a1("C1")), Some("5".to_string()));
+    }
+
+    #[test]
+    fn test_structural_batch_rolls_back_and_stays_on_its_sheet() {
+        let facade = SpreadsheetFacade::new();
+        let a1 = |a1: &str| CellAddress::from_a1(a1).unwrap();
+        facade.set_cell_value(&a1("A1"), "1").unwrap();
+        facade.set_cell_value(&a1("A2"), "=A1*2").unwrap();
+        facade.add_sheet("Other").unwrap();
+        assert!(facade.commit_structural_batch().is_err());
+
+        facade.begin_structural_batch().unwrap();
+        assert!(facade.begin_structural_batch().is_err());
+        facade.insert_rows(0, 3).unwrap();
+        facade.set_cell_value(&a1("A1"), "7").unwrap();
+        facade.delete_rows(4, 1).unwrap();
+        facade.set_active_sheet("Other").unwrap();
+        assert!(facade.insert_rows(0, 1).is_err());
+        facade.set_cell_value(&a1("A1"), "x").unwrap();
+
+        facade.rollback_structural_batch().unwrap();
+        assert!(!facade.is_structural_batch_open());
+        assert_eq!(facade.get_active_sheet(), "Other");
+        assert!(facade.get_all_cells_in_sheet("Other").is_empty());
+        assert_eq!(facade.get_all_cells_in_sheet("Sheet1").len(), 2);
+        assert_eq!(
+            facade
+                .get_cell_in_sheet("Sheet1", &a1("A2"))
+                .unwrap()
+                .get_computed_value(),
+            CellValue::Number(2.0)
+        );
     }
 }
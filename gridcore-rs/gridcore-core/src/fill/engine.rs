@@ -1,11 +1,14 @@
+use super::patterns::DatePatternDetector;
 use super::{
     CellRange, FillDirection, FillOperation, FillResult, FormulaAdjuster, PatternDetector,
     PatternType,
 };
+use crate::evaluator::dates;
 use crate::ports::RepositoryPort;
 use crate::types::{CellAddress, CellValue};
 use crate::utils::object_pool::global::CELL_VALUE_VEC_POOL;
 use crate::{Result, SpreadsheetError};
+use chrono::Duration;
 use std::sync::Arc;
 
 pub struct FillEngine {
@@ -17,8 +20,8 @@ pub struct FillEngine {
 impl FillEngine {
     pub fn new(cell_repository: Arc<dyn RepositoryPort>) -> Self {
         use super::patterns::{
-            CopyPatternDetector, DatePatternDetector, ExponentialPatternDetector,
-            LinearPatternDetector, TextPatternDetector,
+            CopyPatternDetector, ExponentialPatternDetector, LinearPatternDetector,
+            TextPatternDetector,
         };
 
         let mut detectors: Vec<Box<dyn PatternDetector>> = vec![
@@ -45,8 +48,28 @@ impl FillEngine {
     }
 
     pub fn fill(&self, operation: &FillOperation) -> Result<FillResult> {
+        self.fill_ranges(
+            &operation.source_range,
+            std::slice::from_ref(&operation.target_range),
+            operation.direction,
+            operation.pattern.as_ref(),
+        )
+    }
+
+    /// Fill several disjoint rectangles from one source, e.g. the visible
+    /// blocks of a filtered column. The pattern is detected from the source
+    /// alone, and every target cell is generated from its own offset to the
+    /// source, so the gaps between rectangles do not shift references or
+    /// series. The result lists the rectangles' cells in the order given.
+    pub fn fill_ranges(
+        &self,
+        source_range: &CellRange,
+        target_ranges: &[CellRange],
+        direction: FillDirection,
+        pattern: Option<&PatternType>,
+    ) -> Result<FillResult> {
         // Get source values
-        let source_values = self.get_source_values(&operation.source_range)?;
+        let source_values = self.get_source_values(source_range)?;
 
         if source_values.is_empty() {
             return Err(SpreadsheetError::InvalidOperation(
@@ -55,35 +78,33 @@ impl FillEngine {
         }
 
         // Detect pattern if not specified
-        let pattern = if let Some(ref p) = operation.pattern {
-            p.clone()
-        } else {
-            self.detect_pattern(&source_values)?
+        let pattern = match pattern {
+            Some(p) => p.clone(),
+            None => self.detect_pattern(&source_values)?,
         };
 
-        // Generate target values
-        let generated_values = self.generate_values(
-            &source_values,
-            &pattern,
-            &operation.source_range,
-            &operation.target_range,
-            operation.direction,
-        )?;
-
-        // Adjust formulas if needed
-        let adjusted_formulas = if self.formula_adjuster.is_some() {
-            self.adjust_formulas(
-                &operation.source_range,
-                &operation.target_range,
-                operation.direction,
-            )?
-        } else {
-            vec![]
-        };
+        let mut affected_cells = Vec::new();
+        let mut formulas_adjusted = Vec::new();
+        for target_range in target_ranges {
+            affected_cells.extend(self.generate_values(
+                &source_values,
+                &pattern,
+                source_range,
+                target_range,
+                direction,
+            )?);
+            if self.formula_adjuster.is_some() {
+                formulas_adjusted.extend(self.adjust_formulas(
+                    source_range,
+                    target_range,
+                    direction,
+                )?);
+            }
+        }
 
         Ok(FillResult {
-            affected_cells: generated_values,
-            formulas_adjusted: adjusted_formulas,
+            affected_cells,
+            formulas_adjusted,
         })
     }
 
@@ -111,11 +132,11 @@ impl FillEngine {
 
     fn get_source_values(&self, range: &CellRange) -> Result<Vec<CellValue>> {
         // Use pooled vector for better performance
-        let cell_count = range.iter_cells().count();
+        let cell_count = range.cells().count();
         let mut values = CELL_VALUE_VEC_POOL.get();
         values.reserve(cell_count);
 
-        for addr in range.iter_cells() {
+        for addr in range.cells() {
             if let Some(cell) = self.cell_repository.get(&addr) {
                 values.push(cell.get_computed_value());
             } else {
@@ -128,6 +149,17 @@ impl FillEngine {
     }
 
     fn detect_pattern(&self, values: &[CellValue]) -> Result<PatternType> {
+        // No series runs through a blank or through empty text like the
+        // result of ="", so a source with either is copied
+        let hole = |value: &CellValue| match value {
+            CellValue::Empty => true,
+            CellValue::String(text) => text.is_empty(),
+            _ => false,
+        };
+        if values.iter().any(hole) {
+            return Ok(PatternType::Copy);
+        }
+
         // Try each detector in priority order
         for detector in &self.detectors {
             if detector.can_handle(values)
@@ -145,118 +177,75 @@ impl FillEngine {
         &self,
         source_values: &[CellValue],
         pattern: &PatternType,
-        _source_range: &CellRange,
+        source_range: &CellRange,
         target_range: &CellRange,
         direction: FillDirection,
     ) -> Result<Vec<(CellAddress, CellValue)>> {
-        let target_count = target_range.iter_cells().count();
+        let target_count = target_range.cells().count();
         let mut result = Vec::with_capacity(target_count);
 
         match pattern {
             PatternType::Linear { slope } => {
-                self.generate_linear_values(
-                    source_values,
-                    *slope,
-                    target_range,
-                    direction,
-                    &mut result,
-                )?;
+                let base = series_base(source_values, direction)?;
+                for addr in target_range.cells() {
+                    let steps = series_offset(source_range, &addr, direction) as f64;
+                    result.push((addr, CellValue::Number(base + slope * steps)));
+                }
             }
             PatternType::Exponential { rate } => {
-                self.generate_exponential_values(
-                    source_values,
-                    *rate,
-                    target_range,
-                    direction,
-                    &mut result,
-                )?;
+                let base = series_base(source_values, direction)?;
+                for addr in target_range.cells() {
+                    let steps = series_offset(source_range, &addr, direction) as i32;
+                    result.push((addr, CellValue::Number(base * rate.powi(steps))));
+                }
             }
             PatternType::Copy => {
-                self.generate_copy_values(source_values, target_range, direction, &mut result)?;
+                self.generate_copy_values(source_values, source_range, target_range, &mut result);
+            }
+            PatternType::Date { increment_days } => {
+                let base = date_base(source_values, direction)?;
+                for addr in target_range.cells() {
+                    let steps = series_offset(source_range, &addr, direction) as f64;
+                    let days = (increment_days * steps).round() as i64;
+                    let date = DatePatternDetector::parse_date(base)
+                        .and_then(|date| date.checked_add_signed(Duration::try_days(days)?))
+                        .ok_or_else(|| {
+                            SpreadsheetError::InvalidOperation("Date out of range".to_string())
+                        })?;
+                    // Serial numbers continue as serials, text in its own form
+                    let value = match base {
+                        CellValue::String(text) => {
+                            CellValue::from_string(dates::date_text_like(date, text))
+                        }
+                        _ => CellValue::Number(dates::date_serial(date)),
+                    };
+                    result.push((addr, value));
+                }
             }
-            PatternType::Text | PatternType::Date { .. } | PatternType::Custom { .. } => {
+            PatternType::Text | PatternType::Custom { .. } => {
                 // TODO: Implement other pattern types
-                self.generate_copy_values(source_values, target_range, direction, &mut result)?;
+                self.generate_copy_values(source_values, source_range, target_range, &mut result);
             }
         }
 
         Ok(result)
     }
 
-    fn generate_linear_values(
-        &self,
-        source_values: &[CellValue],
-        slope: f64,
-        target_range: &CellRange,
-        _direction: FillDirection,
-        result: &mut Vec<(CellAddress, CellValue)>,
-    ) -> Result<()> {
-        // Get the last numeric value from source
-        let last_value = source_values
-            .iter()
-            .rev()
-            .find_map(|v| match v {
-                CellValue::Number(n) => Some(*n),
-                _ => None,
-            })
-            .ok_or_else(|| {
-                SpreadsheetError::InvalidOperation("No numeric value found".to_string())
-            })?;
-
-        let mut current_value = last_value;
-
-        for addr in target_range.iter_cells() {
-            current_value += slope;
-            result.push((addr, CellValue::Number(current_value)));
-        }
-
-        Ok(())
-    }
-
-    fn generate_exponential_values(
-        &self,
-        source_values: &[CellValue],
-        rate: f64,
-        target_range: &CellRange,
-        _direction: FillDirection,
-        result: &mut Vec<(CellAddress, CellValue)>,
-    ) -> Result<()> {
-        // Get the last numeric value from source
-        let last_value = source_values
-            .iter()
-            .rev()
-            .find_map(|v| match v {
-                CellValue::Number(n) => Some(*n),
-                _ => None,
-            })
-            .ok_or_else(|| {
-                SpreadsheetError::InvalidOperation("No numeric value found".to_string())
-            })?;
-
-        let mut current_value = last_value;
-
-        for addr in target_range.iter_cells() {
-            current_value *= rate;
-            result.push((addr, CellValue::Number(current_value)));
-        }
-
-        Ok(())
-    }
-
     fn generate_copy_values(
         &self,
         source_values: &[CellValue],
+        source_range: &CellRange,
         target_range: &CellRange,
-        _direction: FillDirection,
         result: &mut Vec<(CellAddress, CellValue)>,
-    ) -> Result<()> {
-        let source_len = source_values.len();
-
-        for (index, addr) in target_range.iter_cells().enumerate() {
-            result.push((addr, source_values[index % source_len].clone()));
+    ) {
+        let width = source_range.col_count();
+
+        for addr in target_range.cells() {
+            let from = source_cell_for(source_range, &addr);
+            let index = (from.row - source_range.start.row) as usize * width
+                + (from.col - source_range.start.col) as usize;
+            result.push((addr, source_values[index].clone()));
         }
-
-        Ok(())
     }
 
     fn adjust_formulas(
@@ -269,21 +258,18 @@ impl FillEngine {
             SpreadsheetError::InvalidOperation("No formula adjuster configured".to_string())
         })?;
 
-        let source_count = source_range.iter_cells().count();
-        let target_count = target_range.iter_cells().count();
-        let mut adjusted = Vec::with_capacity(source_count * target_count);
+        let mut adjusted = Vec::with_capacity(target_range.cells().count());
 
-        // For each source cell with a formula
-        for source_addr in source_range.iter_cells() {
+        // Each target cell repeats the source cell at its offset, and its
+        // references move by the distance between the two
+        for target_addr in target_range.cells() {
+            let source_addr = source_cell_for(source_range, &target_addr);
             if let Some(cell) = self.cell_repository.get(&source_addr)
                 && let Some(ref formula) = cell.formula_text
             {
-                // Generate adjusted formulas for each target cell
-                for target_addr in target_range.iter_cells() {
-                    let adjusted_formula =
-                        adjuster.adjust_formula(formula, &source_addr, &target_addr, direction)?;
-                    adjusted.push((target_addr, adjusted_formula));
-                }
+                let adjusted_formula =
+                    adjuster.adjust_formula(formula, &source_addr, &target_addr, direction)?;
+                adjusted.push((target_addr, adjusted_formula));
             }
         }
 
@@ -291,6 +277,56 @@ impl FillEngine {
     }
 }
 
+/// The source cell that `cell` repeats: the one at the same offset from
+/// the source's top-left corner, wrapped to the source's size
+fn source_cell_for(source: &CellRange, cell: &CellAddress) -> CellAddress {
+    let wrap = |at: u32, start: u32, len: usize| {
+        let offset = (i64::from(at) - i64::from(start)).rem_euclid(len as i64);
+        start + offset as u32
+    };
+    CellAddress::new(
+        wrap(cell.col, source.start.col, source.col_count()),
+        wrap(cell.row, source.start.row, source.row_count()),
+    )
+}
+
+/// Where `cell` lies in the series, counted from the source value the
+/// series continues: 1 for the cell right below or right of the source
+/// when filling down or right, -1 right above or left of it when filling
+/// up or left
+fn series_offset(source: &CellRange, cell: &CellAddress, direction: FillDirection) -> i64 {
+    match direction {
+        FillDirection::Down => i64::from(cell.row) - i64::from(source.end.row),
+        FillDirection::Up => i64::from(cell.row) - i64::from(source.start.row),
+        FillDirection::Right => i64::from(cell.col) - i64::from(source.end.col),
+        FillDirection::Left => i64::from(cell.col) - i64::from(source.start.col),
+    }
+}
+
+/// The number a series continues from: the last one of the source when
+/// filling down or right, the first one when filling up or left
+fn series_base(source_values: &[CellValue], direction: FillDirection) -> Result<f64> {
+    let number = |value: &CellValue| match value {
+        CellValue::Number(n) => Some(*n),
+        _ => None,
+    };
+    let base = match direction {
+        FillDirection::Down | FillDirection::Right => source_values.iter().rev().find_map(number),
+        FillDirection::Up | FillDirection::Left => source_values.iter().find_map(number),
+    };
+    base.ok_or_else(|| SpreadsheetError::InvalidOperation("No numeric value found".to_string()))
+}
+
+/// The date a series continues from, like [`series_base`]
+fn date_base(source_values: &[CellValue], direction: FillDirection) -> Result<&CellValue> {
+    let is_date = |value: &&CellValue| DatePatternDetector::parse_date(value).is_some();
+    let base = match direction {
+        FillDirection::Down | FillDirection::Right => source_values.iter().rev().find(is_date),
+        FillDirection::Up | FillDirection::Left => source_values.iter().find(is_date),
+    };
+    base.ok_or_else(|| SpreadsheetError::InvalidOperation("No date value found".to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -5,10 +5,12 @@ use serde::{Deserialize, Serialize};
 pub mod adjuster;
 pub mod engine;
 pub mod patterns;
+pub mod running;
 
 #[cfg(test)]
 mod tests;
 
+pub use crate::formula::CellRange;
 pub use engine::FillEngine;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -45,37 +47,6 @@ pub struct FillResult {
     pub formulas_adjusted: Vec<(CellAddress, String)>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-pub struct CellRange {
-    pub start: CellAddress,
-    pub end: CellAddress,
-}
-
-impl CellRange {
-    pub fn new(start: CellAddress, end: CellAddress) -> Self {
-        Self { start, end }
-    }
-
-    pub fn contains(&self, addr: &CellAddress) -> bool {
-        addr.col >= self.start.col
-            && addr.col <= self.end.col
-            && addr.row >= self.start.row
-            && addr.row <= self.end.row
-    }
-
-    pub fn cell_count(&self) -> usize {
-        let cols = (self.end.col - self.start.col + 1) as usize;
-        let rows = (self.end.row - self.start.row + 1) as usize;
-        cols * rows
-    }
-
-    pub fn iter_cells(&self) -> impl Iterator<Item = CellAddress> + '_ {
-        (self.start.row..=self.end.row).flat_map(move |row| {
-            (self.start.col..=self.end.col).map(move |col| CellAddress::new(col, row))
-        })
-    }
-}
-
 pub trait PatternDetector {
     fn detect(&self, values: &[CellValue]) -> Option<PatternType>;
 
@@ -1,6 +1,7 @@
+use crate::evaluator::dates::{parse_date_text, serial_date};
 use crate::fill::{PatternDetector, PatternType};
 use crate::types::CellValue;
-use chrono::{Duration as ChronoDuration, NaiveDate};
+use chrono::NaiveDate;
 use std::time::Duration;
 
 pub struct DatePatternDetector;
@@ -16,22 +17,11 @@ impl DatePatternDetector {
         Self
     }
 
-    fn parse_date(&self, value: &CellValue) -> Option<NaiveDate> {
+    /// Date of a value: date text, or a serial number
+    pub(crate) fn parse_date(value: &CellValue) -> Option<NaiveDate> {
         match value {
-            CellValue::String(s) => {
-                // Try common date formats
-                NaiveDate::parse_from_str(s, "%Y-%m-%d")
-                    .ok()
-                    .or_else(|| NaiveDate::parse_from_str(s, "%m/%d/%Y").ok())
-                    .or_else(|| NaiveDate::parse_from_str(s, "%d/%m/%Y").ok())
-                    .or_else(|| NaiveDate::parse_from_str(s, "%Y/%m/%d").ok())
-            }
-            CellValue::Number(days) => {
-                // Excel date serial number (days since 1900-01-01, but with quirks)
-                // For simplicity, we'll use a base date
-                let base_date = NaiveDate::from_ymd_opt(1900, 1, 1)?;
-                base_date.checked_add_signed(ChronoDuration::days(*days as i64))
-            }
+            CellValue::String(s) => parse_date_text(s),
+            CellValue::Number(serial) => serial_date(*serial),
             _ => None,
         }
     }
@@ -62,7 +52,7 @@ impl DatePatternDetector {
 
 impl PatternDetector for DatePatternDetector {
     fn detect(&self, values: &[CellValue]) -> Option<PatternType> {
-        let dates: Vec<_> = values.iter().filter_map(|v| self.parse_date(v)).collect();
+        let dates: Vec<_> = values.iter().filter_map(Self::parse_date).collect();
 
         self.detect_date_pattern(&dates)
             .map(|duration| PatternType::Date {
@@ -78,7 +68,7 @@ impl PatternDetector for DatePatternDetector {
         // Need at least 2 parseable dates
         let date_count = values
             .iter()
-            .filter(|v| self.parse_date(v).is_some())
+            .filter(|v| Self::parse_date(v).is_some())
             .count();
 
         date_count >= 2
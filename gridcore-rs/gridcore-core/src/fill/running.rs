@@ -0,0 +1,200 @@
+//! Running aggregates down a column: `=SUM($B$1:B1)`, `=SUM($B$1:B2)`, …
+//!
+//! Each formula reads the source column from its first row, anchored with
+//! `$`, down to its own row, so filling or copying the formulas further
+//! down keeps extending the range. For long columns the values can be
+//! written instead, computed here in one pass.
+
+use crate::Cell;
+use crate::SpreadsheetError;
+use crate::evaluator::FunctionLibrary;
+use crate::formula::{CellRange, Expr};
+use crate::types::{CellAddress, CellValue};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunningAggregate {
+    Sum,
+    /// Number of numbers so far
+    Count,
+    Average,
+    Min,
+    Max,
+}
+
+impl RunningAggregate {
+    /// The function the formulas call
+    pub fn function_name(self) -> &'static str {
+        match self {
+            RunningAggregate::Sum => "SUM",
+            RunningAggregate::Count => "COUNT",
+            RunningAggregate::Average => "AVERAGE",
+            RunningAggregate::Min => "MIN",
+            RunningAggregate::Max => "MAX",
+        }
+    }
+
+    /// Name used in messages, e.g. "running sum"
+    pub fn label(self) -> &'static str {
+        match self {
+            RunningAggregate::Sum => "sum",
+            RunningAggregate::Count => "count",
+            RunningAggregate::Average => "average",
+            RunningAggregate::Min => "min",
+            RunningAggregate::Max => "max",
+        }
+    }
+
+    /// Formula for `row` of a running aggregate over `source`, a range in
+    /// one column: the function over `source` from its first row to `row`
+    pub fn formula(self, source: &CellRange, row: u32) -> Expr {
+        let col = source.start.col;
+        Expr::FunctionCall {
+            name: self.function_name().to_string(),
+            args: vec![Expr::Range {
+                range: CellRange::new(
+                    CellAddress::new(col, source.start.row),
+                    CellAddress::new(col, row),
+                ),
+                absolute_start_col: true,
+                absolute_start_row: true,
+                absolute_end_col: false,
+                absolute_end_row: false,
+            }],
+        }
+    }
+
+    /// Values the formulas of a running aggregate over `values` evaluate
+    /// to, one per value
+    pub fn running_values(self, values: &[CellValue]) -> Vec<CellValue> {
+        let library = FunctionLibrary::new();
+        // What the function returns for a range with no numbers, or with
+        // an error, comes from the function itself; either stays the same
+        // until the next number or for good
+        let call = |range: Vec<CellValue>| {
+            library
+                .call(self.function_name(), &[CellValue::from_array(range)])
+                .unwrap_or_else(|error| {
+                    let mut cell = Cell::new(CellValue::Empty);
+                    cell.set_error(error.to_string());
+                    cell.get_computed_value()
+                })
+        };
+        let mut without_numbers = None;
+        let mut failed = None;
+
+        let (mut sum, mut count) = (0.0, 0usize);
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        let mut running = Vec::with_capacity(values.len());
+        for value in values {
+            if failed.is_none() {
+                match value {
+                    CellValue::Number(n) => {
+                        sum += n;
+                        count += 1;
+                        min = min.min(*n);
+                        max = max.max(*n);
+                    }
+                    CellValue::Error(_) => failed = Some(call(vec![value.clone()])),
+                    _ => {}
+                }
+            }
+            if let Some(error) = &failed {
+                running.push(error.clone());
+                continue;
+            }
+            running.push(match self {
+                RunningAggregate::Sum => CellValue::Number(sum),
+                RunningAggregate::Count => CellValue::Number(count as f64),
+                _ if count == 0 => without_numbers
+                    .get_or_insert_with(|| call(Vec::new()))
+                    .clone(),
+                RunningAggregate::Average => CellValue::Number(sum / count as f64),
+                RunningAggregate::Min => CellValue::Number(min),
+                RunningAggregate::Max => CellValue::Number(max),
+            });
+        }
+        running
+    }
+}
+
+impl FromStr for RunningAggregate {
+    type Err = SpreadsheetError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "sum" => Ok(RunningAggregate::Sum),
+            "count" => Ok(RunningAggregate::Count),
+            "avg" | "average" => Ok(RunningAggregate::Average),
+            "min" => Ok(RunningAggregate::Min),
+            "max" => Ok(RunningAggregate::Max),
+            other => Err(SpreadsheetError::InvalidArguments(format!(
+                "Unknown running aggregate '{}', expected sum, count, average, min or max",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ErrorType;
+
+    #[test]
+    fn test_formulas_anchor_the_first_row() {
+        let source = CellRange::from_string("B2:B9").unwrap();
+        let formula =
+            |aggregate: RunningAggregate, row| aggregate.formula(&source, row).to_string();
+        assert_eq!(formula(RunningAggregate::Sum, 1), "SUM($B$2:B2)");
+        assert_eq!(formula(RunningAggregate::Sum, 5), "SUM($B$2:B6)");
+        assert_eq!(formula(RunningAggregate::Max, 8), "MAX($B$2:B9)");
+    }
+
+    #[test]
+    fn test_running_values() {
+        let values = [
+            CellValue::string_from_str("header"),
+            CellValue::Number(4.0),
+            CellValue::Empty,
+            CellValue::Number(2.0),
+            CellValue::Number(6.0),
+        ];
+        let numbers = |aggregate: RunningAggregate| -> Vec<Option<f64>> {
+            aggregate
+                .running_values(&values)
+                .iter()
+                .map(CellValue::as_number)
+                .collect()
+        };
+        assert_eq!(
+            numbers(RunningAggregate::Sum),
+            [Some(0.0), Some(4.0), Some(4.0), Some(6.0), Some(12.0)]
+        );
+        assert_eq!(
+            numbers(RunningAggregate::Count),
+            [Some(0.0), Some(1.0), Some(1.0), Some(2.0), Some(3.0)]
+        );
+        assert_eq!(
+            numbers(RunningAggregate::Average)[1..],
+            [Some(4.0), Some(4.0), Some(3.0), Some(4.0)]
+        );
+        assert_eq!(
+            numbers(RunningAggregate::Min)[1..],
+            [Some(4.0), Some(4.0), Some(2.0), Some(2.0)]
+        );
+
+        // An error stays in every row below it
+        let values = [
+            CellValue::Number(1.0),
+            CellValue::from_error(ErrorType::NotAvailable),
+            CellValue::Number(2.0),
+        ];
+        let running = RunningAggregate::Sum.running_values(&values);
+        assert_eq!(running[0], CellValue::Number(1.0));
+        assert!(running[1].is_error());
+        assert_eq!(running[2], running[1]);
+    }
+}
@@ -233,10 +233,158 @@ mod fill_integration_tests {
         assert_eq!(result.affected_cells[99].1, CellValue::Number(102.0)); // Last target cell
     }
 
+    #[test]
+    fn test_fill_ranges_offsets_each_cell_from_the_source() {
+        use crate::domain::Cell;
+
+        // C2 holds =B2*2; rows 3, 5 and 6 are filtered out
+        let repo = Arc::new(RepositoryAdapter::new_empty());
+        repo.set(
+            &CellAddress::new(2, 1),
+            Cell::with_formula(CellValue::Number(2.0), "=B2*2".to_string()),
+        )
+        .unwrap();
+        let engine = FillEngine::new(repo.clone())
+            .with_formula_adjuster(Box::new(DefaultFormulaAdjuster::new()));
+
+        let range = |a1: &str| CellRange::from_string(a1).unwrap();
+        let result = engine
+            .fill_ranges(
+                &range("C2:C2"),
+                &[range("C4:C4"), range("C7:C8")],
+                FillDirection::Down,
+                None,
+            )
+            .unwrap();
+        let formulas: Vec<(String, &str)> = result
+            .formulas_adjusted
+            .iter()
+            .map(|(address, formula)| (address.to_string(), formula.as_str()))
+            .collect();
+        assert_eq!(
+            formulas,
+            [
+                ("C4".to_string(), "=B4*2"),
+                ("C7".to_string(), "=B7*2"),
+                ("C8".to_string(), "=B8*2"),
+            ]
+        );
+
+        // A series counts the skipped rows too
+        repo.set(&CellAddress::new(0, 0), Cell::new(CellValue::Number(1.0)))
+            .unwrap();
+        repo.set(&CellAddress::new(0, 1), Cell::new(CellValue::Number(2.0)))
+            .unwrap();
+        let result = engine
+            .fill_ranges(
+                &range("A1:A2"),
+                &[range("A4:A4"), range("A7:A7")],
+                FillDirection::Down,
+                Some(&PatternType::Linear { slope: 1.0 }),
+            )
+            .unwrap();
+        assert_eq!(
+            result.affected_cells,
+            [
+                (CellAddress::new(0, 3), CellValue::Number(4.0)),
+                (CellAddress::new(0, 6), CellValue::Number(7.0)),
+            ]
+        );
+
+        // Copies repeat the source by offset, wherever a rectangle starts
+        let result = engine
+            .fill_ranges(
+                &range("A1:A2"),
+                &[range("A4:A4"), range("A7:A8")],
+                FillDirection::Down,
+                Some(&PatternType::Copy),
+            )
+            .unwrap();
+        let values: Vec<&CellValue> = result.affected_cells.iter().map(|(_, v)| v).collect();
+        assert_eq!(
+            values,
+            [
+                &CellValue::Number(2.0),
+                &CellValue::Number(1.0),
+                &CellValue::Number(2.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_linear_fill_up_continues_from_the_first_value() {
+        use crate::domain::Cell;
+
+        let repo = Arc::new(RepositoryAdapter::new_empty());
+        repo.set(&CellAddress::new(0, 3), Cell::new(CellValue::Number(5.0)))
+            .unwrap();
+        repo.set(&CellAddress::new(0, 4), Cell::new(CellValue::Number(7.0)))
+            .unwrap();
+        let engine = FillEngine::new(repo.clone());
+
+        let operation = FillOperation {
+            source_range: CellRange::new(CellAddress::new(0, 3), CellAddress::new(0, 4)),
+            target_range: CellRange::new(CellAddress::new(0, 1), CellAddress::new(0, 2)),
+            direction: FillDirection::Up,
+            pattern: Some(PatternType::Linear { slope: 2.0 }),
+        };
+        let result = engine.fill(&operation).unwrap();
+        assert_eq!(result.affected_cells[0].1, CellValue::Number(1.0));
+        assert_eq!(result.affected_cells[1].1, CellValue::Number(3.0));
+    }
+
+    #[test]
+    fn test_date_fill_continues_in_the_source_form() {
+        use crate::domain::Cell;
+        use crate::evaluator::parse_cell_value;
+
+        let repo = Arc::new(RepositoryAdapter::new_empty());
+        for (row, date) in ["1/29/2024", "2/5/2024"].into_iter().enumerate() {
+            repo.set(
+                &CellAddress::new(0, row as u32),
+                Cell::new(CellValue::from_string(date.to_string())),
+            )
+            .unwrap();
+            repo.set(
+                &CellAddress::new(1, row as u32),
+                Cell::new(parse_cell_value(date)),
+            )
+            .unwrap();
+        }
+        let engine = FillEngine::new(repo.clone());
+        let fill = |col| {
+            let operation = FillOperation {
+                source_range: CellRange::new(CellAddress::new(col, 0), CellAddress::new(col, 1)),
+                target_range: CellRange::new(CellAddress::new(col, 2), CellAddress::new(col, 3)),
+                direction: FillDirection::Down,
+                pattern: None,
+            };
+            let result = engine.fill(&operation).unwrap();
+            result
+                .affected_cells
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+
+        // Date text keeps its form, typed dates stay serial numbers
+        assert_eq!(
+            fill(0),
+            vec![
+                CellValue::from_string("2/12/2024".to_string()),
+                CellValue::from_string("2/19/2024".to_string()),
+            ]
+        );
+        assert_eq!(
+            fill(1),
+            vec![parse_cell_value("2/12/2024"), parse_cell_value("2/19/2024")]
+        );
+    }
+
     #[test]
     fn test_cell_range_iteration() {
         let range = CellRange::new(CellAddress::new(0, 0), CellAddress::new(2, 2));
-        let cells: Vec<_> = range.iter_cells().collect();
+        let cells: Vec<_> = range.cells().collect();
 
         assert_eq!(cells.len(), 9);
         assert_eq!(cells[0], CellAddress::new(0, 0));
@@ -1,8 +1,9 @@
-use crate::types::{CellAddress, CellValue};
+use crate::types::{CellAddress, CellValue, ErrorType};
+use crate::workbook::sheet_name::quote_sheet_name;
 use serde::{Deserialize, Serialize};
 
 /// Represents a cell range (e.g., A1:B10)
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CellRange {
     pub start: CellAddress,
     pub end: CellAddress,
@@ -77,6 +78,137 @@ impl CellRange {
     pub fn col_count(&self) -> usize {
         (self.end.col - self.start.col + 1) as usize
     }
+
+    /// Iterator over the rows of the range, each as a one-row range
+    pub fn iter_rows(&self) -> impl Iterator<Item = CellRange> + '_ {
+        (self.start.row..=self.end.row).map(move |row| {
+            CellRange::new(
+                CellAddress::new(self.start.col, row),
+                CellAddress::new(self.end.col, row),
+            )
+        })
+    }
+
+    /// The cells this range shares with `other`, if any
+    pub fn intersect(&self, other: &CellRange) -> Option<CellRange> {
+        let start = CellAddress::new(
+            self.start.col.max(other.start.col),
+            self.start.row.max(other.start.row),
+        );
+        let end = CellAddress::new(
+            self.end.col.min(other.end.col),
+            self.end.row.min(other.end.row),
+        );
+        (start.col <= end.col && start.row <= end.row).then(|| CellRange::new(start, end))
+    }
+
+    /// The cells of this range that are not in `other`, as at most four
+    /// disjoint ranges: full-width bands above and below the overlap, then
+    /// the parts to its left and right
+    pub fn subtract(&self, other: &CellRange) -> Vec<CellRange> {
+        let Some(overlap) = self.intersect(other) else {
+            return vec![*self];
+        };
+        let mut parts = Vec::with_capacity(4);
+        if overlap.start.row > self.start.row {
+            parts.push(CellRange::new(
+                self.start,
+                CellAddress::new(self.end.col, overlap.start.row - 1),
+            ));
+        }
+        if overlap.end.row < self.end.row {
+            parts.push(CellRange::new(
+                CellAddress::new(self.start.col, overlap.end.row + 1),
+                self.end,
+            ));
+        }
+        if overlap.start.col > self.start.col {
+            parts.push(CellRange::new(
+                CellAddress::new(self.start.col, overlap.start.row),
+                CellAddress::new(overlap.start.col - 1, overlap.end.row),
+            ));
+        }
+        if overlap.end.col < self.end.col {
+            parts.push(CellRange::new(
+                CellAddress::new(overlap.end.col + 1, overlap.start.row),
+                CellAddress::new(self.end.col, overlap.end.row),
+            ));
+        }
+        parts
+    }
+
+    /// The cells in either range, as normalized disjoint ranges
+    pub fn union(&self, other: &CellRange) -> Vec<CellRange> {
+        CellRange::union_all(&[*self, *other])
+    }
+
+    /// The cells in any of `ranges`, as disjoint ranges in row-major order
+    /// of their top-left cells. Neighbours spanning the same rows or the
+    /// same columns are merged, so a union that is itself a rectangle comes
+    /// back as that one range.
+    pub fn union_all(ranges: &[CellRange]) -> Vec<CellRange> {
+        let mut disjoint: Vec<CellRange> = Vec::new();
+        for range in ranges {
+            let mut pieces = vec![*range];
+            for existing in &disjoint {
+                pieces = pieces
+                    .iter()
+                    .flat_map(|piece| piece.subtract(existing))
+                    .collect();
+            }
+            disjoint.extend(pieces);
+        }
+
+        // Merge until no pair of neighbours forms a rectangle
+        'merge: loop {
+            for i in 0..disjoint.len() {
+                for j in i + 1..disjoint.len() {
+                    if let Some(merged) = disjoint[i].merge_adjacent(&disjoint[j]) {
+                        disjoint[i] = merged;
+                        disjoint.swap_remove(j);
+                        continue 'merge;
+                    }
+                }
+            }
+            break;
+        }
+        disjoint.sort_by_key(|range| (range.start.row, range.start.col));
+        disjoint
+    }
+
+    /// The smallest range covering all of `ranges`, or `None` when there
+    /// are none
+    pub fn bounding_box(ranges: &[CellRange]) -> Option<CellRange> {
+        ranges.iter().copied().reduce(|bounds, range| {
+            CellRange::new(
+                CellAddress::new(
+                    bounds.start.col.min(range.start.col),
+                    bounds.start.row.min(range.start.row),
+                ),
+                CellAddress::new(
+                    bounds.end.col.max(range.end.col),
+                    bounds.end.row.max(range.end.row),
+                ),
+            )
+        })
+    }
+
+    /// The rectangle made of this range and `other` when they sit side by
+    /// side or one above the other with matching edges
+    fn merge_adjacent(&self, other: &CellRange) -> Option<CellRange> {
+        let same_cols = self.start.col == other.start.col && self.end.col == other.end.col;
+        let same_rows = self.start.row == other.start.row && self.end.row == other.end.row;
+        let touches = |a_end: u32, b_start: u32| a_end.checked_add(1) == Some(b_start);
+        let stacked = same_cols
+            && (touches(self.end.row, other.start.row) || touches(other.end.row, self.start.row));
+        let beside = same_rows
+            && (touches(self.end.col, other.start.col) || touches(other.end.col, self.start.col));
+        if stacked || beside {
+            CellRange::bounding_box(&[*self, *other])
+        } else {
+            None
+        }
+    }
 }
 
 /// Represents a parsed formula expression
@@ -108,6 +240,21 @@ pub enum Expr {
         absolute_end_row: bool,
     },
 
+    /// A reference or range on another sheet (e.g., Sheet2!A1 or
+    /// 'My Sheet'!A1:B2), read from that sheet's cells
+    SheetReference { sheet: String, reference: Box<Expr> },
+
+    /// Several areas joined by the union operator, written as a
+    /// comma-separated list in parentheses (e.g., (A1:A5,C1:C5))
+    Union { areas: Vec<Expr> },
+
+    /// The cells two areas share, written with a space between them
+    /// (e.g., A1:C3 B2:D4)
+    Intersection { left: Box<Expr>, right: Box<Expr> },
+
+    /// A defined name (e.g., TaxRate), resolved when the formula is evaluated
+    Name { name: String },
+
     /// A function call (e.g., SUM(A1:A10))
     FunctionCall { name: String, args: Vec<Expr> },
 
@@ -122,6 +269,171 @@ pub enum Expr {
     },
 }
 
+impl Expr {
+    /// The `#REF!` a reference becomes once what it pointed at is deleted
+    pub fn deleted_reference() -> Expr {
+        Expr::Literal {
+            value: CellValue::from_error(ErrorType::InvalidRef {
+                reference: "deleted".to_string(),
+            }),
+        }
+    }
+
+    /// The cells a reference expression covers, as rectangles, or `None`
+    /// when the expression is not made only of references. A union keeps
+    /// its areas in order, overlaps included, so a cell in two areas is
+    /// counted twice; an intersection keeps the non-empty overlaps of its
+    /// operands' areas and may come out empty. Areas on another sheet are
+    /// not cells of this one, so a [`Expr::SheetReference`] has none.
+    pub fn areas(&self) -> Option<Vec<CellRange>> {
+        match self {
+            Expr::Reference { address, .. } => Some(vec![CellRange::new(*address, *address)]),
+            Expr::Range { range, .. } => Some(vec![*range]),
+            Expr::Union { areas } => areas.iter().try_fold(Vec::new(), |mut all, area| {
+                all.extend(area.areas()?);
+                Some(all)
+            }),
+            Expr::Intersection { left, right } => {
+                let right = right.areas()?;
+                Some(
+                    left.areas()?
+                        .iter()
+                        .flat_map(|l| right.iter().filter_map(|r| l.intersect(r)))
+                        .collect(),
+                )
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the expression calls `function` anywhere, ignoring case
+    pub fn calls(&self, function: &str) -> bool {
+        self.calls_any(&|name| name.eq_ignore_ascii_case(function))
+    }
+
+    /// Whether the expression calls a volatile function anywhere, see
+    /// [`FunctionLibrary::is_volatile`](crate::evaluator::FunctionLibrary::is_volatile)
+    pub fn is_volatile(&self) -> bool {
+        self.calls_any(&crate::evaluator::FunctionLibrary::is_volatile)
+    }
+
+    fn calls_any(&self, matches: &dyn Fn(&str) -> bool) -> bool {
+        match self {
+            Expr::FunctionCall { name, args } => {
+                matches(name) || args.iter().any(|arg| arg.calls_any(matches))
+            }
+            Expr::Union { areas } => areas.iter().any(|area| area.calls_any(matches)),
+            Expr::Intersection { left, right } | Expr::BinaryOp { left, right, .. } => {
+                left.calls_any(matches) || right.calls_any(matches)
+            }
+            Expr::UnaryOp { expr, .. } => expr.calls_any(matches),
+            _ => false,
+        }
+    }
+}
+
+/// Canonical formula text, without the leading `=`, that parses back to
+/// the same expression. Parentheses are written only where precedence
+/// needs them.
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Literal { value } => match value {
+                CellValue::String(text) => write!(f, "\"{}\"", text),
+                CellValue::Boolean(b) => f.write_str(if *b { "TRUE" } else { "FALSE" }),
+                value => write!(f, "{}", value),
+            },
+            Expr::Reference {
+                address,
+                absolute_col,
+                absolute_row,
+            } => write_address(f, address, *absolute_col, *absolute_row),
+            Expr::Range {
+                range,
+                absolute_start_col,
+                absolute_start_row,
+                absolute_end_col,
+                absolute_end_row,
+            } => {
+                write_address(f, &range.start, *absolute_start_col, *absolute_start_row)?;
+                f.write_str(":")?;
+                write_address(f, &range.end, *absolute_end_col, *absolute_end_row)
+            }
+            Expr::SheetReference { sheet, reference } => {
+                write!(f, "{}!{}", quote_sheet_name(sheet), reference)
+            }
+            Expr::Union { areas } => {
+                f.write_str("(")?;
+                write_list(f, areas)?;
+                f.write_str(")")
+            }
+            Expr::Intersection { left, right } => write!(f, "{} {}", left, right),
+            Expr::Name { name } => f.write_str(name),
+            Expr::FunctionCall { name, args } => {
+                write!(f, "{}(", name)?;
+                write_list(f, args)?;
+                f.write_str(")")
+            }
+            Expr::UnaryOp { op, expr } => {
+                let operand = match expr.as_ref() {
+                    Expr::BinaryOp { .. } => format!("({})", expr),
+                    _ => expr.to_string(),
+                };
+                match op {
+                    UnaryOperator::Negate => write!(f, "-{}", operand),
+                    UnaryOperator::Percent => write!(f, "{}%", operand),
+                }
+            }
+            Expr::BinaryOp { op, left, right } => {
+                let needs_parens = |side: &Expr, is_right: bool| match side {
+                    Expr::BinaryOp { op: inner, .. } => {
+                        inner.precedence() < op.precedence()
+                            || (inner.precedence() == op.precedence()
+                                && is_right == op.is_left_associative())
+                    }
+                    _ => false,
+                };
+                let write_side = |f: &mut std::fmt::Formatter<'_>, side: &Expr, is_right| {
+                    if needs_parens(side, is_right) {
+                        write!(f, "({})", side)
+                    } else {
+                        write!(f, "{}", side)
+                    }
+                };
+                write_side(f, left, false)?;
+                f.write_str(op.symbol())?;
+                write_side(f, right, true)
+            }
+        }
+    }
+}
+
+fn write_address(
+    f: &mut std::fmt::Formatter<'_>,
+    address: &CellAddress,
+    absolute_col: bool,
+    absolute_row: bool,
+) -> std::fmt::Result {
+    write!(
+        f,
+        "{}{}{}{}",
+        if absolute_col { "$" } else { "" },
+        CellAddress::column_number_to_label(address.col),
+        if absolute_row { "$" } else { "" },
+        address.row + 1
+    )
+}
+
+fn write_list(f: &mut std::fmt::Formatter<'_>, exprs: &[Expr]) -> std::fmt::Result {
+    for (i, expr) in exprs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(",")?;
+        }
+        write!(f, "{}", expr)?;
+    }
+    Ok(())
+}
+
 /// Unary operators
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -179,6 +491,24 @@ impl BinaryOperator {
         }
     }
 
+    /// The operator as written in a formula
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Power => "^",
+            BinaryOperator::Equal => "=",
+            BinaryOperator::NotEqual => "<>",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessThanOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::Concat => "&",
+        }
+    }
+
     /// Check if this operator is left-associative
     pub fn is_left_associative(&self) -> bool {
         // Power is right-associative, all others are left-associative
@@ -329,6 +659,110 @@ mod tests {
         assert_eq!(range.size(), 10);
     }
 
+    /// Every range within a 4x4 grid, small enough to check the rectangle
+    /// algebra against plain cell sets for all pairs
+    fn all_small_ranges() -> Vec<CellRange> {
+        let spans: Vec<(u32, u32)> = (0..4).flat_map(|a| (a..4).map(move |b| (a, b))).collect();
+        spans
+            .iter()
+            .flat_map(|&(c1, c2)| {
+                spans.iter().map(move |&(r1, r2)| {
+                    CellRange::new(CellAddress::new(c1, r1), CellAddress::new(c2, r2))
+                })
+            })
+            .collect()
+    }
+
+    fn cell_set(ranges: &[CellRange]) -> std::collections::BTreeSet<(u32, u32)> {
+        ranges
+            .iter()
+            .flat_map(|range| range.cells())
+            .map(|cell| (cell.col, cell.row))
+            .collect()
+    }
+
+    fn assert_disjoint(ranges: &[CellRange]) {
+        let total: usize = ranges.iter().map(CellRange::size).sum();
+        assert_eq!(total, cell_set(ranges).len(), "{:?} overlap", ranges);
+    }
+
+    #[test]
+    fn test_range_algebra_matches_cell_sets() {
+        let ranges = all_small_ranges();
+        for a in &ranges {
+            let a_cells = cell_set(&[*a]);
+            for b in &ranges {
+                let b_cells = cell_set(&[*b]);
+
+                let both = a.intersect(b);
+                let expected: std::collections::BTreeSet<_> =
+                    a_cells.intersection(&b_cells).copied().collect();
+                assert_eq!(cell_set(&both.into_iter().collect::<Vec<_>>()), expected);
+
+                let difference = a.subtract(b);
+                assert!(difference.len() <= 4);
+                assert_disjoint(&difference);
+                let expected: std::collections::BTreeSet<_> =
+                    a_cells.difference(&b_cells).copied().collect();
+                assert_eq!(cell_set(&difference), expected, "{} - {}", a, b);
+
+                let union = a.union(b);
+                assert_disjoint(&union);
+                let expected: std::collections::BTreeSet<_> =
+                    a_cells.union(&b_cells).copied().collect();
+                assert_eq!(cell_set(&union), expected, "{} | {}", a, b);
+                if expected.len() == a.size() {
+                    // A union that fills one range comes back as that range
+                    assert_eq!(union, vec![*a]);
+                }
+
+                let bounds = CellRange::bounding_box(&[*a, *b]).unwrap();
+                assert!(
+                    expected
+                        .iter()
+                        .all(|&(col, row)| { bounds.contains(&CellAddress::new(col, row)) })
+                );
+                assert!(bounds.contains(&CellAddress::new(
+                    a.start.col.min(b.start.col),
+                    a.start.row.min(b.start.row)
+                )));
+            }
+        }
+
+        // Unions of three, stepping through the ranges to keep it quick
+        for (i, a) in ranges.iter().enumerate().step_by(7) {
+            for b in ranges.iter().skip(i % 5).step_by(11) {
+                for c in ranges.iter().skip(i % 3).step_by(13) {
+                    let union = CellRange::union_all(&[*a, *b, *c]);
+                    assert_disjoint(&union);
+                    assert_eq!(cell_set(&union), cell_set(&[*a, *b, *c]));
+                    let mut sorted = union.clone();
+                    sorted.sort_by_key(|range| (range.start.row, range.start.col));
+                    assert_eq!(union, sorted);
+                }
+            }
+        }
+        assert_eq!(CellRange::bounding_box(&[]), None);
+    }
+
+    #[test]
+    fn test_union_merges_neighbours_and_rows_iterate() {
+        let range = |a1: &str| CellRange::from_string(a1).unwrap();
+        assert_eq!(range("A1:B2").union(&range("C1:C2")), vec![range("A1:C2")]);
+        assert_eq!(range("A1:B2").union(&range("A3:B5")), vec![range("A1:B5")]);
+        assert_eq!(
+            range("A1:B2").union(&range("B2:C3")),
+            vec![range("A1:B2"), range("C2:C2"), range("B3:C3")]
+        );
+        assert_eq!(
+            range("B2:C4").subtract(&range("A3:D3")),
+            vec![range("B2:C2"), range("B4:C4")]
+        );
+
+        let rows: Vec<String> = range("B2:C4").iter_rows().map(|r| r.to_string()).collect();
+        assert_eq!(rows, ["B2:C2", "B3:C3", "B4:C4"]);
+    }
+
     #[test]
     fn test_operator_precedence() {
         assert!(BinaryOperator::Power.precedence() > BinaryOperator::Multiply.precedence());
@@ -336,4 +770,29 @@ mod tests {
         assert!(BinaryOperator::Add.precedence() > BinaryOperator::Equal.precedence());
         assert!(BinaryOperator::Equal.precedence() > BinaryOperator::Concat.precedence());
     }
+
+    #[test]
+    fn test_display_parses_back() {
+        use crate::formula::FormulaParser;
+
+        for formula in [
+            "SUM($B$1:B7)",
+            "A$1+$C2*3",
+            "(A1+B1)*C1",
+            "A1-(B1-C1)",
+            "2^3^2",
+            "(2^3)^2",
+            "-(A1+1)%",
+            "IF(A1>=10,\"big\",FALSE)&\"!\"",
+            "SUM((A1:A5,C1:C5))",
+            "SUM(A1:C3 B2:D4)",
+            "TaxRate*A1",
+            "Sheet2!A1+'My Sheet'!$B$2",
+            "SUM('Q1 ''Draft'''!A1:B5)",
+        ] {
+            let parsed = FormulaParser::parse(formula).unwrap();
+            assert_eq!(parsed.to_string(), formula);
+            assert_eq!(FormulaParser::parse(&parsed.to_string()).unwrap(), parsed);
+        }
+    }
 }
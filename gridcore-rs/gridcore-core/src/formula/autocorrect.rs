@@ -0,0 +1,313 @@
+//! Fixes for common mistakes in typed formulas.
+//!
+//! A formula that does not parse is tried with each enabled correction on
+//! its own. When exactly one distinct fix parses it is applied; when none
+//! or several do, the formula is left for the parse error to report. A
+//! formula that parses only ever gets its function names capitalized.
+
+use super::parser::FormulaParser;
+use super::tokenizer::{LexToken, Tokenizer};
+use std::fmt;
+use std::str::FromStr;
+
+/// A kind of mistake autocorrect fixes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Correction {
+    /// `=SUM(A1:A3` becomes `=SUM(A1:A3)`
+    ClosingParentheses,
+    /// `=A1+` becomes `=A1`
+    TrailingOperator,
+    /// `=sum(A1:A3)` becomes `=SUM(A1:A3)`
+    FunctionCase,
+    /// `=IF(A1;1;2)` becomes `=IF(A1,1,2)`
+    ArgumentSeparators,
+    /// `=A1**2` becomes `=A1*2`
+    DoubledOperator,
+}
+
+impl Correction {
+    pub const ALL: [Correction; 5] = [
+        Correction::ClosingParentheses,
+        Correction::TrailingOperator,
+        Correction::FunctionCase,
+        Correction::ArgumentSeparators,
+        Correction::DoubledOperator,
+    ];
+
+    /// Name used in settings, e.g. `:set autocorrections=parens,case`
+    pub fn name(self) -> &'static str {
+        match self {
+            Correction::ClosingParentheses => "parens",
+            Correction::TrailingOperator => "trailing",
+            Correction::FunctionCase => "case",
+            Correction::ArgumentSeparators => "separators",
+            Correction::DoubledOperator => "doubled",
+        }
+    }
+
+    /// Each way this correction could rewrite `tokens`, with a description
+    fn candidates(self, tokens: &[LexToken]) -> Vec<(String, String)> {
+        match self {
+            Correction::ClosingParentheses => {
+                let depth = tokens.iter().fold(0i32, |depth, token| match token {
+                    LexToken::Punct("(") => depth + 1,
+                    LexToken::Punct(")") => depth - 1,
+                    _ => depth,
+                });
+                if depth <= 0 {
+                    return Vec::new();
+                }
+                let description = match depth {
+                    1 => "Added 1 closing parenthesis".to_string(),
+                    n => format!("Added {} closing parentheses", n),
+                };
+                vec![(join(tokens) + &")".repeat(depth as usize), description)]
+            }
+            Correction::TrailingOperator => {
+                let Some(last) = tokens
+                    .iter()
+                    .rposition(|token| !matches!(token, LexToken::Whitespace(_)))
+                else {
+                    return Vec::new();
+                };
+                match tokens[last] {
+                    LexToken::Punct(op) if is_binary_operator(op) => vec![(
+                        join(&tokens[..last]).trim_end().to_string(),
+                        format!("Removed the trailing '{}'", op),
+                    )],
+                    _ => Vec::new(),
+                }
+            }
+            Correction::FunctionCase => capitalize_functions(tokens)
+                .map(|text| (text, "Capitalized function names".to_string()))
+                .into_iter()
+                .collect(),
+            Correction::ArgumentSeparators => {
+                // In an array literal a semicolon separates rows
+                let semicolons = tokens.contains(&LexToken::Punct(";"));
+                let arrays = tokens.contains(&LexToken::Punct("{"));
+                if !semicolons || arrays {
+                    return Vec::new();
+                }
+                let text = tokens
+                    .iter()
+                    .map(|token| match token {
+                        LexToken::Punct(";") => ",",
+                        token => token.text(),
+                    })
+                    .collect();
+                vec![(text, "Replaced ';' with ','".to_string())]
+            }
+            Correction::DoubledOperator => {
+                let significant: Vec<usize> = (0..tokens.len())
+                    .filter(|&i| !matches!(tokens[i], LexToken::Whitespace(_)))
+                    .collect();
+                let mut candidates = Vec::new();
+                for pair in significant.windows(2) {
+                    let (LexToken::Punct(first), LexToken::Punct(second)) =
+                        (tokens[pair[0]], tokens[pair[1]])
+                    else {
+                        continue;
+                    };
+                    let compound = matches!((first, second), ("<", "=" | ">") | (">", "="));
+                    if !is_binary_operator(first) || !is_binary_operator(second) || compound {
+                        continue;
+                    }
+                    for (removed, op) in [(pair[0], first), (pair[1], second)] {
+                        let text = tokens
+                            .iter()
+                            .enumerate()
+                            .filter(|&(i, _)| i != removed)
+                            .map(|(_, token)| token.text())
+                            .collect();
+                        candidates.push((text, format!("Removed the extra '{}'", op)));
+                    }
+                }
+                candidates
+            }
+        }
+    }
+}
+
+impl fmt::Display for Correction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Correction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Correction::ALL
+            .into_iter()
+            .find(|correction| correction.name() == s)
+            .ok_or_else(|| format!("Unknown correction: {}", s))
+    }
+}
+
+/// Whether typed formulas are corrected, and which mistakes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutocorrectSettings {
+    /// Off unless turned on
+    pub enabled: bool,
+    pub corrections: Vec<Correction>,
+}
+
+impl Default for AutocorrectSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            corrections: Correction::ALL.to_vec(),
+        }
+    }
+}
+
+/// A typed formula and what autocorrect made of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Autocorrection {
+    /// The entry as typed, with its `=`
+    pub original: String,
+    /// The entry to store instead
+    pub corrected: String,
+    pub correction: Correction,
+    /// What changed, for the status line, e.g. "Added 2 closing parentheses"
+    pub description: String,
+}
+
+/// Correct the formula `entry`, including its `=`, under `settings`.
+/// `None` when autocorrect is off, the entry is no formula, nothing needs
+/// fixing or no single fix makes it parse.
+pub fn autocorrect(entry: &str, settings: &AutocorrectSettings) -> Option<Autocorrection> {
+    let formula = entry.strip_prefix('=')?;
+    if !settings.enabled {
+        return None;
+    }
+    let tokens = Tokenizer::lex(formula, '.');
+    let allowed = |correction: &Correction| settings.corrections.contains(correction);
+    let parses = |text: &str| FormulaParser::parse(text).is_ok();
+
+    let corrections: Vec<Correction> = if parses(formula) {
+        vec![Correction::FunctionCase]
+    } else {
+        Correction::ALL.to_vec()
+    };
+    let mut fixes: Vec<(String, Correction, String)> = Vec::new();
+    for correction in corrections.into_iter().filter(allowed) {
+        for (text, description) in correction.candidates(&tokens) {
+            if parses(&text) && !fixes.iter().any(|(fixed, ..)| *fixed == text) {
+                fixes.push((text, correction, description));
+            }
+        }
+    }
+
+    let [(text, correction, description)] = <[_; 1]>::try_from(fixes).ok()?;
+    Some(Autocorrection {
+        original: entry.to_string(),
+        corrected: format!("={}", text),
+        correction,
+        description,
+    })
+}
+
+fn join(tokens: &[LexToken]) -> String {
+    tokens.iter().map(LexToken::text).collect()
+}
+
+fn is_binary_operator(op: &str) -> bool {
+    matches!(op, "+" | "-" | "*" | "/" | "^" | "&" | "=" | "<" | ">")
+}
+
+/// `tokens` with every name called as a function in capitals, or `None`
+/// when they already are
+fn capitalize_functions(tokens: &[LexToken]) -> Option<String> {
+    let mut changed = false;
+    let mut text = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let called = tokens[i + 1..]
+            .iter()
+            .find(|next| !matches!(next, LexToken::Whitespace(_)))
+            .is_some_and(|next| *next == LexToken::Punct("("));
+        match token {
+            LexToken::Ident(name) if called && name.chars().any(char::is_lowercase) => {
+                changed = true;
+                text.push_str(&name.to_uppercase());
+            }
+            token => text.push_str(token.text()),
+        }
+    }
+    changed.then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn on() -> AutocorrectSettings {
+        AutocorrectSettings {
+            enabled: true,
+            ..AutocorrectSettings::default()
+        }
+    }
+
+    fn corrected(entry: &str) -> Option<(String, String)> {
+        autocorrect(entry, &on()).map(|fix| (fix.corrected, fix.description))
+    }
+
+    fn fixed(corrected: &str, description: &str) -> Option<(String, String)> {
+        Some((corrected.to_string(), description.to_string()))
+    }
+
+    #[test]
+    fn test_each_correction() {
+        assert_eq!(
+            corrected("=SUM(A1:A3"),
+            fixed("=SUM(A1:A3)", "Added 1 closing parenthesis")
+        );
+        assert_eq!(
+            corrected("=ROUND(SUM(A1:A3, 2"),
+            fixed("=ROUND(SUM(A1:A3, 2))", "Added 2 closing parentheses")
+        );
+        assert_eq!(
+            corrected("=A1 + "),
+            fixed("=A1", "Removed the trailing '+'")
+        );
+        assert_eq!(
+            corrected("=sum(a1:a3) + Max(1, 2)"),
+            fixed("=SUM(a1:a3) + MAX(1, 2)", "Capitalized function names")
+        );
+        assert_eq!(
+            corrected("=IF(A1;\"a;b\";2)"),
+            fixed("=IF(A1,\"a;b\",2)", "Replaced ';' with ','")
+        );
+        assert_eq!(corrected("=A1**2"), fixed("=A1*2", "Removed the extra '*'"));
+    }
+
+    #[test]
+    fn test_ambiguous_or_unfixable_formulas_are_left_alone() {
+        // Dropping either operator parses, and the two mean different things
+        assert_eq!(corrected("=A1+*2"), None);
+        // Two mistakes need two corrections
+        assert_eq!(corrected("=SUM(1;2"), None);
+        assert_eq!(corrected("=A1 >= 2"), None);
+        assert_eq!(corrected("=SUM(A1:A3)"), None);
+        assert_eq!(corrected("SUM(A1"), None);
+    }
+
+    #[test]
+    fn test_settings_choose_the_corrections() {
+        assert_eq!(
+            autocorrect("=SUM(A1", &AutocorrectSettings::default()),
+            None
+        );
+        let settings = AutocorrectSettings {
+            enabled: true,
+            corrections: vec![Correction::TrailingOperator],
+        };
+        assert_eq!(autocorrect("=SUM(A1", &settings), None);
+        assert_eq!(autocorrect("=sum(A1)", &settings), None);
+        assert!(autocorrect("=A1/", &settings).is_some());
+        assert_eq!("separators".parse(), Ok(Correction::ArgumentSeparators));
+        assert!("commas".parse::<Correction>().is_err());
+    }
+}
@@ -24,6 +24,30 @@ impl ExpressionBuilder {
             .map(|(name, args)| Expr::FunctionCall { name, args })
     }
 
+    /// Build a parenthesized expression parser. A comma-separated list of
+    /// areas in parentheses is a union (e.g., (A1:A5,C1:C5)).
+    pub fn parenthesized<'a>(
+        expr_parser: impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone + 'a,
+    ) -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone {
+        expr_parser
+            .separated_by(just(',').padded())
+            .at_least(1)
+            .collect::<Vec<_>>()
+            .delimited_by(just('(').padded(), just(')').padded())
+            .try_map(|mut exprs, span| {
+                if exprs.len() == 1 {
+                    return Ok(exprs.remove(0));
+                }
+                if exprs.iter().any(|expr| expr.areas().is_none()) {
+                    return Err(Rich::custom(
+                        span,
+                        "Only cell references and ranges can be joined with ','",
+                    ));
+                }
+                Ok(Expr::Union { areas: exprs })
+            })
+    }
+
     /// Build the complete expression parser with operator precedence
     pub fn build<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone {
         recursive(|expr| {
@@ -42,13 +66,13 @@ impl ExpressionBuilder {
         choice((
             // Order matters: try more specific patterns first
             Self::function_call(expr.clone()),
-            Tokenizer::cell_range(),
-            Tokenizer::cell_reference(),
+            Tokenizer::area(),
             Tokenizer::number(),
             Tokenizer::boolean(),
+            Tokenizer::name(),
             Tokenizer::string(),
-            // Parenthesized expression
-            expr.delimited_by(just('(').padded(), just(')').padded()),
+            // Parenthesized expression or union
+            Self::parenthesized(expr),
         ))
     }
 
@@ -1,12 +1,18 @@
 pub mod ast;
+pub mod autocorrect;
 pub mod expression_builder;
 pub mod parser;
+pub mod subexpression;
 pub mod tokenizer;
 pub mod transformer;
+pub mod translator;
 
 #[cfg(test)]
 pub mod parser_tests;
 
 pub use ast::{BinaryOperator, CellRange, Expr, UnaryOperator};
-pub use parser::FormulaParser;
+pub use autocorrect::{AutocorrectSettings, Autocorrection, Correction, autocorrect};
+pub use parser::{FormulaParser, ParseDiagnostic};
+pub use subexpression::enclosing_subexpression;
 pub use transformer::FormulaTransformer;
+pub use translator::{FormulaConvention, FormulaTranslator};
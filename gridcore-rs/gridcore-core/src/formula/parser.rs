@@ -1,16 +1,28 @@
 use super::ast::{BinaryOperator, Expr, UnaryOperator};
 use crate::types::CellAddress;
 use crate::{Result, SpreadsheetError};
+use chumsky::error::RichPattern;
 use chumsky::pratt::*;
 use chumsky::prelude::*;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::ops::Range;
 
 // Static regex for cell reference validation
 static CELL_REF_REGEX: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^([A-Z]+)([0-9]+)$").expect("Invalid regex pattern for cell reference")
 });
 
+/// Why a formula does not parse and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDiagnostic {
+    /// Byte range of the offending text in the formula as given, with its
+    /// `=` if it had one. Empty at the end when the formula stops short.
+    pub span: Range<usize>,
+    /// E.g. `Unexpected '*'`
+    pub message: String,
+}
+
 /// Main formula parser that coordinates tokenization and expression building
 pub struct FormulaParser;
 
@@ -60,6 +72,30 @@ impl FormulaParser {
         }
     }
 
+    /// The first error in `formula`, located in the text as given, or
+    /// `None` when it parses
+    pub fn diagnose(formula: &str) -> Option<ParseDiagnostic> {
+        let body = formula.trim_start_matches('=').trim_start();
+        let offset = formula.len() - body.len();
+        let errors = Self::parser().parse(body.trim_end()).into_errors();
+        let error = errors.first()?;
+        let span = error.span();
+        let message = match error.found() {
+            Some(found) => format!("Unexpected '{}'", found),
+            None if error
+                .expected()
+                .any(|pattern| matches!(pattern, RichPattern::Token(token) if **token == ')')) =>
+            {
+                "Missing closing parenthesis".to_string()
+            }
+            None => "The formula ends too soon".to_string(),
+        };
+        Some(ParseDiagnostic {
+            span: offset + span.start..offset + span.end,
+            message,
+        })
+    }
+
     /// Build the Chumsky 0.10 parser
     fn parser<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> {
         recursive(|expr| {
@@ -70,17 +106,19 @@ impl FormulaParser {
             // Build atom parser - the basic units that can appear in expressions
             let atom = choice((
                 // Order matters: try more specific patterns first
-                // Cell references and ranges must come before function calls
+                // Cell references, ranges and their intersections must come
+                // before function calls
                 // because XYZ999 looks like a function name but is actually a cell reference
-                Tokenizer::cell_range(),
-                Tokenizer::cell_reference(),
+                Tokenizer::area(),
                 ExpressionBuilder::function_call(expr.clone()),
                 Tokenizer::number(),
                 Tokenizer::boolean(),
+                Tokenizer::ref_error(),
+                // Bare words that are not functions, references or booleans
+                Tokenizer::name(),
                 Tokenizer::string(),
-                // Parenthesized expression
-                expr.clone()
-                    .delimited_by(just('(').padded(), just(')').padded()),
+                // Parenthesized expression or union of areas
+                ExpressionBuilder::parenthesized(expr.clone()),
             ));
 
             // Build the expression parser with operator precedence using pratt
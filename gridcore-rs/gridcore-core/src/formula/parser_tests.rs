@@ -1,4 +1,4 @@
-use super::ast::{BinaryOperator, Expr, UnaryOperator};
+use super::ast::{BinaryOperator, CellRange, Expr, UnaryOperator};
 use super::parser::FormulaParser;
 use crate::types::CellValue;
 
@@ -7,10 +7,9 @@ fn test_parse_number() {
     let expr = FormulaParser::parse("42").expect("Failed to parse formula '42' in test");
     assert!(matches!(expr, Expr::Literal { value: CellValue::Number(n) } if n == 42.0));
 
-    let expr = FormulaParser::parse("3.14").expect("Failed to parse formula '3.14' in test");
-    // Compare with the parsed value 3.14
+    let expr = FormulaParser::parse("2.75").expect("Failed to parse formula '2.75' in test");
     assert!(
-        matches!(expr, Expr::Literal { value: CellValue::Number(n) } if (n - 3.140).abs() < 0.001)
+        matches!(expr, Expr::Literal { value: CellValue::Number(n) } if (n - 2.75).abs() < 0.001)
     );
 }
 
@@ -286,9 +285,19 @@ fn test_malformed_cell_references() {
     let result = FormulaParser::parse("1A");
     assert!(result.is_err(), "1A should be invalid");
 
-    // Negative row is invalid
+    // Negative row is not a reference: the bare A reads as a defined name
     let result = FormulaParser::parse("A-1");
-    assert!(result.is_err(), "A-1 should be invalid");
+    assert!(
+        matches!(
+            result,
+            Ok(Expr::BinaryOp {
+                op: BinaryOperator::Subtract,
+                ref left,
+                ..
+            }) if matches!(**left, Expr::Name { .. })
+        ),
+        "A-1 should not parse as a reference"
+    );
 }
 
 #[test]
@@ -304,11 +313,29 @@ fn test_unclosed_parentheses() {
 }
 
 #[test]
-fn test_invalid_ranges() {
-    // End before start (column-wise)
+fn test_reversed_ranges() {
+    // End before start (column-wise) is the same range
     let expr = FormulaParser::parse("B1:A1").expect("Failed to parse formula 'B1:A1' in test");
-    // Parser allows this - range validation happens at evaluation
-    assert!(matches!(expr, Expr::Range { .. }));
+    assert!(
+        matches!(expr, Expr::Range { range, .. } if range == CellRange::from_string("A1:B1").unwrap())
+    );
+
+    // Each corner keeps its own `$` markers once put in order
+    let expr = FormulaParser::parse("B10:$A1").expect("Failed to parse formula 'B10:$A1' in test");
+    match expr {
+        Expr::Range {
+            range,
+            absolute_start_col,
+            absolute_start_row,
+            absolute_end_col,
+            absolute_end_row,
+        } => {
+            assert_eq!(range, CellRange::from_string("A1:B10").unwrap());
+            assert!(absolute_start_col);
+            assert!(!absolute_start_row && !absolute_end_col && !absolute_end_row);
+        }
+        _ => panic!("Expected range"),
+    }
 }
 
 // ==================== COMPLEX FORMULA TESTS ====================
@@ -1022,3 +1049,156 @@ fn test_multi_column_range() {
         _ => panic!("Expected absolute multi-column range"),
     }
 }
+
+#[test]
+fn test_defined_names() {
+    let expr = FormulaParser::parse("=A1*TaxRate").expect("Failed to parse name in test");
+    match expr {
+        Expr::BinaryOp { right, .. } => {
+            assert_eq!(
+                *right,
+                Expr::Name {
+                    name: "TaxRate".to_string()
+                }
+            );
+        }
+        other => panic!("Expected binary op, got {:?}", other),
+    }
+
+    // Function calls, references and booleans still take precedence
+    assert!(matches!(
+        FormulaParser::parse("ROUND(_rate, 2)").unwrap(),
+        Expr::FunctionCall { ref args, .. } if matches!(args[0], Expr::Name { .. })
+    ));
+    assert!(matches!(
+        FormulaParser::parse("TAX1").unwrap(),
+        Expr::Reference { .. }
+    ));
+    assert!(matches!(
+        FormulaParser::parse("TRUE").unwrap(),
+        Expr::Literal { .. }
+    ));
+}
+
+#[test]
+fn test_multi_area_references() {
+    let range = |a1: &str| crate::formula::CellRange::from_string(a1).unwrap();
+
+    // A comma inside parentheses joins areas into a union
+    let expr = FormulaParser::parse("=SUM((A1:A5, C1:C5,E1))").unwrap();
+    let Expr::FunctionCall { args, .. } = expr else {
+        panic!("Expected function call, got {:?}", expr);
+    };
+    assert!(matches!(&args[0], Expr::Union { areas } if areas.len() == 3));
+    assert_eq!(
+        args[0].areas(),
+        Some(vec![range("A1:A5"), range("C1:C5"), range("E1:E1")])
+    );
+
+    // A space between areas intersects them, binding tighter than operators
+    let expr = FormulaParser::parse("A1:C3 B2:D4 + 1").unwrap();
+    let Expr::BinaryOp { left, .. } = expr else {
+        panic!("Expected binary op, got {:?}", expr);
+    };
+    assert!(matches!(*left, Expr::Intersection { .. }));
+    assert_eq!(left.areas(), Some(vec![range("B2:C3")]));
+    assert_eq!(
+        FormulaParser::parse("A1:A3 A2:B3 B3").unwrap().areas(),
+        Some(vec![])
+    );
+
+    // Only references can be joined; a lone parenthesized value is unchanged
+    assert!(FormulaParser::parse("SUM((A1, 2))").is_err());
+    assert_eq!(
+        FormulaParser::parse("(1)").unwrap(),
+        Expr::Literal {
+            value: CellValue::Number(1.0)
+        }
+    );
+    assert!(matches!(
+        FormulaParser::parse("SUM(A1 , B1)").unwrap(),
+        Expr::FunctionCall { ref args, .. } if args.len() == 2
+    ));
+}
+
+#[test]
+fn test_deleted_references_parse_and_print_back() {
+    let expr = FormulaParser::parse("=SUM(#REF!,A1)+#REF!").unwrap();
+    let Expr::BinaryOp { left, right, .. } = &expr else {
+        panic!("Expected binary op, got {:?}", expr);
+    };
+    assert_eq!(**right, Expr::deleted_reference());
+    assert!(matches!(
+        left.as_ref(),
+        Expr::FunctionCall { args, .. } if args[0] == Expr::deleted_reference()
+    ));
+    assert_eq!(expr.to_string(), "SUM(#REF!,A1)+#REF!");
+
+    // Other error codes are not literals
+    assert!(FormulaParser::parse("#NAME?").is_err());
+    assert!(FormulaParser::parse("#REFS!").is_err());
+}
+
+#[test]
+fn test_sheet_references() {
+    let reference = |a1: &str| FormulaParser::parse(a1).unwrap();
+
+    let expr = FormulaParser::parse("=Sheet2!A1").unwrap();
+    assert_eq!(
+        expr,
+        Expr::SheetReference {
+            sheet: "Sheet2".to_string(),
+            reference: Box::new(reference("A1")),
+        }
+    );
+
+    // Quoted names may hold spaces and doubled quotes
+    let expr = FormulaParser::parse("=SUM('My Sheet'!A1:B2, 'Q1 ''Draft'''!$C$3)").unwrap();
+    let Expr::FunctionCall { args, .. } = &expr else {
+        panic!("Expected function call, got {:?}", expr);
+    };
+    assert_eq!(
+        args[0],
+        Expr::SheetReference {
+            sheet: "My Sheet".to_string(),
+            reference: Box::new(reference("A1:B2")),
+        }
+    );
+    assert!(matches!(
+        &args[1],
+        Expr::SheetReference { sheet, reference } if sheet == "Q1 'Draft'"
+            && matches!(**reference, Expr::Reference { absolute_col: true, .. })
+    ));
+    // Another sheet's cells are not this sheet's areas
+    assert_eq!(args[0].areas(), None);
+    assert_eq!(
+        expr.to_string(),
+        "SUM('My Sheet'!A1:B2,'Q1 ''Draft'''!$C$3)"
+    );
+
+    assert!(FormulaParser::parse("=Sheet2!").is_err());
+    assert!(FormulaParser::parse("='Open!A1").is_err());
+}
+
+#[test]
+fn test_diagnose_points_at_the_error() {
+    assert_eq!(FormulaParser::diagnose("=SUM(A1, 2)"), None);
+    let diagnose = |formula: &str| {
+        let diagnostic = FormulaParser::diagnose(formula).unwrap();
+        (formula[diagnostic.span].to_string(), diagnostic.message)
+    };
+    // Spans count the `=` and the spaces after it
+    assert_eq!(
+        diagnose("= 1+*2"),
+        ("*".to_string(), "Unexpected '*'".to_string())
+    );
+    assert_eq!(
+        diagnose("=A1)"),
+        (")".to_string(), "Unexpected ')'".to_string())
+    );
+    assert_eq!(
+        diagnose("=SUM((1)"),
+        (String::new(), "Missing closing parenthesis".to_string())
+    );
+    assert_eq!(FormulaParser::diagnose("=1+").unwrap().span, 3..3);
+}
@@ -0,0 +1,151 @@
+//! Locating complete sub-expressions in formula text, e.g. to evaluate just
+//! the part of a formula selected in the editor.
+
+use super::ast::Expr;
+use super::parser::FormulaParser;
+use super::tokenizer::{LexToken, Tokenizer};
+use crate::Result;
+use std::ops::Range;
+
+/// Byte range of the smallest complete sub-expression of `formula` that
+/// covers `span`, which may be empty to stand for a caret position.
+///
+/// A stretch of the formula is complete when putting it in parentheses
+/// leaves the parsed expression unchanged: in `=A1*B1+C1`, `A1*B1` is
+/// complete but `B1+C1` is not, so selecting the latter snaps to the whole
+/// formula. Ranges are skipped as they have no value of their own. Fails
+/// when the formula itself does not parse.
+pub fn enclosing_subexpression(formula: &str, span: Range<usize>) -> Result<Range<usize>> {
+    let expr = FormulaParser::parse(formula)?;
+
+    let body_start = formula.len() - formula.trim_start().trim_start_matches('=').len();
+    let mut offset = 0;
+    let mut tokens: Vec<(Range<usize>, LexToken<'_>)> = Vec::new();
+    for token in Tokenizer::lex(formula, '.') {
+        let range = offset..offset + token.text().len();
+        offset = range.end;
+        if range.start >= body_start && !matches!(token, LexToken::Whitespace(_)) {
+            tokens.push((range, token));
+        }
+    }
+    let whole = match (tokens.first(), tokens.last()) {
+        (Some((first, _)), Some((last, _))) => first.start..last.end,
+        _ => body_start..formula.len(),
+    };
+
+    // The first and last tokens the span touches
+    let touches = |range: &Range<usize>| {
+        if span.is_empty() {
+            range.start <= span.start && span.start <= range.end
+        } else {
+            range.start < span.end && span.start < range.end
+        }
+    };
+    let Some(first) = tokens.iter().position(|(range, _)| touches(range)) else {
+        return Ok(whole);
+    };
+    let last = if span.is_empty() {
+        first
+    } else {
+        tokens
+            .iter()
+            .rposition(|(range, _)| touches(range))
+            .unwrap_or(first)
+    };
+
+    let mut candidates: Vec<(usize, usize)> = (0..=first)
+        .flat_map(|i| (last..tokens.len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| balanced(&tokens[i..=j]))
+        .collect();
+    candidates.sort_by_key(|&(i, j)| tokens[j].0.end - tokens[i].0.start);
+
+    for (i, j) in candidates {
+        let range = tokens[i].0.start..tokens[j].0.end;
+        if range == whole {
+            break;
+        }
+        let wrapped = format!(
+            "{}({}){}",
+            &formula[..range.start],
+            &formula[range.clone()],
+            &formula[range.end..]
+        );
+        // Ranges only have a value as function arguments
+        let is_range = matches!(
+            FormulaParser::parse(&formula[range.clone()]),
+            Ok(Expr::Range { .. })
+        );
+        if !is_range && FormulaParser::parse(&wrapped).is_ok_and(|wrapped| wrapped == expr) {
+            return Ok(range);
+        }
+    }
+    Ok(whole)
+}
+
+/// Whether the parentheses among `tokens` pair up
+fn balanced(tokens: &[(Range<usize>, LexToken<'_>)]) -> bool {
+    let mut depth = 0usize;
+    for (_, token) in tokens {
+        match token {
+            LexToken::Punct("(") => depth += 1,
+            LexToken::Punct(")") => match depth.checked_sub(1) {
+                Some(outer) => depth = outer,
+                None => return false,
+            },
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Snap the first occurrence of `selected` in `formula`
+    fn snap(formula: &str, selected: &str) -> String {
+        let start = formula.find(selected).unwrap();
+        let range = enclosing_subexpression(formula, start..start + selected.len()).unwrap();
+        formula[range].to_string()
+    }
+
+    #[test]
+    fn test_selection_snaps_to_complete_nodes() {
+        let formula = "=A1*B1+SUM(C1:C10)";
+        assert_eq!(snap(formula, "A1*B1"), "A1*B1");
+        assert_eq!(snap(formula, "1*B"), "A1*B1");
+        assert_eq!(snap(formula, "SUM"), "SUM(C1:C10)");
+        // A range alone is not an expression
+        assert_eq!(snap(formula, "C1"), "SUM(C1:C10)");
+        assert_eq!(snap(formula, "B1+S"), "A1*B1+SUM(C1:C10)");
+
+        // Precedence decides what is complete
+        assert_eq!(snap("=1+2*3", "1+2"), "1+2*3");
+        assert_eq!(snap("=1+2*3", "2*"), "2*3");
+        assert_eq!(snap("=1+2+3", "2+3"), "1+2+3");
+        assert_eq!(snap("=1+2+3", "1+2"), "1+2");
+        assert_eq!(snap("=MAX(1, 2 + 3)", "2 +"), "2 + 3");
+        assert_eq!(snap("=MAX(1, 2 + 3)", "1, 2"), "MAX(1, 2 + 3)");
+    }
+
+    #[test]
+    fn test_caret_and_whitespace() {
+        let formula = "= A1 * (B1 + 2) ";
+        let caret = formula.find("B1").unwrap() + 1;
+        assert_eq!(
+            enclosing_subexpression(formula, caret..caret).unwrap(),
+            caret - 1..caret + 1
+        );
+        let paren = formula.find('(').unwrap();
+        assert_eq!(
+            &formula[enclosing_subexpression(formula, paren..paren + 1).unwrap()],
+            "(B1 + 2)"
+        );
+        // Only the `=` and blanks selected
+        assert_eq!(
+            &formula[enclosing_subexpression(formula, 0..2).unwrap()],
+            "A1 * (B1 + 2)"
+        );
+        assert!(enclosing_subexpression("=A1*(", 1..2).is_err());
+    }
+}
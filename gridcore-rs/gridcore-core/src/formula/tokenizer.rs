@@ -3,6 +3,40 @@ use crate::SpreadsheetError;
 use crate::types::{CellAddress, CellValue};
 use chumsky::prelude::*;
 
+/// A lexical token of formula text, borrowing the exact source slice.
+///
+/// Unlike the expression parsers below, lexing never fails: anything that is
+/// not a literal, identifier or whitespace comes out as a single-character
+/// [`LexToken::Punct`], so concatenating the token texts gives back the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LexToken<'a> {
+    /// Double-quoted string literal including its quotes (may be unterminated)
+    String(&'a str),
+    /// Single-quoted sheet name including its quotes, with embedded quotes
+    /// doubled (may be unterminated)
+    QuotedName(&'a str),
+    /// Unsigned number literal, using the decimal separator given to the lexer
+    Number(&'a str),
+    /// Function name, cell reference, boolean or other bare word
+    Ident(&'a str),
+    Whitespace(&'a str),
+    Punct(&'a str),
+}
+
+impl<'a> LexToken<'a> {
+    /// The source text of the token
+    pub fn text(&self) -> &'a str {
+        match self {
+            LexToken::String(s)
+            | LexToken::QuotedName(s)
+            | LexToken::Number(s)
+            | LexToken::Ident(s)
+            | LexToken::Whitespace(s)
+            | LexToken::Punct(s) => s,
+        }
+    }
+}
+
 /// Tokenizer for formula expressions
 /// Handles parsing of individual tokens like numbers, strings, cell references, etc.
 pub struct Tokenizer;
@@ -35,6 +69,16 @@ impl Tokenizer {
         .padded()
     }
 
+    /// Parse `#REF!`, which structural changes leave in place of deleted
+    /// references
+    pub fn ref_error<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone {
+        just('#')
+            .ignore_then(text::keyword("REF"))
+            .then_ignore(just('!'))
+            .map(|_| Expr::deleted_reference())
+            .padded()
+    }
+
     /// Parse a string literal
     pub fn string<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone {
         just('"')
@@ -84,17 +128,77 @@ impl Tokenizer {
     /// Parse a single cell reference as an expression
     pub fn cell_reference<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone
     {
-        Self::cell_reference_parts()
-            .map(|(addr, abs_col, abs_row)| Expr::Reference {
-                address: addr,
-                absolute_col: abs_col,
-                absolute_row: abs_row,
-            })
-            .padded()
+        Self::unpadded_reference().padded()
+    }
+
+    fn unpadded_reference<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone
+    {
+        Self::cell_reference_parts().map(|(addr, abs_col, abs_row)| Expr::Reference {
+            address: addr,
+            absolute_col: abs_col,
+            absolute_row: abs_row,
+        })
     }
 
     /// Parse a cell range (e.g., A1:B10)
     pub fn cell_range<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone {
+        Self::unpadded_range().padded()
+    }
+
+    /// Parse a cell range or reference, or several of them separated by
+    /// spaces, the intersection operator (e.g., A1:C3 B2:D4)
+    pub fn area<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone {
+        let single = Self::sheet_prefix()
+            .or_not()
+            .then(choice((Self::unpadded_range(), Self::unpadded_reference())))
+            .map(|(sheet, reference)| match sheet {
+                Some(sheet) => Expr::SheetReference {
+                    sheet,
+                    reference: Box::new(reference),
+                },
+                None => reference,
+            });
+        single
+            .clone()
+            .foldl(
+                text::inline_whitespace()
+                    .at_least(1)
+                    .ignore_then(single)
+                    .repeated(),
+                |left, right| Expr::Intersection {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+            )
+            .padded()
+    }
+
+    /// Parse the sheet name and `!` before a reference on another sheet,
+    /// plain (e.g. `Sheet2!`) or quoted with quotes inside it doubled (e.g.
+    /// `'Q1 ''Draft'''!`)
+    fn sheet_prefix<'a>() -> impl Parser<'a, &'a str, String, extra::Err<Rich<'a, char>>> + Clone {
+        let quoted = just('\'')
+            .ignore_then(
+                just("''")
+                    .to('\'')
+                    .or(none_of('\''))
+                    .repeated()
+                    .at_least(1)
+                    .collect::<String>(),
+            )
+            .then_ignore(just('\''));
+        let plain = one_of('A'..='Z')
+            .or(one_of('a'..='z'))
+            .or(one_of('0'..='9'))
+            .or(just('_'))
+            .repeated()
+            .at_least(1)
+            .to_slice()
+            .map(str::to_string);
+        choice((quoted, plain)).then_ignore(just('!'))
+    }
+
+    fn unpadded_range<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone {
         Self::cell_reference_parts()
             .clone()
             .then_ignore(just(':'))
@@ -104,8 +208,17 @@ impl Tokenizer {
                     (start_addr, abs_start_col, abs_start_row),
                     (end_addr, abs_end_col, abs_end_row),
                 )| {
+                    // A range written end-first, like A10:A1, is the same
+                    // range; its corners are put in order with their `$`s
+                    let ((start_col, abs_start_col), (end_col, abs_end_col)) =
+                        ordered((start_addr.col, abs_start_col), (end_addr.col, abs_end_col));
+                    let ((start_row, abs_start_row), (end_row, abs_end_row)) =
+                        ordered((start_addr.row, abs_start_row), (end_addr.row, abs_end_row));
                     Expr::Range {
-                        range: CellRange::new(start_addr, end_addr),
+                        range: CellRange::new(
+                            CellAddress::new(start_col, start_row),
+                            CellAddress::new(end_col, end_row),
+                        ),
                         absolute_start_col: abs_start_col,
                         absolute_start_row: abs_start_row,
                         absolute_end_col: abs_end_col,
@@ -113,9 +226,40 @@ impl Tokenizer {
                     }
                 },
             )
+    }
+
+    /// Parse a defined name (e.g., TaxRate)
+    pub fn name<'a>() -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone {
+        text::ascii::ident()
+            .try_map(|s: &str, span| {
+                if Self::is_name(s) {
+                    Ok(Expr::Name {
+                        name: s.to_string(),
+                    })
+                } else {
+                    Err(Rich::custom(span, format!("'{}' is not a valid name", s)))
+                }
+            })
             .padded()
     }
 
+    /// Whether `text` can be a defined name: a letter or underscore followed
+    /// by letters, digits or underscores, that does not read as a cell
+    /// reference (e.g. `Tax1`) or a boolean
+    pub fn is_name(text: &str) -> bool {
+        let mut chars = text.chars();
+        let starts_well = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+        if !starts_well || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return false;
+        }
+        let letters = text.trim_end_matches(|c: char| c.is_ascii_digit());
+        let reads_as_cell =
+            letters.len() < text.len() && letters.chars().all(|c| c.is_ascii_alphabetic());
+        !reads_as_cell && !text.eq_ignore_ascii_case("TRUE") && !text.eq_ignore_ascii_case("FALSE")
+    }
+
     /// Parse a function name (case insensitive)
     pub fn function_name<'a>()
     -> impl Parser<'a, &'a str, String, extra::Err<Rich<'a, char>>> + Clone {
@@ -123,4 +267,70 @@ impl Tokenizer {
             .map(|s: &str| s.to_uppercase())
             .padded()
     }
+
+    /// Split formula text into lexical tokens, reading numbers with the
+    /// given decimal separator
+    pub fn lex(input: &str, decimal_separator: char) -> Vec<LexToken<'_>> {
+        Self::lexer(decimal_separator)
+            .parse(input)
+            .into_output()
+            .unwrap_or_default()
+    }
+
+    fn lexer<'a>(
+        decimal_separator: char,
+    ) -> impl Parser<'a, &'a str, Vec<LexToken<'a>>, extra::Err<Rich<'a, char>>> {
+        let quoted = |quote: char| {
+            just(quote)
+                .then(none_of(quote).repeated())
+                .then(just(quote).or_not())
+                .to_slice()
+        };
+
+        // Quotes inside a sheet name are doubled, as in 'Q1 ''Draft'''
+        let quoted_name = just('\'')
+            .then(just("''").ignored().or(none_of('\'').ignored()).repeated())
+            .then(just('\'').or_not())
+            .to_slice()
+            .map(LexToken::QuotedName);
+
+        let number = text::digits(10)
+            .then(just(decimal_separator).then(text::digits(10)).or_not())
+            .to_slice()
+            .map(LexToken::Number);
+
+        // Dots are allowed after the first character for names like STDEV.S
+        let ident = any()
+            .filter(|c: &char| c.is_alphabetic() || *c == '_')
+            .then(
+                any()
+                    .filter(|c: &char| c.is_alphanumeric() || *c == '_' || *c == '.')
+                    .repeated(),
+            )
+            .to_slice()
+            .map(LexToken::Ident);
+
+        let whitespace = any()
+            .filter(|c: &char| c.is_whitespace())
+            .repeated()
+            .at_least(1)
+            .to_slice()
+            .map(LexToken::Whitespace);
+
+        choice((
+            quoted('"').map(LexToken::String),
+            quoted_name,
+            number,
+            ident,
+            whitespace,
+            any().to_slice().map(LexToken::Punct),
+        ))
+        .repeated()
+        .collect()
+    }
+}
+
+/// The lower and the higher of two coordinates, each with its `$`
+fn ordered(a: (u32, bool), b: (u32, bool)) -> ((u32, bool), (u32, bool)) {
+    if a.0 <= b.0 { (a, b) } else { (b, a) }
 }
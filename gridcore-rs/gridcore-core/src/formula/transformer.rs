@@ -1,6 +1,7 @@
-use crate::SpreadsheetError;
 use crate::formula::ast::{CellRange, Expr};
-use crate::types::{CellAddress, CellValue, ErrorType};
+use crate::references::StructuralOperation;
+use crate::references::style::{ReferenceStyle, format_cell};
+use crate::types::CellAddress;
 
 /// Transformer for adjusting formulas during structural operations
 #[derive(Debug, Clone)]
@@ -12,66 +13,56 @@ impl FormulaTransformer {
         FormulaTransformer
     }
 
+    /// Adjust formula references for a structural operation, the way
+    /// [`ReferenceAdjuster`](crate::references::ReferenceAdjuster) rewrites
+    /// formula text: ranges grow and shrink, and references to deleted or
+    /// overwritten cells become `#REF!`
+    pub fn adjust(&self, ast: Expr, operation: &StructuralOperation) -> Expr {
+        self.transform_expr(ast, &|range: &CellRange| operation.shift_reference(range))
+    }
+
     /// Adjust formula references when a row is inserted
     pub fn adjust_for_row_insert(&self, ast: Expr, inserted_row: u32) -> Expr {
-        self.transform_expr(ast, |addr, abs_col, abs_row| {
-            let mut new_addr = *addr;
-            // Always adjust for absolute references, adjust for relative if beyond insertion point
-            if addr.row >= inserted_row {
-                new_addr.row += 1;
-            }
-            Ok((new_addr, abs_col, abs_row))
-        })
+        self.adjust(
+            ast,
+            &StructuralOperation::InsertRows {
+                before_row: inserted_row,
+                count: 1,
+            },
+        )
     }
 
     /// Adjust formula references when a row is deleted
     pub fn adjust_for_row_delete(&self, ast: Expr, deleted_row: u32) -> Expr {
-        self.transform_expr(ast, |addr, abs_col, abs_row| {
-            let mut new_addr = *addr;
-
-            // If the reference is to the deleted row, return #REF! error
-            if addr.row == deleted_row {
-                return Err(SpreadsheetError::RefError);
-            }
-
-            // Adjust references beyond the deleted row
-            if addr.row > deleted_row {
-                new_addr.row -= 1;
-            }
-
-            Ok((new_addr, abs_col, abs_row))
-        })
+        self.adjust(
+            ast,
+            &StructuralOperation::DeleteRows {
+                start_row: deleted_row,
+                count: 1,
+            },
+        )
     }
 
     /// Adjust formula references when a column is inserted
     pub fn adjust_for_column_insert(&self, ast: Expr, inserted_col: u32) -> Expr {
-        self.transform_expr(ast, |addr, abs_col, abs_row| {
-            let mut new_addr = *addr;
-            // Always adjust for absolute references, adjust for relative if beyond insertion point
-            if addr.col >= inserted_col {
-                new_addr.col += 1;
-            }
-            Ok((new_addr, abs_col, abs_row))
-        })
+        self.adjust(
+            ast,
+            &StructuralOperation::InsertColumns {
+                before_col: inserted_col,
+                count: 1,
+            },
+        )
     }
 
     /// Adjust formula references when a column is deleted
     pub fn adjust_for_column_delete(&self, ast: Expr, deleted_col: u32) -> Expr {
-        self.transform_expr(ast, |addr, abs_col, abs_row| {
-            let mut new_addr = *addr;
-
-            // If the reference is to the deleted column, return #REF! error
-            if addr.col == deleted_col {
-                return Err(SpreadsheetError::RefError);
-            }
-
-            // Adjust references beyond the deleted column
-            if addr.col > deleted_col {
-                new_addr.col -= 1;
-            }
-
-            Ok((new_addr, abs_col, abs_row))
-        })
+        self.adjust(
+            ast,
+            &StructuralOperation::DeleteColumns {
+                start_col: deleted_col,
+                count: 1,
+            },
+        )
     }
 
     /// Adjust formula references when a range of cells is moved
@@ -82,52 +73,42 @@ impl FormulaTransformer {
         from_end: &CellAddress,
         to_start: &CellAddress,
     ) -> Expr {
-        let row_delta = to_start.row as i32 - from_start.row as i32;
-        let col_delta = to_start.col as i32 - from_start.col as i32;
-
-        self.transform_expr(ast, |addr, abs_col, abs_row| {
-            let mut new_addr = *addr;
-
-            // Check if the address is within the moved range
-            if addr.col >= from_start.col
-                && addr.col <= from_end.col
-                && addr.row >= from_start.row
-                && addr.row <= from_end.row
-            {
-                // Move the reference with the range
-                new_addr.col = ((addr.col as i32) + col_delta) as u32;
-                new_addr.row = ((addr.row as i32) + row_delta) as u32;
-            }
-
-            Ok((new_addr, abs_col, abs_row))
-        })
+        self.adjust(
+            ast,
+            &StructuralOperation::MoveRange {
+                from: CellRange::new(*from_start, *from_end),
+                to: *to_start,
+            },
+        )
     }
 
-    /// Transform an expression by applying a transformation function to all cell references
+    /// Transform an expression by applying `transform` to the cells every
+    /// reference covers, a single cell being a range of one. References
+    /// it returns `None` for become `#REF!`.
     #[allow(clippy::only_used_in_recursion)]
-    fn transform_expr<F>(&self, expr: Expr, transform: F) -> Expr
+    fn transform_expr<F>(&self, expr: Expr, transform: &F) -> Expr
     where
-        F: Fn(&CellAddress, bool, bool) -> Result<(CellAddress, bool, bool), SpreadsheetError>
-            + Clone,
+        F: Fn(&CellRange) -> Option<CellRange>,
     {
         match expr {
             Expr::Literal { value } => Expr::Literal { value },
 
+            Expr::Name { name } => Expr::Name { name },
+
+            // The cells of another sheet do not move with this one's
+            Expr::SheetReference { sheet, reference } => Expr::SheetReference { sheet, reference },
+
             Expr::Reference {
                 address,
                 absolute_col,
                 absolute_row,
-            } => match transform(&address, absolute_col, absolute_row) {
-                Ok((new_addr, new_abs_col, new_abs_row)) => Expr::Reference {
-                    address: new_addr,
-                    absolute_col: new_abs_col,
-                    absolute_row: new_abs_row,
-                },
-                Err(_) => Expr::Literal {
-                    value: CellValue::from_error(ErrorType::InvalidRef {
-                        reference: "deleted".to_string(),
-                    }),
+            } => match transform(&CellRange::new(address, address)) {
+                Some(range) => Expr::Reference {
+                    address: range.start,
+                    absolute_col,
+                    absolute_row,
                 },
+                None => Expr::deleted_reference(),
             },
 
             Expr::Range {
@@ -136,33 +117,21 @@ impl FormulaTransformer {
                 absolute_start_row,
                 absolute_end_col,
                 absolute_end_row,
-            } => {
-                let start_result = transform(&range.start, absolute_start_col, absolute_start_row);
-                let end_result = transform(&range.end, absolute_end_col, absolute_end_row);
-
-                match (start_result, end_result) {
-                    (
-                        Ok((new_start, start_abs_col, start_abs_row)),
-                        Ok((new_end, end_abs_col, end_abs_row)),
-                    ) => Expr::Range {
-                        range: CellRange::new(new_start, new_end),
-                        absolute_start_col: start_abs_col,
-                        absolute_start_row: start_abs_row,
-                        absolute_end_col: end_abs_col,
-                        absolute_end_row: end_abs_row,
-                    },
-                    _ => Expr::Literal {
-                        value: CellValue::from_error(ErrorType::InvalidRef {
-                            reference: "deleted".to_string(),
-                        }),
-                    },
-                }
-            }
+            } => match transform(&range) {
+                Some(range) => Expr::Range {
+                    range,
+                    absolute_start_col,
+                    absolute_start_row,
+                    absolute_end_col,
+                    absolute_end_row,
+                },
+                None => Expr::deleted_reference(),
+            },
 
             Expr::FunctionCall { name, args } => {
                 let new_args = args
                     .into_iter()
-                    .map(|arg| self.transform_expr(arg, transform.clone()))
+                    .map(|arg| self.transform_expr(arg, transform))
                     .collect();
                 Expr::FunctionCall {
                     name,
@@ -177,18 +146,110 @@ impl FormulaTransformer {
 
             Expr::BinaryOp { op, left, right } => Expr::BinaryOp {
                 op,
-                left: Box::new(self.transform_expr(*left, transform.clone())),
+                left: Box::new(self.transform_expr(*left, transform)),
+                right: Box::new(self.transform_expr(*right, transform)),
+            },
+
+            Expr::Union { areas } => Expr::Union {
+                areas: areas
+                    .into_iter()
+                    .map(|area| self.transform_expr(area, transform))
+                    .collect(),
+            },
+
+            Expr::Intersection { left, right } => Expr::Intersection {
+                left: Box::new(self.transform_expr(*left, transform)),
                 right: Box::new(self.transform_expr(*right, transform)),
             },
         }
     }
 
+    /// The formula of the cell `origin` written with its references in
+    /// `style`, without the leading `=`. Only display changes; formulas
+    /// are stored in A1 notation either way.
+    pub fn display(&self, ast: &Expr, style: ReferenceStyle, origin: &CellAddress) -> String {
+        match style {
+            ReferenceStyle::A1 => ast.to_string(),
+            ReferenceStyle::R1C1 => self.written_references(ast.clone(), origin).to_string(),
+        }
+    }
+
+    /// Replace every reference with its R1C1 text, which a name displays
+    /// as is
+    fn written_references(&self, expr: Expr, origin: &CellAddress) -> Expr {
+        let write = |address: &CellAddress, absolute_col, absolute_row| {
+            format_cell(
+                address,
+                absolute_col,
+                absolute_row,
+                ReferenceStyle::R1C1,
+                origin,
+            )
+        };
+        match expr {
+            Expr::Reference {
+                address,
+                absolute_col,
+                absolute_row,
+            } => Expr::Name {
+                name: write(&address, absolute_col, absolute_row),
+            },
+            Expr::Range {
+                range,
+                absolute_start_col,
+                absolute_start_row,
+                absolute_end_col,
+                absolute_end_row,
+            } => Expr::Name {
+                name: format!(
+                    "{}:{}",
+                    write(&range.start, absolute_start_col, absolute_start_row),
+                    write(&range.end, absolute_end_col, absolute_end_row)
+                ),
+            },
+            Expr::SheetReference { sheet, reference } => Expr::SheetReference {
+                sheet,
+                reference: Box::new(self.written_references(*reference, origin)),
+            },
+            Expr::FunctionCall { name, args } => Expr::FunctionCall {
+                name,
+                args: args
+                    .into_iter()
+                    .map(|arg| self.written_references(arg, origin))
+                    .collect(),
+            },
+            Expr::UnaryOp { op, expr } => Expr::UnaryOp {
+                op,
+                expr: Box::new(self.written_references(*expr, origin)),
+            },
+            Expr::BinaryOp { op, left, right } => Expr::BinaryOp {
+                op,
+                left: Box::new(self.written_references(*left, origin)),
+                right: Box::new(self.written_references(*right, origin)),
+            },
+            Expr::Union { areas } => Expr::Union {
+                areas: areas
+                    .into_iter()
+                    .map(|area| self.written_references(area, origin))
+                    .collect(),
+            },
+            Expr::Intersection { left, right } => Expr::Intersection {
+                left: Box::new(self.written_references(*left, origin)),
+                right: Box::new(self.written_references(*right, origin)),
+            },
+            Expr::Literal { .. } | Expr::Name { .. } => expr,
+        }
+    }
+
     /// Shift all references in a formula by the given row and column deltas
     pub fn shift_references(&self, ast: Expr, row_delta: i32, col_delta: i32) -> Expr {
-        self.transform_expr(ast, |addr, abs_col, abs_row| {
-            let new_col = (addr.col as i32 + col_delta).max(0) as u32;
-            let new_row = (addr.row as i32 + row_delta).max(0) as u32;
-            Ok((CellAddress::new(new_col, new_row), abs_col, abs_row))
+        let shift = |address: &CellAddress| {
+            let new_col = (address.col as i32 + col_delta).max(0) as u32;
+            let new_row = (address.row as i32 + row_delta).max(0) as u32;
+            CellAddress::new(new_col, new_row)
+        };
+        self.transform_expr(ast, &|range: &CellRange| {
+            Some(CellRange::new(shift(&range.start), shift(&range.end)))
         })
     }
 }
@@ -203,6 +264,7 @@ impl Default for FormulaTransformer {
 mod tests {
     use super::*;
     use crate::formula::parser::FormulaParser;
+    use crate::types::{CellValue, ErrorType};
 
     fn parse_formula(formula: &str) -> Expr {
         FormulaParser::parse(formula).expect("Failed to parse formula")
@@ -352,4 +414,42 @@ mod tests {
             _ => panic!("Expected BinaryOp"),
         }
     }
+
+    #[test]
+    fn test_partly_deleted_ranges_shrink() {
+        let transformer = FormulaTransformer::new();
+        for (formula, deleted_row, expected) in [
+            ("SUM(A2:A5)", 4, "SUM(A2:A4)"),
+            ("SUM(A2:A5)", 1, "SUM(A2:A4)"),
+            ("SUM(A2:A2)", 1, "SUM(#REF!)"),
+            ("SUM($A$2:A5)+A5", 4, "SUM($A$2:A4)+#REF!"),
+        ] {
+            let adjusted = transformer.adjust_for_row_delete(parse_formula(formula), deleted_row);
+            assert_eq!(adjusted.to_string(), expected, "{formula}");
+        }
+
+        // Only ranges wholly inside a moved block go with it
+        let adjusted = transformer.adjust_for_range_move(
+            parse_formula("SUM(A1:A2)+SUM(A1:A3)"),
+            &CellAddress::new(0, 0),
+            &CellAddress::new(0, 1),
+            &CellAddress::new(2, 0),
+        );
+        assert_eq!(adjusted.to_string(), "SUM(C1:C2)+SUM(A1:A3)");
+    }
+
+    #[test]
+    fn test_display_in_r1c1() {
+        let transformer = FormulaTransformer::new();
+        let origin = CellAddress::new(2, 4); // C5
+        let ast = parse_formula("SUM(A1:C4)*$B$2+'My Data'!C$5-D6*-$A7");
+        assert_eq!(
+            transformer.display(&ast, ReferenceStyle::R1C1, &origin),
+            "SUM(R[-4]C[-2]:R[-1]C)*R2C2+'My Data'!R5C-R[1]C[1]*-R[2]C1"
+        );
+        assert_eq!(
+            transformer.display(&ast, ReferenceStyle::A1, &origin),
+            ast.to_string()
+        );
+    }
 }
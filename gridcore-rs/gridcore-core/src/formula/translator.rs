@@ -0,0 +1,337 @@
+use crate::formula::tokenizer::{LexToken, Tokenizer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Punctuation used when a formula is shown to and typed by the user.
+///
+/// Formulas are always stored in the [`FormulaConvention::Comma`] form; other
+/// conventions only exist at the edit boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FormulaConvention {
+    /// `=SUM(1.5, 2)` and `{1, 2; 3, 4}`, the canonical storage form
+    #[default]
+    Comma,
+    /// `=SUM(1,5; 2)` and `{1\ 2; 3\ 4}`, for locales with a decimal comma
+    Semicolon,
+}
+
+impl FormulaConvention {
+    pub fn argument_separator(self) -> &'static str {
+        match self {
+            FormulaConvention::Comma => ",",
+            FormulaConvention::Semicolon => ";",
+        }
+    }
+
+    pub fn decimal_separator(self) -> char {
+        match self {
+            FormulaConvention::Comma => '.',
+            FormulaConvention::Semicolon => ',',
+        }
+    }
+
+    /// Separates values within a row of an array literal
+    pub fn array_column_separator(self) -> &'static str {
+        match self {
+            FormulaConvention::Comma => ",",
+            FormulaConvention::Semicolon => "\\",
+        }
+    }
+
+    /// Separates rows of an array literal
+    pub fn array_row_separator(self) -> &'static str {
+        ";"
+    }
+}
+
+/// Translates formula text between the canonical storage form and the
+/// display convention chosen in the settings.
+///
+/// Translation works on the lexical token stream, so separators inside
+/// string literals and quoted sheet names are left alone. Text that does not
+/// start with `=` is not a formula and passes through unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct FormulaTranslator {
+    convention: FormulaConvention,
+    /// Canonical (uppercase) function name to display name
+    display_names: HashMap<String, String>,
+    /// Display name (uppercase) to canonical function name
+    canonical_names: HashMap<String, String>,
+}
+
+impl FormulaTranslator {
+    pub fn new(convention: FormulaConvention) -> Self {
+        Self {
+            convention,
+            ..Default::default()
+        }
+    }
+
+    /// Show the function `canonical` as `display`, e.g. `SUM` as `SUMME`
+    pub fn with_function_name(mut self, canonical: &str, display: &str) -> Self {
+        let canonical = canonical.to_uppercase();
+        self.canonical_names
+            .insert(display.to_uppercase(), canonical.clone());
+        self.display_names.insert(canonical, display.to_string());
+        self
+    }
+
+    pub fn convention(&self) -> FormulaConvention {
+        self.convention
+    }
+
+    /// Whether display and canonical text are always the same
+    pub fn is_identity(&self) -> bool {
+        self.convention == FormulaConvention::Comma && self.display_names.is_empty()
+    }
+
+    /// Canonical formula text as it should appear in the editor
+    pub fn to_display(&self, formula: &str) -> String {
+        self.translate(
+            formula,
+            FormulaConvention::Comma,
+            self.convention,
+            &self.display_names,
+        )
+    }
+
+    /// Text committed from the editor in the canonical storage form
+    pub fn to_canonical(&self, formula: &str) -> String {
+        self.translate(
+            formula,
+            self.convention,
+            FormulaConvention::Comma,
+            &self.canonical_names,
+        )
+    }
+
+    /// Byte range of the canonical form of `formula` that corresponds to
+    /// `span` of its display form, e.g. a selection made in the editor
+    pub fn canonical_span(&self, formula: &str, span: Range<usize>) -> Range<usize> {
+        let canonical = self.to_canonical(formula);
+        map_span(
+            (formula, self.convention),
+            (&canonical, FormulaConvention::Comma),
+            span,
+        )
+    }
+
+    /// Byte range of the display form of canonical `formula` that
+    /// corresponds to `span`, the reverse of [`Self::canonical_span`]
+    pub fn display_span(&self, formula: &str, span: Range<usize>) -> Range<usize> {
+        let display = self.to_display(formula);
+        map_span(
+            (formula, FormulaConvention::Comma),
+            (&display, self.convention),
+            span,
+        )
+    }
+
+    fn translate(
+        &self,
+        formula: &str,
+        from: FormulaConvention,
+        to: FormulaConvention,
+        names: &HashMap<String, String>,
+    ) -> String {
+        if !formula.starts_with('=') || self.is_identity() {
+            return formula.to_string();
+        }
+
+        let tokens = Tokenizer::lex(formula, from.decimal_separator());
+        let mut output = String::with_capacity(formula.len());
+        let mut array_depth = 0usize;
+
+        for (i, token) in tokens.iter().enumerate() {
+            match *token {
+                LexToken::Number(text) => {
+                    output.extend(text.chars().map(|c| {
+                        if c == from.decimal_separator() {
+                            to.decimal_separator()
+                        } else {
+                            c
+                        }
+                    }));
+                }
+                LexToken::Ident(text) if is_function_call(&tokens[i + 1..]) => {
+                    match names.get(&text.to_uppercase()) {
+                        Some(name) => output.push_str(name),
+                        None => output.push_str(text),
+                    }
+                }
+                LexToken::Punct(text) => {
+                    match text {
+                        "{" => array_depth += 1,
+                        "}" => array_depth = array_depth.saturating_sub(1),
+                        _ => {}
+                    }
+                    let translated = if array_depth > 0 {
+                        if text == from.array_column_separator() {
+                            to.array_column_separator()
+                        } else if text == from.array_row_separator() {
+                            to.array_row_separator()
+                        } else {
+                            text
+                        }
+                    } else if text == from.argument_separator() {
+                        to.argument_separator()
+                    } else {
+                        text
+                    };
+                    output.push_str(translated);
+                }
+                _ => output.push_str(token.text()),
+            }
+        }
+
+        output
+    }
+}
+
+/// Move `span` from one form of a formula to the other. Translation keeps
+/// the tokens in step, so an offset keeps its distance from the start of
+/// its token, clamped to the token's new length.
+fn map_span(
+    (from, from_convention): (&str, FormulaConvention),
+    (to, to_convention): (&str, FormulaConvention),
+    span: Range<usize>,
+) -> Range<usize> {
+    let token_starts = |text: &str, convention: FormulaConvention| {
+        let mut offset = 0;
+        Tokenizer::lex(text, convention.decimal_separator())
+            .iter()
+            .map(|token| {
+                let start = offset;
+                offset += token.text().len();
+                start
+            })
+            .collect::<Vec<usize>>()
+    };
+    let from_starts = token_starts(from, from_convention);
+    let to_starts = token_starts(to, to_convention);
+    if from == to || from_starts.is_empty() || from_starts.len() != to_starts.len() {
+        return span.start.min(to.len())..span.end.min(to.len());
+    }
+
+    let map = |offset: usize| {
+        let i = from_starts
+            .partition_point(|&start| start <= offset)
+            .saturating_sub(1);
+        let end = to_starts.get(i + 1).copied().unwrap_or(to.len());
+        (to_starts[i] + offset - from_starts[i]).min(end)
+    };
+    map(span.start)..map(span.end)
+}
+
+/// An identifier names a function when the next non-blank token is `(`
+fn is_function_call(rest: &[LexToken<'_>]) -> bool {
+    rest.iter()
+        .find(|token| !matches!(token, LexToken::Whitespace(_)))
+        .is_some_and(|token| *token == LexToken::Punct("("))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::FormulaParser;
+
+    /// Canonical formulas paired with their semicolon-convention display form
+    const CORPUS: &[(&str, &str)] = &[
+        ("=1.5+2", "=1,5+2"),
+        ("=SUM(A1:A10)", "=SUM(A1:A10)"),
+        ("=SUM(1.5, 2, A1)", "=SUM(1,5; 2; A1)"),
+        (
+            "=IF(A1>0.5,ROUND(B2*1.25,2),MAX(C1:C3,0))",
+            "=IF(A1>0,5;ROUND(B2*1,25;2);MAX(C1:C3;0))",
+        ),
+        ("=CONCAT(\"a,b;c\", \"1.5\")", "=CONCAT(\"a,b;c\"; \"1.5\")"),
+        ("=\"x;y\"&\"\"\"q,\"\"\"", "=\"x;y\"&\"\"\"q,\"\"\""),
+        ("=SUM({1,2;3.5,4})", "=SUM({1\\2;3,5\\4})"),
+        (
+            "=INDEX({\"a,b\",\"c\";1,2}, 2, 1)",
+            "=INDEX({\"a,b\"\\\"c\";1\\2}; 2; 1)",
+        ),
+        ("=SUM('Q1, Q2'!A1, $B$2)", "=SUM('Q1, Q2'!A1; $B$2)"),
+        ("=STDEV.S(A1:A5)", "=STDEV.S(A1:A5)"),
+        ("=\"unterminated, text", "=\"unterminated, text"),
+    ];
+
+    #[test]
+    fn test_semicolon_round_trip() {
+        let translator = FormulaTranslator::new(FormulaConvention::Semicolon);
+        for (canonical, display) in CORPUS {
+            assert_eq!(translator.to_display(canonical), *display, "{}", canonical);
+            assert_eq!(translator.to_canonical(display), *canonical, "{}", display);
+        }
+    }
+
+    #[test]
+    fn test_comma_convention_is_identity() {
+        let translator = FormulaTranslator::default();
+        assert!(translator.is_identity());
+        for (canonical, _) in CORPUS {
+            assert_eq!(translator.to_display(canonical), *canonical);
+            assert_eq!(translator.to_canonical(canonical), *canonical);
+        }
+    }
+
+    #[test]
+    fn test_translated_function_names_round_trip() {
+        let names = [("SUM", "SUMME"), ("IF", "WENN"), ("COUNTIF", "ZÄHLENWENN")];
+        for convention in [FormulaConvention::Comma, FormulaConvention::Semicolon] {
+            let translator = names.iter().fold(
+                FormulaTranslator::new(convention),
+                |t, (canonical, display)| t.with_function_name(canonical, display),
+            );
+
+            let canonical = "=IF(SUM(A1:A3)>1.5, COUNTIF(B1:B9, \"SUM(\"), 0)";
+            let display = translator.to_display(canonical);
+            assert!(display.starts_with("=WENN(SUMME(A1:A3)>1"));
+            assert!(display.contains("ZÄHLENWENN(B1:B9"));
+            // Text inside strings is never renamed
+            assert!(display.contains("\"SUM(\""));
+            assert_eq!(translator.to_canonical(&display), canonical);
+
+            // Typed names are matched case-insensitively; other names are kept
+            assert_eq!(
+                translator.to_canonical("=summe(A1) + AVERAGE(A1)"),
+                "=SUM(A1) + AVERAGE(A1)"
+            );
+        }
+    }
+
+    #[test]
+    fn test_canonical_text_parses() {
+        let translator = FormulaTranslator::new(FormulaConvention::Semicolon);
+        let canonical = translator.to_canonical("=SUM(1,5; 2; A1)*ROUND(B2; 1)");
+        assert_eq!(canonical, "=SUM(1.5, 2, A1)*ROUND(B2, 1)");
+        assert!(FormulaParser::parse(&canonical).is_ok());
+    }
+
+    #[test]
+    fn test_spans_follow_translation() {
+        let translator =
+            FormulaTranslator::new(FormulaConvention::Semicolon).with_function_name("SUM", "SUMME");
+        let display = "=SUMME(1,5; A1)*2";
+        let canonical = translator.to_canonical(display);
+        assert_eq!(canonical, "=SUM(1.5, A1)*2");
+
+        let span = translator.canonical_span(display, 1..15);
+        assert_eq!(&canonical[span.clone()], "SUM(1.5, A1)");
+        assert_eq!(
+            &display[translator.display_span(&canonical, span)],
+            "SUMME(1,5; A1)"
+        );
+
+        let span = translator.canonical_span(display, 12..14);
+        assert_eq!(&canonical[span], "A1");
+    }
+
+    #[test]
+    fn test_values_are_not_translated() {
+        let translator = FormulaTranslator::new(FormulaConvention::Semicolon);
+        assert_eq!(translator.to_canonical("1,5; 2"), "1,5; 2");
+        assert_eq!(translator.to_display("a, b"), "a, b");
+    }
+}
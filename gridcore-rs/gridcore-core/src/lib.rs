@@ -1,17 +1,27 @@
 pub mod adapters;
+pub mod chart;
 pub mod command;
+pub mod conditional;
 pub mod constants;
+pub mod csv;
 pub mod dependency;
 pub mod domain;
 pub mod error;
 pub mod evaluator;
+pub mod external;
 pub mod facade;
 pub mod fill;
 pub mod formula;
+pub mod lint;
+pub mod memory;
+pub mod pivot;
 pub mod ports;
 pub mod references;
 pub mod repository;
+pub mod script;
 pub mod services;
+pub mod sparkline;
+pub mod template;
 pub mod traits;
 pub mod types;
 pub mod utils;
@@ -24,7 +34,7 @@ pub mod perf;
 pub mod test_utils;
 
 // Re-export commonly used types
-pub use dependency::{DependencyAnalyzer, DependencyGraph};
+pub use dependency::{DependencyAnalyzer, DependencyGraph, DependencyReport};
 pub use domain::Cell;
 pub use error::{Result, SpreadsheetError};
 pub use evaluator::{EvaluationContext, Evaluator};
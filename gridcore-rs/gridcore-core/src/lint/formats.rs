@@ -0,0 +1,236 @@
+//! [`LintRule::MixedFormats`](super::LintRule::MixedFormats): an aggregate
+//! over numbers shown in different units, such as a percent among
+//! currency amounts. `5%` is stored as 0.05, so summing it with `$5`
+//! gives a total that reads right and is wrong.
+//!
+//! The check reads every cell of a range, so its outcome is cached per
+//! formula cell and range until the range's fingerprint changes.
+
+use crate::domain::{CellFormat, NumberFormat};
+use crate::formula::CellRange;
+use crate::memory::{MemoryFootprint, hash_table_bytes};
+use crate::types::CellAddress;
+use rustc_hash::FxHashMap;
+use std::fmt;
+
+/// Offending cells named in a finding
+const SHOWN_CELLS: usize = 3;
+
+/// What unit a number is shown in. Formats within one category only
+/// differ in looks, e.g. decimals or thousands grouping.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FormatCategory {
+    Plain,
+    Percent,
+    Currency(String),
+    Unit(String),
+    Date,
+}
+
+impl FormatCategory {
+    /// Category of a cell's effective format. `None` for text, which
+    /// aggregates skip.
+    pub fn of(format: Option<&CellFormat>) -> Option<Self> {
+        let Some(format) = format else {
+            return Some(FormatCategory::Plain);
+        };
+        Some(match &format.number_format {
+            NumberFormat::General
+            | NumberFormat::Number { .. }
+            | NumberFormat::Scientific { .. } => FormatCategory::Plain,
+            NumberFormat::Percent { .. } => FormatCategory::Percent,
+            NumberFormat::Currency { symbol, .. } | NumberFormat::Accounting { symbol, .. }
+                if !symbol.is_empty() =>
+            {
+                FormatCategory::Currency(symbol.clone())
+            }
+            NumberFormat::Currency { .. } | NumberFormat::Accounting { .. } => {
+                FormatCategory::Plain
+            }
+            NumberFormat::Unit { unit, .. } => FormatCategory::Unit(unit.clone()),
+            NumberFormat::Date { .. } => FormatCategory::Date,
+            NumberFormat::Text => return None,
+        })
+    }
+}
+
+impl fmt::Display for FormatCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatCategory::Plain => f.write_str("plain"),
+            FormatCategory::Percent => f.write_str("percent"),
+            FormatCategory::Currency(symbol) => write!(f, "currency ({})", symbol),
+            FormatCategory::Unit(unit) => write!(f, "unit ({})", unit),
+            FormatCategory::Date => f.write_str("date"),
+        }
+    }
+}
+
+/// Describe the categories of the numbers in `range`, given in row-major
+/// order, when there is more than one: the categories in order of first
+/// appearance, and the first cells of each but the first
+pub(super) fn describe_mix(
+    range: &CellRange,
+    numbers: impl IntoIterator<Item = (CellAddress, FormatCategory)>,
+) -> Option<String> {
+    let mut categories: Vec<(FormatCategory, Vec<CellAddress>)> = Vec::new();
+    for (address, category) in numbers {
+        match categories.iter_mut().find(|(seen, _)| *seen == category) {
+            Some((_, cells)) => cells.push(address),
+            None => categories.push((category, vec![address])),
+        }
+    }
+    if categories.len() < 2 {
+        return None;
+    }
+    let names: Vec<String> = categories
+        .iter()
+        .map(|(category, _)| category.to_string())
+        .collect();
+    let offending: Vec<String> = categories[1..]
+        .iter()
+        .flat_map(|(_, cells)| cells)
+        .take(SHOWN_CELLS)
+        .map(CellAddress::to_string)
+        .collect();
+    Some(format!(
+        "Range {} mixes {} numbers, e.g. at {}",
+        range,
+        names.join(", "),
+        offending.join(", ")
+    ))
+}
+
+/// Outcomes of [`LintRule::MixedFormats`](super::LintRule::MixedFormats)
+/// checks by formula cell and range, each with the fingerprint of the
+/// range it was computed for
+#[derive(Debug, Default)]
+pub struct MixedFormatCache {
+    entries: FxHashMap<(CellAddress, CellRange), (u64, Option<String>)>,
+    hits: u64,
+    misses: u64,
+}
+
+impl MixedFormatCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The finding message for `range` in the formula at `address`, from
+    /// the cache while `fingerprint` matches, otherwise from `check`. A
+    /// `None` fingerprint is never cached.
+    pub fn get_or_check(
+        &mut self,
+        address: CellAddress,
+        range: CellRange,
+        fingerprint: Option<u64>,
+        check: impl FnOnce() -> Option<String>,
+    ) -> Option<String> {
+        let Some(fingerprint) = fingerprint else {
+            self.misses += 1;
+            return check();
+        };
+        if let Some((cached, message)) = self.entries.get(&(address, range))
+            && *cached == fingerprint
+        {
+            self.hits += 1;
+            return message.clone();
+        }
+        self.misses += 1;
+        let message = check();
+        self.entries
+            .insert((address, range), (fingerprint, message.clone()));
+        message
+    }
+
+    /// Checks answered from the cache and checks run, since creation
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Compacting drops every outcome; the next lint checks again
+impl MemoryFootprint for MixedFormatCache {
+    fn footprint_bytes(&self) -> usize {
+        hash_table_bytes::<(CellAddress, CellRange), (u64, Option<String>)>(self.entries.capacity())
+            + self
+                .entries
+                .values()
+                .filter_map(|(_, message)| message.as_ref())
+                .map(String::capacity)
+                .sum::<usize>()
+    }
+
+    fn compact(&mut self) {
+        self.entries = FxHashMap::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(a1: &str) -> CellAddress {
+        CellAddress::from_a1(a1).unwrap()
+    }
+
+    #[test]
+    fn test_categories_ignore_looks() {
+        let currency = |symbol: &str, decimals| CellFormat {
+            number_format: NumberFormat::Currency {
+                symbol: symbol.to_string(),
+                decimals,
+            },
+            ..CellFormat::default()
+        };
+        assert_eq!(FormatCategory::of(None), Some(FormatCategory::Plain));
+        assert_eq!(
+            FormatCategory::of(Some(&CellFormat::thousands(2))),
+            Some(FormatCategory::Plain)
+        );
+        assert_eq!(
+            FormatCategory::of(Some(&currency("$", 0))),
+            FormatCategory::of(Some(&currency("$", 2)))
+        );
+        assert_ne!(
+            FormatCategory::of(Some(&currency("$", 2))),
+            FormatCategory::of(Some(&currency("€", 2)))
+        );
+        let text = CellFormat {
+            number_format: NumberFormat::Text,
+            ..CellFormat::default()
+        };
+        assert_eq!(FormatCategory::of(Some(&text)), None);
+    }
+
+    #[test]
+    fn test_cache_rechecks_when_the_fingerprint_changes() {
+        let mut cache = MixedFormatCache::new();
+        let range = CellRange::from_string("A1:A3").unwrap();
+        let mixed = || Some("mixed".to_string());
+
+        assert_eq!(
+            cache.get_or_check(cell("B1"), range, Some(1), mixed),
+            mixed()
+        );
+        assert_eq!(
+            cache.get_or_check(cell("B1"), range, Some(1), || unreachable!()),
+            mixed()
+        );
+        assert_eq!(
+            cache.get_or_check(cell("B1"), range, Some(2), || None),
+            None
+        );
+        // Another formula over the same range has its own entry
+        assert_eq!(
+            cache.get_or_check(cell("B2"), range, Some(2), mixed),
+            mixed()
+        );
+        assert_eq!(cache.get_or_check(cell("B2"), range, None, || None), None);
+        assert_eq!(cache.stats(), (1, 4));
+    }
+}
@@ -0,0 +1,558 @@
+//! Formula linting: likely mistakes reported as warnings
+//!
+//! Linting runs on demand over a set of formula cells and never blocks
+//! editing or recalculation. Each rule looks at one formula together with
+//! the cells around it:
+//!
+//! - [`LintRule::OmittedCells`]: an aggregate over a single row or column
+//!   stops just short of more numbers, e.g. `=SUM(A1:A9)` with a number in
+//!   A10. Only typed numbers count, so a total row under the data does not
+//!   make the range look short.
+//! - [`LintRule::EmptyReference`]: a single-cell reference to an empty cell
+//! - [`LintRule::HardCodedConstant`]: a number written next to a cell
+//!   reference in arithmetic, e.g. `=A1*1.2`. 0 and 1 are left alone since
+//!   they rarely stand for an assumption.
+//! - [`LintRule::InconsistentFormula`]: a formula differs from the cells
+//!   directly above and below it while those two agree once filled into
+//!   its position. The ends of a run are never flagged, so a total under a
+//!   column of formulas passes.
+//! - [`LintRule::MixedFormats`]: an aggregate over numbers shown in
+//!   different units, see [`formats`]. Off unless turned on.
+
+pub mod formats;
+
+pub use formats::{FormatCategory, MixedFormatCache};
+
+use crate::domain::{Cell, CellFormat};
+use crate::fill::adjuster::DefaultFormulaAdjuster;
+use crate::fill::{FillDirection, FormulaAdjuster};
+use crate::formula::{BinaryOperator, CellRange, Expr, FormulaParser};
+use crate::types::{CellAddress, CellValue};
+use crate::{Result, SpreadsheetError};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Functions whose range arguments are checked by [`LintRule::OmittedCells`]
+const AGGREGATES: [&str; 5] = ["SUM", "AVERAGE", "MIN", "MAX", "COUNT"];
+
+/// Functions whose range arguments are checked by [`LintRule::MixedFormats`];
+/// counting does not care about units
+const UNIT_AGGREGATES: [&str; 4] = ["SUM", "AVERAGE", "MIN", "MAX"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    OmittedCells,
+    EmptyReference,
+    HardCodedConstant,
+    InconsistentFormula,
+    MixedFormats,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 5] = [
+        LintRule::OmittedCells,
+        LintRule::EmptyReference,
+        LintRule::HardCodedConstant,
+        LintRule::InconsistentFormula,
+        LintRule::MixedFormats,
+    ];
+
+    /// Identifier used in settings and the `:lint` command
+    pub fn id(self) -> &'static str {
+        match self {
+            LintRule::OmittedCells => "omitted-cells",
+            LintRule::EmptyReference => "empty-reference",
+            LintRule::HardCodedConstant => "hard-coded-constant",
+            LintRule::InconsistentFormula => "inconsistent-formula",
+            LintRule::MixedFormats => "mixed-formats",
+        }
+    }
+
+    /// Whether the rule runs unless turned off, as opposed to only once
+    /// turned on
+    pub fn on_by_default(self) -> bool {
+        !matches!(self, LintRule::MixedFormats)
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl FromStr for LintRule {
+    type Err = SpreadsheetError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        LintRule::ALL
+            .into_iter()
+            .find(|rule| rule.id().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                let ids: Vec<&str> = LintRule::ALL.iter().map(|rule| rule.id()).collect();
+                SpreadsheetError::InvalidOperation(format!(
+                    "Unknown lint rule '{}', expected one of {}",
+                    s,
+                    ids.join(", ")
+                ))
+            })
+    }
+}
+
+/// Which rules run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintSettings {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    disabled: Vec<LintRule>,
+    /// Rules turned on that are off by default
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    enabled: Vec<LintRule>,
+}
+
+impl LintSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        if rule.on_by_default() {
+            !self.disabled.contains(&rule)
+        } else {
+            self.enabled.contains(&rule)
+        }
+    }
+
+    pub fn set_enabled(&mut self, rule: LintRule, enabled: bool) {
+        self.disabled.retain(|disabled| *disabled != rule);
+        self.enabled.retain(|turned_on| *turned_on != rule);
+        match (rule.on_by_default(), enabled) {
+            (true, false) => self.disabled.push(rule),
+            (false, true) => self.enabled.push(rule),
+            _ => {}
+        }
+    }
+}
+
+/// One warning about one formula
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LintFinding {
+    pub address: CellAddress,
+    pub rule: LintRule,
+    pub message: String,
+    /// What the user could change, e.g. the range or formula to use instead
+    pub suggestion: Option<String>,
+}
+
+/// What linting reads from the sheet
+pub trait LintSource {
+    fn cell(&self, address: &CellAddress) -> Option<Cell>;
+
+    /// Format that applies to a cell, for [`LintRule::MixedFormats`]
+    fn format(&self, _address: &CellAddress) -> Option<CellFormat> {
+        None
+    }
+
+    /// A value that changes whenever the formats or cells of `range` may
+    /// have, for caching [`LintRule::MixedFormats`] checks. `None` checks
+    /// the range every time.
+    fn fingerprint(&self, _range: &CellRange) -> Option<u64> {
+        None
+    }
+
+    /// The cells of `range` holding something, in row-major order
+    fn cells_in(&self, range: &CellRange) -> Vec<(CellAddress, Cell)> {
+        range
+            .cells()
+            .filter_map(|address| Some((address, self.cell(&address)?)))
+            .collect()
+    }
+}
+
+impl<F> LintSource for F
+where
+    F: Fn(&CellAddress) -> Option<Cell>,
+{
+    fn cell(&self, address: &CellAddress) -> Option<Cell> {
+        self(address)
+    }
+}
+
+/// Lint the formulas among `cells`, reading cells through `read`. Findings
+/// come in the order of `cells`, and by rule within one cell.
+pub fn lint_cells<I, F>(cells: I, settings: &LintSettings, read: F) -> Vec<LintFinding>
+where
+    I: IntoIterator<Item = CellAddress>,
+    F: Fn(&CellAddress) -> Option<Cell>,
+{
+    lint_source(cells, settings, &read, &mut MixedFormatCache::new())
+}
+
+/// [`lint_cells`] reading cells and formats from `source`, with mixed
+/// format checks answered from `cache` where the ranges did not change
+pub fn lint_source<I, S>(
+    cells: I,
+    settings: &LintSettings,
+    source: &S,
+    cache: &mut MixedFormatCache,
+) -> Vec<LintFinding>
+where
+    I: IntoIterator<Item = CellAddress>,
+    S: LintSource,
+{
+    let mut findings = Vec::new();
+    for address in cells {
+        let Some(expr) = source.cell(&address).and_then(|cell| parse_formula(&cell)) else {
+            continue;
+        };
+        let mut lint = Lint {
+            address,
+            source,
+            findings: &mut findings,
+        };
+        for rule in LintRule::ALL {
+            if !settings.is_enabled(rule) {
+                continue;
+            }
+            match rule {
+                LintRule::OmittedCells => lint.omitted_cells(&expr),
+                LintRule::EmptyReference => lint.empty_references(&expr),
+                LintRule::HardCodedConstant => lint.hard_coded_constants(&expr),
+                LintRule::InconsistentFormula => lint.inconsistent_formula(&expr),
+                LintRule::MixedFormats => lint.mixed_formats(&expr, cache),
+            }
+        }
+    }
+    findings
+}
+
+fn parse_formula(cell: &Cell) -> Option<Expr> {
+    FormulaParser::parse(cell.formula_text.as_deref()?).ok()
+}
+
+/// The formula being linted
+struct Lint<'a, S> {
+    address: CellAddress,
+    source: &'a S,
+    findings: &'a mut Vec<LintFinding>,
+}
+
+impl<S> Lint<'_, S>
+where
+    S: LintSource,
+{
+    fn report(&mut self, rule: LintRule, message: String, suggestion: Option<String>) {
+        self.findings.push(LintFinding {
+            address: self.address,
+            rule,
+            message,
+            suggestion,
+        });
+    }
+
+    /// A number typed into the cell, as opposed to one computed by a formula
+    fn is_typed_number(&self, address: &CellAddress) -> bool {
+        *address != self.address
+            && self
+                .source
+                .cell(address)
+                .is_some_and(|cell| !cell.has_formula() && cell.get_computed_value().is_number())
+    }
+
+    fn omitted_cells(&mut self, expr: &Expr) {
+        match expr {
+            Expr::FunctionCall { name, args } => {
+                let aggregate = AGGREGATES.iter().any(|f| f.eq_ignore_ascii_case(name));
+                for arg in args {
+                    match arg {
+                        Expr::Range { range, .. } if aggregate => self.check_range_ends(range),
+                        _ => self.omitted_cells(arg),
+                    }
+                }
+            }
+            Expr::UnaryOp { expr, .. } => self.omitted_cells(expr),
+            Expr::BinaryOp { left, right, .. } => {
+                self.omitted_cells(left);
+                self.omitted_cells(right);
+            }
+            Expr::Literal { .. }
+            | Expr::Reference { .. }
+            | Expr::Range { .. }
+            | Expr::SheetReference { .. }
+            | Expr::Union { .. }
+            | Expr::Intersection { .. }
+            | Expr::Name { .. } => {}
+        }
+    }
+
+    /// Grow a one-row or one-column range over the typed numbers touching
+    /// either end and report it if it grew
+    fn check_range_ends(&mut self, range: &CellRange) {
+        let (start, end) = (range.start, range.end);
+        let step: fn(CellAddress, bool) -> Option<CellAddress> = if start.col == end.col {
+            |address: CellAddress, forward: bool| {
+                let row = if forward {
+                    address.row.checked_add(1)?
+                } else {
+                    address.row.checked_sub(1)?
+                };
+                Some(CellAddress::new(address.col, row))
+            }
+        } else if start.row == end.row {
+            |address: CellAddress, forward: bool| {
+                let col = if forward {
+                    address.col.checked_add(1)?
+                } else {
+                    address.col.checked_sub(1)?
+                };
+                Some(CellAddress::new(col, address.row))
+            }
+        } else {
+            return;
+        };
+
+        let grow = |mut edge: CellAddress, forward: bool| {
+            while let Some(next) = step(edge, forward).filter(|next| self.is_typed_number(next)) {
+                edge = next;
+            }
+            edge
+        };
+        let (new_start, new_end) = (grow(start, false), grow(end, true));
+        if (new_start, new_end) == (start, end) {
+            return;
+        }
+
+        let mut omitted = Vec::new();
+        if new_start != start {
+            omitted.push(span(new_start, step(start, false).unwrap_or(start)));
+        }
+        if new_end != end {
+            omitted.push(span(step(end, true).unwrap_or(end), new_end));
+        }
+        self.report(
+            LintRule::OmittedCells,
+            format!(
+                "Range {} leaves out adjacent numbers in {}",
+                range,
+                omitted.join(" and ")
+            ),
+            Some(format!("Use {}", CellRange::new(new_start, new_end))),
+        );
+    }
+
+    fn empty_references(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Reference { address, .. } => {
+                let empty = self.source.cell(address).is_none_or(|cell| {
+                    !cell.has_formula() && cell.get_computed_value() == CellValue::Empty
+                });
+                if empty {
+                    self.report(
+                        LintRule::EmptyReference,
+                        format!("Refers to empty cell {}", address),
+                        None,
+                    );
+                }
+            }
+            Expr::FunctionCall { args, .. } => {
+                for arg in args {
+                    self.empty_references(arg);
+                }
+            }
+            Expr::UnaryOp { expr, .. } => self.empty_references(expr),
+            Expr::BinaryOp { left, right, .. } => {
+                self.empty_references(left);
+                self.empty_references(right);
+            }
+            // Areas read like ranges, where empty cells are expected
+            Expr::Literal { .. }
+            | Expr::Range { .. }
+            | Expr::SheetReference { .. }
+            | Expr::Union { .. }
+            | Expr::Intersection { .. }
+            | Expr::Name { .. } => {}
+        }
+    }
+
+    fn hard_coded_constants(&mut self, expr: &Expr) {
+        match expr {
+            Expr::BinaryOp { op, left, right } => {
+                if is_arithmetic(*op) {
+                    for (constant, other) in [(left, right), (right, left)] {
+                        if let Some((n, address)) = constant_beside_reference(constant, other) {
+                            self.report(
+                                LintRule::HardCodedConstant,
+                                format!("{} is hard-coded next to {}", n, address),
+                                Some(format!(
+                                    "Put {} in a cell or define it with :let NAME={} and refer to that",
+                                    n, n
+                                )),
+                            );
+                        }
+                    }
+                }
+                self.hard_coded_constants(left);
+                self.hard_coded_constants(right);
+            }
+            Expr::FunctionCall { args, .. } => {
+                for arg in args {
+                    self.hard_coded_constants(arg);
+                }
+            }
+            Expr::UnaryOp { expr, .. } => self.hard_coded_constants(expr),
+            Expr::Literal { .. }
+            | Expr::Reference { .. }
+            | Expr::Range { .. }
+            | Expr::SheetReference { .. }
+            | Expr::Union { .. }
+            | Expr::Intersection { .. }
+            | Expr::Name { .. } => {}
+        }
+    }
+
+    fn inconsistent_formula(&mut self, expr: &Expr) {
+        let Some(above_row) = self.address.row.checked_sub(1) else {
+            return;
+        };
+        let above = CellAddress::new(self.address.col, above_row);
+        let below = CellAddress::new(self.address.col, self.address.row + 1);
+        let (Some(above_text), Some(below_cell)) =
+            (self.formula_text(&above), self.source.cell(&below))
+        else {
+            return;
+        };
+        let Some(below_expr) = parse_formula(&below_cell) else {
+            return;
+        };
+
+        // The neighbors have to agree with each other before this cell can
+        // be the odd one out
+        if filled(&above_text, &above, &below).map(|(_, expr)| expr) != Some(below_expr) {
+            return;
+        }
+        let Some((expected, expected_expr)) = filled(&above_text, &above, &self.address) else {
+            return;
+        };
+        if expected_expr != *expr {
+            self.report(
+                LintRule::InconsistentFormula,
+                format!("Formula differs from {} and {} around it", above, below),
+                Some(format!("Use {}", expected)),
+            );
+        }
+    }
+
+    /// Report each range aggregated by the formula whose numbers are
+    /// shown in more than one unit
+    fn mixed_formats(&mut self, expr: &Expr, cache: &mut MixedFormatCache) {
+        let mut ranges = Vec::new();
+        aggregated_ranges(expr, &mut ranges);
+        for range in ranges {
+            let fingerprint = self.source.fingerprint(&range);
+            let message = cache.get_or_check(self.address, range, fingerprint, || {
+                let cells = self.source.cells_in(&range);
+                let numbers = cells.into_iter().filter_map(|(address, cell)| {
+                    if address == self.address || !cell.get_computed_value().is_number() {
+                        return None;
+                    }
+                    let category = FormatCategory::of(self.source.format(&address).as_ref())?;
+                    Some((address, category))
+                });
+                formats::describe_mix(&range, numbers)
+            });
+            if let Some(message) = message {
+                self.report(
+                    LintRule::MixedFormats,
+                    message,
+                    Some("Convert the numbers to one unit before aggregating them".to_string()),
+                );
+            }
+        }
+    }
+
+    fn formula_text(&self, address: &CellAddress) -> Option<String> {
+        self.source
+            .cell(address)?
+            .formula_text
+            .as_deref()
+            .map(str::to_string)
+    }
+}
+
+/// Ranges passed straight to a unit aggregate anywhere in `expr`
+fn aggregated_ranges(expr: &Expr, ranges: &mut Vec<CellRange>) {
+    match expr {
+        Expr::FunctionCall { name, args } => {
+            let aggregate = UNIT_AGGREGATES.iter().any(|f| f.eq_ignore_ascii_case(name));
+            for arg in args {
+                match arg {
+                    Expr::Range { range, .. } if aggregate => {
+                        if !ranges.contains(range) {
+                            ranges.push(*range);
+                        }
+                    }
+                    _ => aggregated_ranges(arg, ranges),
+                }
+            }
+        }
+        Expr::UnaryOp { expr, .. } => aggregated_ranges(expr, ranges),
+        Expr::BinaryOp { left, right, .. } => {
+            aggregated_ranges(left, ranges);
+            aggregated_ranges(right, ranges);
+        }
+        Expr::Literal { .. }
+        | Expr::Reference { .. }
+        | Expr::Range { .. }
+        | Expr::SheetReference { .. }
+        | Expr::Union { .. }
+        | Expr::Intersection { .. }
+        | Expr::Name { .. } => {}
+    }
+}
+
+/// `formula` as it would read if filled from `from` to `to`, with its parse
+fn filled(formula: &str, from: &CellAddress, to: &CellAddress) -> Option<(String, Expr)> {
+    let text = DefaultFormulaAdjuster::new()
+        .adjust_formula(&format!("={}", formula), from, to, FillDirection::Down)
+        .ok()?;
+    let expr = FormulaParser::parse(&text).ok()?;
+    Some((text, expr))
+}
+
+/// The number in `constant` when it is neither 0 nor 1 and `other` is a
+/// single-cell reference
+fn constant_beside_reference(constant: &Expr, other: &Expr) -> Option<(f64, CellAddress)> {
+    match (constant, other) {
+        (
+            Expr::Literal {
+                value: CellValue::Number(n),
+            },
+            Expr::Reference { address, .. },
+        ) if *n != 0.0 && *n != 1.0 => Some((*n, *address)),
+        _ => None,
+    }
+}
+
+fn is_arithmetic(op: BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Add
+            | BinaryOperator::Subtract
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Power
+    )
+}
+
+/// A1 notation for the cells from `start` to `end`
+fn span(start: CellAddress, end: CellAddress) -> String {
+    if start == end {
+        start.to_string()
+    } else {
+        CellRange::new(start, end).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests;
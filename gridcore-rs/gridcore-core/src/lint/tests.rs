@@ -0,0 +1,285 @@
+use super::{LintFinding, LintRule, LintSettings};
+use crate::SpreadsheetFacade;
+use crate::domain::CellFormat;
+use crate::types::CellAddress;
+
+fn lint_with(entries: &[(&str, &str)], settings: &LintSettings) -> Vec<LintFinding> {
+    let facade = SpreadsheetFacade::new();
+    for (address, value) in entries {
+        facade
+            .set_cell_value(&CellAddress::from_a1(address).unwrap(), value)
+            .unwrap();
+    }
+    facade.lint(None, settings)
+}
+
+/// Findings of one rule as (cell, suggestion)
+fn findings(entries: &[(&str, &str)], rule: LintRule) -> Vec<(String, Option<String>)> {
+    lint_with(entries, &LintSettings::new())
+        .into_iter()
+        .filter(|finding| finding.rule == rule)
+        .map(|finding| (finding.address.to_string(), finding.suggestion))
+        .collect()
+}
+
+fn column_of_numbers(count: u32) -> Vec<(String, String)> {
+    (1..=count)
+        .map(|row| (format!("A{}", row), (row * 10).to_string()))
+        .collect()
+}
+
+fn with<'a>(base: &'a [(String, String)], extra: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+    base.iter()
+        .map(|(address, value)| (address.as_str(), value.as_str()))
+        .chain(extra.iter().copied())
+        .collect()
+}
+
+#[test]
+fn test_omitted_cells_below_and_above_the_range() {
+    let data = column_of_numbers(10);
+    assert_eq!(
+        findings(
+            &with(&data, &[("B1", "=SUM(A1:A9)")]),
+            LintRule::OmittedCells
+        ),
+        vec![("B1".to_string(), Some("Use A1:A10".to_string()))]
+    );
+    assert_eq!(
+        findings(
+            &with(&data, &[("B1", "=AVERAGE(A3:A10)")]),
+            LintRule::OmittedCells
+        ),
+        vec![("B1".to_string(), Some("Use A1:A10".to_string()))]
+    );
+
+    // Along a row as well
+    let row = [
+        ("A1", "1"),
+        ("B1", "2"),
+        ("C1", "3"),
+        ("D1", "4"),
+        ("A3", "=SUM(A1:C1)"),
+    ];
+    assert_eq!(
+        findings(&row, LintRule::OmittedCells),
+        vec![("A3".to_string(), Some("Use A1:D1".to_string()))]
+    );
+}
+
+#[test]
+fn test_omitted_cells_ignores_totals_and_complete_ranges() {
+    let data = column_of_numbers(9);
+    // The total sits right under the data it sums
+    assert!(
+        findings(
+            &with(&data, &[("A10", "=SUM(A1:A9)")]),
+            LintRule::OmittedCells
+        )
+        .is_empty()
+    );
+    // A computed cell next to the range is not more data
+    assert!(
+        findings(
+            &with(&data, &[("A10", "=A9*2"), ("B1", "=SUM(A1:A9)")]),
+            LintRule::OmittedCells
+        )
+        .is_empty()
+    );
+    assert!(
+        findings(
+            &with(&data, &[("B1", "=SUM(A1:A9)")]),
+            LintRule::OmittedCells
+        )
+        .is_empty()
+    );
+    // Only aggregates are checked
+    assert!(
+        findings(
+            &with(&data, &[("B1", "=CONCATENATE(A1:A8)")]),
+            LintRule::OmittedCells
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+fn test_empty_reference() {
+    let entries = [("A1", "5"), ("B1", "=A1+C1"), ("B2", "=SUM(D1:D5)")];
+    assert_eq!(
+        findings(&entries, LintRule::EmptyReference),
+        vec![("B1".to_string(), None)]
+    );
+
+    let entries = [("A1", "5"), ("C1", "=A1"), ("B1", "=A1+C1")];
+    assert!(findings(&entries, LintRule::EmptyReference).is_empty());
+}
+
+#[test]
+fn test_hard_coded_constant() {
+    let entries = [
+        ("A1", "5"),
+        ("B1", "=A1*1.2"),
+        ("B2", "=0.5*A1"),
+        ("B3", "=A1+1"),
+        ("B4", "=A1*0"),
+        ("B5", "=SUM(A1:A3)*2"),
+        ("B6", "=A1*A1"),
+    ];
+    let cells: Vec<String> = findings(&entries, LintRule::HardCodedConstant)
+        .into_iter()
+        .map(|(cell, _)| cell)
+        .collect();
+    assert_eq!(cells, ["B1", "B2"]);
+}
+
+#[test]
+fn test_inconsistent_formula_in_a_column() {
+    let entries = [
+        ("C1", "=A1*B1"),
+        ("C2", "=A2*B2"),
+        ("C3", "=A3+B3"),
+        ("C4", "=A4*B4"),
+        ("C5", "=A5*B5"),
+        // A total under the run is its end, not an inconsistency
+        ("C6", "=SUM(C1:C5)"),
+    ];
+    assert_eq!(
+        findings(&entries, LintRule::InconsistentFormula),
+        vec![("C3".to_string(), Some("Use =A3*B3".to_string()))]
+    );
+
+    // Absolute references stay put when formulas are compared
+    let entries = [
+        ("C1", "=A1*$B$1"),
+        ("C2", "=A2*$B$1"),
+        ("C3", "=A3*$B$1"),
+        ("D1", "=A1*$B$1"),
+        ("D2", "=A2*$B$2"),
+        ("D3", "=A3*$B$1"),
+    ];
+    assert_eq!(
+        findings(&entries, LintRule::InconsistentFormula),
+        vec![("D2".to_string(), Some("Use =A2*$B$1".to_string()))]
+    );
+
+    // Neighbors that disagree with each other give nothing to compare with
+    let entries = [("C1", "=A1"), ("C2", "=A2*2"), ("C3", "=B3")];
+    assert!(findings(&entries, LintRule::InconsistentFormula).is_empty());
+}
+
+#[test]
+fn test_disabled_rules_and_rule_ids() {
+    let entries = [("A1", "5"), ("B1", "=A1*1.2+C1")];
+    let mut settings = LintSettings::new();
+    assert_eq!(lint_with(&entries, &settings).len(), 2);
+
+    settings.set_enabled("hard-coded-constant".parse().unwrap(), false);
+    let rules: Vec<LintRule> = lint_with(&entries, &settings)
+        .into_iter()
+        .map(|finding| finding.rule)
+        .collect();
+    assert_eq!(rules, [LintRule::EmptyReference]);
+
+    settings.set_enabled(LintRule::HardCodedConstant, true);
+    assert_eq!(settings, LintSettings::new());
+    assert!("no-such-rule".parse::<LintRule>().is_err());
+}
+
+fn mixed_formats_on() -> LintSettings {
+    let mut settings = LintSettings::new();
+    settings.set_enabled(LintRule::MixedFormats, true);
+    settings
+}
+
+fn mixed_formats(facade: &SpreadsheetFacade) -> Vec<(String, String)> {
+    facade
+        .lint(None, &mixed_formats_on())
+        .into_iter()
+        .filter(|finding| finding.rule == LintRule::MixedFormats)
+        .map(|finding| (finding.address.to_string(), finding.message))
+        .collect()
+}
+
+/// A column of amounts with percent formats on the rows in `percent`
+fn amounts(percent: &[&str]) -> SpreadsheetFacade {
+    let facade = SpreadsheetFacade::new();
+    for (row, value) in ["5", "12", "0.05", "8", "0.1"].iter().enumerate() {
+        let address = CellAddress::new(0, row as u32);
+        facade.set_cell_value(&address, value).unwrap();
+    }
+    for a1 in percent {
+        let address = CellAddress::from_a1(a1).unwrap();
+        facade
+            .set_cell_format(&address, CellFormat::percent(0))
+            .unwrap();
+    }
+    facade
+        .set_cell_value(&CellAddress::from_a1("B1").unwrap(), "=SUM(A1:A5)")
+        .unwrap();
+    facade
+}
+
+#[test]
+fn test_mixed_formats_flag_percents_among_plain_numbers() {
+    let facade = amounts(&["A3", "A5"]);
+    assert_eq!(
+        mixed_formats(&facade),
+        vec![(
+            "B1".to_string(),
+            "Range A1:A5 mixes plain, percent numbers, e.g. at A3, A5".to_string()
+        )]
+    );
+
+    // Off by default
+    let findings = facade.lint(None, &LintSettings::new());
+    assert!(findings.iter().all(|f| f.rule != LintRule::MixedFormats));
+}
+
+#[test]
+fn test_mixed_formats_pass_ranges_in_one_unit() {
+    assert!(mixed_formats(&amounts(&[])).is_empty());
+    // Looks may differ within a unit, and text formats are skipped
+    let facade = amounts(&["A1", "A2", "A3", "A4", "A5"]);
+    facade
+        .set_cell_format(&CellAddress::from_a1("A2").unwrap(), CellFormat::percent(2))
+        .unwrap();
+    assert!(mixed_formats(&facade).is_empty());
+    // COUNT does not care about units
+    let facade = amounts(&["A3"]);
+    facade
+        .set_cell_value(&CellAddress::from_a1("B1").unwrap(), "=COUNT(A1:A5)")
+        .unwrap();
+    assert!(mixed_formats(&facade).is_empty());
+}
+
+#[test]
+fn test_mixed_formats_are_cached_until_a_range_format_changes() {
+    let facade = amounts(&[]);
+    assert!(mixed_formats(&facade).is_empty());
+    assert!(mixed_formats(&facade).is_empty());
+    assert_eq!(facade.mixed_format_cache_stats(), (1, 1));
+
+    // A format outside the range leaves the cached check alone
+    facade
+        .set_cell_format(&CellAddress::from_a1("A9").unwrap(), CellFormat::percent(0))
+        .unwrap();
+    assert!(mixed_formats(&facade).is_empty());
+    assert_eq!(facade.mixed_format_cache_stats(), (2, 1));
+
+    facade
+        .set_cell_format(&CellAddress::from_a1("A4").unwrap(), CellFormat::percent(0))
+        .unwrap();
+    assert_eq!(mixed_formats(&facade).len(), 1);
+    assert_eq!(facade.mixed_format_cache_stats(), (2, 2));
+
+    // So does a column format reaching into it
+    facade
+        .clear_formats(&CellAddress::from_a1("A4").unwrap())
+        .unwrap();
+    facade
+        .set_column_format(0, Some(CellFormat::currency("$", 2)))
+        .unwrap();
+    assert!(mixed_formats(&facade).is_empty());
+    assert_eq!(facade.mixed_format_cache_stats(), (2, 3));
+}
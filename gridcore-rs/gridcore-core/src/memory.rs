@@ -0,0 +1,239 @@
+//! Memory estimates and compaction for long-running sessions.
+//!
+//! Collections keep their capacity when emptied, so a session that loads a
+//! huge sheet, clears it and loads a small one still holds room for the
+//! huge one. [`SpreadsheetFacade::compact`](crate::SpreadsheetFacade::compact)
+//! shrinks every [`MemoryFootprint`] to fit and reports what each held
+//! before and after in a [`MemoryReport`].
+//!
+//! Sizes are estimates from capacities and element sizes, not allocator
+//! statistics; they are meant for comparing before and after.
+
+use crate::domain::Cell;
+use crate::types::CellValue;
+use serde::Serialize;
+use std::fmt;
+use std::mem::size_of;
+
+/// Stored cells and their error index
+pub const CELLS: &str = "cells";
+/// Per-column lookup indexes
+pub const LOOKUP_INDEX: &str = "lookup index";
+/// Dependency graphs between formula cells
+pub const DEPENDENCIES: &str = "dependencies";
+/// Cell, row and column formats
+pub const FORMATS: &str = "formats";
+/// Fetched external values and their subscribers
+pub const EXTERNAL_DATA: &str = "external data";
+/// Deltas of recent batches kept for fetching, which undo is built from
+pub const BATCH_HISTORY: &str = "batch history";
+/// Data derived for linting and drawing, rebuilt on demand
+pub const CACHES: &str = "caches";
+
+/// History kept by a compaction unless asked otherwise, in bytes
+pub const DEFAULT_HISTORY_BUDGET: usize = 1 << 20;
+
+/// Something that can estimate its memory and give spare capacity back
+pub trait MemoryFootprint {
+    /// Estimated bytes held, spare capacity included
+    fn footprint_bytes(&self) -> usize;
+
+    /// Shrink to fit and drop whatever is rebuilt on demand. Never changes
+    /// what the subsystem answers.
+    fn compact(&mut self);
+}
+
+/// How [`SpreadsheetFacade::compact`](crate::SpreadsheetFacade::compact)
+/// trims what it may drop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactOptions {
+    /// Bytes of batch history to keep, newest batches first
+    pub history_budget: usize,
+}
+
+impl Default for CompactOptions {
+    fn default() -> Self {
+        Self {
+            history_budget: DEFAULT_HISTORY_BUDGET,
+        }
+    }
+}
+
+/// One subsystem's estimated size around a compaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SubsystemMemory {
+    pub name: &'static str,
+    pub before: usize,
+    pub after: usize,
+}
+
+impl SubsystemMemory {
+    pub fn freed(&self) -> usize {
+        self.before.saturating_sub(self.after)
+    }
+}
+
+/// Estimated sizes per subsystem before and after a compaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryReport {
+    pub subsystems: Vec<SubsystemMemory>,
+    /// Whether freed memory goes back to the allocator for the OS to
+    /// reclaim. WebAssembly linear memory never shrinks, so in the browser
+    /// freed bytes only become free space for later allocations.
+    pub returns_to_os: bool,
+}
+
+impl Default for MemoryReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryReport {
+    pub fn new() -> Self {
+        Self {
+            subsystems: Vec::new(),
+            returns_to_os: !cfg!(target_arch = "wasm32"),
+        }
+    }
+
+    /// Add the sizes of a subsystem, to any recorded under the same name
+    pub fn record(&mut self, name: &'static str, before: usize, after: usize) {
+        match self.subsystems.iter_mut().find(|s| s.name == name) {
+            Some(subsystem) => {
+                subsystem.before += before;
+                subsystem.after += after;
+            }
+            None => self.subsystems.push(SubsystemMemory {
+                name,
+                before,
+                after,
+            }),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SubsystemMemory> {
+        self.subsystems.iter().find(|s| s.name == name)
+    }
+
+    pub fn before_bytes(&self) -> usize {
+        self.subsystems.iter().map(|s| s.before).sum()
+    }
+
+    pub fn after_bytes(&self) -> usize {
+        self.subsystems.iter().map(|s| s.after).sum()
+    }
+
+    pub fn freed_bytes(&self) -> usize {
+        self.subsystems.iter().map(SubsystemMemory::freed).sum()
+    }
+
+    /// Freed bytes the process keeps as free space for later allocations
+    pub fn reusable_bytes(&self) -> usize {
+        if self.returns_to_os {
+            0
+        } else {
+            self.freed_bytes()
+        }
+    }
+
+    /// Freed bytes handed back to the allocator and the OS
+    pub fn returned_bytes(&self) -> usize {
+        if self.returns_to_os {
+            self.freed_bytes()
+        } else {
+            0
+        }
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let where_to = if self.returns_to_os {
+            "returned"
+        } else {
+            "kept for reuse"
+        };
+        write!(
+            f,
+            "Compacted {} to {}, {} {}",
+            megabytes(self.before_bytes()),
+            megabytes(self.after_bytes()),
+            megabytes(self.freed_bytes()),
+            where_to
+        )
+    }
+}
+
+fn megabytes(bytes: usize) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Estimated bytes of a hash table holding `capacity` entries of `K` and
+/// `V`, one control byte each, without what the entries point to
+pub(crate) fn hash_table_bytes<K, V>(capacity: usize) -> usize {
+    capacity * (size_of::<K>() + size_of::<V>() + 1)
+}
+
+/// Estimated bytes a cell points to, beyond its own size. Values shared
+/// between cells are counted for each.
+pub(crate) fn cell_heap_bytes(cell: &Cell) -> usize {
+    let text = |text: &Option<std::sync::Arc<str>>| text.as_ref().map_or(0, |t| ARC + t.len());
+    value_heap_bytes(&cell.raw_value)
+        + value_heap_bytes(&cell.computed_value)
+        + text(&cell.formula_text)
+        + text(&cell.error)
+        + cell.spill.as_ref().map_or(0, |array| {
+            ARC + array
+                .values()
+                .iter()
+                .map(|value| size_of::<CellValue>() + value_heap_bytes(value))
+                .sum::<usize>()
+        })
+}
+
+/// Reference counts in front of an `Arc`'s value
+const ARC: usize = 2 * size_of::<usize>();
+
+pub(crate) fn value_heap_bytes(value: &CellValue) -> usize {
+    match value {
+        CellValue::Number(_) | CellValue::Boolean(_) | CellValue::Empty => 0,
+        CellValue::String(text) => ARC + size_of::<String>() + text.capacity(),
+        CellValue::Error(error) => ARC + size_of_val(&**error),
+        CellValue::Array(values) => {
+            ARC + size_of::<Vec<CellValue>>()
+                + values.capacity() * size_of::<CellValue>()
+                + values.iter().map(value_heap_bytes).sum::<usize>()
+        }
+        CellValue::Sparkline(sparkline) => ARC + size_of_val(&**sparkline),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_sums_subsystems_and_tells_freed_memory_apart() {
+        let mut report = MemoryReport::new();
+        report.record(CELLS, 1000, 200);
+        report.record(FORMATS, 300, 300);
+        // A second sheet adds to the first
+        report.record(CELLS, 500, 100);
+
+        assert_eq!(report.subsystems.len(), 2);
+        assert_eq!(report.get(CELLS).unwrap().freed(), 1200);
+        assert_eq!(report.before_bytes(), 1800);
+        assert_eq!(report.after_bytes(), 600);
+        assert_eq!(report.freed_bytes(), 1200);
+
+        report.returns_to_os = false;
+        assert_eq!(report.reusable_bytes(), 1200);
+        assert_eq!(report.returned_bytes(), 0);
+        assert!(report.to_string().ends_with("kept for reuse"));
+
+        report.returns_to_os = true;
+        assert_eq!(report.reusable_bytes(), 0);
+        assert_eq!(report.returned_bytes(), 1200);
+    }
+}
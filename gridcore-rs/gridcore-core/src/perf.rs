@@ -11,6 +11,7 @@ pub const UNDO_OPERATIONS: &str = "gridcore_undo_operations_total";
 pub const REDO_OPERATIONS: &str = "gridcore_redo_operations_total";
 pub const BATCH_OPERATIONS: &str = "gridcore_batch_operations_total";
 pub const BATCH_SIZE: &str = "gridcore_batch_size";
+pub const LOOKUP_INDEX_BYTES: &str = "gridcore_lookup_index_bytes";
 
 /// Helper macro for timing operations
 /// Usage: perf_time!(METRIC_NAME, { code block })
@@ -0,0 +1,559 @@
+//! Pivot aggregation over a block of cells
+//!
+//! A pivot groups the rows of a source range by one or more key columns,
+//! optionally spreads a second key across columns, and aggregates a value
+//! column for every combination. The result is a plain grid of values.
+
+use crate::formula::CellRange;
+use crate::types::{CellAddress, CellValue, ErrorType};
+use crate::{Result, SpreadsheetError};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Label used for rows whose key cell is empty
+pub const BLANK_LABEL: &str = "(blank)";
+/// Label of the grand total row and column
+pub const GRAND_TOTAL_LABEL: &str = "Grand Total";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PivotAggregation {
+    Sum,
+    /// Number of non-empty values
+    Count,
+    Average,
+    Min,
+    Max,
+}
+
+impl PivotAggregation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PivotAggregation::Sum => "Sum",
+            PivotAggregation::Count => "Count",
+            PivotAggregation::Average => "Average",
+            PivotAggregation::Min => "Min",
+            PivotAggregation::Max => "Max",
+        }
+    }
+}
+
+impl FromStr for PivotAggregation {
+    type Err = SpreadsheetError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "sum" => Ok(PivotAggregation::Sum),
+            "count" => Ok(PivotAggregation::Count),
+            "avg" | "average" => Ok(PivotAggregation::Average),
+            "min" => Ok(PivotAggregation::Min),
+            "max" => Ok(PivotAggregation::Max),
+            _ => Err(SpreadsheetError::InvalidOperation(format!(
+                "Unknown pivot aggregation '{}', expected sum, count, avg, min or max",
+                s
+            ))),
+        }
+    }
+}
+
+/// Which columns of the source feed the pivot. Columns are absolute sheet
+/// column indices and must lie inside the source range.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PivotConfig {
+    /// Columns whose values form the row headers, outermost first
+    pub row_keys: Vec<u32>,
+    /// Column whose values become the column headers
+    pub column_key: Option<u32>,
+    pub value_column: u32,
+    pub aggregation: PivotAggregation,
+    /// Append a grand total row, and a grand total column when there is a column key
+    pub grand_totals: bool,
+}
+
+impl PivotConfig {
+    pub fn new(row_keys: Vec<u32>, value_column: u32, aggregation: PivotAggregation) -> Self {
+        Self {
+            row_keys,
+            column_key: None,
+            value_column,
+            aggregation,
+            grand_totals: false,
+        }
+    }
+
+    pub fn with_column_key(mut self, column: u32) -> Self {
+        self.column_key = Some(column);
+        self
+    }
+
+    pub fn with_grand_totals(mut self, grand_totals: bool) -> Self {
+        self.grand_totals = grand_totals;
+        self
+    }
+
+    fn validate(&self, source: &CellRange) -> Result<()> {
+        if self.row_keys.is_empty() {
+            return Err(SpreadsheetError::InvalidOperation(
+                "A pivot needs at least one row key column".to_string(),
+            ));
+        }
+
+        let in_source = |col: u32| col >= source.start.col && col <= source.end.col;
+        let columns = self
+            .row_keys
+            .iter()
+            .chain(self.column_key.iter())
+            .chain(std::iter::once(&self.value_column));
+        for &col in columns {
+            if !in_source(col) {
+                return Err(SpreadsheetError::InvalidOperation(format!(
+                    "Pivot column {} is outside the source range {}",
+                    CellAddress::column_number_to_label(col),
+                    source
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A pivot that was written to a sheet, remembered so it can be refreshed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PivotDefinition {
+    pub source: CellRange,
+    pub config: PivotConfig,
+    /// Cells covered by the last generated output
+    pub output: CellRange,
+}
+
+/// Generated pivot output, row by row, headers included
+#[derive(Debug, Clone, PartialEq)]
+pub struct PivotTable {
+    pub rows: Vec<Vec<CellValue>>,
+}
+
+impl PivotTable {
+    pub fn height(&self) -> u32 {
+        self.rows.len() as u32
+    }
+
+    pub fn width(&self) -> u32 {
+        self.rows.first().map_or(0, |row| row.len() as u32)
+    }
+
+    /// Range covered when the table is written with its top-left corner at `anchor`
+    pub fn output_range(&self, anchor: &CellAddress) -> CellRange {
+        CellRange::new(
+            *anchor,
+            CellAddress::new(
+                anchor.col + self.width().max(1) - 1,
+                anchor.row + self.height().max(1) - 1,
+            ),
+        )
+    }
+
+    /// The table as an array of row arrays, for spilling
+    pub fn to_array(&self) -> CellValue {
+        CellValue::from_array(
+            self.rows
+                .iter()
+                .map(|row| CellValue::from_array(row.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// Group key taken from a source cell. Numbers sort before text and text
+/// before FALSE and TRUE, text sorts case-insensitively and blanks come
+/// last.
+#[derive(Debug, Clone)]
+enum PivotKey {
+    Number(f64),
+    Text(String),
+    Logical(bool),
+    Blank,
+}
+
+impl PivotKey {
+    fn from_value(value: &CellValue) -> Self {
+        match value {
+            CellValue::Empty => PivotKey::Blank,
+            CellValue::Number(n) => PivotKey::Number(*n),
+            CellValue::Boolean(b) => PivotKey::Logical(*b),
+            CellValue::String(s) if s.is_empty() => PivotKey::Blank,
+            other => PivotKey::Text(other.to_string()),
+        }
+    }
+
+    fn to_value(&self) -> CellValue {
+        match self {
+            PivotKey::Number(n) => CellValue::Number(*n),
+            PivotKey::Text(s) => CellValue::string_from_str(s),
+            PivotKey::Logical(b) => CellValue::Boolean(*b),
+            PivotKey::Blank => CellValue::string_from_str(BLANK_LABEL),
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            PivotKey::Number(_) => 0,
+            PivotKey::Text(_) => 1,
+            PivotKey::Logical(_) => 2,
+            PivotKey::Blank => 3,
+        }
+    }
+}
+
+impl Ord for PivotKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (PivotKey::Number(a), PivotKey::Number(b)) => a.total_cmp(b),
+            (PivotKey::Text(a), PivotKey::Text(b)) => a
+                .to_lowercase()
+                .cmp(&b.to_lowercase())
+                .then_with(|| a.cmp(b)),
+            (PivotKey::Logical(a), PivotKey::Logical(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for PivotKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for PivotKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PivotKey {}
+
+/// Running aggregate of one pivot cell
+#[derive(Debug, Clone, Default)]
+struct Accumulator {
+    sum: f64,
+    numbers: usize,
+    non_empty: usize,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    fn add(&mut self, value: &CellValue) {
+        if value.is_empty() {
+            return;
+        }
+        self.non_empty += 1;
+        if let CellValue::Number(n) = value {
+            self.sum += n;
+            self.numbers += 1;
+            self.min = Some(self.min.map_or(*n, |m| m.min(*n)));
+            self.max = Some(self.max.map_or(*n, |m| m.max(*n)));
+        }
+    }
+
+    fn merge(&mut self, other: &Accumulator) {
+        self.sum += other.sum;
+        self.numbers += other.numbers;
+        self.non_empty += other.non_empty;
+        self.min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    fn result(&self, aggregation: PivotAggregation) -> CellValue {
+        match aggregation {
+            PivotAggregation::Count => CellValue::Number(self.non_empty as f64),
+            PivotAggregation::Sum => CellValue::Number(self.sum),
+            PivotAggregation::Average if self.numbers == 0 => {
+                CellValue::from_error(ErrorType::DivideByZero)
+            }
+            PivotAggregation::Average => CellValue::Number(self.sum / self.numbers as f64),
+            PivotAggregation::Min => self.min.map_or(CellValue::Empty, CellValue::Number),
+            PivotAggregation::Max => self.max.map_or(CellValue::Empty, CellValue::Number),
+        }
+    }
+}
+
+/// Build a pivot table from the cells of `source`, read through `read`
+pub fn build_pivot<F>(source: &CellRange, config: &PivotConfig, read: F) -> Result<PivotTable>
+where
+    F: Fn(&CellAddress) -> CellValue,
+{
+    config.validate(source)?;
+
+    let mut groups: BTreeMap<Vec<PivotKey>, BTreeMap<Option<PivotKey>, Accumulator>> =
+        BTreeMap::new();
+    let mut column_keys: BTreeMap<PivotKey, ()> = BTreeMap::new();
+
+    for row in source.start.row..=source.end.row {
+        let row_key: Vec<PivotKey> = config
+            .row_keys
+            .iter()
+            .map(|&col| PivotKey::from_value(&read(&CellAddress::new(col, row))))
+            .collect();
+        let value = read(&CellAddress::new(config.value_column, row));
+        let column_key = config
+            .column_key
+            .map(|col| PivotKey::from_value(&read(&CellAddress::new(col, row))));
+
+        // Rows with nothing in any pivot column are padding, not a blank group
+        if value.is_empty()
+            && column_key
+                .as_ref()
+                .is_none_or(|key| *key == PivotKey::Blank)
+            && row_key.iter().all(|key| *key == PivotKey::Blank)
+        {
+            continue;
+        }
+
+        if let Some(key) = &column_key {
+            column_keys.insert(key.clone(), ());
+        }
+        groups
+            .entry(row_key)
+            .or_default()
+            .entry(column_key)
+            .or_default()
+            .add(&value);
+    }
+
+    let column_keys: Vec<Option<PivotKey>> = if config.column_key.is_some() {
+        column_keys.into_keys().map(Some).collect()
+    } else {
+        vec![None]
+    };
+    let total_column = config.grand_totals && config.column_key.is_some();
+
+    let mut rows = Vec::with_capacity(groups.len() + 2);
+
+    // Header row: key column letters, then the column keys or the value label
+    let mut header: Vec<CellValue> = config
+        .row_keys
+        .iter()
+        .map(|&col| CellValue::from_string(CellAddress::column_number_to_label(col)))
+        .collect();
+    let value_label = format!(
+        "{} of {}",
+        config.aggregation.label(),
+        CellAddress::column_number_to_label(config.value_column)
+    );
+    for key in &column_keys {
+        header.push(match key {
+            Some(key) => key.to_value(),
+            None => CellValue::string_from_str(&value_label),
+        });
+    }
+    if total_column {
+        header.push(CellValue::string_from_str(GRAND_TOTAL_LABEL));
+    }
+    rows.push(header);
+
+    let mut column_totals: Vec<Accumulator> = vec![Accumulator::default(); column_keys.len()];
+    let mut grand_total = Accumulator::default();
+
+    for (row_key, cells) in &groups {
+        let mut row: Vec<CellValue> = row_key.iter().map(PivotKey::to_value).collect();
+        let mut row_total = Accumulator::default();
+
+        for (index, key) in column_keys.iter().enumerate() {
+            match cells.get(key) {
+                Some(acc) => {
+                    row.push(acc.result(config.aggregation));
+                    row_total.merge(acc);
+                    column_totals[index].merge(acc);
+                }
+                None => row.push(CellValue::Empty),
+            }
+        }
+        if total_column {
+            row.push(row_total.result(config.aggregation));
+        }
+        grand_total.merge(&row_total);
+        rows.push(row);
+    }
+
+    if config.grand_totals {
+        let mut row = vec![CellValue::string_from_str(GRAND_TOTAL_LABEL)];
+        row.resize(config.row_keys.len(), CellValue::Empty);
+        row.extend(
+            column_totals
+                .iter()
+                .map(|acc| acc.result(config.aggregation)),
+        );
+        if total_column {
+            row.push(grand_total.result(config.aggregation));
+        }
+        rows.push(row);
+    }
+
+    Ok(PivotTable { rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hash::FxHashMap;
+
+    /// Region | Product | Quarter | Amount, in columns A..D from row 0
+    fn sales() -> FxHashMap<CellAddress, CellValue> {
+        let data: [(&str, &str, &str, f64); 6] = [
+            ("West", "Pens", "Q1", 10.0),
+            ("East", "Pens", "Q1", 5.0),
+            ("West", "Ink", "Q2", 7.0),
+            ("West", "Pens", "Q2", 3.0),
+            ("", "Ink", "Q1", 2.0),
+            ("East", "Pens", "Q2", 1.0),
+        ];
+        let mut cells = FxHashMap::default();
+        for (row, (region, product, quarter, amount)) in data.into_iter().enumerate() {
+            let row = row as u32;
+            if !region.is_empty() {
+                cells.insert(CellAddress::new(0, row), CellValue::string_from_str(region));
+            }
+            cells.insert(
+                CellAddress::new(1, row),
+                CellValue::string_from_str(product),
+            );
+            cells.insert(
+                CellAddress::new(2, row),
+                CellValue::string_from_str(quarter),
+            );
+            cells.insert(CellAddress::new(3, row), CellValue::Number(amount));
+        }
+        cells
+    }
+
+    fn source() -> CellRange {
+        CellRange::new(CellAddress::new(0, 0), CellAddress::new(3, 9))
+    }
+
+    fn text(s: &str) -> CellValue {
+        CellValue::string_from_str(s)
+    }
+
+    #[test]
+    fn test_multiple_row_keys() {
+        let cells = sales();
+        let config = PivotConfig::new(vec![0, 1], 3, PivotAggregation::Sum);
+        let table = build_pivot(&source(), &config, |addr| {
+            cells.get(addr).cloned().unwrap_or_default()
+        })
+        .unwrap();
+
+        assert_eq!(table.rows[0], vec![text("A"), text("B"), text("Sum of D")]);
+        assert_eq!(
+            table.rows[1..],
+            [
+                vec![text("East"), text("Pens"), CellValue::Number(6.0)],
+                vec![text("West"), text("Ink"), CellValue::Number(7.0)],
+                vec![text("West"), text("Pens"), CellValue::Number(13.0)],
+                vec![text(BLANK_LABEL), text("Ink"), CellValue::Number(2.0)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_key_with_grand_totals() {
+        let cells = sales();
+        let config = PivotConfig::new(vec![0], 3, PivotAggregation::Sum)
+            .with_column_key(2)
+            .with_grand_totals(true);
+        let table = build_pivot(&source(), &config, |addr| {
+            cells.get(addr).cloned().unwrap_or_default()
+        })
+        .unwrap();
+
+        let n = CellValue::Number;
+        assert_eq!(
+            table.rows,
+            vec![
+                vec![text("A"), text("Q1"), text("Q2"), text(GRAND_TOTAL_LABEL)],
+                vec![text("East"), n(5.0), n(1.0), n(6.0)],
+                vec![text("West"), n(10.0), n(10.0), n(20.0)],
+                vec![text(BLANK_LABEL), n(2.0), CellValue::Empty, n(2.0)],
+                vec![text(GRAND_TOTAL_LABEL), n(17.0), n(11.0), n(28.0)],
+            ]
+        );
+        assert_eq!(
+            table.output_range(&CellAddress::new(5, 0)),
+            CellRange::new(CellAddress::new(5, 0), CellAddress::new(8, 4))
+        );
+    }
+
+    #[test]
+    fn test_aggregations() {
+        let cells = sales();
+        let read = |addr: &CellAddress| cells.get(addr).cloned().unwrap_or_default();
+        let west = |aggregation| {
+            let config = PivotConfig::new(vec![0], 3, aggregation);
+            build_pivot(&source(), &config, read).unwrap().rows[2][1].clone()
+        };
+
+        assert_eq!(west(PivotAggregation::Count), CellValue::Number(3.0));
+        assert_eq!(
+            west(PivotAggregation::Average),
+            CellValue::Number(20.0 / 3.0)
+        );
+        assert_eq!(west(PivotAggregation::Min), CellValue::Number(3.0));
+        assert_eq!(west(PivotAggregation::Max), CellValue::Number(10.0));
+        assert_eq!(
+            "avg".parse::<PivotAggregation>().unwrap(),
+            PivotAggregation::Average
+        );
+        assert!("median".parse::<PivotAggregation>().is_err());
+    }
+
+    #[test]
+    fn test_columns_must_be_inside_source() {
+        let config = PivotConfig::new(vec![0], 7, PivotAggregation::Sum);
+        assert!(build_pivot(&source(), &config, |_| CellValue::Empty).is_err());
+        let config = PivotConfig::new(vec![], 3, PivotAggregation::Sum);
+        assert!(build_pivot(&source(), &config, |_| CellValue::Empty).is_err());
+    }
+
+    #[test]
+    fn test_boolean_keys_sort_after_text() {
+        let mut cells = FxHashMap::default();
+        for (row, key) in [
+            CellValue::Boolean(true),
+            text("West"),
+            CellValue::Number(3.0),
+            CellValue::Boolean(false),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            cells.insert(CellAddress::new(0, row as u32), key);
+            cells.insert(CellAddress::new(1, row as u32), CellValue::Number(1.0));
+        }
+        let source = CellRange::new(CellAddress::new(0, 0), CellAddress::new(1, 3));
+        let config = PivotConfig::new(vec![0], 1, PivotAggregation::Count);
+        let table = build_pivot(&source, &config, |addr| {
+            cells.get(addr).cloned().unwrap_or_default()
+        })
+        .unwrap();
+
+        let keys: Vec<CellValue> = table.rows[1..].iter().map(|row| row[0].clone()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                CellValue::Number(3.0),
+                text("West"),
+                CellValue::Boolean(false),
+                CellValue::Boolean(true),
+            ]
+        );
+    }
+}
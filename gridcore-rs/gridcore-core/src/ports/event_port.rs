@@ -5,39 +5,92 @@
 //! concrete event infrastructure.
 
 use crate::Result;
-use crate::types::{CellAddress, CellValue};
+use crate::domain::Cell;
+use crate::types::{CellAddress, CellRange, CellValue};
 use std::fmt::Debug;
 
+/// Batches changing at most this many cells carry every change in their
+/// commit event; larger ones carry a [`BatchDelta::Summary`]
+pub const BATCH_DELTA_INLINE_LIMIT: usize = 256;
+
+/// One cell before and after a change. Formulas are stored without the
+/// leading `=`, as in [`Cell::formula_text`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellDelta {
+    pub address: CellAddress,
+    /// `None` when the cell was empty
+    pub old_value: Option<CellValue>,
+    pub old_formula: Option<String>,
+    /// `None` when the cell was deleted
+    pub new_value: Option<CellValue>,
+    pub new_formula: Option<String>,
+}
+
+/// The cells a committed batch changed
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchDelta {
+    /// Every change, in the order the cells were first touched
+    Cells(Vec<CellDelta>),
+    /// Too many changes to inline; fetch them by batch id
+    Summary {
+        changed: usize,
+        /// Smallest range covering every changed cell
+        bounds: Option<CellRange>,
+    },
+}
+
 /// Types of events that can be emitted
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DomainEvent {
     /// Cell value changed
     CellChanged {
         address: CellAddress,
         old_value: Option<CellValue>,
+        old_formula: Option<String>,
         new_value: CellValue,
+        new_formula: Option<String>,
     },
     /// Cell deleted
     CellDeleted {
         address: CellAddress,
         old_value: CellValue,
+        old_formula: Option<String>,
     },
     /// Row inserted
     RowInserted { index: u32 },
-    /// Row deleted
-    RowDeleted { index: u32 },
+    /// Row deleted, with the cells it held
+    RowDeleted {
+        index: u32,
+        removed: Vec<(CellAddress, Cell)>,
+    },
     /// Column inserted
     ColumnInserted { index: u32 },
-    /// Column deleted  
-    ColumnDeleted { index: u32 },
+    /// Column deleted, with the cells it held
+    ColumnDeleted {
+        index: u32,
+        removed: Vec<(CellAddress, Cell)>,
+    },
     /// Batch operation started
     BatchStarted { batch_id: String },
     /// Batch operation committed
-    BatchCommitted { batch_id: String },
+    BatchCommitted { batch_id: String, delta: BatchDelta },
     /// Batch operation rolled back
     BatchRolledBack { batch_id: String },
     /// Calculation completed
     CalculationCompleted { affected_cells: Vec<CellAddress> },
+    /// A recalculation large enough to report on started, with this many
+    /// formulas to evaluate
+    RecalculationStarted { total_dirty: usize },
+    /// A reported recalculation got this far
+    RecalculationProgress { completed: usize, total: usize },
+    /// A reported recalculation finished
+    RecalculationFinished {
+        duration_ms: f64,
+        cells_evaluated: usize,
+    },
+    /// A reported recalculation was cancelled; the formulas it did not
+    /// reach are stale
+    RecalculationCancelled { completed: usize, total: usize },
 }
 
 /// Event handler callback type
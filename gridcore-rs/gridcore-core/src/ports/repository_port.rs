@@ -6,7 +6,8 @@
 
 use crate::Result;
 use crate::domain::Cell;
-use crate::types::{CellAddress, CellRange};
+use crate::repository::{ErrorIndex, LookupKey, SheetHealth, SpillOutcome, SpillRange};
+use crate::types::{CellAddress, CellArray, CellRange, CellValue};
 use std::collections::HashMap;
 
 /// Port interface for repository operations
@@ -48,4 +49,102 @@ pub trait RepositoryPort: Send + Sync {
 
     /// Delete a column at the specified index
     fn delete_column(&self, col_index: u32) -> Result<()>;
+
+    /// Error counts for the stored cells. The default scans every cell;
+    /// implementations that index errors should override it.
+    fn health(&self) -> SheetHealth {
+        let mut index = ErrorIndex::new();
+        for (address, cell) in self.get_all() {
+            index.update(&address, Some(&cell));
+        }
+        index.health()
+    }
+
+    /// A counter that changes whenever the stored cells change, for caching
+    /// data derived from them. `None` when the implementation cannot tell.
+    fn revision(&self) -> Option<u64> {
+        None
+    }
+
+    /// Visit the numeric computed values of one column between two rows
+    /// (inclusive), in row order. Rows without a number are skipped.
+    fn for_each_number_in_column(
+        &self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        visit: &mut dyn FnMut(u32, f64),
+    ) {
+        let range = CellRange::new(
+            CellAddress::new(col, start_row),
+            CellAddress::new(col, end_row),
+        );
+        for (address, cell) in self.get_range(&range) {
+            if let CellValue::Number(n) = cell.get_display_value() {
+                visit(address.row, *n);
+            }
+        }
+    }
+
+    /// First row of column `col` between two rows (inclusive) whose
+    /// computed value matches `key`, from an index of the column. `None`
+    /// when there is no index to ask, in which case the caller scans.
+    fn indexed_first_match(
+        &self,
+        _col: u32,
+        _start_row: u32,
+        _end_row: u32,
+        _key: &LookupKey,
+    ) -> Option<Option<u32>> {
+        None
+    }
+
+    /// Cells of column `col` between two rows (inclusive) whose computed
+    /// value matches `key`, from an index of the column; `None` as for
+    /// [`Self::indexed_first_match`]
+    fn indexed_match_count(
+        &self,
+        _col: u32,
+        _start_row: u32,
+        _end_row: u32,
+        _key: &LookupKey,
+    ) -> Option<usize> {
+        None
+    }
+
+    /// Turn lookup indexing on or off, for implementations that have it
+    fn set_lookup_index(&self, _enabled: bool) {}
+
+    /// Estimated bytes held per subsystem, named as in [`crate::memory`].
+    /// Empty when the implementation cannot tell.
+    fn memory_footprint(&self) -> Vec<(&'static str, usize)> {
+        Vec::new()
+    }
+
+    /// Give spare capacity back, for implementations that keep any
+    fn compact(&self) {}
+
+    /// Spill the array result of the formula at `anchor` over the cells
+    /// right of and below it, or remove what it spilled before when
+    /// `None`. The default spills nothing, leaving arrays to show their
+    /// first value only.
+    fn spill(&self, _anchor: &CellAddress, _array: Option<&CellArray>) -> SpillOutcome {
+        SpillOutcome::default()
+    }
+
+    /// Every spill range, in reading order of the anchors
+    fn spill_ranges(&self) -> Vec<SpillRange> {
+        Vec::new()
+    }
+
+    /// The spill range `address` is the anchor of or was filled by
+    fn spill_range_at(&self, _address: &CellAddress) -> Option<SpillRange> {
+        None
+    }
+
+    /// Anchors other than `address` whose arrays cover it, filled or
+    /// blocked, which need another try when it changes
+    fn spills_over(&self, _address: &CellAddress) -> Vec<CellAddress> {
+        Vec::new()
+    }
 }
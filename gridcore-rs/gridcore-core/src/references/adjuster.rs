@@ -1,9 +1,28 @@
+use super::displacement::Displacement;
 use super::parser::ReferenceParser;
+use super::shift::{Axis, Outcome, Shift};
 use super::{CellRange, Reference, ReferenceType, StructuralOperation};
 use crate::Result;
+use crate::formula::tokenizer::{LexToken, Tokenizer};
 use crate::types::CellAddress;
+use crate::workbook::sheet_name::{SHEET_NAME_PATTERN, sheet_reference, unquote_sheet_name};
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+/// A reference as written in a formula: an optional sheet prefix, a cell and
+/// an optional range end, e.g. `Sheet1!$A$1:B2` or `'My Sheet'!A1`
+static REFERENCE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?:({})!)?(\$?[A-Z]+\$?[0-9]+)(?::(\$?[A-Z]+\$?[0-9]+))?",
+        SHEET_NAME_PATTERN
+    ))
+    .expect("Invalid reference regex - this is a bug")
+});
 
 /// Adjusts references in formulas when structural changes occur
+///
+/// Rows and columns move the same way whether or not they are marked with
+/// `$`: absoluteness only matters when a formula is copied or filled.
 pub struct ReferenceAdjuster {
     parser: ReferenceParser,
 }
@@ -14,6 +33,65 @@ impl Default for ReferenceAdjuster {
     }
 }
 
+/// Coordinates and `$` markers of a single cell reference
+#[derive(Debug, Clone, Copy)]
+struct CellRef {
+    col: u32,
+    row: u32,
+    col_absolute: bool,
+    row_absolute: bool,
+}
+
+/// The sheet a formula is on and the sheet an operation changed
+#[derive(Debug, Clone, Copy)]
+struct Scope<'a> {
+    formula_sheet: &'a str,
+    operated_sheet: &'a str,
+}
+
+impl CellRef {
+    fn of(reference: &Reference) -> Option<Self> {
+        let (col, row, col_absolute, row_absolute) = match reference.ref_type {
+            ReferenceType::Relative(col, row) => (col as u32, row as u32, false, false),
+            ReferenceType::Absolute(col, row) => (col, row, true, true),
+            ReferenceType::MixedCol(col, row) => (col, row as u32, true, false),
+            ReferenceType::MixedRow(col, row) => (col as u32, row, false, true),
+            _ => return None,
+        };
+        Some(Self {
+            col,
+            row,
+            col_absolute,
+            row_absolute,
+        })
+    }
+
+    fn get(&self, axis: Axis) -> u32 {
+        match axis {
+            Axis::Row => self.row,
+            Axis::Col => self.col,
+        }
+    }
+
+    fn with(mut self, axis: Axis, value: u32) -> Self {
+        match axis {
+            Axis::Row => self.row = value,
+            Axis::Col => self.col = value,
+        }
+        self
+    }
+
+    fn format(&self, parser: &ReferenceParser) -> String {
+        format!(
+            "{}{}{}{}",
+            if self.col_absolute { "$" } else { "" },
+            parser.number_to_column(self.col),
+            if self.row_absolute { "$" } else { "" },
+            self.row + 1
+        )
+    }
+}
+
 impl ReferenceAdjuster {
     pub fn new() -> Self {
         Self {
@@ -23,203 +101,375 @@ impl ReferenceAdjuster {
 
     /// Adjust references in a formula based on a structural operation
     pub fn adjust_formula(&self, formula: &str, operation: &StructuralOperation) -> Result<String> {
+        self.rewrite(formula, None, None, |reference, formula_at| {
+            self.operate(reference, operation, formula_at)
+        })
+    }
+
+    /// Adjust a formula stored at `formula_at`, its position before the
+    /// operation. A range that ends right above the formula (or right left of
+    /// it, for columns) grows when rows are inserted between the two, so a
+    /// total keeps covering rows added at the bottom of its data.
+    pub fn adjust_formula_at(
+        &self,
+        formula: &str,
+        operation: &StructuralOperation,
+        formula_at: &CellAddress,
+    ) -> Result<String> {
+        self.rewrite(formula, Some(formula_at), None, |reference, formula_at| {
+            self.operate(reference, operation, formula_at)
+        })
+    }
+
+    /// Adjust a formula on `formula_sheet`, stored at `formula_at` before
+    /// the operation, for an operation on `operated_sheet`. Only references
+    /// to the operated sheet change: unqualified ones when the formula is on
+    /// it, and those naming it.
+    pub fn adjust_formula_in_sheet(
+        &self,
+        formula: &str,
+        operation: &StructuralOperation,
+        formula_at: &CellAddress,
+        formula_sheet: &str,
+        operated_sheet: &str,
+    ) -> Result<String> {
+        let scope = Scope {
+            formula_sheet,
+            operated_sheet,
+        };
+        self.rewrite(
+            formula,
+            Some(formula_at),
+            Some(scope),
+            |reference, formula_at| self.operate(reference, operation, formula_at),
+        )
+    }
+
+    /// Adjust a formula on `formula_sheet`, stored at `formula_at` before
+    /// any of the insertions and deletions `displacement` composes, for
+    /// all of them on `operated_sheet` at once. The result is the same as
+    /// adjusting it for each operation in turn.
+    pub fn adjust_formula_displaced(
+        &self,
+        formula: &str,
+        displacement: &Displacement,
+        formula_at: &CellAddress,
+        formula_sheet: &str,
+        operated_sheet: &str,
+    ) -> Result<String> {
+        let scope = Scope {
+            formula_sheet,
+            operated_sheet,
+        };
+        self.rewrite(
+            formula,
+            Some(formula_at),
+            Some(scope),
+            |reference, formula_at| self.displace_reference(reference, displacement, formula_at),
+        )
+    }
+
+    fn rewrite(
+        &self,
+        formula: &str,
+        formula_at: Option<&CellAddress>,
+        scope: Option<Scope>,
+        adjust: impl Fn(&Reference, Option<&CellAddress>) -> Option<String>,
+    ) -> Result<String> {
         if !formula.starts_with('=') {
             return Ok(formula.to_string());
         }
 
-        let references = self.parser.parse_formula(formula);
-        let mut adjusted_formula = formula.to_string();
+        Ok(self.replace_references(formula, |found| {
+            self.reference_from(found)
+                .and_then(|reference| self.adjust_reference(&reference, formula_at, scope, &adjust))
+        }))
+    }
 
-        // Process references in reverse order to maintain correct positions
-        for reference in references.iter().rev() {
-            if let Some(adjusted_ref) = self.adjust_reference(reference, operation) {
-                adjusted_formula = adjusted_formula.replace(&reference.text, &adjusted_ref);
-            }
+    /// Point references to sheet `old_name`, in any case, at `new_name`
+    /// instead, quoting the new name where needed. Returns `None` when the formula has no
+    /// such reference.
+    pub fn rename_sheet(&self, formula: &str, old_name: &str, new_name: &str) -> Option<String> {
+        if !formula.starts_with('=') {
+            return None;
         }
-
-        Ok(adjusted_formula)
+        let renamed = self.replace_references(formula, |found| {
+            let sheet = found.get(1)?;
+            if !unquote_sheet_name(sheet.as_str())?.eq_ignore_ascii_case(old_name) {
+                return None;
+            }
+            let reference = &found.get(0)?.as_str()[sheet.len() + 1..];
+            Some(sheet_reference(new_name, reference))
+        });
+        (renamed != formula).then_some(renamed)
     }
 
-    /// Adjust a single reference based on a structural operation
-    fn adjust_reference(
-        &self,
-        reference: &Reference,
-        operation: &StructuralOperation,
-    ) -> Option<String> {
-        match operation {
-            StructuralOperation::InsertRows { before_row, count } => {
-                self.adjust_for_insert_rows(reference, *before_row, *count)
-            }
-            StructuralOperation::InsertColumns { before_col, count } => {
-                self.adjust_for_insert_columns(reference, *before_col, *count)
-            }
-            StructuralOperation::DeleteRows { start_row, count } => {
-                self.adjust_for_delete_rows(reference, *start_row, *count)
-            }
-            StructuralOperation::DeleteColumns { start_col, count } => {
-                self.adjust_for_delete_columns(reference, *start_col, *count)
-            }
-            StructuralOperation::MoveRange { from, to } => {
-                self.adjust_for_move_range(reference, from, to)
-            }
+    /// Turn references to sheet `name`, in any case, into `#REF!`, as when
+    /// the sheet is deleted. Returns `None` when the formula has no such
+    /// reference.
+    pub fn remove_sheet(&self, formula: &str, name: &str) -> Option<String> {
+        if !formula.starts_with('=') {
+            return None;
         }
+        let removed = self.replace_references(formula, |found| {
+            let sheet = unquote_sheet_name(found.get(1)?.as_str())?;
+            sheet
+                .eq_ignore_ascii_case(name)
+                .then(|| "#REF!".to_string())
+        });
+        (removed != formula).then_some(removed)
     }
 
-    fn adjust_for_insert_rows(
+    /// Replace every reference `replace` returns text for. References are
+    /// replaced where they were found, so rewriting one cannot touch another
+    /// that happens to have the same text. String literals are left alone.
+    fn replace_references(
         &self,
-        reference: &Reference,
-        before_row: u32,
-        count: u32,
-    ) -> Option<String> {
-        match &reference.ref_type {
-            ReferenceType::Absolute(col, row) => {
-                // before_row is 0-based; we need to check if the reference's row (also 0-based) is affected
-                // References at or after the insertion point should be shifted down
-                if *row >= before_row {
-                    Some(self.format_absolute_reference(*col, row + count))
-                } else {
-                    None
+        formula: &str,
+        mut replace: impl FnMut(&Captures) -> Option<String>,
+    ) -> String {
+        let mut adjusted = String::with_capacity(formula.len());
+        let mut segment = String::new();
+        let mut flush = |segment: &mut String, adjusted: &mut String| {
+            let mut last_end = 0;
+            for found in REFERENCE_REGEX.captures_iter(segment) {
+                let Some(whole) = found.get(0) else {
+                    continue;
+                };
+                // Function names such as LOG10 look like cells
+                if segment[whole.end()..].starts_with('(') {
+                    continue;
                 }
-            }
-            ReferenceType::MixedRow(col, row) => {
-                if *row >= before_row {
-                    Some(self.format_mixed_row_reference(*col, row + count))
-                } else {
-                    None
+                if let Some(replacement) = replace(&found) {
+                    adjusted.push_str(&segment[last_end..whole.start()]);
+                    adjusted.push_str(&replacement);
+                    last_end = whole.end();
                 }
             }
-            ReferenceType::Range(start, end) => {
-                let start_adjusted = self.adjust_for_insert_rows(start, before_row, count);
-                let end_adjusted = self.adjust_for_insert_rows(end, before_row, count);
+            adjusted.push_str(&segment[last_end..]);
+            segment.clear();
+        };
 
-                match (start_adjusted, end_adjusted) {
-                    (Some(s), Some(e)) => Some(format!("{}:{}", s, e)),
-                    _ => None,
+        for token in Tokenizer::lex(formula, '.') {
+            match token {
+                LexToken::String(text) => {
+                    flush(&mut segment, &mut adjusted);
+                    adjusted.push_str(text);
                 }
+                token => segment.push_str(token.text()),
             }
-            ReferenceType::Sheet(sheet_name, inner_ref) => {
-                // Adjust the inner reference and prepend the sheet name
-                self.adjust_for_insert_rows(inner_ref, before_row, count)
-                    .map(|adjusted| format!("{}!{}", sheet_name, adjusted))
-            }
-            _ => None,
         }
+        flush(&mut segment, &mut adjusted);
+        adjusted
+    }
+
+    fn reference_from(&self, found: &Captures) -> Option<Reference> {
+        let start = found.get(2)?;
+        let mut reference = self.parser.parse_single_reference(start.as_str())?;
+        if let Some(end) = found.get(3) {
+            let end_reference = self.parser.parse_single_reference(end.as_str())?;
+            reference = Reference::new(
+                ReferenceType::Range(Box::new(reference), Box::new(end_reference)),
+                format!("{}:{}", start.as_str(), end.as_str()),
+            );
+        }
+        if let Some(sheet) = found.get(1) {
+            reference = Reference::new(
+                ReferenceType::Sheet(unquote_sheet_name(sheet.as_str())?, Box::new(reference)),
+                found.get(0)?.as_str().to_string(),
+            );
+        }
+        Some(reference)
     }
 
-    fn adjust_for_insert_columns(
+    /// Adjust a single reference with `adjust` when it points at the
+    /// operated sheet. Returns `None` when the reference is unaffected.
+    fn adjust_reference(
         &self,
         reference: &Reference,
-        before_col: u32,
-        count: u32,
+        formula_at: Option<&CellAddress>,
+        scope: Option<Scope>,
+        adjust: &impl Fn(&Reference, Option<&CellAddress>) -> Option<String>,
     ) -> Option<String> {
-        match &reference.ref_type {
-            ReferenceType::Absolute(col, row) => {
-                if *col >= before_col {
-                    Some(self.format_absolute_reference(col + count, *row))
-                } else {
-                    None
-                }
-            }
-            ReferenceType::MixedCol(col, row) => {
-                if *col >= before_col {
-                    Some(self.format_mixed_col_reference(col + count, *row))
-                } else {
-                    None
-                }
-            }
-            ReferenceType::Range(start, end) => {
-                let start_adjusted = self.adjust_for_insert_columns(start, before_col, count);
-                let end_adjusted = self.adjust_for_insert_columns(end, before_col, count);
-
-                match (start_adjusted, end_adjusted) {
-                    (Some(s), Some(e)) => Some(format!("{}:{}", s, e)),
-                    _ => None,
-                }
-            }
-            ReferenceType::Sheet(sheet_name, inner_ref) => {
-                // Adjust the inner reference and prepend the sheet name
-                self.adjust_for_insert_columns(inner_ref, before_col, count)
-                    .map(|adjusted| format!("{}!{}", sheet_name, adjusted))
-            }
-            _ => None,
+        if let ReferenceType::Sheet(sheet_name, inner) = &reference.ref_type {
+            // The formula's position only matters on its own sheet
+            let (formula_at, scope) = match scope {
+                Some(scope) if scope.operated_sheet != sheet_name => return None,
+                Some(scope) if scope.formula_sheet != sheet_name => (None, None),
+                _ => (formula_at, None),
+            };
+            return self
+                .adjust_reference(inner, formula_at, scope, adjust)
+                .map(|adjusted| sheet_reference(sheet_name, &adjusted));
+        }
+        if scope.is_some_and(|scope| scope.formula_sheet != scope.operated_sheet) {
+            return None;
         }
+        adjust(reference, formula_at)
     }
 
-    fn adjust_for_delete_rows(
+    /// Adjust a reference on the operated sheet for a structural operation
+    fn operate(
         &self,
         reference: &Reference,
-        start_row: u32,
-        count: u32,
+        operation: &StructuralOperation,
+        formula_at: Option<&CellAddress>,
     ) -> Option<String> {
-        match &reference.ref_type {
-            ReferenceType::Absolute(col, row) => {
-                if *row >= start_row + count {
-                    Some(self.format_absolute_reference(*col, row - count))
-                } else if *row >= start_row {
-                    Some("#REF!".to_string()) // Reference deleted
-                } else {
-                    None
-                }
+        match operation {
+            StructuralOperation::MoveRange { .. } => self.move_reference(reference, operation),
+            _ => {
+                let shift = operation.shift()?;
+                self.shift_reference(reference, &shift, formula_at)
             }
-            _ => None, // Similar logic for other types
         }
     }
 
-    fn adjust_for_delete_columns(
+    fn shift_reference(
         &self,
         reference: &Reference,
-        start_col: u32,
-        count: u32,
+        shift: &Shift,
+        formula_at: Option<&CellAddress>,
     ) -> Option<String> {
+        let axis = shift.axis;
         match &reference.ref_type {
-            ReferenceType::Absolute(col, row) => {
-                if *col >= start_col + count {
-                    Some(self.format_absolute_reference(col - count, *row))
-                } else if *col >= start_col {
-                    Some("#REF!".to_string()) // Reference deleted
-                } else {
-                    None
+            ReferenceType::Sheet(..) | ReferenceType::External(..) => None,
+            ReferenceType::Range(start, end) => {
+                let (mut start, mut end) = (CellRef::of(start)?, CellRef::of(end)?);
+                let reversed = start.get(axis) > end.get(axis);
+                if reversed {
+                    std::mem::swap(&mut start, &mut end);
+                }
+
+                let grows_at_end = formula_at.is_some_and(|formula_at| {
+                    let (position, across, low, high) = match axis {
+                        Axis::Row => (formula_at.row, formula_at.col, start.col, end.col),
+                        Axis::Col => (formula_at.col, formula_at.row, start.row, end.row),
+                    };
+                    position == end.get(axis) + 1
+                        && (low.min(high)..=low.max(high)).contains(&across)
+                });
+
+                match shift.span(start.get(axis), end.get(axis), grows_at_end) {
+                    Outcome::Unchanged => None,
+                    Outcome::Deleted => Some("#REF!".to_string()),
+                    Outcome::Moved(new_start, new_end) => {
+                        let (mut start, mut end) =
+                            (start.with(axis, new_start), end.with(axis, new_end));
+                        if reversed {
+                            std::mem::swap(&mut start, &mut end);
+                        }
+                        Some(format!(
+                            "{}:{}",
+                            start.format(&self.parser),
+                            end.format(&self.parser)
+                        ))
+                    }
+                }
+            }
+            _ => {
+                let cell = CellRef::of(reference)?;
+                let position = cell.get(axis);
+                match shift.span(position, position, false) {
+                    Outcome::Unchanged => None,
+                    Outcome::Deleted => Some("#REF!".to_string()),
+                    Outcome::Moved(new_position, _) => {
+                        Some(cell.with(axis, new_position).format(&self.parser))
+                    }
                 }
             }
-            _ => None, // Similar logic for other types
         }
     }
 
-    fn adjust_for_move_range(
+    /// Adjust a reference on the operated sheet for the composed
+    /// insertions and deletions, keeping its `$` markers and the order its
+    /// corners were written in
+    fn displace_reference(
         &self,
         reference: &Reference,
-        from: &CellRange,
-        to: &CellAddress,
+        displacement: &Displacement,
+        formula_at: Option<&CellAddress>,
     ) -> Option<String> {
-        // Check if reference is within the moved range
-        if let Some(addr) = reference.to_absolute_address(&CellAddress::new(0, 0))
-            && from.contains(&addr)
-        {
-            let row_offset = to.row as i32 - from.start.row as i32;
-            let col_offset = to.col as i32 - from.start.col as i32;
-
-            let new_row = (addr.row as i32 + row_offset).max(0) as u32;
-            let new_col = (addr.col as i32 + col_offset).max(0) as u32;
-
-            return Some(self.format_relative_reference(new_col, new_row));
+        let (start, end, formula_at) = match &reference.ref_type {
+            ReferenceType::Range(start, end) => {
+                (CellRef::of(start)?, CellRef::of(end)?, formula_at)
+            }
+            _ => {
+                // A single cell never grows
+                let cell = CellRef::of(reference)?;
+                (cell, cell, None)
+            }
+        };
+        let bounds = CellRange::new(
+            CellAddress::new(start.col.min(end.col), start.row.min(end.row)),
+            CellAddress::new(start.col.max(end.col), start.row.max(end.row)),
+        );
+        let moved = match displacement.shift_reference(&bounds, formula_at) {
+            None => return Some("#REF!".to_string()),
+            Some(moved) if moved == bounds => return None,
+            Some(moved) => moved,
+        };
+        let (mut start, mut end) = (start, end);
+        for axis in [Axis::Row, Axis::Col] {
+            let (low, high) = match axis {
+                Axis::Row => (moved.start.row, moved.end.row),
+                Axis::Col => (moved.start.col, moved.end.col),
+            };
+            let (first, last) = if start.get(axis) > end.get(axis) {
+                (high, low)
+            } else {
+                (low, high)
+            };
+            start = start.with(axis, first);
+            end = end.with(axis, last);
         }
-        None
-    }
-
-    fn format_absolute_reference(&self, col: u32, row: u32) -> String {
-        format!("${}${}", self.parser.number_to_column(col), row + 1)
+        Some(match reference.ref_type {
+            ReferenceType::Range(..) => format!(
+                "{}:{}",
+                start.format(&self.parser),
+                end.format(&self.parser)
+            ),
+            _ => start.format(&self.parser),
+        })
     }
 
-    fn format_relative_reference(&self, col: u32, row: u32) -> String {
-        format!("{}{}", self.parser.number_to_column(col), row + 1)
-    }
-
-    fn format_mixed_col_reference(&self, col: u32, row: i32) -> String {
-        format!("${}{}", self.parser.number_to_column(col), row + 1)
-    }
-
-    fn format_mixed_row_reference(&self, col: i32, row: u32) -> String {
-        format!("{}${}", self.parser.number_to_column(col as u32), row + 1)
+    /// Carry a reference lying wholly inside the moved block along with
+    /// it, keeping its `$` markers, and lose one to cells the move
+    /// overwrites
+    fn move_reference(
+        &self,
+        reference: &Reference,
+        operation: &StructuralOperation,
+    ) -> Option<String> {
+        let (start, end) = match &reference.ref_type {
+            ReferenceType::Range(start, end) => (CellRef::of(start)?, CellRef::of(end)?),
+            _ => {
+                let cell = CellRef::of(reference)?;
+                (cell, cell)
+            }
+        };
+        let bounds = CellRange::new(
+            CellAddress::new(start.col.min(end.col), start.row.min(end.row)),
+            CellAddress::new(start.col.max(end.col), start.row.max(end.row)),
+        );
+        match operation.shift_reference(&bounds) {
+            None => Some("#REF!".to_string()),
+            Some(moved) if moved == bounds => None,
+            Some(_) => {
+                let carry = |cell: CellRef| {
+                    let moved = operation.shift_address(&CellAddress::new(cell.col, cell.row))?;
+                    Some(cell.with(Axis::Col, moved.col).with(Axis::Row, moved.row))
+                };
+                let (start, end) = (carry(start)?, carry(end)?);
+                Some(match reference.ref_type {
+                    ReferenceType::Range(..) => format!(
+                        "{}:{}",
+                        start.format(&self.parser),
+                        end.format(&self.parser)
+                    ),
+                    _ => start.format(&self.parser),
+                })
+            }
+        }
     }
 }
 
@@ -252,4 +502,272 @@ mod tests {
         let adjusted = adjuster.adjust_formula(formula, &operation).unwrap();
         assert_eq!(adjusted, "=$A$1+$B$1");
     }
+
+    fn insert_rows(before_row: u32, count: u32) -> StructuralOperation {
+        StructuralOperation::InsertRows { before_row, count }
+    }
+
+    fn delete_rows(start_row: u32, count: u32) -> StructuralOperation {
+        StructuralOperation::DeleteRows { start_row, count }
+    }
+
+    fn insert_cols(before_col: u32, count: u32) -> StructuralOperation {
+        StructuralOperation::InsertColumns { before_col, count }
+    }
+
+    fn delete_cols(start_col: u32, count: u32) -> StructuralOperation {
+        StructuralOperation::DeleteColumns { start_col, count }
+    }
+
+    /// Each case is (operation, formula, cell holding the formula, expected).
+    /// Rows and columns in operations are 0-based, so `insert_rows(4, 1)`
+    /// inserts a row above row 5.
+    fn check(cases: &[(StructuralOperation, &str, Option<&str>, &str)]) {
+        let adjuster = ReferenceAdjuster::new();
+        for (operation, formula, formula_at, expected) in cases {
+            let adjusted = match formula_at {
+                Some(at) => {
+                    let at = CellAddress::from_a1(at).unwrap();
+                    adjuster.adjust_formula_at(formula, operation, &at)
+                }
+                None => adjuster.adjust_formula(formula, operation),
+            }
+            .unwrap();
+            assert_eq!(
+                &adjusted, expected,
+                "{:?} on {} at {:?}",
+                operation, formula, formula_at
+            );
+        }
+    }
+
+    #[test]
+    fn test_row_insert_boundaries() {
+        check(&[
+            // Before the start: the whole range moves
+            (insert_rows(0, 1), "=SUM(A1:A10)", None, "=SUM(A2:A11)"),
+            (insert_rows(2, 2), "=SUM(A3:A10)", None, "=SUM(A5:A12)"),
+            // Inside, including right after the first row and at the last row
+            (insert_rows(1, 1), "=SUM(A1:A10)", None, "=SUM(A1:A11)"),
+            (insert_rows(5, 3), "=SUM(A1:A10)", None, "=SUM(A1:A13)"),
+            (insert_rows(9, 1), "=SUM(A1:A10)", None, "=SUM(A1:A11)"),
+            // Right after the end: only a formula directly below grows it
+            (insert_rows(10, 1), "=SUM(A1:A10)", None, "=SUM(A1:A10)"),
+            (
+                insert_rows(10, 1),
+                "=SUM(A1:A10)",
+                Some("A11"),
+                "=SUM(A1:A11)",
+            ),
+            (
+                insert_rows(10, 2),
+                "=SUM(B1:C10)",
+                Some("C11"),
+                "=SUM(B1:C12)",
+            ),
+            (
+                insert_rows(10, 1),
+                "=SUM(A1:A10)",
+                Some("B11"),
+                "=SUM(A1:A10)",
+            ),
+            (
+                insert_rows(10, 1),
+                "=SUM(A1:A10)",
+                Some("A12"),
+                "=SUM(A1:A10)",
+            ),
+            // Further after the end
+            (
+                insert_rows(11, 1),
+                "=SUM(A1:A10)",
+                Some("A12"),
+                "=SUM(A1:A10)",
+            ),
+            (insert_rows(20, 5), "=SUM(A1:A10)", None, "=SUM(A1:A10)"),
+        ]);
+    }
+
+    #[test]
+    fn test_row_delete_boundaries() {
+        check(&[
+            // Entirely before the range
+            (delete_rows(0, 2), "=SUM(A5:A10)", None, "=SUM(A3:A8)"),
+            (delete_rows(2, 2), "=SUM(A5:A10)", None, "=SUM(A3:A8)"),
+            // Overlapping the start
+            (delete_rows(2, 4), "=SUM(A5:A10)", None, "=SUM(A3:A6)"),
+            (delete_rows(4, 1), "=SUM(A5:A10)", None, "=SUM(A5:A9)"),
+            // Fully inside
+            (delete_rows(5, 2), "=SUM(A5:A10)", None, "=SUM(A5:A8)"),
+            (delete_rows(9, 1), "=SUM(A5:A10)", None, "=SUM(A5:A9)"),
+            // Overlapping the end
+            (delete_rows(8, 5), "=SUM(A5:A10)", None, "=SUM(A5:A8)"),
+            // Exactly the range, or more
+            (delete_rows(4, 6), "=SUM(A5:A10)", None, "=SUM(#REF!)"),
+            (delete_rows(0, 20), "=SUM(A5:A10)", None, "=SUM(#REF!)"),
+            // Entirely after the range
+            (delete_rows(10, 3), "=SUM(A5:A10)", None, "=SUM(A5:A10)"),
+            // Single cells are removed or moved
+            (delete_rows(4, 1), "=A5+A6+A4", None, "=#REF!+A5+A4"),
+        ]);
+    }
+
+    #[test]
+    fn test_column_insert_and_delete_boundaries() {
+        check(&[
+            (insert_cols(0, 1), "=SUM(B1:D1)", None, "=SUM(C1:E1)"),
+            (insert_cols(2, 2), "=SUM(B1:D1)", None, "=SUM(B1:F1)"),
+            (insert_cols(4, 1), "=SUM(B1:D1)", None, "=SUM(B1:D1)"),
+            (insert_cols(4, 1), "=SUM(B1:D1)", Some("E1"), "=SUM(B1:E1)"),
+            (insert_cols(4, 1), "=SUM(B1:D3)", Some("E4"), "=SUM(B1:D3)"),
+            (delete_cols(0, 1), "=SUM(B1:D1)", None, "=SUM(A1:C1)"),
+            (delete_cols(0, 2), "=SUM(B1:D1)", None, "=SUM(A1:B1)"),
+            (delete_cols(2, 1), "=SUM(B1:D1)", None, "=SUM(B1:C1)"),
+            (delete_cols(3, 4), "=SUM(B1:D1)", None, "=SUM(B1:C1)"),
+            (delete_cols(1, 3), "=SUM(B1:D1)", None, "=SUM(#REF!)"),
+            (delete_cols(5, 1), "=SUM(B1:D1)", None, "=SUM(B1:D1)"),
+        ]);
+    }
+
+    #[test]
+    fn test_absolute_and_mixed_references_move_like_relative_ones() {
+        check(&[
+            (
+                insert_rows(4, 1),
+                "=SUM($A$1:$A$10)",
+                None,
+                "=SUM($A$1:$A$11)",
+            ),
+            (insert_rows(4, 1), "=SUM($A1:A$10)", None, "=SUM($A1:A$11)"),
+            (
+                insert_rows(0, 2),
+                "=$B$3+B$3+$B3+B3",
+                None,
+                "=$B$5+B$5+$B5+B5",
+            ),
+            (delete_rows(0, 1), "=SUM(A$2:$A10)", None, "=SUM(A$1:$A9)"),
+            (insert_cols(0, 1), "=$A$1*A$1", None, "=$B$1*B$1"),
+            (
+                insert_rows(10, 1),
+                "=SUM($A$1:$A$10)",
+                Some("A11"),
+                "=SUM($A$1:$A$11)",
+            ),
+            (delete_cols(0, 1), "=$A$1+$B$1", None, "=#REF!+$A$1"),
+        ]);
+    }
+
+    #[test]
+    fn test_rewrites_only_what_it_should() {
+        check(&[
+            // Rewriting one reference must not touch another with the same text
+            (insert_rows(4, 2), "=A7+A5", None, "=A9+A7"),
+            (insert_rows(4, 2), "=A5+A5+A7", None, "=A7+A7+A9"),
+            // Sheet-qualified references and reversed ranges
+            (insert_rows(0, 1), "=Sheet2!A1:B2", None, "=Sheet2!A2:B3"),
+            (
+                insert_rows(0, 1),
+                "='Q1 ''A1'''!A1",
+                None,
+                "='Q1 ''A1'''!A2",
+            ),
+            (
+                insert_rows(0, 1),
+                "='a\"b'!A1&\"A1\"",
+                None,
+                "='a\"b'!A2&\"A1\"",
+            ),
+            (insert_rows(2, 1), "=SUM(A10:A1)", None, "=SUM(A11:A1)"),
+            // Strings and function names are left alone
+            (insert_rows(0, 1), "=\"A1\"&A1", None, "=\"A1\"&A2"),
+            (insert_rows(0, 1), "=LOG10(A1)", None, "=LOG10(A2)"),
+            // Plain values are not formulas
+            (insert_rows(0, 1), "A1", None, "A1"),
+        ]);
+    }
+
+    #[test]
+    fn test_moves_keep_markers_and_carry_ranges() {
+        let moved = StructuralOperation::MoveRange {
+            from: CellRange::from_string("A1:B3").unwrap(),
+            to: CellAddress::from_a1("D2").unwrap(),
+        };
+        check(&[
+            (moved, "=$A$1+B$2+$A3", None, "=$D$2+E$3+$D4"),
+            (
+                moved,
+                "=SUM($A1:B3)+SUM(B3:A1)",
+                None,
+                "=SUM($D2:E4)+SUM(E4:D2)",
+            ),
+            // Ranges sticking out of the block stay where they are
+            (moved, "=SUM(A1:A4)+C1", None, "=SUM(A1:A4)+C1"),
+            // What the block lands on is overwritten
+            (moved, "=E3+SUM(D2:D4)+F5", None, "=#REF!+SUM(#REF!)+F5"),
+            (moved, "=Sheet2!A1", None, "=Sheet2!D2"),
+        ]);
+    }
+
+    #[test]
+    fn test_only_references_to_the_changed_sheet_move() {
+        let adjuster = ReferenceAdjuster::new();
+        let at = CellAddress::from_a1("A11").unwrap();
+        let adjust = |formula: &str, formula_sheet: &str| {
+            adjuster
+                .adjust_formula_in_sheet(formula, &insert_rows(0, 1), &at, formula_sheet, "Data")
+                .unwrap()
+        };
+        assert_eq!(
+            adjust("=A1+Data!A1+Other!A1", "Data"),
+            "=A2+Data!A2+Other!A1"
+        );
+        assert_eq!(
+            adjust("=A1+Data!A1+Other!A1", "Other"),
+            "=A1+Data!A2+Other!A1"
+        );
+        assert_eq!(adjust("='Data'!$A$1", "Summary"), "=Data!$A$2");
+
+        // Only a total on the same sheet grows its range
+        let adjust = |formula: &str, formula_sheet: &str| {
+            adjuster
+                .adjust_formula_in_sheet(formula, &insert_rows(10, 1), &at, formula_sheet, "Data")
+                .unwrap()
+        };
+        assert_eq!(adjust("=SUM(A1:A10)", "Data"), "=SUM(A1:A11)");
+        assert_eq!(adjust("=SUM(Data!A1:A10)", "Data"), "=SUM(Data!A1:A11)");
+        assert_eq!(adjust("=SUM(Data!A1:A10)", "Other"), "=SUM(Data!A1:A10)");
+    }
+
+    #[test]
+    fn test_rename_sheet() {
+        let adjuster = ReferenceAdjuster::new();
+        for (formula, expected) in [
+            ("=Data!A1+Other!A1", Some("='Q1 Data'!A1+Other!A1")),
+            ("='Data'!$A$1:B2*2", Some("='Q1 Data'!$A$1:B2*2")),
+            ("=\"Data!A1\"&A1", None),
+            ("=Database!A1", None),
+        ] {
+            assert_eq!(
+                adjuster.rename_sheet(formula, "Data", "Q1 Data").as_deref(),
+                expected,
+                "{formula}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_removed_sheet_references_become_ref_errors() {
+        let adjuster = ReferenceAdjuster::new();
+        for (formula, expected) in [
+            ("=data!A1+Other!A1", Some("=#REF!+Other!A1")),
+            ("=SUM('Data'!$A$1:B2)", Some("=SUM(#REF!)")),
+            ("=\"Data!A1\"&A1", None),
+        ] {
+            assert_eq!(
+                adjuster.remove_sheet(formula, "Data").as_deref(),
+                expected,
+                "{formula}"
+            );
+        }
+    }
 }
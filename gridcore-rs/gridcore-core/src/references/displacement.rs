@@ -0,0 +1,561 @@
+//! Where rows and columns end up after a run of insertions and deletions,
+//! composed into one map so formulas can be adjusted in a single pass.
+//!
+//! Each axis is kept as the rows it ends up with, in order: runs of rows
+//! that were there before, and runs of rows inserted since. A range keeps
+//! what survives of it and takes in the rows inserted inside it, exactly as
+//! if the operations were applied one at a time. Whether an inserted row
+//! went inside depends on the rows it went in between, which may be gone by
+//! the end, so every insertion remembers its neighbours at the time.
+
+use super::shift::Axis;
+use super::{CellRange, StructuralOperation};
+use crate::types::CellAddress;
+
+/// Rows past the last one a sheet can address
+const END: u64 = 1 << 32;
+
+/// A row as it was when an insertion went in next to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    /// A row that was there before, by its position then
+    Kept(u64),
+    /// A row made by the insertion with this number
+    Inserted(usize),
+}
+
+/// The rows an insertion went in between. `after` is `None` for rows
+/// inserted above the first one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Insertion {
+    after: Option<Row>,
+    before: Row,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Run {
+    /// `len` rows that were at `from..` before
+    Kept { from: u64, len: u64 },
+    /// `len` rows made by the insertion with number `by`
+    Inserted { by: usize, len: u64 },
+}
+
+impl Run {
+    fn len(&self) -> u64 {
+        match *self {
+            Run::Kept { len, .. } | Run::Inserted { len, .. } => len,
+        }
+    }
+}
+
+/// One axis of a [`Displacement`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AxisMap {
+    runs: Vec<Run>,
+    /// Insertions, later ones first
+    insertions: Vec<Insertion>,
+}
+
+impl AxisMap {
+    fn identity() -> Self {
+        Self {
+            runs: vec![Run::Kept { from: 0, len: END }],
+            insertions: Vec::new(),
+        }
+    }
+
+    fn is_identity(&self) -> bool {
+        self.runs == [Run::Kept { from: 0, len: END }]
+    }
+
+    /// Put an insertion of `count` rows before row `at` ahead of the map,
+    /// so rows are read as they were before it
+    fn prepend_insert(&mut self, at: u64, count: u64) {
+        let by = self.insertions.len();
+        let earlier = |row: Row| match row {
+            Row::Kept(row) if row >= at + count => Row::Kept(row - count),
+            Row::Kept(row) if row >= at => Row::Inserted(by),
+            row => row,
+        };
+        for insertion in &mut self.insertions {
+            insertion.after = insertion.after.map(earlier);
+            insertion.before = earlier(insertion.before);
+        }
+        self.insertions.push(Insertion {
+            after: at.checked_sub(1).map(Row::Kept),
+            before: Row::Kept(at),
+        });
+
+        let mut runs = Vec::with_capacity(self.runs.len() + 2);
+        for run in self.runs.drain(..) {
+            let Run::Kept { from, len } = run else {
+                runs.push(run);
+                continue;
+            };
+            let end = from + len;
+            if from < at {
+                runs.push(Run::Kept {
+                    from,
+                    len: end.min(at) - from,
+                });
+            }
+            let (first, last) = (from.max(at), end.min(at + count));
+            if first < last {
+                runs.push(Run::Inserted {
+                    by,
+                    len: last - first,
+                });
+            }
+            if end > at + count {
+                let first = from.max(at + count);
+                runs.push(Run::Kept {
+                    from: first - count,
+                    len: end - first,
+                });
+            }
+        }
+        self.runs = merge(runs);
+    }
+
+    /// Put a deletion of `count` rows from row `at` ahead of the map
+    fn prepend_delete(&mut self, at: u64, count: u64) {
+        let earlier = |row: Row| match row {
+            Row::Kept(row) if row >= at => Row::Kept(row + count),
+            row => row,
+        };
+        for insertion in &mut self.insertions {
+            insertion.after = insertion.after.map(earlier);
+            insertion.before = earlier(insertion.before);
+        }
+
+        let mut runs = Vec::with_capacity(self.runs.len() + 1);
+        for run in self.runs.drain(..) {
+            match run {
+                Run::Kept { from, len } if from < at && at < from + len => {
+                    runs.push(Run::Kept {
+                        from,
+                        len: at - from,
+                    });
+                    runs.push(Run::Kept {
+                        from: at + count,
+                        len: from + len - at,
+                    });
+                }
+                Run::Kept { from, len } if from >= at => runs.push(Run::Kept {
+                    from: from + count,
+                    len,
+                }),
+                run => runs.push(run),
+            }
+        }
+        self.runs = merge(runs);
+    }
+
+    /// Where the rows `start..=end` end up, `None` when none are left.
+    /// Rows inserted between two rows of the span are in it, and so are
+    /// rows inserted between its last row and row `grows_into`, as for a
+    /// total right below its range.
+    fn span(&self, start: u64, end: u64, grows_into: Option<u64>) -> Option<(u64, u64)> {
+        // An insertion only sits next to rows inserted before it, which
+        // come later in the list
+        let mut inside = vec![false; self.insertions.len()];
+        if self
+            .runs
+            .iter()
+            .any(|run| matches!(run, Run::Inserted { .. }))
+        {
+            for (by, insertion) in self.insertions.iter().enumerate().rev() {
+                let within = |row: Row| match row {
+                    Row::Kept(row) => (start..=end).contains(&row),
+                    Row::Inserted(by) => inside[by],
+                };
+                inside[by] = insertion.after.is_some_and(within)
+                    && (within(insertion.before)
+                        || grows_into.is_some_and(|row| insertion.before == Row::Kept(row)));
+            }
+        }
+
+        let mut found: Option<(u64, u64)> = None;
+        let mut position = 0;
+        for run in &self.runs {
+            let rows = match *run {
+                // Rows are kept in order, so nothing further on is inside
+                Run::Kept { from, .. } if from > end => break,
+                Run::Kept { from, len } => {
+                    let (first, last) = (start.max(from), end.min(from + len - 1));
+                    (first <= last).then(|| (position + first - from, position + last - from))
+                }
+                Run::Inserted { by, len } => inside[by].then(|| (position, position + len - 1)),
+            };
+            if let Some((first, last)) = rows {
+                found = Some((found.map_or(first, |(first, _)| first), last));
+            }
+            position += run.len();
+        }
+        found
+    }
+
+    /// Where the row now at `row` was before, `None` for an inserted row
+    fn origin(&self, row: u64) -> Option<u64> {
+        let mut position = 0;
+        for run in &self.runs {
+            if row < position + run.len() {
+                return match *run {
+                    Run::Kept { from, .. } => Some(from + row - position),
+                    Run::Inserted { .. } => None,
+                };
+            }
+            position += run.len();
+        }
+        None
+    }
+}
+
+/// Join neighbouring runs that continue each other
+fn merge(runs: Vec<Run>) -> Vec<Run> {
+    let mut merged: Vec<Run> = Vec::with_capacity(runs.len());
+    for run in runs.into_iter().filter(|run| run.len() > 0) {
+        match (merged.last_mut(), run) {
+            (
+                Some(Run::Kept { from, len }),
+                Run::Kept {
+                    from: next,
+                    len: more,
+                },
+            ) if *from + *len == next => *len += more,
+            (
+                Some(Run::Inserted { by, len }),
+                Run::Inserted {
+                    by: next,
+                    len: more,
+                },
+            ) if *by == next => *len += more,
+            _ => merged.push(run),
+        }
+    }
+    merged
+}
+
+/// Insertions and deletions of rows and columns composed into one map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Displacement {
+    rows: AxisMap,
+    cols: AxisMap,
+}
+
+impl Default for Displacement {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Displacement {
+    /// The map leaving everything where it is
+    pub fn new() -> Self {
+        Self {
+            rows: AxisMap::identity(),
+            cols: AxisMap::identity(),
+        }
+    }
+
+    /// The map of `operations` applied in order. Moves are not part of
+    /// any map and are skipped.
+    pub fn compose(operations: &[StructuralOperation]) -> Self {
+        let mut displacement = Self::new();
+        for operation in operations.iter().rev() {
+            displacement.prepend(operation);
+        }
+        displacement
+    }
+
+    /// Make `operation` happen before the operations already in the map,
+    /// so it maps positions from before `operation` on
+    pub fn prepend(&mut self, operation: &StructuralOperation) {
+        let Some(shift) = operation.shift() else {
+            return;
+        };
+        let axis = match shift.axis {
+            Axis::Row => &mut self.rows,
+            Axis::Col => &mut self.cols,
+        };
+        let (at, count) = (u64::from(shift.at), u64::from(shift.count));
+        match operation {
+            StructuralOperation::InsertRows { .. } | StructuralOperation::InsertColumns { .. } => {
+                axis.prepend_insert(at, count)
+            }
+            _ => axis.prepend_delete(at, count),
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.rows.is_identity() && self.cols.is_identity()
+    }
+
+    /// Where the cell at `address` ends up, `None` when it is deleted
+    pub fn shift_address(&self, address: &CellAddress) -> Option<CellAddress> {
+        self.shift_range(&CellRange::new(*address, *address))
+            .map(|range| range.start)
+    }
+
+    /// Where `range` ends up, as [`StructuralOperation::shift_range`]
+    /// would leave it after each operation in turn
+    pub fn shift_range(&self, range: &CellRange) -> Option<CellRange> {
+        self.shift_reference(range, None)
+    }
+
+    /// Where a formula reference to `range` ends up for a formula that
+    /// was at `formula_at`. A range grows by the rows inserted between its
+    /// last row and the formula once nothing else is left between them,
+    /// and likewise for columns, as the adjuster grows it one operation at
+    /// a time.
+    pub fn shift_reference(
+        &self,
+        range: &CellRange,
+        formula_at: Option<&CellAddress>,
+    ) -> Option<CellRange> {
+        let grows_down = formula_at
+            .filter(|at| (range.start.col..=range.end.col).contains(&at.col))
+            .map(|at| u64::from(at.row));
+        let grows_right = formula_at
+            .filter(|at| (range.start.row..=range.end.row).contains(&at.row))
+            .map(|at| u64::from(at.col));
+        let (start_row, end_row) = self.rows.span(
+            u64::from(range.start.row),
+            u64::from(range.end.row),
+            grows_down,
+        )?;
+        let (start_col, end_col) = self.cols.span(
+            u64::from(range.start.col),
+            u64::from(range.end.col),
+            grows_right,
+        )?;
+        let at = |col: u64, row: u64| {
+            Some(CellAddress::new(
+                u32::try_from(col).ok()?,
+                u32::try_from(row).ok()?,
+            ))
+        };
+        Some(CellRange::new(
+            at(start_col, start_row)?,
+            at(end_col, end_row)?,
+        ))
+    }
+
+    /// Where the cell now at `address` was before, `None` when its row or
+    /// column was inserted
+    pub fn origin(&self, address: &CellAddress) -> Option<CellAddress> {
+        let row = self.rows.origin(u64::from(address.row))?;
+        let col = self.cols.origin(u64::from(address.col))?;
+        Some(CellAddress::new(
+            u32::try_from(col).ok()?,
+            u32::try_from(row).ok()?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::references::ReferenceAdjuster;
+
+    fn insert_rows(before_row: u32, count: u32) -> StructuralOperation {
+        StructuralOperation::InsertRows { before_row, count }
+    }
+
+    fn delete_rows(start_row: u32, count: u32) -> StructuralOperation {
+        StructuralOperation::DeleteRows { start_row, count }
+    }
+
+    fn range(a1: &str) -> CellRange {
+        CellRange::from_string(a1).unwrap()
+    }
+
+    fn cell(a1: &str) -> CellAddress {
+        CellAddress::from_a1(a1).unwrap()
+    }
+
+    /// Insertions and deletions of one or two rows or columns near the top
+    fn small_operations(axes: &[Axis]) -> Vec<StructuralOperation> {
+        let mut operations = Vec::new();
+        for &axis in axes {
+            for at in 0..6 {
+                for count in 1..=2 {
+                    operations.extend(match axis {
+                        Axis::Row => [insert_rows(at, count), delete_rows(at, count)],
+                        Axis::Col => [
+                            StructuralOperation::InsertColumns {
+                                before_col: at,
+                                count,
+                            },
+                            StructuralOperation::DeleteColumns {
+                                start_col: at,
+                                count,
+                            },
+                        ],
+                    });
+                }
+            }
+        }
+        operations
+    }
+
+    /// Every sequence of up to `length` operations out of `operations`
+    fn sequences(
+        operations: &[StructuralOperation],
+        length: usize,
+    ) -> Vec<Vec<StructuralOperation>> {
+        let mut all = vec![Vec::new()];
+        let mut last = vec![Vec::new()];
+        for _ in 0..length {
+            last = last
+                .iter()
+                .flat_map(|sequence: &Vec<StructuralOperation>| {
+                    operations.iter().map(move |operation| {
+                        let mut longer = sequence.clone();
+                        longer.push(*operation);
+                        longer
+                    })
+                })
+                .collect();
+            all.extend(last.iter().cloned());
+        }
+        all
+    }
+
+    fn one_at_a_time(operations: &[StructuralOperation], range: &CellRange) -> Option<CellRange> {
+        operations
+            .iter()
+            .try_fold(*range, |range, operation| operation.shift_range(&range))
+    }
+
+    #[test]
+    fn test_insert_then_delete_above_composes() {
+        // Insert 2 at row 5, then delete 1 at row 3 (0-based)
+        let displacement = Displacement::compose(&[insert_rows(5, 2), delete_rows(3, 1)]);
+        assert_eq!(displacement.shift_address(&cell("A1")), Some(cell("A1")));
+        assert_eq!(displacement.shift_address(&cell("A4")), None);
+        assert_eq!(displacement.shift_address(&cell("A5")), Some(cell("A4")));
+        assert_eq!(displacement.shift_address(&cell("A6")), Some(cell("A7")));
+        assert_eq!(
+            displacement.shift_range(&range("A5:A6")),
+            Some(range("A4:A7"))
+        );
+        assert_eq!(displacement.origin(&cell("A5")), None);
+        assert_eq!(displacement.origin(&cell("A7")), Some(cell("A6")));
+        assert!(!displacement.is_identity());
+    }
+
+    #[test]
+    fn test_inserting_and_deleting_the_same_rows_cancels_out() {
+        let displacement = Displacement::compose(&[insert_rows(3, 2), delete_rows(3, 2)]);
+        assert!(displacement.is_identity());
+        assert!(Displacement::compose(&[]).is_identity());
+    }
+
+    #[test]
+    fn test_rows_inserted_inside_stay_inside_when_its_ends_go() {
+        // Rows inserted inside A6:A10 keep it alive once its own rows go
+        let operations = [insert_rows(6, 2), delete_rows(5, 1), delete_rows(7, 4)];
+        let displacement = Displacement::compose(&operations);
+        assert_eq!(
+            displacement.shift_range(&range("A6:A10")),
+            one_at_a_time(&operations, &range("A6:A10"))
+        );
+        assert_eq!(
+            displacement.shift_range(&range("A6:A10")),
+            Some(range("A6:A7"))
+        );
+    }
+
+    #[test]
+    fn test_every_short_sequence_matches_one_operation_at_a_time() {
+        let operations = small_operations(&[Axis::Row]);
+        for sequence in sequences(&operations, 3) {
+            let displacement = Displacement::compose(&sequence);
+            for start in 0..9 {
+                for end in start..9 {
+                    let range =
+                        CellRange::new(CellAddress::new(1, start), CellAddress::new(2, end));
+                    assert_eq!(
+                        displacement.shift_range(&range),
+                        one_at_a_time(&sequence, &range),
+                        "{:?} on {}",
+                        sequence,
+                        range
+                    );
+                }
+                let now = CellAddress::new(0, start);
+                let before = displacement.origin(&now);
+                if let Some(before) = before {
+                    assert_eq!(displacement.shift_address(&before), Some(now));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rows_and_columns_compose_independently() {
+        let operations: Vec<_> = small_operations(&[Axis::Row, Axis::Col])
+            .into_iter()
+            .filter(|operation| operation.shift().is_some_and(|shift| shift.at % 2 == 0))
+            .collect();
+        for sequence in sequences(&operations, 3) {
+            let displacement = Displacement::compose(&sequence);
+            for (start, end) in [(0, 0), (1, 3), (2, 6), (4, 5)] {
+                let range =
+                    CellRange::new(CellAddress::new(start, end), CellAddress::new(end, end + 1));
+                assert_eq!(
+                    displacement.shift_range(&range),
+                    one_at_a_time(&sequence, &range),
+                    "{:?} on {}",
+                    sequence,
+                    range
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_totals_grow_with_rows_inserted_above_them() {
+        let adjuster = ReferenceAdjuster::new();
+        // One row at a time keeps the formula rewrites to a few thousand
+        let operations: Vec<_> = small_operations(&[Axis::Row])
+            .into_iter()
+            .filter(|operation| operation.shift().is_some_and(|shift| shift.count == 1))
+            .collect();
+        for sequence in sequences(&operations, 3) {
+            let displacement = Displacement::compose(&sequence);
+            for start in 0..5 {
+                for end in start..5 {
+                    // Rows between a total and its range may all go
+                    for total in end + 1..end + 4 {
+                        let formula = format!("=SUM(B{}:B{})", start + 1, end + 1);
+                        let at = CellAddress::new(1, total);
+                        let mut expected = formula.clone();
+                        let mut position = Some(at);
+                        for operation in &sequence {
+                            let Some(now) = position else { break };
+                            expected = adjuster
+                                .adjust_formula_at(&expected, operation, &now)
+                                .unwrap();
+                            position = operation.shift_address(&now);
+                        }
+                        if position.is_none() {
+                            continue;
+                        }
+                        assert_eq!(
+                            adjuster
+                                .adjust_formula_displaced(&formula, &displacement, &at, "S", "S")
+                                .unwrap(),
+                            expected,
+                            "{:?} on {} at {}",
+                            sequence,
+                            formula,
+                            at
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
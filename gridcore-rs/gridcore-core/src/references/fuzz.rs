@@ -0,0 +1,630 @@
+//! Randomized invariant checks for formulas under row, column and range
+//! edits.
+//!
+//! Formulas point at cells holding unique tags, so after any sequence of
+//! edits every reference that is not `#REF!` must still find the tags it
+//! started with, `#REF!` must stand only where they are all gone, formula
+//! text must parse and print back unchanged, and undoing everything must
+//! give back the original sheets. Cases come from seeds, and a failing case
+//! is shrunk before it is reported; workbook cases are reported as a script
+//! to replay with [`ScriptSession`]. Set `GRIDCORE_FUZZ_CASES` to run more
+//! cases than the default.
+
+use super::{CellRange, ReferenceAdjuster, StructuralOperation};
+use crate::SpreadsheetFacade;
+use crate::formula::{BinaryOperator, Expr, FormulaParser, FormulaTransformer};
+use crate::script::ScriptSession;
+use crate::types::{CellAddress, CellValue};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// xorshift64*, enough to spread cases without a dependency
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: u32) -> u32 {
+        (self.next() % u64::from(n)) as u32
+    }
+
+    fn chance(&mut self, percent: u32) -> bool {
+        self.below(100) < percent
+    }
+}
+
+fn cases(default: u64) -> u64 {
+    std::env::var("GRIDCORE_FUZZ_CASES")
+        .ok()
+        .and_then(|cases| cases.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Drop items one at a time for as long as what is left still fails
+fn minimize<T: Clone>(mut items: Vec<T>, fails: impl Fn(&[T]) -> bool) -> Vec<T> {
+    let mut i = 0;
+    while i < items.len() {
+        let mut fewer = items.clone();
+        fewer.remove(i);
+        if fails(&fewer) {
+            items = fewer;
+        } else {
+            i += 1;
+        }
+    }
+    items
+}
+
+/// Rows and columns edits and moves start in
+const EDITED: u32 = 10;
+
+/// A cell or range a generated formula reads, with `$` markers at random
+#[derive(Debug, Clone)]
+struct Area {
+    range: CellRange,
+    single: bool,
+    /// Column and row markers of the start, then of the end
+    absolute: [bool; 4],
+}
+
+impl Area {
+    fn random(rng: &mut Rng, cols: u32, rows: u32) -> Self {
+        let start = CellAddress::new(rng.below(cols), rng.below(rows));
+        let single = rng.chance(40);
+        let end = if single {
+            start
+        } else {
+            CellAddress::new(
+                (start.col + rng.below(3)).min(cols - 1),
+                (start.row + rng.below(4)).min(rows - 1),
+            )
+        };
+        Self {
+            range: CellRange::new(start, end),
+            single,
+            absolute: [(); 4].map(|_| rng.chance(30)),
+        }
+    }
+
+    fn text(&self) -> String {
+        let cell = |address: &CellAddress, col: bool, row: bool| {
+            format!(
+                "{}{}{}{}",
+                if col { "$" } else { "" },
+                CellAddress::column_number_to_label(address.col),
+                if row { "$" } else { "" },
+                address.row + 1
+            )
+        };
+        let [start_col, start_row, end_col, end_row] = self.absolute;
+        let start = cell(&self.range.start, start_col, start_row);
+        if self.single {
+            start
+        } else {
+            format!("{}:{}", start, cell(&self.range.end, end_col, end_row))
+        }
+    }
+}
+
+/// `SUM` of the areas plus `id`, canonical so it prints back unchanged
+fn formula_text(areas: &[Area], id: u32) -> String {
+    let areas: Vec<String> = areas.iter().map(Area::text).collect();
+    format!("SUM({})+{}", areas.join(","), id)
+}
+
+/// The id and areas of a formula made by [`formula_text`]
+fn decompose(expr: &Expr) -> Option<(u32, &[Expr])> {
+    let Expr::BinaryOp {
+        op: BinaryOperator::Add,
+        left,
+        right,
+    } = expr
+    else {
+        return None;
+    };
+    let (
+        Expr::FunctionCall { name, args },
+        Expr::Literal {
+            value: CellValue::Number(id),
+        },
+    ) = (left.as_ref(), right.as_ref())
+    else {
+        return None;
+    };
+    (name == "SUM").then_some((*id as u32, args.as_slice()))
+}
+
+fn random_operation(rng: &mut Rng, moves: bool) -> StructuralOperation {
+    let at = rng.below(EDITED);
+    let count = 1 + rng.below(3);
+    match rng.below(if moves { 5 } else { 4 }) {
+        0 => StructuralOperation::InsertRows {
+            before_row: at,
+            count,
+        },
+        1 => StructuralOperation::DeleteRows {
+            start_row: at,
+            count,
+        },
+        2 => StructuralOperation::InsertColumns {
+            before_col: at,
+            count,
+        },
+        3 => StructuralOperation::DeleteColumns {
+            start_col: at,
+            count,
+        },
+        _ => {
+            let start = CellAddress::new(rng.below(EDITED), rng.below(EDITED));
+            let end = CellAddress::new(start.col + rng.below(3), start.row + rng.below(3));
+            StructuralOperation::MoveRange {
+                from: CellRange::new(start, end),
+                to: CellAddress::new(rng.below(EDITED), rng.below(EDITED)),
+            }
+        }
+    }
+}
+
+/// Where references must find what they started with, after each check:
+/// the tags at each cell and every tag still anywhere
+struct Tags {
+    at: HashMap<CellAddress, u32>,
+    alive: BTreeSet<u32>,
+}
+
+impl Tags {
+    fn new(at: HashMap<CellAddress, u32>) -> Self {
+        let alive = at.values().copied().collect();
+        Self { at, alive }
+    }
+
+    fn in_range(&self, range: &CellRange) -> BTreeSet<u32> {
+        range
+            .cells()
+            .filter_map(|cell| self.at.get(&cell).copied())
+            .collect()
+    }
+
+    /// Check that `now`, what `area` has become, still reads the tags
+    /// `before` read that are still anywhere
+    fn check(&self, area: &Area, before: &Tags, now: &Expr) -> Result<(), String> {
+        let had = before.in_range(&area.range);
+        let kept: BTreeSet<u32> = had.intersection(&self.alive).copied().collect();
+        let reads = match now {
+            expr if *expr == Expr::deleted_reference() => BTreeSet::new(),
+            Expr::Reference { address, .. } if area.single => {
+                self.in_range(&CellRange::new(*address, *address))
+            }
+            Expr::Range { range, .. } if !area.single => self.in_range(range),
+            other => return Err(format!("{} became {}", area.text(), other)),
+        };
+        if reads != kept {
+            return Err(format!(
+                "{} became {}, which reads tags {:?} instead of {:?}",
+                area.text(),
+                now,
+                reads,
+                kept
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Cells of a sheet as rows of tags, edited the way rows, columns and
+/// moves edit a sheet, to check references against independently of the
+/// code rewriting them
+struct Grid {
+    rows: Vec<Vec<Option<u32>>>,
+}
+
+impl Grid {
+    fn tagged(size: u32) -> Self {
+        Self {
+            rows: (0..size)
+                .map(|row| (0..size).map(|col| Some(row * 100 + col + 1)).collect())
+                .collect(),
+        }
+    }
+
+    fn tags(&self) -> Tags {
+        let mut at = HashMap::new();
+        for (row, cells) in self.rows.iter().enumerate() {
+            for (col, tag) in cells.iter().enumerate() {
+                if let Some(tag) = tag {
+                    at.insert(CellAddress::new(col as u32, row as u32), *tag);
+                }
+            }
+        }
+        Tags::new(at)
+    }
+
+    fn get(&self, address: &CellAddress) -> Option<u32> {
+        *self
+            .rows
+            .get(address.row as usize)?
+            .get(address.col as usize)?
+    }
+
+    fn set(&mut self, address: &CellAddress, tag: Option<u32>) {
+        let (row, col) = (address.row as usize, address.col as usize);
+        if self.rows.len() <= row {
+            self.rows.resize(row + 1, Vec::new());
+        }
+        if self.rows[row].len() <= col {
+            self.rows[row].resize(col + 1, None);
+        }
+        self.rows[row][col] = tag;
+    }
+
+    fn apply(&mut self, operation: &StructuralOperation) {
+        let delete = |cells: &mut Vec<Option<u32>>, at: u32, count: u32| {
+            let at = (at as usize).min(cells.len());
+            let end = (at + count as usize).min(cells.len());
+            cells.drain(at..end);
+        };
+        let insert = |cells: &mut Vec<Option<u32>>, at: u32, count: u32| {
+            if (at as usize) < cells.len() {
+                cells.splice(at as usize..at as usize, (0..count).map(|_| None));
+            }
+        };
+        match *operation {
+            StructuralOperation::InsertRows { before_row, count } => {
+                if (before_row as usize) < self.rows.len() {
+                    let at = before_row as usize;
+                    self.rows.splice(at..at, (0..count).map(|_| Vec::new()));
+                }
+            }
+            StructuralOperation::DeleteRows { start_row, count } => {
+                let at = (start_row as usize).min(self.rows.len());
+                let end = (at + count as usize).min(self.rows.len());
+                self.rows.drain(at..end);
+            }
+            StructuralOperation::InsertColumns { before_col, count } => self
+                .rows
+                .iter_mut()
+                .for_each(|cells| insert(cells, before_col, count)),
+            StructuralOperation::DeleteColumns { start_col, count } => self
+                .rows
+                .iter_mut()
+                .for_each(|cells| delete(cells, start_col, count)),
+            StructuralOperation::MoveRange { from, to } => {
+                let block: Vec<(CellAddress, Option<u32>)> =
+                    from.cells().map(|cell| (cell, self.get(&cell))).collect();
+                for (cell, _) in &block {
+                    self.set(cell, None);
+                }
+                for (cell, tag) in block {
+                    let moved = CellAddress::new(
+                        to.col + (cell.col - from.start.col),
+                        to.row + (cell.row - from.start.row),
+                    );
+                    self.set(&moved, tag);
+                }
+            }
+        }
+    }
+}
+
+/// Whether a move carries some of the cells of `range` away without the
+/// rest, or overwrites some of them, so that it reads different cells by
+/// design
+fn straddles(operation: &StructuralOperation, range: &CellRange) -> bool {
+    let StructuralOperation::MoveRange { from, to } = operation else {
+        return false;
+    };
+    let destination = CellRange::new(
+        *to,
+        CellAddress::new(
+            to.col + (from.end.col - from.start.col),
+            to.row + (from.end.row - from.start.row),
+        ),
+    );
+    let inside = |block: &CellRange| block.contains(&range.start) && block.contains(&range.end);
+    let touches = |block: &CellRange| block.intersect(range).is_some();
+    !inside(from) && (touches(from) || (touches(&destination) && !inside(&destination)))
+}
+
+/// Rewrite a formula for each operation in turn, as text and as an
+/// expression, and check both against a grid edited the same way
+fn check_adjustment(areas: &[Area], operations: &[StructuralOperation]) -> Result<(), String> {
+    let adjuster = ReferenceAdjuster::new();
+    let transformer = FormulaTransformer::new();
+    let mut grid = Grid::tagged(EDITED + 4);
+    let before = grid.tags();
+    let mut formula = format!("={}", formula_text(areas, 1000));
+    let mut expr = FormulaParser::parse(&formula).map_err(|e| e.to_string())?;
+    // Ranges a move left in place over cells it carried or overwrote
+    let mut straddled = vec![false; areas.len()];
+
+    for operation in operations {
+        let adjusted = adjuster
+            .adjust_formula(&formula, operation)
+            .map_err(|e| e.to_string())?;
+        let parsed = FormulaParser::parse(&adjusted)
+            .map_err(|e| format!("{} does not parse: {}", adjusted, e))?;
+        if parsed.to_string() != adjusted[1..] {
+            return Err(format!("{} prints back as ={}", adjusted, parsed));
+        }
+        let (_, args) = decompose(&expr).ok_or("the formula lost its shape")?;
+        for (straddled, arg) in straddled.iter_mut().zip(args) {
+            if let Expr::Range { range, .. } = arg {
+                *straddled |= straddles(operation, range);
+            }
+        }
+        expr = transformer.adjust(expr, operation);
+        if expr != parsed {
+            return Err(format!(
+                "the transformer gives ={} where the adjuster gives {}",
+                expr, adjusted
+            ));
+        }
+        grid.apply(operation);
+        formula = adjusted;
+    }
+
+    let after = grid.tags();
+    let (_, args) = decompose(&expr).ok_or("the formula lost its shape")?;
+    for ((area, arg), straddled) in areas.iter().zip(args).zip(straddled) {
+        if !straddled {
+            after.check(area, &before, arg)?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_adjusted_formulas_parse_print_back_and_keep_their_cells() {
+    for seed in 0..cases(2000) {
+        let mut rng = Rng::new(seed);
+        let areas: Vec<Area> = (0..1 + rng.below(4))
+            .map(|_| Area::random(&mut rng, EDITED, EDITED))
+            .collect();
+        let operations: Vec<StructuralOperation> = (0..1 + rng.below(4))
+            .map(|_| random_operation(&mut rng, true))
+            .collect();
+        if check_adjustment(&areas, &operations).is_err() {
+            let areas = minimize(areas, |areas| {
+                !areas.is_empty() && check_adjustment(areas, &operations).is_err()
+            });
+            let operations = minimize(operations, |operations| {
+                check_adjustment(&areas, operations).is_err()
+            });
+            panic!(
+                "seed {}: {}\nformula: ={}\noperations: {:?}",
+                seed,
+                check_adjustment(&areas, &operations).unwrap_err(),
+                formula_text(&areas, 1000),
+                operations
+            );
+        }
+    }
+}
+
+/// Sheets of generated workbooks, the first being the facade's own
+const SHEETS: [&str; 2] = ["Sheet1", "Data"];
+/// Tagged values fill columns A to F, read by formulas in column H
+const VALUE_COLS: u32 = 6;
+const FORMULA_COL: u32 = 7;
+const ROWS: u32 = 8;
+
+/// Two sheets of tagged values and formulas reading them, and row and
+/// column edits to make to them
+#[derive(Debug, Clone)]
+struct Workbook {
+    /// Unique tags, by sheet index
+    values: Vec<(usize, CellAddress, u32)>,
+    /// Formulas by sheet index, their ids telling them apart once moved
+    formulas: Vec<(usize, CellAddress, Vec<Area>)>,
+    operations: Vec<(usize, StructuralOperation)>,
+}
+
+impl Workbook {
+    fn random(rng: &mut Rng) -> Self {
+        let mut values = Vec::new();
+        let mut formulas = Vec::new();
+        for sheet in 0..SHEETS.len() {
+            for row in 0..ROWS {
+                for col in 0..VALUE_COLS {
+                    if rng.chance(60) {
+                        let tag = sheet as u32 * 100 + row * 10 + col + 1;
+                        values.push((sheet, CellAddress::new(col, row), tag));
+                    }
+                }
+                if rng.chance(50) {
+                    let areas = (0..1 + rng.below(3))
+                        .map(|_| Area::random(rng, VALUE_COLS, ROWS))
+                        .collect();
+                    formulas.push((sheet, CellAddress::new(FORMULA_COL, row), areas));
+                }
+            }
+        }
+        let operations = (0..1 + rng.below(5))
+            .map(|_| {
+                (
+                    rng.below(SHEETS.len() as u32) as usize,
+                    random_operation(rng, false),
+                )
+            })
+            .collect();
+        Self {
+            values,
+            formulas,
+            operations,
+        }
+    }
+
+    fn formula_id(sheet: usize, at: &CellAddress) -> u32 {
+        1000 + sheet as u32 * 100 + at.row
+    }
+
+    fn setup_script(&self) -> String {
+        let mut script = String::new();
+        for (index, name) in SHEETS.iter().enumerate() {
+            writeln!(script, "sheet {}", name).unwrap();
+            for (_, address, tag) in self.values.iter().filter(|(sheet, ..)| *sheet == index) {
+                writeln!(script, "set {} = {}", address, tag).unwrap();
+            }
+            for (sheet, address, areas) in
+                self.formulas.iter().filter(|(sheet, ..)| *sheet == index)
+            {
+                let id = Self::formula_id(*sheet, address);
+                writeln!(script, "set {} = ={}", address, formula_text(areas, id)).unwrap();
+            }
+        }
+        script
+    }
+
+    fn edit_script(&self) -> String {
+        let mut script = String::new();
+        for (sheet, operation) in &self.operations {
+            let (verb, axis, at, count) = match *operation {
+                StructuralOperation::InsertRows { before_row, count } => {
+                    ("insert", "rows", (before_row + 1).to_string(), count)
+                }
+                StructuralOperation::DeleteRows { start_row, count } => {
+                    ("delete", "rows", (start_row + 1).to_string(), count)
+                }
+                StructuralOperation::InsertColumns { before_col, count } => (
+                    "insert",
+                    "cols",
+                    CellAddress::column_number_to_label(before_col),
+                    count,
+                ),
+                StructuralOperation::DeleteColumns { start_col, count } => (
+                    "delete",
+                    "cols",
+                    CellAddress::column_number_to_label(start_col),
+                    count,
+                ),
+                StructuralOperation::MoveRange { .. } => unreachable!("scripts cannot move"),
+            };
+            writeln!(script, "sheet {}", SHEETS[*sheet]).unwrap();
+            writeln!(script, "{} {} {} {}", verb, axis, at, count).unwrap();
+        }
+        script
+    }
+
+    fn tags(facade: &SpreadsheetFacade, sheet: &str) -> Tags {
+        Tags::new(
+            facade
+                .get_all_cells_in_sheet(sheet)
+                .into_iter()
+                .filter(|(_, cell)| !cell.has_formula())
+                .filter_map(|(address, cell)| match cell.raw_value {
+                    CellValue::Number(tag) => Some((address, tag as u32)),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// Run the edits as a script, check every surviving formula, then undo
+    /// them all
+    fn check(&self) -> Result<(), String> {
+        let facade = SpreadsheetFacade::new();
+        let mut session = ScriptSession::new();
+        session
+            .execute(&facade, &self.setup_script())
+            .map_err(|e| format!("setup: {}", e))?;
+        let hashes =
+            |facade: &SpreadsheetFacade| SHEETS.map(|sheet| facade.sheet_input_hash(sheet));
+        let original = hashes(&facade);
+        let before = SHEETS.map(|sheet| Self::tags(&facade, sheet));
+        let areas: HashMap<u32, &Vec<Area>> = self
+            .formulas
+            .iter()
+            .map(|(sheet, address, areas)| (Self::formula_id(*sheet, address), areas))
+            .collect();
+
+        session
+            .execute(&facade, &self.edit_script())
+            .map_err(|e| format!("edits: {}", e))?;
+        for (index, sheet) in SHEETS.iter().enumerate() {
+            let after = Self::tags(&facade, sheet);
+            for (address, cell) in facade.get_all_cells_in_sheet(sheet) {
+                let Some(formula) = cell.formula_text.as_deref() else {
+                    continue;
+                };
+                let at = format!("{}!{}", sheet, address);
+                let expr = FormulaParser::parse(formula)
+                    .map_err(|e| format!("{} ={} does not parse: {}", at, formula, e))?;
+                if expr.to_string() != formula {
+                    return Err(format!("{} ={} prints back as ={}", at, formula, expr));
+                }
+                let (id, args) = decompose(&expr)
+                    .ok_or_else(|| format!("{} ={} lost its shape", at, formula))?;
+                let Some(areas) = areas.get(&id).filter(|_| id / 100 % 10 == index as u32) else {
+                    return Err(format!(
+                        "{} ={} is not a formula of this sheet",
+                        at, formula
+                    ));
+                };
+                for (area, arg) in areas.iter().zip(args) {
+                    after
+                        .check(area, &before[index], arg)
+                        .map_err(|e| format!("{} ={}: {}", at, formula, e))?;
+                }
+            }
+        }
+
+        session
+            .execute(&facade, &"undo\n".repeat(self.operations.len()))
+            .map_err(|e| format!("undo: {}", e))?;
+        if hashes(&facade) != original {
+            return Err("undoing every edit does not give back the original sheets".to_string());
+        }
+        Ok(())
+    }
+
+    fn minimized(self) -> Self {
+        let fails = |workbook: &Workbook| workbook.check().is_err();
+        let operations = minimize(self.operations.clone(), |operations| {
+            fails(&Workbook {
+                operations: operations.to_vec(),
+                ..self.clone()
+            })
+        });
+        let shrunk = Workbook { operations, ..self };
+        let formulas = minimize(shrunk.formulas.clone(), |formulas| {
+            fails(&Workbook {
+                formulas: formulas.to_vec(),
+                ..shrunk.clone()
+            })
+        });
+        let shrunk = Workbook { formulas, ..shrunk };
+        let values = minimize(shrunk.values.clone(), |values| {
+            fails(&Workbook {
+                values: values.to_vec(),
+                ..shrunk.clone()
+            })
+        });
+        Workbook { values, ..shrunk }
+    }
+}
+
+#[test]
+fn test_workbook_edits_keep_references_and_undo_cleanly() {
+    for seed in 0..cases(100) {
+        let workbook = Workbook::random(&mut Rng::new(seed));
+        if workbook.check().is_err() {
+            let workbook = workbook.minimized();
+            panic!(
+                "seed {}: {}\nrepro:\n{}{}",
+                seed,
+                workbook.check().unwrap_err(),
+                workbook.setup_script(),
+                workbook.edit_script()
+            );
+        }
+    }
+}
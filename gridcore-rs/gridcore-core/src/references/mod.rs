@@ -1,15 +1,24 @@
 use crate::types::CellAddress;
 
+pub use crate::formula::CellRange;
+
 pub mod adjuster;
 pub mod detector;
+pub mod displacement;
 pub mod parser;
+mod shift;
+pub mod style;
 pub mod tracker;
 
 pub use self::adjuster::ReferenceAdjuster;
 pub use self::detector::ReferenceDetector;
+pub use self::displacement::Displacement;
 pub use self::parser::ReferenceParser;
+pub use self::style::ReferenceStyle;
 pub use self::tracker::ReferenceTracker;
 
+#[cfg(test)]
+mod fuzz;
 #[cfg(test)]
 mod tests;
 
@@ -67,7 +76,7 @@ impl Reference {
 }
 
 /// Structural operations that affect references
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StructuralOperation {
     InsertRows { before_row: u32, count: u32 },
     InsertColumns { before_col: u32, count: u32 },
@@ -75,23 +84,3 @@ pub enum StructuralOperation {
     DeleteColumns { start_col: u32, count: u32 },
     MoveRange { from: CellRange, to: CellAddress },
 }
-
-/// A range of cells
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct CellRange {
-    pub start: CellAddress,
-    pub end: CellAddress,
-}
-
-impl CellRange {
-    pub fn new(start: CellAddress, end: CellAddress) -> Self {
-        Self { start, end }
-    }
-
-    pub fn contains(&self, addr: &CellAddress) -> bool {
-        addr.col >= self.start.col
-            && addr.col <= self.end.col
-            && addr.row >= self.start.row
-            && addr.row <= self.end.row
-    }
-}
@@ -1,6 +1,7 @@
 use super::{Reference, ReferenceType};
 use crate::formula::Expr;
 use crate::types::CellAddress;
+use crate::workbook::sheet_name::{SHEET_NAME_PATTERN, unquote_sheet_name};
 use regex::Regex;
 use std::collections::HashSet;
 use std::sync::LazyLock;
@@ -16,8 +17,28 @@ static RANGE_REF_REGEX: LazyLock<Regex> = LazyLock::new(|| {
 });
 
 static SHEET_REF_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"([A-Za-z0-9_]+)!(\$?[A-Z]+\$?[0-9]+(?::\$?[A-Z]+\$?[0-9]+)?)")
-        .expect("Invalid sheet reference regex - this is a bug")
+    Regex::new(&format!(
+        r"({})!(\$?[A-Z]+\$?[0-9]+(?::\$?[A-Z]+\$?[0-9]+)?)",
+        SHEET_NAME_PATTERN
+    ))
+    .expect("Invalid sheet reference regex - this is a bug")
+});
+
+/// One cell in R1C1 notation: each part a 1-based index, a bracketed
+/// offset, or nothing for the formula's own row or column
+const R1C1_CELL_PATTERN: &str = r"R(?:\[-?[0-9]+\]|[0-9]+)?C(?:\[-?[0-9]+\]|[0-9]+)?";
+
+static R1C1_CELL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^R(?:\[(-?[0-9]+)\]|([0-9]+))?C(?:\[(-?[0-9]+)\]|([0-9]+))?$")
+        .expect("Invalid R1C1 reference regex - this is a bug")
+});
+
+static R1C1_REF_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?:({})!)?({}(?::{})?)",
+        SHEET_NAME_PATTERN, R1C1_CELL_PATTERN, R1C1_CELL_PATTERN
+    ))
+    .expect("Invalid R1C1 reference regex - this is a bug")
 });
 
 /// Parser for extracting references from formulas
@@ -35,6 +56,8 @@ impl ReferenceParser {
         let _ = &*CELL_REF_REGEX;
         let _ = &*RANGE_REF_REGEX;
         let _ = &*SHEET_REF_REGEX;
+        let _ = &*R1C1_CELL_REGEX;
+        let _ = &*R1C1_REF_REGEX;
         Self {}
     }
 
@@ -58,7 +81,9 @@ impl ReferenceParser {
             let Some(ref_match) = cap.get(2) else {
                 continue;
             };
-            let sheet_name = sheet_match.as_str();
+            let Some(sheet_name) = unquote_sheet_name(sheet_match.as_str()) else {
+                continue;
+            };
             let ref_text = ref_match.as_str();
 
             // Check if the inner reference is a range
@@ -83,14 +108,14 @@ impl ReferenceParser {
                             ref_text.to_string(),
                         );
                         references.push(Reference::new(
-                            ReferenceType::Sheet(sheet_name.to_string(), Box::new(range_ref)),
+                            ReferenceType::Sheet(sheet_name, Box::new(range_ref)),
                             full_match.as_str().to_string(),
                         ));
                     }
                 }
             } else if let Some(inner_ref) = self.parse_single_reference(ref_text) {
                 references.push(Reference::new(
-                    ReferenceType::Sheet(sheet_name.to_string(), Box::new(inner_ref)),
+                    ReferenceType::Sheet(sheet_name, Box::new(inner_ref)),
                     full_match.as_str().to_string(),
                 ));
             }
@@ -157,6 +182,88 @@ impl ReferenceParser {
         references
     }
 
+    /// Extract all references from a formula written in R1C1 notation for
+    /// the cell `origin`, e.g. `=SUM(R[-3]C:R[-1]C)*R1C2`. References
+    /// reaching above the first row or left of the first column are
+    /// skipped.
+    pub fn parse_formula_r1c1(&self, formula: &str, origin: &CellAddress) -> Vec<Reference> {
+        let mut references = Vec::new();
+        for cap in R1C1_REF_REGEX.captures_iter(formula) {
+            let Some(full_match) = cap.get(0) else {
+                continue;
+            };
+            // Names and function calls such as `ARC` or `RC(` are not references
+            let word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.');
+            let before = formula[..full_match.start()].chars().next_back();
+            let after = formula[full_match.end()..].chars().next();
+            if before.is_some_and(word) || after.is_some_and(|c| word(c) || matches!(c, '(' | '['))
+            {
+                continue;
+            }
+            let Some(ref_text) = cap.get(2).map(|m| m.as_str()) else {
+                continue;
+            };
+            let reference = match ref_text.split_once(':') {
+                Some((start, end)) => match (
+                    self.parse_r1c1_reference(start, origin),
+                    self.parse_r1c1_reference(end, origin),
+                ) {
+                    (Some(start), Some(end)) => Reference::new(
+                        ReferenceType::Range(Box::new(start), Box::new(end)),
+                        ref_text.to_string(),
+                    ),
+                    _ => continue,
+                },
+                None => match self.parse_r1c1_reference(ref_text, origin) {
+                    Some(reference) => reference,
+                    None => continue,
+                },
+            };
+            let reference = match cap.get(1) {
+                Some(sheet_match) => {
+                    let Some(sheet_name) = unquote_sheet_name(sheet_match.as_str()) else {
+                        continue;
+                    };
+                    Reference::new(
+                        ReferenceType::Sheet(sheet_name, Box::new(reference)),
+                        full_match.as_str().to_string(),
+                    )
+                }
+                None => reference,
+            };
+            references.push(reference);
+        }
+        references
+    }
+
+    /// Parse a single cell reference in R1C1 notation for a formula in
+    /// `origin`: `R5C3`, `R[1]C[-2]`, `RC` or a mix of them
+    pub fn parse_r1c1_reference(&self, text: &str, origin: &CellAddress) -> Option<Reference> {
+        let captures = R1C1_CELL_REGEX.captures(text)?;
+        let part = |offset: usize, index: usize, from: u32| -> Option<(u32, bool)> {
+            match (captures.get(offset), captures.get(index)) {
+                (Some(offset), _) => {
+                    let position = from as i64 + offset.as_str().parse::<i64>().ok()?;
+                    Some((u32::try_from(position).ok()?, false))
+                }
+                (None, Some(index)) => {
+                    Some((index.as_str().parse::<u32>().ok()?.checked_sub(1)?, true))
+                }
+                (None, None) => Some((from, false)),
+            }
+        };
+        let (row, row_absolute) = part(1, 2, origin.row)?;
+        let (col, col_absolute) = part(3, 4, origin.col)?;
+
+        let ref_type = match (col_absolute, row_absolute) {
+            (true, true) => ReferenceType::Absolute(col, row),
+            (true, false) => ReferenceType::MixedCol(col, row as i32),
+            (false, true) => ReferenceType::MixedRow(col as i32, row),
+            (false, false) => ReferenceType::Relative(col as i32, row as i32),
+        };
+        Some(Reference::new(ref_type, text.to_string()))
+    }
+
     /// Extract references from an expression AST
     pub fn extract_from_expr(&self, expr: &Expr) -> HashSet<CellAddress> {
         let mut references = HashSet::new();
@@ -195,7 +302,7 @@ impl ReferenceParser {
     }
 
     /// Parse a single cell reference
-    fn parse_single_reference(&self, text: &str) -> Option<Reference> {
+    pub(super) fn parse_single_reference(&self, text: &str) -> Option<Reference> {
         if let Some(captures) = CELL_REF_REGEX.captures(text) {
             let col_absolute = !captures.get(1)?.as_str().is_empty();
             let col_str = captures.get(2)?.as_str();
@@ -203,7 +310,7 @@ impl ReferenceParser {
             let row_str = captures.get(4)?.as_str();
 
             let col = self.column_to_number(col_str)?;
-            let row = row_str.parse::<u32>().ok()? - 1; // Convert to 0-based
+            let row = row_str.parse::<u32>().ok()?.checked_sub(1)?; // Convert to 0-based
 
             let ref_type = match (col_absolute, row_absolute) {
                 (true, true) => ReferenceType::Absolute(col, row),
@@ -0,0 +1,398 @@
+//! Where cells end up after a structural operation.
+//!
+//! One rule serves formula references and everything else that points at
+//! cells (cursors, selections, marks, watches): rows and columns at or after
+//! an insertion move by its size, those after a deletion move back, those
+//! inside a deletion are gone, and moving a block carries what lies wholly
+//! inside it.
+
+use super::{CellRange, StructuralOperation};
+use crate::types::CellAddress;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Axis {
+    Row,
+    Col,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Edit {
+    Insert,
+    Delete,
+}
+
+/// Insertion or deletion of `count` rows or columns starting at `at`
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Shift {
+    pub(super) axis: Axis,
+    pub(super) edit: Edit,
+    pub(super) at: u32,
+    pub(super) count: u32,
+}
+
+/// What a shift does to a span of rows or columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Outcome {
+    Unchanged,
+    Moved(u32, u32),
+    Deleted,
+}
+
+impl Shift {
+    /// Apply the shift to the span `start..=end`. With `grows_at_end`, an
+    /// insertion right after the span extends it instead of leaving it alone.
+    pub(super) fn span(&self, start: u32, end: u32, grows_at_end: bool) -> Outcome {
+        let outcome = match self.edit {
+            Edit::Insert => {
+                if self.at <= start {
+                    Outcome::Moved(start + self.count, end + self.count)
+                } else if self.at <= end || (grows_at_end && self.at == end + 1) {
+                    Outcome::Moved(start, end + self.count)
+                } else {
+                    Outcome::Unchanged
+                }
+            }
+            Edit::Delete => {
+                let last = self.at + self.count - 1;
+                if end < self.at {
+                    Outcome::Unchanged
+                } else if start > last {
+                    Outcome::Moved(start - self.count, end - self.count)
+                } else if start >= self.at && end <= last {
+                    Outcome::Deleted
+                } else {
+                    // Partly deleted: keep whatever survives on either side
+                    let new_start = if start < self.at { start } else { self.at };
+                    let new_end = if end > last {
+                        end - self.count
+                    } else {
+                        self.at - 1
+                    };
+                    Outcome::Moved(new_start, new_end)
+                }
+            }
+        };
+        match outcome {
+            Outcome::Moved(s, e) if s == start && e == end => Outcome::Unchanged,
+            outcome => outcome,
+        }
+    }
+
+    /// Apply the shift to `range` along its axis, `None` once it is deleted
+    fn range(&self, range: &CellRange) -> Option<CellRange> {
+        let (start, end) = match self.axis {
+            Axis::Row => (range.start.row, range.end.row),
+            Axis::Col => (range.start.col, range.end.col),
+        };
+        let (start, end) = match self.span(start, end, false) {
+            Outcome::Unchanged => return Some(*range),
+            Outcome::Deleted => return None,
+            Outcome::Moved(start, end) => (start, end),
+        };
+        Some(match self.axis {
+            Axis::Row => CellRange::new(
+                CellAddress::new(range.start.col, start),
+                CellAddress::new(range.end.col, end),
+            ),
+            Axis::Col => CellRange::new(
+                CellAddress::new(start, range.start.row),
+                CellAddress::new(end, range.end.row),
+            ),
+        })
+    }
+}
+
+impl StructuralOperation {
+    /// The insertion or deletion as a shift along one axis. `None` for
+    /// moves and for operations on no rows or columns.
+    pub(super) fn shift(&self) -> Option<Shift> {
+        let (axis, edit, at, count) = match *self {
+            StructuralOperation::InsertRows { before_row, count } => {
+                (Axis::Row, Edit::Insert, before_row, count)
+            }
+            StructuralOperation::InsertColumns { before_col, count } => {
+                (Axis::Col, Edit::Insert, before_col, count)
+            }
+            StructuralOperation::DeleteRows { start_row, count } => {
+                (Axis::Row, Edit::Delete, start_row, count)
+            }
+            StructuralOperation::DeleteColumns { start_col, count } => {
+                (Axis::Col, Edit::Delete, start_col, count)
+            }
+            StructuralOperation::MoveRange { .. } => return None,
+        };
+        (count > 0).then_some(Shift {
+            axis,
+            edit,
+            at,
+            count,
+        })
+    }
+
+    /// Where the cell at `address` ends up, or `None` when it is deleted
+    pub fn shift_address(&self, address: &CellAddress) -> Option<CellAddress> {
+        self.shift_range(&CellRange::new(*address, *address))
+            .map(|range| range.start)
+    }
+
+    /// Where `address` ends up, a deleted cell going to the one that takes
+    /// its place, e.g. for a cursor that must stay somewhere
+    pub fn shift_address_or_next(&self, address: &CellAddress) -> CellAddress {
+        if let Some(shifted) = self.shift_address(address) {
+            return shifted;
+        }
+        match self.shift() {
+            Some(Shift {
+                axis: Axis::Row,
+                at,
+                ..
+            }) => CellAddress::new(address.col, at),
+            Some(Shift {
+                axis: Axis::Col,
+                at,
+                ..
+            }) => CellAddress::new(at, address.row),
+            None => *address,
+        }
+    }
+
+    /// Where `range` ends up: insertions inside it grow it, deletions
+    /// shrink it and `None` means all of it was deleted. A move carries a
+    /// range lying wholly inside the moved block and leaves others alone.
+    pub fn shift_range(&self, range: &CellRange) -> Option<CellRange> {
+        match self {
+            StructuralOperation::MoveRange { from, to } => {
+                if !from.contains(&range.start) || !from.contains(&range.end) {
+                    return Some(*range);
+                }
+                let offset = |address: &CellAddress| {
+                    CellAddress::new(
+                        to.col + (address.col - from.start.col),
+                        to.row + (address.row - from.start.row),
+                    )
+                };
+                Some(CellRange::new(offset(&range.start), offset(&range.end)))
+            }
+            _ => match self.shift() {
+                Some(shift) => shift.range(range),
+                None => Some(*range),
+            },
+        }
+    }
+
+    /// Where a formula reference to `range` ends up. Like
+    /// [`shift_range`](Self::shift_range), except that a move loses
+    /// references lying wholly inside its destination, as it overwrites
+    /// what they pointed at.
+    pub fn shift_reference(&self, range: &CellRange) -> Option<CellRange> {
+        if let StructuralOperation::MoveRange { from, to } = self
+            && !(from.contains(&range.start) && from.contains(&range.end))
+        {
+            let destination = CellRange::new(
+                *to,
+                CellAddress::new(
+                    to.col + (from.end.col - from.start.col),
+                    to.row + (from.end.row - from.start.row),
+                ),
+            );
+            if destination.contains(&range.start) && destination.contains(&range.end) {
+                return None;
+            }
+        }
+        self.shift_range(range)
+    }
+
+    /// The operation putting rows or columns back where they were, `None`
+    /// for moves, which overwrite their destination
+    pub fn inverse(&self) -> Option<StructuralOperation> {
+        Some(match *self {
+            StructuralOperation::InsertRows { before_row, count } => {
+                StructuralOperation::DeleteRows {
+                    start_row: before_row,
+                    count,
+                }
+            }
+            StructuralOperation::DeleteRows { start_row, count } => {
+                StructuralOperation::InsertRows {
+                    before_row: start_row,
+                    count,
+                }
+            }
+            StructuralOperation::InsertColumns { before_col, count } => {
+                StructuralOperation::DeleteColumns {
+                    start_col: before_col,
+                    count,
+                }
+            }
+            StructuralOperation::DeleteColumns { start_col, count } => {
+                StructuralOperation::InsertColumns {
+                    before_col: start_col,
+                    count,
+                }
+            }
+            StructuralOperation::MoveRange { .. } => return None,
+        })
+    }
+
+    /// Cells whose content may differ afterwards, in a grid ending at
+    /// `last`: everything from the inserted or deleted rows down or
+    /// columns right, or the block spanning both ends of a move
+    pub fn damaged_range(&self, last: &CellAddress) -> CellRange {
+        let everything_from = |col: u32, row: u32| {
+            CellRange::new(
+                CellAddress::new(col.min(last.col), row.min(last.row)),
+                *last,
+            )
+        };
+        match *self {
+            StructuralOperation::InsertRows {
+                before_row: row, ..
+            }
+            | StructuralOperation::DeleteRows { start_row: row, .. } => everything_from(0, row),
+            StructuralOperation::InsertColumns {
+                before_col: col, ..
+            }
+            | StructuralOperation::DeleteColumns { start_col: col, .. } => everything_from(col, 0),
+            StructuralOperation::MoveRange { from, to } => {
+                let to_end = CellAddress::new(
+                    to.col + (from.end.col - from.start.col),
+                    to.row + (from.end.row - from.start.row),
+                );
+                CellRange::new(
+                    CellAddress::new(from.start.col.min(to.col), from.start.row.min(to.row)),
+                    CellAddress::new(
+                        from.end.col.max(to_end.col).min(last.col),
+                        from.end.row.max(to_end.row).min(last.row),
+                    ),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(a1: &str) -> CellAddress {
+        CellAddress::from_a1(a1).unwrap()
+    }
+
+    fn range(a1: &str) -> CellRange {
+        CellRange::from_string(a1).unwrap()
+    }
+
+    #[test]
+    fn test_addresses_shift_around_the_edit_point() {
+        let insert = StructuralOperation::InsertRows {
+            before_row: 4,
+            count: 2,
+        };
+        assert_eq!(insert.shift_address(&cell("B4")), Some(cell("B4")));
+        assert_eq!(insert.shift_address(&cell("B5")), Some(cell("B7")));
+
+        let delete = StructuralOperation::DeleteColumns {
+            start_col: 1,
+            count: 2,
+        };
+        assert_eq!(delete.shift_address(&cell("A9")), Some(cell("A9")));
+        assert_eq!(delete.shift_address(&cell("C9")), None);
+        assert_eq!(delete.shift_address(&cell("E9")), Some(cell("C9")));
+        assert_eq!(delete.shift_address_or_next(&cell("C9")), cell("B9"));
+
+        let moved = StructuralOperation::MoveRange {
+            from: range("A1:B2"),
+            to: cell("D5"),
+        };
+        assert_eq!(moved.shift_address(&cell("B2")), Some(cell("E6")));
+        assert_eq!(moved.shift_address(&cell("C3")), Some(cell("C3")));
+    }
+
+    #[test]
+    fn test_ranges_grow_shrink_and_move() {
+        let insert = StructuralOperation::InsertRows {
+            before_row: 2,
+            count: 1,
+        };
+        assert_eq!(insert.shift_range(&range("A1:B5")), Some(range("A1:B6")));
+        assert_eq!(insert.shift_range(&range("A4:B5")), Some(range("A5:B6")));
+
+        let delete = StructuralOperation::DeleteRows {
+            start_row: 0,
+            count: 3,
+        };
+        assert_eq!(delete.shift_range(&range("A2:A5")), Some(range("A1:A2")));
+        assert_eq!(delete.shift_range(&range("A1:C3")), None);
+
+        // Only blocks wholly inside the moved one go with it
+        let moved = StructuralOperation::MoveRange {
+            from: range("A1:B2"),
+            to: cell("C1"),
+        };
+        assert_eq!(moved.shift_range(&range("A2:B2")), Some(range("C2:D2")));
+        assert_eq!(moved.shift_range(&range("A2:B3")), Some(range("A2:B3")));
+    }
+
+    #[test]
+    fn test_moves_lose_references_to_what_they_overwrite() {
+        let moved = StructuralOperation::MoveRange {
+            from: range("A1:B2"),
+            to: cell("B2"),
+        };
+        // Carried with the block, even where source and destination overlap
+        assert_eq!(moved.shift_reference(&range("B2:B2")), Some(range("C3:C3")));
+        // Overwritten
+        assert_eq!(moved.shift_reference(&range("C3:C3")), None);
+        assert_eq!(moved.shift_reference(&range("B3:C3")), None);
+        // Partly overwritten ranges stay, as cursors do
+        assert_eq!(moved.shift_reference(&range("C3:D4")), Some(range("C3:D4")));
+        assert_eq!(moved.shift_address(&cell("C3")), Some(cell("C3")));
+    }
+
+    #[test]
+    fn test_inverse_puts_rows_and_columns_back() {
+        let insert = StructuralOperation::InsertRows {
+            before_row: 3,
+            count: 2,
+        };
+        let delete = insert.inverse().unwrap();
+        assert_eq!(
+            delete,
+            StructuralOperation::DeleteRows {
+                start_row: 3,
+                count: 2
+            }
+        );
+        assert_eq!(delete.inverse(), Some(insert));
+        for a1 in ["A1", "C3", "C4", "Z99"] {
+            let shifted = insert.shift_address(&cell(a1)).unwrap();
+            assert_eq!(delete.shift_address(&shifted), Some(cell(a1)));
+        }
+
+        let moved = StructuralOperation::MoveRange {
+            from: range("A1:A2"),
+            to: cell("B1"),
+        };
+        assert_eq!(moved.inverse(), None);
+    }
+
+    #[test]
+    fn test_damage_starts_at_the_edit_point() {
+        let last = cell("Z100");
+        let insert = StructuralOperation::InsertRows {
+            before_row: 9,
+            count: 5,
+        };
+        assert_eq!(insert.damaged_range(&last), range("A10:Z100"));
+        let delete = StructuralOperation::DeleteColumns {
+            start_col: 2,
+            count: 1,
+        };
+        assert_eq!(delete.damaged_range(&last), range("C1:Z100"));
+        let moved = StructuralOperation::MoveRange {
+            from: range("C3:D4"),
+            to: cell("A8"),
+        };
+        assert_eq!(moved.damaged_range(&last), range("A3:D9"));
+    }
+}
@@ -0,0 +1,89 @@
+//! Writing references in A1 or R1C1 notation.
+//!
+//! The numbers of a [`ReferenceType`] are always the cell's own column and
+//! row, as [`ReferenceParser`](super::ReferenceParser) reads them; R1C1
+//! writes the relative parts as offsets from the cell holding the formula.
+
+use super::{Reference, ReferenceType};
+use crate::types::CellAddress;
+use crate::workbook::sheet_name::quote_sheet_name;
+
+/// How formulas write cell references
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReferenceStyle {
+    /// Column letters and row numbers, `$` marking the absolute parts
+    /// (e.g. `$B3`)
+    #[default]
+    A1,
+    /// Row and column numbers, bracketed offsets for the relative parts
+    /// (e.g. `R[2]C2`)
+    R1C1,
+}
+
+impl Reference {
+    /// The reference written in `style` for a formula in `origin`
+    pub fn format(&self, style: ReferenceStyle, origin: &CellAddress) -> String {
+        let cell = |col: i64, row: i64, absolute_col, absolute_row| {
+            let address = CellAddress::new(col.max(0) as u32, row.max(0) as u32);
+            format_cell(&address, absolute_col, absolute_row, style, origin)
+        };
+        match &self.ref_type {
+            ReferenceType::Relative(col, row) => cell(*col as i64, *row as i64, false, false),
+            ReferenceType::Absolute(col, row) => cell(*col as i64, *row as i64, true, true),
+            ReferenceType::MixedCol(col, row) => cell(*col as i64, *row as i64, true, false),
+            ReferenceType::MixedRow(col, row) => cell(*col as i64, *row as i64, false, true),
+            ReferenceType::Range(start, end) => format!(
+                "{}:{}",
+                start.format(style, origin),
+                end.format(style, origin)
+            ),
+            ReferenceType::Sheet(sheet, inner) => {
+                format!(
+                    "{}!{}",
+                    quote_sheet_name(sheet),
+                    inner.format(style, origin)
+                )
+            }
+            ReferenceType::External(book, inner) => {
+                format!("[{}]{}", book, inner.format(style, origin))
+            }
+        }
+    }
+}
+
+/// A single cell written in `style` for a formula in `origin`
+pub fn format_cell(
+    address: &CellAddress,
+    absolute_col: bool,
+    absolute_row: bool,
+    style: ReferenceStyle,
+    origin: &CellAddress,
+) -> String {
+    match style {
+        ReferenceStyle::A1 => format!(
+            "{}{}{}{}",
+            if absolute_col { "$" } else { "" },
+            CellAddress::column_number_to_label(address.col),
+            if absolute_row { "$" } else { "" },
+            address.row + 1
+        ),
+        ReferenceStyle::R1C1 => format!(
+            "R{}C{}",
+            r1c1_part(address.row, origin.row, absolute_row),
+            r1c1_part(address.col, origin.col, absolute_col)
+        ),
+    }
+}
+
+/// The number after `R` or `C`: the 1-based index when absolute, else the
+/// bracketed offset, nothing for the origin's own row or column
+fn r1c1_part(index: u32, origin: u32, absolute: bool) -> String {
+    let offset = index as i64 - origin as i64;
+    if absolute {
+        (index + 1).to_string()
+    } else if offset == 0 {
+        String::new()
+    } else {
+        format!("[{}]", offset)
+    }
+}
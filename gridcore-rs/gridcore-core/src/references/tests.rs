@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod references_integration_tests {
     use crate::references::{
-        Reference, ReferenceType, StructuralOperation, adjuster::ReferenceAdjuster,
+        Reference, ReferenceStyle, ReferenceType, StructuralOperation, adjuster::ReferenceAdjuster,
         detector::ReferenceDetector, parser::ReferenceParser, tracker::ReferenceTracker,
     };
     use crate::types::CellAddress;
@@ -220,4 +220,59 @@ mod references_integration_tests {
         assert!(b1_pos < c1_pos);
         assert!(c1_pos < d1_pos);
     }
+
+    #[test]
+    fn test_references_round_trip_between_a1_and_r1c1() {
+        let parser = ReferenceParser::new();
+        let origin = CellAddress::new(2, 4); // C5
+        for (a1, r1c1) in [
+            ("C5", "RC"),
+            ("A1", "R[-4]C[-2]"),
+            ("E9", "R[4]C[2]"),
+            ("$B$2", "R2C2"),
+            ("$A7", "R[2]C1"),
+            ("D$1", "R1C[1]"),
+            ("B3:$D$10", "R[-2]C[-1]:R10C4"),
+            ("'My Data'!C$5", "'My Data'!R5C"),
+        ] {
+            let from_a1 = parser.parse_formula(&format!("={a1}"));
+            assert_eq!(from_a1.len(), 1, "{a1}");
+            assert_eq!(from_a1[0].format(ReferenceStyle::R1C1, &origin), r1c1);
+
+            let from_r1c1 = parser.parse_formula_r1c1(&format!("={r1c1}"), &origin);
+            assert_eq!(from_r1c1.len(), 1, "{r1c1}");
+            assert_eq!(from_r1c1[0].format(ReferenceStyle::A1, &origin), a1);
+            assert_eq!(cells(&from_r1c1[0]), cells(&from_a1[0]), "{r1c1}");
+        }
+    }
+
+    /// The cells a reference names, whichever notation it was written in
+    fn cells(reference: &Reference) -> Vec<ReferenceType> {
+        match &reference.ref_type {
+            ReferenceType::Range(start, end) => [cells(start), cells(end)].concat(),
+            ReferenceType::Sheet(_, inner) | ReferenceType::External(_, inner) => cells(inner),
+            cell => vec![cell.clone()],
+        }
+    }
+
+    #[test]
+    fn test_parse_r1c1_formula() {
+        let parser = ReferenceParser::new();
+        let origin = CellAddress::new(0, 1); // A2
+        let refs = parser.parse_formula_r1c1("=SUM(R[-1]C:R5C[2])+RC[1]*ROUND(R1C1,2)", &origin);
+        let texts: Vec<&str> = refs.iter().map(|r| r.text.as_str()).collect();
+        assert_eq!(texts, ["R[-1]C:R5C[2]", "RC[1]", "R1C1"]);
+        assert_eq!(refs[1].ref_type, ReferenceType::Relative(1, 1));
+
+        // Above the first row or left of the first column
+        assert!(parser.parse_r1c1_reference("R[-2]C", &origin).is_none());
+        assert!(parser.parse_r1c1_reference("RC[-1]", &origin).is_none());
+        assert!(parser.parse_r1c1_reference("R0C1", &origin).is_none());
+        // Names and calls that start like references
+        assert!(
+            parser
+                .parse_formula_r1c1("=RC(1)+ARC+RC_2", &origin)
+                .is_empty()
+        );
+    }
 }
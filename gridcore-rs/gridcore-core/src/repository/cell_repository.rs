@@ -1,6 +1,10 @@
+use super::error_index::{ErrorIndex, SheetHealth};
+use super::lookup_index::{LookupIndex, LookupKey};
+use super::spill_index::{SpillIndex, SpillOutcome, SpillRange};
 use crate::Result;
 use crate::domain::Cell;
-use crate::types::CellAddress;
+use crate::memory::{MemoryFootprint, cell_heap_bytes, hash_table_bytes};
+use crate::types::{CellAddress, CellArray, CellRange, CellValue};
 use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
@@ -14,6 +18,14 @@ use metrics::counter;
 pub struct CellRepository {
     /// HashMap storing cells by their string address (e.g., "A1", "B2")
     cells: HashMap<String, Cell>,
+    /// Addresses of cells holding errors, maintained alongside `cells`
+    errors: ErrorIndex,
+    /// Per-column indexes for exact-match lookups, built on demand
+    lookup: LookupIndex,
+    /// Cells filled by array formulas and the anchors they belong to
+    spills: SpillIndex,
+    /// Bumped on every change, so derived data can tell when it is stale
+    revision: u64,
 }
 
 impl CellRepository {
@@ -21,6 +33,10 @@ impl CellRepository {
     pub fn new() -> Self {
         CellRepository {
             cells: HashMap::new(),
+            errors: ErrorIndex::new(),
+            lookup: LookupIndex::new(),
+            spills: SpillIndex::new(),
+            revision: 0,
         }
     }
 
@@ -32,30 +48,240 @@ impl CellRepository {
         self.cells.get(&address.to_string())
     }
 
-    /// Get a mutable reference to a cell
+    /// Get a mutable reference to a cell.
+    ///
+    /// Changes made through the reference are not seen by the error index,
+    /// and drop the lookup index of the cell's column; use [`Self::set`] to
+    /// replace a cell's value.
     pub fn get_mut(&mut self, address: &CellAddress) -> Option<&mut Cell> {
         #[cfg(feature = "perf")]
         counter!(CELL_READS).increment(1);
 
+        self.lookup.forget_column(address.col);
+        self.revision += 1;
         self.cells.get_mut(&address.to_string())
     }
 
-    /// Set a cell at the given address
+    /// Set a cell at the given address. A cell set over a spilled value
+    /// takes its place; the anchor's spill is left for the caller to settle.
     pub fn set(&mut self, address: &CellAddress, cell: Cell) {
         #[cfg(feature = "perf")]
         counter!(CELL_WRITES).increment(1);
 
-        self.cells.insert(address.to_string(), cell);
+        self.spills.release(address);
+        self.errors.update(address, Some(&cell));
+        self.revision += 1;
+        let old = self.cells.insert(address.to_string(), cell);
+        self.lookup
+            .update(address, old.as_ref(), self.cells.get(&address.to_string()));
     }
 
-    /// Delete a cell at the given address
+    /// Delete a cell at the given address, along with the values it
+    /// spilled
     pub fn delete(&mut self, address: &CellAddress) -> Option<Cell> {
-        self.cells.remove(&address.to_string())
+        for spilled in self.spills.remove(address) {
+            self.remove(&spilled);
+        }
+        self.spills.release(address);
+        self.remove(address)
+    }
+
+    fn remove(&mut self, address: &CellAddress) -> Option<Cell> {
+        self.errors.update(address, None);
+        self.revision += 1;
+        let old = self.cells.remove(&address.to_string());
+        self.lookup.update(address, old.as_ref(), None);
+        old
     }
 
     /// Clear all cells from the repository
     pub fn clear(&mut self) {
         self.cells.clear();
+        self.errors.clear();
+        self.lookup.clear();
+        self.spills.clear();
+        self.revision += 1;
+    }
+
+    /// Spill `array`, the result of the formula at `anchor`, over the cells
+    /// right of and below it, or remove what the anchor spilled before when
+    /// `None`. Cells holding data or spilled by another anchor block the
+    /// array, which then fills nothing and is recorded as blocked so a
+    /// later edit can retry it.
+    pub fn spill(&mut self, anchor: &CellAddress, array: Option<&CellArray>) -> SpillOutcome {
+        let previous: Vec<CellAddress> = self.spills.remove(anchor);
+        let range = array.filter(|array| !array.is_scalar()).map(|array| {
+            CellRange::new(
+                *anchor,
+                CellAddress::new(
+                    anchor.col + array.cols() as u32 - 1,
+                    anchor.row + array.rows() as u32 - 1,
+                ),
+            )
+        });
+
+        // Values the anchor spilled before make way for the new ones
+        let own: HashSet<CellAddress> = previous.iter().copied().collect();
+        let blocked_by: Vec<CellAddress> = range
+            .iter()
+            .flat_map(|range| range.cells())
+            .filter(|cell| cell != anchor && !own.contains(cell))
+            .filter(|cell| {
+                self.spills.owner(cell).is_some()
+                    || self
+                        .cells
+                        .get(&cell.to_string())
+                        .is_some_and(|existing| !existing.is_empty())
+            })
+            .collect();
+        let filled: Vec<(CellAddress, CellValue)> = match (range, array) {
+            (Some(range), Some(array)) if blocked_by.is_empty() => range
+                .cells()
+                .filter(|cell| cell != anchor)
+                .map(|cell| {
+                    let value = array.get(
+                        (cell.row - anchor.row) as usize,
+                        (cell.col - anchor.col) as usize,
+                    );
+                    (cell, value.cloned().unwrap_or_default())
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut changed = Vec::new();
+        for cell in previous {
+            if !filled.iter().any(|(address, _)| *address == cell) {
+                changed.push((cell, self.remove(&cell)));
+            }
+        }
+        for (cell, value) in &filled {
+            let old = self.cells.get(&cell.to_string()).cloned();
+            if old.as_ref().map(Cell::get_computed_value).as_ref() != Some(value) {
+                self.set(cell, Cell::spilled(value.clone()));
+                changed.push((*cell, old));
+            }
+        }
+        if let Some(range) = range {
+            let spill = SpillRange {
+                anchor: *anchor,
+                range,
+                blocked: !blocked_by.is_empty(),
+            };
+            self.spills
+                .insert(spill, filled.into_iter().map(|(cell, _)| cell));
+        }
+        changed.sort_by_key(|(cell, _)| (cell.row, cell.col));
+        SpillOutcome {
+            blocked_by,
+            changed,
+        }
+    }
+
+    /// Remove every spilled value, e.g. before cells move, returning the
+    /// anchors so their formulas can spill again
+    pub fn clear_spills(&mut self) -> Vec<CellAddress> {
+        let anchors: Vec<CellAddress> = self
+            .spills
+            .ranges()
+            .iter()
+            .map(|spill| spill.anchor)
+            .collect();
+        for anchor in &anchors {
+            for spilled in self.spills.remove(anchor) {
+                self.remove(&spilled);
+            }
+        }
+        anchors
+    }
+
+    /// Every spill range, in reading order of the anchors
+    pub fn spill_ranges(&self) -> Vec<SpillRange> {
+        self.spills.ranges()
+    }
+
+    /// The spill range `address` is the anchor of or was filled by
+    pub fn spill_range_at(&self, address: &CellAddress) -> Option<SpillRange> {
+        let anchor = self.spills.owner(address).unwrap_or(*address);
+        self.spills.range(&anchor).copied()
+    }
+
+    /// Anchors other than `address` whose arrays cover it, filled or
+    /// blocked
+    pub fn spills_over(&self, address: &CellAddress) -> Vec<CellAddress> {
+        self.spills.covering(address)
+    }
+
+    /// Counter that changes whenever a cell may have changed, including
+    /// through [`Self::get_mut`]
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Error counts and the first error cell, without scanning the cells
+    pub fn health(&self) -> SheetHealth {
+        self.errors.health()
+    }
+
+    /// Error cells in reading order
+    pub fn error_addresses(&self) -> Vec<CellAddress> {
+        self.errors.errors().collect()
+    }
+
+    /// Turn the lookup index on or off
+    pub fn set_lookup_index(&mut self, enabled: bool) {
+        self.lookup.set_enabled(enabled);
+    }
+
+    /// Approximate memory held by the lookup index, in bytes
+    pub fn lookup_index_bytes(&self) -> usize {
+        self.lookup.memory_bytes()
+    }
+
+    /// Estimated bytes of the lookup index, spare capacity included
+    pub fn lookup_footprint_bytes(&self) -> usize {
+        self.lookup.footprint_bytes()
+    }
+
+    /// First row of column `col` between `start_row` and `end_row` whose
+    /// value matches `key`, answered from the lookup index. `None` when the
+    /// index is off or the range is too short to be worth indexing, in
+    /// which case the caller scans.
+    pub fn indexed_first_match(
+        &mut self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        key: &LookupKey,
+    ) -> Option<Option<u32>> {
+        if !self.lookup.covers(start_row, end_row) {
+            return None;
+        }
+        let cells = &self.cells;
+        Some(
+            self.lookup
+                .first_match(col, start_row, end_row, key, || column_cells(cells, col)),
+        )
+    }
+
+    /// Cells of column `col` between `start_row` and `end_row` whose value
+    /// matches `key`, answered from the lookup index; `None` as for
+    /// [`Self::indexed_first_match`]
+    pub fn indexed_match_count(
+        &mut self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        key: &LookupKey,
+    ) -> Option<usize> {
+        if !self.lookup.covers(start_row, end_row) {
+            return None;
+        }
+        let cells = &self.cells;
+        Some(
+            self.lookup
+                .count_matches(col, start_row, end_row, key, || column_cells(cells, col)),
+        )
     }
 
     /// Get all cells as a vector of (address, cell) pairs
@@ -123,8 +349,10 @@ impl CellRepository {
             .collect()
     }
 
-    /// Shift rows by the specified amount
+    /// Shift rows by the specified amount. Spilled values are removed
+    /// rather than moved; their formulas spill again when recalculated.
     pub fn shift_rows(&mut self, start_row: u32, shift_amount: i32) -> Result<Vec<CellAddress>> {
+        self.clear_spills();
         let cell_count = self.cells.len();
         let mut affected = Vec::with_capacity(cell_count);
         let mut updates = Vec::with_capacity(cell_count);
@@ -144,17 +372,22 @@ impl CellRepository {
             }
         }
 
-        // Apply updates
-        for (old_addr, new_addr, cell) in updates {
-            self.cells.remove(&old_addr.to_string());
-            self.cells.insert(new_addr.to_string(), cell);
+        // Apply updates, removing every old position before inserting so
+        // shifted cells don't clobber each other in the error index
+        for (old_addr, _, _) in &updates {
+            self.delete(old_addr);
+        }
+        for (_, new_addr, cell) in updates {
+            self.set(&new_addr, cell);
         }
 
         Ok(affected)
     }
 
-    /// Shift columns by the specified amount
+    /// Shift columns by the specified amount, removing spilled values as
+    /// [`Self::shift_rows`] does
     pub fn shift_columns(&mut self, start_col: u32, shift_amount: i32) -> Result<Vec<CellAddress>> {
+        self.clear_spills();
         let cell_count = self.cells.len();
         let mut affected = Vec::with_capacity(cell_count);
         let mut updates = Vec::with_capacity(cell_count);
@@ -174,20 +407,61 @@ impl CellRepository {
             }
         }
 
-        // Apply updates
-        for (old_addr, new_addr, cell) in updates {
-            self.cells.remove(&old_addr.to_string());
-            self.cells.insert(new_addr.to_string(), cell);
+        // Apply updates, removing every old position before inserting so
+        // shifted cells don't clobber each other in the error index
+        for (old_addr, _, _) in &updates {
+            self.delete(old_addr);
+        }
+        for (_, new_addr, cell) in updates {
+            self.set(&new_addr, cell);
         }
 
         Ok(affected)
     }
 }
 
+/// The cells and their error index; [`MemoryFootprint::compact`] also
+/// drops the lookup indexes of emptied columns and shrinks the rest
+impl MemoryFootprint for CellRepository {
+    fn footprint_bytes(&self) -> usize {
+        hash_table_bytes::<String, Cell>(self.cells.capacity())
+            + self
+                .cells
+                .iter()
+                .map(|(key, cell)| key.capacity() + cell_heap_bytes(cell))
+                .sum::<usize>()
+            + self.errors.footprint_bytes()
+    }
+
+    fn compact(&mut self) {
+        self.cells.shrink_to_fit();
+        let occupied: HashSet<u32> = self
+            .cells
+            .keys()
+            .filter_map(|key| CellAddress::from_str(key).ok())
+            .map(|address| address.col)
+            .collect();
+        self.lookup.retain_columns(|col| occupied.contains(&col));
+        self.lookup.compact();
+    }
+}
+
+/// Rows and cells of column `col`
+fn column_cells(cells: &HashMap<String, Cell>, col: u32) -> Vec<(u32, &Cell)> {
+    cells
+        .iter()
+        .filter_map(|(addr_str, cell)| {
+            CellAddress::from_str(addr_str)
+                .ok()
+                .filter(|address| address.col == col)
+                .map(|address| (address.row, cell))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::CellValue;
 
     #[test]
     fn test_repository_basic_operations() {
@@ -256,4 +530,44 @@ mod tests {
             assert!(all_cells.iter().any(|(a, c)| a == &addr && c == &cell));
         }
     }
+
+    #[test]
+    fn test_indexed_lookups() {
+        let mut repo = CellRepository::new();
+        let rows = super::super::LOOKUP_INDEX_MIN_ROWS;
+        for row in 0..rows {
+            let cell = Cell::new(CellValue::Number((row % 10) as f64));
+            repo.set(&CellAddress::new(1, row), cell);
+        }
+        let three = LookupKey::of(&CellValue::Number(3.0)).unwrap();
+
+        // Short ranges are left to a scan
+        assert_eq!(repo.indexed_first_match(1, 0, 10, &three), None);
+        assert_eq!(
+            repo.indexed_first_match(1, 0, rows - 1, &three),
+            Some(Some(3))
+        );
+        assert_eq!(
+            repo.indexed_match_count(1, 0, rows - 1, &three),
+            Some((0..rows).filter(|row| row % 10 == 3).count())
+        );
+        assert!(repo.lookup_index_bytes() > 0);
+
+        repo.delete(&CellAddress::new(1, 3));
+        assert_eq!(
+            repo.indexed_first_match(1, 0, rows - 1, &three),
+            Some(Some(13))
+        );
+        if let Some(cell) = repo.get_mut(&CellAddress::new(1, 0)) {
+            cell.set_computed_value(CellValue::Number(3.0));
+        }
+        assert_eq!(
+            repo.indexed_first_match(1, 0, rows - 1, &three),
+            Some(Some(0))
+        );
+
+        repo.set_lookup_index(false);
+        assert_eq!(repo.indexed_first_match(1, 0, rows - 1, &three), None);
+        assert_eq!(repo.lookup_index_bytes(), 0);
+    }
 }
@@ -0,0 +1,154 @@
+use crate::domain::Cell;
+use crate::types::{CellAddress, CellValue};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What a populated cell holds, for colouring an overview of the sheet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CellKind {
+    Number,
+    Text,
+    Formula,
+    Error,
+}
+
+impl CellKind {
+    /// Kind of a cell, or `None` when it is empty. Formulas win over the
+    /// type of their result, errors over both.
+    pub fn of(cell: &Cell) -> Option<CellKind> {
+        match cell.get_display_value() {
+            CellValue::Error(_) => Some(CellKind::Error),
+            _ if cell.has_error() => Some(CellKind::Error),
+            _ if cell.has_formula() => Some(CellKind::Formula),
+            CellValue::Empty => None,
+            CellValue::Number(_) => Some(CellKind::Number),
+            _ => Some(CellKind::Text),
+        }
+    }
+}
+
+/// Populated cells in one `block_size` × `block_size` block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DensityBlock {
+    /// Block row, i.e. the first sheet row of the block divided by the block size
+    pub row: u32,
+    pub col: u32,
+    pub count: u32,
+    /// The most common kind in the block; ties go to the later kind in
+    /// [`CellKind`] order so errors and formulas stay visible
+    pub kind: CellKind,
+}
+
+/// Coarse overview of where a sheet holds data
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DensityMap {
+    pub block_size: u32,
+    /// Non-empty blocks in reading order
+    pub blocks: Vec<DensityBlock>,
+    /// Last populated column and row, `None` for an empty sheet
+    pub extent: Option<CellAddress>,
+}
+
+impl DensityMap {
+    /// Count the populated cells per block. A block size of 0 is treated as 1.
+    pub fn build<'a>(
+        block_size: u32,
+        cells: impl IntoIterator<Item = (CellAddress, &'a Cell)>,
+    ) -> Self {
+        let block_size = block_size.max(1);
+        let mut counts: BTreeMap<(u32, u32), [u32; 4]> = BTreeMap::new();
+        let mut extent: Option<CellAddress> = None;
+
+        for (address, cell) in cells {
+            let Some(kind) = CellKind::of(cell) else {
+                continue;
+            };
+            let key = (address.row / block_size, address.col / block_size);
+            counts.entry(key).or_default()[kind as usize] += 1;
+            extent = Some(match extent {
+                Some(e) => CellAddress::new(e.col.max(address.col), e.row.max(address.row)),
+                None => address,
+            });
+        }
+
+        const KINDS: [CellKind; 4] = [
+            CellKind::Number,
+            CellKind::Text,
+            CellKind::Formula,
+            CellKind::Error,
+        ];
+        let blocks = counts
+            .into_iter()
+            .map(|((row, col), by_kind)| {
+                let (kind, _) = KINDS
+                    .iter()
+                    .zip(by_kind)
+                    .max_by_key(|(_, count)| *count)
+                    .expect("four kinds");
+                DensityBlock {
+                    row,
+                    col,
+                    count: by_kind.iter().sum(),
+                    kind: *kind,
+                }
+            })
+            .collect();
+
+        DensityMap {
+            block_size,
+            blocks,
+            extent,
+        }
+    }
+
+    /// Fullest block, for scaling colours
+    pub fn max_count(&self) -> u32 {
+        self.blocks
+            .iter()
+            .map(|block| block.count)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_cells_are_counted_per_block() {
+        let number = Cell::new(CellValue::Number(1.0));
+        let text = Cell::new(CellValue::string_from_str("a"));
+        let empty = Cell::new(CellValue::Empty);
+        let formula = Cell::with_formula(CellValue::Number(2.0), "1+1".to_string());
+        let cells = [
+            (CellAddress::new(0, 0), &number),
+            (CellAddress::new(3, 3), &text),
+            (CellAddress::new(1, 2), &text),
+            (CellAddress::new(2, 1), &empty),
+            (CellAddress::new(9000, 120_000), &formula),
+        ];
+
+        let map = DensityMap::build(4, cells);
+        assert_eq!(map.extent, Some(CellAddress::new(9000, 120_000)));
+        assert_eq!(map.blocks.len(), 2);
+        assert_eq!(
+            map.blocks[0],
+            DensityBlock {
+                row: 0,
+                col: 0,
+                count: 3,
+                kind: CellKind::Text,
+            }
+        );
+        assert_eq!(map.blocks[1].row, 30_000);
+        assert_eq!(map.blocks[1].col, 2250);
+        assert_eq!(map.blocks[1].kind, CellKind::Formula);
+        assert_eq!(map.max_count(), 3);
+
+        let empty_map = DensityMap::build(4, [(CellAddress::new(0, 0), &empty)]);
+        assert!(empty_map.blocks.is_empty());
+        assert_eq!(empty_map.extent, None);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::domain::Cell;
+use crate::types::{CellAddress, CellValue, ErrorType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Summary of the problems in one sheet
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SheetHealth {
+    /// Cells whose computed value is an error, circular references included
+    pub error_cells: usize,
+    /// Cells whose error is a circular reference
+    pub circular_cells: usize,
+    /// First error cell in reading order (row by row, left to right)
+    pub first_error: Option<CellAddress>,
+}
+
+impl SheetHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.error_cells == 0
+    }
+}
+
+/// Error cells of a repository, kept up to date on every write so the
+/// summary never needs a scan of the sheet
+#[derive(Debug, Clone, Default)]
+pub struct ErrorIndex {
+    /// Error cells keyed by (row, col) so iteration is in reading order
+    errors: BTreeSet<(u32, u32)>,
+    circular: BTreeSet<(u32, u32)>,
+}
+
+impl ErrorIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the cell now stored at `address`, or its removal
+    pub fn update(&mut self, address: &CellAddress, cell: Option<&Cell>) {
+        let key = (address.row, address.col);
+        match cell.map(|cell| cell.get_display_value()) {
+            // Pending external data is not a problem with the sheet
+            Some(CellValue::Error(error)) if matches!(**error, ErrorType::GettingData) => {
+                self.errors.remove(&key);
+                self.circular.remove(&key);
+            }
+            Some(CellValue::Error(error)) => {
+                self.errors.insert(key);
+                if matches!(**error, ErrorType::CircularDependency { .. }) {
+                    self.circular.insert(key);
+                } else {
+                    self.circular.remove(&key);
+                }
+            }
+            _ => {
+                self.errors.remove(&key);
+                self.circular.remove(&key);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.errors.clear();
+        self.circular.clear();
+    }
+
+    /// Error cells in reading order
+    pub fn errors(&self) -> impl Iterator<Item = CellAddress> + '_ {
+        self.errors
+            .iter()
+            .map(|&(row, col)| CellAddress::new(col, row))
+    }
+
+    /// Estimated bytes of both sets; a B-tree frees its nodes as it
+    /// empties, so there is nothing to compact
+    pub fn footprint_bytes(&self) -> usize {
+        (self.errors.len() + self.circular.len()) * 2 * std::mem::size_of::<(u32, u32)>()
+    }
+
+    pub fn health(&self) -> SheetHealth {
+        SheetHealth {
+            error_cells: self.errors.len(),
+            circular_cells: self.circular.len(),
+            first_error: self.errors().next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_tracks_replacements() {
+        let mut index = ErrorIndex::new();
+        let b2 = CellAddress::new(1, 1);
+        let c1 = CellAddress::new(2, 0);
+
+        index.update(
+            &b2,
+            Some(&Cell::new(CellValue::from_error(ErrorType::DivideByZero))),
+        );
+        index.update(
+            &c1,
+            Some(&Cell::new(CellValue::from_error(
+                ErrorType::CircularDependency { cells: vec![] },
+            ))),
+        );
+        let health = index.health();
+        assert_eq!(health.error_cells, 2);
+        assert_eq!(health.circular_cells, 1);
+        // C1 is on an earlier row than B2
+        assert_eq!(health.first_error, Some(c1));
+
+        index.update(&c1, Some(&Cell::new(CellValue::Number(1.0))));
+        index.update(&b2, None);
+        assert!(index.health().is_healthy());
+        assert_eq!(index.health().circular_cells, 0);
+    }
+}
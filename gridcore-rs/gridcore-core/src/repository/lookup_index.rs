@@ -0,0 +1,368 @@
+use crate::domain::Cell;
+use crate::memory::{MemoryFootprint, hash_table_bytes};
+use crate::types::{CellAddress, CellValue};
+use rustc_hash::FxHashMap;
+
+#[cfg(feature = "perf")]
+use crate::perf::LOOKUP_INDEX_BYTES;
+#[cfg(feature = "perf")]
+use metrics::gauge;
+
+/// Rows a lookup must cover before its column is indexed; shorter ranges
+/// are scanned
+pub const LOOKUP_INDEX_MIN_ROWS: u32 = 512;
+
+/// Changes to an indexed column, beyond its number of entries, after which
+/// the index is dropped and rebuilt by the next lookup instead of updated
+const CHURN_FLOOR: usize = 1_024;
+
+/// What exact-match lookups compare: numbers by value, text ignoring case
+/// and booleans as they are. Lookups and their index both compare through
+/// this key, so they agree on what matches.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LookupKey {
+    /// Bits of the number, with -0 taken as 0
+    Number(u64),
+    /// Lowercased text
+    Text(String),
+    Boolean(bool),
+}
+
+impl LookupKey {
+    /// Key of `value`, or `None` for empty cells, errors, arrays and
+    /// sparklines, which no exact-match lookup finds
+    pub fn of(value: &CellValue) -> Option<Self> {
+        match value {
+            CellValue::Number(n) if *n == 0.0 => Some(Self::Number(0f64.to_bits())),
+            CellValue::Number(n) => Some(Self::Number(n.to_bits())),
+            CellValue::String(text) => Some(Self::Text(text.to_lowercase())),
+            CellValue::Boolean(b) => Some(Self::Boolean(*b)),
+            CellValue::Empty
+            | CellValue::Error(_)
+            | CellValue::Array(_)
+            | CellValue::Sparkline(_) => None,
+        }
+    }
+
+    fn heap_bytes(&self) -> usize {
+        match self {
+            Self::Text(text) => text.capacity(),
+            Self::Number(_) | Self::Boolean(_) => 0,
+        }
+    }
+}
+
+/// Rows of one column grouped by value
+#[derive(Debug, Clone, Default)]
+struct ColumnIndex {
+    /// Rows holding each key, in ascending order
+    rows: FxHashMap<LookupKey, Vec<u32>>,
+    /// Rows indexed
+    entries: usize,
+    /// Changes applied since the index was built
+    churn: usize,
+}
+
+impl ColumnIndex {
+    fn insert(&mut self, key: LookupKey, row: u32) {
+        let rows = self.rows.entry(key).or_default();
+        if let Err(at) = rows.binary_search(&row) {
+            rows.insert(at, row);
+            self.entries += 1;
+        }
+    }
+
+    fn remove(&mut self, key: &LookupKey, row: u32) {
+        let Some(rows) = self.rows.get_mut(key) else {
+            return;
+        };
+        if let Ok(at) = rows.binary_search(&row) {
+            rows.remove(at);
+            self.entries -= 1;
+        }
+        if rows.is_empty() {
+            self.rows.remove(key);
+        }
+    }
+
+    /// Rows holding `key` between `start_row` and `end_row` (inclusive)
+    fn rows_between(&self, key: &LookupKey, start_row: u32, end_row: u32) -> &[u32] {
+        let Some(rows) = self.rows.get(key) else {
+            return &[];
+        };
+        let from = rows.partition_point(|&row| row < start_row);
+        let to = rows.partition_point(|&row| row <= end_row);
+        &rows[from..to.max(from)]
+    }
+
+    /// Approximate heap use
+    fn bytes(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|(key, rows)| {
+                std::mem::size_of::<(LookupKey, Vec<u32>)>()
+                    + key.heap_bytes()
+                    + rows.capacity() * std::mem::size_of::<u32>()
+            })
+            .sum()
+    }
+}
+
+/// Sorted per-column indexes for exact-match lookups.
+///
+/// A column is indexed by the first lookup over at least
+/// [`LOOKUP_INDEX_MIN_ROWS`] of its rows, then kept up to date as its cells
+/// are written. A column that changes a lot is dropped and indexed again
+/// when next looked up.
+#[derive(Debug, Clone)]
+pub struct LookupIndex {
+    enabled: bool,
+    columns: FxHashMap<u32, ColumnIndex>,
+    bytes: usize,
+}
+
+impl Default for LookupIndex {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            columns: FxHashMap::default(),
+            bytes: 0,
+        }
+    }
+}
+
+impl LookupIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn indexing on or off; turning it off frees every index
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.clear();
+        }
+    }
+
+    /// Record that the cell at `address` changed from `old` to `new`
+    pub fn update(&mut self, address: &CellAddress, old: Option<&Cell>, new: Option<&Cell>) {
+        let Some(column) = self.columns.get_mut(&address.col) else {
+            return;
+        };
+        let old = old.and_then(|cell| LookupKey::of(cell.get_display_value()));
+        let new = new.and_then(|cell| LookupKey::of(cell.get_display_value()));
+        if old == new {
+            return;
+        }
+        if let Some(old) = &old {
+            column.remove(old, address.row);
+        }
+        if let Some(new) = new {
+            column.insert(new, address.row);
+        }
+        column.churn += 1;
+        if column.churn > column.entries.max(CHURN_FLOOR) {
+            self.forget_column(address.col);
+        }
+    }
+
+    /// Drop the index of `col`, e.g. after a change it cannot follow
+    pub fn forget_column(&mut self, col: u32) {
+        if let Some(column) = self.columns.remove(&col) {
+            self.track_bytes(-(column.bytes() as isize));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.columns.clear();
+        self.track_bytes(-(self.bytes as isize));
+    }
+
+    /// Drop the indexes of columns `keep` rejects, e.g. emptied ones
+    pub fn retain_columns(&mut self, mut keep: impl FnMut(u32) -> bool) {
+        let dropped: Vec<u32> = self
+            .columns
+            .keys()
+            .copied()
+            .filter(|&col| !keep(col))
+            .collect();
+        for col in dropped {
+            self.forget_column(col);
+        }
+    }
+
+    /// Approximate memory held by the indexes, in bytes
+    pub fn memory_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Whether a lookup between these rows should use the index
+    pub fn covers(&self, start_row: u32, end_row: u32) -> bool {
+        self.enabled && end_row.saturating_sub(start_row) + 1 >= LOOKUP_INDEX_MIN_ROWS
+    }
+
+    /// Index of `col`, built from `cells` if there is none yet
+    fn column<'a>(
+        &mut self,
+        col: u32,
+        cells: impl FnOnce() -> Vec<(u32, &'a Cell)>,
+    ) -> &ColumnIndex {
+        if !self.columns.contains_key(&col) {
+            let mut column = ColumnIndex::default();
+            for (row, cell) in cells() {
+                if let Some(key) = LookupKey::of(cell.get_display_value()) {
+                    column.insert(key, row);
+                }
+            }
+            self.track_bytes(column.bytes() as isize);
+            self.columns.insert(col, column);
+        }
+        &self.columns[&col]
+    }
+
+    /// First row of `col` between `start_row` and `end_row` holding `key`,
+    /// indexing the column from `cells` first if needed
+    pub fn first_match<'a>(
+        &mut self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        key: &LookupKey,
+        cells: impl FnOnce() -> Vec<(u32, &'a Cell)>,
+    ) -> Option<u32> {
+        self.column(col, cells)
+            .rows_between(key, start_row, end_row)
+            .first()
+            .copied()
+    }
+
+    /// Rows of `col` between `start_row` and `end_row` holding `key`
+    pub fn count_matches<'a>(
+        &mut self,
+        col: u32,
+        start_row: u32,
+        end_row: u32,
+        key: &LookupKey,
+        cells: impl FnOnce() -> Vec<(u32, &'a Cell)>,
+    ) -> usize {
+        self.column(col, cells)
+            .rows_between(key, start_row, end_row)
+            .len()
+    }
+
+    fn track_bytes(&mut self, delta: isize) {
+        self.bytes = self.bytes.saturating_add_signed(delta);
+        #[cfg(feature = "perf")]
+        {
+            if delta >= 0 {
+                gauge!(LOOKUP_INDEX_BYTES).increment(delta as f64);
+            } else {
+                gauge!(LOOKUP_INDEX_BYTES).decrement(-delta as f64);
+            }
+        }
+    }
+}
+
+impl MemoryFootprint for LookupIndex {
+    fn footprint_bytes(&self) -> usize {
+        self.bytes + hash_table_bytes::<u32, ColumnIndex>(self.columns.capacity())
+    }
+
+    fn compact(&mut self) {
+        for column in self.columns.values_mut() {
+            column.rows.values_mut().for_each(Vec::shrink_to_fit);
+            column.rows.shrink_to_fit();
+        }
+        self.columns.shrink_to_fit();
+        let bytes: usize = self.columns.values().map(ColumnIndex::bytes).sum();
+        self.track_bytes(bytes as isize - self.bytes as isize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(values: &[CellValue]) -> Vec<(u32, Cell)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(row, value)| (row as u32, Cell::new(value.clone())))
+            .collect()
+    }
+
+    fn refs(cells: &[(u32, Cell)]) -> Vec<(u32, &Cell)> {
+        cells.iter().map(|(row, cell)| (*row, cell)).collect()
+    }
+
+    #[test]
+    fn test_keys_ignore_case_and_negative_zero() {
+        assert_eq!(
+            LookupKey::of(&CellValue::string_from_str("Apple")),
+            LookupKey::of(&CellValue::string_from_str("aPPLE"))
+        );
+        assert_eq!(
+            LookupKey::of(&CellValue::Number(-0.0)),
+            LookupKey::of(&CellValue::Number(0.0))
+        );
+        assert_ne!(
+            LookupKey::of(&CellValue::Number(1.0)),
+            LookupKey::of(&CellValue::string_from_str("1"))
+        );
+        assert_eq!(LookupKey::of(&CellValue::Empty), None);
+    }
+
+    #[test]
+    fn test_index_follows_changes() {
+        let column = cells(&[
+            CellValue::string_from_str("b"),
+            CellValue::string_from_str("a"),
+            CellValue::Number(3.0),
+            CellValue::string_from_str("A"),
+        ]);
+        let mut index = LookupIndex::new();
+        let a = LookupKey::of(&CellValue::string_from_str("a")).unwrap();
+
+        assert_eq!(index.first_match(0, 0, 3, &a, || refs(&column)), Some(1));
+        assert_eq!(index.first_match(0, 2, 3, &a, || refs(&column)), Some(3));
+        assert_eq!(index.count_matches(0, 0, 3, &a, || refs(&column)), 2);
+        assert!(index.memory_bytes() > 0);
+
+        // Later lookups read the index, not the cells
+        let unused = Vec::new;
+        let row_1 = CellAddress::new(0, 1);
+        index.update(&row_1, Some(&column[1].1), None);
+        assert_eq!(index.first_match(0, 0, 3, &a, unused), Some(3));
+        index.update(&CellAddress::new(0, 0), None, Some(&column[1].1));
+        assert_eq!(index.first_match(0, 0, 3, &a, unused), Some(0));
+        assert_eq!(index.count_matches(0, 0, 3, &a, unused), 2);
+
+        // Columns without an index ignore updates
+        index.update(&CellAddress::new(1, 0), None, Some(&column[1].1));
+        assert_eq!(index.count_matches(1, 0, 3, &a, unused), 0);
+
+        index.set_enabled(false);
+        assert_eq!(index.memory_bytes(), 0);
+        assert!(!index.covers(0, 100_000));
+    }
+
+    #[test]
+    fn test_churn_drops_the_column() {
+        let column = cells(&[CellValue::Number(1.0)]);
+        let mut index = LookupIndex::new();
+        let one = LookupKey::of(&CellValue::Number(1.0)).unwrap();
+        index.first_match(0, 0, 0, &one, || refs(&column));
+
+        let cell = |n: usize| Cell::new(CellValue::Number(n as f64));
+        for n in 0..=CHURN_FLOOR {
+            index.update(&CellAddress::new(0, 0), Some(&cell(n)), Some(&cell(n + 1)));
+        }
+        assert_eq!(index.memory_bytes(), 0);
+        let rebuilt = cells(&[CellValue::Number(1.0), CellValue::Number(1.0)]);
+        assert_eq!(index.count_matches(0, 0, 1, &one, || refs(&rebuilt)), 2);
+    }
+}
@@ -1,3 +1,11 @@
 pub mod cell_repository;
+pub mod density;
+pub mod error_index;
+pub mod lookup_index;
+pub mod spill_index;
 
 pub use cell_repository::CellRepository;
+pub use density::{CellKind, DensityBlock, DensityMap};
+pub use error_index::{ErrorIndex, SheetHealth};
+pub use lookup_index::{LOOKUP_INDEX_MIN_ROWS, LookupIndex, LookupKey};
+pub use spill_index::{SpillIndex, SpillOutcome, SpillRange};
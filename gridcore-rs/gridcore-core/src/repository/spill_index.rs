@@ -0,0 +1,108 @@
+use crate::domain::Cell;
+use crate::types::{CellAddress, CellRange};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The area a formula's array result covers, from the formula's cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpillRange {
+    /// Cell holding the formula, the top-left of the range
+    pub anchor: CellAddress,
+    pub range: CellRange,
+    /// Whether cells holding data keep the array from spilling, leaving
+    /// the anchor #SPILL!
+    pub blocked: bool,
+}
+
+/// What spilling an anchor's array changed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpillOutcome {
+    /// Cells in the way of the array, in reading order
+    pub blocked_by: Vec<CellAddress>,
+    /// Spilled cells written or removed, with what they held before
+    pub changed: Vec<(CellAddress, Option<Cell>)>,
+}
+
+/// Which cells hold values spilled from which anchors, kept alongside the
+/// cells so spilled values can be told from typed ones
+#[derive(Debug, Clone, Default)]
+pub struct SpillIndex {
+    ranges: HashMap<CellAddress, SpillRange>,
+    /// Anchor of each spilled cell
+    owners: HashMap<CellAddress, CellAddress>,
+}
+
+impl SpillIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn range(&self, anchor: &CellAddress) -> Option<&SpillRange> {
+        self.ranges.get(anchor)
+    }
+
+    /// Anchor whose array fills `address`
+    pub fn owner(&self, address: &CellAddress) -> Option<CellAddress> {
+        self.owners.get(address).copied()
+    }
+
+    /// Every spill range, in reading order of the anchors
+    pub fn ranges(&self) -> Vec<SpillRange> {
+        let mut ranges: Vec<SpillRange> = self.ranges.values().copied().collect();
+        ranges.sort_by_key(|spill| (spill.anchor.row, spill.anchor.col));
+        ranges
+    }
+
+    /// Anchors whose range, filled or blocked, covers `address` other than
+    /// as the anchor
+    pub fn covering(&self, address: &CellAddress) -> Vec<CellAddress> {
+        let mut anchors: Vec<CellAddress> = self
+            .ranges
+            .values()
+            .filter(|spill| spill.anchor != *address && spill.range.contains(address))
+            .map(|spill| spill.anchor)
+            .collect();
+        anchors.sort_by_key(|anchor| (anchor.row, anchor.col));
+        anchors
+    }
+
+    /// Record `range` for its anchor, filled with `cells` unless blocked
+    pub fn insert(&mut self, spill: SpillRange, cells: impl IntoIterator<Item = CellAddress>) {
+        for cell in cells {
+            self.owners.insert(cell, spill.anchor);
+        }
+        self.ranges.insert(spill.anchor, spill);
+    }
+
+    /// Forget `anchor`'s range, returning the cells its array still fills
+    pub fn remove(&mut self, anchor: &CellAddress) -> Vec<CellAddress> {
+        let Some(spill) = self.ranges.remove(anchor) else {
+            return Vec::new();
+        };
+        spill
+            .range
+            .cells()
+            .filter(|cell| {
+                let owned = self.owners.get(cell) == Some(anchor);
+                if owned {
+                    self.owners.remove(cell);
+                }
+                owned
+            })
+            .collect()
+    }
+
+    /// `address` now holds a cell of its own rather than a spilled value
+    pub fn release(&mut self, address: &CellAddress) {
+        self.owners.remove(address);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+        self.owners.clear();
+    }
+}
@@ -0,0 +1,701 @@
+//! A small line-oriented command language over the facade, for debugging
+//! consoles and power users
+//!
+//! Each line holds one command, a verb followed by its arguments:
+//!
+//! - `get A1`: the value and formula of a cell
+//! - `set B2 = =SUM(A1:A5)`: enter everything after `=` into a cell, as if
+//!   typed; an empty input clears the cell
+//! - `range A1:C10`: the values of a range as a table
+//! - `recalc`: recalculate every formula
+//! - `insert rows 3 2`, `delete cols C`: insert or delete rows or columns
+//!   of the active sheet, from the given one on; the count defaults to 1
+//! - `sheet Data`: switch to a sheet, adding it if there is none by that name
+//! - `undo`: revert the last `set`, insert or delete of this session
+//! - `stats`: counts of cells, formulas and errors on the active sheet
+//! - `depgraph B2`: the cells a cell reads and the cells that read it
+//!
+//! Blank lines and lines starting with `#` are skipped, and verbs are case
+//! insensitive. There are no variables or control flow.
+//!
+//! [`ScriptSession::load`] runs a script as a bulk load instead, adjusting
+//! formulas once for each stretch of `set`, `insert` and `delete` lines.
+
+use crate::SpreadsheetFacade;
+use crate::domain::Cell;
+use crate::formula::CellRange;
+use crate::references::StructuralOperation;
+use crate::types::{CellAddress, CellValue};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Verbs in the order they are listed in errors
+const VERBS: [&str; 10] = [
+    "get", "set", "range", "insert", "delete", "sheet", "recalc", "undo", "stats", "depgraph",
+];
+
+/// One parsed line of a script
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptCommand {
+    Get(CellAddress),
+    Set {
+        address: CellAddress,
+        input: String,
+    },
+    Range(CellRange),
+    /// Insert or delete rows or columns of the active sheet
+    Structural(StructuralOperation),
+    Sheet(String),
+    Recalc,
+    Undo,
+    Stats,
+    DepGraph(CellAddress),
+}
+
+/// What a command produced, serialized with a `kind` tag for hosts that
+/// render it themselves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScriptOutput {
+    Value {
+        address: CellAddress,
+        value: CellValue,
+        formula: Option<String>,
+    },
+    /// Values of a range, row by row
+    Table {
+        range: CellRange,
+        rows: Vec<Vec<CellValue>>,
+    },
+    /// A command that changes cells and shows nothing
+    Done { message: String },
+    Stats {
+        sheet: String,
+        cells: usize,
+        formulas: usize,
+        errors: usize,
+    },
+    /// Direct precedents and dependents of a cell, in row-major order
+    DependencyGraph {
+        address: CellAddress,
+        precedents: Vec<CellAddress>,
+        dependents: Vec<CellAddress>,
+    },
+}
+
+impl fmt::Display for ScriptOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptOutput::Value {
+                address,
+                value,
+                formula: Some(formula),
+            } => write!(f, "{} = {} (={})", address, value, formula),
+            ScriptOutput::Value { address, value, .. } => write!(f, "{} = {}", address, value),
+            ScriptOutput::Table { range, rows } => {
+                write!(f, "{}", range)?;
+                for row in rows {
+                    let fields: Vec<String> = row.iter().map(CellValue::to_string).collect();
+                    write!(f, "\n{}", fields.join("\t"))?;
+                }
+                Ok(())
+            }
+            ScriptOutput::Done { message } => f.write_str(message),
+            ScriptOutput::Stats {
+                sheet,
+                cells,
+                formulas,
+                errors,
+            } => write!(
+                f,
+                "{}: {} cells, {} formulas, {} errors",
+                sheet, cells, formulas, errors
+            ),
+            ScriptOutput::DependencyGraph {
+                address,
+                precedents,
+                dependents,
+            } => {
+                let list = |cells: &[CellAddress]| {
+                    let names: Vec<String> = cells.iter().map(CellAddress::to_string).collect();
+                    if names.is_empty() {
+                        "none".to_string()
+                    } else {
+                        names.join(", ")
+                    }
+                };
+                write!(
+                    f,
+                    "{} reads {}; read by {}",
+                    address,
+                    list(precedents),
+                    list(dependents)
+                )
+            }
+        }
+    }
+}
+
+/// A script error at a 1-based line and column
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Parse a whole script, returning each command with its line number.
+/// Nothing runs when any line is malformed.
+pub fn parse_script(script: &str) -> Result<Vec<(usize, ScriptCommand)>, ScriptError> {
+    let mut commands = Vec::new();
+    for (index, line) in script.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let command = parse_line(trimmed.trim_end()).map_err(|(offset, message)| ScriptError {
+            line: index + 1,
+            column: indent + offset + 1,
+            message,
+        })?;
+        commands.push((index + 1, command));
+    }
+    Ok(commands)
+}
+
+/// Parse one trimmed line; errors carry a 0-based byte offset
+fn parse_line(line: &str) -> Result<ScriptCommand, (usize, String)> {
+    let verb_end = line.find(char::is_whitespace).unwrap_or(line.len());
+    let verb = line[..verb_end].to_ascii_lowercase();
+    let rest = &line[verb_end..];
+    let args_start = verb_end + (rest.len() - rest.trim_start().len());
+    let args = rest.trim();
+
+    let no_args = |command: ScriptCommand| {
+        if args.is_empty() {
+            Ok(command)
+        } else {
+            Err((args_start, format!("'{}' takes no arguments", verb)))
+        }
+    };
+    match verb.as_str() {
+        "get" => parse_cell(args, args_start).map(ScriptCommand::Get),
+        "depgraph" => parse_cell(args, args_start).map(ScriptCommand::DepGraph),
+        "range" => parse_range(args, args_start).map(ScriptCommand::Range),
+        "insert" | "delete" => {
+            parse_structural(&verb, args, args_start).map(ScriptCommand::Structural)
+        }
+        "sheet" if args.is_empty() => Err((args_start, "Expected a sheet name".to_string())),
+        "sheet" => Ok(ScriptCommand::Sheet(args.to_string())),
+        "set" => {
+            let Some(equals) = args.find('=') else {
+                return Err((
+                    args_start + args.len(),
+                    "Expected '=' after the cell, as in 'set A1 = 42'".to_string(),
+                ));
+            };
+            let address = parse_cell(args[..equals].trim_end(), args_start)?;
+            let input = args[equals + 1..].trim().to_string();
+            Ok(ScriptCommand::Set { address, input })
+        }
+        "recalc" => no_args(ScriptCommand::Recalc),
+        "undo" => no_args(ScriptCommand::Undo),
+        "stats" => no_args(ScriptCommand::Stats),
+        _ => Err((
+            0,
+            format!(
+                "Unknown command '{}', expected one of {}",
+                &line[..verb_end],
+                VERBS.join(", ")
+            ),
+        )),
+    }
+}
+
+fn parse_cell(text: &str, offset: usize) -> Result<CellAddress, (usize, String)> {
+    if text.is_empty() {
+        return Err((offset, "Expected a cell such as A1".to_string()));
+    }
+    CellAddress::from_a1(text).map_err(|_| (offset, format!("'{}' is not a cell", text)))
+}
+
+/// A range, where a single cell is a range of one
+fn parse_range(text: &str, offset: usize) -> Result<CellRange, (usize, String)> {
+    if text.is_empty() {
+        return Err((offset, "Expected a range such as A1:C10".to_string()));
+    }
+    if !text.contains(':') {
+        return parse_cell(text, offset).map(|cell| CellRange::new(cell, cell));
+    }
+    CellRange::from_string(text).map_err(|_| (offset, format!("'{}' is not a range", text)))
+}
+
+/// Parse `rows 3 [count]` or `cols C [count]`, the arguments of `insert`
+/// and `delete`
+fn parse_structural(
+    verb: &str,
+    args: &str,
+    offset: usize,
+) -> Result<StructuralOperation, (usize, String)> {
+    let words: Vec<(usize, &str)> = args
+        .split_whitespace()
+        .map(|word| {
+            (
+                offset + word.as_ptr() as usize - args.as_ptr() as usize,
+                word,
+            )
+        })
+        .collect();
+    let rows = match words.first().map(|(_, word)| word.to_ascii_lowercase()) {
+        Some(axis) if axis == "rows" || axis == "row" => true,
+        Some(axis) if ["cols", "col", "columns", "column"].contains(&axis.as_str()) => false,
+        _ => {
+            return Err((
+                offset,
+                format!(
+                    "Expected rows or cols, as in '{} rows 3 2' or '{} cols C'",
+                    verb, verb
+                ),
+            ));
+        }
+    };
+    let what = if rows { "row" } else { "column" };
+    let Some(&(at_offset, at)) = words.get(1) else {
+        return Err((
+            offset + args.len(),
+            format!("Expected the first {} to {}", what, verb),
+        ));
+    };
+    let at = if rows {
+        at.parse::<u32>()
+            .ok()
+            .filter(|row| *row >= 1)
+            .map(|row| row - 1)
+    } else {
+        CellAddress::column_label_to_number(&at.to_ascii_uppercase()).ok()
+    }
+    .ok_or_else(|| (at_offset, format!("'{}' is not a {}", at, what)))?;
+    let count = match words.get(2) {
+        None => 1,
+        Some(&(count_offset, count)) => count
+            .parse::<u32>()
+            .ok()
+            .filter(|count| *count >= 1)
+            .ok_or_else(|| (count_offset, format!("'{}' is not a count", count)))?,
+    };
+    if let Some(&(extra, _)) = words.get(3) {
+        return Err((
+            extra,
+            "Expected at most a count after the position".to_string(),
+        ));
+    }
+    Ok(match (verb, rows) {
+        ("insert", true) => StructuralOperation::InsertRows {
+            before_row: at,
+            count,
+        },
+        ("insert", false) => StructuralOperation::InsertColumns {
+            before_col: at,
+            count,
+        },
+        (_, true) => StructuralOperation::DeleteRows {
+            start_row: at,
+            count,
+        },
+        (_, false) => StructuralOperation::DeleteColumns {
+            start_col: at,
+            count,
+        },
+    })
+}
+
+/// What `undo` puts back
+#[derive(Debug)]
+enum Edit {
+    /// A cell of `sheet` before a `set`
+    Set {
+        sheet: String,
+        address: CellAddress,
+        previous: Option<Cell>,
+    },
+    /// The cells of every sheet before rows or columns of `sheet` were
+    /// inserted or deleted, as those edits rewrite formulas anywhere
+    Structural {
+        sheet: String,
+        operation: StructuralOperation,
+        before: Vec<(String, Vec<(CellAddress, Cell)>)>,
+    },
+}
+
+/// Runs scripts against one facade, remembering what `set`, `insert` and
+/// `delete` changed so `undo` can put it back in later scripts of the same
+/// session
+#[derive(Debug, Default)]
+pub struct ScriptSession {
+    /// Edits, latest last
+    history: Vec<Edit>,
+}
+
+impl ScriptSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run every line of `script` as one batch and return the output of
+    /// each command. A malformed line fails the script before anything
+    /// runs; a command that fails stops it, keeping earlier changes.
+    pub fn execute(
+        &mut self,
+        facade: &SpreadsheetFacade,
+        script: &str,
+    ) -> Result<Vec<ScriptOutput>, ScriptError> {
+        let commands = parse_script(script)?;
+        let failed = |line: usize, error: crate::SpreadsheetError| ScriptError {
+            line,
+            column: 1,
+            message: error.to_string(),
+        };
+
+        let batch_id = facade.begin_batch().map_err(|e| failed(1, e))?;
+        let mut outputs = Vec::with_capacity(commands.len());
+        let mut result = Ok(());
+        for (line, command) in commands {
+            match self.run(facade, command) {
+                Ok(output) => outputs.push(output),
+                Err(error) => {
+                    result = Err(failed(line, error));
+                    break;
+                }
+            }
+        }
+        let committed = facade.commit_batch(&batch_id).map_err(|e| failed(1, e));
+        result.and(committed).map(|_| outputs)
+    }
+
+    /// Run `script` as a bulk load, in one batch like [`Self::execute`].
+    /// Each stretch of `set`, `insert` and `delete` lines runs in a
+    /// structural batch, so formulas are adjusted and recalculated once
+    /// for the stretch instead of after every line; any other command
+    /// commits the stretch first, as it may read cells. Loaded lines are
+    /// not remembered for `undo`.
+    pub fn load(
+        &mut self,
+        facade: &SpreadsheetFacade,
+        script: &str,
+    ) -> Result<Vec<ScriptOutput>, ScriptError> {
+        let commands = parse_script(script)?;
+        let failed = |line: usize, error: crate::SpreadsheetError| ScriptError {
+            line,
+            column: 1,
+            message: error.to_string(),
+        };
+
+        let batch_id = facade.begin_batch().map_err(|e| failed(1, e))?;
+        let mut outputs = Vec::with_capacity(commands.len());
+        let mut result = Ok(());
+        let mut last_line = 1;
+        for (line, command) in commands {
+            last_line = line;
+            let loads = matches!(
+                command,
+                ScriptCommand::Set { .. } | ScriptCommand::Structural(_)
+            );
+            let ran = if loads != facade.is_structural_batch_open() {
+                if loads {
+                    facade.begin_structural_batch()
+                } else {
+                    facade.commit_structural_batch()
+                }
+            } else {
+                Ok(())
+            }
+            .and_then(|_| match command {
+                ScriptCommand::Set { address, input } => write(facade, &address, &input),
+                ScriptCommand::Structural(operation) => Ok(ScriptOutput::Done {
+                    message: apply(facade, operation).map(|_| describe(&operation))?,
+                }),
+                command => self.run(facade, command),
+            });
+            match ran {
+                Ok(output) => outputs.push(output),
+                Err(error) => {
+                    result = Err(failed(line, error));
+                    break;
+                }
+            }
+        }
+        // Whatever loaded before a failure stays
+        if facade.is_structural_batch_open() {
+            let committed = facade
+                .commit_structural_batch()
+                .map_err(|e| failed(last_line, e));
+            result = result.and(committed);
+        }
+        let committed = facade.commit_batch(&batch_id).map_err(|e| failed(1, e));
+        result.and(committed).map(|_| outputs)
+    }
+
+    fn run(
+        &mut self,
+        facade: &SpreadsheetFacade,
+        command: ScriptCommand,
+    ) -> crate::Result<ScriptOutput> {
+        Ok(match command {
+            ScriptCommand::Get(address) => {
+                let cell = facade.get_cell(&address);
+                ScriptOutput::Value {
+                    address,
+                    value: cell
+                        .as_ref()
+                        .map(Cell::get_computed_value)
+                        .unwrap_or_default(),
+                    formula: cell
+                        .and_then(|cell| cell.formula_text.map(|formula| formula.to_string())),
+                }
+            }
+            ScriptCommand::Set { address, input } => {
+                self.history.push(Edit::Set {
+                    sheet: facade.get_active_sheet(),
+                    address,
+                    previous: facade.get_cell(&address),
+                });
+                write(facade, &address, &input)?
+            }
+            ScriptCommand::Range(range) => {
+                let rows = (range.start.row..=range.end.row)
+                    .map(|row| {
+                        (range.start.col..=range.end.col)
+                            .map(|col| {
+                                facade
+                                    .get_cell_raw_value(&CellAddress::new(col, row))
+                                    .unwrap_or_default()
+                            })
+                            .collect()
+                    })
+                    .collect();
+                ScriptOutput::Table { range, rows }
+            }
+            ScriptCommand::Structural(operation) => {
+                let before = facade
+                    .get_sheets()
+                    .into_iter()
+                    .map(|(sheet, _)| {
+                        let cells = facade.get_all_cells_in_sheet(&sheet);
+                        (sheet, cells)
+                    })
+                    .collect();
+                apply(facade, operation)?;
+                self.history.push(Edit::Structural {
+                    sheet: facade.get_active_sheet(),
+                    operation,
+                    before,
+                });
+                ScriptOutput::Done {
+                    message: describe(&operation),
+                }
+            }
+            ScriptCommand::Sheet(name) => {
+                let message = if facade.has_sheet(&name) {
+                    format!("Switched to {}", name)
+                } else {
+                    facade.add_sheet(&name)?;
+                    format!("Added {}", name)
+                };
+                facade.set_active_sheet(&name)?;
+                ScriptOutput::Done { message }
+            }
+            ScriptCommand::Recalc => {
+                facade.recalculate()?;
+                ScriptOutput::Done {
+                    message: "Recalculated".to_string(),
+                }
+            }
+            ScriptCommand::Undo => {
+                let Some(edit) = self.history.pop() else {
+                    return Err(crate::SpreadsheetError::InvalidOperation(
+                        "Nothing to undo".to_string(),
+                    ));
+                };
+                let message = match edit {
+                    Edit::Set {
+                        sheet,
+                        address,
+                        previous,
+                    } => {
+                        on_sheet(facade, &sheet, || restore(facade, &address, previous))?;
+                        format!("Restored {}", address)
+                    }
+                    Edit::Structural {
+                        sheet,
+                        operation,
+                        before,
+                    } => {
+                        undo_structural(facade, &sheet, &operation, before)?;
+                        format!("Undid: {}", describe(&operation))
+                    }
+                };
+                ScriptOutput::Done { message }
+            }
+            ScriptCommand::Stats => {
+                let cells = facade.get_all_cells();
+                let sheet = facade.get_active_sheet();
+                ScriptOutput::Stats {
+                    errors: facade
+                        .get_sheet_health(&sheet)
+                        .map_or(0, |health| health.error_cells),
+                    formulas: cells.iter().filter(|(_, cell)| cell.has_formula()).count(),
+                    cells: cells.len(),
+                    sheet,
+                }
+            }
+            ScriptCommand::DepGraph(address) => dependency_graph(facade, address),
+        })
+    }
+}
+
+/// Enter `input` into a cell as `set` does, clearing it when empty
+fn write(
+    facade: &SpreadsheetFacade,
+    address: &CellAddress,
+    input: &str,
+) -> crate::Result<ScriptOutput> {
+    if input.is_empty() {
+        facade.delete_cell(address)?;
+    } else {
+        facade.set_cell_value(address, input)?;
+    }
+    Ok(ScriptOutput::Done {
+        message: format!("Set {}", address),
+    })
+}
+
+/// Put a cell back the way it was before a `set`
+fn restore(
+    facade: &SpreadsheetFacade,
+    address: &CellAddress,
+    previous: Option<Cell>,
+) -> crate::Result<()> {
+    match previous {
+        None => facade.delete_cell(address),
+        Some(cell) if cell.has_formula() => {
+            facade.set_cell_value(address, &cell.raw_value.to_string())
+        }
+        Some(cell) => match &cell.raw_value {
+            CellValue::String(text) => facade.set_cell_text(address, text),
+            value => facade.set_cell_value(address, &value.to_string()),
+        },
+    }
+}
+
+fn apply(facade: &SpreadsheetFacade, operation: StructuralOperation) -> crate::Result<()> {
+    match operation {
+        StructuralOperation::InsertRows { before_row, count } => {
+            facade.insert_rows(before_row, count)
+        }
+        StructuralOperation::DeleteRows { start_row, count } => {
+            facade.delete_rows(start_row, count)
+        }
+        StructuralOperation::InsertColumns { before_col, count } => {
+            facade.insert_columns(before_col, count)
+        }
+        StructuralOperation::DeleteColumns { start_col, count } => {
+            facade.delete_columns(start_col, count)
+        }
+        StructuralOperation::MoveRange { .. } => Err(crate::SpreadsheetError::InvalidOperation(
+            "Scripts cannot move ranges".to_string(),
+        )),
+    }
+}
+
+/// Put rows or columns back with the inverse operation, then every cell
+/// of every sheet that still differs, such as deleted cells and formulas
+/// left with `#REF!`
+fn undo_structural(
+    facade: &SpreadsheetFacade,
+    sheet: &str,
+    operation: &StructuralOperation,
+    before: Vec<(String, Vec<(CellAddress, Cell)>)>,
+) -> crate::Result<()> {
+    if let Some(inverse) = operation.inverse() {
+        on_sheet(facade, sheet, || apply(facade, inverse))?;
+    }
+    before.into_iter().try_for_each(|(sheet, cells)| {
+        on_sheet(facade, &sheet, || {
+            let mut cells: HashMap<CellAddress, Cell> = cells.into_iter().collect();
+            for (address, cell) in facade.get_all_cells() {
+                match cells.remove(&address) {
+                    Some(previous) if previous.raw_value == cell.raw_value => {}
+                    previous => restore(facade, &address, previous)?,
+                }
+            }
+            cells
+                .into_iter()
+                .try_for_each(|(address, previous)| restore(facade, &address, Some(previous)))
+        })
+    })
+}
+
+/// Run `edit` with `sheet` active, switching back afterwards
+fn on_sheet(
+    facade: &SpreadsheetFacade,
+    sheet: &str,
+    edit: impl FnOnce() -> crate::Result<()>,
+) -> crate::Result<()> {
+    let active = facade.get_active_sheet();
+    if active == sheet {
+        return edit();
+    }
+    facade.set_active_sheet(sheet)?;
+    let edited = edit();
+    facade.set_active_sheet(&active)?;
+    edited
+}
+
+/// What an insert or delete did, with 1-based rows and lettered columns
+fn describe(operation: &StructuralOperation) -> String {
+    let span = |first: u32, count: u32, label: &dyn Fn(u32) -> String| {
+        if count == 1 {
+            label(first)
+        } else {
+            format!("{}:{}", label(first), label(first + count - 1))
+        }
+    };
+    let row = |row: u32| (row + 1).to_string();
+    let column = |col: u32| CellAddress::column_number_to_label(col);
+    match *operation {
+        StructuralOperation::InsertRows { before_row, count } => {
+            format!("Inserted rows {}", span(before_row, count, &row))
+        }
+        StructuralOperation::DeleteRows { start_row, count } => {
+            format!("Deleted rows {}", span(start_row, count, &row))
+        }
+        StructuralOperation::InsertColumns { before_col, count } => {
+            format!("Inserted columns {}", span(before_col, count, &column))
+        }
+        StructuralOperation::DeleteColumns { start_col, count } => {
+            format!("Deleted columns {}", span(start_col, count, &column))
+        }
+        StructuralOperation::MoveRange { from, to } => format!("Moved {} to {}", from, to),
+    }
+}
+
+fn dependency_graph(facade: &SpreadsheetFacade, address: CellAddress) -> ScriptOutput {
+    ScriptOutput::DependencyGraph {
+        address,
+        precedents: facade.get_precedents(&address, false),
+        dependents: facade.get_dependents(&address, false),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,311 @@
+use super::{ScriptCommand, ScriptError, ScriptOutput, ScriptSession, parse_script};
+use crate::SpreadsheetFacade;
+use crate::formula::CellRange;
+use crate::types::{CellAddress, CellValue};
+
+fn addr(a1: &str) -> CellAddress {
+    CellAddress::from_a1(a1).unwrap()
+}
+
+fn run(facade: &SpreadsheetFacade, script: &str) -> Vec<ScriptOutput> {
+    ScriptSession::new().execute(facade, script).unwrap()
+}
+
+fn error_at(script: &str) -> (usize, usize) {
+    let ScriptError { line, column, .. } = parse_script(script).unwrap_err();
+    (line, column)
+}
+
+#[test]
+fn test_get_set_and_range() {
+    let facade = SpreadsheetFacade::new();
+    let outputs = run(
+        &facade,
+        "set A1 = 2\nSET A2 = 3\nset B2 = =SUM(A1:A2)\nget B2\nrange A1:B2",
+    );
+    assert_eq!(outputs.len(), 5);
+    assert_eq!(
+        outputs[3],
+        ScriptOutput::Value {
+            address: addr("B2"),
+            value: CellValue::Number(5.0),
+            formula: Some("SUM(A1:A2)".to_string()),
+        }
+    );
+    assert_eq!(
+        outputs[4],
+        ScriptOutput::Table {
+            range: CellRange::from_string("A1:B2").unwrap(),
+            rows: vec![
+                vec![CellValue::Number(2.0), CellValue::Empty],
+                vec![CellValue::Number(3.0), CellValue::Number(5.0)],
+            ],
+        }
+    );
+    assert_eq!(outputs[3].to_string(), "B2 = 5 (=SUM(A1:A2))");
+
+    // Results serialize with their kind for hosts
+    let json = serde_json::to_value(&outputs[4]).unwrap();
+    assert_eq!(json["kind"], "table");
+    assert_eq!(json["rows"].as_array().unwrap().len(), 2);
+
+    // A single cell is a range of one, and an empty input clears
+    let outputs = run(&facade, "set A1 =\nrange A1");
+    assert_eq!(
+        outputs[1],
+        ScriptOutput::Table {
+            range: CellRange::new(addr("A1"), addr("A1")),
+            rows: vec![vec![CellValue::Empty]],
+        }
+    );
+}
+
+#[test]
+fn test_recalc_stats_and_depgraph() {
+    let facade = SpreadsheetFacade::new();
+    let outputs = run(
+        &facade,
+        "set A1 = 1\nset B1 = =A1*2\nset C1 = =SUM(A1:B1)\nset D1 = =1/0\n\
+         recalc\nstats\ndepgraph B1",
+    );
+    assert_eq!(
+        outputs[4],
+        ScriptOutput::Done {
+            message: "Recalculated".to_string()
+        }
+    );
+    assert_eq!(
+        outputs[5],
+        ScriptOutput::Stats {
+            sheet: facade.get_active_sheet(),
+            cells: 4,
+            formulas: 3,
+            errors: 1,
+        }
+    );
+    assert_eq!(
+        outputs[6],
+        ScriptOutput::DependencyGraph {
+            address: addr("B1"),
+            precedents: vec![addr("A1")],
+            dependents: vec![addr("C1")],
+        }
+    );
+    assert_eq!(outputs[6].to_string(), "B1 reads A1; read by C1");
+}
+
+#[test]
+fn test_undo_reverts_sets_across_scripts_of_a_session() {
+    let facade = SpreadsheetFacade::new();
+    let mut session = ScriptSession::new();
+    session
+        .execute(&facade, "set A1 = 'hello\nset B1 = =LEN(A1)")
+        .unwrap();
+    session.execute(&facade, "set A1 = 7\nset B1 = 0").unwrap();
+
+    session.execute(&facade, "undo\nundo").unwrap();
+    assert_eq!(
+        facade.get_cell_raw_value(&addr("A1")),
+        Some(CellValue::string_from_str("hello"))
+    );
+    assert_eq!(
+        facade
+            .get_cell(&addr("B1"))
+            .unwrap()
+            .formula_text
+            .as_deref(),
+        Some("LEN(A1)")
+    );
+
+    session.execute(&facade, "undo\nundo").unwrap();
+    assert_eq!(facade.get_cell(&addr("A1")), None);
+    let error = session.execute(&facade, "get A1\nundo").unwrap_err();
+    assert_eq!(
+        (error.line, error.message.contains("Nothing to undo")),
+        (2, true)
+    );
+}
+
+#[test]
+fn test_malformed_lines_report_their_position() {
+    // Column of the offending argument, after indentation
+    assert_eq!(error_at("get A1\n  get 12"), (2, 7));
+    assert_eq!(error_at("fetch A1"), (1, 1));
+    assert_eq!(error_at("set A1 42"), (1, 10));
+    assert_eq!(error_at("set = 42"), (1, 5));
+    assert_eq!(error_at("range A1:"), (1, 7));
+    assert_eq!(error_at("stats now"), (1, 7));
+    assert_eq!(error_at("depgraph"), (1, 9));
+    assert_eq!(error_at("insert 3"), (1, 8));
+    assert_eq!(error_at("insert rows"), (1, 12));
+    assert_eq!(error_at("delete rows 0"), (1, 13));
+    assert_eq!(error_at("delete cols C1"), (1, 13));
+    assert_eq!(error_at("insert cols C x"), (1, 15));
+    assert_eq!(error_at("insert rows 2 1 1"), (1, 17));
+    assert_eq!(error_at("sheet"), (1, 6));
+
+    let error = parse_script("frobnicate").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "1:1: Unknown command 'frobnicate', expected one of get, set, range, insert, delete, \
+         sheet, recalc, undo, stats, depgraph"
+    );
+}
+
+#[test]
+fn test_script_runs_as_one_batch_and_stops_at_a_failure() {
+    let facade = SpreadsheetFacade::new();
+    let commands = parse_script("# setup\n\nset A1 = 1\n  get A1  \n").unwrap();
+    assert_eq!(
+        commands,
+        vec![
+            (
+                3,
+                ScriptCommand::Set {
+                    address: addr("A1"),
+                    input: "1".to_string()
+                }
+            ),
+            (4, ScriptCommand::Get(addr("A1"))),
+        ]
+    );
+
+    // A malformed line anywhere keeps the whole script from running
+    assert!(
+        ScriptSession::new()
+            .execute(&facade, "set A1 = 5\nget nowhere")
+            .is_err()
+    );
+    assert_eq!(facade.get_cell(&addr("A1")), None);
+
+    // A failing command stops the script but earlier lines stay applied,
+    // and the batch is closed so later scripts still run
+    let mut session = ScriptSession::new();
+    let error = session
+        .execute(&facade, "set A1 = 5\nundo\nundo\nset A2 = 6")
+        .unwrap_err();
+    assert_eq!(error.line, 3);
+    assert_eq!(facade.get_cell(&addr("A1")), None);
+    assert_eq!(facade.get_cell(&addr("A2")), None);
+    assert_eq!(session.execute(&facade, "set A2 = 6").unwrap().len(), 1);
+    assert_eq!(
+        facade.get_cell_raw_value(&addr("A2")),
+        Some(CellValue::Number(6.0))
+    );
+}
+
+#[test]
+fn test_row_and_column_edits_undo_across_sheets() {
+    let facade = SpreadsheetFacade::new();
+    let first = facade.get_active_sheet();
+    let mut session = ScriptSession::new();
+    session
+        .execute(
+            &facade,
+            "set A1 = 1\nset A2 = 2\nset B3 = =SUM(A1:A2)+A2\n\
+             sheet Other\nset C1 = =A2*2\nset A2 = 5",
+        )
+        .unwrap();
+    let hashes = |facade: &SpreadsheetFacade| {
+        [first.as_str(), "Other"].map(|sheet| facade.sheet_input_hash(sheet).unwrap())
+    };
+    let original = hashes(&facade);
+
+    let outputs = session
+        .execute(
+            &facade,
+            &format!("sheet {}\ndelete rows 2\ninsert cols A 2\nget D2", first),
+        )
+        .unwrap();
+    assert_eq!(outputs[1].to_string(), "Deleted rows 2");
+    assert_eq!(outputs[2].to_string(), "Inserted columns A:B");
+    assert_eq!(
+        outputs[3],
+        ScriptOutput::Value {
+            address: addr("D2"),
+            value: CellValue::from_error(crate::types::ErrorType::InvalidRef {
+                reference: "deleted".to_string()
+            }),
+            formula: Some("SUM(C1:C1)+#REF!".to_string()),
+        }
+    );
+    // Other sheets are left alone
+    assert_eq!(
+        facade
+            .get_cell_in_sheet("Other", &addr("C1"))
+            .unwrap()
+            .formula_text
+            .as_deref(),
+        Some("A2*2")
+    );
+
+    let outputs = session.execute(&facade, "undo\nundo").unwrap();
+    assert_eq!(outputs[1].to_string(), "Undid: Deleted rows 2");
+    assert_eq!(hashes(&facade), original);
+    assert_eq!(
+        facade.get_cell_raw_value(&addr("B3")),
+        Some(CellValue::Number(5.0))
+    );
+
+    // A set is undone on its own sheet, wherever the session is
+    session.execute(&facade, "undo").unwrap();
+    assert_eq!(facade.get_active_sheet(), first);
+    assert_eq!(facade.get_cell_in_sheet("Other", &addr("A2")), None);
+}
+
+#[test]
+fn test_load_adjusts_once_and_matches_execute() {
+    let mut script = String::from("set A1 = 1\nset A2 = =SUM(A1:A1)\n");
+    for row in 2..=6 {
+        script.push_str(&format!("insert rows {}\nset A{} = {}\n", row, row, row));
+    }
+    script.push_str("get A7\ndelete rows 1\nset B1 = =A6*2\nsheet Other\nset A1 = 3");
+
+    let executed = SpreadsheetFacade::new();
+    let expected = ScriptSession::new().execute(&executed, &script).unwrap();
+    let loaded = SpreadsheetFacade::new();
+    let mut session = ScriptSession::new();
+    let outputs = session.load(&loaded, &script).unwrap();
+    assert_eq!(outputs, expected);
+    assert_eq!(
+        outputs[12],
+        ScriptOutput::Value {
+            address: addr("A7"),
+            value: CellValue::Number(21.0),
+            formula: Some("SUM(A1:A6)".to_string()),
+        }
+    );
+    let sheet = executed.get_active_sheet();
+    for facade in [&executed, &loaded] {
+        facade.set_active_sheet("Sheet1").unwrap();
+    }
+    assert_eq!(
+        loaded.get_all_cells_in_sheet("Sheet1").len(),
+        executed.get_all_cells_in_sheet("Sheet1").len()
+    );
+    assert_eq!(
+        loaded.get_cell_raw_value(&addr("A6")),
+        Some(CellValue::Number(20.0))
+    );
+    assert_eq!(
+        loaded.get_cell_raw_value(&addr("B1")),
+        Some(CellValue::Number(40.0))
+    );
+    assert_eq!(sheet, "Other");
+
+    // Loaded lines are not undone, and a failure keeps what loaded before
+    assert!(session.execute(&loaded, "undo").is_err());
+    let error = session
+        .load(&loaded, "insert rows 1\nset A1 = 9\nundo")
+        .unwrap_err();
+    assert_eq!(error.line, 3);
+    assert!(!loaded.is_structural_batch_open());
+    assert_eq!(
+        loaded.get_cell_raw_value(&addr("A1")),
+        Some(CellValue::Number(9.0))
+    );
+    assert_eq!(
+        loaded.get_cell_raw_value(&addr("A7")),
+        Some(CellValue::Number(20.0))
+    );
+}
@@ -109,11 +109,16 @@ impl CalculationService for CalculationServiceImpl {
             SpreadsheetError::LockError("Failed to acquire dependency graph lock".to_string())
         })?;
 
-        // Find all cells affected by the given addresses
+        // Find all cells affected by the given addresses, and volatile
+        // formulas, which are recalculated whatever changed
         let mut affected = HashSet::new();
-        for address in addresses {
-            affected.insert(*address);
-            affected.extend(dependency_graph.get_dependents(address));
+        for address in addresses
+            .iter()
+            .copied()
+            .chain(dependency_graph.volatile_cells())
+        {
+            affected.insert(address);
+            affected.extend(dependency_graph.get_dependents(&address));
         }
 
         // Get calculation order for all cells (filtering to affected only)
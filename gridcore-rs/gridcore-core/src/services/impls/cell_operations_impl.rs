@@ -82,8 +82,8 @@ impl CellOperationsService for CellOperationsServiceImpl {
 
             // Check for circular dependencies
             for reference in &references {
-                if dependency_graph.would_create_cycle(address, reference) {
-                    return Err(SpreadsheetError::CircularDependency);
+                if let Some(cells) = dependency_graph.cycle_if_added(address, reference) {
+                    return Err(SpreadsheetError::CircularDependencyWith(cells));
                 }
             }
 
@@ -92,6 +92,9 @@ impl CellOperationsService for CellOperationsServiceImpl {
             for reference in &references {
                 dependency_graph.add_dependency(*address, *reference);
             }
+            for name in DependencyAnalyzer::extract_names(&formula) {
+                dependency_graph.add_name_dependency(*address, &name);
+            }
 
             // Update reference tracker
             reference_tracker.update_dependencies(address, &formula);
@@ -68,7 +68,7 @@ impl StructuralOperationsService for StructuralOperationsServiceImpl {
         Ok(affected_addresses)
     }
 
-    fn delete_rows(&self, start: u32, count: u32) -> Result<Vec<Cell>> {
+    fn delete_rows(&self, start: u32, count: u32) -> Result<Vec<(CellAddress, Cell)>> {
         let mut repository = self.repository.lock().map_err(|_| {
             SpreadsheetError::LockError("Failed to acquire repository lock".to_string())
         })?;
@@ -80,17 +80,19 @@ impl StructuralOperationsService for StructuralOperationsServiceImpl {
         })?;
 
         // Collect cells to be deleted
+        let mut doomed: Vec<CellAddress> = repository
+            .get_all_addresses()
+            .into_iter()
+            .filter(|address| (start..start + count).contains(&address.row))
+            .collect();
+        doomed.sort_by_key(|address| (address.row, address.col));
+
         let mut deleted_cells = Vec::new();
-        for row in start..start + count {
-            for col in 0..100 {
-                // Assuming max 100 columns
-                let address = CellAddress::new(col, row);
-                if let Some(cell) = repository.get(&address) {
-                    deleted_cells.push(cell.clone());
-                    repository.delete(&address);
-                    dependency_graph.remove_dependencies_for(&address);
-                    reference_tracker.remove_dependencies(&address);
-                }
+        for address in doomed {
+            if let Some(cell) = repository.delete(&address) {
+                deleted_cells.push((address, cell));
+                dependency_graph.remove_dependencies_for(&address);
+                reference_tracker.remove_dependencies(&address);
             }
         }
 
@@ -144,7 +146,7 @@ impl StructuralOperationsService for StructuralOperationsServiceImpl {
         Ok(affected_addresses)
     }
 
-    fn delete_columns(&self, start: u32, count: u32) -> Result<Vec<Cell>> {
+    fn delete_columns(&self, start: u32, count: u32) -> Result<Vec<(CellAddress, Cell)>> {
         let mut repository = self.repository.lock().map_err(|_| {
             SpreadsheetError::LockError("Failed to acquire repository lock".to_string())
         })?;
@@ -156,17 +158,19 @@ impl StructuralOperationsService for StructuralOperationsServiceImpl {
         })?;
 
         // Collect cells to be deleted
+        let mut doomed: Vec<CellAddress> = repository
+            .get_all_addresses()
+            .into_iter()
+            .filter(|address| (start..start + count).contains(&address.col))
+            .collect();
+        doomed.sort_by_key(|address| (address.row, address.col));
+
         let mut deleted_cells = Vec::new();
-        for col in start..start + count {
-            for row in 0..1000 {
-                // Assuming max 1000 rows
-                let address = CellAddress::new(col, row);
-                if let Some(cell) = repository.get(&address) {
-                    deleted_cells.push(cell.clone());
-                    repository.delete(&address);
-                    dependency_graph.remove_dependencies_for(&address);
-                    reference_tracker.remove_dependencies(&address);
-                }
+        for address in doomed {
+            if let Some(cell) = repository.delete(&address) {
+                deleted_cells.push((address, cell));
+                dependency_graph.remove_dependencies_for(&address);
+                reference_tracker.remove_dependencies(&address);
             }
         }
 
@@ -0,0 +1,213 @@
+//! Sparklines: small charts drawn inside the cell of a `SPARKLINE` formula
+//!
+//! `=SPARKLINE(range, [type], [options])` evaluates to a [`Sparkline`]
+//! holding the range's numbers scaled to `0..=1`, ready to be stretched
+//! over the cell by the host. The type is `"line"` (the default), `"bar"`
+//! or `"winloss"`; options are `key=value` pairs separated by `;`, e.g.
+//! `"color=#188038; negcolor=#d93025"`.
+
+use crate::types::CellValue;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Name of the formula function that draws a sparkline
+pub const SPARKLINE_FUNCTION: &str = "SPARKLINE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SparklineKind {
+    Line,
+    /// One bar per value, growing up or down from zero
+    Bar,
+    /// One bar per value, up for positive values and down for negative
+    /// ones, all of the same height
+    WinLoss,
+}
+
+impl SparklineKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "" | "line" => Some(Self::Line),
+            "bar" | "column" => Some(Self::Bar),
+            "winloss" => Some(Self::WinLoss),
+            _ => None,
+        }
+    }
+}
+
+/// Colors a sparkline is drawn in; the host's defaults where `None`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SparklineOptions {
+    /// The line, or the bars of positive values
+    pub color: Option<String>,
+    /// Bars of negative values
+    pub negative_color: Option<String>,
+}
+
+impl SparklineOptions {
+    /// Read `key=value` pairs separated by `;`. Keys are `color` and
+    /// `negcolor`; anything else is an error naming the pair.
+    pub fn parse(options: &str) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        for pair in options
+            .split(';')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let Some((key, value)) = pair.split_once('=') else {
+                return Err(format!("option \"{}\" is not key=value", pair));
+            };
+            let value = value.trim().to_string();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "color" => parsed.color = Some(value),
+                "negcolor" => parsed.negative_color = Some(value),
+                other => return Err(format!("unknown option \"{}\"", other)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// A sparkline ready to draw. Points run left to right, one per cell of
+/// the range, with `None` where a cell holds no number.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sparkline {
+    pub kind: SparklineKind,
+    /// Values scaled to `0..=1` between the smallest and the largest, or
+    /// the sign (1, 0 or -1) for win/loss sparklines. Values are all 0.5
+    /// when they are equal.
+    pub points: Vec<Option<f64>>,
+    /// Height of zero on the same scale, where bars start from
+    pub baseline: f64,
+    pub options: SparklineOptions,
+}
+
+impl Sparkline {
+    /// Sparkline of the numbers in `values`; text, booleans, blanks and
+    /// errors become gaps
+    pub fn new(kind: SparklineKind, values: &[CellValue], options: SparklineOptions) -> Self {
+        let numbers: Vec<Option<f64>> = values
+            .iter()
+            .map(|value| match value {
+                CellValue::Number(n) if n.is_finite() => Some(*n),
+                _ => None,
+            })
+            .collect();
+
+        if kind == SparklineKind::WinLoss {
+            return Self {
+                kind,
+                points: numbers
+                    .iter()
+                    .map(|n| n.map(|n| if n == 0.0 { 0.0 } else { n.signum() }))
+                    .collect(),
+                baseline: 0.5,
+                options,
+            };
+        }
+
+        let (mut min, mut max) = numbers
+            .iter()
+            .flatten()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &n| {
+                (min.min(n), max.max(n))
+            });
+        // Bars grow from zero, so zero must be on the scale
+        if kind == SparklineKind::Bar && min <= max {
+            min = min.min(0.0);
+            max = max.max(0.0);
+        }
+        let span = max - min;
+        let scale = |n: f64| if span > 0.0 { (n - min) / span } else { 0.5 };
+        Self {
+            kind,
+            points: numbers.iter().map(|n| n.map(scale)).collect(),
+            baseline: if min <= max {
+                scale(0.0).clamp(0.0, 1.0)
+            } else {
+                0.0
+            },
+            options,
+        }
+    }
+
+    /// Whether there is nothing to draw, e.g. for a blank range
+    pub fn is_blank(&self) -> bool {
+        self.points.iter().all(Option::is_none)
+    }
+}
+
+/// Sparklines have no text; exports and copies show them as empty
+impl fmt::Display for Sparkline {
+    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(values: &[Option<f64>]) -> Vec<CellValue> {
+        values
+            .iter()
+            .map(|value| value.map_or(CellValue::Empty, CellValue::Number))
+            .collect()
+    }
+
+    #[test]
+    fn test_points_are_scaled_between_min_and_max() {
+        let values = numbers(&[Some(10.0), Some(30.0), None, Some(20.0)]);
+        let line = Sparkline::new(SparklineKind::Line, &values, SparklineOptions::default());
+        assert_eq!(line.points, [Some(0.0), Some(1.0), None, Some(0.5)]);
+
+        // Bars keep zero on the scale and start from it
+        let bars = Sparkline::new(SparklineKind::Bar, &values, SparklineOptions::default());
+        assert_eq!(
+            bars.points,
+            [Some(1.0 / 3.0), Some(1.0), None, Some(2.0 / 3.0)]
+        );
+        assert_eq!(bars.baseline, 0.0);
+
+        let values = numbers(&[Some(-5.0), Some(0.0), Some(15.0)]);
+        let winloss = Sparkline::new(SparklineKind::WinLoss, &values, SparklineOptions::default());
+        assert_eq!(winloss.points, [Some(-1.0), Some(0.0), Some(1.0)]);
+        let bars = Sparkline::new(SparklineKind::Bar, &values, SparklineOptions::default());
+        assert_eq!(bars.baseline, 0.25);
+    }
+
+    #[test]
+    fn test_equal_and_blank_values() {
+        let equal = numbers(&[Some(7.0), Some(7.0)]);
+        let line = Sparkline::new(SparklineKind::Line, &equal, SparklineOptions::default());
+        assert_eq!(line.points, [Some(0.5), Some(0.5)]);
+
+        let blank = vec![
+            CellValue::Empty,
+            CellValue::string_from_str("n/a"),
+            CellValue::Boolean(true),
+        ];
+        for kind in [
+            SparklineKind::Line,
+            SparklineKind::Bar,
+            SparklineKind::WinLoss,
+        ] {
+            let sparkline = Sparkline::new(kind, &blank, SparklineOptions::default());
+            assert!(sparkline.is_blank());
+            assert!(sparkline.baseline.is_finite());
+        }
+    }
+
+    #[test]
+    fn test_parse_type_and_options() {
+        assert_eq!(SparklineKind::parse("Column"), Some(SparklineKind::Bar));
+        assert_eq!(SparklineKind::parse(""), Some(SparklineKind::Line));
+        assert_eq!(SparklineKind::parse("pie"), None);
+
+        let options = SparklineOptions::parse("color=#188038; negcolor = red;").unwrap();
+        assert_eq!(options.color.as_deref(), Some("#188038"));
+        assert_eq!(options.negative_color.as_deref(), Some("red"));
+        assert!(SparklineOptions::parse("width=2").is_err());
+        assert!(SparklineOptions::parse("color").is_err());
+    }
+}
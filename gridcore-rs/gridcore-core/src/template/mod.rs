@@ -0,0 +1,328 @@
+//! Workbook templates.
+//!
+//! A template is a sheet exported as CSV with its [`Sidecar`], plus a name,
+//! a description and the parameters a "new from template" wizard asks for.
+//! Each parameter names the cell its value goes into; the template's own
+//! content of that cell is the default. Templates come from a
+//! [`TemplateProvider`]: [`EmbeddedTemplates`] holds the curated set built
+//! into the crate, and [`TemplateSet`] holds templates the host read from
+//! files or browser storage.
+
+use crate::csv::{Sidecar, migrate};
+use crate::types::CellAddress;
+use crate::{Result, SpreadsheetError, SpreadsheetFacade};
+use serde::{Deserialize, Serialize};
+
+/// Templates built into the crate, as JSON documents
+const BUILTIN: [&[u8]; 3] = [
+    include_bytes!("builtin/budget.json"),
+    include_bytes!("builtin/invoice.json"),
+    include_bytes!("builtin/timesheet.json"),
+];
+
+/// A value a template asks for and the cell it goes into
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateParameter {
+    /// Key the value is passed under, e.g. `month`
+    pub name: String,
+    /// What the wizard shows next to the input
+    pub label: String,
+    /// A1 address of the cell the value is written to
+    pub cell: String,
+}
+
+/// What a template picker shows of a template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub parameters: Vec<TemplateParameter>,
+}
+
+/// A template: its metadata and the sheet it creates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    #[serde(flatten)]
+    pub info: TemplateInfo,
+    /// The sheet as [`SpreadsheetFacade::export_csv`] writes it
+    pub csv: String,
+    #[serde(
+        default,
+        deserialize_with = "migrate::deserialize_sidecar",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sidecar: Option<Sidecar>,
+}
+
+impl Template {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Self::from_slice(json.as_bytes())
+    }
+
+    fn from_slice(json: &[u8]) -> Result<Self> {
+        serde_json::from_slice(json)
+            .map_err(|e| SpreadsheetError::InvalidOperation(format!("Invalid template: {}", e)))
+    }
+
+    /// Create a new document from the template, writing each of
+    /// `parameters` into its cell and recalculating. Parameters left out
+    /// keep the template's content; one the template does not have is an
+    /// error.
+    pub fn instantiate(&self, parameters: &[(String, String)]) -> Result<SpreadsheetFacade> {
+        let mut writes = Vec::with_capacity(parameters.len());
+        for (name, value) in parameters {
+            let parameter = self
+                .info
+                .parameters
+                .iter()
+                .find(|parameter| parameter.name == *name)
+                .ok_or_else(|| {
+                    SpreadsheetError::InvalidArguments(format!(
+                        "Template {} has no parameter {}",
+                        self.info.id, name
+                    ))
+                })?;
+            let address = CellAddress::from_a1(&parameter.cell).map_err(|_| {
+                SpreadsheetError::InvalidOperation(format!(
+                    "Template {} puts {} in {}, which is not a cell address",
+                    self.info.id, name, parameter.cell
+                ))
+            })?;
+            writes.push((address, value));
+        }
+
+        let facade = SpreadsheetFacade::new();
+        let report = facade.import_csv(&self.csv, self.sidecar.as_ref())?;
+        if !report.is_faithful() {
+            return Err(SpreadsheetError::InvalidOperation(format!(
+                "Template {} did not load cleanly. {}",
+                self.info.id, report
+            )));
+        }
+        for (address, value) in writes {
+            facade.set_cell_value(&address, value)?;
+        }
+        facade.recalculate()?;
+        Ok(facade)
+    }
+}
+
+/// Somewhere templates come from
+pub trait TemplateProvider {
+    /// Metadata of every template, in the order to offer them
+    fn list(&self) -> Vec<TemplateInfo>;
+
+    /// The template with `id`
+    fn load(&self, id: &str) -> Result<Template>;
+}
+
+/// Templates offered by `provider`
+pub fn list_templates(provider: &dyn TemplateProvider) -> Vec<TemplateInfo> {
+    provider.list()
+}
+
+/// Create a new document from the template `id` of `provider`; see
+/// [`Template::instantiate`]
+pub fn instantiate_template(
+    provider: &dyn TemplateProvider,
+    id: &str,
+    parameters: &[(String, String)],
+) -> Result<SpreadsheetFacade> {
+    provider.load(id)?.instantiate(parameters)
+}
+
+fn unknown_template(id: &str) -> SpreadsheetError {
+    SpreadsheetError::InvalidOperation(format!("Unknown template: {}", id))
+}
+
+/// The budget, invoice and timesheet templates built into the crate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmbeddedTemplates;
+
+impl EmbeddedTemplates {
+    fn templates() -> impl Iterator<Item = Template> {
+        BUILTIN
+            .into_iter()
+            .map(|json| Template::from_slice(json).expect("built-in templates are valid"))
+    }
+}
+
+impl TemplateProvider for EmbeddedTemplates {
+    fn list(&self) -> Vec<TemplateInfo> {
+        Self::templates().map(|template| template.info).collect()
+    }
+
+    fn load(&self, id: &str) -> Result<Template> {
+        Self::templates()
+            .find(|template| template.info.id == id)
+            .ok_or_else(|| unknown_template(id))
+    }
+}
+
+/// Templates the host keeps, e.g. read from a directory or from browser
+/// storage, optionally offered after another provider's
+#[derive(Default)]
+pub struct TemplateSet {
+    templates: Vec<Template>,
+    fallback: Option<Box<dyn TemplateProvider>>,
+}
+
+impl TemplateSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer the templates of `provider` first; templates added here with
+    /// the same id replace them
+    pub fn with_fallback(provider: impl TemplateProvider + 'static) -> Self {
+        Self {
+            templates: Vec::new(),
+            fallback: Some(Box::new(provider)),
+        }
+    }
+
+    /// Add `template`, replacing one with the same id
+    pub fn add(&mut self, template: Template) {
+        self.templates
+            .retain(|existing| existing.info.id != template.info.id);
+        self.templates.push(template);
+    }
+
+    /// Add the template in `json`, as written by [`Template::to_json`]
+    pub fn add_json(&mut self, json: &str) -> Result<()> {
+        self.add(Template::from_json(json)?);
+        Ok(())
+    }
+
+    fn own(&self, id: &str) -> Option<&Template> {
+        self.templates
+            .iter()
+            .find(|template| template.info.id == id)
+    }
+}
+
+impl TemplateProvider for TemplateSet {
+    fn list(&self) -> Vec<TemplateInfo> {
+        let mut list: Vec<TemplateInfo> = match &self.fallback {
+            Some(fallback) => fallback
+                .list()
+                .into_iter()
+                .filter(|info| self.own(&info.id).is_none())
+                .collect(),
+            None => Vec::new(),
+        };
+        list.extend(self.templates.iter().map(|template| template.info.clone()));
+        list
+    }
+
+    fn load(&self, id: &str) -> Result<Template> {
+        match (self.own(id), &self.fallback) {
+            (Some(template), _) => Ok(template.clone()),
+            (None, Some(fallback)) => fallback.load(id),
+            (None, None) => Err(unknown_template(id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CellValue;
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn value(facade: &SpreadsheetFacade, a1: &str) -> Option<CellValue> {
+        facade.get_cell_raw_value(&CellAddress::from_a1(a1).unwrap())
+    }
+
+    #[test]
+    fn test_embedded_templates_load_cleanly() {
+        let list = list_templates(&EmbeddedTemplates);
+        let ids: Vec<&str> = list.iter().map(|info| info.id.as_str()).collect();
+        assert_eq!(ids, ["budget", "invoice", "timesheet"]);
+
+        for info in &list {
+            let template = EmbeddedTemplates.load(&info.id).unwrap();
+            for parameter in &info.parameters {
+                assert!(
+                    CellAddress::from_a1(&parameter.cell).is_ok(),
+                    "{}: {}",
+                    info.id,
+                    parameter.cell
+                );
+            }
+            let facade = template.instantiate(&[]).unwrap();
+            let errors: Vec<_> = facade
+                .get_all_cells()
+                .into_iter()
+                .filter(|(_, cell)| matches!(cell.get_computed_value(), CellValue::Error(_)))
+                .collect();
+            assert!(errors.is_empty(), "{}: {:?}", info.id, errors);
+            assert_eq!(Template::from_json(&template.to_json()).unwrap(), template);
+        }
+    }
+
+    #[test]
+    fn test_parameters_feed_the_formulas_reading_them() {
+        let facade = instantiate_template(
+            &EmbeddedTemplates,
+            "timesheet",
+            &params(&[("employee", "Ada"), ("rate", "40")]),
+        )
+        .unwrap();
+        assert_eq!(
+            value(&facade, "B2"),
+            Some(CellValue::from_string("Ada".to_string()))
+        );
+        // 40 hours at the new rate
+        assert_eq!(value(&facade, "B15"), Some(CellValue::Number(1600.0)));
+
+        let invoice = instantiate_template(
+            &EmbeddedTemplates,
+            "invoice",
+            &params(&[("tax_rate", "0.1")]),
+        )
+        .unwrap();
+        assert_eq!(value(&invoice, "D13"), Some(CellValue::Number(1250.0)));
+        assert_eq!(value(&invoice, "D15"), Some(CellValue::Number(1375.0)));
+    }
+
+    #[test]
+    fn test_unknown_templates_and_parameters_are_errors() {
+        assert!(matches!(
+            instantiate_template(
+                &EmbeddedTemplates,
+                "budget",
+                &params(&[("month", "2025-02"), ("salary", "1")])
+            ),
+            Err(SpreadsheetError::InvalidArguments(message)) if message.contains("salary")
+        ));
+        assert!(instantiate_template(&EmbeddedTemplates, "payroll", &[]).is_err());
+    }
+
+    #[test]
+    fn test_template_set_overrides_its_fallback() {
+        let mut budget = EmbeddedTemplates.load("budget").unwrap();
+        budget.info.name = "Household budget".to_string();
+        let mut set = TemplateSet::with_fallback(EmbeddedTemplates);
+        set.add_json(&budget.to_json()).unwrap();
+
+        let names: Vec<String> = set.list().into_iter().map(|info| info.name).collect();
+        assert_eq!(names, ["Invoice", "Weekly timesheet", "Household budget"]);
+        assert_eq!(set.load("budget").unwrap().info.name, "Household budget");
+        assert!(set.load("invoice").is_ok());
+        assert!(TemplateSet::new().load("invoice").is_err());
+        assert!(set.add_json("{\"id\": \"broken\"}").is_err());
+    }
+}
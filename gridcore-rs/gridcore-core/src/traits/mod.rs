@@ -54,14 +54,14 @@ pub trait StructuralOperationsService: Send + Sync {
     /// Insert rows at the specified index
     fn insert_rows(&self, start: u32, count: u32) -> Result<Vec<CellAddress>>;
 
-    /// Delete rows at the specified index
-    fn delete_rows(&self, start: u32, count: u32) -> Result<Vec<Cell>>;
+    /// Delete rows at the specified index, returning the cells they held
+    fn delete_rows(&self, start: u32, count: u32) -> Result<Vec<(CellAddress, Cell)>>;
 
     /// Insert columns at the specified index
     fn insert_columns(&self, start: u32, count: u32) -> Result<Vec<CellAddress>>;
 
-    /// Delete columns at the specified index
-    fn delete_columns(&self, start: u32, count: u32) -> Result<Vec<Cell>>;
+    /// Delete columns at the specified index, returning the cells they held
+    fn delete_columns(&self, start: u32, count: u32) -> Result<Vec<(CellAddress, Cell)>>;
 
     /// Get the bounds of the spreadsheet
     fn get_bounds(&self) -> (u32, u32);
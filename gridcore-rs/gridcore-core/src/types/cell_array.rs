@@ -0,0 +1,148 @@
+use super::{CellValue, ErrorType};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Rows and columns of values, such as a formula over a range evaluates
+/// to. A stored formula whose result has more than one value spills it
+/// over the cells right of and below its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CellArray {
+    rows: usize,
+    cols: usize,
+    /// Values row by row
+    values: Vec<CellValue>,
+}
+
+impl CellArray {
+    /// An array of `rows` by `cols` from its values row by row; `None` when
+    /// the count does not match or the array would be empty
+    pub fn new(rows: usize, cols: usize, values: Vec<CellValue>) -> Option<Self> {
+        (rows > 0 && cols > 0 && values.len() == rows * cols).then_some(Self { rows, cols, values })
+    }
+
+    /// An array of `rows` by `cols` holding `value(row, col)` at each
+    /// position. Both are at least 1.
+    pub fn from_fn(
+        rows: usize,
+        cols: usize,
+        mut value: impl FnMut(usize, usize) -> CellValue,
+    ) -> Self {
+        let (rows, cols) = (rows.max(1), cols.max(1));
+        let values = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .map(|(row, col)| value(row, col))
+            .collect();
+        Self { rows, cols, values }
+    }
+
+    /// A single value
+    pub fn scalar(value: CellValue) -> Self {
+        Self {
+            rows: 1,
+            cols: 1,
+            values: vec![value],
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_scalar(&self) -> bool {
+        self.values.len() == 1
+    }
+
+    /// Values row by row
+    pub fn values(&self) -> &[CellValue] {
+        &self.values
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&CellValue> {
+        (row < self.rows && col < self.cols).then(|| &self.values[row * self.cols + col])
+    }
+
+    /// The value at `row`, `col` of an array stretched to a larger size: a
+    /// single row or column repeats, and positions past the end of a
+    /// longer one are #N/A
+    pub fn broadcast(&self, row: usize, col: usize) -> CellValue {
+        let row = if self.rows == 1 { 0 } else { row };
+        let col = if self.cols == 1 { 0 } else { col };
+        self.get(row, col)
+            .cloned()
+            .unwrap_or_else(|| CellValue::from_error(ErrorType::NotAvailable))
+    }
+
+    /// The same shape with `f` applied to every value
+    pub fn map(&self, f: impl FnMut(&CellValue) -> CellValue) -> Self {
+        Self {
+            rows: self.rows,
+            cols: self.cols,
+            values: self.values.iter().map(f).collect(),
+        }
+    }
+
+    /// Rows turned into columns
+    pub fn transpose(&self) -> Self {
+        let values = (0..self.cols)
+            .flat_map(|col| (0..self.rows).map(move |row| (row, col)))
+            .map(|(row, col)| self.values[row * self.cols + col].clone())
+            .collect();
+        Self {
+            rows: self.cols,
+            cols: self.rows,
+            values,
+        }
+    }
+
+    /// The value itself for a single value, otherwise an array of all the
+    /// values, as functions take ranges
+    pub fn into_value(mut self) -> CellValue {
+        if self.is_scalar() {
+            self.values.pop().unwrap_or_default()
+        } else {
+            CellValue::Array(Arc::new(self.values))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(rows: usize, cols: usize) -> CellArray {
+        let values = (1..=rows * cols)
+            .map(|n| CellValue::Number(n as f64))
+            .collect();
+        CellArray::new(rows, cols, values).unwrap()
+    }
+
+    #[test]
+    fn test_shape_transpose_and_broadcast() {
+        assert!(CellArray::new(2, 2, vec![CellValue::Empty]).is_none());
+        assert!(CellArray::new(0, 1, Vec::new()).is_none());
+
+        let array = numbers(2, 3);
+        let transposed = array.transpose();
+        assert_eq!((transposed.rows(), transposed.cols()), (3, 2));
+        assert_eq!(transposed.get(2, 1), Some(&CellValue::Number(6.0)));
+        assert_eq!(transposed.transpose(), array);
+
+        // A column repeats across; a short column runs out into #N/A
+        let column = numbers(2, 1);
+        assert_eq!(column.broadcast(1, 5), CellValue::Number(2.0));
+        assert_eq!(
+            column.broadcast(2, 0),
+            CellValue::from_error(ErrorType::NotAvailable)
+        );
+
+        assert_eq!(
+            CellArray::scalar(CellValue::Number(1.0)).into_value(),
+            CellValue::Number(1.0)
+        );
+        assert!(matches!(array.into_value(), CellValue::Array(values) if values.len() == 6));
+    }
+}
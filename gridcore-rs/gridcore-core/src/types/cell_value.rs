@@ -1,4 +1,5 @@
 use super::ErrorType;
+use crate::sparkline::Sparkline;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::sync::Arc;
@@ -15,6 +16,8 @@ pub enum CellValue {
     Empty,
     Error(Arc<ErrorType>),
     Array(Arc<Vec<CellValue>>),
+    /// Result of a `SPARKLINE` formula: a chart drawn in place of text
+    Sparkline(Arc<Sparkline>),
 }
 
 impl CellValue {
@@ -108,6 +111,7 @@ impl CellValue {
             CellValue::Empty => "empty",
             CellValue::Error(_) => "error",
             CellValue::Array(_) => "array",
+            CellValue::Sparkline(_) => "sparkline",
         }
     }
 
@@ -120,6 +124,7 @@ impl CellValue {
             CellValue::Empty => String::new(),
             CellValue::Error(e) => e.excel_code().to_string(),
             CellValue::Array(arr) => format!("{:?}", arr),
+            CellValue::Sparkline(sparkline) => sparkline.to_string(),
         }
     }
 }
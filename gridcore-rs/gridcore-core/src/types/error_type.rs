@@ -19,6 +19,10 @@ pub enum ErrorType {
         cells: Vec<CellAddress>,
     },
     NumError,
+    /// An intersection of areas that share no cells
+    NullIntersection,
+    /// A lookup found no matching value
+    NotAvailable,
     ParseError {
         message: String,
     },
@@ -32,6 +36,16 @@ pub enum ErrorType {
     InvalidOperation {
         message: String,
     },
+    /// An external data request is still waiting for the host
+    GettingData,
+    /// The host could not fetch or read external data
+    ExternalData {
+        message: String,
+    },
+    /// An array result has no room to spill: the listed cells hold data
+    Spill {
+        blocked_by: Vec<CellAddress>,
+    },
 }
 
 impl ErrorType {
@@ -44,10 +58,15 @@ impl ErrorType {
             ErrorType::ValueError { .. } => "#VALUE!",
             ErrorType::CircularDependency { .. } => "#CIRC!",
             ErrorType::NumError => "#NUM!",
+            ErrorType::NullIntersection => "#NULL!",
+            ErrorType::NotAvailable => "#N/A",
             ErrorType::ParseError { .. } => "#ERROR!",
             ErrorType::InvalidRange { .. } => "#REF!",
             ErrorType::InvalidArguments { .. } => "#VALUE!",
             ErrorType::InvalidOperation { .. } => "#ERROR!",
+            ErrorType::GettingData => "#GETTING_DATA",
+            ErrorType::ExternalData { .. } => "#VALUE!",
+            ErrorType::Spill { .. } => "#SPILL!",
         }
     }
 
@@ -75,12 +94,19 @@ impl ErrorType {
                 }
             }
             ErrorType::NumError => "Numeric calculation error".to_string(),
+            ErrorType::NullIntersection => "The intersected areas share no cells".to_string(),
+            ErrorType::NotAvailable => "No value matches the lookup".to_string(),
             ErrorType::ParseError { message } => format!("Parse error: {}", message),
             ErrorType::InvalidRange { range } => format!("Invalid range: {}", range),
             ErrorType::InvalidArguments { function, message } => {
                 format!("Invalid arguments for {}: {}", function, message)
             }
             ErrorType::InvalidOperation { message } => format!("Invalid operation: {}", message),
+            ErrorType::GettingData => "Waiting for external data".to_string(),
+            ErrorType::ExternalData { message } => format!("External data error: {}", message),
+            ErrorType::Spill { blocked_by } => {
+                format!("Spill range isn't blank: {}", cell_list(blocked_by))
+            }
         }
     }
 
@@ -88,6 +114,34 @@ impl ErrorType {
     pub fn full_display(&self) -> String {
         format!("{} - {}", self.excel_code(), self.description())
     }
+
+    /// The cells of a circular reference as a path back to where it
+    /// started, e.g. `A1 → B2 → A1`. `None` for other errors and for
+    /// cycles whose cells are not known.
+    pub fn cycle_path(&self) -> Option<String> {
+        match self {
+            ErrorType::CircularDependency { cells } if !cells.is_empty() => Some(cycle_text(cells)),
+            _ => None,
+        }
+    }
+}
+
+/// Cells joined into a path, e.g. `A1 → B2 → A1`
+pub(crate) fn cycle_text(cells: &[CellAddress]) -> String {
+    cells
+        .iter()
+        .map(CellAddress::to_string)
+        .collect::<Vec<_>>()
+        .join(" → ")
+}
+
+/// Cells separated by commas, e.g. `C3, C4`
+pub(crate) fn cell_list(cells: &[CellAddress]) -> String {
+    cells
+        .iter()
+        .map(CellAddress::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl std::fmt::Display for ErrorType {
@@ -159,6 +213,21 @@ mod tests {
             .excel_code(),
             "#ERROR!"
         );
+        assert_eq!(ErrorType::GettingData.excel_code(), "#GETTING_DATA");
+        assert_eq!(
+            ErrorType::Spill {
+                blocked_by: vec![CellAddress::new(2, 3)]
+            }
+            .excel_code(),
+            "#SPILL!"
+        );
+        assert_eq!(
+            ErrorType::ExternalData {
+                message: "HTTP 404".to_string()
+            }
+            .excel_code(),
+            "#VALUE!"
+        );
     }
 
     #[test]
@@ -386,6 +455,12 @@ mod tests {
         assert_eq!(error.excel_code(), "#CIRC!");
         assert!(error.description().contains("A1, B1, C1, A1"));
         assert!(error.full_display().contains("#CIRC!"));
+        assert_eq!(error.cycle_path().as_deref(), Some("A1 → B1 → C1 → A1"));
+        assert_eq!(
+            ErrorType::CircularDependency { cells: vec![] }.cycle_path(),
+            None
+        );
+        assert_eq!(ErrorType::NumError.cycle_path(), None);
         assert!(
             error
                 .full_display()
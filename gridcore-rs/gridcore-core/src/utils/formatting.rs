@@ -7,10 +7,11 @@ pub fn format_cell_value(value: CellValue) -> String {
     match value {
         CellValue::Number(n) => n.to_string(),
         CellValue::String(s) => s.as_ref().clone(),
-        CellValue::Boolean(b) => b.to_string(),
+        CellValue::Boolean(b) => b.to_string().to_uppercase(),
         CellValue::Error(e) => format!("#{}", e),
         CellValue::Empty => String::new(),
         CellValue::Array(arr) => format_array(&arr),
+        CellValue::Sparkline(_) => String::new(),
     }
 }
 
@@ -1,9 +1,21 @@
+pub mod names;
+pub mod settings;
 pub mod sheet;
 pub mod sheet_manager;
+pub mod sheet_name;
 pub mod types;
 
+pub use self::names::{
+    DefinedName, NameDefinition, NameScope, NamedConstant, NamedRange, NamedRangeRegistry,
+    VisibleNames,
+};
+pub use self::settings::{Determinism, OverlongInput, WorkbookSettings};
 pub use self::sheet::{Sheet, SheetProperties};
 pub use self::sheet_manager::SheetManager;
+pub use self::sheet_name::{
+    quote_sheet_name, sheet_reference, split_sheet_reference, unquote_sheet_name,
+    validate_sheet_name,
+};
 pub use self::types::{Workbook, WorkbookMetadata};
 
 #[cfg(test)]
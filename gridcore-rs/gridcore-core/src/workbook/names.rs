@@ -0,0 +1,240 @@
+//! Defined names: named ranges and named constants
+//!
+//! Names are matched without regard to case, so both sheets and the workbook
+//! key their names by [`name_key`] and keep the spelling they were defined
+//! with for display.
+
+use crate::formula::CellRange;
+use crate::formula::tokenizer::Tokenizer;
+use crate::references::StructuralOperation;
+use crate::types::{CellValue, ErrorType};
+use crate::{Result, SpreadsheetError};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Where a defined name is visible. A sheet-scoped name shadows a
+/// workbook-scoped name with the same spelling on that sheet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", content = "sheet", rename_all = "camelCase")]
+pub enum NameScope {
+    Workbook,
+    Sheet(String),
+}
+
+/// A name standing for a fixed value, e.g. `TaxRate = 0.21`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedConstant {
+    pub name: String,
+    pub value: CellValue,
+    /// Text the value was evaluated from when it was defined
+    pub definition: String,
+}
+
+/// A name standing for a block of cells, e.g. `Sales = B2:B20`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedRange {
+    pub name: String,
+    /// Sheet the cells are on
+    pub sheet: String,
+    /// The cells, `None` once all of them were deleted; formulas using the
+    /// name then show #REF!
+    pub range: Option<CellRange>,
+}
+
+/// Named ranges of one scope, keyed by [`name_key`]
+#[derive(Debug, Clone, Default)]
+pub struct NamedRangeRegistry {
+    ranges: FxHashMap<String, NamedRange>,
+}
+
+/// What the names visible to a formula stand for, keyed by [`name_key`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VisibleNames {
+    /// Constants, and names whose range was deleted as #REF!
+    pub values: HashMap<String, CellValue>,
+    /// Ranges on the formula's own sheet
+    pub ranges: HashMap<String, CellRange>,
+}
+
+/// What a defined name refers to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NameDefinition {
+    Range {
+        sheet: String,
+        range: Option<CellRange>,
+    },
+    Constant {
+        value: CellValue,
+        definition: String,
+    },
+}
+
+/// One entry of the names manager
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DefinedName {
+    pub name: String,
+    pub scope: NameScope,
+    pub definition: NameDefinition,
+}
+
+impl NamedConstant {
+    pub fn new(name: impl Into<String>, value: CellValue, definition: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value,
+            definition: definition.into(),
+        }
+    }
+}
+
+impl NamedRange {
+    pub fn new(name: impl Into<String>, sheet: impl Into<String>, range: CellRange) -> Self {
+        Self {
+            name: name.into(),
+            sheet: sheet.into(),
+            range: Some(range),
+        }
+    }
+}
+
+impl NamedRangeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define or redefine a range, returning the one it replaced
+    pub fn define(&mut self, range: NamedRange) -> Option<NamedRange> {
+        self.ranges.insert(name_key(&range.name), range)
+    }
+
+    /// Get a named range, ignoring case
+    pub fn get(&self, name: &str) -> Option<&NamedRange> {
+        self.ranges.get(&name_key(name))
+    }
+
+    /// Remove a named range, ignoring case
+    pub fn remove(&mut self, name: &str) -> Option<NamedRange> {
+        self.ranges.remove(&name_key(name))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &NamedRange> {
+        self.ranges.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Move the ranges on `sheet` the way formula references move for
+    /// `operation` on it: insertions inside a range grow it, deletions
+    /// shrink it, and a range whose cells were all deleted is lost
+    pub fn apply_structural_operation(&mut self, sheet: &str, operation: &StructuralOperation) {
+        for named in self.ranges.values_mut() {
+            if named.sheet == sheet {
+                named.range = named
+                    .range
+                    .and_then(|range| operation.shift_reference(&range));
+            }
+        }
+    }
+
+    /// Point the ranges on `old_name` at `new_name`
+    pub fn rename_sheet(&mut self, old_name: &str, new_name: &str) {
+        for named in self.ranges.values_mut() {
+            if named.sheet == old_name {
+                named.sheet = new_name.to_string();
+            }
+        }
+    }
+
+    /// Drop the ranges on `sheet`
+    pub fn remove_sheet(&mut self, sheet: &str) {
+        self.ranges.retain(|_, named| named.sheet != sheet);
+    }
+}
+
+impl VisibleNames {
+    /// Let `named` stand for its range in formulas on `sheet`, over any
+    /// name of the same spelling added before
+    pub fn insert_range(&mut self, named: &NamedRange, sheet: &str) {
+        let key = name_key(&named.name);
+        match named.range {
+            Some(range) if named.sheet == sheet => {
+                self.values.remove(&key);
+                self.ranges.insert(key, range);
+            }
+            // Other sheets cannot be read from here
+            Some(_) => {}
+            None => {
+                self.ranges.remove(&key);
+                let error = ErrorType::InvalidRef {
+                    reference: named.name.clone(),
+                };
+                self.values.insert(key, CellValue::from_error(error));
+            }
+        }
+    }
+
+    /// Let `constant` stand for its value, over any name of the same
+    /// spelling added before
+    pub fn insert_constant(&mut self, constant: &NamedConstant) {
+        let key = name_key(&constant.name);
+        self.ranges.remove(&key);
+        self.values.insert(key, constant.value.clone());
+    }
+}
+
+/// Key a name is stored under
+pub fn name_key(name: &str) -> String {
+    name.to_uppercase()
+}
+
+/// Check that `name` can be used in formulas
+pub fn validate_name(name: &str) -> Result<()> {
+    if Tokenizer::is_name(name) {
+        Ok(())
+    } else {
+        Err(SpreadsheetError::InvalidOperation(format!(
+            "'{}' is not a valid name: use letters, digits and underscores, \
+             and avoid names that read as cell references",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("TaxRate").is_ok());
+        assert!(validate_name("_rate_2024").is_ok());
+        for invalid in ["", "Tax1", "XFD100", "true", "2nd", "Tax Rate", "Tax.Rate"] {
+            assert!(
+                validate_name(invalid).is_err(),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_defined_name_round_trips_through_json() {
+        let name = DefinedName {
+            name: "TaxRate".to_string(),
+            scope: NameScope::Sheet("Sheet1".to_string()),
+            definition: NameDefinition::Constant {
+                value: CellValue::Number(0.21),
+                definition: "0.21".to_string(),
+            },
+        };
+        let json = serde_json::to_string(&name).unwrap();
+        assert_eq!(serde_json::from_str::<DefinedName>(&json).unwrap(), name);
+    }
+}
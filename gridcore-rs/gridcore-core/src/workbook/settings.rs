@@ -0,0 +1,185 @@
+use crate::formula::AutocorrectSettings;
+use crate::references::ReferenceStyle;
+use crate::{Result, SpreadsheetError};
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Longest text a cell holds by default, as in Excel
+pub const MAX_TEXT_LENGTH: usize = 32_767;
+/// Longest formula by default, as in Excel
+pub const MAX_FORMULA_LENGTH: usize = 8_192;
+
+/// What happens to input longer than the limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlongInput {
+    /// Refuse it with [`SpreadsheetError::InputTooLong`]
+    #[default]
+    Reject,
+    /// Keep the text up to the limit; the caller warns about it. Formulas
+    /// are still refused, since a cut formula is a different formula.
+    Truncate,
+}
+
+/// Settings that hold for every sheet of a workbook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkbookSettings {
+    /// Characters a cell's text may have
+    pub max_text_length: usize,
+    /// Characters a formula may have after its `=`
+    pub max_formula_length: usize,
+    pub overlong_input: OverlongInput,
+    /// Index looked-up columns so exact-match lookups over long ranges
+    /// need not scan them
+    pub lookup_index: bool,
+    /// Give unformatted cells the format their input was typed in, e.g.
+    /// currency for `$5.00`
+    pub infer_formats: bool,
+    /// Fix common mistakes in typed formulas; off by default
+    pub autocorrect: AutocorrectSettings,
+    /// Refuse to commit typed formulas that do not parse, rather than
+    /// storing them to show an error; off by default
+    pub strict_formulas: bool,
+    /// Make volatile functions reproducible; off by default
+    pub deterministic: Option<Determinism>,
+    /// How formulas are displayed; they are stored in A1 notation
+    pub reference_style: ReferenceStyle,
+}
+
+/// Deterministic mode: RAND and RANDBETWEEN draw from a seeded generator
+/// and NOW and TODAY read a frozen time, so replaying the same edits
+/// computes the same values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Determinism {
+    /// Seeds the generator; written as hex text, which survives JSON
+    /// readers that hold numbers as doubles
+    #[serde(serialize_with = "hex_seed", deserialize_with = "seed_from_hex")]
+    pub seed: u64,
+    /// Milliseconds since the Unix epoch, read as wall-clock time
+    pub now_ms: i64,
+}
+
+impl Determinism {
+    /// The time NOW reads
+    pub fn now(&self) -> NaiveDateTime {
+        DateTime::from_timestamp_millis(self.now_ms)
+            .unwrap_or_default()
+            .naive_utc()
+    }
+}
+
+fn hex_seed<S: Serializer>(seed: &u64, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:016x}", seed))
+}
+
+fn seed_from_hex<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u64, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    u64::from_str_radix(&text, 16).map_err(serde::de::Error::custom)
+}
+
+impl Default for WorkbookSettings {
+    fn default() -> Self {
+        Self {
+            max_text_length: MAX_TEXT_LENGTH,
+            max_formula_length: MAX_FORMULA_LENGTH,
+            overlong_input: OverlongInput::Reject,
+            lookup_index: true,
+            infer_formats: true,
+            autocorrect: AutocorrectSettings::default(),
+            strict_formulas: false,
+            deterministic: None,
+            reference_style: ReferenceStyle::A1,
+        }
+    }
+}
+
+impl WorkbookSettings {
+    /// The part of `input` to store: all of it when it is within the
+    /// limits, the text up to the limit when truncating, an error otherwise
+    pub fn fit<'a>(&self, input: &'a str) -> Result<&'a str> {
+        match input.strip_prefix('=') {
+            Some(formula) => self
+                .cut(formula, self.max_formula_length, true)
+                .map(|_| input),
+            None => self.fit_text(input),
+        }
+    }
+
+    /// [`Self::fit`] for input stored as text even when it starts with `=`
+    pub fn fit_text<'a>(&self, text: &'a str) -> Result<&'a str> {
+        self.cut(text, self.max_text_length, false)
+    }
+
+    fn cut<'a>(&self, text: &'a str, limit: usize, formula: bool) -> Result<&'a str> {
+        let Some((end, _)) = text.char_indices().nth(limit) else {
+            return Ok(text);
+        };
+        if formula || self.overlong_input == OverlongInput::Reject {
+            return Err(SpreadsheetError::InputTooLong {
+                length: text.chars().count(),
+                limit,
+                formula,
+            });
+        }
+        Ok(&text[..end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(overlong_input: OverlongInput) -> WorkbookSettings {
+        WorkbookSettings {
+            max_text_length: 5,
+            max_formula_length: 4,
+            overlong_input,
+            lookup_index: true,
+            infer_formats: true,
+            autocorrect: AutocorrectSettings::default(),
+            strict_formulas: false,
+            deterministic: None,
+            reference_style: ReferenceStyle::A1,
+        }
+    }
+
+    #[test]
+    fn test_determinism_round_trips_its_seed_as_hex() {
+        let determinism = Determinism {
+            seed: u64::MAX - 1,
+            now_ms: 1_700_000_000_000,
+        };
+        let json = serde_json::to_string(&determinism).unwrap();
+        assert_eq!(
+            json,
+            r#"{"seed":"fffffffffffffffe","now_ms":1700000000000}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Determinism>(&json).unwrap(),
+            determinism
+        );
+        assert_eq!(determinism.now().to_string(), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn test_fit_counts_characters() {
+        let reject = settings(OverlongInput::Reject);
+        assert_eq!(reject.fit("héllo").unwrap(), "héllo");
+        assert_eq!(reject.fit("=1+23").unwrap(), "=1+23");
+        assert!(matches!(
+            reject.fit("héllo!"),
+            Err(SpreadsheetError::InputTooLong {
+                length: 6,
+                limit: 5,
+                formula: false
+            })
+        ));
+
+        let truncate = settings(OverlongInput::Truncate);
+        assert_eq!(truncate.fit("héllo, world").unwrap(), "héllo");
+        assert_eq!(truncate.fit_text("=1+234").unwrap(), "=1+23");
+        assert!(matches!(
+            truncate.fit("=1+234"),
+            Err(SpreadsheetError::InputTooLong { formula: true, .. })
+        ));
+    }
+}
@@ -1,9 +1,14 @@
+use super::names::{NamedConstant, NamedRange, NamedRangeRegistry, name_key};
 use crate::Result;
+use crate::conditional::ConditionalFormatRule;
 use crate::dependency::DependencyGraph;
-use crate::domain::Cell;
+use crate::domain::{Cell, FormatStore};
+use crate::formula::CellRange;
+use crate::pivot::PivotDefinition;
 use crate::ports::RepositoryPort;
 use crate::types::CellAddress;
 use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
 
 /// Properties for a spreadsheet sheet
@@ -23,6 +28,9 @@ pub struct SheetProperties {
     pub default_row_height: f64,
     /// Sheet color (for tab)
     pub tab_color: Option<String>,
+    /// Whether edits recalculate the sheet's formulas. When off, formulas
+    /// that need it are only marked stale until the sheet is recalculated.
+    pub calculation_enabled: bool,
 }
 
 impl Default for SheetProperties {
@@ -35,6 +43,7 @@ impl Default for SheetProperties {
             default_column_width: 100.0,
             default_row_height: 20.0,
             tab_color: None,
+            calculation_enabled: true,
         }
     }
 }
@@ -49,8 +58,19 @@ pub struct Sheet {
     dependencies: Arc<Mutex<DependencyGraph>>,
     /// Sheet properties
     properties: SheetProperties,
-    /// Named ranges in this sheet
-    named_ranges: FxHashMap<String, Vec<CellAddress>>,
+    /// Named ranges scoped to this sheet
+    named_ranges: NamedRangeRegistry,
+    /// Named constants scoped to this sheet, keyed by [`name_key`]
+    constants: FxHashMap<String, NamedConstant>,
+    /// Cell, row and column display formats
+    formats: FormatStore,
+    /// Pivot outputs in this sheet, keyed by their top-left cell
+    pivots: FxHashMap<CellAddress, PivotDefinition>,
+    /// Data bars and color scales, earliest first
+    conditional_formats: Vec<ConditionalFormatRule>,
+    /// Sidecar sections loaded without being understood, written back when
+    /// the sheet is exported
+    preserved_sections: BTreeMap<String, serde_json::Value>,
 }
 
 impl Sheet {
@@ -62,7 +82,12 @@ impl Sheet {
             cells: Arc::new(RepositoryAdapter::new_empty()),
             dependencies: Arc::new(Mutex::new(DependencyGraph::new())),
             properties: SheetProperties::default(),
-            named_ranges: FxHashMap::default(),
+            named_ranges: NamedRangeRegistry::new(),
+            constants: FxHashMap::default(),
+            formats: FormatStore::default(),
+            pivots: FxHashMap::default(),
+            conditional_formats: Vec::new(),
+            preserved_sections: BTreeMap::new(),
         }
     }
 
@@ -74,7 +99,12 @@ impl Sheet {
             cells: Arc::new(RepositoryAdapter::new_empty()),
             dependencies: Arc::new(Mutex::new(DependencyGraph::new())),
             properties,
-            named_ranges: FxHashMap::default(),
+            named_ranges: NamedRangeRegistry::new(),
+            constants: FxHashMap::default(),
+            formats: FormatStore::default(),
+            pivots: FxHashMap::default(),
+            conditional_formats: Vec::new(),
+            preserved_sections: BTreeMap::new(),
         }
     }
 
@@ -85,7 +115,12 @@ impl Sheet {
             cells: repository,
             dependencies: Arc::new(Mutex::new(DependencyGraph::new())),
             properties: SheetProperties::default(),
-            named_ranges: FxHashMap::default(),
+            named_ranges: NamedRangeRegistry::new(),
+            constants: FxHashMap::default(),
+            formats: FormatStore::default(),
+            pivots: FxHashMap::default(),
+            conditional_formats: Vec::new(),
+            preserved_sections: BTreeMap::new(),
         }
     }
 
@@ -96,7 +131,9 @@ impl Sheet {
 
     /// Rename the sheet
     pub fn rename(&mut self, new_name: impl Into<String>) -> Result<()> {
-        self.name = new_name.into();
+        let new_name = new_name.into();
+        self.named_ranges.rename_sheet(&self.name, &new_name);
+        self.name = new_name;
         Ok(())
     }
 
@@ -130,6 +167,45 @@ impl Sheet {
         self.cells.set(address, cell)
     }
 
+    /// Get the display formats of this sheet
+    pub fn formats(&self) -> &FormatStore {
+        &self.formats
+    }
+
+    /// Get mutable access to the display formats of this sheet
+    pub fn formats_mut(&mut self) -> &mut FormatStore {
+        &mut self.formats
+    }
+
+    /// Get the pivots defined in this sheet, keyed by anchor cell
+    pub fn pivots(&self) -> &FxHashMap<CellAddress, PivotDefinition> {
+        &self.pivots
+    }
+
+    /// Get mutable access to the pivots defined in this sheet
+    pub fn pivots_mut(&mut self) -> &mut FxHashMap<CellAddress, PivotDefinition> {
+        &mut self.pivots
+    }
+
+    /// Get the conditional formats of this sheet; earlier rules win
+    pub fn conditional_formats(&self) -> &[ConditionalFormatRule] {
+        &self.conditional_formats
+    }
+
+    /// Get mutable access to the conditional formats of this sheet
+    pub fn conditional_formats_mut(&mut self) -> &mut Vec<ConditionalFormatRule> {
+        &mut self.conditional_formats
+    }
+
+    /// Sidecar sections kept from the last lenient import, by name
+    pub fn preserved_sections(&self) -> &BTreeMap<String, serde_json::Value> {
+        &self.preserved_sections
+    }
+
+    pub fn preserved_sections_mut(&mut self) -> &mut BTreeMap<String, serde_json::Value> {
+        &mut self.preserved_sections
+    }
+
     /// Get the cell repository
     pub fn cells(&self) -> Arc<dyn RepositoryPort> {
         self.cells.clone()
@@ -168,21 +244,52 @@ impl Sheet {
             .unwrap_or(self.properties.default_row_height)
     }
 
-    /// Add a named range
-    pub fn add_named_range(&mut self, name: impl Into<String>, addresses: Vec<CellAddress>) {
-        self.named_ranges.insert(name.into(), addresses);
+    /// Define or redefine a named range of this sheet's cells
+    pub fn add_named_range(&mut self, name: impl Into<String>, range: CellRange) {
+        self.named_ranges
+            .define(NamedRange::new(name, self.name.clone(), range));
     }
 
-    /// Get a named range
-    pub fn get_named_range(&self, name: &str) -> Option<&Vec<CellAddress>> {
+    /// Get a named range, ignoring case
+    pub fn get_named_range(&self, name: &str) -> Option<&NamedRange> {
         self.named_ranges.get(name)
     }
 
-    /// Remove a named range
-    pub fn remove_named_range(&mut self, name: &str) -> Option<Vec<CellAddress>> {
+    /// Remove a named range, ignoring case
+    pub fn remove_named_range(&mut self, name: &str) -> Option<NamedRange> {
         self.named_ranges.remove(name)
     }
 
+    /// Iterate over the named ranges in this sheet
+    pub fn named_ranges(&self) -> impl Iterator<Item = &NamedRange> {
+        self.named_ranges.iter()
+    }
+
+    /// Named ranges of this sheet, for adjusting them
+    pub fn named_ranges_mut(&mut self) -> &mut NamedRangeRegistry {
+        &mut self.named_ranges
+    }
+
+    /// Define or redefine a named constant scoped to this sheet
+    pub fn define_constant(&mut self, constant: NamedConstant) {
+        self.constants.insert(name_key(&constant.name), constant);
+    }
+
+    /// Get a named constant, ignoring case
+    pub fn get_constant(&self, name: &str) -> Option<&NamedConstant> {
+        self.constants.get(&name_key(name))
+    }
+
+    /// Remove a named constant, ignoring case
+    pub fn remove_constant(&mut self, name: &str) -> Option<NamedConstant> {
+        self.constants.remove(&name_key(name))
+    }
+
+    /// Iterate over the named constants scoped to this sheet
+    pub fn constants(&self) -> impl Iterator<Item = &NamedConstant> {
+        self.constants.values()
+    }
+
     /// Clear all cells in the sheet
     pub fn clear(&self) {
         // Clear the repository
@@ -212,8 +319,12 @@ impl Sheet {
             let _ = new_repo.set(&address, cell);
         }
 
+        let new_name = new_name.into();
+        let mut named_ranges = self.named_ranges.clone();
+        named_ranges.rename_sheet(&self.name, &new_name);
+
         Self {
-            name: new_name.into(),
+            name: new_name,
             cells: new_repo as Arc<dyn RepositoryPort>,
             dependencies: Arc::new(Mutex::new(
                 self.dependencies
@@ -222,7 +333,12 @@ impl Sheet {
                     .clone(),
             )),
             properties: self.properties.clone(),
-            named_ranges: self.named_ranges.clone(),
+            named_ranges,
+            constants: self.constants.clone(),
+            formats: self.formats.clone(),
+            pivots: self.pivots.clone(),
+            conditional_formats: self.conditional_formats.clone(),
+            preserved_sections: self.preserved_sections.clone(),
         }
     }
 }
@@ -257,17 +373,17 @@ mod tests {
     #[test]
     fn test_named_ranges() {
         let mut sheet = Sheet::new("Sheet1");
-        let range = vec![
-            CellAddress::new(0, 0),
-            CellAddress::new(1, 0),
-            CellAddress::new(2, 0),
-        ];
-        sheet.add_named_range("MyRange", range.clone());
+        let range = CellRange::new(CellAddress::new(0, 0), CellAddress::new(2, 0));
+        sheet.add_named_range("MyRange", range);
+
+        let named = sheet.get_named_range("myrange").unwrap();
+        assert_eq!((named.sheet.as_str(), named.range), ("Sheet1", Some(range)));
 
-        assert_eq!(sheet.get_named_range("MyRange"), Some(&range));
+        sheet.rename("Data").unwrap();
+        assert_eq!(sheet.get_named_range("MyRange").unwrap().sheet, "Data");
 
-        let removed = sheet.remove_named_range("MyRange");
-        assert_eq!(removed, Some(range));
+        let removed = sheet.remove_named_range("MYRANGE").unwrap();
+        assert_eq!(removed.name, "MyRange");
         assert_eq!(sheet.get_named_range("MyRange"), None);
     }
 }
@@ -1,8 +1,10 @@
 use super::Workbook;
+use super::sheet_name::sheet_reference;
 use crate::domain::Cell;
-use crate::references::{ReferenceAdjuster, StructuralOperation};
+use crate::references::{Displacement, ReferenceAdjuster, StructuralOperation};
 use crate::types::{CellAddress, CellValue};
 use crate::{Result, SpreadsheetError};
+use std::collections::HashSet;
 
 /// Manages operations across multiple sheets
 pub struct SheetManager {
@@ -58,24 +60,46 @@ impl SheetManager {
             for row in start.row..=end.row {
                 for col in start.col..=end.col {
                     let addr = CellAddress::new(col, row);
-                    if let Some(cell) = source.get_cell(&addr) {
+                    // A styled cell keeps its style and only its own format.
+                    // Otherwise the effective format travels as an explicit
+                    // format, so cells pasted outside a formatted column keep
+                    // looking the same.
+                    let formats = source.formats();
+                    let style = formats.cell_style(&addr).map(str::to_string);
+                    let format = match style {
+                        Some(_) => formats.cell_format(&addr).cloned(),
+                        None => formats
+                            .effective_format(&addr, self.workbook.styles())
+                            .cloned(),
+                    };
+                    let cell = source.get_cell(&addr);
+                    if cell.is_some() || format.is_some() || style.is_some() {
                         let offset_row = row - start.row;
                         let offset_col = col - start.col;
-                        cells_to_copy.push((offset_row, offset_col, cell));
+                        cells_to_copy.push((offset_row, offset_col, cell, format, style));
                     }
                 }
             }
         }
 
         // Set cells in target sheet
-        let target = self.workbook.get_sheet(target_sheet).ok_or_else(|| {
+        let target = self.workbook.get_sheet_mut(target_sheet).ok_or_else(|| {
             SpreadsheetError::InvalidOperation(format!("Target sheet '{}' not found", target_sheet))
         })?;
 
-        for (offset_row, offset_col, cell) in cells_to_copy {
+        for (offset_row, offset_col, cell, format, style) in cells_to_copy {
             let target_addr =
                 CellAddress::new(target_start.col + offset_col, target_start.row + offset_row);
-            target.set_cell(&target_addr, cell)?;
+            if let Some(cell) = cell {
+                target.set_cell(&target_addr, cell)?;
+            }
+            target.formats_mut().set_cell_style(target_addr, style);
+            match format {
+                Some(format) => target.formats_mut().set_cell_format(target_addr, format),
+                None => {
+                    target.formats_mut().clear_cell_format(&target_addr);
+                }
+            }
         }
 
         Ok(())
@@ -109,12 +133,18 @@ impl SheetManager {
         Ok(())
     }
 
-    /// Apply a structural operation to all sheets
+    /// Adjust the formulas of every sheet for a structural operation on
+    /// `operated_sheet`, before its cells are moved
     pub fn apply_structural_operation_to_all(
         &mut self,
+        operated_sheet: &str,
         operation: StructuralOperation,
     ) -> Result<()> {
         let adjuster = ReferenceAdjuster::new();
+        self.workbook
+            .apply_structural_operation_to_names(operated_sheet, &operation);
+        self.workbook
+            .apply_structural_operation_to_formats(operated_sheet, &operation);
 
         for sheet_name in self.workbook.sheet_names().to_vec() {
             if let Some(sheet) = self.workbook.get_sheet(&sheet_name) {
@@ -126,7 +156,13 @@ impl SheetManager {
                     if cell.has_formula()
                         && let CellValue::String(formula_str) = &cell.raw_value
                         && formula_str.starts_with('=')
-                        && let Ok(adjusted) = adjuster.adjust_formula(formula_str, &operation)
+                        && let Ok(adjusted) = adjuster.adjust_formula_in_sheet(
+                            formula_str,
+                            &operation,
+                            &address,
+                            &sheet_name,
+                            operated_sheet,
+                        )
                         && adjusted != formula_str.as_ref().as_str()
                     {
                         adjusted_cells.push((address, adjusted));
@@ -151,6 +187,118 @@ impl SheetManager {
         Ok(())
     }
 
+    /// Adjust the formulas of every sheet for `operations` on
+    /// `operated_sheet`, applied in order after its cells were moved for
+    /// all of them. `written` lists the cells written in between, in
+    /// order, by the number of operations before the write, the sheet and
+    /// where the cell was written then. A written formula is adjusted only
+    /// for the operations after it; every other one for all of them, as if
+    /// each operation had been applied on its own.
+    pub fn apply_structural_batch(
+        &mut self,
+        operated_sheet: &str,
+        operations: &[StructuralOperation],
+        written: &[(usize, String, CellAddress)],
+    ) -> Result<()> {
+        for operation in operations {
+            self.workbook
+                .apply_structural_operation_to_names(operated_sheet, operation);
+            self.workbook
+                .apply_structural_operation_to_formats(operated_sheet, operation);
+        }
+        let adjuster = ReferenceAdjuster::new();
+        let formula_of = |sheet_name: &str, address: &CellAddress| {
+            let cell = self.workbook.get_sheet(sheet_name)?.cells().get(address)?;
+            match &cell.raw_value {
+                CellValue::String(formula) if cell.has_formula() && formula.starts_with('=') => {
+                    Some(formula.to_string())
+                }
+                _ => None,
+            }
+        };
+
+        // Later writes go first, so the last one to a cell is the one kept
+        let mut claimed = HashSet::new();
+        let mut adjusted_cells = Vec::new();
+        let mut displacement = Displacement::new();
+        let mut writes = written.iter().rev().peekable();
+        for epoch in (0..=operations.len()).rev() {
+            if let Some(operation) = operations.get(epoch) {
+                displacement.prepend(operation);
+            }
+            while let Some((_, sheet_name, address)) =
+                writes.next_if(|(before, ..)| *before == epoch)
+            {
+                let now = if sheet_name == operated_sheet {
+                    displacement.shift_address(address)
+                } else {
+                    Some(*address)
+                };
+                let Some(now) = now else {
+                    continue;
+                };
+                if !claimed.insert((sheet_name.as_str(), now)) {
+                    continue;
+                }
+                if let Some(formula) = formula_of(sheet_name, &now) {
+                    let adjusted = adjuster.adjust_formula_displaced(
+                        &formula,
+                        &displacement,
+                        address,
+                        sheet_name,
+                        operated_sheet,
+                    )?;
+                    if adjusted != formula {
+                        adjusted_cells.push((sheet_name.clone(), now, adjusted));
+                    }
+                }
+            }
+        }
+
+        for sheet_name in self.workbook.sheet_names() {
+            let Some(sheet) = self.workbook.get_sheet(sheet_name) else {
+                continue;
+            };
+            for (address, cell) in sheet.cells().get_all() {
+                if claimed.contains(&(sheet_name.as_str(), address)) {
+                    continue;
+                }
+                // Cells only reach inserted rows by being written
+                let was_at = if sheet_name == operated_sheet {
+                    displacement.origin(&address)
+                } else {
+                    Some(address)
+                };
+                if cell.has_formula()
+                    && let Some(was_at) = was_at
+                    && let CellValue::String(formula_str) = &cell.raw_value
+                    && formula_str.starts_with('=')
+                    && let Ok(adjusted) = adjuster.adjust_formula_displaced(
+                        formula_str,
+                        &displacement,
+                        &was_at,
+                        sheet_name,
+                        operated_sheet,
+                    )
+                    && adjusted != formula_str.as_ref().as_str()
+                {
+                    adjusted_cells.push((sheet_name.clone(), address, adjusted));
+                }
+            }
+        }
+
+        for (sheet_name, address, adjusted_formula) in adjusted_cells {
+            // Store the formula text without the leading '='
+            let formula_text = adjusted_formula[1..].to_string();
+            let new_cell =
+                Cell::with_formula(CellValue::from_string(adjusted_formula), formula_text);
+            if let Some(sheet) = self.workbook.get_sheet(&sheet_name) {
+                sheet.set_cell(&address, new_cell)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Find all cells that reference a specific cell across all sheets
     pub fn find_references_to(
         &self,
@@ -167,7 +315,7 @@ impl SheetManager {
                         // Check if this formula references the target
                         // Full formula parsing would be handled by the application layer
                         if let CellValue::String(formula) = &cell.raw_value {
-                            let target_ref = format!("{}!{}", target_sheet, target_address.to_a1());
+                            let target_ref = sheet_reference(target_sheet, &target_address.to_a1());
                             if formula.contains(&target_ref) {
                                 references.push((sheet_name.clone(), address));
                             }
@@ -297,6 +445,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_copy_cells_carries_effective_format() {
+        use crate::domain::CellFormat;
+
+        let mut manager = SheetManager::new();
+        manager.workbook_mut().create_sheet("Source").unwrap();
+        manager.workbook_mut().create_sheet("Target").unwrap();
+
+        let source = manager.workbook_mut().get_sheet_mut("Source").unwrap();
+        source
+            .set_cell(&CellAddress::new(1, 0), Cell::new(CellValue::Number(0.25)))
+            .unwrap();
+        source
+            .formats_mut()
+            .set_column_format(1, Some(CellFormat::percent(1)));
+
+        let range = vec![(CellAddress::new(1, 0), CellAddress::new(1, 0))];
+        manager
+            .copy_cells("Source", &range, "Target", &CellAddress::new(4, 0))
+            .unwrap();
+
+        // The column default of the source becomes an explicit format on the target
+        let target = manager.workbook().get_sheet("Target").unwrap();
+        let pasted = CellAddress::new(4, 0);
+        assert_eq!(
+            target.formats().cell_format(&pasted),
+            Some(&CellFormat::percent(1))
+        );
+        assert!(target.formats().column_format(4).is_none());
+        assert_eq!(
+            target.formats().format_value(
+                &pasted,
+                &CellValue::Number(0.25),
+                manager.workbook().styles()
+            ),
+            "25.0%"
+        );
+    }
+
     #[test]
     fn test_workbook_statistics() {
         let mut manager = SheetManager::new();
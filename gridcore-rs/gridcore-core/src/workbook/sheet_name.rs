@@ -0,0 +1,156 @@
+//! Sheet names and how they are written in references.
+//!
+//! A name that could be mistaken for something else in a formula is written
+//! in single quotes, with quotes inside it doubled: `'Q1 ''Draft'''!A1`.
+//! Plain names such as `Sheet1` are written as they are.
+
+use crate::types::CellAddress;
+use crate::{Result, SpreadsheetError};
+
+/// Longest allowed sheet name, in characters
+pub const MAX_SHEET_NAME_LEN: usize = 31;
+
+/// Characters a sheet name cannot contain
+pub const FORBIDDEN_SHEET_NAME_CHARS: &[char] = &['[', ']', ':', '*', '?', '/', '\\'];
+
+/// Regex matching a sheet name as written before `!`, quoted or plain
+pub(crate) const SHEET_NAME_PATTERN: &str = r"'(?:[^']|'')+'|[A-Za-z0-9_]+";
+
+/// Check that `name` can be used for a sheet
+pub fn validate_sheet_name(name: &str) -> Result<()> {
+    let problem = if name.trim().is_empty() {
+        Some("it cannot be blank".to_string())
+    } else if name.chars().count() > MAX_SHEET_NAME_LEN {
+        Some(format!(
+            "it is longer than {} characters",
+            MAX_SHEET_NAME_LEN
+        ))
+    } else if let Some(c) = name
+        .chars()
+        .find(|c| FORBIDDEN_SHEET_NAME_CHARS.contains(c) || c.is_control())
+    {
+        Some(format!("it contains {:?}", c))
+    } else if name.starts_with('\'') || name.ends_with('\'') {
+        Some("it cannot start or end with a quote".to_string())
+    } else {
+        None
+    };
+
+    match problem {
+        Some(problem) => Err(SpreadsheetError::InvalidOperation(format!(
+            "'{}' is not a valid sheet name: {}",
+            name, problem
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Whether `name` has to be quoted in a reference: anything but letters,
+/// digits and underscores, a leading digit, or a name that reads as a cell
+/// or a boolean
+pub fn sheet_name_needs_quotes(name: &str) -> bool {
+    let mut chars = name.chars();
+    let plain = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    !plain
+        || CellAddress::from_a1(&name.to_ascii_uppercase()).is_ok()
+        || name.eq_ignore_ascii_case("TRUE")
+        || name.eq_ignore_ascii_case("FALSE")
+}
+
+/// `name` as written before the `!` of a reference
+pub fn quote_sheet_name(name: &str) -> String {
+    if sheet_name_needs_quotes(name) {
+        format!("'{}'", name.replace('\'', "''"))
+    } else {
+        name.to_string()
+    }
+}
+
+/// The sheet name written as `text`, undoing [`quote_sheet_name`]. Returns
+/// `None` when the quotes are unbalanced.
+pub fn unquote_sheet_name(text: &str) -> Option<String> {
+    let Some(quoted) = text.strip_prefix('\'') else {
+        return (!text.is_empty() && !text.contains('\'')).then(|| text.to_string());
+    };
+    let inner = quoted.strip_suffix('\'')?;
+    let mut name = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\'' && chars.next() != Some('\'') {
+            return None;
+        }
+        name.push(c);
+    }
+    (!name.is_empty()).then_some(name)
+}
+
+/// A reference on another sheet, e.g. `'My Sheet'!A1:B2`
+pub fn sheet_reference(sheet: &str, reference: &str) -> String {
+    format!("{}!{}", quote_sheet_name(sheet), reference)
+}
+
+/// Split `'My Sheet'!A1` into the sheet name and the part after the `!`
+pub fn split_sheet_reference(text: &str) -> Option<(String, &str)> {
+    let end = if text.starts_with('\'') {
+        // Skip doubled quotes to find the closing one
+        let mut chars = text.char_indices().skip(1).peekable();
+        loop {
+            match chars.next()? {
+                (_, '\'') if chars.peek().is_some_and(|(_, c)| *c == '\'') => {
+                    chars.next();
+                }
+                (index, '\'') => break index + 1,
+                _ => {}
+            }
+        }
+    } else {
+        text.find('!')?
+    };
+    let rest = text[end..].strip_prefix('!')?;
+    Some((unquote_sheet_name(&text[..end])?, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_sheet_name() {
+        assert!(validate_sheet_name("Q1 'Draft' Résumé").is_ok());
+        assert!(validate_sheet_name(&"x".repeat(MAX_SHEET_NAME_LEN)).is_ok());
+        for invalid in ["", "  ", "a/b", "[Book]", "Sum:", "'Quoted", "Tab\t"] {
+            assert!(
+                validate_sheet_name(invalid).is_err(),
+                "{invalid:?} should be rejected"
+            );
+        }
+        assert!(validate_sheet_name(&"x".repeat(MAX_SHEET_NAME_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_quoting() {
+        for (name, written) in [
+            ("Sheet1", "Sheet1"),
+            ("Data_2024", "Data_2024"),
+            ("My Sheet", "'My Sheet'"),
+            ("2024", "'2024'"),
+            ("Q1", "'Q1'"),
+            ("true", "'true'"),
+            ("Résumé", "'Résumé'"),
+            ("it's", "'it''s'"),
+        ] {
+            assert_eq!(quote_sheet_name(name), written);
+            assert_eq!(unquote_sheet_name(written).as_deref(), Some(name));
+        }
+        assert_eq!(unquote_sheet_name("'it's'"), None);
+        assert_eq!(unquote_sheet_name("'open"), None);
+        assert_eq!(
+            split_sheet_reference("'a''!b'!$A$1:B2"),
+            Some(("a'!b".to_string(), "$A$1:B2"))
+        );
+        assert_eq!(split_sheet_reference("A1"), None);
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod workbook_integration_tests {
     use crate::domain::Cell;
+    use crate::formula::CellRange;
+    use crate::references::StructuralOperation;
     use crate::types::{CellAddress, CellValue};
     use crate::workbook::{Sheet, SheetManager, Workbook};
 
@@ -116,25 +118,43 @@ mod workbook_integration_tests {
 
         // Add sheet-level named range
         let sheet = workbook.get_sheet_mut("Sheet1").unwrap();
-        let range = vec![
-            CellAddress::new(0, 0),
-            CellAddress::new(0, 1),
-            CellAddress::new(0, 2),
-        ];
-        sheet.add_named_range("HeaderRow", range.clone());
+        let range = CellRange::new(CellAddress::new(0, 0), CellAddress::new(0, 2));
+        sheet.add_named_range("HeaderRow", range);
 
         // Add global named range
         workbook
-            .add_global_named_range("GlobalData", "Sheet1", range.clone())
+            .add_global_named_range("GlobalData", "Sheet1", range)
             .unwrap();
 
         // Verify ranges
         let sheet = workbook.get_sheet("Sheet1").unwrap();
-        assert_eq!(sheet.get_named_range("HeaderRow"), Some(&range));
+        assert_eq!(
+            sheet.get_named_range("HeaderRow").unwrap().range,
+            Some(range)
+        );
 
-        let (sheet_name, global_range) = workbook.get_global_named_range("GlobalData").unwrap();
-        assert_eq!(sheet_name, "Sheet1");
-        assert_eq!(global_range, &range);
+        let global = workbook.get_global_named_range("globaldata").unwrap();
+        assert_eq!(global.sheet, "Sheet1");
+        assert_eq!(global.range, Some(range));
+
+        // Rows inserted inside the range grow it; deleting all of it loses it
+        let insert = StructuralOperation::InsertRows {
+            before_row: 1,
+            count: 2,
+        };
+        workbook.apply_structural_operation_to_names("Sheet1", &insert);
+        let grown = CellRange::new(CellAddress::new(0, 0), CellAddress::new(0, 4));
+        assert_eq!(
+            workbook.get_global_named_range("GlobalData").unwrap().range,
+            Some(grown)
+        );
+        let delete = StructuralOperation::DeleteRows {
+            start_row: 0,
+            count: 5,
+        };
+        workbook.apply_structural_operation_to_names("Sheet1", &delete);
+        let sheet = workbook.get_sheet("Sheet1").unwrap();
+        assert_eq!(sheet.get_named_range("HeaderRow").unwrap().range, None);
     }
 
     #[test]
@@ -146,7 +166,8 @@ mod workbook_integration_tests {
             .set_cell(&CellAddress::new(0, 0), Cell::new(CellValue::Number(42.0)))
             .unwrap();
         original.set_column_width(0, 200.0);
-        original.add_named_range("TestRange", vec![CellAddress::new(0, 0)]);
+        let a1 = CellAddress::new(0, 0);
+        original.add_named_range("TestRange", CellRange::new(a1, a1));
 
         // Clone the sheet
         let cloned = original.clone_with_name("Cloned");
@@ -161,7 +182,7 @@ mod workbook_integration_tests {
             CellValue::Number(42.0)
         );
         assert_eq!(cloned.get_column_width(0), 200.0);
-        assert!(cloned.get_named_range("TestRange").is_some());
+        assert_eq!(cloned.get_named_range("TestRange").unwrap().sheet, "Cloned");
     }
 
     #[test]
@@ -192,4 +213,109 @@ mod workbook_integration_tests {
         assert_eq!(stats.formula_cells, 0);
         assert_eq!(stats.error_cells, 0);
     }
+
+    #[test]
+    fn test_tricky_sheet_names_round_trip() {
+        use crate::formula::FormulaParser;
+        use crate::formula::tokenizer::{LexToken, Tokenizer};
+        use crate::references::{ReferenceParser, ReferenceType};
+        use crate::workbook::{sheet_reference, split_sheet_reference, validate_sheet_name};
+
+        const POOL: &[&str] = &[
+            "Q", "1", "'", " ", "é", "Résumé", "!", "\"", "-", "_", "A1", "TRUE", "漢", ".", "$",
+            "(", "''", "Sheet", ",", "#",
+        ];
+        // A fixed linear congruential generator keeps failures reproducible
+        let mut seed: u64 = 0x5eed;
+        let mut next = |bound: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % bound
+        };
+        let mut names = vec!["Q1 'Draft' Résumé".to_string(), "2024".to_string()];
+        while names.len() < 200 {
+            let name: String = (0..1 + next(5)).map(|_| POOL[next(POOL.len())]).collect();
+            if validate_sheet_name(&name).is_ok() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        let parser = ReferenceParser::new();
+        let mut workbook = Workbook::new();
+        workbook.create_sheet("Main").unwrap();
+        for (i, name) in names.iter().enumerate() {
+            let written = sheet_reference(name, "$A$1:B2");
+            assert_eq!(
+                split_sheet_reference(&written),
+                Some((name.clone(), "$A$1:B2")),
+                "{written}"
+            );
+
+            let formula = format!("=SUM({})+\"{}\"", written, name.replace('"', ""));
+            let tokens = Tokenizer::lex(&formula, '.');
+            assert_eq!(tokens.iter().map(|t| t.text()).collect::<String>(), formula);
+            if let Some(quoted) = written.strip_suffix("!$A$1:B2")
+                && quoted.starts_with('\'')
+            {
+                assert!(tokens.contains(&LexToken::QuotedName(quoted)), "{formula}");
+            }
+
+            let references = parser.parse_formula(&format!("=SUM({})+1", written));
+            assert_eq!(references.len(), 1, "{written}");
+            assert!(
+                matches!(&references[0].ref_type, ReferenceType::Sheet(sheet, _) if sheet == name),
+                "{written}"
+            );
+            let expr = FormulaParser::parse(&formula).unwrap();
+            assert_eq!(expr.to_string(), &formula[1..], "{written}");
+
+            // Resolve a reference to a cell on the sheet
+            workbook.create_sheet(name.as_str()).unwrap();
+            workbook
+                .get_sheet(name)
+                .unwrap()
+                .set_cell(
+                    &CellAddress::new(0, 0),
+                    Cell::new(CellValue::Number(i as f64)),
+                )
+                .unwrap();
+            let (sheet, address) = workbook
+                .parse_sheet_reference(&sheet_reference(name, "A1"))
+                .unwrap();
+            assert_eq!(
+                workbook.get_cell_value(&sheet, &address),
+                Some(CellValue::Number(i as f64))
+            );
+        }
+
+        // Renaming rewrites references, quoting the new name where needed
+        let (old, new) = (&names[0], &names[1]);
+        let main = workbook.get_sheet("Main").unwrap();
+        let formula = format!("={}*2", sheet_reference(old, "A1"));
+        main.set_cell(
+            &CellAddress::new(0, 0),
+            Cell::with_formula(
+                CellValue::from_string(formula.clone()),
+                formula[1..].to_string(),
+            ),
+        )
+        .unwrap();
+        workbook.remove_sheet(new).unwrap();
+        workbook.rename_sheet(old, new.as_str()).unwrap();
+        let cell = workbook
+            .get_sheet("Main")
+            .unwrap()
+            .get_cell(&CellAddress::new(0, 0))
+            .unwrap();
+        assert_eq!(
+            cell.raw_value,
+            CellValue::from_string(format!("={}*2", sheet_reference(new, "A1")))
+        );
+        assert_eq!(cell.raw_value.to_string(), "='2024'!A1*2");
+
+        assert!(workbook.rename_sheet("Main", "a/b").is_err());
+        assert!(workbook.copy_sheet("Main", "").is_err());
+        assert!(workbook.create_sheet("x".repeat(32)).is_err());
+    }
 }
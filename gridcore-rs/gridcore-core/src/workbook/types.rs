@@ -1,12 +1,20 @@
+use super::names::{
+    DefinedName, NameDefinition, NameScope, NamedConstant, NamedRange, NamedRangeRegistry,
+    VisibleNames, name_key,
+};
+use super::settings::WorkbookSettings;
 use super::sheet::Sheet;
+use super::sheet_name::{split_sheet_reference, validate_sheet_name};
 use crate::constants::{UNTITLED, VERSION_DEFAULT};
-use crate::domain::Cell;
-use crate::formula::Expr;
+use crate::domain::{Cell, CellFormat, CellStyle, StyleRegistry, StyleRemoval};
+use crate::formula::{CellRange, Expr};
+use crate::references::{ReferenceAdjuster, StructuralOperation};
 use crate::types::{CellAddress, CellValue};
 use crate::{Result, SpreadsheetError};
 use chrono::{DateTime, Utc};
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Metadata for a workbook
 #[derive(Debug, Clone)]
@@ -25,6 +33,31 @@ pub struct WorkbookMetadata {
     pub version: Cow<'static, str>,
     /// Custom properties
     pub custom_properties: HashMap<String, String>,
+    /// Sidecar layout version the document was loaded from, before any
+    /// migration; `None` unless it was loaded with a sidecar
+    pub loaded_schema_version: Option<u32>,
+}
+
+fn range_entry(named: &NamedRange, scope: NameScope) -> DefinedName {
+    DefinedName {
+        name: named.name.clone(),
+        scope,
+        definition: NameDefinition::Range {
+            sheet: named.sheet.clone(),
+            range: named.range,
+        },
+    }
+}
+
+fn constant_entry(constant: &NamedConstant, scope: NameScope) -> DefinedName {
+    DefinedName {
+        name: constant.name.clone(),
+        scope,
+        definition: NameDefinition::Constant {
+            value: constant.value.clone(),
+            definition: constant.definition.clone(),
+        },
+    }
 }
 
 impl Default for WorkbookMetadata {
@@ -38,6 +71,7 @@ impl Default for WorkbookMetadata {
             modified_at: now,
             version: Cow::Borrowed(VERSION_DEFAULT),
             custom_properties: HashMap::new(),
+            loaded_schema_version: None,
         }
     }
 }
@@ -55,8 +89,13 @@ pub struct Workbook {
     /// Shared formulas (for optimization)
     #[allow(dead_code)]
     shared_formulas: HashMap<String, Expr>,
-    /// Global named ranges (accessible from all sheets)
-    global_named_ranges: HashMap<String, (String, Vec<CellAddress>)>, // name -> (sheet, addresses)
+    /// Workbook-scoped named ranges
+    global_named_ranges: NamedRangeRegistry,
+    /// Workbook-scoped named constants, keyed by [`name_key`]
+    global_constants: HashMap<String, NamedConstant>,
+    /// Named styles cells on any sheet can refer to
+    styles: StyleRegistry,
+    settings: WorkbookSettings,
 }
 
 impl Workbook {
@@ -68,7 +107,10 @@ impl Workbook {
             active_sheet: None,
             metadata: WorkbookMetadata::default(),
             shared_formulas: HashMap::new(),
-            global_named_ranges: HashMap::new(),
+            global_named_ranges: NamedRangeRegistry::new(),
+            global_constants: HashMap::new(),
+            styles: StyleRegistry::with_builtins(),
+            settings: WorkbookSettings::default(),
         }
     }
 
@@ -88,6 +130,7 @@ impl Workbook {
     pub fn add_sheet(&mut self, sheet: Sheet) -> Result<()> {
         let name = sheet.name().to_string();
 
+        validate_sheet_name(&name)?;
         if self.sheets.contains_key(&name) {
             return Err(SpreadsheetError::InvalidOperation(format!(
                 "Sheet '{}' already exists",
@@ -95,6 +138,7 @@ impl Workbook {
             )));
         }
 
+        sheet.cells().set_lookup_index(self.settings.lookup_index);
         self.sheets.insert(name.clone(), sheet);
         self.sheet_order.push(name.clone());
 
@@ -113,7 +157,8 @@ impl Workbook {
         self.add_sheet(sheet)
     }
 
-    /// Remove a sheet from the workbook
+    /// Remove a sheet from the workbook, turning the references other
+    /// sheets' formulas make to it into `#REF!`
     pub fn remove_sheet(&mut self, name: &str) -> Result<Sheet> {
         if self.sheets.len() <= 1 {
             return Err(SpreadsheetError::InvalidOperation(
@@ -133,17 +178,21 @@ impl Workbook {
         }
 
         // Remove global named ranges from this sheet
-        self.global_named_ranges
-            .retain(|_, (sheet_name, _)| sheet_name != name);
+        self.global_named_ranges.remove_sheet(name);
+
+        // Formulas reading the sheet now read nothing
+        let adjuster = ReferenceAdjuster::new();
+        self.rewrite_formulas(|formula| adjuster.remove_sheet(formula, name))?;
 
         self.metadata.modified_at = Utc::now();
         Ok(sheet)
     }
 
-    /// Rename a sheet
+    /// Rename a sheet, pointing formulas that refer to it at the new name
     pub fn rename_sheet(&mut self, old_name: &str, new_name: impl Into<String>) -> Result<()> {
         let new_name = new_name.into();
 
+        validate_sheet_name(&new_name)?;
         if self.sheets.contains_key(&new_name) {
             return Err(SpreadsheetError::InvalidOperation(format!(
                 "Sheet '{}' already exists",
@@ -170,21 +219,46 @@ impl Workbook {
         }
 
         // Update global named ranges
-        for (sheet_name, _) in self.global_named_ranges.values_mut() {
-            if sheet_name == old_name {
-                *sheet_name = new_name.clone();
-            }
-        }
+        self.global_named_ranges.rename_sheet(old_name, &new_name);
 
-        self.sheets.insert(new_name, sheet);
+        self.sheets.insert(new_name.clone(), sheet);
+        let adjuster = ReferenceAdjuster::new();
+        self.rewrite_formulas(|formula| adjuster.rename_sheet(formula, old_name, &new_name))?;
         self.metadata.modified_at = Utc::now();
         Ok(())
     }
 
+    /// Replace the formulas `rewrite` returns new text for, keeping their
+    /// values until they are recalculated
+    fn rewrite_formulas(&mut self, rewrite: impl Fn(&str) -> Option<String>) -> Result<()> {
+        for sheet in self.sheets.values() {
+            let renamed: Vec<_> = sheet
+                .cells()
+                .get_all()
+                .into_iter()
+                .filter_map(|(address, cell)| {
+                    let CellValue::String(formula) = &cell.raw_value else {
+                        return None;
+                    };
+                    let formula = rewrite(formula)?;
+                    let mut cell = cell.clone();
+                    cell.formula_text = Some(Arc::from(&formula[1..]));
+                    cell.raw_value = CellValue::from_string(formula);
+                    Some((address, cell))
+                })
+                .collect();
+            for (address, cell) in renamed {
+                sheet.set_cell(&address, cell)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Copy a sheet
     pub fn copy_sheet(&mut self, source_name: &str, target_name: impl Into<String>) -> Result<()> {
         let target_name = target_name.into();
 
+        validate_sheet_name(&target_name)?;
         if self.sheets.contains_key(&target_name) {
             return Err(SpreadsheetError::InvalidOperation(format!(
                 "Sheet '{}' already exists",
@@ -197,6 +271,9 @@ impl Workbook {
         })?;
 
         let new_sheet = source_sheet.clone_with_name(target_name.clone());
+        new_sheet
+            .cells()
+            .set_lookup_index(self.settings.lookup_index);
 
         self.sheets.insert(target_name.clone(), new_sheet);
         self.sheet_order.push(target_name);
@@ -296,12 +373,25 @@ impl Workbook {
         &mut self.metadata
     }
 
-    /// Add a global named range
+    pub fn settings(&self) -> &WorkbookSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: WorkbookSettings) {
+        if settings.lookup_index != self.settings.lookup_index {
+            for sheet in self.sheets.values() {
+                sheet.cells().set_lookup_index(settings.lookup_index);
+            }
+        }
+        self.settings = settings;
+    }
+
+    /// Define or redefine a workbook-scoped named range
     pub fn add_global_named_range(
         &mut self,
         name: impl Into<String>,
         sheet_name: impl Into<String>,
-        addresses: Vec<CellAddress>,
+        range: CellRange,
     ) -> Result<()> {
         let sheet_name = sheet_name.into();
         if !self.sheets.contains_key(&sheet_name) {
@@ -312,35 +402,266 @@ impl Workbook {
         }
 
         self.global_named_ranges
-            .insert(name.into(), (sheet_name, addresses));
+            .define(NamedRange::new(name, sheet_name, range));
         self.metadata.modified_at = Utc::now();
         Ok(())
     }
 
-    /// Get a global named range
-    pub fn get_global_named_range(&self, name: &str) -> Option<(&str, &Vec<CellAddress>)> {
-        self.global_named_ranges
-            .get(name)
-            .map(|(sheet, addresses)| (sheet.as_str(), addresses))
+    /// Get a workbook-scoped named range, ignoring case
+    pub fn get_global_named_range(&self, name: &str) -> Option<&NamedRange> {
+        self.global_named_ranges.get(name)
     }
 
-    /// Remove a global named range
-    pub fn remove_global_named_range(&mut self, name: &str) -> Option<(String, Vec<CellAddress>)> {
+    /// Remove a workbook-scoped named range, ignoring case
+    pub fn remove_global_named_range(&mut self, name: &str) -> Option<NamedRange> {
         self.metadata.modified_at = Utc::now();
         self.global_named_ranges.remove(name)
     }
 
-    /// Parse a cross-sheet reference (e.g., "Sheet1!A1")
-    pub fn parse_sheet_reference(&self, reference: &str) -> Result<(String, CellAddress)> {
-        let parts: Vec<&str> = reference.split('!').collect();
-        if parts.len() != 2 {
+    /// Workbook-scoped named ranges, in no particular order
+    pub fn global_named_ranges(&self) -> impl Iterator<Item = &NamedRange> {
+        self.global_named_ranges.iter()
+    }
+
+    /// Move the named ranges of every scope that are on `operated_sheet`
+    /// for `operation` on it
+    pub fn apply_structural_operation_to_names(
+        &mut self,
+        operated_sheet: &str,
+        operation: &StructuralOperation,
+    ) {
+        self.global_named_ranges
+            .apply_structural_operation(operated_sheet, operation);
+        if let Some(sheet) = self.sheets.get_mut(operated_sheet) {
+            sheet
+                .named_ranges_mut()
+                .apply_structural_operation(operated_sheet, operation);
+        }
+    }
+
+    /// Move the cell, row and column formats of `operated_sheet` with its
+    /// cells for `operation`. Conditional formats move like named ranges:
+    /// they grow and shrink with their range and go once all of it is
+    /// deleted.
+    pub fn apply_structural_operation_to_formats(
+        &mut self,
+        operated_sheet: &str,
+        operation: &StructuralOperation,
+    ) {
+        if let Some(sheet) = self.sheets.get_mut(operated_sheet) {
+            sheet.formats_mut().apply_structural_operation(operation);
+            sheet.conditional_formats_mut().retain_mut(|rule| {
+                match operation.shift_reference(&rule.range) {
+                    Some(range) => {
+                        rule.range = range;
+                        true
+                    }
+                    None => false,
+                }
+            });
+        }
+    }
+
+    /// Define or redefine a workbook-scoped named constant
+    pub fn define_global_constant(&mut self, constant: NamedConstant) {
+        self.global_constants
+            .insert(name_key(&constant.name), constant);
+        self.metadata.modified_at = Utc::now();
+    }
+
+    /// Get a workbook-scoped named constant, ignoring case
+    pub fn get_global_constant(&self, name: &str) -> Option<&NamedConstant> {
+        self.global_constants.get(&name_key(name))
+    }
+
+    /// Remove a workbook-scoped named constant, ignoring case
+    pub fn remove_global_constant(&mut self, name: &str) -> Option<NamedConstant> {
+        self.metadata.modified_at = Utc::now();
+        self.global_constants.remove(&name_key(name))
+    }
+
+    /// Named styles, in gallery order
+    pub fn styles(&self) -> &StyleRegistry {
+        &self.styles
+    }
+
+    /// Define a style or change the format of an existing one. Cells using
+    /// it pick the change up on their next lookup. Returns the name cells
+    /// refer to the style by.
+    pub fn define_style(&mut self, name: &str, format: CellFormat) -> Result<String> {
+        let name = self.styles.define(name, format)?;
+        self.metadata.modified_at = Utc::now();
+        Ok(name)
+    }
+
+    /// Remove a style. Cells still using it either block the removal or get
+    /// the style's format as their own, as `removal` says.
+    pub fn remove_style(&mut self, name: &str, removal: StyleRemoval) -> Result<CellStyle> {
+        let style = self.styles.get(name).cloned().ok_or_else(|| {
+            SpreadsheetError::InvalidOperation(format!("Style '{}' does not exist", name))
+        })?;
+
+        let users: Vec<(String, Vec<CellAddress>)> = self
+            .sheet_order
+            .iter()
+            .filter_map(|sheet_name| {
+                let formats = self.sheets.get(sheet_name)?.formats();
+                let cells: Vec<CellAddress> =
+                    formats.cells_with_style(&style.name).copied().collect();
+                (!cells.is_empty()).then(|| (sheet_name.clone(), cells))
+            })
+            .collect();
+        if removal == StyleRemoval::Block
+            && let Some((sheet_name, cells)) = users.first()
+        {
             return Err(SpreadsheetError::InvalidOperation(format!(
-                "Invalid sheet reference: {}",
-                reference
+                "Style '{}' is used by {} cells on '{}'",
+                style.name,
+                cells.len(),
+                sheet_name
             )));
         }
 
-        let sheet_name = parts[0].to_string();
+        for (sheet_name, cells) in users {
+            let Some(sheet) = self.sheets.get_mut(&sheet_name) else {
+                continue;
+            };
+            let formats = sheet.formats_mut();
+            for address in cells {
+                formats.set_cell_style(address, None);
+                if formats.cell_format(&address).is_none() {
+                    formats.set_cell_format(address, style.format.clone());
+                }
+            }
+        }
+        self.styles.remove(&style.name);
+        self.metadata.modified_at = Utc::now();
+        Ok(style)
+    }
+
+    /// The constant `name` stands for in formulas on `sheet_name`: the
+    /// sheet's own constant if it has one, else the workbook's
+    pub fn resolve_constant(&self, sheet_name: &str, name: &str) -> Option<&NamedConstant> {
+        self.sheets
+            .get(sheet_name)
+            .and_then(|sheet| sheet.get_constant(name))
+            .or_else(|| self.get_global_constant(name))
+    }
+
+    /// Every name visible in `scope`. Formulas on a sheet see its own
+    /// names over the workbook's, and only the ranges on that sheet.
+    pub fn visible_names(&self, scope: &NameScope) -> VisibleNames {
+        let mut names = VisibleNames::default();
+        let sheet_name = match scope {
+            NameScope::Sheet(sheet_name) => Some(sheet_name.as_str()),
+            NameScope::Workbook => None,
+        };
+        for named in self.global_named_ranges.iter() {
+            names.insert_range(named, sheet_name.unwrap_or_default());
+        }
+        for constant in self.global_constants.values() {
+            names.insert_constant(constant);
+        }
+        if let Some(sheet) = sheet_name.and_then(|name| self.sheets.get(name)) {
+            for named in sheet.named_ranges() {
+                names.insert_range(named, sheet.name());
+            }
+            for constant in sheet.constants() {
+                names.insert_constant(constant);
+            }
+        }
+        names
+    }
+
+    /// Every named range and named constant, workbook-scoped names first,
+    /// then each sheet's in sheet order, alphabetically within each scope
+    pub fn defined_names(&self) -> Vec<DefinedName> {
+        let mut workbook_names: Vec<DefinedName> = self
+            .global_named_ranges
+            .iter()
+            .map(|named| range_entry(named, NameScope::Workbook))
+            .chain(
+                self.global_constants
+                    .values()
+                    .map(|constant| constant_entry(constant, NameScope::Workbook)),
+            )
+            .collect();
+        workbook_names.sort_by_key(|entry| name_key(&entry.name));
+
+        let mut names = workbook_names;
+        for sheet in self
+            .sheet_order
+            .iter()
+            .filter_map(|name| self.sheets.get(name))
+        {
+            let scope = NameScope::Sheet(sheet.name().to_string());
+            let mut sheet_names: Vec<DefinedName> = sheet
+                .named_ranges()
+                .map(|named| range_entry(named, scope.clone()))
+                .chain(
+                    sheet
+                        .constants()
+                        .map(|constant| constant_entry(constant, scope.clone())),
+                )
+                .collect();
+            sheet_names.sort_by_key(|entry| name_key(&entry.name));
+            names.extend(sheet_names);
+        }
+        names
+    }
+
+    /// Add a name listed by [`Self::defined_names`], e.g. when loading a
+    /// saved workbook. Constants keep their saved value.
+    pub fn restore_name(&mut self, entry: DefinedName) -> Result<()> {
+        match (entry.scope, entry.definition) {
+            (NameScope::Workbook, NameDefinition::Range { sheet, range }) => {
+                if !self.sheets.contains_key(&sheet) {
+                    return Err(SpreadsheetError::InvalidOperation(format!(
+                        "Sheet '{}' not found",
+                        sheet
+                    )));
+                }
+                self.global_named_ranges.define(NamedRange {
+                    name: entry.name,
+                    sheet,
+                    range,
+                });
+                self.metadata.modified_at = Utc::now();
+                Ok(())
+            }
+            (NameScope::Workbook, NameDefinition::Constant { value, definition }) => {
+                self.define_global_constant(NamedConstant::new(entry.name, value, definition));
+                Ok(())
+            }
+            (NameScope::Sheet(sheet_name), definition) => {
+                let sheet = self.sheets.get_mut(&sheet_name).ok_or_else(|| {
+                    SpreadsheetError::InvalidOperation(format!("Sheet '{}' not found", sheet_name))
+                })?;
+                match definition {
+                    NameDefinition::Range { range, .. } => {
+                        let sheet_name = sheet.name().to_string();
+                        sheet.named_ranges_mut().define(NamedRange {
+                            name: entry.name,
+                            sheet: sheet_name,
+                            range,
+                        });
+                    }
+                    NameDefinition::Constant { value, definition } => {
+                        sheet.define_constant(NamedConstant::new(entry.name, value, definition))
+                    }
+                }
+                self.metadata.modified_at = Utc::now();
+                Ok(())
+            }
+        }
+    }
+
+    /// Parse a cross-sheet reference (e.g., "Sheet1!A1" or "'My Sheet'!A1")
+    pub fn parse_sheet_reference(&self, reference: &str) -> Result<(String, CellAddress)> {
+        let (sheet_name, address) = split_sheet_reference(reference).ok_or_else(|| {
+            SpreadsheetError::InvalidOperation(format!("Invalid sheet reference: {}", reference))
+        })?;
+
         if !self.sheets.contains_key(&sheet_name) {
             return Err(SpreadsheetError::InvalidOperation(format!(
                 "Sheet '{}' not found",
@@ -349,8 +670,8 @@ impl Workbook {
         }
 
         // Parse the cell address
-        let address = CellAddress::from_a1(parts[1]).map_err(|_| {
-            SpreadsheetError::InvalidOperation(format!("Invalid cell address: {}", parts[1]))
+        let address = CellAddress::from_a1(address).map_err(|_| {
+            SpreadsheetError::InvalidOperation(format!("Invalid cell address: {}", address))
         })?;
 
         Ok((sheet_name, address))
@@ -481,14 +802,23 @@ mod tests {
     fn test_global_named_ranges() {
         let mut workbook = Workbook::with_sheet("Sheet1");
 
-        let range = vec![CellAddress::new(0, 0), CellAddress::new(1, 0)];
+        let range = CellRange::new(CellAddress::new(0, 0), CellAddress::new(1, 0));
         workbook
-            .add_global_named_range("MyRange", "Sheet1", range.clone())
+            .add_global_named_range("MyRange", "Sheet1", range)
             .unwrap();
 
-        let (sheet, addresses) = workbook.get_global_named_range("MyRange").unwrap();
-        assert_eq!(sheet, "Sheet1");
-        assert_eq!(addresses, &range);
+        let named = workbook.get_global_named_range("myrange").unwrap();
+        assert_eq!(named.sheet, "Sheet1");
+        assert_eq!(named.range, Some(range));
+
+        workbook.create_sheet("Sheet2").unwrap();
+        workbook.rename_sheet("Sheet1", "Data").unwrap();
+        assert_eq!(
+            workbook.get_global_named_range("MyRange").unwrap().sheet,
+            "Data"
+        );
+        workbook.remove_sheet("Data").unwrap();
+        assert!(workbook.get_global_named_range("MyRange").is_none());
     }
 
     #[test]
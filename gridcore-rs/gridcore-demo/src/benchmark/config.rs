@@ -40,6 +40,16 @@ impl BenchmarkConfig {
         self.output_format = format;
         self
     }
+
+    pub fn with_isolation(mut self, enabled: bool) -> Self {
+        self.isolate_scenarios = enabled;
+        self
+    }
+
+    pub fn with_settle_delay(mut self, ms: u32) -> Self {
+        self.settle_ms = ms;
+        self
+    }
 }
 
 /// Predefined benchmark configurations for common scenarios
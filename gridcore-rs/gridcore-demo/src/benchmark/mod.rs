@@ -4,7 +4,9 @@ pub mod results;
 pub mod runner;
 pub mod scenarios;
 
+use crate::demo::data_generator::DataGenerator;
 use gridcore_controller::controller::SpreadsheetController;
+use gridcore_core::types::CellAddress;
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -29,6 +31,72 @@ pub trait BenchmarkScenario {
 
     /// Cleanup after benchmark completes
     fn cleanup(&mut self, controller: Rc<RefCell<SpreadsheetController>>);
+
+    /// Data the runner loads into the controller before warmup
+    fn dataset(&self) -> Option<ScenarioDataset> {
+        None
+    }
+
+    /// Whether the scenario measures what earlier scenarios left behind,
+    /// and so runs on the shared controller even when scenarios are isolated
+    fn measures_accumulated_state(&self) -> bool {
+        false
+    }
+}
+
+/// Cells a scenario needs, generated by [`DataGenerator`] from a seed so
+/// every run of the scenario starts from the same sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioDataset {
+    pub shape: DatasetShape,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatasetShape {
+    /// Dense numbers between `min` and `max`
+    NumericGrid {
+        rows: u32,
+        cols: u32,
+        min: f64,
+        max: f64,
+    },
+    /// Dense numbers with a share of formulas over their neighbours
+    Mixed {
+        rows: u32,
+        cols: u32,
+        formula_density: f64,
+    },
+    /// Numbers scattered over a large area
+    Sparse { rows: u32, cols: u32, density: f64 },
+}
+
+impl ScenarioDataset {
+    pub fn new(shape: DatasetShape, seed: u64) -> Self {
+        Self { shape, seed }
+    }
+
+    pub fn generate(&self) -> Vec<(CellAddress, String)> {
+        let mut gen = DataGenerator::with_seed(self.seed);
+        match self.shape {
+            DatasetShape::NumericGrid {
+                rows,
+                cols,
+                min,
+                max,
+            } => gen.generate_numeric_grid(rows, cols, min, max),
+            DatasetShape::Mixed {
+                rows,
+                cols,
+                formula_density,
+            } => gen.generate_mixed_sheet(rows, cols, formula_density),
+            DatasetShape::Sparse {
+                rows,
+                cols,
+                density,
+            } => gen.generate_sparse_data(rows, cols, density),
+        }
+    }
 }
 
 /// Result from a single benchmark run
@@ -39,6 +107,9 @@ pub struct BenchmarkResult {
     pub metrics: BenchmarkMetrics,
     pub success: bool,
     pub error_message: Option<String>,
+    /// Whether the scenario ran on a controller of its own
+    #[serde(default)]
+    pub isolated: bool,
 }
 
 /// Comprehensive benchmark metrics
@@ -156,6 +227,11 @@ pub struct BenchmarkConfig {
     pub throttle_network: Option<NetworkThrottle>,
     pub viewport_size: (u32, u32),
     pub output_format: OutputFormat,
+    /// Run each scenario on a freshly built controller, dropped afterwards
+    pub isolate_scenarios: bool,
+    /// Pause after a GC hint before each measured iteration, so memory
+    /// snapshots do not count garbage from the previous one
+    pub settle_ms: u32,
 }
 
 impl Default for BenchmarkConfig {
@@ -169,6 +245,8 @@ impl Default for BenchmarkConfig {
             throttle_network: None,
             viewport_size: (1920, 1080),
             output_format: OutputFormat::Json,
+            isolate_scenarios: true,
+            settle_ms: 50,
         }
     }
 }
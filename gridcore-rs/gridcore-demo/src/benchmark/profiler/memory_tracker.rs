@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use wasm_bindgen::JsCast;
 use web_sys::Performance;
 
 /// Tracks memory usage during benchmarks
@@ -101,11 +100,32 @@ impl MemoryTracker {
     /// Force garbage collection if available (Chrome only with --expose-gc flag)
     pub fn force_gc() {
         // Try to call gc() if available
-        if let Ok(gc_fn) = js_sys::Reflect::get(&js_sys::global(), &"gc".into()) {
-            if let Ok(gc_fn) = gc_fn.dyn_into::<js_sys::Function>() {
-                let _ = gc_fn.call0(&js_sys::global());
-                leptos::logging::log!("Forced garbage collection");
+        #[cfg(target_arch = "wasm32")]
+        {
+            use wasm_bindgen::JsCast;
+
+            if let Ok(gc_fn) = js_sys::Reflect::get(&js_sys::global(), &"gc".into()) {
+                if let Ok(gc_fn) = gc_fn.dyn_into::<js_sys::Function>() {
+                    let _ = gc_fn.call0(&js_sys::global());
+                    leptos::logging::log!("Forced garbage collection");
+                }
             }
         }
     }
+
+    /// Hint a garbage collection, then wait `ms` for the heap to settle
+    /// before a memory snapshot
+    pub fn settle(ms: u32) {
+        Self::force_gc();
+        #[cfg(target_arch = "wasm32")]
+        {
+            // Iterations run synchronously, so the wait is a spin on the clock
+            if let Some(performance) = web_sys::window().and_then(|w| w.performance()) {
+                let until = performance.now() + ms as f64;
+                while performance.now() < until {}
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+    }
 }
@@ -1,6 +1,13 @@
+pub mod suggestions;
+
 use super::runner::{BenchmarkReport, BenchmarkSummary};
+use super::scenarios::editing_storm::EVENTS_PER_EDIT_WARNING;
 use super::{BenchmarkConfig, BenchmarkResult};
 use std::collections::HashMap;
+use suggestions::{
+    SuggestedAction, Suggestion, SuggestionKind, HIGH_LATENCY_MS, LOW_FPS, MEMORY_GROWTH_MB,
+    SCENARIO_LOW_FPS,
+};
 
 /// Collects and analyzes benchmark results
 pub struct ResultsCollector {
@@ -134,6 +141,18 @@ impl ResultsCollector {
             }
         }
 
+        // Check for event fan-out
+        for result in &self.results {
+            if let Some(events) = result.metrics.custom_metrics.get("events_per_edit") {
+                if *events > EVENTS_PER_EDIT_WARNING {
+                    warnings.push(format!(
+                        "Event fan-out in '{}': {:.1} events per edit (limit: {})",
+                        result.scenario_name, events, EVENTS_PER_EDIT_WARNING
+                    ));
+                }
+            }
+        }
+
         // Check for dropped frames
         for result in &self.results {
             if result.metrics.dropped_frames > 5 {
@@ -147,30 +166,49 @@ impl ResultsCollector {
         warnings
     }
 
-    fn generate_suggestions(&self) -> Vec<String> {
+    fn generate_suggestions(&self) -> Vec<Suggestion> {
         let mut suggestions = Vec::new();
 
         // Analyze results and provide suggestions
         let summary = self.calculate_summary();
+        let overall = |kind, measured, threshold, action| Suggestion {
+            kind,
+            scenario: None,
+            measured,
+            threshold,
+            action,
+        };
 
-        if summary.avg_fps < 50.0 {
-            suggestions.push(
-                "Consider optimizing rendering performance - average FPS is below 50".to_string(),
-            );
+        if summary.avg_fps < LOW_FPS {
+            suggestions.push(overall(
+                SuggestionKind::LowFps,
+                summary.avg_fps,
+                LOW_FPS,
+                SuggestedAction::HideMinimap,
+            ));
         }
 
-        if summary.p95_latency > 150.0 {
-            suggestions
-                .push("Input latency is high - consider optimizing event handlers".to_string());
+        if summary.p95_latency > HIGH_LATENCY_MS {
+            suggestions.push(overall(
+                SuggestionKind::HighLatency,
+                summary.p95_latency,
+                HIGH_LATENCY_MS,
+                SuggestedAction::ManualCalculation,
+            ));
         }
 
-        if summary.total_memory_growth > 50.0 {
-            suggestions
-                .push("Significant memory growth detected - check for memory leaks".to_string());
+        if summary.total_memory_growth > MEMORY_GROWTH_MB {
+            suggestions.push(overall(
+                SuggestionKind::MemoryGrowth,
+                summary.total_memory_growth,
+                MEMORY_GROWTH_MB,
+                SuggestedAction::CheckMemoryLeaks,
+            ));
         }
 
         // Check for specific scenario issues
-        let scenario_groups = self.group_by_scenario();
+        let mut scenario_groups: Vec<_> = self.group_by_scenario().into_iter().collect();
+        scenario_groups.sort_by(|a, b| a.0.cmp(&b.0));
         for (scenario, results) in scenario_groups {
             let avg_fps: f64 = results
                 .iter()
@@ -179,11 +217,14 @@ impl ResultsCollector {
                 .sum::<f64>()
                 / results.len().max(1) as f64;
 
-            if avg_fps < 30.0 && avg_fps > 0.0 {
-                suggestions.push(format!(
-                    "Scenario '{}' needs optimization - average FPS: {:.1}",
-                    scenario, avg_fps
-                ));
+            if avg_fps < SCENARIO_LOW_FPS && avg_fps > 0.0 {
+                suggestions.push(Suggestion {
+                    kind: SuggestionKind::ScenarioSpecific,
+                    scenario: Some(scenario),
+                    measured: avg_fps,
+                    threshold: SCENARIO_LOW_FPS,
+                    action: SuggestedAction::ProfileScenario,
+                });
             }
         }
 
@@ -259,3 +300,72 @@ impl ResultsCollector {
         csv
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::BenchmarkMetrics;
+
+    fn result(scenario: &str, fps: f64, latency: f64, memory_growth: f64) -> BenchmarkResult {
+        BenchmarkResult {
+            scenario_name: scenario.to_string(),
+            iteration: 0,
+            metrics: BenchmarkMetrics {
+                fps_avg: fps,
+                input_latency_avg: latency,
+                memory_growth,
+                ..BenchmarkMetrics::new()
+            },
+            success: true,
+            error_message: None,
+            isolated: true,
+        }
+    }
+
+    fn suggestions(results: Vec<BenchmarkResult>) -> Vec<Suggestion> {
+        let mut collector = ResultsCollector::new();
+        collector.add_results(results);
+        collector.generate_suggestions()
+    }
+
+    #[test]
+    fn test_suggestions_follow_thresholds() {
+        assert!(suggestions(vec![result("Scroll", 60.0, 150.0, 50.0)]).is_empty());
+
+        let slow = suggestions(vec![result("Scroll", 49.0, 151.0, 51.0)]);
+        let kinds: Vec<_> = slow.iter().map(|s| (s.kind, s.action)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (SuggestionKind::LowFps, SuggestedAction::HideMinimap),
+                (
+                    SuggestionKind::HighLatency,
+                    SuggestedAction::ManualCalculation
+                ),
+                (
+                    SuggestionKind::MemoryGrowth,
+                    SuggestedAction::CheckMemoryLeaks
+                ),
+            ]
+        );
+        assert_eq!((slow[0].measured, slow[0].threshold), (49.0, LOW_FPS));
+        assert!(slow.iter().all(|s| s.scenario.is_none()));
+    }
+
+    #[test]
+    fn test_scenario_suggestions_name_the_slow_scenario() {
+        let found = suggestions(vec![
+            result("Scroll", 90.0, 0.0, 0.0),
+            result("Render", 90.0, 0.0, 0.0),
+            result("Formula", 20.0, 0.0, 0.0),
+        ]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, SuggestionKind::ScenarioSpecific);
+        assert_eq!(found[0].scenario.as_deref(), Some("Formula"));
+        // Reports render the structured form
+        assert_eq!(
+            found[0].to_string(),
+            "Scenario 'Formula' needs optimization - average FPS: 20.0"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Performance suggestions: what was measured, against which threshold,
+//! and what to do about it. Benchmark reports derive them from results,
+//! and [`LiveAdvisor`] derives the same ones from a rolling window of live
+//! metrics.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Average FPS over a benchmark run below which rendering needs work
+pub const LOW_FPS: f64 = 50.0;
+/// Average FPS of a single scenario below which it needs work
+pub const SCENARIO_LOW_FPS: f64 = 30.0;
+/// p95 input latency over a benchmark run, in milliseconds
+pub const HIGH_LATENCY_MS: f64 = 150.0;
+/// Memory growth over a benchmark run, in megabytes
+pub const MEMORY_GROWTH_MB: f64 = 50.0;
+
+/// FPS sustained while scrolling below which live metrics suggest a fix
+pub const LIVE_LOW_FPS: f64 = 45.0;
+/// Frame time sustained over the window, in milliseconds
+pub const LIVE_HIGH_LATENCY_MS: f64 = 100.0;
+/// Memory growth within the window, in megabytes
+pub const LIVE_MEMORY_GROWTH_MB: f64 = 20.0;
+/// Span of live metrics a problem must last for
+pub const LIVE_WINDOW_MS: f64 = 3000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SuggestionKind {
+    LowFps,
+    HighLatency,
+    MemoryGrowth,
+    /// One scenario is slow while the run as a whole is fine
+    ScenarioSpecific,
+}
+
+/// What to do about a suggestion. Some map to a setting the app can
+/// change on the spot, the others are advice for the developer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SuggestedAction {
+    /// Stop drawing the minimap on every frame
+    HideMinimap,
+    /// Stop recalculating on every edit
+    ManualCalculation,
+    CheckMemoryLeaks,
+    ProfileScenario,
+}
+
+impl SuggestedAction {
+    /// Stable id, e.g. for reports and tests
+    pub fn id(self) -> &'static str {
+        match self {
+            SuggestedAction::HideMinimap => "hide-minimap",
+            SuggestedAction::ManualCalculation => "manual-calculation",
+            SuggestedAction::CheckMemoryLeaks => "check-memory-leaks",
+            SuggestedAction::ProfileScenario => "profile-scenario",
+        }
+    }
+
+    /// Caption of the button applying the action, `None` when the app has
+    /// nothing to apply
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            SuggestedAction::HideMinimap => Some("Hide minimap"),
+            SuggestedAction::ManualCalculation => Some("Switch to manual calculation"),
+            SuggestedAction::CheckMemoryLeaks | SuggestedAction::ProfileScenario => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    /// The scenario or activity the measurement comes from, `None` for a
+    /// whole benchmark run
+    pub scenario: Option<String>,
+    pub measured: f64,
+    pub threshold: f64,
+    pub action: SuggestedAction,
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let during = match &self.scenario {
+            Some(scenario) => format!(" while {}", scenario),
+            None => String::new(),
+        };
+        match self.kind {
+            SuggestionKind::LowFps => write!(
+                f,
+                "Consider optimizing rendering performance - average FPS{} is below {:.0} ({:.1})",
+                during, self.threshold, self.measured
+            ),
+            SuggestionKind::HighLatency => write!(
+                f,
+                "Input latency{} is high - consider optimizing event handlers ({:.1}ms, limit {:.0}ms)",
+                during, self.measured, self.threshold
+            ),
+            SuggestionKind::MemoryGrowth => write!(
+                f,
+                "Significant memory growth detected{} - check for memory leaks ({:.1}MB, limit {:.0}MB)",
+                during, self.measured, self.threshold
+            ),
+            SuggestionKind::ScenarioSpecific => write!(
+                f,
+                "Scenario '{}' needs optimization - average FPS: {:.1}",
+                self.scenario.as_deref().unwrap_or("unknown"),
+                self.measured
+            ),
+        }
+    }
+}
+
+/// Live metrics at one moment
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LiveSample {
+    pub timestamp_ms: f64,
+    pub fps: f64,
+    /// Time the last frame took to draw, which every input waits for
+    pub frame_time_ms: f64,
+    pub memory_mb: f64,
+    /// Whether the grid was scrolling
+    pub scrolling: bool,
+}
+
+/// Suggestions from a rolling window of live metrics. A problem counts once
+/// it lasts the whole window, so a single slow frame suggests nothing.
+pub struct LiveAdvisor {
+    window_ms: f64,
+    /// Samples within the window, plus the newest one at or before its start
+    samples: VecDeque<LiveSample>,
+}
+
+impl Default for LiveAdvisor {
+    fn default() -> Self {
+        Self::new(LIVE_WINDOW_MS)
+    }
+}
+
+impl LiveAdvisor {
+    pub fn new(window_ms: f64) -> Self {
+        Self {
+            window_ms,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, sample: LiveSample) {
+        let start = sample.timestamp_ms - self.window_ms;
+        self.samples.push_back(sample);
+        while self.samples.len() > 1 && self.samples[1].timestamp_ms <= start {
+            self.samples.pop_front();
+        }
+    }
+
+    /// What the window shows, nothing until the samples span all of it
+    pub fn suggestions(&self) -> Vec<Suggestion> {
+        let (Some(first), Some(last)) = (self.samples.front(), self.samples.back()) else {
+            return Vec::new();
+        };
+        if last.timestamp_ms - first.timestamp_ms < self.window_ms {
+            return Vec::new();
+        }
+        let average = |value: fn(&LiveSample) -> f64| {
+            self.samples.iter().map(value).sum::<f64>() / self.samples.len() as f64
+        };
+        let mut suggestions = Vec::new();
+
+        let fps = average(|sample| sample.fps);
+        if self.samples.iter().all(|sample| sample.scrolling) && fps < LIVE_LOW_FPS {
+            suggestions.push(Suggestion {
+                kind: SuggestionKind::LowFps,
+                scenario: Some("scrolling".to_string()),
+                measured: fps,
+                threshold: LIVE_LOW_FPS,
+                action: SuggestedAction::HideMinimap,
+            });
+        }
+
+        let frame_time = average(|sample| sample.frame_time_ms);
+        if frame_time > LIVE_HIGH_LATENCY_MS {
+            suggestions.push(Suggestion {
+                kind: SuggestionKind::HighLatency,
+                scenario: None,
+                measured: frame_time,
+                threshold: LIVE_HIGH_LATENCY_MS,
+                action: SuggestedAction::ManualCalculation,
+            });
+        }
+
+        // Browsers without a heap figure report none at all
+        let growth = last.memory_mb - first.memory_mb;
+        if first.memory_mb > 0.0 && growth > LIVE_MEMORY_GROWTH_MB {
+            suggestions.push(Suggestion {
+                kind: SuggestionKind::MemoryGrowth,
+                scenario: None,
+                measured: growth,
+                threshold: LIVE_MEMORY_GROWTH_MB,
+                action: SuggestedAction::CheckMemoryLeaks,
+            });
+        }
+
+        suggestions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrolling_at(fps: f64) -> impl Fn(f64) -> LiveSample {
+        move |timestamp_ms| LiveSample {
+            timestamp_ms,
+            fps,
+            frame_time_ms: 10.0,
+            memory_mb: 40.0,
+            scrolling: true,
+        }
+    }
+
+    /// Feed a sample every 100ms from `from` up to and including `to`
+    fn feed(advisor: &mut LiveAdvisor, from: f64, to: f64, sample: impl Fn(f64) -> LiveSample) {
+        let mut timestamp = from;
+        while timestamp <= to {
+            advisor.push(sample(timestamp));
+            timestamp += 100.0;
+        }
+    }
+
+    fn kinds(advisor: &LiveAdvisor) -> Vec<SuggestionKind> {
+        advisor.suggestions().iter().map(|s| s.kind).collect()
+    }
+
+    #[test]
+    fn test_sustained_slow_scrolling_suggests_hiding_the_minimap() {
+        let mut advisor = LiveAdvisor::default();
+        feed(&mut advisor, 0.0, 2900.0, scrolling_at(30.0));
+        // Not sustained for the whole window yet
+        assert!(advisor.suggestions().is_empty());
+
+        feed(&mut advisor, 3000.0, 3000.0, scrolling_at(30.0));
+        let suggestions = advisor.suggestions();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].kind, SuggestionKind::LowFps);
+        assert_eq!(suggestions[0].scenario.as_deref(), Some("scrolling"));
+        assert_eq!(suggestions[0].measured, 30.0);
+        assert_eq!(suggestions[0].action.id(), "hide-minimap");
+
+        // Recovering drops the suggestion once the slow samples leave the window
+        feed(&mut advisor, 3100.0, 3500.0, scrolling_at(60.0));
+        assert_eq!(kinds(&advisor), [SuggestionKind::LowFps]);
+        feed(&mut advisor, 3600.0, 6100.0, scrolling_at(60.0));
+        assert!(advisor.suggestions().is_empty());
+    }
+
+    #[test]
+    fn test_slow_frames_without_scrolling_or_briefly_suggest_nothing() {
+        let mut advisor = LiveAdvisor::default();
+        feed(&mut advisor, 0.0, 4000.0, |timestamp_ms| LiveSample {
+            scrolling: false,
+            ..scrolling_at(20.0)(timestamp_ms)
+        });
+        assert!(advisor.suggestions().is_empty());
+
+        // One scroll stutter within otherwise smooth scrolling
+        let mut advisor = LiveAdvisor::default();
+        feed(&mut advisor, 0.0, 4000.0, |timestamp_ms| {
+            let fps = if timestamp_ms == 2000.0 { 5.0 } else { 60.0 };
+            scrolling_at(fps)(timestamp_ms)
+        });
+        assert!(advisor.suggestions().is_empty());
+    }
+
+    #[test]
+    fn test_slow_frames_and_memory_growth_over_the_window() {
+        let mut advisor = LiveAdvisor::default();
+        feed(&mut advisor, 0.0, 3000.0, |timestamp_ms| LiveSample {
+            timestamp_ms,
+            fps: 8.0,
+            frame_time_ms: 120.0,
+            memory_mb: 40.0 + timestamp_ms / 100.0,
+            scrolling: false,
+        });
+        let suggestions = advisor.suggestions();
+        assert_eq!(
+            suggestions.iter().map(|s| s.kind).collect::<Vec<_>>(),
+            [SuggestionKind::HighLatency, SuggestionKind::MemoryGrowth]
+        );
+        assert_eq!(suggestions[0].action, SuggestedAction::ManualCalculation);
+        assert_eq!(suggestions[1].measured, 30.0);
+        assert_eq!(suggestions[1].action.label(), None);
+    }
+}
@@ -1,27 +1,39 @@
+use super::profiler::memory_tracker::MemoryTracker;
 use super::profiler::PerformanceProfiler;
+use super::results::suggestions::Suggestion;
 use super::results::ResultsCollector;
 use super::{BenchmarkConfig, BenchmarkMetrics, BenchmarkResult, BenchmarkScenario};
-use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::controller::{SpreadsheetController, SpreadsheetControllerBuilder};
 use std::cell::RefCell;
 use std::rc::Rc;
 
-/// Main benchmark runner that executes scenarios and collects results
+type ControllerFactory = Box<dyn Fn() -> SpreadsheetControllerBuilder>;
+
+/// Main benchmark runner that executes scenarios and collects results.
+///
+/// With [`BenchmarkConfig::isolate_scenarios`] each scenario runs on a
+/// controller built for it, loaded with the scenario's dataset and dropped
+/// once it is done, so no scenario measures what another left behind.
+/// Scenarios that measure accumulated state run on the shared controller.
 pub struct UIBenchmarkRunner {
     scenarios: Vec<Box<dyn BenchmarkScenario>>,
-    profiler: PerformanceProfiler,
+    /// Created on first use, as it needs the browser's Performance API
+    profiler: Option<PerformanceProfiler>,
     results_collector: ResultsCollector,
     config: BenchmarkConfig,
     controller: Rc<RefCell<SpreadsheetController>>,
+    controller_factory: ControllerFactory,
 }
 
 impl UIBenchmarkRunner {
     pub fn new(controller: Rc<RefCell<SpreadsheetController>>) -> Self {
         Self {
             scenarios: Vec::new(),
-            profiler: PerformanceProfiler::new(),
+            profiler: None,
             results_collector: ResultsCollector::new(),
             config: BenchmarkConfig::default(),
             controller,
+            controller_factory: Box::new(SpreadsheetController::builder),
         }
     }
 
@@ -30,6 +42,15 @@ impl UIBenchmarkRunner {
         self
     }
 
+    /// Configure the controllers isolated scenarios run on
+    pub fn with_controller_builder(
+        mut self,
+        factory: impl Fn() -> SpreadsheetControllerBuilder + 'static,
+    ) -> Self {
+        self.controller_factory = Box::new(factory);
+        self
+    }
+
     pub fn add_scenario(&mut self, scenario: Box<dyn BenchmarkScenario>) {
         self.scenarios.push(scenario);
     }
@@ -48,18 +69,32 @@ impl UIBenchmarkRunner {
         leptos::logging::log!("Starting benchmark: {}", scenario.name());
         leptos::logging::log!("Description: {}", scenario.description());
 
+        let isolated = self.config.isolate_scenarios && !scenario.measures_accumulated_state();
+        let controller = if isolated {
+            Rc::new(RefCell::new((self.controller_factory)().build()))
+        } else {
+            self.controller.clone()
+        };
+        if let Some(dataset) = scenario.dataset() {
+            let ctrl = controller.borrow();
+            let facade = ctrl.facade();
+            for (addr, value) in dataset.generate() {
+                let _ = facade.set_cell_value(&addr, &value);
+            }
+        }
+
         // Warmup phase
         leptos::logging::log!(
             "Running {} warmup iterations...",
             self.config.warmup_iterations
         );
         for _ in 0..self.config.warmup_iterations {
-            scenario.warmup(self.controller.clone());
-            scenario.cleanup(self.controller.clone());
+            scenario.warmup(controller.clone());
+            scenario.cleanup(controller.clone());
         }
 
         // Setup for measurement
-        scenario.warmup(self.controller.clone());
+        scenario.warmup(controller.clone());
 
         // Measurement phase
         leptos::logging::log!(
@@ -67,18 +102,23 @@ impl UIBenchmarkRunner {
             self.config.measurement_iterations
         );
         for iteration in 0..self.config.measurement_iterations {
+            if self.config.settle_ms > 0 {
+                MemoryTracker::settle(self.config.settle_ms);
+            }
+
             // Start profiling if enabled
             if self.config.enable_profiling {
-                self.profiler.start_recording();
+                self.profiler().start_recording();
             }
 
             // Run the benchmark
-            let mut result = scenario.run(self.controller.clone());
+            let mut result = scenario.run(controller.clone());
             result.iteration = iteration + 1;
+            result.isolated = isolated;
 
             // Stop profiling and merge metrics
             if self.config.enable_profiling {
-                let profile_metrics = self.profiler.stop_recording();
+                let profile_metrics = self.profiler().stop_recording();
                 result.metrics = self.merge_metrics(result.metrics, profile_metrics);
             }
 
@@ -89,13 +129,29 @@ impl UIBenchmarkRunner {
         }
 
         // Cleanup
-        scenario.cleanup(self.controller.clone());
+        scenario.cleanup(controller.clone());
+        if isolated {
+            Self::teardown(controller, scenario.name());
+        }
 
         leptos::logging::log!("Benchmark complete: {}", scenario.name());
 
         results
     }
 
+    /// Drop an isolated scenario's controller along with its subscribers,
+    /// even those the scenario did not unsubscribe
+    fn teardown(controller: Rc<RefCell<SpreadsheetController>>, name: &str) {
+        controller.borrow_mut().clear_event_listeners();
+        if Rc::strong_count(&controller) > 1 {
+            leptos::logging::warn!("{} kept its controller after cleanup", name);
+        }
+    }
+
+    fn profiler(&mut self) -> &mut PerformanceProfiler {
+        self.profiler.get_or_insert_with(PerformanceProfiler::new)
+    }
+
     /// Run all registered benchmark scenarios
     pub fn run_all(&mut self) -> BenchmarkReport {
         let mut all_results = Vec::new();
@@ -203,7 +259,7 @@ pub struct BenchmarkReport {
     pub results: Vec<BenchmarkResult>,
     pub summary: BenchmarkSummary,
     pub warnings: Vec<String>,
-    pub suggestions: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
 }
 
 /// Summary statistics for benchmark results
@@ -219,3 +275,160 @@ pub struct BenchmarkSummary {
     pub total_memory_growth: f64,
     pub total_duration: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::{DatasetShape, ScenarioDataset};
+    use gridcore_core::types::CellAddress;
+    use std::sync::Arc;
+
+    /// Writes a cell per run and subscribes without ever unsubscribing
+    #[derive(Clone, Default)]
+    struct StubScenario {
+        accumulates: bool,
+        /// Held by every listener the scenario subscribes
+        subscriber: Arc<()>,
+        /// Cells found at the start of each run
+        cells_seen: Rc<RefCell<Vec<usize>>>,
+    }
+
+    impl BenchmarkScenario for StubScenario {
+        fn name(&self) -> &str {
+            "Stub"
+        }
+
+        fn description(&self) -> &str {
+            "Counts the cells it finds"
+        }
+
+        fn warmup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+            let subscriber = self.subscriber.clone();
+            controller
+                .borrow_mut()
+                .subscribe_to_events(move |_| drop(subscriber.clone()));
+        }
+
+        fn run(&mut self, controller: Rc<RefCell<SpreadsheetController>>) -> BenchmarkResult {
+            let ctrl = controller.borrow();
+            let facade = ctrl.facade();
+            let cells = facade.cell_count();
+            self.cells_seen.borrow_mut().push(cells);
+            let _ = facade.set_cell_value(&CellAddress::new(0, 100 + cells as u32), "1");
+            BenchmarkResult {
+                scenario_name: self.name().to_string(),
+                iteration: 1,
+                metrics: BenchmarkMetrics::new(),
+                success: true,
+                error_message: None,
+                isolated: false,
+            }
+        }
+
+        fn cleanup(&mut self, _controller: Rc<RefCell<SpreadsheetController>>) {}
+
+        fn dataset(&self) -> Option<ScenarioDataset> {
+            let shape = DatasetShape::NumericGrid {
+                rows: 2,
+                cols: 3,
+                min: 0.0,
+                max: 1.0,
+            };
+            Some(ScenarioDataset::new(shape, 1))
+        }
+
+        fn measures_accumulated_state(&self) -> bool {
+            self.accumulates
+        }
+    }
+
+    /// A runner doing one warmup and two measured runs, and the number of
+    /// controllers it has built
+    fn counting_runner(
+        shared: Rc<RefCell<SpreadsheetController>>,
+        isolate: bool,
+    ) -> (UIBenchmarkRunner, Rc<RefCell<usize>>) {
+        let builds = Rc::new(RefCell::new(0));
+        let counter = builds.clone();
+        let config = BenchmarkConfig::default()
+            .with_iterations(1, 2)
+            .with_profiling(false)
+            .with_settle_delay(0)
+            .with_isolation(isolate);
+        let runner = UIBenchmarkRunner::new(shared)
+            .with_config(config)
+            .with_controller_builder(move || {
+                *counter.borrow_mut() += 1;
+                SpreadsheetController::builder()
+            });
+        (runner, builds)
+    }
+
+    fn run(runner: &mut UIBenchmarkRunner, scenario: &StubScenario) -> Vec<BenchmarkResult> {
+        let mut boxed: Box<dyn BenchmarkScenario> = Box::new(scenario.clone());
+        runner.run_scenario(&mut boxed)
+    }
+
+    #[test]
+    fn test_isolated_scenarios_get_fresh_controllers() {
+        let shared = Rc::new(RefCell::new(SpreadsheetController::builder().build()));
+        let (mut runner, builds) = counting_runner(shared.clone(), true);
+
+        for expected_builds in 1..=2 {
+            let scenario = StubScenario::default();
+            let results = run(&mut runner, &scenario);
+            assert_eq!(*builds.borrow(), expected_builds);
+            assert!(results.iter().all(|result| result.isolated));
+            // Each scenario starts from its dataset alone
+            assert_eq!(*scenario.cells_seen.borrow(), [6, 7]);
+            // Listeners left subscribed are dropped with the controller
+            assert_eq!(Arc::strong_count(&scenario.subscriber), 1);
+        }
+        assert_eq!(shared.borrow().facade().cell_count(), 0);
+    }
+
+    #[test]
+    fn test_accumulating_scenarios_share_the_controller() {
+        let shared = Rc::new(RefCell::new(SpreadsheetController::builder().build()));
+        let (mut runner, builds) = counting_runner(shared.clone(), true);
+        let accumulating = StubScenario {
+            accumulates: true,
+            ..Default::default()
+        };
+        let results = run(&mut runner, &accumulating);
+        assert_eq!(*builds.borrow(), 0);
+        assert!(results.iter().all(|result| !result.isolated));
+
+        // With isolation off every scenario finds what the last left behind
+        let (mut runner, builds) = counting_runner(shared.clone(), false);
+        let scenario = StubScenario::default();
+        let results = run(&mut runner, &scenario);
+        assert_eq!(*builds.borrow(), 0);
+        assert!(results.iter().all(|result| !result.isolated));
+        assert_eq!(*scenario.cells_seen.borrow(), [8, 9]);
+    }
+
+    #[test]
+    fn test_memory_scenarios_start_from_an_empty_sheet() {
+        use crate::benchmark::scenarios::memory::MemoryGrowthBenchmark;
+
+        let shared = Rc::new(RefCell::new(SpreadsheetController::builder().build()));
+        let leftover = CellAddress::new(5, 5);
+        let _ = shared
+            .borrow()
+            .facade()
+            .set_cell_value(&leftover, "left over");
+        let (mut runner, _) = counting_runner(shared, true);
+        let mut scenario: Box<dyn BenchmarkScenario> = Box::new(MemoryGrowthBenchmark::new());
+        let results = runner.run_scenario(&mut scenario);
+
+        assert_eq!(results.len(), 2);
+        for result in &results {
+            assert!(result.isolated);
+            assert_eq!(result.metrics.cells_updated, 1000);
+            assert!(result.metrics.duration_ms >= 0.0);
+            // No heap figures off the browser, so no per-cell estimate
+            assert!(!result.metrics.custom_metrics.contains_key("bytes_per_cell"));
+        }
+    }
+}
@@ -188,6 +188,7 @@ impl BenchmarkScenario for DrawCallBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -326,6 +327,7 @@ impl BenchmarkScenario for CanvasStateBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -0,0 +1,339 @@
+use crate::benchmark::{BenchmarkMetrics, BenchmarkResult, BenchmarkScenario};
+use crate::demo::data_generator::DataGenerator;
+use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::state::{Action, Selection, SelectionType};
+use gridcore_core::types::CellAddress;
+use rand::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Events per edit above which a run is reported as a fan-out regression
+pub const EVENTS_PER_EDIT_WARNING: f64 = 12.0;
+
+/// Rows and columns read back after every edit, standing in for a render
+const VIEWPORT: (u32, u32) = (40, 12);
+
+/// Counts shared with the simulated UI subscribers
+#[derive(Default)]
+struct StormCounters {
+    events: AtomicU64,
+    signal_updates: AtomicU64,
+}
+
+/// Benchmark one edit fanning out through events, subscribers, recalculation
+/// and the read-back of the visible cells.
+///
+/// Every subscriber stands in for a UI component holding a reactive signal.
+/// Without coalescing each event updates every signal; with it a subscriber
+/// only marks itself dirty and its signal updates once per frame, which is
+/// what batching events per animation frame would give.
+pub struct EditingStormBenchmark {
+    rows: u32,
+    cols: u32,
+    formula_density: f64,
+    edits: usize,
+    subscribers: usize,
+    coalesce_events: bool,
+    seed: u64,
+    counters: Arc<StormCounters>,
+    dirty: Vec<Arc<AtomicBool>>,
+    listeners: Vec<usize>,
+}
+
+impl Default for EditingStormBenchmark {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditingStormBenchmark {
+    /// 500 edits over a 100k-cell sheet with 30% formulas, 20 subscribers
+    pub fn new() -> Self {
+        Self {
+            rows: 1000,
+            cols: 100,
+            formula_density: 0.3,
+            edits: 500,
+            subscribers: 20,
+            coalesce_events: false,
+            seed: 42,
+            counters: Arc::new(StormCounters::default()),
+            dirty: Vec::new(),
+            listeners: Vec::new(),
+        }
+    }
+
+    /// The same storm with subscribers that coalesce events per frame
+    pub fn coalesced() -> Self {
+        Self {
+            coalesce_events: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn with_sheet_size(mut self, rows: u32, cols: u32) -> Self {
+        self.rows = rows;
+        self.cols = cols;
+        self
+    }
+
+    pub fn with_edits(mut self, edits: usize) -> Self {
+        self.edits = edits;
+        self
+    }
+
+    pub fn with_subscribers(mut self, subscribers: usize) -> Self {
+        self.subscribers = subscribers;
+        self
+    }
+
+    fn subscribe(&mut self, controller: &mut SpreadsheetController) {
+        let counters = self.counters.clone();
+        self.listeners
+            .push(controller.subscribe_to_events(move |_| {
+                counters.events.fetch_add(1, Ordering::Relaxed);
+            }));
+
+        for _ in 0..self.subscribers {
+            let counters = self.counters.clone();
+            let dirty = Arc::new(AtomicBool::new(false));
+            let flag = dirty.clone();
+            let coalesce = self.coalesce_events;
+            self.listeners
+                .push(controller.subscribe_to_events(move |_| {
+                    if coalesce {
+                        flag.store(true, Ordering::Relaxed);
+                    } else {
+                        counters.signal_updates.fetch_add(1, Ordering::Relaxed);
+                    }
+                }));
+            self.dirty.push(dirty);
+        }
+    }
+
+    /// End the frame: coalesced signals update once, then the visible cells
+    /// are read back. Returns the number of cells read.
+    fn render(&self, controller: &SpreadsheetController) -> u32 {
+        for dirty in &self.dirty {
+            if dirty.swap(false, Ordering::Relaxed) {
+                self.counters.signal_updates.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let cursor = controller.cursor();
+        let top = cursor.row.saturating_sub(VIEWPORT.0 / 2);
+        let left = cursor.col.saturating_sub(VIEWPORT.1 / 2);
+        let mut cells = 0;
+        for row in top..top + VIEWPORT.0 {
+            for col in left..left + VIEWPORT.1 {
+                let _ = controller.get_cell_display_for_ui(&CellAddress::new(col, row));
+                cells += 1;
+            }
+        }
+        cells
+    }
+}
+
+impl BenchmarkScenario for EditingStormBenchmark {
+    fn name(&self) -> &str {
+        if self.coalesce_events {
+            "Editing Storm (coalesced)"
+        } else {
+            "Editing Storm"
+        }
+    }
+
+    fn description(&self) -> &str {
+        "Measures end-to-end edit latency and event fan-out with many subscribers"
+    }
+
+    fn iterations(&self) -> usize {
+        3
+    }
+
+    fn warmup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        let mut gen = DataGenerator::with_seed(self.seed);
+        let data = gen.generate_mixed_sheet(self.rows, self.cols, self.formula_density);
+
+        let mut ctrl = controller.borrow_mut();
+        {
+            let facade = ctrl.facade();
+            for (addr, value) in data {
+                let _ = facade.set_cell_value(&addr, &value);
+            }
+        }
+        self.subscribe(&mut ctrl);
+    }
+
+    fn run(&mut self, controller: Rc<RefCell<SpreadsheetController>>) -> BenchmarkResult {
+        let mut metrics = BenchmarkMetrics::new();
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.counters.events.store(0, Ordering::Relaxed);
+        self.counters.signal_updates.store(0, Ordering::Relaxed);
+        metrics.start_time = Self::now();
+
+        let mut ctrl = controller.borrow_mut();
+        for i in 0..self.edits {
+            let address = CellAddress::new(
+                rng.random_range(0..self.cols),
+                rng.random_range(0..self.rows),
+            );
+            let value = if address.col > 0 && rng.random_bool(self.formula_density) {
+                format!(
+                    "={}*2",
+                    CellAddress::new(address.col - 1, address.row).to_a1()
+                )
+            } else {
+                format!("{:.2}", rng.random_range(0.0..1000.0))
+            };
+
+            let start = Self::now();
+            let _ = ctrl.dispatch_action(Action::UpdateCursor { cursor: address });
+            if i % 5 == 0 {
+                let end = CellAddress::new(address.col + 3, address.row + 3);
+                let _ = ctrl.dispatch_action(Action::UpdateSelection {
+                    selection: Selection {
+                        selection_type: SelectionType::Range {
+                            start: address,
+                            end,
+                        },
+                        anchor: Some(address),
+                    },
+                });
+            }
+            let _ = ctrl.dispatch_action(Action::StartEditing {
+                edit_mode: None,
+                initial_value: Some(value.clone()),
+                cursor_position: None,
+            });
+            let _ = ctrl.dispatch_action(Action::SubmitCellEdit { value });
+            metrics.cells_rendered += self.render(&ctrl);
+            metrics.interaction_latencies.push(Self::now() - start);
+        }
+        drop(ctrl);
+
+        metrics.end_time = Self::now();
+        metrics.cells_updated = self.edits as u32;
+        metrics.finalize();
+
+        let events = self.counters.events.load(Ordering::Relaxed) as f64;
+        let signal_updates = self.counters.signal_updates.load(Ordering::Relaxed) as f64;
+        let edits = self.edits.max(1) as f64;
+        let custom = &mut metrics.custom_metrics;
+        custom.insert("events_dispatched".to_string(), events);
+        custom.insert("events_per_edit".to_string(), events / edits);
+        custom.insert("signal_updates".to_string(), signal_updates);
+        custom.insert(
+            "signal_updates_per_edit".to_string(),
+            signal_updates / edits,
+        );
+        custom.insert("subscribers".to_string(), self.subscribers as f64);
+
+        BenchmarkResult {
+            scenario_name: self.name().to_string(),
+            iteration: 1,
+            metrics,
+            success: true,
+            error_message: None,
+            isolated: false,
+        }
+    }
+
+    fn cleanup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        let mut ctrl = controller.borrow_mut();
+        // Listener ids are positions, so drop the newest first
+        for id in self.listeners.drain(..).rev() {
+            ctrl.unsubscribe_from_events(id);
+        }
+        self.dirty.clear();
+
+        let facade = ctrl.facade();
+        for (addr, _) in facade.get_all_cells() {
+            let _ = facade.delete_cell(&addr);
+        }
+    }
+}
+
+/// Run the storm without and with coalescing on fresh controllers, without
+/// a browser: everything but the canvas paint
+pub fn run_headless(storm: &EditingStormBenchmark) -> Vec<BenchmarkResult> {
+    [false, true]
+        .into_iter()
+        .map(|coalesce_events| {
+            let mut scenario = storm.fresh(coalesce_events);
+            let controller = Rc::new(RefCell::new(SpreadsheetController::builder().build()));
+            scenario.warmup(controller.clone());
+            let result = scenario.run(controller.clone());
+            scenario.cleanup(controller);
+            result
+        })
+        .collect()
+}
+
+impl EditingStormBenchmark {
+    /// The same settings with nothing subscribed yet
+    fn fresh(&self, coalesce_events: bool) -> Self {
+        Self {
+            rows: self.rows,
+            cols: self.cols,
+            formula_density: self.formula_density,
+            edits: self.edits,
+            subscribers: self.subscribers,
+            coalesce_events,
+            seed: self.seed,
+            counters: Arc::new(StormCounters::default()),
+            dirty: Vec::new(),
+            listeners: Vec::new(),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn now() -> f64 {
+        web_sys::window()
+            .and_then(|w| w.performance())
+            .map(|p| p.now())
+            .unwrap_or(0.0)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn now() -> f64 {
+        use std::sync::OnceLock;
+        use std::time::Instant;
+
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coalescing_cuts_signal_updates() {
+        let storm = EditingStormBenchmark::new()
+            .with_sheet_size(30, 10)
+            .with_edits(20)
+            .with_subscribers(4);
+        let results = run_headless(&storm);
+        let metric = |i: usize, name: &str| results[i].metrics.custom_metrics[name];
+
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .all(|r| r.metrics.interaction_latencies.len() == 20));
+        // Both runs dispatch the same events
+        assert_eq!(
+            metric(0, "events_dispatched"),
+            metric(1, "events_dispatched")
+        );
+        assert!(metric(0, "events_per_edit") >= 1.0);
+        assert_eq!(
+            metric(0, "signal_updates"),
+            4.0 * metric(0, "events_dispatched")
+        );
+        assert!(metric(1, "signal_updates") <= 4.0 * 20.0);
+    }
+}
@@ -124,6 +124,7 @@ impl BenchmarkScenario for SimpleFormulaBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -294,6 +295,7 @@ impl BenchmarkScenario for ComplexFormulaBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
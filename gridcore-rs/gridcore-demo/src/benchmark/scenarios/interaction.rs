@@ -1,10 +1,80 @@
 use crate::benchmark::{BenchmarkMetrics, BenchmarkResult, BenchmarkScenario};
 use crate::demo::data_generator::DataGenerator;
-use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::controller::{ConcernTracker, Concerns, SpreadsheetController};
 use gridcore_controller::state::{Action, Selection, SelectionType};
 use gridcore_core::types::CellAddress;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// Counts the signal updates the UI makes per action. With one generation
+/// counter every event reaches the views of every concern; the concern
+/// signals change only with what they name.
+struct SignalCounter {
+    pending: Arc<Mutex<(Concerns, usize)>>,
+    subscription: usize,
+    tracker: ConcernTracker,
+    actions: usize,
+    events: usize,
+}
+
+impl SignalCounter {
+    fn attach(controller: &mut SpreadsheetController) -> Self {
+        let pending = Arc::new(Mutex::new((Concerns::NONE, 0)));
+        let sink = pending.clone();
+        let subscription = controller.subscribe_to_events(move |event| {
+            if let Ok(mut pending) = sink.lock() {
+                pending.0 |= Concerns::of(event);
+                pending.1 += 1;
+            }
+        });
+        Self {
+            pending,
+            subscription,
+            tracker: ConcernTracker::new(controller),
+            actions: 0,
+            events: 0,
+        }
+    }
+
+    /// Bring the concerns up to date after one action
+    fn action(&mut self, controller: &SpreadsheetController) {
+        let (concerns, events) = self
+            .pending
+            .lock()
+            .map(|mut pending| std::mem::take(&mut *pending))
+            .unwrap_or_default();
+        self.tracker.sync(controller, concerns);
+        self.actions += 1;
+        self.events += events;
+    }
+
+    fn detach(&self, controller: &mut SpreadsheetController) {
+        controller.unsubscribe_from_events(self.subscription);
+    }
+
+    /// Record both counts per action. Fails when the concern signals do not
+    /// update less often than the generation counter did.
+    fn record(&self, metrics: &mut BenchmarkMetrics) -> Result<(), String> {
+        let actions = self.actions.max(1) as f64;
+        let baseline = (self.events * Concerns::ALL.len()) as f64 / actions;
+        let updates = self.tracker.updates() as f64 / actions;
+        metrics
+            .custom_metrics
+            .insert("generation_updates_per_action".to_string(), baseline);
+        metrics
+            .custom_metrics
+            .insert("signal_updates_per_action".to_string(), updates);
+
+        if self.events > 0 && updates >= baseline {
+            return Err(format!(
+                "{:.2} signal updates per action, not below the {:.2} of one generation counter",
+                updates, baseline
+            ));
+        }
+        Ok(())
+    }
+}
 
 /// Benchmark cell editing performance
 pub struct CellEditBenchmark {
@@ -64,6 +134,8 @@ impl BenchmarkScenario for CellEditBenchmark {
         let mut metrics = BenchmarkMetrics::new();
         metrics.start_time = Self::now();
 
+        let mut signals = SignalCounter::attach(&mut controller.borrow_mut());
+
         // Test single cell edits
         for (pos, value) in self.test_positions.iter().zip(&self.test_values) {
             let mut ctrl = controller.borrow_mut();
@@ -72,6 +144,7 @@ impl BenchmarkScenario for CellEditBenchmark {
             let nav_start = Self::now();
             let _ = ctrl.dispatch_action(Action::UpdateCursor { cursor: *pos });
             let nav_time = Self::now() - nav_start;
+            signals.action(&ctrl);
 
             // Enter edit mode
             let edit_start = Self::now();
@@ -81,6 +154,7 @@ impl BenchmarkScenario for CellEditBenchmark {
                 cursor_position: None,
             });
             let enter_edit_time = Self::now() - edit_start;
+            signals.action(&ctrl);
 
             // For now, we'll use the facade to set the value directly
             // since the edit buffer API has changed
@@ -99,6 +173,7 @@ impl BenchmarkScenario for CellEditBenchmark {
             let exit_start = Self::now();
             let _ = ctrl.dispatch_action(Action::ExitToNavigation);
             let exit_time = Self::now() - exit_start;
+            signals.action(&ctrl);
 
             drop(ctrl);
 
@@ -140,6 +215,10 @@ impl BenchmarkScenario for CellEditBenchmark {
             .custom_metrics
             .insert("bulk_paste_100_cells_ms".to_string(), paste_time);
         metrics.cells_updated = paste_data.len() as u32;
+        drop(ctrl);
+
+        signals.detach(&mut controller.borrow_mut());
+        let signal_check = signals.record(&mut metrics);
 
         metrics.end_time = Self::now();
         metrics.finalize();
@@ -148,8 +227,9 @@ impl BenchmarkScenario for CellEditBenchmark {
             scenario_name: self.name().to_string(),
             iteration: 1,
             metrics,
-            success: true,
-            error_message: None,
+            success: signal_check.is_ok(),
+            error_message: signal_check.err(),
+            isolated: false,
         }
     }
 
@@ -229,6 +309,7 @@ impl BenchmarkScenario for SelectionBenchmark {
         metrics.start_time = Self::now();
 
         let mut ctrl = controller.borrow_mut();
+        let mut signals = SignalCounter::attach(&mut ctrl);
 
         // Test different selection sizes
         for (start, end, label) in &self.test_ranges {
@@ -244,6 +325,7 @@ impl BenchmarkScenario for SelectionBenchmark {
                 },
             });
             let select_time = Self::now() - select_start;
+            signals.action(&ctrl);
 
             metrics.interaction_latencies.push(select_time);
             metrics
@@ -265,6 +347,7 @@ impl BenchmarkScenario for SelectionBenchmark {
                 },
             });
             let clear_time = Self::now() - clear_start;
+            signals.action(&ctrl);
             metrics
                 .custom_metrics
                 .insert(format!("{}_clear_ms", label), clear_time);
@@ -282,6 +365,7 @@ impl BenchmarkScenario for SelectionBenchmark {
         let multi_start = Self::now();
         for addr in &multi_ranges {
             let _ = ctrl.dispatch_action(Action::UpdateCursor { cursor: *addr });
+            signals.action(&ctrl);
         }
         let multi_time = Self::now() - multi_start;
 
@@ -301,11 +385,15 @@ impl BenchmarkScenario for SelectionBenchmark {
             },
         });
         let select_all_time = Self::now() - select_all_start;
+        signals.action(&ctrl);
 
         metrics
             .custom_metrics
             .insert("select_all_ms".to_string(), select_all_time);
 
+        signals.detach(&mut ctrl);
+        let signal_check = signals.record(&mut metrics);
+
         metrics.end_time = Self::now();
         metrics.finalize();
 
@@ -313,8 +401,9 @@ impl BenchmarkScenario for SelectionBenchmark {
             scenario_name: self.name().to_string(),
             iteration: 1,
             metrics,
-            success: true,
-            error_message: None,
+            success: signal_check.is_ok(),
+            error_message: signal_check.err(),
+            isolated: false,
         }
     }
 
@@ -343,18 +432,61 @@ impl BenchmarkScenario for SelectionBenchmark {
 // Helper functions
 impl CellEditBenchmark {
     fn now() -> f64 {
-        web_sys::window()
-            .and_then(|w| w.performance())
-            .map(|p| p.now())
-            .unwrap_or(0.0)
+        now_ms()
     }
 }
 
 impl SelectionBenchmark {
     fn now() -> f64 {
-        web_sys::window()
-            .and_then(|w| w.performance())
-            .map(|p| p.now())
-            .unwrap_or(0.0)
+        now_ms()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now_ms() -> f64 {
+    web_sys::window()
+        .and_then(|w| w.performance())
+        .map(|p| p.now())
+        .unwrap_or(0.0)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(scenario: &mut dyn BenchmarkScenario) -> BenchmarkResult {
+        let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+        scenario.warmup(controller.clone());
+        let result = scenario.run(controller.clone());
+        scenario.cleanup(controller);
+        result
+    }
+
+    #[test]
+    fn test_concern_signals_update_less_than_one_generation() {
+        let results = [
+            run(&mut CellEditBenchmark::new()),
+            run(&mut SelectionBenchmark::new()),
+        ];
+        for result in &results {
+            assert!(result.success, "{:?}", result.error_message);
+            let metric = |name: &str| result.metrics.custom_metrics[name];
+            assert!(metric("signal_updates_per_action") > 0.0);
+            assert!(
+                metric("signal_updates_per_action") * 2.0 < metric("generation_updates_per_action"),
+                "{}: {:?}",
+                result.scenario_name,
+                result.metrics.custom_metrics
+            );
+        }
     }
 }
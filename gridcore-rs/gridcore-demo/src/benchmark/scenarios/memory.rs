@@ -3,6 +3,7 @@ use crate::benchmark::{BenchmarkMetrics, BenchmarkResult, BenchmarkScenario};
 use crate::demo::data_generator::DataGenerator;
 use gridcore_controller::controller::SpreadsheetController;
 use gridcore_controller::state::Action;
+use gridcore_core::memory::CompactOptions;
 use gridcore_core::types::CellAddress;
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -27,6 +28,12 @@ impl MemoryGrowthBenchmark {
         }
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_memory_usage() -> f64 {
+        0.0
+    }
+
+    #[cfg(target_arch = "wasm32")]
     fn get_memory_usage() -> f64 {
         // Try to get memory usage from performance.memory (Chrome only)
         if let Some(window) = web_sys::window() {
@@ -71,8 +78,6 @@ impl BenchmarkScenario for MemoryGrowthBenchmark {
         let mut memory_samples = Vec::new();
         memory_samples.push(metrics.heap_used_start);
 
-        let _gen = DataGenerator::new();
-
         // Run multiple cycles of operations
         for cycle in 0..self.cycles {
             leptos::logging::log!("Memory test cycle {}/{}", cycle + 1, self.cycles);
@@ -140,11 +145,11 @@ impl BenchmarkScenario for MemoryGrowthBenchmark {
         metrics.heap_peak = memory_samples.iter().fold(0.0, |a, &b| a.max(b));
         metrics.memory_growth = metrics.heap_used_end - metrics.heap_used_start;
 
-        // Calculate average memory per cell
+        // Calculate average memory per cell, when the heap size is known
         drop(ctrl);
         let ctrl = controller.borrow();
         let cell_count = ctrl.facade().cell_count();
-        if cell_count > 0 {
+        if cell_count > 0 && metrics.memory_growth > 0.0 {
             let bytes_per_cell = (metrics.memory_growth * 1024.0 * 1024.0) / cell_count as f64;
             metrics
                 .custom_metrics
@@ -161,6 +166,7 @@ impl BenchmarkScenario for MemoryGrowthBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -180,6 +186,7 @@ impl BenchmarkScenario for MemoryGrowthBenchmark {
 /// Benchmark memory cleanup effectiveness
 pub struct MemoryCleanupBenchmark {
     data_size: u32,
+    seed: u64,
 }
 
 impl Default for MemoryCleanupBenchmark {
@@ -190,7 +197,10 @@ impl Default for MemoryCleanupBenchmark {
 
 impl MemoryCleanupBenchmark {
     pub fn new() -> Self {
-        Self { data_size: 1000 }
+        Self {
+            data_size: 1000,
+            seed: 7,
+        }
     }
 
     fn get_memory_usage() -> f64 {
@@ -230,7 +240,7 @@ impl BenchmarkScenario for MemoryCleanupBenchmark {
 
         // Phase 1: Create large dataset
         let phase1_start = Self::now();
-        let mut gen = DataGenerator::new();
+        let mut gen = DataGenerator::with_seed(self.seed);
         let data = gen.generate_numeric_grid(self.data_size, 50, 0.0, 1000.0);
 
         let ctrl = controller.borrow();
@@ -262,7 +272,17 @@ impl BenchmarkScenario for MemoryCleanupBenchmark {
 
         let phase2_memory_before_gc = Self::get_memory_usage();
 
-        // Force GC and measure again
+        // Give back what the cleared cells left behind, then force GC
+        let report = facade.compact(&CompactOptions::default());
+        let mb = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+        for (name, bytes) in [
+            ("compact_before_mb", report.before_bytes()),
+            ("compact_after_mb", report.after_bytes()),
+            ("compact_reusable_mb", report.reusable_bytes()),
+            ("compact_returned_mb", report.returned_bytes()),
+        ] {
+            metrics.custom_metrics.insert(name.to_string(), mb(bytes));
+        }
         MemoryTracker::force_gc();
         let phase2_memory_after_gc = Self::get_memory_usage();
         let phase2_time = Self::now() - phase2_start;
@@ -342,6 +362,7 @@ impl BenchmarkScenario for MemoryCleanupBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -359,19 +380,26 @@ impl BenchmarkScenario for MemoryCleanupBenchmark {
 
 // Helper functions
 impl MemoryGrowthBenchmark {
+    #[cfg(target_arch = "wasm32")]
     fn now() -> f64 {
         web_sys::window()
             .and_then(|w| w.performance())
             .map(|p| p.now())
             .unwrap_or(0.0)
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn now() -> f64 {
+        use std::sync::OnceLock;
+        use std::time::Instant;
+
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+    }
 }
 
 impl MemoryCleanupBenchmark {
     fn now() -> f64 {
-        web_sys::window()
-            .and_then(|w| w.performance())
-            .map(|p| p.now())
-            .unwrap_or(0.0)
+        MemoryGrowthBenchmark::now()
     }
 }
@@ -1,4 +1,5 @@
 pub mod canvas;
+pub mod editing_storm;
 pub mod formula;
 pub mod interaction;
 pub mod memory;
@@ -63,6 +64,15 @@ impl ScenarioRegistry {
             Box::new(interaction::SelectionBenchmark::new()),
         );
 
+        self.register(
+            "editing_storm",
+            Box::new(editing_storm::EditingStormBenchmark::new()),
+        );
+        self.register(
+            "editing_storm_coalesced",
+            Box::new(editing_storm::EditingStormBenchmark::coalesced()),
+        );
+
         // Formula benchmarks
         self.register(
             "formula_simple",
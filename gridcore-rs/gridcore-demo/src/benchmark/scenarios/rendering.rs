@@ -114,6 +114,7 @@ impl BenchmarkScenario for InitialRenderBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -252,6 +253,7 @@ impl BenchmarkScenario for LargeGridRenderBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -1,10 +1,55 @@
 use crate::benchmark::{BenchmarkMetrics, BenchmarkResult, BenchmarkScenario};
 use crate::demo::data_generator::DataGenerator;
-use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::controller::{SpreadsheetController, SpreadsheetEvent, ViewportBounds};
 use gridcore_controller::state::{Action, ViewportInfo};
 use gridcore_core::types::CellAddress;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Frames per second the wheel phase of [`SmoothScrollBenchmark`] assumes
+const FRAMES_PER_SECOND: f64 = 60.0;
+
+/// Wheel events a trackpad sends per frame at 60 frames per second
+const WHEEL_EVENTS_PER_FRAME: usize = 8;
+
+/// Pixels per trackpad wheel event
+const WHEEL_DELTA: f64 = 2.5;
+
+/// Scroll `frames` frames of trackpad wheel events and count the
+/// `ViewportScrolled` events dispatched, applying every wheel event as it
+/// comes or, with `batched`, once per frame
+pub fn count_wheel_scroll_events(
+    controller: &mut SpreadsheetController,
+    frames: usize,
+    batched: bool,
+) -> u64 {
+    let events = Arc::new(AtomicU64::new(0));
+    let counter = events.clone();
+    let listener = controller.subscribe_to_events(move |event| {
+        if let SpreadsheetEvent::ViewportScrolled { .. } = event {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+
+    for _ in 0..frames {
+        for _ in 0..WHEEL_EVENTS_PER_FRAME {
+            if batched {
+                controller.queue_scroll(0.0, WHEEL_DELTA);
+            } else {
+                controller.scroll_now(0.0, WHEEL_DELTA);
+            }
+        }
+        controller.apply_pending_scroll();
+    }
+
+    controller.unsubscribe_from_events(listener);
+    controller
+        .get_viewport_manager_mut()
+        .set_scroll_position(0.0, 0.0);
+    events.load(Ordering::Relaxed)
+}
 
 /// Benchmark smooth scrolling performance
 pub struct SmoothScrollBenchmark {
@@ -35,7 +80,7 @@ impl BenchmarkScenario for SmoothScrollBenchmark {
     }
 
     fn description(&self) -> &str {
-        "Measures FPS and latency during smooth vertical scrolling"
+        "Measures FPS, latency and scroll events per second during smooth vertical scrolling"
     }
 
     fn warmup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
@@ -95,6 +140,18 @@ impl BenchmarkScenario for SmoothScrollBenchmark {
             );
         }
 
+        // Trackpad scrolling, with each wheel event applied and with the
+        // wheel events of a frame applied together
+        let frames = self.scroll_steps;
+        let seconds = frames as f64 / FRAMES_PER_SECOND;
+        for (name, batched) in [("unbatched", false), ("batched", true)] {
+            let events = count_wheel_scroll_events(&mut ctrl, frames, batched);
+            metrics.custom_metrics.insert(
+                format!("{}_events_per_second", name),
+                events as f64 / seconds,
+            );
+        }
+
         metrics.end_time = Self::now();
         metrics.finalize();
 
@@ -104,6 +161,7 @@ impl BenchmarkScenario for SmoothScrollBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -229,6 +287,7 @@ impl BenchmarkScenario for JumpNavigationBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -331,7 +390,21 @@ impl BenchmarkScenario for LargeDatasetScrollBenchmark {
                     },
                 });
 
+                // What the renderer would draw for this frame
+                let bounds = ViewportBounds {
+                    start_row: row as usize,
+                    end_row: row as usize + 19,
+                    start_col: 0,
+                    end_col: 9,
+                };
+                let _cells = ctrl.get_display_list(&bounds);
+
                 let scroll_end = Self::now();
+
+                // The UI prefetches from an idle callback, outside the frame
+                if ctrl.needs_prefetch(&bounds) {
+                    ctrl.prefetch_viewport(&bounds);
+                }
                 let scroll_time = scroll_end - scroll_start;
 
                 metrics.frame_times.push(scroll_time);
@@ -341,6 +414,14 @@ impl BenchmarkScenario for LargeDatasetScrollBenchmark {
             }
         }
 
+        let cache_stats = ctrl.get_viewport_cache().stats();
+        metrics
+            .custom_metrics
+            .insert("cache_hits".to_string(), cache_stats.hits as f64);
+        metrics
+            .custom_metrics
+            .insert("cache_misses".to_string(), cache_stats.misses as f64);
+
         // Get cell count for reference
         let facade = ctrl.facade();
         metrics.cells_rendered = facade.cell_count() as u32;
@@ -354,6 +435,7 @@ impl BenchmarkScenario for LargeDatasetScrollBenchmark {
             metrics,
             success: true,
             error_message: None,
+            isolated: false,
         }
     }
 
@@ -403,3 +485,17 @@ impl LargeDatasetScrollBenchmark {
             .unwrap_or(0.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batching_dispatches_one_scroll_event_per_frame() {
+        let mut controller = SpreadsheetController::builder().build();
+        let unbatched = count_wheel_scroll_events(&mut controller, 10, false);
+        let batched = count_wheel_scroll_events(&mut controller, 10, true);
+        assert_eq!(unbatched, 10 * WHEEL_EVENTS_PER_FRAME as u64);
+        assert_eq!(batched, 10);
+    }
+}
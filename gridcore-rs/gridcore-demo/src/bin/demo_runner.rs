@@ -3,8 +3,17 @@
 
 use clap::{Parser, Subcommand};
 use gridcore_controller::controller::SpreadsheetController;
+use gridcore_core::csv::{preview_import, CsvImportOptions, ImportOptions};
+use gridcore_core::script::ScriptSession;
+use gridcore_core::template::{
+    instantiate_template, list_templates, EmbeddedTemplates, TemplateSet,
+};
+use gridcore_core::SpreadsheetFacade;
+use gridcore_demo::benchmark::scenarios::editing_storm::{self, EditingStormBenchmark};
 use gridcore_demo::{demo::scenarios, DemoController};
 use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 #[derive(Parser)]
@@ -44,6 +53,112 @@ enum Commands {
         #[arg(short, long, default_value = "text")]
         format: String,
     },
+
+    /// Run the editing storm headless, without and with event coalescing
+    Storm {
+        /// Number of edits
+        #[arg(short, long, default_value = "500")]
+        edits: usize,
+
+        /// Number of simulated UI subscribers
+        #[arg(short, long, default_value = "20")]
+        subscribers: usize,
+    },
+
+    /// Type script commands (get, set, range, ...) against an empty sheet
+    Console {
+        /// Print results as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+
+    /// Build a sheet from a script file and check its dependency graph
+    Check {
+        /// Script to run first
+        file: PathBuf,
+
+        /// Rebuild the inconsistent parts of the graph
+        #[arg(short, long)]
+        repair: bool,
+
+        /// Print the report as JSON
+        #[arg(short, long)]
+        json: bool,
+    },
+
+    /// Build a sheet from a script file and export it as CSV with formulas
+    ExportCsv {
+        /// Script that builds the sheet
+        file: PathBuf,
+
+        /// Write the formats the CSV cannot hold to this JSON file
+        #[arg(long, value_name = "SIDECAR")]
+        with_sidecar: Option<PathBuf>,
+
+        /// Write the CSV to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import a CSV written by export-csv and report what was not restored
+    ImportCsv {
+        /// CSV file to import
+        file: PathBuf,
+
+        /// Sidecar written alongside the CSV
+        #[arg(long)]
+        sidecar: Option<PathBuf>,
+
+        /// Print the report as JSON
+        #[arg(short, long)]
+        json: bool,
+
+        /// Load what can be read of a damaged or newer document: formulas
+        /// that do not parse are kept as text and unknown sidecar sections
+        /// are kept for export
+        #[arg(long)]
+        lenient: bool,
+
+        /// Types of the columns from the left, e.g. text,number,skip;
+        /// general, text, number, date or skip each
+        #[arg(long, value_name = "TYPES", conflicts_with = "sidecar")]
+        column_types: Option<String>,
+
+        /// Print what the first rows suggest as import options JSON
+        /// instead of importing
+        #[arg(long, value_name = "ROWS", conflicts_with = "sidecar")]
+        preview: Option<usize>,
+    },
+
+    /// List the templates `new` can start from
+    Templates {
+        /// Also offer the template JSON files in this directory
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+    },
+
+    /// Create a sheet from a template and export it as CSV with formulas
+    New {
+        /// Id of the template, e.g. budget
+        #[arg(short, long)]
+        template: String,
+
+        /// Value for a template parameter, e.g. month=2025-01
+        #[arg(short, long = "param", value_name = "NAME=VALUE")]
+        params: Vec<String>,
+
+        /// Also offer the template JSON files in this directory
+        #[arg(long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+
+        /// Write the formats the CSV cannot hold to this JSON file
+        #[arg(long, value_name = "SIDECAR")]
+        with_sidecar: Option<PathBuf>,
+
+        /// Write the CSV to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 }
 
 fn main() {
@@ -74,14 +189,316 @@ fn main() {
         Commands::Benchmark { quick, format } => {
             run_benchmark(quick, &format);
         }
+
+        Commands::Storm { edits, subscribers } => {
+            run_storm(edits, subscribers);
+        }
+
+        Commands::Console { json } => {
+            run_console(json);
+        }
+
+        Commands::Check { file, repair, json } => {
+            run_check(&file, repair, json);
+        }
+
+        Commands::ExportCsv {
+            file,
+            with_sidecar,
+            output,
+        } => {
+            run_export_csv(&file, with_sidecar.as_deref(), output.as_deref());
+        }
+
+        Commands::ImportCsv {
+            file,
+            sidecar,
+            json,
+            lenient,
+            column_types,
+            preview,
+        } => match (column_types, preview) {
+            (None, None) => run_import_csv(&file, sidecar.as_deref(), json, lenient),
+            (column_types, preview) => {
+                run_typed_import(&file, column_types.as_deref(), preview, json, lenient)
+            }
+        },
+
+        Commands::Templates { dir } => {
+            run_templates(dir.as_deref());
+        }
+
+        Commands::New {
+            template,
+            params,
+            dir,
+            with_sidecar,
+            output,
+        } => {
+            run_new(
+                &template,
+                &params,
+                dir.as_deref(),
+                with_sidecar.as_deref(),
+                output.as_deref(),
+            );
+        }
+    }
+}
+
+fn read_file(file: &Path) -> String {
+    match std::fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Cannot read {}: {}", file.display(), e);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn write_file(file: &Path, text: &str) {
+    if let Err(e) = std::fs::write(file, text) {
+        eprintln!("Cannot write {}: {}", file.display(), e);
+        std::process::exit(2);
+    }
+}
+
+/// A sheet built by bulk loading a script file
+fn load_script(file: &Path) -> SpreadsheetFacade {
+    let script = read_file(file);
+    let facade = SpreadsheetFacade::new();
+    if let Err(e) = ScriptSession::new().load(&facade, &script) {
+        eprintln!("{}:{}", file.display(), e);
+        std::process::exit(2);
+    }
+    facade
+}
+
+fn run_export_csv(file: &Path, sidecar_file: Option<&Path>, output: Option<&Path>) {
+    let facade = load_script(file);
+    let export = facade.export_csv(sidecar_file.is_some());
+    if let (Some(path), Some(sidecar)) = (sidecar_file, &export.sidecar) {
+        write_file(path, &sidecar.to_json());
+    }
+    match output {
+        Some(path) => write_file(path, &export.csv),
+        None => print!("{}", export.csv),
+    }
+}
+
+fn run_import_csv(file: &Path, sidecar_file: Option<&Path>, json: bool, lenient: bool) {
+    let csv = read_file(file);
+    let sidecar = sidecar_file.map(read_file);
+    let options = if lenient {
+        CsvImportOptions::lenient()
+    } else {
+        CsvImportOptions::default()
+    };
+
+    let facade = SpreadsheetFacade::new();
+    let report = match facade.import_csv_document(&csv, sidecar.as_deref(), &options) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Import failed: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string(&report).unwrap_or_default());
+    } else {
+        println!("{}", report);
+    }
+    if !report.is_faithful() {
+        std::process::exit(1);
+    }
+}
+
+/// Import with the types of the columns given, or print the options a
+/// preview of the first `preview` rows suggests
+fn run_typed_import(
+    file: &Path,
+    column_types: Option<&str>,
+    preview: Option<usize>,
+    json: bool,
+    lenient: bool,
+) {
+    let source = match std::fs::read(file) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Cannot read {}: {}", file.display(), e);
+            std::process::exit(2);
+        }
+    };
+    let mut options = ImportOptions::default();
+    if lenient {
+        options.fields = CsvImportOptions::lenient();
+    }
+    if let Some(list) = column_types {
+        options.column_types = match ImportOptions::parse_column_types(list) {
+            Ok(types) => types,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        };
+    }
+    if let Some(rows) = preview {
+        println!(
+            "{}",
+            preview_import(&source, &options, rows).options.to_json()
+        );
+        return;
+    }
+
+    let facade = SpreadsheetFacade::new();
+    let report = match facade.commit_import(&source, &options) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Import failed: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string(&report).unwrap_or_default());
+    } else {
+        println!("{}", report);
+    }
+    if !report.is_faithful() {
+        std::process::exit(1);
     }
 }
 
+/// The built-in templates, with those in the JSON files of `dir` added
+fn load_templates(dir: Option<&Path>) -> TemplateSet {
+    let mut set = TemplateSet::with_fallback(EmbeddedTemplates);
+    let Some(dir) = dir else {
+        return set;
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Cannot read {}: {}", dir.display(), e);
+            std::process::exit(2);
+        }
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    for file in files {
+        if let Err(e) = set.add_json(&read_file(&file)) {
+            eprintln!("{}: {}", file.display(), e);
+            std::process::exit(2);
+        }
+    }
+    set
+}
+
+fn run_templates(dir: Option<&Path>) {
+    for info in list_templates(&load_templates(dir)) {
+        println!("{} - {}", info.id, info.name);
+        println!("    {}", info.description);
+        for parameter in &info.parameters {
+            println!(
+                "    --param {}=...  {} ({})",
+                parameter.name, parameter.label, parameter.cell
+            );
+        }
+    }
+}
+
+fn run_new(
+    template: &str,
+    params: &[String],
+    dir: Option<&Path>,
+    sidecar_file: Option<&Path>,
+    output: Option<&Path>,
+) {
+    let parameters: Vec<(String, String)> = params
+        .iter()
+        .map(|param| match param.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => {
+                eprintln!("Parameter {} is not NAME=VALUE", param);
+                std::process::exit(2);
+            }
+        })
+        .collect();
+
+    let facade = match instantiate_template(&load_templates(dir), template, &parameters) {
+        Ok(facade) => facade,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let export = facade.export_csv(sidecar_file.is_some());
+    if let (Some(path), Some(sidecar)) = (sidecar_file, &export.sidecar) {
+        write_file(path, &sidecar.to_json());
+    }
+    match output {
+        Some(path) => write_file(path, &export.csv),
+        None => print!("{}", export.csv),
+    }
+}
+
+fn run_check(file: &Path, repair: bool, json: bool) {
+    let facade = load_script(file);
+
+    let report = if repair {
+        match facade.repair_dependencies() {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("Repair failed: {}", e);
+                std::process::exit(2);
+            }
+        }
+    } else {
+        facade.verify_dependencies()
+    };
+    if json {
+        println!("{}", serde_json::to_string(&report).unwrap_or_default());
+    } else {
+        println!("{}", report);
+    }
+    if !repair && !report.is_consistent() {
+        std::process::exit(1);
+    }
+}
+
+fn run_console(json: bool) {
+    let facade = SpreadsheetFacade::new();
+    let mut session = ScriptSession::new();
+    let stdin = io::stdin();
+
+    print!("> ");
+    io::stdout().flush().ok();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        match session.execute(&facade, &line) {
+            Ok(outputs) => {
+                for output in outputs {
+                    if json {
+                        println!("{}", serde_json::to_string(&output).unwrap_or_default());
+                    } else {
+                        println!("{}", output);
+                    }
+                }
+            }
+            Err(e) => eprintln!("error at {}", e),
+        }
+        print!("> ");
+        io::stdout().flush().ok();
+    }
+    println!();
+}
+
 fn run_demo(scenario: &str, max_steps: usize, enable_perf: bool) {
     println!("Starting demo: {}", scenario);
 
     // Create controller and demo controller
-    let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+    let controller = Rc::new(RefCell::new(SpreadsheetController::builder().build()));
     let mut demo_controller = DemoController::new();
 
     // Start the demo
@@ -114,6 +531,9 @@ fn run_demo(scenario: &str, max_steps: usize, enable_perf: bool) {
 
             println!("Demo completed after {} steps", step_count);
 
+            let report = demo_controller.get_report();
+            println!("{}", report.summary());
+
             if enable_perf {
                 let final_metrics = demo_controller.get_performance_metrics();
                 println!("\nFinal Performance Metrics:");
@@ -122,6 +542,10 @@ fn run_demo(scenario: &str, max_steps: usize, enable_perf: bool) {
                 println!("  Memory Usage: {:.2} MB", final_metrics.memory_usage_mb);
                 println!("  Operations/s: {:.1}", final_metrics.operations_per_second);
             }
+
+            if !report.is_success() {
+                std::process::exit(1);
+            }
         }
         Err(e) => {
             eprintln!("Failed to start demo: {}", e);
@@ -137,7 +561,7 @@ fn run_benchmark(quick: bool, format: &str) {
     );
 
     // Create controller and demo controller
-    let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+    let controller = Rc::new(RefCell::new(SpreadsheetController::builder().build()));
     let mut demo_controller = DemoController::new();
 
     if quick {
@@ -156,6 +580,31 @@ fn run_benchmark(quick: bool, format: &str) {
     }
 }
 
+fn run_storm(edits: usize, subscribers: usize) {
+    let storm = EditingStormBenchmark::new()
+        .with_edits(edits)
+        .with_subscribers(subscribers);
+
+    for result in editing_storm::run_headless(&storm) {
+        let metrics = &result.metrics;
+        let custom = &metrics.custom_metrics;
+        println!("\n{}", result.scenario_name);
+        println!("  Avg Latency: {:.2}ms", metrics.input_latency_avg);
+        println!("  P95 Latency: {:.2}ms", metrics.input_latency_p95);
+        println!("  Events/edit: {:.1}", custom["events_per_edit"]);
+        println!(
+            "  Signal updates/edit: {:.1}",
+            custom["signal_updates_per_edit"]
+        );
+        if custom["events_per_edit"] > editing_storm::EVENTS_PER_EDIT_WARNING {
+            println!(
+                "  WARNING: more than {} events per edit",
+                editing_storm::EVENTS_PER_EDIT_WARNING
+            );
+        }
+    }
+}
+
 fn print_benchmark_results(results: &str, format: &str) {
     match format {
         "json" => {
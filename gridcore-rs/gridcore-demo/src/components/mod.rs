@@ -1,9 +1,17 @@
+use crate::benchmark::results::suggestions::{SuggestedAction, Suggestion};
 use crate::demo::performance::Metrics;
+use crate::demo::tutorial::Instruction;
 use leptos::prelude::*;
 
-/// Performance overlay component that displays real-time metrics
+/// Performance overlay component that displays real-time metrics, and the
+/// suggestions of the last benchmark run
 #[component]
-pub fn PerformanceOverlay(metrics: Signal<Metrics>, visible: Signal<bool>) -> impl IntoView {
+pub fn PerformanceOverlay(
+    metrics: Signal<Metrics>,
+    visible: Signal<bool>,
+    suggestions: Signal<Vec<Suggestion>>,
+    on_action: Callback<SuggestedAction>,
+) -> impl IntoView {
     view! {
         <Show
             when=move || visible.get()
@@ -38,6 +46,50 @@ pub fn PerformanceOverlay(metrics: Signal<Metrics>, visible: Signal<bool>) -> im
                     <span class="metric-label">"Ops/s: "</span>
                     <span class="metric-value">{move || format!("{:.1}", metrics.get().operations_per_second)}</span>
                 </div>
+                <SuggestionsDrawer suggestions=suggestions on_action=on_action />
+            </div>
+        </Show>
+    }
+}
+
+/// Collapsible list of performance suggestions, with a button for those the
+/// app can act on
+#[component]
+pub fn SuggestionsDrawer(
+    suggestions: Signal<Vec<Suggestion>>,
+    on_action: Callback<SuggestedAction>,
+) -> impl IntoView {
+    let expanded = RwSignal::new(false);
+    view! {
+        <Show
+            when=move || !suggestions.get().is_empty()
+            fallback=|| ()
+        >
+            <div class="suggestions-drawer">
+                <button
+                    class="suggestions-toggle"
+                    on:click=move |_| expanded.update(|open| *open = !*open)
+                >
+                    {move || {
+                        let arrow = if expanded.get() { "▾" } else { "▸" };
+                        format!("{} Suggestions ({})", arrow, suggestions.get().len())
+                    }}
+                </button>
+                <Show when=move || expanded.get() fallback=|| ()>
+                    <ul class="suggestions-list">
+                        {move || suggestions.get().into_iter().map(|suggestion| {
+                            let action = suggestion.action;
+                            view! {
+                                <li class="suggestion" data-action=action.id()>
+                                    <span class="suggestion-text">{suggestion.to_string()}</span>
+                                    {action.label().map(|label| view! {
+                                        <button on:click=move |_| on_action.run(action)>{label}</button>
+                                    })}
+                                </li>
+                            }
+                        }).collect_view()}
+                    </ul>
+                </Show>
             </div>
         </Show>
     }
@@ -50,6 +102,8 @@ pub fn DemoProgressBar(
     total_steps: Signal<usize>,
     scenario_name: Signal<String>,
     is_running: Signal<bool>,
+    /// Number of steps whose assertions failed; turns the bar red
+    failed_steps: Signal<usize>,
 ) -> impl IntoView {
     let progress_percent = move || {
         let total = total_steps.get();
@@ -65,11 +119,21 @@ pub fn DemoProgressBar(
             when=move || is_running.get()
             fallback=|| ()
         >
-            <div class="demo-progress-bar">
+            <div class=move || {
+                if failed_steps.get() > 0 { "demo-progress-bar failed" } else { "demo-progress-bar" }
+            }>
                 <div class="demo-info">
                     <span class="demo-scenario">{move || scenario_name.get()}</span>
                     <span class="demo-step">
-                        {move || format!("Step {}/{}", current_step.get(), total_steps.get())}
+                        {move || match failed_steps.get() {
+                            0 => format!("Step {}/{}", current_step.get(), total_steps.get()),
+                            failed => format!(
+                                "Step {}/{} ({} failed)",
+                                current_step.get(),
+                                total_steps.get(),
+                                failed
+                            ),
+                        }}
                     </span>
                 </div>
                 <div class="progress-bar-container">
@@ -82,3 +146,34 @@ pub fn DemoProgressBar(
         </Show>
     }
 }
+
+/// Callout showing the current tutorial instruction next to the cell it
+/// points at
+#[component]
+pub fn TutorialCallout(
+    instruction: Signal<Option<Instruction>>,
+    /// Page coordinates of the callout's top-left corner
+    position: Signal<(f64, f64)>,
+    on_skip: Callback<()>,
+    on_restart: Callback<()>,
+) -> impl IntoView {
+    view! {
+        {move || instruction.get().map(|instruction| {
+            let (left, top) = position.get();
+            view! {
+                <div
+                    class="tutorial-callout"
+                    style:left=format!("{}px", left)
+                    style:top=format!("{}px", top)
+                >
+                    <div class="tutorial-title">{instruction.title}</div>
+                    <div class="tutorial-body">{instruction.body}</div>
+                    <div class="tutorial-actions">
+                        <button on:click=move |_| on_restart.run(())>"Restart step"</button>
+                        <button on:click=move |_| on_skip.run(())>"Skip"</button>
+                    </div>
+                </div>
+            }
+        })}
+    }
+}
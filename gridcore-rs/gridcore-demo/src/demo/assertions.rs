@@ -0,0 +1,197 @@
+use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::state::{format_content_hash, SelectionType};
+use gridcore_core::types::{CellAddress, CellValue};
+use serde::{Deserialize, Serialize};
+
+/// A check a scenario runs against the controller after one of its steps,
+/// e.g. `{"assert": "cell_equals", "address": "D2", "expected": "274"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "assert", rename_all = "snake_case")]
+pub enum Assertion {
+    /// The displayed value of a cell, errors as their code (e.g. `#DIV/0!`)
+    CellEquals { address: String, expected: String },
+    /// At most `max` cells of the active sheet hold an error value
+    ErrorCount { max: usize },
+    /// The selection, or the cursor when nothing is selected, e.g. `B2:C4`
+    SelectionIs { range: String },
+    /// The active sheet's content hash, as a bug report writes it
+    ContentHash { expected: String },
+}
+
+impl Assertion {
+    pub fn cell_equals(address: &str, expected: &str) -> Self {
+        Assertion::CellEquals {
+            address: address.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    pub fn error_count(max: usize) -> Self {
+        Assertion::ErrorCount { max }
+    }
+
+    pub fn selection_is(range: &str) -> Self {
+        Assertion::SelectionIs {
+            range: range.to_string(),
+        }
+    }
+
+    pub fn content_hash(expected: &str) -> Self {
+        Assertion::ContentHash {
+            expected: expected.to_string(),
+        }
+    }
+
+    /// Check the assertion, describing the mismatch when it fails
+    pub fn check(&self, controller: &SpreadsheetController) -> Result<(), String> {
+        match self {
+            Assertion::CellEquals { address, expected } => {
+                let cell = CellAddress::from_a1(address).map_err(|e| e.to_string())?;
+                let actual = controller
+                    .facade()
+                    .get_cell_raw_value(&cell)
+                    .map(|value| value.to_display_string())
+                    .unwrap_or_default();
+                if actual == *expected {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "{} is '{}', expected '{}'",
+                        address, actual, expected
+                    ))
+                }
+            }
+            Assertion::ErrorCount { max } => {
+                let errors = controller
+                    .facade()
+                    .get_all_cells()
+                    .into_iter()
+                    .filter(|(_, cell)| matches!(cell.get_display_value(), CellValue::Error(_)))
+                    .count();
+                if errors <= *max {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "{} cells hold errors, expected at most {}",
+                        errors, max
+                    ))
+                }
+            }
+            Assertion::SelectionIs { range } => {
+                let expected = normalize_range(range)?;
+                let actual = describe_selection(controller);
+                if actual == expected {
+                    Ok(())
+                } else {
+                    Err(format!("selection is {}, expected {}", actual, expected))
+                }
+            }
+            Assertion::ContentHash { expected } => {
+                let actual = format_content_hash(controller.facade().content_hash());
+                if actual == *expected {
+                    Ok(())
+                } else {
+                    Err(format!("content hash is {}, expected {}", actual, expected))
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of one assertion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssertionResult {
+    /// Zero-based index of the step the assertion ran after
+    pub step: usize,
+    pub assertion: Assertion,
+    /// Why the assertion failed, `None` when it passed
+    pub failure: Option<String>,
+}
+
+impl AssertionResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// What happened during a scenario run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioReport {
+    pub scenario: String,
+    pub steps_run: usize,
+    pub results: Vec<AssertionResult>,
+    /// Set when the scenario stopped early, either from a step error or
+    /// because an assertion failed with abort-on-failure enabled
+    pub error: Option<String>,
+    /// Guided steps the user skipped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_steps: Vec<usize>,
+    /// Guided steps that moved on after their timeout
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub timed_out_steps: Vec<usize>,
+}
+
+impl ScenarioReport {
+    pub fn failures(&self) -> impl Iterator<Item = &AssertionResult> {
+        self.results.iter().filter(|result| !result.passed())
+    }
+
+    /// Steps with at least one failed assertion
+    pub fn failed_steps(&self) -> Vec<usize> {
+        let mut steps: Vec<usize> = self.failures().map(|result| result.step).collect();
+        steps.dedup();
+        steps
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && self.failures().next().is_none()
+    }
+
+    pub fn summary(&self) -> String {
+        let passed = self.results.iter().filter(|result| result.passed()).count();
+        let mut summary = format!(
+            "{}: {} steps, {}/{} assertions passed",
+            self.scenario,
+            self.steps_run,
+            passed,
+            self.results.len()
+        );
+        for failure in self.failures() {
+            summary.push_str(&format!(
+                "\n  step {}: {}",
+                failure.step + 1,
+                failure.failure.as_deref().unwrap_or_default()
+            ));
+        }
+        if let Some(error) = &self.error {
+            summary.push_str(&format!("\n  stopped: {}", error));
+        }
+        summary
+    }
+}
+
+fn normalize_range(range: &str) -> Result<String, String> {
+    let (start, end) = range.split_once(':').unwrap_or((range, range));
+    let start = CellAddress::from_a1(start.trim()).map_err(|e| e.to_string())?;
+    let end = CellAddress::from_a1(end.trim()).map_err(|e| e.to_string())?;
+    Ok(format_range(start, end))
+}
+
+fn format_range(start: CellAddress, end: CellAddress) -> String {
+    let top_left = CellAddress::new(start.col.min(end.col), start.row.min(end.row));
+    let bottom_right = CellAddress::new(start.col.max(end.col), start.row.max(end.row));
+    if top_left == bottom_right {
+        top_left.to_a1()
+    } else {
+        format!("{}:{}", top_left.to_a1(), bottom_right.to_a1())
+    }
+}
+
+fn describe_selection(controller: &SpreadsheetController) -> String {
+    match controller.get_selection().map(|s| &s.selection_type) {
+        None => controller.cursor().to_a1(),
+        Some(SelectionType::Cell { address }) => address.to_a1(),
+        Some(SelectionType::Range { start, end }) => format_range(*start, *end),
+        Some(other) => format!("{:?}", other),
+    }
+}
@@ -60,10 +60,10 @@ impl DataGenerator {
         // Revenue items
         let items = vec![
             ("Revenue", 100000.0, 150000.0),
-            ("Cost of Goods Sold", -40000.0, -60000.0),
-            ("Operating Expenses", -20000.0, -30000.0),
-            ("Marketing", -5000.0, -10000.0),
-            ("R&D", -10000.0, -20000.0),
+            ("Cost of Goods Sold", -60000.0, -40000.0),
+            ("Operating Expenses", -30000.0, -20000.0),
+            ("Marketing", -10000.0, -5000.0),
+            ("R&D", -20000.0, -10000.0),
         ];
 
         let mut row = 2;
@@ -185,6 +185,30 @@ impl DataGenerator {
         data
     }
 
+    /// Generate a dense `rows` x `cols` sheet where roughly `formula_density`
+    /// of the cells are formulas over the cells left of and above them
+    pub fn generate_mixed_sheet(
+        &mut self,
+        rows: u32,
+        cols: u32,
+        formula_density: f64,
+    ) -> Vec<(CellAddress, String)> {
+        let mut data = Vec::with_capacity((rows * cols) as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                let value = if col > 0 && row > 0 && self.rng.random_bool(formula_density) {
+                    let left = CellAddress::new(col - 1, row).to_a1();
+                    let above = CellAddress::new(col, row - 1).to_a1();
+                    format!("={}+{}", left, above)
+                } else {
+                    format!("{:.2}", self.rng.random_range(0.0..1000.0))
+                };
+                data.push((CellAddress::new(col, row), value));
+            }
+        }
+        data
+    }
+
     /// Generate sparse data across a large grid
     pub fn generate_sparse_data(
         &mut self,
@@ -248,25 +272,26 @@ impl DataGenerator {
                         )
                     }
                     3 => {
-                        // Sum of surrounding cells (if not on edge)
+                        // Sum of the cells above (if not on edge); a block
+                        // around the cell would include the cell itself
                         if row > 0 && col > 0 && row < size - 1 && col < size - 1 {
                             format!(
                                 "=SUM({}{}:{}{})",
                                 CellAddress::column_number_to_label(col - 1),
                                 row,
                                 CellAddress::column_number_to_label(col + 1),
-                                row + 2
+                                row
                             )
                         } else {
                             "0".to_string()
                         }
                     }
                     _ => {
-                        // Average of row
+                        // Average of the row up to the cell
                         format!(
                             "=AVERAGE(A{}:{}{})",
                             row + 1,
-                            CellAddress::column_number_to_label(col),
+                            CellAddress::column_number_to_label(col - 1),
                             row + 1
                         )
                     }
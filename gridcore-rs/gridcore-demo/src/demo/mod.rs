@@ -1,10 +1,15 @@
+pub mod assertions;
 pub mod data_generator;
 pub mod performance;
 pub mod runner;
 pub mod scenarios;
+pub mod scripted;
+pub mod tutorial;
+pub mod typing;
 
 use crate::benchmark::{
     config::BenchmarkPresets,
+    results::suggestions::Suggestion,
     runner::{BenchmarkReport, UIBenchmarkRunner},
     scenarios::{
         canvas::{CanvasStateBenchmark, DrawCallBenchmark},
@@ -24,6 +29,7 @@ use wasm_bindgen_futures::spawn_local;
 #[derive(Debug, Clone, PartialEq)]
 pub enum DemoMode {
     Off,
+    /// A guided tutorial: each step waits for the user to do it
     Manual,
     Automated,
 }
@@ -34,6 +40,8 @@ pub struct DemoConfig {
     pub playback_speed: f32,
     pub show_performance: bool,
     pub auto_repeat: bool,
+    /// How automated scenarios play their steps back
+    pub playback: typing::Playback,
 }
 
 impl Default for DemoConfig {
@@ -43,6 +51,7 @@ impl Default for DemoConfig {
             playback_speed: 1.0,
             show_performance: true,
             auto_repeat: false,
+            playback: typing::Playback::Instant,
         }
     }
 }
@@ -51,6 +60,8 @@ pub struct DemoController {
     config: DemoConfig,
     runner: runner::DemoRunner,
     performance_monitor: performance::PerformanceMonitor,
+    /// Suggestions of the last quick benchmark
+    suggestions: Vec<Suggestion>,
 }
 
 impl Default for DemoController {
@@ -65,6 +76,7 @@ impl DemoController {
             config: DemoConfig::default(),
             runner: runner::DemoRunner::new(),
             performance_monitor: performance::PerformanceMonitor::new(),
+            suggestions: Vec::new(),
         }
     }
 
@@ -89,6 +101,36 @@ impl DemoController {
         Ok(())
     }
 
+    /// Start a built-in tutorial in [`DemoMode::Manual`]
+    pub fn start_tutorial(
+        &mut self,
+        tutorial_name: &str,
+        controller: Rc<RefCell<SpreadsheetController>>,
+    ) -> Result<(), String> {
+        self.config.mode = DemoMode::Manual;
+        self.runner.load_tutorial(tutorial_name)?;
+        self.runner.start(controller)
+    }
+
+    /// Move past the current tutorial step without doing it
+    pub fn skip_step(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        self.runner.skip_step(controller);
+    }
+
+    /// Start the current tutorial step over
+    pub fn restart_step(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        self.runner.restart_step(controller);
+    }
+
+    /// What the current tutorial step asks of the user
+    pub fn current_instruction(&self) -> Option<tutorial::Instruction> {
+        self.runner.current_instruction().cloned()
+    }
+
+    pub fn get_available_tutorials(&self) -> Vec<String> {
+        tutorial::get_available_tutorials()
+    }
+
     pub fn stop_demo(&mut self) {
         self.config.mode = DemoMode::Off;
         self.runner.stop();
@@ -103,9 +145,20 @@ impl DemoController {
         self.runner.resume();
     }
 
+    /// Advance one whole step, however it is played back
     pub fn step_forward(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
         self.runner.step(controller.clone());
+        self.record_progress(&controller);
+    }
+
+    /// Play back whatever is due by now, for a timer driving an
+    /// automated scenario
+    pub fn tick(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        self.runner.tick(controller.clone());
+        self.record_progress(&controller);
+    }
 
+    fn record_progress(&mut self, controller: &Rc<RefCell<SpreadsheetController>>) {
         // Record performance metrics
         self.performance_monitor.record_operation();
 
@@ -119,6 +172,12 @@ impl DemoController {
             .update_cell_counts(cell_count, formula_count);
     }
 
+    /// Play automated scenarios back instantly or as keystrokes
+    pub fn set_playback(&mut self, playback: typing::Playback) {
+        self.config.playback = playback.clone();
+        self.runner.set_playback(playback);
+    }
+
     pub fn set_playback_speed(&mut self, speed: f32) {
         self.config.playback_speed = speed.clamp(0.1, 10.0);
         self.runner.set_speed(self.config.playback_speed);
@@ -160,6 +219,15 @@ impl DemoController {
         self.runner.get_total_steps()
     }
 
+    /// Steps of the running scenario whose assertions failed
+    pub fn get_failed_steps(&self) -> Vec<usize> {
+        self.runner.failed_steps()
+    }
+
+    pub fn get_report(&self) -> assertions::ScenarioReport {
+        self.runner.report()
+    }
+
     /// Run a quick benchmark and return results
     pub fn run_quick_benchmark(
         &mut self,
@@ -198,10 +266,19 @@ impl DemoController {
         for warning in &report.warnings {
             crate::log_warn!("⚠️ {}", warning);
         }
+        for suggestion in &report.suggestions {
+            crate::log_info!("💡 {}", suggestion);
+        }
+        self.suggestions = report.suggestions;
 
         Ok(summary)
     }
 
+    /// What the last quick benchmark suggests doing
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
     /// Run full benchmark suite
     #[cfg(feature = "web")]
     pub fn run_full_benchmark(
@@ -279,6 +356,8 @@ impl DemoController {
             // Interaction benchmarks
             "Cell Edit Latency".to_string(),
             "Selection Performance".to_string(),
+            "Editing Storm".to_string(),
+            "Editing Storm (coalesced)".to_string(),
             // Formula benchmarks
             "Simple Formula".to_string(),
             "Complex Formula".to_string(),
@@ -1,7 +1,12 @@
+use super::assertions::{AssertionResult, ScenarioReport};
 use super::scenarios::{self, DemoScenario, StepResult};
+use super::tutorial::{self, Instruction};
+use super::typing::{Playback, SharedTypist, Typist};
 use gridcore_controller::controller::SpreadsheetController;
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum RunnerState {
@@ -18,6 +23,30 @@ pub struct DemoRunner {
     playback_speed: f32,
     step_delay_ms: u32,
     auto_repeat: bool,
+    abort_on_failure: bool,
+    steps_run: usize,
+    results: Vec<AssertionResult>,
+    /// Controller events seen, so a guided step is only checked again
+    /// after something happened
+    events: Arc<AtomicUsize>,
+    /// Controller the event count listens to
+    listening_to: Option<Weak<RefCell<SpreadsheetController>>>,
+    /// Event count when the current guided step was last found not done
+    checked_at: Option<usize>,
+    /// When the current guided step started waiting, in milliseconds
+    waiting_since: Option<f64>,
+    skip_requested: bool,
+    skipped: Vec<usize>,
+    timed_out: Vec<usize>,
+    /// Milliseconds timeouts are measured in
+    clock: Box<dyn Fn() -> f64>,
+    typist: SharedTypist,
+    /// Step whose keystrokes are still being played back
+    typing: Option<usize>,
+    /// When [`tick`](Self::tick) last played a keystroke or finished a
+    /// step, in milliseconds
+    played_at: f64,
+    keystrokes: usize,
 }
 
 impl Default for DemoRunner {
@@ -34,20 +63,59 @@ impl DemoRunner {
             playback_speed: 1.0,
             step_delay_ms: 500, // Default 500ms between steps
             auto_repeat: false,
+            abort_on_failure: false,
+            steps_run: 0,
+            results: Vec::new(),
+            events: Arc::new(AtomicUsize::new(0)),
+            listening_to: None,
+            checked_at: None,
+            waiting_since: None,
+            skip_requested: false,
+            skipped: Vec::new(),
+            timed_out: Vec::new(),
+            clock: Box::new(now_ms),
+            typist: Typist::shared(Playback::Instant),
+            typing: None,
+            played_at: 0.0,
+            keystrokes: 0,
         }
     }
 
+    /// Measure step timeouts with `clock`, in milliseconds, instead of
+    /// the system time
+    pub fn set_clock(&mut self, clock: impl Fn() -> f64 + 'static) {
+        self.clock = Box::new(clock);
+    }
+
     pub fn load_scenario(&mut self, name: &str) -> Result<(), String> {
         match scenarios::create_scenario(name) {
             Ok(scenario) => {
-                self.current_scenario = Some(scenario);
-                self.state = RunnerState::Idle;
+                self.load(scenario);
                 Ok(())
             }
             Err(e) => Err(e),
         }
     }
 
+    /// Load a built-in tutorial, see [`tutorial`]
+    pub fn load_tutorial(&mut self, name: &str) -> Result<(), String> {
+        self.load(tutorial::create_tutorial(name)?);
+        Ok(())
+    }
+
+    /// Load a scenario that is not one of the built-in ones
+    pub fn load(&mut self, mut scenario: Box<dyn DemoScenario>) {
+        scenario.set_typist(self.typist.clone());
+        self.current_scenario = Some(scenario);
+        self.state = RunnerState::Idle;
+    }
+
+    /// Play steps back instantly or as keystrokes, see [`super::typing`]
+    pub fn set_playback(&mut self, playback: Playback) {
+        self.typist.borrow_mut().set_playback(playback);
+        self.typing = None;
+    }
+
     pub fn start(&mut self, controller: Rc<RefCell<SpreadsheetController>>) -> Result<(), String> {
         if self.current_scenario.is_none() {
             return Err("No scenario loaded".to_string());
@@ -57,6 +125,17 @@ impl DemoRunner {
         if let Some(scenario) = &mut self.current_scenario {
             scenario.setup(controller.clone());
         }
+        self.steps_run = 0;
+        self.results.clear();
+        self.skipped.clear();
+        self.timed_out.clear();
+        self.typist.borrow_mut().reset();
+        self.typing = None;
+        self.played_at = (self.clock)();
+        self.keystrokes = 0;
+        self.listen(&controller);
+        self.reset_wait();
+        self.show_instruction(&controller);
 
         self.state = RunnerState::Running;
         self.run_loop(controller);
@@ -64,8 +143,15 @@ impl DemoRunner {
     }
 
     pub fn stop(&mut self) {
+        if let Some(controller) = self.listening_to.as_ref().and_then(Weak::upgrade) {
+            if let Ok(mut ctrl) = controller.try_borrow_mut() {
+                ctrl.set_highlighted_cell(None);
+            }
+        }
         self.state = RunnerState::Idle;
         self.current_scenario = None;
+        self.typist.borrow_mut().reset();
+        self.typing = None;
     }
 
     pub fn pause(&mut self) {
@@ -80,35 +166,238 @@ impl DemoRunner {
         }
     }
 
+    /// Run the next step, keystrokes and all. A guided step waits until
+    /// the user has done what it asks, it times out or it is skipped. A
+    /// step [`tick`](Self::tick) is still typing is finished instead.
     pub fn step(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        if self.typing.is_none() {
+            self.begin_step(&controller);
+        }
+        self.finish_step(&controller);
+    }
+
+    /// Play back what is due by now: the keystrokes of the current step
+    /// whose delays have passed, or the next step once the step delay
+    /// has. For driving the runner from a timer.
+    pub fn tick(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        let now_ms = (self.clock)();
+        if self.typing.is_none() {
+            let step_delay = self.step_delay_ms as f64 / self.playback_speed as f64;
+            if now_ms - self.played_at < step_delay {
+                return;
+            }
+            self.begin_step(&controller);
+            self.played_at = now_ms;
+            if self.typing.is_none() {
+                return;
+            }
+        }
+        loop {
+            let Some(delay) = self.typist.borrow().next_delay() else {
+                break;
+            };
+            if self.played_at + delay as f64 > now_ms {
+                return;
+            }
+            self.played_at += delay as f64;
+            self.play_keystroke(&controller);
+        }
+        self.finish_step(&controller);
+        self.played_at = now_ms;
+    }
+
+    /// Run the scenario's next step, leaving its keystrokes queued
+    fn begin_step(&mut self, controller: &Rc<RefCell<SpreadsheetController>>) {
+        if !self.gate_open(controller) {
+            return;
+        }
         if let Some(scenario) = &mut self.current_scenario {
+            let index = scenario.current_step();
             match scenario.run_step(controller.clone()) {
                 StepResult::Continue => {
-                    leptos::logging::log!(
+                    crate::log_info!(
                         "Demo step {}/{}",
                         scenario.current_step(),
                         scenario.total_steps()
                     );
+                    self.steps_run += 1;
+                    self.typing = Some(index);
                 }
                 StepResult::Complete => {
-                    leptos::logging::log!("Demo scenario complete");
+                    crate::log_info!("Demo scenario complete");
                     self.state = RunnerState::Complete;
                     scenario.cleanup(controller.clone());
 
+                    let passed = self.results.iter().filter(|r| r.passed()).count();
+                    crate::log_info!("{}/{} assertions passed", passed, self.results.len());
+
                     if self.auto_repeat {
                         // Restart the scenario
                         scenario.setup(controller.clone());
                         self.state = RunnerState::Running;
+                        self.steps_run = 0;
+                        self.results.clear();
                     }
                 }
                 StepResult::Error(e) => {
-                    leptos::logging::log!("Demo error: {}", e);
+                    crate::log_error!("Demo error: {}", e);
                     self.state = RunnerState::Error(e);
                 }
             }
         }
     }
 
+    fn play_keystroke(&mut self, controller: &Rc<RefCell<SpreadsheetController>>) {
+        let keystroke = self.typist.borrow_mut().pop();
+        if let Some(keystroke) = keystroke {
+            self.keystrokes += 1;
+            if let Err(e) = controller.borrow_mut().dispatch_action(keystroke.action) {
+                crate::log_warn!("Demo keystroke failed: {}", e);
+            }
+        }
+    }
+
+    /// Play the rest of the current step's keystrokes and check its
+    /// assertions
+    fn finish_step(&mut self, controller: &Rc<RefCell<SpreadsheetController>>) {
+        let Some(index) = self.typing.take() else {
+            return;
+        };
+        while !self.typist.borrow().is_idle() {
+            self.play_keystroke(controller);
+        }
+        let Some(scenario) = &self.current_scenario else {
+            return;
+        };
+
+        let ctrl = controller.borrow();
+        let results: Vec<AssertionResult> = scenario
+            .assertions(index)
+            .into_iter()
+            .map(|assertion| AssertionResult {
+                step: index,
+                failure: assertion.check(&ctrl).err(),
+                assertion,
+            })
+            .collect();
+        for failure in results.iter().filter_map(|r| r.failure.as_ref()) {
+            crate::log_warn!("Demo step {} failed: {}", index + 1, failure);
+        }
+        let failed = results.iter().any(|result| !result.passed());
+        self.results.extend(results);
+        drop(ctrl);
+        self.reset_wait();
+        self.show_instruction(controller);
+
+        if failed && self.abort_on_failure {
+            self.state = RunnerState::Error(format!("Assertions failed at step {}", index + 1));
+        }
+    }
+
+    /// Whether the current step may run: it is not guided, or the user
+    /// did what it asks, or it was skipped or timed out
+    fn gate_open(&mut self, controller: &Rc<RefCell<SpreadsheetController>>) -> bool {
+        let Some(scenario) = &self.current_scenario else {
+            return true;
+        };
+        let index = scenario.current_step();
+        let Some(step) = scenario.tutorial_step(index) else {
+            return true;
+        };
+        if std::mem::take(&mut self.skip_requested) {
+            self.skipped.push(index);
+            return true;
+        }
+
+        let now_ms = (self.clock)();
+        let waiting_since = *self.waiting_since.get_or_insert(now_ms);
+        let events = self.events.load(Ordering::Relaxed);
+        if self.checked_at != Some(events) {
+            if step.wait_for.is_met(&controller.borrow()) {
+                return true;
+            }
+            self.checked_at = Some(events);
+        }
+        if step
+            .timeout_ms
+            .is_some_and(|timeout| now_ms - waiting_since >= timeout)
+        {
+            crate::log_info!("Tutorial step {} timed out", index + 1);
+            self.timed_out.push(index);
+            return true;
+        }
+        false
+    }
+
+    /// Count the controller's events, once per controller
+    fn listen(&mut self, controller: &Rc<RefCell<SpreadsheetController>>) {
+        let listening = self
+            .listening_to
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .is_some_and(|current| Rc::ptr_eq(&current, controller));
+        if !listening {
+            let events = self.events.clone();
+            controller.borrow_mut().subscribe_to_events(move |_| {
+                events.fetch_add(1, Ordering::Relaxed);
+            });
+            self.listening_to = Some(Rc::downgrade(controller));
+        }
+    }
+
+    fn reset_wait(&mut self) {
+        self.checked_at = None;
+        self.waiting_since = None;
+    }
+
+    /// Outline the cell the current instruction is about
+    fn show_instruction(&self, controller: &Rc<RefCell<SpreadsheetController>>) {
+        if self.current_scenario.is_none() {
+            return;
+        }
+        let target = self
+            .current_instruction()
+            .and_then(Instruction::target_address);
+        controller.borrow_mut().set_highlighted_cell(target);
+    }
+
+    /// Move past the current guided step without waiting for the user
+    pub fn skip_step(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        if self.is_waiting() {
+            self.skip_requested = true;
+            self.step(controller);
+        }
+    }
+
+    /// Put the sheet back as it was when the current step started and
+    /// wait for the user again, restarting the timeout
+    pub fn restart_step(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        if let Some(scenario) = &mut self.current_scenario {
+            let index = scenario.current_step();
+            scenario.restart_step(index, controller.clone());
+        }
+        self.reset_wait();
+        self.show_instruction(&controller);
+    }
+
+    /// What the current guided step asks of the user
+    pub fn current_instruction(&self) -> Option<&Instruction> {
+        let scenario = self.current_scenario.as_ref()?;
+        scenario
+            .tutorial_step(scenario.current_step())
+            .map(|step| &step.instruction)
+    }
+
+    /// Keystrokes played back since the scenario started
+    pub fn keystrokes_played(&self) -> usize {
+        self.keystrokes
+    }
+
+    /// Whether the runner is held by a guided step
+    pub fn is_waiting(&self) -> bool {
+        self.is_running() && self.current_instruction().is_some()
+    }
+
     fn run_loop(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
         if self.state != RunnerState::Running {
             return;
@@ -128,12 +417,19 @@ impl DemoRunner {
 
     pub fn set_speed(&mut self, speed: f32) {
         self.playback_speed = speed.clamp(0.1, 10.0);
+        self.typist.borrow_mut().set_speed(self.playback_speed);
     }
 
     pub fn set_auto_repeat(&mut self, repeat: bool) {
         self.auto_repeat = repeat;
     }
 
+    /// Stop the scenario at the first step with a failed assertion instead
+    /// of recording the failure and carrying on
+    pub fn set_abort_on_failure(&mut self, abort: bool) {
+        self.abort_on_failure = abort;
+    }
+
     pub fn is_running(&self) -> bool {
         self.state == RunnerState::Running
     }
@@ -177,4 +473,56 @@ impl DemoRunner {
             .map(|s| s.total_steps())
             .unwrap_or(0)
     }
+
+    /// Steps with at least one failed assertion so far
+    pub fn failed_steps(&self) -> Vec<usize> {
+        self.report().failed_steps()
+    }
+
+    /// Assertion results of the current run
+    pub fn report(&self) -> ScenarioReport {
+        ScenarioReport {
+            scenario: self.get_current_scenario().unwrap_or_default(),
+            steps_run: self.steps_run,
+            results: self.results.clone(),
+            error: match &self.state {
+                RunnerState::Error(e) => Some(e.clone()),
+                _ => None,
+            },
+            skipped_steps: self.skipped.clone(),
+            timed_out_steps: self.timed_out.clone(),
+        }
+    }
+}
+
+/// Run a built-in scenario to completion against a fresh controller, without
+/// a browser, and return its assertion results
+pub fn run_scenario_headless(name: &str) -> Result<ScenarioReport, String> {
+    run_headless(scenarios::create_scenario(name)?)
+}
+
+/// [`run_scenario_headless`] for a scenario that is not one of the
+/// built-in ones, such as a [`ScriptedScenario`](super::scripted::ScriptedScenario)
+pub fn run_headless(scenario: Box<dyn DemoScenario>) -> Result<ScenarioReport, String> {
+    let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+    let mut runner = DemoRunner::new();
+    runner.load(scenario);
+    runner.start(controller.clone())?;
+    while runner.is_running() {
+        runner.step(controller.clone());
+    }
+    Ok(runner.report())
+}
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+fn now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
 }
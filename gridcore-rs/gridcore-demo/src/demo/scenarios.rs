@@ -1,4 +1,7 @@
+use super::assertions::Assertion;
 use super::data_generator::DataGenerator;
+use super::tutorial::TutorialStep;
+use super::typing::{Playback, SharedTypist, Typist};
 use gridcore_controller::controller::SpreadsheetController;
 use gridcore_controller::state::Action;
 use gridcore_core::types::CellAddress;
@@ -13,6 +16,26 @@ pub trait DemoScenario {
     fn cleanup(&mut self, controller: Rc<RefCell<SpreadsheetController>>);
     fn total_steps(&self) -> usize;
     fn current_step(&self) -> usize;
+
+    /// Checks to run once step `step` (zero-based) has completed
+    fn assertions(&self, _step: usize) -> Vec<Assertion> {
+        Vec::new()
+    }
+
+    /// What the user is asked to do before step `step` runs, for guided
+    /// scenarios. The runner holds the step until it is done.
+    fn tutorial_step(&self, _step: usize) -> Option<&TutorialStep> {
+        None
+    }
+
+    /// Put the sheet back as it was when step `step` started, so the user
+    /// can try it again
+    fn restart_step(&mut self, _step: usize, _controller: Rc<RefCell<SpreadsheetController>>) {}
+
+    /// Enter cells and move the cursor through `typist`, so the runner
+    /// can play steps back as keystrokes. Scenarios that jump around or
+    /// load data in bulk ignore it.
+    fn set_typist(&mut self, _typist: SharedTypist) {}
 }
 
 #[derive(Debug, Clone)]
@@ -54,6 +77,7 @@ pub struct BasicOperationsScenario {
     step: usize,
     total_steps: usize,
     _data_generator: DataGenerator,
+    typist: SharedTypist,
 }
 
 impl Default for BasicOperationsScenario {
@@ -68,6 +92,7 @@ impl BasicOperationsScenario {
             step: 0,
             total_steps: 20,
             _data_generator: DataGenerator::new(),
+            typist: Typist::shared(Playback::Instant),
         }
     }
 }
@@ -81,6 +106,10 @@ impl DemoScenario for BasicOperationsScenario {
         "Demonstrates basic spreadsheet operations: navigation, editing, selection"
     }
 
+    fn set_typist(&mut self, typist: SharedTypist) {
+        self.typist = typist;
+    }
+
     fn setup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
         let ctrl = controller.borrow_mut();
         let facade = ctrl.facade();
@@ -131,7 +160,7 @@ impl DemoScenario for BasicOperationsScenario {
                         (current.col as i32 + dx).max(0) as u32,
                         (current.row as i32 + dy).max(0) as u32,
                     );
-                    let _ = ctrl.dispatch_action(Action::UpdateCursor { cursor: new_cursor });
+                    self.typist.borrow_mut().move_to(&mut ctrl, new_cursor);
                 }
             }
             5..=9 => {
@@ -147,8 +176,9 @@ impl DemoScenario for BasicOperationsScenario {
                 let idx = self.step - 5;
                 if idx < edits.len() {
                     let (addr, value) = edits[idx];
-                    let _ = ctrl.dispatch_action(Action::UpdateCursor { cursor: addr });
-                    let _ = ctrl.facade().set_cell_value(&addr, value);
+                    let mut typist = self.typist.borrow_mut();
+                    typist.move_to(&mut ctrl, addr);
+                    typist.enter_cell(&mut ctrl, addr, value);
                 }
             }
             _ => {
@@ -171,6 +201,20 @@ impl DemoScenario for BasicOperationsScenario {
     fn current_step(&self) -> usize {
         self.step
     }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        match step {
+            // The cursor walked right, down, left, up and diagonally from A1
+            4 => vec![Assertion::selection_is("B2")],
+            9 => vec![
+                Assertion::selection_is("D2"),
+                Assertion::cell_equals("A4", "Charlie"),
+                Assertion::cell_equals("D2", "274"),
+                Assertion::error_count(0),
+            ],
+            _ => Vec::new(),
+        }
+    }
 }
 
 // Formula Engine Scenario
@@ -178,6 +222,7 @@ pub struct FormulaEngineScenario {
     step: usize,
     total_steps: usize,
     _data_generator: DataGenerator,
+    typist: SharedTypist,
 }
 
 impl Default for FormulaEngineScenario {
@@ -192,6 +237,7 @@ impl FormulaEngineScenario {
             step: 0,
             total_steps: 30,
             _data_generator: DataGenerator::new(),
+            typist: Typist::shared(Playback::Instant),
         }
     }
 }
@@ -205,6 +251,10 @@ impl DemoScenario for FormulaEngineScenario {
         "Showcases formula capabilities: functions, references, error handling"
     }
 
+    fn set_typist(&mut self, typist: SharedTypist) {
+        self.typist = typist;
+    }
+
     fn setup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
         let ctrl = controller.borrow_mut();
         let facade = ctrl.facade();
@@ -237,8 +287,7 @@ impl DemoScenario for FormulaEngineScenario {
             return StepResult::Complete;
         }
 
-        let ctrl = controller.borrow_mut();
-        let facade = ctrl.facade();
+        let mut ctrl = controller.borrow_mut();
 
         // Add more complex formulas progressively
         match self.step {
@@ -254,7 +303,9 @@ impl DemoScenario for FormulaEngineScenario {
 
                 if self.step < formulas.len() {
                     let (addr, formula) = formulas[self.step];
-                    let _ = facade.set_cell_value(&addr, formula);
+                    self.typist
+                        .borrow_mut()
+                        .enter_cell(&mut ctrl, addr, formula);
                 }
             }
             6..=10 => {
@@ -269,7 +320,9 @@ impl DemoScenario for FormulaEngineScenario {
                 let idx = self.step - 6;
                 if idx < formulas.len() {
                     let (addr, formula) = formulas[idx];
-                    let _ = facade.set_cell_value(&addr, formula);
+                    self.typist
+                        .borrow_mut()
+                        .enter_cell(&mut ctrl, addr, formula);
                 }
             }
             _ => {
@@ -292,6 +345,29 @@ impl DemoScenario for FormulaEngineScenario {
     fn current_step(&self) -> usize {
         self.step
     }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        match step {
+            0 => vec![
+                Assertion::cell_equals("C2", "30"),
+                Assertion::cell_equals("C3", "150"),
+                Assertion::cell_equals("C4", "30"),
+                Assertion::cell_equals("E2", "20"),
+            ],
+            4 => vec![
+                Assertion::cell_equals("E4", "900"),
+                Assertion::cell_equals("E6", "50"),
+            ],
+            9 => vec![
+                Assertion::cell_equals("G2", "Low"),
+                Assertion::cell_equals("G3", "10"),
+                Assertion::cell_equals("G4", "5"),
+                Assertion::cell_equals("G5", "5"),
+                Assertion::error_count(0),
+            ],
+            _ => Vec::new(),
+        }
+    }
 }
 
 // Large Dataset Scenario
@@ -334,10 +410,10 @@ impl DemoScenario for LargeDatasetScenario {
             let facade = ctrl.facade();
 
             // Generate and load large dataset
-            leptos::logging::log!("Generating large dataset...");
+            crate::log_info!("Generating large dataset...");
             let data = self.data_generator.generate_large_dataset(10000); // Start with 10K for demo
 
-            leptos::logging::log!("Loading {} cells...", data.len());
+            crate::log_info!("Loading {} cells...", data.len());
             for (addr, value) in data.iter().take(1000) {
                 // Load first 1000 for initial demo
                 let _ = facade.set_cell_value(addr, value);
@@ -393,6 +469,14 @@ impl DemoScenario for LargeDatasetScenario {
     fn current_step(&self) -> usize {
         self.step
     }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        match step {
+            1 => vec![Assertion::selection_is("K101")],
+            4 => vec![Assertion::selection_is("A1")],
+            _ => Vec::new(),
+        }
+    }
 }
 
 // Financial Dashboard Scenario
@@ -400,6 +484,7 @@ pub struct FinancialDashboardScenario {
     step: usize,
     total_steps: usize,
     data_generator: DataGenerator,
+    typist: SharedTypist,
 }
 
 impl Default for FinancialDashboardScenario {
@@ -414,6 +499,7 @@ impl FinancialDashboardScenario {
             step: 0,
             total_steps: 15,
             data_generator: DataGenerator::new(),
+            typist: Typist::shared(Playback::Instant),
         }
     }
 }
@@ -427,6 +513,10 @@ impl DemoScenario for FinancialDashboardScenario {
         "Creates a financial dashboard with income statements and calculations"
     }
 
+    fn set_typist(&mut self, typist: SharedTypist) {
+        self.typist = typist;
+    }
+
     fn setup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
         let ctrl = controller.borrow_mut();
         let facade = ctrl.facade();
@@ -459,7 +549,7 @@ impl DemoScenario for FinancialDashboardScenario {
 
         if self.step < positions.len() {
             let cursor = positions[self.step];
-            let _ = ctrl.dispatch_action(Action::UpdateCursor { cursor });
+            self.typist.borrow_mut().move_to(&mut ctrl, cursor);
         }
 
         self.step += 1;
@@ -477,6 +567,13 @@ impl DemoScenario for FinancialDashboardScenario {
     fn current_step(&self) -> usize {
         self.step
     }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        match step {
+            4 => vec![Assertion::selection_is("F8"), Assertion::error_count(0)],
+            _ => Vec::new(),
+        }
+    }
 }
 
 // Scientific Data Scenario
@@ -546,6 +643,14 @@ impl DemoScenario for ScientificDataScenario {
     fn current_step(&self) -> usize {
         self.step
     }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        if step + 1 == self.total_steps {
+            vec![Assertion::error_count(0)]
+        } else {
+            Vec::new()
+        }
+    }
 }
 
 // Fill Operations Scenario
@@ -615,6 +720,14 @@ impl DemoScenario for FillOperationsScenario {
     fn current_step(&self) -> usize {
         self.step
     }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        if step + 1 == self.total_steps {
+            vec![Assertion::error_count(0)]
+        } else {
+            Vec::new()
+        }
+    }
 }
 
 // Performance Stress Test Scenario
@@ -656,7 +769,7 @@ impl DemoScenario for PerformanceStressScenario {
         // Generate complex formula network
         let data = self.data_generator.generate_formula_stress_test();
 
-        leptos::logging::log!("Loading {} cells with complex formulas...", data.len());
+        crate::log_info!("Loading {} cells with complex formulas...", data.len());
         for (addr, value) in data.iter().take(500) {
             // Load subset for demo
             let _ = facade.set_cell_value(addr, value);
@@ -686,6 +799,14 @@ impl DemoScenario for PerformanceStressScenario {
     fn current_step(&self) -> usize {
         self.step
     }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        if step + 1 == self.total_steps {
+            vec![Assertion::error_count(0)]
+        } else {
+            Vec::new()
+        }
+    }
 }
 
 // Error Handling Scenario
@@ -693,6 +814,7 @@ pub struct ErrorHandlingScenario {
     step: usize,
     total_steps: usize,
     data_generator: DataGenerator,
+    typist: SharedTypist,
 }
 
 impl Default for ErrorHandlingScenario {
@@ -707,6 +829,7 @@ impl ErrorHandlingScenario {
             step: 0,
             total_steps: 10,
             data_generator: DataGenerator::new(),
+            typist: Typist::shared(Playback::Instant),
         }
     }
 }
@@ -720,6 +843,10 @@ impl DemoScenario for ErrorHandlingScenario {
         "Demonstrates error detection and handling capabilities"
     }
 
+    fn set_typist(&mut self, typist: SharedTypist) {
+        self.typist = typist;
+    }
+
     fn setup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
         let ctrl = controller.borrow_mut();
         let facade = ctrl.facade();
@@ -744,7 +871,7 @@ impl DemoScenario for ErrorHandlingScenario {
         // Navigate through error cells
         if self.step < 7 {
             let cursor = CellAddress::new(0, self.step as u32);
-            let _ = ctrl.dispatch_action(Action::UpdateCursor { cursor });
+            self.typist.borrow_mut().move_to(&mut ctrl, cursor);
         }
 
         self.step += 1;
@@ -762,4 +889,18 @@ impl DemoScenario for ErrorHandlingScenario {
     fn current_step(&self) -> usize {
         self.step
     }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        match step {
+            0 => vec![
+                Assertion::cell_equals("A2", "#DIV/0!"),
+                Assertion::cell_equals("A4", "#VALUE!"),
+                Assertion::cell_equals("A5", "#NAME?"),
+                Assertion::cell_equals("A7", "#DIV/0!"),
+                Assertion::error_count(6),
+            ],
+            6 => vec![Assertion::selection_is("A7")],
+            _ => Vec::new(),
+        }
+    }
 }
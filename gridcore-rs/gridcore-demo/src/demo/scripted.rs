@@ -0,0 +1,249 @@
+//! Scenarios written as JSON rather than code: a sheet to start from, the
+//! actions to dispatch, one per step, and the assertions to check after
+//! the last one. A [`BugReport`] copied from the debug overlay turns into
+//! one that replays the reported session and checks it ends on the same
+//! content hash.
+
+use super::assertions::Assertion;
+use super::scenarios::{DemoScenario, StepResult};
+use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::state::{Action, BugReport};
+use gridcore_core::csv::Sidecar;
+use gridcore_core::types::CellAddress;
+use gridcore_core::workbook::Determinism;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptedScenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The sheet to start from as CSV, formulas as text
+    #[serde(default)]
+    pub csv: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sidecar: Option<Sidecar>,
+    /// Deterministic mode to run in, so volatile formulas replay the
+    /// same values
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub determinism: Option<Determinism>,
+    /// Where the cursor starts, e.g. `B2`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub actions: Vec<Action>,
+    /// Checked once the last action ran
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    #[serde(skip)]
+    step: usize,
+}
+
+impl ScriptedScenario {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid scripted scenario: {}", e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Replay of a bug report, checking its content hash at the end. A
+    /// report whose log dropped actions cannot be replayed.
+    pub fn from_bug_report(report: &BugReport) -> Result<Self, String> {
+        if report.dropped > 0 {
+            return Err(format!(
+                "The report is missing its first {} actions",
+                report.dropped
+            ));
+        }
+        Ok(Self {
+            name: "Bug report".to_string(),
+            description: format!("Replay of {} actions", report.actions.len()),
+            csv: report.initial.csv.clone(),
+            sidecar: report.initial.sidecar.clone(),
+            determinism: report.initial.determinism,
+            cursor: Some(report.initial.state.state.cursor().to_a1()),
+            actions: report.actions.clone(),
+            assertions: vec![Assertion::content_hash(&report.content_hash)],
+            step: 0,
+        })
+    }
+}
+
+impl DemoScenario for ScriptedScenario {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn setup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        self.step = 0;
+        let mut ctrl = controller.borrow_mut();
+        if !self.csv.is_empty() {
+            if let Err(e) = ctrl.import_csv(&self.csv, self.sidecar.as_ref()) {
+                crate::log_warn!("Scripted scenario '{}' setup: {}", self.name, e);
+            }
+        }
+        // Importing evaluated the formulas; the actions draw from here on
+        if self.determinism.is_some() {
+            ctrl.facade().set_determinism(self.determinism);
+        }
+        if let Some(cursor) = self
+            .cursor
+            .as_deref()
+            .and_then(|a1| CellAddress::from_a1(a1).ok())
+        {
+            ctrl.set_cursor(cursor);
+        }
+    }
+
+    fn run_step(&mut self, controller: Rc<RefCell<SpreadsheetController>>) -> StepResult {
+        let Some(action) = self.actions.get(self.step).cloned() else {
+            return StepResult::Complete;
+        };
+        // Actions that failed when recorded fail the same way on replay
+        if let Err(e) = controller.borrow_mut().dispatch_action(action) {
+            crate::log_info!("Scripted step {} failed: {}", self.step + 1, e);
+        }
+        self.step += 1;
+        StepResult::Continue
+    }
+
+    fn cleanup(&mut self, _controller: Rc<RefCell<SpreadsheetController>>) {}
+
+    fn total_steps(&self) -> usize {
+        self.actions.len()
+    }
+
+    fn current_step(&self) -> usize {
+        self.step
+    }
+
+    fn assertions(&self, step: usize) -> Vec<Assertion> {
+        if step + 1 == self.actions.len() {
+            self.assertions.clone()
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::demo::runner::run_headless;
+
+    fn edit(cursor: &str, value: &str) -> Vec<Action> {
+        vec![
+            Action::UpdateCursor {
+                cursor: CellAddress::from_a1(cursor).unwrap(),
+            },
+            Action::UpdateFormulaBar {
+                value: value.to_string(),
+            },
+            Action::SubmitFormulaBar,
+        ]
+    }
+
+    #[test]
+    fn test_scripted_scenario_reads_from_json_and_runs() {
+        let json = r#"{
+            "name": "Totals",
+            "csv": "1\n2\n",
+            "cursor": "A3",
+            "actions": [
+                {"StartEditing": {"edit_mode": null, "initial_value": "=SUM(A1:A2)", "cursor_position": null}},
+                {"SubmitCellEdit": {"value": "=SUM(A1:A2)"}}
+            ],
+            "assertions": [{"assert": "cell_equals", "address": "A3", "expected": "3"}]
+        }"#;
+        let scenario = ScriptedScenario::from_json(json).unwrap();
+        let report = run_headless(Box::new(scenario)).unwrap();
+        assert!(report.is_success(), "{}", report.summary());
+        assert_eq!(report.steps_run, 2);
+        assert_eq!(report.results.len(), 1);
+
+        let mut wrong = ScriptedScenario::from_json(json).unwrap();
+        wrong.assertions = vec![Assertion::cell_equals("A3", "4")];
+        let report = run_headless(Box::new(wrong)).unwrap();
+        assert_eq!(report.failed_steps(), vec![1]);
+    }
+
+    #[test]
+    fn test_bug_report_replays_to_the_same_content_hash() {
+        let mut controller = SpreadsheetController::new();
+        controller
+            .import_csv("10,=A1*2\n", None)
+            .expect("import the starting sheet");
+        let actions: Vec<Action> = [edit("A2", "5"), edit("B2", "=A2+B1")].concat();
+        for action in actions {
+            controller.dispatch_action(action).unwrap();
+        }
+        let b2 = CellAddress::from_a1("B2").unwrap();
+        let b2 = controller.facade().get_cell_raw_value(&b2);
+        assert_eq!(
+            b2.map(|value| value.to_display_string()).as_deref(),
+            Some("25")
+        );
+        let report = controller.bug_report().expect("actions were logged");
+        assert_eq!(report.actions.len(), 6);
+        assert_eq!(report.dropped, 0);
+
+        // Through the clipboard and back
+        let report = BugReport::from_json(&report.to_json()).unwrap();
+        let scenario = ScriptedScenario::from_bug_report(&report).unwrap();
+        let scenario = ScriptedScenario::from_json(&scenario.to_json()).unwrap();
+        let replay = run_headless(Box::new(scenario)).unwrap();
+        assert!(replay.is_success(), "{}", replay.summary());
+        assert!(matches!(
+            replay.results[0].assertion,
+            Assertion::ContentHash { .. }
+        ));
+
+        // A different ending fails the hash check
+        let mut changed = ScriptedScenario::from_bug_report(&report).unwrap();
+        changed.actions.extend(edit("C1", "x"));
+        let replay = run_headless(Box::new(changed)).unwrap();
+        assert!(!replay.is_success());
+    }
+
+    #[test]
+    fn test_deterministic_session_with_rand_replays_identically() {
+        let mut controller = SpreadsheetController::new();
+        controller
+            .import_csv("=RAND(),=A1*100\n=RANDBETWEEN(1,6),\n", None)
+            .expect("import the starting sheet");
+        controller.facade().set_determinism(Some(Determinism {
+            seed: 2024,
+            now_ms: 1_700_000_000_000,
+        }));
+        // Some draws before the log starts, which the replay must skip
+        controller.facade().recalculate().unwrap();
+        controller.clear_action_log();
+
+        let actions: Vec<Action> =
+            [edit("C1", "1"), edit("C2", "=B1+A2"), edit("C3", "2")].concat();
+        for action in actions {
+            controller.dispatch_action(action).unwrap();
+        }
+        let report = controller.bug_report().expect("actions were logged");
+        assert!(report.initial.determinism.is_some());
+
+        let report = BugReport::from_json(&report.to_json()).unwrap();
+        let scenario = ScriptedScenario::from_bug_report(&report).unwrap();
+        let scenario = ScriptedScenario::from_json(&scenario.to_json()).unwrap();
+        let replay = run_headless(Box::new(scenario)).unwrap();
+        assert!(replay.is_success(), "{}", replay.summary());
+
+        // Without deterministic mode the volatile values differ
+        let mut random = ScriptedScenario::from_bug_report(&report).unwrap();
+        random.determinism = None;
+        let replay = run_headless(Box::new(random)).unwrap();
+        assert!(!replay.is_success());
+    }
+}
@@ -0,0 +1,423 @@
+//! Guided tutorials.
+//!
+//! A tutorial is a scenario whose steps wait for the user. Each step shows
+//! an [`Instruction`], anchored to the cell it is about, and the runner
+//! holds the step until its [`WaitFor`] condition holds: the cursor reaches
+//! a cell, a cell shows a value or a mode is entered. Conditions are
+//! checked when the controller reports an event, not on a timer, so a
+//! waiting tutorial costs nothing. A step can be skipped or started over,
+//! and one with a timeout moves on by itself.
+
+use super::assertions::Assertion;
+use super::scenarios::{DemoScenario, StepResult};
+use gridcore_controller::controller::{EditorMode, SpreadsheetController};
+use gridcore_controller::state::{Action, SpreadsheetMode};
+use gridcore_core::types::CellAddress;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// What a step tells the user, shown as a callout next to `target`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Instruction {
+    pub title: String,
+    pub body: String,
+    /// Cell the callout points at and the grid outlines, e.g. `B2`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+impl Instruction {
+    pub fn new(title: &str, body: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            body: body.to_string(),
+            target: None,
+        }
+    }
+
+    pub fn at(mut self, target: &str) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    /// The target cell, `None` without one or when it is not an address
+    pub fn target_address(&self) -> Option<CellAddress> {
+        self.target
+            .as_deref()
+            .and_then(|target| CellAddress::from_a1(target).ok())
+    }
+}
+
+/// What the user has to do to finish a step, e.g.
+/// `{"wait_for": "cell_equals", "address": "B5", "expected": "21"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "wait_for", rename_all = "snake_case")]
+pub enum WaitFor {
+    /// The cursor is on `address`
+    CursorAt { address: String },
+    /// The displayed value of a cell, as [`Assertion::CellEquals`] reads it
+    CellEquals { address: String, expected: String },
+    /// The editor is in `mode`: navigation, editing, command, visual or resize
+    ModeIs { mode: String },
+}
+
+impl WaitFor {
+    pub fn cursor_at(address: &str) -> Self {
+        WaitFor::CursorAt {
+            address: address.to_string(),
+        }
+    }
+
+    pub fn cell_equals(address: &str, expected: &str) -> Self {
+        WaitFor::CellEquals {
+            address: address.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    pub fn mode_is(mode: &str) -> Self {
+        WaitFor::ModeIs {
+            mode: mode.to_string(),
+        }
+    }
+
+    pub fn is_met(&self, controller: &SpreadsheetController) -> bool {
+        match self {
+            WaitFor::CursorAt { address } => {
+                CellAddress::from_a1(address).is_ok_and(|address| controller.cursor() == address)
+            }
+            WaitFor::CellEquals { address, expected } => Assertion::cell_equals(address, expected)
+                .check(controller)
+                .is_ok(),
+            WaitFor::ModeIs { mode } => {
+                mode_name(controller.get_mode().to_spreadsheet_mode()) == mode.to_lowercase()
+            }
+        }
+    }
+}
+
+fn mode_name(mode: SpreadsheetMode) -> &'static str {
+    match mode {
+        SpreadsheetMode::Navigation => "navigation",
+        SpreadsheetMode::Visual => "visual",
+        SpreadsheetMode::Editing => "editing",
+        SpreadsheetMode::Command => "command",
+        SpreadsheetMode::Resize => "resize",
+        SpreadsheetMode::Insert => "insert",
+        SpreadsheetMode::Delete => "delete",
+        SpreadsheetMode::BulkOperation => "bulk_operation",
+    }
+}
+
+/// One step of a tutorial
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TutorialStep {
+    pub instruction: Instruction,
+    pub wait_for: WaitFor,
+    /// Where the cursor goes when the step is started over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_at: Option<String>,
+    /// Cells put back as the tutorial wrote them when the step is started
+    /// over; cells the tutorial did not write are cleared
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restore: Vec<String>,
+    /// Milliseconds to wait for the user before moving on without them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<f64>,
+}
+
+impl TutorialStep {
+    pub fn new(instruction: Instruction, wait_for: WaitFor) -> Self {
+        Self {
+            instruction,
+            wait_for,
+            start_at: None,
+            restore: Vec::new(),
+            timeout_ms: None,
+        }
+    }
+
+    pub fn starting_at(mut self, address: &str) -> Self {
+        self.start_at = Some(address.to_string());
+        self
+    }
+
+    pub fn restoring(mut self, cells: &[&str]) -> Self {
+        self.restore = cells.iter().map(|cell| cell.to_string()).collect();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout_ms: f64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+}
+
+/// A scenario of steps the user performs, see the module documentation
+pub struct Tutorial {
+    name: String,
+    description: String,
+    /// Cells written when the tutorial starts, as `(address, input)`
+    data: Vec<(String, String)>,
+    steps: Vec<TutorialStep>,
+    step: usize,
+}
+
+impl Tutorial {
+    pub fn new(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            data: Vec::new(),
+            steps: Vec::new(),
+            step: 0,
+        }
+    }
+
+    pub fn with_data(mut self, data: &[(&str, &str)]) -> Self {
+        self.data = data
+            .iter()
+            .map(|(address, input)| (address.to_string(), input.to_string()))
+            .collect();
+        self
+    }
+
+    pub fn step(mut self, step: TutorialStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn steps(&self) -> &[TutorialStep] {
+        &self.steps
+    }
+
+    /// Navigation, editing, a SUM formula and filling a formula down
+    pub fn getting_started() -> Self {
+        Tutorial::new(
+            "Getting Started",
+            "Learn to move around, edit cells, write a formula and fill it down",
+        )
+        .with_data(&[
+            ("A1", "Item"),
+            ("B1", "Price"),
+            ("C1", "Retail"),
+            ("A2", "Pens"),
+            ("B2", "3"),
+            ("A3", "Paper"),
+            ("B3", "5"),
+            ("A4", "Ink"),
+            ("B4", "12"),
+            ("A5", "Total"),
+        ])
+        .step(
+            TutorialStep::new(
+                Instruction::new(
+                    "Move around",
+                    "Press l to move right and j to move down until you reach the price of pens.",
+                )
+                .at("B2"),
+                WaitFor::cursor_at("B2"),
+            )
+            .starting_at("A1"),
+        )
+        .step(
+            TutorialStep::new(
+                Instruction::new(
+                    "Edit a cell",
+                    "Pens went up: press a, Backspace and type 4, then Escape twice to save.",
+                )
+                .at("B2"),
+                WaitFor::cell_equals("B2", "4"),
+            )
+            .starting_at("B2")
+            .restoring(&["B2"]),
+        )
+        .step(
+            TutorialStep::new(
+                Instruction::new(
+                    "Add them up",
+                    "Go to B5, press i, type =SUM(B2:B4) and press Escape twice.",
+                )
+                .at("B5"),
+                WaitFor::cell_equals("B5", "21"),
+            )
+            .starting_at("B5")
+            .restoring(&["B5"]),
+        )
+        .step(
+            TutorialStep::new(
+                Instruction::new(
+                    "Write a formula to copy",
+                    "In C2, enter =B2*2 to price pens at twice what they cost.",
+                )
+                .at("C2"),
+                WaitFor::cell_equals("C2", "8"),
+            )
+            .starting_at("C2")
+            .restoring(&["C2"]),
+        )
+        .step(
+            TutorialStep::new(
+                Instruction::new(
+                    "Select where it goes",
+                    "With the cursor on C2, press v to start a selection.",
+                )
+                .at("C2"),
+                WaitFor::mode_is("visual"),
+            )
+            .starting_at("C2"),
+        )
+        .step(
+            TutorialStep::new(
+                Instruction::new(
+                    "Fill it down",
+                    "Press j twice to reach C4, then Ctrl+D to fill the formula down.",
+                )
+                .at("C4"),
+                WaitFor::cell_equals("C4", "24"),
+            )
+            .starting_at("C2")
+            .restoring(&["C3", "C4"]),
+        )
+    }
+
+    fn input_for(&self, address: &str) -> &str {
+        self.data
+            .iter()
+            .find(|(cell, _)| cell.eq_ignore_ascii_case(address))
+            .map_or("", |(_, input)| input)
+    }
+}
+
+impl DemoScenario for Tutorial {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn setup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        let mut ctrl = controller.borrow_mut();
+        for (address, input) in &self.data {
+            if let Ok(address) = CellAddress::from_a1(address) {
+                let _ = ctrl.write_cell(&address, input);
+            }
+        }
+        let start = self
+            .steps
+            .first()
+            .and_then(|step| step.start_at.as_deref())
+            .and_then(|address| CellAddress::from_a1(address).ok());
+        if let Some(start) = start {
+            ctrl.set_cursor(start);
+        }
+        self.step = 0;
+    }
+
+    /// The user did the work; a step only moves the tutorial on
+    fn run_step(&mut self, _controller: Rc<RefCell<SpreadsheetController>>) -> StepResult {
+        if self.step >= self.steps.len() {
+            return StepResult::Complete;
+        }
+        self.step += 1;
+        StepResult::Continue
+    }
+
+    fn cleanup(&mut self, controller: Rc<RefCell<SpreadsheetController>>) {
+        controller.borrow_mut().set_highlighted_cell(None);
+        self.step = 0;
+    }
+
+    fn total_steps(&self) -> usize {
+        self.steps.len()
+    }
+
+    fn current_step(&self) -> usize {
+        self.step
+    }
+
+    fn tutorial_step(&self, step: usize) -> Option<&TutorialStep> {
+        self.steps.get(step)
+    }
+
+    fn restart_step(&mut self, step: usize, controller: Rc<RefCell<SpreadsheetController>>) {
+        let Some(tutorial_step) = self.steps.get(step) else {
+            return;
+        };
+        let mut ctrl = controller.borrow_mut();
+        let was_visual = ctrl.get_mode().is_visual();
+        if !ctrl.get_mode().is_navigation() {
+            ctrl.set_mode(EditorMode::Navigation);
+        }
+        ctrl.set_selection(None);
+        if was_visual {
+            let _ = ctrl.dispatch_action(Action::ExitSpreadsheetVisualMode);
+        }
+        for cell in &tutorial_step.restore {
+            if let Ok(address) = CellAddress::from_a1(cell) {
+                let _ = ctrl.write_cell(&address, self.input_for(cell));
+            }
+        }
+        let start = tutorial_step
+            .start_at
+            .as_deref()
+            .and_then(|address| CellAddress::from_a1(address).ok());
+        if let Some(start) = start {
+            ctrl.set_cursor(start);
+        }
+    }
+}
+
+/// Names of the built-in tutorials
+pub fn get_available_tutorials() -> Vec<String> {
+    vec!["Getting Started".to_string()]
+}
+
+pub fn create_tutorial(name: &str) -> Result<Box<dyn DemoScenario>, String> {
+    match name {
+        "Getting Started" => Ok(Box::new(Tutorial::getting_started())),
+        _ => Err(format!("Unknown tutorial: {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conditions() {
+        let mut controller = SpreadsheetController::new();
+        controller.set_cursor(CellAddress::new(1, 1));
+        controller
+            .write_cell(&CellAddress::new(0, 0), "=2*3")
+            .unwrap();
+
+        assert!(WaitFor::cursor_at("B2").is_met(&controller));
+        assert!(!WaitFor::cursor_at("A1").is_met(&controller));
+        assert!(WaitFor::cell_equals("A1", "6").is_met(&controller));
+        assert!(WaitFor::mode_is("Navigation").is_met(&controller));
+        assert!(!WaitFor::mode_is("visual").is_met(&controller));
+    }
+
+    #[test]
+    fn test_steps_read_from_json() {
+        let step: TutorialStep = serde_json::from_str(
+            r#"{
+                "instruction": {"title": "Sum", "body": "Add them up", "target": "B5"},
+                "wait_for": {"wait_for": "cell_equals", "address": "B5", "expected": "21"},
+                "timeout_ms": 30000
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(step.wait_for, WaitFor::cell_equals("B5", "21"));
+        assert_eq!(
+            step.instruction.target_address(),
+            Some(CellAddress::new(1, 4))
+        );
+        assert_eq!(step.timeout_ms, Some(30000.0));
+        assert!(step.restore.is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+//! Playback of scenario steps. Instantly, a step writes its cells and
+//! moves the cursor in one go. Humanized, entering a cell becomes the
+//! keystrokes a person would type, through the same actions the editor
+//! dispatches, and moving the cursor walks it one cell at a time. The
+//! keystrokes are queued with a delay each for the runner to play back.
+
+use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::state::Action;
+use gridcore_core::types::CellAddress;
+use rand::prelude::*;
+use rand::SeedableRng;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Playback {
+    Instant,
+    Humanized(Humanize),
+}
+
+/// Timing of humanized playback, before the playback speed applies
+#[derive(Debug, Clone, PartialEq)]
+pub struct Humanize {
+    /// Milliseconds between typed characters
+    pub key_delay_ms: f32,
+    /// Milliseconds between cursor moves
+    pub move_delay_ms: f32,
+    /// How far a delay may stray from its base, as a fraction of it
+    pub jitter: f32,
+    /// Seeds the jitter, so a run can be repeated exactly
+    pub seed: u64,
+}
+
+impl Default for Humanize {
+    fn default() -> Self {
+        Self {
+            key_delay_ms: 80.0,
+            move_delay_ms: 60.0,
+            jitter: 0.3,
+            seed: 0,
+        }
+    }
+}
+
+/// An action to dispatch once `delay_ms` passed since the one before
+#[derive(Debug, Clone)]
+pub struct Keystroke {
+    pub delay_ms: f32,
+    pub action: Action,
+}
+
+/// A typist shared by the runner, which plays its keystrokes back, and
+/// the scenario, which queues them
+pub type SharedTypist = Rc<RefCell<Typist>>;
+
+pub struct Typist {
+    playback: Playback,
+    speed: f32,
+    rng: StdRng,
+    /// Where the cursor is once the queued keystrokes ran
+    cursor: Option<CellAddress>,
+    queue: VecDeque<Keystroke>,
+}
+
+impl Default for Typist {
+    fn default() -> Self {
+        Self::new(Playback::Instant)
+    }
+}
+
+impl Typist {
+    pub fn new(playback: Playback) -> Self {
+        let seed = match &playback {
+            Playback::Humanized(humanize) => humanize.seed,
+            Playback::Instant => 0,
+        };
+        Self {
+            playback,
+            speed: 1.0,
+            rng: StdRng::seed_from_u64(seed),
+            cursor: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn shared(playback: Playback) -> SharedTypist {
+        Rc::new(RefCell::new(Self::new(playback)))
+    }
+
+    pub fn playback(&self) -> &Playback {
+        &self.playback
+    }
+
+    pub fn set_playback(&mut self, playback: Playback) {
+        let speed = self.speed;
+        *self = Self::new(playback);
+        self.speed = speed;
+    }
+
+    /// Divide every delay by `speed`
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(0.1, 10.0);
+    }
+
+    /// Drop queued keystrokes and reseed, to play a scenario from the start
+    pub fn reset(&mut self) {
+        let playback = self.playback.clone();
+        self.set_playback(playback);
+    }
+
+    /// Move the cursor to `target`
+    pub fn move_to(&mut self, ctrl: &mut SpreadsheetController, target: CellAddress) {
+        let Playback::Humanized(humanize) = self.playback.clone() else {
+            let _ = ctrl.dispatch_action(Action::UpdateCursor { cursor: target });
+            return;
+        };
+        let mut cursor = self.cursor.unwrap_or_else(|| ctrl.cursor());
+        while cursor != target {
+            if cursor.col != target.col {
+                cursor.col = step_towards(cursor.col, target.col);
+            } else {
+                cursor.row = step_towards(cursor.row, target.row);
+            }
+            self.push(
+                humanize.move_delay_ms,
+                &humanize,
+                Action::UpdateCursor { cursor },
+            );
+        }
+        self.cursor = Some(target);
+    }
+
+    /// Put `value` in the cell at `address`. Humanized, the cursor walks
+    /// there first and the value is typed one character at a time.
+    pub fn enter_cell(
+        &mut self,
+        ctrl: &mut SpreadsheetController,
+        address: CellAddress,
+        value: &str,
+    ) {
+        let Playback::Humanized(humanize) = self.playback.clone() else {
+            let _ = ctrl.write_cell(&address, value);
+            return;
+        };
+        self.move_to(ctrl, address);
+        self.push(
+            humanize.move_delay_ms,
+            &humanize,
+            Action::StartEditing {
+                edit_mode: None,
+                initial_value: Some(String::new()),
+                cursor_position: Some(0),
+            },
+        );
+        let mut typed = String::new();
+        for ch in value.chars() {
+            typed.push(ch);
+            self.push(
+                humanize.key_delay_ms,
+                &humanize,
+                Action::UpdateEditingValue {
+                    value: typed.clone(),
+                    cursor_position: typed.chars().count(),
+                },
+            );
+        }
+        self.push(
+            humanize.key_delay_ms,
+            &humanize,
+            Action::SubmitCellEdit {
+                value: value.to_string(),
+            },
+        );
+    }
+
+    /// Whether every queued keystroke was played
+    pub fn is_idle(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Delay before the next queued keystroke
+    pub fn next_delay(&self) -> Option<f32> {
+        self.queue.front().map(|keystroke| keystroke.delay_ms)
+    }
+
+    /// Take the next queued keystroke
+    pub fn pop(&mut self) -> Option<Keystroke> {
+        let keystroke = self.queue.pop_front();
+        if self.queue.is_empty() {
+            self.cursor = None;
+        }
+        keystroke
+    }
+
+    fn push(&mut self, base_ms: f32, humanize: &Humanize, action: Action) {
+        let jitter = humanize.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            self.rng.random_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+        self.queue.push_back(Keystroke {
+            delay_ms: base_ms * factor / self.speed,
+            action,
+        });
+    }
+}
+
+fn step_towards(from: u32, to: u32) -> u32 {
+    if from < to {
+        from + 1
+    } else {
+        from - 1
+    }
+}
@@ -6,7 +6,10 @@ pub mod utils;
 pub mod components;
 
 // Re-export main types
+pub use demo::assertions::{Assertion, AssertionResult, ScenarioReport};
+pub use demo::runner::{run_headless, run_scenario_headless};
+pub use demo::scripted::ScriptedScenario;
 pub use demo::{DemoConfig, DemoController, DemoMode};
 
 #[cfg(feature = "web")]
-pub use components::{DemoProgressBar, PerformanceOverlay};
+pub use components::{DemoProgressBar, PerformanceOverlay, SuggestionsDrawer, TutorialCallout};
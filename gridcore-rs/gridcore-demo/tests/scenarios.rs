@@ -0,0 +1,359 @@
+use gridcore_demo::demo::scenarios::get_available_scenarios;
+use gridcore_demo::run_scenario_headless;
+
+#[test]
+fn built_in_scenarios_pass_their_assertions() {
+    for name in get_available_scenarios() {
+        let report = run_scenario_headless(&name).unwrap();
+        assert!(report.is_success(), "{}", report.summary());
+        assert!(report.steps_run > 0, "{} ran no steps", name);
+    }
+}
+
+#[test]
+fn failed_assertions_are_recorded_per_step() {
+    use gridcore_controller::controller::SpreadsheetController;
+    use gridcore_demo::demo::runner::DemoRunner;
+    use gridcore_demo::demo::scenarios::{DemoScenario, StepResult};
+    use gridcore_demo::Assertion;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Broken {
+        step: usize,
+    }
+
+    impl DemoScenario for Broken {
+        fn name(&self) -> &str {
+            "Broken"
+        }
+        fn description(&self) -> &str {
+            "Expects a value nobody writes"
+        }
+        fn setup(&mut self, _controller: Rc<RefCell<SpreadsheetController>>) {}
+        fn run_step(&mut self, controller: Rc<RefCell<SpreadsheetController>>) -> StepResult {
+            if self.step >= 3 {
+                return StepResult::Complete;
+            }
+            let address = gridcore_core::types::CellAddress::new(0, self.step as u32);
+            let _ = controller.borrow_mut().write_cell(&address, "1");
+            self.step += 1;
+            StepResult::Continue
+        }
+        fn cleanup(&mut self, _controller: Rc<RefCell<SpreadsheetController>>) {}
+        fn total_steps(&self) -> usize {
+            3
+        }
+        fn current_step(&self) -> usize {
+            self.step
+        }
+        fn assertions(&self, step: usize) -> Vec<Assertion> {
+            vec![Assertion::cell_equals(
+                "A2",
+                if step == 0 { "" } else { "2" },
+            )]
+        }
+    }
+
+    let run = |abort: bool| {
+        let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+        let mut runner = DemoRunner::new();
+        runner.set_abort_on_failure(abort);
+        runner.load(Box::new(Broken { step: 0 }));
+        runner.start(controller.clone()).unwrap();
+        while runner.is_running() {
+            runner.step(controller.clone());
+        }
+        runner.report()
+    };
+
+    let report = run(false);
+    assert_eq!(report.failed_steps(), vec![1, 2]);
+    assert_eq!(report.steps_run, 3);
+    assert!(report.error.is_none());
+
+    let report = run(true);
+    assert_eq!(report.failed_steps(), vec![1]);
+    assert_eq!(report.steps_run, 2);
+    assert!(report.error.is_some());
+}
+
+mod tutorials {
+    use gridcore_controller::controller::{KeyboardEvent, SpreadsheetController};
+    use gridcore_core::types::CellAddress;
+    use gridcore_demo::demo::runner::DemoRunner;
+    use gridcore_demo::demo::tutorial::{Instruction, Tutorial, TutorialStep, WaitFor};
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    fn press(controller: &Rc<RefCell<SpreadsheetController>>, keys: &[&str]) {
+        for key in keys {
+            let event = match key.strip_prefix("C-") {
+                Some(key) => {
+                    KeyboardEvent::new(key.to_string()).with_modifiers(false, true, false, false)
+                }
+                None => KeyboardEvent::new(key.to_string()),
+            };
+            controller
+                .borrow_mut()
+                .handle_keyboard_event(event)
+                .unwrap();
+        }
+    }
+
+    fn type_text(controller: &Rc<RefCell<SpreadsheetController>>, text: &str) {
+        for c in text.chars() {
+            press(controller, &[&c.to_string()]);
+        }
+    }
+
+    fn value(controller: &Rc<RefCell<SpreadsheetController>>, address: &str) -> String {
+        let address = CellAddress::from_a1(address).unwrap();
+        controller
+            .borrow()
+            .facade()
+            .get_cell_raw_value(&address)
+            .map(|value| value.to_display_string())
+            .unwrap_or_default()
+    }
+
+    fn highlight(controller: &Rc<RefCell<SpreadsheetController>>) -> Option<String> {
+        controller
+            .borrow()
+            .highlighted_cell()
+            .map(|a| a.to_string())
+    }
+
+    fn start(name: &str) -> (Rc<RefCell<SpreadsheetController>>, DemoRunner) {
+        let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+        let mut runner = DemoRunner::new();
+        runner.load_tutorial(name).unwrap();
+        runner.start(controller.clone()).unwrap();
+        (controller, runner)
+    }
+
+    #[test]
+    fn getting_started_waits_for_each_action() {
+        let (controller, mut runner) = start("Getting Started");
+        assert!(runner.is_waiting());
+        assert_eq!(runner.get_current_step(), 0);
+        assert_eq!(highlight(&controller).as_deref(), Some("B2"));
+
+        // Nothing happens until the user does what the step asks
+        runner.step(controller.clone());
+        runner.step(controller.clone());
+        assert_eq!(runner.get_current_step(), 0);
+
+        press(&controller, &["l", "j"]);
+        runner.step(controller.clone());
+        assert_eq!(runner.get_current_step(), 1);
+
+        press(&controller, &["a", "Backspace", "4", "Escape", "Escape"]);
+        runner.step(controller.clone());
+        assert_eq!(runner.get_current_step(), 2);
+        assert_eq!(highlight(&controller).as_deref(), Some("B5"));
+
+        controller.borrow_mut().set_cursor(CellAddress::new(1, 4));
+        press(&controller, &["i"]);
+        type_text(&controller, "=SUM(B2:B4)");
+        press(&controller, &["Escape", "Escape"]);
+        runner.step(controller.clone());
+        assert_eq!(value(&controller, "B5"), "21");
+        assert_eq!(runner.get_current_step(), 3);
+
+        controller.borrow_mut().set_cursor(CellAddress::new(2, 1));
+        press(&controller, &["i"]);
+        type_text(&controller, "=B2*2");
+        press(&controller, &["Escape", "Escape"]);
+        runner.step(controller.clone());
+        assert_eq!(runner.get_current_step(), 4);
+
+        controller.borrow_mut().set_cursor(CellAddress::new(2, 1));
+        press(&controller, &["v"]);
+        runner.step(controller.clone());
+        assert_eq!(runner.get_current_step(), 5);
+
+        press(&controller, &["j", "j", "C-d"]);
+        assert_eq!(value(&controller, "C4"), "24");
+        runner.step(controller.clone());
+        runner.step(controller.clone());
+        assert!(!runner.is_running());
+        assert_eq!(highlight(&controller), None);
+
+        let report = runner.report();
+        assert!(report.is_success(), "{}", report.summary());
+        assert_eq!(report.steps_run, 6);
+        assert!(report.skipped_steps.is_empty());
+    }
+
+    #[test]
+    fn steps_can_be_restarted_and_skipped() {
+        let (controller, mut runner) = start("Getting Started");
+        press(&controller, &["l", "j"]);
+        runner.step(controller.clone());
+        assert_eq!(runner.get_current_step(), 1);
+
+        press(&controller, &["a", "Backspace", "9", "Escape", "Escape"]);
+        runner.step(controller.clone());
+        assert_eq!(value(&controller, "B2"), "9");
+        assert_eq!(runner.get_current_step(), 1);
+
+        runner.restart_step(controller.clone());
+        assert_eq!(value(&controller, "B2"), "3");
+        assert_eq!(controller.borrow().cursor(), CellAddress::new(1, 1));
+
+        runner.skip_step(controller.clone());
+        runner.step(controller.clone());
+        assert_eq!(runner.get_current_step(), 2);
+        assert_eq!(runner.report().skipped_steps, vec![1]);
+    }
+
+    #[test]
+    fn steps_move_on_after_their_timeout() {
+        let tutorial = Tutorial::new("Timed", "").step(
+            TutorialStep::new(
+                Instruction::new("Wait", "Nothing to do here."),
+                WaitFor::cursor_at("Z99"),
+            )
+            .with_timeout(1000.0),
+        );
+        let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+        let now = Rc::new(Cell::new(0.0));
+        let mut runner = DemoRunner::new();
+        let clock = now.clone();
+        runner.set_clock(move || clock.get());
+        runner.load(Box::new(tutorial));
+        runner.start(controller.clone()).unwrap();
+
+        now.set(999.0);
+        runner.step(controller.clone());
+        assert_eq!(runner.get_current_step(), 0);
+
+        now.set(1000.0);
+        runner.step(controller.clone());
+        runner.step(controller.clone());
+        assert!(!runner.is_running());
+        assert_eq!(runner.report().timed_out_steps, vec![0]);
+    }
+}
+
+mod humanized {
+    use gridcore_controller::controller::SpreadsheetController;
+    use gridcore_core::types::CellAddress;
+    use gridcore_demo::demo::runner::DemoRunner;
+    use gridcore_demo::demo::typing::{Humanize, Playback, Typist};
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    fn humanized(seed: u64) -> Playback {
+        Playback::Humanized(Humanize {
+            seed,
+            ..Humanize::default()
+        })
+    }
+
+    fn run(playback: Playback) -> (Rc<RefCell<SpreadsheetController>>, DemoRunner) {
+        let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+        let mut runner = DemoRunner::new();
+        runner.set_playback(playback);
+        runner.load_scenario("Basic Operations").unwrap();
+        runner.start(controller.clone()).unwrap();
+        while runner.is_running() {
+            runner.step(controller.clone());
+        }
+        (controller, runner)
+    }
+
+    #[test]
+    fn typed_scenario_ends_on_the_instant_document() {
+        let (instant, _) = run(Playback::Instant);
+        let (typed, runner) = run(humanized(7));
+        assert!(
+            runner.report().is_success(),
+            "{}",
+            runner.report().summary()
+        );
+        assert_eq!(
+            typed.borrow().facade().content_hash(),
+            instant.borrow().facade().content_hash()
+        );
+        // 16 single-cell cursor moves, then per edit a start, a keystroke
+        // per character and a commit: Charlie, 28, 92, Total, =SUM(C2:C4)
+        assert_eq!(runner.keystrokes_played(), 16 + 9 + 4 + 4 + 7 + 13);
+    }
+
+    #[test]
+    fn keystrokes_walk_and_type_with_seeded_delays() {
+        let mut controller = SpreadsheetController::new();
+        let plan = |seed: u64| {
+            let mut typist = Typist::new(humanized(seed));
+            typist.set_speed(2.0);
+            let mut controller = SpreadsheetController::new();
+            typist.enter_cell(&mut controller, CellAddress::new(2, 1), "=1");
+            let mut delays = Vec::new();
+            while let Some(keystroke) = typist.pop() {
+                delays.push(keystroke.delay_ms);
+            }
+            delays
+        };
+        let delays = plan(1);
+        // C2 is three moves from A1, then start, "=", "1" and commit
+        assert_eq!(delays.len(), 7);
+        assert_eq!(delays, plan(1));
+        assert_ne!(delays, plan(2));
+        // Key delays are 80ms within 30% jitter, halved by the speed
+        assert!(delays[4..]
+            .iter()
+            .all(|delay| (28.0..=52.0).contains(delay)));
+
+        let mut typist = Typist::new(humanized(1));
+        typist.enter_cell(&mut controller, CellAddress::new(1, 0), "ab");
+        let mut actions = Vec::new();
+        while let Some(keystroke) = typist.pop() {
+            actions.push(format!("{:?}", keystroke.action));
+        }
+        assert!(actions[0].starts_with("UpdateCursor"));
+        assert!(actions[1].starts_with("StartEditing"));
+        assert!(actions[2].contains("value: \"a\""));
+        assert!(actions[3].contains("value: \"ab\""));
+        assert!(actions[4].starts_with("SubmitCellEdit"));
+        assert!(controller
+            .facade()
+            .get_cell_raw_value(&CellAddress::new(1, 0))
+            .is_none());
+    }
+
+    #[test]
+    fn ticks_type_as_delays_pass_and_step_finishes_the_step() {
+        let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+        let now = Rc::new(Cell::new(0.0));
+        let mut runner = DemoRunner::new();
+        let clock = now.clone();
+        runner.set_clock(move || clock.get());
+        runner.set_playback(Playback::Humanized(Humanize {
+            jitter: 0.0,
+            ..Humanize::default()
+        }));
+        runner.load_scenario("Basic Operations").unwrap();
+        runner.start(controller.clone()).unwrap();
+        // Starting ran the first step, a single move to the right
+        assert_eq!(runner.keystrokes_played(), 1);
+
+        // The next step waits for the step delay, then moves down
+        now.set(499.0);
+        runner.tick(controller.clone());
+        assert_eq!(runner.keystrokes_played(), 1);
+        now.set(500.0);
+        runner.tick(controller.clone());
+        assert_eq!(runner.keystrokes_played(), 1);
+        now.set(560.0);
+        runner.tick(controller.clone());
+        assert_eq!(runner.keystrokes_played(), 2);
+        assert_eq!(controller.borrow().cursor(), CellAddress::new(1, 1));
+
+        // A manual step runs a whole step, not a keystroke
+        runner.step(controller.clone());
+        assert_eq!(runner.keystrokes_played(), 3);
+        assert_eq!(runner.get_current_step(), 3);
+    }
+}
@@ -1,12 +1,19 @@
 use crate::components::error_display::ErrorDisplay;
 use crate::components::grid::GridContainer;
+use crate::components::lint_panel::LintPanel;
+use crate::components::load_report_panel::LoadReportPanel;
+use crate::components::minimap::Minimap;
 use crate::components::status_bar::StatusBar;
 use crate::components::tab_bar::{Sheet, TabBar};
+use crate::components::template_picker::TemplatePicker;
 use crate::components::viewport::Viewport;
+use crate::components::watch_panel::WatchPanel;
 use crate::context::AppState;
 use crate::reactive::ReactiveState;
-use crate::rendering::default_theme;
+use crate::rendering::{default_theme, palette_css_variables};
 use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::state::Action;
+use gridcore_controller::theme::Palette;
 use gridcore_core::types::CellAddress;
 use leptos::prelude::*;
 use std::cell::RefCell;
@@ -14,15 +21,28 @@ use std::rc::Rc;
 
 #[cfg(feature = "perf")]
 use crate::components::metrics_display::{MetricsDisplay, MetricsToggle};
+#[cfg(feature = "debug")]
+use crate::components::script_console::ScriptConsole;
+#[cfg(feature = "debug")]
+use crate::debug::ActionLogOverlay;
 #[cfg(feature = "perf")]
 use crate::metrics_collector::MetricsSnapshot;
 
+#[cfg(any(feature = "demo", feature = "perf"))]
+use gridcore_demo::benchmark::results::suggestions::SuggestedAction;
 #[cfg(feature = "demo")]
-use crate::{DemoProgressBar, PerformanceOverlay};
+use gridcore_demo::benchmark::results::suggestions::Suggestion;
+
+#[cfg(feature = "demo")]
+use crate::{DemoProgressBar, PerformanceOverlay, TutorialCallout};
 #[cfg(feature = "demo")]
 use gridcore_demo::DemoController;
 #[cfg(feature = "demo")]
 use gridcore_demo::demo::performance::Metrics;
+#[cfg(feature = "demo")]
+use gridcore_demo::demo::tutorial::{Instruction, get_available_tutorials};
+#[cfg(feature = "demo")]
+use gridcore_demo::demo::typing::{Humanize, Playback};
 
 #[component]
 pub fn App() -> impl IntoView {
@@ -42,8 +62,9 @@ pub fn App() -> impl IntoView {
     }
 
     // Create the SpreadsheetController
-    let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+    let controller = Rc::new(RefCell::new(SpreadsheetController::builder().build()));
     let controller_stored = StoredValue::<_, LocalStorage>::new_local(controller.clone());
+    crate::host::attach(&controller);
 
     // Create viewport
     let theme = default_theme();
@@ -56,15 +77,43 @@ pub fn App() -> impl IntoView {
         .unwrap_or(1.0);
     let device_pixel_ratio_signal = Signal::from(device_pixel_ratio);
 
+    let show_minimap = RwSignal::new(false);
+    let show_templates = RwSignal::new(false);
+    let debug_mode = RwSignal::new(false);
+
+    // Buttons of performance suggestions change these settings
+    #[cfg(any(feature = "demo", feature = "perf"))]
+    let on_suggestion = Callback::new(move |action: SuggestedAction| match action {
+        SuggestedAction::HideMinimap => show_minimap.set(false),
+        SuggestedAction::ManualCalculation => dispatch_toolbar_action(
+            controller_stored,
+            Action::SetCalculationEnabled { enabled: false },
+        ),
+        SuggestedAction::CheckMemoryLeaks | SuggestedAction::ProfileScenario => {}
+    });
+
     // Demo feature state
     #[cfg(feature = "demo")]
     let demo_state = create_demo_state();
 
+    // Create reactive state that tracks controller changes
+    let reactive_state = ReactiveState::new(controller.clone());
+    let concerns = reactive_state.concerns;
+    let render_generation = reactive_state.render_generation;
+
+    // Follows `:set palette=` as well as the View menu
+    let palette = Memo::new(move |_| {
+        render_generation.get();
+        controller_stored.with_value(|ctrl| ctrl.borrow().palette())
+    });
+
     // Metrics feature state
     #[cfg(feature = "perf")]
     let show_metrics = RwSignal::new(false);
     #[cfg(feature = "perf")]
     let current_metrics = RwSignal::new(MetricsSnapshot::default());
+    #[cfg(feature = "perf")]
+    let live_suggestions = RwSignal::new(Vec::new());
 
     // Set up metrics collection interval
     #[cfg(feature = "perf")]
@@ -72,13 +121,28 @@ pub fn App() -> impl IntoView {
         use gloo_timers::callback::Interval;
 
         let metrics_signal = current_metrics;
+        let signal_stats = reactive_state.stats;
         // Collect metrics every 100ms
         let _interval = Interval::new(100, move || {
             if let Some(collector) = crate::perf::get_metrics_collector() {
-                let snapshot = {
+                let mut snapshot = {
                     let borrowed = collector.borrow();
                     borrowed.collect_snapshot()
                 };
+                if let Some(idle) =
+                    controller_stored.try_with_value(|ctrl| ctrl.borrow().idle_stats())
+                {
+                    snapshot.idle_items_processed = idle.processed;
+                    snapshot.idle_cancellations = idle.cancelled;
+                    snapshot.idle_time_ms = idle.time_ms;
+                }
+                let signals = signal_stats.get_value();
+                snapshot.signal_updates = signals.signal_updates;
+                snapshot.signal_updates_per_action = signals.updates_per_action();
+                let suggestions = collector.borrow().advise(&snapshot);
+                if live_suggestions.with_untracked(|current| *current != suggestions) {
+                    live_suggestions.set(suggestions);
+                }
                 collector.borrow().record_snapshot(snapshot.clone());
                 metrics_signal.set(snapshot);
             }
@@ -90,50 +154,70 @@ pub fn App() -> impl IntoView {
     // We'll initialize test data after ErrorDisplay is available
     let init_data = RwSignal::new(false);
 
-    // Create reactive state that tracks controller changes
-    let reactive_state = ReactiveState::new(controller.clone());
-
     // Provide unified app state through context
     provide_context(AppState {
         controller: controller_stored,
         viewport: viewport_stored,
         state_generation: reactive_state.generation,
         render_generation: reactive_state.render_generation,
+        concerns,
         device_pixel_ratio: device_pixel_ratio_signal,
     });
 
     // Create derived signals that automatically track state changes
-    let active_cell = Signal::derive(move || {
-        reactive_state.generation.get(); // Track changes
-        controller_stored.with_value(|ctrl| ctrl.borrow().cursor())
-    });
+    let active_cell = Signal::derive(move || concerns.cursor.get());
 
     let formula_bar_value = Signal::derive(move || {
-        reactive_state.generation.get(); // Track changes
+        concerns.editing.get(); // Follows the cursor and edits
         controller_stored.with_value(|ctrl| ctrl.borrow().get_formula_bar_value().to_string())
     });
 
     // The reactive state already subscribes to events, no need for separate listener
 
     let _current_mode = Signal::derive(move || {
-        reactive_state.generation.get(); // Track changes
+        concerns.editing.get();
         controller_stored.with_value(|ctrl| ctrl.borrow().get_mode().to_spreadsheet_mode())
     });
 
     let sheets = Memo::new(move |_| {
-        reactive_state.generation.get(); // Track changes
+        concerns.sheets.get();
         controller_stored.with_value(|ctrl| {
-            ctrl.borrow()
-                .get_sheets()
+            let ctrl = ctrl.borrow();
+            ctrl.get_workbook_health()
                 .into_iter()
-                .map(|(name, id)| Sheet { id, name })
+                .enumerate()
+                .map(|(id, (name, health))| Sheet {
+                    id,
+                    modified: ctrl.save_state().is_sheet_modified(&name),
+                    name,
+                    health,
+                })
                 .collect::<Vec<_>>()
         })
     });
 
+    // The page title marks a document with unsaved changes
+    Effect::new(move |_| {
+        concerns.sheets.get();
+        let modified = controller_stored.with_value(|ctrl| ctrl.borrow().is_document_modified());
+        if let Some(document) = web_sys::window().and_then(|window| window.document()) {
+            let title = document.title();
+            let saved_title = title.trim_start_matches("● ");
+            document.set_title(&if modified {
+                format!("● {}", saved_title)
+            } else {
+                saved_title.to_string()
+            });
+        }
+    });
+
     let active_sheet = Memo::new(move |_| {
-        reactive_state.generation.get(); // Track changes
-        0usize
+        let name = concerns.active_sheet.get();
+        sheets
+            .get()
+            .iter()
+            .find(|sheet| sheet.name == name)
+            .map_or(0, |sheet| sheet.id)
     });
 
     // Handle formula bar Enter key
@@ -150,6 +234,12 @@ pub fn App() -> impl IntoView {
         }
     };
 
+    // Perform the requests of FETCH formulas whenever the controller queues new ones
+    Effect::new(move |_| {
+        reactive_state.generation.get(); // Track changes
+        controller_stored.with_value(crate::external_fetch::dispatch_external_requests);
+    });
+
     // Initialize test data with error handling after ErrorDisplay is mounted
     Effect::new(move |_| {
         if !init_data.get() {
@@ -183,19 +273,69 @@ pub fn App() -> impl IntoView {
     });
 
     view! {
-        <div class="spreadsheet-app">
+        <div class="spreadsheet-app" style=move || palette_css_variables(palette.get())>
             <div class="top-toolbar">
                 <div class="toolbar-row">
+                    <button
+                        on:click=move |_| show_templates.set(true)
+                        disabled=move || !concerns.capabilities.get().edit
+                    >
+                        "New…"
+                    </button>
                     <label style="margin-left: 20px;">
                         <input
                             type="checkbox"
                             on:change=move |ev| {
                                 let checked = event_target_checked(&ev);
                                 crate::debug::set_debug_mode(checked);
+                                debug_mode.set(checked);
                             }
                         />
                         " Debug Mode"
                     </label>
+                    <label style="margin-left: 10px;">
+                        <input
+                            type="checkbox"
+                            prop:checked=move || show_minimap.get()
+                            on:change=move |ev| show_minimap.set(event_target_checked(&ev))
+                        />
+                        " Minimap"
+                    </label>
+                    <label style="margin-left: 10px;">
+                        "View: "
+                        <select
+                            prop:value=move || palette.get().name()
+                            on:change=move |ev| {
+                                if let Ok(palette) = event_target_value(&ev).parse::<Palette>() {
+                                    dispatch_toolbar_action(controller_stored, Action::SetPalette { palette });
+                                }
+                            }
+                        >
+                            {Palette::ALL
+                                .into_iter()
+                                .map(|palette| view! { <option value=palette.name()>{palette.label()}</option> })
+                                .collect_view()}
+                        </select>
+                    </label>
+
+                    // Formula auditing
+                    <div style="display: inline-block; margin-left: 20px; border-left: 1px solid #ccc; padding-left: 20px;">
+                        <button on:click=move |_| dispatch_toolbar_action(controller_stored, Action::TracePrecedents)>
+                            "Trace Precedents"
+                        </button>
+                        <button
+                            style="margin-left: 5px;"
+                            on:click=move |_| dispatch_toolbar_action(controller_stored, Action::TraceDependents)
+                        >
+                            "Trace Dependents"
+                        </button>
+                        <button
+                            style="margin-left: 5px;"
+                            on:click=move |_| dispatch_toolbar_action(controller_stored, Action::ClearTraceArrows)
+                        >
+                            "Remove Arrows"
+                        </button>
+                    </div>
 
                     // Metrics toggle button (only when perf feature is enabled)
                     {
@@ -241,6 +381,7 @@ pub fn App() -> impl IntoView {
                         class="formula-input"
                         placeholder="Enter formula or value"
                         value=move || formula_bar_value.get()
+                        readonly=move || !concerns.capabilities.get().edit
                         on:input=move |ev| {
                             let new_value = event_target_value(&ev);
                             controller.borrow_mut().dispatch_action(
@@ -256,6 +397,12 @@ pub fn App() -> impl IntoView {
 
             <div class="main-content">
                 <GridContainer />
+                <Show when=move || show_minimap.get()>
+                    <Minimap />
+                </Show>
+                <WatchPanel />
+                <LintPanel />
+                <LoadReportPanel />
             </div>
 
             <div class="bottom-toolbar">
@@ -269,7 +416,12 @@ pub fn App() -> impl IntoView {
                 {
                     #[cfg(feature = "demo")]
                     {
-                        create_demo_overlay(demo_state.clone())
+                        create_demo_overlay(
+                            demo_state.clone(),
+                            controller_stored,
+                            viewport_stored,
+                            on_suggestion,
+                        )
                     }
                     #[cfg(not(feature = "demo"))]
                     {
@@ -293,6 +445,23 @@ pub fn App() -> impl IntoView {
             // Add error display overlay
             <ErrorDisplay />
 
+            <TemplatePicker open=show_templates />
+
+            // Script console and action log (only when debug feature is enabled)
+            {
+                #[cfg(feature = "debug")]
+                {
+                    view! {
+                        <ScriptConsole visible=Signal::from(debug_mode) />
+                        <ActionLogOverlay visible=Signal::from(debug_mode) />
+                    }
+                }
+                #[cfg(not(feature = "debug"))]
+                {
+                    view! { <span></span> }
+                }
+            }
+
             // Metrics display overlay (only when perf feature is enabled)
             {
                 #[cfg(feature = "perf")]
@@ -301,6 +470,8 @@ pub fn App() -> impl IntoView {
                         <MetricsDisplay
                             metrics=Signal::from(current_metrics)
                             visible=Signal::from(show_metrics)
+                            suggestions=Signal::from(live_suggestions)
+                            on_action=on_suggestion
                         />
                     }
                 }
@@ -313,6 +484,17 @@ pub fn App() -> impl IntoView {
     }
 }
 
+fn dispatch_toolbar_action(
+    controller_stored: StoredValue<Rc<RefCell<SpreadsheetController>>, LocalStorage>,
+    action: Action,
+) {
+    controller_stored.with_value(|ctrl| {
+        if let Err(e) = ctrl.borrow_mut().dispatch_action(action) {
+            leptos::logging::log!("Error dispatching toolbar action: {}", e);
+        }
+    });
+}
+
 // Demo-specific state and UI functions
 #[cfg(feature = "demo")]
 #[derive(Clone)]
@@ -324,9 +506,14 @@ struct DemoState {
     demo_metrics: RwSignal<Metrics>,
     demo_current_step: RwSignal<usize>,
     demo_total_steps: RwSignal<usize>,
+    demo_failed_steps: RwSignal<usize>,
+    /// What the running tutorial asks the user to do next
+    demo_instruction: RwSignal<Option<Instruction>>,
     show_performance: RwSignal<bool>,
     benchmark_running: RwSignal<bool>,
     benchmark_results: RwSignal<String>,
+    /// Suggestions of the last benchmark run, in the performance overlay
+    benchmark_suggestions: RwSignal<Vec<Suggestion>>,
     show_benchmark_results: RwSignal<bool>,
     demo_interval_handle:
         StoredValue<Option<leptos::leptos_dom::helpers::IntervalHandle>, LocalStorage>,
@@ -344,9 +531,12 @@ fn create_demo_state() -> DemoState {
         demo_metrics: RwSignal::new(Metrics::default()),
         demo_current_step: RwSignal::new(0usize),
         demo_total_steps: RwSignal::new(0usize),
+        demo_failed_steps: RwSignal::new(0usize),
+        demo_instruction: RwSignal::new(None),
         show_performance: RwSignal::new(false),
         benchmark_running: RwSignal::new(false),
         benchmark_results: RwSignal::new(String::new()),
+        benchmark_suggestions: RwSignal::new(Vec::new()),
         show_benchmark_results: RwSignal::new(false),
         demo_interval_handle: StoredValue::new_local(None),
         fps_interval_handle: StoredValue::new_local(None),
@@ -403,6 +593,12 @@ fn create_demo_toolbar(
                                 <option value="Fill Operations">"Fill Operations"</option>
                                 <option value="Performance Stress Test">"Performance Stress Test"</option>
                                 <option value="Error Handling">"Error Handling"</option>
+                                <optgroup label="Tutorials">
+                                    {get_available_tutorials()
+                                        .into_iter()
+                                        .map(|name| view! { <option value=name.clone()>{name.clone()}</option> })
+                                        .collect_view()}
+                                </optgroup>
                             </select>
 
                             // Start/Stop button
@@ -448,7 +644,47 @@ fn create_demo_toolbar(
 }
 
 #[cfg(feature = "demo")]
-fn create_demo_overlay(demo_state: DemoState) -> impl IntoView {
+fn create_demo_overlay(
+    demo_state: DemoState,
+    controller_stored: StoredValue<Rc<RefCell<SpreadsheetController>>, LocalStorage>,
+    viewport_stored: StoredValue<Rc<RefCell<Viewport>>, LocalStorage>,
+    on_suggestion: Callback<SuggestedAction>,
+) -> impl IntoView {
+    let (state_generation, _) = crate::context::use_reactive_signals();
+    // Just below the highlighted cell, in page coordinates
+    let callout_position = Signal::derive(move || {
+        state_generation.get();
+        let cell = controller_stored.with_value(|ctrl| ctrl.borrow().highlighted_cell());
+        let canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.query_selector(".grid-canvas").ok().flatten())
+            .map(|canvas| canvas.get_bounding_client_rect());
+        match (cell, canvas) {
+            (Some(cell), Some(canvas)) => controller_stored.with_value(|ctrl| {
+                let ctrl = ctrl.borrow();
+                let config = ctrl.get_config();
+                let pos = viewport_stored.with_value(|vp| vp.borrow().get_cell_position(&cell));
+                (
+                    canvas.left() + config.row_header_width + pos.x,
+                    canvas.top() + config.column_header_height + pos.y + pos.height + 8.0,
+                )
+            }),
+            _ => (20.0, 120.0),
+        }
+    });
+    let skip_state = demo_state.clone();
+    let on_skip = Callback::new(move |_| {
+        skip_state.demo_controller.with_value(|demo| {
+            controller_stored.with_value(|ctrl| demo.borrow_mut().skip_step(ctrl.clone()));
+        });
+    });
+    let restart_state = demo_state.clone();
+    let on_restart = Callback::new(move |_| {
+        restart_state.demo_controller.with_value(|demo| {
+            controller_stored.with_value(|ctrl| demo.borrow_mut().restart_step(ctrl.clone()));
+        });
+    });
+
     view! {
         <>
             <DemoProgressBar
@@ -456,10 +692,19 @@ fn create_demo_overlay(demo_state: DemoState) -> impl IntoView {
                 total_steps=Signal::from(demo_state.demo_total_steps)
                 scenario_name=Signal::from(demo_state.demo_scenario)
                 is_running=Signal::from(demo_state.demo_running)
+                failed_steps=Signal::from(demo_state.demo_failed_steps)
             />
             <PerformanceOverlay
                 metrics=Signal::from(demo_state.demo_metrics)
                 visible=Signal::from(demo_state.show_performance)
+                suggestions=Signal::from(demo_state.benchmark_suggestions)
+                on_action=on_suggestion
+            />
+            <TutorialCallout
+                instruction=Signal::from(demo_state.demo_instruction)
+                position=callout_position
+                on_skip=on_skip
+                on_restart=on_restart
             />
         </>
     }
@@ -506,6 +751,8 @@ fn handle_demo_toggle(
             demo.stop_demo();
             demo_state.demo_running.set(false);
 
+            demo_state.demo_instruction.set(None);
+
             // Clear intervals
             demo_state.demo_interval_handle.update_value(|handle| {
                 if let Some(h) = handle.take() {
@@ -519,17 +766,30 @@ fn handle_demo_toggle(
             });
         } else {
             // Start the demo
+            let tutorial = get_available_tutorials().contains(&scenario);
             controller_stored.with_value(|ctrl| {
-                match demo.start_demo(&scenario, ctrl.clone()) {
+                let started = if tutorial {
+                    demo.start_tutorial(&scenario, ctrl.clone())
+                } else {
+                    // Type into cells as a person would
+                    demo.set_playback(Playback::Humanized(Humanize::default()));
+                    demo.start_demo(&scenario, ctrl.clone())
+                };
+                match started {
                     Ok(_) => {
                         demo_state.demo_running.set(true);
                         demo_state.demo_current_step.set(demo.get_current_step());
                         demo_state.demo_total_steps.set(demo.get_total_steps());
+                        demo_state
+                            .demo_failed_steps
+                            .set(demo.get_failed_steps().len());
                         let metrics = demo.get_performance_metrics();
                         demo_state.demo_metrics.set(metrics);
 
-                        // Set up demo runner interval
-                        // Note: In a real implementation, you'd set up the intervals here
+                        if tutorial {
+                            demo_state.demo_instruction.set(demo.current_instruction());
+                        }
+                        start_demo_interval(demo_state.clone(), controller_stored, tutorial);
                     }
                     Err(e) => {
                         leptos::logging::log!("Failed to start demo: {}", e);
@@ -540,6 +800,48 @@ fn handle_demo_toggle(
     });
 }
 
+/// Re-check a tutorial's gate a few times a second, or play an automated
+/// scenario's keystrokes back as they fall due, until it finishes
+#[cfg(feature = "demo")]
+fn start_demo_interval(
+    demo_state: DemoState,
+    controller_stored: StoredValue<Rc<RefCell<SpreadsheetController>>, LocalStorage>,
+    tutorial: bool,
+) {
+    let tick_state = demo_state.clone();
+    let period = if tutorial { 250 } else { 20 };
+    let handle = leptos::leptos_dom::helpers::set_interval_with_handle(
+        move || {
+            let running = tick_state.demo_controller.with_value(|demo| {
+                let mut demo = demo.borrow_mut();
+                controller_stored.with_value(|ctrl| {
+                    if tutorial {
+                        demo.step_forward(ctrl.clone());
+                    } else {
+                        demo.tick(ctrl.clone());
+                    }
+                });
+                tick_state.demo_current_step.set(demo.get_current_step());
+                tick_state.demo_instruction.set(demo.current_instruction());
+                demo.is_running()
+            });
+            if !running {
+                tick_state.demo_running.set(false);
+                tick_state.demo_interval_handle.update_value(|handle| {
+                    if let Some(h) = handle.take() {
+                        h.clear();
+                    }
+                });
+            }
+        },
+        std::time::Duration::from_millis(period),
+    );
+    match handle {
+        Ok(handle) => demo_state.demo_interval_handle.set_value(Some(handle)),
+        Err(e) => leptos::logging::log!("Failed to start demo: {:?}", e),
+    }
+}
+
 #[cfg(feature = "demo")]
 fn run_quick_benchmark(
     demo_state: DemoState,
@@ -555,6 +857,9 @@ fn run_quick_benchmark(
             match demo.run_quick_benchmark(ctrl.clone()) {
                 Ok(results) => {
                     demo_state.benchmark_results.set(results);
+                    demo_state
+                        .benchmark_suggestions
+                        .set(demo.suggestions().to_vec());
                     demo_state.show_benchmark_results.set(true);
                 }
                 Err(e) => {
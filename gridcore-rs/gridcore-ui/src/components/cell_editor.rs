@@ -1,11 +1,34 @@
-use crate::context::use_controller;
+use crate::context::{use_concerns, use_controller};
+use gridcore_controller::behaviors::formula_preview::FormulaPreview;
 use gridcore_controller::state::actions::Action;
 use gridcore_core::types::CellAddress;
 use leptos::html::Textarea;
 use leptos::prelude::*;
+use std::time::Duration;
 use wasm_bindgen::JsCast;
 use web_sys::KeyboardEvent;
 
+/// Pause in typing before the formula preview is recomputed
+const PREVIEW_DELAY: Duration = Duration::from_millis(150);
+
+/// Height of one line of editor text, in pixels
+const EDITOR_LINE_HEIGHT: f64 = 17.0;
+
+/// Lines the editor grows to before it scrolls instead
+const MAX_EDITOR_LINES: usize = 8;
+
+/// Byte offset into `text` of a UTF-16 position reported by the browser
+fn byte_offset(text: &str, position: usize) -> usize {
+    let mut units = 0;
+    for (offset, c) in text.char_indices() {
+        if units >= position {
+            return offset;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
 #[component]
 pub fn CellEditor(
     active_cell: Memo<CellAddress>,
@@ -62,6 +85,13 @@ pub fn CellEditor(
         }
     });
 
+    // The cell was written programmatically while this editor was open
+    let editing_generation = use_concerns().editing;
+    let edit_stale = Signal::derive(move || {
+        editing_generation.get(); // Conflicting writes are editing events
+        controller_stored.with_value(|ctrl| ctrl.borrow().is_edit_stale())
+    });
+
     // Create a derived signal for the current editing value from controller
     let current_editing_value = Signal::derive(move || {
         controller_stored.with_value(|ctrl| {
@@ -80,6 +110,39 @@ pub fn CellEditor(
         })
     });
 
+    // Preview the formula's result, or the part selected with the mouse,
+    // once typing pauses
+    let (formula_preview, set_formula_preview) = signal::<Option<FormulaPreview>>(None);
+    let (mouse_selection, set_mouse_selection) = signal::<Option<(usize, usize)>>(None);
+    let preview_timer = StoredValue::new(None::<TimeoutHandle>);
+    Effect::new(move |_| {
+        editing_generation.get(); // Visual selections move without the value changing
+        let value = current_editing_value.get();
+        let selection = mouse_selection.get();
+        if let Some(timer) = preview_timer.get_value() {
+            timer.clear();
+        }
+        if !editing_mode.get_untracked() || !value.starts_with('=') {
+            set_formula_preview.set(None);
+            return;
+        }
+        let timer = set_timeout_with_handle(
+            move || {
+                let preview = controller_stored.with_value(|ctrl| {
+                    let ctrl = ctrl.borrow();
+                    match selection {
+                        Some((start, end)) => ctrl.preview_formula_selection(start, end),
+                        None => ctrl.formula_preview(),
+                    }
+                });
+                set_formula_preview.set(preview);
+            },
+            PREVIEW_DELAY,
+        )
+        .ok();
+        preview_timer.set_value(timer);
+    });
+
     // Handle formula autocomplete using pure functions
     Effect::new(move |_| {
         let value = current_editing_value.get();
@@ -110,6 +173,30 @@ pub fn CellEditor(
         }
     });
 
+    // Recent entries listed by Ctrl+Down, chosen with the arrows and Enter
+    let entry_picker = Signal::derive(move || {
+        editing_generation.get(); // Opening and moving through the list are editing events
+        controller_stored.with_value(|ctrl| ctrl.borrow().entry_picker().cloned())
+    });
+
+    // A formula strict mode refused to commit, with the offending text
+    let errors_generation = use_concerns().errors;
+    let blocked_entry = Signal::derive(move || {
+        errors_generation.get(); // Each refusal is reported on the status line
+        editing_generation.get();
+        controller_stored.with_value(|ctrl| ctrl.borrow().blocked_entry().cloned())
+    });
+
+    // Warn as a formula nears the length limit
+    let length_warning = Memo::new(move |_| {
+        current_editing_value.with(|value| {
+            controller_stored.with_value(|ctrl| ctrl.borrow().formula_length_warning(value))
+        })
+    });
+
+    let editor_lines =
+        Memo::new(move |_| current_editing_value.with(|value| value.split('\n').count()));
+
     view! {
         <Show when=move || editing_mode.get()>
             <div
@@ -117,6 +204,9 @@ pub fn CellEditor(
                 tabindex="-1"
                 style=move || {
                     let (x, y, width, height) = cell_position.get();
+                    // Grow down with each line break, up to the cap
+                    let lines = editor_lines.get().min(MAX_EDITOR_LINES);
+                    let height = height.max(lines as f64 * EDITOR_LINE_HEIGHT + 8.0);
                     format!(
                         "position: absolute; left: {}px; top: {}px; width: {}px; height: {}px; z-index: 1000;",
                         x, y, width, height
@@ -125,7 +215,22 @@ pub fn CellEditor(
             >
                 <textarea
                     node_ref=input_ref
+                    on:mouseup=move |_| {
+                        let selection = input_ref.get().and_then(|input| {
+                            let start = input.selection_start().ok()??;
+                            let end = input.selection_end().ok()??;
+                            let value = input.value();
+                            (start != end).then(|| {
+                                (
+                                    byte_offset(&value, start as usize),
+                                    byte_offset(&value, end as usize),
+                                )
+                            })
+                        });
+                        set_mouse_selection.set(selection);
+                    }
                     on:keydown=move |ev: KeyboardEvent| {
+                        set_mouse_selection.set(None);
                         let key = ev.key();
                         let shift = ev.shift_key();
                         let ctrl = ev.ctrl_key();
@@ -208,9 +313,93 @@ pub fn CellEditor(
                         });
                     }
                     prop:value=move || current_editing_value.get()
-                    style="width: 100%; height: 100%; border: 2px solid #4285f4; padding: 2px 4px; font-family: monospace; font-size: 13px; outline: none; resize: none; overflow: hidden;"
+                    style=move || {
+                        let overflow = if editor_lines.get() > MAX_EDITOR_LINES { "auto" } else { "hidden" };
+                        format!(
+                            "width: 100%; height: 100%; border: 2px solid #4285f4; padding: 2px 4px; font-family: monospace; font-size: 13px; line-height: {}px; outline: none; resize: none; overflow-y: {}; overflow-x: hidden;",
+                            EDITOR_LINE_HEIGHT, overflow
+                        )
+                    }
                 />
 
+                <Show when=move || edit_stale.get()>
+                    <div
+                        class="cell-editor-stale-badge"
+                        title="This cell was changed while you were editing it"
+                    >
+                        "value changed underneath"
+                    </div>
+                </Show>
+
+                {move || {
+                    length_warning
+                        .get()
+                        .map(|warning| view! { <div class="cell-editor-length-warning">{warning}</div> })
+                }}
+
+                {move || {
+                    blocked_entry
+                        .get()
+                        .filter(|_| editing_mode.get())
+                        .map(|blocked| {
+                            let (before, rest) = blocked.value.split_at(blocked.span.start);
+                            let (marked, after) = rest.split_at(blocked.span.len());
+                            // Missing text is marked where it should be
+                            let marked = if marked.is_empty() { " " } else { marked };
+                            view! {
+                                <div class="cell-editor-parse-error">
+                                    <span class="cell-editor-parse-message">{blocked.message.clone()}</span>
+                                    {before.to_string()}
+                                    <mark>{marked.to_string()}</mark>
+                                    {after.to_string()}
+                                </div>
+                            }
+                        })
+                }}
+
+                {move || {
+                    formula_preview
+                        .get()
+                        .map(|preview| {
+                            let class = if preview.is_error {
+                                "cell-editor-preview error"
+                            } else {
+                                "cell-editor-preview"
+                            };
+                            let label = if preview.span.is_some() { "selection = " } else { "= " };
+                            view! { <div class=class>{label}{preview.text}</div> }
+                        })
+                }}
+
+                {move || {
+                    entry_picker
+                        .get()
+                        .map(|picker| {
+                            let selected = picker.selected;
+                            view! {
+                                <div
+                                    class="entry-picker-dropdown"
+                                    style="position: absolute; top: 100%; left: 0; min-width: 100%; background: white; border: 1px solid #ccc; border-radius: 4px; box-shadow: 0 2px 4px rgba(0,0,0,0.1); max-height: 150px; overflow-y: auto; z-index: 1002;"
+                                >
+                                    {picker
+                                        .candidates
+                                        .into_iter()
+                                        .enumerate()
+                                        .map(|(idx, candidate)| {
+                                            let background = if idx == selected { "#e3f2fd" } else { "white" };
+                                            view! {
+                                                <div style=format!(
+                                                    "padding: 4px 8px; font-size: 12px; white-space: nowrap; background: {};",
+                                                    background
+                                                )>{candidate}</div>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </div>
+                            }
+                        })
+                }}
+
                 <Show when=move || !suggestions.get().is_empty()>
                     <div
                         class="autocomplete-dropdown"
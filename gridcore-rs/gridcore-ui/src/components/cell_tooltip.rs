@@ -0,0 +1,77 @@
+use gridcore_controller::controller::CellInspection;
+use leptos::prelude::*;
+
+/// Tooltip beside the pointer describing the hovered cell, drawn at `x`,
+/// `y` within the grid
+#[component]
+pub fn CellTooltip(x: f64, y: f64, inspection: CellInspection) -> impl IntoView {
+    let CellInspection {
+        address,
+        display,
+        input,
+        is_formula,
+        clipped,
+        error,
+        findings,
+        format,
+        style,
+        stale,
+    } = inspection;
+
+    // A formula is shown with its result; a value only when the cell
+    // cuts it off
+    let content = if is_formula {
+        Some(format!("{} → {}", input, display))
+    } else if clipped {
+        Some(display)
+    } else {
+        None
+    };
+    let details = [
+        format.map(|format| format!("Format: {}", format)),
+        style.map(|style| format!("Style: {}", style)),
+        stale.then(|| "Waiting for recalculation".to_string()),
+    ];
+
+    view! {
+        <div class="cell-tooltip" style=format!("left: {}px; top: {}px;", x + 12.0, y + 16.0)>
+            <div class="cell-tooltip-address">{address.to_string()}</div>
+            {content.map(|content| view! { <div class="cell-tooltip-content">{content}</div> })}
+            {error
+                .map(|error| {
+                    view! {
+                        <div class="cell-tooltip-error">
+                            {format!("{} {}", error.code, error.message)}
+                            {error
+                                .cycle
+                                .map(|cycle| {
+                                    view! { <div class="cell-tooltip-cycle">{cycle}</div> }
+                                })}
+                        </div>
+                    }
+                })}
+            {findings
+                .into_iter()
+                .map(|finding| {
+                    view! {
+                        <div class="cell-tooltip-finding">
+                            {finding.message}
+                            {finding
+                                .suggestion
+                                .map(|suggestion| {
+                                    view! {
+                                        <div class="cell-tooltip-suggestion">{suggestion}</div>
+                                    }
+                                })}
+                        </div>
+                    }
+                })
+                .collect_view()}
+            {details
+                .into_iter()
+                .flatten()
+                .map(|detail| view! { <div class="cell-tooltip-detail">{detail}</div> })
+                .collect_view()}
+        </div>
+    }
+}
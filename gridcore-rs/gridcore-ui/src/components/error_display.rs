@@ -1,4 +1,4 @@
-use crate::context::{use_controller, use_state_generation};
+use crate::context::{use_concerns, use_controller};
 use leptos::prelude::*;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -25,12 +25,12 @@ pub struct ErrorContext {
 pub fn ErrorDisplay() -> impl IntoView {
     // Get controller and reactive state from context
     let controller_stored = use_controller();
-    let state_generation = use_state_generation();
+    let errors_generation = use_concerns().errors;
 
     // Derive errors from controller's ErrorManager
     let errors = Signal::derive(move || {
-        // Track state changes to update when errors change
-        state_generation.get();
+        // Track changes to the error list
+        errors_generation.get();
 
         controller_stored.with_value(|ctrl| {
             let ctrl_borrow = ctrl.borrow();
@@ -1,27 +1,47 @@
-use crate::context::{use_device_pixel_ratio, use_reactive_signals, use_viewport};
+use crate::context::{
+    use_concerns, use_controller, use_device_pixel_ratio, use_render_generation, use_viewport,
+};
+use gridcore_controller::theme::Palette;
 use leptos::html::Canvas;
 use leptos::prelude::*;
 
-use crate::rendering::{CanvasRenderer, default_theme};
+use crate::components::grid::grid_cells::cell_text_measurer;
+use crate::idle_work::schedule_idle_work;
+use crate::rendering::{CanvasRenderer, GridTheme, default_theme};
 
 #[component]
 pub fn GridCanvas() -> impl IntoView {
     // Get viewport and reactive signals from context
+    let controller_stored = use_controller();
     let viewport_stored = use_viewport();
-    let (state_generation, render_generation) = use_reactive_signals();
+    let render_generation = use_render_generation();
+    let concerns = use_concerns();
     let device_pixel_ratio_signal = use_device_pixel_ratio();
     let canvas_ref = NodeRef::<Canvas>::new();
     let (canvas_dimensions, set_canvas_dimensions) = signal((0.0, 0.0));
 
     let theme = default_theme();
-    let renderer = CanvasRenderer::new(theme);
+    // Cell texts are measured with the cell font on a canvas of their own
+    controller_stored.with_value(|ctrl| {
+        ctrl.borrow_mut()
+            .set_text_measurer(cell_text_measurer(&theme))
+    });
+    let mut renderer = CanvasRenderer::new(theme);
+    let mut palette = Palette::default();
 
     // Set up canvas rendering effect - only for DOM updates
     Effect::new(move |_| {
         render_generation.get(); // Track render changes
-        state_generation.get(); // Also track state changes
+        concerns.visible_data.get(); // Also track writes to visible cells
         let device_pixel_ratio = device_pixel_ratio_signal.get();
 
+        // Changing the palette bumps the render generation
+        let wanted = controller_stored.with_value(|ctrl| ctrl.borrow().palette());
+        if wanted != palette {
+            palette = wanted;
+            renderer = CanvasRenderer::new(GridTheme::with_palette(palette));
+        }
+
         if let Some(canvas) = canvas_ref.get() {
             let canvas_elem: &web_sys::HtmlCanvasElement = &canvas;
 
@@ -44,6 +64,11 @@ pub fn GridCanvas() -> impl IntoView {
 
             // Render the grid - simply pass the canvas element
             renderer.render(canvas_elem);
+
+            // Refill the cell cache around the viewport and warm text widths
+            // once the frame is drawn, so the next scroll step is served
+            // without facade lookups
+            schedule_idle_work(controller_stored);
         }
     });
 
@@ -1,11 +1,26 @@
-use gridcore_core::types::{CellAddress, CellValue};
+use gridcore_controller::controller::{
+    DisplayCell, SpreadsheetController, TextMeasurer, ViewportBounds,
+};
+use gridcore_core::sparkline::{Sparkline, SparklineKind};
+use gridcore_core::types::CellAddress;
 use leptos::prelude::{GetUntracked, WithValue};
+use std::borrow::Cow;
+use std::collections::HashSet;
 use wasm_bindgen::JsCast;
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
 
 use crate::context::{use_controller, use_device_pixel_ratio, use_viewport};
 use crate::rendering::GridTheme;
 
+/// Distance between the baselines of wrapped lines, relative to the font size
+const LINE_SPACING: f64 = 1.3;
+
+/// Space kept free around a sparkline inside its cell
+const SPARKLINE_INSET: f64 = 3.0;
+
+/// Space kept free above and below a data bar inside its cell
+const DATA_BAR_INSET: f64 = 2.0;
+
 #[derive(Clone)]
 pub struct GridCells {
     theme: GridTheme,
@@ -34,10 +49,23 @@ impl GridCells {
                 let viewport = vp.borrow();
                 let bounds = viewport.get_visible_bounds();
                 let ctrl_borrow = ctrl.borrow();
-                let facade = ctrl_borrow.facade();
+                let cells = ctrl_borrow.get_display_list(&bounds);
                 let config = ctrl_borrow.get_config();
 
-                self.render_cell_content(&ctx, &viewport, &bounds, facade, config);
+                // Every fill goes down before any text, so text running
+                // into a neighbour is not painted over by its fill
+                self.render_conditional_fills(&ctx, &viewport, &cells, config);
+                self.render_cell_content(&ctx, &viewport, &ctrl_borrow, &cells, config);
+
+                let flagged: Vec<CellAddress> = ctrl_borrow
+                    .get_lint_warnings()
+                    .findings()
+                    .iter()
+                    .map(|finding| finding.address)
+                    .filter(|address| in_bounds(address, &bounds))
+                    .collect();
+                self.render_lint_markers(&ctx, &viewport, &flagged, config);
+                self.render_stale_markers(&ctx, &viewport, &cells, config);
             });
         });
 
@@ -55,46 +83,309 @@ impl GridCells {
         &self,
         ctx: &CanvasRenderingContext2d,
         viewport: &crate::components::viewport::Viewport,
-        bounds: &gridcore_controller::controller::ViewportBounds,
-        facade: &gridcore_core::SpreadsheetFacade,
+        ctrl: &SpreadsheetController,
+        cells: &[DisplayCell],
         config: &gridcore_controller::controller::GridConfiguration,
     ) {
+        let occupied: HashSet<CellAddress> = cells.iter().map(|cell| cell.address).collect();
+
         ctx.set_fill_style_str(&self.theme.cell_text_color);
         ctx.set_font(&format!(
             "{}px {}",
             self.theme.cell_font_size, self.theme.cell_font_family
         ));
 
-        for row in bounds.start_row..=bounds.end_row {
-            for col in bounds.start_col..=bounds.end_col {
-                let cell_address = CellAddress::new(col as u32, row as u32);
+        for cell in cells {
+            let row = cell.address.row as usize;
+            let (x, y) = viewport.point_of_cell(&cell.address);
+            let x = x + config.row_header_width;
+            let y = y + config.column_header_height;
+            let height = viewport.get_row_height(row);
 
-                if let Some(cell) = facade.get_cell(&cell_address) {
-                    let display_value = cell.get_display_value();
-                    let value_str = display_value.to_string();
+            if let Some(sparkline) = &cell.sparkline {
+                let width = viewport.get_column_width(cell.address.col as usize);
+                self.render_sparkline(ctx, sparkline, x, y, width, height);
+                continue;
+            }
 
-                    let x = viewport.get_column_x(col) - viewport.get_scroll_position().x
-                        + config.row_header_width;
-                    let y = viewport.get_row_y(row) - viewport.get_scroll_position().y
-                        + config.column_header_height;
-                    let height = viewport.get_row_height(row);
+            if cell.is_error {
+                ctx.set_fill_style_str(&self.theme.error_text_color);
+            } else {
+                ctx.set_fill_style_str(&self.theme.cell_text_color);
+            }
 
-                    let is_error = matches!(display_value, CellValue::Error(_));
-                    if is_error {
-                        ctx.set_fill_style_str("#ff4444");
-                    } else {
-                        ctx.set_fill_style_str(&self.theme.cell_text_color);
-                    }
+            let width = viewport.get_column_width(cell.address.col as usize);
+            let text_x = if cell.is_boolean {
+                ctx.set_text_align("center");
+                x + width / 2.0
+            } else {
+                x + self.theme.cell_padding_left
+            };
+            // A single line sits in the middle of the row; wrapped lines
+            // run down from the top and are cut at the bottom edge
+            let lines = cell.lines();
+            let multi_line = lines.len() > 1;
+            let line_height = self.theme.cell_font_size * LINE_SPACING;
+            let text_y = if multi_line {
+                y + line_height
+            } else {
+                y + height / 2.0 + 4.0
+            };
+
+            // Text running into an occupied neighbour is cut at the cell edge
+            let next = CellAddress::new(cell.address.col + 1, cell.address.row);
+            let clip = multi_line
+                || (occupied.contains(&next)
+                    && lines
+                        .first()
+                        .and_then(|line| ctrl.text_width(line))
+                        .is_some_and(|text_width| {
+                            text_width > width - self.theme.cell_padding_left
+                        }));
+            if clip {
+                ctx.save();
+                ctx.begin_path();
+                ctx.rect(x, y, width, height);
+                ctx.clip();
+            }
+            for (i, line) in lines.iter().enumerate() {
+                let line_y = text_y + i as f64 * line_height;
+                if line_y - line_height > y + height {
+                    break;
+                }
+                ctx.fill_text(line, text_x, line_y).ok();
+            }
+            if clip {
+                ctx.restore();
+            }
+            if cell.is_boolean {
+                ctx.set_text_align("left");
+            }
+
+            if cell.is_error {
+                ctx.set_fill_style_str(&self.theme.cell_text_color);
+            }
+        }
+    }
 
-                    let text_x = x + self.theme.cell_padding_left;
-                    let text_y = y + height / 2.0 + 4.0;
-                    ctx.fill_text(&value_str, text_x, text_y).ok();
+    /// Color-scale backgrounds and data bars, inside the grid lines
+    fn render_conditional_fills(
+        &self,
+        ctx: &CanvasRenderingContext2d,
+        viewport: &crate::components::viewport::Viewport,
+        cells: &[DisplayCell],
+        config: &gridcore_controller::controller::GridConfiguration,
+    ) {
+        for cell in cells {
+            let Some(fill) = &cell.conditional else {
+                continue;
+            };
+            let (x, y) = viewport.point_of_cell(&cell.address);
+            let x = x + config.row_header_width + 1.0;
+            let y = y + config.column_header_height + 1.0;
+            let width = viewport.get_column_width(cell.address.col as usize) - 1.0;
+            let height = viewport.get_row_height(cell.address.row as usize) - 1.0;
 
-                    if is_error {
-                        ctx.set_fill_style_str(&self.theme.cell_text_color);
+            if let Some(background) = &fill.background {
+                ctx.set_fill_style_str(&self.theme.rule_color(background));
+                ctx.fill_rect(x, y, width, height);
+            }
+            let Some(bar) = &fill.data_bar else {
+                continue;
+            };
+            let bar_height = height - 2.0 * DATA_BAR_INSET;
+            if bar_height <= 0.0 {
+                continue;
+            }
+            let top = y + DATA_BAR_INSET;
+            ctx.set_fill_style_str(&self.theme.rule_color(&bar.color));
+            ctx.fill_rect(
+                x + bar.start * width,
+                top,
+                (bar.end - bar.start) * width,
+                bar_height,
+            );
+            if let Some(axis) = bar.axis {
+                // A dotted line at zero, where negative bars turn back
+                let axis_x = (x + axis * width).round() + 0.5;
+                ctx.save();
+                ctx.set_stroke_style_str(&self.theme.cell_text_color);
+                ctx.set_line_width(1.0);
+                let dash = js_sys::Array::of2(&2.0.into(), &2.0.into());
+                ctx.set_line_dash(&dash).ok();
+                ctx.begin_path();
+                ctx.move_to(axis_x, top);
+                ctx.line_to(axis_x, top + bar_height);
+                ctx.stroke();
+                ctx.restore();
+            }
+        }
+    }
+
+    /// Draw `sparkline` scaled to the cell at (`x`, `y`), kept inside it
+    fn render_sparkline(
+        &self,
+        ctx: &CanvasRenderingContext2d,
+        sparkline: &Sparkline,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) {
+        let count = sparkline.points.len();
+        if sparkline.is_blank() || width <= 2.0 * SPARKLINE_INSET {
+            return;
+        }
+        let left = x + SPARKLINE_INSET;
+        let top = y + SPARKLINE_INSET;
+        let inner_width = width - 2.0 * SPARKLINE_INSET;
+        let inner_height = (height - 2.0 * SPARKLINE_INSET).max(1.0);
+        let color = sparkline.options.color.as_deref().map_or(
+            Cow::Borrowed(self.theme.sparkline_color.as_str()),
+            |color| self.theme.rule_color(color),
+        );
+        let negative_color = sparkline.options.negative_color.as_deref().map_or(
+            Cow::Borrowed(self.theme.sparkline_negative_color.as_str()),
+            |color| self.theme.rule_color(color),
+        );
+
+        ctx.save();
+        ctx.begin_path();
+        ctx.rect(x, y, width, height);
+        ctx.clip();
+        match sparkline.kind {
+            SparklineKind::Line => {
+                // Gaps for blank cells break the line
+                let step = inner_width / (count.max(2) - 1) as f64;
+                ctx.set_stroke_style_str(&color);
+                ctx.set_line_width(1.5);
+                ctx.begin_path();
+                let mut drawing = false;
+                for (i, point) in sparkline.points.iter().enumerate() {
+                    let Some(point) = point else {
+                        drawing = false;
+                        continue;
+                    };
+                    let px = if count == 1 {
+                        left + inner_width / 2.0
+                    } else {
+                        left + i as f64 * step
+                    };
+                    let py = top + (1.0 - point) * inner_height;
+                    if drawing {
+                        ctx.line_to(px, py);
+                    } else {
+                        ctx.move_to(px, py);
+                        drawing = true;
                     }
                 }
+                ctx.stroke();
+            }
+            SparklineKind::Bar | SparklineKind::WinLoss => {
+                let slot = inner_width / count as f64;
+                let bar_width = (slot * 0.8).max(1.0);
+                let baseline = top + (1.0 - sparkline.baseline) * inner_height;
+                for (i, point) in sparkline.points.iter().enumerate() {
+                    let Some(point) = *point else { continue };
+                    // Win/loss bars take half the height above or below the middle
+                    let end = match sparkline.kind {
+                        SparklineKind::WinLoss => baseline - point * inner_height / 2.0,
+                        _ => top + (1.0 - point) * inner_height,
+                    };
+                    let negative = end > baseline;
+                    ctx.set_fill_style_str(if negative { &negative_color } else { &color });
+                    let bar_x = left + i as f64 * slot + (slot - bar_width) / 2.0;
+                    // Zero still shows as a hairline
+                    let bar_height = (end - baseline).abs().max(1.0);
+                    ctx.fill_rect(bar_x, baseline.min(end), bar_width, bar_height);
+                }
             }
         }
+        ctx.restore();
     }
+
+    /// Small triangles in the top-left corner of cells with lint warnings,
+    /// kept apart from the red text of errors
+    fn render_lint_markers(
+        &self,
+        ctx: &CanvasRenderingContext2d,
+        viewport: &crate::components::viewport::Viewport,
+        flagged: &[CellAddress],
+        config: &gridcore_controller::controller::GridConfiguration,
+    ) {
+        const MARKER_SIZE: f64 = 6.0;
+
+        ctx.set_fill_style_str(&self.theme.lint_marker_color);
+        for address in flagged {
+            let (x, y) = viewport.point_of_cell(address);
+            let x = x + config.row_header_width;
+            let y = y + config.column_header_height;
+
+            ctx.begin_path();
+            ctx.move_to(x, y);
+            ctx.line_to(x + MARKER_SIZE, y);
+            ctx.line_to(x, y + MARKER_SIZE);
+            ctx.close_path();
+            ctx.fill();
+        }
+        ctx.set_fill_style_str(&self.theme.cell_text_color);
+    }
+
+    /// Small triangles in the top-right corner of formulas waiting for a
+    /// recalculation, opposite the lint markers
+    fn render_stale_markers(
+        &self,
+        ctx: &CanvasRenderingContext2d,
+        viewport: &crate::components::viewport::Viewport,
+        cells: &[DisplayCell],
+        config: &gridcore_controller::controller::GridConfiguration,
+    ) {
+        const MARKER_SIZE: f64 = 6.0;
+
+        ctx.set_fill_style_str(&self.theme.stale_marker_color);
+        for cell in cells.iter().filter(|cell| cell.is_stale) {
+            let (x, y) = viewport.point_of_cell(&cell.address);
+            let right =
+                x + config.row_header_width + viewport.get_column_width(cell.address.col as usize);
+            let y = y + config.column_header_height;
+
+            ctx.begin_path();
+            ctx.move_to(right, y);
+            ctx.line_to(right - MARKER_SIZE, y);
+            ctx.line_to(right, y + MARKER_SIZE);
+            ctx.close_path();
+            ctx.fill();
+        }
+        ctx.set_fill_style_str(&self.theme.cell_text_color);
+    }
+}
+
+fn in_bounds(address: &CellAddress, bounds: &ViewportBounds) -> bool {
+    let (row, col) = (address.row as usize, address.col as usize);
+    (bounds.start_row..=bounds.end_row).contains(&row)
+        && (bounds.start_col..=bounds.end_col).contains(&col)
+}
+
+/// Measures cell texts in the cell font, on a canvas kept for measuring
+pub fn cell_text_measurer(theme: &GridTheme) -> Option<TextMeasurer> {
+    let canvas = web_sys::window()?
+        .document()?
+        .create_element("canvas")
+        .ok()?
+        .dyn_into::<HtmlCanvasElement>()
+        .ok()?;
+    let ctx = canvas
+        .get_context("2d")
+        .ok()??
+        .dyn_into::<CanvasRenderingContext2d>()
+        .ok()?;
+    ctx.set_font(&format!(
+        "{}px {}",
+        theme.cell_font_size, theme.cell_font_family
+    ));
+    Some(Box::new(move |text: &str| {
+        ctx.measure_text(text)
+            .map(|metrics| metrics.width())
+            .unwrap_or(0.0)
+    }))
 }
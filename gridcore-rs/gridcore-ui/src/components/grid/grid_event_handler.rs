@@ -1,10 +1,16 @@
-use crate::context::{use_controller, use_render_generation, use_viewport};
+use crate::components::cell_tooltip::CellTooltip;
+use crate::context::{use_concerns, use_controller, use_render_generation, use_viewport};
+use gridcore_controller::behaviors::range_drag::SelectionHit;
+use gridcore_controller::controller::{CellInspection, HoverDebounce};
 use gridcore_controller::state::Action;
+use gridcore_core::{domain::CellFormat, types::CellAddress};
 use leptos::prelude::*;
+use std::time::Duration;
 use wasm_bindgen::JsCast;
 use web_sys::{MouseEvent, WheelEvent};
 
 use crate::interaction::resize_handler::ResizeHandler;
+use crate::interaction::wheel_scroll::queue_wheel_scroll;
 
 #[component]
 pub fn GridEventHandler(resize_handler: ResizeHandler, children: Children) -> impl IntoView {
@@ -12,9 +18,70 @@ pub fn GridEventHandler(resize_handler: ResizeHandler, children: Children) -> im
     let controller_stored = use_controller();
     let viewport_stored = use_viewport();
     let (resize_hover_state, set_resize_hover_state) = signal("cell");
+    let (context_menu, set_context_menu) = signal(None::<(f64, f64, CellAddress)>);
+    // Set by a range drop so the click that ends it leaves the cursor alone
+    let (range_dropped, set_range_dropped) = signal(false);
+    // Formats and styles change the workbook, so read-only grids disable them
+    let capabilities = use_concerns().capabilities;
+    let edit_item_class = move || {
+        if capabilities.get().edit {
+            "grid-context-menu-item"
+        } else {
+            "grid-context-menu-item disabled"
+        }
+    };
+
+    // Describe the hovered cell, inspecting it at most once per interval
+    let (tooltip, set_tooltip) = signal(None::<(f64, f64, CellInspection)>);
+    let hover = StoredValue::new(HoverDebounce::new());
+    let hover_position = StoredValue::new((0.0, 0.0));
+    let hover_timer = StoredValue::new(None::<TimeoutHandle>);
+    let show_inspection = move |cell: CellAddress| {
+        let inspection = controller_stored.with_value(|c| c.borrow_mut().inspect_cell(&cell));
+        let (x, y) = hover_position.get_value();
+        set_tooltip.set((!inspection.is_empty()).then_some((x, y, inspection)));
+    };
+    let on_hover = move |cell: Option<CellAddress>, x: f64, y: f64| {
+        if let Some(timer) = hover_timer.get_value() {
+            timer.clear();
+        }
+        hover_position.set_value((x, y));
+        let now = js_sys::Date::now();
+        let mut due = None;
+        let mut delay = None;
+        hover.update_value(|hover| {
+            due = hover.hover(cell, now);
+            delay = hover.pending_delay(now);
+        });
+        if due.is_none() && hover.with_value(|hover| hover.shown()) != cell {
+            set_tooltip.set(None);
+        }
+        if let Some(cell) = due {
+            show_inspection(cell);
+        } else if let Some(delay) = delay {
+            let timer = set_timeout_with_handle(
+                move || {
+                    let mut due = None;
+                    hover.update_value(|hover| due = hover.poll(js_sys::Date::now()));
+                    if let Some(cell) = due {
+                        show_inspection(cell);
+                    }
+                },
+                Duration::from_millis(delay.ceil() as u64),
+            )
+            .ok();
+            hover_timer.set_value(timer);
+        }
+    };
 
     // Handle mouse click
     let on_click = move |ev: MouseEvent| {
+        set_context_menu.set(None);
+        if range_dropped.get_untracked() {
+            set_range_dropped.set(false);
+            return;
+        }
+
         // Focus the parent grid-container instead of this element
         if let Some(current_target) = ev.current_target()
             && let Ok(element) = current_target.dyn_into::<web_sys::HtmlElement>()
@@ -35,7 +102,7 @@ pub fn GridEventHandler(resize_handler: ResizeHandler, children: Children) -> im
             let cell_y = y - config.column_header_height;
 
             if let Some(cell) =
-                viewport_stored.with_value(|vp| vp.borrow().get_cell_at_position(cell_x, cell_y))
+                viewport_stored.with_value(|vp| vp.borrow().cell_at_point(cell_x, cell_y))
             {
                 controller_stored.with_value(|c| {
                     let _ = c
@@ -58,7 +125,7 @@ pub fn GridEventHandler(resize_handler: ResizeHandler, children: Children) -> im
             let cell_y = y - config.column_header_height;
 
             if let Some(cell) =
-                viewport_stored.with_value(|vp| vp.borrow().get_cell_at_position(cell_x, cell_y))
+                viewport_stored.with_value(|vp| vp.borrow().cell_at_point(cell_x, cell_y))
             {
                 controller_stored.with_value(|ctrl| {
                     let mut ctrl_mut = ctrl.borrow_mut();
@@ -88,12 +155,20 @@ pub fn GridEventHandler(resize_handler: ResizeHandler, children: Children) -> im
         let x = ev.offset_x() as f64;
         let y = ev.offset_y() as f64;
 
-        controller_stored.with_value(|c| {
+        let hovered = controller_stored.with_value(|c| {
             let mut controller = c.borrow_mut();
-            
+
             if resize_handler_move.is_resizing(&controller) {
                 resize_handler_move.handle_resize(&ev, &mut controller);
                 // Render will update automatically via state changes
+            } else if controller.get_range_drag().is_some() {
+                let config = controller.get_config().clone();
+                let _ = controller.dispatch_action(Action::UpdateRangeDrag {
+                    x: x - config.row_header_width,
+                    y: y - config.column_header_height,
+                });
+                // Dragging near the edge scrolls the viewport
+                use_render_generation().update(|g| *g += 1);
             } else {
                 let config = controller.get_config().clone();
                 let is_col_header = y < config.column_header_height;
@@ -107,19 +182,29 @@ pub fn GridEventHandler(resize_handler: ResizeHandler, children: Children) -> im
                         &controller,
                     );
                     set_resize_hover_state.set(cursor);
+                    return None;
+                }
+                let (cell_x, cell_y) =
+                    (x - config.row_header_width, y - config.column_header_height);
+                if controller.selection_hit(cell_x, cell_y) == SelectionHit::Border {
+                    set_resize_hover_state.set("move");
                 } else {
                     set_resize_hover_state.set("cell");
                 }
+                return viewport_stored.with_value(|vp| vp.borrow().cell_at_point(cell_x, cell_y));
             }
+            None
         });
+        on_hover(hovered, x, y);
     };
 
     // Handle mouse down
     let resize_handler_down = resize_handler.clone();
     let on_mouse_down = move |ev: MouseEvent| {
+        set_tooltip.set(None);
         let x = ev.offset_x() as f64;
         let y = ev.offset_y() as f64;
-        
+
         controller_stored.with_value(|c| {
             let mut controller = c.borrow_mut();
             let config = controller.get_config().clone();
@@ -136,18 +221,36 @@ pub fn GridEventHandler(resize_handler: ResizeHandler, children: Children) -> im
             {
                 ev.prevent_default();
                 resize_handler_down.start_resize(&ev, resize_type, index, &mut controller);
+            } else if !is_col_header && !is_row_header {
+                // Grabbing the selection border picks the block up
+                let (cell_x, cell_y) =
+                    (x - config.row_header_width, y - config.column_header_height);
+                if controller.selection_hit(cell_x, cell_y) == SelectionHit::Border
+                    && let Some(grab) =
+                        viewport_stored.with_value(|vp| vp.borrow().cell_at_point(cell_x, cell_y))
+                {
+                    ev.prevent_default();
+                    let _ = controller.dispatch_action(Action::StartRangeDrag { grab });
+                }
             }
         });
     };
 
     // Handle mouse up
     let resize_handler_up = resize_handler.clone();
-    let on_mouse_up = move |_ev: MouseEvent| {
+    let on_mouse_up = move |ev: MouseEvent| {
         controller_stored.with_value(|c| {
             let mut controller = c.borrow_mut();
             if resize_handler_up.is_resizing(&controller) {
                 resize_handler_up.end_resize(&mut controller);
                 // Render will update automatically via state changes
+            } else if controller.get_range_drag().is_some() {
+                // Holding Ctrl (Cmd on macOS) copies instead of moving
+                let copy = ev.ctrl_key() || ev.meta_key();
+                if let Err(e) = controller.dispatch_action(Action::DropRange { copy }) {
+                    leptos::logging::log!("Error dropping range: {}", e);
+                }
+                set_range_dropped.set(true);
             }
         });
     };
@@ -167,26 +270,178 @@ pub fn GridEventHandler(resize_handler: ResizeHandler, children: Children) -> im
             (delta_x * scroll_factor, delta_y * scroll_factor)
         };
 
+        // Applied once per frame; the ViewportScrolled event redraws
         if scroll_x != 0.0 || scroll_y != 0.0 {
-            viewport_stored.with_value(|vp| vp.borrow_mut().scroll_by(scroll_x, scroll_y));
-            // Manual render update for scroll
-            let render_gen = use_render_generation();
-            render_gen.update(|g| *g += 1);
+            queue_wheel_scroll(viewport_stored, scroll_x, scroll_y);
+        }
+    };
+
+    // Handle right-click: move the cursor and open the cell context menu
+    let on_context_menu = move |ev: MouseEvent| {
+        ev.prevent_default();
+
+        let x = ev.offset_x() as f64;
+        let y = ev.offset_y() as f64;
+
+        let config = controller_stored.with_value(|c| c.borrow().get_config().clone());
+
+        if x > config.row_header_width && y > config.column_header_height {
+            let cell_x = x - config.row_header_width;
+            let cell_y = y - config.column_header_height;
+
+            if let Some(cell) =
+                viewport_stored.with_value(|vp| vp.borrow().cell_at_point(cell_x, cell_y))
+            {
+                controller_stored.with_value(|c| {
+                    let _ = c
+                        .borrow_mut()
+                        .dispatch_action(Action::UpdateCursor { cursor: cell });
+                });
+                set_context_menu.set(Some((x, y, cell)));
+            }
         }
     };
 
+    let on_add_watch = move |ev: MouseEvent| {
+        ev.stop_propagation();
+        if let Some((_, _, cell)) = context_menu.get_untracked() {
+            controller_stored.with_value(|c| {
+                if let Err(e) = c.borrow_mut().dispatch_action(Action::AddWatch {
+                    address: cell,
+                    sheet: None,
+                    label: None,
+                }) {
+                    leptos::logging::log!("Error adding watch: {}", e);
+                }
+            });
+        }
+        set_context_menu.set(None);
+    };
+
+    // Apply a default format to the column of the right-clicked cell
+    let set_column_format = move |format: Option<CellFormat>| {
+        if !capabilities.get_untracked().edit {
+            return;
+        }
+        if let Some((_, _, cell)) = context_menu.get_untracked() {
+            controller_stored.with_value(|c| {
+                if let Err(e) = c.borrow_mut().dispatch_action(Action::SetColumnFormat {
+                    column: cell.col,
+                    format,
+                }) {
+                    leptos::logging::log!("Error setting column format: {}", e);
+                }
+            });
+        }
+        set_context_menu.set(None);
+    };
+
+    // Apply a named style from the gallery to the selection
+    let apply_style = move |name: String| {
+        if !capabilities.get_untracked().edit {
+            return;
+        }
+        controller_stored.with_value(|c| {
+            if let Err(e) = c
+                .borrow_mut()
+                .dispatch_action(Action::ApplyStyle { name, ranges: None })
+            {
+                leptos::logging::log!("Error applying style: {}", e);
+            }
+        });
+        set_context_menu.set(None);
+    };
+
     view! {
         <div
             class="grid-event-handler"
             on:click=on_click
+            on:contextmenu=on_context_menu
             on:dblclick=on_dblclick
             on:mousedown=on_mouse_down
             on:mousemove=on_mouse_move
             on:mouseup=on_mouse_up
+            on:mouseleave=move |_| on_hover(None, 0.0, 0.0)
             on:wheel=on_wheel
             style=move || format!("cursor: {}; width: 100%; height: 100%; outline: none;", resize_hover_state.get())
         >
             {children()}
+            {move || {
+                tooltip
+                    .get()
+                    .filter(|_| context_menu.get().is_none())
+                    .map(|(x, y, inspection)| view! { <CellTooltip x y inspection /> })
+            }}
+            {move || {
+                context_menu.get().map(|(x, y, _)| {
+                    view! {
+                        <div
+                            class="grid-context-menu"
+                            style=format!("left: {}px; top: {}px;", x, y)
+                        >
+                            <div class="grid-context-menu-item" on:click=on_add_watch>
+                                "Add to watch"
+                            </div>
+                            <div class="grid-context-menu-separator"></div>
+                            <div
+                                class=edit_item_class
+                                on:click=move |ev| {
+                                    ev.stop_propagation();
+                                    set_column_format(None)
+                                }
+                            >
+                                "Column format: General"
+                            </div>
+                            <div
+                                class=edit_item_class
+                                on:click=move |ev| {
+                                    ev.stop_propagation();
+                                    set_column_format(Some(CellFormat::number(2)))
+                                }
+                            >
+                                "Column format: Number"
+                            </div>
+                            <div
+                                class=edit_item_class
+                                on:click=move |ev| {
+                                    ev.stop_propagation();
+                                    set_column_format(Some(CellFormat::percent(1)))
+                                }
+                            >
+                                "Column format: Percent"
+                            </div>
+                            <div
+                                class=edit_item_class
+                                on:click=move |ev| {
+                                    ev.stop_propagation();
+                                    set_column_format(Some(CellFormat::currency("$", 2)))
+                                }
+                            >
+                                "Column format: Currency"
+                            </div>
+                            <div class="grid-context-menu-separator"></div>
+                            {controller_stored
+                                .with_value(|c| c.borrow().get_styles())
+                                .into_iter()
+                                .map(|style| {
+                                    let label = format!("Style: {}", style.name);
+                                    view! {
+                                        <div
+                                            class=edit_item_class
+                                            on:click=move |ev| {
+                                                ev.stop_propagation();
+                                                apply_style(style.name.clone())
+                                            }
+                                        >
+                                            {label}
+                                        </div>
+                                    }
+                                })
+                                .collect_view()}
+                        </div>
+                    }
+                })
+            }}
         </div>
     }
 }
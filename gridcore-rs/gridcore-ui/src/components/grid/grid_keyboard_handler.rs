@@ -1,6 +1,7 @@
 use crate::context::{use_controller, use_render_generation, use_viewport};
 use crate::debug_log;
 use crate::interaction::auto_scroll::AutoScroller;
+use gridcore_controller::behaviors::clipboard::CLIPBOARD_TYPE;
 use leptos::html::Div;
 use leptos::prelude::*;
 use wasm_bindgen::JsCast;
@@ -15,7 +16,7 @@ pub fn GridKeyboardHandler(children: Children) -> impl IntoView {
     Effect::new(move |_| {
         if let Some(wrapper) = wrapper_ref.get() {
             let element: &web_sys::HtmlDivElement = wrapper.as_ref();
-            
+
             // Use setTimeout with a small delay to ensure all components are mounted
             // This is necessary when the perf feature is enabled and MetricsToggle is rendered
             let window = web_sys::window().expect("window should exist");
@@ -24,12 +25,14 @@ pub fn GridKeyboardHandler(children: Children) -> impl IntoView {
                 let _ = element_clone.focus();
                 debug_log!("Grid keyboard handler focused after timeout");
             });
-            
+
             // Use a 10ms delay to ensure all components are fully mounted
-            window.set_timeout_with_callback_and_timeout_and_arguments_0(
-                focus_closure.as_ref().unchecked_ref(),
-                10
-            ).ok();
+            window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    focus_closure.as_ref().unchecked_ref(),
+                    10,
+                )
+                .ok();
             focus_closure.forget();
 
             render_generation.update(|g| *g += 1);
@@ -58,6 +61,12 @@ pub fn GridKeyboardHandler(children: Children) -> impl IntoView {
             return;
         }
 
+        // Leave copy and paste to the browser, which fires the clipboard
+        // events handled below
+        if (ctrl_pressed || meta_pressed) && matches!(key.as_str(), "c" | "v") {
+            return;
+        }
+
         match key.as_str() {
             "Tab" | "Enter" | "Escape" | "Delete" | "Backspace" | "ArrowUp" | "ArrowDown"
             | "ArrowLeft" | "ArrowRight" => {
@@ -95,6 +104,61 @@ pub fn GridKeyboardHandler(children: Children) -> impl IntoView {
         }
     };
 
+    // Clicking back into the grid takes focus from an open panel
+    let on_focusin = move |_: web_sys::FocusEvent| {
+        controller_stored.with_value(|ctrl| {
+            let has_grid_focus = ctrl.borrow().has_grid_focus();
+            if !has_grid_focus {
+                ctrl.borrow_mut().focus_grid();
+            }
+        });
+    };
+
+    let on_copy = move |ev: web_sys::ClipboardEvent| {
+        let Some(data) = ev.clipboard_data() else {
+            return;
+        };
+        let copied = controller_stored.with_value(|ctrl| {
+            let mut ctrl = ctrl.borrow_mut();
+            if ctrl.get_mode().is_editing() {
+                return None;
+            }
+            Some(ctrl.copy_selection())
+        });
+        match copied {
+            Some(Ok(contents)) => {
+                ev.prevent_default();
+                data.set_data("text/plain", &contents.text).ok();
+                data.set_data(CLIPBOARD_TYPE, &contents.payload.to_json())
+                    .ok();
+            }
+            Some(Err(e)) => leptos::logging::log!("Error copying selection: {:?}", e),
+            None => {}
+        }
+    };
+
+    let on_paste = move |ev: web_sys::ClipboardEvent| {
+        let Some(data) = ev.clipboard_data() else {
+            return;
+        };
+        let is_editing = controller_stored.with_value(|ctrl| ctrl.borrow().get_mode().is_editing());
+        if is_editing {
+            return;
+        }
+        ev.prevent_default();
+        let text = data.get_data("text/plain").unwrap_or_default();
+        let rich = data
+            .get_data(CLIPBOARD_TYPE)
+            .ok()
+            .filter(|rich| !rich.is_empty());
+        let result = controller_stored
+            .with_value(|ctrl| ctrl.borrow_mut().paste_clipboard(&text, rich.as_deref()));
+        if let Err(e) = result {
+            leptos::logging::log!("Error pasting: {:?}", e);
+        }
+        render_generation.update(|g| *g += 1);
+    };
+
     view! {
         <div
             class="grid-container grid-keyboard-handler"
@@ -102,6 +166,9 @@ pub fn GridKeyboardHandler(children: Children) -> impl IntoView {
             tabindex="0"
             autofocus=true
             on:keydown=on_keydown
+            on:focusin=on_focusin
+            on:copy=on_copy
+            on:paste=on_paste
             style="width: 100%; height: 100%; outline: none; position: relative; overflow: hidden;"
         >
             {children()}
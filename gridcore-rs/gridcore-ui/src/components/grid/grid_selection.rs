@@ -1,3 +1,4 @@
+use gridcore_controller::behaviors::resize::ResizeType;
 use gridcore_controller::state::{Selection, SelectionType};
 use leptos::prelude::{GetUntracked, WithValue};
 use wasm_bindgen::JsCast;
@@ -42,7 +43,25 @@ impl GridSelection {
                     self.render_selection_overlay(&ctx, sel, &viewport, config, &bounds);
                 }
 
-                self.render_active_cell_border(&ctx, &viewport, &active_cell, &bounds, config);
+                self.render_active_cell_border(
+                    &ctx,
+                    &viewport,
+                    &active_cell,
+                    &bounds,
+                    &ctrl_borrow,
+                );
+
+                if let Some(drag) = ctrl_borrow.get_range_drag() {
+                    self.render_drop_outline(&ctx, drag.destination(), &viewport, config);
+                }
+
+                if let Some(cell) = ctrl_borrow.highlighted_cell() {
+                    self.render_highlight(&ctx, &cell, &viewport, config);
+                }
+
+                if let Some((resize_type, edge)) = ctrl_borrow.resize_guideline() {
+                    self.render_resize_guideline(&ctx, resize_type, edge, &viewport, config);
+                }
             });
         });
 
@@ -64,8 +83,8 @@ impl GridSelection {
         config: &gridcore_controller::controller::GridConfiguration,
         bounds: &gridcore_controller::controller::ViewportBounds,
     ) {
-        ctx.set_fill_style_str("rgba(0, 120, 215, 0.2)");
-        ctx.set_stroke_style_str("rgba(0, 120, 215, 0.8)");
+        ctx.set_fill_style_str(&self.theme.selection_background_color);
+        ctx.set_stroke_style_str(&self.theme.selection_border_color);
         ctx.set_line_width(1.0);
 
         match &selection.selection_type {
@@ -80,16 +99,16 @@ impl GridSelection {
                     && min_row <= bounds.end_row
                     && max_row >= bounds.start_row
                 {
-                    let x1 = viewport.get_column_x(min_col) - viewport.get_scroll_position().x
-                        + config.row_header_width;
-                    let x2 = viewport.get_column_x(max_col) - viewport.get_scroll_position().x
-                        + config.row_header_width
-                        + viewport.get_column_width(max_col);
-                    let y1 = viewport.get_row_y(min_row) - viewport.get_scroll_position().y
-                        + config.column_header_height;
-                    let y2 = viewport.get_row_y(max_row) - viewport.get_scroll_position().y
-                        + config.column_header_height
-                        + viewport.get_row_height(max_row);
+                    let top_left =
+                        gridcore_core::types::CellAddress::new(min_col as u32, min_row as u32);
+                    let bottom_right = viewport.get_cell_position(
+                        &gridcore_core::types::CellAddress::new(max_col as u32, max_row as u32),
+                    );
+                    let (x1, y1) = viewport.point_of_cell(&top_left);
+                    let x1 = x1 + config.row_header_width;
+                    let y1 = y1 + config.column_header_height;
+                    let x2 = bottom_right.x + config.row_header_width + bottom_right.width;
+                    let y2 = bottom_right.y + config.column_header_height + bottom_right.height;
 
                     ctx.fill_rect(x1, y1, x2 - x1, y2 - y1);
                     ctx.stroke_rect(x1, y1, x2 - x1, y2 - y1);
@@ -140,23 +159,120 @@ impl GridSelection {
         }
     }
 
+    /// Dashed outline where a dragged range would land
+    fn render_drop_outline(
+        &self,
+        ctx: &CanvasRenderingContext2d,
+        destination: &gridcore_core::formula::CellRange,
+        viewport: &crate::components::viewport::Viewport,
+        config: &gridcore_controller::controller::GridConfiguration,
+    ) {
+        let (x1, y1) = viewport.point_of_cell(&destination.start);
+        let bottom_right = viewport.get_cell_position(&destination.end);
+        let x1 = x1 + config.row_header_width;
+        let y1 = y1 + config.column_header_height;
+        let x2 = bottom_right.x + config.row_header_width + bottom_right.width;
+        let y2 = bottom_right.y + config.column_header_height + bottom_right.height;
+
+        ctx.save();
+        ctx.set_stroke_style_str(&self.theme.active_cell_border_color);
+        ctx.set_line_width(2.0);
+        let dash = js_sys::Array::of2(&4.0.into(), &3.0.into());
+        ctx.set_line_dash(&dash).ok();
+        ctx.stroke_rect(x1, y1, x2 - x1, y2 - y1);
+        ctx.restore();
+    }
+
+    /// Line across the grid where a resized column or row would end
+    fn render_resize_guideline(
+        &self,
+        ctx: &CanvasRenderingContext2d,
+        resize_type: ResizeType,
+        edge: f64,
+        viewport: &crate::components::viewport::Viewport,
+        config: &gridcore_controller::controller::GridConfiguration,
+    ) {
+        let (width, height) = (
+            viewport.get_viewport_width(),
+            viewport.get_viewport_height(),
+        );
+        ctx.save();
+        ctx.set_stroke_style_str(&self.theme.resize_guide_color);
+        ctx.set_line_width(1.0);
+        let dash = js_sys::Array::of2(&4.0.into(), &3.0.into());
+        ctx.set_line_dash(&dash).ok();
+        ctx.begin_path();
+        match resize_type {
+            ResizeType::Column => {
+                let x = edge + config.row_header_width;
+                ctx.move_to(x, 0.0);
+                ctx.line_to(x, height);
+            }
+            ResizeType::Row => {
+                let y = edge + config.column_header_height;
+                ctx.move_to(0.0, y);
+                ctx.line_to(width, y);
+            }
+            ResizeType::None => {}
+        }
+        ctx.stroke();
+        ctx.restore();
+    }
+
+    /// Thick outline around the cell a tutorial step points at
+    fn render_highlight(
+        &self,
+        ctx: &CanvasRenderingContext2d,
+        cell: &gridcore_core::types::CellAddress,
+        viewport: &crate::components::viewport::Viewport,
+        config: &gridcore_controller::controller::GridConfiguration,
+    ) {
+        let pos = viewport.get_cell_position(cell);
+        let cell_x = pos.x + config.row_header_width;
+        let cell_y = pos.y + config.column_header_height;
+
+        ctx.save();
+        ctx.set_stroke_style_str(&self.theme.tutorial_highlight_color);
+        ctx.set_line_width(3.0);
+        ctx.stroke_rect(
+            cell_x - 2.0,
+            cell_y - 2.0,
+            pos.width + 4.0,
+            pos.height + 4.0,
+        );
+        ctx.restore();
+    }
+
+    /// Solid outline around the cursor, dashed and grey when the cell
+    /// cannot be edited and dimmed while keys go to a panel
     fn render_active_cell_border(
         &self,
         ctx: &CanvasRenderingContext2d,
         viewport: &crate::components::viewport::Viewport,
         active_cell: &gridcore_core::types::CellAddress,
         bounds: &gridcore_controller::controller::ViewportBounds,
-        config: &gridcore_controller::controller::GridConfiguration,
+        controller: &gridcore_controller::controller::SpreadsheetController,
     ) {
+        let config = controller.get_config();
         if active_cell.row as usize <= bounds.end_row && active_cell.col as usize <= bounds.end_col
         {
             let pos = viewport.get_cell_position(active_cell);
             let cell_x = pos.x + config.row_header_width;
             let cell_y = pos.y + config.column_header_height;
 
-            ctx.set_stroke_style_str(&self.theme.active_cell_border_color);
+            ctx.save();
+            if controller.is_read_only() {
+                ctx.set_stroke_style_str(&self.theme.read_only_cell_border_color);
+                let dash = js_sys::Array::of2(&3.0.into(), &2.0.into());
+                ctx.set_line_dash(&dash).ok();
+            } else if controller.has_grid_focus() {
+                ctx.set_stroke_style_str(&self.theme.active_cell_border_color);
+            } else {
+                ctx.set_stroke_style_str(&self.theme.unfocused_cell_border_color);
+            }
             ctx.set_line_width(2.0);
             ctx.stroke_rect(cell_x, cell_y, pos.width, pos.height);
+            ctx.restore();
         }
     }
 }
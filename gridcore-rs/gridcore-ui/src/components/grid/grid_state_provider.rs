@@ -1,4 +1,4 @@
-use crate::context::{use_app_state, use_reactive_signals, use_viewport};
+use crate::context::{use_app_state, use_viewport};
 use gridcore_core::types::CellAddress;
 use leptos::prelude::*;
 
@@ -6,16 +6,13 @@ use leptos::prelude::*;
 pub fn GridStateProvider(children: Children) -> impl IntoView {
     let app_state = use_app_state();
     let controller_stored = app_state.controller;
-    let (state_generation, _render_generation) = use_reactive_signals();
+    let concerns = app_state.concerns;
     let viewport_stored = use_viewport();
 
-    let active_cell = Memo::new(move |_| {
-        state_generation.get();
-        controller_stored.with_value(|ctrl| ctrl.borrow().cursor())
-    });
+    let active_cell = Memo::new(move |_| concerns.cursor.get());
 
     let editing_mode = Memo::new(move |_| {
-        state_generation.get();
+        concerns.editing.get();
         controller_stored.with_value(|ctrl| ctrl.borrow().get_mode().is_editing())
     });
 
@@ -0,0 +1,107 @@
+use gridcore_controller::behaviors::trace::{ArrowGeometry, TraceDirection};
+use leptos::prelude::{GetUntracked, WithValue};
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+use crate::context::{use_controller, use_device_pixel_ratio};
+use crate::rendering::GridTheme;
+
+const ARROW_HEAD_LENGTH: f64 = 8.0;
+const CLIPPED_INDICATOR_RADIUS: f64 = 4.0;
+
+/// Draws precedent/dependent trace arrows above the cells and below the cursor
+#[derive(Clone)]
+pub struct GridTraceArrows {
+    theme: GridTheme,
+}
+
+impl GridTraceArrows {
+    pub fn new(theme: GridTheme) -> Self {
+        Self { theme }
+    }
+
+    pub fn render(&self, canvas: &HtmlCanvasElement) {
+        let ctx = match self.get_context(canvas) {
+            Some(ctx) => ctx,
+            None => return,
+        };
+
+        let controller_stored = use_controller();
+        let device_pixel_ratio = use_device_pixel_ratio().get_untracked();
+
+        // Geometry is recomputed from cell addresses every frame so arrows track scrolling
+        let arrows = controller_stored.with_value(|ctrl| ctrl.borrow().get_trace_arrow_geometry());
+        if arrows.is_empty() {
+            return;
+        }
+
+        ctx.save();
+        ctx.scale(device_pixel_ratio, device_pixel_ratio).ok();
+
+        for arrow in &arrows {
+            self.render_arrow(&ctx, arrow);
+        }
+
+        ctx.restore();
+    }
+
+    fn get_context(&self, canvas: &HtmlCanvasElement) -> Option<CanvasRenderingContext2d> {
+        canvas
+            .get_context("2d")
+            .ok()?
+            .and_then(|ctx| ctx.dyn_into::<CanvasRenderingContext2d>().ok())
+    }
+
+    fn render_arrow(&self, ctx: &CanvasRenderingContext2d, arrow: &ArrowGeometry) {
+        let color = match arrow.direction {
+            TraceDirection::Precedents => &self.theme.precedent_arrow_color,
+            TraceDirection::Dependents => &self.theme.dependent_arrow_color,
+        };
+        ctx.set_stroke_style_str(color);
+        ctx.set_fill_style_str(color);
+        ctx.set_line_width(1.5);
+
+        if let Some(rect) = &arrow.source_box {
+            ctx.stroke_rect(rect.x, rect.y, rect.width, rect.height);
+        }
+
+        let (from_x, from_y) = arrow.from;
+        let (to_x, to_y) = arrow.to;
+
+        ctx.begin_path();
+        ctx.move_to(from_x, from_y);
+        ctx.line_to(to_x, to_y);
+        ctx.stroke();
+
+        // Dot at the tail, arrow head at the target
+        ctx.begin_path();
+        ctx.arc(from_x, from_y, 2.5, 0.0, std::f64::consts::TAU)
+            .ok();
+        ctx.fill();
+
+        let angle = (to_y - from_y).atan2(to_x - from_x);
+        ctx.begin_path();
+        ctx.move_to(to_x, to_y);
+        for offset in [-0.4, 0.4] {
+            ctx.line_to(
+                to_x - ARROW_HEAD_LENGTH * (angle + offset).cos(),
+                to_y - ARROW_HEAD_LENGTH * (angle + offset).sin(),
+            );
+        }
+        ctx.close_path();
+        ctx.fill();
+
+        // Hollow markers where an endpoint was clamped to the viewport edge
+        for (clipped, (x, y)) in [
+            (arrow.from_clipped, arrow.from),
+            (arrow.to_clipped, arrow.to),
+        ] {
+            if clipped {
+                ctx.begin_path();
+                ctx.arc(x, y, CLIPPED_INDICATOR_RADIUS, 0.0, std::f64::consts::TAU)
+                    .ok();
+                ctx.stroke();
+            }
+        }
+    }
+}
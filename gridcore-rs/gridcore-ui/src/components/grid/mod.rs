@@ -6,6 +6,7 @@ pub mod grid_headers;
 pub mod grid_keyboard_handler;
 pub mod grid_selection;
 pub mod grid_state_provider;
+pub mod grid_trace_arrows;
 
 pub use grid_canvas::GridCanvas;
 pub use grid_cells::GridCells;
@@ -15,3 +16,4 @@ pub use grid_headers::GridHeaders;
 pub use grid_keyboard_handler::GridKeyboardHandler;
 pub use grid_selection::GridSelection;
 pub use grid_state_provider::{GridStateProvider, use_grid_state};
+pub use grid_trace_arrows::GridTraceArrows;
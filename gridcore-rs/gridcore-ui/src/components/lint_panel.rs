@@ -0,0 +1,85 @@
+use crate::context::{use_controller, use_state_generation};
+use gridcore_controller::state::Action;
+use gridcore_core::types::CellAddress;
+use leptos::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+struct LintRow {
+    address: CellAddress,
+    rule: String,
+    message: String,
+    suggestion: String,
+}
+
+/// Docked panel listing the warnings of the last `:lint` run
+#[component]
+pub fn LintPanel() -> impl IntoView {
+    let controller_stored = use_controller();
+    let state_generation = use_state_generation();
+
+    let rows = Signal::derive(move || {
+        state_generation.get(); // Track changes
+        controller_stored.with_value(|ctrl| {
+            ctrl.borrow()
+                .get_lint_warnings()
+                .findings()
+                .iter()
+                .map(|finding| LintRow {
+                    address: finding.address,
+                    rule: finding.rule.to_string(),
+                    message: finding.message.clone(),
+                    suggestion: finding.suggestion.clone().unwrap_or_default(),
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let dispatch = move |action: Action| {
+        controller_stored.with_value(|ctrl| {
+            if let Err(e) = ctrl.borrow_mut().dispatch_action(action) {
+                leptos::logging::log!("Error handling lint action: {}", e);
+            }
+        });
+    };
+
+    view! {
+        <Show when=move || !rows.get().is_empty()>
+            <div class="lint-panel">
+                <div class="lint-panel-header">
+                    <span>{move || format!("Lint warnings ({})", rows.get().len())}</span>
+                    <button
+                        class="lint-dismiss"
+                        aria-label="Clear lint warnings"
+                        on:click=move |_| dispatch(Action::ClearLintWarnings)
+                    >
+                        "×"
+                    </button>
+                </div>
+                <table class="lint-table">
+                    <tbody>
+                        <For
+                            each=move || rows.get()
+                            key=|row| (row.address, row.rule.clone(), row.message.clone())
+                            children=move |row| {
+                                let address = row.address;
+                                view! {
+                                    <tr
+                                        class="lint-row"
+                                        title=row.suggestion
+                                        on:click=move |_| {
+                                            dispatch(Action::GotoCell { address, sheet: None })
+                                        }
+                                    >
+                                        <td class="lint-address">{address.to_string()}</td>
+                                        <td class="lint-message">{row.message}</td>
+                                        <td class="lint-rule">{row.rule}</td>
+                                    </tr>
+                                }
+                            }
+                        />
+                    </tbody>
+                </table>
+            </div>
+        </Show>
+    }
+}
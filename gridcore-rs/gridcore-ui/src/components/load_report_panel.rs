@@ -0,0 +1,107 @@
+use crate::context::{use_controller, use_state_generation};
+use gridcore_controller::state::Action;
+use gridcore_core::types::CellAddress;
+use leptos::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+struct ReportRow {
+    address: Option<CellAddress>,
+    message: String,
+}
+
+/// Banner summarising what the last import could not load as it was, with
+/// the details listed on request
+#[component]
+pub fn LoadReportPanel() -> impl IntoView {
+    let controller_stored = use_controller();
+    let state_generation = use_state_generation();
+    let (expanded, set_expanded) = signal(false);
+
+    let report = Signal::derive(move || {
+        state_generation.get(); // Track changes
+        controller_stored.with_value(|ctrl| {
+            ctrl.borrow().get_load_report().map(|report| {
+                let rows = report
+                    .formulas_as_text
+                    .iter()
+                    .chain(&report.issues)
+                    .map(|issue| ReportRow {
+                        address: issue.address,
+                        message: issue.message.clone(),
+                    })
+                    .chain(report.preserved_sections.iter().map(|name| ReportRow {
+                        address: None,
+                        message: format!("Kept sidecar section {} unread", name),
+                    }))
+                    .collect::<Vec<_>>();
+                (report.summary().unwrap_or_default(), rows)
+            })
+        })
+    });
+
+    let dispatch = move |action: Action| {
+        controller_stored.with_value(|ctrl| {
+            if let Err(e) = ctrl.borrow_mut().dispatch_action(action) {
+                leptos::logging::log!("Error handling load report action: {}", e);
+            }
+        });
+    };
+
+    view! {
+        <Show when=move || report.with(Option::is_some)>
+            <div class="load-report-panel">
+                <div class="load-report-header">
+                    <span
+                        class="load-report-summary"
+                        title="Show details"
+                        on:click=move |_| set_expanded.update(|expanded| *expanded = !*expanded)
+                    >
+                        {move || {
+                            report.with(|report| {
+                                report.as_ref().map(|(summary, _)| format!("Loaded with problems: {}", summary))
+                            })
+                        }}
+                    </span>
+                    <button
+                        class="load-report-dismiss"
+                        aria-label="Dismiss load report"
+                        on:click=move |_| {
+                            set_expanded.set(false);
+                            dispatch(Action::DismissLoadReport)
+                        }
+                    >
+                        "×"
+                    </button>
+                </div>
+                <Show when=move || expanded.get()>
+                    <table class="load-report-table">
+                        <tbody>
+                            <For
+                                each=move || report.get().map(|(_, rows)| rows).unwrap_or_default()
+                                key=|row| (row.address, row.message.clone())
+                                children=move |row| {
+                                    let address = row.address;
+                                    view! {
+                                        <tr
+                                            class="load-report-row"
+                                            on:click=move |_| {
+                                                if let Some(address) = address {
+                                                    dispatch(Action::GotoCell { address, sheet: None })
+                                                }
+                                            }
+                                        >
+                                            <td class="load-report-address">
+                                                {address.map(|address| address.to_string())}
+                                            </td>
+                                            <td>{row.message}</td>
+                                        </tr>
+                                    }
+                                }
+                            />
+                        </tbody>
+                    </table>
+                </Show>
+            </div>
+        </Show>
+    }
+}
@@ -1,6 +1,8 @@
 //! Real-time metrics display component
 
 use crate::metrics_collector::MetricsSnapshot;
+use gridcore_demo::SuggestionsDrawer;
+use gridcore_demo::benchmark::results::suggestions::{SuggestedAction, Suggestion};
 use leptos::prelude::*;
 use wasm_bindgen::JsCast;
 
@@ -11,6 +13,10 @@ pub fn MetricsDisplay(
     metrics: Signal<MetricsSnapshot>,
     /// Signal controlling visibility
     visible: Signal<bool>,
+    /// What the recent metrics suggest doing
+    suggestions: Signal<Vec<Suggestion>>,
+    /// Apply the action of a suggestion
+    on_action: Callback<SuggestedAction>,
 ) -> impl IntoView {
     view! {
         <Show
@@ -19,6 +25,7 @@ pub fn MetricsDisplay(
         >
             <div class="metrics-overlay">
                 <h3 class="metrics-title">"Performance Metrics"</h3>
+                <SuggestionsDrawer suggestions=suggestions on_action=on_action />
 
                 // Operations per second
                 <div class="metrics-section">
@@ -105,6 +112,36 @@ pub fn MetricsDisplay(
                     </div>
                 </div>
 
+                // Idle work metrics
+                <div class="metrics-section">
+                    <h4>"Idle Work"</h4>
+                    <div class="metric">
+                        <span class="metric-label">"Items Processed: "</span>
+                        <span class="metric-value">{move || metrics.get().idle_items_processed.to_string()}</span>
+                    </div>
+                    <div class="metric">
+                        <span class="metric-label">"Cancellations: "</span>
+                        <span class="metric-value">{move || metrics.get().idle_cancellations.to_string()}</span>
+                    </div>
+                    <div class="metric">
+                        <span class="metric-label">"Time Spent: "</span>
+                        <span class="metric-value">{move || format!("{:.1} ms", metrics.get().idle_time_ms)}</span>
+                    </div>
+                </div>
+
+                // Reactive signal metrics
+                <div class="metrics-section">
+                    <h4>"Signals"</h4>
+                    <div class="metric">
+                        <span class="metric-label">"Updates: "</span>
+                        <span class="metric-value">{move || metrics.get().signal_updates.to_string()}</span>
+                    </div>
+                    <div class="metric">
+                        <span class="metric-label">"Per Action: "</span>
+                        <span class="metric-value">{move || format!("{:.2}", metrics.get().signal_updates_per_action)}</span>
+                    </div>
+                </div>
+
                 // Sparklines for trending (placeholder for now)
                 <div class="metrics-section">
                     <h4>"Trends"</h4>
@@ -157,7 +194,7 @@ pub fn MetricsToggle(
 ) -> impl IntoView {
     // Use Effect to set button properties after mount to avoid any initial focus issues
     let button_ref = NodeRef::<leptos::html::Button>::new();
-    
+
     Effect::new(move |_| {
         if let Some(button) = button_ref.get() {
             let element: &web_sys::HtmlElement = button.as_ref();
@@ -165,7 +202,7 @@ pub fn MetricsToggle(
             element.set_tab_index(-1);
         }
     });
-    
+
     view! {
         <button
             node_ref=button_ref
@@ -0,0 +1,212 @@
+use crate::context::{use_controller, use_device_pixel_ratio, use_reactive_signals};
+use crate::rendering::{GridTheme, default_theme};
+use gridcore_controller::controller::{MinimapGeometry, MinimapRect};
+use gridcore_controller::theme::Palette;
+use gridcore_core::repository::{CellKind, DensityMap};
+use leptos::html::Canvas;
+use leptos::prelude::*;
+use std::sync::Arc;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, PointerEvent};
+
+/// Blocks are made at least this many CSS pixels tall
+const MIN_BLOCK_HEIGHT: f64 = 2.0;
+
+/// Overview strip of the whole sheet showing where the data is and which
+/// part of it is on screen. Clicking or dragging scrolls the grid there.
+#[component]
+pub fn Minimap() -> impl IntoView {
+    let controller_stored = use_controller();
+    let (state_generation, render_generation) = use_reactive_signals();
+    let device_pixel_ratio_signal = use_device_pixel_ratio();
+    let density_ref = NodeRef::<Canvas>::new();
+    let overlay_ref = NodeRef::<Canvas>::new();
+    let mut theme = default_theme();
+
+    // Geometry of the last frame, for mapping pointer positions back to cells
+    let geometry = StoredValue::new(None::<MinimapGeometry>);
+    // What the density canvas shows, so it is only redrawn when that changes
+    let drawn = StoredValue::new(None::<(Arc<DensityMap>, MinimapGeometry, f64, Palette)>);
+    let block_size = StoredValue::new(1u32);
+    let dragging = StoredValue::new(false);
+
+    Effect::new(move |_| {
+        render_generation.get(); // Viewport rectangle follows scrolling
+        state_generation.get(); // Density follows edits
+        let device_pixel_ratio = device_pixel_ratio_signal.get();
+
+        let (Some(density_canvas), Some(overlay_canvas)) = (density_ref.get(), overlay_ref.get())
+        else {
+            return;
+        };
+        let Some(rect) = density_canvas
+            .parent_element()
+            .map(|parent| parent.get_bounding_client_rect())
+        else {
+            return;
+        };
+        let (width, height) = (rect.width(), rect.height());
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+
+        let (map, frame, viewport, palette) = controller_stored.with_value(|ctrl| {
+            let ctrl = ctrl.borrow();
+            let bounds = ctrl.get_viewport_manager().get_visible_bounds();
+            let mut map = ctrl.minimap_density(block_size.get_value());
+            let frame = MinimapGeometry::covering(width, height, &map, &bounds);
+            // The extent does not depend on the block size, so the frame
+            // stays valid when the map is rebuilt with coarser blocks
+            let wanted = ((frame.rows as f64 * MIN_BLOCK_HEIGHT / height).ceil() as u32).max(1);
+            if wanted != map.block_size {
+                block_size.set_value(wanted);
+                map = ctrl.minimap_density(wanted);
+            }
+            let viewport = frame.viewport_rect(&bounds);
+            (map, frame, viewport, ctrl.palette())
+        });
+        if palette != theme.palette {
+            theme = GridTheme::with_palette(palette);
+        }
+        geometry.set_value(Some(frame));
+
+        let unchanged = drawn.with_value(|drawn| {
+            drawn
+                .as_ref()
+                .is_some_and(|(last_map, last_frame, last_ratio, last_palette)| {
+                    Arc::ptr_eq(last_map, &map)
+                        && *last_frame == frame
+                        && *last_ratio == device_pixel_ratio
+                        && *last_palette == palette
+                })
+        });
+        if !unchanged {
+            draw_density(&density_canvas, &theme, &map, &frame, device_pixel_ratio);
+            drawn.set_value(Some((map, frame, device_pixel_ratio, palette)));
+        }
+        draw_viewport(
+            &overlay_canvas,
+            &theme,
+            &frame,
+            viewport,
+            device_pixel_ratio,
+        );
+    });
+
+    let jump = move |ev: &PointerEvent| {
+        let Some(frame) = geometry.get_value() else {
+            return;
+        };
+        controller_stored.with_value(|ctrl| {
+            ctrl.borrow_mut()
+                .jump_from_minimap(&frame, ev.offset_x() as f64, ev.offset_y() as f64);
+        });
+        render_generation.update(|g| *g += 1);
+    };
+
+    view! {
+        <div class="minimap" title="Sheet overview">
+            <canvas node_ref=density_ref />
+            <canvas
+                node_ref=overlay_ref
+                on:pointerdown=move |ev: PointerEvent| {
+                    // Keep receiving moves while dragging outside the strip
+                    if let Some(target) = ev.target().and_then(|t| t.dyn_into::<web_sys::Element>().ok()) {
+                        let _ = target.set_pointer_capture(ev.pointer_id());
+                    }
+                    dragging.set_value(true);
+                    jump(&ev);
+                }
+                on:pointermove=move |ev: PointerEvent| {
+                    if dragging.get_value() {
+                        jump(&ev);
+                    }
+                }
+                on:pointerup=move |_| dragging.set_value(false)
+                on:pointercancel=move |_| dragging.set_value(false)
+            />
+        </div>
+    }
+}
+
+fn get_context(canvas: &HtmlCanvasElement) -> Option<CanvasRenderingContext2d> {
+    canvas
+        .get_context("2d")
+        .ok()
+        .flatten()
+        .and_then(|ctx| ctx.dyn_into::<CanvasRenderingContext2d>().ok())
+}
+
+/// Size the canvas backing store for the frame. Resizing also clears it.
+fn resize(canvas: &HtmlCanvasElement, frame: &MinimapGeometry, device_pixel_ratio: f64) {
+    let width = (frame.width * device_pixel_ratio) as u32;
+    let height = (frame.height * device_pixel_ratio) as u32;
+    if canvas.width() != width || canvas.height() != height {
+        canvas.set_width(width);
+        canvas.set_height(height);
+    }
+}
+
+fn kind_color(theme: &GridTheme, kind: CellKind) -> &str {
+    match kind {
+        CellKind::Number => &theme.minimap_number_color,
+        CellKind::Text => &theme.minimap_text_color,
+        CellKind::Formula => &theme.minimap_formula_color,
+        CellKind::Error => &theme.minimap_error_color,
+    }
+}
+
+fn draw_density(
+    canvas: &HtmlCanvasElement,
+    theme: &GridTheme,
+    map: &DensityMap,
+    frame: &MinimapGeometry,
+    device_pixel_ratio: f64,
+) {
+    resize(canvas, frame, device_pixel_ratio);
+    let Some(ctx) = get_context(canvas) else {
+        return;
+    };
+    ctx.set_transform(device_pixel_ratio, 0.0, 0.0, device_pixel_ratio, 0.0, 0.0)
+        .ok();
+    ctx.set_global_alpha(1.0);
+    ctx.set_fill_style_str(&theme.minimap_background_color);
+    ctx.fill_rect(0.0, 0.0, frame.width, frame.height);
+
+    // Fuller blocks are drawn more opaque; even a single cell stays visible
+    let max_count = map.max_count().max(1) as f64;
+    for block in &map.blocks {
+        let rect = frame.block_rect(block, map.block_size);
+        ctx.set_global_alpha(0.35 + 0.65 * block.count as f64 / max_count);
+        ctx.set_fill_style_str(kind_color(theme, block.kind));
+        ctx.fill_rect(rect.x, rect.y, rect.width.max(1.0), rect.height.max(1.0));
+    }
+    ctx.set_global_alpha(1.0);
+}
+
+fn draw_viewport(
+    canvas: &HtmlCanvasElement,
+    theme: &GridTheme,
+    frame: &MinimapGeometry,
+    viewport: MinimapRect,
+    device_pixel_ratio: f64,
+) {
+    resize(canvas, frame, device_pixel_ratio);
+    let Some(ctx) = get_context(canvas) else {
+        return;
+    };
+    ctx.set_transform(device_pixel_ratio, 0.0, 0.0, device_pixel_ratio, 0.0, 0.0)
+        .ok();
+    ctx.clear_rect(0.0, 0.0, frame.width, frame.height);
+
+    // Keep the outline visible when the viewport is tiny next to the sheet
+    let width = viewport.width.max(4.0);
+    let height = viewport.height.max(4.0);
+    ctx.set_global_alpha(0.15);
+    ctx.set_fill_style_str(&theme.minimap_viewport_color);
+    ctx.fill_rect(viewport.x, viewport.y, width, height);
+    ctx.set_global_alpha(1.0);
+    ctx.set_stroke_style_str(&theme.minimap_viewport_color);
+    ctx.set_line_width(1.5);
+    ctx.stroke_rect(viewport.x, viewport.y, width, height);
+}
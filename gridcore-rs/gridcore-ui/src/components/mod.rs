@@ -1,13 +1,22 @@
 pub mod cell_editor;
+pub mod cell_tooltip;
 pub mod error_display;
 pub mod grid;
+pub mod lint_panel;
+pub mod load_report_panel;
+pub mod minimap;
 pub mod status_bar;
 pub mod tab_bar;
+pub mod template_picker;
 pub mod viewport;
+pub mod watch_panel;
 
 #[cfg(feature = "perf")]
 pub mod metrics_display;
 
+#[cfg(feature = "debug")]
+pub mod script_console;
+
 // Re-export commonly used grid components for convenience
 pub use grid::{
     GridCanvas, GridCells, GridContainer, GridEventHandler, GridKeyboardHandler, GridSelection,
@@ -0,0 +1,100 @@
+use crate::context::{use_concerns, use_controller};
+use crate::interaction::focus::{handle_panel_key, use_focus_scope};
+use leptos::prelude::*;
+use web_sys::KeyboardEvent;
+
+#[derive(Clone, Debug, PartialEq)]
+enum ConsoleLine {
+    Input(String),
+    Output(String),
+    Error(String),
+}
+
+/// Console for typing script commands (`get A1`, `set B2 = 5`, ...) against
+/// the workbook, shown in debug mode. Ctrl+Enter runs the script.
+#[component]
+pub fn ScriptConsole(#[prop(into)] visible: Signal<bool>) -> impl IntoView {
+    let controller_stored = use_controller();
+    let (script, set_script) = signal(String::new());
+    let (log, set_log) = signal(Vec::<ConsoleLine>::new());
+    let capabilities = use_concerns().capabilities;
+    let on_focusin = use_focus_scope(
+        "script-console",
+        visible,
+        Signal::derive(|| vec!["script-console-input".into(), "script-console-clear".into()]),
+    );
+
+    let run = move || {
+        let text = script.get_untracked();
+        if text.trim().is_empty() {
+            return;
+        }
+        let result = controller_stored.with_value(|ctrl| ctrl.borrow_mut().execute_script(&text));
+        set_log.update(|log| {
+            log.extend(
+                text.lines()
+                    .map(|line| ConsoleLine::Input(line.to_string())),
+            );
+            match result {
+                Ok(outputs) => log.extend(
+                    outputs
+                        .iter()
+                        .map(|output| ConsoleLine::Output(output.to_string())),
+                ),
+                Err(e) => log.push(ConsoleLine::Error(e.to_string())),
+            }
+        });
+        set_script.set(String::new());
+    };
+
+    let on_keydown = move |ev: KeyboardEvent| {
+        if controller_stored.with_value(|ctrl| handle_panel_key(ctrl, &ev)) {
+            return;
+        }
+        if ev.key() == "Enter" && (ev.ctrl_key() || ev.meta_key()) {
+            ev.prevent_default();
+            run();
+        }
+    };
+
+    view! {
+        <Show when=move || visible.get()>
+            <div class="script-console" on:focusin=on_focusin>
+                <div class="script-console-header">
+                    <span>"Console"</span>
+                    <button id="script-console-clear" on:click=move |_| set_log.set(Vec::new())>
+                        "Clear"
+                    </button>
+                </div>
+                <pre class="script-console-log">
+                    {move || {
+                        log.get()
+                            .into_iter()
+                            .map(|line| match line {
+                                ConsoleLine::Input(text) => {
+                                    view! { <div class="script-input">{format!("> {}", text)}</div> }
+                                }
+                                ConsoleLine::Output(text) => {
+                                    view! { <div class="script-output">{text}</div> }
+                                }
+                                ConsoleLine::Error(text) => {
+                                    view! { <div class="script-error">{text}</div> }
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </pre>
+                <textarea
+                    id="script-console-input"
+                    class="script-console-input"
+                    rows="3"
+                    placeholder="get A1, set B2 = =SUM(A1:A5), range A1:C10, recalc, undo, stats, depgraph B2"
+                    prop:value=move || script.get()
+                    disabled=move || !capabilities.get().run_scripts
+                    on:input=move |ev| set_script.set(event_target_value(&ev))
+                    on:keydown=on_keydown
+                />
+            </div>
+        </Show>
+    }
+}
@@ -1,4 +1,4 @@
-use crate::context::{use_controller, use_state_generation};
+use crate::context::{use_concerns, use_controller};
 use gridcore_controller::state::VisualMode;
 use leptos::prelude::*;
 
@@ -6,13 +6,13 @@ use leptos::prelude::*;
 pub fn StatusBar() -> impl IntoView {
     // Get controller and reactive state from context
     let controller_stored = use_controller();
-    let state_generation = use_state_generation();
+    let concerns = use_concerns();
 
     // Create a reactive signal that updates when current_mode or selection changes
     // This ensures the UI updates when mode changes
     let mode_display = move || {
-        // Track state changes for reactivity
-        state_generation.get();
+        // Track mode changes for reactivity
+        concerns.editing.get();
 
         // Get the current mode using the new architecture
         controller_stored.with_value(|ctrl| {
@@ -49,7 +49,9 @@ pub fn StatusBar() -> impl IntoView {
 
     // Derive selection statistics
     let selection_stats = Signal::derive(move || {
-        state_generation.get(); // Track changes
+        concerns.cursor.get();
+        concerns.selection.get();
+        concerns.visible_data.get(); // Selected values can change too
         controller_stored.with_value(|ctrl| ctrl.borrow().get_current_selection_stats())
     });
 
@@ -78,6 +80,48 @@ pub fn StatusBar() -> impl IntoView {
         }
     };
 
+    // Unsaved changes, re-read on edits and twice a minute so the age of the
+    // last change stays current
+    let (clock, set_clock) = signal(0u32);
+    if let Ok(handle) = leptos::leptos_dom::helpers::set_interval_with_handle(
+        move || set_clock.update(|tick| *tick += 1),
+        std::time::Duration::from_secs(30),
+    ) {
+        on_cleanup(move || handle.clear());
+    }
+    let save_display = move || {
+        clock.get();
+        concerns.sheets.get();
+        concerns.visible_data.get();
+        controller_stored.with_value(|ctrl| {
+            let ctrl = ctrl.borrow();
+            let state = ctrl.save_state();
+            if !state.is_modified() {
+                return String::new();
+            }
+            let count = state.unsaved_operations();
+            let mut text = format!(
+                "{} unsaved change{}",
+                count,
+                if count == 1 { "" } else { "s" }
+            );
+            if let Some(ago) = state.last_change_label() {
+                text.push_str(&format!(" · {}", ago));
+            }
+            text
+        })
+    };
+
+    // Spinner and percent while a long recalculation runs
+    let calculation_display = move || {
+        concerns.visible_data.get();
+        controller_stored.with_value(|ctrl| {
+            ctrl.borrow()
+                .calculation_status()
+                .map(|status| format!("{:.0}%", status.fraction() * 100.0))
+        })
+    };
+
     view! {
         <div
             class="status-bar"
@@ -103,6 +147,22 @@ pub fn StatusBar() -> impl IntoView {
                 }}
             </div>
 
+            <span class="save-state" style="color: #999;">
+                {save_display}
+            </span>
+
+            {move || {
+                calculation_display()
+                    .map(|percent| {
+                        view! {
+                            <span class="calculation-indicator" title="Calculating…">
+                                <span class="calculation-spinner"></span>
+                                {percent}
+                            </span>
+                        }
+                    })
+            }}
+
             // Right section: Mode indicator - structure compatible with e2e tests
             {move || {
                 let (mode_text, mode_color, mode_detail) = mode_display();
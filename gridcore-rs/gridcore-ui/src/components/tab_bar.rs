@@ -1,5 +1,6 @@
-use crate::context::use_controller;
+use crate::context::{use_concerns, use_controller};
 use gridcore_controller::state::Action;
+use gridcore_core::repository::SheetHealth;
 use leptos::either::Either;
 use leptos::prelude::*;
 use web_sys::MouseEvent;
@@ -8,6 +9,25 @@ use web_sys::MouseEvent;
 pub struct Sheet {
     pub id: usize,
     pub name: String,
+    pub health: SheetHealth,
+    /// Changed since the document was last saved
+    pub modified: bool,
+}
+
+fn health_tooltip(health: &SheetHealth) -> String {
+    let mut tooltip = format!(
+        "{} error{}",
+        health.error_cells,
+        if health.error_cells == 1 { "" } else { "s" }
+    );
+    if health.circular_cells > 0 {
+        tooltip.push_str(&format!(
+            ", {} in circular references",
+            health.circular_cells
+        ));
+    }
+    tooltip.push_str(" - click to go to the first one");
+    tooltip
 }
 
 #[component]
@@ -21,6 +41,8 @@ pub fn TabBar(sheets: Memo<Vec<Sheet>>, active_sheet: Memo<usize>) -> impl IntoV
     // Get controller from context
     let controller_stored = use_controller();
     let controller = controller_stored.get_value();
+    let capabilities = use_concerns().capabilities;
+    let can_manage_sheets = move || capabilities.get().manage_sheets;
 
     // Add new sheet
     let controller_for_add = controller.clone();
@@ -38,6 +60,10 @@ pub fn TabBar(sheets: Memo<Vec<Sheet>>, active_sheet: Memo<usize>) -> impl IntoV
     // Handle right-click on tab
     let on_context_menu = move |ev: MouseEvent, sheet_id: usize| {
         ev.prevent_default();
+        // Every entry of the menu changes the sheets
+        if !can_manage_sheets() {
+            return;
+        }
         set_context_menu_sheet.set(sheet_id);
         set_context_menu_pos.set((ev.client_x() as f64, ev.client_y() as f64));
         set_show_context_menu.set(true);
@@ -45,6 +71,9 @@ pub fn TabBar(sheets: Memo<Vec<Sheet>>, active_sheet: Memo<usize>) -> impl IntoV
 
     // Start renaming
     let start_rename = move |sheet_id: usize| {
+        if !can_manage_sheets() {
+            return;
+        }
         let sheet_name = sheets
             .get()
             .iter()
@@ -87,6 +116,9 @@ pub fn TabBar(sheets: Memo<Vec<Sheet>>, active_sheet: Memo<usize>) -> impl IntoV
                 {move || sheets.get().into_iter().map(|sheet| {
                     let sheet_id = sheet.id;
                     let sheet_name = sheet.name.clone();
+                    let health = sheet.health;
+                    let modified = sheet.modified;
+                    let badge_sheet = sheet.name.clone();
                     let is_active = move || active_sheet.get() == sheet_id;
                     let is_editing = move || editing_sheet.get() == Some(sheet_id);
 
@@ -179,6 +211,37 @@ pub fn TabBar(sheets: Memo<Vec<Sheet>>, active_sheet: Memo<usize>) -> impl IntoV
                                     </span>
                                 })
                             }}
+                            {modified.then(|| view! {
+                                <span
+                                    class="tab-modified-dot"
+                                    title="Changed since the last save"
+                                    style="margin-left: 4px; color: #757575; font-size: 10px;"
+                                >
+                                    "●"
+                                </span>
+                            })}
+                            {(!health.is_healthy()).then(|| {
+                                let badge_sheet = badge_sheet.clone();
+                                view! {
+                                    <span
+                                        class="tab-error-badge"
+                                        title=health_tooltip(&health)
+                                        on:click=move |ev: MouseEvent| {
+                                            // Don't let the tab's own click handler switch sheets first
+                                            ev.stop_propagation();
+                                            let controller = controller_stored.get_value();
+                                            controller.borrow_mut()
+                                                .dispatch_action(Action::GotoFirstError { sheet: badge_sheet.clone() })
+                                                .unwrap_or_else(|e| {
+                                                    leptos::logging::log!("Error going to first error: {}", e);
+                                                });
+                                        }
+                                        style="margin-left: 6px; padding: 0 5px; border-radius: 8px; background: var(--gc-error-badge); color: var(--gc-badge-text); font-size: 11px; cursor: pointer;"
+                                    >
+                                        {health.error_cells}
+                                    </span>
+                                }
+                            })}
                         </div>
                     }
                 }).collect::<Vec<_>>()}
@@ -186,6 +249,7 @@ pub fn TabBar(sheets: Memo<Vec<Sheet>>, active_sheet: Memo<usize>) -> impl IntoV
 
             <button
                 on:click=add_sheet
+                disabled=move || !can_manage_sheets()
                 style="margin-left: 8px; padding: 2px 8px; cursor: pointer; background: transparent; border: 1px solid #e0e0e0; border-radius: 3px;"
                 title="Add new sheet"
             >
@@ -235,7 +299,7 @@ pub fn TabBar(sheets: Memo<Vec<Sheet>>, active_sheet: Memo<usize>) -> impl IntoV
                                         });
                                     set_show_context_for_delete.set(false);
                                 }
-                                style="padding: 8px 12px; cursor: pointer; color: #d32f2f;"
+                                style="padding: 8px 12px; cursor: pointer; color: var(--gc-error-text);"
                                 onmouseover="this.style.background='#ffebee'"
                                 onmouseout="this.style.background='white'"
                             >
@@ -0,0 +1,155 @@
+use crate::context::use_controller;
+use crate::interaction::focus::{handle_panel_key, use_focus_scope};
+use gridcore_core::template::{
+    EmbeddedTemplates, TemplateInfo, instantiate_template, list_templates,
+};
+use leptos::prelude::*;
+use web_sys::KeyboardEvent;
+
+/// Dialog for starting a new document from one of the built-in templates.
+/// Parameters left blank keep the template's own values.
+#[component]
+pub fn TemplatePicker(open: RwSignal<bool>) -> impl IntoView {
+    let controller_stored = use_controller();
+    let templates = StoredValue::new(list_templates(&EmbeddedTemplates));
+    let (selected, set_selected) = signal(0usize);
+    let values = RwSignal::new(Vec::<String>::new());
+    let (error, set_error) = signal(None::<String>);
+
+    let template = move || -> Option<TemplateInfo> {
+        templates.with_value(|templates| templates.get(selected.get()).cloned())
+    };
+
+    // Fresh inputs whenever the dialog opens or another template is picked
+    Effect::new(move |_| {
+        if open.get() {
+            let count = template().map_or(0, |info| info.parameters.len());
+            values.set(vec![String::new(); count]);
+            set_error.set(None);
+        }
+    });
+
+    // Tab order: the template, its parameters, then the buttons
+    let on_focusin = use_focus_scope(
+        "template-picker",
+        open.into(),
+        Signal::derive(move || {
+            let mut items = vec!["template-picker-select".to_string()];
+            items.extend((0..values.with(Vec::len)).map(parameter_id));
+            items.extend([
+                "template-picker-create".into(),
+                "template-picker-cancel".into(),
+            ]);
+            items
+        }),
+    );
+
+    let create = move || {
+        let Some(info) = template() else {
+            return;
+        };
+        let parameters: Vec<(String, String)> = info
+            .parameters
+            .iter()
+            .zip(values.get_untracked())
+            .filter(|(_, value)| !value.trim().is_empty())
+            .map(|(parameter, value)| (parameter.name.clone(), value))
+            .collect();
+        match instantiate_template(&EmbeddedTemplates, &info.id, &parameters) {
+            Ok(document) => {
+                controller_stored.with_value(|ctrl| ctrl.borrow_mut().open_document(document));
+                open.set(false);
+            }
+            Err(e) => set_error.set(Some(e.to_string())),
+        }
+    };
+
+    let on_keydown = move |ev: KeyboardEvent| {
+        let escape = ev.key() == "Escape";
+        if controller_stored.with_value(|ctrl| handle_panel_key(ctrl, &ev)) {
+            // Escape closed the scope; close the dialog with it
+            if escape {
+                open.set(false);
+            }
+            return;
+        }
+        if ev.key() == "Enter" {
+            ev.prevent_default();
+            create();
+        }
+    };
+
+    view! {
+        <Show when=move || open.get()>
+            <div class="template-picker" on:keydown=on_keydown on:focusin=on_focusin>
+                <div class="template-picker-header">
+                    <span>"New from template"</span>
+                    <button id="template-picker-cancel" on:click=move |_| open.set(false)>
+                        "Cancel"
+                    </button>
+                </div>
+                <select id="template-picker-select" on:change=move |ev| {
+                    if let Ok(index) = event_target_value(&ev).parse() {
+                        set_selected.set(index);
+                    }
+                }>
+                    {templates
+                        .get_value()
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, info)| {
+                            view! {
+                                <option value=index.to_string() selected=move || selected.get() == index>
+                                    {info.name}
+                                </option>
+                            }
+                        })
+                        .collect_view()}
+                </select>
+                <p class="template-picker-description">
+                    {move || template().map(|info| info.description)}
+                </p>
+                {move || {
+                    template()
+                        .map(|info| {
+                            info.parameters
+                                .into_iter()
+                                .enumerate()
+                                .map(|(index, parameter)| {
+                                    view! {
+                                        <label class="template-picker-parameter">
+                                            <span>{parameter.label}</span>
+                                            <input
+                                                id=parameter_id(index)
+                                                type="text"
+                                                placeholder="as in the template"
+                                                prop:value=move || {
+                                                    values.with(|values| values.get(index).cloned().unwrap_or_default())
+                                                }
+                                                on:input=move |ev| {
+                                                    let value = event_target_value(&ev);
+                                                    values.update(|values| {
+                                                        if let Some(slot) = values.get_mut(index) {
+                                                            *slot = value;
+                                                        }
+                                                    });
+                                                }
+                                            />
+                                        </label>
+                                    }
+                                })
+                                .collect_view()
+                        })
+                }}
+                {move || error.get().map(|error| view! { <div class="template-picker-error">{error}</div> })}
+                <button id="template-picker-create" class="template-picker-create" on:click=move |_| create()>
+                    "Create"
+                </button>
+            </div>
+        </Show>
+    }
+}
+
+fn parameter_id(index: usize) -> String {
+    format!("template-picker-parameter-{}", index)
+}
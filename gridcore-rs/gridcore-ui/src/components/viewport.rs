@@ -1,6 +1,6 @@
 use crate::rendering::GridTheme;
 use gridcore_controller::controller::{
-    CellPosition, ScrollPosition, SpreadsheetController, ViewportBounds,
+    CellPosition, ScrollPosition, ScrollbarMetrics, SpreadsheetController, ViewportBounds,
 };
 use gridcore_core::types::CellAddress;
 use std::cell::RefCell;
@@ -18,12 +18,22 @@ impl Viewport {
         Self { theme, controller }
     }
 
+    /// Rows that can be scrolled to, growing as data or the cursor moves out
     pub fn get_total_rows(&self) -> usize {
-        self.controller.borrow().get_config().total_rows
+        self.controller
+            .borrow()
+            .get_viewport_manager()
+            .get_extent()
+            .rows() as usize
     }
 
+    /// Columns that can be scrolled to, growing as data or the cursor moves out
     pub fn get_total_cols(&self) -> usize {
-        self.controller.borrow().get_config().total_cols
+        self.controller
+            .borrow()
+            .get_viewport_manager()
+            .get_extent()
+            .cols() as usize
     }
 
     pub fn get_theme(&self) -> &GridTheme {
@@ -38,10 +48,11 @@ impl Viewport {
     }
 
     pub fn set_scroll_position(&mut self, x: f64, y: f64) {
-        self.controller
-            .borrow_mut()
+        let mut controller = self.controller.borrow_mut();
+        controller
             .get_viewport_manager_mut()
             .set_scroll_position(x, y);
+        controller.note_viewport_scrolled();
     }
 
     pub fn get_scroll_position(&self) -> ScrollPosition {
@@ -65,18 +76,31 @@ impl Viewport {
             .get_viewport_height()
     }
 
+    /// Scroll right away, without waiting for the next frame
     pub fn scroll_by(&mut self, delta_x: f64, delta_y: f64) {
+        self.controller.borrow_mut().scroll_now(delta_x, delta_y);
+    }
+
+    /// Queue a wheel delta for [`Self::apply_pending_scroll`]
+    pub fn queue_scroll(&mut self, delta_x: f64, delta_y: f64) {
+        self.controller.borrow_mut().queue_scroll(delta_x, delta_y);
+    }
+
+    /// Apply the wheel scroll queued since the last frame. Returns whether
+    /// the view moved.
+    pub fn apply_pending_scroll(&mut self) -> bool {
         self.controller
             .borrow_mut()
-            .get_viewport_manager_mut()
-            .scroll_by(delta_x, delta_y);
+            .apply_pending_scroll()
+            .is_some()
     }
 
     pub fn scroll_to_cell(&mut self, cell: &CellAddress, position: &str) {
-        self.controller
-            .borrow_mut()
+        let mut controller = self.controller.borrow_mut();
+        controller
             .get_viewport_manager_mut()
             .scroll_to_cell(cell, position);
+        controller.note_viewport_scrolled();
     }
 
     pub fn get_column_width(&self, col: usize) -> f64 {
@@ -128,6 +152,22 @@ impl Viewport {
             .get_cell_at_position(x, y)
     }
 
+    /// Cell under a point of the cell area, see [`ViewportManager::cell_at_point`]
+    pub fn cell_at_point(&self, x: f64, y: f64) -> Option<CellAddress> {
+        self.controller
+            .borrow()
+            .get_viewport_manager()
+            .cell_at_point(x, y)
+    }
+
+    /// Top-left corner of a cell in the coordinates of [`Self::cell_at_point`]
+    pub fn point_of_cell(&self, address: &CellAddress) -> (f64, f64) {
+        self.controller
+            .borrow()
+            .get_viewport_manager()
+            .point_of_cell(address)
+    }
+
     pub fn get_column_x(&self, col: usize) -> f64 {
         self.controller
             .borrow()
@@ -155,4 +195,10 @@ impl Viewport {
             .get_viewport_manager()
             .get_total_grid_height()
     }
+
+    pub fn get_scrollbars(&self) -> (ScrollbarMetrics, ScrollbarMetrics) {
+        let controller = self.controller.borrow();
+        let manager = controller.get_viewport_manager();
+        (manager.horizontal_scrollbar(), manager.vertical_scrollbar())
+    }
 }
@@ -0,0 +1,125 @@
+use crate::context::{use_controller, use_state_generation};
+use gridcore_controller::state::Action;
+use gridcore_core::types::CellAddress;
+use leptos::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+struct WatchRow {
+    sheet: String,
+    address: CellAddress,
+    label: String,
+    qualified_address: String,
+    value: String,
+    stale: bool,
+    change_count: u32,
+}
+
+/// Docked panel listing watched cells and their current values
+#[component]
+pub fn WatchPanel() -> impl IntoView {
+    let controller_stored = use_controller();
+    let state_generation = use_state_generation();
+    let (docked_bottom, set_docked_bottom) = signal(false);
+
+    let rows = Signal::derive(move || {
+        state_generation.get(); // Track changes
+        controller_stored.with_value(|ctrl| {
+            ctrl.borrow()
+                .get_watch_list()
+                .cells()
+                .iter()
+                .map(|cell| WatchRow {
+                    sheet: cell.entry.sheet.clone(),
+                    address: cell.entry.address,
+                    label: cell.entry.label.clone().unwrap_or_default(),
+                    qualified_address: cell.entry.qualified_address(),
+                    value: if cell.stale {
+                        "#REF!".to_string()
+                    } else {
+                        cell.value.clone().unwrap_or_default()
+                    },
+                    stale: cell.stale,
+                    change_count: cell.change_count,
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+
+    let dispatch = move |action: Action| {
+        controller_stored.with_value(|ctrl| {
+            if let Err(e) = ctrl.borrow_mut().dispatch_action(action) {
+                leptos::logging::log!("Error handling watch action: {}", e);
+            }
+        });
+    };
+
+    view! {
+        <Show when=move || !rows.get().is_empty()>
+            <div class=move || {
+                if docked_bottom.get() { "watch-panel docked-bottom" } else { "watch-panel docked-right" }
+            }>
+                <div class="watch-panel-header">
+                    <span>"Watch"</span>
+                    <button
+                        class="watch-dock-toggle"
+                        title="Move panel"
+                        on:click=move |_| set_docked_bottom.update(|bottom| *bottom = !*bottom)
+                    >
+                        {move || if docked_bottom.get() { "⇥" } else { "⤓" }}
+                    </button>
+                </div>
+                <table class="watch-table">
+                    <tbody>
+                        // Rows are keyed on the change count so the flash animation replays
+                        <For
+                            each=move || rows.get()
+                            key=|row| (row.qualified_address.clone(), row.change_count)
+                            children=move |row| {
+                                let goto_sheet = row.sheet.clone();
+                                let goto_address = row.address;
+                                let remove_sheet = row.sheet.clone();
+                                let remove_address = row.address;
+                                let class = match (row.stale, row.change_count > 0) {
+                                    (true, _) => "watch-row stale",
+                                    (false, true) => "watch-row watch-flash",
+                                    (false, false) => "watch-row",
+                                };
+
+                                view! {
+                                    <tr
+                                        class=class
+                                        on:click=move |_| {
+                                            dispatch(Action::GotoCell {
+                                                address: goto_address,
+                                                sheet: Some(goto_sheet.clone()),
+                                            })
+                                        }
+                                    >
+                                        <td class="watch-label">{row.label}</td>
+                                        <td class="watch-address">{row.qualified_address}</td>
+                                        <td class="watch-value">{row.value}</td>
+                                        <td>
+                                            <button
+                                                class="watch-remove"
+                                                aria-label="Remove watch"
+                                                on:click=move |ev| {
+                                                    ev.stop_propagation();
+                                                    dispatch(Action::RemoveWatch {
+                                                        address: remove_address,
+                                                        sheet: Some(remove_sheet.clone()),
+                                                    })
+                                                }
+                                            >
+                                                "×"
+                                            </button>
+                                        </td>
+                                    </tr>
+                                }
+                            }
+                        />
+                    </tbody>
+                </table>
+            </div>
+        </Show>
+    }
+}
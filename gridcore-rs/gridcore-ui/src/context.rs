@@ -1,4 +1,5 @@
 use crate::components::viewport::Viewport;
+use crate::reactive::ConcernSignals;
 use gridcore_controller::controller::SpreadsheetController;
 use leptos::prelude::*;
 use std::cell::RefCell;
@@ -16,6 +17,8 @@ pub struct AppState {
     pub state_generation: RwSignal<u32>,
     /// Signal that increments when render is needed
     pub render_generation: RwSignal<u32>,
+    /// Signals that change only with the part of the state they name
+    pub concerns: ConcernSignals,
     /// Device pixel ratio for high-DPI displays
     pub device_pixel_ratio: Signal<f64>,
 }
@@ -39,6 +42,11 @@ pub fn use_reactive_signals() -> (RwSignal<u32>, RwSignal<u32>) {
     (state.state_generation, state.render_generation)
 }
 
+/// Get the per-concern signals from context.
+pub fn use_concerns() -> ConcernSignals {
+    use_app_state().concerns
+}
+
 /// Get just the state generation signal from context.
 pub fn use_state_generation() -> RwSignal<u32> {
     use_app_state().state_generation
@@ -0,0 +1,199 @@
+use crate::context::use_controller;
+use gridcore_controller::controller::events::SpreadsheetEvent;
+use gridcore_controller::state::{ActionLogEntry, HandlerPath};
+use leptos::prelude::*;
+
+/// One logged action as the overlay lists it
+#[derive(Clone, PartialEq)]
+struct EntryRow {
+    sequence: u64,
+    timestamp_ms: i64,
+    action: String,
+    modes: String,
+    path: &'static str,
+    error: Option<String>,
+}
+
+impl EntryRow {
+    fn new(entry: &ActionLogEntry) -> Self {
+        let modes = if entry.mode_before == entry.mode_after {
+            format!("{:?}", entry.mode_after)
+        } else {
+            format!("{:?} → {:?}", entry.mode_before, entry.mode_after)
+        };
+        Self {
+            sequence: entry.sequence,
+            timestamp_ms: entry.timestamp_ms,
+            action: action_name(entry),
+            modes,
+            path: match entry.path {
+                HandlerPath::Refused => "refused",
+                HandlerPath::Vetoed => "vetoed",
+                HandlerPath::Direct => "direct",
+                HandlerPath::ViaPlugins => "plugins",
+            },
+            error: entry.error.clone(),
+        }
+    }
+}
+
+/// UTC time of day of a timestamp, e.g. `14:03:27.045`
+fn time_of_day(timestamp_ms: i64) -> String {
+    let ms = timestamp_ms.rem_euclid(86_400_000);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// The action's variant, e.g. `UpdateCursor`
+fn action_name(entry: &ActionLogEntry) -> String {
+    format!("{:?}", entry.action)
+        .chars()
+        .take_while(char::is_ascii_alphanumeric)
+        .collect()
+}
+
+/// Collapsible list of the controller's last dispatched actions, shown in
+/// debug mode. Clicking an action shows the state diff it caused; the copy
+/// button puts a bug report replaying the session on the clipboard.
+#[component]
+pub fn ActionLogOverlay(#[prop(into)] visible: Signal<bool>) -> impl IntoView {
+    let controller_stored = use_controller();
+    let (expanded, set_expanded) = signal(true);
+    let (selected, set_selected) = signal(None::<u64>);
+    let (copied, set_copied) = signal(None::<String>);
+    let logged = RwSignal::new(0u64);
+
+    // Entries are read when the list redraws, not while the controller
+    // that sent the event is still busy
+    let subscription = controller_stored.with_value(|ctrl| {
+        ctrl.borrow_mut()
+            .subscribe_to_events(move |event: &SpreadsheetEvent| {
+                if let SpreadsheetEvent::ActionLogged { sequence } = event {
+                    logged.set(*sequence);
+                }
+            })
+    });
+    on_cleanup(move || {
+        controller_stored.with_value(|ctrl| {
+            if let Ok(mut ctrl) = ctrl.try_borrow_mut() {
+                ctrl.unsubscribe_from_events(subscription);
+            }
+        })
+    });
+
+    let rows = move || {
+        logged.track();
+        controller_stored.with_value(|ctrl| {
+            let Ok(ctrl) = ctrl.try_borrow() else {
+                return Vec::new();
+            };
+            ctrl.action_log()
+                .entries()
+                .rev()
+                .map(EntryRow::new)
+                .collect::<Vec<_>>()
+        })
+    };
+
+    let diff = move || {
+        let sequence = selected.get()?;
+        logged.track();
+        controller_stored.with_value(|ctrl| {
+            let ctrl = ctrl.try_borrow().ok()?;
+            Some(ctrl.action_log().get(sequence)?.state_diff_json())
+        })
+    };
+
+    let copy_report = move |_| {
+        let report = controller_stored
+            .with_value(|ctrl| ctrl.try_borrow().ok().and_then(|ctrl| ctrl.bug_report()));
+        let Some(report) = report else {
+            set_copied.set(Some("Nothing logged yet".to_string()));
+            return;
+        };
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let _ = window.navigator().clipboard().write_text(&report.to_json());
+        let note = match report.dropped {
+            0 => format!("Copied {} actions", report.actions.len()),
+            dropped => format!(
+                "Copied {} actions; {} older ones were dropped",
+                report.actions.len(),
+                dropped
+            ),
+        };
+        set_copied.set(Some(note));
+    };
+
+    let clear = move |_| {
+        controller_stored.with_value(|ctrl| ctrl.borrow_mut().clear_action_log());
+        set_selected.set(None);
+        set_copied.set(None);
+        logged.update(|sequence| *sequence = sequence.wrapping_add(1));
+    };
+
+    view! {
+        <Show when=move || visible.get()>
+            <div class="action-log">
+                <div class="action-log-header">
+                    <button
+                        class="action-log-toggle"
+                        on:click=move |_| set_expanded.update(|open| *open = !*open)
+                    >
+                        {move || if expanded.get() { "▾ Actions" } else { "▸ Actions" }}
+                    </button>
+                    <span class="action-log-note">{move || copied.get()}</span>
+                    <button on:click=copy_report>"Copy bug report"</button>
+                    <button on:click=clear>"Clear"</button>
+                </div>
+                <Show when=move || expanded.get()>
+                    <div class="action-log-entries">
+                        <For
+                            each=rows
+                            key=|row| (row.sequence, row.timestamp_ms)
+                            children=move |row| {
+                                let sequence = row.sequence;
+                                let failed = row.error.is_some();
+                                view! {
+                                    <div
+                                        class="action-log-entry"
+                                        class:selected=move || selected.get() == Some(sequence)
+                                        class:failed=failed
+                                        on:click=move |_| {
+                                            set_selected
+                                                .update(|selected| {
+                                                    *selected = (*selected != Some(sequence))
+                                                        .then_some(sequence);
+                                                })
+                                        }
+                                    >
+                                        <span class="action-log-time">
+                                            {time_of_day(row.timestamp_ms)}
+                                        </span>
+                                        <span class="action-log-name">{row.action}</span>
+                                        <span class="action-log-modes">{row.modes}</span>
+                                        <span class="action-log-path">{row.path}</span>
+                                        {row
+                                            .error
+                                            .map(|error| {
+                                                view! { <span class="action-log-error">{error}</span> }
+                                            })}
+                                    </div>
+                                }
+                            }
+                        />
+                    </div>
+                    {move || {
+                        diff().map(|json| view! { <pre class="action-log-diff">{json}</pre> })
+                    }}
+                </Show>
+            </div>
+        </Show>
+    }
+}
@@ -1,3 +1,9 @@
+#[cfg(feature = "debug")]
+mod action_log_overlay;
+
+#[cfg(feature = "debug")]
+pub use action_log_overlay::ActionLogOverlay;
+
 use leptos::prelude::*;
 use std::sync::atomic::{AtomicBool, Ordering};
 
@@ -0,0 +1,59 @@
+//! Browser host for `FETCH` formulas. Core only queues requests; this
+//! module performs them with `window.fetch` and hands the bodies back.
+
+use gridcore_controller::controller::SpreadsheetController;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{JsFuture, spawn_local};
+
+/// Start a fetch for every request the controller has queued. Results of
+/// requests cancelled in the meantime are ignored by the controller.
+pub fn dispatch_external_requests(controller: &Rc<RefCell<SpreadsheetController>>) {
+    let requests = {
+        let ctrl = controller.borrow();
+        // Browser fetches are not aborted; their late results are dropped
+        ctrl.take_cancelled_external_requests();
+        ctrl.take_external_requests()
+    };
+
+    for request in requests {
+        let controller = controller.clone();
+        spawn_local(async move {
+            let response = fetch_text(&request.url).await;
+            if let Err(e) = controller.borrow_mut().resolve_external(&request, response) {
+                leptos::logging::log!("Failed to apply data from {}: {}", request.url, e);
+            }
+        });
+    }
+}
+
+async fn fetch_text(url: &str) -> Result<String, String> {
+    let window = web_sys::window().ok_or_else(|| "No window available".to_string())?;
+    let response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(describe)?;
+    let response: web_sys::Response = response.dyn_into().map_err(describe)?;
+
+    if !response.ok() {
+        return Err(format!(
+            "HTTP {} {}",
+            response.status(),
+            response.status_text()
+        ));
+    }
+
+    let body = JsFuture::from(response.text().map_err(describe)?)
+        .await
+        .map_err(describe)?;
+    body.as_string()
+        .ok_or_else(|| "Response body is not text".to_string())
+}
+
+fn describe(error: JsValue) -> String {
+    error
+        .dyn_ref::<js_sys::Error>()
+        .map(|e| String::from(e.message()))
+        .or_else(|| error.as_string())
+        .unwrap_or_else(|| "Network error".to_string())
+}
@@ -0,0 +1,33 @@
+//! Functions the page embedding the spreadsheet calls from JavaScript
+
+use gridcore_controller::controller::SpreadsheetController;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    /// Controller of the mounted app, held weakly so unmounting frees it
+    static CONTROLLER: RefCell<Weak<RefCell<SpreadsheetController>>> =
+        const { RefCell::new(Weak::new()) };
+}
+
+/// Make `controller` the one the host functions act on
+pub fn attach(controller: &Rc<RefCell<SpreadsheetController>>) {
+    CONTROLLER.with(|slot| *slot.borrow_mut() = Rc::downgrade(controller));
+}
+
+/// Refuse or allow changes to the workbook, e.g. to embed it as a report
+/// viewer. Does nothing before the app is mounted.
+#[wasm_bindgen]
+pub fn set_read_only(read_only: bool) {
+    if let Some(controller) = CONTROLLER.with(|slot| slot.borrow().upgrade()) {
+        controller.borrow_mut().set_read_only(read_only);
+    }
+}
+
+#[wasm_bindgen]
+pub fn is_read_only() -> bool {
+    CONTROLLER
+        .with(|slot| slot.borrow().upgrade())
+        .is_some_and(|controller| controller.borrow().is_read_only())
+}
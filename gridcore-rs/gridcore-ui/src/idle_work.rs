@@ -0,0 +1,72 @@
+//! Browser driver for the controller's idle work queue. Slices run from
+//! `requestIdleCallback` where the browser has it and from a zero-delay
+//! timer otherwise, each within a small time budget so input is never held
+//! up for long.
+
+use gridcore_controller::controller::SpreadsheetController;
+use leptos::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::closure::Closure;
+
+type ControllerStore = StoredValue<Rc<RefCell<SpreadsheetController>>, LocalStorage>;
+
+/// Longest a slice may run, even when the browser offers more idle time
+const SLICE_BUDGET_MS: f64 = 8.0;
+
+thread_local! {
+    /// Whether a slice is already requested, so frames drawn meanwhile do
+    /// not request more
+    static SCHEDULED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Queue the idle work the current view calls for and request a slice to
+/// run it, unless one is already on its way
+pub fn schedule_idle_work(controller: ControllerStore) {
+    if SCHEDULED.get() {
+        return;
+    }
+    let has_work = controller
+        .try_with_value(|ctrl| ctrl.borrow_mut().schedule_idle_work())
+        .unwrap_or(false);
+    if has_work {
+        SCHEDULED.set(true);
+        request_slice(controller);
+    }
+}
+
+fn request_slice(controller: ControllerStore) {
+    if let Some(window) = web_sys::window()
+        && js_sys::Reflect::has(&window, &"requestIdleCallback".into()).unwrap_or(false)
+    {
+        let callback = Closure::once_into_js(move |deadline: web_sys::IdleDeadline| {
+            run_slice(controller, deadline.time_remaining().min(SLICE_BUDGET_MS));
+        });
+        if window
+            .request_idle_callback(callback.unchecked_ref())
+            .is_ok()
+        {
+            return;
+        }
+    }
+    gloo_timers::callback::Timeout::new(0, move || run_slice(controller, SLICE_BUDGET_MS)).forget();
+}
+
+fn run_slice(controller: ControllerStore, budget_ms: f64) {
+    let performance = web_sys::window().and_then(|window| window.performance());
+    let clock = || {
+        performance
+            .as_ref()
+            .map_or(0.0, |performance| performance.now())
+    };
+    // The grid may have been unmounted before the slice ran
+    let more = controller
+        .try_with_value(|ctrl| ctrl.borrow_mut().run_idle_work(budget_ms, &clock))
+        .unwrap_or(false);
+    if more {
+        request_slice(controller);
+    } else {
+        SCHEDULED.set(false);
+    }
+}
@@ -0,0 +1,105 @@
+use gridcore_controller::controller::{FocusTarget, KeyboardEvent, SpreadsheetController};
+use leptos::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+
+/// Element taking keys for the grid
+pub const GRID_SELECTOR: &str = ".grid-container";
+
+/// Move DOM focus to what the controller says has focus. Panel items are
+/// element ids. Focus moves on the next frame, so a panel that has just
+/// opened is mounted by then.
+pub fn focus_target(target: &FocusTarget) {
+    let target = target.clone();
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let closure = wasm_bindgen::closure::Closure::once(move || {
+        let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+            return;
+        };
+        let element = match &target {
+            FocusTarget::Grid => document.query_selector(GRID_SELECTOR).ok().flatten(),
+            FocusTarget::Panel { item: Some(id), .. } => document.get_element_by_id(id),
+            FocusTarget::Panel { item: None, .. } => None,
+        };
+        if let Some(element) = element.and_then(|e| e.dyn_into::<web_sys::HtmlElement>().ok()) {
+            let _ = element.focus();
+        }
+    });
+    window
+        .request_animation_frame(closure.as_ref().unchecked_ref())
+        .ok();
+    closure.forget();
+}
+
+/// Register a panel as focus scope `id` while `open`, Tab cycling through
+/// the elements with the ids in `items`. Returns a `focusin` handler for
+/// the panel, so clicking back into it after Escape gives it focus again.
+pub fn use_focus_scope(
+    id: &'static str,
+    open: Signal<bool>,
+    items: Signal<Vec<String>>,
+) -> impl Fn(web_sys::FocusEvent) + Copy + 'static {
+    let controller_stored = crate::context::use_controller();
+
+    Effect::new(move |_| {
+        let opened = open.get();
+        let items = items.get_untracked();
+        controller_stored.with_value(|ctrl| {
+            let mut ctrl = ctrl.borrow_mut();
+            if opened {
+                ctrl.push_focus_scope(id, items);
+            } else {
+                ctrl.close_focus_scope(id);
+            }
+        });
+    });
+    Effect::new(move |_| {
+        let items = items.get();
+        controller_stored.with_value(|ctrl| ctrl.borrow_mut().set_focus_scope_items(id, items));
+    });
+
+    move |ev: web_sys::FocusEvent| {
+        let Some(element) = ev
+            .target()
+            .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+        else {
+            return;
+        };
+        let item = element.id();
+        controller_stored.with_value(|ctrl| {
+            let mut ctrl = ctrl.borrow_mut();
+            let focused = matches!(
+                ctrl.focus(),
+                FocusTarget::Panel { scope, item: Some(current) } if scope == id && current == item
+            );
+            if !focused {
+                ctrl.push_focus_scope(id, items.get_untracked());
+                ctrl.focus_item(id, &item);
+            }
+        });
+    }
+}
+
+/// Keydown handling shared by panels: Tab and Escape move focus through
+/// the controller, and no key reaches the grid's own handlers. Returns
+/// whether the key moved focus.
+pub fn handle_panel_key(
+    controller: &Rc<RefCell<SpreadsheetController>>,
+    ev: &web_sys::KeyboardEvent,
+) -> bool {
+    ev.stop_propagation();
+    let event = KeyboardEvent::new(ev.key()).with_modifiers(
+        ev.shift_key(),
+        ev.ctrl_key(),
+        ev.alt_key(),
+        ev.meta_key(),
+    );
+    let moved = controller.borrow_mut().handle_focus_key(&event);
+    if moved {
+        ev.prevent_default();
+    }
+    moved
+}
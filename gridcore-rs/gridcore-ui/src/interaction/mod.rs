@@ -1,7 +1,9 @@
 pub mod auto_scroll;
+pub mod focus;
 pub mod keyboard_handler;
 pub mod mouse_handler;
 pub mod resize_handler;
+pub mod wheel_scroll;
 
 pub use auto_scroll::AutoScroller;
 pub use keyboard_handler::KeyboardHandler;
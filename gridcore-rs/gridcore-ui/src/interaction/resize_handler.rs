@@ -1,5 +1,6 @@
 use gridcore_controller::behaviors::resize::{self, ResizeType};
 use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::state::{Action, ResizeTarget};
 use web_sys::MouseEvent;
 
 #[derive(Clone)]
@@ -34,7 +35,7 @@ impl ResizeHandler {
             // Check column header for resize - only check visible columns
             let mut current_x = 0.0;
             let scroll_x = viewport_manager.get_scroll_position().x;
-            
+
             // Start from first visible column
             for col in 0..visible_bounds.start_col {
                 current_x += viewport_manager.get_column_width(col);
@@ -59,7 +60,7 @@ impl ResizeHandler {
             // Check row header for resize - only check visible rows
             let mut current_y = 0.0;
             let scroll_y = viewport_manager.get_scroll_position().y;
-            
+
             // Start from first visible row
             for row in 0..visible_bounds.start_row {
                 current_y += viewport_manager.get_row_height(row);
@@ -92,26 +93,20 @@ impl ResizeHandler {
         index: usize,
         controller: &mut SpreadsheetController,
     ) {
-        let viewport_manager = controller.get_viewport_manager();
-
-        let start_position = match resize_type {
-            ResizeType::Column => event.client_x() as f64,
-            ResizeType::Row => event.client_y() as f64,
-            ResizeType::None => 0.0,
-        };
-
-        let start_size = match resize_type {
-            ResizeType::Column => viewport_manager.get_column_width(index),
-            ResizeType::Row => viewport_manager.get_row_height(index),
-            ResizeType::None => 0.0,
+        let index = index as u32;
+        let (target, initial_position) = match resize_type {
+            ResizeType::Column => (ResizeTarget::Column { index }, event.client_x() as f64),
+            ResizeType::Row => (ResizeTarget::Row { index }, event.client_y() as f64),
+            ResizeType::None => return,
         };
 
-        // Use pure function to create resize state
-        let new_state =
-            resize::start_mouse_resize(resize_type, index, start_position, start_size);
-
-        // Update the controller's resize state
-        *controller.resize_state_mut() = new_state;
+        // Selected columns or rows including this one are resized with it
+        if let Err(e) = controller.dispatch_action(Action::StartResize {
+            target,
+            initial_position,
+        }) {
+            leptos::logging::log!("Error starting resize: {}", e);
+        }
     }
 
     pub fn handle_resize(&self, event: &MouseEvent, controller: &mut SpreadsheetController) {
@@ -138,35 +133,21 @@ impl ResizeHandler {
             ResizeType::None => return,
         };
 
-        // Update resize using pure function
-        if let Some((resize_type, index, new_size)) =
+        // The size stays provisional until the mouse is released
+        if let Some((_, _, new_size)) =
             resize::update_mouse_resize(resize_state, current_position, min_size, max_size)
         {
-            // Update the resize state
-            controller.resize_state_mut().current_size = new_size;
-
-            // Apply the resize to the viewport
-            let viewport_manager = controller.get_viewport_manager_mut();
-            match resize_type {
-                ResizeType::Column => {
-                    viewport_manager.set_column_width(index, new_size);
-                }
-                ResizeType::Row => {
-                    viewport_manager.set_row_height(index, new_size);
-                }
-                ResizeType::None => {}
+            let delta = new_size - resize_state.current_size;
+            if delta != 0.0 {
+                let _ = controller.dispatch_action(Action::UpdateResize { delta });
             }
         }
     }
 
     pub fn end_resize(&self, controller: &mut SpreadsheetController) {
-        let resize_state = controller.resize_state();
-
-        // Use pure function to end resize
-        resize::end_mouse_resize(resize_state);
-
-        // Reset the resize state
-        *controller.resize_state_mut() = Default::default();
+        if let Err(e) = controller.dispatch_action(Action::ConfirmResize) {
+            leptos::logging::log!("Error resizing: {}", e);
+        }
     }
 
     pub fn is_resizing(&self, controller: &SpreadsheetController) -> bool {
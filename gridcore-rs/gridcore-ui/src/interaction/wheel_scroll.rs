@@ -0,0 +1,47 @@
+//! Browser driver for batched wheel scrolling. Wheel deltas are queued on
+//! the controller and applied from one `requestAnimationFrame` callback per
+//! frame, however many wheel events arrive in between.
+
+use crate::components::viewport::Viewport;
+use leptos::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::closure::Closure;
+
+type ViewportStore = StoredValue<Rc<RefCell<Viewport>>, LocalStorage>;
+
+thread_local! {
+    /// Whether a frame is already requested for the queued scroll
+    static FRAME_REQUESTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Queue a wheel delta and request a frame to apply it, unless one is
+/// already on its way
+pub fn queue_wheel_scroll(viewport: ViewportStore, delta_x: f64, delta_y: f64) {
+    viewport.with_value(|vp| vp.borrow_mut().queue_scroll(delta_x, delta_y));
+    #[cfg(feature = "perf")]
+    crate::perf::record_scroll();
+    if FRAME_REQUESTED.get() {
+        return;
+    }
+    let Some(window) = web_sys::window() else {
+        apply_frame(viewport);
+        return;
+    };
+    let callback = Closure::once_into_js(move |_time: f64| apply_frame(viewport));
+    if window
+        .request_animation_frame(callback.unchecked_ref())
+        .is_ok()
+    {
+        FRAME_REQUESTED.set(true);
+    } else {
+        apply_frame(viewport);
+    }
+}
+
+fn apply_frame(viewport: ViewportStore) {
+    FRAME_REQUESTED.set(false);
+    // The grid may have been unmounted before the frame
+    let _ = viewport.try_with_value(|vp| vp.borrow_mut().apply_pending_scroll());
+}
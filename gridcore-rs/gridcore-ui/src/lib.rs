@@ -2,6 +2,9 @@ pub mod app;
 pub mod components;
 pub mod context;
 pub mod debug;
+pub mod external_fetch;
+pub mod host;
+pub mod idle_work;
 pub mod interaction;
 pub mod reactive;
 pub mod rendering;
@@ -18,7 +21,7 @@ pub mod metrics_recorder;
 
 // Re-export demo components when demo feature is enabled
 #[cfg(feature = "demo")]
-pub use gridcore_demo::components::{DemoProgressBar, PerformanceOverlay};
+pub use gridcore_demo::components::{DemoProgressBar, PerformanceOverlay, TutorialCallout};
 #[cfg(feature = "demo")]
 pub use gridcore_demo::{DemoConfig, DemoController, DemoMode, demo};
 
@@ -26,7 +29,8 @@ use app::App;
 use leptos::prelude::*;
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen(start)]
+// The browser tests draw to their own canvas and have no #app to mount into
+#[cfg_attr(not(feature = "perf-test"), wasm_bindgen(start))]
 pub fn run_app() {
     // Set panic hook for better error messages in browser console (debug builds only)
     #[cfg(feature = "debug")]
@@ -1,12 +1,16 @@
 //! Metrics collection system for real-time performance monitoring
 
-use std::cell::RefCell;
-use std::collections::HashMap;
+use gridcore_demo::benchmark::results::suggestions::{LiveAdvisor, LiveSample, Suggestion};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use wasm_bindgen::prelude::*;
 use web_sys::Performance;
 
+/// How long after the last scroll event the grid still counts as scrolling
+const SCROLL_IDLE_MS: f64 = 250.0;
+
 /// Metrics snapshot containing current values
 #[derive(Debug, Clone, Default)]
 #[wasm_bindgen]
@@ -30,6 +34,10 @@ pub struct MetricsSnapshot {
     pub action_dispatch_time_p99: f64,
     pub render_time_ms: f64,
 
+    // Frames drawn over the last second, and whether the grid is scrolling
+    pub fps: f64,
+    pub scrolling: bool,
+
     // Rates (operations per second)
     pub formula_eval_rate: f64,
     pub cell_read_rate: f64,
@@ -41,6 +49,15 @@ pub struct MetricsSnapshot {
     pub formula_count: usize,
     pub memory_usage_mb: f64,
 
+    // Idle work
+    pub idle_items_processed: u64,
+    pub idle_cancellations: u64,
+    pub idle_time_ms: f64,
+
+    // Reactive signals
+    pub signal_updates: u64,
+    pub signal_updates_per_action: f64,
+
     // Timestamp
     pub timestamp: f64,
 }
@@ -135,6 +152,11 @@ pub struct MetricsCollector {
 
     // Previous values for rate calculation
     prev_values: Rc<RefCell<HashMap<String, u64>>>,
+
+    // Start and duration of the frames drawn over the last second
+    frames: RefCell<VecDeque<(f64, f64)>>,
+    last_scroll_time: Cell<f64>,
+    advisor: RefCell<LiveAdvisor>,
 }
 
 impl Default for MetricsCollector {
@@ -153,6 +175,9 @@ impl Default for MetricsCollector {
             action_dispatch_counter: None,
             cursor_move_counter: None,
             prev_values: Rc::new(RefCell::new(HashMap::new())),
+            frames: RefCell::new(VecDeque::new()),
+            last_scroll_time: Cell::new(f64::NEG_INFINITY),
+            advisor: RefCell::new(LiveAdvisor::default()),
         }
     }
 }
@@ -219,6 +244,9 @@ impl MetricsCollector {
         // Get memory usage
         let memory_usage_mb = self.get_memory_usage();
 
+        let (fps, render_time_ms) = self.frame_stats(current_time);
+        let scrolling = current_time - self.last_scroll_time.get() < SCROLL_IDLE_MS;
+
         // TODO: Implement histogram percentile calculations
         // For now, use placeholder values
 
@@ -238,7 +266,10 @@ impl MetricsCollector {
             action_dispatch_time_p50: 0.0,
             action_dispatch_time_p95: 0.0,
             action_dispatch_time_p99: 0.0,
-            render_time_ms: 0.0,
+            render_time_ms,
+
+            fps,
+            scrolling,
 
             formula_eval_rate,
             cell_read_rate,
@@ -249,16 +280,68 @@ impl MetricsCollector {
             formula_count: 0, // TODO: Get from controller
             memory_usage_mb,
 
+            idle_items_processed: 0, // Filled from the controller
+            idle_cancellations: 0,
+            idle_time_ms: 0.0,
+
+            signal_updates: 0, // Filled from the reactive state
+            signal_updates_per_action: 0.0,
+
             timestamp: current_time,
         }
     }
 
+    /// Note a drawn frame that took `duration_ms`
+    pub fn record_frame(&self, duration_ms: f64) {
+        let now = self.performance.now();
+        self.frames.borrow_mut().push_back((now, duration_ms));
+        self.frame_stats(now);
+    }
+
+    pub fn record_scroll(&self) {
+        self.last_scroll_time.set(self.performance.now());
+    }
+
+    /// Frames drawn over the second before `now`, and their average
+    /// duration, dropping older frames
+    fn frame_stats(&self, now: f64) -> (f64, f64) {
+        let mut frames = self.frames.borrow_mut();
+        while frames
+            .front()
+            .is_some_and(|&(start, _)| now - start > 1000.0)
+        {
+            frames.pop_front();
+        }
+        let count = frames.len() as f64;
+        let average = if frames.is_empty() {
+            0.0
+        } else {
+            frames.iter().map(|&(_, duration)| duration).sum::<f64>() / count
+        };
+        (count, average)
+    }
+
+    /// Add `snapshot` to the rolling window and return what the window
+    /// suggests, by the same rules as benchmark reports
+    pub fn advise(&self, snapshot: &MetricsSnapshot) -> Vec<Suggestion> {
+        let mut advisor = self.advisor.borrow_mut();
+        advisor.push(LiveSample {
+            timestamp_ms: snapshot.timestamp,
+            fps: snapshot.fps,
+            frame_time_ms: snapshot.render_time_ms,
+            memory_mb: snapshot.memory_usage_mb,
+            scrolling: snapshot.scrolling,
+        });
+        advisor.suggestions()
+    }
+
     fn get_memory_usage(&self) -> f64 {
         // Try to get memory usage from performance.memory if available
         if let Ok(memory) = js_sys::Reflect::get(&self.performance, &"memory".into())
             && let Ok(used_js_heap_size) = js_sys::Reflect::get(&memory, &"usedJSHeapSize".into())
-            && let Some(bytes) = used_js_heap_size.as_f64() {
-                return bytes / (1024.0 * 1024.0); // Convert to MB
+            && let Some(bytes) = used_js_heap_size.as_f64()
+        {
+            return bytes / (1024.0 * 1024.0); // Convert to MB
         }
         0.0
     }
@@ -41,6 +41,22 @@ pub fn get_metrics_collector() -> Option<Rc<RefCell<crate::metrics_collector::Me
     METRICS_COLLECTOR.with(|cell| cell.get().cloned())
 }
 
+/// Note a drawn grid frame for the live FPS and frame time
+#[cfg(feature = "perf")]
+pub fn record_frame(duration_ms: f64) {
+    if let Some(collector) = get_metrics_collector() {
+        collector.borrow().record_frame(duration_ms);
+    }
+}
+
+/// Note that the grid scrolled, for suggestions about scrolling
+#[cfg(feature = "perf")]
+pub fn record_scroll() {
+    if let Some(collector) = get_metrics_collector() {
+        collector.borrow().record_scroll();
+    }
+}
+
 /// Initialize metrics system for UI
 #[cfg(feature = "perf")]
 pub fn init_metrics() -> Result<(), Box<dyn std::error::Error>> {
@@ -1,7 +1,12 @@
-use gridcore_controller::controller::SpreadsheetController;
+use gridcore_controller::controller::{
+    Capabilities, ConcernTracker, Concerns, SpreadsheetController,
+};
+use gridcore_core::types::CellAddress;
 use leptos::prelude::*;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
 
 /// Creates a reactive generation signal that increments whenever the controller state changes.
 /// This allows Leptos components to efficiently track controller state changes without
@@ -21,19 +26,108 @@ pub fn create_reactive_state(controller: Rc<RefCell<SpreadsheetController>>) ->
     generation
 }
 
-/// Creates a more granular reactive state with separate signals for different aspects
+/// One signal per part of the controller state. Each is set only when its
+/// part really changed, so a view tracking it skips the events that do not
+/// concern it.
+#[derive(Clone, Copy)]
+pub struct ConcernSignals {
+    pub cursor: RwSignal<CellAddress>,
+    pub selection: RwSignal<u32>,
+    pub active_sheet: RwSignal<String>,
+    /// Sheet names and their error counts
+    pub sheets: RwSignal<u32>,
+    /// Cells inside the visible range
+    pub visible_data: RwSignal<u32>,
+    /// Mode, edited text and formula bar
+    pub editing: RwSignal<u32>,
+    pub errors: RwSignal<u32>,
+    /// What the user may do; menus and buttons that would be refused are
+    /// disabled
+    pub capabilities: RwSignal<Capabilities>,
+}
+
+impl ConcernSignals {
+    pub fn new(controller: &SpreadsheetController) -> Self {
+        Self {
+            cursor: RwSignal::new(controller.cursor()),
+            selection: RwSignal::new(0),
+            active_sheet: RwSignal::new(controller.get_active_sheet()),
+            sheets: RwSignal::new(0),
+            visible_data: RwSignal::new(0),
+            editing: RwSignal::new(0),
+            errors: RwSignal::new(0),
+            capabilities: RwSignal::new(controller.capabilities()),
+        }
+    }
+
+    /// Set the signals of the `changed` concerns
+    pub fn apply(&self, changed: Concerns, controller: &SpreadsheetController) {
+        let bump = |signal: RwSignal<u32>, concern: Concerns| {
+            if changed.contains(concern) {
+                signal.update(|g| *g += 1);
+            }
+        };
+        if changed.contains(Concerns::CURSOR) {
+            self.cursor.set(controller.cursor());
+        }
+        if changed.contains(Concerns::ACTIVE_SHEET) {
+            self.active_sheet.set(controller.get_active_sheet());
+        }
+        if changed.contains(Concerns::CAPABILITIES) {
+            self.capabilities.set(controller.capabilities());
+        }
+        bump(self.selection, Concerns::SELECTION);
+        bump(self.sheets, Concerns::SHEETS);
+        bump(self.visible_data, Concerns::VISIBLE_DATA);
+        bump(self.editing, Concerns::EDITING);
+        bump(self.errors, Concerns::ERRORS);
+    }
+}
+
+/// How many concern signals were set, over how many user actions. Events
+/// sent while handling one action are applied together, so each time the
+/// signals are brought up to date counts as one action.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SignalStats {
+    pub actions: u64,
+    pub events: u64,
+    pub signal_updates: u64,
+}
+
+impl SignalStats {
+    pub fn updates_per_action(&self) -> f64 {
+        if self.actions == 0 {
+            0.0
+        } else {
+            self.signal_updates as f64 / self.actions as f64
+        }
+    }
+}
+
+/// The coarse generation signals, still tracked by views that depend on
+/// everything, plus the per-concern signals
 pub struct ReactiveState {
     pub generation: RwSignal<u32>,
     pub render_generation: RwSignal<u32>,
+    pub concerns: ConcernSignals,
+    pub stats: StoredValue<SignalStats>,
 }
 
 impl ReactiveState {
     pub fn new(controller: Rc<RefCell<SpreadsheetController>>) -> Self {
         let generation = RwSignal::new(0);
         let render_generation = RwSignal::new(0);
+        let (concerns, tracker) = {
+            let ctrl = controller.borrow();
+            (ConcernSignals::new(&ctrl), ConcernTracker::new(&ctrl))
+        };
+        let stats = StoredValue::new(SignalStats::default());
+        // Concerns named by the events since the signals were last updated
+        let pending = Arc::new(AtomicU8::new(0));
 
         let gen_for_callback = generation;
         let render_for_callback = render_generation;
+        let pending_for_callback = pending.clone();
 
         controller.borrow_mut().subscribe_to_events(Box::new(
             move |event: &gridcore_controller::controller::events::SpreadsheetEvent| {
@@ -41,13 +135,26 @@ impl ReactiveState {
 
                 // Always update generation for any event
                 gen_for_callback.update(|g| *g += 1);
+                pending_for_callback.fetch_or(Concerns::of(event).bits(), Ordering::Relaxed);
+                stats.update_value(|stats| stats.events += 1);
 
                 // Update render generation for visual changes
                 match event {
                     SpreadsheetEvent::CursorMoved { .. }
                     | SpreadsheetEvent::StateChanged
                     | SpreadsheetEvent::CellEditCompleted { .. }
-                    | SpreadsheetEvent::EditCanceled { .. } => {
+                    | SpreadsheetEvent::EditCanceled { .. }
+                    | SpreadsheetEvent::ViewportScrolled { .. } => {
+                        render_for_callback.update(|g| *g += 1);
+                    }
+                    // The view already scrolled with the rows or columns
+                    // ahead of it, so the repaint shows the same content
+                    event if event.structural_operation().is_some() => {
+                        render_for_callback.update(|g| *g += 1);
+                    }
+                    // The cursor dims while a panel has focus
+                    SpreadsheetEvent::FocusChanged { target } => {
+                        crate::interaction::focus::focus_target(target);
                         render_for_callback.update(|g| *g += 1);
                     }
                     _ => {}
@@ -55,9 +162,32 @@ impl ReactiveState {
             },
         ));
 
+        // Events arrive while the controller is borrowed, so the concerns
+        // they name are compared once the handler that sent them returns
+        let tracker = RefCell::new(tracker);
+        Effect::new(move |_| {
+            generation.get();
+            let pending_concerns = Concerns::from_bits(pending.swap(0, Ordering::Relaxed));
+            if pending_concerns.is_empty() {
+                return;
+            }
+            let Ok(ctrl) = controller.try_borrow() else {
+                pending.fetch_or(pending_concerns.bits(), Ordering::Relaxed);
+                return;
+            };
+            let changed = tracker.borrow_mut().sync(&ctrl, pending_concerns);
+            stats.update_value(|stats| {
+                stats.actions += 1;
+                stats.signal_updates += changed.len() as u64;
+            });
+            concerns.apply(changed, &ctrl);
+        });
+
         ReactiveState {
             generation,
             render_generation,
+            concerns,
+            stats,
         }
     }
 }
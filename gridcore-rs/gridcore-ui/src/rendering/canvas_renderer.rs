@@ -5,6 +5,7 @@ use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
 
 use crate::components::grid::{
     grid_cells::GridCells, grid_headers::GridHeaders, grid_selection::GridSelection,
+    grid_trace_arrows::GridTraceArrows,
 };
 use crate::context::{use_controller, use_device_pixel_ratio, use_viewport};
 use crate::rendering::GridTheme;
@@ -14,6 +15,7 @@ pub struct CanvasRenderer {
     headers: GridHeaders,
     cells: GridCells,
     selection: GridSelection,
+    trace_arrows: GridTraceArrows,
 }
 
 impl CanvasRenderer {
@@ -22,6 +24,7 @@ impl CanvasRenderer {
             headers: GridHeaders::new(theme.clone()),
             cells: GridCells::new(theme.clone()),
             selection: GridSelection::new(theme.clone()),
+            trace_arrows: GridTraceArrows::new(theme.clone()),
             theme,
         }
     }
@@ -31,6 +34,10 @@ impl CanvasRenderer {
             Some(ctx) => ctx,
             None => return,
         };
+        #[cfg(feature = "perf")]
+        let performance = web_sys::window().and_then(|window| window.performance());
+        #[cfg(feature = "perf")]
+        let started = performance.as_ref().map_or(0.0, |p| p.now());
 
         let controller_stored = use_controller();
         let viewport_stored = use_viewport();
@@ -68,7 +75,13 @@ impl CanvasRenderer {
         // Render components using their own contexts
         self.headers.render(canvas);
         self.cells.render(canvas);
+        self.trace_arrows.render(canvas);
         self.selection.render(canvas);
+
+        #[cfg(feature = "perf")]
+        if let Some(performance) = performance {
+            crate::perf::record_frame(performance.now() - started);
+        }
     }
 
     fn get_context(&self, canvas: &HtmlCanvasElement) -> Option<CanvasRenderingContext2d> {
@@ -2,4 +2,4 @@ pub mod canvas_renderer;
 pub mod theme;
 
 pub use canvas_renderer::CanvasRenderer;
-pub use theme::{GridTheme, default_theme};
+pub use theme::{GridTheme, default_theme, palette_css_variables};
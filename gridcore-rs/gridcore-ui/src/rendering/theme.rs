@@ -1,15 +1,43 @@
+use gridcore_controller::theme::{Palette, Surface};
+use std::borrow::Cow;
+
 #[derive(Clone, Debug)]
 pub struct GridTheme {
+    /// Palette the colors come from, applied to colors stored on rules
+    pub palette: Palette,
+    pub surface: Surface,
+
     // Colors
     pub background_color: String,
     pub grid_line_color: String,
     pub cell_text_color: String,
+    /// Text of cells holding errors
+    pub error_text_color: String,
     pub header_background_color: String,
     pub header_text_color: String,
     pub selection_background_color: String,
     pub selection_border_color: String,
     pub active_cell_border_color: String,
+    /// Cursor outline while the spreadsheet is read-only
+    pub read_only_cell_border_color: String,
+    /// Cursor outline while a panel has keyboard focus
+    pub unfocused_cell_border_color: String,
     pub resize_guide_color: String,
+    pub precedent_arrow_color: String,
+    pub dependent_arrow_color: String,
+    pub lint_marker_color: String,
+    pub stale_marker_color: String,
+    /// Sparkline lines and bars, unless the formula names a color
+    pub sparkline_color: String,
+    /// Sparkline bars below zero
+    pub sparkline_negative_color: String,
+    pub tutorial_highlight_color: String,
+    pub minimap_background_color: String,
+    pub minimap_number_color: String,
+    pub minimap_text_color: String,
+    pub minimap_formula_color: String,
+    pub minimap_error_color: String,
+    pub minimap_viewport_color: String,
 
     // Fonts
     pub cell_font_family: String,
@@ -25,16 +53,35 @@ pub struct GridTheme {
 impl Default for GridTheme {
     fn default() -> Self {
         Self {
+            palette: Palette::Standard,
+            surface: Surface::Light,
+
             // Colors
             background_color: "#ffffff".to_string(),
             grid_line_color: "#e0e0e0".to_string(),
             cell_text_color: "#333333".to_string(),
+            error_text_color: "#c5221f".to_string(),
             header_background_color: "#f5f5f5".to_string(),
             header_text_color: "#666666".to_string(),
             selection_background_color: "rgba(0, 102, 204, 0.1)".to_string(),
             selection_border_color: "#0066cc".to_string(),
             active_cell_border_color: "#0066cc".to_string(),
+            read_only_cell_border_color: "#80868b".to_string(),
+            unfocused_cell_border_color: "rgba(0, 102, 204, 0.4)".to_string(),
             resize_guide_color: "#4285f4".to_string(),
+            precedent_arrow_color: "#1a73e8".to_string(),
+            dependent_arrow_color: "#d93025".to_string(),
+            lint_marker_color: "#188038".to_string(),
+            stale_marker_color: "#f29900".to_string(),
+            sparkline_color: "#1a73e8".to_string(),
+            sparkline_negative_color: "#d93025".to_string(),
+            tutorial_highlight_color: "#f29900".to_string(),
+            minimap_background_color: "#fafafa".to_string(),
+            minimap_number_color: "#1a73e8".to_string(),
+            minimap_text_color: "#5f6368".to_string(),
+            minimap_formula_color: "#188038".to_string(),
+            minimap_error_color: "#d93025".to_string(),
+            minimap_viewport_color: "#0066cc".to_string(),
 
             // Fonts
             cell_font_family: "sans-serif".to_string(),
@@ -49,6 +96,68 @@ impl Default for GridTheme {
     }
 }
 
+impl GridTheme {
+    /// The default theme drawn with `palette`. Marker colors of the
+    /// standard palette are swapped for the palette's own.
+    pub fn with_palette(palette: Palette) -> Self {
+        let surface = Surface::Light;
+        let colors = palette.colors(surface);
+        let mut theme = Self {
+            palette,
+            surface,
+            background_color: colors.background.to_string(),
+            cell_text_color: colors.text.to_string(),
+            error_text_color: colors.error_text.to_string(),
+            selection_background_color: colors.selection_fill.to_string(),
+            selection_border_color: colors.selection_border.to_string(),
+            active_cell_border_color: colors.active_border.to_string(),
+            ..Self::default()
+        };
+        if palette != Palette::Standard {
+            theme.unfocused_cell_border_color = colors.selection_border.to_string();
+        }
+        for color in [
+            &mut theme.precedent_arrow_color,
+            &mut theme.dependent_arrow_color,
+            &mut theme.lint_marker_color,
+            &mut theme.stale_marker_color,
+            &mut theme.sparkline_color,
+            &mut theme.sparkline_negative_color,
+            &mut theme.tutorial_highlight_color,
+            &mut theme.minimap_formula_color,
+            &mut theme.minimap_error_color,
+        ] {
+            *color = palette.remap(surface, color).into_owned();
+        }
+        theme
+    }
+
+    /// How a color stored on a rule, such as a sparkline color, is drawn.
+    /// The stored color itself is left as the user wrote it.
+    pub fn rule_color<'a>(&self, stored: &'a str) -> Cow<'a, str> {
+        self.palette.remap(self.surface, stored)
+    }
+}
+
 pub fn default_theme() -> GridTheme {
     GridTheme::default()
 }
+
+/// CSS custom properties giving panels, badges and overlays the status
+/// colors of `palette`
+pub fn palette_css_variables(palette: Palette) -> String {
+    let colors = palette.colors(Surface::Light);
+    format!(
+        "--gc-error-text: {}; --gc-warning-text: {}; --gc-good-text: {}; \
+         --gc-bad-fill: {}; --gc-error-badge: {}; --gc-warning-badge: {}; \
+         --gc-info-badge: {}; --gc-badge-text: {};",
+        colors.error_text,
+        colors.warning_text,
+        colors.good_text,
+        colors.bad_fill,
+        colors.error_badge,
+        colors.warning_badge,
+        colors.info_badge,
+        colors.badge_text,
+    )
+}
@@ -23,6 +23,11 @@ impl UiError {
                 SpreadsheetError::CircularDependency => {
                     "Circular reference detected in formula".to_string()
                 }
+                SpreadsheetError::CircularDependencyWith(_) => match e.to_error_type().cycle_path()
+                {
+                    Some(path) => format!("Circular: {}", path),
+                    None => "Circular reference detected in formula".to_string(),
+                },
                 SpreadsheetError::DivideByZero | SpreadsheetError::DivisionByZero => {
                     "#DIV/0! - Division by zero".to_string()
                 }
@@ -53,6 +58,7 @@ impl UiError {
         match self {
             UiError::Spreadsheet(e) => match e {
                 SpreadsheetError::CircularDependency
+                | SpreadsheetError::CircularDependencyWith(_)
                 | SpreadsheetError::DivideByZero
                 | SpreadsheetError::DivisionByZero
                 | SpreadsheetError::ValueError
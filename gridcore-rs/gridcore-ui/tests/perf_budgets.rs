@@ -0,0 +1,298 @@
+//! Performance budgets for rendering, navigation and loading, run in a
+//! browser with
+//!
+//! ```bash
+//! wasm-pack test --headless --chrome gridcore-rs/gridcore-ui --features perf-test
+//! ```
+#![cfg(all(target_arch = "wasm32", feature = "perf-test"))]
+
+use gridcore_controller::controller::{KeyboardEvent, SpreadsheetController};
+use gridcore_core::types::CellAddress;
+use gridcore_demo::benchmark::profiler::wasm_profiler::WasmProfiler;
+use gridcore_ui::components::viewport::Viewport;
+use gridcore_ui::context::AppState;
+use gridcore_ui::reactive::ConcernSignals;
+use gridcore_ui::rendering::{CanvasRenderer, default_theme};
+use leptos::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::*;
+use web_sys::HtmlCanvasElement;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[global_allocator]
+static ALLOCATOR: counting::CountingAllocator = counting::CountingAllocator;
+
+/// Every budget the tests assert. They leave generous headroom over a
+/// desktop browser; build with `GRIDCORE_PERF_BUDGET_SCALE=3` to loosen
+/// them all on a slow CI machine.
+mod budgets {
+    /// First frame of the 200×50 viewport full of data
+    pub const INITIAL_RENDER_MS: f64 = 500.0;
+    /// 100 cursor moves, each dispatched and drawn
+    pub const CURSOR_MOVES_MS: f64 = 6000.0;
+    /// Writing 10,000 cells in one batch
+    pub const BATCH_LOAD_MS: f64 = 1000.0;
+    /// Allocations per visible cell while building a cold display list
+    pub const DISPLAY_LIST_ALLOCATIONS_PER_CELL: usize = 8;
+
+    pub fn scale() -> f64 {
+        option_env!("GRIDCORE_PERF_BUDGET_SCALE")
+            .and_then(|scale| scale.parse::<f64>().ok())
+            .filter(|scale| *scale > 0.0)
+            .unwrap_or(1.0)
+    }
+
+    pub fn ms(budget: f64) -> f64 {
+        budget * scale()
+    }
+
+    pub fn count(budget: usize) -> usize {
+        (budget as f64 * scale()).ceil() as usize
+    }
+}
+
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    /// The system allocator, counting allocations and reallocations
+    pub struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    pub fn allocations() -> usize {
+        ALLOCATIONS.load(Ordering::Relaxed)
+    }
+}
+
+const ROWS: u32 = 200;
+const COLS: u32 = 50;
+
+/// A controller drawn to a real canvas, timing each phase in a profiler
+struct Harness {
+    owner: Owner,
+    controller: Rc<RefCell<SpreadsheetController>>,
+    canvas: HtmlCanvasElement,
+    renderer: CanvasRenderer,
+    profiler: WasmProfiler,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let controller = Rc::new(RefCell::new(SpreadsheetController::new()));
+        let mut viewport = Viewport::new(default_theme(), controller.clone());
+        let (width, height) = {
+            let ctrl = controller.borrow();
+            let config = ctrl.get_config();
+            (
+                config.row_header_width + COLS as f64 * config.default_cell_width,
+                config.column_header_height + ROWS as f64 * config.default_cell_height,
+            )
+        };
+        viewport.set_viewport_size(width, height);
+
+        let document = web_sys::window().unwrap().document().unwrap();
+        let canvas = document
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<HtmlCanvasElement>()
+            .unwrap();
+        canvas.set_width(width as u32);
+        canvas.set_height(height as u32);
+        document.body().unwrap().append_child(&canvas).unwrap();
+
+        let owner = Owner::new();
+        owner.with(|| {
+            provide_context(AppState {
+                controller: StoredValue::new_local(controller.clone()),
+                viewport: StoredValue::new_local(Rc::new(RefCell::new(viewport))),
+                state_generation: RwSignal::new(0),
+                render_generation: RwSignal::new(0),
+                concerns: ConcernSignals::new(&controller.borrow()),
+                device_pixel_ratio: Signal::from(1.0),
+            })
+        });
+
+        Self {
+            owner,
+            controller,
+            canvas,
+            renderer: CanvasRenderer::new(default_theme()),
+            profiler: WasmProfiler::new(),
+        }
+    }
+
+    /// Run `f`, recording how long it took under `name`
+    fn time<T>(&mut self, name: &str, f: impl FnOnce(&Self) -> T) -> (T, f64) {
+        let start = now_ms();
+        let result = f(self);
+        let elapsed = now_ms() - start;
+        self.profiler.record_call(name, elapsed);
+        (result, elapsed)
+    }
+
+    /// Fill the viewport with numbers, with a row total in the last column
+    fn load(&mut self) -> f64 {
+        let (_, elapsed) = self.time("batch_load", |harness| {
+            let ctrl = harness.controller.borrow();
+            let facade = ctrl.facade();
+            let batch_id = facade.begin_batch().unwrap();
+            for row in 0..ROWS {
+                for col in 0..COLS {
+                    let input = if col == COLS - 1 {
+                        format!("=SUM(A{}:AV{})", row + 1, row + 1)
+                    } else {
+                        (row * COLS + col).to_string()
+                    };
+                    facade
+                        .set_cell_value(&CellAddress::new(col, row), &input)
+                        .unwrap();
+                }
+            }
+            facade.commit_batch(&batch_id).unwrap();
+        });
+        elapsed
+    }
+
+    fn render(&mut self) -> f64 {
+        let (_, elapsed) = self.time("render", |harness| {
+            harness
+                .owner
+                .with(|| harness.renderer.render(&harness.canvas))
+        });
+        elapsed
+    }
+
+    fn press(&mut self, key: &str) -> f64 {
+        let (_, elapsed) = self.time("dispatch", |harness| {
+            harness
+                .controller
+                .borrow_mut()
+                .handle_keyboard_event(KeyboardEvent::new(key.to_string()))
+                .unwrap()
+        });
+        elapsed
+    }
+
+    /// Slowest phases so far and the display-list cache counters
+    fn breakdown(&self) -> String {
+        let mut lines: Vec<String> = self
+            .profiler
+            .get_top_functions(8)
+            .iter()
+            .map(|stats| {
+                format!(
+                    "  {:<12} {:>4} calls, {:>9.2}ms total, {:>8.2}ms p95",
+                    stats.name, stats.call_count, stats.total_duration, stats.p95_duration
+                )
+            })
+            .collect();
+        let cache = self.controller.borrow().get_viewport_cache().stats();
+        lines.push(format!(
+            "  display list: {} cache hits, {} misses, {} prefetches",
+            cache.hits, cache.misses, cache.prefetches
+        ));
+        lines.join("\n")
+    }
+
+    fn assert_within_ms(&self, what: &str, measured: f64, budget: f64) {
+        let budget = budgets::ms(budget);
+        assert!(
+            measured <= budget,
+            "{} took {:.2}ms, over its {:.2}ms budget\n{}",
+            what,
+            measured,
+            budget,
+            self.breakdown()
+        );
+    }
+}
+
+fn now_ms() -> f64 {
+    web_sys::window().unwrap().performance().unwrap().now()
+}
+
+#[wasm_bindgen_test]
+fn initial_render_fits_budget() {
+    let mut harness = Harness::new();
+    harness.load();
+    let elapsed = harness.render();
+    harness.assert_within_ms(
+        "Initial render of 200×50 cells",
+        elapsed,
+        budgets::INITIAL_RENDER_MS,
+    );
+}
+
+#[wasm_bindgen_test]
+fn cursor_moves_fit_budget() {
+    let mut harness = Harness::new();
+    harness.load();
+    harness.render();
+
+    let mut elapsed = 0.0;
+    for i in 0..100 {
+        elapsed += harness.press(if i % 20 < 10 { "j" } else { "k" });
+        elapsed += harness.render();
+    }
+    harness.assert_within_ms(
+        "100 cursor moves with renders",
+        elapsed,
+        budgets::CURSOR_MOVES_MS,
+    );
+}
+
+#[wasm_bindgen_test]
+fn batch_load_fits_budget() {
+    let mut harness = Harness::new();
+    let elapsed = harness.load();
+    harness.assert_within_ms(
+        "Loading 10,000 cells in a batch",
+        elapsed,
+        budgets::BATCH_LOAD_MS,
+    );
+}
+
+#[wasm_bindgen_test]
+fn display_list_allocations_fit_budget() {
+    let mut harness = Harness::new();
+    harness.load();
+
+    let ctrl = harness.controller.borrow();
+    let bounds = ctrl.get_viewport_manager().get_visible_bounds();
+    let visible = (bounds.end_row - bounds.start_row + 1) * (bounds.end_col - bounds.start_col + 1);
+    let before = counting::allocations();
+    let cells = ctrl.get_display_list(&bounds);
+    let allocations = counting::allocations() - before;
+    drop(ctrl);
+
+    assert_eq!(cells.len(), (ROWS * COLS) as usize);
+    let budget = budgets::count(visible * budgets::DISPLAY_LIST_ALLOCATIONS_PER_CELL);
+    assert!(
+        allocations <= budget,
+        "Building the display list of {} cells made {} allocations, over its budget of {}\n{}",
+        visible,
+        allocations,
+        budget,
+        harness.breakdown()
+    );
+}